@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Builder for [`Backoff`]. It's also the structure of the backoff configuration in a config file.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize)]
+pub struct BackoffBuilder {
+    /// the ceiling of the first delay in milliseconds
+    pub base: u64,
+    /// the max delay in milliseconds
+    pub max: u64,
+    /// whether full jitter is applied
+    pub jitter: bool,
+}
+
+impl BackoffBuilder {
+    pub fn build(&self) -> Backoff {
+        Backoff::new(
+            Duration::from_millis(self.base),
+            Duration::from_millis(self.max),
+            self.jitter,
+        )
+    }
+}
+
+/// [`Backoff`] produces the delay before each retry. The ceiling of delays grows exponentially with the attempt and it's capped by `max`:
+///
+/// `ceiling = min(max, base * 2^attempt)`
+///
+/// With full jitter, the delay is picked from `[0, ceiling]` uniformly, so retries of different clients won't be synchronized.
+/// Otherwise the delay is the ceiling itself.
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    jitter: bool,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration, jitter: bool) -> Self {
+        Self {
+            base,
+            max,
+            jitter,
+            attempt: 0,
+        }
+    }
+
+    /// the ceiling of the delay of an attempt
+    pub fn get_ceiling(&self, attempt: u32) -> Duration {
+        1u32.checked_shl(attempt)
+            .and_then(|factor| self.base.checked_mul(factor))
+            .map(|ceiling| ceiling.min(self.max))
+            .unwrap_or(self.max)
+    }
+
+    /// the number of delays which have been produced
+    pub fn get_attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// produce the delay of the next attempt
+    pub fn next_delay(&mut self) -> Duration {
+        let ceiling = self.get_ceiling(self.attempt);
+        self.attempt = self.attempt.saturating_add(1);
+        if self.jitter {
+            let nanos = ceiling.as_nanos().min(u64::MAX as u128) as u64;
+            Duration::from_nanos(rand::thread_rng().gen_range(0..=nanos))
+        } else {
+            ceiling
+        }
+    }
+
+    /// start over from the first attempt, e.g. after a retry succeeds
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_delay())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use proptest::prelude::*;
+
+    use super::{Backoff, BackoffBuilder};
+
+    #[test]
+    fn test_backoff_without_jitter() {
+        let backoff = BackoffBuilder {
+            base: 100,
+            max: 1000,
+            jitter: false,
+        }
+        .build();
+
+        assert_eq!(
+            backoff.take(6).collect::<Vec<_>>(),
+            [100, 200, 400, 800, 1000, 1000]
+                .map(Duration::from_millis)
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn test_backoff_reset() {
+        let mut backoff = Backoff::new(Duration::from_millis(10), Duration::from_secs(1), false);
+        backoff.next_delay();
+        backoff.next_delay();
+        assert_eq!(backoff.get_attempt(), 2);
+
+        backoff.reset();
+        assert_eq!(backoff.get_attempt(), 0);
+        assert_eq!(backoff.next_delay(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_backoff_ceiling_overflow() {
+        let backoff = Backoff::new(Duration::from_secs(u64::MAX / 2), Duration::MAX, true);
+        assert_eq!(
+            backoff.get_ceiling(1),
+            Duration::from_secs(u64::MAX / 2 * 2)
+        );
+        assert_eq!(backoff.get_ceiling(2), Duration::MAX);
+        assert_eq!(backoff.get_ceiling(u32::MAX), Duration::MAX);
+    }
+
+    proptest! {
+        #[test]
+        fn test_backoff_bounded_by_ceiling(
+            base in 0u64..10_000,
+            max in 0u64..100_000,
+            jitter in any::<bool>(),
+            attempts in 1usize..64,
+        ) {
+            let mut backoff = BackoffBuilder { base, max, jitter }.build();
+            for attempt in 0..attempts {
+                let ceiling = backoff.get_ceiling(attempt as u32);
+                let delay = backoff.next_delay();
+                prop_assert!(ceiling <= Duration::from_millis(max));
+                prop_assert!(delay <= ceiling);
+            }
+        }
+
+        #[test]
+        fn test_backoff_monotonic_in_expectation(
+            base in 1u64..1_000,
+            max in 1u64..100_000,
+        ) {
+            const SAMPLES: u32 = 2000;
+            let backoff = BackoffBuilder { base, max, jitter: true }.build();
+
+            let mut prev_mean = Duration::ZERO;
+            for attempt in 0..20 {
+                let ceiling = backoff.get_ceiling(attempt);
+                prop_assert!(ceiling >= backoff.get_ceiling(attempt.saturating_sub(1)));
+
+                let mut sampler = backoff.clone();
+                let total = (0..SAMPLES)
+                    .map(|_| {
+                        sampler.attempt = attempt;
+                        sampler.next_delay()
+                    })
+                    .sum::<Duration>();
+                let mean = total / SAMPLES;
+
+                // the expected delay is the half of the ceiling
+                prop_assert!(mean.as_secs_f64() >= ceiling.as_secs_f64() * 0.45);
+                prop_assert!(mean.as_secs_f64() <= ceiling.as_secs_f64() * 0.55);
+                prop_assert!(mean.as_secs_f64() >= prev_mean.as_secs_f64() * 0.9);
+                prev_mean = mean;
+            }
+        }
+    }
+}
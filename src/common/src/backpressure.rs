@@ -0,0 +1,132 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// metric of the fraction of time an operator is blocked by its downstreams in per mille, i.e. 1000 if it's blocked all the time.
+/// It's reported in the states of the operator, and the Coordinator avoids placing new partitions on the TaskManagers under sustained backpressure
+pub const BACKPRESSURE_METRIC: &str = "backpressure.blocked_permille";
+
+/// the fraction is measured over windows of at least this long, so a short stall doesn't look like sustained backpressure
+pub const BACKPRESSURE_WINDOW: Duration = Duration::from_secs(10);
+
+/// [`Backpressure`] measures the time an executor is blocked by sending events to its downstreams and sinks.
+/// It's shared by the executors of a task and read when the states of the task are reported.
+/// A send which is still blocked is counted until the time it's read, so an executor blocked forever is reported as well
+pub struct Backpressure {
+    window: Duration,
+    state: Mutex<BackpressureState>,
+}
+
+struct BackpressureState {
+    /// when the current window starts
+    window_start: Instant,
+    /// the blocked time of the finished sends in the current window
+    blocked: Duration,
+    /// when the send in hand starts
+    blocked_since: Option<Instant>,
+    /// the fraction of the last finished window in per mille
+    ratio: u64,
+}
+
+pub type SharedBackpressure = Arc<Backpressure>;
+
+impl Backpressure {
+    pub fn new_shared(window: Duration) -> SharedBackpressure {
+        Arc::new(Self {
+            window,
+            state: Mutex::new(BackpressureState {
+                window_start: Instant::now(),
+                blocked: Duration::ZERO,
+                blocked_since: None,
+                ratio: 0,
+            }),
+        })
+    }
+
+    /// the executor starts to send events at `now`
+    pub fn block(&self, now: Instant) {
+        self.lock().blocked_since = Some(now);
+    }
+
+    /// the send in hand finishes at `now`
+    pub fn unblock(&self, now: Instant) {
+        let mut state = self.lock();
+        if let Some(since) = state.blocked_since.take() {
+            let window_start = state.window_start;
+            state.blocked += now.saturating_duration_since(since.max(window_start));
+        }
+        self.roll(&mut state, now);
+    }
+
+    /// the fraction of time blocked in the last finished window in per mille
+    pub fn get_ratio(&self, now: Instant) -> u64 {
+        let mut state = self.lock();
+        self.roll(&mut state, now);
+        state.ratio
+    }
+
+    /// finish the current window if it's long enough. A window is as long as the time between the reads,
+    /// so the fraction of an idle executor decays instead of being stuck at its last value
+    fn roll(&self, state: &mut BackpressureState, now: Instant) {
+        let elapsed = now.saturating_duration_since(state.window_start);
+        if elapsed < self.window || elapsed.is_zero() {
+            return;
+        }
+        let blocked = state.blocked
+            + state
+                .blocked_since
+                .map(|since| now.saturating_duration_since(since.max(state.window_start)))
+                .unwrap_or_default();
+        state.ratio = ((blocked.as_micros() * 1000 / elapsed.as_micros()) as u64).min(1000);
+        state.window_start = now;
+        state.blocked = Duration::ZERO;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BackpressureState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::Backpressure;
+
+    #[test]
+    fn test_backpressure_ratio() {
+        let backpressure = Backpressure::new_shared(Duration::from_secs(10));
+        let start = backpressure.lock().window_start;
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        // the window is not finished yet
+        backpressure.block(at(1));
+        backpressure.unblock(at(4));
+        assert_eq!(backpressure.get_ratio(at(5)), 0);
+
+        backpressure.block(at(6));
+        backpressure.unblock(at(8));
+        assert_eq!(backpressure.get_ratio(at(10)), 500);
+
+        // a send which is still blocked is counted, and the ratio is kept until the next window is finished
+        backpressure.block(at(10));
+        assert_eq!(backpressure.get_ratio(at(15)), 500);
+        assert_eq!(backpressure.get_ratio(at(20)), 1000);
+        // only the part in the current window is counted once it finishes
+        backpressure.unblock(at(25));
+        assert_eq!(backpressure.get_ratio(at(30)), 500);
+
+        // an idle executor is not backpressured
+        assert_eq!(backpressure.get_ratio(at(60)), 0);
+    }
+
+    #[test]
+    fn test_backpressure_ratio_is_capped() {
+        let backpressure = Backpressure::new_shared(Duration::from_secs(1));
+        let now = Instant::now();
+        backpressure.block(now);
+        backpressure.unblock(now + Duration::from_secs(3));
+        assert!(backpressure.get_ratio(now + Duration::from_secs(3)) <= 1000);
+    }
+}
@@ -3,10 +3,43 @@ pub mod env_keys {
     pub const SEND_OPERATOR_EVENT_CONNECT_TIMEOUT: &str =
         "lightflus.send_operator_event.connect_timeout";
     pub const SEND_OPERATOR_EVENT_RPC_TIMEOUT: &str = "lightflus.send_operator_event.rpc_timeout";
+    pub const SEND_OPERATOR_EVENT_FAN_OUT_CONCURRENCY: &str =
+        "lightflus.send_operator_event.fan_out_concurrency";
+    pub const REPORT_OPERATOR_ERROR_CONNECT_TIMEOUT: &str =
+        "lightflus.report_operator_error.connect_timeout";
+    pub const REPORT_OPERATOR_ERROR_RPC_TIMEOUT: &str =
+        "lightflus.report_operator_error.rpc_timeout";
+    pub const SCHEMA_REGISTRY_TIMEOUT: &str = "lightflus.schema_registry.timeout";
+    pub const SCHEMA_REGISTRY_MAX_RETRIES: &str = "lightflus.schema_registry.max_retries";
+    pub const SCHEMA_REGISTRY_RETRY_BACKOFF: &str = "lightflus.schema_registry.retry_backoff";
+    pub const SCHEMA_REGISTRY_RETRY_BACKOFF_MAX: &str =
+        "lightflus.schema_registry.retry_backoff_max";
+    pub const SOURCE_REPLAY_BUFFER_CAPACITY: &str = "lightflus.source.replay_buffer.capacity";
+    pub const SOURCE_REPLAY_BUFFER_MAX_DOWNTIME: &str =
+        "lightflus.source.replay_buffer.max_downtime";
+    pub const STOP_DRAIN_TIMEOUT: &str = "lightflus.stop.drain_timeout";
+    pub const GATEWAY_RERESOLVE_INTERVAL: &str = "lightflus.gateway.reresolve_interval";
 }
 
 pub mod default_configs {
     pub const DEFAULT_CHANNEL_SIZE: usize = 1000;
     pub const DEFAULT_SEND_OPERATOR_EVENT_RPC_TIMEOUT_MILLIS: u64 = 3000;
     pub const DEFAULT_SEND_OPERATOR_EVENT_CONNECT_TIMEOUT_MILLIS: u64 = 3000;
+    /// how many downstream operators an event is sent to at the same time
+    pub const DEFAULT_SEND_OPERATOR_EVENT_FAN_OUT_CONCURRENCY: usize = 16;
+    pub const DEFAULT_REPORT_OPERATOR_ERROR_CONNECT_TIMEOUT_SECS: u64 = 3;
+    pub const DEFAULT_REPORT_OPERATOR_ERROR_RPC_TIMEOUT_SECS: u64 = 3;
+    pub const DEFAULT_SCHEMA_REGISTRY_TIMEOUT_SECS: u64 = 3;
+    pub const DEFAULT_SCHEMA_REGISTRY_MAX_RETRIES: u32 = 3;
+    pub const DEFAULT_SCHEMA_REGISTRY_RETRY_BACKOFF_MILLIS: u64 = 100;
+    pub const DEFAULT_SCHEMA_REGISTRY_RETRY_BACKOFF_MAX_MILLIS: u64 = 10000;
+    pub const DEFAULT_SOURCE_REPLAY_BUFFER_CAPACITY: usize = 1000;
+    pub const DEFAULT_SOURCE_REPLAY_BUFFER_MAX_DOWNTIME_MILLIS: u64 = 10000;
+    /// it should be shorter than the rpc timeout of the coordinator, so that the stop request returns in time
+    pub const DEFAULT_STOP_DRAIN_TIMEOUT_MILLIS: u64 = 2000;
+    /// how often the gateways re-resolve the hosts they connect to, 0 disables the re-resolution
+    pub const DEFAULT_GATEWAY_RERESOLVE_INTERVAL_MILLIS: u64 = 30000;
+    pub const DEFAULT_SNAPSHOT_STORE_REGION: &str = "us-east-1";
+    pub const DEFAULT_SNAPSHOT_STORE_TIMEOUT_SECS: u64 = 10;
+    pub const DEFAULT_REMOTE_CHECKPOINTS_RETAINED: usize = 3;
 }
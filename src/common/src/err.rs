@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use proto::{common::ErrorCode, common_impl::DataflowValidateError};
 
-use rdkafka::error::KafkaError;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
 use tonic::metadata::MetadataValue;
 
 pub type BizCode = i32;
@@ -105,11 +105,41 @@ impl std::fmt::Display for KafkaException {
     }
 }
 
+impl KafkaException {
+    /// whether the message can't reach the brokers, e.g. the brokers are down or the request times out.
+    /// Otherwise the message is rejected by the brokers.
+    pub fn is_transport_failure(&self) -> bool {
+        matches!(
+            self.err.rdkafka_error_code(),
+            Some(
+                RDKafkaErrorCode::BrokerTransportFailure
+                    | RDKafkaErrorCode::Resolve
+                    | RDKafkaErrorCode::MessageTimedOut
+                    | RDKafkaErrorCode::AllBrokersDown
+                    | RDKafkaErrorCode::QueueFull
+                    | RDKafkaErrorCode::LeaderNotAvailable
+                    | RDKafkaErrorCode::NotLeaderForPartition
+                    | RDKafkaErrorCode::RequestTimedOut
+                    | RDKafkaErrorCode::BrokerNotAvailable
+                    | RDKafkaErrorCode::NetworkException
+            )
+        )
+    }
+}
+
 #[derive(Debug)]
 pub enum RedisException {
+    /// the connection can't be established, or it's broken or timed out during a command
     ConnectFailed(String),
     SetValueFailed(String),
     SetMultipleValueFailed(String),
     GetValueFailed(String),
     DelValueFailed(String),
 }
+
+impl RedisException {
+    /// whether the command fails because of the connection instead of being rejected by the server
+    pub fn is_transport_failure(&self) -> bool {
+        matches!(self, Self::ConnectFailed(_))
+    }
+}
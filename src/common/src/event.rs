@@ -162,6 +162,9 @@ impl StreamEvent for LocalEvent {
                                 key: bytes::Bytes::copy_from_slice(&k),
                                 payload: bytes::Bytes::from(payload_result.unwrap()),
                                 timestamp: Some(timestamp.timestamp_millis()),
+                                partition: None,
+                                offset: None,
+                                headers: vec![],
                             })
                         }
 
@@ -0,0 +1,1129 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use apache_avro::{
+    schema::{Name, RecordField},
+    types::Value,
+    Decimal, Schema,
+};
+use proto::common::{avro_format::SchemaRegistry, AvroFormat};
+use tokio::sync::OnceCell;
+
+use crate::{
+    backoff::BackoffBuilder,
+    consts::{
+        default_configs::{
+            DEFAULT_SCHEMA_REGISTRY_MAX_RETRIES, DEFAULT_SCHEMA_REGISTRY_RETRY_BACKOFF_MAX_MILLIS,
+            DEFAULT_SCHEMA_REGISTRY_RETRY_BACKOFF_MILLIS, DEFAULT_SCHEMA_REGISTRY_TIMEOUT_SECS,
+        },
+        env_keys::{
+            SCHEMA_REGISTRY_MAX_RETRIES, SCHEMA_REGISTRY_RETRY_BACKOFF,
+            SCHEMA_REGISTRY_RETRY_BACKOFF_MAX, SCHEMA_REGISTRY_TIMEOUT,
+        },
+    },
+    types::TypedValue,
+    utils::get_env,
+};
+
+/// The first byte of a message in the Confluent wire format
+const MAGIC_BYTE: u8 = 0;
+/// magic byte + 4-byte schema id
+const FRAME_HEADER_LEN: usize = 5;
+const SCHEMA_REGISTRY_CONTENT_TYPE: &str = "application/vnd.schemaregistry.v1+json";
+
+/// Errors of encoding or decoding Avro records and talking to the schema registry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AvroError {
+    /// the payload is not in the Confluent wire format
+    InvalidFrame(String),
+    /// the schema registry can not be reached or it fails with a server error. It's retriable
+    RegistryUnavailable(String),
+    /// the schema registry rejects the request, e.g. the schema id or the subject does not exist
+    Registry { status: u16, message: String },
+    /// the schema is not in the in-process cache and it can't be fetched in the current context
+    SchemaNotCached(u32),
+    /// the schema which is fetched from the registry or configured is invalid
+    InvalidSchema(String),
+    /// the datum can not be decoded by its writer schema
+    Decode(String),
+    /// the value can not be encoded against the schema
+    Encode(String),
+}
+
+impl AvroError {
+    /// whether the operation may succeed if it's retried later
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            Self::RegistryUnavailable(_) | Self::SchemaNotCached(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for AvroError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidFrame(message) => {
+                f.write_fmt(format_args!("invalid avro frame: {message}"))
+            }
+            Self::RegistryUnavailable(message) => {
+                f.write_fmt(format_args!("schema registry is unavailable: {message}"))
+            }
+            Self::Registry { status, message } => f.write_fmt(format_args!(
+                "schema registry responds with status [{status}]: {message}"
+            )),
+            Self::SchemaNotCached(id) => f.write_fmt(format_args!("schema [{id}] is not cached")),
+            Self::InvalidSchema(message) => {
+                f.write_fmt(format_args!("invalid avro schema: {message}"))
+            }
+            Self::Decode(message) => f.write_fmt(format_args!("avro decode failed: {message}")),
+            Self::Encode(message) => f.write_fmt(format_args!("avro encode failed: {message}")),
+        }
+    }
+}
+
+/// A client of Confluent schema registry.
+///
+/// Schemas fetched by id are kept in an in-process cache, so records whose schemas are cached can still be decoded while the registry is unavailable.
+/// Requests which fail because the registry is unavailable will be retried with exponential backoff.
+#[derive(Clone)]
+pub struct SchemaRegistryClient {
+    url: String,
+    username: String,
+    password: String,
+    client: reqwest::Client,
+    max_retries: u32,
+    retry_backoff: BackoffBuilder,
+    cache: Arc<RwLock<HashMap<u32, Arc<Schema>>>>,
+}
+
+impl SchemaRegistryClient {
+    pub fn new(registry: &SchemaRegistry) -> Self {
+        let timeout = get_env(SCHEMA_REGISTRY_TIMEOUT)
+            .and_then(|timeout| timeout.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SCHEMA_REGISTRY_TIMEOUT_SECS);
+        let max_retries = get_env(SCHEMA_REGISTRY_MAX_RETRIES)
+            .and_then(|retries| retries.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_SCHEMA_REGISTRY_MAX_RETRIES);
+        let retry_backoff = get_env(SCHEMA_REGISTRY_RETRY_BACKOFF)
+            .and_then(|backoff| backoff.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SCHEMA_REGISTRY_RETRY_BACKOFF_MILLIS);
+        let retry_backoff_max = get_env(SCHEMA_REGISTRY_RETRY_BACKOFF_MAX)
+            .and_then(|backoff| backoff.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SCHEMA_REGISTRY_RETRY_BACKOFF_MAX_MILLIS);
+
+        Self {
+            url: registry.url.trim_end_matches('/').to_string(),
+            username: registry.username.clone(),
+            password: registry.password.clone(),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(timeout))
+                .build()
+                .unwrap_or_default(),
+            max_retries,
+            retry_backoff: BackoffBuilder {
+                base: retry_backoff,
+                max: retry_backoff_max,
+                jitter: true,
+            },
+            cache: Default::default(),
+        }
+    }
+
+    /// override the retry policy. A request will be sent at most `max_retries + 1` times.
+    pub fn with_retry(mut self, max_retries: u32, retry_backoff: BackoffBuilder) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    pub fn get_cached_schema(&self, id: u32) -> Option<Arc<Schema>> {
+        self.cache
+            .read()
+            .ok()
+            .and_then(|cache| cache.get(&id).cloned())
+    }
+
+    pub fn cache_schema(&self, id: u32, schema: Schema) -> Arc<Schema> {
+        let schema = Arc::new(schema);
+        if let Ok(mut cache) = self.cache.write() {
+            cache.insert(id, schema.clone());
+        }
+        schema
+    }
+
+    /// get the schema by its id. The cache will be looked up first.
+    pub async fn get_schema_by_id(&self, id: u32) -> Result<Arc<Schema>, AvroError> {
+        match self.get_cached_schema(id) {
+            Some(schema) => Ok(schema),
+            None => {
+                let url = format!("{}/schemas/ids/{}", &self.url, id);
+                let response = self.send_with_retry(|| self.client.get(&url)).await?;
+                let schema = parse_schema_response(&response)?;
+                Ok(self.cache_schema(id, schema))
+            }
+        }
+    }
+
+    /// get the id and the schema of the latest version of the subject
+    pub async fn get_latest_schema(&self, subject: &str) -> Result<(u32, Arc<Schema>), AvroError> {
+        let url = format!("{}/subjects/{}/versions/latest", &self.url, subject);
+        let response = self.send_with_retry(|| self.client.get(&url)).await?;
+        let id = parse_id_response(&response)?;
+        let schema = parse_schema_response(&response)?;
+        Ok((id, self.cache_schema(id, schema)))
+    }
+
+    /// register the schema under the subject and return its id. It's idempotent if the schema has been registered.
+    pub async fn register_schema(&self, subject: &str, schema: &str) -> Result<u32, AvroError> {
+        let url = format!("{}/subjects/{}/versions", &self.url, subject);
+        let body = serde_json::json!({ "schema": schema });
+        let response = self
+            .send_with_retry(|| self.client.post(&url).json(&body))
+            .await?;
+        parse_id_response(&response)
+    }
+
+    /// look up the id of a schema which has been registered under the subject
+    pub async fn lookup_schema(&self, subject: &str, schema: &str) -> Result<u32, AvroError> {
+        let url = format!("{}/subjects/{}", &self.url, subject);
+        let body = serde_json::json!({ "schema": schema });
+        let response = self
+            .send_with_retry(|| self.client.post(&url).json(&body))
+            .await?;
+        parse_id_response(&response)
+    }
+
+    async fn send_with_retry<F: Fn() -> reqwest::RequestBuilder>(
+        &self,
+        request: F,
+    ) -> Result<serde_json::Value, AvroError> {
+        let mut backoff = self.retry_backoff.build();
+        loop {
+            match self.send(request()).await {
+                Err(err) if err.is_retriable() && backoff.get_attempt() < self.max_retries => {
+                    let delay = backoff.next_delay();
+                    tracing::warn!(
+                        "request to schema registry [{}] failed: {}, retry after {:?}",
+                        &self.url,
+                        err,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<serde_json::Value, AvroError> {
+        let request = if self.username.is_empty() {
+            request
+        } else {
+            request.basic_auth(&self.username, Some(&self.password))
+        };
+        let response = request
+            .header(reqwest::header::ACCEPT, SCHEMA_REGISTRY_CONTENT_TYPE)
+            .send()
+            .await
+            .map_err(|err| AvroError::RegistryUnavailable(err.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            response
+                .json::<serde_json::Value>()
+                .await
+                .map_err(|err| AvroError::Registry {
+                    status: status.as_u16(),
+                    message: err.to_string(),
+                })
+        } else {
+            let message = response.text().await.unwrap_or_default();
+            if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                Err(AvroError::RegistryUnavailable(format!(
+                    "[{status}] {message}"
+                )))
+            } else {
+                Err(AvroError::Registry {
+                    status: status.as_u16(),
+                    message,
+                })
+            }
+        }
+    }
+}
+
+fn parse_schema_response(response: &serde_json::Value) -> Result<Schema, AvroError> {
+    response
+        .get("schema")
+        .and_then(|schema| schema.as_str())
+        .ok_or_else(|| AvroError::InvalidSchema(format!("no schema in response {}", response)))
+        .and_then(|schema| {
+            Schema::parse_str(schema).map_err(|err| AvroError::InvalidSchema(err.to_string()))
+        })
+}
+
+fn parse_id_response(response: &serde_json::Value) -> Result<u32, AvroError> {
+    response
+        .get("id")
+        .and_then(|id| id.as_u64())
+        .map(|id| id as u32)
+        .ok_or_else(|| AvroError::InvalidSchema(format!("no schema id in response {}", response)))
+}
+
+/// frame an Avro binary datum in the Confluent wire format
+pub fn frame(schema_id: u32, datum: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(FRAME_HEADER_LEN + datum.len());
+    payload.push(MAGIC_BYTE);
+    payload.extend_from_slice(&schema_id.to_be_bytes());
+    payload.extend_from_slice(datum);
+    payload
+}
+
+/// split a payload in the Confluent wire format into the schema id and the Avro binary datum
+pub fn unframe(payload: &[u8]) -> Result<(u32, &[u8]), AvroError> {
+    if payload.len() < FRAME_HEADER_LEN {
+        Err(AvroError::InvalidFrame(format!(
+            "payload has {} bytes, less than the header size",
+            payload.len()
+        )))
+    } else if payload[0] != MAGIC_BYTE {
+        Err(AvroError::InvalidFrame(format!(
+            "unknown magic byte [{}]",
+            payload[0]
+        )))
+    } else {
+        let mut id = [0u8; 4];
+        id.copy_from_slice(&payload[1..FRAME_HEADER_LEN]);
+        Ok((u32::from_be_bytes(id), &payload[FRAME_HEADER_LEN..]))
+    }
+}
+
+/// Decodes Avro records in the Confluent wire format into [`TypedValue`].
+///
+/// Each record is decoded by its writer schema which is fetched by the id embedded in the message.
+/// If a reader schema is configured, records will be resolved into it, so fields added by the writer are dropped and
+/// fields removed by the writer are filled with their defaults.
+pub struct AvroDecoder {
+    registry: SchemaRegistryClient,
+    reader_schema: Option<Schema>,
+}
+
+impl AvroDecoder {
+    pub fn new(format: &AvroFormat) -> Self {
+        Self::with_registry(
+            SchemaRegistryClient::new(&format.registry.clone().unwrap_or_default()),
+            format,
+        )
+    }
+
+    pub fn with_registry(registry: SchemaRegistryClient, format: &AvroFormat) -> Self {
+        Self {
+            registry,
+            reader_schema: if format.schema.is_empty() {
+                None
+            } else {
+                Schema::parse_str(&format.schema).ok()
+            },
+        }
+    }
+
+    pub fn registry(&self) -> &SchemaRegistryClient {
+        &self.registry
+    }
+
+    /// decode a message. Its writer schema will be fetched from the registry if it's not cached.
+    pub async fn decode(&self, payload: &[u8]) -> Result<TypedValue, AvroError> {
+        let (id, datum) = unframe(payload)?;
+        let writer_schema = self.registry.get_schema_by_id(id).await?;
+        self.decode_datum(&writer_schema, datum)
+    }
+
+    /// decode a message only by cached schemas. It returns [`AvroError::SchemaNotCached`] if the writer schema is not cached.
+    pub fn decode_cached(&self, payload: &[u8]) -> Result<TypedValue, AvroError> {
+        let (id, datum) = unframe(payload)?;
+        match self.registry.get_cached_schema(id) {
+            Some(writer_schema) => self.decode_datum(&writer_schema, datum),
+            None => Err(AvroError::SchemaNotCached(id)),
+        }
+    }
+
+    fn decode_datum(
+        &self,
+        writer_schema: &Schema,
+        mut datum: &[u8],
+    ) -> Result<TypedValue, AvroError> {
+        let value =
+            apache_avro::from_avro_datum(writer_schema, &mut datum, self.reader_schema.as_ref())
+                .map_err(|err| AvroError::Decode(err.to_string()))?;
+        let schema = self.reader_schema.as_ref().unwrap_or(writer_schema);
+        let names = collect_names(schema);
+        Ok(to_typed_value(value, schema, &names))
+    }
+}
+
+/// Encodes [`TypedValue`] into Avro records in the Confluent wire format.
+///
+/// The writer schema is resolved at the first encoding: the configured schema is registered under the subject if auto-registration is enabled,
+/// or it's looked up in the subject. If no schema is configured, the latest schema of the subject will be used.
+pub struct AvroEncoder {
+    registry: SchemaRegistryClient,
+    subject: String,
+    schema: String,
+    auto_register: bool,
+    writer_schema: OnceCell<(u32, Arc<Schema>)>,
+}
+
+impl AvroEncoder {
+    pub fn new(format: &AvroFormat) -> Self {
+        Self::with_registry(
+            SchemaRegistryClient::new(&format.registry.clone().unwrap_or_default()),
+            format,
+        )
+    }
+
+    pub fn with_registry(registry: SchemaRegistryClient, format: &AvroFormat) -> Self {
+        Self {
+            registry,
+            subject: format.subject.clone(),
+            schema: format.schema.clone(),
+            auto_register: format.auto_register,
+            writer_schema: OnceCell::new(),
+        }
+    }
+
+    pub async fn encode(&self, value: &TypedValue) -> Result<Vec<u8>, AvroError> {
+        let (id, schema) = self
+            .writer_schema
+            .get_or_try_init(|| self.resolve_writer_schema())
+            .await?;
+        let names = collect_names(schema);
+        let value = to_avro_value(value, schema, &names)?;
+        apache_avro::to_avro_datum(schema, value)
+            .map(|datum| frame(*id, &datum))
+            .map_err(|err| AvroError::Encode(err.to_string()))
+    }
+
+    async fn resolve_writer_schema(&self) -> Result<(u32, Arc<Schema>), AvroError> {
+        if self.schema.is_empty() {
+            return self.registry.get_latest_schema(&self.subject).await;
+        }
+
+        let schema = Schema::parse_str(&self.schema)
+            .map_err(|err| AvroError::InvalidSchema(err.to_string()))?;
+        let id = if self.auto_register {
+            self.registry
+                .register_schema(&self.subject, &self.schema)
+                .await?
+        } else {
+            self.registry
+                .lookup_schema(&self.subject, &self.schema)
+                .await?
+        };
+        Ok((id, self.registry.cache_schema(id, schema)))
+    }
+}
+
+/// named types (record, enum and fixed) of a schema, which are used to resolve [`Schema::Ref`]
+type Names = HashMap<Name, Schema>;
+
+fn collect_names(schema: &Schema) -> Names {
+    fn collect(schema: &Schema, names: &mut Names) {
+        match schema {
+            Schema::Record { name, fields, .. } => {
+                names.insert(name.clone(), schema.clone());
+                fields
+                    .iter()
+                    .for_each(|field| collect(&field.schema, names));
+            }
+            Schema::Enum { name, .. } | Schema::Fixed { name, .. } => {
+                names.insert(name.clone(), schema.clone());
+            }
+            Schema::Array(inner) | Schema::Map(inner) => collect(inner, names),
+            Schema::Union(union) => union
+                .variants()
+                .iter()
+                .for_each(|variant| collect(variant, names)),
+            Schema::Decimal { inner, .. } => collect(inner, names),
+            _ => {}
+        }
+    }
+
+    let mut names = HashMap::new();
+    collect(schema, &mut names);
+    names
+}
+
+fn resolve_ref<'a>(schema: &'a Schema, names: &'a Names) -> &'a Schema {
+    match schema {
+        Schema::Ref { name } => names.get(name).unwrap_or(schema),
+        _ => schema,
+    }
+}
+
+/// Map an Avro value onto [`TypedValue`]:
+/// - int, long and logical types of date and time are mapped to [`TypedValue::BigInt`] in their own units, e.g. timestamp-millis is in milliseconds
+/// - float, double and decimal are mapped to [`TypedValue::Number`]
+/// - string, enum and uuid are mapped to [`TypedValue::String`]
+/// - bytes and fixed are mapped to an array of bytes
+/// - record and map are mapped to [`TypedValue::Object`]; duration is mapped to an object of months, days and millis
+fn to_typed_value(value: Value, schema: &Schema, names: &Names) -> TypedValue {
+    let schema = resolve_ref(schema, names);
+    match value {
+        Value::Null => TypedValue::Null,
+        Value::Boolean(v) => TypedValue::Boolean(v),
+        Value::Int(v) | Value::Date(v) | Value::TimeMillis(v) => TypedValue::BigInt(v as i64),
+        Value::Long(v)
+        | Value::TimeMicros(v)
+        | Value::TimestampMillis(v)
+        | Value::TimestampMicros(v) => TypedValue::BigInt(v),
+        Value::Float(v) => TypedValue::Number(v as f64),
+        Value::Double(v) => TypedValue::Number(v),
+        Value::Bytes(bytes) | Value::Fixed(_, bytes) => TypedValue::Array(
+            bytes
+                .into_iter()
+                .map(|b| TypedValue::BigInt(b as i64))
+                .collect(),
+        ),
+        Value::String(v) | Value::Enum(_, v) => TypedValue::String(v),
+        Value::Uuid(v) => TypedValue::String(v.to_string()),
+        Value::Decimal(decimal) => {
+            let scale = match schema {
+                Schema::Decimal { scale, .. } => *scale,
+                _ => 0,
+            };
+            TypedValue::Number(decimal_to_f64(&decimal, scale))
+        }
+        Value::Duration(duration) => TypedValue::Object(BTreeMap::from([
+            (
+                "months".to_string(),
+                TypedValue::BigInt(u32::from(duration.months()) as i64),
+            ),
+            (
+                "days".to_string(),
+                TypedValue::BigInt(u32::from(duration.days()) as i64),
+            ),
+            (
+                "millis".to_string(),
+                TypedValue::BigInt(u32::from(duration.millis()) as i64),
+            ),
+        ])),
+        Value::Union(index, inner) => {
+            let variant = match schema {
+                Schema::Union(union) => union.variants().get(index as usize),
+                _ => None,
+            };
+            to_typed_value(*inner, variant.unwrap_or(&Schema::Null), names)
+        }
+        Value::Array(items) => {
+            let item_schema = match schema {
+                Schema::Array(inner) => inner.as_ref(),
+                _ => &Schema::Null,
+            };
+            TypedValue::Array(
+                items
+                    .into_iter()
+                    .map(|item| to_typed_value(item, item_schema, names))
+                    .collect(),
+            )
+        }
+        Value::Map(items) => {
+            let value_schema = match schema {
+                Schema::Map(inner) => inner.as_ref(),
+                _ => &Schema::Null,
+            };
+            TypedValue::Object(
+                items
+                    .into_iter()
+                    .map(|(key, item)| (key, to_typed_value(item, value_schema, names)))
+                    .collect(),
+            )
+        }
+        Value::Record(fields) => {
+            let record_fields = match schema {
+                Schema::Record { fields, .. } => fields.as_slice(),
+                _ => &[],
+            };
+            TypedValue::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, field)| {
+                        let field_schema = record_fields
+                            .iter()
+                            .find(|f| f.name == name)
+                            .map(|f| &f.schema)
+                            .unwrap_or(&Schema::Null);
+                        let value = to_typed_value(field, field_schema, names);
+                        (name, value)
+                    })
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// the unscaled value of a decimal is a big-endian two's-complement integer
+fn decimal_to_f64(decimal: &Decimal, scale: usize) -> f64 {
+    let bytes = Vec::<u8>::try_from(decimal).unwrap_or_default();
+    let negative = bytes.first().map(|b| b & 0x80 != 0).unwrap_or(false);
+    let mut unscaled = bytes.iter().fold(0f64, |acc, b| acc * 256.0 + *b as f64);
+    if negative {
+        unscaled -= 256f64.powi(bytes.len() as i32);
+    }
+    unscaled / 10f64.powi(scale as i32)
+}
+
+fn f64_to_decimal(value: f64, scale: usize) -> Result<Decimal, AvroError> {
+    let unscaled = (value * 10f64.powi(scale as i32)).round();
+    if !unscaled.is_finite() || unscaled.abs() >= i128::MAX as f64 {
+        return Err(AvroError::Encode(format!(
+            "decimal {value} with scale {scale} is out of range"
+        )));
+    }
+    let bytes = (unscaled as i128).to_be_bytes();
+    // keep the minimal two's-complement representation
+    let sign_byte = if unscaled < 0.0 { 0xff } else { 0x00 };
+    let start = bytes
+        .windows(2)
+        .position(|w| w[0] != sign_byte || (w[1] & 0x80) != (sign_byte & 0x80))
+        .unwrap_or(bytes.len() - 1);
+    Ok(Decimal::from(bytes[start..].to_vec()))
+}
+
+fn to_i64(value: &TypedValue) -> Option<i64> {
+    match value {
+        TypedValue::BigInt(v) => Some(*v),
+        TypedValue::Number(v) if v.fract() == 0.0 => Some(*v as i64),
+        _ => None,
+    }
+}
+
+fn to_i32(value: &TypedValue) -> Option<i32> {
+    to_i64(value).and_then(|v| i32::try_from(v).ok())
+}
+
+fn to_f64(value: &TypedValue) -> Option<f64> {
+    match value {
+        TypedValue::BigInt(v) => Some(*v as f64),
+        TypedValue::Number(v) => Some(*v),
+        _ => None,
+    }
+}
+
+fn to_bytes(value: &TypedValue) -> Option<Vec<u8>> {
+    match value {
+        TypedValue::String(v) => Some(v.as_bytes().to_vec()),
+        TypedValue::Array(items) => items
+            .iter()
+            .map(|item| to_i64(item).and_then(|b| u8::try_from(b).ok()))
+            .collect(),
+        _ => None,
+    }
+}
+
+/// Map [`TypedValue`] onto an Avro value of the schema. It's the inverse of [`to_typed_value`].
+fn to_avro_value(value: &TypedValue, schema: &Schema, names: &Names) -> Result<Value, AvroError> {
+    let mismatch = || {
+        AvroError::Encode(format!(
+            "value {} does not match schema {}",
+            value.to_json_value(),
+            schema.canonical_form()
+        ))
+    };
+    let schema = resolve_ref(schema, names);
+    match schema {
+        Schema::Null => match value {
+            TypedValue::Null | TypedValue::Invalid => Ok(Value::Null),
+            _ => Err(mismatch()),
+        },
+        Schema::Boolean => match value {
+            TypedValue::Boolean(v) => Ok(Value::Boolean(*v)),
+            _ => Err(mismatch()),
+        },
+        Schema::Int => to_i32(value).map(Value::Int).ok_or_else(mismatch),
+        Schema::Long => to_i64(value).map(Value::Long).ok_or_else(mismatch),
+        Schema::Float => to_f64(value)
+            .map(|v| Value::Float(v as f32))
+            .ok_or_else(mismatch),
+        Schema::Double => to_f64(value).map(Value::Double).ok_or_else(mismatch),
+        Schema::Date => to_i32(value).map(Value::Date).ok_or_else(mismatch),
+        Schema::TimeMillis => to_i32(value).map(Value::TimeMillis).ok_or_else(mismatch),
+        Schema::TimeMicros => to_i64(value).map(Value::TimeMicros).ok_or_else(mismatch),
+        Schema::TimestampMillis => to_i64(value)
+            .map(Value::TimestampMillis)
+            .ok_or_else(mismatch),
+        Schema::TimestampMicros => to_i64(value)
+            .map(Value::TimestampMicros)
+            .ok_or_else(mismatch),
+        Schema::String => match value {
+            TypedValue::String(v) => Ok(Value::String(v.clone())),
+            _ => Err(mismatch()),
+        },
+        Schema::Uuid => match value {
+            TypedValue::String(v) => Value::String(v.clone())
+                .resolve(schema)
+                .map_err(|err| AvroError::Encode(err.to_string())),
+            _ => Err(mismatch()),
+        },
+        Schema::Bytes => to_bytes(value).map(Value::Bytes).ok_or_else(mismatch),
+        Schema::Fixed { size, .. } => to_bytes(value)
+            .filter(|bytes| bytes.len() == *size)
+            .map(|bytes| Value::Fixed(*size, bytes))
+            .ok_or_else(mismatch),
+        Schema::Decimal { scale, .. } => to_f64(value)
+            .ok_or_else(mismatch)
+            .and_then(|v| f64_to_decimal(v, *scale))
+            .map(Value::Decimal),
+        Schema::Enum { symbols, .. } => match value {
+            TypedValue::String(v) => symbols
+                .iter()
+                .position(|symbol| symbol == v)
+                .map(|index| Value::Enum(index as u32, v.clone()))
+                .ok_or_else(mismatch),
+            _ => Err(mismatch()),
+        },
+        Schema::Union(union) => union
+            .variants()
+            .iter()
+            .enumerate()
+            .find_map(|(index, variant)| {
+                to_avro_value(value, variant, names)
+                    .ok()
+                    .map(|v| Value::Union(index as u32, Box::new(v)))
+            })
+            .ok_or_else(mismatch),
+        Schema::Array(inner) => match value {
+            TypedValue::Array(items) => items
+                .iter()
+                .map(|item| to_avro_value(item, inner, names))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            _ => Err(mismatch()),
+        },
+        Schema::Map(inner) => match value {
+            TypedValue::Object(items) => items
+                .iter()
+                .map(|(key, item)| to_avro_value(item, inner, names).map(|v| (key.clone(), v)))
+                .collect::<Result<HashMap<_, _>, _>>()
+                .map(Value::Map),
+            _ => Err(mismatch()),
+        },
+        Schema::Record { fields, .. } => match value {
+            TypedValue::Object(items) => fields
+                .iter()
+                .map(|field| to_record_field(items.get(&field.name), field, names))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Record),
+            _ => Err(mismatch()),
+        },
+        Schema::Duration | Schema::Ref { .. } => Err(AvroError::Encode(format!(
+            "schema {} is not supported",
+            schema.canonical_form()
+        ))),
+    }
+}
+
+/// a field absent in the value will be filled with its default
+fn to_record_field(
+    value: Option<&TypedValue>,
+    field: &RecordField,
+    names: &Names,
+) -> Result<(String, Value), AvroError> {
+    let value = match (value, &field.default) {
+        (Some(value), _) => to_avro_value(value, &field.schema, names)?,
+        (None, Some(default)) => to_avro_value(
+            &TypedValue::from_json_value(default.clone()),
+            &field.schema,
+            names,
+        )?,
+        (None, None) => {
+            return Err(AvroError::Encode(format!(
+                "field [{}] is missing and has no default",
+                &field.name
+            )))
+        }
+    };
+    Ok((field.name.clone(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use apache_avro::{types::Value, Schema};
+    use proto::common::{avro_format::SchemaRegistry, AvroFormat};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use crate::{backoff::BackoffBuilder, types::TypedValue};
+
+    use super::{frame, unframe, AvroDecoder, AvroEncoder, AvroError, SchemaRegistryClient};
+
+    const USER_V1: &str = r#"{
+        "type": "record",
+        "name": "User",
+        "fields": [
+            {"name": "name", "type": "string"},
+            {"name": "age", "type": "int"}
+        ]
+    }"#;
+
+    /// v2 removes `age` and adds an optional `email`
+    const USER_V2: &str = r#"{
+        "type": "record",
+        "name": "User",
+        "fields": [
+            {"name": "name", "type": "string"},
+            {"name": "email", "type": ["null", "string"], "default": null}
+        ]
+    }"#;
+
+    /// a registry which can't be connected
+    fn test_backoff() -> BackoffBuilder {
+        BackoffBuilder {
+            base: 1,
+            max: 1,
+            jitter: true,
+        }
+    }
+
+    fn unavailable_registry() -> SchemaRegistryClient {
+        SchemaRegistryClient::new(&SchemaRegistry {
+            url: "http://127.0.0.1:1".to_string(),
+            username: Default::default(),
+            password: Default::default(),
+        })
+        .with_retry(1, test_backoff())
+    }
+
+    fn encode_datum(schema: &str, value: Value) -> Vec<u8> {
+        let schema = Schema::parse_str(schema).unwrap();
+        apache_avro::to_avro_datum(&schema, value).unwrap()
+    }
+
+    fn user_v1(name: &str, age: i32) -> Value {
+        Value::Record(vec![
+            ("name".to_string(), Value::String(name.to_string())),
+            ("age".to_string(), Value::Int(age)),
+        ])
+    }
+
+    /// serve `responses` in order, one response per connection
+    async fn mock_registry(responses: Vec<(u16, String)>) -> (String, tokio::task::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handler = tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let _ = stream.read(&mut buf).await.unwrap();
+                let response = format!(
+                    "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+        });
+        (url, handler)
+    }
+
+    #[test]
+    fn test_frame() {
+        let payload = frame(258, &[1, 2, 3]);
+        assert_eq!(payload, vec![0, 0, 0, 1, 2, 1, 2, 3]);
+        assert_eq!(unframe(&payload), Ok((258, [1u8, 2, 3].as_slice())));
+
+        assert!(matches!(
+            unframe(&[0, 0, 1]),
+            Err(AvroError::InvalidFrame(_))
+        ));
+        assert!(matches!(
+            unframe(&[1, 0, 0, 0, 1, 2]),
+            Err(AvroError::InvalidFrame(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_logical_types() {
+        let schema = r#"{
+            "type": "record",
+            "name": "Order",
+            "fields": [
+                {"name": "created_at", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+                {"name": "day", "type": {"type": "int", "logicalType": "date"}},
+                {"name": "amount", "type": {"type": "bytes", "logicalType": "decimal", "precision": 10, "scale": 2}},
+                {"name": "refund", "type": {"type": "bytes", "logicalType": "decimal", "precision": 10, "scale": 2}},
+                {"name": "status", "type": {"type": "enum", "name": "Status", "symbols": ["PAID", "SHIPPED"]}},
+                {"name": "tags", "type": {"type": "array", "items": "string"}},
+                {"name": "attrs", "type": {"type": "map", "values": "long"}},
+                {"name": "note", "type": ["null", "string"]}
+            ]
+        }"#;
+        let decoder = AvroDecoder::with_registry(unavailable_registry(), &AvroFormat::default());
+        decoder
+            .registry()
+            .cache_schema(1, Schema::parse_str(schema).unwrap());
+
+        let datum = encode_datum(
+            schema,
+            Value::Record(vec![
+                (
+                    "created_at".to_string(),
+                    Value::TimestampMillis(1672531200000),
+                ),
+                ("day".to_string(), Value::Date(19358)),
+                (
+                    "amount".to_string(),
+                    Value::Decimal(vec![0x30, 0x39].into()),
+                ),
+                (
+                    "refund".to_string(),
+                    Value::Decimal(vec![0xff, 0x38].into()),
+                ),
+                ("status".to_string(), Value::Enum(1, "SHIPPED".to_string())),
+                (
+                    "tags".to_string(),
+                    Value::Array(vec![Value::String("a".to_string())]),
+                ),
+                (
+                    "attrs".to_string(),
+                    Value::Map([("k".to_string(), Value::Long(1))].into_iter().collect()),
+                ),
+                (
+                    "note".to_string(),
+                    Value::Union(1, Box::new(Value::String("gift".to_string()))),
+                ),
+            ]),
+        );
+
+        let result = decoder.decode_cached(&frame(1, &datum));
+        assert_eq!(
+            result,
+            Ok(TypedValue::Object(BTreeMap::from([
+                ("created_at".to_string(), TypedValue::BigInt(1672531200000)),
+                ("day".to_string(), TypedValue::BigInt(19358)),
+                ("amount".to_string(), TypedValue::Number(123.45)),
+                ("refund".to_string(), TypedValue::Number(-2.0)),
+                (
+                    "status".to_string(),
+                    TypedValue::String("SHIPPED".to_string())
+                ),
+                (
+                    "tags".to_string(),
+                    TypedValue::Array(vec![TypedValue::String("a".to_string())])
+                ),
+                (
+                    "attrs".to_string(),
+                    TypedValue::Object(BTreeMap::from([("k".to_string(), TypedValue::BigInt(1))]))
+                ),
+                ("note".to_string(), TypedValue::String("gift".to_string())),
+            ])))
+        );
+    }
+
+    #[test]
+    fn test_decode_schema_evolution() {
+        let registry = unavailable_registry();
+        registry.cache_schema(1, Schema::parse_str(USER_V1).unwrap());
+        registry.cache_schema(2, Schema::parse_str(USER_V2).unwrap());
+
+        let v1_payload = frame(1, &encode_datum(USER_V1, user_v1("alice", 18)));
+        let v2_payload = frame(
+            2,
+            &encode_datum(
+                USER_V2,
+                Value::Record(vec![
+                    ("name".to_string(), Value::String("bob".to_string())),
+                    (
+                        "email".to_string(),
+                        Value::Union(1, Box::new(Value::String("bob@a.com".to_string()))),
+                    ),
+                ]),
+            ),
+        );
+
+        // without reader schema, each record is decoded by its own writer schema
+        let decoder = AvroDecoder::with_registry(registry.clone(), &AvroFormat::default());
+        assert_eq!(
+            decoder.decode_cached(&v1_payload),
+            Ok(TypedValue::Object(BTreeMap::from([
+                ("name".to_string(), TypedValue::String("alice".to_string())),
+                ("age".to_string(), TypedValue::BigInt(18)),
+            ])))
+        );
+        assert_eq!(
+            decoder.decode_cached(&v2_payload),
+            Ok(TypedValue::Object(BTreeMap::from([
+                ("name".to_string(), TypedValue::String("bob".to_string())),
+                (
+                    "email".to_string(),
+                    TypedValue::String("bob@a.com".to_string())
+                ),
+            ])))
+        );
+
+        // the removed field is dropped and the new optional field is filled with its default
+        let decoder = AvroDecoder::with_registry(
+            registry.clone(),
+            &AvroFormat {
+                schema: USER_V2.to_string(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            decoder.decode_cached(&v1_payload),
+            Ok(TypedValue::Object(BTreeMap::from([
+                ("name".to_string(), TypedValue::String("alice".to_string())),
+                ("email".to_string(), TypedValue::Null),
+            ])))
+        );
+
+        // the new field is ignored by an old reader
+        let decoder = AvroDecoder::with_registry(
+            registry,
+            &AvroFormat {
+                schema: r#"{"type": "record", "name": "User", "fields": [{"name": "name", "type": "string"}]}"#
+                    .to_string(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            decoder.decode_cached(&v2_payload),
+            Ok(TypedValue::Object(BTreeMap::from([(
+                "name".to_string(),
+                TypedValue::String("bob".to_string())
+            )])))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_decode_with_unavailable_registry() {
+        let decoder = AvroDecoder::with_registry(unavailable_registry(), &AvroFormat::default());
+        let payload = frame(1, &encode_datum(USER_V1, user_v1("alice", 18)));
+
+        let result = decoder.decode(&payload).await;
+        assert!(matches!(result, Err(AvroError::RegistryUnavailable(_))));
+        assert!(result.unwrap_err().is_retriable());
+        assert_eq!(
+            decoder.decode_cached(&payload),
+            Err(AvroError::SchemaNotCached(1))
+        );
+
+        // cached schemas keep working while the registry is unavailable
+        decoder
+            .registry()
+            .cache_schema(1, Schema::parse_str(USER_V1).unwrap());
+        assert!(decoder.decode(&payload).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_schema_by_id() {
+        let (url, handler) = mock_registry(vec![
+            (503, "{}".to_string()),
+            (200, serde_json::json!({ "schema": USER_V1 }).to_string()),
+        ])
+        .await;
+        let registry = SchemaRegistryClient::new(&SchemaRegistry {
+            url,
+            username: "user".to_string(),
+            password: "password".to_string(),
+        })
+        .with_retry(2, test_backoff());
+        let decoder = AvroDecoder::with_registry(registry, &AvroFormat::default());
+        let payload = frame(7, &encode_datum(USER_V1, user_v1("alice", 18)));
+
+        // the first request fails with 503 and will be retried
+        assert!(decoder.decode(&payload).await.is_ok());
+        handler.await.unwrap();
+
+        // the registry is gone but the schema is cached
+        assert!(decoder.decode(&payload).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_schema_not_found() {
+        let (url, handler) = mock_registry(vec![(
+            404,
+            r#"{"error_code": 40403, "message": "Schema not found"}"#.to_string(),
+        )])
+        .await;
+        let registry = SchemaRegistryClient::new(&SchemaRegistry {
+            url,
+            ..Default::default()
+        })
+        .with_retry(2, test_backoff());
+
+        let result = registry.get_schema_by_id(7).await;
+        assert!(matches!(
+            result,
+            Err(AvroError::Registry { status: 404, .. })
+        ));
+        assert!(!result.unwrap_err().is_retriable());
+        handler.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_encode_with_auto_register() {
+        let (url, handler) = mock_registry(vec![(200, r#"{"id": 3}"#.to_string())]).await;
+        let registry = SchemaRegistryClient::new(&SchemaRegistry {
+            url,
+            ..Default::default()
+        });
+        let format = AvroFormat {
+            subject: "users-value".to_string(),
+            auto_register: true,
+            schema: r#"{
+                "type": "record",
+                "name": "Payment",
+                "fields": [
+                    {"name": "id", "type": "long"},
+                    {"name": "amount", "type": {"type": "bytes", "logicalType": "decimal", "precision": 10, "scale": 2}},
+                    {"name": "paid_at", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+                    {"name": "memo", "type": ["null", "string"], "default": null}
+                ]
+            }"#
+            .to_string(),
+            ..Default::default()
+        };
+        let encoder = AvroEncoder::with_registry(registry.clone(), &format);
+        let value = TypedValue::Object(BTreeMap::from([
+            ("id".to_string(), TypedValue::BigInt(1)),
+            ("amount".to_string(), TypedValue::Number(-123.45)),
+            ("paid_at".to_string(), TypedValue::BigInt(1672531200000)),
+        ]));
+
+        let payload = encoder.encode(&value).await.unwrap();
+        handler.await.unwrap();
+        assert_eq!(unframe(&payload).unwrap().0, 3);
+
+        // the writer schema is resolved once
+        assert!(encoder.encode(&value).await.is_ok());
+
+        let decoder = AvroDecoder::with_registry(registry, &AvroFormat::default());
+        assert_eq!(
+            decoder.decode_cached(&payload),
+            Ok(TypedValue::Object(BTreeMap::from([
+                ("id".to_string(), TypedValue::BigInt(1)),
+                ("amount".to_string(), TypedValue::Number(-123.45)),
+                ("paid_at".to_string(), TypedValue::BigInt(1672531200000)),
+                ("memo".to_string(), TypedValue::Null),
+            ])))
+        );
+
+        let result = encoder
+            .encode(&TypedValue::Object(BTreeMap::from([(
+                "id".to_string(),
+                TypedValue::String("1".to_string()),
+            )])))
+            .await;
+        assert!(matches!(result, Err(AvroError::Encode(_))));
+    }
+}
@@ -0,0 +1,671 @@
+use std::{collections::BTreeMap, fmt::Display, io::Read};
+
+use proto::common::{
+    csv_format::{Column, QuotePolicy},
+    CsvFormat, DataTypeEnum,
+};
+
+use crate::types::TypedValue;
+
+/// Errors of encoding or decoding CSV records. The row number is 1-based and counts the header row if it exists.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvError {
+    /// the row has a different number of columns from the previous rows
+    ColumnCount {
+        row: u64,
+        expected: u64,
+        actual: u64,
+    },
+    /// the column which is configured in [`CsvFormat`] does not exist in the row
+    MissingColumn { row: u64, column: String },
+    /// the value of the column can not be coerced into the configured data type
+    Coercion {
+        row: u64,
+        column: String,
+        value: String,
+        data_type: DataTypeEnum,
+    },
+    /// the row is malformed, e.g. invalid utf-8
+    Malformed { row: u64, message: String },
+    /// the value can not be written as a CSV row
+    Encode(String),
+}
+
+impl CsvError {
+    /// the row number where the error occurs, 0 if the error is not related to a specific row
+    pub fn row(&self) -> u64 {
+        match self {
+            Self::ColumnCount { row, .. } => *row,
+            Self::MissingColumn { row, .. } => *row,
+            Self::Coercion { row, .. } => *row,
+            Self::Malformed { row, .. } => *row,
+            Self::Encode(_) => 0,
+        }
+    }
+
+    fn from_csv_error(err: ::csv::Error, row: u64) -> Self {
+        let row = err.position().map(|pos| pos.line()).unwrap_or(row);
+        match err.kind() {
+            ::csv::ErrorKind::UnequalLengths {
+                expected_len, len, ..
+            } => Self::ColumnCount {
+                row,
+                expected: *expected_len,
+                actual: *len,
+            },
+            _ => Self::Malformed {
+                row,
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+impl Display for CsvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ColumnCount {
+                row,
+                expected,
+                actual,
+            } => f.write_fmt(format_args!(
+                "row [{row}] has {actual} columns but {expected} columns are expected"
+            )),
+            Self::MissingColumn { row, column } => {
+                f.write_fmt(format_args!("row [{row}] has no column [{column}]"))
+            }
+            Self::Coercion {
+                row,
+                column,
+                value,
+                data_type,
+            } => f.write_fmt(format_args!(
+                "row [{row}] column [{column}]: value [{value}] can not be coerced into {}",
+                data_type.as_str_name()
+            )),
+            Self::Malformed { row, message } => {
+                f.write_fmt(format_args!("row [{row}] is malformed: {message}"))
+            }
+            Self::Encode(message) => f.write_fmt(format_args!("encode csv failed: {message}")),
+        }
+    }
+}
+
+/// Decodes CSV records into [`TypedValue`]s.
+///
+/// - If columns are configured, each row will be decoded as [`TypedValue::Object`] whose fields are the column names.
+/// A column is located by its name in the header row, or by its index if there's no header row.
+/// - If no column is configured but there's a header row, each row will be decoded as [`TypedValue::Object`] of strings keyed by header names.
+/// - Otherwise, each row will be decoded as [`TypedValue::Array`] of strings.
+#[derive(Clone, Debug)]
+pub struct CsvDecoder {
+    format: CsvFormat,
+}
+
+impl CsvDecoder {
+    pub fn new(format: &CsvFormat) -> Self {
+        Self {
+            format: format.clone(),
+        }
+    }
+
+    /// decode the rows from a reader. Rows are parsed incrementally so the reader will not be buffered entirely.
+    pub fn decode<R: Read>(&self, reader: R) -> CsvRows<'_, R> {
+        let reader = ::csv::ReaderBuilder::new()
+            .delimiter(self.format.get_delimiter())
+            .quote(self.format.get_quote())
+            .has_headers(self.format.has_header)
+            .flexible(false)
+            .from_reader(reader);
+        CsvRows {
+            format: &self.format,
+            reader,
+            columns: None,
+            record: ::csv::StringRecord::new(),
+            row: 0,
+            finished: false,
+        }
+    }
+
+    /// decode all rows of a slice
+    pub fn decode_slice(&self, data: &[u8]) -> Vec<Result<TypedValue, CsvError>> {
+        self.decode(data).collect()
+    }
+}
+
+/// An incremental iterator over the decoded rows of [`CsvDecoder`].
+pub struct CsvRows<'a, R> {
+    format: &'a CsvFormat,
+    reader: ::csv::Reader<R>,
+    /// column names and their indexes in a row, resolved after the header row is read
+    columns: Option<Vec<(String, usize, DataTypeEnum)>>,
+    record: ::csv::StringRecord,
+    row: u64,
+    finished: bool,
+}
+
+impl<'a, R: Read> CsvRows<'a, R> {
+    fn resolve_columns(&mut self) -> Result<Vec<(String, usize, DataTypeEnum)>, CsvError> {
+        let header = if self.format.has_header {
+            Some(
+                self.reader
+                    .headers()
+                    .map_err(|err| CsvError::from_csv_error(err, 1))?
+                    .clone(),
+            )
+        } else {
+            None
+        };
+
+        let columns = if self.format.columns.is_empty() {
+            header
+                .iter()
+                .flat_map(|header| header.iter())
+                .enumerate()
+                .map(|(index, name)| (name.to_string(), index, DataTypeEnum::String))
+                .collect()
+        } else {
+            self.format
+                .columns
+                .iter()
+                .map(|column| {
+                    let index = header
+                        .as_ref()
+                        .and_then(|header| header.iter().position(|name| name == column.name))
+                        .unwrap_or(column.index as usize);
+                    (column.name.clone(), index, column_type(column))
+                })
+                .collect()
+        };
+
+        Ok(columns)
+    }
+
+    fn decode_record(&self) -> Result<TypedValue, CsvError> {
+        match &self.columns {
+            Some(columns) if !columns.is_empty() => {
+                let mut object = BTreeMap::new();
+                for (name, index, data_type) in columns {
+                    let field = self
+                        .record
+                        .get(*index)
+                        .ok_or_else(|| CsvError::MissingColumn {
+                            row: self.row,
+                            column: name.clone(),
+                        })?;
+                    let value = coerce(field, *data_type).ok_or_else(|| CsvError::Coercion {
+                        row: self.row,
+                        column: name.clone(),
+                        value: field.to_string(),
+                        data_type: *data_type,
+                    })?;
+                    object.insert(name.clone(), value);
+                }
+                Ok(TypedValue::Object(object))
+            }
+            _ => Ok(TypedValue::Array(
+                self.record
+                    .iter()
+                    .map(|field| TypedValue::String(field.to_string()))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+impl<'a, R: Read> Iterator for CsvRows<'a, R> {
+    type Item = Result<TypedValue, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        if self.columns.is_none() {
+            match self.resolve_columns() {
+                Ok(columns) => self.columns = Some(columns),
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+
+        match self.reader.read_record(&mut self.record) {
+            Ok(true) => {
+                self.row = self
+                    .record
+                    .position()
+                    .map(|pos| pos.line())
+                    .unwrap_or(self.row + 1);
+                Some(self.decode_record())
+            }
+            Ok(false) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                // an I/O error can not be recovered, but a malformed row can be skipped
+                self.finished = err.is_io_error();
+                let err = CsvError::from_csv_error(err, self.row + 1);
+                self.row = err.row();
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Encodes [`TypedValue`]s into CSV records.
+///
+/// - [`TypedValue::Object`] is written in the order of the configured columns, or in the order of its keys if no column is configured.
+/// - [`TypedValue::Array`] is written as a row of its elements.
+/// - Other values are written as a row with a single column.
+///
+/// Null and undefined are written as empty fields.
+#[derive(Clone, Debug)]
+pub struct CsvEncoder {
+    format: CsvFormat,
+}
+
+impl CsvEncoder {
+    pub fn new(format: &CsvFormat) -> Self {
+        Self {
+            format: format.clone(),
+        }
+    }
+
+    /// encode rows into a CSV payload. If header emission is enabled, the payload starts with a header row.
+    pub fn encode(&self, rows: &[TypedValue]) -> Result<Vec<u8>, CsvError> {
+        let mut writer = ::csv::WriterBuilder::new()
+            .delimiter(self.format.get_delimiter())
+            .quote(self.format.get_quote())
+            .quote_style(quote_style(self.format.quote_policy()))
+            .flexible(true)
+            .from_writer(vec![]);
+
+        if self.format.has_header {
+            let header = if self.format.columns.is_empty() {
+                match rows.first() {
+                    Some(TypedValue::Object(object)) => object.keys().cloned().collect(),
+                    _ => vec![],
+                }
+            } else {
+                self.format
+                    .columns
+                    .iter()
+                    .map(|column| column.name.clone())
+                    .collect::<Vec<_>>()
+            };
+
+            if !header.is_empty() {
+                writer
+                    .write_record(&header)
+                    .map_err(|err| CsvError::Encode(err.to_string()))?;
+            }
+        }
+
+        for row in rows {
+            writer
+                .write_record(self.fields(row))
+                .map_err(|err| CsvError::Encode(err.to_string()))?;
+        }
+
+        writer
+            .into_inner()
+            .map_err(|err| CsvError::Encode(err.to_string()))
+    }
+
+    fn fields(&self, row: &TypedValue) -> Vec<String> {
+        match row {
+            TypedValue::Object(object) => {
+                if self.format.columns.is_empty() {
+                    object.values().map(to_field).collect()
+                } else {
+                    self.format
+                        .columns
+                        .iter()
+                        .map(|column| object.get(&column.name).map(to_field).unwrap_or_default())
+                        .collect()
+                }
+            }
+            TypedValue::Array(values) => values.iter().map(to_field).collect(),
+            value => vec![to_field(value)],
+        }
+    }
+}
+
+fn column_type(column: &Column) -> DataTypeEnum {
+    match column.data_type() {
+        DataTypeEnum::Unspecified => DataTypeEnum::String,
+        data_type => data_type,
+    }
+}
+
+/// coerce a field into the value of data type. Empty fields are coerced into null except for string.
+fn coerce(field: &str, data_type: DataTypeEnum) -> Option<TypedValue> {
+    if field.is_empty() && data_type != DataTypeEnum::String {
+        return Some(TypedValue::Null);
+    }
+
+    match data_type {
+        DataTypeEnum::Unspecified | DataTypeEnum::String => {
+            Some(TypedValue::String(field.to_string()))
+        }
+        DataTypeEnum::Bigint => field.trim().parse::<i64>().ok().map(TypedValue::BigInt),
+        DataTypeEnum::Number => field.trim().parse::<f64>().ok().map(TypedValue::Number),
+        DataTypeEnum::Boolean => match field.trim().to_lowercase().as_str() {
+            "true" | "1" => Some(TypedValue::Boolean(true)),
+            "false" | "0" => Some(TypedValue::Boolean(false)),
+            _ => None,
+        },
+        DataTypeEnum::Null => Some(TypedValue::Null),
+        DataTypeEnum::Object | DataTypeEnum::Array => {
+            match serde_json::from_str::<serde_json::Value>(field) {
+                Ok(value @ serde_json::Value::Object(_)) if data_type == DataTypeEnum::Object => {
+                    Some(TypedValue::from_json_value(value))
+                }
+                Ok(value @ serde_json::Value::Array(_)) if data_type == DataTypeEnum::Array => {
+                    Some(TypedValue::from_json_value(value))
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+fn to_field(value: &TypedValue) -> String {
+    match value {
+        TypedValue::Null | TypedValue::Invalid => String::new(),
+        value => value.to_string(),
+    }
+}
+
+fn quote_style(policy: QuotePolicy) -> ::csv::QuoteStyle {
+    match policy {
+        QuotePolicy::Necessary => ::csv::QuoteStyle::Necessary,
+        QuotePolicy::Always => ::csv::QuoteStyle::Always,
+        QuotePolicy::NonNumeric => ::csv::QuoteStyle::NonNumeric,
+        QuotePolicy::Never => ::csv::QuoteStyle::Never,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use proto::common::{
+        csv_format::{Column, QuotePolicy},
+        CsvFormat, DataTypeEnum,
+    };
+
+    use crate::types::TypedValue;
+
+    use super::{CsvDecoder, CsvEncoder, CsvError};
+
+    fn column(name: &str, index: u32, data_type: DataTypeEnum) -> Column {
+        Column {
+            name: name.to_string(),
+            index,
+            data_type: data_type as i32,
+        }
+    }
+
+    #[test]
+    fn test_decode_quoted_fields_with_delimiters_and_newlines() {
+        let decoder = CsvDecoder::new(&CsvFormat::default());
+        let data = "a,\"b,c\",\"d\ne\"\n\"f \"\"g\"\"\",h,i\n";
+
+        let rows = decoder.decode_slice(data.as_bytes());
+        assert_eq!(
+            rows,
+            vec![
+                Ok(TypedValue::Array(vec![
+                    TypedValue::String("a".to_string()),
+                    TypedValue::String("b,c".to_string()),
+                    TypedValue::String("d\ne".to_string()),
+                ])),
+                Ok(TypedValue::Array(vec![
+                    TypedValue::String("f \"g\"".to_string()),
+                    TypedValue::String("h".to_string()),
+                    TypedValue::String("i".to_string()),
+                ])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_with_custom_delimiter_and_quote() {
+        let decoder = CsvDecoder::new(&CsvFormat {
+            delimiter: ";".to_string(),
+            quote: "'".to_string(),
+            ..Default::default()
+        });
+
+        let rows = decoder.decode_slice("'a;b';c\n".as_bytes());
+        assert_eq!(
+            rows,
+            vec![Ok(TypedValue::Array(vec![
+                TypedValue::String("a;b".to_string()),
+                TypedValue::String("c".to_string()),
+            ]))]
+        );
+    }
+
+    #[test]
+    fn test_decode_header_and_column_mapping() {
+        let decoder = CsvDecoder::new(&CsvFormat {
+            has_header: true,
+            columns: vec![
+                column("count", 0, DataTypeEnum::Bigint),
+                column("name", 0, DataTypeEnum::Unspecified),
+                column("ok", 0, DataTypeEnum::Boolean),
+                column("score", 0, DataTypeEnum::Number),
+            ],
+            ..Default::default()
+        });
+        let data = "name,score,count,ok\n\"x, y\",1.5,10,true\nz,,3,0\n";
+
+        let rows = decoder.decode_slice(data.as_bytes());
+        assert_eq!(
+            rows,
+            vec![
+                Ok(TypedValue::Object(BTreeMap::from([
+                    ("count".to_string(), TypedValue::BigInt(10)),
+                    ("name".to_string(), TypedValue::String("x, y".to_string())),
+                    ("ok".to_string(), TypedValue::Boolean(true)),
+                    ("score".to_string(), TypedValue::Number(1.5)),
+                ]))),
+                Ok(TypedValue::Object(BTreeMap::from([
+                    ("count".to_string(), TypedValue::BigInt(3)),
+                    ("name".to_string(), TypedValue::String("z".to_string())),
+                    ("ok".to_string(), TypedValue::Boolean(false)),
+                    ("score".to_string(), TypedValue::Null),
+                ]))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_header_without_columns() {
+        let decoder = CsvDecoder::new(&CsvFormat {
+            has_header: true,
+            ..Default::default()
+        });
+
+        let rows = decoder.decode_slice("k,v\n1,2\n".as_bytes());
+        assert_eq!(
+            rows,
+            vec![Ok(TypedValue::Object(BTreeMap::from([
+                ("k".to_string(), TypedValue::String("1".to_string())),
+                ("v".to_string(), TypedValue::String("2".to_string())),
+            ])))]
+        );
+    }
+
+    #[test]
+    fn test_decode_columns_by_index() {
+        let decoder = CsvDecoder::new(&CsvFormat {
+            columns: vec![column("v", 1, DataTypeEnum::Bigint)],
+            ..Default::default()
+        });
+
+        let rows = decoder.decode_slice("a,1\nb,2\n".as_bytes());
+        assert_eq!(
+            rows,
+            vec![
+                Ok(TypedValue::Object(BTreeMap::from([(
+                    "v".to_string(),
+                    TypedValue::BigInt(1)
+                )]))),
+                Ok(TypedValue::Object(BTreeMap::from([(
+                    "v".to_string(),
+                    TypedValue::BigInt(2)
+                )]))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_errors_with_row_number() {
+        let decoder = CsvDecoder::new(&CsvFormat {
+            has_header: true,
+            columns: vec![column("v", 0, DataTypeEnum::Bigint)],
+            ..Default::default()
+        });
+        // the quoted newline makes the 2nd record span two lines
+        let data = "k,v\n\"a\nb\",1\nc,2,3\nd,x\ne,4\n";
+
+        let rows = decoder.decode_slice(data.as_bytes());
+        assert_eq!(rows.len(), 4);
+        assert_eq!(
+            rows[0],
+            Ok(TypedValue::Object(BTreeMap::from([(
+                "v".to_string(),
+                TypedValue::BigInt(1)
+            )])))
+        );
+        assert_eq!(
+            rows[1],
+            Err(CsvError::ColumnCount {
+                row: 4,
+                expected: 2,
+                actual: 3
+            })
+        );
+        assert_eq!(
+            rows[2],
+            Err(CsvError::Coercion {
+                row: 5,
+                column: "v".to_string(),
+                value: "x".to_string(),
+                data_type: DataTypeEnum::Bigint
+            })
+        );
+        assert_eq!(
+            rows[3],
+            Ok(TypedValue::Object(BTreeMap::from([(
+                "v".to_string(),
+                TypedValue::BigInt(4)
+            )])))
+        );
+    }
+
+    #[test]
+    fn test_decode_missing_column() {
+        let decoder = CsvDecoder::new(&CsvFormat {
+            columns: vec![column("v", 3, DataTypeEnum::String)],
+            ..Default::default()
+        });
+
+        let rows = decoder.decode_slice("a,b\n".as_bytes());
+        assert_eq!(
+            rows,
+            vec![Err(CsvError::MissingColumn {
+                row: 1,
+                column: "v".to_string()
+            })]
+        );
+    }
+
+    #[test]
+    fn test_encode_with_header_and_column_order() {
+        let encoder = CsvEncoder::new(&CsvFormat {
+            has_header: true,
+            columns: vec![
+                column("name", 0, DataTypeEnum::String),
+                column("count", 1, DataTypeEnum::Bigint),
+                column("missing", 2, DataTypeEnum::String),
+            ],
+            ..Default::default()
+        });
+        let rows = vec![
+            TypedValue::Object(BTreeMap::from([
+                ("count".to_string(), TypedValue::BigInt(1)),
+                ("name".to_string(), TypedValue::String("a,b".to_string())),
+            ])),
+            TypedValue::Object(BTreeMap::from([
+                ("count".to_string(), TypedValue::Null),
+                ("name".to_string(), TypedValue::String("c\nd".to_string())),
+            ])),
+        ];
+
+        let result = encoder.encode(&rows).expect("encode failed");
+        assert_eq!(
+            String::from_utf8(result).unwrap(),
+            "name,count,missing\n\"a,b\",1,\n\"c\nd\",,\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_quote_policy() {
+        let rows = vec![TypedValue::Array(vec![
+            TypedValue::String("a".to_string()),
+            TypedValue::BigInt(1),
+            TypedValue::Number(1.5),
+        ])];
+
+        let encoder = CsvEncoder::new(&CsvFormat {
+            quote_policy: QuotePolicy::Always as i32,
+            ..Default::default()
+        });
+        assert_eq!(
+            String::from_utf8(encoder.encode(&rows).unwrap()).unwrap(),
+            "\"a\",\"1\",\"1.5\"\n"
+        );
+
+        let encoder = CsvEncoder::new(&CsvFormat {
+            quote_policy: QuotePolicy::NonNumeric as i32,
+            ..Default::default()
+        });
+        assert_eq!(
+            String::from_utf8(encoder.encode(&rows).unwrap()).unwrap(),
+            "\"a\",1,1.5\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let format = CsvFormat {
+            has_header: true,
+            columns: vec![
+                column("name", 0, DataTypeEnum::String),
+                column("score", 1, DataTypeEnum::Number),
+            ],
+            ..Default::default()
+        };
+        let rows = vec![TypedValue::Object(BTreeMap::from([
+            (
+                "name".to_string(),
+                TypedValue::String("x,\"y\"\nz".to_string()),
+            ),
+            ("score".to_string(), TypedValue::Number(2.5)),
+        ]))];
+
+        let payload = CsvEncoder::new(&format).encode(&rows).unwrap();
+        let decoded = CsvDecoder::new(&format)
+            .decode_slice(&payload)
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(decoded, Ok(rows));
+    }
+}
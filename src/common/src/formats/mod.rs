@@ -0,0 +1,5 @@
+//! Encoders and decoders of the record formats which are supported by sources and sinks.
+
+pub mod avro;
+pub mod csv;
+pub mod protobuf;
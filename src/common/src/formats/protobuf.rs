@@ -0,0 +1,547 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use prost_reflect::{DynamicMessage, Kind, MapKey, MessageDescriptor, ReflectMessage, Value};
+use proto::common::ProtobufFormat;
+
+use crate::types::TypedValue;
+
+/// The reserved key under which unknown fields of a message are kept.
+/// Its value is an object from field number to the wire-format encodings (tag included) of the field.
+pub const UNKNOWN_FIELDS_KEY: &str = "__unknown_fields";
+
+/// Errors of decoding protobuf records.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtobufError {
+    /// the file descriptor set or the message name is invalid
+    InvalidFormat(String),
+    /// the payload can not be decoded as the message
+    Decode(String),
+}
+
+impl Display for ProtobufError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidFormat(message) => {
+                f.write_fmt(format_args!("invalid protobuf format: {message}"))
+            }
+            Self::Decode(message) => f.write_fmt(format_args!("protobuf decode failed: {message}")),
+        }
+    }
+}
+
+/// Decodes protobuf records dynamically by the message descriptor which is resolved from [`ProtobufFormat`].
+///
+/// A message is mapped onto [`TypedValue::Object`] keyed by field names:
+/// - integers are mapped to [`TypedValue::BigInt`]; uint64 values which overflow i64 are mapped to [`TypedValue::Number`]
+/// - float and double are mapped to [`TypedValue::Number`]
+/// - enums are mapped to the names of their values, or [`TypedValue::BigInt`] if the number is not defined
+/// - bytes are mapped to an array of bytes
+/// - repeated fields are mapped to [`TypedValue::Array`] and map fields are mapped to [`TypedValue::Object`]
+/// - fields with presence (messages, oneofs and optional fields) which are not set are mapped to [`TypedValue::Null`];
+///   other fields which are not set are mapped to their default values
+pub struct ProtobufDecoder {
+    message: MessageDescriptor,
+    preserve_unknown_fields: bool,
+}
+
+impl ProtobufDecoder {
+    pub fn new(format: &ProtobufFormat) -> Result<Self, ProtobufError> {
+        format
+            .get_message_descriptor()
+            .map(|message| Self {
+                message,
+                preserve_unknown_fields: format.preserve_unknown_fields,
+            })
+            .map_err(|err| ProtobufError::InvalidFormat(format!("{:?}", err)))
+    }
+
+    pub fn decode(&self, payload: &[u8]) -> Result<TypedValue, ProtobufError> {
+        DynamicMessage::decode(self.message.clone(), payload)
+            .map(|message| self.message_to_typed_value(&message))
+            .map_err(|err| ProtobufError::Decode(err.to_string()))
+    }
+
+    fn message_to_typed_value(&self, message: &DynamicMessage) -> TypedValue {
+        let mut fields = message
+            .descriptor()
+            .fields()
+            .map(|field| {
+                let value = if field.supports_presence() && !message.has_field(&field) {
+                    TypedValue::Null
+                } else {
+                    self.to_typed_value(&message.get_field(&field), &field.kind())
+                };
+                (field.name().to_string(), value)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        if self.preserve_unknown_fields {
+            let mut unknown_fields = BTreeMap::<String, TypedValue>::new();
+            message.unknown_fields().for_each(|field| {
+                let mut buf = vec![];
+                field.encode(&mut buf);
+                let encoded = bytes_to_typed_value(&buf);
+                match unknown_fields
+                    .entry(field.number().to_string())
+                    .or_insert_with(|| TypedValue::Array(vec![]))
+                {
+                    TypedValue::Array(values) => values.push(encoded),
+                    _ => {}
+                }
+            });
+            if !unknown_fields.is_empty() {
+                fields.insert(
+                    UNKNOWN_FIELDS_KEY.to_string(),
+                    TypedValue::Object(unknown_fields),
+                );
+            }
+        }
+
+        TypedValue::Object(fields)
+    }
+
+    fn to_typed_value(&self, value: &Value, kind: &Kind) -> TypedValue {
+        match value {
+            Value::Bool(v) => TypedValue::Boolean(*v),
+            Value::I32(v) => TypedValue::BigInt(*v as i64),
+            Value::I64(v) => TypedValue::BigInt(*v),
+            Value::U32(v) => TypedValue::BigInt(*v as i64),
+            Value::U64(v) => i64::try_from(*v)
+                .map(TypedValue::BigInt)
+                .unwrap_or(TypedValue::Number(*v as f64)),
+            Value::F32(v) => TypedValue::Number(*v as f64),
+            Value::F64(v) => TypedValue::Number(*v),
+            Value::String(v) => TypedValue::String(v.clone()),
+            Value::Bytes(v) => bytes_to_typed_value(v),
+            Value::EnumNumber(number) => kind
+                .as_enum()
+                .and_then(|desc| desc.get_value(*number))
+                .map(|value| TypedValue::String(value.name().to_string()))
+                .unwrap_or(TypedValue::BigInt(*number as i64)),
+            Value::Message(message) => self.message_to_typed_value(message),
+            Value::List(values) => TypedValue::Array(
+                values
+                    .iter()
+                    .map(|value| self.to_typed_value(value, kind))
+                    .collect(),
+            ),
+            Value::Map(entries) => {
+                let value_kind = kind
+                    .as_message()
+                    .map(|entry| entry.map_entry_value_field().kind())
+                    .unwrap_or_else(|| kind.clone());
+                TypedValue::Object(
+                    entries
+                        .iter()
+                        .map(|(key, value)| {
+                            (
+                                map_key_to_string(key),
+                                self.to_typed_value(value, &value_kind),
+                            )
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+fn bytes_to_typed_value(bytes: &[u8]) -> TypedValue {
+    TypedValue::Array(
+        bytes
+            .iter()
+            .map(|b| TypedValue::BigInt(*b as i64))
+            .collect(),
+    )
+}
+
+fn map_key_to_string(key: &MapKey) -> String {
+    match key {
+        MapKey::Bool(v) => v.to_string(),
+        MapKey::I32(v) => v.to_string(),
+        MapKey::I64(v) => v.to_string(),
+        MapKey::U32(v) => v.to_string(),
+        MapKey::U64(v) => v.to_string(),
+        MapKey::String(v) => v.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use prost::Message;
+    use prost_reflect::{DescriptorPool, DynamicMessage, MapKey, Value};
+    use prost_types::{
+        field_descriptor_proto::{Label, Type},
+        DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
+        FileDescriptorProto, FileDescriptorSet, MessageOptions, OneofDescriptorProto,
+    };
+    use proto::{
+        common::{
+            kafka_desc, operator_info::Details, source, DataTypeEnum, Dataflow, DataflowMeta,
+            KafkaDesc, OperatorInfo, ProtobufFormat, ResourceId, Source,
+        },
+        common_impl::DataflowValidateError,
+    };
+
+    use crate::types::TypedValue;
+
+    use super::{ProtobufDecoder, ProtobufError, UNKNOWN_FIELDS_KEY};
+
+    fn field(name: &str, number: i32, label: Label, r#type: Type) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(label as i32),
+            r#type: Some(r#type as i32),
+            json_name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn typed_field(
+        name: &str,
+        number: i32,
+        label: Label,
+        r#type: Type,
+        type_name: &str,
+    ) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            type_name: Some(type_name.to_string()),
+            ..field(name, number, label, r#type)
+        }
+    }
+
+    /// ```proto
+    /// syntax = "proto3";
+    /// package test;
+    /// enum Status { UNKNOWN = 0; ACTIVE = 1; }
+    /// message Address { string city = 1; }
+    /// message Person {
+    ///   string name = 1;
+    ///   int32 age = 2;
+    ///   repeated string tags = 3;
+    ///   Address address = 4;
+    ///   map<string, int64> scores = 5;
+    ///   Status status = 6;
+    ///   bytes avatar = 7;
+    ///   uint64 id = 8;
+    ///   oneof contact { string email = 9; string phone = 10; }
+    ///   // only in v2
+    ///   string nickname = 11;
+    /// }
+    /// ```
+    fn file_descriptor_set(v2: bool) -> Vec<u8> {
+        let mut person_fields = vec![
+            field("name", 1, Label::Optional, Type::String),
+            field("age", 2, Label::Optional, Type::Int32),
+            field("tags", 3, Label::Repeated, Type::String),
+            typed_field(
+                "address",
+                4,
+                Label::Optional,
+                Type::Message,
+                ".test.Address",
+            ),
+            typed_field(
+                "scores",
+                5,
+                Label::Repeated,
+                Type::Message,
+                ".test.Person.ScoresEntry",
+            ),
+            typed_field("status", 6, Label::Optional, Type::Enum, ".test.Status"),
+            field("avatar", 7, Label::Optional, Type::Bytes),
+            field("id", 8, Label::Optional, Type::Uint64),
+            FieldDescriptorProto {
+                oneof_index: Some(0),
+                ..field("email", 9, Label::Optional, Type::String)
+            },
+            FieldDescriptorProto {
+                oneof_index: Some(0),
+                ..field("phone", 10, Label::Optional, Type::String)
+            },
+        ];
+        if v2 {
+            person_fields.push(field("nickname", 11, Label::Optional, Type::String));
+        }
+
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("person.proto".to_string()),
+                package: Some("test".to_string()),
+                syntax: Some("proto3".to_string()),
+                enum_type: vec![EnumDescriptorProto {
+                    name: Some("Status".to_string()),
+                    value: vec![
+                        EnumValueDescriptorProto {
+                            name: Some("UNKNOWN".to_string()),
+                            number: Some(0),
+                            ..Default::default()
+                        },
+                        EnumValueDescriptorProto {
+                            name: Some("ACTIVE".to_string()),
+                            number: Some(1),
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                }],
+                message_type: vec![
+                    DescriptorProto {
+                        name: Some("Address".to_string()),
+                        field: vec![field("city", 1, Label::Optional, Type::String)],
+                        ..Default::default()
+                    },
+                    DescriptorProto {
+                        name: Some("Person".to_string()),
+                        field: person_fields,
+                        nested_type: vec![DescriptorProto {
+                            name: Some("ScoresEntry".to_string()),
+                            field: vec![
+                                field("key", 1, Label::Optional, Type::String),
+                                field("value", 2, Label::Optional, Type::Int64),
+                            ],
+                            options: Some(MessageOptions {
+                                map_entry: Some(true),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }],
+                        oneof_decl: vec![OneofDescriptorProto {
+                            name: Some("contact".to_string()),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+        }
+        .encode_to_vec()
+    }
+
+    fn new_format(message_name: &str, preserve_unknown_fields: bool) -> ProtobufFormat {
+        ProtobufFormat {
+            file_descriptor_set: file_descriptor_set(false),
+            message_name: message_name.to_string(),
+            preserve_unknown_fields,
+        }
+    }
+
+    /// encode a person by the v2 descriptor
+    fn encode_person(nickname: Option<&str>) -> Vec<u8> {
+        let pool = DescriptorPool::decode(file_descriptor_set(true).as_slice()).unwrap();
+        let desc = pool.get_message_by_name("test.Person").unwrap();
+        let address_desc = pool.get_message_by_name("test.Address").unwrap();
+
+        let mut address = DynamicMessage::new(address_desc);
+        address.set_field_by_name("city", Value::String("Shanghai".to_string()));
+
+        let mut person = DynamicMessage::new(desc);
+        person.set_field_by_name("name", Value::String("alice".to_string()));
+        person.set_field_by_name("age", Value::I32(18));
+        person.set_field_by_name(
+            "tags",
+            Value::List(vec![
+                Value::String("a".to_string()),
+                Value::String("b".to_string()),
+            ]),
+        );
+        person.set_field_by_name("address", Value::Message(address));
+        person.set_field_by_name(
+            "scores",
+            Value::Map(HashMap::from([(
+                MapKey::String("math".to_string()),
+                Value::I64(90),
+            )])),
+        );
+        person.set_field_by_name("status", Value::EnumNumber(1));
+        person.set_field_by_name("avatar", Value::Bytes(vec![1u8, 2].into()));
+        person.set_field_by_name("id", Value::U64(u64::MAX));
+        person.set_field_by_name("email", Value::String("alice@a.com".to_string()));
+        nickname.iter().for_each(|nickname| {
+            person.set_field_by_name("nickname", Value::String(nickname.to_string()))
+        });
+        person.encode_to_vec()
+    }
+
+    fn expected_person() -> BTreeMap<String, TypedValue> {
+        BTreeMap::from([
+            ("name".to_string(), TypedValue::String("alice".to_string())),
+            ("age".to_string(), TypedValue::BigInt(18)),
+            (
+                "tags".to_string(),
+                TypedValue::Array(vec![
+                    TypedValue::String("a".to_string()),
+                    TypedValue::String("b".to_string()),
+                ]),
+            ),
+            (
+                "address".to_string(),
+                TypedValue::Object(BTreeMap::from([(
+                    "city".to_string(),
+                    TypedValue::String("Shanghai".to_string()),
+                )])),
+            ),
+            (
+                "scores".to_string(),
+                TypedValue::Object(BTreeMap::from([(
+                    "math".to_string(),
+                    TypedValue::BigInt(90),
+                )])),
+            ),
+            (
+                "status".to_string(),
+                TypedValue::String("ACTIVE".to_string()),
+            ),
+            (
+                "avatar".to_string(),
+                TypedValue::Array(vec![TypedValue::BigInt(1), TypedValue::BigInt(2)]),
+            ),
+            ("id".to_string(), TypedValue::Number(u64::MAX as f64)),
+            (
+                "email".to_string(),
+                TypedValue::String("alice@a.com".to_string()),
+            ),
+            ("phone".to_string(), TypedValue::Null),
+        ])
+    }
+
+    #[test]
+    fn test_decode() {
+        let decoder = ProtobufDecoder::new(&new_format("test.Person", false)).unwrap();
+        assert_eq!(
+            decoder.decode(&encode_person(None)),
+            Ok(TypedValue::Object(expected_person()))
+        );
+
+        // unknown fields are dropped by default
+        assert_eq!(
+            decoder.decode(&encode_person(Some("ali"))),
+            Ok(TypedValue::Object(expected_person()))
+        );
+
+        // fields which are not set
+        let mut expected = BTreeMap::from([
+            ("name".to_string(), TypedValue::String("".to_string())),
+            ("age".to_string(), TypedValue::BigInt(0)),
+            ("tags".to_string(), TypedValue::Array(vec![])),
+            ("address".to_string(), TypedValue::Null),
+            ("scores".to_string(), TypedValue::Object(Default::default())),
+            (
+                "status".to_string(),
+                TypedValue::String("UNKNOWN".to_string()),
+            ),
+            ("avatar".to_string(), TypedValue::Array(vec![])),
+            ("id".to_string(), TypedValue::BigInt(0)),
+            ("email".to_string(), TypedValue::Null),
+            ("phone".to_string(), TypedValue::Null),
+        ]);
+        assert_eq!(
+            decoder.decode(&[]),
+            Ok(TypedValue::Object(expected.clone()))
+        );
+
+        // undefined enum number
+        expected.insert("status".to_string(), TypedValue::BigInt(7));
+        assert_eq!(
+            decoder.decode(&[0x30, 0x07]),
+            Ok(TypedValue::Object(expected))
+        );
+    }
+
+    #[test]
+    fn test_decode_preserve_unknown_fields() {
+        let decoder = ProtobufDecoder::new(&new_format("test.Person", true)).unwrap();
+        assert_eq!(
+            decoder.decode(&encode_person(None)),
+            Ok(TypedValue::Object(expected_person()))
+        );
+
+        let mut expected = expected_person();
+        // tag of field 11 with wire type 2, length and "ali"
+        expected.insert(
+            UNKNOWN_FIELDS_KEY.to_string(),
+            TypedValue::Object(BTreeMap::from([(
+                "11".to_string(),
+                TypedValue::Array(vec![TypedValue::Array(
+                    [0x5a, 3, b'a', b'l', b'i']
+                        .iter()
+                        .map(|b| TypedValue::BigInt(*b as i64))
+                        .collect(),
+                )]),
+            )])),
+        );
+        assert_eq!(
+            decoder.decode(&encode_person(Some("ali"))),
+            Ok(TypedValue::Object(expected))
+        );
+    }
+
+    #[test]
+    fn test_decode_failed() {
+        let decoder = ProtobufDecoder::new(&new_format("test.Person", false)).unwrap();
+        // field 1 is length-delimited but the payload is truncated
+        assert!(matches!(
+            decoder.decode(&[0x0a, 0x05, b'a']),
+            Err(ProtobufError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_format() {
+        assert!(matches!(
+            ProtobufDecoder::new(&new_format("test.Persons", false)),
+            Err(ProtobufError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            ProtobufDecoder::new(&ProtobufFormat {
+                file_descriptor_set: vec![0xff, 0xff],
+                message_name: "test.Person".to_string(),
+                preserve_unknown_fields: false,
+            }),
+            Err(ProtobufError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_dataflow_with_protobuf_format() {
+        let new_dataflow = |message_name: &str| Dataflow {
+            job_id: Some(ResourceId::new("namespace_id", "resourceId")),
+            meta: vec![DataflowMeta {
+                center: 0,
+                neighbors: vec![],
+                edge_types: Default::default(),
+            }],
+            nodes: HashMap::from([(
+                0,
+                OperatorInfo {
+                    operator_id: 0,
+                    details: Some(Details::Source(Source {
+                        desc: Some(source::Desc::Kafka(KafkaDesc {
+                            brokers: vec!["localhost:9092".to_string()],
+                            topic: "topic".to_string(),
+                            data_type: DataTypeEnum::Object as i32,
+                            format: Some(kafka_desc::Format::Protobuf(new_format(
+                                message_name,
+                                false,
+                            ))),
+                            ..Default::default()
+                        })),
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+
+        assert!(new_dataflow("test.Person").validate().is_ok());
+        match new_dataflow("test.Persons").validate() {
+            Err(DataflowValidateError::InvalidProtobufFormat(_)) => {}
+            _ => panic!("unexpected result"),
+        }
+    }
+}
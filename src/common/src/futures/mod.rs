@@ -32,6 +32,61 @@ pub fn join_all<'a, T, F: Fn(T)>(
     }) {}
 }
 
+/// [`JoinBounded`] is like [`join_all`], but the futures are polled concurrently instead of one after another, so a slow future doesn't delay the following ones.
+/// At most `concurrency` futures are in flight, and the next future in the list starts once one of them is ready.
+/// The futures which are not ready are kept across polls, so a pending future yields the thread instead of being polled in a busy loop.
+/// It resolves to the outputs with the indexes of their futures, in the order they're ready
+pub struct JoinBounded<'a, T> {
+    fut_list: Vec<Option<Pin<Box<dyn Future<Output = T> + Send + 'a>>>>,
+    concurrency: usize,
+    outputs: Vec<(usize, T)>,
+}
+
+impl<'a, T> JoinBounded<'a, T> {
+    pub fn new(
+        fut_list: Vec<Pin<Box<dyn Future<Output = T> + Send + 'a>>>,
+        concurrency: usize,
+    ) -> Self {
+        Self {
+            outputs: Vec::with_capacity(fut_list.len()),
+            fut_list: fut_list.into_iter().map(Some).collect(),
+            concurrency: concurrency.max(1),
+        }
+    }
+}
+
+// the outputs are never pinned, so it's not pinned by them either
+impl<T> Unpin for JoinBounded<'_, T> {}
+
+impl<T> Future for JoinBounded<'_, T> {
+    type Output = Vec<(usize, T)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        // the futures in flight are always the first ones which are not ready, so they're polled before the following ones start
+        let mut in_flight = 0;
+        for (idx, slot) in this.fut_list.iter_mut().enumerate() {
+            if in_flight >= this.concurrency {
+                break;
+            }
+            if let Some(fut) = slot {
+                match fut.poll_unpin(cx) {
+                    Poll::Ready(val) => {
+                        this.outputs.push((idx, val));
+                        *slot = None;
+                    }
+                    Poll::Pending => in_flight += 1,
+                }
+            }
+        }
+        if in_flight > 0 {
+            Poll::Pending
+        } else {
+            Poll::Ready(std::mem::take(&mut this.outputs))
+        }
+    }
+}
+
 pub fn select<Left, Right>(left: Poll<Left>, right: Poll<Right>) -> Poll<Either<Left, Right>> {
     match left {
         Poll::Ready(val) => Poll::Ready(Either::Left(val)),
@@ -44,11 +99,14 @@ pub fn select<Left, Right>(left: Poll<Left>, right: Poll<Right>) -> Poll<Either<
 
 #[cfg(test)]
 mod tests {
-    use std::sync::atomic::{AtomicI64, Ordering};
+    use std::{
+        pin::Pin,
+        sync::atomic::{AtomicI64, Ordering},
+    };
 
     use tonic::async_trait;
 
-    use crate::futures::join_all;
+    use crate::futures::{join_all, JoinBounded};
 
     #[tokio::test]
     async fn test_join_all() {
@@ -92,4 +150,90 @@ mod tests {
         assert!(ok.is_ok());
         assert_eq!(ok.unwrap(), 14);
     }
+
+    /// the futures record the most futures in flight at the same time
+    struct InFlight {
+        current: AtomicI64,
+        max: AtomicI64,
+    }
+
+    impl InFlight {
+        async fn run(&self, idx: usize, delay_millis: u64) -> usize {
+            let current = self.current.fetch_add(1, Ordering::AcqRel) + 1;
+            self.max.fetch_max(current, Ordering::AcqRel);
+            tokio::time::sleep(std::time::Duration::from_millis(delay_millis)).await;
+            self.current.fetch_sub(1, Ordering::AcqRel);
+            idx
+        }
+    }
+
+    /// it returns the most futures in flight and the indexes of the futures in the order they're ready
+    async fn join_bounded(concurrency: usize, delays: Vec<u64>) -> (i64, Vec<usize>) {
+        let in_flight = InFlight {
+            current: AtomicI64::new(0),
+            max: AtomicI64::new(0),
+        };
+        let futures = delays
+            .iter()
+            .enumerate()
+            .map(|(idx, delay)| {
+                Box::pin(in_flight.run(idx, *delay))
+                    as Pin<Box<dyn futures_util::Future<Output = usize> + Send + '_>>
+            })
+            .collect::<Vec<_>>();
+        let ready = JoinBounded::new(futures, concurrency)
+            .await
+            .into_iter()
+            .map(|(idx, result)| {
+                assert_eq!(idx, result);
+                result
+            })
+            .collect();
+
+        (in_flight.max.load(Ordering::Relaxed), ready)
+    }
+
+    #[tokio::test]
+    async fn test_join_bounded() {
+        // all the futures are in flight at the same time, so the fast ones are ready before the slow one
+        let (max_in_flight, ready) = join_bounded(8, vec![100, 10, 10, 10]).await;
+        assert_eq!(max_in_flight, 4);
+        assert_eq!(ready.last(), Some(&0));
+        assert_eq!(ready.len(), 4);
+
+        // the concurrency is bounded
+        let (max_in_flight, mut ready) = join_bounded(2, vec![10; 5]).await;
+        assert_eq!(max_in_flight, 2);
+        ready.sort();
+        assert_eq!(ready, vec![0, 1, 2, 3, 4]);
+
+        // a zero concurrency is treated as one
+        let (max_in_flight, ready) = join_bounded(0, vec![10; 3]).await;
+        assert_eq!(max_in_flight, 1);
+        assert_eq!(ready, vec![0, 1, 2]);
+
+        // nothing to join
+        assert_eq!(join_bounded(2, vec![]).await, (0, vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_join_bounded_yields_on_pending() {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut join = JoinBounded::new(
+            vec![
+                Box::pin(async { 0usize })
+                    as Pin<Box<dyn futures_util::Future<Output = usize> + Send>>,
+                Box::pin(async { rx.await.unwrap() }),
+                Box::pin(async { 2 }),
+            ],
+            2,
+        );
+        // the pending future is kept for the next poll instead of blocking the thread, and the following one isn't delayed by it
+        assert!(futures_util::poll!(&mut join).is_pending());
+        assert!(futures_util::poll!(&mut join).is_pending());
+
+        // the sender runs on the same thread
+        tokio::spawn(async move { tx.send(1) });
+        assert_eq!(join.await, vec![(0, 0), (2, 2), (1, 1)]);
+    }
 }
@@ -0,0 +1,357 @@
+use std::fmt::{self, Display};
+
+use proto::{
+    common::{redaction, source_sampling, KeyedDataEvent, Redaction, Source, SourceSampling},
+    common_impl::DataflowValidateError,
+    json_path::{JsonPath, PathSegment},
+};
+use sha2::{Digest, Sha256};
+
+use crate::types::TypedValue;
+
+/// metric of the events dropped by the sampling of the source
+pub const SOURCE_SAMPLED_OUT_METRIC: &str = "source.sampled_out";
+/// metric of the payload fields redacted by the source
+pub const SOURCE_REDACTED_FIELDS_METRIC: &str = "source.redacted_fields";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IngestError {
+    /// the sampling of the source is invalid
+    InvalidSampling(String),
+    /// a redaction of the source is invalid
+    InvalidRedaction(String),
+}
+
+impl Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IngestError::InvalidSampling(msg) => write!(f, "invalid source sampling: {}", msg),
+            IngestError::InvalidRedaction(msg) => write!(f, "invalid redaction: {}", msg),
+        }
+    }
+}
+
+impl From<DataflowValidateError> for IngestError {
+    fn from(err: DataflowValidateError) -> Self {
+        match err {
+            DataflowValidateError::InvalidSourceSampling(msg) => Self::InvalidSampling(msg),
+            DataflowValidateError::InvalidRedaction(msg) => Self::InvalidRedaction(msg),
+            _ => Self::InvalidRedaction(format!("{:?}", err)),
+        }
+    }
+}
+
+/// [`Sampler`] keeps 1 of every `rate` events, randomly or by the hash of the event key
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    rate: u64,
+    mode: source_sampling::Mode,
+}
+
+impl Sampler {
+    pub fn new(sampling: &SourceSampling) -> Result<Self, IngestError> {
+        if sampling.rate == 0 {
+            return Err(IngestError::InvalidSampling(
+                "rate must be positive".to_string(),
+            ));
+        }
+        Ok(Self {
+            rate: sampling.rate as u64,
+            mode: sampling.get_mode()?,
+        })
+    }
+
+    /// whether the event is kept
+    pub fn sample(&self, event: &KeyedDataEvent) -> bool {
+        if self.rate <= 1 {
+            return true;
+        }
+        match (self.mode, event.key.as_ref()) {
+            (source_sampling::Mode::Key, Some(key)) => {
+                let digest = Sha256::digest(&key.value);
+                let mut bucket = [0; 8];
+                bucket.copy_from_slice(&digest[..8]);
+                u64::from_be_bytes(bucket) % self.rate == 0
+            }
+            _ => rand::random::<u64>() % self.rate == 0,
+        }
+    }
+}
+
+struct RedactedField {
+    path: JsonPath,
+    strategy: redaction::Strategy,
+    mask: String,
+}
+
+/// [`Redactor`] replaces the values of the redacted fields of a payload by a mask or their hashes.
+/// The missing and null fields are left as is
+#[derive(Default)]
+pub struct Redactor {
+    fields: Vec<RedactedField>,
+}
+
+impl Redactor {
+    pub fn new(redactions: &[Redaction]) -> Result<Self, IngestError> {
+        let mut fields = vec![];
+        for redaction in redactions {
+            fields.push(RedactedField {
+                path: redaction.get_path()?,
+                strategy: redaction.get_strategy()?,
+                mask: redaction.get_mask().to_string(),
+            })
+        }
+        Ok(Self { fields })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// redact the payload in place. It returns the number of the redacted fields
+    pub fn redact(&self, payload: &mut TypedValue) -> usize {
+        let mut redacted = 0;
+        for field in &self.fields {
+            let value = match select_mut(payload, &field.path.segments) {
+                Some(value) if !matches!(value, TypedValue::Null | TypedValue::Invalid) => value,
+                _ => continue,
+            };
+            *value = match field.strategy {
+                redaction::Strategy::Mask => TypedValue::String(field.mask.clone()),
+                redaction::Strategy::Hash => TypedValue::String(hash(value)),
+            };
+            redacted += 1;
+        }
+        redacted
+    }
+}
+
+fn select_mut<'a>(
+    value: &'a mut TypedValue,
+    segments: &[PathSegment],
+) -> Option<&'a mut TypedValue> {
+    segments
+        .iter()
+        .try_fold(value, |current, segment| match (segment, current) {
+            (PathSegment::Field(name), TypedValue::Object(object)) => object.get_mut(name),
+            (PathSegment::Index(index), TypedValue::Array(array)) => array.get_mut(*index),
+            _ => None,
+        })
+}
+
+/// the hex-encoded SHA-256 of the JSON encoding of the value
+fn hash(value: &TypedValue) -> String {
+    Sha256::digest(value.to_json_value().to_string().as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// [`Ingestion`] samples and redacts the events fetched by a source before they enter the pipeline.
+/// It's applied before the events are recorded by the replay buffer, so the raw values of the redacted fields never reach
+/// the checkpoints, the dead letters, the taps or the logs.
+pub struct Ingestion {
+    sampler: Option<Sampler>,
+    redactor: Redactor,
+}
+
+impl Ingestion {
+    /// it returns `None` if neither sampling nor redaction is configured for the source
+    pub fn new(source: &Source) -> Result<Option<Self>, IngestError> {
+        let sampler = source.sampling.as_ref().map(Sampler::new).transpose()?;
+        let redactor = Redactor::new(&source.redactions)?;
+        if sampler.is_none() && redactor.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self { sampler, redactor }))
+    }
+
+    /// sample and redact the event. It returns `None` if the event is sampled out.
+    /// Otherwise it returns the number of the redacted fields of all payloads
+    pub fn ingest(&self, event: &mut KeyedDataEvent) -> Option<usize> {
+        if let Some(sampler) = &self.sampler {
+            if !sampler.sample(event) {
+                return None;
+            }
+        }
+        if self.redactor.is_empty() {
+            return Some(0);
+        }
+        let mut redacted = 0;
+        for entry in event.data.iter_mut() {
+            let mut payload = TypedValue::from(&*entry);
+            let fields = self.redactor.redact(&mut payload);
+            if fields > 0 {
+                entry.set_data_type(payload.get_type());
+                entry.value = payload.get_data_bytes();
+                redacted += fields;
+            }
+        }
+        Some(redacted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proto::common::{
+        redaction, source_sampling, Entry, KeyedDataEvent, Redaction, Source, SourceSampling,
+    };
+
+    use super::{IngestError, Ingestion, Redactor, Sampler};
+    use crate::types::TypedValue;
+
+    fn new_event(key: Option<&str>, payload: serde_json::Value) -> KeyedDataEvent {
+        let payload = TypedValue::from_json_value(payload);
+        let mut event = KeyedDataEvent::default();
+        event.key = key.map(|key| {
+            let key = TypedValue::String(key.to_string());
+            let mut entry = Entry::default();
+            entry.set_data_type(key.get_type());
+            entry.value = key.get_data_bytes();
+            entry
+        });
+        let mut entry = Entry::default();
+        entry.set_data_type(payload.get_type());
+        entry.value = payload.get_data_bytes();
+        event.data = vec![entry];
+        event
+    }
+
+    fn redaction(path: &str, strategy: redaction::Strategy) -> Redaction {
+        Redaction {
+            path: path.to_string(),
+            strategy: strategy as i32,
+            mask: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_redact() {
+        let redactor = Redactor::new(&[
+            redaction("$.user.email", redaction::Strategy::Mask),
+            redaction("$.cards[1]", redaction::Strategy::Hash),
+            redaction("$.ssn", redaction::Strategy::Hash),
+            redaction("$.missing.field", redaction::Strategy::Mask),
+        ])
+        .unwrap();
+        let mut payload = TypedValue::from_json_value(serde_json::json!({
+            "user": {"name": "alice", "email": "alice@example.com"},
+            "cards": ["1111", "2222"],
+            "ssn": null,
+        }));
+        assert_eq!(redactor.redact(&mut payload), 2);
+
+        let hashed = payload.to_json_value()["cards"][1].clone();
+        assert_eq!(
+            payload.to_json_value(),
+            serde_json::json!({
+                "user": {"name": "alice", "email": "***"},
+                "cards": ["1111", hashed.clone()],
+                "ssn": null,
+            })
+        );
+        // the hashes of the same value are the same
+        let mut other = TypedValue::from_json_value(serde_json::json!({"cards": [0, "2222"]}));
+        assert_eq!(redactor.redact(&mut other), 1);
+        assert_eq!(other.to_json_value()["cards"][1], hashed);
+        assert_eq!(hashed.as_str().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_invalid_redactions() {
+        for path in ["user.email", "$", "$.user?(@.email).email"] {
+            assert!(matches!(
+                Redactor::new(&[redaction(path, redaction::Strategy::Mask)]),
+                Err(IngestError::InvalidRedaction(_))
+            ));
+        }
+        let mut invalid = redaction("$.email", redaction::Strategy::Mask);
+        invalid.strategy = 10;
+        assert!(Redactor::new(&[invalid]).is_err());
+        assert!(matches!(
+            Sampler::new(&SourceSampling::default()),
+            Err(IngestError::InvalidSampling(_))
+        ));
+    }
+
+    #[test]
+    fn test_sample() {
+        let sampler = Sampler::new(&SourceSampling {
+            rate: 4,
+            mode: source_sampling::Mode::Random as i32,
+        })
+        .unwrap();
+        let kept = (0..4000)
+            .filter(|_| sampler.sample(&new_event(None, serde_json::json!({}))))
+            .count();
+        assert!(kept > 700 && kept < 1300, "{} events are kept", kept);
+
+        // all or none of the events of a key are kept
+        let sampler = Sampler::new(&SourceSampling {
+            rate: 4,
+            mode: source_sampling::Mode::Key as i32,
+        })
+        .unwrap();
+        let mut kept_keys = 0;
+        for key in 0..400 {
+            let event = new_event(Some(&key.to_string()), serde_json::json!({}));
+            let kept = sampler.sample(&event);
+            assert!((0..10).all(|_| sampler.sample(&event) == kept));
+            kept_keys += kept as usize;
+        }
+        assert!(
+            kept_keys > 50 && kept_keys < 150,
+            "{} keys are kept",
+            kept_keys
+        );
+
+        // rate 1 keeps all events
+        let sampler = Sampler::new(&SourceSampling {
+            rate: 1,
+            mode: source_sampling::Mode::Key as i32,
+        })
+        .unwrap();
+        assert!((0..100)
+            .all(|key| sampler.sample(&new_event(Some(&key.to_string()), serde_json::json!({})))));
+    }
+
+    #[test]
+    fn test_ingest() {
+        assert!(Ingestion::new(&Source::default()).unwrap().is_none());
+
+        let ingestion = Ingestion::new(&Source {
+            desc: None,
+            sampling: None,
+            redactions: vec![redaction("$.email", redaction::Strategy::Mask)],
+        })
+        .unwrap()
+        .unwrap();
+        let mut event = new_event(None, serde_json::json!({"email": "alice@example.com"}));
+        event.data.push(event.data[0].clone());
+        assert_eq!(ingestion.ingest(&mut event), Some(2));
+        for entry in &event.data {
+            assert_eq!(
+                TypedValue::from(entry).to_json_value(),
+                serde_json::json!({"email": "***"})
+            );
+        }
+
+        let ingestion = Ingestion::new(&Source {
+            desc: None,
+            sampling: Some(SourceSampling {
+                rate: u32::MAX,
+                mode: source_sampling::Mode::Key as i32,
+            }),
+            redactions: vec![],
+        })
+        .unwrap()
+        .unwrap();
+        let sampled_out = (0..10)
+            .filter(|key| {
+                let mut event = new_event(Some(&key.to_string()), serde_json::json!({}));
+                ingestion.ingest(&mut event).is_none()
+            })
+            .count();
+        assert!(sampled_out > 0);
+    }
+}
@@ -1,33 +1,43 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
 
-use futures_util::StreamExt;
+use futures_util::{future, StreamExt};
 use rdkafka::{
     consumer::{Consumer, StreamConsumer},
-    producer::{FutureProducer, FutureRecord},
-    ClientConfig, Message,
+    error::KafkaError,
+    message::{Header, Headers, OwnedHeaders, OwnedMessage},
+    producer::{DeliveryFuture, FutureProducer, FutureRecord, Producer},
+    ClientConfig, Message, Offset, TopicPartitionList,
 };
 
+use tokio_util::sync::CancellationToken;
+
 use crate::err::KafkaException;
 
-pub fn run_consumer(
-    brokers: &str,
-    group_id: &str,
-    topic: &str,
-) -> Result<KafkaConsumer, rdkafka::error::KafkaError> {
+fn consumer_config(brokers: &str, group_id: &str, auto_commit: bool) -> ClientConfig {
     let group_id = if group_id.is_empty() {
         "lightflus"
     } else {
         group_id
     };
 
-    let consumer_result: Result<StreamConsumer, rdkafka::error::KafkaError> = ClientConfig::new()
+    let mut config = ClientConfig::new();
+    config
         .set("group.id", group_id)
         .set("bootstrap.servers", brokers)
         .set("enable.partition.eof", "false")
         .set("session.timeout.ms", "6000")
-        .set("enable.auto.commit", "true")
-        .set("auto.offset.reset", "beginning")
-        .create();
+        .set("enable.auto.commit", auto_commit.to_string())
+        .set("auto.offset.reset", "beginning");
+    config
+}
+
+pub fn run_consumer(
+    brokers: &str,
+    group_id: &str,
+    topic: &str,
+) -> Result<KafkaConsumer, rdkafka::error::KafkaError> {
+    let consumer_result: Result<StreamConsumer, rdkafka::error::KafkaError> =
+        consumer_config(brokers, group_id, true).create();
     consumer_result.and_then(|consumer| {
         consumer
             .subscribe(&[topic])
@@ -35,6 +45,41 @@ pub fn run_consumer(
     })
 }
 
+/// Like [`run_consumer`], but the offsets are committed by the caller instead of being committed to the group automatically.
+/// The partitions of the topic resume from `offsets`, which are the next offsets to consume keyed by partition,
+/// and the partitions without offset resume from the offsets committed to the group. All partitions are assigned to the consumer
+pub fn run_consumer_from(
+    brokers: &str,
+    group_id: &str,
+    topic: &str,
+    offsets: &BTreeMap<i32, i64>,
+    timeout: Duration,
+) -> Result<KafkaConsumer, rdkafka::error::KafkaError> {
+    let consumer: StreamConsumer = consumer_config(brokers, group_id, false).create()?;
+    if offsets.is_empty() {
+        return consumer
+            .subscribe(&[topic])
+            .map(|_| KafkaConsumer::new(consumer));
+    }
+    let metadata = consumer.fetch_metadata(Some(topic), timeout)?;
+    let mut assignment = TopicPartitionList::new();
+    for partition in metadata
+        .topics()
+        .iter()
+        .filter(|topic_metadata| topic_metadata.name() == topic)
+        .flat_map(|topic_metadata| topic_metadata.partitions())
+    {
+        let offset = offsets
+            .get(&partition.id())
+            .map(|offset| Offset::Offset(*offset))
+            .unwrap_or(Offset::Stored);
+        assignment.add_partition_offset(topic, partition.id(), offset)?;
+    }
+    consumer
+        .assign(&assignment)
+        .map(|_| KafkaConsumer::new(consumer))
+}
+
 pub fn run_producer(
     brokers: &str,
     topic: &str,
@@ -85,12 +130,129 @@ impl KafkaProducer {
         }
     }
 
+    /// enqueue a record without waiting for its delivery. The record is sent to the partition of the producer if it has no partition.
+    /// The returned [`KafkaDelivery`] resolves once the delivery report of the record is received
+    pub fn enqueue(&self, record: &KafkaRecord) -> Result<KafkaDelivery, KafkaException> {
+        let mut future_record = FutureRecord::to(self.topic.as_str())
+            .partition(record.partition.unwrap_or(self.partition))
+            .payload(record.payload.as_ref())
+            .key(record.key.as_ref());
+        if let Some(timestamp) = record.timestamp {
+            future_record = future_record.timestamp(timestamp);
+        }
+        if !record.headers.is_empty() {
+            future_record = future_record.headers(record.headers.iter().fold(
+                OwnedHeaders::new_with_capacity(record.headers.len()),
+                |headers, (key, value)| {
+                    headers.insert(Header {
+                        key,
+                        value: Some(value),
+                    })
+                },
+            ));
+        }
+
+        self.producer
+            .send_result(future_record)
+            .map(KafkaDelivery)
+            .map_err(|(err, _)| KafkaException { err })
+    }
+
+    /// the number of partitions of the topic, it's fetched from the brokers
+    pub fn get_partition_count(&self, timeout: Duration) -> Result<i32, KafkaException> {
+        self.producer
+            .client()
+            .fetch_metadata(Some(&self.topic), timeout)
+            .map_err(|err| KafkaException { err })
+            .and_then(|metadata| {
+                metadata
+                    .topics()
+                    .iter()
+                    .find(|topic| topic.name() == self.topic)
+                    .map(|topic| topic.partitions().len() as i32)
+                    .filter(|partitions| *partitions > 0)
+                    .ok_or(KafkaException {
+                        err: KafkaError::MetadataFetch(
+                            rdkafka::types::RDKafkaErrorCode::UnknownTopicOrPartition,
+                        ),
+                    })
+            })
+    }
+
+    /// wait until all the enqueued messages are delivered
+    pub fn flush(&self, timeout: Duration) -> Result<(), KafkaException> {
+        self.producer
+            .flush(timeout)
+            .map_err(|err| KafkaException { err })
+    }
+
     pub fn close(&mut self) {
         self.topic.clear();
         drop(self.partition);
     }
 }
 
+/// A record sent by [`KafkaProducer::enqueue`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KafkaRecord {
+    pub key: bytes::Bytes,
+    pub payload: bytes::Bytes,
+    pub partition: Option<i32>,
+    pub timestamp: Option<i64>,
+    pub headers: Vec<(String, Vec<u8>)>,
+}
+
+/// The delivery of an enqueued record
+pub struct KafkaDelivery(DeliveryFuture);
+
+impl KafkaDelivery {
+    /// wait for the delivery report. It returns the partition and the offset of the record if it's delivered
+    pub async fn wait(self) -> Result<(i32, i64), KafkaException> {
+        match self.0.await {
+            Ok(Ok(delivered)) => Ok(delivered),
+            Ok(Err((err, _))) => Err(KafkaException { err }),
+            Err(_) => Err(KafkaException {
+                err: KafkaError::Canceled,
+            }),
+        }
+    }
+}
+
+/// murmur2 hash of Kafka, which is used by the default partitioner of the Java client
+pub fn murmur2(data: &[u8]) -> i32 {
+    const SEED: u32 = 0x9747b28c;
+    const M: u32 = 0x5bd1e995;
+    const R: u32 = 24;
+
+    let mut h = SEED ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        rest.iter()
+            .enumerate()
+            .for_each(|(i, byte)| h ^= (*byte as u32) << (8 * i));
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h as i32
+}
+
+/// the partition of a key picked by the default partitioner of the Java client
+pub fn key_hash_partition(key: &[u8], partitions: i32) -> i32 {
+    (murmur2(key) & 0x7fffffff) % partitions
+}
+
 pub struct KafkaConsumer {
     consumer: StreamConsumer,
 }
@@ -102,6 +264,43 @@ pub struct KafkaMessage {
     pub key: bytes::Bytes,
     pub payload: bytes::Bytes,
     pub timestamp: Option<i64>,
+    /// the partition which the message is fetched from. It's absent if the message is going to be sent
+    pub partition: Option<i32>,
+    /// the offset of the fetched message in its partition. It's absent if the message is going to be sent
+    pub offset: Option<i64>,
+    /// headers of the fetched message. The headers whose values are not UTF-8 are skipped
+    pub headers: Vec<(String, String)>,
+}
+
+impl KafkaMessage {
+    fn from_fetched(msg: &OwnedMessage, payload: &[u8]) -> Self {
+        let key = msg
+            .key()
+            .map(|key| bytes::Bytes::copy_from_slice(key))
+            .unwrap_or_default();
+        let headers = msg
+            .headers()
+            .map(|headers| {
+                (0..headers.count())
+                    .map(|idx| headers.get(idx))
+                    .filter_map(|header| {
+                        header
+                            .value
+                            .and_then(|value| std::str::from_utf8(value).ok())
+                            .map(|value| (header.key.to_string(), value.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        KafkaMessage {
+            key,
+            payload: bytes::Bytes::copy_from_slice(payload),
+            timestamp: msg.timestamp().to_millis(),
+            partition: Some(msg.partition()),
+            offset: Some(msg.offset()),
+            headers,
+        }
+    }
 }
 
 impl KafkaConsumer {
@@ -117,17 +316,8 @@ impl KafkaConsumer {
             .and_then(|msg| match msg {
                 Ok(msg) => {
                     let msg = msg.detach();
-                    msg.payload().map(|payload| {
-                        let key = msg
-                            .key()
-                            .map(|key| bytes::Bytes::copy_from_slice(key))
-                            .unwrap_or_default();
-                        processor(KafkaMessage {
-                            key,
-                            payload: bytes::Bytes::copy_from_slice(payload),
-                            timestamp: msg.timestamp().to_millis(),
-                        })
-                    })
+                    msg.payload()
+                        .map(|payload| processor(KafkaMessage::from_fetched(&msg, payload)))
                 }
                 Err(err) => {
                     tracing::error!("fail to fetch data from kafka: {}", err);
@@ -136,32 +326,68 @@ impl KafkaConsumer {
             })
     }
 
-    pub fn blocking_fetch<M, F: Fn(KafkaMessage) -> M>(&self, processor: F) -> Option<M> {
-        futures_executor::block_on_stream(self.consumer.stream())
-            .next()
-            .and_then(|result| match result {
-                Ok(msg) => {
-                    let msg = msg.detach();
-                    msg.payload().map(|payload| {
-                        let key = msg
-                            .key()
-                            .map(|key| bytes::Bytes::copy_from_slice(key))
-                            .unwrap_or_default();
-                        processor(KafkaMessage {
-                            key,
-                            payload: bytes::Bytes::copy_from_slice(payload),
-                            timestamp: msg.timestamp().to_millis(),
-                        })
-                    })
-                }
-                Err(err) => {
-                    tracing::error!("fail to fetch data from kafka: {}", err);
-                    None
-                }
-            })
+    /// block until a message is fetched. Nothing is fetched if the cancellation token fires before a message arrives
+    pub fn blocking_fetch<M, F: Fn(KafkaMessage) -> M>(
+        &self,
+        cancellation: &CancellationToken,
+        processor: F,
+    ) -> Option<M> {
+        if cancellation.is_cancelled() {
+            return None;
+        }
+        let mut stream = self.consumer.stream();
+        let cancelled = cancellation.cancelled();
+        futures_util::pin_mut!(cancelled);
+        let fetched = match futures_executor::block_on(future::select(stream.next(), cancelled)) {
+            future::Either::Left((fetched, _)) => fetched,
+            future::Either::Right(_) => None,
+        };
+        fetched.and_then(|result| match result {
+            Ok(msg) => {
+                let msg = msg.detach();
+                msg.payload()
+                    .map(|payload| processor(KafkaMessage::from_fetched(&msg, payload)))
+            }
+            Err(err) => {
+                tracing::error!("fail to fetch data from kafka: {}", err);
+                None
+            }
+        })
     }
 
     pub fn unsubscribe(&self) {
         self.consumer.unsubscribe();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{key_hash_partition, murmur2};
+
+    #[test]
+    fn test_murmur2() {
+        // the same cases as the tests of the Java client
+        for (data, hash) in [
+            ("21".as_bytes(), -973932308),
+            ("foobar".as_bytes(), -790332482),
+            ("a-little-bit-long-string".as_bytes(), -985981536),
+            ("a-little-bit-longer-string".as_bytes(), -1486304829),
+            (
+                "lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8".as_bytes(),
+                -58897971,
+            ),
+            (&[b'a', b'b', b'c'], 479470107),
+        ] {
+            assert_eq!(murmur2(data), hash);
+        }
+    }
+
+    #[test]
+    fn test_key_hash_partition() {
+        for partitions in 1..10 {
+            let partition = key_hash_partition(b"foobar", partitions);
+            assert!((0..partitions).contains(&partition));
+            assert_eq!(partition, (-790332482i32 & 0x7fffffff) % partitions);
+        }
+    }
+}
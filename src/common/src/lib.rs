@@ -1,12 +1,27 @@
+pub mod backoff;
+pub mod backpressure;
 pub mod collections;
 #[cfg(not(tarpaulin_include))]
 pub mod consts;
 pub mod db;
 pub mod err;
 pub mod event;
+pub mod formats;
+pub mod ingest;
 pub mod kafka;
+pub mod logging;
+pub mod lookup;
+pub mod metrics;
 pub mod net;
+pub mod ordering;
+pub mod project;
 pub mod redis;
+pub mod replay;
+pub mod schema;
+pub mod snapshot;
+pub mod sql_expr;
+pub mod tap;
+pub mod throttle;
 pub mod types;
 pub mod utils;
 pub mod testutils;
@@ -0,0 +1,338 @@
+use std::{
+    fmt,
+    sync::{Once, RwLock},
+};
+
+use proto::common::ResourceId;
+use tracing::{
+    field::{Field, Visit},
+    level_filters::LevelFilter,
+    span,
+    subscriber::Interest,
+    Metadata, Span, Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, Filter, SubscriberExt},
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    Layer,
+};
+
+use crate::types::ExecutorId;
+
+/// name of the span which the operators of a dataflow run in
+pub const DATAFLOW_SPAN: &str = "dataflow";
+/// field of [`DATAFLOW_SPAN`] which overrides the log level within the span
+const LOG_LEVEL_FIELD: &str = "log_level";
+
+/// the most verbose level overridden by the dataflow spans created so far
+static MAX_OVERRIDDEN_LEVEL: RwLock<LevelFilter> = RwLock::new(LevelFilter::OFF);
+
+/// create the span of an operator of a dataflow. If `log_level` is set, the logs within the span are filtered by it
+/// instead of the level of the worker
+pub fn dataflow_span(
+    job_id: &ResourceId,
+    operator_id: ExecutorId,
+    log_level: Option<LevelFilter>,
+) -> Span {
+    match log_level {
+        Some(log_level) => {
+            raise_max_overridden_level(log_level);
+            tracing::error_span!(
+                DATAFLOW_SPAN,
+                namespace = %job_id.namespace_id,
+                job = %job_id.resource_id,
+                operator_id,
+                log_level = %log_level
+            )
+        }
+        None => tracing::error_span!(
+            DATAFLOW_SPAN,
+            namespace = %job_id.namespace_id,
+            job = %job_id.resource_id,
+            operator_id
+        ),
+    }
+}
+
+fn get_max_overridden_level() -> LevelFilter {
+    *MAX_OVERRIDDEN_LEVEL
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+fn raise_max_overridden_level(level: LevelFilter) {
+    let raised = {
+        let mut max_level = MAX_OVERRIDDEN_LEVEL
+            .write()
+            .unwrap_or_else(|err| err.into_inner());
+        if level > *max_level {
+            *max_level = level;
+            true
+        } else {
+            false
+        }
+    };
+    // the callsites which were disabled by the level of the worker have to be enabled up to the new level.
+    // It must not be done while a span is being created, so it's done before the span is created
+    if raised {
+        tracing::callsite::rebuild_interest_cache();
+    }
+}
+
+/// install the global subscriber. Logs are printed at the level of `RUST_LOG`, which is INFO by default,
+/// or at the level overridden by the dataflow they belong to.
+///
+/// It's safe to call it more than once, e.g. by the entrypoints which are reused by the tests: the subscriber is installed by the first call only,
+/// and it's not installed at all if another global subscriber is installed already
+pub fn init() {
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        let level = std::env::var("RUST_LOG")
+            .ok()
+            .and_then(|level| level.parse::<LevelFilter>().ok())
+            .unwrap_or(LevelFilter::INFO);
+        let _ = tracing_subscriber::registry()
+            .with(tracing_subscriber::fmt::layer().with_filter(DataflowLevelFilter::new(level)))
+            .try_init();
+    })
+}
+
+/// the overridden log level of a dataflow span, it's kept in the extensions of the span
+struct LogLevelOverride(LevelFilter);
+
+#[derive(Default)]
+struct LogLevelVisitor(Option<LevelFilter>);
+
+impl Visit for LogLevelVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == LOG_LEVEL_FIELD {
+            self.0 = value.parse().ok();
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == LOG_LEVEL_FIELD {
+            self.0 = format!("{:?}", value).parse().ok();
+        }
+    }
+}
+
+fn is_dataflow_span(metadata: &Metadata<'_>) -> bool {
+    metadata.is_span() && metadata.name() == DATAFLOW_SPAN && metadata.target() == module_path!()
+}
+
+/// [`DataflowLevelFilter`] filters spans and events by the log level of the innermost dataflow span they are in,
+/// so one dataflow can be debugged without changing the level of the others.
+/// Spans and events outside of any dataflow span are filtered by the default level.
+pub struct DataflowLevelFilter {
+    default_level: LevelFilter,
+}
+
+impl DataflowLevelFilter {
+    pub fn new(default_level: LevelFilter) -> Self {
+        Self { default_level }
+    }
+
+    /// callsites which are more verbose than both the default level and all the overrides are never enabled
+    fn get_max_level(&self) -> LevelFilter {
+        self.default_level.max(get_max_overridden_level())
+    }
+
+    fn get_level<S>(&self, cx: &Context<'_, S>) -> LevelFilter
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        cx.lookup_current()
+            .and_then(|span| {
+                span.scope().find_map(|span| {
+                    span.extensions()
+                        .get::<LogLevelOverride>()
+                        .map(|level| level.0)
+                })
+            })
+            .unwrap_or(self.default_level)
+    }
+}
+
+impl<S> Filter<S> for DataflowLevelFilter
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn enabled(&self, metadata: &Metadata<'_>, cx: &Context<'_, S>) -> bool {
+        is_dataflow_span(metadata) || metadata.level() <= &self.get_level(cx)
+    }
+
+    /// whether a callsite is enabled depends on the span it's in, so it's checked every time
+    fn callsite_enabled(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if is_dataflow_span(metadata) {
+            Interest::always()
+        } else if metadata.level() > &self.get_max_level() {
+            Interest::never()
+        } else {
+            Interest::sometimes()
+        }
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.get_max_level())
+    }
+
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if !is_dataflow_span(attrs.metadata()) {
+            return;
+        }
+        let mut visitor = LogLevelVisitor::default();
+        attrs.record(&mut visitor);
+        let level = match visitor.0 {
+            Some(level) => level,
+            None => return,
+        };
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(LogLevelOverride(level));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use proto::common::ResourceId;
+    use tracing::{level_filters::LevelFilter, Instrument};
+    use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, Layer};
+
+    use super::{dataflow_span, init, DataflowLevelFilter};
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Output {
+        type Writer = Output;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    impl Output {
+        fn get_lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| line.to_string())
+                .collect()
+        }
+    }
+
+    fn job(resource_id: &str) -> ResourceId {
+        ResourceId::new("default", resource_id)
+    }
+
+    #[test]
+    fn test_dataflow_log_level_override() {
+        let output = Output::default();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(output.clone())
+                .with_ansi(false)
+                .with_filter(DataflowLevelFilter::new(LevelFilter::INFO)),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let noisy = dataflow_span(&job("noisy"), 1, Some(LevelFilter::DEBUG));
+            let other = dataflow_span(&job("other"), 1, None);
+            let quiet = dataflow_span(&job("quiet"), 1, Some(LevelFilter::WARN));
+
+            noisy.in_scope(|| {
+                tracing::debug!("debug of noisy");
+                tracing::trace!("trace of noisy");
+                // the override is inherited by the nested spans
+                tracing::debug_span!("operator").in_scope(|| tracing::debug!("nested of noisy"));
+            });
+            other.in_scope(|| {
+                tracing::debug!("debug of other");
+                tracing::info!("info of other");
+            });
+            quiet.in_scope(|| {
+                tracing::info!("info of quiet");
+                tracing::warn!("warn of quiet");
+            });
+            tracing::debug!("debug outside");
+            tracing::info!("info outside");
+
+            // executors are spawned in the span of their operators
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                tokio::spawn(
+                    async { tracing::debug!("debug of noisy executor") }.instrument(dataflow_span(
+                        &job("noisy"),
+                        2,
+                        Some(LevelFilter::DEBUG),
+                    )),
+                )
+                .await
+                .unwrap();
+                tokio::spawn(
+                    async { tracing::debug!("debug of other executor") }.instrument(dataflow_span(
+                        &job("other"),
+                        2,
+                        None,
+                    )),
+                )
+                .await
+                .unwrap();
+            });
+        });
+
+        let lines = output.get_lines();
+        let contains = |message: &str| lines.iter().any(|line| line.ends_with(message));
+        for message in [
+            "debug of noisy",
+            "nested of noisy",
+            "info of other",
+            "warn of quiet",
+            "info outside",
+            "debug of noisy executor",
+        ] {
+            assert!(contains(message), "{message} is missing in {lines:?}");
+        }
+        for message in [
+            "trace of noisy",
+            "debug of other",
+            "info of quiet",
+            "debug outside",
+            "debug of other executor",
+        ] {
+            assert!(!contains(message), "{message} is printed in {lines:?}");
+        }
+        // logs of a dataflow are printed with its job id and operator id
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("job=noisy") && line.contains("operator_id=1")));
+    }
+
+    #[test]
+    fn test_init_twice() {
+        init();
+        init();
+        tracing::info!("logged after initializing twice");
+    }
+}
@@ -0,0 +1,670 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::{self, Display},
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use proto::{
+    common::{async_lookup, AsyncLookup, Entry, RedisDesc},
+    common_impl::DataflowValidateError,
+    json_path::JsonPath,
+};
+use redis::aio::MultiplexedConnection;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::{project::select_path, redis::to_connection_info, types::TypedValue};
+
+/// metric of the lookups answered by the cache
+pub const LOOKUP_CACHE_HITS_METRIC: &str = "async_lookup.cache.hits";
+/// metric of the lookups which are sent to the backend because their keys are not cached
+pub const LOOKUP_CACHE_MISSES_METRIC: &str = "async_lookup.cache.misses";
+/// number of the lookups in flight, including the ones waiting for retry. Unlike other metrics, it's a gauge
+pub const LOOKUP_IN_FLIGHT_METRIC: &str = "async_lookup.in_flight";
+
+const DEFAULT_CONCURRENCY: usize = 16;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+const KEY_PLACEHOLDER: &str = "{key}";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LookupError {
+    /// the configuration of the AsyncLookup operator is invalid
+    InvalidLookup(String),
+    /// the payload isn't an object, so the result can't be set to it
+    InvalidPayload(String),
+    Timeout(Duration),
+    /// the request failed or the response is neither successful nor not found
+    HttpFailed(String),
+    RedisFailed(String),
+}
+
+impl LookupError {
+    /// invalid configurations and payloads fail again, so only the failures of the backend are retryable
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, Self::InvalidLookup(_) | Self::InvalidPayload(_))
+    }
+}
+
+impl Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLookup(msg) => write!(f, "invalid async lookup operator: {}", msg),
+            Self::InvalidPayload(msg) => write!(f, "invalid payload: {}", msg),
+            Self::Timeout(timeout) => write!(f, "lookup timed out after {:?}", timeout),
+            Self::HttpFailed(msg) => write!(f, "http lookup failed: {}", msg),
+            Self::RedisFailed(msg) => write!(f, "redis lookup failed: {}", msg),
+        }
+    }
+}
+
+impl From<DataflowValidateError> for LookupError {
+    fn from(err: DataflowValidateError) -> Self {
+        match err {
+            DataflowValidateError::InvalidAsyncLookup(msg) => Self::InvalidLookup(msg),
+            _ => Self::InvalidLookup(format!("{:?}", err)),
+        }
+    }
+}
+
+pub type LookupFuture = Pin<Box<dyn Future<Output = Result<TypedValue, LookupError>> + Send>>;
+
+/// the external system which keys are looked up in. It's cheap to clone,
+/// so each lookup owns a clone and can be in flight without borrowing the operator
+#[derive(Clone)]
+pub enum LookupBackend {
+    Http {
+        client: reqwest::Client,
+        url_template: String,
+        headers: HeaderMap,
+    },
+    Redis {
+        client: redis::Client,
+        // the connection is shared by the lookups and it's reconnected by the next lookup once it's broken
+        connection: Arc<tokio::sync::Mutex<Option<MultiplexedConnection>>>,
+        key_template: String,
+        hash_field: Option<String>,
+    },
+}
+
+impl LookupBackend {
+    pub fn new(backend: Option<&async_lookup::Backend>) -> Result<Self, LookupError> {
+        match backend {
+            Some(async_lookup::Backend::Http(http)) => {
+                let mut headers = HeaderMap::new();
+                for (name, value) in &http.headers {
+                    let name = HeaderName::from_bytes(name.as_bytes()).map_err(|err| {
+                        LookupError::InvalidLookup(format!("invalid header [{}]: {}", name, err))
+                    })?;
+                    let value = HeaderValue::from_str(value).map_err(|err| {
+                        LookupError::InvalidLookup(format!("invalid header [{}]: {}", name, err))
+                    })?;
+                    headers.insert(name, value);
+                }
+                Ok(Self::Http {
+                    client: reqwest::Client::new(),
+                    url_template: http.url_template.clone(),
+                    headers,
+                })
+            }
+            Some(async_lookup::Backend::Redis(redis)) => {
+                let desc = RedisDesc {
+                    connection_opts: redis.connection_opts.clone(),
+                    ..Default::default()
+                };
+                let client = redis::Client::open(to_connection_info(&desc))
+                    .map_err(|err| LookupError::InvalidLookup(format!("{}", err)))?;
+                Ok(Self::Redis {
+                    client,
+                    connection: Default::default(),
+                    key_template: redis.key_template.clone(),
+                    hash_field: Some(redis.hash_field.clone()).filter(|field| !field.is_empty()),
+                })
+            }
+            None => Err(LookupError::InvalidLookup(
+                "lookup backend is missing".to_string(),
+            )),
+        }
+    }
+
+    /// look up the key. The result is null if nothing is found
+    pub fn lookup(&self, key: &str) -> LookupFuture {
+        match self {
+            Self::Http {
+                client,
+                url_template,
+                headers,
+            } => {
+                let request = client
+                    .get(url_template.replace(KEY_PLACEHOLDER, &encode_url_component(key)))
+                    .headers(headers.clone());
+                Box::pin(async move {
+                    let response = request
+                        .send()
+                        .await
+                        .map_err(|err| LookupError::HttpFailed(format!("{}", err)))?;
+                    let status = response.status();
+                    if status == reqwest::StatusCode::NOT_FOUND {
+                        return Ok(TypedValue::Null);
+                    }
+                    if !status.is_success() {
+                        return Err(LookupError::HttpFailed(format!("status {}", status)));
+                    }
+                    response
+                        .bytes()
+                        .await
+                        .map(|body| decode_result(&body))
+                        .map_err(|err| LookupError::HttpFailed(format!("{}", err)))
+                })
+            }
+            Self::Redis {
+                client,
+                connection,
+                key_template,
+                hash_field,
+            } => {
+                let client = client.clone();
+                let connection = connection.clone();
+                let key = if key_template.is_empty() {
+                    key.to_string()
+                } else {
+                    key_template.replace(KEY_PLACEHOLDER, key)
+                };
+                let command = match hash_field {
+                    Some(field) => {
+                        let mut command = redis::cmd("HGET");
+                        command.arg(key).arg(field);
+                        command
+                    }
+                    None => {
+                        let mut command = redis::cmd("GET");
+                        command.arg(key);
+                        command
+                    }
+                };
+                Box::pin(async move {
+                    let mut conn = {
+                        let mut guard = connection.lock().await;
+                        match guard.as_ref() {
+                            Some(conn) => conn.clone(),
+                            None => {
+                                let conn = client
+                                    .get_multiplexed_tokio_connection()
+                                    .await
+                                    .map_err(|err| LookupError::RedisFailed(format!("{}", err)))?;
+                                *guard = Some(conn.clone());
+                                conn
+                            }
+                        }
+                    };
+                    let result: redis::RedisResult<Option<Vec<u8>>> =
+                        command.query_async(&mut conn).await;
+                    match result {
+                        Ok(value) => {
+                            Ok(value.map_or(TypedValue::Null, |value| decode_result(&value)))
+                        }
+                        Err(err) => {
+                            if err.is_io_error() || err.is_connection_dropped() {
+                                *connection.lock().await = None;
+                            }
+                            Err(LookupError::RedisFailed(format!("{}", err)))
+                        }
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// the result is decoded as JSON, or kept as a string if it's not JSON
+fn decode_result(value: &[u8]) -> TypedValue {
+    match serde_json::from_slice(value) {
+        Ok(value) => TypedValue::from_json_value(value),
+        Err(_) => TypedValue::String(String::from_utf8_lossy(value).to_string()),
+    }
+}
+
+/// percent-encode everything except the unreserved characters of RFC 3986
+fn encode_url_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+struct CachedResult {
+    value: TypedValue,
+    cached_at: Instant,
+    // the tick of the last access, it's the key of the recency index
+    tick: u64,
+}
+
+/// [`LookupCache`] is an LRU cache of the lookup results with an optional TTL.
+/// The least recently used result is evicted once the capacity is reached, and expired results are removed when they are read
+pub struct LookupCache {
+    capacity: usize,
+    ttl: Option<Duration>,
+    entries: HashMap<String, CachedResult>,
+    // keys ordered by their last access, the first one is the least recently used
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl LookupCache {
+    pub fn new(capacity: usize, ttl: Option<Duration>) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            ttl,
+            entries: Default::default(),
+            recency: Default::default(),
+            tick: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &str, now: Instant) -> Option<TypedValue> {
+        let cached = self.entries.get_mut(key)?;
+        if matches!(self.ttl, Some(ttl) if now.saturating_duration_since(cached.cached_at) >= ttl) {
+            self.recency.remove(&cached.tick);
+            self.entries.remove(key);
+            return None;
+        }
+        self.tick += 1;
+        self.recency.remove(&cached.tick);
+        self.recency.insert(self.tick, key.to_string());
+        cached.tick = self.tick;
+        Some(cached.value.clone())
+    }
+
+    pub fn put(&mut self, key: String, value: TypedValue, now: Instant) {
+        self.tick += 1;
+        if let Some(cached) = self.entries.get(&key) {
+            self.recency.remove(&cached.tick);
+        } else if self.entries.len() >= self.capacity {
+            if let Some((_, evicted)) = self.recency.pop_first() {
+                self.entries.remove(&evicted);
+            }
+        }
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(
+            key,
+            CachedResult {
+                value,
+                cached_at: now,
+                tick: self.tick,
+            },
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// [`AsyncLookupRuntime`] is the runtime of the AsyncLookup operator. It's created once per task,
+/// and the lookups it creates are driven by the executor
+pub struct AsyncLookupRuntime {
+    backend: LookupBackend,
+    key_path: JsonPath,
+    target_field: String,
+    concurrency: usize,
+    timeout: Duration,
+    cache: Option<LookupCache>,
+    ordered: bool,
+}
+
+impl AsyncLookupRuntime {
+    pub fn new(async_lookup: &AsyncLookup) -> Result<Self, LookupError> {
+        let ordered = async_lookup.get_emission()? == async_lookup::Emission::Ordered;
+        let to_std = |time: &proto::common::Time| time.to_duration().to_std().unwrap_or_default();
+        Ok(Self {
+            backend: LookupBackend::new(async_lookup.backend.as_ref())?,
+            key_path: async_lookup.get_key_path()?,
+            target_field: async_lookup.target_field.clone(),
+            concurrency: match async_lookup.concurrency {
+                0 => DEFAULT_CONCURRENCY,
+                concurrency => concurrency as usize,
+            },
+            timeout: async_lookup
+                .timeout
+                .as_ref()
+                .map(to_std)
+                .unwrap_or(DEFAULT_TIMEOUT),
+            cache: async_lookup.cache.as_ref().map(|cache| {
+                LookupCache::new(cache.capacity as usize, cache.ttl.as_ref().map(to_std))
+            }),
+            ordered,
+        })
+    }
+
+    pub fn get_concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn is_ordered(&self) -> bool {
+        self.ordered
+    }
+
+    pub fn has_cache(&self) -> bool {
+        self.cache.is_some()
+    }
+
+    /// the lookup key of the payload. It's `None` if the key is missing or null, and then the result is null without lookup
+    pub fn get_lookup_key(&self, entry: &Entry) -> Result<Option<String>, LookupError> {
+        let payload = TypedValue::from(entry);
+        if !matches!(payload, TypedValue::Object(_)) {
+            return Err(LookupError::InvalidPayload(format!(
+                "payload {} is not an object",
+                payload.to_string()
+            )));
+        }
+        Ok(select_path(&payload, &self.key_path)
+            .filter(|key| !matches!(key, TypedValue::Null | TypedValue::Invalid))
+            .map(|key| key.to_string()))
+    }
+
+    /// the cached result of the key. It's always `None` if the cache is not enabled
+    pub fn get_cached(&mut self, key: &str, now: Instant) -> Option<TypedValue> {
+        self.cache.as_mut().and_then(|cache| cache.get(key, now))
+    }
+
+    pub fn cache(&mut self, key: String, value: &TypedValue, now: Instant) {
+        if let Some(cache) = self.cache.as_mut() {
+            cache.put(key, value.clone(), now)
+        }
+    }
+
+    /// look up the key within the timeout
+    pub fn lookup(&self, key: &str) -> LookupFuture {
+        let lookup = self.backend.lookup(key);
+        let timeout = self.timeout;
+        Box::pin(async move {
+            tokio::time::timeout(timeout, lookup)
+                .await
+                .unwrap_or(Err(LookupError::Timeout(timeout)))
+        })
+    }
+
+    /// set the result to the target field of the payload
+    pub fn enrich(&self, entry: &Entry, result: TypedValue) -> Entry {
+        match TypedValue::from(entry) {
+            TypedValue::Object(mut object) => {
+                object.insert(self.target_field.clone(), result);
+                let value = TypedValue::Object(object);
+                let mut new_entry = Entry::default();
+                new_entry.set_data_type(value.get_type());
+                new_entry.value = value.get_data_bytes();
+                new_entry
+            }
+            _ => entry.clone(),
+        }
+    }
+}
+
+struct Slot<T> {
+    key: Vec<u8>,
+    item: T,
+    remaining: usize,
+}
+
+/// [`EmissionBuffer`] keeps the items whose lookups are not completed and decides when they are emitted.
+///
+/// In ordered mode, an item is emitted once its lookups and the ones of all the earlier items of the same key are completed.
+/// In unordered mode, it's emitted as soon as its own lookups are completed
+pub struct EmissionBuffer<T> {
+    ordered: bool,
+    next_seq: u64,
+    slots: BTreeMap<u64, Slot<T>>,
+    // sequences of the buffered items of each key in the input order, only used in ordered mode
+    queues: HashMap<Vec<u8>, VecDeque<u64>>,
+}
+
+impl<T> EmissionBuffer<T> {
+    pub fn new(ordered: bool) -> Self {
+        Self {
+            ordered,
+            next_seq: 0,
+            slots: Default::default(),
+            queues: Default::default(),
+        }
+    }
+
+    /// buffer an item which waits for `lookups` lookups. It returns the sequence of the item and the items which can be emitted now
+    pub fn push(&mut self, key: Vec<u8>, item: T, lookups: usize) -> (u64, Vec<T>) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if self.ordered {
+            self.queues.entry(key.clone()).or_default().push_back(seq);
+        }
+        self.slots.insert(
+            seq,
+            Slot {
+                key,
+                item,
+                remaining: lookups,
+            },
+        );
+        (seq, self.release(seq))
+    }
+
+    pub fn get_mut(&mut self, seq: u64) -> Option<&mut T> {
+        self.slots.get_mut(&seq).map(|slot| &mut slot.item)
+    }
+
+    /// complete one lookup of the item, and return the items which can be emitted now
+    pub fn complete(&mut self, seq: u64) -> Vec<T> {
+        match self.slots.get_mut(&seq) {
+            Some(slot) => slot.remaining = slot.remaining.saturating_sub(1),
+            None => return vec![],
+        }
+        self.release(seq)
+    }
+
+    fn release(&mut self, seq: u64) -> Vec<T> {
+        let key = match self.slots.get(&seq) {
+            Some(slot) if !self.ordered && slot.remaining == 0 => {
+                return self
+                    .slots
+                    .remove(&seq)
+                    .into_iter()
+                    .map(|slot| slot.item)
+                    .collect()
+            }
+            Some(slot) if self.ordered => slot.key.clone(),
+            _ => return vec![],
+        };
+
+        let mut released = vec![];
+        if let Some(queue) = self.queues.get_mut(&key) {
+            while let Some(seq) = queue.front() {
+                match self.slots.get(seq) {
+                    Some(slot) if slot.remaining > 0 => break,
+                    _ => {}
+                }
+                released.extend(self.slots.remove(seq).map(|slot| slot.item));
+                queue.pop_front();
+            }
+            if queue.is_empty() {
+                self.queues.remove(&key);
+            }
+        }
+        released
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// take all the buffered items in the input order
+    pub fn take_all(&mut self) -> Vec<T> {
+        self.queues.clear();
+        std::mem::take(&mut self.slots)
+            .into_values()
+            .map(|slot| slot.item)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use proto::common::{async_lookup, AsyncLookup, Entry};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use crate::types::TypedValue;
+
+    use super::{AsyncLookupRuntime, EmissionBuffer, LookupCache, LookupError};
+
+    fn new_entry(value: serde_json::Value) -> Entry {
+        let value = TypedValue::from_json_value(value);
+        let mut entry = Entry::default();
+        entry.set_data_type(value.get_type());
+        entry.value = value.get_data_bytes();
+        entry
+    }
+
+    #[test]
+    fn test_lookup_cache() {
+        let now = Instant::now();
+        let mut cache = LookupCache::new(2, Some(Duration::from_secs(10)));
+        cache.put("a".to_string(), TypedValue::BigInt(1), now);
+        cache.put("b".to_string(), TypedValue::BigInt(2), now);
+        // a is used more recently than b, so b is evicted
+        assert_eq!(cache.get("a", now), Some(TypedValue::BigInt(1)));
+        cache.put("c".to_string(), TypedValue::BigInt(3), now);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b", now), None);
+        assert_eq!(cache.get("c", now), Some(TypedValue::BigInt(3)));
+
+        // expired results are removed when they are read
+        let later = now + Duration::from_secs(10);
+        assert_eq!(cache.get("a", later), None);
+        assert_eq!(cache.len(), 1);
+        cache.put("a".to_string(), TypedValue::BigInt(4), later);
+        assert_eq!(cache.get("a", later), Some(TypedValue::BigInt(4)));
+    }
+
+    #[test]
+    fn test_emission_buffer() {
+        let key = |key: &str| key.as_bytes().to_vec();
+
+        let mut ordered = EmissionBuffer::new(true);
+        let (first, released) = ordered.push(key("a"), 1, 1);
+        assert!(released.is_empty());
+        let (second, released) = ordered.push(key("a"), 2, 1);
+        assert!(released.is_empty());
+        // other keys are not held back
+        let (_, released) = ordered.push(key("b"), 3, 0);
+        assert_eq!(released, vec![3]);
+        assert!(ordered.complete(second).is_empty());
+        assert_eq!(ordered.complete(first), vec![1, 2]);
+        assert!(ordered.is_empty());
+
+        let mut unordered = EmissionBuffer::new(false);
+        let (first, _) = unordered.push(key("a"), 1, 2);
+        let (second, _) = unordered.push(key("a"), 2, 1);
+        assert_eq!(unordered.complete(second), vec![2]);
+        assert!(unordered.complete(first).is_empty());
+        assert_eq!(unordered.complete(first), vec![1]);
+
+        // buffered items are taken in the input order
+        let mut buffer = EmissionBuffer::new(true);
+        buffer.push(key("b"), 1, 1);
+        buffer.push(key("a"), 2, 1);
+        assert_eq!(buffer.take_all(), vec![1, 2]);
+        assert!(buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_http_lookup() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(_) => return,
+                };
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    let n = stream.read(&mut buf).await.unwrap_or_default();
+                    let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                    let (status, body) = if request.starts_with("GET /users/u%201 ") {
+                        ("200 OK", r#"{"name":"alice"}"#)
+                    } else if request.starts_with("GET /users/slow ") {
+                        tokio::time::sleep(Duration::from_millis(500)).await;
+                        ("200 OK", "slow")
+                    } else if request.starts_with("GET /users/broken ") {
+                        ("500 Internal Server Error", "")
+                    } else {
+                        ("404 Not Found", "")
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        let runtime = AsyncLookupRuntime::new(&AsyncLookup {
+            backend: Some(async_lookup::Backend::Http(async_lookup::HttpLookup {
+                url_template: format!("http://{}/users/{{key}}", addr),
+                headers: Default::default(),
+            })),
+            key_path: "$.user_id".to_string(),
+            target_field: "user".to_string(),
+            timeout: Some(proto::common::Time {
+                millis: 200,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let entry = new_entry(serde_json::json!({"user_id": "u 1"}));
+        let key = runtime.get_lookup_key(&entry).unwrap().unwrap();
+        let result = runtime.lookup(&key).await.unwrap();
+        assert_eq!(
+            TypedValue::from(&runtime.enrich(&entry, result)).to_json_value(),
+            serde_json::json!({"user_id": "u 1", "user": {"name": "alice"}})
+        );
+
+        assert_eq!(runtime.lookup("missing").await, Ok(TypedValue::Null));
+        assert!(matches!(
+            runtime.lookup("broken").await,
+            Err(LookupError::HttpFailed(_))
+        ));
+        assert!(matches!(
+            runtime.lookup("slow").await,
+            Err(LookupError::Timeout(_))
+        ));
+
+        // payloads without the key are not looked up, and payloads which are not objects are invalid
+        assert_eq!(
+            runtime.get_lookup_key(&new_entry(serde_json::json!({"id": 1}))),
+            Ok(None)
+        );
+        let err = runtime
+            .get_lookup_key(&new_entry(serde_json::json!("u1")))
+            .unwrap_err();
+        assert!(!err.is_retryable());
+    }
+}
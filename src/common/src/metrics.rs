@@ -0,0 +1,12 @@
+//! Counters reported by the operators in the states of their executors, which the Coordinator aggregates into the metrics summary of a dataflow
+
+/// metric of the events received by an operator
+pub const OPERATOR_EVENTS_IN_METRIC: &str = "operator.events.in";
+/// metric of the events produced by an operator
+pub const OPERATOR_EVENTS_OUT_METRIC: &str = "operator.events.out";
+/// metric of the failed events which fail the operator after its error policy is applied
+pub const ERROR_POLICY_FAILED_METRIC: &str = "error_policy_failed";
+/// metric of the failed events which are skipped by the error policy of the operator
+pub const ERROR_POLICY_SKIPPED_METRIC: &str = "error_policy_skipped";
+/// metric of the failed events which are sent to the dead-letter operator by the error policy of the operator
+pub const ERROR_POLICY_DEAD_LETTERED_METRIC: &str = "error_policy_dead_lettered";
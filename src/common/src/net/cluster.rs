@@ -4,22 +4,27 @@ use crate::types::SingleKV;
 
 use proto::common::DataflowMeta;
 use proto::common::{Dataflow, HostAddr};
+use proto::coordinator::{ClusterTopology, NodeHealth, NodeTopology};
 
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::vec;
 
 use super::gateway::taskmanager::SafeTaskManagerRpcGateway;
 use super::DEFAULT_TASKMANAGER_PORT;
 
-#[derive(Clone, Eq, PartialEq, Debug, Copy)]
+#[derive(Clone, Eq, PartialEq, Debug, Copy, Default)]
 pub enum NodeStatus {
     /// initializated status of node
+    #[default]
     Pending,
     /// status if node is running
     Running,
+    /// status if node misses heartbeats but it's not unreachable yet. It's probed more frequently
+    Suspect,
     /// status if node is unreached
     Unreachable,
 }
@@ -28,38 +33,143 @@ pub enum NodeStatus {
 /// Node will record all status of remote worker such as CPU, memory, I/O and liveness
 #[derive(Clone, Debug)]
 pub struct Node {
-    /// The status of node
-    status: NodeStatus,
     /// The address of node
     pub host_addr: HostAddr,
     // gateway of task manager
     gateway: SafeTaskManagerRpcGateway,
     /// node's id. It's always aligned with the list of [NodeBuilder]
     node_id: u32,
+    /// backpressure sampled from the operators on the node. It's shared by the clones of the node
+    backpressure: Arc<Mutex<NodeBackpressure>>,
+    /// the status of node and its missed heartbeats. It's shared by the clones of the node
+    liveness: Arc<Mutex<NodeLiveness>>,
+}
+
+#[derive(Debug, Default)]
+struct NodeBackpressure {
+    /// per mille of the time the most blocked operator is blocked in the latest sample
+    ratio: u64,
+    /// since when the samples are over the threshold
+    since: Option<Instant>,
+    /// whether the samples have been over the threshold for the sustained period
+    sustained: bool,
+}
+
+#[derive(Debug, Default)]
+struct NodeLiveness {
+    status: NodeStatus,
+    /// the missed heartbeats which are not forgiven yet
+    misses: u32,
+    /// since when the misses are decayed
+    decayed_at: Option<Instant>,
+    /// when the node is probed lastly
+    probed_at: Option<Instant>,
+}
+
+impl NodeLiveness {
+    /// forgive one miss per `decay` seconds
+    fn decay(&mut self, now: Instant, config: &LivenessConfig) {
+        let since = match self.decayed_at {
+            Some(since) if config.decay > 0 => since,
+            _ => return,
+        };
+        let forgiven = now.saturating_duration_since(since).as_secs() / config.decay;
+        if forgiven == 0 {
+            return;
+        }
+        self.misses = self
+            .misses
+            .saturating_sub(forgiven.min(u32::MAX as u64) as u32);
+        self.decayed_at = if self.misses == 0 {
+            None
+        } else {
+            Some(since + Duration::from_secs(forgiven * config.decay))
+        };
+    }
 }
 
 impl Node {
     pub fn new(host_addr: HostAddr, gateway: SafeTaskManagerRpcGateway) -> Self {
         Self {
-            status: NodeStatus::Pending,
             host_addr,
             gateway,
             node_id: 0,
+            backpressure: Default::default(),
+            liveness: Default::default(),
         }
     }
 
+    /// The missed heartbeats are reset once the node is running
     pub fn update_status(&mut self, status: NodeStatus) {
-        self.status = status;
+        let mut liveness = self.lock_liveness();
+        liveness.status = status;
+        if status == NodeStatus::Running {
+            liveness.misses = 0;
+            liveness.decayed_at = None;
+        }
     }
 
     #[inline]
-    pub fn get_status(&self) -> &NodeStatus {
-        &self.status
+    pub fn get_status(&self) -> NodeStatus {
+        self.lock_liveness().status
     }
 
     #[inline]
     pub fn is_available(&self) -> bool {
-        self.status == NodeStatus::Running
+        self.get_status() == NodeStatus::Running
+    }
+
+    /// the missed heartbeats which are not forgiven yet
+    pub fn get_misses(&self) -> u32 {
+        self.lock_liveness().misses
+    }
+
+    /// record whether the node answers the heartbeat at `now`. It returns whether the status of the node changes.
+    /// A node is suspected once the missed heartbeats reach `suspect_threshold`, and unreachable once they reach `down_threshold`.
+    /// It's running again as soon as it answers, and the missed heartbeats are reset
+    fn observe_heartbeat(&self, answered: bool, now: Instant, config: &LivenessConfig) -> bool {
+        let mut liveness = self.lock_liveness();
+        liveness.probed_at = Some(now);
+        liveness.decay(now, config);
+        let status = if answered {
+            if liveness.status != NodeStatus::Running {
+                liveness.misses = 0;
+                liveness.decayed_at = None;
+            }
+            NodeStatus::Running
+        } else {
+            liveness.misses = liveness.misses.saturating_add(1);
+            liveness.decayed_at.get_or_insert(now);
+            if liveness.misses >= config.down_threshold {
+                NodeStatus::Unreachable
+            } else if liveness.misses >= config.suspect_threshold
+                && liveness.status != NodeStatus::Unreachable
+            {
+                NodeStatus::Suspect
+            } else {
+                liveness.status
+            }
+        };
+        let changed = liveness.status != status;
+        liveness.status = status;
+        changed
+    }
+
+    /// whether the node should be probed at `now`. A suspected node is probed every `suspect_interval` seconds
+    fn is_due(&self, now: Instant, config: &LivenessConfig) -> bool {
+        let liveness = self.lock_liveness();
+        let interval = match liveness.status {
+            NodeStatus::Suspect => config.get_suspect_interval(),
+            _ => config.get_heartbeat_interval(),
+        };
+        liveness
+            .probed_at
+            .map(|probed_at| now.saturating_duration_since(probed_at) >= interval)
+            .unwrap_or(true)
+    }
+
+    fn lock_liveness(&self) -> std::sync::MutexGuard<'_, NodeLiveness> {
+        self.liveness.lock().unwrap_or_else(|err| err.into_inner())
     }
 
     #[inline]
@@ -72,6 +182,184 @@ impl Node {
     pub fn get_id(&self) -> u32 {
         self.node_id
     }
+
+    /// the weight of node when a dataflow is partitioned.
+    /// Operators are evenly hashed into available nodes so it's 1 if node is available and 0 otherwise.
+    /// A node under sustained backpressure weighs 0 as well, it only takes operators if all of the available nodes are backpressured
+    #[inline]
+    pub fn get_weight(&self) -> u32 {
+        if self.is_available() && !self.is_backpressured() {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// per mille of the time the most blocked operator on the node is blocked in the latest sample
+    pub fn get_backpressure(&self) -> u64 {
+        self.lock_backpressure().ratio
+    }
+
+    /// whether the samples of the node have been over the threshold for the sustained period
+    pub fn is_backpressured(&self) -> bool {
+        self.lock_backpressure().sustained
+    }
+
+    /// record a sample of the backpressure at `now`. It returns whether the node's state of sustained backpressure changes
+    fn observe_backpressure(&self, ratio: u64, now: Instant, config: &BackpressureConfig) -> bool {
+        let mut backpressure = self.lock_backpressure();
+        backpressure.ratio = ratio;
+        let sustained = if ratio >= config.threshold {
+            let since = *backpressure.since.get_or_insert(now);
+            now.saturating_duration_since(since) >= config.get_sustain()
+        } else {
+            backpressure.since = None;
+            false
+        };
+        let changed = backpressure.sustained != sustained;
+        backpressure.sustained = sustained;
+        changed
+    }
+
+    fn lock_backpressure(&self) -> std::sync::MutexGuard<'_, NodeBackpressure> {
+        self.backpressure
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// whether the remote worker answers. The status of the node is not updated
+    pub async fn probe(&self) -> bool {
+        self.gateway.probe().await
+    }
+
+    pub fn get_health(&self) -> NodeHealth {
+        match self.get_status() {
+            NodeStatus::Pending => NodeHealth::Pending,
+            NodeStatus::Running => NodeHealth::Running,
+            NodeStatus::Suspect => NodeHealth::Suspect,
+            NodeStatus::Unreachable => NodeHealth::Unreachable,
+        }
+    }
+}
+
+/// How the Coordinator reacts to the backpressure reported by the operators in the [`crate::backpressure::BACKPRESSURE_METRIC`] metric.
+/// A node is backpressured once the most blocked operator on it is blocked for at least `threshold` per mille of the time
+/// in all of the samples over `sustain` seconds. New partitions are not placed on it unless all of the available nodes are backpressured
+#[derive(Clone, serde::Deserialize, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct BackpressureConfig {
+    /// per mille of the time blocked
+    pub threshold: u64,
+    /// how long the backpressure lasts in seconds before the node is avoided
+    pub sustain: u64,
+    /// how often the operators are sampled in seconds. They're not sampled if it's zero, and no node is avoided
+    pub sample_interval: u64,
+}
+
+impl Default for BackpressureConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 800,
+            sustain: 30,
+            sample_interval: 5,
+        }
+    }
+}
+
+impl BackpressureConfig {
+    pub fn get_sustain(&self) -> Duration {
+        Duration::from_secs(self.sustain)
+    }
+
+    pub fn get_sample_interval(&self) -> Duration {
+        Duration::from_secs(self.sample_interval)
+    }
+}
+
+/// How the Coordinator detects the nodes which are down by heartbeats. Each node is probed every `heartbeat_interval` seconds and a failed probe is a missed heartbeat.
+/// Missed heartbeats are counted per node and one of them is forgiven every `decay` seconds, so that transient blips don't add up.
+/// A node is suspected once the count reaches `suspect_threshold` and it's probed every `suspect_interval` seconds then.
+/// It's unreachable once the count reaches `down_threshold`. New partitions are placed on neither of them
+#[derive(Clone, serde::Deserialize, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct LivenessConfig {
+    /// missed heartbeats before the node is suspected
+    pub suspect_threshold: u32,
+    /// missed heartbeats before the node is unreachable
+    pub down_threshold: u32,
+    /// how long a missed heartbeat is counted in seconds. They're never forgiven until the node answers if it's zero
+    pub decay: u64,
+    /// how often the nodes are probed in seconds. They're not probed if it's zero, and no node is detected down
+    pub heartbeat_interval: u64,
+    /// how often the suspected nodes are probed in seconds
+    pub suspect_interval: u64,
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        Self {
+            suspect_threshold: 2,
+            down_threshold: 5,
+            decay: 30,
+            heartbeat_interval: 5,
+            suspect_interval: 1,
+        }
+    }
+}
+
+impl LivenessConfig {
+    pub fn get_heartbeat_interval(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_interval)
+    }
+
+    pub fn get_suspect_interval(&self) -> Duration {
+        Duration::from_secs(self.suspect_interval)
+    }
+}
+
+/// Whether the operators are moved onto a node once it's added by [`Cluster::add_node`].
+/// The operators which the node wins by rendezvous hashing are moved, so the others stay where they are, see [`Cluster::get_rebalanced_operators`].
+/// A moved operator is restored from a savepoint of its dataflow, so it keeps its state
+#[derive(Clone, serde::Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct RebalanceConfig {
+    /// the operators stay where they are if it's disabled, only new partitions are placed on the added node
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClusterError {
+    /// the node is already a member of the cluster
+    NodeExists(HostAddr),
+    /// the node doesn't answer the probe
+    NodeUnreachable(HostAddr),
+}
+
+impl std::fmt::Display for ClusterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClusterError::NodeExists(addr) => {
+                write!(
+                    f,
+                    "node {}:{} is already in the cluster",
+                    addr.host, addr.port
+                )
+            }
+            ClusterError::NodeUnreachable(addr) => {
+                write!(f, "node {}:{} is unreachable", addr.host, addr.port)
+            }
+        }
+    }
+}
+
+/// the score of the operator on the node by rendezvous hashing. An operator belongs to the node which scores highest,
+/// so adding a node only takes the operators it wins from the others
+fn rendezvous_score(operator_id: u32, addr: &HostAddr) -> u64 {
+    let ref mut hasher = DefaultHasher::new();
+    operator_id.hash(hasher);
+    addr.host.hash(hasher);
+    addr.port.hash(hasher);
+    hasher.finish()
 }
 
 /// [`Cluster`] is an abstraction of a remote cluster
@@ -80,6 +368,12 @@ impl Node {
 pub struct Cluster {
     /// all remote workers
     workers: Vec<Node>,
+    backpressure: BackpressureConfig,
+    liveness: LivenessConfig,
+    rebalance: RebalanceConfig,
+    /// timeouts of the gateways of the nodes added by [`Cluster::add_node`]
+    connect_timeout: Duration,
+    rpc_timeout: Duration,
 }
 
 impl Cluster {
@@ -90,16 +384,179 @@ impl Cluster {
             .next()
     }
 
+    /// add a node to the cluster once it answers the probe. It's running at once and takes the next node id
+    pub async fn add_node(&mut self, builder: &NodeBuilder) -> Result<Node, ClusterError> {
+        let host_addr = builder.get_host_addr();
+        if self.get_node(&host_addr).is_some() {
+            return Err(ClusterError::NodeExists(host_addr));
+        }
+        let mut node = builder.build(SafeTaskManagerRpcGateway::with_timeout(
+            &host_addr,
+            self.connect_timeout,
+            self.rpc_timeout,
+        ));
+        if !node.probe().await {
+            return Err(ClusterError::NodeUnreachable(host_addr));
+        }
+
+        node.node_id = self.workers.len() as u32;
+        node.update_status(NodeStatus::Running);
+        tracing::info!("node {}:{} is added", host_addr.host, host_addr.port);
+        self.workers.push(node.clone());
+        Ok(node)
+    }
+
+    /// the operators of the dataflow which should be moved onto the node, ordered by their ids.
+    /// Each operator belongs to the node which scores highest by rendezvous hashing among the nodes taking new partitions, see [`Node::get_weight`].
+    /// Only the operators which the node wins are moved, so none is moved between the other nodes. Nothing is moved if the node doesn't take new partitions
+    pub fn get_rebalanced_operators(&self, dataflow: &Dataflow, addr: &HostAddr) -> Vec<u32> {
+        let candidates = self
+            .workers
+            .iter()
+            .filter(|worker| worker.get_weight() > 0)
+            .map(|worker| &worker.host_addr)
+            .collect::<Vec<_>>();
+        if !candidates.contains(&addr) {
+            return vec![];
+        }
+
+        let mut operator_ids = dataflow
+            .nodes
+            .iter()
+            .filter(|(_, operator)| operator.host_addr.as_ref() != Some(addr))
+            .filter(|(operator_id, _)| {
+                candidates
+                    .iter()
+                    .max_by_key(|candidate| rendezvous_score(**operator_id, candidate))
+                    == Some(&addr)
+            })
+            .map(|(operator_id, _)| *operator_id)
+            .collect::<Vec<_>>();
+        operator_ids.sort();
+        operator_ids
+    }
+
+    pub fn with_backpressure(mut self, config: &BackpressureConfig) -> Self {
+        self.backpressure = config.clone();
+        self
+    }
+
+    pub fn get_backpressure_config(&self) -> &BackpressureConfig {
+        &self.backpressure
+    }
+
+    pub fn with_liveness(mut self, config: &LivenessConfig) -> Self {
+        self.liveness = config.clone();
+        self
+    }
+
+    pub fn get_liveness_config(&self) -> &LivenessConfig {
+        &self.liveness
+    }
+
+    pub fn with_rebalance(mut self, config: &RebalanceConfig) -> Self {
+        self.rebalance = config.clone();
+        self
+    }
+
+    pub fn get_rebalance_config(&self) -> &RebalanceConfig {
+        &self.rebalance
+    }
+
+    /// probe the nodes which are due at `now` and record whether they answer, see [`LivenessConfig`]
+    pub async fn heartbeat(&self, now: Instant) {
+        let due = self
+            .workers
+            .iter()
+            .filter(|worker| worker.is_due(now, &self.liveness))
+            .collect::<Vec<_>>();
+        let answers = futures_util::future::join_all(due.iter().map(|worker| worker.probe())).await;
+        self.observe_heartbeats(
+            &due.iter()
+                .map(|worker| worker.host_addr.clone())
+                .zip(answers)
+                .collect(),
+            now,
+        );
+    }
+
+    /// record whether the nodes answer the heartbeats at `now`, keyed by their addresses. Nodes which are not probed are skipped
+    pub fn observe_heartbeats(&self, answers: &HashMap<HostAddr, bool>, now: Instant) {
+        for worker in &self.workers {
+            let answered = match answers.get(&worker.host_addr) {
+                Some(answered) => *answered,
+                None => continue,
+            };
+            if worker.observe_heartbeat(answered, now, &self.liveness) {
+                match worker.get_status() {
+                    NodeStatus::Suspect => tracing::warn!(
+                        "node {}:{} is suspected, {} heartbeats are missed",
+                        worker.host_addr.host,
+                        worker.host_addr.port,
+                        worker.get_misses()
+                    ),
+                    NodeStatus::Unreachable => tracing::error!(
+                        "node {}:{} is unreachable, {} heartbeats are missed",
+                        worker.host_addr.host,
+                        worker.host_addr.port,
+                        worker.get_misses()
+                    ),
+                    status => tracing::info!(
+                        "node {}:{} is {:?}",
+                        worker.host_addr.host,
+                        worker.host_addr.port,
+                        status
+                    ),
+                }
+            }
+        }
+    }
+
+    /// record the backpressure sampled at `now`, keyed by the address of each node. A node without samples hosts no operator so it's not backpressured
+    pub fn observe_backpressure(&self, samples: &HashMap<HostAddr, u64>, now: Instant) {
+        for worker in &self.workers {
+            let ratio = samples.get(&worker.host_addr).cloned().unwrap_or_default();
+            if worker.observe_backpressure(ratio, now, &self.backpressure) {
+                if worker.is_backpressured() {
+                    tracing::warn!(
+                        "node {}:{} is backpressured, {} per mille of the time is blocked",
+                        worker.host_addr.host,
+                        worker.host_addr.port,
+                        ratio
+                    );
+                } else {
+                    tracing::info!(
+                        "node {}:{} recovers from backpressure",
+                        worker.host_addr.host,
+                        worker.host_addr.port
+                    );
+                }
+            }
+        }
+    }
+
+    /// the node of the key among the available nodes. Nodes under sustained backpressure are skipped unless all of the available nodes are
     pub fn partition_key<T: types::KeyedValue<K, V>, K: Hash, V>(&self, keyed: &T) -> HostAddr {
         let ref mut hasher = DefaultHasher::new();
         keyed.key().hash(hasher);
 
-        let workers: Vec<HostAddr> = self
+        let available = self
             .workers
             .iter()
             .filter(|worker| worker.is_available())
-            .map(|node| node.host_addr.clone())
-            .collect();
+            .collect::<Vec<_>>();
+        let workers: Vec<HostAddr> = match available.iter().any(|worker| !worker.is_backpressured())
+        {
+            true => available
+                .iter()
+                .filter(|worker| !worker.is_backpressured())
+                .map(|node| node.host_addr.clone())
+                .collect(),
+            false => available
+                .iter()
+                .map(|node| node.host_addr.clone())
+                .collect(),
+        };
 
         if workers.is_empty() {
             return Default::default();
@@ -116,6 +573,42 @@ impl Cluster {
             .is_some()
     }
 
+    /// the cluster is probed once any of the workers answers
+    pub async fn probe(&self) -> bool {
+        for worker in &self.workers {
+            if worker.probe().await {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// get the topology of cluster. `partitions` is the number of partitions hosted by each node
+    pub fn get_topology(&self, partitions: &HashMap<HostAddr, u32>) -> ClusterTopology {
+        ClusterTopology {
+            nodes: self
+                .workers
+                .iter()
+                .map(|worker| {
+                    let mut node = NodeTopology {
+                        node_id: worker.get_id(),
+                        host_addr: Some(worker.host_addr.clone()),
+                        weight: worker.get_weight(),
+                        partitions: partitions
+                            .get(&worker.host_addr)
+                            .cloned()
+                            .unwrap_or_default(),
+                        backpressure: worker.get_backpressure() as u32,
+                        backpressured: worker.is_backpressured(),
+                        ..Default::default()
+                    };
+                    node.set_health(worker.get_health());
+                    node
+                })
+                .collect(),
+        }
+    }
+
     /// A dataflow will be splitted into several partitions and deploy these sub-dataflow into different workers
     /// Graph-Partition is an NP-hard problem. Fortunately, a dataflow execution graph is too small to apply specific graph-partition algorithm
     pub fn partition_dataflow(&self, dataflow: &mut Dataflow) {
@@ -162,6 +655,7 @@ impl Cluster {
                 new_dataflow.meta = entry.1.iter().map(|meta| (*meta).clone()).collect();
                 new_dataflow.nodes = nodes;
                 new_dataflow.job_id = dataflow.job_id.clone();
+                new_dataflow.log_level = dataflow.log_level.clone();
 
                 (entry.0.clone(), new_dataflow)
             })
@@ -177,13 +671,23 @@ pub struct NodeBuilder {
 
 impl NodeBuilder {
     pub fn build(&self, gateway: SafeTaskManagerRpcGateway) -> Node {
-        Node::new(
-            HostAddr {
-                host: self.host.clone(),
-                port: self.port as u32,
-            },
-            gateway,
-        )
+        Node::new(self.get_host_addr(), gateway)
+    }
+
+    pub fn get_host_addr(&self) -> HostAddr {
+        HostAddr {
+            host: self.host.clone(),
+            port: self.port as u32,
+        }
+    }
+}
+
+impl From<&HostAddr> for NodeBuilder {
+    fn from(addr: &HostAddr) -> Self {
+        Self {
+            host: addr.host.clone(),
+            port: addr.port as u16,
+        }
     }
 }
 
@@ -229,10 +733,7 @@ impl ClusterBuilder {
         Cluster {
             workers: lang::index_map(&self.get_nodes(), |index, builder| {
                 let mut node = builder.build(SafeTaskManagerRpcGateway::with_timeout(
-                    &HostAddr {
-                        host: builder.host.clone(),
-                        port: builder.port as u32,
-                    },
+                    &builder.get_host_addr(),
                     Duration::from_secs(self.connect_timeout),
                     Duration::from_secs(self.rpc_timeout),
                 ));
@@ -240,6 +741,11 @@ impl ClusterBuilder {
                 node.node_id = index as u32;
                 node
             }),
+            backpressure: Default::default(),
+            liveness: Default::default(),
+            rebalance: Default::default(),
+            connect_timeout: Duration::from_secs(self.connect_timeout),
+            rpc_timeout: Duration::from_secs(self.rpc_timeout),
         }
     }
 
@@ -253,7 +759,9 @@ impl ClusterBuilder {
 
 #[cfg(test)]
 mod cluster_tests {
-    use proto::common::HostAddr;
+    use std::collections::HashMap;
+
+    use proto::{common::HostAddr, coordinator::NodeHealth};
 
     use crate::{
         net::{
@@ -275,7 +783,7 @@ mod cluster_tests {
         cluster
             .workers
             .iter_mut()
-            .for_each(|node| node.status = super::NodeStatus::Running);
+            .for_each(|node| node.update_status(super::NodeStatus::Running));
 
         assert!(cluster.is_available())
     }
@@ -299,6 +807,7 @@ mod cluster_tests {
         let meta_1 = DataflowMeta {
             center: 0,
             neighbors: vec![1, 2, 3],
+            edge_types: Default::default(),
         };
 
         let mut nodes = HashMap::new();
@@ -330,7 +839,7 @@ mod cluster_tests {
         cluster
             .workers
             .iter_mut()
-            .for_each(|node| node.status = NodeStatus::Running);
+            .for_each(|node| node.update_status(NodeStatus::Running));
 
         cluster.partition_dataflow(&mut dataflow);
 
@@ -361,21 +870,25 @@ mod cluster_tests {
         let meta_1 = DataflowMeta {
             center: 0,
             neighbors: vec![1, 2, 3],
+            edge_types: Default::default(),
         };
 
         let meta_2 = DataflowMeta {
             center: 1,
             neighbors: vec![],
+            edge_types: Default::default(),
         };
 
         let meta_3 = DataflowMeta {
             center: 2,
             neighbors: vec![],
+            edge_types: Default::default(),
         };
 
         let meta_4 = DataflowMeta {
             center: 3,
             neighbors: vec![],
+            edge_types: Default::default(),
         };
 
         let mut nodes = HashMap::new();
@@ -397,11 +910,12 @@ mod cluster_tests {
 
         dataflow.meta = vec![meta_1, meta_2, meta_3, meta_4];
         dataflow.nodes = nodes;
+        dataflow.log_level = "debug".to_string();
 
         cluster
             .workers
             .iter_mut()
-            .for_each(|node| node.status = NodeStatus::Running);
+            .for_each(|node| node.update_status(NodeStatus::Running));
 
         cluster.partition_dataflow(&mut dataflow);
 
@@ -409,6 +923,9 @@ mod cluster_tests {
         assert!(!result.is_empty());
 
         assert_eq!(result.len(), 3);
+        assert!(result
+            .values()
+            .all(|subdataflow| subdataflow.log_level == "debug"));
     }
 
     #[test]
@@ -450,11 +967,11 @@ mod cluster_tests {
             port: 9999,
         }));
 
-        assert_eq!(node.get_status(), &super::NodeStatus::Pending);
+        assert_eq!(node.get_status(), super::NodeStatus::Pending);
         let now = prost_now();
 
         node.update_status(super::NodeStatus::Running);
-        assert_eq!(node.get_status(), &super::NodeStatus::Running);
+        assert_eq!(node.get_status(), super::NodeStatus::Running);
         assert!(node.is_available());
     }
 
@@ -476,7 +993,7 @@ mod cluster_tests {
         let node = node.unwrap();
 
         assert_eq!(node.get_id(), 0);
-        assert_eq!(node.get_status(), &super::NodeStatus::Pending);
+        assert_eq!(node.get_status(), super::NodeStatus::Pending);
 
         let node = cluster.get_node(&HostAddr {
             host: "localhost_2".to_string(),
@@ -487,6 +1004,501 @@ mod cluster_tests {
         let node = node.unwrap();
 
         assert_eq!(node.get_id(), 1);
-        assert_eq!(node.get_status(), &super::NodeStatus::Pending);
+        assert_eq!(node.get_status(), super::NodeStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_cluster_probe_unreachable() {
+        let builder = super::ClusterBuilder {
+            nodes: "127.0.0.1:8820,127.0.0.1:8821".to_string(),
+            rpc_timeout: 1,
+            connect_timeout: 1,
+        };
+
+        let cluster = builder.build();
+        assert!(!cluster.probe().await);
+        assert!(cluster
+            .workers
+            .iter()
+            .all(|node| node.get_status() == super::NodeStatus::Pending));
+    }
+
+    #[tokio::test]
+    async fn test_cluster_topology() {
+        let builder = super::ClusterBuilder {
+            nodes: "localhost_1:9999,localhost_2:9999,localhost_3:9999".to_string(),
+            rpc_timeout: 3,
+            connect_timeout: 3,
+        };
+
+        let mut cluster = builder.build();
+        cluster.workers[0].update_status(super::NodeStatus::Running);
+        cluster.workers[1].update_status(super::NodeStatus::Unreachable);
+
+        let first = HostAddr {
+            host: "localhost_1".to_string(),
+            port: 9999,
+        };
+        let second = HostAddr {
+            host: "localhost_2".to_string(),
+            port: 9999,
+        };
+        let topology =
+            cluster.get_topology(&HashMap::from([(first.clone(), 2), (second.clone(), 1)]));
+
+        assert_eq!(topology.nodes.len(), 3);
+        let health = topology
+            .nodes
+            .iter()
+            .map(|node| node.health())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            health,
+            vec![
+                NodeHealth::Running,
+                NodeHealth::Unreachable,
+                NodeHealth::Pending
+            ]
+        );
+        let weights = topology
+            .nodes
+            .iter()
+            .map(|node| node.weight)
+            .collect::<Vec<_>>();
+        assert_eq!(weights, vec![1, 0, 0]);
+        let partitions = topology
+            .nodes
+            .iter()
+            .map(|node| node.partitions)
+            .collect::<Vec<_>>();
+        assert_eq!(partitions, vec![2, 1, 0]);
+        assert_eq!(topology.nodes[0].host_addr.as_ref(), Some(&first));
+        assert_eq!(topology.nodes[2].node_id, 2);
+    }
+}
+
+#[cfg(test)]
+mod backpressure_tests {
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    };
+
+    use proto::common::{Dataflow, DataflowMeta, HostAddr, OperatorInfo};
+
+    use super::{BackpressureConfig, ClusterBuilder, NodeStatus};
+
+    fn dataflow() -> Dataflow {
+        let mut dataflow = Dataflow::default();
+        dataflow.meta = vec![DataflowMeta {
+            center: 0,
+            neighbors: (1..16).collect(),
+            edge_types: Default::default(),
+        }];
+        dataflow.nodes = (0..16)
+            .map(|operator_id| {
+                (
+                    operator_id,
+                    OperatorInfo {
+                        operator_id,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+        dataflow
+    }
+
+    fn placed_hosts(dataflow: &Dataflow) -> Vec<String> {
+        let mut hosts = dataflow
+            .nodes
+            .values()
+            .filter_map(|operator| operator.host_addr.as_ref())
+            .map(|host_addr| host_addr.host.clone())
+            .collect::<Vec<_>>();
+        hosts.sort();
+        hosts.dedup();
+        hosts
+    }
+
+    #[tokio::test]
+    async fn test_backpressured_node_is_deprioritized() {
+        let mut cluster = ClusterBuilder {
+            nodes: "198.0.0.1:8080,198.0.0.2:8080".to_string(),
+            rpc_timeout: 3,
+            connect_timeout: 3,
+        }
+        .build()
+        .with_backpressure(&BackpressureConfig {
+            threshold: 800,
+            sustain: 10,
+            sample_interval: 5,
+        });
+        cluster
+            .workers
+            .iter_mut()
+            .for_each(|node| node.update_status(NodeStatus::Running));
+        let first = HostAddr {
+            host: "198.0.0.1".to_string(),
+            port: 8080,
+        };
+        let second = HostAddr {
+            host: "198.0.0.2".to_string(),
+            port: 8080,
+        };
+
+        // backpressure which doesn't last for the sustained period is tolerated
+        let now = Instant::now();
+        cluster.observe_backpressure(&HashMap::from([(first.clone(), 900)]), now);
+        let mut tolerated = dataflow();
+        cluster.partition_dataflow(&mut tolerated);
+        assert_eq!(placed_hosts(&tolerated), vec!["198.0.0.1", "198.0.0.2"]);
+
+        cluster.observe_backpressure(
+            &HashMap::from([(first.clone(), 950), (second.clone(), 100)]),
+            now + Duration::from_secs(10),
+        );
+        let mut deprioritized = dataflow();
+        cluster.partition_dataflow(&mut deprioritized);
+        assert_eq!(placed_hosts(&deprioritized), vec!["198.0.0.2"]);
+
+        let topology = cluster.get_topology(&HashMap::new());
+        let nodes = topology
+            .nodes
+            .iter()
+            .map(|node| (node.weight, node.backpressure, node.backpressured))
+            .collect::<Vec<_>>();
+        assert_eq!(nodes, vec![(0, 950, true), (1, 100, false)]);
+
+        // all of the nodes are backpressured, operators are placed on all of them
+        cluster.observe_backpressure(
+            &HashMap::from([(first.clone(), 1000), (second.clone(), 1000)]),
+            now + Duration::from_secs(20),
+        );
+        cluster.observe_backpressure(
+            &HashMap::from([(first.clone(), 1000), (second.clone(), 1000)]),
+            now + Duration::from_secs(30),
+        );
+        let mut fallback = dataflow();
+        cluster.partition_dataflow(&mut fallback);
+        assert_eq!(placed_hosts(&fallback), vec!["198.0.0.1", "198.0.0.2"]);
+
+        // a node without samples recovers
+        cluster.observe_backpressure(&HashMap::new(), now + Duration::from_secs(40));
+        assert!(cluster.workers.iter().all(|node| !node.is_backpressured()));
+    }
+}
+
+#[cfg(test)]
+mod liveness_tests {
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    };
+
+    use proto::{common::HostAddr, coordinator::NodeHealth};
+
+    use super::{ClusterBuilder, LivenessConfig, NodeStatus};
+
+    fn config() -> LivenessConfig {
+        LivenessConfig {
+            suspect_threshold: 2,
+            down_threshold: 4,
+            decay: 30,
+            heartbeat_interval: 5,
+            suspect_interval: 1,
+        }
+    }
+
+    fn host_addr(host: &str) -> HostAddr {
+        HostAddr {
+            host: host.to_string(),
+            port: 8080,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_misses_suspect_then_down() {
+        let cluster = ClusterBuilder {
+            nodes: "198.0.0.1:8080,198.0.0.2:8080".to_string(),
+            rpc_timeout: 3,
+            connect_timeout: 3,
+        }
+        .build()
+        .with_liveness(&config());
+        let first = host_addr("198.0.0.1");
+        let second = host_addr("198.0.0.2");
+        let now = Instant::now();
+        let statuses = || {
+            cluster
+                .workers
+                .iter()
+                .map(|node| (node.get_status(), node.get_misses()))
+                .collect::<Vec<_>>()
+        };
+
+        cluster.observe_heartbeats(
+            &HashMap::from([(first.clone(), true), (second.clone(), true)]),
+            now,
+        );
+        assert_eq!(
+            statuses(),
+            vec![(NodeStatus::Running, 0), (NodeStatus::Running, 0)]
+        );
+
+        // a single miss is tolerated
+        cluster.observe_heartbeats(
+            &HashMap::from([(first.clone(), false), (second.clone(), true)]),
+            now + Duration::from_secs(5),
+        );
+        assert_eq!(
+            statuses(),
+            vec![(NodeStatus::Running, 1), (NodeStatus::Running, 0)]
+        );
+        assert!(cluster.workers[0].is_available());
+
+        cluster.observe_heartbeats(
+            &HashMap::from([(first.clone(), false)]),
+            now + Duration::from_secs(10),
+        );
+        assert_eq!(
+            statuses(),
+            vec![(NodeStatus::Suspect, 2), (NodeStatus::Running, 0)]
+        );
+        assert!(!cluster.workers[0].is_available());
+        assert_eq!(cluster.workers[0].get_health(), NodeHealth::Suspect);
+
+        cluster.observe_heartbeats(
+            &HashMap::from([(first.clone(), false)]),
+            now + Duration::from_secs(11),
+        );
+        assert_eq!(statuses()[0], (NodeStatus::Suspect, 3));
+        cluster.observe_heartbeats(
+            &HashMap::from([(first.clone(), false)]),
+            now + Duration::from_secs(12),
+        );
+        assert_eq!(statuses()[0], (NodeStatus::Unreachable, 4));
+
+        let topology = cluster.get_topology(&HashMap::new());
+        let nodes = topology
+            .nodes
+            .iter()
+            .map(|node| (node.health(), node.weight))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            nodes,
+            vec![(NodeHealth::Unreachable, 0), (NodeHealth::Running, 1)]
+        );
+
+        // the unreachable node keeps unreachable until it answers, even if the misses decay
+        cluster.observe_heartbeats(
+            &HashMap::from([(first.clone(), false)]),
+            now + Duration::from_secs(100),
+        );
+        assert_eq!(statuses()[0], (NodeStatus::Unreachable, 2));
+    }
+
+    #[tokio::test]
+    async fn test_recovery_resets_misses() {
+        let cluster = ClusterBuilder {
+            nodes: "198.0.0.1:8080".to_string(),
+            rpc_timeout: 3,
+            connect_timeout: 3,
+        }
+        .build()
+        .with_liveness(&config());
+        let first = host_addr("198.0.0.1");
+        let node = &cluster.workers[0];
+        let now = Instant::now();
+
+        for (secs, answered) in [(0, true), (5, false), (10, false), (11, false)] {
+            cluster.observe_heartbeats(
+                &HashMap::from([(first.clone(), answered)]),
+                now + Duration::from_secs(secs),
+            );
+        }
+        assert_eq!(node.get_status(), NodeStatus::Suspect);
+        assert_eq!(node.get_misses(), 3);
+
+        // the suspected node answers so it's running again and the misses are reset
+        cluster.observe_heartbeats(
+            &HashMap::from([(first.clone(), true)]),
+            now + Duration::from_secs(12),
+        );
+        assert_eq!(node.get_status(), NodeStatus::Running);
+        assert_eq!(node.get_misses(), 0);
+
+        // it takes as many misses as before to be suspected again
+        cluster.observe_heartbeats(
+            &HashMap::from([(first.clone(), false)]),
+            now + Duration::from_secs(17),
+        );
+        assert_eq!(node.get_status(), NodeStatus::Running);
+        cluster.observe_heartbeats(
+            &HashMap::from([(first.clone(), false)]),
+            now + Duration::from_secs(22),
+        );
+        assert_eq!(node.get_status(), NodeStatus::Suspect);
+    }
+
+    #[tokio::test]
+    async fn test_misses_decay() {
+        let cluster = ClusterBuilder {
+            nodes: "198.0.0.1:8080".to_string(),
+            rpc_timeout: 3,
+            connect_timeout: 3,
+        }
+        .build()
+        .with_liveness(&config());
+        let first = host_addr("198.0.0.1");
+        let node = &cluster.workers[0];
+        let now = Instant::now();
+
+        // transient blips which are far apart never add up to a suspicion
+        for secs in [0, 35, 70, 105] {
+            cluster.observe_heartbeats(
+                &HashMap::from([(first.clone(), true)]),
+                now + Duration::from_secs(secs),
+            );
+            cluster.observe_heartbeats(
+                &HashMap::from([(first.clone(), false)]),
+                now + Duration::from_secs(secs + 5),
+            );
+            assert_eq!(node.get_status(), NodeStatus::Running);
+            assert_eq!(node.get_misses(), 1);
+        }
+
+        cluster.observe_heartbeats(
+            &HashMap::from([(first.clone(), true)]),
+            now + Duration::from_secs(140),
+        );
+        assert_eq!(node.get_misses(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_suspect_probed_frequently() {
+        let cluster = ClusterBuilder {
+            nodes: "127.0.0.1:8832".to_string(),
+            rpc_timeout: 1,
+            connect_timeout: 1,
+        }
+        .build()
+        .with_liveness(&LivenessConfig {
+            suspect_threshold: 1,
+            down_threshold: 3,
+            ..config()
+        });
+        let node = &cluster.workers[0];
+        let now = Instant::now();
+
+        cluster.heartbeat(now).await;
+        assert_eq!(node.get_status(), NodeStatus::Suspect);
+        assert_eq!(node.get_misses(), 1);
+
+        // the suspected node is probed every second
+        cluster.heartbeat(now + Duration::from_secs(1)).await;
+        assert_eq!(node.get_misses(), 2);
+        cluster.heartbeat(now + Duration::from_secs(2)).await;
+        assert_eq!(node.get_status(), NodeStatus::Unreachable);
+        assert_eq!(node.get_misses(), 3);
+
+        // the unreachable node is probed every heartbeat interval
+        cluster.heartbeat(now + Duration::from_secs(3)).await;
+        assert_eq!(node.get_misses(), 3);
+        cluster.heartbeat(now + Duration::from_secs(7)).await;
+        assert_eq!(node.get_misses(), 4);
+    }
+}
+
+#[cfg(test)]
+mod rebalance_tests {
+    use proto::common::{Dataflow, HostAddr, OperatorInfo};
+
+    use crate::net::gateway::taskmanager::SafeTaskManagerRpcGateway;
+
+    use super::{ClusterBuilder, ClusterError, NodeBuilder, NodeStatus};
+
+    #[tokio::test]
+    async fn test_rebalanced_operators() {
+        let mut cluster = ClusterBuilder {
+            nodes: "198.0.0.1:8080,198.0.0.2:8080,198.0.0.3:8080".to_string(),
+            rpc_timeout: 3,
+            connect_timeout: 3,
+        }
+        .build();
+        cluster
+            .workers
+            .iter_mut()
+            .for_each(|node| node.update_status(NodeStatus::Running));
+        let mut dataflow = Dataflow::default();
+        dataflow.nodes = (0..64)
+            .map(|operator_id| {
+                (
+                    operator_id,
+                    OperatorInfo {
+                        operator_id,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+        cluster.partition_dataflow(&mut dataflow);
+
+        let added = HostAddr {
+            host: "198.0.0.4".to_string(),
+            port: 8080,
+        };
+        let mut node = NodeBuilder::from(&added).build(SafeTaskManagerRpcGateway::new(&added));
+        node.node_id = 3;
+        cluster.workers.push(node);
+        // the node doesn't take new partitions before it's running
+        assert!(cluster
+            .get_rebalanced_operators(&dataflow, &added)
+            .is_empty());
+
+        cluster.workers[3].update_status(NodeStatus::Running);
+        let moved = cluster.get_rebalanced_operators(&dataflow, &added);
+        // about a quarter of the operators are won by the added node, the others stay where they are
+        assert!(!moved.is_empty());
+        assert!(moved.len() < dataflow.nodes.len() / 2);
+        assert!(moved.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(cluster.get_rebalanced_operators(&dataflow, &added), moved);
+
+        // nothing else is moved once the operators are on the added node
+        moved.iter().for_each(|operator_id| {
+            dataflow.nodes.get_mut(operator_id).unwrap().host_addr = Some(added.clone())
+        });
+        assert!(cluster
+            .get_rebalanced_operators(&dataflow, &added)
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_add_node() {
+        let mut cluster = ClusterBuilder {
+            nodes: "198.0.0.1:8080".to_string(),
+            rpc_timeout: 1,
+            connect_timeout: 1,
+        }
+        .build();
+
+        let result = cluster.add_node(&NodeBuilder::from("198.0.0.1:8080")).await;
+        assert_eq!(
+            result.unwrap_err(),
+            ClusterError::NodeExists(HostAddr {
+                host: "198.0.0.1".to_string(),
+                port: 8080,
+            })
+        );
+
+        let result = cluster.add_node(&NodeBuilder::from("127.0.0.1:8842")).await;
+        assert_eq!(
+            result.unwrap_err(),
+            ClusterError::NodeUnreachable(HostAddr {
+                host: "127.0.0.1".to_string(),
+                port: 8842,
+            })
+        );
+        assert_eq!(cluster.workers.len(), 1);
     }
 }
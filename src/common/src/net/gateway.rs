@@ -1,4 +1,4 @@
-use proto::common::{Ack, Heartbeat, HostAddr, Response};
+use proto::common::{Ack, Heartbeat, HostAddr, OperatorError, Response};
 use tokio::sync::mpsc;
 use tonic::async_trait;
 
@@ -19,10 +19,20 @@ pub trait ReceiveHeartbeatRpcGateway: RpcGateway {
     async fn receive_heartbeat(&self, request: Heartbeat) -> Result<Response, tonic::Status>;
 }
 
+/// Trait for [RpcGateway] that must implements report_operator_error rpc call
+#[async_trait]
+pub trait ReportOperatorErrorRpcGateway: RpcGateway {
+    async fn report_operator_error(
+        &self,
+        request: OperatorError,
+    ) -> Result<Response, tonic::Status>;
+}
+
 #[derive(Clone)]
 pub struct MockRpcGateway {
     ack_channel: mpsc::Sender<Ack>,
     heartbeat_channel: mpsc::Sender<Heartbeat>,
+    operator_error_channel: Option<mpsc::Sender<OperatorError>>,
 }
 
 unsafe impl Send for MockRpcGateway {}
@@ -56,7 +66,36 @@ impl ReceiveHeartbeatRpcGateway for MockRpcGateway {
     }
 }
 
+#[async_trait]
+impl ReportOperatorErrorRpcGateway for MockRpcGateway {
+    async fn report_operator_error(
+        &self,
+        request: OperatorError,
+    ) -> Result<Response, tonic::Status> {
+        match &self.operator_error_channel {
+            Some(channel) => channel
+                .send(request)
+                .await
+                .map(|_| Response::ok())
+                .map_err(|err| tonic::Status::data_loss(err.to_string())),
+            None => Err(tonic::Status::unimplemented(
+                "operator error channel is not set",
+            )),
+        }
+    }
+}
+
 impl MockRpcGateway {
+    /// set a channel to receive the operator errors which are reported to this gateway
+    pub fn with_operator_error_channel(
+        mut self,
+        buf_size: usize,
+    ) -> (Self, mpsc::Receiver<OperatorError>) {
+        let (tx, rx) = mpsc::channel(buf_size);
+        self.operator_error_channel = Some(tx);
+        (self, rx)
+    }
+
     pub fn new(
         ack_buf_size: usize,
         heartbeat_buf_size: usize,
@@ -67,6 +106,7 @@ impl MockRpcGateway {
             Self {
                 ack_channel: ack_tx,
                 heartbeat_channel: heartbeat_tx,
+                operator_error_channel: None,
             },
             ack_rx,
             heartbeat_rx,
@@ -75,24 +115,28 @@ impl MockRpcGateway {
 }
 
 pub mod taskmanager {
-    use std::{sync::Arc, time::Duration};
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
 
     use prost::Message;
     use proto::{
         common::{
-            Ack, Heartbeat, HostAddr, KeyedDataEvent, KeyedEventSet, ResourceId, Response,
-            SubDataflowStates,
+            Ack, Heartbeat, HostAddr, KeyedDataEvent, KeyedEventSet, OperatorStates, ResourceId,
+            Response, SubDataflowStates,
         },
         taskmanager::{
             task_manager_api_client::TaskManagerApiClient, BatchSendEventsToOperatorResponse,
-            CreateSubDataflowRequest, CreateSubDataflowResponse, SendEventToOperatorResponse,
-            StopDataflowResponse,
+            CreateSubDataflowRequest, CreateSubDataflowResponse, OperatorRequest,
+            SendEventToOperatorResponse, StopDataflowRequest, StopDataflowResponse,
+            TapOperatorRequest, UpdateSinkConfigRequest,
         },
     };
     use tokio::sync::Mutex;
     use tonic::{async_trait, transport::Channel};
 
-    use crate::net::DEFAULT_RPC_TIMEOUT;
+    use crate::net::{resolver::Reresolution, DEFAULT_RPC_TIMEOUT};
 
     use super::{
         super::DEFAULT_CONNECT_TIMEOUT, ReceiveAckRpcGateway, ReceiveHeartbeatRpcGateway,
@@ -102,12 +146,16 @@ pub mod taskmanager {
     /// A thread-safe RpcGateway wrapper for [`TaskManagerApiClient`]. It's also reponsible for concurrency control of client-side gRPC.
     /// [`SafeTaskWorkerRpcGateway`] ensures only one thread can call [`TaskManagerApiClient`] at the same time. Requests have to be sent FIFO, without any fault tolerance.
     /// [`SafeTaskWorkerRpcGateway`] can be shared in different threads safely.
+    ///
+    /// The clones of a gateway share the same connection, which is torn down once the last clone is closed or dropped.
+    /// The connection is rebuilt once the host is re-resolved into other addresses.
     #[derive(Debug, Clone)]
     pub struct SafeTaskManagerRpcGateway {
         inner: Arc<Mutex<Option<TaskManagerApiClient<Channel>>>>,
         host_addr: HostAddr,
         connect_timeout: Duration,
         rpc_timeout: Duration,
+        reresolution: Arc<Mutex<Reresolution>>,
     }
 
     unsafe impl Send for SafeTaskManagerRpcGateway {}
@@ -121,16 +169,18 @@ pub mod taskmanager {
 
     impl Unpin for SafeTaskManagerRpcGateway {}
 
+    impl Drop for SafeTaskManagerRpcGateway {
+        fn drop(&mut self) {
+            self.close()
+        }
+    }
+
     #[async_trait]
     impl ReceiveAckRpcGateway for SafeTaskManagerRpcGateway {
         async fn receive_ack(&self, request: Ack) -> Result<Response, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                TaskManagerApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    Duration::from_secs(DEFAULT_CONNECT_TIMEOUT),
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
 
             inner
                 .receive_ack(tonic::Request::new(request))
@@ -143,12 +193,8 @@ pub mod taskmanager {
     impl ReceiveHeartbeatRpcGateway for SafeTaskManagerRpcGateway {
         async fn receive_heartbeat(&self, request: Heartbeat) -> Result<Response, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                TaskManagerApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    Duration::from_secs(DEFAULT_CONNECT_TIMEOUT),
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
 
             inner
                 .receive_heartbeat(tonic::Request::new(request))
@@ -159,16 +205,11 @@ pub mod taskmanager {
 
     impl SafeTaskManagerRpcGateway {
         pub fn new(host_addr: &HostAddr) -> Self {
-            let client = TaskManagerApiClient::with_connection_timeout(
-                host_addr.as_uri(),
+            Self::with_timeout(
+                host_addr,
                 Duration::from_secs(DEFAULT_CONNECT_TIMEOUT),
-            );
-            Self {
-                inner: Arc::new(tokio::sync::Mutex::new(Some(client))),
-                host_addr: host_addr.clone(),
-                connect_timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT),
-                rpc_timeout: Duration::from_secs(DEFAULT_RPC_TIMEOUT),
-            }
+                Duration::from_secs(DEFAULT_RPC_TIMEOUT),
+            )
         }
 
         pub fn with_timeout(
@@ -176,13 +217,47 @@ pub mod taskmanager {
             connect_timeout: Duration,
             rpc_timeout: Duration,
         ) -> Self {
-            let client =
-                TaskManagerApiClient::with_connection_timeout(host_addr.as_uri(), connect_timeout);
+            let client = TaskManagerApiClient::with_connection_timeout(
+                host_addr.as_uri(),
+                connect_timeout,
+                rpc_timeout,
+            );
             Self {
                 inner: Arc::new(tokio::sync::Mutex::new(Some(client))),
                 host_addr: host_addr.clone(),
                 connect_timeout,
                 rpc_timeout,
+                reresolution: Default::default(),
+            }
+        }
+
+        /// replace the default re-resolution, which re-resolves the host by the system DNS resolver at the interval configured by the env
+        pub fn with_reresolution(mut self, reresolution: Reresolution) -> Self {
+            self.reresolution = Arc::new(Mutex::new(reresolution));
+            self
+        }
+
+        /// every lazy reconnect applies the same connect timeout and rpc deadline as the initial connection
+        fn connect_lazily(&self) -> TaskManagerApiClient<Channel> {
+            TaskManagerApiClient::with_connection_timeout(
+                self.host_addr.as_uri(),
+                self.connect_timeout,
+                self.rpc_timeout,
+            )
+        }
+
+        /// the client is dropped once the host is re-resolved into other addresses, and it reconnects lazily
+        async fn reresolve(&self, client: &mut Option<TaskManagerApiClient<Channel>>) {
+            let mut reresolution = self.reresolution.lock().await;
+            if reresolution
+                .is_changed(&self.host_addr, Instant::now())
+                .await
+            {
+                tracing::info!(
+                    "host {} is resolved into new addresses, the connection is rebuilt",
+                    self.host_addr.as_uri()
+                );
+                client.take();
             }
         }
 
@@ -191,12 +266,8 @@ pub mod taskmanager {
             event: KeyedDataEvent,
         ) -> Result<SendEventToOperatorResponse, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                TaskManagerApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    self.connect_timeout,
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
 
             let mut request = tonic::Request::new(event);
             request.set_timeout(self.rpc_timeout);
@@ -209,17 +280,13 @@ pub mod taskmanager {
 
         pub async fn stop_dataflow(
             &self,
-            job_id: ResourceId,
+            req: StopDataflowRequest,
         ) -> Result<StopDataflowResponse, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                TaskManagerApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    self.connect_timeout,
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
 
-            let mut request = tonic::Request::new(job_id);
+            let mut request = tonic::Request::new(req);
             request.set_timeout(self.rpc_timeout);
 
             inner
@@ -233,12 +300,8 @@ pub mod taskmanager {
             req: CreateSubDataflowRequest,
         ) -> Result<CreateSubDataflowResponse, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                TaskManagerApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    self.connect_timeout,
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
 
             let mut request = tonic::Request::new(req);
             request.set_timeout(self.rpc_timeout);
@@ -252,9 +315,12 @@ pub mod taskmanager {
             result
         }
 
+        /// close the gateway. The connection is torn down if no other clone of the gateway shares it
         pub fn close(&mut self) {
             self.host_addr.clear();
-            drop(self.inner.as_ref())
+            if let Some(inner) = Arc::get_mut(&mut self.inner) {
+                inner.get_mut().take();
+            }
         }
 
         pub async fn batch_send_events_to_operator(
@@ -262,12 +328,8 @@ pub mod taskmanager {
             req: KeyedEventSet,
         ) -> Result<BatchSendEventsToOperatorResponse, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                TaskManagerApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    self.connect_timeout,
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
 
             let mut request = tonic::Request::new(req);
             request.set_timeout(self.rpc_timeout);
@@ -283,12 +345,8 @@ pub mod taskmanager {
             req: ResourceId,
         ) -> Result<SubDataflowStates, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                TaskManagerApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    self.connect_timeout,
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
 
             let mut request = tonic::Request::new(req);
             request.set_timeout(self.rpc_timeout);
@@ -298,6 +356,103 @@ pub mod taskmanager {
                 .await
                 .map(|resp| resp.into_inner())
         }
+
+        /// whether the TaskManager answers. It's reachable unless the rpc fails in the transport, even if the rpc is rejected
+        pub async fn probe(&self) -> bool {
+            match self.get_sub_dataflow(ResourceId::default()).await {
+                Ok(_) => true,
+                Err(status) => !matches!(
+                    status.code(),
+                    tonic::Code::Unavailable
+                        | tonic::Code::DeadlineExceeded
+                        | tonic::Code::Cancelled
+                        | tonic::Code::Unknown
+                ),
+            }
+        }
+
+        pub async fn drain_operator(
+            &self,
+            req: OperatorRequest,
+        ) -> Result<Response, tonic::Status> {
+            let mut guard = self.inner.lock().await;
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
+
+            let mut request = tonic::Request::new(req);
+            request.set_timeout(self.rpc_timeout);
+
+            inner
+                .drain_operator(request)
+                .await
+                .map(|resp| resp.into_inner())
+        }
+
+        pub async fn resume_operator(
+            &self,
+            req: OperatorRequest,
+        ) -> Result<Response, tonic::Status> {
+            let mut guard = self.inner.lock().await;
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
+
+            let mut request = tonic::Request::new(req);
+            request.set_timeout(self.rpc_timeout);
+
+            inner
+                .resume_operator(request)
+                .await
+                .map(|resp| resp.into_inner())
+        }
+
+        pub async fn trigger_savepoint(
+            &self,
+            req: ResourceId,
+        ) -> Result<OperatorStates, tonic::Status> {
+            let mut guard = self.inner.lock().await;
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
+
+            let mut request = tonic::Request::new(req);
+            request.set_timeout(self.rpc_timeout);
+
+            inner
+                .trigger_savepoint(request)
+                .await
+                .map(|resp| resp.into_inner())
+        }
+
+        pub async fn update_sink_config(
+            &self,
+            req: UpdateSinkConfigRequest,
+        ) -> Result<Response, tonic::Status> {
+            let mut guard = self.inner.lock().await;
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
+
+            let mut request = tonic::Request::new(req);
+            request.set_timeout(self.rpc_timeout);
+
+            inner
+                .update_sink_config(request)
+                .await
+                .map(|resp| resp.into_inner())
+        }
+
+        /// tap an operator. The stream of sampled events has no rpc timeout, the tap is closed once it's dropped
+        pub async fn tap_operator(
+            &self,
+            req: TapOperatorRequest,
+        ) -> Result<tonic::Streaming<KeyedDataEvent>, tonic::Status> {
+            let mut guard = self.inner.lock().await;
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
+
+            inner
+                .tap_operator(tonic::Request::new(req))
+                .await
+                .map(|resp| resp.into_inner())
+        }
     }
 
     #[derive(Clone)]
@@ -306,6 +461,7 @@ pub mod taskmanager {
         connect_timeout: Duration,
         rpc_timeout: Duration,
         host_addr: HostAddr,
+        reresolution: Reresolution,
     }
 
     impl RpcGateway for UnsafeTaskManagerRpcGateway {
@@ -324,10 +480,23 @@ pub mod taskmanager {
                 inner: Some(TaskManagerApiClient::with_connection_timeout(
                     host_addr.as_uri(),
                     connect_timeout,
+                    rpc_timeout,
                 )),
                 connect_timeout,
                 rpc_timeout,
                 host_addr: host_addr.clone(),
+                reresolution: Default::default(),
+            }
+        }
+
+        /// the client is dropped once the host is re-resolved into other addresses, and it reconnects lazily
+        async fn reresolve(&mut self) {
+            if self
+                .reresolution
+                .is_changed(&self.host_addr, Instant::now())
+                .await
+            {
+                self.inner.take();
             }
         }
 
@@ -335,10 +504,12 @@ pub mod taskmanager {
             &mut self,
             req: CreateSubDataflowRequest,
         ) -> Result<CreateSubDataflowResponse, tonic::Status> {
+            self.reresolve().await;
             let inner = self.inner.get_or_insert_with(|| {
                 TaskManagerApiClient::with_connection_timeout(
                     self.host_addr.as_uri(),
                     self.connect_timeout,
+                    self.rpc_timeout,
                 )
             });
             let mut request = tonic::Request::new(req);
@@ -354,10 +525,12 @@ pub mod taskmanager {
             &mut self,
             event: KeyedDataEvent,
         ) -> Result<SendEventToOperatorResponse, tonic::Status> {
+            self.reresolve().await;
             let inner = self.inner.get_or_insert_with(|| {
                 TaskManagerApiClient::with_connection_timeout(
                     self.host_addr.as_uri(),
                     self.connect_timeout,
+                    self.rpc_timeout,
                 )
             });
 
@@ -373,29 +546,38 @@ pub mod taskmanager {
 }
 
 pub mod coordinator {
-    use std::{sync::Arc, time::Duration};
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
 
     use tokio::sync::Mutex;
     use tonic::async_trait;
 
     use proto::{
-        common::{Ack, Dataflow, DataflowStates, Heartbeat, HostAddr, ResourceId, Response},
+        common::{
+            Ack, Dataflow, DataflowStates, Heartbeat, HostAddr, OperatorError, ResourceId, Response,
+        },
         coordinator::{coordinator_api_client::CoordinatorApiClient, GetDataflowRequest},
     };
 
-    use crate::net::{DEFAULT_CONNECT_TIMEOUT, DEFAULT_RPC_TIMEOUT};
+    use crate::net::{resolver::Reresolution, DEFAULT_CONNECT_TIMEOUT, DEFAULT_RPC_TIMEOUT};
 
-    use super::{ReceiveAckRpcGateway, ReceiveHeartbeatRpcGateway, RpcGateway};
+    use super::{
+        ReceiveAckRpcGateway, ReceiveHeartbeatRpcGateway, ReportOperatorErrorRpcGateway, RpcGateway,
+    };
 
     /// A thread-safe RpcGateway wrapper for [`CoordinatorApiClient`]. It's also reponsible for concurrency control of client-side gRPC.
     /// [`SafeCoordinatorRpcGateway`] ensures only one thread can call [`CoordinatorApiClient`] at the same time. Requests have to be sent FIFO, without any fault tolerance.
     /// [`SafeCoordinatorRpcGateway`] can be shared in different threads safely.
+    /// The connection is rebuilt once the host is re-resolved into other addresses.
     #[derive(Debug, Clone)]
     pub struct SafeCoordinatorRpcGateway {
         inner: Arc<Mutex<Option<CoordinatorApiClient<tonic::transport::Channel>>>>,
         host_addr: HostAddr,
-        rpc_timeout: u64,
-        connect_timeout: u64,
+        rpc_timeout: Duration,
+        connect_timeout: Duration,
+        reresolution: Arc<Mutex<Reresolution>>,
     }
 
     impl RpcGateway for SafeCoordinatorRpcGateway {
@@ -409,15 +591,11 @@ pub mod coordinator {
     impl ReceiveHeartbeatRpcGateway for SafeCoordinatorRpcGateway {
         async fn receive_heartbeat(&self, request: Heartbeat) -> Result<Response, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                CoordinatorApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    Duration::from_secs(DEFAULT_CONNECT_TIMEOUT),
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
 
             let mut request = tonic::Request::new(request);
-            request.set_timeout(Duration::from_secs(self.rpc_timeout));
+            request.set_timeout(self.rpc_timeout);
 
             inner
                 .receive_heartbeat(request)
@@ -430,14 +608,10 @@ pub mod coordinator {
     impl ReceiveAckRpcGateway for SafeCoordinatorRpcGateway {
         async fn receive_ack(&self, req: Ack) -> Result<Response, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                CoordinatorApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    Duration::from_secs(DEFAULT_CONNECT_TIMEOUT),
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
             let mut request = tonic::Request::new(req);
-            request.set_timeout(Duration::from_secs(self.rpc_timeout));
+            request.set_timeout(self.rpc_timeout);
 
             inner
                 .receive_ack(request)
@@ -446,29 +620,100 @@ pub mod coordinator {
         }
     }
 
+    #[async_trait]
+    impl ReportOperatorErrorRpcGateway for SafeCoordinatorRpcGateway {
+        async fn report_operator_error(
+            &self,
+            req: OperatorError,
+        ) -> Result<Response, tonic::Status> {
+            let mut guard = self.inner.lock().await;
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
+            let mut request = tonic::Request::new(req);
+            request.set_timeout(self.rpc_timeout);
+
+            inner
+                .report_operator_error(request)
+                .await
+                .map(|resp| resp.into_inner())
+        }
+    }
+
     impl SafeCoordinatorRpcGateway {
+        /// create a gateway which connects to remote coordinator lazily
+        pub fn with_timeout(host_addr: &HostAddr, connect_timeout: u64, rpc_timeout: u64) -> Self {
+            let connect_timeout = Duration::from_secs(connect_timeout);
+            let rpc_timeout = Duration::from_secs(rpc_timeout);
+            let client = CoordinatorApiClient::with_connection_timeout(
+                host_addr.as_uri(),
+                connect_timeout,
+                rpc_timeout,
+            );
+            Self {
+                inner: Arc::new(tokio::sync::Mutex::new(Some(client))),
+                host_addr: host_addr.clone(),
+                rpc_timeout,
+                connect_timeout,
+                reresolution: Default::default(),
+            }
+        }
+
+        /// create a gateway which tries to connect to remote coordinator eagerly. If the connection can't be established before the connect timeout, the gateway falls back to connect lazily
         pub async fn new(host_addr: &HostAddr) -> Self {
+            let connect_timeout = Duration::from_secs(DEFAULT_CONNECT_TIMEOUT);
+            let rpc_timeout = Duration::from_secs(DEFAULT_RPC_TIMEOUT);
             let client = CoordinatorApiClient::connect_with_timeout(
                 host_addr.as_uri(),
-                Duration::from_secs(DEFAULT_CONNECT_TIMEOUT),
+                connect_timeout,
+                rpc_timeout,
             )
             .await;
             Self {
                 inner: Arc::new(tokio::sync::Mutex::new(client.ok())),
                 host_addr: host_addr.clone(),
-                rpc_timeout: DEFAULT_RPC_TIMEOUT,
-                connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+                rpc_timeout,
+                connect_timeout,
+                reresolution: Default::default(),
+            }
+        }
+
+        /// replace the default re-resolution, which re-resolves the host by the system DNS resolver at the interval configured by the env
+        pub fn with_reresolution(mut self, reresolution: Reresolution) -> Self {
+            self.reresolution = Arc::new(Mutex::new(reresolution));
+            self
+        }
+
+        /// every lazy reconnect applies the same connect timeout and rpc deadline as the initial connection
+        fn connect_lazily(&self) -> CoordinatorApiClient<tonic::transport::Channel> {
+            CoordinatorApiClient::with_connection_timeout(
+                self.host_addr.as_uri(),
+                self.connect_timeout,
+                self.rpc_timeout,
+            )
+        }
+
+        /// the client is dropped once the host is re-resolved into other addresses, and it reconnects lazily
+        async fn reresolve(
+            &self,
+            client: &mut Option<CoordinatorApiClient<tonic::transport::Channel>>,
+        ) {
+            let mut reresolution = self.reresolution.lock().await;
+            if reresolution
+                .is_changed(&self.host_addr, Instant::now())
+                .await
+            {
+                tracing::info!(
+                    "host {} is resolved into new addresses, the connection is rebuilt",
+                    self.host_addr.as_uri()
+                );
+                client.take();
             }
         }
 
         pub async fn create_dataflow(&self, dataflow: Dataflow) -> Result<Response, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                CoordinatorApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    Duration::from_secs(self.connect_timeout),
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
 
             let result = inner
                 .create_dataflow(tonic::Request::new(dataflow))
@@ -481,14 +726,10 @@ pub mod coordinator {
 
         pub async fn terminate_dataflow(&self, req: ResourceId) -> Result<Response, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                CoordinatorApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    Duration::from_secs(self.connect_timeout),
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
             let mut request = tonic::Request::new(req);
-            request.set_timeout(Duration::from_secs(self.rpc_timeout));
+            request.set_timeout(self.rpc_timeout);
 
             inner
                 .terminate_dataflow(request)
@@ -501,15 +742,11 @@ pub mod coordinator {
             req: GetDataflowRequest,
         ) -> Result<DataflowStates, tonic::Status> {
             let mut guard = self.inner.lock().await;
-            let inner = guard.get_or_insert_with(|| {
-                CoordinatorApiClient::with_connection_timeout(
-                    self.host_addr.as_uri(),
-                    Duration::from_secs(self.connect_timeout),
-                )
-            });
+            self.reresolve(&mut guard).await;
+            let inner = guard.get_or_insert_with(|| self.connect_lazily());
 
             let mut request = tonic::Request::new(req);
-            request.set_timeout(Duration::from_secs(self.rpc_timeout));
+            request.set_timeout(self.rpc_timeout);
 
             inner
                 .get_dataflow(request)
@@ -518,3 +755,270 @@ pub mod coordinator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use proto::{
+        common::{HostAddr, ResourceId},
+        coordinator::GetDataflowRequest,
+        taskmanager::StopDataflowRequest,
+    };
+
+    use super::{
+        coordinator::SafeCoordinatorRpcGateway, taskmanager::SafeTaskManagerRpcGateway, RpcGateway,
+    };
+    use crate::net::resolver::{MockResolver, Reresolution};
+
+    /// a non-routable address which drops every packet, so the tcp handshake never completes
+    fn black_hole_addr() -> HostAddr {
+        HostAddr {
+            host: "10.255.255.1".to_string(),
+            port: 8791,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_coordinator_gateway_black_hole_deadline() {
+        let start = Instant::now();
+        let gateway = SafeCoordinatorRpcGateway::new(&black_hole_addr()).await;
+        // falls back to the lazy connection once the connect timeout elapses
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let start = Instant::now();
+        let r = gateway
+            .get_dataflow(GetDataflowRequest {
+                job_id: Some(ResourceId::default()),
+                effective: false,
+                include_metrics: false,
+            })
+            .await;
+        assert!(r.is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let gateway = SafeCoordinatorRpcGateway::with_timeout(&black_hole_addr(), 1, 1);
+        let start = Instant::now();
+        let r = gateway.terminate_dataflow(ResourceId::default()).await;
+        assert!(r.is_err());
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn test_taskmanager_gateway_black_hole_deadline() {
+        let gateway = SafeTaskManagerRpcGateway::with_timeout(
+            &black_hole_addr(),
+            Duration::from_millis(500),
+            Duration::from_secs(1),
+        );
+
+        let start = Instant::now();
+        let r = gateway.get_sub_dataflow(ResourceId::default()).await;
+        assert!(r.is_err());
+        assert!(start.elapsed() < Duration::from_secs(3));
+
+        // the lazy reconnect keeps the same deadline
+        let start = Instant::now();
+        let r = gateway.stop_dataflow(StopDataflowRequest::default()).await;
+        assert!(r.is_err());
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+
+    /// the tcp handshake completes but the server never answers, like a half-open connection
+    #[tokio::test]
+    async fn test_gateway_silent_server_deadline() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind failed");
+        let addr = HostAddr {
+            host: "127.0.0.1".to_string(),
+            port: listener.local_addr().expect("msg").port() as u32,
+        };
+
+        let gateway = SafeTaskManagerRpcGateway::with_timeout(
+            &addr,
+            Duration::from_millis(500),
+            Duration::from_secs(1),
+        );
+        let start = Instant::now();
+        let r = gateway.get_sub_dataflow(ResourceId::default()).await;
+        assert!(r.is_err());
+        assert!(start.elapsed() < Duration::from_secs(3));
+
+        let gateway = SafeCoordinatorRpcGateway::with_timeout(&addr, 1, 1);
+        let start = Instant::now();
+        let r = gateway.terminate_dataflow(ResourceId::default()).await;
+        assert!(r.is_err());
+        assert!(start.elapsed() < Duration::from_secs(3));
+
+        drop(listener);
+    }
+
+    /// read the socket until the peer closes it or the timeout elapses. It returns whether the socket is closed
+    async fn wait_closed(socket: &mut tokio::net::TcpStream, timeout: Duration) -> bool {
+        use tokio::io::AsyncReadExt;
+
+        let mut buf = [0; 1024];
+        let read_until_closed = async {
+            loop {
+                match socket.read(&mut buf).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(_) => {}
+                }
+            }
+        };
+        tokio::time::timeout(timeout, read_until_closed)
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_taskmanager_gateway_closed_after_last_clone_dropped() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind failed");
+        let addr = HostAddr {
+            host: "127.0.0.1".to_string(),
+            port: listener.local_addr().expect("msg").port() as u32,
+        };
+
+        let gateway = SafeTaskManagerRpcGateway::with_timeout(
+            &addr,
+            Duration::from_millis(500),
+            Duration::from_millis(200),
+        );
+        let mut cloned = gateway.clone();
+        // the silent server never answers, but the connection is established
+        assert!(gateway
+            .get_sub_dataflow(ResourceId::default())
+            .await
+            .is_err());
+        let (mut socket, _) = tokio::time::timeout(Duration::from_secs(1), listener.accept())
+            .await
+            .expect("no connection")
+            .expect("accept failed");
+
+        // the connection is kept while a clone is alive
+        drop(gateway);
+        assert!(!wait_closed(&mut socket, Duration::from_millis(300)).await);
+        assert!(cloned.get_host_addr().is_valid());
+
+        cloned.close();
+        assert!(!cloned.get_host_addr().is_valid());
+        assert!(wait_closed(&mut socket, Duration::from_secs(3)).await);
+    }
+
+    #[tokio::test]
+    async fn test_taskmanager_gateway_drop_tears_down_connection() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind failed");
+        let addr = HostAddr {
+            host: "127.0.0.1".to_string(),
+            port: listener.local_addr().expect("msg").port() as u32,
+        };
+
+        let gateway = SafeTaskManagerRpcGateway::with_timeout(
+            &addr,
+            Duration::from_millis(500),
+            Duration::from_millis(200),
+        );
+        let clones = (0..3).map(|_| gateway.clone()).collect::<Vec<_>>();
+        assert!(gateway
+            .get_sub_dataflow(ResourceId::default())
+            .await
+            .is_err());
+        let (mut socket, _) = tokio::time::timeout(Duration::from_secs(1), listener.accept())
+            .await
+            .expect("no connection")
+            .expect("accept failed");
+
+        drop(gateway);
+        drop(clones);
+        assert!(wait_closed(&mut socket, Duration::from_secs(3)).await);
+    }
+
+    #[tokio::test]
+    async fn test_gateway_rebuilt_after_reresolution() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind failed");
+        let addr = HostAddr {
+            host: "127.0.0.1".to_string(),
+            port: listener.local_addr().expect("msg").port() as u32,
+        };
+        let old = "10.0.0.1:8792".parse().unwrap();
+        let new = "10.0.0.2:8792".parse().unwrap();
+        let resolver = MockResolver::new(&[old]);
+
+        let gateway = SafeTaskManagerRpcGateway::with_timeout(
+            &addr,
+            Duration::from_millis(500),
+            Duration::from_millis(200),
+        )
+        .with_reresolution(Reresolution::new(
+            std::sync::Arc::new(resolver.clone()),
+            Duration::from_millis(300),
+        ));
+        assert!(gateway
+            .get_sub_dataflow(ResourceId::default())
+            .await
+            .is_err());
+        let (mut socket, _) = tokio::time::timeout(Duration::from_secs(1), listener.accept())
+            .await
+            .expect("no connection")
+            .expect("accept failed");
+
+        // the channel is kept while the host is resolved into the same addresses
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(gateway
+            .get_sub_dataflow(ResourceId::default())
+            .await
+            .is_err());
+        assert!(!wait_closed(&mut socket, Duration::from_millis(300)).await);
+        assert_eq!(resolver.get_resolutions(), 2);
+
+        // the channel is rebuilt once the host is resolved into a new address
+        resolver.set_addrs(&[new]);
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(gateway
+            .get_sub_dataflow(ResourceId::default())
+            .await
+            .is_err());
+        assert!(wait_closed(&mut socket, Duration::from_secs(3)).await);
+        let (_socket, _) = tokio::time::timeout(Duration::from_secs(1), listener.accept())
+            .await
+            .expect("no new connection")
+            .expect("accept failed");
+
+        // the same for the coordinator gateway
+        let resolver = MockResolver::new(&[old]);
+        let gateway = SafeCoordinatorRpcGateway::with_timeout(&addr, 1, 1).with_reresolution(
+            Reresolution::new(
+                std::sync::Arc::new(resolver.clone()),
+                Duration::from_millis(300),
+            ),
+        );
+        assert!(gateway
+            .terminate_dataflow(ResourceId::default())
+            .await
+            .is_err());
+        let (mut coordinator_socket, _) =
+            tokio::time::timeout(Duration::from_secs(1), listener.accept())
+                .await
+                .expect("no connection")
+                .expect("accept failed");
+        resolver.set_addrs(&[new]);
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(gateway
+            .terminate_dataflow(ResourceId::default())
+            .await
+            .is_err());
+        assert!(wait_closed(&mut coordinator_socket, Duration::from_secs(3)).await);
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), listener.accept())
+                .await
+                .is_ok()
+        );
+    }
+}
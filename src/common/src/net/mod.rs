@@ -7,12 +7,14 @@ use std::{
 };
 
 use futures_util::{ready, Future, FutureExt};
-use proto::common::{Ack, Heartbeat, HostAddr, NodeType, SubDataflowId};
+use proto::common::{Ack, Heartbeat, HostAddr, NodeType, OperatorError, Response, SubDataflowId};
 use tokio::sync::mpsc;
 
 use crate::{futures::join_all, types::ExecutorId, utils};
 
-use self::gateway::{ReceiveAckRpcGateway, ReceiveHeartbeatRpcGateway};
+use self::gateway::{
+    ReceiveAckRpcGateway, ReceiveHeartbeatRpcGateway, ReportOperatorErrorRpcGateway,
+};
 
 pub(crate) const DEFAULT_RPC_TIMEOUT: u64 = 3;
 pub(crate) const DEFAULT_CONNECT_TIMEOUT: u64 = 3;
@@ -20,6 +22,7 @@ pub(crate) const DEFAULT_TASKMANAGER_PORT: u16 = 8792;
 pub mod cluster;
 #[cfg(not(tarpaulin_include))]
 pub mod gateway;
+pub mod resolver;
 
 pub fn local(port: usize) -> HostAddr {
     HostAddr {
@@ -145,6 +148,7 @@ impl HeartbeatBuilder {
             execution_id: None,
             current_heartbeat_id: AtomicU64::default(),
             task_id,
+            in_flight: None,
         }
     }
 }
@@ -155,6 +159,9 @@ pub struct HeartbeatSender<T: ReceiveHeartbeatRpcGateway> {
     execution_id: Option<SubDataflowId>,
     current_heartbeat_id: AtomicU64,
     task_id: ExecutorId,
+    /// the heartbeat rpc which is not finished yet. It's polled by the sender instead of being waited in a busy loop,
+    /// otherwise a worker thread of the runtime will be blocked until the rpc finishes
+    in_flight: Option<Pin<Box<dyn Future<Output = Result<Response, tonic::Status>> + Send>>>,
 }
 impl<T: ReceiveHeartbeatRpcGateway> HeartbeatSender<T> {
     pub fn update_execution_id(&mut self, execution_id: SubDataflowId) {
@@ -162,40 +169,50 @@ impl<T: ReceiveHeartbeatRpcGateway> HeartbeatSender<T> {
     }
 }
 
-impl<T: ReceiveHeartbeatRpcGateway> Future for HeartbeatSender<T> {
+impl<T: ReceiveHeartbeatRpcGateway + Clone + Send + Sync + 'static> Future for HeartbeatSender<T> {
     type Output = ();
 
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
-        ready!(Pin::new(&mut this.interval).poll_tick(cx));
-        let now = utils::times::now();
-        tracing::debug!("heartbeat sent at time {:?}", now);
-        let future = this.gateway.receive_heartbeat(Heartbeat {
-            heartbeat_id: this
-                .current_heartbeat_id
-                .fetch_add(1, atomic::Ordering::SeqCst),
-            timestamp: Some(prost_types::Timestamp {
-                seconds: now.timestamp(),
-                nanos: now.timestamp_subsec_nanos() as i32,
-            }),
-            node_type: NodeType::JobManager as i32,
-            subdataflow_id: this.execution_id.clone(),
-            task_id: this.task_id,
-        });
-        join_all(cx, &mut vec![future], |r| match r {
-            Ok(_) => tracing::info!(
-                "heartbeat sent success  [execution_id: {:?}, task_id: {}]",
-                &this.execution_id,
-                this.task_id,
-            ),
-            Err(err) => tracing::error!(
-                "heartbeat sent failed, [execution_id: {:?}, task_id: {}], err: {}",
-                &this.execution_id,
-                this.task_id,
-                err,
-            ),
-        });
-        Poll::Pending
+        loop {
+            if let Some(in_flight) = this.in_flight.as_mut() {
+                let result = ready!(in_flight.as_mut().poll(cx));
+                this.in_flight = None;
+                match result {
+                    Ok(_) => tracing::info!(
+                        "heartbeat sent success  [execution_id: {:?}, task_id: {}]",
+                        &this.execution_id,
+                        this.task_id,
+                    ),
+                    Err(err) => tracing::error!(
+                        "heartbeat sent failed, [execution_id: {:?}, task_id: {}], err: {}",
+                        &this.execution_id,
+                        this.task_id,
+                        err,
+                    ),
+                }
+            }
+
+            ready!(Pin::new(&mut this.interval).poll_tick(cx));
+            let now = utils::times::now();
+            tracing::debug!("heartbeat sent at time {:?}", now);
+            let heartbeat = Heartbeat {
+                heartbeat_id: this
+                    .current_heartbeat_id
+                    .fetch_add(1, atomic::Ordering::SeqCst),
+                timestamp: Some(prost_types::Timestamp {
+                    seconds: now.timestamp(),
+                    nanos: now.timestamp_subsec_nanos() as i32,
+                }),
+                node_type: NodeType::JobManager as i32,
+                subdataflow_id: this.execution_id.clone(),
+                task_id: this.task_id,
+            };
+            let gateway = this.gateway.clone();
+            this.in_flight = Some(Box::pin(async move {
+                gateway.receive_heartbeat(heartbeat).await
+            }));
+        }
     }
 }
 
@@ -232,7 +249,7 @@ impl<T: ReceiveHeartbeatRpcGateway> Future for HeartbeatSender<T> {
 ///         host: "localhost".to_string(),
 ///         port: 8080
 ///     };
-/// 
+///
 ///     let (responder, _) = builder.build(addr, |addr, connect_timeout, rpc_timeout| SafeTaskManagerRpcGateway::with_timeout(addr, connect_timeout, rpc_timeout));
 ///     let _ = tokio::spawn(responder);
 /// }
@@ -252,7 +269,7 @@ impl<T: ReceiveHeartbeatRpcGateway> Future for HeartbeatSender<T> {
 ///         connect_timeout: 3,
 ///         rpc_timeout: 3
 ///     };
-/// 
+///
 ///     let ref addr = HostAddr {
 ///         host: "localhost".to_string(),
 ///         port: 8080
@@ -311,18 +328,71 @@ impl<T: ReceiveAckRpcGateway> Future for AckResponder<T> {
         let mut all_ack_futures = vec![];
 
         loop {
-            match this.recv.poll_recv(cx) {
+            let poll = this.recv.poll_recv(cx);
+            match poll {
                 Poll::Ready(Some(ack)) => {
                     let future = this.gateway.receive_ack(ack.clone());
                     all_ack_futures.push(future);
                 }
-                Poll::Ready(None) => continue,
                 _ => {
                     join_all(cx, &mut all_ack_futures, |r| match r {
                         Ok(_) => tracing::info!("ack success"),
                         Err(status) => tracing::error!("ack failed: {}", status),
                     });
-                    return Poll::Pending;
+                    // AckResponder is finished once all senders are dropped
+                    return match poll {
+                        Poll::Ready(None) => Poll::Ready(()),
+                        _ => Poll::Pending,
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// [OperatorErrorReporter] reports the errors of operators to the remote coordinator.
+///
+/// OperatorErrorReporter::new will return two values:
+/// - a new [OperatorErrorReporter]
+/// - a [mpsc::Sender] channel for [OperatorError] messages. Operators can report an error by sending an [OperatorError] message into it.
+///
+/// [OperatorErrorReporter] implements [Future] and it will be finished once all senders are dropped.
+pub struct OperatorErrorReporter<T: ReportOperatorErrorRpcGateway> {
+    recv: mpsc::Receiver<OperatorError>,
+    gateway: T,
+}
+
+impl<T: ReportOperatorErrorRpcGateway> OperatorErrorReporter<T> {
+    pub fn new(gateway: T, buf_size: usize) -> (Self, mpsc::Sender<OperatorError>) {
+        let (tx, rx) = mpsc::channel(buf_size);
+        (Self { recv: rx, gateway }, tx)
+    }
+}
+
+impl<T: ReportOperatorErrorRpcGateway> Future for OperatorErrorReporter<T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut all_report_futures = vec![];
+
+        loop {
+            let poll = this.recv.poll_recv(cx);
+            match poll {
+                Poll::Ready(Some(err)) => {
+                    all_report_futures.push(this.gateway.report_operator_error(err));
+                    continue;
+                }
+                _ => {
+                    join_all(cx, &mut all_report_futures, |r| match r {
+                        Ok(_) => tracing::info!("report operator error success"),
+                        Err(status) => tracing::error!("report operator error failed: {}", status),
+                    });
+
+                    return match poll {
+                        Poll::Ready(None) => Poll::Ready(()),
+                        _ => Poll::Pending,
+                    };
                 }
             }
         }
@@ -480,6 +550,32 @@ mod tests {
         handler.abort()
     }
 
+    #[tokio::test]
+    async fn test_operator_error_reporter() {
+        use super::OperatorErrorReporter;
+        use proto::common::{OperatorError, OperatorErrorKind};
+
+        let (gateway, _, _) = MockRpcGateway::new(10, 10);
+        let (gateway, mut rx) = gateway.with_operator_error_channel(10);
+
+        let (reporter, tx) = OperatorErrorReporter::new(gateway, 10);
+        let handler = tokio::spawn(reporter);
+
+        let err = OperatorError {
+            job_id: Some(ResourceId::new("namespace_id", "resource_id")),
+            operator_id: 1,
+            kind: OperatorErrorKind::Execution as i32,
+            message: "process event failed".to_string(),
+            timestamp: None,
+        };
+        let result = tx.send(err.clone()).await;
+        assert!(result.is_ok());
+        assert_eq!(rx.recv().await, Some(err));
+
+        drop(tx);
+        assert!(handler.await.is_ok());
+    }
+
     #[tokio::test]
     async fn test_heartbeat_update_execution_id() {
         let builder = HeartbeatBuilder {
@@ -499,19 +595,13 @@ mod tests {
             |_, _, _| gateway.clone(),
         );
         heartbeat.update_execution_id(SubDataflowId {
-            job_id: Some(ResourceId {
-                resource_id: "resource_id".to_string(),
-                namespace_id: "namespace_id".to_string(),
-            }),
+            job_id: Some(ResourceId::new("namespace_id", "resource_id")),
             sub_id: 1,
         });
         assert_eq!(
             heartbeat.execution_id,
             Some(SubDataflowId {
-                job_id: Some(ResourceId {
-                    resource_id: "resource_id".to_string(),
-                    namespace_id: "namespace_id".to_string(),
-                }),
+                job_id: Some(ResourceId::new("namespace_id", "resource_id")),
                 sub_id: 1,
             })
         )
@@ -0,0 +1,224 @@
+use std::{
+    collections::BTreeSet,
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use proto::common::HostAddr;
+use tonic::async_trait;
+
+use crate::{
+    consts::{
+        default_configs::DEFAULT_GATEWAY_RERESOLVE_INTERVAL_MILLIS,
+        env_keys::GATEWAY_RERESOLVE_INTERVAL,
+    },
+    utils::get_env,
+};
+
+/// [`Resolver`] resolves the host of a [`HostAddr`] into socket addresses
+#[async_trait]
+pub trait Resolver: Send + Sync + std::fmt::Debug {
+    async fn resolve(&self, host_addr: &HostAddr) -> io::Result<Vec<SocketAddr>>;
+}
+
+/// [`DnsResolver`] resolves the host by the DNS resolver of the system
+#[derive(Debug, Default)]
+pub struct DnsResolver;
+
+#[async_trait]
+impl Resolver for DnsResolver {
+    async fn resolve(&self, host_addr: &HostAddr) -> io::Result<Vec<SocketAddr>> {
+        tokio::net::lookup_host((host_addr.host.as_str(), host_addr.port as u16))
+            .await
+            .map(|addrs| addrs.collect())
+    }
+}
+
+/// [`MockResolver`] resolves every host into the addresses set by the tests
+#[derive(Debug, Default, Clone)]
+pub struct MockResolver {
+    // the resolutions fail if it's none
+    addrs: Arc<Mutex<Option<Vec<SocketAddr>>>>,
+    resolutions: Arc<AtomicUsize>,
+}
+
+impl MockResolver {
+    pub fn new(addrs: &[SocketAddr]) -> Self {
+        let resolver = Self::default();
+        resolver.set_addrs(addrs);
+        resolver
+    }
+
+    pub fn set_addrs(&self, addrs: &[SocketAddr]) {
+        *self.addrs.lock().unwrap() = Some(addrs.to_vec());
+    }
+
+    /// the following resolutions fail until the addresses are set again
+    pub fn set_failed(&self) {
+        *self.addrs.lock().unwrap() = None;
+    }
+
+    /// how many times the hosts have been resolved
+    pub fn get_resolutions(&self) -> usize {
+        self.resolutions.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl Resolver for MockResolver {
+    async fn resolve(&self, _host_addr: &HostAddr) -> io::Result<Vec<SocketAddr>> {
+        self.resolutions.fetch_add(1, Ordering::SeqCst);
+        self.addrs
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+}
+
+/// [`Reresolution`] re-resolves the host of a gateway periodically.
+///
+/// A channel keeps connecting to the addresses which its host was resolved into when it connected.
+/// If the DNS record of the host changes, e.g. a pod is rescheduled behind a stable DNS name, the gateway should rebuild the channel.
+/// The host is re-resolved at most once per interval, and a failed resolution never changes the addresses.
+#[derive(Debug, Clone)]
+pub struct Reresolution {
+    resolver: Arc<dyn Resolver>,
+    interval: Duration,
+    next_resolution: Option<Instant>,
+    resolved: Option<BTreeSet<SocketAddr>>,
+}
+
+impl Default for Reresolution {
+    /// the host is re-resolved by the DNS resolver of the system, and the interval is taken from the env
+    fn default() -> Self {
+        let interval = get_env(GATEWAY_RERESOLVE_INTERVAL)
+            .and_then(|interval| interval.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_GATEWAY_RERESOLVE_INTERVAL_MILLIS);
+        Self::new(Arc::new(DnsResolver), Duration::from_millis(interval))
+    }
+}
+
+impl Reresolution {
+    /// the re-resolution is disabled if the interval is zero
+    pub fn new(resolver: Arc<dyn Resolver>, interval: Duration) -> Self {
+        Self {
+            resolver,
+            interval,
+            next_resolution: None,
+            resolved: None,
+        }
+    }
+
+    pub fn get_interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// re-resolve the host if the interval has elapsed since the last resolution.
+    /// It returns true if the host is resolved into other addresses than the last successful resolution,
+    /// and the channel connecting to the old addresses should be rebuilt
+    pub async fn is_changed(&mut self, host_addr: &HostAddr, now: Instant) -> bool {
+        if self.interval.is_zero() || matches!(self.next_resolution, Some(next) if now < next) {
+            return false;
+        }
+        self.next_resolution = Some(now + self.interval);
+
+        let resolved = match self.resolver.resolve(host_addr).await {
+            Ok(addrs) if !addrs.is_empty() => addrs.into_iter().collect::<BTreeSet<_>>(),
+            Ok(_) => return false,
+            Err(err) => {
+                tracing::warn!("re-resolve host {} failed: {}", host_addr.as_uri(), err);
+                return false;
+            }
+        };
+        match self.resolved.replace(resolved) {
+            Some(last) => Some(&last) != self.resolved.as_ref(),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use proto::common::HostAddr;
+
+    use super::{MockResolver, Reresolution};
+
+    #[tokio::test]
+    async fn test_reresolution() {
+        let old: SocketAddr = "10.0.0.1:8792".parse().unwrap();
+        let new: SocketAddr = "10.0.0.2:8792".parse().unwrap();
+        let host_addr = HostAddr {
+            host: "worker-0.lightflus".to_string(),
+            port: 8792,
+        };
+        let resolver = MockResolver::new(&[old]);
+        let mut reresolution =
+            Reresolution::new(Arc::new(resolver.clone()), Duration::from_secs(10));
+
+        // the first resolution is the addresses which the channel connects to
+        let now = Instant::now();
+        assert!(!reresolution.is_changed(&host_addr, now).await);
+        assert!(
+            !reresolution
+                .is_changed(&host_addr, now + Duration::from_secs(10))
+                .await
+        );
+        assert_eq!(resolver.get_resolutions(), 2);
+
+        // the host is re-resolved at most once per interval
+        resolver.set_addrs(&[new]);
+        assert!(
+            !reresolution
+                .is_changed(&host_addr, now + Duration::from_secs(15))
+                .await
+        );
+        assert_eq!(resolver.get_resolutions(), 2);
+        assert!(
+            reresolution
+                .is_changed(&host_addr, now + Duration::from_secs(20))
+                .await
+        );
+        assert!(
+            !reresolution
+                .is_changed(&host_addr, now + Duration::from_secs(30))
+                .await
+        );
+
+        // failed resolutions keep the addresses
+        resolver.set_failed();
+        assert!(
+            !reresolution
+                .is_changed(&host_addr, now + Duration::from_secs(40))
+                .await
+        );
+        resolver.set_addrs(&[old, new]);
+        assert!(
+            reresolution
+                .is_changed(&host_addr, now + Duration::from_secs(50))
+                .await
+        );
+        resolver.set_addrs(&[new, old]);
+        assert!(
+            !reresolution
+                .is_changed(&host_addr, now + Duration::from_secs(60))
+                .await
+        );
+
+        // the re-resolution is disabled by the zero interval
+        let mut reresolution = Reresolution::new(Arc::new(resolver.clone()), Duration::ZERO);
+        assert!(!reresolution.is_changed(&host_addr, now).await);
+        assert_eq!(resolver.get_resolutions(), 7);
+    }
+}
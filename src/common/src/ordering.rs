@@ -0,0 +1,400 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use prost::Message;
+use proto::common::KeyedDataEvent;
+
+use crate::{types::ExecutorId, utils::times::now_timestamp};
+
+pub type SharedSequencer = Arc<Mutex<Sequencer>>;
+
+/// The ordering contract of Lightflus: within a (sending operator, key) pair, events are delivered to the downstream operator in send order.
+///
+/// - an operator sends its events one batch after another, and a failed batch is retried as a whole before the next one is sent
+/// - [`Sequencer`] numbers the events sent by an operator per key
+/// - [`IngressOrdering`] drops the events whose sequences are not increasing at the ingress of the receiver,
+/// so a late copy of a retried batch can't be delivered after the events sent after it
+///
+/// Sequences can have gaps because events can be skipped or dead-lettered by the error policy of the sender.
+#[derive(Debug)]
+pub struct Sequencer {
+    epoch: u64,
+    // the run of the operator in the epoch, 0 until the first run begins
+    run: u64,
+    sequences: HashMap<Vec<u8>, u64>,
+}
+
+impl Sequencer {
+    pub fn new(epoch: u64) -> Self {
+        Self {
+            epoch,
+            run: 0,
+            sequences: Default::default(),
+        }
+    }
+
+    /// the epoch of a new sequencer is its creation time, so that it's newer than the epochs before a restart
+    pub fn new_shared() -> SharedSequencer {
+        Arc::new(Mutex::new(Self::new(now_timestamp() as u64)))
+    }
+
+    /// number the event as the next one of its key sent by the operator `from_operator_id`
+    pub fn stamp(&mut self, from_operator_id: ExecutorId, event: &mut KeyedDataEvent) {
+        let sequence = self
+            .sequences
+            .entry(event.get_key().encode_to_vec())
+            .or_default();
+        *sequence += 1;
+        event.from_operator_id = from_operator_id;
+        event.sequence = *sequence;
+        event.sequence_epoch = self.epoch;
+        event.restart_epoch = self.run;
+    }
+
+    /// a new run of the operator begins, i.e. its executor is created or restarted. The events sent later supersede the output of the former runs
+    pub fn begin_run(&mut self) -> u64 {
+        self.run += 1;
+        self.run
+    }
+}
+
+/// the result of [`RestartFence::observe`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fencing {
+    /// the event is sent by the latest run of its operator, or it's not fenced
+    Current,
+    /// the event is the first one of a new run of its operator, so the output of the former runs is superseded
+    NewRun,
+    /// the event is sent by a run which has been superseded
+    Superseded,
+}
+
+/// [`RestartFence`] keeps the latest run of each sending operator seen by a sink.
+///
+/// Once an operator restarts, the output of its former runs which isn't flushed by the sink yet may be partial, or be sent again by the new run.
+/// The sink discards it once the new run is observed, and the events of the former runs arriving later are dropped.
+/// Runs are ordered by the epoch of the sequence first, so a run after the TaskWorker restarts is newer though it starts from 1 again.
+#[derive(Debug, Default)]
+pub struct RestartFence {
+    runs: HashMap<ExecutorId, (u64, u64)>,
+}
+
+pub type SharedRestartFence = Arc<Mutex<RestartFence>>;
+
+impl RestartFence {
+    pub fn observe(&mut self, event: &KeyedDataEvent) -> Fencing {
+        if event.restart_epoch == 0 {
+            return Fencing::Current;
+        }
+        let current = (event.sequence_epoch, event.restart_epoch);
+        match self.runs.get_mut(&event.from_operator_id) {
+            Some(latest) if current > *latest => {
+                *latest = current;
+                Fencing::NewRun
+            }
+            Some(latest) if current < *latest => Fencing::Superseded,
+            Some(_) => Fencing::Current,
+            None => {
+                self.runs.insert(event.from_operator_id, current);
+                Fencing::Current
+            }
+        }
+    }
+}
+
+/// [`IngressOrdering`] keeps the last accepted sequence of each (sending operator, key) pair.
+///
+/// An event is accepted if its sequence is greater than the last accepted one in the same epoch, or its epoch is newer.
+/// Events which are not sequenced are always accepted.
+#[derive(Debug, Default)]
+pub struct IngressOrdering {
+    accepted: HashMap<(ExecutorId, Vec<u8>), (u64, u64)>,
+}
+
+impl IngressOrdering {
+    /// keep the events which are in order, including the ones following an accepted event of the same key in the batch.
+    /// It returns the number of dropped events. The events are not accepted until they are committed
+    pub fn retain_in_order(
+        &self,
+        from_operator_id: ExecutorId,
+        events: &mut Vec<KeyedDataEvent>,
+    ) -> usize {
+        let mut pending = HashMap::new();
+        let len = events.len();
+        events.retain(|event| {
+            if event.sequence == 0 {
+                return true;
+            }
+            let key = (from_operator_id, event.get_key().encode_to_vec());
+            let last = pending
+                .get(&key)
+                .or_else(|| self.accepted.get(&key))
+                .copied();
+            let current = (event.sequence_epoch, event.sequence);
+            if last.iter().all(|last| current > *last) {
+                pending.insert(key, current);
+                true
+            } else {
+                false
+            }
+        });
+        len - events.len()
+    }
+
+    /// accept the events which have been delivered to the operator
+    pub fn commit(&mut self, from_operator_id: ExecutorId, events: &[KeyedDataEvent]) {
+        events
+            .iter()
+            .filter(|event| event.sequence > 0)
+            .for_each(|event| {
+                let current = (event.sequence_epoch, event.sequence);
+                self.accepted
+                    .entry((from_operator_id, event.get_key().encode_to_vec()))
+                    .and_modify(|last| *last = current.max(*last))
+                    .or_insert(current);
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use proto::common::{Entry, KeyedDataEvent};
+    use rand::Rng;
+
+    use super::{Fencing, IngressOrdering, RestartFence, Sequencer};
+
+    fn new_event(key: &str, event_id: i64) -> KeyedDataEvent {
+        KeyedDataEvent {
+            key: Some(Entry {
+                value: key.as_bytes().to_vec().into(),
+                ..Default::default()
+            }),
+            event_id,
+            ..Default::default()
+        }
+    }
+
+    fn get_sequences(events: &[KeyedDataEvent]) -> Vec<u64> {
+        events.iter().map(|event| event.sequence).collect()
+    }
+
+    #[test]
+    fn test_sequencer_numbers_events_per_key() {
+        let mut sequencer = Sequencer::new(7);
+        let mut events = ["a", "b", "a", "a", "b"]
+            .into_iter()
+            .enumerate()
+            .map(|(event_id, key)| new_event(key, event_id as i64))
+            .collect::<Vec<_>>();
+        events
+            .iter_mut()
+            .for_each(|event| sequencer.stamp(3, event));
+
+        assert_eq!(get_sequences(&events), vec![1, 1, 2, 3, 2]);
+        assert!(events
+            .iter()
+            .all(|event| event.sequence_epoch == 7 && event.from_operator_id == 3));
+    }
+
+    #[test]
+    fn test_ingress_drops_stale_events() {
+        let mut sequencer = Sequencer::new(1);
+        let mut events = (0..4)
+            .map(|event_id| new_event(if event_id % 2 == 0 { "a" } else { "b" }, event_id))
+            .collect::<Vec<_>>();
+        events
+            .iter_mut()
+            .for_each(|event| sequencer.stamp(1, event));
+
+        let mut ordering = IngressOrdering::default();
+        let mut batch = events.clone();
+        assert_eq!(ordering.retain_in_order(1, &mut batch), 0);
+        ordering.commit(1, &batch);
+
+        // a retried copy of the batch is dropped
+        let mut batch = events.clone();
+        assert_eq!(ordering.retain_in_order(1, &mut batch), 4);
+        assert!(batch.is_empty());
+
+        // the same sequences of another sender are accepted
+        let mut batch = events.clone();
+        assert_eq!(ordering.retain_in_order(2, &mut batch), 0);
+
+        // an older event of a key is dropped, but the newer events of the same batch are kept
+        let mut newer = new_event("a", 4);
+        sequencer.stamp(1, &mut newer);
+        let mut batch = vec![events[0].clone(), newer.clone(), events[1].clone()];
+        assert_eq!(ordering.retain_in_order(1, &mut batch), 2);
+        assert_eq!(batch, vec![newer.clone()]);
+        // events which are not committed are not accepted
+        let mut batch = vec![newer.clone()];
+        assert_eq!(ordering.retain_in_order(1, &mut batch), 0);
+
+        // a restarted sender starts over in a new epoch
+        let mut sequencer = Sequencer::new(2);
+        let mut restarted = new_event("a", 5);
+        sequencer.stamp(1, &mut restarted);
+        assert_eq!(restarted.sequence, 1);
+        let mut batch = vec![restarted];
+        assert_eq!(ordering.retain_in_order(1, &mut batch), 0);
+
+        // events which are not sequenced are always accepted
+        let mut batch = vec![new_event("a", 6), new_event("a", 6)];
+        assert_eq!(ordering.retain_in_order(1, &mut batch), 0);
+    }
+
+    #[test]
+    fn test_restart_fence_drops_superseded_runs() {
+        let mut sequencer = Sequencer::new(1);
+        let mut fence = RestartFence::default();
+        let stamp = |sequencer: &mut Sequencer, event_id| {
+            let mut event = new_event("a", event_id);
+            sequencer.stamp(1, &mut event);
+            event
+        };
+
+        // events sent before the first run are not fenced
+        assert_eq!(fence.observe(&stamp(&mut sequencer, 0)), Fencing::Current);
+
+        assert_eq!(sequencer.begin_run(), 1);
+        let first_run = stamp(&mut sequencer, 1);
+        assert_eq!(first_run.restart_epoch, 1);
+        assert_eq!(fence.observe(&first_run), Fencing::Current);
+        assert_eq!(fence.observe(&first_run), Fencing::Current);
+
+        // the operator restarts
+        assert_eq!(sequencer.begin_run(), 2);
+        assert_eq!(fence.observe(&stamp(&mut sequencer, 2)), Fencing::NewRun);
+        assert_eq!(fence.observe(&stamp(&mut sequencer, 3)), Fencing::Current);
+        assert_eq!(fence.observe(&first_run), Fencing::Superseded);
+
+        // the runs of another operator are fenced apart
+        let mut other = first_run.clone();
+        other.from_operator_id = 2;
+        assert_eq!(fence.observe(&other), Fencing::Current);
+
+        // the runs start over after the TaskWorker restarts, but they are newer
+        let mut sequencer = Sequencer::new(2);
+        sequencer.begin_run();
+        assert_eq!(fence.observe(&stamp(&mut sequencer, 4)), Fencing::NewRun);
+    }
+
+    /// Two workers send events of interleaved keys through a gateway with random faults:
+    /// - the request fails before it's delivered
+    /// - the request is delivered but the response is lost, so it's retried
+    /// - the request is delivered late, after the sender has given up on it
+    ///
+    /// Each batch is retried until it's acknowledged, so every event should reach the sink exactly once and in the send order of its key.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_per_key_order_under_faults() {
+        const WORKERS: u32 = 2;
+        const BATCHES: i64 = 200;
+        const KEYS: [&str; 3] = ["a", "b", "c"];
+
+        // the ingress of the receiver and the events received by the sink
+        let receiver = Arc::new(tokio::sync::Mutex::new((
+            IngressOrdering::default(),
+            vec![],
+        )));
+        let faults = Arc::new(AtomicUsize::new(0));
+
+        async fn deliver(
+            receiver: &tokio::sync::Mutex<(IngressOrdering, Vec<KeyedDataEvent>)>,
+            from_operator_id: u32,
+            mut events: Vec<KeyedDataEvent>,
+        ) {
+            let mut guard = receiver.lock().await;
+            let (ordering, sink) = &mut *guard;
+            ordering.retain_in_order(from_operator_id, &mut events);
+            ordering.commit(from_operator_id, &events);
+            sink.extend(events);
+        }
+
+        let workers = (1..=WORKERS)
+            .map(|worker| {
+                let receiver = receiver.clone();
+                let faults = faults.clone();
+                tokio::spawn(async move {
+                    let mut sequencer = Sequencer::new(1);
+                    let mut late_deliveries = vec![];
+                    let mut sent = 0;
+                    for batch in 0..BATCHES {
+                        let mut events = (0..rand::thread_rng().gen_range(1..4))
+                            .map(|index| {
+                                let key = KEYS[rand::thread_rng().gen_range(0..KEYS.len())];
+                                new_event(key, batch * 10 + index)
+                            })
+                            .collect::<Vec<_>>();
+                        events
+                            .iter_mut()
+                            .for_each(|event| sequencer.stamp(worker, event));
+                        sent += events.len();
+
+                        loop {
+                            let fault = rand::thread_rng().gen_range(0..4);
+                            match fault {
+                                0 => {}
+                                1 => deliver(&receiver, worker, events.clone()).await,
+                                2 => {
+                                    let receiver = receiver.clone();
+                                    let events = events.clone();
+                                    let delay = rand::thread_rng().gen_range(0..3);
+                                    late_deliveries.push(tokio::spawn(async move {
+                                        tokio::time::sleep(Duration::from_millis(delay)).await;
+                                        deliver(&receiver, worker, events).await
+                                    }));
+                                }
+                                _ => {
+                                    deliver(&receiver, worker, events.clone()).await;
+                                    break;
+                                }
+                            }
+                            faults.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    for late_delivery in late_deliveries {
+                        late_delivery.await.unwrap();
+                    }
+                    sent
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut sent = 0;
+        for worker in workers {
+            sent += worker.await.unwrap();
+        }
+        assert!(faults.load(Ordering::Relaxed) > 0);
+
+        let guard = receiver.lock().await;
+        let mut received = HashMap::<_, Vec<_>>::new();
+        guard.1.iter().for_each(|event| {
+            received
+                .entry((event.from_operator_id, event.get_key().value))
+                .or_default()
+                .push(event.event_id)
+        });
+        for ((worker, key), event_ids) in received {
+            let mut sorted = event_ids.clone();
+            sorted.sort();
+            sorted.dedup();
+            assert_eq!(
+                event_ids, sorted,
+                "events of key {:?} from worker {} are reordered or duplicated",
+                key, worker
+            );
+        }
+        // every event is delivered exactly once
+        assert_eq!(guard.1.len(), sent);
+    }
+}
@@ -0,0 +1,441 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+use proto::{
+    common::{DataTypeEnum, Project},
+    common_impl::DataflowValidateError,
+    json_path::{JsonPath, PathSegment},
+};
+
+use crate::types::TypedValue;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectError {
+    /// the configuration of project operator is invalid
+    InvalidProject(String),
+    /// the projected value can not be cast into the target type
+    CastFailed {
+        field: String,
+        value: String,
+        cast: DataTypeEnum,
+    },
+}
+
+impl Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::InvalidProject(msg) => write!(f, "invalid project operator: {}", msg),
+            ProjectError::CastFailed { field, value, cast } => write!(
+                f,
+                "value [{}] of field [{}] can not be cast into {:?}",
+                value, field, cast
+            ),
+        }
+    }
+}
+
+impl From<DataflowValidateError> for ProjectError {
+    fn from(err: DataflowValidateError) -> Self {
+        match err {
+            DataflowValidateError::InvalidProject(msg) => Self::InvalidProject(msg),
+            _ => Self::InvalidProject(format!("{:?}", err)),
+        }
+    }
+}
+
+/// the result of selecting a path over a payload
+enum Selected<'a> {
+    Value(&'a TypedValue),
+    Missing,
+    /// an existence filter of the path doesn't match
+    Filtered,
+}
+
+struct ProjectedField {
+    name: String,
+    path: JsonPath,
+    cast: DataTypeEnum,
+    default_value: Option<TypedValue>,
+}
+
+/// [`Projector`] builds a new payload which only contains the projected fields. It's the runtime of the `Project` operator.
+///
+/// For each projected field:
+/// - if any existence filter of the path doesn't match, the whole payload is dropped
+/// - if the path is missing or null, the default value is used. Otherwise the field will be null
+/// - the value is cast into the target type if it's specified
+pub struct Projector {
+    fields: Vec<ProjectedField>,
+    /// index of the field which the event key is re-derived from
+    key_field: Option<usize>,
+}
+
+impl Projector {
+    pub fn new(project: &Project) -> Result<Self, ProjectError> {
+        let mut fields = vec![];
+        for field in &project.fields {
+            fields.push(ProjectedField {
+                name: field.name.clone(),
+                path: field.get_path()?,
+                cast: field.cast(),
+                default_value: field.get_default_value()?.map(TypedValue::from_json_value),
+            })
+        }
+        let key_field = fields
+            .iter()
+            .position(|field| !project.key_field.is_empty() && field.name == project.key_field);
+
+        Ok(Self { fields, key_field })
+    }
+
+    /// project a payload. it returns `None` if the payload is dropped by existence filters.
+    /// Otherwise it returns the new payload and the new key if the key is re-derived.
+    pub fn project(
+        &self,
+        payload: &TypedValue,
+    ) -> Result<Option<(TypedValue, Option<TypedValue>)>, ProjectError> {
+        let mut projected = BTreeMap::new();
+        let mut key = None;
+        for (index, field) in self.fields.iter().enumerate() {
+            let value = match select(payload, &field.path.segments) {
+                Selected::Filtered => return Ok(None),
+                Selected::Value(value) if !is_absent(value) => value.clone(),
+                _ => field.default_value.clone().unwrap_or(TypedValue::Null),
+            };
+            let value = cast(&value, field.cast).ok_or_else(|| ProjectError::CastFailed {
+                field: field.name.clone(),
+                value: value.to_json_value().to_string(),
+                cast: field.cast,
+            })?;
+
+            if self.key_field == Some(index) {
+                key = Some(value.clone());
+            }
+            projected.insert(field.name.clone(), value);
+        }
+
+        Ok(Some((TypedValue::Object(projected), key)))
+    }
+}
+
+fn is_absent(value: &TypedValue) -> bool {
+    matches!(value, TypedValue::Null | TypedValue::Invalid)
+}
+
+/// select the value of a path over a payload. It returns `None` if the path is missing or null, or any existence filter of the path doesn't match
+pub fn select_path<'a>(payload: &'a TypedValue, path: &JsonPath) -> Option<&'a TypedValue> {
+    match select(payload, &path.segments) {
+        Selected::Value(value) if !is_absent(value) => Some(value),
+        _ => None,
+    }
+}
+
+fn select<'a>(value: &'a TypedValue, segments: &[PathSegment]) -> Selected<'a> {
+    let mut current = value;
+    for (index, segment) in segments.iter().enumerate() {
+        let next = match (segment, current) {
+            (PathSegment::Field(name), TypedValue::Object(object)) => object.get(name),
+            (PathSegment::Index(index), TypedValue::Array(array)) => array.get(*index),
+            (PathSegment::Exists(path), _) => match select(current, &path.segments) {
+                Selected::Value(value) if !is_absent(value) => Some(current),
+                _ => return Selected::Filtered,
+            },
+            _ => None,
+        };
+        match next {
+            Some(value) => current = value,
+            None => {
+                // the remaining existence filters can never match if the path is missing
+                return if segments[index..]
+                    .iter()
+                    .any(|segment| matches!(segment, PathSegment::Exists(_)))
+                {
+                    Selected::Filtered
+                } else {
+                    Selected::Missing
+                };
+            }
+        }
+    }
+
+    Selected::Value(current)
+}
+
+/// cast a value into the target type. null is never cast and `None` means the cast fails.
+fn cast(value: &TypedValue, target: DataTypeEnum) -> Option<TypedValue> {
+    if is_absent(value) {
+        return Some(TypedValue::Null);
+    }
+
+    match target {
+        DataTypeEnum::Unspecified => Some(value.clone()),
+        DataTypeEnum::String => match value {
+            TypedValue::String(v) => Some(TypedValue::String(v.clone())),
+            TypedValue::BigInt(v) => Some(TypedValue::String(v.to_string())),
+            TypedValue::Number(v) => Some(TypedValue::String(v.to_string())),
+            TypedValue::Boolean(v) => Some(TypedValue::String(v.to_string())),
+            _ => Some(TypedValue::String(value.to_json_value().to_string())),
+        },
+        DataTypeEnum::Bigint => match value {
+            TypedValue::BigInt(v) => Some(TypedValue::BigInt(*v)),
+            TypedValue::Number(v)
+                if v.fract() == 0.0 && *v >= i64::MIN as f64 && *v <= i64::MAX as f64 =>
+            {
+                Some(TypedValue::BigInt(*v as i64))
+            }
+            TypedValue::String(v) => v.trim().parse().ok().map(TypedValue::BigInt),
+            TypedValue::Boolean(v) => Some(TypedValue::BigInt(*v as i64)),
+            _ => None,
+        },
+        DataTypeEnum::Number => match value {
+            TypedValue::BigInt(v) => Some(TypedValue::Number(*v as f64)),
+            TypedValue::Number(v) => Some(TypedValue::Number(*v)),
+            TypedValue::String(v) => v.trim().parse().ok().map(TypedValue::Number),
+            TypedValue::Boolean(v) => Some(TypedValue::Number(*v as i64 as f64)),
+            _ => None,
+        },
+        DataTypeEnum::Boolean => match value {
+            TypedValue::Boolean(v) => Some(TypedValue::Boolean(*v)),
+            TypedValue::String(v) => match v.trim() {
+                "true" => Some(TypedValue::Boolean(true)),
+                "false" => Some(TypedValue::Boolean(false)),
+                _ => None,
+            },
+            TypedValue::BigInt(0) => Some(TypedValue::Boolean(false)),
+            TypedValue::BigInt(1) => Some(TypedValue::Boolean(true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use proto::{
+        common::{
+            operator_info::Details, project::Field, DataTypeEnum, Dataflow, DataflowMeta,
+            OperatorInfo, Project, ResourceId,
+        },
+        common_impl::DataflowValidateError,
+    };
+
+    use crate::types::TypedValue;
+
+    use super::{ProjectError, Projector};
+
+    fn new_field(name: &str, path: &str, cast: DataTypeEnum, default_value: Option<&str>) -> Field {
+        Field {
+            name: name.to_string(),
+            path: path.to_string(),
+            cast: cast as i32,
+            default_value: default_value.map(|value| value.to_string()),
+        }
+    }
+
+    fn new_payload() -> TypedValue {
+        TypedValue::from_json_value(serde_json::json!({
+            "user": {
+                "id": "42",
+                "name": "lightflus",
+                "email": "lightflus@example.com",
+                "tags": ["admin", "dev"],
+            },
+            "orders": [
+                {"amount": 10.5, "paid": "true"},
+                {"amount": 3, "coupon": null},
+            ],
+            "first name": "light",
+        }))
+    }
+
+    fn new_object(fields: Vec<(&str, TypedValue)>) -> TypedValue {
+        TypedValue::Object(BTreeMap::from_iter(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value)),
+        ))
+    }
+
+    #[test]
+    fn test_project_fields() {
+        let projector = Projector::new(&Project {
+            fields: vec![
+                new_field("id", "$.user.id", DataTypeEnum::Bigint, None),
+                new_field("tag", "$.user.tags[1]", DataTypeEnum::Unspecified, None),
+                new_field("amount", "$.orders[1].amount", DataTypeEnum::Number, None),
+                new_field("paid", "$.orders[0].paid", DataTypeEnum::Boolean, None),
+                new_field("first_name", "$['first name']", DataTypeEnum::String, None),
+                new_field(
+                    "coupon",
+                    "$.orders[1].coupon",
+                    DataTypeEnum::String,
+                    Some("\"none\""),
+                ),
+                new_field("region", "$.user.region", DataTypeEnum::Unspecified, None),
+            ],
+            key_field: "id".to_string(),
+        })
+        .expect("valid project");
+
+        let result = projector.project(&new_payload());
+        assert_eq!(
+            result,
+            Ok(Some((
+                new_object(vec![
+                    ("id", TypedValue::BigInt(42)),
+                    ("tag", TypedValue::String("dev".to_string())),
+                    ("amount", TypedValue::Number(3.0)),
+                    ("paid", TypedValue::Boolean(true)),
+                    ("first_name", TypedValue::String("light".to_string())),
+                    ("coupon", TypedValue::String("none".to_string())),
+                    ("region", TypedValue::Null),
+                ]),
+                Some(TypedValue::BigInt(42))
+            )))
+        );
+    }
+
+    #[test]
+    fn test_project_existence_filter() {
+        let projector = Projector::new(&Project {
+            fields: vec![new_field(
+                "name",
+                "$.user?(@.email).name",
+                DataTypeEnum::Unspecified,
+                None,
+            )],
+            key_field: Default::default(),
+        })
+        .expect("valid project");
+
+        assert_eq!(
+            projector.project(&new_payload()),
+            Ok(Some((
+                new_object(vec![("name", TypedValue::String("lightflus".to_string()))]),
+                None
+            )))
+        );
+
+        let projector = Projector::new(&Project {
+            fields: vec![
+                new_field("name", "$.user.name", DataTypeEnum::Unspecified, None),
+                new_field(
+                    "coupon",
+                    "$.orders[1]?(@.coupon)",
+                    DataTypeEnum::Unspecified,
+                    None,
+                ),
+            ],
+            key_field: Default::default(),
+        })
+        .expect("valid project");
+        assert_eq!(projector.project(&new_payload()), Ok(None));
+
+        let projector = Projector::new(&Project {
+            fields: vec![new_field(
+                "phone",
+                "$.contact?(@.phone)",
+                DataTypeEnum::Unspecified,
+                Some("\"unknown\""),
+            )],
+            key_field: Default::default(),
+        })
+        .expect("valid project");
+        assert_eq!(projector.project(&new_payload()), Ok(None));
+    }
+
+    #[test]
+    fn test_project_cast_failed() {
+        let projector = Projector::new(&Project {
+            fields: vec![new_field("name", "$.user.name", DataTypeEnum::Bigint, None)],
+            key_field: Default::default(),
+        })
+        .expect("valid project");
+
+        assert_eq!(
+            projector.project(&new_payload()),
+            Err(ProjectError::CastFailed {
+                field: "name".to_string(),
+                value: "\"lightflus\"".to_string(),
+                cast: DataTypeEnum::Bigint
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_project() {
+        let new_dataflow = |project: Project| {
+            let mut nodes = HashMap::new();
+            nodes.insert(
+                0,
+                OperatorInfo {
+                    operator_id: 0,
+                    details: Some(Details::Project(project)),
+                    ..Default::default()
+                },
+            );
+            Dataflow {
+                job_id: Some(ResourceId::default()),
+                meta: vec![DataflowMeta {
+                    center: 0,
+                    neighbors: vec![],
+                    edge_types: Default::default(),
+                }],
+                nodes,
+                ..Default::default()
+            }
+        };
+
+        let valid = Project {
+            fields: vec![new_field(
+                "id",
+                "$.user.id",
+                DataTypeEnum::Bigint,
+                Some("0"),
+            )],
+            key_field: "id".to_string(),
+        };
+        assert!(new_dataflow(valid.clone()).validate().is_ok());
+
+        for invalid in [
+            Project::default(),
+            Project {
+                fields: vec![new_field("id", "$.user.", DataTypeEnum::Unspecified, None)],
+                ..valid.clone()
+            },
+            Project {
+                fields: vec![new_field("id", "$.user.id", DataTypeEnum::Object, None)],
+                ..valid.clone()
+            },
+            Project {
+                fields: vec![new_field(
+                    "id",
+                    "$.user.id",
+                    DataTypeEnum::Bigint,
+                    Some("zero"),
+                )],
+                ..valid.clone()
+            },
+            Project {
+                fields: vec![
+                    new_field("id", "$.user.id", DataTypeEnum::Unspecified, None),
+                    new_field("id", "$.user.name", DataTypeEnum::Unspecified, None),
+                ],
+                ..valid.clone()
+            },
+            Project {
+                key_field: "name".to_string(),
+                ..valid.clone()
+            },
+        ] {
+            match new_dataflow(invalid.clone()).validate() {
+                Err(DataflowValidateError::InvalidProject(_)) => {}
+                result => panic!("{:?} should be invalid but got {:?}", invalid, result),
+            }
+        }
+    }
+}
@@ -40,8 +40,8 @@ impl RedisClient {
     ) -> Result<(), RedisException> {
         self.connect()?;
         let conn = self.inner.as_mut().unwrap();
-        conn.set(key, value)
-            .map_err(|err| RedisException::SetValueFailed(format!("{}", err)))
+        let result = conn.set(key, value);
+        self.to_exception(result, RedisException::SetValueFailed)
     }
 
     pub fn set_multiple<K: ToRedisArgs, V: ToRedisArgs>(
@@ -51,22 +51,43 @@ impl RedisClient {
         self.connect()?;
 
         let conn = self.inner.as_mut().unwrap();
-        conn.set_multiple(items)
-            .map_err(|err| RedisException::SetMultipleValueFailed(format!("{}", err)))
+        let result = conn.set_multiple(items);
+        self.to_exception(result, RedisException::SetMultipleValueFailed)
     }
 
     pub fn get<K: ToRedisArgs>(&mut self, key: &K) -> Result<Vec<u8>, RedisException> {
         self.connect()?;
         let conn = self.inner.as_mut().unwrap();
-        conn.get(key)
-            .map_err(|err| RedisException::GetValueFailed(format!("{}", err)))
+        let result = conn.get(key);
+        self.to_exception(result, RedisException::GetValueFailed)
     }
 
     pub fn del<K: ToRedisArgs>(&mut self, key: &K) -> Result<(), RedisException> {
         self.connect()?;
         let conn = self.inner.as_mut().unwrap();
-        conn.del(key)
-            .map_err(|err| RedisException::DelValueFailed(format!("{}", err)))
+        let result = conn.del(key);
+        self.to_exception(result, RedisException::DelValueFailed)
+    }
+
+    /// Failures of the connection are reported as [`RedisException::ConnectFailed`] and the broken connection is dropped,
+    /// so that the next command will reconnect.
+    fn to_exception<T>(
+        &mut self,
+        result: redis::RedisResult<T>,
+        command_failed: fn(String) -> RedisException,
+    ) -> Result<T, RedisException> {
+        result.map_err(|err| {
+            if err.is_io_error()
+                || err.is_timeout()
+                || err.is_connection_dropped()
+                || err.is_connection_refusal()
+            {
+                self.inner = None;
+                RedisException::ConnectFailed(format!("{}", err))
+            } else {
+                command_failed(format!("{}", err))
+            }
+        })
     }
 }
 
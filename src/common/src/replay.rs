@@ -0,0 +1,155 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use proto::common::KeyedDataEvent;
+
+/// metric of the events replayed from [`ReplayBuffer`] after a source restarts
+pub const REPLAYED_EVENTS_METRIC: &str = "source.replay_buffer.replayed";
+
+pub type SharedReplayBuffer = Arc<Mutex<ReplayBuffer>>;
+
+/// where a restarted source should resume from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Replay {
+    /// the source restarts shortly, the buffered events should be emitted again before the new ones
+    Buffer(Vec<KeyedDataEvent>),
+    /// the buffer can't cover the restart, the source resumes from its committed offset
+    Source,
+}
+
+/// [`ReplayBuffer`] keeps the latest events emitted by a source in memory so that a source restarted shortly
+/// can replay them instead of re-reading the external source from its last committed offset.
+///
+/// It's a best-effort optimization bounded by memory:
+/// - at most `capacity` events are kept. The oldest events are evicted once it's exceeded
+/// - the buffer only covers a restart within `max_downtime` since the last emitted event
+///
+/// Lightflus doesn't acknowledge single events yet, so the events are kept until they are evicted.
+#[derive(Debug)]
+pub struct ReplayBuffer {
+    capacity: usize,
+    max_downtime: Duration,
+    events: VecDeque<KeyedDataEvent>,
+    last_emitted: Option<Instant>,
+}
+
+impl ReplayBuffer {
+    pub fn new(capacity: usize, max_downtime: Duration) -> Self {
+        Self {
+            capacity,
+            max_downtime,
+            events: VecDeque::with_capacity(capacity),
+            last_emitted: None,
+        }
+    }
+
+    pub fn new_shared(capacity: usize, max_downtime: Duration) -> SharedReplayBuffer {
+        Arc::new(Mutex::new(Self::new(capacity, max_downtime)))
+    }
+
+    /// record an event emitted by the source. The oldest event will be evicted if the buffer is full
+    pub fn push(&mut self, event: &KeyedDataEvent, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+        self.last_emitted = Some(now);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// decide where a restarted source should resume from. The buffer is always cleared because
+    /// the replayed events will be recorded again once they are emitted.
+    pub fn replay(&mut self, now: Instant) -> Replay {
+        let last_emitted = self.last_emitted.take();
+        let events = std::mem::take(&mut self.events);
+        match last_emitted {
+            Some(last_emitted)
+                if !events.is_empty()
+                    && now.saturating_duration_since(last_emitted) <= self.max_downtime =>
+            {
+                Replay::Buffer(events.into())
+            }
+            _ => Replay::Source,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use proto::common::{KeyedDataEvent, ResourceId};
+
+    use super::{Replay, ReplayBuffer};
+
+    fn new_event(event_id: i64) -> KeyedDataEvent {
+        KeyedDataEvent {
+            job_id: Some(ResourceId::default()),
+            event_id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_short_restart_replays_from_buffer() {
+        let now = Instant::now();
+        let mut buffer = ReplayBuffer::new(10, Duration::from_secs(5));
+        (0..3).for_each(|event_id| buffer.push(&new_event(event_id), now));
+        assert_eq!(buffer.len(), 3);
+
+        assert_eq!(
+            buffer.replay(now + Duration::from_secs(1)),
+            Replay::Buffer((0..3).map(new_event).collect())
+        );
+        assert!(buffer.is_empty());
+        // the replayed events have been taken
+        assert_eq!(buffer.replay(now + Duration::from_secs(1)), Replay::Source);
+    }
+
+    #[test]
+    fn test_long_restart_resumes_from_source() {
+        let now = Instant::now();
+        let mut buffer = ReplayBuffer::new(10, Duration::from_secs(5));
+        (0..3).for_each(|event_id| buffer.push(&new_event(event_id), now));
+
+        assert_eq!(buffer.replay(now + Duration::from_secs(6)), Replay::Source);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_replay_buffer_evicts_oldest_events() {
+        let now = Instant::now();
+        let mut buffer = ReplayBuffer::new(2, Duration::from_secs(5));
+        (0..3).for_each(|event_id| buffer.push(&new_event(event_id), now));
+        assert_eq!(buffer.len(), 2);
+
+        assert_eq!(
+            buffer.replay(now),
+            Replay::Buffer(vec![new_event(1), new_event(2)])
+        );
+    }
+
+    #[test]
+    fn test_disabled_replay_buffer() {
+        let now = Instant::now();
+        let mut buffer = ReplayBuffer::new(0, Duration::from_secs(5));
+        buffer.push(&new_event(0), now);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.replay(now), Replay::Source);
+    }
+}
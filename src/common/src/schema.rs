@@ -0,0 +1,181 @@
+use std::fmt::{self, Display};
+
+use proto::common::{payload_schema, DataTypeEnum, PayloadSchema};
+use rand::Rng;
+
+use crate::types::TypedValue;
+
+/// metric of the input payloads which are validated against the input schema
+pub const SCHEMA_VALIDATED_METRIC: &str = "schema.validated";
+/// metric of the input payloads which violate the input schema
+pub const SCHEMA_VIOLATIONS_METRIC: &str = "schema.violations";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaError {
+    /// the payload is not an object
+    NotObject(DataTypeEnum),
+    /// a required field is absent or null
+    MissingField(String),
+    TypeMismatch {
+        field: String,
+        expected: DataTypeEnum,
+        actual: DataTypeEnum,
+    },
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::NotObject(actual) => {
+                write!(f, "payload of type {:?} is not an object", actual)
+            }
+            SchemaError::MissingField(field) => write!(f, "required field [{}] is missing", field),
+            SchemaError::TypeMismatch {
+                field,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "field [{}] is {:?} but {:?} is expected",
+                field, actual, expected
+            ),
+        }
+    }
+}
+
+/// [`SchemaValidator`] validates the input payloads of an operator against its input schema at runtime.
+///
+/// - in strict mode, every payload is validated
+/// - in sampling mode, each payload is validated with the probability `sample_fraction`
+///
+/// Absent and null fields are the same. Fields which are not declared are not checked.
+pub struct SchemaValidator {
+    fields: Vec<payload_schema::Field>,
+    sample_fraction: Option<f64>,
+}
+
+impl SchemaValidator {
+    /// it returns `None` if the runtime validation is disabled
+    pub fn new(schema: &PayloadSchema) -> Option<Self> {
+        let sample_fraction = match schema.validation() {
+            payload_schema::Validation::Disabled => return None,
+            payload_schema::Validation::Sampling => Some(schema.sample_fraction.clamp(0.0, 1.0)),
+            payload_schema::Validation::Strict => None,
+        };
+        Some(Self {
+            fields: schema.fields.clone(),
+            sample_fraction,
+        })
+    }
+
+    /// whether the next payload should be validated
+    pub fn should_validate(&self) -> bool {
+        match self.sample_fraction {
+            Some(fraction) => rand::thread_rng().gen_bool(fraction),
+            None => true,
+        }
+    }
+
+    pub fn validate(&self, payload: &TypedValue) -> Result<(), SchemaError> {
+        let object = match payload {
+            TypedValue::Object(object) => object,
+            _ => return Err(SchemaError::NotObject(payload.get_type())),
+        };
+        for field in &self.fields {
+            match object.get(&field.name) {
+                None | Some(TypedValue::Null) | Some(TypedValue::Invalid) => {
+                    if field.required {
+                        return Err(SchemaError::MissingField(field.name.clone()));
+                    }
+                }
+                Some(value) if !value.get_type().is_assignable_to(field.data_type()) => {
+                    return Err(SchemaError::TypeMismatch {
+                        field: field.name.clone(),
+                        expected: field.data_type(),
+                        actual: value.get_type(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proto::common::{payload_schema, DataTypeEnum, PayloadSchema};
+
+    use crate::types::TypedValue;
+
+    use super::{SchemaError, SchemaValidator};
+
+    fn new_schema(validation: payload_schema::Validation, sample_fraction: f64) -> PayloadSchema {
+        PayloadSchema {
+            fields: vec![
+                payload_schema::Field {
+                    name: "id".to_string(),
+                    data_type: DataTypeEnum::Bigint as i32,
+                    required: true,
+                },
+                payload_schema::Field {
+                    name: "score".to_string(),
+                    data_type: DataTypeEnum::Number as i32,
+                    required: false,
+                },
+            ],
+            validation: validation as i32,
+            sample_fraction,
+        }
+    }
+
+    #[test]
+    fn test_schema_validate() {
+        let validator =
+            SchemaValidator::new(&new_schema(payload_schema::Validation::Strict, 0.0)).unwrap();
+        assert!(validator.should_validate());
+
+        let validate =
+            |value: serde_json::Value| validator.validate(&TypedValue::from_json_value(value));
+        assert_eq!(validate(serde_json::json!({"id": 1, "score": 1.5})), Ok(()));
+        // a bigint is also a number, and optional fields can be absent or null
+        assert_eq!(validate(serde_json::json!({"id": 1, "score": 2})), Ok(()));
+        assert_eq!(
+            validate(serde_json::json!({"id": 1, "score": null})),
+            Ok(())
+        );
+        assert_eq!(validate(serde_json::json!({"id": 1, "other": "x"})), Ok(()));
+
+        assert_eq!(
+            validate(serde_json::json!({"ID": 1})),
+            Err(SchemaError::MissingField("id".to_string()))
+        );
+        assert_eq!(
+            validate(serde_json::json!({"id": "1"})),
+            Err(SchemaError::TypeMismatch {
+                field: "id".to_string(),
+                expected: DataTypeEnum::Bigint,
+                actual: DataTypeEnum::String,
+            })
+        );
+        assert_eq!(
+            validate(serde_json::json!([1])),
+            Err(SchemaError::NotObject(DataTypeEnum::Array))
+        );
+    }
+
+    #[test]
+    fn test_schema_validation_mode() {
+        assert!(
+            SchemaValidator::new(&new_schema(payload_schema::Validation::Disabled, 1.0)).is_none()
+        );
+
+        let validator =
+            SchemaValidator::new(&new_schema(payload_schema::Validation::Sampling, 1.0)).unwrap();
+        assert!((0..100).all(|_| validator.should_validate()));
+
+        let validator =
+            SchemaValidator::new(&new_schema(payload_schema::Validation::Sampling, 0.0)).unwrap();
+        assert!((0..100).all(|_| !validator.should_validate()));
+    }
+}
@@ -0,0 +1,1592 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use proto::common::{Dataflow, ResourceId};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+
+use crate::{
+    consts::default_configs::{
+        DEFAULT_REMOTE_CHECKPOINTS_RETAINED, DEFAULT_SNAPSHOT_STORE_REGION,
+        DEFAULT_SNAPSHOT_STORE_TIMEOUT_SECS,
+    },
+    types::ExecutorId,
+};
+
+/// metric of the checkpoints uploaded to the remote snapshot store
+pub const CHECKPOINT_UPLOADED_METRIC: &str = "checkpoint.remote.uploaded";
+/// metric of the checkpoints which fail to be uploaded. They are only kept locally
+pub const CHECKPOINT_NON_DURABLE_METRIC: &str = "checkpoint.remote.non_durable";
+/// milliseconds the latest upload takes
+pub const CHECKPOINT_UPLOAD_MILLIS_METRIC: &str = "checkpoint.remote.upload_millis";
+/// milliseconds since the earliest local checkpoint which is not uploaded yet, measured when the latest upload finishes
+pub const CHECKPOINT_DURABILITY_LAG_MILLIS_METRIC: &str = "checkpoint.remote.durability_lag_millis";
+
+const MANIFEST_CONTENT_TYPE: &str = "application/json";
+/// name of the manifest of a savepoint
+const SAVEPOINT_MANIFEST: &str = "manifest.json";
+/// headers signed by the requests to the snapshot store, sorted by name
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+const EMPTY_PAYLOAD_SHA256: &str =
+    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// Builder of [`SnapshotStore`], the S3-compatible storage which checkpoints are uploaded to, e.g. AWS S3 or MinIO.
+/// The configuration looks like:
+///
+/// ```json
+/// "snapshot_store": {
+///     "endpoint": "http://${MINIO_HOST}:9000",
+///     "bucket": "lightflus",
+///     "access_key": "${S3_ACCESS_KEY}",
+///     "secret_key": "${S3_SECRET_KEY}",
+///     "prefix": "checkpoints",
+///     "retained": 3
+/// }
+/// ```
+///
+/// - `endpoint`: `scheme://host[:port]` of the storage. Objects are addressed in path style
+/// - `region`: `us-east-1` by default
+/// - `prefix`: prefix of the keys of all checkpoints, empty by default
+/// - `retained`: the number of the latest checkpoints kept for each subdataflow, 3 by default
+/// - `timeout`: timeout of a request in seconds, 10 by default
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotStoreBuilder {
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default = "default_retained")]
+    pub retained: usize,
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+fn default_region() -> String {
+    DEFAULT_SNAPSHOT_STORE_REGION.to_string()
+}
+
+fn default_retained() -> usize {
+    DEFAULT_REMOTE_CHECKPOINTS_RETAINED
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_SNAPSHOT_STORE_TIMEOUT_SECS
+}
+
+impl SnapshotStoreBuilder {
+    pub fn build(&self) -> Result<SnapshotStore, SnapshotError> {
+        let endpoint = reqwest::Url::parse(&self.endpoint)
+            .map_err(|err| SnapshotError::InvalidEndpoint(format!("{}: {}", self.endpoint, err)))?;
+        let host = match (endpoint.host_str(), endpoint.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(SnapshotError::InvalidEndpoint(self.endpoint.clone())),
+        };
+
+        Ok(SnapshotStore {
+            client: S3Client {
+                endpoint: self.endpoint.trim_end_matches('/').to_string(),
+                host,
+                bucket: self.bucket.clone(),
+                region: self.region.clone(),
+                access_key: self.access_key.clone(),
+                secret_key: self.secret_key.clone(),
+                client: reqwest::Client::builder()
+                    .timeout(Duration::from_secs(self.timeout))
+                    .build()
+                    .unwrap_or_default(),
+            },
+            prefix: self.prefix.trim_matches('/').to_string(),
+            retained: self.retained.max(1),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    InvalidEndpoint(String),
+    /// the storage can't be connected, or it fails to serve the request temporarily
+    Unavailable(String),
+    /// the storage rejects the request
+    Storage {
+        status: u16,
+        message: String,
+    },
+    NotFound(String),
+    InvalidManifest(String),
+    /// the size or the checksum of an artifact doesn't match its manifest
+    Corrupted(String),
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::InvalidEndpoint(endpoint) => {
+                write!(f, "invalid snapshot store endpoint {}", endpoint)
+            }
+            SnapshotError::Unavailable(message) => {
+                write!(f, "snapshot store is unavailable: {}", message)
+            }
+            SnapshotError::Storage { status, message } => {
+                write!(f, "snapshot store responds [{}]: {}", status, message)
+            }
+            SnapshotError::NotFound(key) => write!(f, "object {} is not found", key),
+            SnapshotError::InvalidManifest(message) => write!(f, "invalid manifest: {}", message),
+            SnapshotError::Corrupted(key) => write!(f, "artifact {} is corrupted", key),
+        }
+    }
+}
+
+/// the smallest operator id of a subdataflow. It identifies the subdataflow in the snapshot store, so its checkpoints can be found after it fails over to another worker
+pub fn get_partition_id(subdataflow: &Dataflow) -> ExecutorId {
+    subdataflow
+        .meta
+        .iter()
+        .map(|meta| meta.center)
+        .min()
+        .unwrap_or_default()
+}
+
+/// [`SnapshotManifest`] describes a complete remote checkpoint of a subdataflow.
+/// It's uploaded after all the artifacts, so a checkpoint without manifest is never restored from.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SnapshotManifest {
+    pub namespace_id: String,
+    pub resource_id: String,
+    /// see [`get_partition_id`]
+    pub partition: ExecutorId,
+    pub epoch: u64,
+    pub checkpoint: u64,
+    pub artifacts: Vec<SnapshotArtifact>,
+    /// milliseconds since the unix epoch when the checkpoint is uploaded
+    pub created_at: i64,
+    /// the state schema of each operator when it's checkpointed, see [`proto::common::OperatorInfo::get_state_schema`].
+    /// It's empty for the checkpoints uploaded by former versions
+    #[serde(default)]
+    pub state_schemas: BTreeMap<ExecutorId, String>,
+}
+
+impl SnapshotManifest {
+    fn new(job_id: &ResourceId, partition: ExecutorId, epoch: u64, checkpoint: u64) -> Self {
+        Self {
+            namespace_id: job_id.namespace_id.clone(),
+            resource_id: job_id.resource_id.clone(),
+            partition,
+            epoch,
+            checkpoint,
+            artifacts: vec![],
+            created_at: 0,
+            state_schemas: Default::default(),
+        }
+    }
+
+    /// whether the checkpointed state of an operator can be restored into an operator with `state_schema`
+    pub fn is_compatible(&self, operator_id: ExecutorId, state_schema: &str) -> bool {
+        self.state_schemas
+            .get(&operator_id)
+            .map(|schema| schema == state_schema)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SnapshotArtifact {
+    pub operator_id: ExecutorId,
+    /// name of the object in the directory of the checkpoint
+    pub name: String,
+    pub size: u64,
+    /// hex-encoded SHA-256 of the object
+    pub sha256: String,
+}
+
+/// a checkpoint downloaded from the snapshot store, with the states of each operator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteCheckpoint {
+    pub manifest: SnapshotManifest,
+    pub states: BTreeMap<ExecutorId, Vec<u8>>,
+}
+
+/// the states which a new job is warm-started from, collected from the latest checkpoints of a prior job
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WarmStart {
+    pub states: BTreeMap<ExecutorId, Vec<u8>>,
+    /// the operators which start with empty state because their state schemas changed
+    pub warnings: Vec<String>,
+}
+
+/// [`SnapshotStore`] keeps the checkpoints of subdataflows in S3-compatible storage, so they survive the loss of a worker.
+/// Objects of a checkpoint are laid out as:
+///
+/// ```text
+/// {prefix}/{namespace_id}/{resource_id}/{epoch}/checkpoint_{n}/operator_{operator_id}.state
+/// {prefix}/{namespace_id}/{resource_id}/{epoch}/checkpoint_{n}/manifest_{partition}.json
+/// ```
+///
+/// Savepoints contain the states of all operators of a dataflow and are laid out as:
+///
+/// ```text
+/// {prefix}/{namespace_id}/{resource_id}/savepoints/{savepoint_id}/operator_{operator_id}.state
+/// {prefix}/{namespace_id}/{resource_id}/savepoints/{savepoint_id}/manifest.json
+/// ```
+///
+/// The epoch increases every time a subdataflow is restored from a remote checkpoint, and `n` increases with each checkpoint in an epoch.
+/// Subdataflows of a job share the directories, but their artifacts and manifests don't collide because operator ids are unique in a job.
+#[derive(Clone)]
+pub struct SnapshotStore {
+    client: S3Client,
+    prefix: String,
+    retained: usize,
+}
+
+impl SnapshotStore {
+    /// upload the states of the operators of a subdataflow as a checkpoint and return the key of its manifest.
+    /// The oldest checkpoints of the subdataflow are deleted if there are more than `retained` of them.
+    pub async fn upload(
+        &self,
+        job_id: &ResourceId,
+        partition: ExecutorId,
+        epoch: u64,
+        checkpoint: u64,
+        states: &BTreeMap<ExecutorId, Vec<u8>>,
+        state_schemas: &BTreeMap<ExecutorId, String>,
+    ) -> Result<String, SnapshotError> {
+        let dir = format!(
+            "{}{}/checkpoint_{}/",
+            self.get_job_prefix(job_id),
+            epoch,
+            checkpoint
+        );
+        let mut manifest = SnapshotManifest::new(job_id, partition, epoch, checkpoint);
+        manifest.state_schemas = state_schemas.clone();
+        let key = self
+            .put_checkpoint(
+                &dir,
+                &format!("manifest_{}.json", partition),
+                manifest,
+                states,
+            )
+            .await?;
+
+        // the checkpoint is durable once its manifest is uploaded, even if the expired ones fail to be deleted
+        if let Err(err) = self.expire(job_id, partition).await {
+            tracing::warn!(
+                "expired checkpoints of job {:?} are not deleted: {}",
+                job_id,
+                err
+            )
+        }
+        Ok(key)
+    }
+
+    /// upload the states of the operators of a dataflow as a savepoint and return the key of its manifest.
+    /// Savepoints are never deleted by the retention of checkpoints
+    pub async fn upload_savepoint(
+        &self,
+        job_id: &ResourceId,
+        savepoint_id: &str,
+        states: &BTreeMap<ExecutorId, Vec<u8>>,
+    ) -> Result<String, SnapshotError> {
+        let dir = format!("{}{}/", self.get_savepoint_prefix(job_id), savepoint_id);
+        let manifest = SnapshotManifest::new(job_id, 0, 0, 0);
+        self.put_checkpoint(&dir, SAVEPOINT_MANIFEST, manifest, states)
+            .await
+    }
+
+    /// the keys and manifests of the savepoints of a dataflow, in the order they're created
+    pub async fn list_savepoints(
+        &self,
+        job_id: &ResourceId,
+    ) -> Result<Vec<(String, SnapshotManifest)>, SnapshotError> {
+        let prefix = self.get_savepoint_prefix(job_id);
+        let mut savepoints = vec![];
+        for key in self.client.list_objects(&prefix).await? {
+            if is_savepoint_key(&key, &prefix) {
+                let manifest = self.get_manifest(&key).await?;
+                savepoints.push((key, manifest));
+            }
+        }
+        savepoints.sort_by_key(|(_, manifest)| manifest.created_at);
+        Ok(savepoints)
+    }
+
+    /// delete a savepoint of a dataflow by the key of its manifest
+    pub async fn delete_savepoint(
+        &self,
+        job_id: &ResourceId,
+        manifest_key: &str,
+    ) -> Result<(), SnapshotError> {
+        if !is_savepoint_key(manifest_key, &self.get_savepoint_prefix(job_id)) {
+            return Err(SnapshotError::NotFound(manifest_key.to_string()));
+        }
+        self.delete_checkpoint(manifest_key).await
+    }
+
+    /// the key of the manifest of the latest complete checkpoint of a subdataflow
+    pub async fn latest_manifest(
+        &self,
+        job_id: &ResourceId,
+        partition: ExecutorId,
+    ) -> Result<Option<String>, SnapshotError> {
+        self.list_manifests(job_id, partition)
+            .await
+            .map(|manifests| manifests.into_iter().last().map(|(_, key)| key))
+    }
+
+    /// the keys of the manifests of the latest complete checkpoints of all subdataflows of a job, in the order of their partitions
+    pub async fn latest_manifests(
+        &self,
+        job_id: &ResourceId,
+    ) -> Result<Vec<String>, SnapshotError> {
+        let prefix = self.get_job_prefix(job_id);
+        let mut latest = BTreeMap::new();
+        for key in self.client.list_objects(&prefix).await? {
+            let partition = key
+                .rsplit_once('/')
+                .and_then(|(_, name)| name.strip_prefix("manifest_"))
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|partition| partition.parse::<ExecutorId>().ok());
+            let name = partition.map(|partition| format!("manifest_{}.json", partition));
+            if let (Some(partition), Some(version)) = (
+                partition,
+                name.and_then(|name| parse_manifest_key(&key, &prefix, &name)),
+            ) {
+                match latest.get(&partition) {
+                    Some((latest_version, _)) if *latest_version >= version => {}
+                    _ => {
+                        latest.insert(partition, (version, key));
+                    }
+                }
+            }
+        }
+        Ok(latest.into_values().map(|(_, key)| key).collect())
+    }
+
+    /// download a checkpoint by the key of its manifest. Each artifact is verified by its size and checksum
+    pub async fn download(&self, manifest_key: &str) -> Result<RemoteCheckpoint, SnapshotError> {
+        let manifest = self.get_manifest(manifest_key).await?;
+        let mut states = BTreeMap::new();
+        for artifact in &manifest.artifacts {
+            let state = self.get_artifact(manifest_key, artifact).await?;
+            states.insert(artifact.operator_id, state);
+        }
+        Ok(RemoteCheckpoint { manifest, states })
+    }
+
+    /// collect the states of the operators in `state_schemas` from the checkpoints of a prior job.
+    /// Only the artifacts of the operators whose state schemas are unchanged are downloaded, the others start with empty state
+    pub async fn warm_start(
+        &self,
+        manifest_keys: &[String],
+        state_schemas: &BTreeMap<ExecutorId, String>,
+    ) -> Result<WarmStart, SnapshotError> {
+        let mut warm_start = WarmStart::default();
+        for manifest_key in manifest_keys {
+            let manifest = self.get_manifest(manifest_key).await?;
+            for artifact in &manifest.artifacts {
+                match state_schemas.get(&artifact.operator_id) {
+                    Some(schema) if manifest.is_compatible(artifact.operator_id, schema) => {
+                        let state = self.get_artifact(manifest_key, artifact).await?;
+                        warm_start.states.insert(artifact.operator_id, state);
+                    }
+                    Some(_) => warm_start.warnings.push(format!(
+                        "operator {} starts with empty state because its type, upstreams or input schema changed since checkpoint {}",
+                        artifact.operator_id, manifest_key
+                    )),
+                    // the operator is removed or it belongs to another subdataflow
+                    None => {}
+                }
+            }
+        }
+        Ok(warm_start)
+    }
+
+    fn get_job_prefix(&self, job_id: &ResourceId) -> String {
+        if self.prefix.is_empty() {
+            format!("{}/{}/", job_id.namespace_id, job_id.resource_id)
+        } else {
+            format!(
+                "{}/{}/{}/",
+                self.prefix, job_id.namespace_id, job_id.resource_id
+            )
+        }
+    }
+
+    fn get_savepoint_prefix(&self, job_id: &ResourceId) -> String {
+        format!("{}savepoints/", self.get_job_prefix(job_id))
+    }
+
+    /// upload the artifacts of a checkpoint into `dir`, then its manifest named `manifest_name`, and return the key of the manifest
+    async fn put_checkpoint(
+        &self,
+        dir: &str,
+        manifest_name: &str,
+        mut manifest: SnapshotManifest,
+        states: &BTreeMap<ExecutorId, Vec<u8>>,
+    ) -> Result<String, SnapshotError> {
+        for (operator_id, state) in states {
+            let name = format!("operator_{}.state", operator_id);
+            self.client
+                .put_object(&format!("{}{}", dir, name), state.clone(), None)
+                .await?;
+            manifest.artifacts.push(SnapshotArtifact {
+                operator_id: *operator_id,
+                name,
+                size: state.len() as u64,
+                sha256: hex(&Sha256::digest(state)),
+            });
+        }
+
+        manifest.created_at = chrono::Utc::now().timestamp_millis();
+        let key = format!("{}{}", dir, manifest_name);
+        self.client
+            .put_object(
+                &key,
+                serde_json::to_vec(&manifest).unwrap_or_default(),
+                Some(MANIFEST_CONTENT_TYPE),
+            )
+            .await?;
+        Ok(key)
+    }
+
+    /// delete a checkpoint by the key of its manifest. The manifest is deleted first, so a checkpoint being deleted is never restored from
+    async fn delete_checkpoint(&self, manifest_key: &str) -> Result<(), SnapshotError> {
+        let manifest = self.get_manifest(manifest_key).await;
+        self.client.delete_object(manifest_key).await?;
+        if let (Ok(manifest), Some((dir, _))) = (manifest, manifest_key.rsplit_once('/')) {
+            for artifact in manifest.artifacts {
+                self.client
+                    .delete_object(&format!("{}/{}", dir, artifact.name))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// download an artifact in the directory of the manifest and verify it by its size and checksum
+    async fn get_artifact(
+        &self,
+        manifest_key: &str,
+        artifact: &SnapshotArtifact,
+    ) -> Result<Vec<u8>, SnapshotError> {
+        let key = manifest_key
+            .rsplit_once('/')
+            .map(|(dir, _)| format!("{}/{}", dir, artifact.name))
+            .unwrap_or_else(|| artifact.name.clone());
+        let state = self
+            .client
+            .get_object(&key)
+            .await?
+            .ok_or_else(|| SnapshotError::NotFound(key.clone()))?;
+        if state.len() as u64 != artifact.size || hex(&Sha256::digest(&state)) != artifact.sha256 {
+            return Err(SnapshotError::Corrupted(key));
+        }
+        Ok(state)
+    }
+
+    async fn get_manifest(&self, key: &str) -> Result<SnapshotManifest, SnapshotError> {
+        let manifest = self
+            .client
+            .get_object(key)
+            .await?
+            .ok_or_else(|| SnapshotError::NotFound(key.to_string()))?;
+        serde_json::from_slice(&manifest)
+            .map_err(|err| SnapshotError::InvalidManifest(format!("{}: {}", key, err)))
+    }
+
+    /// keys of the manifests of a subdataflow, in the order of (epoch, checkpoint)
+    async fn list_manifests(
+        &self,
+        job_id: &ResourceId,
+        partition: ExecutorId,
+    ) -> Result<Vec<((u64, u64), String)>, SnapshotError> {
+        let prefix = self.get_job_prefix(job_id);
+        let name = format!("manifest_{}.json", partition);
+        let mut manifests = self
+            .client
+            .list_objects(&prefix)
+            .await?
+            .into_iter()
+            .filter_map(|key| Some((parse_manifest_key(&key, &prefix, &name)?, key)))
+            .collect::<Vec<_>>();
+        manifests.sort();
+        Ok(manifests)
+    }
+
+    async fn expire(
+        &self,
+        job_id: &ResourceId,
+        partition: ExecutorId,
+    ) -> Result<(), SnapshotError> {
+        let manifests = self.list_manifests(job_id, partition).await?;
+        let expired = manifests.len().saturating_sub(self.retained);
+        for (_, key) in manifests.into_iter().take(expired) {
+            self.delete_checkpoint(&key).await?;
+        }
+        Ok(())
+    }
+}
+
+/// whether the key is the manifest of a savepoint like `{prefix}{savepoint_id}/manifest.json`
+fn is_savepoint_key(key: &str, prefix: &str) -> bool {
+    key.strip_prefix(prefix)
+        .and_then(|key| key.strip_suffix(SAVEPOINT_MANIFEST))
+        .and_then(|dir| dir.strip_suffix('/'))
+        .map(|savepoint_id| !savepoint_id.is_empty() && !savepoint_id.contains('/'))
+        .unwrap_or_default()
+}
+
+/// (epoch, checkpoint) of a manifest key like `{prefix}{epoch}/checkpoint_{n}/{name}`
+fn parse_manifest_key(key: &str, prefix: &str, name: &str) -> Option<(u64, u64)> {
+    let mut parts = key.strip_prefix(prefix)?.split('/');
+    let epoch = parts.next()?.parse::<u64>().ok()?;
+    let checkpoint = parts
+        .next()?
+        .strip_prefix("checkpoint_")?
+        .parse::<u64>()
+        .ok()?;
+    (parts.next()? == name && parts.next().is_none()).then_some((epoch, checkpoint))
+}
+
+/// a minimal client of the S3 API, which signs requests with AWS Signature Version 4
+#[derive(Clone)]
+struct S3Client {
+    endpoint: String,
+    host: String,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    client: reqwest::Client,
+}
+
+impl S3Client {
+    async fn put_object(
+        &self,
+        key: &str,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> Result<(), SnapshotError> {
+        self.send(reqwest::Method::PUT, key, vec![], body, content_type)
+            .await
+            .map(|_| {})
+    }
+
+    /// `None` if the object doesn't exist
+    async fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>, SnapshotError> {
+        match self
+            .send(reqwest::Method::GET, key, vec![], vec![], None)
+            .await
+        {
+            Ok(response) => response
+                .bytes()
+                .await
+                .map(|body| Some(body.to_vec()))
+                .map_err(|err| SnapshotError::Unavailable(err.to_string())),
+            Err(SnapshotError::Storage { status: 404, .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<(), SnapshotError> {
+        match self
+            .send(reqwest::Method::DELETE, key, vec![], vec![], None)
+            .await
+        {
+            Ok(_) | Err(SnapshotError::Storage { status: 404, .. }) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// keys of all objects whose keys start with the prefix
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<String>, SnapshotError> {
+        let mut keys = vec![];
+        let mut continuation_token = None;
+        loop {
+            let mut query = vec![
+                ("list-type".to_string(), "2".to_string()),
+                ("prefix".to_string(), prefix.to_string()),
+            ];
+            if let Some(token) = continuation_token.take() {
+                query.push(("continuation-token".to_string(), token));
+            }
+            let response = self
+                .send(reqwest::Method::GET, "", query, vec![], None)
+                .await?
+                .text()
+                .await
+                .map_err(|err| SnapshotError::Unavailable(err.to_string()))?;
+
+            keys.extend(xml_values(&response, "Key"));
+            let truncated = xml_values(&response, "IsTruncated")
+                .first()
+                .map(|truncated| truncated == "true")
+                .unwrap_or_default();
+            continuation_token = xml_values(&response, "NextContinuationToken")
+                .into_iter()
+                .next();
+            if !truncated || continuation_token.is_none() {
+                return Ok(keys);
+            }
+        }
+    }
+
+    /// send a request to an object, or to the bucket if the key is empty
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        query: Vec<(String, String)>,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> Result<reqwest::Response, SnapshotError> {
+        let path = if key.is_empty() {
+            format!("/{}", uri_encode(&self.bucket, true))
+        } else {
+            format!(
+                "/{}/{}",
+                uri_encode(&self.bucket, true),
+                uri_encode(key, false)
+            )
+        };
+        let query = canonical_query(&query);
+        let payload_hash = if body.is_empty() {
+            EMPTY_PAYLOAD_SHA256.to_string()
+        } else {
+            hex(&Sha256::digest(&body))
+        };
+        let datetime = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let authorization = authorization(
+            &SigningKey {
+                access_key: &self.access_key,
+                secret_key: &self.secret_key,
+                region: &self.region,
+                service: "s3",
+            },
+            &datetime,
+            &canonical_request(
+                method.as_str(),
+                &path,
+                &query,
+                &[
+                    ("host", &self.host),
+                    ("x-amz-content-sha256", &payload_hash),
+                    ("x-amz-date", &datetime),
+                ],
+                &payload_hash,
+            ),
+        );
+
+        let url = if query.is_empty() {
+            format!("{}{}", self.endpoint, path)
+        } else {
+            format!("{}{}?{}", self.endpoint, path, query)
+        };
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", datetime)
+            .header(reqwest::header::AUTHORIZATION, authorization);
+        if let Some(content_type) = content_type {
+            request = request.header(reqwest::header::CONTENT_TYPE, content_type);
+        }
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| SnapshotError::Unavailable(err.to_string()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(response)
+        } else {
+            let message = response.text().await.unwrap_or_default();
+            if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                Err(SnapshotError::Unavailable(format!(
+                    "[{}] {}",
+                    status, message
+                )))
+            } else {
+                Err(SnapshotError::Storage {
+                    status: status.as_u16(),
+                    message,
+                })
+            }
+        }
+    }
+}
+
+struct SigningKey<'a> {
+    access_key: &'a str,
+    secret_key: &'a str,
+    region: &'a str,
+    service: &'a str,
+}
+
+/// the `Authorization` header of AWS Signature Version 4. `datetime` is formatted as `%Y%m%dT%H%M%SZ`
+fn authorization(key: &SigningKey, datetime: &str, canonical_request: &str) -> String {
+    let (scope, signature) = sign(key, datetime, canonical_request);
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        key.access_key, scope, SIGNED_HEADERS, signature
+    )
+}
+
+/// the credential scope and the signature of a canonical request
+fn sign(key: &SigningKey, datetime: &str, canonical_request: &str) -> (String, String) {
+    let date = &datetime[..8.min(datetime.len())];
+    let scope = format!("{}/{}/{}/aws4_request", date, key.region, key.service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        datetime,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signing_key = [key.region, key.service, "aws4_request"].iter().fold(
+        hmac_sha256(
+            format!("AWS4{}", key.secret_key).as_bytes(),
+            date.as_bytes(),
+        ),
+        |signing_key, data| hmac_sha256(&signing_key, data.as_bytes()),
+    );
+    let signature = hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+    (scope, signature)
+}
+
+/// `headers` should be sorted by their lowercase names
+fn canonical_request(
+    method: &str,
+    path: &str,
+    query: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+) -> String {
+    let canonical_headers = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect::<String>();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method, path, query, canonical_headers, signed_headers, payload_hash
+    )
+}
+
+fn canonical_query(query: &[(String, String)]) -> String {
+    let mut query = query
+        .iter()
+        .map(|(name, value)| (uri_encode(name, true), uri_encode(value, true)))
+        .collect::<Vec<_>>();
+    query.sort();
+    query
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// percent-encode everything except the unreserved characters, as AWS Signature Version 4 requires
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            b'/' if !encode_slash => "/".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|byte| byte ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|byte| byte ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// text of all elements with the tag, e.g. the keys in the response of ListObjectsV2
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut values = vec![];
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        match rest.find(&close) {
+            Some(end) => {
+                values.push(
+                    rest[..end]
+                        .replace("&lt;", "<")
+                        .replace("&gt;", ">")
+                        .replace("&quot;", "\"")
+                        .replace("&apos;", "'")
+                        .replace("&amp;", "&"),
+                );
+                rest = &rest[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+    values
+}
+
+/// the states of an operator checkpointed locally
+#[derive(Debug)]
+pub struct LocalCheckpoint {
+    pub operator_id: ExecutorId,
+    /// the encoded states of the operator
+    pub state: Vec<u8>,
+    pub completed_at: Instant,
+}
+
+pub type SharedCheckpointMetrics = Arc<Mutex<HashMap<String, u64>>>;
+
+/// [`CheckpointUploader`] uploads the local checkpoints of the operators of a subdataflow to the [`SnapshotStore`].
+/// Each upload contains the latest states of all operators of the subdataflow, and local checkpoints completed during an upload are merged into the next one.
+/// A failed upload doesn't fail the local checkpoint: it's counted as non-durable and its states are uploaded with the next local checkpoint.
+pub struct CheckpointUploader {
+    store: SnapshotStore,
+    job_id: ResourceId,
+    partition: ExecutorId,
+    epoch: u64,
+    checkpoint: u64,
+    states: BTreeMap<ExecutorId, Vec<u8>>,
+    /// the state schemas of the operators recorded in the manifests, so the checkpoints can warm-start other jobs
+    state_schemas: BTreeMap<ExecutorId, String>,
+    /// when the earliest local checkpoint which is not uploaded yet is completed
+    pending_since: Option<Instant>,
+    metrics: SharedCheckpointMetrics,
+    rx: mpsc::UnboundedReceiver<LocalCheckpoint>,
+}
+
+impl CheckpointUploader {
+    /// If the subdataflow is restored from a remote checkpoint, the uploader starts a new epoch after the restored one,
+    /// and the restored states are uploaded until their operators are checkpointed again.
+    pub fn new(
+        store: &SnapshotStore,
+        job_id: &ResourceId,
+        partition: ExecutorId,
+        restored: Option<&RemoteCheckpoint>,
+    ) -> (Self, mpsc::UnboundedSender<LocalCheckpoint>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (
+            Self {
+                store: store.clone(),
+                job_id: job_id.clone(),
+                partition,
+                epoch: restored
+                    .map(|checkpoint| checkpoint.manifest.epoch + 1)
+                    .unwrap_or_default(),
+                checkpoint: 0,
+                states: restored
+                    .map(|checkpoint| checkpoint.states.clone())
+                    .unwrap_or_default(),
+                state_schemas: Default::default(),
+                pending_since: None,
+                metrics: Default::default(),
+                rx,
+            },
+            tx,
+        )
+    }
+
+    pub fn with_state_schemas(mut self, state_schemas: BTreeMap<ExecutorId, String>) -> Self {
+        self.state_schemas = state_schemas;
+        self
+    }
+
+    pub fn get_epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    pub fn get_metrics(&self) -> SharedCheckpointMetrics {
+        self.metrics.clone()
+    }
+
+    /// upload the local checkpoints until all senders are dropped
+    pub async fn run(mut self) {
+        while let Some(checkpoint) = self.rx.recv().await {
+            self.receive(checkpoint);
+            while let Ok(checkpoint) = self.rx.try_recv() {
+                self.receive(checkpoint);
+            }
+            self.upload().await;
+        }
+    }
+
+    fn receive(&mut self, checkpoint: LocalCheckpoint) {
+        self.states.insert(checkpoint.operator_id, checkpoint.state);
+        self.pending_since.get_or_insert(checkpoint.completed_at);
+    }
+
+    async fn upload(&mut self) {
+        self.checkpoint += 1;
+        let start = Instant::now();
+        let result = self
+            .store
+            .upload(
+                &self.job_id,
+                self.partition,
+                self.epoch,
+                self.checkpoint,
+                &self.states,
+                &self.state_schemas,
+            )
+            .await;
+        let lag = self
+            .pending_since
+            .map(|since| since.elapsed().as_millis() as u64)
+            .unwrap_or_default();
+
+        let mut metrics = self.metrics.lock().unwrap_or_else(|err| err.into_inner());
+        match result {
+            Ok(_) => {
+                self.pending_since = None;
+                *metrics
+                    .entry(CHECKPOINT_UPLOADED_METRIC.to_string())
+                    .or_default() += 1;
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "checkpoint {} of job {:?} is not durable: {}",
+                    self.checkpoint,
+                    &self.job_id,
+                    err
+                );
+                *metrics
+                    .entry(CHECKPOINT_NON_DURABLE_METRIC.to_string())
+                    .or_default() += 1;
+            }
+        }
+        metrics.insert(
+            CHECKPOINT_UPLOAD_MILLIS_METRIC.to_string(),
+            start.elapsed().as_millis() as u64,
+        );
+        metrics.insert(CHECKPOINT_DURABILITY_LAG_MILLIS_METRIC.to_string(), lag);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::Instant,
+    };
+
+    use proto::common::ResourceId;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::{
+        canonical_request, sign, CheckpointUploader, LocalCheckpoint, SigningKey, SnapshotError,
+        SnapshotStore, SnapshotStoreBuilder, WarmStart, CHECKPOINT_NON_DURABLE_METRIC,
+        CHECKPOINT_UPLOADED_METRIC,
+    };
+
+    type Objects = Arc<Mutex<BTreeMap<String, Vec<u8>>>>;
+
+    /// an in-memory S3-compatible storage. Listing is paginated by 2 keys to exercise continuation
+    struct MockStorage {
+        endpoint: String,
+        objects: Objects,
+        unavailable: Arc<AtomicBool>,
+    }
+
+    async fn mock_storage() -> MockStorage {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let objects = Objects::default();
+        let unavailable = Arc::new(AtomicBool::new(false));
+        let storage = MockStorage {
+            endpoint,
+            objects: objects.clone(),
+            unavailable: unavailable.clone(),
+        };
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(serve(stream, objects.clone(), unavailable.clone()));
+            }
+        });
+        storage
+    }
+
+    async fn serve(mut stream: TcpStream, objects: Objects, unavailable: Arc<AtomicBool>) {
+        let mut buf = vec![];
+        let mut chunk = [0u8; 4096];
+        let header_end = loop {
+            let n = stream.read(&mut chunk).await.unwrap();
+            if n == 0 {
+                return;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            if let Some(pos) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+        let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+        let content_length = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().ok())?
+            })
+            .unwrap_or_default();
+        while buf.len() < header_end + content_length {
+            let n = stream.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        let body = buf[header_end..header_end + content_length].to_vec();
+        assert!(head.to_lowercase().contains(&format!(
+            "authorization: aws4-hmac-sha256 credential=access_key/"
+        )));
+
+        let mut request_line = head.lines().next().unwrap().split(' ');
+        let method = request_line.next().unwrap().to_string();
+        let target = request_line.next().unwrap().to_string();
+        let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+        let key = percent_decode(path.trim_start_matches("/bucket").trim_start_matches('/'));
+
+        let (status, response) = if unavailable.load(Ordering::SeqCst) {
+            (503, vec![])
+        } else {
+            let mut objects = objects.lock().unwrap();
+            match method.as_str() {
+                "PUT" => {
+                    objects.insert(key, body);
+                    (200, vec![])
+                }
+                "DELETE" => {
+                    objects.remove(&key);
+                    (204, vec![])
+                }
+                "GET" if key.is_empty() => {
+                    let params = query
+                        .split('&')
+                        .filter_map(|param| param.split_once('='))
+                        .map(|(name, value)| (name.to_string(), percent_decode(value)))
+                        .collect::<BTreeMap<_, _>>();
+                    let prefix = params.get("prefix").cloned().unwrap_or_default();
+                    let start = params
+                        .get("continuation-token")
+                        .cloned()
+                        .unwrap_or_default();
+                    let keys = objects
+                        .keys()
+                        .filter(|key| key.starts_with(&prefix) && **key > start)
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    let page = keys.iter().take(2).collect::<Vec<_>>();
+                    let mut xml = format!(
+                        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult><IsTruncated>{}</IsTruncated>",
+                        keys.len() > 2
+                    );
+                    if keys.len() > 2 {
+                        xml.push_str(&format!(
+                            "<NextContinuationToken>{}</NextContinuationToken>",
+                            page.last().unwrap()
+                        ));
+                    }
+                    page.iter().for_each(|key| {
+                        xml.push_str(&format!(
+                            "<Contents><Key>{}</Key></Contents>",
+                            key.replace('&', "&amp;")
+                        ))
+                    });
+                    xml.push_str("</ListBucketResult>");
+                    (200, xml.into_bytes())
+                }
+                "GET" => match objects.get(&key) {
+                    Some(object) => (200, object.clone()),
+                    None => (404, b"NoSuchKey".to_vec()),
+                },
+                _ => (405, vec![]),
+            }
+        };
+        let head = format!(
+            "HTTP/1.1 {} OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            response.len()
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(&response).await.unwrap();
+        stream.shutdown().await.unwrap();
+    }
+
+    fn percent_decode(value: &str) -> String {
+        let bytes = value.as_bytes();
+        let mut decoded = vec![];
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' && i + 2 < bytes.len() {
+                decoded.push(u8::from_str_radix(&value[i + 1..i + 3], 16).unwrap());
+                i += 3;
+            } else {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8(decoded).unwrap()
+    }
+
+    fn store_builder(endpoint: &str, retained: usize) -> SnapshotStoreBuilder {
+        SnapshotStoreBuilder {
+            endpoint: endpoint.to_string(),
+            bucket: "bucket".to_string(),
+            region: "us-east-1".to_string(),
+            access_key: "access_key".to_string(),
+            secret_key: "secret_key".to_string(),
+            prefix: "/checkpoints/".to_string(),
+            retained,
+            timeout: 3,
+        }
+    }
+
+    fn job_id() -> ResourceId {
+        ResourceId::new("ns", "job")
+    }
+
+    fn states(value: &str) -> BTreeMap<u32, Vec<u8>> {
+        BTreeMap::from_iter([
+            (1, format!("{}-1", value).into_bytes()),
+            (2, format!("{}-2", value).into_bytes()),
+        ])
+    }
+
+    #[test]
+    fn test_sign_request() {
+        // the example of AWS Signature Version 4 in the AWS General Reference
+        let canonical_request = canonical_request(
+            "GET",
+            "/",
+            "Action=ListUsers&Version=2010-05-08",
+            &[
+                (
+                    "content-type",
+                    "application/x-www-form-urlencoded; charset=utf-8",
+                ),
+                ("host", "iam.amazonaws.com"),
+                ("x-amz-date", "20150830T123600Z"),
+            ],
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        );
+        let (scope, signature) = sign(
+            &SigningKey {
+                access_key: "AKIDEXAMPLE",
+                secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                region: "us-east-1",
+                service: "iam",
+            },
+            "20150830T123600Z",
+            &canonical_request,
+        );
+        assert_eq!(scope, "20150830/us-east-1/iam/aws4_request");
+        assert_eq!(
+            signature,
+            "5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+
+    #[test]
+    fn test_snapshot_store_builder() {
+        let builder = serde_json::from_str::<SnapshotStoreBuilder>(
+            r#"{
+                "endpoint": "http://localhost:9000",
+                "bucket": "lightflus",
+                "access_key": "access_key",
+                "secret_key": "secret_key"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(builder.region, "us-east-1");
+        assert_eq!(builder.prefix, "");
+        assert_eq!(builder.retained, 3);
+        assert!(builder.build().is_ok());
+
+        let mut builder = builder;
+        builder.endpoint = "localhost".to_string();
+        assert!(matches!(
+            builder.build(),
+            Err(SnapshotError::InvalidEndpoint(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_store() {
+        let storage = mock_storage().await;
+        let store: SnapshotStore = store_builder(&storage.endpoint, 2).build().unwrap();
+        let job_id = job_id();
+
+        assert_eq!(store.latest_manifest(&job_id, 1).await, Ok(None));
+
+        for checkpoint in 1..=3 {
+            let key = store
+                .upload(
+                    &job_id,
+                    1,
+                    0,
+                    checkpoint,
+                    &states(&checkpoint.to_string()),
+                    &Default::default(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(
+                key,
+                format!(
+                    "checkpoints/ns/job/0/checkpoint_{}/manifest_1.json",
+                    checkpoint
+                )
+            );
+        }
+        // a checkpoint of another subdataflow of the job
+        store
+            .upload(
+                &job_id,
+                3,
+                0,
+                1,
+                &BTreeMap::from_iter([(3, b"other".to_vec())]),
+                &Default::default(),
+            )
+            .await
+            .unwrap();
+        // a checkpoint of a later epoch whose manifest is not uploaded
+        storage.objects.lock().unwrap().insert(
+            "checkpoints/ns/job/1/checkpoint_1/operator_1.state".to_string(),
+            b"partial".to_vec(),
+        );
+
+        let latest = store.latest_manifest(&job_id, 1).await.unwrap().unwrap();
+        assert_eq!(latest, "checkpoints/ns/job/0/checkpoint_3/manifest_1.json");
+        let checkpoint = store.download(&latest).await.unwrap();
+        assert_eq!(checkpoint.states, states("3"));
+        assert_eq!(checkpoint.manifest.epoch, 0);
+        assert_eq!(checkpoint.manifest.checkpoint, 3);
+        assert_eq!(checkpoint.manifest.partition, 1);
+        assert_eq!(
+            store.latest_manifest(&job_id, 3).await.unwrap().unwrap(),
+            "checkpoints/ns/job/0/checkpoint_1/manifest_3.json"
+        );
+
+        // only the latest 2 checkpoints of the subdataflow are retained
+        let keys = storage
+            .objects
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                "checkpoints/ns/job/0/checkpoint_1/manifest_3.json",
+                "checkpoints/ns/job/0/checkpoint_1/operator_3.state",
+                "checkpoints/ns/job/0/checkpoint_2/manifest_1.json",
+                "checkpoints/ns/job/0/checkpoint_2/operator_1.state",
+                "checkpoints/ns/job/0/checkpoint_2/operator_2.state",
+                "checkpoints/ns/job/0/checkpoint_3/manifest_1.json",
+                "checkpoints/ns/job/0/checkpoint_3/operator_1.state",
+                "checkpoints/ns/job/0/checkpoint_3/operator_2.state",
+                "checkpoints/ns/job/1/checkpoint_1/operator_1.state",
+            ]
+        );
+
+        // artifacts are verified by the manifest
+        storage.objects.lock().unwrap().insert(
+            "checkpoints/ns/job/0/checkpoint_3/operator_2.state".to_string(),
+            b"3-x".to_vec(),
+        );
+        assert_eq!(
+            store.download(&latest).await,
+            Err(SnapshotError::Corrupted(
+                "checkpoints/ns/job/0/checkpoint_3/operator_2.state".to_string()
+            ))
+        );
+        assert!(matches!(
+            store
+                .download("checkpoints/ns/job/0/checkpoint_1/manifest_1.json")
+                .await,
+            Err(SnapshotError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_store_savepoints() {
+        let storage = mock_storage().await;
+        let store = store_builder(&storage.endpoint, 1).build().unwrap();
+        let job_id = job_id();
+
+        let first = store
+            .upload_savepoint(&job_id, "savepoint_1", &states("1"))
+            .await
+            .unwrap();
+        assert_eq!(
+            first,
+            "checkpoints/ns/job/savepoints/savepoint_1/manifest.json"
+        );
+        let second = store
+            .upload_savepoint(&job_id, "savepoint_2", &states("2"))
+            .await
+            .unwrap();
+        // savepoints are not expired by the retention of checkpoints
+        for checkpoint in 1..=2 {
+            store
+                .upload(
+                    &job_id,
+                    1,
+                    0,
+                    checkpoint,
+                    &states("checkpoint"),
+                    &Default::default(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let savepoints = store.list_savepoints(&job_id).await.unwrap();
+        assert_eq!(
+            savepoints
+                .iter()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>(),
+            vec![first.as_str(), second.as_str()]
+        );
+        assert_eq!(savepoints[0].1.artifacts.len(), 2);
+        assert_eq!(store.download(&second).await.unwrap().states, states("2"));
+        // savepoints are not mistaken for checkpoints
+        assert_eq!(
+            store.latest_manifest(&job_id, 1).await.unwrap().unwrap(),
+            "checkpoints/ns/job/0/checkpoint_2/manifest_1.json"
+        );
+
+        // only savepoints of the job can be deleted
+        let other_job = ResourceId::new("ns", "other");
+        assert!(matches!(
+            store.delete_savepoint(&other_job, &first).await,
+            Err(SnapshotError::NotFound(_))
+        ));
+        assert!(matches!(
+            store
+                .delete_savepoint(&job_id, "checkpoints/ns/job/0/checkpoint_2/manifest_1.json")
+                .await,
+            Err(SnapshotError::NotFound(_))
+        ));
+        store.delete_savepoint(&job_id, &first).await.unwrap();
+        assert!(storage
+            .objects
+            .lock()
+            .unwrap()
+            .keys()
+            .all(|key| !key.contains("savepoint_1")));
+        assert_eq!(store.list_savepoints(&job_id).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_store_warm_start() {
+        let storage = mock_storage().await;
+        let store = store_builder(&storage.endpoint, 3).build().unwrap();
+        let job_id = job_id();
+        let schemas = |schemas: &[(u32, &str)]| {
+            schemas
+                .iter()
+                .map(|(operator_id, schema)| (*operator_id, schema.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        assert_eq!(store.latest_manifests(&job_id).await, Ok(vec![]));
+        for checkpoint in 1..=2 {
+            store
+                .upload(
+                    &job_id,
+                    1,
+                    0,
+                    checkpoint,
+                    &states(&checkpoint.to_string()),
+                    &schemas(&[(1, "mapper"), (2, "reducer")]),
+                )
+                .await
+                .unwrap();
+        }
+        store
+            .upload(
+                &job_id,
+                3,
+                1,
+                1,
+                &BTreeMap::from_iter([(3, b"other".to_vec())]),
+                &schemas(&[(3, "sink")]),
+            )
+            .await
+            .unwrap();
+        // a checkpoint uploaded by a former version doesn't record the state schemas
+        store
+            .upload(
+                &job_id,
+                4,
+                0,
+                1,
+                &BTreeMap::from_iter([(4, b"former".to_vec())]),
+                &Default::default(),
+            )
+            .await
+            .unwrap();
+        // savepoints are not the latest checkpoints
+        store
+            .upload_savepoint(&job_id, "savepoint", &states("savepoint"))
+            .await
+            .unwrap();
+
+        let manifests = store.latest_manifests(&job_id).await.unwrap();
+        assert_eq!(
+            manifests,
+            vec![
+                "checkpoints/ns/job/0/checkpoint_2/manifest_1.json",
+                "checkpoints/ns/job/1/checkpoint_1/manifest_3.json",
+                "checkpoints/ns/job/0/checkpoint_1/manifest_4.json",
+            ]
+        );
+
+        // the reducer is restored into an operator of another type, so it starts with empty state
+        let warm_start = store
+            .warm_start(
+                &manifests,
+                &schemas(&[(1, "mapper"), (2, "filter"), (3, "sink"), (4, "sink")]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            warm_start.states,
+            BTreeMap::from_iter([(1, b"2-1".to_vec()), (3, b"other".to_vec())])
+        );
+        assert_eq!(warm_start.warnings.len(), 2);
+        assert!(warm_start.warnings[0].starts_with("operator 2 starts with empty state"));
+        assert!(warm_start.warnings[1].starts_with("operator 4 starts with empty state"));
+
+        // operators of other subdataflows and removed operators are ignored, and their artifacts are not downloaded
+        storage.objects.lock().unwrap().insert(
+            "checkpoints/ns/job/0/checkpoint_2/operator_2.state".to_string(),
+            b"corrupted".to_vec(),
+        );
+        let warm_start = store
+            .warm_start(&manifests, &schemas(&[(1, "mapper"), (5, "sink")]))
+            .await
+            .unwrap();
+        assert_eq!(
+            warm_start,
+            WarmStart {
+                states: BTreeMap::from_iter([(1, b"2-1".to_vec())]),
+                warnings: vec![],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_uploader() {
+        let storage = mock_storage().await;
+        let store = store_builder(&storage.endpoint, 3).build().unwrap();
+        let job_id = job_id();
+
+        let (uploader, tx) = CheckpointUploader::new(&store, &job_id, 1, None);
+        assert_eq!(uploader.get_epoch(), 0);
+        let metrics = uploader.get_metrics();
+        let handler = tokio::spawn(uploader.run());
+
+        // the local checkpoint is kept even if it fails to be uploaded
+        storage.unavailable.store(true, Ordering::SeqCst);
+        tx.send(LocalCheckpoint {
+            operator_id: 1,
+            state: b"a".to_vec(),
+            completed_at: Instant::now(),
+        })
+        .unwrap();
+        while metrics
+            .lock()
+            .unwrap()
+            .get(CHECKPOINT_NON_DURABLE_METRIC)
+            .is_none()
+        {
+            tokio::task::yield_now().await;
+        }
+        assert!(storage.objects.lock().unwrap().is_empty());
+
+        // the next checkpoint uploads the latest states of all operators
+        storage.unavailable.store(false, Ordering::SeqCst);
+        tx.send(LocalCheckpoint {
+            operator_id: 2,
+            state: b"b".to_vec(),
+            completed_at: Instant::now(),
+        })
+        .unwrap();
+        drop(tx);
+        handler.await.unwrap();
+
+        let metrics = metrics.lock().unwrap().clone();
+        assert_eq!(metrics.get(CHECKPOINT_NON_DURABLE_METRIC), Some(&1));
+        assert_eq!(metrics.get(CHECKPOINT_UPLOADED_METRIC), Some(&1));
+
+        let latest = store.latest_manifest(&job_id, 1).await.unwrap().unwrap();
+        assert_eq!(latest, "checkpoints/ns/job/0/checkpoint_2/manifest_1.json");
+        let checkpoint = store.download(&latest).await.unwrap();
+        assert_eq!(
+            checkpoint.states,
+            BTreeMap::from_iter([(1, b"a".to_vec()), (2, b"b".to_vec())])
+        );
+
+        // a restored subdataflow starts a new epoch with the restored states
+        let (uploader, tx) = CheckpointUploader::new(&store, &job_id, 1, Some(&checkpoint));
+        assert_eq!(uploader.get_epoch(), 1);
+        let handler = tokio::spawn(uploader.run());
+        tx.send(LocalCheckpoint {
+            operator_id: 2,
+            state: b"c".to_vec(),
+            completed_at: Instant::now(),
+        })
+        .unwrap();
+        drop(tx);
+        handler.await.unwrap();
+
+        let latest = store.latest_manifest(&job_id, 1).await.unwrap().unwrap();
+        assert_eq!(latest, "checkpoints/ns/job/1/checkpoint_1/manifest_1.json");
+        assert_eq!(
+            store.download(&latest).await.unwrap().states,
+            BTreeMap::from_iter([(1, b"a".to_vec()), (2, b"c".to_vec())])
+        );
+    }
+}
@@ -0,0 +1,1331 @@
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+use proto::{
+    common::{operator_info::Details, Entry, FilterExpr, MapExpr, Route},
+    common_impl::DataflowValidateError,
+    sql_expr::{BinaryOp, Expr, Function, Literal, UnaryOp},
+};
+
+use crate::types::TypedValue;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// the expression of the operator is invalid
+    InvalidExpr(String),
+    /// the operands have types which the operator doesn't support
+    TypeMismatch(String),
+    /// an argument of a function is out of its domain
+    InvalidArgument(String),
+    DivisionByZero,
+    /// the result of an arithmetic operation is out of range
+    Overflow,
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::InvalidExpr(msg) => write!(f, "invalid expression: {}", msg),
+            EvalError::TypeMismatch(msg) => write!(f, "type mismatch: {}", msg),
+            EvalError::InvalidArgument(msg) => write!(f, "invalid argument: {}", msg),
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+            EvalError::Overflow => write!(f, "numeric overflow"),
+        }
+    }
+}
+
+impl From<DataflowValidateError> for EvalError {
+    fn from(err: DataflowValidateError) -> Self {
+        match err {
+            DataflowValidateError::InvalidSqlExpr(msg) | DataflowValidateError::InvalidRoute(msg) => {
+                Self::InvalidExpr(msg)
+            }
+            _ => Self::InvalidExpr(format!("{:?}", err)),
+        }
+    }
+}
+
+/// the value of a compiled expression, a field is borrowed from the payload without copying
+type Value<'a> = Cow<'a, TypedValue>;
+
+/// a compiled expression node. Literals are converted into [`TypedValue`]s and constant subexpressions are folded
+#[derive(Debug, Clone)]
+enum Node {
+    Const(TypedValue),
+    Field(Box<[String]>),
+    Neg(Box<Node>),
+    Not(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    IsNull(Box<Node>, bool),
+    Function(Function, Vec<Node>),
+    Case {
+        operand: Option<Box<Node>>,
+        branches: Vec<(Node, Node)>,
+        otherwise: Option<Box<Node>>,
+    },
+}
+
+/// [`CompiledExpr`] evaluates an [`Expr`] over payloads without parsing it again.
+///
+/// The evaluation follows the SQL semantics:
+/// - absent fields are null, and operators and functions return null if any operand is null,
+/// except `AND`, `OR`, `IS NULL`, `coalesce` and `CASE`
+/// - `AND` and `OR` are three-valued and short-circuit: `FALSE AND NULL` is false and `TRUE OR NULL` is true
+/// - integer arithmetic is checked and division truncates. An integer and a decimal are computed as decimals
+/// - division by zero, overflow and operands of unsupported types are errors, which are handled by the error policy of the operator
+#[derive(Debug, Clone)]
+pub struct CompiledExpr {
+    node: Node,
+}
+
+impl CompiledExpr {
+    pub fn compile(expr: &Expr) -> Self {
+        Self {
+            node: compile_node(expr),
+        }
+    }
+
+    pub fn eval<'a>(&'a self, payload: &'a TypedValue) -> Result<Value<'a>, EvalError> {
+        eval_node(&self.node, payload)
+    }
+}
+
+fn compile_node(expr: &Expr) -> Node {
+    let node = match expr {
+        Expr::Literal(literal) => Node::Const(match literal {
+            Literal::Null => TypedValue::Null,
+            Literal::Boolean(value) => TypedValue::Boolean(*value),
+            Literal::Integer(value) => TypedValue::BigInt(*value),
+            Literal::Float(value) => TypedValue::Number(*value),
+            Literal::String(value) => TypedValue::String(value.clone()),
+        }),
+        Expr::Field(path) => Node::Field(path.clone().into_boxed_slice()),
+        Expr::Unary(UnaryOp::Neg, expr) => Node::Neg(Box::new(compile_node(expr))),
+        Expr::Unary(UnaryOp::Not, expr) => Node::Not(Box::new(compile_node(expr))),
+        Expr::Binary(op, left, right) => Node::Binary(
+            *op,
+            Box::new(compile_node(left)),
+            Box::new(compile_node(right)),
+        ),
+        Expr::IsNull { expr, negated } => Node::IsNull(Box::new(compile_node(expr)), *negated),
+        Expr::Function(func, args) => {
+            Node::Function(*func, args.iter().map(compile_node).collect())
+        }
+        Expr::Case {
+            operand,
+            branches,
+            otherwise,
+        } => Node::Case {
+            operand: operand
+                .as_ref()
+                .map(|operand| Box::new(compile_node(operand))),
+            branches: branches
+                .iter()
+                .map(|(condition, result)| (compile_node(condition), compile_node(result)))
+                .collect(),
+            otherwise: otherwise
+                .as_ref()
+                .map(|otherwise| Box::new(compile_node(otherwise))),
+        },
+    };
+    fold_constant(node)
+}
+
+/// a node without field references is evaluated once at compile time. If it fails, it's kept so the error is reported by each event
+fn fold_constant(node: Node) -> Node {
+    let is_constant = match &node {
+        Node::Const(_) | Node::Field(_) => return node,
+        Node::Neg(child) | Node::Not(child) | Node::IsNull(child, _) => is_const(child),
+        Node::Binary(_, left, right) => is_const(left) && is_const(right),
+        Node::Function(_, args) => args.iter().all(is_const),
+        Node::Case {
+            operand,
+            branches,
+            otherwise,
+        } => {
+            operand.iter().all(|operand| is_const(operand))
+                && branches
+                    .iter()
+                    .all(|(condition, result)| is_const(condition) && is_const(result))
+                && otherwise.iter().all(|otherwise| is_const(otherwise))
+        }
+    };
+    if !is_constant {
+        return node;
+    }
+    match eval_node(&node, &TypedValue::Null) {
+        Ok(value) => Node::Const(value.into_owned()),
+        Err(_) => node,
+    }
+}
+
+fn is_const(node: &Node) -> bool {
+    matches!(node, Node::Const(_))
+}
+
+fn is_null(value: &TypedValue) -> bool {
+    matches!(value, TypedValue::Null | TypedValue::Invalid)
+}
+
+const NULL: Value<'static> = Cow::Owned(TypedValue::Null);
+
+fn select_field<'a>(path: &[String], payload: &'a TypedValue) -> Value<'a> {
+    let mut current = payload;
+    for name in path {
+        match current {
+            TypedValue::Object(object) => match object.get(name) {
+                Some(value) => current = value,
+                None => return NULL,
+            },
+            _ => return NULL,
+        }
+    }
+    if is_null(current) {
+        NULL
+    } else {
+        Cow::Borrowed(current)
+    }
+}
+
+/// evaluate a boolean operand, which is `None` if it's null
+fn eval_bool(node: &Node, payload: &TypedValue, context: &str) -> Result<Option<bool>, EvalError> {
+    match eval_node(node, payload)?.as_ref() {
+        TypedValue::Boolean(value) => Ok(Some(*value)),
+        value if is_null(value) => Ok(None),
+        value => Err(EvalError::TypeMismatch(format!(
+            "{} expects a boolean but found {:?}",
+            context,
+            value.get_type()
+        ))),
+    }
+}
+
+fn eval_node<'a>(node: &'a Node, payload: &'a TypedValue) -> Result<Value<'a>, EvalError> {
+    match node {
+        Node::Const(value) => Ok(Cow::Borrowed(value)),
+        Node::Field(path) => Ok(select_field(path, payload)),
+        Node::Neg(child) => match eval_node(child, payload)?.as_ref() {
+            TypedValue::BigInt(value) => value
+                .checked_neg()
+                .map(|value| Cow::Owned(TypedValue::BigInt(value)))
+                .ok_or(EvalError::Overflow),
+            TypedValue::Number(value) => Ok(Cow::Owned(TypedValue::Number(-value))),
+            value if is_null(value) => Ok(NULL),
+            value => Err(EvalError::TypeMismatch(format!(
+                "can't negate {:?}",
+                value.get_type()
+            ))),
+        },
+        Node::Not(child) => Ok(eval_bool(child, payload, "NOT")?
+            .map(|value| Cow::Owned(TypedValue::Boolean(!value)))
+            .unwrap_or(NULL)),
+        Node::Binary(BinaryOp::And, left, right) => {
+            let left = eval_bool(left, payload, "AND")?;
+            if left == Some(false) {
+                return Ok(Cow::Owned(TypedValue::Boolean(false)));
+            }
+            Ok(match (left, eval_bool(right, payload, "AND")?) {
+                (_, Some(false)) => Cow::Owned(TypedValue::Boolean(false)),
+                (Some(true), Some(true)) => Cow::Owned(TypedValue::Boolean(true)),
+                _ => NULL,
+            })
+        }
+        Node::Binary(BinaryOp::Or, left, right) => {
+            let left = eval_bool(left, payload, "OR")?;
+            if left == Some(true) {
+                return Ok(Cow::Owned(TypedValue::Boolean(true)));
+            }
+            Ok(match (left, eval_bool(right, payload, "OR")?) {
+                (_, Some(true)) => Cow::Owned(TypedValue::Boolean(true)),
+                (Some(false), Some(false)) => Cow::Owned(TypedValue::Boolean(false)),
+                _ => NULL,
+            })
+        }
+        Node::Binary(op, left, right) => {
+            let left = eval_node(left, payload)?;
+            let right = eval_node(right, payload)?;
+            if is_null(&left) || is_null(&right) {
+                return Ok(NULL);
+            }
+            eval_binary(*op, &left, &right).map(Cow::Owned)
+        }
+        Node::IsNull(child, negated) => Ok(Cow::Owned(TypedValue::Boolean(
+            is_null(eval_node(child, payload)?.as_ref()) != *negated,
+        ))),
+        Node::Function(Function::Coalesce, args) => {
+            for arg in args {
+                let value = eval_node(arg, payload)?;
+                if !is_null(&value) {
+                    return Ok(value);
+                }
+            }
+            Ok(NULL)
+        }
+        Node::Function(func, args) => {
+            let mut values = Vec::with_capacity(args.len());
+            for arg in args {
+                let value = eval_node(arg, payload)?;
+                if is_null(&value) {
+                    return Ok(NULL);
+                }
+                values.push(value);
+            }
+            eval_function(*func, &values).map(Cow::Owned)
+        }
+        Node::Case {
+            operand,
+            branches,
+            otherwise,
+        } => {
+            let operand = match operand {
+                Some(operand) => Some(eval_node(operand, payload)?),
+                None => None,
+            };
+            for (condition, result) in branches {
+                let matched = match &operand {
+                    Some(operand) => {
+                        let value = eval_node(condition, payload)?;
+                        !is_null(operand)
+                            && !is_null(&value)
+                            && compare(operand, &value)? == Ordering::Equal
+                    }
+                    None => eval_bool(condition, payload, "WHEN")? == Some(true),
+                };
+                if matched {
+                    return eval_node(result, payload);
+                }
+            }
+            match otherwise {
+                Some(otherwise) => eval_node(otherwise, payload),
+                None => Ok(NULL),
+            }
+        }
+    }
+}
+
+/// evaluate a binary operator other than `AND` and `OR` over non-null operands
+fn eval_binary(
+    op: BinaryOp,
+    left: &TypedValue,
+    right: &TypedValue,
+) -> Result<TypedValue, EvalError> {
+    let ordering = |expected: fn(Ordering) -> bool| {
+        compare(left, right).map(|ordering| TypedValue::Boolean(expected(ordering)))
+    };
+    match op {
+        BinaryOp::Eq => ordering(|ordering| ordering == Ordering::Equal),
+        BinaryOp::NotEq => ordering(|ordering| ordering != Ordering::Equal),
+        BinaryOp::Lt => ordering(|ordering| ordering == Ordering::Less),
+        BinaryOp::LtEq => ordering(|ordering| ordering != Ordering::Greater),
+        BinaryOp::Gt => ordering(|ordering| ordering == Ordering::Greater),
+        BinaryOp::GtEq => ordering(|ordering| ordering != Ordering::Less),
+        BinaryOp::Concat => Ok(TypedValue::String(
+            to_concat_string(left)? + &to_concat_string(right)?,
+        )),
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => {
+            eval_arithmetic(op, left, right)
+        }
+        BinaryOp::And | BinaryOp::Or => unreachable!("boolean operators are short-circuit"),
+    }
+}
+
+fn to_concat_string(value: &TypedValue) -> Result<String, EvalError> {
+    match value {
+        TypedValue::String(_)
+        | TypedValue::BigInt(_)
+        | TypedValue::Number(_)
+        | TypedValue::Boolean(_) => Ok(value.to_string()),
+        _ => Err(EvalError::TypeMismatch(format!(
+            "can't concatenate {:?}",
+            value.get_type()
+        ))),
+    }
+}
+
+fn type_mismatch(op: BinaryOp, left: &TypedValue, right: &TypedValue) -> EvalError {
+    EvalError::TypeMismatch(format!(
+        "{:?} and {:?} are not supported by {:?}",
+        left.get_type(),
+        right.get_type(),
+        op
+    ))
+}
+
+/// numbers are compared by value, strings lexicographically and `FALSE` is less than `TRUE`
+fn compare(left: &TypedValue, right: &TypedValue) -> Result<Ordering, EvalError> {
+    let ordering = match (left, right) {
+        (TypedValue::BigInt(left), TypedValue::BigInt(right)) => Some(left.cmp(right)),
+        (TypedValue::BigInt(left), TypedValue::Number(right)) => (*left as f64).partial_cmp(right),
+        (TypedValue::Number(left), TypedValue::BigInt(right)) => left.partial_cmp(&(*right as f64)),
+        (TypedValue::Number(left), TypedValue::Number(right)) => left.partial_cmp(right),
+        (TypedValue::String(left), TypedValue::String(right)) => Some(left.cmp(right)),
+        (TypedValue::Boolean(left), TypedValue::Boolean(right)) => Some(left.cmp(right)),
+        _ => None,
+    };
+    ordering.ok_or_else(|| {
+        EvalError::TypeMismatch(format!(
+            "can't compare {:?} with {:?}",
+            left.get_type(),
+            right.get_type()
+        ))
+    })
+}
+
+fn eval_arithmetic(
+    op: BinaryOp,
+    left: &TypedValue,
+    right: &TypedValue,
+) -> Result<TypedValue, EvalError> {
+    let (left, right) = match (left, right) {
+        (TypedValue::BigInt(left), TypedValue::BigInt(right)) => {
+            let result = match op {
+                BinaryOp::Add => left.checked_add(*right),
+                BinaryOp::Sub => left.checked_sub(*right),
+                BinaryOp::Mul => left.checked_mul(*right),
+                BinaryOp::Div | BinaryOp::Mod if *right == 0 => {
+                    return Err(EvalError::DivisionByZero)
+                }
+                BinaryOp::Div => left.checked_div(*right),
+                // `i64::MIN % -1` is 0 rather than an overflow
+                _ => Some(left.wrapping_rem(*right)),
+            };
+            return result.map(TypedValue::BigInt).ok_or(EvalError::Overflow);
+        }
+        (TypedValue::BigInt(left), TypedValue::Number(right)) => (*left as f64, *right),
+        (TypedValue::Number(left), TypedValue::BigInt(right)) => (*left, *right as f64),
+        (TypedValue::Number(left), TypedValue::Number(right)) => (*left, *right),
+        _ => return Err(type_mismatch(op, left, right)),
+    };
+    let result = match op {
+        BinaryOp::Add => left + right,
+        BinaryOp::Sub => left - right,
+        BinaryOp::Mul => left * right,
+        BinaryOp::Div | BinaryOp::Mod if right == 0.0 => return Err(EvalError::DivisionByZero),
+        BinaryOp::Div => left / right,
+        _ => left % right,
+    };
+    if result.is_finite() {
+        Ok(TypedValue::Number(result))
+    } else {
+        Err(EvalError::Overflow)
+    }
+}
+
+fn eval_function(func: Function, args: &[Value]) -> Result<TypedValue, EvalError> {
+    let get_string = |index: usize| match args[index].as_ref() {
+        TypedValue::String(value) => Ok(value.as_str()),
+        value => Err(EvalError::TypeMismatch(format!(
+            "{} expects a string but found {:?}",
+            func.get_name(),
+            value.get_type()
+        ))),
+    };
+    let get_integer = |index: usize| match args[index].as_ref() {
+        TypedValue::BigInt(value) => Ok(*value),
+        value => Err(EvalError::TypeMismatch(format!(
+            "{} expects an integer but found {:?}",
+            func.get_name(),
+            value.get_type()
+        ))),
+    };
+    match func {
+        Function::Lower => Ok(TypedValue::String(get_string(0)?.to_lowercase())),
+        Function::Upper => Ok(TypedValue::String(get_string(0)?.to_uppercase())),
+        Function::Trim => Ok(TypedValue::String(get_string(0)?.trim().to_string())),
+        Function::Length => Ok(TypedValue::BigInt(get_string(0)?.chars().count() as i64)),
+        Function::Substr => {
+            let value = get_string(0)?;
+            let start = get_integer(1)?;
+            // the characters in [start, start + length) of the 1-based positions
+            let end = match args.get(2) {
+                Some(_) => {
+                    let length = get_integer(2)?;
+                    if length < 0 {
+                        return Err(EvalError::InvalidArgument(format!(
+                            "negative substring length {}",
+                            length
+                        )));
+                    }
+                    Some(start.saturating_add(length))
+                }
+                None => None,
+            };
+            let skip = start.max(1) - 1;
+            let take = end.map(|end| end.saturating_sub(1).saturating_sub(skip).max(0));
+            let chars = value.chars().skip(skip as usize);
+            Ok(TypedValue::String(match take {
+                Some(take) => chars.take(take as usize).collect(),
+                None => chars.collect(),
+            }))
+        }
+        Function::Coalesce => unreachable!("coalesce is evaluated lazily"),
+    }
+}
+
+/// [`ExprFilter`] is the runtime of the `FilterExpr` operator. A payload is kept only if the expression is true
+#[derive(Debug, Clone)]
+pub struct ExprFilter {
+    predicate: CompiledExpr,
+}
+
+impl ExprFilter {
+    pub fn new(filter_expr: &FilterExpr) -> Result<Self, EvalError> {
+        Ok(Self {
+            predicate: CompiledExpr::compile(&filter_expr.get_expr()?),
+        })
+    }
+
+    pub fn filter(&self, payload: &TypedValue) -> Result<bool, EvalError> {
+        eval_bool(&self.predicate.node, payload, "filter expression")
+            .map(|value| value == Some(true))
+    }
+}
+
+/// [`ExprMapper`] is the runtime of the `MapExpr` operator. It builds a new object whose fields are the select items
+#[derive(Debug, Clone)]
+pub struct ExprMapper {
+    items: Vec<(String, CompiledExpr)>,
+}
+
+impl ExprMapper {
+    pub fn new(map_expr: &MapExpr) -> Result<Self, EvalError> {
+        Ok(Self {
+            items: map_expr
+                .get_select()?
+                .items
+                .iter()
+                .map(|item| (item.alias.clone(), CompiledExpr::compile(&item.expr)))
+                .collect(),
+        })
+    }
+
+    pub fn map(&self, payload: &TypedValue) -> Result<TypedValue, EvalError> {
+        let mut object = BTreeMap::new();
+        for (alias, expr) in &self.items {
+            object.insert(alias.clone(), expr.eval(payload)?.into_owned());
+        }
+        Ok(TypedValue::Object(object))
+    }
+}
+
+/// [`RouteOperator`] is the runtime of the `Route` operator. A payload is sent to the downstream of the first rule whose predicate is true,
+/// or to the default downstream if none of them is. The predicates are compiled once per task
+#[derive(Debug, Clone)]
+pub struct RouteOperator {
+    rules: Vec<(CompiledExpr, u32)>,
+    default_output: Option<u32>,
+}
+
+impl RouteOperator {
+    pub fn new(route: &Route) -> Result<Self, EvalError> {
+        Ok(Self {
+            rules: route
+                .get_rules()?
+                .iter()
+                .map(|(predicate, downstream)| (CompiledExpr::compile(predicate), *downstream))
+                .collect(),
+            default_output: route.get_default_output()?,
+        })
+    }
+
+    /// the downstream of the payload, it's none if the payload is dropped
+    pub fn route(&self, payload: &TypedValue) -> Result<Option<u32>, EvalError> {
+        for (predicate, downstream) in &self.rules {
+            if eval_bool(&predicate.node, payload, "route predicate")? == Some(true) {
+                return Ok(Some(*downstream));
+            }
+        }
+        Ok(self.default_output)
+    }
+
+    /// group the payloads of an event by their downstreams. The payloads keep their order in each group
+    pub fn process(&self, data: &[Entry]) -> Result<BTreeMap<u32, Vec<Entry>>, EvalError> {
+        let mut outputs = BTreeMap::<u32, Vec<Entry>>::new();
+        for entry in data {
+            if let Some(downstream) = self.route(&TypedValue::from(entry))? {
+                outputs.entry(downstream).or_default().push(entry.clone());
+            }
+        }
+        Ok(outputs)
+    }
+}
+
+/// the runtime of the `FilterExpr` and `MapExpr` operators. The expression is compiled once per task
+#[derive(Debug, Clone)]
+pub enum SqlExprOperator {
+    Filter(ExprFilter),
+    Map(ExprMapper),
+}
+
+impl SqlExprOperator {
+    /// it returns `None` if the operator is neither `FilterExpr` nor `MapExpr`
+    pub fn new(details: &Details) -> Option<Result<Self, EvalError>> {
+        match details {
+            Details::FilterExpr(filter_expr) => {
+                Some(ExprFilter::new(filter_expr).map(Self::Filter))
+            }
+            Details::MapExpr(map_expr) => Some(ExprMapper::new(map_expr).map(Self::Map)),
+            _ => None,
+        }
+    }
+
+    /// process the payloads of an event. The kept payloads of a filter are not decoded again
+    pub fn process(&self, data: &[Entry]) -> Result<Vec<Entry>, EvalError> {
+        let mut outputs = Vec::with_capacity(data.len());
+        for entry in data {
+            let payload = TypedValue::from(entry);
+            match self {
+                Self::Filter(filter) => {
+                    if filter.filter(&payload)? {
+                        outputs.push(entry.clone());
+                    }
+                }
+                Self::Map(mapper) => {
+                    let value = mapper.map(&payload)?;
+                    let mut new_entry = Entry::default();
+                    new_entry.set_data_type(value.get_type());
+                    new_entry.value = value.get_data_bytes();
+                    outputs.push(new_entry);
+                }
+            }
+        }
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proto::{
+        common::{operator_info::Details, route, Entry, FilterExpr, MapExpr, Route},
+        sql_expr::{BinaryOp, Expr, Function, Literal, UnaryOp},
+    };
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::types::TypedValue;
+
+    use super::{CompiledExpr, EvalError, ExprFilter, ExprMapper, RouteOperator, SqlExprOperator};
+
+    fn payload() -> TypedValue {
+        TypedValue::from_json_value(serde_json::json!({
+            "amount": 150,
+            "rate": 0.5,
+            "country": "SE",
+            "name": "Ärlig Ström",
+            "vip": true,
+            "note": null,
+            "user": {"id": 7, "tags": ["a"]},
+        }))
+    }
+
+    fn eval(sql: &str) -> Result<TypedValue, EvalError> {
+        CompiledExpr::compile(&Expr::parse(sql).unwrap())
+            .eval(&payload())
+            .map(|value| value.into_owned())
+    }
+
+    #[test]
+    fn test_eval_sql_expr() {
+        let cases = [
+            ("amount > 100 AND country = 'SE'", TypedValue::Boolean(true)),
+            ("amount * rate", TypedValue::Number(75.0)),
+            ("amount / 7", TypedValue::BigInt(21)),
+            ("-amount % 7", TypedValue::BigInt(-3)),
+            ("amount = 150.0", TypedValue::Boolean(true)),
+            ("user.id + 1", TypedValue::BigInt(8)),
+            (
+                "country || '-' || amount || vip",
+                TypedValue::String("SE-150true".to_string()),
+            ),
+            (
+                "upper(name) || lower(name)",
+                TypedValue::String("ÄRLIG STRÖMärlig ström".to_string()),
+            ),
+            (
+                "substr(name, 3)",
+                TypedValue::String("lig Ström".to_string()),
+            ),
+            ("substr(name, 0, 3)", TypedValue::String("Är".to_string())),
+            (
+                "substring(name, 7, 100)",
+                TypedValue::String("Ström".to_string()),
+            ),
+            ("length(trim('  ab  '))", TypedValue::BigInt(2)),
+            // nulls
+            (
+                "note IS NULL AND missing IS NULL AND user.missing.x IS NULL",
+                TypedValue::Boolean(true),
+            ),
+            ("amount IS NOT NULL", TypedValue::Boolean(true)),
+            ("note = note", TypedValue::Null),
+            ("amount + missing", TypedValue::Null),
+            ("lower(note)", TypedValue::Null),
+            ("NOT note", TypedValue::Null),
+            ("note AND FALSE", TypedValue::Boolean(false)),
+            ("note OR TRUE", TypedValue::Boolean(true)),
+            ("note AND TRUE", TypedValue::Null),
+            (
+                "coalesce(note, missing, country)",
+                TypedValue::String("SE".to_string()),
+            ),
+            // short circuit skips the errors of the right side
+            ("FALSE AND 1 / 0 = 1", TypedValue::Boolean(false)),
+            ("vip OR country + 1 = 1", TypedValue::Boolean(true)),
+            // case
+            (
+                "CASE WHEN amount > 1000 THEN 'high' WHEN amount > 100 THEN 'mid' ELSE 'low' END",
+                TypedValue::String("mid".to_string()),
+            ),
+            (
+                "CASE country WHEN 'NO' THEN 1 WHEN 'SE' THEN 2 END",
+                TypedValue::BigInt(2),
+            ),
+            ("CASE note WHEN NULL THEN 1 END", TypedValue::Null),
+            ("CASE WHEN note THEN 1 ELSE 0 END", TypedValue::BigInt(0)),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(eval(sql), Ok(expected), "{sql}");
+        }
+
+        assert_eq!(eval("amount / 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("rate % 0"), Err(EvalError::DivisionByZero));
+        assert_eq!(
+            eval("9223372036854775807 + amount"),
+            Err(EvalError::Overflow)
+        );
+        assert!(matches!(
+            eval("country > 1"),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval("amount AND vip"),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval("user || 'x'"),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval("upper(amount)"),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval("substr(name, 1, -1)"),
+            Err(EvalError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_compile_folds_constants() {
+        let expr = CompiledExpr::compile(&Expr::parse("1 + 2 * 3 = amount").unwrap());
+        assert_eq!(
+            format!("{:?}", expr.node),
+            format!(
+                "{:?}",
+                super::Node::Binary(
+                    BinaryOp::Eq,
+                    Box::new(super::Node::Const(TypedValue::BigInt(7))),
+                    Box::new(super::Node::Field(
+                        vec!["amount".to_string()].into_boxed_slice()
+                    ))
+                )
+            )
+        );
+        // the constant errors are reported at runtime
+        let expr = CompiledExpr::compile(&Expr::parse("1 / 0").unwrap());
+        assert_eq!(expr.eval(&payload()), Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_sql_expr_operator() {
+        let filter = ExprFilter::new(&FilterExpr {
+            expression: "amount > 100 AND country = 'SE'".to_string(),
+        })
+        .unwrap();
+        assert_eq!(filter.filter(&payload()), Ok(true));
+        assert_eq!(filter.filter(&TypedValue::Null), Ok(false));
+        assert!(ExprFilter::new(&FilterExpr {
+            expression: "amount".to_string(),
+        })
+        .unwrap()
+        .filter(&payload())
+        .is_err());
+        assert!(matches!(
+            ExprFilter::new(&FilterExpr {
+                expression: "amount >".to_string(),
+            }),
+            Err(EvalError::InvalidExpr(_))
+        ));
+
+        let mapper = ExprMapper::new(&MapExpr {
+            expression: "SELECT amount * rate AS amount_eur, user.id, missing".to_string(),
+        })
+        .unwrap();
+        assert_eq!(
+            mapper.map(&payload()),
+            Ok(TypedValue::from_json_value(serde_json::json!({
+                "amount_eur": 75.0,
+                "id": 7,
+                "missing": null,
+            })))
+        );
+
+        let to_entry = |value: serde_json::Value| {
+            let value = TypedValue::from_json_value(value);
+            let mut entry = Entry::default();
+            entry.set_data_type(value.get_type());
+            entry.value = value.get_data_bytes();
+            entry
+        };
+        let data = vec![
+            to_entry(serde_json::json!({"amount": 1})),
+            to_entry(serde_json::json!({"amount": 200})),
+            to_entry(serde_json::json!({})),
+        ];
+        let operator = SqlExprOperator::new(&Details::FilterExpr(FilterExpr {
+            expression: "amount >= 100".to_string(),
+        }))
+        .unwrap()
+        .unwrap();
+        assert_eq!(operator.process(&data), Ok(vec![data[1].clone()]));
+
+        let operator = SqlExprOperator::new(&Details::MapExpr(MapExpr {
+            expression: "SELECT coalesce(amount, 0) * 2 AS double".to_string(),
+        }))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            operator
+                .process(&data)
+                .unwrap()
+                .iter()
+                .map(TypedValue::from)
+                .collect::<Vec<_>>(),
+            [2, 400, 0]
+                .into_iter()
+                .map(|double| TypedValue::from_json_value(serde_json::json!({ "double": double })))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_route_operator() {
+        let rule = |predicate: &str, output: &str| route::Rule {
+            predicate: predicate.to_string(),
+            output: output.to_string(),
+        };
+        let mut route = Route {
+            outputs: [("large", 2), ("swedish", 3), ("others", 4)]
+                .into_iter()
+                .map(|(name, downstream)| (name.to_string(), downstream))
+                .collect(),
+            rules: vec![
+                rule("amount > 100", "large"),
+                rule("country = 'SE'", "swedish"),
+            ],
+            default_output: "others".to_string(),
+        };
+
+        // the first matching rule wins, and null predicates don't match
+        let operator = RouteOperator::new(&route).unwrap();
+        for (value, downstream) in [
+            (serde_json::json!({"amount": 150, "country": "SE"}), Some(2)),
+            (serde_json::json!({"amount": 50, "country": "SE"}), Some(3)),
+            (serde_json::json!({"country": "SE"}), Some(3)),
+            (serde_json::json!({"amount": 50, "country": "NO"}), Some(4)),
+            (serde_json::json!({}), Some(4)),
+        ] {
+            assert_eq!(
+                operator.route(&TypedValue::from_json_value(value)),
+                Ok(downstream)
+            );
+        }
+        assert!(matches!(
+            operator.route(&TypedValue::from_json_value(
+                serde_json::json!({"amount": "x"})
+            )),
+            Err(EvalError::TypeMismatch(_))
+        ));
+
+        // the payloads are grouped by their downstreams, and they're dropped if there is no default output
+        route.default_output = "".to_string();
+        let operator = RouteOperator::new(&route).unwrap();
+        let to_entry = |value: serde_json::Value| {
+            let value = TypedValue::from_json_value(value);
+            let mut entry = Entry::default();
+            entry.set_data_type(value.get_type());
+            entry.value = value.get_data_bytes();
+            entry
+        };
+        let data = vec![
+            to_entry(serde_json::json!({"amount": 200})),
+            to_entry(serde_json::json!({"amount": 1, "country": "NO"})),
+            to_entry(serde_json::json!({"amount": 1, "country": "SE"})),
+            to_entry(serde_json::json!({"amount": 300})),
+        ];
+        let outputs = operator.process(&data).unwrap();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[&2], vec![data[0].clone(), data[3].clone()]);
+        assert_eq!(outputs[&3], vec![data[2].clone()]);
+
+        // a rule which can't be parsed or refers to an undeclared output is invalid
+        route.rules.push(rule("amount >", "large"));
+        assert!(matches!(
+            RouteOperator::new(&route),
+            Err(EvalError::InvalidExpr(_))
+        ));
+        route.rules.pop();
+        route.rules.push(rule("TRUE", "missing"));
+        assert!(matches!(
+            RouteOperator::new(&route),
+            Err(EvalError::InvalidExpr(_))
+        ));
+    }
+
+    /// the value of the reference interpretation, integers are computed in i128 and checked against the range of i64 afterwards
+    #[derive(Debug, Clone, PartialEq)]
+    enum RefValue {
+        Null,
+        Bool(bool),
+        Int(i128),
+        Float(f64),
+        Str(String),
+        Other,
+    }
+
+    fn to_ref_value(value: &serde_json::Value) -> RefValue {
+        match value {
+            serde_json::Value::Null => RefValue::Null,
+            serde_json::Value::Bool(value) => RefValue::Bool(*value),
+            serde_json::Value::Number(number) => match number.as_i64() {
+                Some(value) => RefValue::Int(value as i128),
+                None => RefValue::Float(number.as_f64().unwrap()),
+            },
+            serde_json::Value::String(value) => RefValue::Str(value.clone()),
+            _ => RefValue::Other,
+        }
+    }
+
+    fn from_typed_value(value: &TypedValue) -> RefValue {
+        match value {
+            TypedValue::Null | TypedValue::Invalid => RefValue::Null,
+            TypedValue::Boolean(value) => RefValue::Bool(*value),
+            TypedValue::BigInt(value) => RefValue::Int(*value as i128),
+            TypedValue::Number(value) => RefValue::Float(*value),
+            TypedValue::String(value) => RefValue::Str(value.clone()),
+            _ => RefValue::Other,
+        }
+    }
+
+    /// a straightforward interpretation of the SQL semantics over JSON, the errors are not distinguished
+    fn reference_eval(expr: &Expr, payload: &serde_json::Value) -> Result<RefValue, ()> {
+        let as_bool = |value: RefValue| match value {
+            RefValue::Null => Ok(None),
+            RefValue::Bool(value) => Ok(Some(value)),
+            _ => Err(()),
+        };
+        let as_float = |value: &RefValue| match value {
+            RefValue::Int(value) => Ok(*value as f64),
+            RefValue::Float(value) => Ok(*value),
+            _ => Err(()),
+        };
+        let check_int = |value: i128| {
+            if value >= i64::MIN as i128 && value <= i64::MAX as i128 {
+                Ok(RefValue::Int(value))
+            } else {
+                Err(())
+            }
+        };
+        let check_float = |value: f64| {
+            if value.is_finite() {
+                Ok(RefValue::Float(value))
+            } else {
+                Err(())
+            }
+        };
+        let compare = |left: &RefValue, right: &RefValue| match (left, right) {
+            (RefValue::Int(left), RefValue::Int(right)) => Ok(left.cmp(right)),
+            (RefValue::Str(left), RefValue::Str(right)) => Ok(left.cmp(right)),
+            (RefValue::Bool(left), RefValue::Bool(right)) => Ok(left.cmp(right)),
+            _ => as_float(left)?.partial_cmp(&as_float(right)?).ok_or(()),
+        };
+        let to_text = |value: &RefValue| match value {
+            RefValue::Bool(value) => Ok(value.to_string()),
+            RefValue::Int(value) => Ok(value.to_string()),
+            RefValue::Float(value) => Ok(value.to_string()),
+            RefValue::Str(value) => Ok(value.clone()),
+            _ => Err(()),
+        };
+
+        Ok(match expr {
+            Expr::Literal(Literal::Null) => RefValue::Null,
+            Expr::Literal(Literal::Boolean(value)) => RefValue::Bool(*value),
+            Expr::Literal(Literal::Integer(value)) => RefValue::Int(*value as i128),
+            Expr::Literal(Literal::Float(value)) => RefValue::Float(*value),
+            Expr::Literal(Literal::String(value)) => RefValue::Str(value.clone()),
+            Expr::Field(path) => {
+                let mut current = payload;
+                for name in path {
+                    current = current.get(name).unwrap_or(&serde_json::Value::Null);
+                }
+                to_ref_value(current)
+            }
+            Expr::Unary(UnaryOp::Not, expr) => match as_bool(reference_eval(expr, payload)?)? {
+                Some(value) => RefValue::Bool(!value),
+                None => RefValue::Null,
+            },
+            Expr::Unary(UnaryOp::Neg, expr) => match reference_eval(expr, payload)? {
+                RefValue::Null => RefValue::Null,
+                RefValue::Int(value) => check_int(-value)?,
+                RefValue::Float(value) => RefValue::Float(-value),
+                _ => return Err(()),
+            },
+            Expr::Binary(BinaryOp::And, left, right) => {
+                match as_bool(reference_eval(left, payload)?)? {
+                    Some(false) => RefValue::Bool(false),
+                    left => match (left, as_bool(reference_eval(right, payload)?)?) {
+                        (_, Some(false)) => RefValue::Bool(false),
+                        (None, _) | (_, None) => RefValue::Null,
+                        _ => RefValue::Bool(true),
+                    },
+                }
+            }
+            Expr::Binary(BinaryOp::Or, left, right) => {
+                match as_bool(reference_eval(left, payload)?)? {
+                    Some(true) => RefValue::Bool(true),
+                    left => match (left, as_bool(reference_eval(right, payload)?)?) {
+                        (_, Some(true)) => RefValue::Bool(true),
+                        (None, _) | (_, None) => RefValue::Null,
+                        _ => RefValue::Bool(false),
+                    },
+                }
+            }
+            Expr::Binary(op, left, right) => {
+                let left = reference_eval(left, payload)?;
+                let right = reference_eval(right, payload)?;
+                if left == RefValue::Null || right == RefValue::Null {
+                    return Ok(RefValue::Null);
+                }
+                match op {
+                    BinaryOp::Eq => RefValue::Bool(compare(&left, &right)?.is_eq()),
+                    BinaryOp::NotEq => RefValue::Bool(compare(&left, &right)?.is_ne()),
+                    BinaryOp::Lt => RefValue::Bool(compare(&left, &right)?.is_lt()),
+                    BinaryOp::LtEq => RefValue::Bool(compare(&left, &right)?.is_le()),
+                    BinaryOp::Gt => RefValue::Bool(compare(&left, &right)?.is_gt()),
+                    BinaryOp::GtEq => RefValue::Bool(compare(&left, &right)?.is_ge()),
+                    BinaryOp::Concat => RefValue::Str(to_text(&left)? + &to_text(&right)?),
+                    _ => match (&left, &right) {
+                        (RefValue::Int(left), RefValue::Int(right)) => match op {
+                            BinaryOp::Add => check_int(left + right)?,
+                            BinaryOp::Sub => check_int(left - right)?,
+                            BinaryOp::Mul => check_int(left * right)?,
+                            _ if *right == 0 => return Err(()),
+                            BinaryOp::Div => check_int(left / right)?,
+                            _ => check_int(left % right)?,
+                        },
+                        _ => {
+                            let (left, right) = (as_float(&left)?, as_float(&right)?);
+                            match op {
+                                BinaryOp::Add => check_float(left + right)?,
+                                BinaryOp::Sub => check_float(left - right)?,
+                                BinaryOp::Mul => check_float(left * right)?,
+                                _ if right == 0.0 => return Err(()),
+                                BinaryOp::Div => check_float(left / right)?,
+                                _ => check_float(left % right)?,
+                            }
+                        }
+                    },
+                }
+            }
+            Expr::IsNull { expr, negated } => {
+                RefValue::Bool((reference_eval(expr, payload)? == RefValue::Null) != *negated)
+            }
+            Expr::Function(Function::Coalesce, args) => {
+                for arg in args {
+                    let value = reference_eval(arg, payload)?;
+                    if value != RefValue::Null {
+                        return Ok(value);
+                    }
+                }
+                RefValue::Null
+            }
+            Expr::Function(func, args) => {
+                let mut values = vec![];
+                for arg in args {
+                    match reference_eval(arg, payload)? {
+                        RefValue::Null => return Ok(RefValue::Null),
+                        value => values.push(value),
+                    }
+                }
+                let text = match &values[0] {
+                    RefValue::Str(text) => text.clone(),
+                    _ => return Err(()),
+                };
+                match func {
+                    Function::Lower => RefValue::Str(text.to_lowercase()),
+                    Function::Upper => RefValue::Str(text.to_uppercase()),
+                    Function::Trim => RefValue::Str(text.trim().to_string()),
+                    Function::Length => RefValue::Int(text.chars().count() as i128),
+                    _ => {
+                        let start = match &values[1] {
+                            RefValue::Int(start) => *start,
+                            _ => return Err(()),
+                        };
+                        let end = match values.get(2) {
+                            Some(RefValue::Int(length)) if *length >= 0 => start + length,
+                            Some(_) => return Err(()),
+                            None => i128::MAX,
+                        };
+                        RefValue::Str(
+                            text.chars()
+                                .enumerate()
+                                .filter(|(index, _)| {
+                                    let position = *index as i128 + 1;
+                                    position >= start && position < end
+                                })
+                                .map(|(_, c)| c)
+                                .collect(),
+                        )
+                    }
+                }
+            }
+            Expr::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                let operand = match operand {
+                    Some(operand) => Some(reference_eval(operand, payload)?),
+                    None => None,
+                };
+                for (condition, result) in branches {
+                    let value = reference_eval(condition, payload)?;
+                    let matched = match &operand {
+                        Some(RefValue::Null) => false,
+                        Some(_) if value == RefValue::Null => false,
+                        Some(operand) => compare(operand, &value)?.is_eq(),
+                        None => as_bool(value)? == Some(true),
+                    };
+                    if matched {
+                        return reference_eval(result, payload);
+                    }
+                }
+                match otherwise {
+                    Some(otherwise) => reference_eval(otherwise, payload)?,
+                    None => RefValue::Null,
+                }
+            }
+        })
+    }
+
+    const FIELDS: [&str; 7] = ["i", "j", "f", "s", "b", "n", "missing"];
+
+    fn random_payload(rng: &mut StdRng) -> serde_json::Value {
+        let mut object = serde_json::Map::new();
+        let strings = ["", "a", "Ab", " x ", "ström", "SE"];
+        for name in FIELDS {
+            // any field can be absent or null
+            let value = match rng.gen_range(0..8) {
+                0 => continue,
+                1 => serde_json::Value::Null,
+                _ => match name {
+                    "i" | "j" => serde_json::json!(*[0, 1, -3, 7, 100, i64::MAX, i64::MIN]
+                        .get(rng.gen_range(0..7))
+                        .unwrap()),
+                    "f" => serde_json::json!(rng.gen_range(-8..8) as f64 / 4.0),
+                    "s" => serde_json::json!(strings[rng.gen_range(0..strings.len())]),
+                    "b" => serde_json::json!(rng.gen_bool(0.5)),
+                    "n" => serde_json::json!({ "i": rng.gen_range(-2..3) }),
+                    _ => continue,
+                },
+            };
+            object.insert(name.to_string(), value);
+        }
+        serde_json::Value::Object(object)
+    }
+
+    fn random_child(rng: &mut StdRng, depth: u32) -> Box<Expr> {
+        Box::new(random_expr(rng, depth - 1))
+    }
+
+    fn random_expr(rng: &mut StdRng, depth: u32) -> Expr {
+        let leaf = depth == 0 || rng.gen_bool(0.25);
+        if leaf {
+            return match rng.gen_range(0..9) {
+                0 => Expr::Literal(Literal::Null),
+                1 => Expr::Literal(Literal::Boolean(rng.gen_bool(0.5))),
+                2 => Expr::Literal(Literal::Integer(rng.gen_range(0..10))),
+                3 => Expr::Literal(Literal::Float(rng.gen_range(0..10) as f64 / 2.0)),
+                4 => Expr::Literal(Literal::String(
+                    ["", "a", "it's", "SE"][rng.gen_range(0..4)].to_string(),
+                )),
+                5 => Expr::Field(vec!["n".to_string(), "i".to_string()]),
+                _ => Expr::Field(vec![FIELDS[rng.gen_range(0..FIELDS.len())].to_string()]),
+            };
+        }
+        match rng.gen_range(0..7) {
+            0 => Expr::Unary(
+                if rng.gen_bool(0.5) {
+                    UnaryOp::Neg
+                } else {
+                    UnaryOp::Not
+                },
+                random_child(rng, depth),
+            ),
+            1 | 2 => {
+                let ops = [
+                    BinaryOp::Add,
+                    BinaryOp::Sub,
+                    BinaryOp::Mul,
+                    BinaryOp::Div,
+                    BinaryOp::Mod,
+                    BinaryOp::Concat,
+                    BinaryOp::Eq,
+                    BinaryOp::NotEq,
+                    BinaryOp::Lt,
+                    BinaryOp::LtEq,
+                    BinaryOp::Gt,
+                    BinaryOp::GtEq,
+                    BinaryOp::And,
+                    BinaryOp::Or,
+                ];
+                let op = ops[rng.gen_range(0..ops.len())];
+                Expr::Binary(op, random_child(rng, depth), random_child(rng, depth))
+            }
+            3 => Expr::IsNull {
+                expr: random_child(rng, depth),
+                negated: rng.gen_bool(0.5),
+            },
+            4 => {
+                let func = Function::ALL[rng.gen_range(0..Function::ALL.len())];
+                let (min, max) = func.get_arity();
+                let arity = rng.gen_range(min..=max.min(3));
+                let args = (0..arity).map(|_| random_expr(rng, depth - 1)).collect();
+                Expr::Function(func, args)
+            }
+            _ => {
+                let operand = if rng.gen_bool(0.5) {
+                    Some(random_child(rng, depth))
+                } else {
+                    None
+                };
+                let branches = (0..rng.gen_range(1..3))
+                    .map(|_| (random_expr(rng, depth - 1), random_expr(rng, depth - 1)))
+                    .collect();
+                let otherwise = if rng.gen_bool(0.5) {
+                    Some(random_child(rng, depth))
+                } else {
+                    None
+                };
+                Expr::Case {
+                    operand,
+                    branches,
+                    otherwise,
+                }
+            }
+        }
+    }
+
+    /// random expressions are printed, parsed and compiled, and evaluated over random payloads.
+    /// The results must be the same as the reference interpretation of the generated expressions
+    #[test]
+    fn test_sql_expr_against_reference() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let payloads = (0..32)
+            .map(|_| random_payload(&mut rng))
+            .collect::<Vec<_>>();
+        let mut evaluated = 0;
+        for _ in 0..3000 {
+            let expr = random_expr(&mut rng, 4);
+            let sql = expr.to_string();
+            let parsed = Expr::parse(&sql).unwrap_or_else(|err| panic!("{sql}: {err}"));
+            assert_eq!(parsed, expr, "{sql}");
+            let compiled = CompiledExpr::compile(&parsed);
+
+            for payload in &payloads {
+                let actual = compiled
+                    .eval(&TypedValue::from_json_value(payload.clone()))
+                    .map(|value| from_typed_value(&value))
+                    .map_err(|_| ());
+                let expected = reference_eval(&expr, payload);
+                assert_eq!(actual, expected, "{sql} over {payload}");
+                if actual.is_ok() {
+                    evaluated += 1;
+                }
+            }
+        }
+        // most of the random expressions are not type errors
+        assert!(evaluated > 3000 * 32 / 4, "{evaluated}");
+    }
+
+    #[test]
+    fn test_filter_map_corpus() {
+        let payloads = [
+            serde_json::json!({"amount": 120, "rate": 0.5, "country": "SE", "user_id": "u1"}),
+            serde_json::json!({"amount": 80, "rate": 2, "country": "SE", "user_id": "u2"}),
+            serde_json::json!({"amount": 500, "country": "NO", "user_id": "u3"}),
+            serde_json::json!({"amount": null, "rate": 1.5, "country": "se"}),
+        ];
+        let corpus = [
+            (
+                "amount > 100 AND country = 'SE'",
+                vec![true, false, false, false],
+            ),
+            (
+                "amount > 100 OR rate IS NULL",
+                vec![true, false, true, false],
+            ),
+            (
+                "upper(country) = 'SE' AND NOT amount < 100",
+                vec![true, false, false, false],
+            ),
+            (
+                "coalesce(amount, 0) * coalesce(rate, 1) >= 160",
+                vec![false, true, true, false],
+            ),
+            (
+                "CASE WHEN country = 'NO' THEN amount > 1000 ELSE TRUE END",
+                vec![true, true, false, true],
+            ),
+        ];
+        for (sql, expected) in corpus {
+            let filter = ExprFilter::new(&FilterExpr {
+                expression: sql.to_string(),
+            })
+            .unwrap();
+            let actual = payloads
+                .iter()
+                .map(|payload| filter.filter(&TypedValue::from_json_value(payload.clone())))
+                .collect::<Result<Vec<_>, _>>();
+            assert_eq!(actual, Ok(expected), "{sql}");
+        }
+
+        let mapper = ExprMapper::new(&MapExpr {
+            expression: "SELECT amount * rate AS amount_eur, user_id, \
+                CASE WHEN amount IS NULL THEN 'unknown' ELSE lower(country) END AS region"
+                .to_string(),
+        })
+        .unwrap();
+        let actual = payloads
+            .iter()
+            .map(|payload| {
+                mapper
+                    .map(&TypedValue::from_json_value(payload.clone()))
+                    .map(|value| value.to_json_value())
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            actual,
+            vec![
+                serde_json::json!({"amount_eur": 60.0, "user_id": "u1", "region": "se"}),
+                serde_json::json!({"amount_eur": 160, "user_id": "u2", "region": "se"}),
+                serde_json::json!({"amount_eur": null, "user_id": "u3", "region": "no"}),
+                serde_json::json!({"amount_eur": null, "user_id": null, "region": "unknown"}),
+            ]
+        );
+    }
+}
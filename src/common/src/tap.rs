@@ -0,0 +1,152 @@
+use std::{
+    fmt::{self, Display},
+    time::Instant,
+};
+
+use proto::common::{throttle::RateLimit, KeyedDataEvent};
+use tokio::sync::mpsc;
+
+use crate::throttle::TokenBucket;
+
+/// the number of sampled events buffered by a [`Tap`] before it starts dropping them
+pub const TAP_BUFFER_SIZE: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TapError {
+    /// the sample rate is not a positive number
+    InvalidSampleRate(f64),
+}
+
+impl Display for TapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TapError::InvalidSampleRate(rate) => write!(f, "invalid tap sample rate: {}", rate),
+        }
+    }
+}
+
+/// [`Tap`] streams a sampled copy of the events passing through an operator for debugging.
+///
+/// A tap never affects the main data path:
+/// - at most `sample_rate` events per second are sampled, the others are skipped
+/// - a sampled event is dropped if the receiver can't keep up with it
+///
+/// The tap is closed once its receiver is dropped.
+#[derive(Debug)]
+pub struct Tap {
+    bucket: TokenBucket,
+    tx: mpsc::Sender<KeyedDataEvent>,
+    dropped: u64,
+}
+
+impl Tap {
+    /// create a tap sampling at most `sample_rate` events per second and the receiver of the sampled events
+    pub fn new(
+        sample_rate: f64,
+        now: Instant,
+    ) -> Result<(Self, mpsc::Receiver<KeyedDataEvent>), TapError> {
+        if !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return Err(TapError::InvalidSampleRate(sample_rate));
+        }
+        let (tx, rx) = mpsc::channel(TAP_BUFFER_SIZE);
+        let bucket = TokenBucket::new(
+            &RateLimit {
+                events_per_sec: sample_rate,
+                burst: 1,
+                drop: true,
+            },
+            now,
+        );
+        Ok((
+            Self {
+                bucket,
+                tx,
+                dropped: 0,
+            },
+            rx,
+        ))
+    }
+
+    /// offer an event to the tap. It never blocks and returns false once the tap is closed
+    pub fn offer(&mut self, event: &KeyedDataEvent, now: Instant) -> bool {
+        if self.tx.is_closed() {
+            return false;
+        }
+        if self.bucket.acquire(now).is_err() {
+            return true;
+        }
+        match self.tx.try_send(event.clone()) {
+            Ok(_) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.dropped += 1;
+                true
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
+
+    /// the number of sampled events dropped because the receiver couldn't keep up
+    #[inline]
+    pub fn get_dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use proto::common::KeyedDataEvent;
+
+    use super::{Tap, TapError, TAP_BUFFER_SIZE};
+
+    #[test]
+    fn test_tap_invalid_sample_rate() {
+        assert_eq!(
+            Tap::new(0.0, Instant::now()).err(),
+            Some(TapError::InvalidSampleRate(0.0))
+        );
+        assert!(Tap::new(-1.0, Instant::now()).is_err());
+        assert!(Tap::new(f64::NAN, Instant::now()).is_err());
+    }
+
+    #[test]
+    fn test_tap_sample_rate() {
+        let start = Instant::now();
+        let (mut tap, mut rx) = Tap::new(10.0, start).unwrap();
+        let event = KeyedDataEvent::default();
+
+        // 1000 events per second in 5 seconds
+        let mut received = 0;
+        for i in 0..5000u64 {
+            assert!(tap.offer(&event, start + Duration::from_millis(i)));
+            while rx.try_recv().is_ok() {
+                received += 1;
+            }
+        }
+
+        assert!((48..=52).contains(&received), "received {}", received);
+        assert_eq!(tap.get_dropped(), 0);
+    }
+
+    #[test]
+    fn test_tap_drop_on_backpressure() {
+        let start = Instant::now();
+        let (mut tap, mut rx) = Tap::new(1000.0, start).unwrap();
+        let event = KeyedDataEvent::default();
+
+        for i in 0..(TAP_BUFFER_SIZE as u64 + 10) {
+            assert!(tap.offer(&event, start + Duration::from_secs(i)));
+        }
+        assert_eq!(tap.get_dropped(), 10);
+
+        let mut received = 0;
+        while rx.try_recv().is_ok() {
+            received += 1;
+        }
+        assert_eq!(received, TAP_BUFFER_SIZE);
+
+        drop(rx);
+        assert!(!tap.offer(&event, start + Duration::from_secs(1000)));
+    }
+}
@@ -0,0 +1,346 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    time::{Duration, Instant},
+};
+
+use proto::{
+    common::{throttle, Throttle},
+    common_impl::DataflowValidateError,
+};
+use rand::Rng;
+
+/// metric of the payloads dropped by the token-bucket rate limit
+pub const RATE_LIMIT_DROPPED_METRIC: &str = "throttle.rate_limit.dropped";
+/// metric of the payloads dropped by probabilistic sampling
+pub const SAMPLING_DROPPED_METRIC: &str = "throttle.sampling.dropped";
+/// metric of the payloads dropped by per-key sampling
+pub const KEY_SAMPLING_DROPPED_METRIC: &str = "throttle.key_sampling.dropped";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThrottleError {
+    /// the configuration of throttle operator is invalid
+    InvalidThrottle(String),
+}
+
+impl Display for ThrottleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThrottleError::InvalidThrottle(msg) => write!(f, "invalid throttle operator: {}", msg),
+        }
+    }
+}
+
+impl From<DataflowValidateError> for ThrottleError {
+    fn from(err: DataflowValidateError) -> Self {
+        match err {
+            DataflowValidateError::InvalidThrottle(msg) => Self::InvalidThrottle(msg),
+            _ => Self::InvalidThrottle(format!("{:?}", err)),
+        }
+    }
+}
+
+/// the decision of [`Throttler`] for a payload
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Admission {
+    Pass,
+    Drop,
+    /// no token is available in blocking mode. The payload should be admitted again after the delay
+    Wait(Duration),
+}
+
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    /// tokens per second
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+    drop: bool,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(rate_limit: &throttle::RateLimit, now: Instant) -> Self {
+        let capacity = if rate_limit.burst == 0 {
+            rate_limit.events_per_sec.max(1.0)
+        } else {
+            rate_limit.burst as f64
+        };
+        Self {
+            rate: rate_limit.events_per_sec,
+            capacity,
+            tokens: capacity,
+            last_refill: now,
+            drop: rate_limit.drop,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+        self.last_refill = self.last_refill.max(now);
+    }
+
+    /// take a token. It returns the time to wait until a token is available if the bucket is empty
+    pub(crate) fn acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
+
+enum Mode {
+    RateLimit(TokenBucket),
+    Sampling(f64),
+    KeySampling(u32),
+}
+
+impl Mode {
+    fn new(throttle: &Throttle, now: Instant) -> Result<Self, ThrottleError> {
+        throttle.check()?;
+        match throttle.mode.as_ref() {
+            Some(throttle::Mode::RateLimit(rate_limit)) => {
+                Ok(Self::RateLimit(TokenBucket::new(rate_limit, now)))
+            }
+            Some(throttle::Mode::Sampling(sampling)) => Ok(Self::Sampling(sampling.fraction)),
+            Some(throttle::Mode::KeySampling(key_sampling)) => {
+                Ok(Self::KeySampling(key_sampling.modulus))
+            }
+            None => Err(ThrottleError::InvalidThrottle(
+                "throttle mode is missing".to_string(),
+            )),
+        }
+    }
+}
+
+/// [`Throttler`] decides whether a payload passes the `Throttle` operator. It's the runtime of the `Throttle` operator.
+///
+/// - rate limit: a token bucket refilled at `events_per_sec` whose capacity is `burst`. Payloads without tokens are either dropped or have to wait
+/// - sampling: each payload is kept with the probability `fraction`
+/// - key sampling: payloads whose hash of the key modulo `modulus` is zero are kept. The hash is stable across processes and restarts
+///
+/// Dropped payloads are counted per mode, and the counters are kept if the configuration is updated.
+pub struct Throttler {
+    mode: Mode,
+    rate_limit_dropped: u64,
+    sampling_dropped: u64,
+    key_sampling_dropped: u64,
+}
+
+impl Throttler {
+    pub fn new(throttle: &Throttle, now: Instant) -> Result<Self, ThrottleError> {
+        Ok(Self {
+            mode: Mode::new(throttle, now)?,
+            rate_limit_dropped: 0,
+            sampling_dropped: 0,
+            key_sampling_dropped: 0,
+        })
+    }
+
+    /// apply a new configuration. If both of the old and the new one are rate limits, the remaining tokens are kept.
+    pub fn update(&mut self, throttle: &Throttle, now: Instant) -> Result<(), ThrottleError> {
+        let mut mode = Mode::new(throttle, now)?;
+        if let (Mode::RateLimit(old), Mode::RateLimit(new)) = (&mut self.mode, &mut mode) {
+            old.refill(now);
+            new.tokens = old.tokens.min(new.capacity);
+        }
+        self.mode = mode;
+        Ok(())
+    }
+
+    /// decide whether a payload passes. `key` is the key of the event which the payload belongs to.
+    /// A payload which has to wait will not be counted as dropped.
+    pub fn admit(&mut self, key: Option<&[u8]>, payload: &[u8], now: Instant) -> Admission {
+        match &mut self.mode {
+            Mode::RateLimit(bucket) => match bucket.acquire(now) {
+                Ok(_) => Admission::Pass,
+                Err(_) if bucket.drop => {
+                    self.rate_limit_dropped += 1;
+                    Admission::Drop
+                }
+                Err(delay) => Admission::Wait(delay),
+            },
+            Mode::Sampling(fraction) => {
+                if rand::thread_rng().gen_bool(*fraction) {
+                    Admission::Pass
+                } else {
+                    self.sampling_dropped += 1;
+                    Admission::Drop
+                }
+            }
+            Mode::KeySampling(modulus) => {
+                let hashed = key.filter(|key| !key.is_empty()).unwrap_or(payload);
+                if fnv1a_hash(hashed) % (*modulus as u64) == 0 {
+                    Admission::Pass
+                } else {
+                    self.key_sampling_dropped += 1;
+                    Admission::Drop
+                }
+            }
+        }
+    }
+
+    /// the dropped counters of all modes
+    pub fn get_metrics(&self) -> HashMap<String, u64> {
+        HashMap::from([
+            (
+                RATE_LIMIT_DROPPED_METRIC.to_string(),
+                self.rate_limit_dropped,
+            ),
+            (SAMPLING_DROPPED_METRIC.to_string(), self.sampling_dropped),
+            (
+                KEY_SAMPLING_DROPPED_METRIC.to_string(),
+                self.key_sampling_dropped,
+            ),
+        ])
+    }
+}
+
+/// 64-bit FNV-1a. Unlike [`std::collections::hash_map::DefaultHasher`], it's guaranteed to be stable
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use proto::common::{throttle, Throttle};
+
+    use super::{
+        Admission, ThrottleError, Throttler, KEY_SAMPLING_DROPPED_METRIC,
+        RATE_LIMIT_DROPPED_METRIC, SAMPLING_DROPPED_METRIC,
+    };
+
+    fn rate_limit(events_per_sec: f64, burst: u32, drop: bool) -> Throttle {
+        Throttle {
+            mode: Some(throttle::Mode::RateLimit(throttle::RateLimit {
+                events_per_sec,
+                burst,
+                drop,
+            })),
+        }
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let now = Instant::now();
+        let mut throttler = Throttler::new(&rate_limit(10.0, 2, true), now).unwrap();
+
+        // burst
+        assert_eq!(throttler.admit(None, b"a", now), Admission::Pass);
+        assert_eq!(throttler.admit(None, b"b", now), Admission::Pass);
+        assert_eq!(throttler.admit(None, b"c", now), Admission::Drop);
+
+        // a token is refilled every 100ms
+        let now = now + Duration::from_millis(100);
+        assert_eq!(throttler.admit(None, b"d", now), Admission::Pass);
+        assert_eq!(throttler.admit(None, b"e", now), Admission::Drop);
+        assert_eq!(throttler.get_metrics()[RATE_LIMIT_DROPPED_METRIC], 2);
+
+        // blocking mode
+        let mut throttler = Throttler::new(&rate_limit(10.0, 0, false), now).unwrap();
+        for _ in 0..10 {
+            assert_eq!(throttler.admit(None, b"a", now), Admission::Pass);
+        }
+        match throttler.admit(None, b"a", now) {
+            Admission::Wait(delay) => assert!((delay.as_secs_f64() - 0.1).abs() < 1e-6),
+            admission => panic!("unexpected admission {:?}", admission),
+        }
+        let now = now + Duration::from_millis(100);
+        assert_eq!(throttler.admit(None, b"a", now), Admission::Pass);
+        assert_eq!(throttler.get_metrics()[RATE_LIMIT_DROPPED_METRIC], 0);
+    }
+
+    #[test]
+    fn test_sampling() {
+        let now = Instant::now();
+        let sampling = |fraction| Throttle {
+            mode: Some(throttle::Mode::Sampling(throttle::Sampling { fraction })),
+        };
+
+        let mut throttler = Throttler::new(&sampling(0.0), now).unwrap();
+        assert!((0..100).all(|_| throttler.admit(None, b"a", now) == Admission::Drop));
+        assert_eq!(throttler.get_metrics()[SAMPLING_DROPPED_METRIC], 100);
+
+        let mut throttler = Throttler::new(&sampling(1.0), now).unwrap();
+        assert!((0..100).all(|_| throttler.admit(None, b"a", now) == Admission::Pass));
+
+        let mut throttler = Throttler::new(&sampling(0.3), now).unwrap();
+        let passed = (0..10000)
+            .filter(|_| throttler.admit(None, b"a", now) == Admission::Pass)
+            .count();
+        assert!(passed > 2700 && passed < 3300, "{} passed", passed);
+    }
+
+    #[test]
+    fn test_key_sampling() {
+        let now = Instant::now();
+        let mut throttler = Throttler::new(
+            &Throttle {
+                mode: Some(throttle::Mode::KeySampling(throttle::KeySampling {
+                    modulus: 4,
+                })),
+            },
+            now,
+        )
+        .unwrap();
+
+        let mut kept_keys = 0;
+        for key in 0..1000 {
+            let key = format!("key-{}", key);
+            let admission = throttler.admit(Some(key.as_bytes()), b"a", now);
+            // all the payloads of a key have the same admission
+            for payload in 0..10 {
+                let payload = format!("{}", payload);
+                assert_eq!(
+                    throttler.admit(Some(key.as_bytes()), payload.as_bytes(), now),
+                    admission
+                );
+            }
+            if admission == Admission::Pass {
+                kept_keys += 1;
+            }
+        }
+        assert!(kept_keys > 200 && kept_keys < 300, "{} kept", kept_keys);
+        assert_eq!(
+            throttler.get_metrics()[KEY_SAMPLING_DROPPED_METRIC],
+            (1000 - kept_keys) * 11
+        );
+    }
+
+    #[test]
+    fn test_update_throttle() {
+        let now = Instant::now();
+        let mut throttler = Throttler::new(&rate_limit(1.0, 5, true), now).unwrap();
+        for _ in 0..5 {
+            assert_eq!(throttler.admit(None, b"a", now), Admission::Pass);
+        }
+        assert_eq!(throttler.admit(None, b"a", now), Admission::Drop);
+
+        // the remaining tokens are kept
+        throttler.update(&rate_limit(100.0, 10, true), now).unwrap();
+        assert_eq!(throttler.admit(None, b"a", now), Admission::Drop);
+        let now = now + Duration::from_millis(65);
+        for _ in 0..6 {
+            assert_eq!(throttler.admit(None, b"a", now), Admission::Pass);
+        }
+        assert_eq!(throttler.get_metrics()[RATE_LIMIT_DROPPED_METRIC], 2);
+
+        // invalid configurations are rejected and the current one is kept
+        assert!(matches!(
+            throttler.update(&rate_limit(0.0, 10, true), now),
+            Err(ThrottleError::InvalidThrottle(_))
+        ));
+        assert!(throttler.update(&Throttle::default(), now).is_err());
+        assert_eq!(throttler.admit(None, b"a", now), Admission::Drop);
+    }
+}
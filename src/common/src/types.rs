@@ -459,6 +459,15 @@ impl From<&Entry> for TypedValue {
     }
 }
 
+impl From<TypedValue> for Entry {
+    fn from(value: TypedValue) -> Self {
+        Self {
+            data_type: value.get_type() as i32,
+            value: value.get_data_bytes(),
+        }
+    }
+}
+
 pub type RowIdx = u64;
 pub type NodeIdx = u32;
 pub type SinkId = u32;
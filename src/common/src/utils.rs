@@ -46,6 +46,11 @@ pub mod times {
         }
     }
 
+    /// milliseconds since the unix epoch
+    pub fn prost_to_millis(timestamp: &prost_types::Timestamp) -> i64 {
+        timestamp.seconds * 1000 + timestamp.nanos as i64 / 1_000_000
+    }
+
     pub fn now_timestamp() -> i64 {
         now().timestamp_millis()
     }
@@ -357,6 +362,7 @@ mod tests {
         let meta1 = DataflowMeta {
             center: 0,
             neighbors: vec![1],
+            edge_types: Default::default(),
         };
 
         let dataflow = super::to_dataflow(&job_id, &operators, &[meta1.clone()]);
@@ -378,10 +384,7 @@ mod tests {
         use std::collections::HashMap;
 
         let mut dataflow = Dataflow::default();
-        dataflow.job_id = Some(ResourceId {
-            resource_id: "resourceId".to_string(),
-            namespace_id: "namespace_id".to_string(),
-        });
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
         let mut meta = DataflowMeta::default();
         meta.center = 0;
         meta.neighbors = vec![1, 2, 3];
@@ -415,6 +418,938 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_validate_deduplicate_side_output() {
+        use proto::common::Dataflow;
+        use proto::common::DataflowMeta;
+        use proto::common::Deduplicate;
+        use proto::common::OperatorInfo;
+        use proto::common::Time;
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        meta.neighbors = vec![1, 2];
+        dataflow.meta = vec![meta];
+
+        let mut nodes = HashMap::default();
+        (1..3).for_each(|index| {
+            let mut info = OperatorInfo::default();
+            info.operator_id = index;
+            info.details = Some(Details::Filter(Default::default()));
+            nodes.insert(index, info);
+        });
+        let mut deduplicate = Deduplicate {
+            key_path: "$.id".to_string(),
+            horizon: Some(Time {
+                millis: 0,
+                seconds: 10,
+                minutes: 0,
+                hours: 0,
+            }),
+            side_output: Some(2),
+        };
+        let mut info = OperatorInfo::default();
+        info.operator_id = 0;
+        info.details = Some(Details::Deduplicate(deduplicate.clone()));
+        nodes.insert(0, info.clone());
+        dataflow.nodes = nodes.clone();
+        assert!(dataflow.validate().is_ok());
+
+        deduplicate.side_output = Some(3);
+        info.details = Some(Details::Deduplicate(deduplicate));
+        nodes.insert(0, info);
+        dataflow.nodes = nodes;
+        match dataflow.validate() {
+            Err(DataflowValidateError::InvalidDeduplicate(_)) => {}
+            _ => panic!("unexpected result"),
+        };
+    }
+
+    #[test]
+    fn test_validate_wasm_udf() {
+        use proto::common::wasm_udf::Module;
+        use proto::common::Dataflow;
+        use proto::common::DataflowMeta;
+        use proto::common::OperatorInfo;
+        use proto::common::WasmUdf;
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+
+        let validate = |dataflow: &mut Dataflow, wasm_udf: WasmUdf| {
+            let mut info = OperatorInfo::default();
+            info.details = Some(Details::WasmUdf(wasm_udf));
+            dataflow.nodes = HashMap::from_iter([(0, info)]);
+            dataflow.validate()
+        };
+
+        let checksum = "a".repeat(64);
+        let mut wasm_udf = WasmUdf::default();
+        wasm_udf.module = Some(Module::Inline(b"(module)".to_vec()));
+        assert!(validate(&mut dataflow, wasm_udf.clone()).is_ok());
+
+        wasm_udf.module = Some(Module::Url("http://localhost/udf.wasm".to_string()));
+        wasm_udf.sha256 = checksum.clone();
+        assert!(validate(&mut dataflow, wasm_udf.clone()).is_ok());
+
+        for (module, sha256) in [
+            (None, checksum.clone()),
+            (Some(Module::Inline(vec![])), checksum.clone()),
+            (Some(Module::Url("".to_string())), checksum.clone()),
+            (
+                Some(Module::Url("http://localhost/udf.wasm".to_string())),
+                "".to_string(),
+            ),
+            (
+                Some(Module::Inline(b"(module)".to_vec())),
+                "xyz".to_string(),
+            ),
+        ] {
+            wasm_udf.module = module;
+            wasm_udf.sha256 = sha256;
+            match validate(&mut dataflow, wasm_udf.clone()) {
+                Err(DataflowValidateError::InvalidWasmUdf(_)) => {}
+                _ => panic!("unexpected result"),
+            };
+        }
+    }
+
+    #[test]
+    fn test_validate_sql_expr() {
+        use proto::common::{Dataflow, DataflowMeta, FilterExpr, MapExpr, OperatorInfo};
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+
+        let validate = |dataflow: &mut Dataflow, details: Details| {
+            let mut info = OperatorInfo::default();
+            info.details = Some(details);
+            dataflow.nodes = HashMap::from_iter([(0, info)]);
+            dataflow.validate()
+        };
+        let filter = |expression: &str| {
+            Details::FilterExpr(FilterExpr {
+                expression: expression.to_string(),
+            })
+        };
+        let map = |expression: &str| {
+            Details::MapExpr(MapExpr {
+                expression: expression.to_string(),
+            })
+        };
+
+        assert!(validate(&mut dataflow, filter("amount > 100 AND country = 'SE'")).is_ok());
+        assert!(validate(
+            &mut dataflow,
+            map("SELECT amount * rate AS amount_eur, user_id")
+        )
+        .is_ok());
+
+        // syntax errors are rejected at submission
+        for details in [
+            filter(""),
+            filter("amount >"),
+            filter("upper(a, b)"),
+            map("amount * rate AS amount_eur"),
+            map("SELECT amount * rate"),
+        ] {
+            match validate(&mut dataflow, details) {
+                Err(DataflowValidateError::InvalidSqlExpr(_)) => {}
+                _ => panic!("unexpected result"),
+            };
+        }
+    }
+
+    #[test]
+    fn test_validate_route() {
+        use proto::common::{route, Dataflow, DataflowMeta, OperatorInfo, Route};
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        dataflow.meta = vec![DataflowMeta {
+            center: 0,
+            neighbors: vec![1, 2],
+            edge_types: Default::default(),
+        }];
+
+        let validate = |dataflow: &mut Dataflow, route: Route| {
+            let mut info = OperatorInfo::default();
+            info.details = Some(Details::Route(route));
+            dataflow.nodes = HashMap::from_iter([
+                (0, info),
+                (1, OperatorInfo::default()),
+                (2, OperatorInfo::default()),
+            ]);
+            dataflow.check_operator(0)
+        };
+        let rule = |predicate: &str, output: &str| route::Rule {
+            predicate: predicate.to_string(),
+            output: output.to_string(),
+        };
+        let route = Route {
+            outputs: HashMap::from_iter([("large".to_string(), 1), ("others".to_string(), 2)]),
+            rules: vec![rule("amount > 100", "large")],
+            default_output: "others".to_string(),
+        };
+        assert!(validate(&mut dataflow, route.clone()).is_ok());
+
+        for invalid in [
+            // no rule
+            Route {
+                rules: vec![],
+                ..route.clone()
+            },
+            // the predicate can't be parsed
+            Route {
+                rules: vec![rule("amount >", "large")],
+                ..route.clone()
+            },
+            // the output is not declared
+            Route {
+                rules: vec![rule("amount > 100", "small")],
+                ..route.clone()
+            },
+            Route {
+                default_output: "missing".to_string(),
+                ..route.clone()
+            },
+            // the output is not a downstream
+            Route {
+                outputs: HashMap::from_iter([("large".to_string(), 3), ("others".to_string(), 2)]),
+                ..route.clone()
+            },
+        ] {
+            match validate(&mut dataflow, invalid) {
+                Err(DataflowValidateError::InvalidRoute(_)) => {}
+                _ => panic!("unexpected result"),
+            };
+        }
+    }
+
+    #[test]
+    fn test_validate_async_lookup() {
+        use proto::common::{async_lookup, AsyncLookup, Dataflow, DataflowMeta, OperatorInfo};
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+
+        let validate = |dataflow: &mut Dataflow, async_lookup: AsyncLookup| {
+            let mut info = OperatorInfo::default();
+            info.details = Some(Details::AsyncLookup(async_lookup));
+            dataflow.nodes = HashMap::from_iter([(0, info)]);
+            dataflow.validate()
+        };
+
+        let async_lookup = AsyncLookup {
+            backend: Some(async_lookup::Backend::Http(async_lookup::HttpLookup {
+                url_template: "http://localhost/users/{key}".to_string(),
+                headers: Default::default(),
+            })),
+            key_path: "$.user_id".to_string(),
+            target_field: "user".to_string(),
+            ..Default::default()
+        };
+        assert!(validate(&mut dataflow, async_lookup.clone()).is_ok());
+
+        for invalid in [
+            AsyncLookup {
+                backend: None,
+                ..async_lookup.clone()
+            },
+            AsyncLookup {
+                backend: Some(async_lookup::Backend::Redis(Default::default())),
+                ..async_lookup.clone()
+            },
+            AsyncLookup {
+                key_path: "user_id[".to_string(),
+                ..async_lookup.clone()
+            },
+            AsyncLookup {
+                target_field: "".to_string(),
+                ..async_lookup.clone()
+            },
+            AsyncLookup {
+                cache: Some(async_lookup::Cache {
+                    capacity: 0,
+                    ttl: None,
+                }),
+                ..async_lookup.clone()
+            },
+        ] {
+            match validate(&mut dataflow, invalid) {
+                Err(DataflowValidateError::InvalidAsyncLookup(_)) => {}
+                _ => panic!("unexpected result"),
+            };
+        }
+    }
+
+    #[test]
+    fn test_validate_state_limit() {
+        use proto::common::{
+            state_limit, Dataflow, DataflowMeta, Deduplicate, OperatorInfo, StateLimit, Time,
+        };
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+
+        let validate = |dataflow: &mut Dataflow, state_limit: StateLimit| {
+            let mut info = OperatorInfo::default();
+            info.details = Some(Details::Deduplicate(Deduplicate {
+                key_path: "$.id".to_string(),
+                horizon: Some(Time {
+                    seconds: 10,
+                    ..Default::default()
+                }),
+                side_output: None,
+            }));
+            info.state_limit = Some(state_limit);
+            dataflow.nodes = HashMap::from_iter([(0, info)]);
+            dataflow.validate()
+        };
+
+        assert!(validate(
+            &mut dataflow,
+            StateLimit {
+                max_bytes: 1024,
+                policy: state_limit::Policy::EvictOldest as i32,
+            }
+        )
+        .is_ok());
+
+        for invalid in [
+            StateLimit {
+                max_bytes: 0,
+                policy: state_limit::Policy::Fail as i32,
+            },
+            StateLimit {
+                max_bytes: 1024,
+                policy: 10,
+            },
+        ] {
+            match validate(&mut dataflow, invalid) {
+                Err(DataflowValidateError::InvalidStateLimit(_)) => {}
+                _ => panic!("unexpected result"),
+            };
+        }
+    }
+
+    #[test]
+    fn test_validate_log_level() {
+        use proto::common::{Dataflow, DataflowMeta, FilterExpr, OperatorInfo};
+        use std::collections::HashMap;
+        use tracing::level_filters::LevelFilter;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+        let mut info = OperatorInfo::default();
+        info.details = Some(Details::FilterExpr(FilterExpr {
+            expression: "amount > 100".to_string(),
+        }));
+        dataflow.nodes = HashMap::from_iter([(0, info)]);
+
+        assert!(dataflow.validate().is_ok());
+        assert!(matches!(dataflow.get_log_level(), Ok(None)));
+
+        dataflow.log_level = "DEBUG".to_string();
+        assert!(dataflow.validate().is_ok());
+        assert!(matches!(
+            dataflow.get_log_level(),
+            Ok(Some(LevelFilter::DEBUG))
+        ));
+
+        dataflow.log_level = "verbose".to_string();
+        match dataflow.validate() {
+            Err(DataflowValidateError::InvalidLogLevel(level)) => assert_eq!(level, "verbose"),
+            _ => panic!("unexpected result"),
+        }
+    }
+
+    #[test]
+    fn test_validate_labels() {
+        use proto::common::{Dataflow, DataflowMeta, FilterExpr, OperatorInfo};
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+        let mut info = OperatorInfo::default();
+        info.details = Some(Details::FilterExpr(FilterExpr {
+            expression: "amount > 100".to_string(),
+        }));
+        dataflow.nodes = HashMap::from_iter([(0, info)]);
+
+        dataflow.labels = HashMap::from_iter([
+            ("team".to_string(), "payments".to_string()),
+            ("app.kubernetes_io-name".to_string(), "".to_string()),
+        ]);
+        assert!(dataflow.validate().is_ok());
+
+        for (key, value) in [
+            ("", "payments"),
+            ("team", "pay ments"),
+            ("team", "-payments"),
+            ("team=env", "payments"),
+            ("team", &"p".repeat(64)),
+        ] {
+            dataflow.labels = HashMap::from_iter([(key.to_string(), value.to_string())]);
+            match dataflow.validate() {
+                Err(DataflowValidateError::InvalidLabels(_)) => {}
+                _ => panic!("unexpected result of label {key}={value}"),
+            }
+        }
+
+        dataflow.labels = (0..65)
+            .map(|index| (format!("key-{index}"), "value".to_string()))
+            .collect();
+        match dataflow.validate() {
+            Err(DataflowValidateError::InvalidLabels(_)) => {}
+            _ => panic!("unexpected result"),
+        }
+    }
+
+    #[test]
+    fn test_validate_dependencies() {
+        use proto::common::{Dataflow, DataflowMeta, FilterExpr, OperatorInfo};
+        use std::collections::HashMap;
+
+        let job_id = |resource_id: &str| ResourceId::new("default", resource_id);
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(job_id("enrich"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+        let mut info = OperatorInfo::default();
+        info.details = Some(Details::FilterExpr(FilterExpr {
+            expression: "amount > 100".to_string(),
+        }));
+        dataflow.nodes = HashMap::from_iter([(0, info)]);
+
+        dataflow.depends_on = vec![job_id("ingest"), job_id("dedup")];
+        assert!(dataflow.validate().is_ok());
+        assert!(dataflow.depends_on_job(&job_id("ingest")));
+        assert!(!dataflow.depends_on_job(&job_id("report")));
+
+        for depends_on in [
+            vec![job_id("enrich")],
+            vec![job_id("ingest"), job_id("ingest")],
+        ] {
+            dataflow.depends_on = depends_on;
+            match dataflow.validate() {
+                Err(DataflowValidateError::InvalidDependencies(_)) => {}
+                _ => panic!("unexpected result"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_operator_headers() {
+        use proto::common::{Dataflow, DataflowMeta, FilterExpr, OperatorInfo};
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::default());
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+        let mut info = OperatorInfo::default();
+        info.details = Some(Details::FilterExpr(FilterExpr {
+            expression: "amount > 100".to_string(),
+        }));
+        info.headers = HashMap::from_iter([("team".to_string(), "payments".to_string())]);
+        dataflow.nodes = HashMap::from_iter([(0, info.clone())]);
+        assert!(dataflow.validate().is_ok());
+
+        info.headers.insert("".to_string(), "empty".to_string());
+        dataflow.nodes = HashMap::from_iter([(0, info)]);
+        match dataflow.validate() {
+            Err(DataflowValidateError::InvalidHeaders(_)) => {}
+            _ => panic!("unexpected result"),
+        }
+    }
+
+    #[test]
+    fn test_order_by_dependencies() {
+        use proto::common::Dataflow;
+        use proto::common_impl::order_by_dependencies;
+
+        let new_dataflow = |resource_id: &str, depends_on: &[&str]| Dataflow {
+            job_id: Some(ResourceId::new("default", resource_id)),
+            depends_on: depends_on
+                .iter()
+                .map(|resource_id| ResourceId::new("default", *resource_id))
+                .collect(),
+            ..Default::default()
+        };
+
+        // report depends on enrich, which depends on ingest and an external job
+        let dataflows = vec![
+            new_dataflow("report", &["enrich"]),
+            new_dataflow("audit", &[]),
+            new_dataflow("enrich", &["ingest", "external"]),
+            new_dataflow("ingest", &[]),
+        ];
+        assert_eq!(order_by_dependencies(&dataflows), vec![3, 2, 0, 1]);
+
+        // a cycle doesn't drop any dataflow
+        let dataflows = vec![new_dataflow("a", &["b"]), new_dataflow("b", &["a"])];
+        assert_eq!(order_by_dependencies(&dataflows), vec![1, 0]);
+        assert!(order_by_dependencies(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_validate_event_dedup() {
+        use proto::common::{Dataflow, DataflowMeta, EventDedup, FilterExpr, OperatorInfo, Time};
+        use proto::common_impl::DedupKeyField;
+        use proto::json_path::JsonPath;
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+        let mut info = OperatorInfo::default();
+        info.details = Some(Details::FilterExpr(FilterExpr {
+            expression: "amount > 100".to_string(),
+        }));
+        dataflow.nodes = HashMap::from_iter([(0, info)]);
+
+        let event_dedup = EventDedup {
+            key_fields: vec!["key".to_string(), "$.id".to_string()],
+            retention: Some(Time {
+                millis: 0,
+                seconds: 10,
+                minutes: 0,
+                hours: 0,
+            }),
+        };
+        dataflow.event_dedup = Some(event_dedup.clone());
+        assert!(dataflow.validate().is_ok());
+        assert_eq!(
+            event_dedup.get_key_fields().unwrap(),
+            vec![
+                DedupKeyField::Key,
+                DedupKeyField::Path(JsonPath::parse("$.id").unwrap())
+            ]
+        );
+
+        for invalid in [
+            EventDedup {
+                key_fields: vec![],
+                ..event_dedup.clone()
+            },
+            EventDedup {
+                key_fields: vec!["id".to_string()],
+                ..event_dedup.clone()
+            },
+            EventDedup {
+                retention: None,
+                ..event_dedup
+            },
+        ] {
+            dataflow.event_dedup = Some(invalid);
+            match dataflow.validate() {
+                Err(DataflowValidateError::InvalidEventDedup(_)) => {}
+                _ => panic!("unexpected result"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_watchdog() {
+        use proto::common::{
+            Dataflow, DataflowMeta, FilterExpr, OperatorInfo, OperatorWatchdog, Time,
+        };
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+        let mut info = OperatorInfo::default();
+        info.details = Some(Details::FilterExpr(FilterExpr {
+            expression: "amount > 100".to_string(),
+        }));
+        dataflow.nodes = HashMap::from_iter([(0, info)]);
+
+        let watchdog = OperatorWatchdog {
+            stuck_timeout: Some(Time {
+                millis: 0,
+                seconds: 30,
+                minutes: 0,
+                hours: 0,
+            }),
+            max_restarts: 3,
+        };
+        dataflow.watchdog = Some(watchdog.clone());
+        assert!(dataflow.validate().is_ok());
+        assert_eq!(
+            watchdog.get_stuck_timeout().unwrap(),
+            chrono::Duration::seconds(30)
+        );
+
+        for stuck_timeout in [None, Some(Time::default())] {
+            dataflow.watchdog = Some(OperatorWatchdog {
+                stuck_timeout,
+                ..watchdog.clone()
+            });
+            match dataflow.validate() {
+                Err(DataflowValidateError::InvalidWatchdog(_)) => {}
+                _ => panic!("unexpected result"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_kafka_sink_options() {
+        use proto::common::kafka_desc::{kafka_sink_options::Partitioner, KafkaSinkOptions};
+        use proto::common::{
+            sink, DataTypeEnum, Dataflow, DataflowMeta, KafkaDesc, OperatorInfo, Sink,
+        };
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+
+        let validate = |dataflow: &mut Dataflow, sink_opts: KafkaSinkOptions| {
+            let mut info = OperatorInfo::default();
+            info.details = Some(Details::Sink(Sink {
+                desc: Some(sink::Desc::Kafka(KafkaDesc {
+                    brokers: vec!["localhost:9092".to_string()],
+                    topic: "topic".to_string(),
+                    data_type: DataTypeEnum::Object as i32,
+                    sink_opts: Some(sink_opts),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }));
+            dataflow.nodes = HashMap::from_iter([(0, info)]);
+            dataflow.validate()
+        };
+
+        let mut sink_opts = KafkaSinkOptions::default();
+        sink_opts.set_partitioner(Partitioner::KeyHash);
+        sink_opts.key_field = "id".to_string();
+        assert!(validate(&mut dataflow, sink_opts.clone()).is_ok());
+
+        sink_opts.set_partitioner(Partitioner::Field);
+        match validate(&mut dataflow, sink_opts.clone()) {
+            Err(DataflowValidateError::InvalidKafkaSinkOptions(_)) => {}
+            _ => panic!("unexpected result"),
+        };
+
+        sink_opts.partition_field = "partition".to_string();
+        assert!(validate(&mut dataflow, sink_opts).is_ok());
+    }
+
+    #[test]
+    fn test_validate_sink_batching() {
+        use proto::common::{
+            sink, sink_batching, DataTypeEnum, Dataflow, DataflowMeta, KafkaDesc, OperatorInfo,
+            Sink, SinkBatching,
+        };
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+
+        let validate = |dataflow: &mut Dataflow, batching: SinkBatching| {
+            let mut info = OperatorInfo::default();
+            info.details = Some(Details::Sink(Sink {
+                desc: Some(sink::Desc::Kafka(KafkaDesc {
+                    brokers: vec!["localhost:9092".to_string()],
+                    topic: "topic".to_string(),
+                    data_type: DataTypeEnum::Object as i32,
+                    ..Default::default()
+                })),
+                batching: Some(batching),
+                ..Default::default()
+            }));
+            dataflow.nodes = HashMap::from_iter([(0, info)]);
+            dataflow.validate()
+        };
+
+        assert!(validate(
+            &mut dataflow,
+            SinkBatching {
+                max_events: 100,
+                linger_millis: 200,
+                capacity: 1000,
+                overflow: sink_batching::Overflow::DropOldest as i32,
+                ..Default::default()
+            }
+        )
+        .is_ok());
+
+        for invalid in [
+            SinkBatching {
+                max_events: 100,
+                capacity: 10,
+                ..Default::default()
+            },
+            SinkBatching {
+                overflow: 10,
+                ..Default::default()
+            },
+        ] {
+            match validate(&mut dataflow, invalid) {
+                Err(DataflowValidateError::InvalidSinkBatching(_)) => {}
+                _ => panic!("unexpected result"),
+            };
+        }
+    }
+
+    #[test]
+    fn test_validate_source_sampling_and_redactions() {
+        use proto::common::{
+            redaction, source, source_sampling, DataTypeEnum, Dataflow, DataflowMeta, KafkaDesc,
+            OperatorInfo, Redaction, Source, SourceSampling,
+        };
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        dataflow.meta = vec![meta];
+
+        let validate = |dataflow: &mut Dataflow, sampling: Option<SourceSampling>, path: &str| {
+            let mut info = OperatorInfo::default();
+            info.details = Some(Details::Source(Source {
+                desc: Some(source::Desc::Kafka(KafkaDesc {
+                    brokers: vec!["localhost:9092".to_string()],
+                    topic: "topic".to_string(),
+                    data_type: DataTypeEnum::Object as i32,
+                    ..Default::default()
+                })),
+                sampling,
+                redactions: vec![Redaction {
+                    path: path.to_string(),
+                    strategy: redaction::Strategy::Hash as i32,
+                    mask: Default::default(),
+                }],
+            }));
+            dataflow.nodes = HashMap::from_iter([(0, info)]);
+            dataflow.validate()
+        };
+
+        let sampling = SourceSampling {
+            rate: 10,
+            mode: source_sampling::Mode::Key as i32,
+        };
+        assert!(validate(&mut dataflow, Some(sampling.clone()), "$.user['e-mail']").is_ok());
+
+        for path in ["$.user.", "user", "$.user?(@.email).email"] {
+            match validate(&mut dataflow, None, path) {
+                Err(DataflowValidateError::InvalidRedaction(_)) => {}
+                _ => panic!("unexpected result"),
+            };
+        }
+        for invalid in [
+            SourceSampling {
+                rate: 0,
+                ..sampling.clone()
+            },
+            SourceSampling {
+                mode: 10,
+                ..sampling
+            },
+        ] {
+            match validate(&mut dataflow, Some(invalid), "$.email") {
+                Err(DataflowValidateError::InvalidSourceSampling(_)) => {}
+                _ => panic!("unexpected result"),
+            };
+        }
+    }
+
+    #[test]
+    fn test_validate_operator_config() {
+        use proto::common::{
+            async_lookup, avro_format, kafka_desc, mapper, mysql_desc, sink, source, wasm_udf,
+            AsyncLookup, AvroFormat, DataTypeEnum, Dataflow, DataflowMeta, Func, KafkaDesc, Mapper,
+            MysqlDesc, OperatorInfo, Sink, Source, WasmUdf,
+        };
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 3;
+        dataflow.meta = vec![meta];
+
+        let validate = |dataflow: &mut Dataflow, details: Details| {
+            let mut info = OperatorInfo::default();
+            info.operator_id = 3;
+            info.details = Some(details);
+            dataflow.nodes = HashMap::from_iter([(3, info)]);
+            dataflow.validate()
+        };
+        let kafka = |brokers: &[&str], format: Option<kafka_desc::Format>| KafkaDesc {
+            brokers: brokers.iter().map(|broker| broker.to_string()).collect(),
+            topic: "topic".to_string(),
+            data_type: DataTypeEnum::Object as i32,
+            format,
+            ..Default::default()
+        };
+        let avro = |url: &str| {
+            Some(kafka_desc::Format::Avro(AvroFormat {
+                registry: Some(avro_format::SchemaRegistry {
+                    url: url.to_string(),
+                    ..Default::default()
+                }),
+                subject: "subject".to_string(),
+                ..Default::default()
+            }))
+        };
+        let with_function = |function: &str| {
+            Details::Mapper(Mapper {
+                value: Some(mapper::Value::Func(Func {
+                    function: function.to_string(),
+                })),
+            })
+        };
+        let with_mysql_host = |host: &str| {
+            Details::Sink(Sink {
+                desc: Some(sink::Desc::Mysql(MysqlDesc {
+                    connection_opts: Some(mysql_desc::ConnectionOpts {
+                        host: host.to_string(),
+                        ..Default::default()
+                    }),
+                    statement: Some(mysql_desc::Statement {
+                        statement: "insert into t values (?)".to_string(),
+                        extractors: vec![],
+                    }),
+                })),
+                ..Default::default()
+            })
+        };
+        let with_url_template = |url_template: &str| {
+            Details::AsyncLookup(AsyncLookup {
+                backend: Some(async_lookup::Backend::Http(async_lookup::HttpLookup {
+                    url_template: url_template.to_string(),
+                    ..Default::default()
+                })),
+                key_path: "$.id".to_string(),
+                target_field: "user".to_string(),
+                ..Default::default()
+            })
+        };
+        let with_wasm_url = |url: &str| {
+            Details::WasmUdf(WasmUdf {
+                module: Some(wasm_udf::Module::Url(url.to_string())),
+                sha256: "a".repeat(64),
+                ..Default::default()
+            })
+        };
+
+        for valid in [
+            with_function("(a) => a"),
+            Details::Source(Source {
+                desc: Some(source::Desc::Kafka(kafka(
+                    &["localhost:9092", "10.0.0.1:9093"],
+                    avro("https://registry:8081/"),
+                ))),
+                ..Default::default()
+            }),
+            with_mysql_host("localhost"),
+            with_mysql_host("db:3306"),
+            with_url_template("http://localhost/users/{key}"),
+            with_wasm_url("http://localhost/udf.wasm"),
+        ] {
+            assert!(validate(&mut dataflow, valid).is_ok());
+        }
+
+        for (invalid, kind, field) in [
+            (with_function("  "), "mapper", "mapper.value.func.function"),
+            (
+                Details::Source(Source {
+                    desc: Some(source::Desc::Kafka(kafka(
+                        &["localhost:9092", "localhost"],
+                        None,
+                    ))),
+                    ..Default::default()
+                }),
+                "source",
+                "source.desc.kafka.brokers[1]",
+            ),
+            (
+                Details::Source(Source {
+                    desc: Some(source::Desc::Kafka(kafka(
+                        &["localhost:9092"],
+                        avro("localhost:8081"),
+                    ))),
+                    ..Default::default()
+                }),
+                "source",
+                "source.desc.kafka.format.avro.registry.url",
+            ),
+            (
+                Details::Sink(Sink {
+                    desc: Some(sink::Desc::Kafka(kafka(&["kafka://broker:9092"], None))),
+                    ..Default::default()
+                }),
+                "sink",
+                "sink.desc.kafka.brokers[0]",
+            ),
+            (
+                with_mysql_host("db:port"),
+                "sink",
+                "sink.desc.mysql.connection_opts.host",
+            ),
+            (
+                with_url_template("ftp://localhost/users/{key}"),
+                "async_lookup",
+                "async_lookup.backend.http.url_template",
+            ),
+            (
+                with_wasm_url("http:///udf.wasm"),
+                "wasm_udf",
+                "wasm_udf.module.url",
+            ),
+        ] {
+            match validate(&mut dataflow, invalid) {
+                Err(DataflowValidateError::InvalidOperatorConfig(err)) => {
+                    assert_eq!(err.operator_id, 3);
+                    assert_eq!(err.kind, kind);
+                    assert_eq!(err.field, field);
+                }
+                result => panic!("unexpected result {:?}", result),
+            };
+        }
+    }
+
     #[test]
     fn test_dataflow_is_cyclic() {
         use proto::common::Dataflow;
@@ -434,10 +1369,7 @@ mod tests {
         meta_2.neighbors = vec![0];
 
         dataflow.meta = vec![meta, meta_1, meta_2];
-        dataflow.job_id = Some(ResourceId {
-            resource_id: "resourceId".to_string(),
-            namespace_id: "namespace_id".to_string(),
-        });
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
         let mut nodes = HashMap::default();
 
         (0..5).for_each(|index| {
@@ -458,6 +1390,433 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_validate_broadcast_edge() {
+        use proto::common::redis_desc::ConnectionOpts;
+        use proto::common::{
+            sink, Dataflow, DataflowMeta, EdgeType, Func, OperatorInfo, RedisDesc, Sink,
+        };
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::new("namespace_id", "resourceId"));
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        meta.neighbors = vec![1, 2];
+        meta.edge_types = HashMap::from([(1, EdgeType::Broadcast as i32)]);
+        dataflow.meta = vec![meta.clone()];
+
+        let mut nodes = HashMap::default();
+        (0..2).for_each(|index| {
+            let mut info = OperatorInfo::default();
+            info.operator_id = index;
+            info.details = Some(Details::Filter(Default::default()));
+            nodes.insert(index, info);
+        });
+        let mut sink_info = OperatorInfo::default();
+        sink_info.operator_id = 2;
+        sink_info.details = Some(Details::Sink(Sink {
+            desc: Some(sink::Desc::Redis(RedisDesc {
+                connection_opts: Some(ConnectionOpts {
+                    host: "localhost".to_string(),
+                    username: Default::default(),
+                    password: Default::default(),
+                    database: 0,
+                    tls: false,
+                }),
+                key_extractor: Some(Func::default()),
+                value_extractor: Some(Func::default()),
+            })),
+            delivery_guarentee: Default::default(),
+            batching: None,
+        }));
+        nodes.insert(2, sink_info);
+        dataflow.nodes = nodes;
+        assert!(dataflow.validate().is_ok());
+
+        // broadcast edges into sinks are invalid
+        meta.edge_types = HashMap::from([(2, EdgeType::Broadcast as i32)]);
+        dataflow.meta = vec![meta.clone()];
+        match dataflow.validate() {
+            Err(DataflowValidateError::InvalidBroadcastEdge(_)) => {}
+            _ => panic!("unexpected result"),
+        };
+
+        // the target of a broadcast edge must be a neighbor
+        meta.edge_types = HashMap::from([(3, EdgeType::Broadcast as i32)]);
+        dataflow.meta = vec![meta];
+        match dataflow.validate() {
+            Err(DataflowValidateError::InvalidBroadcastEdge(_)) => {}
+            _ => panic!("unexpected result"),
+        };
+    }
+
+    #[test]
+    fn test_validate_error_policy() {
+        use proto::common::{error_policy, Dataflow, DataflowMeta, ErrorPolicy, OperatorInfo};
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::default());
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        meta.neighbors = vec![1];
+        dataflow.meta = vec![meta];
+
+        let new_nodes = |error_policy: ErrorPolicy| {
+            (0..2)
+                .map(|index| {
+                    let mut info = OperatorInfo::default();
+                    info.operator_id = index;
+                    info.details = Some(Details::Filter(Default::default()));
+                    if index == 0 {
+                        info.error_policy = Some(error_policy.clone());
+                    }
+                    (index, info)
+                })
+                .collect::<HashMap<_, _>>()
+        };
+        let dead_letter = |sink| ErrorPolicy {
+            policy: Some(error_policy::Policy::DeadLetter(error_policy::DeadLetter {
+                sink,
+            })),
+        };
+        let retry = |attempts, fallback| ErrorPolicy {
+            policy: Some(error_policy::Policy::Retry(Box::new(error_policy::Retry {
+                attempts,
+                backoff: None,
+                fallback: Some(Box::new(fallback)),
+            }))),
+        };
+
+        dataflow.nodes = new_nodes(retry(3, dead_letter(1)));
+        assert!(dataflow.validate().is_ok());
+
+        // the dead-letter operator must be a downstream
+        for error_policy in [dead_letter(2), retry(3, dead_letter(2))] {
+            dataflow.nodes = new_nodes(error_policy);
+            match dataflow.validate() {
+                Err(DataflowValidateError::InvalidErrorPolicy(_)) => {}
+                _ => panic!("unexpected result"),
+            };
+        }
+
+        for error_policy in [retry(0, dead_letter(1)), retry(3, retry(3, dead_letter(1)))] {
+            dataflow.nodes = new_nodes(error_policy);
+            match dataflow.validate() {
+                Err(DataflowValidateError::InvalidErrorPolicy(_)) => {}
+                _ => panic!("unexpected result"),
+            };
+        }
+    }
+
+    #[test]
+    fn test_validate_payload_schema() {
+        use proto::common::{
+            error_policy, payload_schema, DataTypeEnum, Dataflow, DataflowMeta, ErrorPolicy,
+            OperatorInfo, PayloadSchema,
+        };
+        use std::collections::HashMap;
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::default());
+        let mut meta = DataflowMeta::default();
+        meta.center = 0;
+        meta.neighbors = vec![1, 2];
+        dataflow.meta = vec![meta];
+
+        let field = |name: &str, data_type: DataTypeEnum, required| payload_schema::Field {
+            name: name.to_string(),
+            data_type: data_type as i32,
+            required,
+        };
+        let schema = |fields| PayloadSchema {
+            fields,
+            ..Default::default()
+        };
+        let new_nodes = |output: PayloadSchema, input: PayloadSchema| {
+            (0..3)
+                .map(|index| {
+                    let mut info = OperatorInfo::default();
+                    info.operator_id = index;
+                    info.details = Some(Details::Filter(Default::default()));
+                    match index {
+                        0 => {
+                            info.output_schema = Some(output.clone());
+                            info.error_policy = Some(ErrorPolicy {
+                                policy: Some(error_policy::Policy::DeadLetter(
+                                    error_policy::DeadLetter { sink: 2 },
+                                )),
+                            });
+                        }
+                        // the dead-letter operator receives the input events, so its schema is not checked
+                        _ => info.input_schema = Some(input.clone()),
+                    }
+                    (index, info)
+                })
+                .collect::<HashMap<_, _>>()
+        };
+        let output = schema(vec![
+            field("id", DataTypeEnum::Bigint, true),
+            field("name", DataTypeEnum::String, false),
+        ]);
+
+        dataflow.nodes = new_nodes(
+            output.clone(),
+            schema(vec![
+                field("id", DataTypeEnum::Number, true),
+                field("name", DataTypeEnum::Unspecified, false),
+                field("score", DataTypeEnum::Number, false),
+            ]),
+        );
+        assert!(dataflow.validate().is_ok());
+
+        for (input, msg) in [
+            (
+                schema(vec![field("ID", DataTypeEnum::Bigint, true)]),
+                "output of operator 0 does not satisfy input of operator 1: field [ID] is required but not emitted",
+            ),
+            (
+                schema(vec![field("name", DataTypeEnum::String, true)]),
+                "output of operator 0 does not satisfy input of operator 1: field [name] is required but emitted as optional",
+            ),
+            (
+                schema(vec![field("id", DataTypeEnum::String, false)]),
+                "output of operator 0 does not satisfy input of operator 1: field [id] is emitted as Bigint but received as String",
+            ),
+        ] {
+            dataflow.nodes = new_nodes(output.clone(), input);
+            match dataflow.validate() {
+                Err(DataflowValidateError::PayloadSchemaMismatch(err)) => assert_eq!(err, msg),
+                _ => panic!("unexpected result"),
+            };
+        }
+
+        let mut sampling = schema(vec![field("id", DataTypeEnum::Bigint, true)]);
+        sampling.set_validation(payload_schema::Validation::Sampling);
+        for input in [
+            schema(vec![field("", DataTypeEnum::Bigint, true)]),
+            schema(vec![
+                field("id", DataTypeEnum::Bigint, true),
+                field("id", DataTypeEnum::Bigint, true),
+            ]),
+            sampling,
+        ] {
+            dataflow.nodes = new_nodes(output.clone(), input);
+            match dataflow.validate() {
+                Err(DataflowValidateError::InvalidPayloadSchema(_)) => {}
+                _ => panic!("unexpected result"),
+            };
+        }
+    }
+
+    #[test]
+    fn test_dataflow_lint() {
+        use proto::common::{
+            error_policy, mysql_desc, payload_schema, sink, Dataflow, DataflowMeta,
+            DeliveryGuarentee, ErrorPolicy, KafkaDesc, MysqlDesc, OperatorInfo, PayloadSchema,
+            Sink,
+        };
+        use proto::common_impl::{LintKind, LintSeverity};
+
+        let new_sink = |desc: sink::Desc, delivery_guarentee: DeliveryGuarentee| {
+            let mut sink = Sink::default();
+            sink.desc = Some(desc);
+            sink.set_delivery_guarentee(delivery_guarentee);
+            Details::Sink(sink)
+        };
+        let new_mysql = |statement: &str| {
+            sink::Desc::Mysql(MysqlDesc {
+                statement: Some(mysql_desc::Statement {
+                    statement: statement.to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+        };
+        let retry = |fallback: Option<ErrorPolicy>| ErrorPolicy {
+            policy: Some(error_policy::Policy::Retry(Box::new(error_policy::Retry {
+                attempts: 3,
+                fallback: fallback.map(Box::new),
+                ..Default::default()
+            }))),
+        };
+        let mut strict = PayloadSchema::default();
+        strict.set_validation(payload_schema::Validation::Strict);
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::default());
+        dataflow.meta = [
+            (0, vec![1, 4, 6, 7]),
+            (1, vec![2]),
+            (2, vec![3]),
+            (6, vec![5]),
+        ]
+        .into_iter()
+        .map(|(center, neighbors)| DataflowMeta {
+            center,
+            neighbors,
+            ..Default::default()
+        })
+        .collect();
+        dataflow.nodes = [
+            (0, Details::Source(Default::default()), None, None),
+            // window without side output
+            (1, Details::Window(Default::default()), None, None),
+            // retry without fallback
+            (
+                2,
+                Details::Mapper(Default::default()),
+                None,
+                Some(retry(None)),
+            ),
+            // at-least-once kafka sink
+            (
+                3,
+                new_sink(
+                    sink::Desc::Kafka(KafkaDesc::default()),
+                    DeliveryGuarentee::DeliveryAtLeastOnce,
+                ),
+                None,
+                None,
+            ),
+            // dangling operator
+            (4, Details::Filter(Default::default()), None, None),
+            // idempotent mysql sink
+            (
+                5,
+                new_sink(
+                    new_mysql("insert into t values (?) on duplicate key update v = ?"),
+                    DeliveryGuarentee::DeliveryAtLeastOnce,
+                ),
+                None,
+                None,
+            ),
+            // runtime validation without error policy
+            (6, Details::Filter(Default::default()), Some(strict), None),
+            // non-idempotent mysql sink
+            (
+                7,
+                new_sink(
+                    new_mysql("insert into t values (?)"),
+                    DeliveryGuarentee::None,
+                ),
+                None,
+                None,
+            ),
+        ]
+        .into_iter()
+        .map(|(operator_id, details, input_schema, error_policy)| {
+            let mut info = OperatorInfo::default();
+            info.operator_id = operator_id;
+            info.details = Some(details);
+            info.input_schema = input_schema;
+            info.error_policy = error_policy;
+            (operator_id, info)
+        })
+        .collect();
+
+        let warnings = dataflow
+            .lint()
+            .into_iter()
+            .map(|warning| (warning.operator_id, warning.kind, warning.severity))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            vec![
+                (1, LintKind::DroppedLateEvents, LintSeverity::Info),
+                (2, LintKind::RetryWithoutFallback, LintSeverity::Info),
+                (3, LintKind::DuplicateSinkWrites, LintSeverity::Warning),
+                (4, LintKind::DanglingOperator, LintSeverity::Warning),
+                (6, LintKind::SilentSchemaViolations, LintSeverity::Warning),
+                (7, LintKind::DuplicateSinkWrites, LintSeverity::Warning),
+            ]
+        );
+
+        // a retry with fallback and an exactly-once sink don't have warnings
+        let operator = dataflow.nodes.get_mut(&2).unwrap();
+        operator.error_policy = Some(retry(Some(ErrorPolicy::default())));
+        let operator = dataflow.nodes.get_mut(&3).unwrap();
+        operator.details = Some(new_sink(
+            sink::Desc::Kafka(KafkaDesc::default()),
+            DeliveryGuarentee::DeliveryExactlyOnce,
+        ));
+        assert!(dataflow
+            .lint()
+            .iter()
+            .all(|warning| warning.operator_id != 2 && warning.operator_id != 3));
+    }
+
+    #[test]
+    fn test_operator_chains() {
+        use proto::common::{
+            Chaining, Dataflow, DataflowMeta, EdgeType, HostAddr, OperatorInfo, Sink, Source,
+        };
+        use std::collections::{BTreeMap, HashMap};
+
+        let mut dataflow = Dataflow::default();
+        dataflow.job_id = Some(ResourceId::default());
+        dataflow.meta = (0..8)
+            .map(|center| DataflowMeta {
+                center,
+                neighbors: if center < 7 { vec![center + 1] } else { vec![] },
+                edge_types: Default::default(),
+            })
+            .collect();
+        let details = [
+            Details::Source(Source::default()),
+            Details::Mapper(Default::default()),
+            Details::Filter(Default::default()),
+            Details::Project(Default::default()),
+            Details::KeyBy(Default::default()),
+            Details::Mapper(Default::default()),
+            Details::FlatMap(Default::default()),
+            Details::Sink(Sink::default()),
+        ];
+        dataflow.nodes = details
+            .into_iter()
+            .enumerate()
+            .map(|(index, details)| {
+                let mut info = OperatorInfo::default();
+                info.operator_id = index as u32;
+                info.details = Some(details);
+                (index as u32, info)
+            })
+            .collect::<HashMap<_, _>>();
+        dataflow
+            .nodes
+            .get_mut(&5)
+            .unwrap()
+            .set_chaining(Chaining::Disabled);
+
+        // sources, sinks, keyBy and operators whose chaining is disabled are not chained
+        assert_eq!(
+            dataflow.get_operator_chains(),
+            BTreeMap::from([(1, vec![2, 3])])
+        );
+
+        // operators on different hosts are not chained
+        dataflow.nodes.get_mut(&3).unwrap().host_addr = Some(HostAddr {
+            host: "localhost".to_string(),
+            port: 8792,
+        });
+        assert_eq!(
+            dataflow.get_operator_chains(),
+            BTreeMap::from([(1, vec![2])])
+        );
+
+        // operators connected by a broadcast edge are not chained
+        dataflow.meta[1]
+            .edge_types
+            .insert(2, EdgeType::Broadcast as i32);
+        assert!(dataflow.get_operator_chains().is_empty());
+
+        // an operator with multiple upstreams is not chained
+        dataflow.meta[1].edge_types.clear();
+        dataflow.meta[0].neighbors.push(2);
+        assert!(dataflow.get_operator_chains().is_empty());
+    }
+
     #[test]
     fn test_serde_env() {
         let origin = "{\"name\":\"${your.name}\", \"card\": \"${your.card}\", \"info\": {\"address\": \"${your.addr}\", \"second_address\": \"${your.addr}\"}}";
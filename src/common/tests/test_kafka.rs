@@ -81,7 +81,7 @@ async fn test_kafka_pub_sub_blocking_fetch() {
         assert!(send_result.is_ok());
     }
 
-    let opt = consumer.blocking_fetch(|msg| {
+    let opt = consumer.blocking_fetch(&Default::default(), |msg| {
         let key = String::from_utf8(msg.key.to_vec());
         assert!(key.is_ok());
         let key = key.unwrap();
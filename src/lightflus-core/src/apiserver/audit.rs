@@ -0,0 +1,429 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
+
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{
+    errors::server::ServerError,
+    metrics::{Counter, MetricsRegistry},
+};
+
+use super::config::{AuditConfig, AuditFileConfig, AuditKafkaConfig};
+
+/// the records which are dropped because the queue of the sink is full
+pub const AUDIT_DROPPED_METRIC: &str = "lightflus_apiserver_audit_dropped_records_total";
+
+/// a mutation requested through the API, see [`super::middleware::Audit`]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuditRecord {
+    /// milliseconds since the unix epoch when the request is received
+    pub timestamp: i64,
+    pub request_id: String,
+    /// the identity of the caller, `anonymous` if the request is not authenticated
+    pub identity: String,
+    /// one of `create`, `update`, `delete`, `terminate` and `apply`, which creates or updates a batch of resources
+    pub verb: String,
+    pub method: String,
+    pub path: String,
+    /// the namespace in the path of the request. The resources of a creation or a batch termination are in its body
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub namespace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    /// the HTTP status of the response
+    pub status: u16,
+    pub latency_ms: u64,
+    /// hex-encoded SHA-256 of the body which is read by the handler
+    pub body_sha256: String,
+}
+
+/// response of `GET /audit`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub(crate) struct AuditTail {
+    /// the oldest first
+    pub records: Vec<AuditRecord>,
+}
+
+/// [`AuditLog`] keeps the latest records in memory and sends every record to the sink of [`AuditConfig`] in the background.
+/// The records wait in a bounded queue, so a slow sink never stalls the requests: once the queue is full,
+/// the new records are dropped, logged and counted in the [`AUDIT_DROPPED_METRIC`] counter
+pub(crate) struct AuditLog {
+    /// it's taken once the log is flushed, see [`AuditLog::flush`]
+    sender: Mutex<Option<mpsc::Sender<AuditRecord>>>,
+    /// the task serving the sink, it's done once the sender is dropped and the queued records are sent
+    sink: Mutex<Option<JoinHandle<()>>>,
+    tail: Mutex<VecDeque<AuditRecord>>,
+    tail_size: usize,
+    dropped: Counter,
+}
+
+impl AuditLog {
+    /// the sink is served by a task in the background, so it should be called in a Tokio runtime if a sink is configured.
+    /// It fails if the file can't be opened or the producer can't be created
+    pub(crate) fn new(
+        config: &AuditConfig,
+        registry: &MetricsRegistry,
+    ) -> Result<Self, ServerError> {
+        let (sender, receiver) = mpsc::channel(config.queue_size.max(1));
+        let sink = match (config.file.as_ref(), config.kafka.as_ref()) {
+            (Some(file), _) => {
+                let mut file = AuditFile::open(file)?;
+                tokio::task::spawn_blocking(move || file.write_all(receiver))
+            }
+            (None, Some(kafka)) => {
+                let sink = AuditTopic::new(kafka)?;
+                tokio::spawn(sink.send_all(receiver))
+            }
+            (None, None) => return Ok(Self::in_memory(config, registry)),
+        };
+        Ok(Self {
+            sender: Mutex::new(Some(sender)),
+            sink: Mutex::new(Some(sink)),
+            ..Self::in_memory(config, registry)
+        })
+    }
+
+    /// the records are only kept in memory
+    fn in_memory(config: &AuditConfig, registry: &MetricsRegistry) -> Self {
+        Self {
+            sender: Mutex::new(None),
+            sink: Mutex::new(None),
+            tail: Mutex::new(VecDeque::with_capacity(config.tail)),
+            tail_size: config.tail,
+            dropped: registry.counter(AUDIT_DROPPED_METRIC),
+        }
+    }
+
+    pub(crate) fn record(&self, record: AuditRecord) {
+        tracing::info!(
+            target: "audit",
+            request_id = %record.request_id,
+            "{} {} {} {}",
+            record.identity,
+            record.verb,
+            record.path,
+            record.status
+        );
+        if self.tail_size > 0 {
+            let mut tail = self.tail.lock().unwrap_or_else(|err| err.into_inner());
+            if tail.len() >= self.tail_size {
+                tail.pop_front();
+            }
+            tail.push_back(record.clone());
+        }
+        let sender = self.sender.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(sender) = sender.as_ref() {
+            if let Err(err) = sender.try_send(record) {
+                self.dropped.inc(1);
+                let record = match err {
+                    mpsc::error::TrySendError::Full(record)
+                    | mpsc::error::TrySendError::Closed(record) => record,
+                };
+                tracing::warn!(
+                    target: "audit",
+                    "audit record of request {} is dropped since the sink is full or closed",
+                    record.request_id
+                );
+            }
+        }
+    }
+
+    /// close the queue and wait until the queued records are sent to the sink, returns false if they're not sent in time.
+    /// The records which are recorded later are only kept in memory
+    pub(crate) async fn flush(&self, timeout: Duration) -> bool {
+        self.sender
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+        let sink = self
+            .sink
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+        match sink {
+            Some(sink) => tokio::time::timeout(timeout, sink).await.is_ok(),
+            None => true,
+        }
+    }
+
+    /// the latest records, the oldest first. All of the kept ones are returned if `limit` is none
+    pub(crate) fn get_tail(&self, limit: Option<usize>) -> Vec<AuditRecord> {
+        let tail = self.tail.lock().unwrap_or_else(|err| err.into_inner());
+        let skip = limit
+            .map(|limit| tail.len().saturating_sub(limit))
+            .unwrap_or_default();
+        tail.iter().skip(skip).cloned().collect()
+    }
+}
+
+/// an append-only JSON Lines file which is rotated by its size, see [`AuditFileConfig`]
+struct AuditFile {
+    path: PathBuf,
+    file: fs::File,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl AuditFile {
+    fn open(config: &AuditFileConfig) -> io::Result<Self> {
+        let path = PathBuf::from(&config.path);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        Ok(Self {
+            size: file.metadata()?.len(),
+            path,
+            file,
+            max_size: config.max_size,
+            max_files: config.max_files,
+        })
+    }
+
+    /// write the records until all of the senders are dropped
+    fn write_all(&mut self, mut receiver: mpsc::Receiver<AuditRecord>) {
+        while let Some(record) = receiver.blocking_recv() {
+            if let Err(err) = self.append(&record) {
+                tracing::error!(
+                    target: "audit",
+                    "write audit record of request {} to {:?} failed: {}",
+                    record.request_id,
+                    &self.path,
+                    err
+                );
+            }
+        }
+    }
+
+    fn append(&mut self, record: &AuditRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        // a record larger than the max size takes a file of its own
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(&line)?;
+        self.file.flush()?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// the current file becomes `{path}.1` and the older ones are shifted, the oldest one is removed
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated = |index: usize| PathBuf::from(format!("{}.{index}", self.path.display()));
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            match fs::remove_file(rotated(self.max_files)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+            for index in (1..self.max_files).rev() {
+                match fs::rename(rotated(index), rotated(index + 1)) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                    _ => {}
+                }
+            }
+            fs::rename(&self.path, rotated(1))?;
+        }
+        self.file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// a Kafka topic whose messages are the records in JSON, keyed by their request ids.
+/// They're sent to the first partition, so they're kept in the order of the requests
+struct AuditTopic {
+    producer: common::kafka::KafkaProducer,
+    topic: String,
+}
+
+impl AuditTopic {
+    fn new(config: &AuditKafkaConfig) -> Result<Self, ServerError> {
+        common::kafka::run_producer(&config.brokers, &config.topic, "lightflus-audit", 0)
+            .map(|producer| Self {
+                producer,
+                topic: config.topic.clone(),
+            })
+            .map_err(|err| {
+                ServerError::InvalidConfig(vec![format!(
+                    "audit.kafka: create the producer failed: {err}"
+                )])
+            })
+    }
+
+    /// send the records until all of the senders are dropped
+    async fn send_all(self, mut receiver: mpsc::Receiver<AuditRecord>) {
+        while let Some(record) = receiver.recv().await {
+            let result = match serde_json::to_vec(&record) {
+                Ok(payload) => self
+                    .producer
+                    .send(record.request_id.as_bytes(), &payload)
+                    .await
+                    .map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            if let Err(err) = result {
+                tracing::error!(
+                    target: "audit",
+                    "send audit record of request {} to topic {} failed: {}",
+                    record.request_id,
+                    &self.topic,
+                    err
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Mutex, time::Duration};
+
+    use crate::{
+        apiserver::config::{AuditConfig, AuditFileConfig},
+        metrics::MetricsRegistry,
+    };
+
+    use super::{AuditFile, AuditLog, AuditRecord, AUDIT_DROPPED_METRIC};
+
+    fn new_record(request_id: &str) -> AuditRecord {
+        AuditRecord {
+            timestamp: 0,
+            request_id: request_id.to_string(),
+            identity: "alice".to_string(),
+            verb: "delete".to_string(),
+            method: "DELETE".to_string(),
+            path: "/resources/default/job".to_string(),
+            namespace: Some("default".to_string()),
+            name: Some("job".to_string()),
+            status: 202,
+            latency_ms: 1,
+            body_sha256: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_audit_file_rotation() {
+        let dir = std::env::temp_dir().join(format!("lightflus-audit-{}", common::utils::uuid()));
+        let path = dir.join("audit.jsonl");
+        let line_size = serde_json::to_vec(&new_record("0")).unwrap().len() as u64 + 1;
+        let mut file = AuditFile::open(&AuditFileConfig {
+            path: path.to_string_lossy().to_string(),
+            max_size: line_size * 2,
+            max_files: 2,
+        })
+        .unwrap();
+        for request_id in 0..7 {
+            file.append(&new_record(&request_id.to_string())).unwrap();
+        }
+
+        // two records per file, and the oldest file is removed
+        let request_ids = |suffix: &str| {
+            fs::read_to_string(format!("{}{suffix}", path.display()))
+                .unwrap()
+                .lines()
+                .map(|line| {
+                    serde_json::from_str::<AuditRecord>(line)
+                        .unwrap()
+                        .request_id
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(request_ids(""), vec!["6"]);
+        assert_eq!(request_ids(".1"), vec!["4", "5"]);
+        assert_eq!(request_ids(".2"), vec!["2", "3"]);
+        assert!(!dir.join("audit.jsonl.3").exists());
+
+        // the size of an existing file is counted after a restart
+        let mut file = AuditFile::open(&AuditFileConfig {
+            path: path.to_string_lossy().to_string(),
+            max_size: line_size * 2,
+            max_files: 2,
+        })
+        .unwrap();
+        file.append(&new_record("7")).unwrap();
+        file.append(&new_record("8")).unwrap();
+        assert_eq!(request_ids(""), vec!["8"]);
+        assert_eq!(request_ids(".1"), vec!["6", "7"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_audit_queue_overflow() {
+        let registry = MetricsRegistry::default();
+        let config = AuditConfig {
+            queue_size: 2,
+            tail: 3,
+            ..Default::default()
+        };
+        // nothing drains the queue
+        let (sender, _receiver) = tokio::sync::mpsc::channel(config.queue_size);
+        let audit = AuditLog {
+            sender: Mutex::new(Some(sender)),
+            ..AuditLog::in_memory(&config, &registry)
+        };
+        for request_id in 0..5 {
+            audit.record(new_record(&request_id.to_string()));
+        }
+        assert_eq!(registry.counter(AUDIT_DROPPED_METRIC).get(), 3);
+        // the tail keeps the latest records, including the dropped ones
+        let request_ids = |records: Vec<AuditRecord>| {
+            records
+                .into_iter()
+                .map(|record| record.request_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(request_ids(audit.get_tail(None)), vec!["2", "3", "4"]);
+        assert_eq!(request_ids(audit.get_tail(Some(1))), vec!["4"]);
+    }
+
+    #[tokio::test]
+    async fn test_audit_flush() {
+        let registry = MetricsRegistry::default();
+        let dir = std::env::temp_dir().join(format!("lightflus-audit-{}", common::utils::uuid()));
+        let path = dir.join("audit.jsonl");
+        let audit = AuditLog::new(
+            &AuditConfig {
+                file: Some(AuditFileConfig {
+                    path: path.to_string_lossy().to_string(),
+                    max_size: 1024 * 1024,
+                    max_files: 1,
+                }),
+                ..Default::default()
+            },
+            &registry,
+        )
+        .unwrap();
+        for request_id in 0..3 {
+            audit.record(new_record(&request_id.to_string()));
+        }
+        // the queued records are written once it's flushed
+        assert!(audit.flush(Duration::from_secs(3)).await);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        // the records are only kept in memory after it's flushed
+        audit.record(new_record("3"));
+        assert_eq!(audit.get_tail(None).len(), 4);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        assert!(audit.flush(Duration::from_secs(3)).await);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::SystemTime,
+};
+
+use actix_web::{dev::Payload, FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::{ready, Ready};
+
+use crate::{
+    errors::apiserver::{ApiError, ApiErrorCode, ApiErrorDetail},
+    CALLER_METADATA_KEY, REQUEST_ID_METADATA_KEY,
+};
+
+use super::middleware::RequestContext;
+
+/// path of the token file, the requests are not authenticated if it's not set. See [`TokenStore`] for the format
+pub const TOKEN_FILE_ENV: &str = "LIGHTFLUS_API_TOKEN_FILE";
+/// the health endpoints are authenticated as well if it's `true`
+pub const AUTHENTICATE_HEALTH_ENV: &str = "LIGHTFLUS_API_AUTHENTICATE_HEALTH";
+
+/// what a caller is allowed to do. A writer can read as well, and an admin can write and read the audit log
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Role {
+    Read,
+    Write,
+    Admin,
+}
+
+impl Role {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct TokenEntry {
+    token: String,
+    identity: String,
+    /// all of the namespaces are allowed if it's empty
+    #[serde(default)]
+    namespaces: Vec<String>,
+    role: Role,
+}
+
+#[derive(serde::Deserialize)]
+struct TokenFile {
+    tokens: Vec<TokenEntry>,
+}
+
+/// the identity of the caller of a request, which is attached to the requests to the coordinator by [`Caller::new_request`].
+/// Handlers extract it to check the namespaces of the resources before the coordinator is called
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Caller {
+    identity: String,
+    /// none if all of the namespaces are allowed
+    namespaces: Option<HashSet<String>>,
+    role: Role,
+}
+
+impl Caller {
+    /// the caller of the requests which are not authenticated, it's allowed to do anything
+    pub(crate) fn anonymous() -> Self {
+        Self {
+            identity: "anonymous".to_string(),
+            namespaces: None,
+            role: Role::Admin,
+        }
+    }
+
+    pub(crate) fn get_identity(&self) -> &str {
+        &self.identity
+    }
+
+    pub(crate) fn authorize_role(&self, role: Role) -> Result<(), ApiError> {
+        if self.role >= role {
+            Ok(())
+        } else {
+            Err(ApiError::new(
+                ApiErrorCode::PermissionDenied,
+                format!("{} is not allowed to {}", self.identity, role.as_str()),
+            ))
+        }
+    }
+
+    pub(crate) fn authorize_namespace(&self, namespace: &str) -> Result<(), ApiError> {
+        match &self.namespaces {
+            Some(namespaces) if !namespaces.contains(namespace) => Err(ApiError::new(
+                ApiErrorCode::PermissionDenied,
+                format!(
+                    "{} is not allowed to access namespace {}",
+                    self.identity, namespace
+                ),
+            )
+            .with_detail(
+                ApiErrorDetail::new(format!("namespace {namespace} is not allowed"))
+                    .with_field("namespace"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// a caller restricted to some namespaces can't access the resources of all of them at once
+    pub(crate) fn authorize_all_namespaces(&self) -> Result<(), ApiError> {
+        match &self.namespaces {
+            Some(_) => Err(ApiError::new(
+                ApiErrorCode::PermissionDenied,
+                format!(
+                    "{} is not allowed to access all of the namespaces",
+                    self.identity
+                ),
+            )
+            .with_detail(ApiErrorDetail::new("namespace is required").with_field("namespace"))),
+            None => Ok(()),
+        }
+    }
+
+    /// a request to the coordinator with the identity in its metadata, so the coordinator can audit it.
+    /// The id of the request in hand is in the metadata as well, see [`RequestContext`]
+    pub(crate) fn new_request<T>(&self, message: T) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+        if let Ok(identity) = self.identity.parse() {
+            request.metadata_mut().insert(CALLER_METADATA_KEY, identity);
+        }
+        if let Some(Ok(request_id)) =
+            RequestContext::current().map(|context| context.get_request_id().parse())
+        {
+            request
+                .metadata_mut()
+                .insert(REQUEST_ID_METADATA_KEY, request_id);
+        }
+        request
+    }
+}
+
+impl FromRequest for Caller {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<Caller>()
+            .cloned()
+            .unwrap_or_else(Caller::anonymous)))
+    }
+}
+
+/// version of the token file, it's reloaded once the version changes
+type FileVersion = (Option<SystemTime>, u64);
+
+struct TokenState {
+    version: FileVersion,
+    callers: HashMap<String, Caller>,
+}
+
+/// [`TokenStore`] authenticates the bearer tokens by a JSON file like
+///
+/// ```json
+/// {"tokens": [{"token": "secret", "identity": "alice", "namespaces": ["default"], "role": "write"}]}
+/// ```
+///
+/// The file is reloaded once it's modified. If it fails to be reloaded, the tokens loaded before are kept
+pub(crate) struct TokenStore {
+    path: PathBuf,
+    state: RwLock<TokenState>,
+}
+
+impl TokenStore {
+    /// the file must be valid on startup
+    pub(crate) fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let version = get_version(&path)?;
+        let callers = read_callers(&path)?;
+        Ok(Self {
+            path,
+            state: RwLock::new(TokenState { version, callers }),
+        })
+    }
+
+    pub(crate) fn authenticate(&self, token: &str) -> Option<Caller> {
+        self.reload_if_modified();
+        self.state
+            .read()
+            .ok()
+            .and_then(|state| state.callers.get(token).cloned())
+    }
+
+    fn reload_if_modified(&self) {
+        let version = match get_version(&self.path) {
+            Ok(version) => version,
+            Err(err) => {
+                tracing::error!("read token file {:?} failed: {}", &self.path, err);
+                return;
+            }
+        };
+        if self
+            .state
+            .read()
+            .map(|state| state.version == version)
+            .unwrap_or(true)
+        {
+            return;
+        }
+
+        let result = read_callers(&self.path);
+        if let Ok(mut state) = self.state.write() {
+            state.version = version;
+            match result {
+                Ok(callers) => {
+                    tracing::info!("token file {:?} is reloaded", &self.path);
+                    state.callers = callers;
+                }
+                Err(err) => tracing::error!("reload token file {:?} failed: {}", &self.path, err),
+            }
+        }
+    }
+}
+
+fn get_version(path: &Path) -> io::Result<FileVersion> {
+    fs::metadata(path).map(|metadata| (metadata.modified().ok(), metadata.len()))
+}
+
+fn read_callers(path: &Path) -> io::Result<HashMap<String, Caller>> {
+    let file: TokenFile = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(file
+        .tokens
+        .into_iter()
+        .map(|entry| {
+            (
+                entry.token,
+                Caller {
+                    identity: entry.identity,
+                    namespaces: Some(entry.namespaces.into_iter().collect::<HashSet<_>>())
+                        .filter(|namespaces| !namespaces.is_empty()),
+                    role: entry.role,
+                },
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use crate::{
+        apiserver::middleware::RequestContext, errors::apiserver::ApiErrorCode,
+        CALLER_METADATA_KEY, REQUEST_ID_METADATA_KEY,
+    };
+
+    use super::{Caller, Role, TokenStore};
+
+    #[test]
+    fn test_token_store_reload() {
+        let dir = std::env::temp_dir().join(format!("lightflus-tokens-{}", common::utils::uuid()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tokens.json");
+        assert!(TokenStore::load(&path).is_err());
+        fs::write(
+            &path,
+            r#"{"tokens": [{"token": "t1", "identity": "alice", "namespaces": ["default"], "role": "read"}]}"#,
+        )
+        .unwrap();
+
+        let store = TokenStore::load(&path).unwrap();
+        let caller = store.authenticate("t1").unwrap();
+        assert_eq!(caller.identity, "alice");
+        assert!(caller.authorize_role(Role::Read).is_ok());
+        assert_eq!(
+            caller.authorize_role(Role::Write).unwrap_err().code,
+            ApiErrorCode::PermissionDenied
+        );
+        assert!(caller.authorize_namespace("default").is_ok());
+        assert_eq!(
+            caller.authorize_namespace("other").unwrap_err().code,
+            ApiErrorCode::PermissionDenied
+        );
+        assert!(caller.authorize_all_namespaces().is_err());
+        assert!(store.authenticate("t2").is_none());
+
+        // the modified file is reloaded
+        std::thread::sleep(Duration::from_millis(10));
+        fs::write(
+            &path,
+            r#"{"tokens": [{"token": "t2", "identity": "bob", "role": "write"}]}"#,
+        )
+        .unwrap();
+        assert!(store.authenticate("t1").is_none());
+        let caller = store.authenticate("t2").unwrap();
+        assert!(caller.authorize_role(Role::Write).is_ok());
+        assert!(caller.authorize_namespace("other").is_ok());
+        assert!(caller.authorize_all_namespaces().is_ok());
+
+        // the tokens are kept if the file is invalid
+        fs::write(&path, "{").unwrap();
+        assert_eq!(store.authenticate("t2"), Some(caller));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_caller_metadata() {
+        let request = Caller::anonymous().new_request(());
+        assert_eq!(
+            request.metadata().get(CALLER_METADATA_KEY).unwrap(),
+            "anonymous"
+        );
+        assert!(request.metadata().get(REQUEST_ID_METADATA_KEY).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_request_id_metadata() {
+        let request = RequestContext::new("test-request")
+            .scope(async { Caller::anonymous().new_request(()) })
+            .await;
+        assert_eq!(
+            request.metadata().get(REQUEST_ID_METADATA_KEY).unwrap(),
+            "test-request"
+        );
+    }
+}
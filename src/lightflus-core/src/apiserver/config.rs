@@ -0,0 +1,1029 @@
+use std::{collections::BTreeMap, fs, path::Path, time::Duration};
+
+use actix_web::http::Method;
+use common::backoff::BackoffBuilder;
+use tonic::transport::Endpoint;
+
+use crate::errors::server::ServerError;
+
+use super::{
+    auth::{AUTHENTICATE_HEALTH_ENV, TOKEN_FILE_ENV},
+    handler::{coordinator::DEFAULT_COORDINATOR, COORDINATOR_URI_ENV},
+    API_SERVER_PORT,
+};
+
+/// Configuration of the HTTP API server. It's the `apiserver` section of the config file of the unified binary,
+/// or a JSON file of its own loaded by [`ApiServerConfig::load`]. `${ENV}` in the file is replaced by the environment variable.
+///
+/// All of the fields are optional, e.g.
+/// ```json
+/// {
+///   "host": "0.0.0.0",
+///   "port": 8080,
+///   "coordinator": {
+///     "endpoints": ["coordinator-0:8791", "coordinator-1:8791"],
+///     "connect_timeout": 3,
+///     "rpc_timeout": 3,
+///     "request_timeout": 10,
+///     "retries": 1,
+///     "retry_backoff": {"base": 100, "max": 1000, "jitter": true},
+///     "read_cache_ttl": 5,
+///     "regions": {
+///       "eu": ["coordinator-eu-0:8791", "coordinator-eu-1:8791"],
+///       "us": ["coordinator-us-0:8791"]
+///     },
+///     "routes": [{"prefix": "eu-", "region": "eu"}, {"prefix": "us-", "region": "us"}]
+///   },
+///   "cors": {
+///     "allowed_origins": ["https://console.lightflus.io"],
+///     "allowed_methods": ["GET", "POST", "DELETE"],
+///     "allowed_headers": ["Authorization", "Content-Type"],
+///     "allow_credentials": true,
+///     "max_age": 3600
+///   },
+///   "auth": {
+///     "token_file": "/etc/lightflus/tokens.json",
+///     "authenticate_health": false
+///   },
+///   "operations": {
+///     "capacity": 1000,
+///     "ttl": 600
+///   },
+///   "events": {
+///     "poll_interval": 1,
+///     "heartbeat": 15,
+///     "buffer": 16
+///   },
+///   "audit": {
+///     "file": {"path": "/var/log/lightflus/audit.jsonl", "max_size": 104857600, "max_files": 5},
+///     "queue_size": 1024,
+///     "tail": 100
+///   },
+///   "limits": {
+///     "max_body_size": 4194304,
+///     "rate": {"rate": 50, "burst": 100},
+///     "writes": {"rate": 5, "burst": 10}
+///   },
+///   "batch": {
+///     "max_resources": 100,
+///     "concurrency": 8
+///   },
+///   "swagger_ui": false,
+///   "shutdown": {
+///     "grace_period": 30
+///   },
+///   "probes": {
+///     "interval": 5,
+///     "timeout": 2
+///   },
+///   "metrics": {
+///     "port": 9101
+///   },
+///   "tls": {
+///     "cert_file": "/etc/lightflus/tls.crt",
+///     "key_file": "/etc/lightflus/tls.key"
+///   }
+/// }
+/// ```
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ApiServerConfig {
+    /// the address the API server binds to
+    pub host: String,
+    pub port: u16,
+    pub coordinator: CoordinatorConfig,
+    pub cors: CorsConfig,
+    pub auth: AuthConfig,
+    pub operations: OperationsConfig,
+    pub events: EventsConfig,
+    pub audit: AuditConfig,
+    pub limits: LimitsConfig,
+    pub batch: BatchConfig,
+    /// the Swagger UI of the OpenAPI document is served at `/docs` if it's enabled. The document itself is always served at `/openapi.json`
+    pub swagger_ui: bool,
+    pub shutdown: ShutdownConfig,
+    pub probes: ProbesConfig,
+    pub metrics: ApiMetricsConfig,
+    /// the API server is served by HTTPS if it's configured
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for ApiServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: API_SERVER_PORT,
+            coordinator: Default::default(),
+            cors: Default::default(),
+            auth: Default::default(),
+            operations: Default::default(),
+            events: Default::default(),
+            audit: Default::default(),
+            limits: Default::default(),
+            batch: Default::default(),
+            swagger_ui: false,
+            shutdown: Default::default(),
+            probes: Default::default(),
+            metrics: Default::default(),
+            tls: None,
+        }
+    }
+}
+
+/// the coordinators which the requests are sent to, see [`super::handler::coordinator::CoordinatorGateway`].
+/// Besides the coordinator of `endpoints`, which is named `default`, there may be named coordinators of the regions.
+/// A request of a namespace is routed to a coordinator by the prefix of the namespace, see [`super::handler::coordinator::CoordinatorRouter`]
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct CoordinatorConfig {
+    /// endpoints like `coordinator-0:8791`, the scheme is `http` if it's not given
+    pub endpoints: Vec<String>,
+    /// timeout of connecting to a coordinator in seconds, a dead coordinator is skipped after it
+    pub connect_timeout: u64,
+    /// timeout of a request to a coordinator in seconds
+    pub rpc_timeout: u64,
+    /// timeout of a request of a handler in seconds, including all of its failovers and retries
+    pub request_timeout: u64,
+    /// how many more rounds over all of the endpoints are tried by a read while all of them are unavailable.
+    /// The writes are never retried
+    pub retries: u32,
+    /// the delays between the rounds of a read
+    pub retry_backoff: BackoffBuilder,
+    /// how long the response of a read is kept in seconds, it's served while the coordinators are unavailable.
+    /// The reads are not cached if it's 0
+    pub read_cache_ttl: u64,
+    /// endpoints of the coordinators by the names of their regions. They share the timeouts, the retries and the cache TTL above
+    pub regions: BTreeMap<String, Vec<String>>,
+    /// the namespaces which are served by the coordinators of the regions. A namespace is routed by the longest prefix it matches
+    pub routes: Vec<RouteConfig>,
+    /// the region of the namespaces which match no route. They're served by the coordinator of `endpoints` if it's not set
+    pub default_region: Option<String>,
+}
+
+/// the namespaces starting with `prefix` are served by the coordinator of `region`
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct RouteConfig {
+    pub prefix: String,
+    pub region: String,
+}
+
+impl Default for CoordinatorConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: vec![],
+            connect_timeout: 3,
+            rpc_timeout: 3,
+            request_timeout: 10,
+            retries: 0,
+            retry_backoff: BackoffBuilder {
+                base: 100,
+                max: 1000,
+                jitter: true,
+            },
+            read_cache_ttl: 0,
+            regions: Default::default(),
+            routes: vec![],
+            default_region: None,
+        }
+    }
+}
+
+impl CoordinatorConfig {
+    pub fn get_connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout)
+    }
+
+    pub fn get_rpc_timeout(&self) -> Duration {
+        Duration::from_secs(self.rpc_timeout)
+    }
+
+    pub fn get_request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout)
+    }
+
+    pub fn get_read_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.read_cache_ttl)
+    }
+}
+
+/// the cross-origin requests which are allowed, see [`super::middleware::Cors`].
+/// CORS is disabled if there is no origin, and the requests are served as they are without the CORS headers
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// origins like `https://console.lightflus.io`, or `*` for any origin
+    pub allowed_origins: Vec<String>,
+    /// all of the methods of the API are allowed if it's empty
+    pub allowed_methods: Vec<String>,
+    /// the headers the browsers are allowed to send besides the CORS-safelisted ones, e.g. `Authorization`
+    pub allowed_headers: Vec<String>,
+    /// whether the browsers send the credentials like cookies. It can't be set along with the origin `*`
+    pub allow_credentials: bool,
+    /// how long the browsers cache the results of the preflight requests in seconds
+    pub max_age: Option<u64>,
+}
+
+/// see [`super::auth::TokenStore`] and [`super::middleware::Authentication`]
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// path of the token file, the requests are not authenticated if it's not set
+    pub token_file: Option<String>,
+    /// the health endpoints and the metrics are authenticated as well if it's true
+    pub authenticate_health: bool,
+}
+
+/// the store of the asynchronous operations, see [`super::operations::OperationStore`]
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct OperationsConfig {
+    /// max number of the operations kept in memory
+    pub capacity: usize,
+    /// how long a done operation is kept in seconds
+    pub ttl: u64,
+}
+
+impl Default for OperationsConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1000,
+            ttl: 600,
+        }
+    }
+}
+
+impl OperationsConfig {
+    pub fn get_ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl)
+    }
+}
+
+/// the status events of the dataflows, see [`super::events::EventHub`]
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct EventsConfig {
+    /// how often a watched dataflow is polled in seconds
+    pub poll_interval: u64,
+    /// how long a stream is idle before a heartbeat comment is sent in seconds
+    pub heartbeat: u64,
+    /// max number of the events buffered for a watcher, a slower watcher skips the oldest of them
+    pub buffer: usize,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: 1,
+            heartbeat: 15,
+            buffer: 16,
+        }
+    }
+}
+
+impl EventsConfig {
+    pub fn get_poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval)
+    }
+
+    pub fn get_heartbeat(&self) -> Duration {
+        Duration::from_secs(self.heartbeat)
+    }
+}
+
+/// the resources applied by `POST /resources:batch`
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BatchConfig {
+    /// max number of the resources in a batch
+    pub max_resources: usize,
+    /// max number of the resources submitted to the coordinators at the same time
+    pub concurrency: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_resources: 100,
+            concurrency: 8,
+        }
+    }
+}
+
+/// the graceful shutdown of the API server, see [`super::ApiServer::serve`]
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ShutdownConfig {
+    /// how long the in-flight requests and the pending operations are waited for in seconds once the server is signaled to shut down
+    pub grace_period: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self { grace_period: 30 }
+    }
+}
+
+impl ShutdownConfig {
+    pub fn get_grace_period(&self) -> Duration {
+        Duration::from_secs(self.grace_period)
+    }
+}
+
+/// the background probes of the dependencies which `GET /readyz` reports, see [`super::probe::Prober`]
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ProbesConfig {
+    /// how often the dependencies are probed in seconds
+    pub interval: u64,
+    /// how long a coordinator is waited for by a probe in seconds
+    pub timeout: u64,
+}
+
+impl Default for ProbesConfig {
+    fn default() -> Self {
+        Self {
+            interval: 5,
+            timeout: 2,
+        }
+    }
+}
+
+impl ProbesConfig {
+    pub fn get_interval(&self) -> Duration {
+        Duration::from_secs(self.interval)
+    }
+
+    pub fn get_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+}
+
+/// the records of the mutations, see [`super::audit::AuditLog`]. The records are only kept in memory if neither `file` nor `kafka` is set
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// the records are appended to the file if it's set
+    pub file: Option<AuditFileConfig>,
+    /// the records are sent to the topic if it's set. Only one of `file` and `kafka` can be set
+    pub kafka: Option<AuditKafkaConfig>,
+    /// max number of the records waiting to be written, the records are dropped and counted once it's full
+    pub queue_size: usize,
+    /// how many of the latest records are kept in memory for `GET /audit`
+    pub tail: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            kafka: None,
+            queue_size: 1024,
+            tail: 100,
+        }
+    }
+}
+
+/// a JSON Lines file, which is rotated once it exceeds `max_size` bytes. The rotated files are named `{path}.1`, `{path}.2`
+/// and so on, the older the larger the suffix, and only `max_files` of them are kept
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditFileConfig {
+    pub path: String,
+    #[serde(default = "default_audit_file_max_size")]
+    pub max_size: u64,
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: usize,
+}
+
+fn default_audit_file_max_size() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_audit_max_files() -> usize {
+    5
+}
+
+/// a Kafka topic, each record is a JSON message keyed by its request id
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditKafkaConfig {
+    /// comma-separated brokers like `kafka-0:9092,kafka-1:9092`
+    pub brokers: String,
+    pub topic: String,
+}
+
+/// the limits of the requests of each client, see [`super::middleware::RateLimit`]
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// max size of a request body in bytes, a larger body fails with 413
+    pub max_body_size: usize,
+    /// the rate limit of all of the routes, the requests are not limited if it's not set
+    pub rate: Option<RateLimitConfig>,
+    /// the rate limit of the reads, which are `GET` and `HEAD` requests. It overrides `rate`
+    pub reads: Option<RateLimitConfig>,
+    /// the rate limit of the writes, which are the other requests. It overrides `rate`
+    pub writes: Option<RateLimitConfig>,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_size: 4 * 1024 * 1024,
+            rate: None,
+            reads: None,
+            writes: None,
+        }
+    }
+}
+
+impl LimitsConfig {
+    pub fn get_read_limit(&self) -> Option<&RateLimitConfig> {
+        self.reads.as_ref().or(self.rate.as_ref())
+    }
+
+    pub fn get_write_limit(&self) -> Option<&RateLimitConfig> {
+        self.writes.as_ref().or(self.rate.as_ref())
+    }
+}
+
+/// a token bucket of each client, which holds `burst` tokens at most and is refilled by `rate` tokens per second.
+/// Each request takes a token, so a client can send `burst` requests at once and `rate` requests per second after them
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct RateLimitConfig {
+    pub rate: u32,
+    pub burst: u32,
+}
+
+/// where the metrics of the requests are served, see [`super::middleware::AccessLog`]
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct ApiMetricsConfig {
+    /// the metrics are served at `/metrics` by a server of their own on the port if it's set,
+    /// otherwise they're served at `/metrics` of the API server
+    pub port: Option<u16>,
+}
+
+/// PEM files of the certificate chain and the private key of the API server
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct TlsConfig {
+    pub cert_file: String,
+    pub key_file: String,
+}
+
+impl ApiServerConfig {
+    /// load the config from a JSON file by the common config loader
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ServerError> {
+        let content = common::utils::from_reader(fs::File::open(path)?)
+            .map_err(|err| ServerError::InvalidConfig(vec![err.to_string()]))?;
+        serde_json::from_str(&content)
+            .map_err(|err| ServerError::InvalidConfig(vec![err.to_string()]))
+    }
+
+    /// apply the environment variables which override the file:
+    /// - `LIGHTFLUS_COORDINATOR_URI`: comma-separated endpoints of the coordinators
+    /// - `LIGHTFLUS_API_TOKEN_FILE`: path of the token file
+    /// - `LIGHTFLUS_API_AUTHENTICATE_HEALTH`: whether the health endpoints are authenticated
+    pub fn with_env_overrides(mut self) -> Self {
+        if let Ok(uris) = std::env::var(COORDINATOR_URI_ENV) {
+            self.coordinator.endpoints = uris
+                .split(',')
+                .map(|uri| uri.trim())
+                .filter(|uri| !uri.is_empty())
+                .map(|uri| uri.to_string())
+                .collect();
+        }
+        if let Ok(path) = std::env::var(TOKEN_FILE_ENV) {
+            self.auth.token_file = Some(path);
+        }
+        if let Ok(value) = std::env::var(AUTHENTICATE_HEALTH_ENV) {
+            self.auth.authenticate_health = value.eq_ignore_ascii_case("true");
+        }
+        self
+    }
+
+    /// check all of the fields, the error lists every invalid one of them
+    pub fn validate(&self) -> Result<(), ServerError> {
+        let mut invalid_fields = vec![];
+        let mut invalid =
+            |field: &str, message: String| invalid_fields.push(format!("{field}: {message}"));
+
+        if self.host.trim().is_empty() || self.host.contains(char::is_whitespace) {
+            invalid("host", format!("invalid host {:?}", self.host));
+        }
+        if self.port == 0 {
+            invalid("port", "port should be positive".to_string());
+        }
+
+        if self.coordinator.endpoints.is_empty() && self.coordinator.default_region.is_none() {
+            invalid(
+                "coordinator.endpoints",
+                format!("at least one endpoint is required, or set {COORDINATOR_URI_ENV}"),
+            );
+        }
+        std::iter::once((
+            "coordinator.endpoints".to_string(),
+            &self.coordinator.endpoints,
+        ))
+        .chain(
+            self.coordinator
+                .regions
+                .iter()
+                .map(|(region, endpoints)| (format!("coordinator.regions.{region}"), endpoints)),
+        )
+        .for_each(|(field, endpoints)| {
+            endpoints.iter().enumerate().for_each(|(index, uri)| {
+                if let Err(err) = Endpoint::from_shared(with_scheme(uri)) {
+                    invalid(
+                        &format!("{field}[{index}]"),
+                        format!("invalid endpoint {uri:?}: {err}"),
+                    );
+                }
+            })
+        });
+        self.coordinator
+            .regions
+            .iter()
+            .for_each(|(region, endpoints)| {
+                let field = format!("coordinator.regions.{region}");
+                if region.trim().is_empty() || region == DEFAULT_COORDINATOR {
+                    invalid(&field, format!("invalid region name {region:?}"));
+                } else if endpoints.is_empty() {
+                    invalid(&field, "at least one endpoint is required".to_string());
+                }
+            });
+        let is_region = |region: &str| {
+            self.coordinator.regions.contains_key(region)
+                || (region == DEFAULT_COORDINATOR && !self.coordinator.endpoints.is_empty())
+        };
+        self.coordinator
+            .routes
+            .iter()
+            .enumerate()
+            .for_each(|(index, route)| {
+                if route.prefix.is_empty() {
+                    invalid(
+                        &format!("coordinator.routes[{index}].prefix"),
+                        "prefix should not be empty, set coordinator.default_region instead"
+                            .to_string(),
+                    );
+                }
+                if !is_region(&route.region) {
+                    invalid(
+                        &format!("coordinator.routes[{index}].region"),
+                        format!("unknown region {:?}", route.region),
+                    );
+                }
+            });
+        if let Some(region) = self.coordinator.default_region.as_ref() {
+            if !is_region(region) {
+                invalid(
+                    "coordinator.default_region",
+                    format!("unknown region {region:?}"),
+                );
+            }
+        }
+        if self.coordinator.connect_timeout == 0 {
+            invalid(
+                "coordinator.connect_timeout",
+                "timeout should be positive".to_string(),
+            );
+        }
+        if self.coordinator.rpc_timeout == 0 {
+            invalid(
+                "coordinator.rpc_timeout",
+                "timeout should be positive".to_string(),
+            );
+        }
+        if self.coordinator.request_timeout == 0 {
+            invalid(
+                "coordinator.request_timeout",
+                "timeout should be positive".to_string(),
+            );
+        }
+
+        self.cors
+            .allowed_origins
+            .iter()
+            .enumerate()
+            .for_each(|(index, origin)| {
+                if origin != "*"
+                    && !(origin.starts_with("http://") || origin.starts_with("https://"))
+                {
+                    invalid(
+                        &format!("cors.allowed_origins[{index}]"),
+                        format!(
+                            "origin {origin:?} should be `*` or start with http:// or https://"
+                        ),
+                    );
+                }
+            });
+        self.cors
+            .allowed_methods
+            .iter()
+            .enumerate()
+            .for_each(|(index, method)| {
+                if Method::from_bytes(method.as_bytes()).is_err() {
+                    invalid(
+                        &format!("cors.allowed_methods[{index}]"),
+                        format!("invalid method {method:?}"),
+                    );
+                }
+            });
+        self.cors
+            .allowed_headers
+            .iter()
+            .enumerate()
+            .for_each(|(index, header)| {
+                if actix_web::http::header::HeaderName::from_bytes(header.as_bytes()).is_err() {
+                    invalid(
+                        &format!("cors.allowed_headers[{index}]"),
+                        format!("invalid header {header:?}"),
+                    );
+                }
+            });
+        if self.cors.allow_credentials
+            && self.cors.allowed_origins.iter().any(|origin| origin == "*")
+        {
+            invalid(
+                "cors.allow_credentials",
+                "credentials can't be allowed for any origin `*`".to_string(),
+            );
+        }
+
+        if self.operations.capacity == 0 {
+            invalid(
+                "operations.capacity",
+                "capacity should be positive".to_string(),
+            );
+        }
+        if self.operations.ttl == 0 {
+            invalid("operations.ttl", "ttl should be positive".to_string());
+        }
+        if self.events.poll_interval == 0 {
+            invalid(
+                "events.poll_interval",
+                "interval should be positive".to_string(),
+            );
+        }
+        if self.events.heartbeat == 0 {
+            invalid(
+                "events.heartbeat",
+                "heartbeat should be positive".to_string(),
+            );
+        }
+        if self.events.buffer == 0 {
+            invalid("events.buffer", "buffer should be positive".to_string());
+        }
+        if let Some(file) = self.audit.file.as_ref() {
+            if file.path.trim().is_empty() {
+                invalid("audit.file.path", "path should not be empty".to_string());
+            }
+            if file.max_size == 0 {
+                invalid("audit.file.max_size", "size should be positive".to_string());
+            }
+        }
+        if let Some(kafka) = self.audit.kafka.as_ref() {
+            if self.audit.file.is_some() {
+                invalid(
+                    "audit.kafka",
+                    "only one of audit.file and audit.kafka can be set".to_string(),
+                );
+            }
+            if kafka.brokers.trim().is_empty() {
+                invalid(
+                    "audit.kafka.brokers",
+                    "brokers should not be empty".to_string(),
+                );
+            }
+            if kafka.topic.trim().is_empty() {
+                invalid("audit.kafka.topic", "topic should not be empty".to_string());
+            }
+        }
+        if self.audit.queue_size == 0 {
+            invalid(
+                "audit.queue_size",
+                "queue size should be positive".to_string(),
+            );
+        }
+        if self.limits.max_body_size == 0 {
+            invalid(
+                "limits.max_body_size",
+                "size should be positive".to_string(),
+            );
+        }
+        [
+            ("limits.rate", &self.limits.rate),
+            ("limits.reads", &self.limits.reads),
+            ("limits.writes", &self.limits.writes),
+        ]
+        .into_iter()
+        .for_each(|(field, limit)| match limit {
+            Some(limit) if limit.rate == 0 => invalid(
+                &format!("{field}.rate"),
+                "rate should be positive".to_string(),
+            ),
+            Some(limit) if limit.burst == 0 => invalid(
+                &format!("{field}.burst"),
+                "burst should be positive".to_string(),
+            ),
+            _ => {}
+        });
+        if self.batch.max_resources == 0 {
+            invalid(
+                "batch.max_resources",
+                "max number of resources should be positive".to_string(),
+            );
+        }
+        if self.batch.concurrency == 0 {
+            invalid(
+                "batch.concurrency",
+                "concurrency should be positive".to_string(),
+            );
+        }
+        if self.shutdown.grace_period == 0 {
+            invalid(
+                "shutdown.grace_period",
+                "grace period should be positive".to_string(),
+            );
+        }
+        if self.probes.interval == 0 {
+            invalid("probes.interval", "interval should be positive".to_string());
+        }
+        if self.probes.timeout == 0 {
+            invalid("probes.timeout", "timeout should be positive".to_string());
+        }
+        match self.metrics.port {
+            Some(0) => invalid("metrics.port", "port should be positive".to_string()),
+            Some(port) if port == self.port => invalid(
+                "metrics.port",
+                format!("port {port} is taken by the API server"),
+            ),
+            _ => {}
+        }
+
+        if let Some(path) = self.auth.token_file.as_ref() {
+            if !Path::new(path).is_file() {
+                invalid("auth.token_file", format!("file {path} doesn't exist"));
+            }
+        }
+
+        if let Some(tls) = self.tls.as_ref() {
+            if !Path::new(&tls.cert_file).is_file() {
+                invalid(
+                    "tls.cert_file",
+                    format!("file {} doesn't exist", tls.cert_file),
+                );
+            }
+            if !Path::new(&tls.key_file).is_file() {
+                invalid(
+                    "tls.key_file",
+                    format!("file {} doesn't exist", tls.key_file),
+                );
+            }
+        }
+
+        if invalid_fields.is_empty() {
+            Ok(())
+        } else {
+            Err(ServerError::InvalidConfig(invalid_fields))
+        }
+    }
+
+    /// the rustls config of the certificate and the key in the PEM files
+    pub(crate) fn load_tls(tls: &TlsConfig) -> Result<rustls::ServerConfig, ServerError> {
+        let invalid = |field: &str, message: String| {
+            ServerError::InvalidConfig(vec![format!("{field}: {message}")])
+        };
+        let certs = rustls_pemfile::certs(&mut std::io::BufReader::new(fs::File::open(
+            &tls.cert_file,
+        )?))
+        .map_err(|err| invalid("tls.cert_file", err.to_string()))?
+        .into_iter()
+        .map(rustls::Certificate)
+        .collect::<Vec<_>>();
+        if certs.is_empty() {
+            return Err(invalid(
+                "tls.cert_file",
+                "no certificate is found".to_string(),
+            ));
+        }
+
+        let key = rustls_pemfile::pkcs8_private_keys(&mut std::io::BufReader::new(fs::File::open(
+            &tls.key_file,
+        )?))
+        .map_err(|err| invalid("tls.key_file", err.to_string()))?
+        .into_iter()
+        .next()
+        .map(rustls::PrivateKey)
+        .ok_or_else(|| invalid("tls.key_file", "no PKCS#8 private key is found".to_string()))?;
+
+        rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|err| invalid("tls", err.to_string()))
+    }
+}
+
+/// endpoints may be given without scheme, e.g. `localhost:8791`
+pub(crate) fn with_scheme(uri: &str) -> String {
+    if uri.contains("://") {
+        uri.to_string()
+    } else {
+        format!("http://{uri}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::server::ServerError;
+
+    use super::ApiServerConfig;
+
+    #[test]
+    fn test_default_config() {
+        let config: ApiServerConfig = serde_json::from_value(serde_json::json!({
+            "coordinator": {
+                "endpoints": ["localhost:8791"]
+            }
+        }))
+        .unwrap();
+        assert_eq!(config.host, "0.0.0.0");
+        assert_eq!(config.port, super::API_SERVER_PORT);
+        assert_eq!(config.coordinator.rpc_timeout, 3);
+        assert_eq!(config.coordinator.retries, 0);
+        assert_eq!(config.coordinator.request_timeout, 10);
+        assert_eq!(config.coordinator.read_cache_ttl, 0);
+        assert!(config.tls.is_none());
+        assert!(config.metrics.port.is_none());
+        assert!(config.audit.file.is_none() && config.audit.kafka.is_none());
+        assert_eq!(config.audit.queue_size, 1024);
+        assert_eq!(config.limits.max_body_size, 4 * 1024 * 1024);
+        assert!(config.limits.get_read_limit().is_none());
+        assert_eq!(config.batch.max_resources, 100);
+        assert_eq!(config.batch.concurrency, 8);
+        assert_eq!(config.shutdown.grace_period, 30);
+        assert_eq!(config.probes.interval, 5);
+        assert_eq!(config.probes.timeout, 2);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_lists_all_invalid_fields() {
+        let config: ApiServerConfig = serde_json::from_value(serde_json::json!({
+            "host": "",
+            "port": 0,
+            "coordinator": {
+                "endpoints": ["localhost:8791", "http://bad host"],
+                "rpc_timeout": 0
+            },
+            "cors": {
+                "allowed_origins": ["*", "console.lightflus.io"],
+                "allowed_methods": ["GET", "BAD METHOD"],
+                "allow_credentials": true
+            },
+            "auth": {
+                "token_file": "/not/exists/tokens.json"
+            },
+            "events": {
+                "buffer": 0
+            },
+            "audit": {
+                "file": {"path": ""},
+                "queue_size": 0
+            },
+            "limits": {
+                "rate": {"rate": 0, "burst": 10},
+                "writes": {"rate": 1, "burst": 0}
+            },
+            "batch": {
+                "concurrency": 0
+            },
+            "shutdown": {
+                "grace_period": 0
+            },
+            "probes": {
+                "interval": 0
+            },
+            "metrics": {
+                "port": 0
+            },
+            "tls": {
+                "cert_file": "/not/exists/tls.crt",
+                "key_file": "/not/exists/tls.key"
+            }
+        }))
+        .unwrap();
+
+        match config.validate() {
+            Err(ServerError::InvalidConfig(fields)) => {
+                let fields = fields
+                    .iter()
+                    .map(|field| field.split(':').next().unwrap())
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    fields,
+                    vec![
+                        "host",
+                        "port",
+                        "coordinator.endpoints[1]",
+                        "coordinator.rpc_timeout",
+                        "cors.allowed_origins[1]",
+                        "cors.allowed_methods[1]",
+                        "cors.allow_credentials",
+                        "events.buffer",
+                        "audit.file.path",
+                        "audit.queue_size",
+                        "limits.rate.rate",
+                        "limits.writes.burst",
+                        "batch.concurrency",
+                        "shutdown.grace_period",
+                        "probes.interval",
+                        "metrics.port",
+                        "auth.token_file",
+                        "tls.cert_file",
+                        "tls.key_file",
+                    ]
+                );
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+
+        let config = ApiServerConfig::default();
+        assert!(matches!(
+            config.validate(),
+            Err(ServerError::InvalidConfig(fields)) if fields.len() == 1 && fields[0].starts_with("coordinator.endpoints")
+        ));
+    }
+
+    #[test]
+    fn test_validate_regions() {
+        let config: ApiServerConfig = serde_json::from_value(serde_json::json!({
+            "coordinator": {
+                "regions": {
+                    "eu": ["coordinator-eu:8791"],
+                    "us": ["coordinator-us:8791"]
+                },
+                "routes": [{"prefix": "eu-", "region": "eu"}],
+                "default_region": "us"
+            }
+        }))
+        .unwrap();
+        // no coordinator is named default if every namespace is routed to a region
+        assert!(config.validate().is_ok());
+
+        let config: ApiServerConfig = serde_json::from_value(serde_json::json!({
+            "coordinator": {
+                "regions": {
+                    "default": ["coordinator-0:8791"],
+                    "eu": ["http://bad host"],
+                    "us": []
+                },
+                "routes": [
+                    {"prefix": "", "region": "eu"},
+                    {"prefix": "ap-", "region": "ap"}
+                ],
+                "default_region": "ap"
+            }
+        }))
+        .unwrap();
+        match config.validate() {
+            Err(ServerError::InvalidConfig(fields)) => {
+                let fields = fields
+                    .iter()
+                    .map(|field| field.split(':').next().unwrap())
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    fields,
+                    vec![
+                        "coordinator.regions.eu[0]",
+                        "coordinator.regions.default",
+                        "coordinator.regions.us",
+                        "coordinator.routes[0].prefix",
+                        "coordinator.routes[1].region",
+                        "coordinator.default_region",
+                    ]
+                );
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_rate_limits_override() {
+        let config: ApiServerConfig = serde_json::from_value(serde_json::json!({
+            "coordinator": {
+                "endpoints": ["localhost:8791"]
+            },
+            "limits": {
+                "rate": {"rate": 50, "burst": 100},
+                "writes": {"rate": 5, "burst": 10}
+            }
+        }))
+        .unwrap();
+        assert!(config.validate().is_ok());
+        let limit =
+            |limit: Option<&super::RateLimitConfig>| limit.map(|limit| (limit.rate, limit.burst));
+        assert_eq!(limit(config.limits.get_read_limit()), Some((50, 100)));
+        assert_eq!(limit(config.limits.get_write_limit()), Some((5, 10)));
+    }
+}
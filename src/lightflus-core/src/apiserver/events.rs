@@ -0,0 +1,449 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    sync::Mutex,
+};
+
+use actix_web::web;
+use common::snapshot::CHECKPOINT_UPLOADED_METRIC;
+use futures_util::Stream;
+use proto::{
+    common::{DataflowStates, ResourceId},
+    coordinator::{GetDataflowRequest, GetDataflowStatusRequest},
+};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    watch,
+};
+
+use crate::errors::apiserver::{ApiError, ApiErrorCode};
+
+use super::{
+    auth::Caller, config::EventsConfig, handler::coordinator::CoordinatorGateway,
+    types::ResourceDetail,
+};
+
+/// the comment sent when no event is sent for a heartbeat, which keeps the connection alive
+const HEARTBEAT_COMMENT: &[u8] = b": heartbeat\n\n";
+
+/// A change of the status of a dataflow, which is sent as a server-sent event whose name is the `type` of it.
+/// The `deleted` event or the `shutdown` event is the last one of the stream
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum StatusEvent {
+    /// the status of the dataflow is changed
+    Status { from: String, to: String },
+    /// the status of an operator is changed
+    Operator {
+        #[serde(rename = "operatorId")]
+        operator_id: u32,
+        from: String,
+        to: String,
+    },
+    /// an operator is moved to another TaskManager or its executor is created again
+    Failover {
+        #[serde(rename = "operatorId")]
+        operator_id: u32,
+        from: String,
+        to: String,
+        #[serde(rename = "restartCount")]
+        restart_count: u32,
+    },
+    /// checkpoints are completed since the last event, `total` is the number of the checkpoints uploaded by the operators
+    Checkpoint { completed: u64, total: u64 },
+    /// the dataflow is deleted
+    Deleted,
+    /// the API server is shutting down, the watcher should watch the dataflow again through another one
+    Shutdown,
+}
+
+impl StatusEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Status { .. } => "status",
+            Self::Operator { .. } => "operator",
+            Self::Failover { .. } => "failover",
+            Self::Checkpoint { .. } => "checkpoint",
+            Self::Deleted => "deleted",
+            Self::Shutdown => "shutdown",
+        }
+    }
+
+    /// the event in the format of the server-sent events
+    pub fn to_sse(&self) -> web::Bytes {
+        web::Bytes::from(format!(
+            "event: {}\ndata: {}\n\n",
+            self.name(),
+            serde_json::to_string(self).unwrap_or_default()
+        ))
+    }
+}
+
+/// the status of a dataflow polled from the coordinator, the events are the differences between two of them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct StatusSnapshot {
+    pub detail: ResourceDetail,
+    pub checkpoints: u64,
+}
+
+impl StatusSnapshot {
+    pub fn new(detail: ResourceDetail, states: &DataflowStates) -> Self {
+        let checkpoints = states
+            .subdataflow_infos
+            .iter()
+            .flat_map(|info| info.executors_info.values())
+            .filter_map(|executor| executor.metrics.get(CHECKPOINT_UPLOADED_METRIC))
+            .sum();
+        Self {
+            detail,
+            checkpoints,
+        }
+    }
+
+    /// the events which change the previous snapshot into this one
+    pub fn diff(&self, prev: &Self) -> Vec<StatusEvent> {
+        let mut events = vec![];
+        if self.detail.summary.status != prev.detail.summary.status {
+            events.push(StatusEvent::Status {
+                from: prev.detail.summary.status.clone(),
+                to: self.detail.summary.status.clone(),
+            });
+        }
+
+        let prev_operators = prev
+            .detail
+            .operators
+            .iter()
+            .map(|operator| (operator.id, operator))
+            .collect::<BTreeMap<_, _>>();
+        for operator in &self.detail.operators {
+            let prev = match prev_operators.get(&operator.id) {
+                Some(prev) => prev,
+                None => continue,
+            };
+            // an operator is placed for the first time once its host is known, which is not a failover
+            let moved = prev.host.as_ref().filter(|host| !host.is_empty()).is_some()
+                && operator.host != prev.host;
+            if moved
+                || operator.restart_count.unwrap_or_default()
+                    > prev.restart_count.unwrap_or_default()
+            {
+                events.push(StatusEvent::Failover {
+                    operator_id: operator.id,
+                    from: prev.host.clone().unwrap_or_default(),
+                    to: operator.host.clone().unwrap_or_default(),
+                    restart_count: operator.restart_count.unwrap_or_default(),
+                });
+            }
+            if operator.status != prev.status {
+                events.push(StatusEvent::Operator {
+                    operator_id: operator.id,
+                    from: prev.status.clone().unwrap_or_default(),
+                    to: operator.status.clone().unwrap_or_default(),
+                });
+            }
+        }
+
+        if self.checkpoints > prev.checkpoints {
+            events.push(StatusEvent::Checkpoint {
+                completed: self.checkpoints - prev.checkpoints,
+                total: self.checkpoints,
+            });
+        }
+        events
+    }
+}
+
+/// [`EventHub`] polls the coordinator for the status of the dataflows which are watched, and fans the changes out to the watchers.
+/// A dataflow is polled once per interval however many watchers it has, and it's not polled once all of its watchers are gone.
+///
+/// Each watcher has a bounded buffer of the events. A slow watcher skips the events it lags behind instead of buffering them.
+/// Once the hub is closed, every stream is ended by a `shutdown` event
+pub(crate) struct EventHub {
+    config: EventsConfig,
+    feeds: Mutex<HashMap<ResourceId, broadcast::Sender<StatusEvent>>>,
+    closed: watch::Sender<bool>,
+}
+
+impl EventHub {
+    pub(crate) fn new(config: &EventsConfig) -> Self {
+        Self {
+            config: config.clone(),
+            feeds: Default::default(),
+            closed: watch::channel(false).0,
+        }
+    }
+
+    /// end the streams of all of the watchers, including the ones which start watching later
+    pub(crate) fn close(&self) {
+        self.closed.send_replace(true);
+    }
+
+    /// watch the status of the dataflow. The dataflow is polled by the identity of the caller who starts the feed of it
+    pub(crate) fn subscribe(
+        hub: web::Data<Self>,
+        coordinator: web::Data<CoordinatorGateway>,
+        caller: Caller,
+        job_id: ResourceId,
+    ) -> broadcast::Receiver<StatusEvent> {
+        let mut feeds = hub.feeds.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(sender) = feeds.get(&job_id) {
+            return sender.subscribe();
+        }
+        let (sender, receiver) = broadcast::channel(hub.config.buffer);
+        feeds.insert(job_id.clone(), sender.clone());
+        drop(feeds);
+        actix_web::rt::spawn(Self::feed(hub, coordinator, caller, job_id, sender));
+        receiver
+    }
+
+    /// poll the dataflow until it's deleted or nobody watches it
+    async fn feed(
+        hub: web::Data<Self>,
+        coordinator: web::Data<CoordinatorGateway>,
+        caller: Caller,
+        job_id: ResourceId,
+        sender: broadcast::Sender<StatusEvent>,
+    ) {
+        let mut last = None::<StatusSnapshot>;
+        loop {
+            match poll(&coordinator, &caller, &job_id).await {
+                Ok(Some(snapshot)) => {
+                    if let Some(last) = last.as_ref() {
+                        snapshot.diff(last).into_iter().for_each(|event| {
+                            let _ = sender.send(event);
+                        });
+                    }
+                    last = Some(snapshot);
+                }
+                Ok(None) => {
+                    hub.remove(&job_id);
+                    let _ = sender.send(StatusEvent::Deleted);
+                    return;
+                }
+                Err(err) => tracing::warn!("poll status of dataflow {:?} failed: {}", job_id, err),
+            }
+
+            tokio::time::sleep(hub.config.get_poll_interval()).await;
+            let mut feeds = hub.feeds.lock().unwrap_or_else(|err| err.into_inner());
+            if sender.receiver_count() == 0 {
+                feeds.remove(&job_id);
+                return;
+            }
+        }
+    }
+
+    fn remove(&self, job_id: &ResourceId) {
+        self.feeds
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(job_id);
+    }
+
+    /// the server-sent events of the watcher. A heartbeat comment is sent if no event is sent for a while
+    pub(crate) fn to_sse_stream(
+        &self,
+        receiver: broadcast::Receiver<StatusEvent>,
+    ) -> impl Stream<Item = Result<web::Bytes, Infallible>> {
+        let heartbeat = self.config.get_heartbeat();
+        let closed = self.closed.subscribe();
+        futures_util::stream::unfold(Some((receiver, closed)), move |state| async move {
+            let (mut receiver, mut closed) = state?;
+            loop {
+                if *closed.borrow() {
+                    return Some((Ok(StatusEvent::Shutdown.to_sse()), None));
+                }
+                let recv = tokio::select! {
+                    recv = tokio::time::timeout(heartbeat, receiver.recv()) => recv,
+                    changed = closed.changed() => match changed {
+                        Ok(_) => continue,
+                        // the hub is dropped along with the server
+                        Err(_) => return None,
+                    },
+                };
+                match recv {
+                    Err(_) => {
+                        return Some((
+                            Ok(web::Bytes::from_static(HEARTBEAT_COMMENT)),
+                            Some((receiver, closed)),
+                        ))
+                    }
+                    Ok(Ok(event)) => {
+                        let state =
+                            Some((receiver, closed)).filter(|_| event != StatusEvent::Deleted);
+                        return Some((Ok(event.to_sse()), state));
+                    }
+                    Ok(Err(RecvError::Lagged(skipped))) => {
+                        tracing::debug!("{} status events are skipped by a slow watcher", skipped)
+                    }
+                    Ok(Err(RecvError::Closed)) => return None,
+                }
+            }
+        })
+    }
+}
+
+/// the snapshot of the dataflow, it's none if the dataflow is not found
+async fn poll(
+    coordinator: &CoordinatorGateway,
+    caller: &Caller,
+    job_id: &ResourceId,
+) -> Result<Option<StatusSnapshot>, ApiError> {
+    let not_found = |err: &ApiError| err.code == ApiErrorCode::NotFound;
+    let status = coordinator
+        .read(|mut client| {
+            let request = caller.new_request(GetDataflowStatusRequest {
+                job_id: Some(job_id.clone()),
+                with_operators: true,
+            });
+            async move { client.get_dataflow_status(request).await }
+        })
+        .await
+        .map_err(ApiError::from);
+    let status = match status {
+        Err(err) if not_found(&err) => return Ok(None),
+        status => status?,
+    };
+    let states = coordinator
+        .read(|mut client| {
+            let request = caller.new_request(GetDataflowRequest {
+                job_id: Some(job_id.clone()),
+                effective: false,
+                include_metrics: false,
+            });
+            async move { client.get_dataflow(request).await }
+        })
+        .await
+        .map_err(ApiError::from);
+    match states {
+        Err(err) if not_found(&err) => Ok(None),
+        states => Ok(Some(StatusSnapshot::new(
+            ResourceDetail::new(&status, None),
+            &states?,
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use proto::common::{DataflowStates, ExecutorInfo, SubdataflowInfo};
+
+    use crate::apiserver::{
+        config::EventsConfig,
+        types::{OperatorDetail, ResourceDetail, ResourceSummary},
+    };
+
+    use super::{EventHub, StatusEvent, StatusSnapshot};
+
+    fn new_snapshot(
+        status: &str,
+        operators: Vec<(&str, &str, u32)>,
+        checkpoints: u64,
+    ) -> StatusSnapshot {
+        StatusSnapshot::new(
+            ResourceDetail {
+                summary: ResourceSummary {
+                    status: status.to_string(),
+                    ..Default::default()
+                },
+                operators: operators
+                    .into_iter()
+                    .enumerate()
+                    .map(|(id, (status, host, restart_count))| OperatorDetail {
+                        id: id as u32,
+                        status: Some(status.to_string()),
+                        host: Some(host.to_string()),
+                        restart_count: Some(restart_count),
+                        ..Default::default()
+                    })
+                    .collect(),
+            },
+            &DataflowStates {
+                subdataflow_infos: vec![SubdataflowInfo {
+                    executors_info: [(
+                        0,
+                        ExecutorInfo {
+                            metrics: [(
+                                common::snapshot::CHECKPOINT_UPLOADED_METRIC.to_string(),
+                                checkpoints,
+                            )]
+                            .into(),
+                            ..Default::default()
+                        },
+                    )]
+                    .into(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        let prev = new_snapshot(
+            "pending",
+            vec![("pending", "", 0), ("running", "tm-0:8792", 0)],
+            0,
+        );
+        let next = new_snapshot(
+            "running",
+            vec![("running", "tm-0:8792", 0), ("running", "tm-1:8792", 1)],
+            2,
+        );
+        assert_eq!(
+            next.diff(&prev),
+            vec![
+                StatusEvent::Status {
+                    from: "pending".to_string(),
+                    to: "running".to_string()
+                },
+                StatusEvent::Operator {
+                    operator_id: 0,
+                    from: "pending".to_string(),
+                    to: "running".to_string()
+                },
+                StatusEvent::Failover {
+                    operator_id: 1,
+                    from: "tm-0:8792".to_string(),
+                    to: "tm-1:8792".to_string(),
+                    restart_count: 1
+                },
+                StatusEvent::Checkpoint {
+                    completed: 2,
+                    total: 2
+                },
+            ]
+        );
+        assert!(next.diff(&next).is_empty());
+
+        assert_eq!(
+            String::from_utf8(StatusEvent::Deleted.to_sse().to_vec()).unwrap(),
+            "event: deleted\ndata: {\"type\":\"deleted\"}\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_close_ends_streams() {
+        let hub = EventHub::new(&EventsConfig::default());
+        let (sender, receiver) = tokio::sync::broadcast::channel(4);
+        let stream = hub.to_sse_stream(receiver);
+        sender
+            .send(StatusEvent::Checkpoint {
+                completed: 1,
+                total: 1,
+            })
+            .unwrap();
+        hub.close();
+        // the pending events are skipped once the hub is closed
+        let events = stream.collect::<Vec<_>>().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].as_ref().unwrap(), &StatusEvent::Shutdown.to_sse());
+
+        // a stream which starts after the hub is closed ends at once
+        let (_sender, receiver) = tokio::sync::broadcast::channel(4);
+        let events = hub.to_sse_stream(receiver).collect::<Vec<_>>().await;
+        assert_eq!(events.len(), 1);
+    }
+}
@@ -0,0 +1,356 @@
+use proto::{
+    common::{operator_info::Details, Dataflow},
+    coordinator::DataflowRuntimeStatus,
+};
+
+use super::types::{ResourceDetail, ResourceSummary};
+
+/// the format of `GET /resources/{namespace}/{name}/graph`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum GraphFormat {
+    /// the node-link structure of [`ResourceGraph`]
+    #[default]
+    Json,
+    /// a graphviz document, e.g. `curl ... | dot -Tsvg > graph.svg`
+    Dot,
+}
+
+/// query of `GET /resources/{namespace}/{name}/graph`
+#[derive(serde::Deserialize, Default)]
+pub(crate) struct GetResourceGraphQuery {
+    #[serde(default)]
+    pub format: GraphFormat,
+}
+
+/// how the events of an edge are distributed to the instances of its target
+#[derive(
+    serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Partitioning {
+    /// events are sent to the target as they are
+    Forward,
+    /// events are keyed by the source, so they're partitioned by their keys
+    Hash,
+    /// events update the broadcast state of all instances of the target
+    Broadcast,
+}
+
+impl Partitioning {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Forward => "forward",
+            Self::Hash => "hash",
+            Self::Broadcast => "broadcast",
+        }
+    }
+}
+
+/// an operator of [`ResourceGraph`]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct GraphNode {
+    pub id: u32,
+    /// the type of the operator, e.g. `reducer`
+    pub kind: String,
+    /// the number of instances of the operator. An operator runs in one instance on its TaskManager for now
+    pub parallelism: u32,
+    /// `host:port` of the TaskManager where the operator is placed. It's absent if the operator is not placed
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub host: Option<String>,
+    /// the same as the status of the operator in `GET /resources/{namespace}/{name}`
+    pub status: String,
+    /// the fill color of the status in the DOT document, see [`get_status_color`]
+    pub color: String,
+}
+
+/// an edge of [`ResourceGraph`] from an upstream to its downstream
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct GraphLink {
+    pub source: u32,
+    pub target: u32,
+    pub partitioning: Partitioning,
+}
+
+/// body of `GET /resources/{namespace}/{name}/graph` in JSON. The operators and the edges are in the node-link structure
+/// which graph libraries like d3 and networkx read
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct ResourceGraph {
+    pub name: String,
+    pub namespace: String,
+    /// the status of the dataflow
+    pub status: String,
+    /// operators ordered by their ids
+    pub nodes: Vec<GraphNode>,
+    /// edges ordered by their sources and targets
+    pub links: Vec<GraphLink>,
+}
+
+/// the graphviz color of the status of an operator: green if it's running, red if it's failed, white if it's not reported yet
+pub(crate) fn get_status_color(status: &str) -> &'static str {
+    match status {
+        "initialized" => "lightblue",
+        "running" => "palegreen",
+        "terminating" | "drained" => "khaki",
+        "terminated" => "lightgray",
+        "failed" => "salmon",
+        _ => "white",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// a quoted DOT string, so any text is a valid ID
+fn quote(text: &str) -> String {
+    format!("\"{}\"", escape(text))
+}
+
+impl ResourceGraph {
+    /// the operators of the spec annotated with the runtime status of them. Operators without status are pending
+    pub fn new(status: &DataflowRuntimeStatus, dataflow: &Dataflow) -> Self {
+        let ResourceDetail { summary, operators } = ResourceDetail::new(status, Some(dataflow));
+        let ResourceSummary {
+            name,
+            namespace,
+            status,
+            ..
+        } = summary;
+        let nodes = operators
+            .into_iter()
+            .map(|operator| {
+                let status = operator.status.unwrap_or_else(|| "pending".to_string());
+                let host = operator.host.filter(|host| !host.is_empty()).or_else(|| {
+                    dataflow
+                        .nodes
+                        .get(&operator.id)
+                        .and_then(|info| info.host_addr.as_ref())
+                        .map(|addr| format!("{}:{}", addr.host, addr.port))
+                });
+                GraphNode {
+                    id: operator.id,
+                    kind: operator.kind.unwrap_or_else(|| "unknown".to_string()),
+                    parallelism: 1,
+                    host,
+                    color: get_status_color(&status).to_string(),
+                    status,
+                }
+            })
+            .collect();
+
+        let mut links = dataflow
+            .meta
+            .iter()
+            .flat_map(|meta| {
+                let broadcast = meta.get_broadcast_neighbors();
+                let keyed = matches!(
+                    dataflow
+                        .nodes
+                        .get(&meta.center)
+                        .and_then(|info| info.details.as_ref()),
+                    Some(Details::KeyBy(_))
+                );
+                meta.neighbors.iter().map(move |neighbor| GraphLink {
+                    source: meta.center,
+                    target: *neighbor,
+                    partitioning: if broadcast.contains(neighbor) {
+                        Partitioning::Broadcast
+                    } else if keyed {
+                        Partitioning::Hash
+                    } else {
+                        Partitioning::Forward
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        links.sort_by_key(|link| (link.source, link.target));
+        links.dedup_by_key(|link| (link.source, link.target));
+
+        Self {
+            name,
+            namespace,
+            status,
+            nodes,
+            links,
+        }
+    }
+
+    /// the graphviz document of the graph. Operators are boxes filled with the colors of their status,
+    /// and broadcast edges are dashed
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "digraph {} {{",
+                quote(&format!("{}/{}", self.namespace, self.name))
+            ),
+            "  rankdir=LR;".to_string(),
+            format!(
+                "  label={};",
+                quote(&format!(
+                    "{}/{} ({})",
+                    self.namespace, self.name, self.status
+                ))
+            ),
+            "  node [shape=box, style=\"rounded,filled\"];".to_string(),
+        ];
+        self.nodes.iter().for_each(|node| {
+            let mut label = vec![
+                format!("{}: {}", node.id, node.kind),
+                format!("parallelism: {}", node.parallelism),
+            ];
+            if let Some(host) = node.host.as_ref() {
+                label.push(format!("host: {host}"));
+            }
+            label.push(format!("status: {}", node.status));
+            let label = label
+                .iter()
+                .map(|line| escape(line))
+                .collect::<Vec<_>>()
+                .join("\\n");
+            lines.push(format!(
+                "  {} [label=\"{}\", fillcolor={}];",
+                node.id,
+                label,
+                quote(&node.color)
+            ));
+        });
+        self.links.iter().for_each(|link| {
+            let style = match link.partitioning {
+                Partitioning::Broadcast => ", style=dashed",
+                _ => "",
+            };
+            lines.push(format!(
+                "  {} -> {} [label={}{}];",
+                link.source,
+                link.target,
+                quote(link.partitioning.as_str()),
+                style
+            ));
+        });
+        lines.push("}".to_string());
+        lines.join("\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proto::{
+        common::{
+            operator_info::Details, Dataflow, DataflowStatus, ExecutorStatus, HostAddr, KeyBy,
+            OperatorInfo,
+        },
+        coordinator::{DataflowRuntimeStatus, DataflowSummary, OperatorRuntimeStatus},
+    };
+
+    use super::{GraphFormat, ResourceGraph};
+
+    /// rules are broadcast to the key_by, which feeds the reducer. The sink is not reported yet
+    fn sample() -> (DataflowRuntimeStatus, Dataflow) {
+        let operator = |operator_id: u32, details: Details| {
+            OperatorInfo::new(operator_id, details).with_host_addr("tm-1", 8792)
+        };
+        let dataflow = Dataflow::builder()
+            .job_id("default", "orders")
+            .operator(operator(0, Details::Source(Default::default())))
+            .operator(operator(1, Details::KeyBy(KeyBy::default())))
+            .operator(operator(2, Details::Reducer(Default::default())))
+            .operator(operator(3, Details::Sink(Default::default())))
+            .operator(operator(4, Details::Source(Default::default())))
+            .edge(0, 1)
+            .edge(1, 2)
+            .edge(2, 3)
+            .broadcast_edge(4, 1)
+            .build_unvalidated();
+        let runtime = |operator_id: u32, status: ExecutorStatus| OperatorRuntimeStatus {
+            operator_id,
+            host_addr: Some(HostAddr {
+                host: "tm-0".to_string(),
+                port: 8792,
+            }),
+            status: Some(status as i32),
+            last_heartbeat_at: 1,
+            restart_count: 0,
+        };
+        let status = DataflowRuntimeStatus {
+            summary: Some(DataflowSummary {
+                job_id: dataflow.job_id.clone(),
+                status: DataflowStatus::Running as i32,
+                operator_count: 5,
+                ..Default::default()
+            }),
+            operators: vec![
+                runtime(0, ExecutorStatus::Running),
+                runtime(1, ExecutorStatus::Running),
+                runtime(2, ExecutorStatus::Failed),
+                runtime(4, ExecutorStatus::Running),
+            ],
+        };
+        (status, dataflow)
+    }
+
+    #[test]
+    fn test_graph_json() {
+        let (status, dataflow) = sample();
+        assert_eq!(
+            serde_json::to_value(ResourceGraph::new(&status, &dataflow)).unwrap(),
+            serde_json::json!({
+                "name": "orders",
+                "namespace": "default",
+                "status": "running",
+                "nodes": [
+                    {"id": 0, "kind": "source", "parallelism": 1, "host": "tm-0:8792", "status": "running", "color": "palegreen"},
+                    {"id": 1, "kind": "key_by", "parallelism": 1, "host": "tm-0:8792", "status": "running", "color": "palegreen"},
+                    {"id": 2, "kind": "reducer", "parallelism": 1, "host": "tm-0:8792", "status": "failed", "color": "salmon"},
+                    {"id": 3, "kind": "sink", "parallelism": 1, "host": "tm-1:8792", "status": "pending", "color": "white"},
+                    {"id": 4, "kind": "source", "parallelism": 1, "host": "tm-0:8792", "status": "running", "color": "palegreen"}
+                ],
+                "links": [
+                    {"source": 0, "target": 1, "partitioning": "forward"},
+                    {"source": 1, "target": 2, "partitioning": "hash"},
+                    {"source": 2, "target": 3, "partitioning": "forward"},
+                    {"source": 4, "target": 1, "partitioning": "broadcast"}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_graph_dot() {
+        let (status, dataflow) = sample();
+        assert_eq!(
+            ResourceGraph::new(&status, &dataflow).to_dot(),
+            r#"digraph "default/orders" {
+  rankdir=LR;
+  label="default/orders (running)";
+  node [shape=box, style="rounded,filled"];
+  0 [label="0: source\nparallelism: 1\nhost: tm-0:8792\nstatus: running", fillcolor="palegreen"];
+  1 [label="1: key_by\nparallelism: 1\nhost: tm-0:8792\nstatus: running", fillcolor="palegreen"];
+  2 [label="2: reducer\nparallelism: 1\nhost: tm-0:8792\nstatus: failed", fillcolor="salmon"];
+  3 [label="3: sink\nparallelism: 1\nhost: tm-1:8792\nstatus: pending", fillcolor="white"];
+  4 [label="4: source\nparallelism: 1\nhost: tm-0:8792\nstatus: running", fillcolor="palegreen"];
+  0 -> 1 [label="forward"];
+  1 -> 2 [label="hash"];
+  2 -> 3 [label="forward"];
+  4 -> 1 [label="broadcast", style=dashed];
+}
+"#
+        );
+
+        // quotes in the names are escaped
+        let mut graph = ResourceGraph::new(&status, &dataflow);
+        graph.name = "say \"hi\"".to_string();
+        assert!(graph
+            .to_dot()
+            .starts_with("digraph \"default/say \\\"hi\\\"\" {\n"));
+    }
+
+    #[test]
+    fn test_graph_format() {
+        assert_eq!(
+            serde_json::from_value::<GraphFormat>(serde_json::json!("dot")).unwrap(),
+            GraphFormat::Dot
+        );
+        assert_eq!(GraphFormat::default(), GraphFormat::Json);
+    }
+}
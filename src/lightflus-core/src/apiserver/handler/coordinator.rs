@@ -0,0 +1,718 @@
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use common::{backoff::BackoffBuilder, utils::times};
+use proto::coordinator::{coordinator_api_client::CoordinatorApiClient, GetClusterTopologyRequest};
+use tonic::transport::{Channel, Endpoint};
+
+use crate::apiserver::{
+    auth::Caller,
+    config::{with_scheme, CoordinatorConfig},
+    middleware::RequestContext,
+};
+
+/// the name of the coordinator of [`CoordinatorConfig::endpoints`] among the coordinators of the regions
+pub(crate) const DEFAULT_COORDINATOR: &str = "default";
+
+/// [`ReadKey`] identifies a read in the cache by its method, its caller and its request,
+/// so a caller is never served by the response of another one
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct ReadKey {
+    method: &'static str,
+    identity: String,
+    request: Vec<u8>,
+}
+
+impl ReadKey {
+    pub(crate) fn new<M: prost::Message>(
+        method: &'static str,
+        caller: &Caller,
+        request: &M,
+    ) -> Self {
+        Self {
+            method,
+            identity: caller.get_identity().to_string(),
+            request: request.encode_to_vec(),
+        }
+    }
+}
+
+/// the response of a read and when it's cached
+struct CachedRead {
+    value: Arc<dyn Any + Send + Sync>,
+    cached_at: Instant,
+}
+
+/// [`CoordinatorGateway`] sends the requests of the handlers to one of the configured coordinators,
+/// see [`CoordinatorConfig`]. The endpoints can be overridden by `LIGHTFLUS_COORDINATOR_URI`,
+/// which is a comma-separated list of endpoints like `coordinator-0:8791,coordinator-1:8791`.
+///
+/// A request is sent to the coordinator which served the last request first. If it's [`tonic::Code::Unavailable`],
+/// the request is sent to the next coordinator in the list until one of them serves it, and that one is preferred by the following requests.
+/// Other errors are returned to the handlers directly, since the other coordinators would reject the request in the same way.
+///
+/// A write is sent by [`CoordinatorGateway::call`], which fails once all of the endpoints are unavailable.
+/// A read is sent by [`CoordinatorGateway::read`], which tries the endpoints for `retries` more rounds with the backoff between them,
+/// and the response of the reads sent by [`CoordinatorGateway::read_cached`] is served for `read_cache_ttl` while the coordinators are unavailable.
+/// Each request fails with [`tonic::Code::DeadlineExceeded`] if it's not served in `request_timeout`.
+///
+/// The time spent on the coordinators, including the failovers, is added to the latency of the request in hand, see [`RequestContext`].
+/// Whether the latest request is served is kept as the health of the coordinator, see [`CoordinatorHealth`]
+pub(crate) struct CoordinatorGateway {
+    /// the region of the coordinator, which is named by the errors of the outages. It's only set if the regions are configured
+    region: Option<String>,
+    clients: Vec<(String, CoordinatorApiClient<Channel>)>,
+    /// index of the coordinator which served the last request
+    preferred: AtomicUsize,
+    retries: u32,
+    retry_backoff: BackoffBuilder,
+    request_timeout: Duration,
+    read_cache_ttl: Duration,
+    read_cache: Mutex<HashMap<ReadKey, CachedRead>>,
+    health: Mutex<UpstreamHealth>,
+}
+
+/// the outcome of the latest request to a coordinator
+#[derive(Clone, Debug, Default)]
+struct UpstreamHealth {
+    available: Option<bool>,
+    error: Option<String>,
+    checked_at: Option<i64>,
+}
+
+/// the health of a coordinator in the body of `GET /coordinators`
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct CoordinatorHealth {
+    pub name: String,
+    pub endpoints: Vec<String>,
+    /// whether the latest request is served, rather than failing as the coordinator is unavailable. It's absent before any request
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub available: Option<bool>,
+    /// why the latest request fails if the coordinator is unavailable
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<String>,
+    /// milliseconds since the unix epoch
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub checked_at: Option<i64>,
+}
+
+impl CoordinatorGateway {
+    /// create the gateway of a comma-separated list of endpoints with the default timeouts. Invalid endpoints are ignored.
+    /// The server creates its gateways by [`CoordinatorGateway::from_config`], so it's only for tests
+    #[cfg(test)]
+    pub(crate) fn new(uris: &str) -> Self {
+        Self::from_config(&CoordinatorConfig {
+            endpoints: uris
+                .split(',')
+                .map(|uri| uri.trim())
+                .filter(|uri| !uri.is_empty())
+                .map(|uri| uri.to_string())
+                .collect(),
+            ..Default::default()
+        })
+    }
+
+    /// create the gateway of the configured endpoints. Connections are established lazily
+    pub(crate) fn from_config(config: &CoordinatorConfig) -> Self {
+        Self::with_endpoints(config, &config.endpoints)
+    }
+
+    /// create the gateway of the endpoints with the timeouts, the retries and the cache TTL of the config
+    fn with_endpoints(config: &CoordinatorConfig, endpoints: &[String]) -> Self {
+        let clients = endpoints
+            .iter()
+            .map(|uri| with_scheme(uri))
+            .filter_map(|uri| match Endpoint::from_shared(uri.clone()) {
+                Ok(endpoint) => Some((
+                    uri,
+                    CoordinatorApiClient::new(
+                        endpoint
+                            .connect_timeout(config.get_connect_timeout())
+                            .timeout(config.get_rpc_timeout())
+                            .connect_lazy(),
+                    ),
+                )),
+                Err(err) => {
+                    tracing::error!("invalid coordinator endpoint {}: {}", uri, err);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            region: None,
+            clients,
+            preferred: Default::default(),
+            retries: config.retries,
+            retry_backoff: config.retry_backoff.clone(),
+            request_timeout: config.get_request_timeout(),
+            read_cache_ttl: config.get_read_cache_ttl(),
+            read_cache: Default::default(),
+            health: Default::default(),
+        }
+    }
+
+    fn with_region(mut self, region: &str) -> Self {
+        self.region = Some(region.to_string());
+        self
+    }
+
+    pub(crate) fn get_endpoints(&self) -> Vec<&str> {
+        self.clients.iter().map(|(uri, _)| uri.as_str()).collect()
+    }
+
+    /// the region of the coordinator if the regions are configured
+    pub(crate) fn get_region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// the health of the coordinator by the outcome of its latest request
+    pub(crate) fn get_health(&self, name: &str) -> CoordinatorHealth {
+        let health = self.lock_health().clone();
+        CoordinatorHealth {
+            name: name.to_string(),
+            endpoints: self
+                .get_endpoints()
+                .into_iter()
+                .map(|uri| uri.to_string())
+                .collect(),
+            available: health.available,
+            error: health.error,
+            checked_at: health.checked_at,
+        }
+    }
+
+    fn lock_health(&self) -> std::sync::MutexGuard<'_, UpstreamHealth> {
+        self.health.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// send a request by `call`, failing over to the next coordinator while the current one is unavailable.
+    /// `call` may be invoked once per coordinator, so it should build a new request every time.
+    /// It's never retried, so it's the way the writes are sent
+    pub(crate) async fn call<T, F, Fut>(&self, call: F) -> Result<T, tonic::Status>
+    where
+        F: Fn(CoordinatorApiClient<Channel>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<T>, tonic::Status>>,
+    {
+        self.send(self.call_with_failover(&call)).await
+    }
+
+    /// send an idempotent read by `call` like [`CoordinatorGateway::call`], but it's retried while all of the coordinators are unavailable
+    pub(crate) async fn read<T, F, Fut>(&self, call: F) -> Result<T, tonic::Status>
+    where
+        F: Fn(CoordinatorApiClient<Channel>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<T>, tonic::Status>>,
+    {
+        self.send(self.read_with_retries(&call)).await
+    }
+
+    /// send a read like [`CoordinatorGateway::read`] and cache its response by the key. While the coordinators are unavailable,
+    /// the cached response is served if it's not expired, and the request in hand is marked stale, see [`RequestContext`]
+    pub(crate) async fn read_cached<T, F, Fut>(
+        &self,
+        key: ReadKey,
+        call: F,
+    ) -> Result<T, tonic::Status>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Fn(CoordinatorApiClient<Channel>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<T>, tonic::Status>>,
+    {
+        let result = self.read(call).await;
+        if self.read_cache_ttl.is_zero() {
+            return result;
+        }
+
+        let now = Instant::now();
+        let mut cache = self
+            .read_cache
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        cache.retain(|_, cached| now.duration_since(cached.cached_at) < self.read_cache_ttl);
+        match result {
+            Ok(value) => {
+                cache.insert(
+                    key,
+                    CachedRead {
+                        value: Arc::new(value.clone()),
+                        cached_at: now,
+                    },
+                );
+                Ok(value)
+            }
+            Err(err) if is_outage(&err) => {
+                match cache
+                    .get(&key)
+                    .and_then(|cached| cached.value.downcast_ref::<T>())
+                {
+                    Some(value) => {
+                        tracing::warn!("serve {} from the cache: {}", key.method, err);
+                        if let Some(context) = RequestContext::current() {
+                            context.mark_stale();
+                        }
+                        Ok(value.clone())
+                    }
+                    None => Err(err),
+                }
+            }
+            Err(err) => {
+                // the coordinator answers the read in another way now
+                cache.remove(&key);
+                Err(err)
+            }
+        }
+    }
+
+    /// probe whether any endpoint of the coordinator responds in the timeout, see [`crate::apiserver::probe::Prober`].
+    /// A rejected request still proves the coordinator is reachable. The outcome is not kept as the health of the coordinator,
+    /// which only follows the requests of the handlers
+    pub(crate) async fn probe(&self, timeout: Duration) -> Result<(), tonic::Status> {
+        let get_topology = |mut client: CoordinatorApiClient<Channel>| async move {
+            client
+                .get_cluster_topology(GetClusterTopologyRequest::default())
+                .await
+        };
+        let result = tokio::time::timeout(timeout, self.call_with_failover(&get_topology))
+            .await
+            .unwrap_or_else(|_| {
+                Err(tonic::Status::deadline_exceeded(format!(
+                    "no endpoint responds in {timeout:?}"
+                )))
+            });
+        match result {
+            Err(err) if is_outage(&err) => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// send the request in the timeout, and add the time spent on it to the request in hand.
+    /// The health of the coordinator is updated by the outcome, and an outage names the region of the coordinator
+    async fn send<T, Fut>(&self, request: Fut) -> Result<T, tonic::Status>
+    where
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        let started_at = Instant::now();
+        let result = tokio::time::timeout(self.request_timeout, request)
+            .await
+            .unwrap_or_else(|_| {
+                Err(tonic::Status::deadline_exceeded(format!(
+                    "no coordinator responds in {:?}",
+                    self.request_timeout
+                )))
+            });
+        if let Some(context) = RequestContext::current() {
+            context.add_upstream_latency(started_at.elapsed());
+        }
+
+        let outage = result.as_ref().err().filter(|err| is_outage(err));
+        *self.lock_health() = UpstreamHealth {
+            available: Some(outage.is_none()),
+            error: outage.map(|err| err.message().to_string()),
+            checked_at: Some(times::now_timestamp()),
+        };
+        match (result, self.region.as_ref()) {
+            // the details are attached by the coordinator, so it's not an outage of the connection
+            (Err(err), Some(region)) if is_outage(&err) && err.details().is_empty() => {
+                Err(tonic::Status::new(
+                    err.code(),
+                    format!(
+                        "coordinator of region {region} is unavailable: {}",
+                        err.message()
+                    ),
+                ))
+            }
+            (result, _) => result,
+        }
+    }
+
+    async fn read_with_retries<T, F, Fut>(&self, call: &F) -> Result<T, tonic::Status>
+    where
+        F: Fn(CoordinatorApiClient<Channel>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<T>, tonic::Status>>,
+    {
+        let mut backoff = self.retry_backoff.build();
+        loop {
+            match self.call_with_failover(call).await {
+                Err(err)
+                    if err.code() == tonic::Code::Unavailable
+                        && backoff.get_attempt() < self.retries =>
+                {
+                    let delay = backoff.next_delay();
+                    tracing::warn!(
+                        "all of the coordinators are unavailable: {}, retry after {:?}",
+                        err,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn call_with_failover<T, F, Fut>(&self, call: &F) -> Result<T, tonic::Status>
+    where
+        F: Fn(CoordinatorApiClient<Channel>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<T>, tonic::Status>>,
+    {
+        let preferred = self.preferred.load(Ordering::Acquire);
+        let mut last_err = tonic::Status::unavailable("no coordinator endpoint is configured");
+        for index in (0..self.clients.len()).map(|offset| (preferred + offset) % self.clients.len())
+        {
+            let (uri, client) = &self.clients[index];
+            match call(client.clone()).await {
+                Ok(resp) => {
+                    if index != preferred {
+                        tracing::info!("fail over to coordinator {}", uri);
+                        self.preferred.store(index, Ordering::Release);
+                    }
+                    return Ok(resp.into_inner());
+                }
+                Err(err) if err.code() == tonic::Code::Unavailable => {
+                    tracing::warn!("coordinator {} is unavailable: {}", uri, err);
+                    last_err = err;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_err)
+    }
+}
+
+/// [`CoordinatorRouter`] keeps a [`CoordinatorGateway`] of each coordinator, and resolves the one serving a namespace by the routes of [`CoordinatorConfig`].
+/// The coordinator of `endpoints` is named [`DEFAULT_COORDINATOR`], and the others are named by their regions.
+/// A namespace matching no route is served by the coordinator of `default_region`, or the one of `endpoints` if it's not set
+pub(crate) struct CoordinatorRouter {
+    /// gateways by the names of the coordinators
+    gateways: BTreeMap<String, Arc<CoordinatorGateway>>,
+    /// the prefixes of the namespaces and the coordinators serving them, the longest prefix first
+    routes: Vec<(String, String)>,
+    default: Arc<CoordinatorGateway>,
+}
+
+impl CoordinatorRouter {
+    pub(crate) fn from_config(config: &CoordinatorConfig) -> Self {
+        let named = |gateway: CoordinatorGateway, name: &str| {
+            if config.regions.is_empty() {
+                gateway
+            } else {
+                gateway.with_region(name)
+            }
+        };
+        let mut gateways = BTreeMap::new();
+        if !config.endpoints.is_empty() || config.default_region.is_none() {
+            gateways.insert(
+                DEFAULT_COORDINATOR.to_string(),
+                Arc::new(named(
+                    CoordinatorGateway::from_config(config),
+                    DEFAULT_COORDINATOR,
+                )),
+            );
+        }
+        config.regions.iter().for_each(|(region, endpoints)| {
+            gateways.insert(
+                region.clone(),
+                Arc::new(named(
+                    CoordinatorGateway::with_endpoints(config, endpoints),
+                    region,
+                )),
+            );
+        });
+
+        let mut routes = config
+            .routes
+            .iter()
+            .map(|route| (route.prefix.clone(), route.region.clone()))
+            .collect::<Vec<_>>();
+        routes.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+        let default = config
+            .default_region
+            .as_ref()
+            .and_then(|region| gateways.get(region))
+            .or_else(|| gateways.get(DEFAULT_COORDINATOR))
+            .cloned()
+            .unwrap_or_else(|| Arc::new(CoordinatorGateway::from_config(config)));
+
+        Self {
+            gateways,
+            routes,
+            default,
+        }
+    }
+
+    /// the gateway of the coordinator serving the namespace
+    pub(crate) fn route(&self, namespace: &str) -> &Arc<CoordinatorGateway> {
+        self.routes
+            .iter()
+            .find(|(prefix, _)| namespace.starts_with(prefix.as_str()))
+            .and_then(|(_, name)| self.gateways.get(name))
+            .unwrap_or(&self.default)
+    }
+
+    /// the gateway of the coordinator serving the namespaces which match no route
+    pub(crate) fn get_default(&self) -> &Arc<CoordinatorGateway> {
+        &self.default
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Arc<CoordinatorGateway>> {
+        self.gateways.get(name)
+    }
+
+    /// the names of all of the coordinators and their gateways, in the order of the names
+    pub(crate) fn all(&self) -> impl Iterator<Item = (&str, &Arc<CoordinatorGateway>)> {
+        self.gateways
+            .iter()
+            .map(|(name, gateway)| (name.as_str(), gateway))
+    }
+
+    /// the health of all of the coordinators
+    pub(crate) fn get_health(&self) -> Vec<CoordinatorHealth> {
+        self.all()
+            .map(|(name, gateway)| gateway.get_health(name))
+            .collect()
+    }
+}
+
+/// the router of a single coordinator, which serves all of the namespaces
+impl From<CoordinatorGateway> for CoordinatorRouter {
+    fn from(gateway: CoordinatorGateway) -> Self {
+        let default = Arc::new(gateway);
+        Self {
+            gateways: BTreeMap::from([(DEFAULT_COORDINATOR.to_string(), default.clone())]),
+            routes: vec![],
+            default,
+        }
+    }
+}
+
+/// the coordinators can't serve the request for now, rather than rejecting it
+pub(crate) fn is_outage(err: &tonic::Status) -> bool {
+    matches!(
+        err.code(),
+        tonic::Code::Unavailable | tonic::Code::DeadlineExceeded
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use proto::coordinator::GetClusterTopologyRequest;
+
+    use crate::apiserver::config::{CoordinatorConfig, RouteConfig};
+
+    use super::{CoordinatorGateway, CoordinatorRouter, DEFAULT_COORDINATOR};
+
+    /// the address of a port which nothing listens on
+    fn dead_endpoint() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("127.0.0.1:{}", listener.local_addr().unwrap().port())
+    }
+
+    #[tokio::test]
+    async fn test_coordinator_endpoints() {
+        let gateway = CoordinatorGateway::new(" localhost:8791, ,http://10.0.0.1:8791,");
+        assert_eq!(
+            gateway.get_endpoints(),
+            vec!["http://localhost:8791", "http://10.0.0.1:8791"]
+        );
+
+        let result = CoordinatorGateway::new("")
+            .call(|mut client| async move {
+                client
+                    .get_cluster_topology(GetClusterTopologyRequest::default())
+                    .await
+            })
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::Unavailable);
+
+        let result = CoordinatorGateway::new(&dead_endpoint())
+            .call(|mut client| async move {
+                client
+                    .get_cluster_topology(GetClusterTopologyRequest::default())
+                    .await
+            })
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::Unavailable);
+
+        // the dead endpoint is retried by a read but it's still unavailable
+        let result = CoordinatorGateway::from_config(&CoordinatorConfig {
+            endpoints: vec![dead_endpoint()],
+            retries: 2,
+            ..Default::default()
+        })
+        .read(|mut client| async move {
+            client
+                .get_cluster_topology(GetClusterTopologyRequest::default())
+                .await
+        })
+        .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn test_coordinator_routes() {
+        let route = |prefix: &str, region: &str| RouteConfig {
+            prefix: prefix.to_string(),
+            region: region.to_string(),
+        };
+        let router = CoordinatorRouter::from_config(&CoordinatorConfig {
+            endpoints: vec![dead_endpoint()],
+            regions: [("eu", dead_endpoint()), ("us", dead_endpoint())]
+                .into_iter()
+                .map(|(region, endpoint)| (region.to_string(), vec![endpoint]))
+                .collect(),
+            routes: vec![route("eu-", "eu"), route("eu-west-", "us")],
+            ..Default::default()
+        });
+        // the longest prefix wins, and the namespaces matching no route go to the default coordinator
+        for (namespace, region) in [
+            ("eu-central", "eu"),
+            ("eu-west-1", "us"),
+            ("payments", DEFAULT_COORDINATOR),
+        ] {
+            assert_eq!(router.route(namespace).get_region(), Some(region));
+        }
+        assert_eq!(
+            router.all().map(|(name, _)| name).collect::<Vec<_>>(),
+            vec![DEFAULT_COORDINATOR, "eu", "us"]
+        );
+        assert!(router
+            .get_health()
+            .iter()
+            .all(|health| health.available.is_none()));
+
+        // the error of a coordinator which is down names its region
+        let err = router
+            .route("eu-central")
+            .call(|mut client| async move {
+                client
+                    .get_cluster_topology(GetClusterTopologyRequest::default())
+                    .await
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unavailable);
+        assert!(err
+            .message()
+            .starts_with("coordinator of region eu is unavailable"));
+        let health = router.get_health();
+        assert_eq!(health[1].name, "eu");
+        assert_eq!(health[1].available, Some(false));
+        assert!(health[1].error.is_some());
+        assert!(health[1].checked_at.is_some());
+        assert!(health[2].available.is_none());
+
+        // without a default coordinator, the namespaces matching no route go to the default region
+        let router = CoordinatorRouter::from_config(&CoordinatorConfig {
+            regions: [("eu".to_string(), vec![dead_endpoint()])]
+                .into_iter()
+                .collect(),
+            default_region: Some("eu".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(router.route("payments").get_region(), Some("eu"));
+        assert!(router.get(DEFAULT_COORDINATOR).is_none());
+
+        // a single coordinator isn't named by a region
+        let router = CoordinatorRouter::from_config(&CoordinatorConfig {
+            endpoints: vec![dead_endpoint()],
+            ..Default::default()
+        });
+        assert!(router.route("payments").get_region().is_none());
+        assert!(router.get(DEFAULT_COORDINATOR).is_some());
+    }
+
+    #[cfg(feature = "coordinator")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_coordinator_failover() {
+        use std::{sync::atomic::Ordering, time::Duration};
+
+        use proto::{
+            common::ResourceId,
+            coordinator::{coordinator_api_server::CoordinatorApiServer, GetDataflowRequest},
+        };
+
+        use crate::coordinator::{api::CoordinatorApiImpl, coord::CoordinatorBuilder};
+
+        let port = 8806;
+        let builder: CoordinatorBuilder = serde_json::from_value(serde_json::json!({
+            "port": port,
+            "cluster": {
+                "nodes": format!("127.0.0.1:{port}"),
+                "rpc_timeout": 3,
+                "connect_timeout": 3
+            },
+            "storage": {
+                "Memory": {
+                    "ttl": null,
+                    "max_entries": null
+                }
+            },
+            "heartbeat": {
+                "period": 3,
+                "connect_timeout": 3,
+                "rpc_timeout": 3
+            },
+            "ack": {
+                "delay": 1,
+                "buf_size": 500,
+                "connect_timeout": 3,
+                "rpc_timeout": 3
+            }
+        }))
+        .unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(CoordinatorApiServer::new(CoordinatorApiImpl::new(
+                    builder.build(),
+                )))
+                .serve(format!("127.0.0.1:{port}").parse().unwrap()),
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let gateway = CoordinatorGateway::new(&format!("{},127.0.0.1:{port}", dead_endpoint()));
+        for _ in 0..2 {
+            let result = gateway
+                .call(|mut client| async move {
+                    client
+                        .get_cluster_topology(GetClusterTopologyRequest::default())
+                        .await
+                })
+                .await;
+            assert!(result.is_ok());
+            // the live coordinator is preferred once it serves a request
+            assert_eq!(gateway.preferred.load(Ordering::Acquire), 1);
+        }
+
+        // errors other than unavailable are not failed over
+        let result = gateway
+            .call(|mut client| async move {
+                client
+                    .get_dataflow(GetDataflowRequest {
+                        job_id: Some(ResourceId::new("default", "unknown")),
+                        effective: false,
+                        include_metrics: false,
+                    })
+                    .await
+            })
+            .await;
+        let err = result.unwrap_err();
+        assert_ne!(err.code(), tonic::Code::Unavailable);
+        assert_eq!(gateway.preferred.load(Ordering::Acquire), 1);
+
+        // the coordinator attaches the details of the error to the status
+        assert!(!err.details().is_empty());
+        let response = proto::common::Response::from_status(&err);
+        let detail = response.get_error().unwrap();
+        assert_eq!(detail.get_code(), err.code());
+        assert!(!detail.retryable);
+    }
+}
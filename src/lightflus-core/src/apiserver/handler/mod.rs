@@ -1,5 +1,7 @@
+pub(crate) mod coordinator;
 pub mod resources;
 mod services;
 
 pub const RESOURCES_HANDLER_ROOT: &str = "/resources";
+/// comma-separated endpoints of the coordinators, see [`coordinator::CoordinatorGateway`]
 pub const COORDINATOR_URI_ENV: &str = "LIGHTFLUS_COORDINATOR_URI";
@@ -1,53 +1,3534 @@
-use actix_web::{error::ErrorBadRequest, get, post, web, HttpResponse};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
 use common::utils::{from_pb_slice, pb_to_bytes_mut};
 use futures_util::StreamExt;
 use proto::apiserver::{CreateResourceRequest, CreateResourceResponse, ResourceTypeEnum};
 
-use crate::apiserver::{
-    handler::services::create_dataflow,
-    types::{GetResourceArgs, ListResourcesArgs},
+use crate::{
+    apiserver::{
+        audit::{AuditLog, AuditTail},
+        auth::{Caller, Role},
+        config::BatchConfig,
+        events::EventHub,
+        graph::GetResourceGraphQuery,
+        handler::services::{
+            accepted_format, apply_resources, content_format, create_dataflow, create_resources,
+            get_operation, submit_resources,
+        },
+        openapi::{openapi, swagger_ui_page},
+        operations::OperationStore,
+        probe::Prober,
+        shutdown::Draining,
+        types::{
+            AuditQuery, BatchResourcesQuery, ClusterQuery, CreateResourceQuery,
+            DeleteResourceQuery, GetResourceArgs, GetResourceQuery, ListResourcesArgs,
+            ResourcePathArgs, TerminateResourcesRequest, UpdateResourceQuery,
+        },
+    },
+    errors::apiserver::{ApiError, ApiErrorCode},
+    metrics::MetricsRegistry,
 };
 
-use super::services::get_dataflow;
+use super::{
+    coordinator::CoordinatorRouter,
+    services::{
+        delete_dataflow, get_cluster_topology, get_dataflow, get_dataflow_detail,
+        get_dataflow_graph, list_dataflows, terminate_dataflows, update_dataflow, watch_dataflow,
+    },
+};
 
-#[post("/create")]
-async fn create_resource(mut req: web::Payload) -> actix_web::Result<HttpResponse> {
+async fn read_payload(mut payload: web::Payload) -> Result<web::BytesMut, ApiError> {
     let mut bytes = web::BytesMut::new();
-    while let Some(item) = req.next().await {
-        let item = item?;
+    while let Some(item) = payload.next().await {
+        let item = item.map_err(ApiError::from)?;
         bytes.extend_from_slice(&item);
     }
+    Ok(bytes)
+}
+
+/// the body is a protobuf [`CreateResourceRequest`] unless its `Content-Type` is JSON or YAML.
+/// A YAML body may define multiple resources in its documents, see [`ResourceDefinition`](crate::apiserver::types::ResourceDefinition).
+/// The resources of a JSON or YAML body are created in the background if the query `async` is true, see [`submit_resources`]
+#[post("/create")]
+async fn create_resource(
+    coordinators: web::Data<CoordinatorRouter>,
+    operations: web::Data<OperationStore>,
+    caller: Caller,
+    http_req: HttpRequest,
+    query: web::Query<CreateResourceQuery>,
+    req: web::Payload,
+) -> Result<HttpResponse, ApiError> {
+    let bytes = read_payload(req).await?;
+
+    if let Some(format) = content_format(&http_req) {
+        let resources = format.parse_resources(&bytes)?;
+        if query.is_async {
+            return submit_resources(
+                coordinators,
+                operations,
+                caller,
+                resources,
+                accepted_format(&http_req),
+            )
+            .await;
+        }
+        return create_resources(
+            &coordinators,
+            &caller,
+            &resources,
+            accepted_format(&http_req),
+        )
+        .await;
+    }
+    if query.is_async {
+        return Err(ApiError::invalid_argument(
+            "asynchronous creation requires a JSON or YAML body",
+        ));
+    }
 
     match from_pb_slice::<CreateResourceRequest>(bytes.iter().as_slice()) {
         Ok(req) => match req.resource_type() {
-            ResourceTypeEnum::Dataflow => create_dataflow(req)
+            ResourceTypeEnum::Dataflow => create_dataflow(&coordinators, &caller, req)
                 .await
                 .map(|resp| HttpResponse::Created().body(pb_to_bytes_mut(resp))),
             _ => Ok(
                 HttpResponse::Created().body(pb_to_bytes_mut(CreateResourceResponse::default()))
             ),
         },
-        Err(err) => Err(ErrorBadRequest(err)),
+        Err(err) => Err(ApiError::invalid_argument(format!(
+            "invalid protobuf body: {err}"
+        ))),
     }
 }
 
+/// create or update the resources of a JSON array or a multi-document YAML body, see [`apply_resources`].
+/// The query `atomic` makes the batch rejected if any resource is invalid, and `strategy` is the one of [`update_resource`]
+#[post("/resources:batch")]
+async fn batch_resources(
+    coordinators: web::Data<CoordinatorRouter>,
+    config: web::Data<BatchConfig>,
+    caller: Caller,
+    http_req: HttpRequest,
+    query: web::Query<BatchResourcesQuery>,
+    req: web::Payload,
+) -> Result<HttpResponse, ApiError> {
+    let bytes = read_payload(req).await?;
+    let format = content_format(&http_req).ok_or_else(|| {
+        ApiError::invalid_argument("the resources of a batch must be defined in JSON or YAML")
+    })?;
+    let resources = format.parse_resource_batch(&bytes)?;
+    apply_resources(
+        &coordinators,
+        &config,
+        &caller,
+        &resources,
+        &query,
+        accepted_format(&http_req),
+    )
+    .await
+}
+
 #[get("/get/{namespace}/{resource_type}/{resource_id}")]
-async fn get_resource(args: web::Path<GetResourceArgs>) -> actix_web::Result<HttpResponse> {
+async fn get_resource(
+    coordinators: web::Data<CoordinatorRouter>,
+    caller: Caller,
+    args: web::Path<GetResourceArgs>,
+) -> Result<HttpResponse, ApiError> {
     match ResourceTypeEnum::from_i32(args.resource_type) {
         Some(resource_type) => match resource_type {
-            ResourceTypeEnum::Dataflow => get_dataflow(args.as_ref()).await,
+            ResourceTypeEnum::Dataflow => {
+                get_dataflow(coordinators.route(&args.namespace), &caller, args.as_ref()).await
+            }
             _ => Ok(HttpResponse::Ok().finish()),
         },
         None => Ok(HttpResponse::Ok().finish()),
     }
 }
 
-#[get("/list/{namespace}/{resource_type}")]
-async fn list_resources(args: web::Path<ListResourcesArgs>) -> actix_web::Result<HttpResponse> {
-    Ok(HttpResponse::Ok().finish())
+/// list the dataflows page by page with their status summaries, see [`ListResourcesArgs`] for the query
+#[get("")]
+async fn list_resources(
+    coordinators: web::Data<CoordinatorRouter>,
+    caller: Caller,
+    req: HttpRequest,
+    args: web::Query<ListResourcesArgs>,
+) -> Result<HttpResponse, ApiError> {
+    list_dataflows(&coordinators, &caller, &args, accepted_format(&req)).await
+}
+
+/// terminate a batch of dataflows. The result of each dataflow is responded, see [`TerminateResourcesRequest`] for the body
+#[post("/terminate")]
+async fn terminate_resources(
+    coordinators: web::Data<CoordinatorRouter>,
+    caller: Caller,
+    req: web::Json<TerminateResourcesRequest>,
+) -> Result<HttpResponse, ApiError> {
+    terminate_dataflows(&coordinators, &caller, &req).await
+}
+
+/// the summary of a dataflow with the spec and the runtime status of its operators.
+/// The query `view` is one of `spec`, `status` and `full`, which is the default
+#[get("/{namespace}/{name}")]
+async fn get_resource_detail(
+    coordinators: web::Data<CoordinatorRouter>,
+    caller: Caller,
+    req: HttpRequest,
+    args: web::Path<ResourcePathArgs>,
+    query: web::Query<GetResourceQuery>,
+) -> Result<HttpResponse, ApiError> {
+    get_dataflow_detail(
+        coordinators.route(&args.namespace),
+        &caller,
+        &args,
+        query.view,
+        accepted_format(&req),
+    )
+    .await
+}
+
+/// replace a dataflow with the resource defined in the JSON or YAML body, whose `resource_version` must be the current one.
+/// The query `strategy` is either `rolling`, which is the default but not supported yet, or `recreate`
+#[put("/{namespace}/{name}")]
+async fn update_resource(
+    coordinators: web::Data<CoordinatorRouter>,
+    caller: Caller,
+    http_req: HttpRequest,
+    args: web::Path<ResourcePathArgs>,
+    query: web::Query<UpdateResourceQuery>,
+    req: web::Payload,
+) -> Result<HttpResponse, ApiError> {
+    let bytes = read_payload(req).await?;
+    let format = content_format(&http_req).ok_or_else(|| {
+        ApiError::invalid_argument("the updated resource must be defined in JSON or YAML")
+    })?;
+    let resources = format.parse_resources(&bytes)?;
+    update_dataflow(
+        coordinators.route(&args.namespace),
+        &caller,
+        &args,
+        &resources,
+        query.strategy,
+        accepted_format(&http_req),
+    )
+    .await
+}
+
+/// terminate a dataflow. The query `mode` is either `drain`, which is the default, or `force`
+#[delete("/{namespace}/{name}")]
+async fn delete_resource(
+    coordinators: web::Data<CoordinatorRouter>,
+    caller: Caller,
+    args: web::Path<ResourcePathArgs>,
+    query: web::Query<DeleteResourceQuery>,
+) -> Result<HttpResponse, ApiError> {
+    delete_dataflow(
+        coordinators.route(&args.namespace),
+        &caller,
+        &args,
+        query.mode,
+    )
+    .await
+}
+
+/// the status changes of a dataflow as server-sent events: `status`, `operator`, `failover`, `checkpoint` and `deleted`,
+/// which is the last one. Clients should read the detail first, the stream only sends the changes after it starts
+#[get("/{namespace}/{name}/events")]
+async fn get_resource_events(
+    coordinators: web::Data<CoordinatorRouter>,
+    events: web::Data<EventHub>,
+    caller: Caller,
+    args: web::Path<ResourcePathArgs>,
+) -> Result<HttpResponse, ApiError> {
+    let coordinator = web::Data::from(coordinators.route(&args.namespace).clone());
+    watch_dataflow(coordinator, events, caller, &args).await
+}
+
+/// the graph of the operators of a dataflow annotated with their runtime status.
+/// The query `format` is either `json`, which is the default node-link structure, or `dot` for graphviz
+#[get("/{namespace}/{name}/graph")]
+async fn get_resource_graph(
+    coordinators: web::Data<CoordinatorRouter>,
+    caller: Caller,
+    args: web::Path<ResourcePathArgs>,
+    query: web::Query<GetResourceGraphQuery>,
+) -> Result<HttpResponse, ApiError> {
+    get_dataflow_graph(
+        coordinators.route(&args.namespace),
+        &caller,
+        &args,
+        query.format,
+    )
+    .await
+}
+
+/// the state of an asynchronous operation, and its result once it's done
+#[get("/operations/{id}")]
+async fn operation(
+    operations: web::Data<OperationStore>,
+    caller: Caller,
+    req: HttpRequest,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    get_operation(&operations, &caller, &id, accepted_format(&req))
+}
+
+/// the topology of the TaskManager cluster: nodes, their health, weights and the number of partitions they host.
+/// The query `coordinator` is the name of the coordinator managing the cluster, the default coordinator if it's not given
+#[get("/cluster")]
+async fn cluster(
+    coordinators: web::Data<CoordinatorRouter>,
+    caller: Caller,
+    query: web::Query<ClusterQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let coordinator = match query.coordinator.as_deref() {
+        Some(name) => coordinators.get(name).ok_or_else(|| {
+            ApiError::new(ApiErrorCode::NotFound, format!("no coordinator {name}"))
+        })?,
+        None => coordinators.get_default(),
+    };
+    get_cluster_topology(coordinator, &caller).await
+}
+
+/// the health of each coordinator by the outcome of its latest request, see [`CoordinatorRouter`]
+#[get("/coordinators")]
+async fn coordinator_health(coordinators: web::Data<CoordinatorRouter>) -> HttpResponse {
+    HttpResponse::Ok().json(coordinators.get_health())
+}
+
+/// the latest mutations kept in memory by the audit log, the oldest first. It requires the admin role
+#[get("/audit")]
+async fn audit_tail(
+    audit: web::Data<AuditLog>,
+    caller: Caller,
+    query: web::Query<AuditQuery>,
+) -> Result<HttpResponse, ApiError> {
+    caller.authorize_role(Role::Admin)?;
+    Ok(HttpResponse::Ok().json(AuditTail {
+        records: audit.get_tail(query.limit),
+    }))
 }
 
 #[get("/overview")]
 async fn overview() -> HttpResponse {
     HttpResponse::Ok().finish()
 }
+
+/// the API server is alive. It's not authenticated unless the health endpoints are locked
+#[get("/health")]
+async fn health() -> HttpResponse {
+    HttpResponse::Ok().finish()
+}
+
+/// the API server is ready. It's unavailable once the server is shutting down, so the load balancer stops sending requests to it.
+/// It's not authenticated unless the health endpoints are locked
+#[get("/ready")]
+async fn readiness(draining: web::Data<Draining>) -> Result<HttpResponse, ApiError> {
+    if draining.is_draining() {
+        return Err(ApiError::new(
+            ApiErrorCode::Unavailable,
+            "the API server is shutting down",
+        ));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// the liveness probe: the API server is alive while its background probes keep running, see [`Prober`].
+/// It's not authenticated unless the health endpoints are locked
+#[get("/healthz")]
+async fn liveness_probe(prober: web::Data<Prober>) -> HttpResponse {
+    prober.liveness().to_response()
+}
+
+/// the readiness probe, which details the check of each dependency by the latest background probes, see [`Prober`].
+/// It fails once the server is shutting down. It's not authenticated unless the health endpoints are locked
+#[get("/readyz")]
+async fn readiness_probe(prober: web::Data<Prober>, draining: web::Data<Draining>) -> HttpResponse {
+    prober.readiness(&draining).to_response()
+}
+
+/// the OpenAPI document of the endpoints, see [`openapi`]
+#[get("/openapi.json")]
+async fn openapi_document() -> HttpResponse {
+    HttpResponse::Ok().json(openapi())
+}
+
+/// the Swagger UI of the OpenAPI document. It's only registered if it's enabled by [`ApiServerConfig::swagger_ui`](crate::apiserver::config::ApiServerConfig::swagger_ui)
+#[get("/docs")]
+async fn swagger_ui() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(swagger_ui_page())
+}
+
+/// the metrics in the Prometheus text format, see [`AccessLog`](crate::apiserver::middleware::AccessLog).
+/// It's only registered if the metrics are not served on a port of their own by [`ApiMetricsConfig::port`](crate::apiserver::config::ApiMetricsConfig::port)
+#[get("/metrics")]
+async fn prometheus_metrics(registry: web::Data<MetricsRegistry>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(registry.render())
+}
+
+/// requests of unknown endpoints are not found
+pub(crate) async fn not_found(req: HttpRequest) -> Result<HttpResponse, ApiError> {
+    Err(ApiError::new(
+        ApiErrorCode::NotFound,
+        format!("no endpoint {} {}", req.method(), req.path()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::Arc,
+    };
+
+    use actix_web::{body::MessageBody, dev::ServiceResponse, http::StatusCode, test, web, App};
+    use proto::{
+        apiserver::ResourceTypeEnum,
+        common::{
+            operator_info::Details, Ack, Chaining, Dataflow, DataflowStates, DataflowStatus,
+            ErrorDetail, ExecutorInfo, ExecutorStatus, Heartbeat, HostAddr, OperatorError,
+            OperatorInfo, ResourceId, Response, SubdataflowInfo,
+        },
+        coordinator::{
+            coordinator_api_server::CoordinatorApi, AddNodeRequest, AddNodeResponse,
+            ClusterTopology, DataflowRuntimeStatus, DataflowSummary, DeleteSavepointRequest,
+            GetClusterTopologyRequest, GetDataflowRequest, GetDataflowStatusRequest,
+            ListDataflowsRequest, ListDataflowsResponse, ListSavepointsResponse,
+            OperatorRuntimeStatus, Savepoint, TerminateDataflowResult, TerminateDataflowsRequest,
+            TerminateDataflowsResponse, UpdateDataflowRequest, UpdateDataflowResponse,
+            UpdateStrategy, WorkerFailure,
+        },
+    };
+
+    use crate::{
+        apiserver::{
+            audit::{AuditLog, AuditRecord, AuditTail},
+            auth::TokenStore,
+            config::{
+                ApiServerConfig, AuditConfig, AuditFileConfig, BatchConfig, CorsConfig,
+                LimitsConfig,
+            },
+            configure,
+            handler::{
+                coordinator::{CoordinatorGateway, CoordinatorRouter},
+                services::to_delete_response,
+                RESOURCES_HANDLER_ROOT,
+            },
+            middleware::{
+                AccessLog, Audit, Authentication, Cors, RateLimit, RequestId, REQUEST_ID_HEADER,
+            },
+            operations::OperationStore,
+            operators::OperatorSettings,
+            probe::{CheckStatus, ProbeReport, Prober},
+            shutdown::Draining,
+            types::{
+                BodyFormat, ListResourcesResponse, ResourceDetail, ResourceKind, ResourceSummary,
+            },
+        },
+        errors::apiserver::{ApiError, ApiErrorCode},
+        metrics::MetricsRegistry,
+    };
+
+    const DATAFLOW_YAML: &str = r#"
+namespace: default
+name: job
+dataflow:
+  meta:
+    - center: 0
+      neighbors: [1]
+  nodes:
+    0:
+      operator_id: 0
+      details:
+        mapper:
+          value:
+            func:
+              function: "(a) => a"
+    1:
+      operator_id: 1
+      upstreams: [0]
+"#;
+
+    /// a dataflow of a single operator which passes [`Dataflow::validate`], unlike the one of [`DATAFLOW_YAML`]
+    const VALID_DATAFLOW_YAML: &str = r#"
+namespace: default
+name: job
+dataflow:
+  meta:
+    - center: 0
+  nodes:
+    0:
+      operator_id: 0
+      details:
+        mapper:
+          value:
+            func:
+              function: "(a) => a"
+"#;
+
+    /// the address of a port which nothing listens on
+    fn dead_endpoint() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("127.0.0.1:{}", listener.local_addr().unwrap().port())
+    }
+
+    /// the request id of the requests whose errors are read by [`read_error`]
+    const REQUEST_ID: &str = "test-request";
+
+    fn with_request_id(req: test::TestRequest) -> test::TestRequest {
+        req.insert_header((REQUEST_ID_HEADER, REQUEST_ID))
+    }
+
+    /// read the error of a request sent [`with_request_id`]. The id is responded in both of the header and the body
+    async fn read_error<B: MessageBody>(
+        resp: ServiceResponse<B>,
+        status: StatusCode,
+    ) -> serde_json::Value {
+        assert_eq!(resp.status(), status);
+        assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), REQUEST_ID);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["requestId"], REQUEST_ID);
+        body
+    }
+
+    /// the message of an error caused by the transport varies, so it's only checked to be given
+    fn without_message(mut body: serde_json::Value) -> serde_json::Value {
+        assert!(!body["message"].as_str().unwrap().is_empty());
+        assert!(!body["message"].as_str().unwrap().contains("Status {"));
+        body["message"] = serde_json::Value::Null;
+        body
+    }
+
+    #[actix_web::test]
+    async fn test_coordinator_unavailable_errors() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&dead_endpoint()),
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .configure(configure),
+        )
+        .await;
+
+        let unavailable = serde_json::json!({
+            "code": "unavailable",
+            "message": null,
+            "details": [],
+            "requestId": REQUEST_ID
+        });
+        for req in [
+            test::TestRequest::get().uri("/resources?limit=10&namespace=default"),
+            test::TestRequest::get().uri("/resources/get/default/1/job"),
+            test::TestRequest::get().uri("/resources/default/job"),
+            test::TestRequest::delete().uri("/resources/default/job"),
+            test::TestRequest::post()
+                .uri("/resources/terminate")
+                .set_json(serde_json::json!({
+                    "resources": [{"id": "job", "namespace": "default"}]
+                })),
+            test::TestRequest::get().uri("/cluster"),
+        ] {
+            assert_eq!(
+                without_message(
+                    read_error(
+                        test::call_service(&app, with_request_id(req).to_request()).await,
+                        StatusCode::SERVICE_UNAVAILABLE
+                    )
+                    .await
+                ),
+                unavailable
+            );
+        }
+
+        // a new id is generated if the request has none
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/cluster").to_request()).await;
+        let request_id = resp.headers().get(REQUEST_ID_HEADER).unwrap().clone();
+        let err: ApiError = test::read_body_json(resp).await;
+        assert_eq!(err.code, ApiErrorCode::Unavailable);
+        assert_eq!(err.request_id.as_deref(), request_id.to_str().ok());
+        assert!(!request_id.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_request_errors() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&dead_endpoint()),
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .configure(configure),
+        )
+        .await;
+
+        let body = read_error(
+            test::call_service(
+                &app,
+                with_request_id(
+                    test::TestRequest::post()
+                        .uri("/resources/terminate")
+                        .set_json(serde_json::json!({ "resources": [] })),
+                )
+                .to_request(),
+            )
+            .await,
+            StatusCode::BAD_REQUEST,
+        )
+        .await;
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "code": "invalid_argument",
+                "message": "no resource to terminate",
+                "details": [{"field": "resources", "message": "at least one resource is required"}],
+                "requestId": REQUEST_ID
+            })
+        );
+
+        let body = read_error(
+            test::call_service(
+                &app,
+                with_request_id(
+                    test::TestRequest::post()
+                        .uri("/resources/terminate")
+                        .insert_header(("Content-Type", "application/json"))
+                        .set_payload("{\n  \"mode\": \"force\"\n}"),
+                )
+                .to_request(),
+            )
+            .await,
+            StatusCode::BAD_REQUEST,
+        )
+        .await;
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "code": "invalid_argument",
+                "message": "invalid body: missing field `resources` at line 3 column 1",
+                "details": [{"message": "missing field `resources` at line 3 column 1", "line": 3, "column": 1}],
+                "requestId": REQUEST_ID
+            })
+        );
+
+        let body = read_error(
+            test::call_service(
+                &app,
+                with_request_id(
+                    test::TestRequest::post()
+                        .uri("/resources/create")
+                        .insert_header(("Content-Type", "application/yaml"))
+                        .set_payload("namespace: default\nname: [job]\n"),
+                )
+                .to_request(),
+            )
+            .await,
+            StatusCode::BAD_REQUEST,
+        )
+        .await;
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "code": "invalid_argument",
+                "message": "name: invalid type: sequence, expected a string at line 2 column 7",
+                "details": [{
+                    "field": "name",
+                    "message": "name: invalid type: sequence, expected a string at line 2 column 7",
+                    "document": 0,
+                    "line": 2,
+                    "column": 7
+                }],
+                "requestId": REQUEST_ID
+            })
+        );
+
+        let body = read_error(
+            test::call_service(
+                &app,
+                with_request_id(
+                    test::TestRequest::post()
+                        .uri("/resources/create")
+                        .set_payload(vec![0xff, 0xff]),
+                )
+                .to_request(),
+            )
+            .await,
+            StatusCode::BAD_REQUEST,
+        )
+        .await;
+        assert_eq!(body["code"], "invalid_argument");
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid protobuf body: "));
+        assert_eq!(body["details"], serde_json::json!([]));
+
+        for (req, message) in [
+            (
+                test::TestRequest::get().uri("/resources?limit=many"),
+                "invalid query: Query deserialize error: invalid digit found in string",
+            ),
+            (
+                test::TestRequest::get().uri("/resources/default/job?view=invalid"),
+                "invalid query: Query deserialize error: unknown variant `invalid`, expected one of `spec`, `status`, `full`",
+            ),
+            (
+                test::TestRequest::delete().uri("/resources/default/job?mode=invalid"),
+                "invalid query: Query deserialize error: unknown variant `invalid`, expected `drain` or `force`",
+            ),
+            (
+                test::TestRequest::get().uri("/resources/get/default/dataflow/job"),
+                "invalid path: Path deserialize error: can not parse \"dataflow\" to a i32",
+            ),
+        ] {
+            assert_eq!(
+                read_error(test::call_service(&app, with_request_id(req).to_request()).await, StatusCode::BAD_REQUEST).await,
+                serde_json::json!({
+                    "code": "invalid_argument",
+                    "message": message,
+                    "details": [],
+                    "requestId": REQUEST_ID
+                })
+            );
+        }
+
+        assert_eq!(
+            read_error(
+                test::call_service(
+                    &app,
+                    with_request_id(test::TestRequest::get().uri("/unknown")).to_request()
+                )
+                .await,
+                StatusCode::NOT_FOUND
+            )
+            .await,
+            serde_json::json!({
+                "code": "not_found",
+                "message": "no endpoint GET /unknown",
+                "details": [],
+                "requestId": REQUEST_ID
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_authentication() {
+        let dir = std::env::temp_dir().join(format!("lightflus-auth-{}", common::utils::uuid()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tokens.json");
+        std::fs::write(
+            &path,
+            serde_json::json!({
+                "tokens": [
+                    {"token": "reader", "identity": "alice", "namespaces": ["default"], "role": "read"},
+                    {"token": "writer", "identity": "bob", "role": "write"}
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let tokens = Arc::new(TokenStore::load(&path).unwrap());
+        let app = test::init_service(
+            App::new()
+                .wrap(Authentication::new(Some(tokens.clone())))
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&dead_endpoint()),
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .configure(configure),
+        )
+        .await;
+
+        let unauthenticated = |message: &str| {
+            serde_json::json!({
+                "code": "unauthenticated",
+                "message": message,
+                "details": [],
+                "requestId": REQUEST_ID
+            })
+        };
+        let resp = test::call_service(
+            &app,
+            with_request_id(test::TestRequest::get().uri("/resources/default/job")).to_request(),
+        )
+        .await;
+        assert_eq!(resp.headers().get("WWW-Authenticate").unwrap(), "Bearer");
+        assert_eq!(
+            read_error(resp, StatusCode::UNAUTHORIZED).await,
+            unauthenticated("bearer token is required")
+        );
+        for authorization in ["Bearer unknown", "Basic reader"] {
+            let body = read_error(
+                test::call_service(
+                    &app,
+                    with_request_id(
+                        test::TestRequest::get()
+                            .uri("/resources/default/job")
+                            .insert_header(("Authorization", authorization)),
+                    )
+                    .to_request(),
+                )
+                .await,
+                StatusCode::UNAUTHORIZED,
+            )
+            .await;
+            assert_eq!(body["code"], "unauthenticated");
+        }
+
+        // the permissions are checked before the coordinator is called
+        let forbidden = [
+            (
+                test::TestRequest::delete().uri("/resources/default/job"),
+                serde_json::json!({
+                    "code": "permission_denied",
+                    "message": "alice is not allowed to write",
+                    "details": [],
+                    "requestId": REQUEST_ID
+                }),
+            ),
+            (
+                test::TestRequest::get().uri("/resources/other/job"),
+                serde_json::json!({
+                    "code": "permission_denied",
+                    "message": "alice is not allowed to access namespace other",
+                    "details": [{"field": "namespace", "message": "namespace other is not allowed"}],
+                    "requestId": REQUEST_ID
+                }),
+            ),
+            (
+                test::TestRequest::get().uri("/resources"),
+                serde_json::json!({
+                    "code": "permission_denied",
+                    "message": "alice is not allowed to access all of the namespaces",
+                    "details": [{"field": "namespace", "message": "namespace is required"}],
+                    "requestId": REQUEST_ID
+                }),
+            ),
+        ];
+        for (req, expected) in forbidden {
+            assert_eq!(
+                read_error(
+                    test::call_service(
+                        &app,
+                        with_request_id(req.insert_header(("Authorization", "Bearer reader")))
+                            .to_request()
+                    )
+                    .await,
+                    StatusCode::FORBIDDEN
+                )
+                .await,
+                expected
+            );
+        }
+
+        // authorized requests reach the coordinator, which is unavailable
+        for (req, token) in [
+            (
+                test::TestRequest::get().uri("/resources?namespace=default"),
+                "Bearer reader",
+            ),
+            (test::TestRequest::get().uri("/cluster"), "Bearer reader"),
+            (
+                test::TestRequest::post()
+                    .uri("/resources/terminate")
+                    .set_json(serde_json::json!({
+                        "resources": [{"id": "job", "namespace": "other"}]
+                    })),
+                "bearer writer",
+            ),
+        ] {
+            let body = read_error(
+                test::call_service(
+                    &app,
+                    with_request_id(req.insert_header(("Authorization", token))).to_request(),
+                )
+                .await,
+                StatusCode::SERVICE_UNAVAILABLE,
+            )
+            .await;
+            assert_eq!(body["code"], "unavailable");
+        }
+
+        // the health endpoints are not authenticated unless they're locked
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/health").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let locked = test::init_service(
+            App::new()
+                .wrap(Authentication::new(Some(tokens)).with_health_locked(true))
+                .wrap(RequestId)
+                .configure(configure),
+        )
+        .await;
+        for uri in ["/health", "/overview"] {
+            let resp = test::call_service(
+                &locked,
+                with_request_id(test::TestRequest::get().uri(uri)).to_request(),
+            )
+            .await;
+            assert_eq!(
+                read_error(resp, StatusCode::UNAUTHORIZED).await,
+                unauthenticated("bearer token is required")
+            );
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limits() {
+        let dir = std::env::temp_dir().join(format!("lightflus-limits-{}", common::utils::uuid()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tokens.json");
+        std::fs::write(
+            &path,
+            serde_json::json!({
+                "tokens": [
+                    {"token": "bob", "identity": "bob", "role": "write"},
+                    {"token": "carol", "identity": "carol", "role": "write"}
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let limits: LimitsConfig = serde_json::from_value(serde_json::json!({
+            "max_body_size": 1024,
+            "rate": {"rate": 1, "burst": 2}
+        }))
+        .unwrap();
+        let registry = MetricsRegistry::default();
+        let app = test::init_service(
+            App::new()
+                .wrap(RateLimit::new(&limits, &registry))
+                .wrap(Authentication::new(Some(Arc::new(
+                    TokenStore::load(&path).unwrap(),
+                ))))
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&dead_endpoint()),
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .configure(configure),
+        )
+        .await;
+        let submit = |token: &str, name: &str| {
+            with_request_id(
+                test::TestRequest::post()
+                    .uri("/resources/create?async=true")
+                    .insert_header(("Authorization", format!("Bearer {token}")))
+                    .set_json(serde_json::json!({
+                        "namespace": "default",
+                        "name": name,
+                        "dataflow": {
+                            "meta": [{ "center": 0, "neighbors": [] }],
+                            "nodes": {
+                                "0": {
+                                    "operator_id": 0,
+                                    "details": { "mapper": { "value": { "func": { "function": "(a) => a" } } } }
+                                }
+                            }
+                        }
+                    })),
+            )
+            .to_request()
+        };
+        let get = |token: &str, uri: &str| {
+            with_request_id(
+                test::TestRequest::get()
+                    .uri(uri)
+                    .insert_header(("Authorization", format!("Bearer {token}"))),
+            )
+            .to_request()
+        };
+
+        // a burst of the writes is accepted at once
+        let mut operations = vec![];
+        for name in ["job-0", "job-1"] {
+            let resp = test::call_service(&app, submit("bob", name)).await;
+            assert_eq!(resp.status(), StatusCode::ACCEPTED);
+            let operation: serde_json::Value = test::read_body_json(resp).await;
+            operations.push(operation["id"].as_str().unwrap().to_string());
+        }
+        let resp = test::call_service(&app, submit("bob", "job-2")).await;
+        assert_eq!(resp.headers().get("Retry-After").unwrap(), "1");
+        assert_eq!(
+            read_error(resp, StatusCode::TOO_MANY_REQUESTS).await,
+            serde_json::json!({
+                "code": "resource_exhausted",
+                "message": "bob exceeds the rate limit of the writes",
+                "details": [],
+                "requestId": REQUEST_ID
+            })
+        );
+
+        // the submissions can still be followed, and the reads and the other callers have buckets of their own
+        for _ in 0..5 {
+            for id in &operations {
+                let resp = test::call_service(&app, get("bob", &format!("/operations/{id}"))).await;
+                assert_eq!(resp.status(), StatusCode::OK);
+            }
+        }
+        let resp = test::call_service(&app, get("bob", "/resources/default/job")).await;
+        read_error(resp, StatusCode::SERVICE_UNAVAILABLE).await;
+        let resp = test::call_service(&app, submit("carol", "job-3")).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+        // a large body is rejected by its length before it's read, or once it exceeds the limit while it's read
+        let resp = test::call_service(
+            &app,
+            with_request_id(
+                test::TestRequest::post()
+                    .uri("/resources/create")
+                    .insert_header(("Authorization", "Bearer bob"))
+                    .insert_header(("Content-Length", "2048"))
+                    .set_payload(vec![0u8; 16]),
+            )
+            .to_request(),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::PAYLOAD_TOO_LARGE).await;
+        assert_eq!(body["code"], "resource_exhausted");
+        let resp = test::call_service(
+            &app,
+            with_request_id(
+                test::TestRequest::post()
+                    .uri("/resources/create")
+                    .insert_header(("Authorization", "Bearer carol"))
+                    .insert_header(("Content-Type", "application/yaml"))
+                    .set_payload(vec![b'#'; 2048]),
+            )
+            .to_request(),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::PAYLOAD_TOO_LARGE).await;
+        assert_eq!(body["message"], "the body is too large");
+
+        let metrics = registry.render();
+        for line in [
+            "lightflus_apiserver_rate_limited_requests_total 1\n",
+            "lightflus_apiserver_oversized_requests_total 2\n",
+            "lightflus_apiserver_rate_limit_read_tokens_consumed 1\n",
+            "lightflus_apiserver_rate_limit_write_tokens_consumed 4\n",
+        ] {
+            assert!(metrics.contains(line), "{line} is not in {metrics}");
+        }
+
+        // the requests which are not authenticated are limited by the addresses of their peers
+        let app = test::init_service(
+            App::new()
+                .wrap(RateLimit::new(&limits, &MetricsRegistry::default()))
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&dead_endpoint()),
+                )))
+                .configure(configure),
+        )
+        .await;
+        let get = |peer: &str, uri: &str| {
+            with_request_id(
+                test::TestRequest::get()
+                    .uri(uri)
+                    .peer_addr(peer.parse().unwrap()),
+            )
+            .to_request()
+        };
+        for _ in 0..2 {
+            let resp = test::call_service(&app, get("10.0.0.1:4000", "/cluster")).await;
+            read_error(resp, StatusCode::SERVICE_UNAVAILABLE).await;
+        }
+        let resp = test::call_service(&app, get("10.0.0.1:4001", "/cluster")).await;
+        let body = read_error(resp, StatusCode::TOO_MANY_REQUESTS).await;
+        assert_eq!(
+            body["message"],
+            "10.0.0.1 exceeds the rate limit of the reads"
+        );
+        let resp = test::call_service(&app, get("10.0.0.1:4000", "/health")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, get("10.0.0.2:4000", "/cluster")).await;
+        read_error(resp, StatusCode::SERVICE_UNAVAILABLE).await;
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_api_error_status() {
+        use actix_web::ResponseError;
+        use proto::common::Response;
+
+        for (code, expected, status) in [
+            (
+                tonic::Code::InvalidArgument,
+                ApiErrorCode::InvalidArgument,
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                tonic::Code::NotFound,
+                ApiErrorCode::NotFound,
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                tonic::Code::AlreadyExists,
+                ApiErrorCode::AlreadyExists,
+                StatusCode::CONFLICT,
+            ),
+            (
+                tonic::Code::ResourceExhausted,
+                ApiErrorCode::ResourceExhausted,
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            (
+                tonic::Code::FailedPrecondition,
+                ApiErrorCode::FailedPrecondition,
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (
+                tonic::Code::Aborted,
+                ApiErrorCode::Aborted,
+                StatusCode::CONFLICT,
+            ),
+            (
+                tonic::Code::Unimplemented,
+                ApiErrorCode::Unimplemented,
+                StatusCode::NOT_IMPLEMENTED,
+            ),
+            (
+                tonic::Code::Unavailable,
+                ApiErrorCode::Unavailable,
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                tonic::Code::Internal,
+                ApiErrorCode::Internal,
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ] {
+            // the code of the status and the one of the response attached to it are the same
+            let err = ApiError::from(tonic::Status::new(code, "failed"));
+            assert_eq!(err.code, expected);
+            assert_eq!(err.status_code(), status);
+            let err = ApiError::from(&Response::error(ErrorDetail::from_status(
+                &tonic::Status::new(code, "failed"),
+            )));
+            assert_eq!(err, ApiError::new(expected, "failed"), "code {:?}", code);
+            assert_eq!(err.into_tonic_status().code(), code);
+        }
+
+        // the details of the status are dropped
+        let err = ApiError::from(
+            Response::error(ErrorDetail::from_status(&tonic::Status::not_found(
+                "not found dataflow",
+            )))
+            .into_status(),
+        );
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "code": "not_found",
+                "message": "not found dataflow",
+                "details": []
+            })
+        );
+        // a failed response without details is internal
+        assert_eq!(
+            ApiError::from(&Response {
+                status: "failure".to_string(),
+                err_msg: "failed".to_string(),
+                ..Default::default()
+            }),
+            ApiError::internal("failed")
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_parse_resources() {
+        let resources = BodyFormat::Yaml
+            .parse_resources(
+                format!("{DATAFLOW_YAML}---\nnamespace: default\nname: empty\n").as_bytes(),
+            )
+            .unwrap();
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0].kind, ResourceKind::Dataflow);
+        assert!(resources[1].dataflow.is_none());
+
+        let req = resources[0].to_create_resource_request();
+        assert_eq!(req.namespace, "default");
+        assert_eq!(req.resource_type(), ResourceTypeEnum::Dataflow);
+        assert_eq!(
+            req.get_dataflow(),
+            Dataflow::builder()
+                .job_id("default", "job")
+                .operator(OperatorInfo::map(0, "(a) => a"))
+                .operator(OperatorInfo {
+                    operator_id: 1,
+                    ..Default::default()
+                })
+                .edge(0, 1)
+                .build_unvalidated()
+        );
+        assert!(resources[1]
+            .to_create_resource_request()
+            .is_dataflow_empty());
+
+        // the labels of the resource override the labels of the dataflow
+        let mut labeled = resources[0].clone();
+        labeled.labels = BTreeMap::from_iter([("team".to_string(), "payments".to_string())]);
+        labeled.dataflow.as_mut().unwrap().labels = HashMap::from_iter([
+            ("team".to_string(), "risk".to_string()),
+            ("env".to_string(), "dev".to_string()),
+        ]);
+        assert_eq!(
+            labeled.to_create_resource_request().get_dataflow().labels,
+            HashMap::from_iter([
+                ("team".to_string(), "payments".to_string()),
+                ("env".to_string(), "dev".to_string()),
+            ])
+        );
+
+        // the operator settings override the ones of the operators in the dataflow
+        let resources = BodyFormat::Yaml
+            .parse_resources(
+                format!("{DATAFLOW_YAML}operators:\n  1:\n    chaining: disabled\n    error_policy:\n      retry:\n        attempts: 3\n        fallback:\n          fail: {{}}\n    state_limit:\n      max_bytes: 1024\n")
+                    .as_bytes(),
+            )
+            .unwrap();
+        let dataflow = resources[0].to_create_resource_request().get_dataflow();
+        let operator = &dataflow.nodes[&1];
+        assert_eq!(operator.chaining(), Chaining::Disabled);
+        assert_eq!(OperatorSettings::from(operator), resources[0].operators[&1]);
+        assert_eq!(
+            OperatorSettings::from(&dataflow.nodes[&0]),
+            Default::default()
+        );
+
+        // JSON and YAML are converted to the same request
+        let json = serde_json::to_vec(&resources[0]).unwrap();
+        assert_eq!(
+            BodyFormat::Json.parse_resources(&json).unwrap(),
+            vec![resources[0].clone()]
+        );
+
+        // the errors are located by the document, the line and the column
+        let err = BodyFormat::Yaml
+            .parse_resources(
+                format!("{DATAFLOW_YAML}---\nnamespace: default\nname: invalid\ndataflow:\n  nodes: []\n")
+                    .as_bytes(),
+            )
+            .unwrap_err();
+        assert_eq!(err.code, ApiErrorCode::InvalidArgument);
+        assert_eq!(err.details.len(), 1);
+        assert_eq!(err.details[0].field.as_deref(), Some("dataflow.nodes"));
+        assert_eq!(err.details[0].document, Some(1));
+        assert_eq!(err.details[0].line, Some(23));
+        assert_eq!(err.details[0].column, Some(10));
+
+        // so are the invalid operator settings
+        let err = BodyFormat::Yaml
+            .parse_resources(
+                format!("{DATAFLOW_YAML}---\n{DATAFLOW_YAML}operators:\n  1:\n    error_policy:\n      retry:\n        attempts: 0\n")
+                    .as_bytes(),
+            )
+            .unwrap_err();
+        assert_eq!(err.code, ApiErrorCode::InvalidArgument);
+        assert_eq!(
+            err.details[0].field.as_deref(),
+            Some("operators.1.error_policy.retry.attempts")
+        );
+        assert_eq!(err.details[0].document, Some(1));
+        let err = BodyFormat::Json
+            .parse_resources(
+                serde_json::json!({
+                    "namespace": "default",
+                    "name": "job",
+                    "operators": { "2": { "chaining": "disabled" } },
+                })
+                .to_string()
+                .as_bytes(),
+            )
+            .unwrap_err();
+        assert_eq!(err.details[0].field.as_deref(), Some("operators.2"));
+        assert_eq!(err.details[0].document, None);
+        let err = BodyFormat::Json
+            .parse_resources(
+                serde_json::json!({
+                    "namespace": "default",
+                    "name": "job",
+                    "operators": { "2": { "error_policy": { "ignore": {} } } },
+                })
+                .to_string()
+                .as_bytes(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.details[0].field.as_deref(),
+            Some("operators.2.error_policy")
+        );
+
+        let err = BodyFormat::Json
+            .parse_resources(b"{\n  \"namespace\": \"default\"\n}")
+            .unwrap_err();
+        assert_eq!(err.code, ApiErrorCode::InvalidArgument);
+        assert!(err.message.contains("missing field `name`"));
+        assert_eq!(err.details[0].field, None);
+        assert_eq!(err.details[0].document, None);
+        assert_eq!(err.details[0].line, Some(3));
+        assert_eq!(err.details[0].column, Some(1));
+
+        assert_eq!(
+            BodyFormat::from_accept("text/html, application/yaml;q=0.9"),
+            BodyFormat::Yaml
+        );
+        assert_eq!(BodyFormat::from_accept("*/*"), BodyFormat::Json);
+        assert_eq!(
+            BodyFormat::from_media_type("application/json; charset=utf-8"),
+            Some(BodyFormat::Json)
+        );
+        assert_eq!(
+            BodyFormat::from_media_type("application/octet-stream"),
+            None
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_create_resources_coordinator_unavailable() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&dead_endpoint()),
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .configure(configure),
+        )
+        .await;
+
+        // each document has its own result
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/resources/create")
+                .insert_header(("Content-Type", "application/yaml"))
+                .insert_header(("Accept", "application/yaml"))
+                .set_payload(format!(
+                    "{DATAFLOW_YAML}---\nnamespace: default\nname: empty\n"
+                ))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "application/yaml"
+        );
+        let body: serde_yaml::Value = serde_yaml::from_slice(&test::read_body(resp).await).unwrap();
+        let results = body["results"].as_sequence().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["name"], "job");
+        assert_eq!(results[0]["error"]["code"], "unavailable");
+        assert_eq!(results[1]["name"], "empty");
+        assert_eq!(results[1]["error"]["code"], "invalid_argument");
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/resources/create")
+                .set_json(serde_json::json!({ "namespace": "default", "name": "job" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["results"][0]["error"]["code"], "invalid_argument");
+    }
+
+    #[actix_web::test]
+    async fn test_create_resources_async() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&dead_endpoint()),
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .configure(configure),
+        )
+        .await;
+        let resource = serde_json::json!({
+            "namespace": "default",
+            "name": "job",
+            "dataflow": {
+                "meta": [{ "center": 0, "neighbors": [] }],
+                "nodes": {
+                    "0": {
+                        "operator_id": 0,
+                        "details": { "mapper": { "value": { "func": { "function": "(a) => a" } } } }
+                    }
+                }
+            }
+        });
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/resources/create?async=true")
+                .set_json(&resource)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let operation: serde_json::Value = test::read_body_json(resp).await;
+        let id = operation["id"].as_str().unwrap().to_string();
+        assert_eq!(operation["namespaces"], serde_json::json!(["default"]));
+
+        // a duplicate submission is served by the pending operation
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/resources/create?async=true")
+                .set_json(&resource)
+                .to_request(),
+        )
+        .await;
+        let duplicate: serde_json::Value = test::read_body_json(resp).await;
+        if duplicate["status"] == "pending" {
+            assert_eq!(duplicate["id"], id.as_str());
+        }
+
+        let mut operation = serde_json::Value::Null;
+        for _ in 0..50 {
+            let resp = test::call_service(
+                &app,
+                test::TestRequest::get()
+                    .uri(&format!("/operations/{id}"))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            operation = test::read_body_json(resp).await;
+            if operation["status"] != "pending" {
+                break;
+            }
+            actix_web::rt::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        assert_eq!(operation["status"], "failed");
+        assert_eq!(operation["error"]["code"], "unavailable");
+        assert_eq!(
+            operation["result"]["results"][0]["error"]["code"],
+            "unavailable"
+        );
+
+        let resp = test::call_service(
+            &app,
+            with_request_id(test::TestRequest::get().uri("/operations/unknown")).to_request(),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::NOT_FOUND).await;
+        assert_eq!(body["code"], "not_found");
+
+        // the payload is validated before it's accepted
+        let resp = test::call_service(
+            &app,
+            with_request_id(
+                test::TestRequest::post()
+                    .uri("/resources/create?async=true")
+                    .insert_header(("Content-Type", "application/yaml"))
+                    .set_payload(DATAFLOW_YAML),
+            )
+            .to_request(),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::BAD_REQUEST).await;
+        assert_eq!(body["code"], "invalid_argument");
+        assert_eq!(body["details"][0]["field"], "dataflow");
+        assert_eq!(body["details"][0]["document"], 0);
+
+        let resp = test::call_service(
+            &app,
+            with_request_id(
+                test::TestRequest::post()
+                    .uri("/resources/create?async=true")
+                    .set_payload(vec![0u8]),
+            )
+            .to_request(),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::BAD_REQUEST).await;
+        assert_eq!(body["code"], "invalid_argument");
+    }
+
+    #[cfg(feature = "coordinator")]
+    #[actix_web::test]
+    async fn test_list_and_terminate_resources() {
+        use std::time::Duration;
+
+        use proto::coordinator::coordinator_api_server::CoordinatorApiServer;
+
+        use crate::coordinator::{api::CoordinatorApiImpl, coord::CoordinatorBuilder};
+
+        let port = 8807;
+        let builder: CoordinatorBuilder = serde_json::from_value(serde_json::json!({
+            "port": port,
+            "cluster": {
+                "nodes": format!("127.0.0.1:{port}"),
+                "rpc_timeout": 3,
+                "connect_timeout": 3
+            },
+            "storage": {
+                "Memory": {
+                    "ttl": null,
+                    "max_entries": null
+                }
+            },
+            "heartbeat": {
+                "period": 3,
+                "connect_timeout": 3,
+                "rpc_timeout": 3
+            },
+            "ack": {
+                "delay": 1,
+                "buf_size": 500,
+                "connect_timeout": 3,
+                "rpc_timeout": 3
+            }
+        }))
+        .unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(CoordinatorApiServer::new(CoordinatorApiImpl::new(
+                    builder.build(),
+                )))
+                .serve(format!("127.0.0.1:{port}").parse().unwrap()),
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&format!("127.0.0.1:{port}")),
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .configure(configure),
+        )
+        .await;
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/resources").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body, serde_json::json!({ "resources": [] }));
+        let page: ListResourcesResponse = serde_json::from_value(body).unwrap();
+        assert!(page.continue_token.is_none());
+
+        // an invalid continue token is a bad request
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/resources?continue=invalid")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let err: ApiError = test::read_body_json(resp).await;
+        assert_eq!(err.code, ApiErrorCode::InvalidArgument);
+        assert!(err.message.contains("invalid page token invalid"));
+
+        // so is an invalid label selector, which is rejected before the Coordinator is called
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/resources?labelSelector=team")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let err: ApiError = test::read_body_json(resp).await;
+        assert_eq!(err.code, ApiErrorCode::InvalidArgument);
+        assert_eq!(err.details[0].field.as_deref(), Some("labelSelector"));
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/resources?labelSelector=team%3Dpayments,env!%3Ddev")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // unknown resources are reported one by one
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/resources/terminate")
+                .set_json(serde_json::json!({
+                    "resources": [{"id": "unknown", "namespace": "default"}]
+                }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let result = &body["results"][0];
+        assert_eq!(result["id"], "unknown");
+        assert_eq!(result["namespace"], "default");
+        assert!(result.get("status").is_none());
+        assert_eq!(result["error"]["code"], "not_found");
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/resources/terminate")
+                .set_json(serde_json::json!({ "resources": [] }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // deleting a resource which doesn't exist is not found every time
+        for uri in [
+            "/resources/default/unknown",
+            "/resources/default/unknown?mode=force",
+        ] {
+            let resp =
+                test::call_service(&app, test::TestRequest::delete().uri(uri).to_request()).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+            let err: ApiError = test::read_body_json(resp).await;
+            assert_eq!(err.code, ApiErrorCode::NotFound);
+        }
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::delete()
+                .uri("/resources/default/unknown?mode=invalid")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        for uri in [
+            "/resources/default/unknown",
+            "/resources/default/unknown?view=spec",
+            "/resources/default/unknown?view=status",
+        ] {
+            let resp =
+                test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        }
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/resources/default/unknown?view=invalid")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_delete_response() {
+        let job_id = ResourceId::new("default", "job");
+        let body_json = |resp: actix_web::HttpResponse| -> serde_json::Value {
+            serde_json::from_slice(&resp.into_body().try_into_bytes().unwrap()).unwrap()
+        };
+
+        let resp = to_delete_response(&TerminateDataflowResult {
+            job_id: Some(job_id.clone()),
+            status: DataflowStatus::Closed as i32,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        assert_eq!(
+            body_json(resp),
+            serde_json::json!({"id": "job", "namespace": "default", "status": "closed"})
+        );
+
+        let resp = to_delete_response(&TerminateDataflowResult {
+            job_id: Some(job_id.clone()),
+            error: Some(ErrorDetail::from_status(&tonic::Status::aborted(
+                "subdataflows fail to stop",
+            ))),
+            worker_failures: vec![WorkerFailure {
+                node: Some(HostAddr {
+                    host: "10.0.0.1".to_string(),
+                    port: 8792,
+                }),
+                error: Some(ErrorDetail::from_status(&tonic::Status::unavailable(
+                    "connection refused",
+                ))),
+            }],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body = body_json(resp);
+        assert_eq!(body["worker_failures"][0]["node"], "10.0.0.1:8792");
+        assert_eq!(
+            body["worker_failures"][0]["error"],
+            serde_json::json!({
+                "code": "unavailable",
+                "message": "connection refused",
+                "details": []
+            })
+        );
+
+        let err = to_delete_response(&TerminateDataflowResult {
+            job_id: Some(job_id),
+            error: Some(ErrorDetail::from_status(&tonic::Status::not_found(
+                "not found dataflow",
+            ))),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert_eq!(err.code, ApiErrorCode::NotFound);
+    }
+
+    #[actix_web::test]
+    async fn test_resource_detail() {
+        let job_id = ResourceId::new("default", "job");
+        let dataflow = Dataflow::builder()
+            .job_id(&job_id.namespace_id, &job_id.resource_id)
+            .operator(OperatorInfo::source(0, Default::default()))
+            .operator(OperatorInfo::new(1, Details::Reducer(Default::default())))
+            .edge(0, 1)
+            .build_unvalidated();
+        let host_addr = HostAddr {
+            host: "10.0.0.1".to_string(),
+            port: 8792,
+        };
+        let status = DataflowRuntimeStatus {
+            summary: Some(DataflowSummary {
+                job_id: Some(job_id),
+                status: DataflowStatus::Running as i32,
+                operator_count: 2,
+                created_at: 1,
+                updated_at: 2,
+                labels: Default::default(),
+                resource_version: 3,
+                status_reason: String::new(),
+            }),
+            operators: vec![
+                OperatorRuntimeStatus {
+                    operator_id: 0,
+                    host_addr: Some(host_addr.clone()),
+                    status: Some(ExecutorStatus::Failed as i32),
+                    last_heartbeat_at: 3,
+                    restart_count: 2,
+                },
+                // the TaskManager of the reducer hasn't reported it
+                OperatorRuntimeStatus {
+                    operator_id: 1,
+                    host_addr: Some(host_addr),
+                    ..Default::default()
+                },
+            ],
+        };
+        let summary = serde_json::json!({
+            "id": "job",
+            "name": "job",
+            "namespace": "default",
+            "status": "running",
+            "operator_count": 2,
+            "created_at": 1,
+            "updated_at": 2,
+            "labels": {},
+            "resource_version": 3,
+        });
+        let with_operators = |operators: serde_json::Value| {
+            let mut detail = summary.clone();
+            detail["operators"] = operators;
+            detail
+        };
+        // none of the settings is submitted, so all of them are filled by the defaults
+        let resolved = serde_json::json!({ "chaining": "enabled", "error_policy": { "skip": {} } });
+
+        assert_eq!(
+            serde_json::to_value(ResourceDetail::new(&status, Some(&dataflow))).unwrap(),
+            with_operators(serde_json::json!([
+                {
+                    "id": 0,
+                    "kind": "source",
+                    "upstreams": [],
+                    "settings": {},
+                    "resolved": resolved,
+                    "status": "failed",
+                    "host": "10.0.0.1:8792",
+                    "last_heartbeat_at": 3,
+                    "restart_count": 2
+                },
+                {
+                    "id": 1,
+                    "kind": "reducer",
+                    "upstreams": [0],
+                    "settings": {},
+                    "resolved": resolved,
+                    "status": "pending",
+                    "host": "10.0.0.1:8792",
+                    "last_heartbeat_at": 0,
+                    "restart_count": 0
+                }
+            ]))
+        );
+
+        // the spec view has no status of the operators
+        let spec = DataflowRuntimeStatus {
+            operators: vec![],
+            ..status.clone()
+        };
+        assert_eq!(
+            serde_json::to_value(ResourceDetail::new(&spec, Some(&dataflow))).unwrap(),
+            with_operators(serde_json::json!([
+                {"id": 0, "kind": "source", "upstreams": [], "settings": {}, "resolved": resolved},
+                {"id": 1, "kind": "reducer", "upstreams": [0], "settings": {}, "resolved": resolved}
+            ]))
+        );
+
+        let detail = ResourceDetail::new(&status, None);
+        assert_eq!(detail.operators[0].kind, None);
+        assert_eq!(detail.operators[1].status.as_deref(), Some("pending"));
+        // the summary has the same fields as the one in the list
+        assert_eq!(
+            serde_json::to_value(&detail.summary).unwrap(),
+            serde_json::to_value(ResourceSummary::from(status.summary.as_ref().unwrap())).unwrap()
+        );
+    }
+
+    /// a coordinator whose dataflow `default/job` goes through the scripted states, one per poll of its operators.
+    /// The last state is kept, and the dataflow is deleted once a state is none. Created dataflows are recorded in order,
+    /// and they're the ones which are listed and terminated
+    #[derive(Default)]
+    struct MockCoordinator {
+        script: std::sync::Mutex<std::collections::VecDeque<Option<(DataflowRuntimeStatus, u64)>>>,
+        current: std::sync::Mutex<Option<(DataflowRuntimeStatus, u64)>>,
+        created: std::sync::Mutex<Vec<ResourceId>>,
+    }
+
+    impl MockCoordinator {
+        fn current(
+            &self,
+            job_id: Option<&ResourceId>,
+        ) -> Result<(DataflowRuntimeStatus, u64), tonic::Status> {
+            match job_id {
+                Some(job_id) if job_id.resource_id == "job" => self
+                    .current
+                    .lock()
+                    .unwrap()
+                    .clone()
+                    .ok_or_else(|| tonic::Status::not_found("job is deleted")),
+                _ => Err(tonic::Status::not_found("no job")),
+            }
+        }
+    }
+
+    #[tonic::async_trait]
+    impl CoordinatorApi for MockCoordinator {
+        async fn get_dataflow_status(
+            &self,
+            request: tonic::Request<GetDataflowStatusRequest>,
+        ) -> Result<tonic::Response<DataflowRuntimeStatus>, tonic::Status> {
+            let request = request.into_inner();
+            if request.with_operators {
+                let mut script = self.script.lock().unwrap();
+                if let Some(state) = script.pop_front() {
+                    *self.current.lock().unwrap() = state;
+                }
+            }
+            self.current(request.job_id.as_ref())
+                .map(|(status, _)| tonic::Response::new(status))
+        }
+
+        async fn get_dataflow(
+            &self,
+            request: tonic::Request<GetDataflowRequest>,
+        ) -> Result<tonic::Response<DataflowStates>, tonic::Status> {
+            let (_, checkpoints) = self.current(request.get_ref().job_id.as_ref())?;
+            Ok(tonic::Response::new(DataflowStates {
+                subdataflow_infos: vec![SubdataflowInfo {
+                    executors_info: HashMap::from_iter([(
+                        0,
+                        ExecutorInfo {
+                            metrics: HashMap::from_iter([(
+                                common::snapshot::CHECKPOINT_UPLOADED_METRIC.to_string(),
+                                checkpoints,
+                            )]),
+                            ..Default::default()
+                        },
+                    )]),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }))
+        }
+
+        async fn create_dataflow(
+            &self,
+            request: tonic::Request<Dataflow>,
+        ) -> Result<tonic::Response<Response>, tonic::Status> {
+            self.created
+                .lock()
+                .unwrap()
+                .push(request.get_ref().get_job_id());
+            Ok(tonic::Response::new(Response::ok()))
+        }
+
+        async fn terminate_dataflow(
+            &self,
+            _: tonic::Request<ResourceId>,
+        ) -> Result<tonic::Response<Response>, tonic::Status> {
+            Err(tonic::Status::unimplemented("terminate_dataflow"))
+        }
+
+        async fn receive_ack(
+            &self,
+            _: tonic::Request<Ack>,
+        ) -> Result<tonic::Response<Response>, tonic::Status> {
+            Err(tonic::Status::unimplemented("receive_ack"))
+        }
+
+        async fn receive_heartbeat(
+            &self,
+            _: tonic::Request<Heartbeat>,
+        ) -> Result<tonic::Response<Response>, tonic::Status> {
+            Err(tonic::Status::unimplemented("receive_heartbeat"))
+        }
+
+        async fn report_operator_error(
+            &self,
+            _: tonic::Request<OperatorError>,
+        ) -> Result<tonic::Response<Response>, tonic::Status> {
+            Err(tonic::Status::unimplemented("report_operator_error"))
+        }
+
+        async fn get_cluster_topology(
+            &self,
+            _: tonic::Request<GetClusterTopologyRequest>,
+        ) -> Result<tonic::Response<ClusterTopology>, tonic::Status> {
+            Err(tonic::Status::unimplemented("get_cluster_topology"))
+        }
+
+        async fn trigger_savepoint(
+            &self,
+            _: tonic::Request<ResourceId>,
+        ) -> Result<tonic::Response<Savepoint>, tonic::Status> {
+            Err(tonic::Status::unimplemented("trigger_savepoint"))
+        }
+
+        async fn list_savepoints(
+            &self,
+            _: tonic::Request<ResourceId>,
+        ) -> Result<tonic::Response<ListSavepointsResponse>, tonic::Status> {
+            Err(tonic::Status::unimplemented("list_savepoints"))
+        }
+
+        async fn delete_savepoint(
+            &self,
+            _: tonic::Request<DeleteSavepointRequest>,
+        ) -> Result<tonic::Response<Response>, tonic::Status> {
+            Err(tonic::Status::unimplemented("delete_savepoint"))
+        }
+
+        async fn list_dataflows(
+            &self,
+            request: tonic::Request<ListDataflowsRequest>,
+        ) -> Result<tonic::Response<ListDataflowsResponse>, tonic::Status> {
+            let namespace = &request.get_ref().namespace;
+            Ok(tonic::Response::new(ListDataflowsResponse {
+                dataflows: self
+                    .created
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|job_id| namespace.is_empty() || &job_id.namespace_id == namespace)
+                    .map(|job_id| DataflowSummary {
+                        job_id: Some(job_id.clone()),
+                        status: DataflowStatus::Running as i32,
+                        ..Default::default()
+                    })
+                    .collect(),
+                next_page_token: String::new(),
+            }))
+        }
+
+        async fn terminate_dataflows(
+            &self,
+            request: tonic::Request<TerminateDataflowsRequest>,
+        ) -> Result<tonic::Response<TerminateDataflowsResponse>, tonic::Status> {
+            let created = self.created.lock().unwrap();
+            Ok(tonic::Response::new(TerminateDataflowsResponse {
+                results: request
+                    .into_inner()
+                    .job_ids
+                    .into_iter()
+                    .map(|job_id| {
+                        if created.contains(&job_id) {
+                            TerminateDataflowResult {
+                                job_id: Some(job_id),
+                                status: DataflowStatus::Closed as i32,
+                                ..Default::default()
+                            }
+                        } else {
+                            TerminateDataflowResult {
+                                job_id: Some(job_id),
+                                error: Some(ErrorDetail::from_status(&tonic::Status::not_found(
+                                    "no job",
+                                ))),
+                                ..Default::default()
+                            }
+                        }
+                    })
+                    .collect(),
+            }))
+        }
+
+        /// only the recreation is supported, and the dataflow takes the next version once it's updated
+        async fn update_dataflow(
+            &self,
+            request: tonic::Request<UpdateDataflowRequest>,
+        ) -> Result<tonic::Response<UpdateDataflowResponse>, tonic::Status> {
+            let request = request.into_inner();
+            let dataflow = request.dataflow.clone().unwrap_or_default();
+            let (mut status, checkpoints) = self.current(dataflow.job_id.as_ref())?;
+            if request.strategy() != UpdateStrategy::Recreate {
+                return Err(tonic::Status::unimplemented("rolling update"));
+            }
+            let summary = status.summary.get_or_insert_with(Default::default);
+            if summary.resource_version != request.resource_version {
+                return Err(tonic::Status::aborted("stale resource version"));
+            }
+            summary.resource_version += 1;
+            summary.operator_count = dataflow.nodes.len() as u32;
+            let summary = summary.clone();
+            *self.current.lock().unwrap() = Some((status, checkpoints));
+
+            let mut restarted_operator_ids = dataflow.nodes.keys().cloned().collect::<Vec<_>>();
+            restarted_operator_ids.sort();
+            Ok(tonic::Response::new(UpdateDataflowResponse {
+                summary: Some(summary),
+                restarted_operator_ids,
+                placement: None,
+                warnings: vec!["states of operator 2 are dropped".to_string()],
+            }))
+        }
+
+        async fn add_node(
+            &self,
+            _: tonic::Request<AddNodeRequest>,
+        ) -> Result<tonic::Response<AddNodeResponse>, tonic::Status> {
+            Err(tonic::Status::unimplemented("add_node"))
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_create_resources_in_dependency_order() {
+        use proto::coordinator::coordinator_api_server::CoordinatorApiServer;
+
+        let coordinator = Arc::new(MockCoordinator::default());
+        let port = 8830;
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(CoordinatorApiServer::from_arc(coordinator.clone()))
+                .serve(format!("127.0.0.1:{port}").parse().unwrap()),
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&format!("127.0.0.1:{port}")),
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .configure(configure),
+        )
+        .await;
+        // report depends on ingest, which is defined after it
+        let body = format!(
+            "{}  depends_on:\n    - namespace_id: default\n      resource_id: ingest\n---\n{}",
+            DATAFLOW_YAML.replace("name: job", "name: report"),
+            DATAFLOW_YAML.replace("name: job", "name: ingest"),
+        );
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/resources/create")
+                .insert_header(("Content-Type", "application/yaml"))
+                .set_payload(body)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        // the results are in the order of the documents
+        assert_eq!(
+            body["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|result| result["name"].as_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["report", "ingest"]
+        );
+        assert_eq!(
+            coordinator
+                .created
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|job_id| job_id.resource_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["ingest", "report"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_batch_resources() {
+        use proto::coordinator::coordinator_api_server::CoordinatorApiServer;
+
+        let coordinator = Arc::new(MockCoordinator {
+            current: std::sync::Mutex::new(Some((
+                DataflowRuntimeStatus {
+                    summary: Some(DataflowSummary {
+                        resource_version: 7,
+                        ..Default::default()
+                    }),
+                    operators: vec![],
+                },
+                0,
+            ))),
+            ..Default::default()
+        });
+        let port = 8840;
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(CoordinatorApiServer::from_arc(coordinator.clone()))
+                .serve(format!("127.0.0.1:{port}").parse().unwrap()),
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&format!("127.0.0.1:{port}")),
+                )))
+                .app_data(web::Data::new(BatchConfig {
+                    max_resources: 4,
+                    concurrency: 2,
+                }))
+                .configure(configure),
+        )
+        .await;
+        let named = |name: &str| VALID_DATAFLOW_YAML.replace("name: job", &format!("name: {name}"));
+        let versioned =
+            |version: u64| format!("{VALID_DATAFLOW_YAML}resource_version: {version}\n");
+        let batch = |query: &str, content_type: &str, body: String| {
+            with_request_id(
+                test::TestRequest::post()
+                    .uri(&format!("/resources:batch{query}"))
+                    .insert_header(("Content-Type", content_type))
+                    .set_payload(body),
+            )
+            .to_request()
+        };
+        let outcomes = |body: &serde_json::Value| {
+            body["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|result| {
+                    (
+                        result["name"].as_str().unwrap().to_string(),
+                        result["outcome"].as_str().unwrap().to_string(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let created = || {
+            coordinator
+                .created
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|job_id| job_id.resource_id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // report depends on ingest, job is updated, and empty has no dataflow
+        let resources = BodyFormat::Yaml
+            .parse_resources(
+                format!(
+                    "{}  depends_on:\n    - namespace_id: default\n      resource_id: ingest\n---\n{}---\n{}---\nnamespace: default\nname: empty\n",
+                    named("report"),
+                    named("ingest"),
+                    versioned(7),
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+
+        // none of them is applied if any of them is invalid in an atomic batch
+        let resp = test::call_service(
+            &app,
+            batch(
+                "?atomic=true&strategy=recreate",
+                "application/json",
+                serde_json::to_string(&resources).unwrap(),
+            ),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::BAD_REQUEST).await;
+        assert_eq!(body["details"][0]["field"], "dataflow");
+        assert_eq!(body["details"][0]["document"], 3);
+        assert!(created().is_empty());
+
+        let resp = test::call_service(
+            &app,
+            batch(
+                "?strategy=recreate",
+                "application/json",
+                serde_json::to_string(&resources).unwrap(),
+            ),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["status"], "partial");
+        // the results are in the order of the resources, while the dependencies are created first
+        assert_eq!(
+            outcomes(&body),
+            [
+                ("report", "created"),
+                ("ingest", "created"),
+                ("job", "updated"),
+                ("empty", "failed"),
+            ]
+            .map(|(name, outcome)| (name.to_string(), outcome.to_string()))
+        );
+        assert_eq!(body["results"][0]["status"], "starting");
+        assert_eq!(body["results"][2]["resource_version"], 8);
+        assert_eq!(body["results"][3]["error"]["code"], "invalid_argument");
+        assert!(body["results"][3]["error"]["requestId"].is_null());
+        assert_eq!(created(), vec!["ingest", "report"]);
+
+        // the failure of a resource to be applied is its own, e.g. the stale version of job
+        let resp = test::call_service(
+            &app,
+            batch(
+                "?strategy=recreate",
+                "application/yaml",
+                format!("{}---\n{}", versioned(7), named("other")),
+            ),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["status"], "partial");
+        assert_eq!(body["results"][0]["outcome"], "failed");
+        assert_eq!(body["results"][0]["error"]["code"], "aborted");
+        assert_eq!(body["results"][1]["outcome"], "created");
+
+        // a duplicate resource fails on its own, and the batch fails if none of them is applied
+        let resp = test::call_service(
+            &app,
+            batch(
+                "",
+                "application/yaml",
+                format!(
+                    "namespace: default\nname: empty\n---\n{}---\n{}",
+                    versioned(1),
+                    versioned(1)
+                ),
+            ),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["status"], "failed");
+        assert_eq!(body["results"][2]["error"]["details"][0]["field"], "name");
+
+        // an invalid config of an operator is detailed by its field
+        let resp = test::call_service(
+            &app,
+            batch(
+                "",
+                "application/yaml",
+                VALID_DATAFLOW_YAML.replace("(a) => a", " "),
+            ),
+        )
+        .await;
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["results"][0]["error"]["details"][0]["field"],
+            "dataflow.nodes.0.details.mapper.value.func.function"
+        );
+
+        let resp = test::call_service(
+            &app,
+            batch(
+                "",
+                "application/yaml",
+                vec![VALID_DATAFLOW_YAML; 5].join("---\n"),
+            ),
+        )
+        .await;
+        read_error(resp, StatusCode::BAD_REQUEST).await;
+        let resp = test::call_service(&app, batch("", "text/plain", String::new())).await;
+        read_error(resp, StatusCode::BAD_REQUEST).await;
+    }
+
+    #[actix_web::test]
+    async fn test_coordinator_regions() {
+        use proto::coordinator::coordinator_api_server::CoordinatorApiServer;
+
+        use crate::apiserver::config::{CoordinatorConfig, RouteConfig};
+
+        let eu = Arc::new(MockCoordinator::default());
+        let us = Arc::new(MockCoordinator::default());
+        for (coordinator, port) in [(&eu, 8833), (&us, 8834)] {
+            tokio::spawn(
+                tonic::transport::Server::builder()
+                    .add_service(CoordinatorApiServer::from_arc(coordinator.clone()))
+                    .serve(format!("127.0.0.1:{port}").parse().unwrap()),
+            );
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        // the coordinator of region ap is down
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from_config(
+                    &CoordinatorConfig {
+                        regions: BTreeMap::from([
+                            ("ap".to_string(), vec![dead_endpoint()]),
+                            ("eu".to_string(), vec!["127.0.0.1:8833".to_string()]),
+                            ("us".to_string(), vec!["127.0.0.1:8834".to_string()]),
+                        ]),
+                        routes: ["ap", "eu"]
+                            .into_iter()
+                            .map(|region| RouteConfig {
+                                prefix: format!("{region}-"),
+                                region: region.to_string(),
+                            })
+                            .collect(),
+                        default_region: Some("us".to_string()),
+                        ..Default::default()
+                    },
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .configure(configure),
+        )
+        .await;
+
+        // the resources are created by the coordinators of their namespaces
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/resources/create")
+                .insert_header(("Content-Type", "application/yaml"))
+                .set_payload(format!(
+                    "{}---\n{}",
+                    DATAFLOW_YAML.replace("namespace: default", "namespace: eu-payments"),
+                    DATAFLOW_YAML.replace("namespace: default", "namespace: orders"),
+                ))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let namespaces = |coordinator: &MockCoordinator| {
+            coordinator
+                .created
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|job_id| job_id.namespace_id.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(namespaces(&eu), vec!["eu-payments"]);
+        assert_eq!(namespaces(&us), vec!["orders"]);
+
+        // a fanned-out listing merges the live coordinators and reports the others
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/resources?fanOut=true")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let page: ListResourcesResponse = test::read_body_json(resp).await;
+        assert_eq!(
+            page.resources
+                .iter()
+                .map(|resource| (
+                    resource.namespace.as_str(),
+                    resource.coordinator.as_deref().unwrap()
+                ))
+                .collect::<Vec<_>>(),
+            vec![("eu-payments", "eu"), ("orders", "us")]
+        );
+        assert_eq!(page.unavailable, vec!["ap"]);
+        assert!(page.continue_token.is_none());
+
+        // a listing of a namespace is served by its coordinator only
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/resources?namespace=orders")
+                .to_request(),
+        )
+        .await;
+        let page: ListResourcesResponse = test::read_body_json(resp).await;
+        assert_eq!(page.resources.len(), 1);
+        assert_eq!(page.resources[0].coordinator.as_deref(), Some("us"));
+
+        // the error of the coordinator which is down names its region
+        let resp = test::call_service(
+            &app,
+            with_request_id(test::TestRequest::get().uri("/resources?namespace=ap-sales"))
+                .to_request(),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::SERVICE_UNAVAILABLE).await;
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("coordinator of region ap is unavailable"));
+
+        // a batch across the regions fails the resources of the coordinator which is down only
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/resources/terminate")
+                .set_json(serde_json::json!({
+                    "resources": [
+                        {"id": "job", "namespace": "ap-sales"},
+                        {"id": "job", "namespace": "orders"},
+                        {"id": "job", "namespace": "eu-payments"}
+                    ]
+                }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| result["namespace"].as_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["ap-sales", "orders", "eu-payments"]
+        );
+        assert_eq!(results[0]["error"]["code"], "unavailable");
+        assert_eq!(results[1]["status"], "closed");
+        assert_eq!(results[2]["status"], "closed");
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/coordinators").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body.as_array()
+                .unwrap()
+                .iter()
+                .map(|health| (
+                    health["name"].as_str().unwrap(),
+                    health["available"].as_bool()
+                ))
+                .collect::<Vec<_>>(),
+            vec![("ap", Some(false)), ("eu", Some(true)), ("us", Some(true))]
+        );
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/cluster?coordinator=unknown")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_stale_reads() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+        use proto::coordinator::coordinator_api_server::CoordinatorApiServer;
+
+        use crate::apiserver::{config::CoordinatorConfig, middleware::STALE_HEADER};
+
+        let coordinator = MockCoordinator {
+            current: std::sync::Mutex::new(Some((
+                DataflowRuntimeStatus {
+                    summary: Some(DataflowSummary {
+                        job_id: Some(ResourceId::new("default", "job")),
+                        status: DataflowStatus::Running as i32,
+                        operator_count: 2,
+                        ..Default::default()
+                    }),
+                    operators: vec![],
+                },
+                0,
+            ))),
+            ..Default::default()
+        };
+        // the coordinator flaps: it rejects every request while it's down
+        let down = Arc::new(AtomicBool::new(false));
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let port = 8831;
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(CoordinatorApiServer::with_interceptor(coordinator, {
+                    let down = down.clone();
+                    let attempts = attempts.clone();
+                    move |request: tonic::Request<()>| {
+                        attempts.fetch_add(1, Ordering::SeqCst);
+                        if down.load(Ordering::SeqCst) {
+                            Err(tonic::Status::unavailable("the coordinator is restarting"))
+                        } else {
+                            Ok(request)
+                        }
+                    }
+                }))
+                .serve(format!("127.0.0.1:{port}").parse().unwrap()),
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::from_config(
+                        &serde_json::from_value::<CoordinatorConfig>(serde_json::json!({
+                            "endpoints": [format!("127.0.0.1:{port}")],
+                            "retries": 2,
+                            "retry_backoff": {"base": 10, "max": 10, "jitter": false},
+                            "read_cache_ttl": 1
+                        }))
+                        .unwrap(),
+                    ),
+                )))
+                .configure(configure),
+        )
+        .await;
+        let get = |uri: &str| with_request_id(test::TestRequest::get().uri(uri)).to_request();
+
+        let resp = test::call_service(&app, get("/resources/default/job")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(STALE_HEADER).is_none());
+        let live = test::read_body(resp).await;
+
+        // the reads are served from the cache within the staleness window, and the others are retried before they fail
+        down.store(true, Ordering::SeqCst);
+        let resp = test::call_service(&app, get("/resources/default/job")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(STALE_HEADER).unwrap(), "true");
+        assert_eq!(test::read_body(resp).await, live);
+        attempts.store(0, Ordering::SeqCst);
+        let resp = test::call_service(&app, get("/resources/default/other")).await;
+        read_error(resp, StatusCode::SERVICE_UNAVAILABLE).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // the writes fail fast without any retry
+        attempts.store(0, Ordering::SeqCst);
+        let resp = test::call_service(
+            &app,
+            with_request_id(test::TestRequest::delete().uri("/resources/default/job")).to_request(),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::SERVICE_UNAVAILABLE).await;
+        assert_eq!(body["code"], "unavailable");
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        // the cache expires after the window
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let resp = test::call_service(&app, get("/resources/default/job")).await;
+        read_error(resp, StatusCode::SERVICE_UNAVAILABLE).await;
+
+        down.store(false, Ordering::SeqCst);
+        let resp = test::call_service(&app, get("/resources/default/job")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(STALE_HEADER).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_update_resource() {
+        use proto::coordinator::coordinator_api_server::CoordinatorApiServer;
+
+        let coordinator = MockCoordinator {
+            current: std::sync::Mutex::new(Some((
+                DataflowRuntimeStatus {
+                    summary: Some(DataflowSummary {
+                        job_id: Some(ResourceId::new("default", "job")),
+                        status: DataflowStatus::Running as i32,
+                        operator_count: 3,
+                        resource_version: 7,
+                        ..Default::default()
+                    }),
+                    operators: vec![],
+                },
+                0,
+            ))),
+            ..Default::default()
+        };
+        let port = 8829;
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(CoordinatorApiServer::new(coordinator))
+                .serve(format!("127.0.0.1:{port}").parse().unwrap()),
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&format!("127.0.0.1:{port}")),
+                )))
+                .configure(configure),
+        )
+        .await;
+        let put = |uri: &str, body: String| {
+            with_request_id(
+                test::TestRequest::put()
+                    .uri(uri)
+                    .insert_header(("Content-Type", "application/yaml"))
+                    .set_payload(body),
+            )
+            .to_request()
+        };
+        let versioned = |version: u64| format!("{DATAFLOW_YAML}resource_version: {version}\n");
+
+        // the name of a resource can't be changed
+        let resp = test::call_service(
+            &app,
+            put("/resources/default/renamed?strategy=recreate", versioned(7)),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::UNPROCESSABLE_ENTITY).await;
+        assert_eq!(body["code"], "failed_precondition");
+        assert_eq!(body["details"][0]["field"], "name");
+
+        // the resource version is required
+        let resp = test::call_service(
+            &app,
+            put(
+                "/resources/default/job?strategy=recreate",
+                DATAFLOW_YAML.to_string(),
+            ),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::BAD_REQUEST).await;
+        assert_eq!(body["details"][0]["field"], "resource_version");
+
+        // the rolling update is not supported yet
+        let resp = test::call_service(&app, put("/resources/default/job", versioned(7))).await;
+        let body = read_error(resp, StatusCode::NOT_IMPLEMENTED).await;
+        assert_eq!(body["code"], "unimplemented");
+
+        let resp = test::call_service(
+            &app,
+            put("/resources/default/unknown?strategy=recreate", {
+                DATAFLOW_YAML.replace("name: job", "name: unknown") + "resource_version: 7\n"
+            }),
+        )
+        .await;
+        read_error(resp, StatusCode::NOT_FOUND).await;
+
+        let resp = test::call_service(
+            &app,
+            put("/resources/default/job?strategy=recreate", versioned(6)),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::CONFLICT).await;
+        assert_eq!(body["code"], "aborted");
+
+        let resp = test::call_service(
+            &app,
+            put("/resources/default/job?strategy=recreate", versioned(7)),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["name"], "job");
+        assert_eq!(body["resource_version"], 8);
+        assert_eq!(body["operator_count"], 2);
+        assert_eq!(body["restarted_operators"], serde_json::json!([0, 1]));
+        assert_eq!(
+            body["warnings"],
+            serde_json::json!(["states of operator 2 are dropped"])
+        );
+
+        // the version which is updated is stale now
+        let resp = test::call_service(
+            &app,
+            put("/resources/default/job?strategy=recreate", versioned(7)),
+        )
+        .await;
+        read_error(resp, StatusCode::CONFLICT).await;
+    }
+
+    #[actix_web::test]
+    async fn test_resource_events() {
+        use proto::coordinator::coordinator_api_server::CoordinatorApiServer;
+
+        use crate::apiserver::{config::EventsConfig, events::EventHub};
+
+        let state = |status: DataflowStatus,
+                     executor: Option<ExecutorStatus>,
+                     host: &str,
+                     restart_count: u32,
+                     checkpoints: u64| {
+            Some((
+                DataflowRuntimeStatus {
+                    summary: Some(DataflowSummary {
+                        job_id: Some(ResourceId::new("default", "job")),
+                        status: status as i32,
+                        operator_count: 1,
+                        ..Default::default()
+                    }),
+                    operators: vec![OperatorRuntimeStatus {
+                        operator_id: 0,
+                        host_addr: Some(HostAddr {
+                            host: host.to_string(),
+                            port: 8792,
+                        }),
+                        status: executor.map(|status| status as i32),
+                        last_heartbeat_at: 0,
+                        restart_count,
+                    }],
+                },
+                checkpoints,
+            ))
+        };
+        let pending = state(DataflowStatus::Pending, None, "tm-0", 0, 0);
+        let running = state(
+            DataflowStatus::Running,
+            Some(ExecutorStatus::Running),
+            "tm-0",
+            0,
+            1,
+        );
+        let failed_over = state(
+            DataflowStatus::Running,
+            Some(ExecutorStatus::Running),
+            "tm-1",
+            1,
+            1,
+        );
+        let coordinator = MockCoordinator {
+            current: std::sync::Mutex::new(pending.clone()),
+            // the same state is polled twice, so the stream is idle for a heartbeat
+            script: std::sync::Mutex::new(
+                [pending, running.clone(), running, failed_over, None].into(),
+            ),
+            ..Default::default()
+        };
+        let port = 8822;
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(CoordinatorApiServer::new(coordinator))
+                .serve(format!("127.0.0.1:{port}").parse().unwrap()),
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&format!("127.0.0.1:{port}")),
+                )))
+                .app_data(web::Data::new(EventHub::new(&EventsConfig {
+                    poll_interval: 1,
+                    heartbeat: 1,
+                    buffer: 16,
+                })))
+                .configure(configure),
+        )
+        .await;
+
+        let resp = test::call_service(
+            &app,
+            with_request_id(test::TestRequest::get().uri("/resources/default/unknown/events"))
+                .to_request(),
+        )
+        .await;
+        let body = read_error(resp, StatusCode::NOT_FOUND).await;
+        assert_eq!(body["code"], "not_found");
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/resources/default/job/events")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "text/event-stream"
+        );
+        // the stream ends once the dataflow is deleted
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        let events = body
+            .split("\n\n")
+            .filter(|frame| !frame.is_empty() && !frame.starts_with(':'))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                "event: status\ndata: {\"type\":\"status\",\"from\":\"pending\",\"to\":\"running\"}",
+                "event: operator\ndata: {\"type\":\"operator\",\"operatorId\":0,\"from\":\"pending\",\"to\":\"running\"}",
+                "event: checkpoint\ndata: {\"type\":\"checkpoint\",\"completed\":1,\"total\":1}",
+                "event: failover\ndata: {\"type\":\"failover\",\"operatorId\":0,\"from\":\"tm-0:8792\",\"to\":\"tm-1:8792\",\"restartCount\":1}",
+                "event: deleted\ndata: {\"type\":\"deleted\"}",
+            ]
+        );
+        assert!(body.contains(": heartbeat\n\n"));
+    }
+
+    /// the routes of the handlers in this file, `(method, path)` as they're declared by the route macros
+    fn declared_routes() -> Vec<(String, String)> {
+        let source = include_str!("resources.rs");
+        let handlers = source.split("#[cfg(test)]").next().unwrap();
+        handlers
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                ["get", "post", "put", "delete"].iter().find_map(|method| {
+                    line.strip_prefix(&format!("#[{method}(\""))
+                        .and_then(|rest| rest.strip_suffix("\")]"))
+                        .map(|path| (method.to_string(), path.to_string()))
+                })
+            })
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_openapi_covers_routes() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(MetricsRegistry::default()))
+                .service(super::swagger_ui)
+                .service(super::prometheus_metrics)
+                .configure(configure),
+        )
+        .await;
+
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/openapi.json").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let document: serde_json::Value = test::read_body_json(resp).await;
+        assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+        let paths = document["paths"].as_object().unwrap();
+
+        let routes = declared_routes();
+        assert!(routes.len() >= 13);
+        // every route is documented, either in the scope of the resources or at the root
+        routes.iter().for_each(|(method, path)| {
+            assert!(
+                [format!("{RESOURCES_HANDLER_ROOT}{path}"), path.clone()]
+                    .iter()
+                    .any(|path| paths
+                        .get(path)
+                        .map(|item| item[method.as_str()].is_object())
+                        .unwrap_or_default()),
+                "{} {} is not documented",
+                method,
+                path
+            )
+        });
+
+        // every documented endpoint is served by a declared route
+        for (path, item) in paths {
+            for method in item.as_object().unwrap().keys() {
+                assert!(
+                    routes.iter().any(|(declared_method, declared_path)| {
+                        declared_method == method
+                            && (path == declared_path
+                                || *path == format!("{RESOURCES_HANDLER_ROOT}{declared_path}"))
+                    }),
+                    "{} {} is not declared",
+                    method,
+                    path
+                );
+
+                let uri = path
+                    .split('/')
+                    .map(|segment| {
+                        if segment.starts_with('{') {
+                            "x"
+                        } else {
+                            segment
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let req = test::TestRequest::default()
+                    .method(method.to_uppercase().parse().unwrap())
+                    .uri(&uri)
+                    .to_request();
+                let resp = test::call_service(&app, req).await;
+                if resp.status() == StatusCode::NOT_FOUND {
+                    let body: serde_json::Value = test::read_body_json(resp).await;
+                    assert!(
+                        !body["message"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .starts_with("no endpoint"),
+                        "{} {} is not routed",
+                        method,
+                        path
+                    );
+                }
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_swagger_ui() {
+        // the Swagger UI is not served unless it's enabled
+        let app = test::init_service(App::new().configure(configure)).await;
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/docs").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let app =
+            test::init_service(App::new().service(super::swagger_ui).configure(configure)).await;
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/docs").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "text/html; charset=utf-8"
+        );
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains("url: \"/openapi.json\""));
+    }
+
+    #[actix_web::test]
+    async fn test_request_metrics() {
+        let registry = Arc::new(MetricsRegistry::default());
+        let app = test::init_service(
+            App::new()
+                .wrap(Authentication::new(None))
+                .wrap(AccessLog::new(registry.clone()))
+                .wrap(RequestId)
+                .app_data(web::Data::from(registry.clone()))
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&dead_endpoint()),
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .service(super::prometheus_metrics)
+                .configure(configure),
+        )
+        .await;
+
+        for req in [
+            test::TestRequest::get().uri("/health"),
+            test::TestRequest::get().uri("/health"),
+            test::TestRequest::get().uri("/unknown/path"),
+            test::TestRequest::delete().uri("/unknown/path"),
+            test::TestRequest::get().uri("/resources/default/job"),
+        ] {
+            test::call_service(&app, req.to_request()).await;
+        }
+
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        // the scrape itself is in flight
+        assert!(body.contains("# TYPE lightflus_apiserver_requests_in_flight gauge\nlightflus_apiserver_requests_in_flight 1\n"));
+        assert!(body.contains("# TYPE lightflus_apiserver_request_duration_seconds histogram\n"));
+        for (labels, count) in [
+            (r#"route="/health",method="GET",status="2xx""#, 2),
+            // unknown paths share the same route
+            (r#"route="unmatched",method="GET",status="4xx""#, 1),
+            (r#"route="unmatched",method="DELETE",status="4xx""#, 1),
+            (
+                r#"route="/resources/{namespace}/{name}",method="GET",status="5xx""#,
+                1,
+            ),
+        ] {
+            assert!(
+                body.contains(&format!(
+                    "lightflus_apiserver_request_duration_seconds_count{{{labels}}} {count}\n"
+                )),
+                "{labels} is not counted in {body}"
+            );
+            assert!(body.contains(&format!(
+                "lightflus_apiserver_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {count}\n"
+            )));
+        }
+        assert!(!body.contains("/unknown/path"));
+        // the scrape is recorded once it's responded
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(body.contains(
+            r#"lightflus_apiserver_request_duration_seconds_count{route="/metrics",method="GET",status="2xx"} 1"#
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_audit_log() {
+        let dir = std::env::temp_dir().join(format!("lightflus-audit-{}", common::utils::uuid()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tokens.json");
+        std::fs::write(
+            &path,
+            serde_json::json!({
+                "tokens": [
+                    {"token": "reader", "identity": "alice", "role": "read"},
+                    {"token": "writer", "identity": "bob", "role": "write"},
+                    {"token": "admin", "identity": "carol", "role": "admin"}
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let audit_path = dir.join("audit").join("audit.jsonl");
+        let audit_log = Arc::new(
+            AuditLog::new(
+                &AuditConfig {
+                    file: Some(AuditFileConfig {
+                        path: audit_path.to_string_lossy().to_string(),
+                        max_size: 1024 * 1024,
+                        max_files: 1,
+                    }),
+                    ..Default::default()
+                },
+                &MetricsRegistry::default(),
+            )
+            .unwrap(),
+        );
+        let app = test::init_service(
+            App::new()
+                .wrap(Authentication::new(Some(Arc::new(
+                    TokenStore::load(&path).unwrap(),
+                ))))
+                .wrap(Audit::new(audit_log.clone()))
+                .wrap(RequestId)
+                .app_data(web::Data::from(audit_log))
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&dead_endpoint()),
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .configure(configure),
+        )
+        .await;
+
+        let body = serde_json::json!({
+            "resources": [{"id": "job", "namespace": "default"}]
+        })
+        .to_string();
+        for (req, token, status) in [
+            (
+                test::TestRequest::delete().uri("/resources/default/job"),
+                "writer",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                test::TestRequest::post()
+                    .uri("/resources/terminate")
+                    .insert_header(("Content-Type", "application/json"))
+                    .set_payload(body.clone()),
+                "writer",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            // the reads are not audited
+            (
+                test::TestRequest::get().uri("/resources/default/job"),
+                "writer",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            // the rejected mutations are audited as well
+            (
+                test::TestRequest::put().uri("/resources/default/job"),
+                "reader",
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                test::TestRequest::delete().uri("/resources/default/job"),
+                "unknown",
+                StatusCode::UNAUTHORIZED,
+            ),
+        ] {
+            let resp = test::call_service(
+                &app,
+                with_request_id(req.insert_header(("Authorization", format!("Bearer {token}"))))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), status);
+        }
+
+        // the tail requires the admin role
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/audit")
+                .insert_header(("Authorization", "Bearer writer"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/audit")
+                .insert_header(("Authorization", "Bearer admin"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let tail: AuditTail = test::read_body_json(resp).await;
+        let summaries = tail
+            .records
+            .iter()
+            .map(|record| {
+                (
+                    record.identity.as_str(),
+                    record.verb.as_str(),
+                    record.path.as_str(),
+                    record.status,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summaries,
+            vec![
+                ("bob", "delete", "/resources/default/job", 503),
+                ("bob", "terminate", "/resources/terminate", 503),
+                ("alice", "update", "/resources/default/job", 403),
+                ("anonymous", "delete", "/resources/default/job", 401),
+            ]
+        );
+        assert!(tail
+            .records
+            .iter()
+            .all(|record| record.request_id == REQUEST_ID));
+        assert_eq!(tail.records[0].namespace.as_deref(), Some("default"));
+        assert_eq!(tail.records[0].name.as_deref(), Some("job"));
+        // the hash of an empty body
+        assert_eq!(
+            tail.records[0].body_sha256,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(tail.records[1].namespace, None);
+        let digest = <sha2::Sha256 as sha2::Digest>::digest(body.as_bytes());
+        assert_eq!(
+            tail.records[1].body_sha256,
+            digest
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        );
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/audit?limit=1")
+                .insert_header(("Authorization", "Bearer admin"))
+                .to_request(),
+        )
+        .await;
+        let latest: AuditTail = test::read_body_json(resp).await;
+        assert_eq!(latest.records, tail.records[3..]);
+
+        // the records are appended to the file in the background
+        let mut records = vec![];
+        for _ in 0..50 {
+            records = std::fs::read_to_string(&audit_path)
+                .unwrap_or_default()
+                .lines()
+                .map(|line| serde_json::from_str::<AuditRecord>(line).unwrap())
+                .collect::<Vec<_>>();
+            if records.len() == tail.records.len() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(records, tail.records);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_cors() {
+        let dir = std::env::temp_dir().join(format!("lightflus-cors-{}", common::utils::uuid()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tokens.json");
+        std::fs::write(
+            &path,
+            serde_json::json!({
+                "tokens": [{"token": "writer", "identity": "bob", "role": "write"}]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        const CONSOLE: &str = "https://console.lightflus.io";
+        let new_app = |cors: CorsConfig| {
+            App::new()
+                .wrap(RateLimit::new(
+                    &LimitsConfig::default(),
+                    &MetricsRegistry::default(),
+                ))
+                .wrap(Authentication::new(Some(Arc::new(
+                    TokenStore::load(&path).unwrap(),
+                ))))
+                .wrap(Cors::new(&cors))
+                .wrap(RequestId)
+                .app_data(web::Data::new(CoordinatorRouter::from(
+                    CoordinatorGateway::new(&dead_endpoint()),
+                )))
+                .app_data(web::Data::new(OperationStore::new(&Default::default())))
+                .configure(configure)
+        };
+        let app = test::init_service(new_app(CorsConfig {
+            allowed_origins: vec![CONSOLE.to_string()],
+            allowed_methods: vec!["GET".to_string(), "DELETE".to_string()],
+            allowed_headers: vec!["Authorization".to_string()],
+            allow_credentials: true,
+            max_age: Some(600),
+        }))
+        .await;
+        let header = |resp: &ServiceResponse<_>, name: &str| {
+            resp.headers()
+                .get(name)
+                .map(|value| value.to_str().unwrap().to_string())
+        };
+
+        // the preflight requests are answered without the tokens
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::default()
+                .method(actix_web::http::Method::OPTIONS)
+                .uri("/resources/default/job")
+                .insert_header(("Origin", CONSOLE))
+                .insert_header(("Access-Control-Request-Method", "DELETE"))
+                .insert_header(("Access-Control-Request-Headers", "authorization"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            header(&resp, "Access-Control-Allow-Origin").as_deref(),
+            Some(CONSOLE)
+        );
+        assert_eq!(
+            header(&resp, "Access-Control-Allow-Methods").as_deref(),
+            Some("GET, DELETE")
+        );
+        assert_eq!(
+            header(&resp, "Access-Control-Allow-Headers").as_deref(),
+            Some("authorization")
+        );
+        assert_eq!(
+            header(&resp, "Access-Control-Allow-Credentials").as_deref(),
+            Some("true")
+        );
+        assert_eq!(
+            header(&resp, "Access-Control-Max-Age").as_deref(),
+            Some("600")
+        );
+        assert_eq!(header(&resp, "Vary").as_deref(), Some("Origin"));
+
+        // the disallowed origins, methods and headers are rejected rather than served without the CORS headers
+        for (origin, method, headers, message) in [
+            (
+                "https://evil.io",
+                "GET",
+                "",
+                "origin https://evil.io is not allowed",
+            ),
+            (
+                CONSOLE,
+                "PUT",
+                "",
+                "method PUT is not allowed for origin https://console.lightflus.io",
+            ),
+            (
+                CONSOLE,
+                "GET",
+                "Authorization, X-Custom",
+                "header X-Custom is not allowed for origin https://console.lightflus.io",
+            ),
+        ] {
+            let mut req = test::TestRequest::default()
+                .method(actix_web::http::Method::OPTIONS)
+                .uri("/resources/default/job")
+                .insert_header(("Origin", origin))
+                .insert_header(("Access-Control-Request-Method", method));
+            if !headers.is_empty() {
+                req = req.insert_header(("Access-Control-Request-Headers", headers));
+            }
+            let resp = test::call_service(&app, with_request_id(req).to_request()).await;
+            assert_eq!(header(&resp, "Access-Control-Allow-Origin"), None);
+            let body = read_error(resp, StatusCode::FORBIDDEN).await;
+            assert_eq!(body["code"], "permission_denied");
+            assert_eq!(body["message"], message);
+        }
+        let resp = test::call_service(
+            &app,
+            with_request_id(
+                test::TestRequest::get()
+                    .uri("/resources/default/job")
+                    .insert_header(("Origin", "https://evil.io"))
+                    .insert_header(("Authorization", "Bearer writer")),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(
+            read_error(resp, StatusCode::FORBIDDEN).await["message"],
+            "origin https://evil.io is not allowed"
+        );
+
+        // the requests from the allowed origins are authenticated, and their responses carry the CORS headers, even the errors
+        for (token, status) in [
+            (None, StatusCode::UNAUTHORIZED),
+            (Some("Bearer writer"), StatusCode::SERVICE_UNAVAILABLE),
+        ] {
+            let mut req = test::TestRequest::get()
+                .uri("/resources/default/job")
+                .insert_header(("Origin", CONSOLE));
+            if let Some(token) = token {
+                req = req.insert_header(("Authorization", token));
+            }
+            let resp = test::call_service(&app, with_request_id(req).to_request()).await;
+            assert_eq!(
+                header(&resp, "Access-Control-Allow-Origin").as_deref(),
+                Some(CONSOLE)
+            );
+            assert_eq!(
+                header(&resp, "Access-Control-Allow-Credentials").as_deref(),
+                Some("true")
+            );
+            assert_eq!(
+                header(&resp, "Access-Control-Expose-Headers").as_deref(),
+                Some("x-request-id, x-lightflus-stale, retry-after")
+            );
+            read_error(resp, status).await;
+        }
+
+        // any origin is allowed by the wildcard, and the requests without an origin are served as they are
+        let app = test::init_service(new_app(CorsConfig {
+            allowed_origins: vec!["*".to_string()],
+            ..Default::default()
+        }))
+        .await;
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/health")
+                .insert_header(("Origin", "https://any.io"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            header(&resp, "Access-Control-Allow-Origin").as_deref(),
+            Some("*")
+        );
+        assert_eq!(header(&resp, "Vary"), None);
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::default()
+                .method(actix_web::http::Method::OPTIONS)
+                .uri("/resources/default/job")
+                .insert_header(("Origin", "https://any.io"))
+                .insert_header(("Access-Control-Request-Method", "PUT"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            header(&resp, "Access-Control-Allow-Methods").as_deref(),
+            Some("GET, HEAD, POST, PUT, DELETE")
+        );
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/health").to_request()).await;
+        assert_eq!(header(&resp, "Access-Control-Allow-Origin"), None);
+
+        // CORS is disabled by default, the preflight requests are authenticated like the others
+        let app = test::init_service(new_app(CorsConfig::default())).await;
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::default()
+                .method(actix_web::http::Method::OPTIONS)
+                .uri("/resources/default/job")
+                .insert_header(("Origin", CONSOLE))
+                .insert_header(("Access-Control-Request-Method", "GET"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(header(&resp, "Access-Control-Allow-Origin"), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_readiness() {
+        let draining = web::Data::new(Draining::default());
+        let app =
+            test::init_service(App::new().app_data(draining.clone()).configure(configure)).await;
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/ready").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // it's unavailable once the server is shutting down, while the server is still alive
+        draining.start();
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/ready").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: ApiError = test::read_body_json(resp).await;
+        assert_eq!(body.code, ApiErrorCode::Unavailable);
+        let resp =
+            test::call_service(&app, test::TestRequest::get().uri("/health").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    /// the status of a probe and the names of its failed checks
+    async fn read_probe<B: MessageBody>(resp: ServiceResponse<B>) -> (StatusCode, Vec<String>) {
+        let status = resp.status();
+        let report: ProbeReport = test::read_body_json(resp).await;
+        assert_eq!(report.status == CheckStatus::Ok, status == StatusCode::OK);
+        let failed = report
+            .checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Failed)
+            .map(|check| check.name.clone())
+            .collect();
+        (status, failed)
+    }
+
+    #[actix_web::test]
+    async fn test_probes() {
+        use proto::coordinator::coordinator_api_server::CoordinatorApiServer;
+
+        let port = 8841;
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(CoordinatorApiServer::new(MockCoordinator::default()))
+                .serve(format!("127.0.0.1:{port}").parse().unwrap()),
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let dir = std::env::temp_dir().join(format!("lightflus-probes-{}", common::utils::uuid()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let token_file = dir.join("tokens.json");
+        std::fs::write(&token_file, r#"{"tokens": []}"#).unwrap();
+        let config: ApiServerConfig = serde_json::from_value(serde_json::json!({
+            "coordinator": {
+                "endpoints": [format!("127.0.0.1:{port}")],
+                "regions": {"eu": [dead_endpoint()]}
+            },
+            "auth": {"token_file": token_file}
+        }))
+        .unwrap();
+        let prober = web::Data::new(Prober::new(
+            &config,
+            web::Data::new(CoordinatorRouter::from_config(&config.coordinator)),
+        ));
+        let draining = web::Data::new(Draining::default());
+        let app = test::init_service(
+            App::new()
+                .app_data(prober.clone())
+                .app_data(draining.clone())
+                .configure(configure),
+        )
+        .await;
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        // it's not ready until the coordinators are probed, while it's alive
+        let resp = test::call_service(&app, get("/readyz")).await;
+        assert_eq!(
+            read_probe(resp).await,
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                vec!["coordinators".to_string()]
+            )
+        );
+        let resp = test::call_service(&app, get("/healthz")).await;
+        assert_eq!(read_probe(resp).await, (StatusCode::OK, vec![]));
+
+        // one reachable coordinator is enough, the unreachable one is named by the check.
+        // The mock rejects the probes, which still proves it's reachable
+        prober.probe().await;
+        let resp = test::call_service(&app, get("/readyz")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let report: ProbeReport = test::read_body_json(resp).await;
+        assert_eq!(
+            report
+                .checks
+                .iter()
+                .map(|check| check.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["shutdown", "coordinators", "token_file", "config"]
+        );
+        assert!(report.checks[1]
+            .message
+            .as_ref()
+            .unwrap()
+            .starts_with("unreachable: eu: "));
+        assert!(report.checks[1].checked_at.is_some());
+
+        // the token file is gone, which invalidates the config as well
+        std::fs::remove_file(&token_file).unwrap();
+        prober.probe().await;
+        let resp = test::call_service(&app, get("/readyz")).await;
+        assert_eq!(
+            read_probe(resp).await,
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                vec!["token_file".to_string(), "config".to_string()]
+            )
+        );
+
+        // it's not ready once the server is shutting down, while it's still alive
+        std::fs::write(&token_file, r#"{"tokens": []}"#).unwrap();
+        prober.probe().await;
+        draining.start();
+        let resp = test::call_service(&app, get("/readyz")).await;
+        assert_eq!(
+            read_probe(resp).await,
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                vec!["shutdown".to_string()]
+            )
+        );
+        let resp = test::call_service(&app, get("/healthz")).await;
+        assert_eq!(read_probe(resp).await, (StatusCode::OK, vec![]));
+
+        // no coordinator is reachable
+        let mut config = ApiServerConfig::default();
+        config.coordinator.endpoints = vec![dead_endpoint()];
+        let prober = Prober::new(
+            &config,
+            web::Data::new(CoordinatorRouter::from_config(&config.coordinator)),
+        );
+        prober.probe().await;
+        let report = prober.readiness(&Draining::default());
+        assert_eq!(report.status, CheckStatus::Failed);
+        assert!(report.checks[1]
+            .message
+            .as_ref()
+            .unwrap()
+            .starts_with("no coordinator is reachable: default: "));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,80 +1,918 @@
-use actix_web::{
-    error::{ErrorBadRequest, ErrorInternalServerError},
-    HttpResponse,
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
 };
+
+use actix_web::{http::header, web, HttpRequest, HttpResponse, HttpResponseBuilder};
 use common::utils::pb_to_bytes_mut;
+use futures_util::StreamExt;
 use proto::{
     apiserver::{
         CreateResourceRequest, CreateResourceResponse, GetResourceResponse, Resource,
         ResourceStatusEnum, ResourceTypeEnum,
     },
-    coordinator::{coordinator_api_client::CoordinatorApiClient, GetDataflowRequest},
+    common::{DataflowStates, ResourceId},
+    common_impl::{order_by_dependencies, DataflowValidateError},
+    coordinator::{
+        DataflowRuntimeStatus, GetClusterTopologyRequest, GetDataflowRequest,
+        GetDataflowStatusRequest, ListDataflowsRequest, TerminateDataflowResult,
+        TerminateDataflowsRequest, UpdateDataflowRequest,
+    },
 };
 
-use crate::{apiserver::types::GetResourceArgs, errors::apiserver::ApiError};
+use crate::{
+    apiserver::{
+        auth::Caller,
+        config::BatchConfig,
+        events::EventHub,
+        graph::{GraphFormat, ResourceGraph},
+        operations::OperationStore,
+        types::{
+            BatchResourceResult, BatchResourcesQuery, BatchResourcesResponse, BodyFormat,
+            CreateResourceResult, CreateResourcesResponse, GetResourceArgs, ListResourcesArgs,
+            ListResourcesResponse, ResourceDefinition, ResourceDetail, ResourcePathArgs,
+            ResourceRef, ResourceView, TerminateMode, TerminateResourceResult,
+            TerminateResourcesRequest, TerminateResourcesResponse, UpdateResourceResponse,
+            UpdateStrategy,
+        },
+    },
+    errors::apiserver::{ApiError, ApiErrorCode, ApiErrorDetail},
+};
+
+use super::coordinator::{CoordinatorGateway, CoordinatorRouter, ReadKey};
+
+/// the format of the body of the request by its `Content-Type`. It's none if the body is neither JSON nor YAML
+pub(crate) fn content_format(req: &HttpRequest) -> Option<BodyFormat> {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(BodyFormat::from_media_type)
+}
 
-use super::COORDINATOR_URI_ENV;
+/// the format of the response body by the `Accept` header of the request, JSON by default
+pub(crate) fn accepted_format(req: &HttpRequest) -> BodyFormat {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(BodyFormat::from_accept)
+        .unwrap_or_default()
+}
 
+pub(crate) fn respond<T: serde::Serialize>(
+    mut builder: HttpResponseBuilder,
+    format: BodyFormat,
+    body: &T,
+) -> Result<HttpResponse, ApiError> {
+    match format {
+        BodyFormat::Json => Ok(builder.json(body)),
+        BodyFormat::Yaml => serde_yaml::to_string(body)
+            .map(|yaml| builder.content_type("application/yaml").body(yaml))
+            .map_err(ApiError::internal),
+    }
+}
+
+/// the dataflow is created in the namespace of its job id, by the coordinator serving the namespace
 pub(crate) async fn create_dataflow(
+    coordinators: &CoordinatorRouter,
+    caller: &Caller,
     req: CreateResourceRequest,
-) -> Result<CreateResourceResponse, actix_web::Error> {
-    let req = req.clone();
-
+) -> Result<CreateResourceResponse, ApiError> {
     if req.is_dataflow_empty() {
-        return Err(ErrorBadRequest("empty dataflow"));
+        return Err(ApiError::invalid_argument("empty dataflow")
+            .with_detail(ApiErrorDetail::new("dataflow is required").with_field("dataflow")));
     }
+    let dataflow = req.get_dataflow();
+    let namespace = dataflow
+        .job_id
+        .as_ref()
+        .map(|job_id| job_id.namespace_id.as_str())
+        .unwrap_or(req.namespace.as_str());
+    caller.authorize_namespace(namespace)?;
 
-    let uri = common::utils::get_env(COORDINATOR_URI_ENV);
-    let ref mut cli = CoordinatorApiClient::connect(uri.unwrap_or_default()).await;
+    coordinators
+        .route(namespace)
+        .call(|mut client| {
+            let request = caller.new_request(dataflow.clone());
+            async move { client.create_dataflow(request).await }
+        })
+        .await
+        .map_err(ApiError::from)
+        .map(|_| {
+            let mut response = CreateResourceResponse::default();
+            response.set_status(ResourceStatusEnum::Starting);
+            response
+        })
+}
 
-    match cli {
-        Ok(client) => {
-            let result = client
-                .create_dataflow(tonic::Request::new(req.get_dataflow()))
-                .await;
-            result
-                .map_err(|err| ErrorInternalServerError(ApiError::from(err)))
-                .map(|_| {
-                    let mut response = CreateResourceResponse::default();
-                    response.set_status(ResourceStatusEnum::Starting);
-                    response
+/// each resource is created on its own, so the failure of one doesn't stop the others.
+/// 201 if all of them are created, otherwise 200 with the errors of the failed ones.
+/// None of them is created if the caller is not allowed to access the namespace of any of them
+pub(crate) async fn create_resources(
+    coordinators: &CoordinatorRouter,
+    caller: &Caller,
+    resources: &[ResourceDefinition],
+    format: BodyFormat,
+) -> Result<HttpResponse, ApiError> {
+    authorize_resources(caller, resources)?;
+    let response = create_each_resource(coordinators, caller, resources).await;
+    let builder = if response.results.iter().all(|result| result.error.is_none()) {
+        HttpResponse::Created()
+    } else {
+        HttpResponse::Ok()
+    };
+    respond(builder, format, &response)
+}
+
+/// the resources are validated and authorized, then they're created in the background by an [`Operation`](crate::apiserver::operations::Operation) like [`create_resources`].
+/// 202 with the pending operation, which is the existing one if the same resources are being created by the same caller
+pub(crate) async fn submit_resources(
+    coordinators: web::Data<CoordinatorRouter>,
+    operations: web::Data<OperationStore>,
+    caller: Caller,
+    resources: Vec<ResourceDefinition>,
+    format: BodyFormat,
+) -> Result<HttpResponse, ApiError> {
+    authorize_resources(&caller, &resources)?;
+    resources
+        .iter()
+        .enumerate()
+        .try_for_each(|(index, resource)| {
+            resource
+                .to_create_resource_request()
+                .get_dataflow()
+                .validate()
+                .map_err(|err| {
+                    ApiError::invalid_argument(format!(
+                        "invalid dataflow {}/{}",
+                        resource.namespace, resource.name
+                    ))
+                    .with_detail(invalid_dataflow_detail(&err).with_document(index))
                 })
-        }
-        Err(err) => Err(ErrorInternalServerError(ApiError::from(err))),
+        })?;
+
+    let key = format!(
+        "{}:{}",
+        caller.get_identity(),
+        serde_json::to_string(&resources).map_err(ApiError::internal)?
+    );
+    let namespaces = resources
+        .iter()
+        .map(|resource| resource.namespace.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let (operation, created) = operations.submit(key, namespaces)?;
+    if created {
+        let id = operation.id.clone();
+        actix_web::rt::spawn(async move {
+            let response = create_each_resource(&coordinators, &caller, &resources).await;
+            operations.complete(&id, response);
+        });
     }
+    respond(HttpResponse::Accepted(), format, &operation)
+}
+
+/// the operation is not found if it's expired, or if the caller is not allowed to access it
+pub(crate) fn get_operation(
+    operations: &OperationStore,
+    caller: &Caller,
+    id: &str,
+    format: BodyFormat,
+) -> Result<HttpResponse, ApiError> {
+    let operation = operations
+        .get(id)
+        .ok_or_else(|| ApiError::new(ApiErrorCode::NotFound, format!("no operation {id}")))?;
+    operation.authorize(caller)?;
+    respond(HttpResponse::Ok(), format, &operation)
 }
 
-pub(crate) async fn get_dataflow(args: &GetResourceArgs) -> actix_web::Result<HttpResponse> {
+fn authorize_resources(caller: &Caller, resources: &[ResourceDefinition]) -> Result<(), ApiError> {
+    if resources.is_empty() {
+        return Err(ApiError::invalid_argument("no resource to create"));
+    }
+    resources
+        .iter()
+        .try_for_each(|resource| caller.authorize_namespace(&resource.namespace))
+}
+
+/// the resources are created in the order of their dependencies, so a dataflow is created after the ones it depends on in the same batch.
+/// The results are still in the order of the resources
+async fn create_each_resource(
+    coordinators: &CoordinatorRouter,
+    caller: &Caller,
+    resources: &[ResourceDefinition],
+) -> CreateResourcesResponse {
+    let requests = resources
+        .iter()
+        .map(|resource| resource.to_create_resource_request())
+        .collect::<Vec<_>>();
+    let dataflows = requests
+        .iter()
+        .map(|request| request.get_dataflow())
+        .collect::<Vec<_>>();
+    let mut results = resources.iter().map(|_| None).collect::<Vec<_>>();
+    for index in order_by_dependencies(&dataflows) {
+        let result = create_dataflow(coordinators, caller, requests[index].clone()).await;
+        results[index] = Some(CreateResourceResult::new(&resources[index], result));
+    }
+    CreateResourcesResponse {
+        results: results.into_iter().flatten().collect(),
+    }
+}
+
+pub(crate) async fn get_dataflow(
+    coordinator: &CoordinatorGateway,
+    caller: &Caller,
+    args: &GetResourceArgs,
+) -> Result<HttpResponse, ApiError> {
+    caller.authorize_namespace(&args.namespace)?;
     let mut resp = HttpResponse::Ok();
-    let uri = common::utils::get_env(COORDINATOR_URI_ENV).unwrap();
-    let mut cli = CoordinatorApiClient::connect(uri).await;
+    read_dataflow(coordinator, caller, &args.to_resource_id())
+        .await
+        .and_then(|states| {
+            let mut response = GetResourceResponse::default();
+            let mut resource = Resource::default();
+            match states.graph {
+                Some(dataflow) => {
+                    resource.resource_id = dataflow.job_id.clone();
+                    resource.set_resource_type(ResourceTypeEnum::Dataflow);
+                    response.resource = Some(resource);
+                    Ok(response)
+                }
+                None => Err(ApiError::internal("empty graph response")),
+            }
+        })
+        .map(|response| resp.body(pb_to_bytes_mut(response)))
+}
+
+/// the summary of the dataflow merged with the spec and the runtime status of its operators, depending on the view.
+/// The TaskManagers are not asked for the status in the `spec` view
+pub(crate) async fn get_dataflow_detail(
+    coordinator: &CoordinatorGateway,
+    caller: &Caller,
+    args: &ResourcePathArgs,
+    view: ResourceView,
+    format: BodyFormat,
+) -> Result<HttpResponse, ApiError> {
+    caller.authorize_namespace(&args.namespace)?;
+    let job_id = args.to_resource_id();
+    let status = read_status(coordinator, caller, &job_id, view.with_status()).await?;
+    let dataflow = if view.with_spec() {
+        read_dataflow(coordinator, caller, &job_id).await?.graph
+    } else {
+        None
+    };
+    respond(
+        HttpResponse::Ok(),
+        format,
+        &ResourceDetail::new(&status, dataflow.as_ref()),
+    )
+}
+
+/// the runtime status of the dataflow, which is served from the cache while the coordinators are unavailable
+async fn read_status(
+    coordinator: &CoordinatorGateway,
+    caller: &Caller,
+    job_id: &ResourceId,
+    with_operators: bool,
+) -> Result<DataflowRuntimeStatus, ApiError> {
+    let req = GetDataflowStatusRequest {
+        job_id: Some(job_id.clone()),
+        with_operators,
+    };
+    coordinator
+        .read_cached(
+            ReadKey::new("get_dataflow_status", caller, &req),
+            |mut client| {
+                let request = caller.new_request(req.clone());
+                async move { client.get_dataflow_status(request).await }
+            },
+        )
+        .await
+        .map_err(ApiError::from)
+}
+
+/// the spec of the dataflow, which is served from the cache while the coordinators are unavailable
+async fn read_dataflow(
+    coordinator: &CoordinatorGateway,
+    caller: &Caller,
+    job_id: &ResourceId,
+) -> Result<DataflowStates, ApiError> {
+    let req = GetDataflowRequest {
+        job_id: Some(job_id.clone()),
+        effective: false,
+        include_metrics: false,
+    };
+    coordinator
+        .read_cached(ReadKey::new("get_dataflow", caller, &req), |mut client| {
+            let request = caller.new_request(req.clone());
+            async move { client.get_dataflow(request).await }
+        })
+        .await
+        .map_err(ApiError::from)
+}
+
+/// the graph of the operators of the dataflow with their runtime status, in the node-link JSON or a DOT document
+pub(crate) async fn get_dataflow_graph(
+    coordinator: &CoordinatorGateway,
+    caller: &Caller,
+    args: &ResourcePathArgs,
+    format: GraphFormat,
+) -> Result<HttpResponse, ApiError> {
+    caller.authorize_namespace(&args.namespace)?;
+    let job_id = args.to_resource_id();
+    let status = read_status(coordinator, caller, &job_id, true).await?;
+    let dataflow = read_dataflow(coordinator, caller, &job_id)
+        .await?
+        .graph
+        .ok_or_else(|| ApiError::internal("empty graph response"))?;
+
+    let graph = ResourceGraph::new(&status, &dataflow);
+    match format {
+        GraphFormat::Json => Ok(HttpResponse::Ok().json(graph)),
+        GraphFormat::Dot => Ok(HttpResponse::Ok()
+            .content_type("text/vnd.graphviz; charset=utf-8")
+            .body(graph.to_dot())),
+    }
+}
+
+/// stream the status events of the dataflow, see [`EventHub`]. The dataflow must exist when the stream starts
+pub(crate) async fn watch_dataflow(
+    coordinator: web::Data<CoordinatorGateway>,
+    events: web::Data<EventHub>,
+    caller: Caller,
+    args: &ResourcePathArgs,
+) -> Result<HttpResponse, ApiError> {
+    caller.authorize_namespace(&args.namespace)?;
+    let job_id = args.to_resource_id();
+    coordinator
+        .read(|mut client| {
+            let request = caller.new_request(GetDataflowStatusRequest {
+                job_id: Some(job_id.clone()),
+                with_operators: false,
+            });
+            async move { client.get_dataflow_status(request).await }
+        })
+        .await
+        .map_err(ApiError::from)?;
+
+    let receiver = EventHub::subscribe(events.clone(), coordinator, caller, job_id);
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events.to_sse_stream(receiver)))
+}
+
+pub(crate) async fn get_cluster_topology(
+    coordinator: &CoordinatorGateway,
+    caller: &Caller,
+) -> Result<HttpResponse, ApiError> {
+    let req = GetClusterTopologyRequest::default();
+    coordinator
+        .read_cached(
+            ReadKey::new("get_cluster_topology", caller, &req),
+            |mut client| {
+                let request = caller.new_request(req.clone());
+                async move { client.get_cluster_topology(request).await }
+            },
+        )
+        .await
+        .map_err(ApiError::from)
+        .map(|topology| HttpResponse::Ok().body(pb_to_bytes_mut(topology)))
+}
+
+/// a caller restricted to some namespaces must list the dataflows of one of them.
+/// The label selector is applied by the coordinator before the page is cut.
+/// The dataflows of a namespace are listed by the coordinator serving it, and the ones of all namespaces are listed by the default coordinator,
+/// or by all of the coordinators if the query `fanOut` is true, see [`list_all_coordinators`]
+pub(crate) async fn list_dataflows(
+    coordinators: &CoordinatorRouter,
+    caller: &Caller,
+    args: &ListResourcesArgs,
+    format: BodyFormat,
+) -> Result<HttpResponse, ApiError> {
+    match &args.namespace {
+        Some(namespace) => caller.authorize_namespace(namespace)?,
+        None => caller.authorize_all_namespaces()?,
+    }
+    // the selector is evaluated by the coordinator, it's parsed here so that an invalid one is reported with the field
+    args.get_label_selector()?;
+    let response = match &args.namespace {
+        Some(namespace) => {
+            list_coordinator(
+                coordinators.route(namespace),
+                caller,
+                args.to_list_dataflows_request(),
+            )
+            .await?
+        }
+        None if args.fan_out => list_all_coordinators(coordinators, caller, args).await?,
+        None => {
+            list_coordinator(
+                coordinators.get_default(),
+                caller,
+                args.to_list_dataflows_request(),
+            )
+            .await?
+        }
+    };
+    respond(HttpResponse::Ok(), format, &response)
+}
+
+/// a page of the dataflows of a coordinator, which are marked by its region if the regions are configured
+async fn list_coordinator(
+    coordinator: &CoordinatorGateway,
+    caller: &Caller,
+    req: ListDataflowsRequest,
+) -> Result<ListResourcesResponse, ApiError> {
+    coordinator
+        .read_cached(
+            ReadKey::new("list_dataflows", caller, &req),
+            |mut client| {
+                let request = caller.new_request(req.clone());
+                async move { client.list_dataflows(request).await }
+            },
+        )
+        .await
+        .map_err(ApiError::from)
+        .map(|response| {
+            ListResourcesResponse::from(response).with_coordinator(coordinator.get_region())
+        })
+}
+
+/// the dataflows of all of the coordinators, which are marked by the names of their coordinators. Each coordinator fills a page of its own,
+/// and the `continue` token carries the page tokens of the coordinators which have more pages.
+/// The coordinators which are unavailable are listed in `unavailable` and they're skipped by the following pages. It fails if all of them are unavailable
+async fn list_all_coordinators(
+    coordinators: &CoordinatorRouter,
+    caller: &Caller,
+    args: &ListResourcesArgs,
+) -> Result<ListResourcesResponse, ApiError> {
+    let tokens = match args.continue_token.as_deref() {
+        Some(token) => decode_fan_out_token(token)?,
+        None => coordinators
+            .all()
+            .map(|(name, _)| (name.to_string(), String::new()))
+            .collect(),
+    };
+    let mut pages = vec![];
+    for (name, token) in tokens {
+        let coordinator = coordinators
+            .get(&name)
+            .ok_or_else(|| invalid_continue_token(format!("unknown coordinator {name:?}")))?;
+        let req = ListDataflowsRequest {
+            page_token: token,
+            ..args.to_list_dataflows_request()
+        };
+        pages.push(async move { (name, list_coordinator(coordinator, caller, req).await) });
+    }
+    let count = pages.len();
 
-    match cli
-        .as_mut()
-        .map_err(|err| ErrorInternalServerError(ApiError::from(err)))
+    let mut response = ListResourcesResponse::default();
+    let mut next_tokens = BTreeMap::new();
+    let mut outage = None;
+    for (name, page) in futures_util::future::join_all(pages).await {
+        match page {
+            Ok(page) => {
+                if let Some(token) = page.continue_token.as_ref() {
+                    next_tokens.insert(name.clone(), token.clone());
+                }
+                response
+                    .resources
+                    .extend(page.with_coordinator(Some(&name)).resources);
+            }
+            Err(err)
+                if matches!(
+                    err.code,
+                    ApiErrorCode::Unavailable | ApiErrorCode::DeadlineExceeded
+                ) =>
+            {
+                tracing::warn!(
+                    "skip the dataflows of coordinator {}: {}",
+                    name,
+                    err.message
+                );
+                response.unavailable.push(name);
+                outage = Some(err);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    match outage {
+        Some(err) if response.unavailable.len() == count => Err(err),
+        _ => {
+            response.continue_token = Some(next_tokens)
+                .filter(|tokens| !tokens.is_empty())
+                .map(|tokens| encode_fan_out_token(&tokens));
+            Ok(response)
+        }
+    }
+}
+
+/// the `continue` token of a fanned-out listing is the hex-encoded JSON of the page tokens by the names of the coordinators
+fn encode_fan_out_token(tokens: &BTreeMap<String, String>) -> String {
+    serde_json::to_vec(tokens)
+        .unwrap_or_default()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn decode_fan_out_token(token: &str) -> Result<BTreeMap<String, String>, ApiError> {
+    (0..token.len())
+        .step_by(2)
+        .map(|index| {
+            token
+                .get(index..index + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect::<Option<Vec<_>>>()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(|| {
+            invalid_continue_token("it's not a token of a fanned-out listing".to_string())
+        })
+}
+
+fn invalid_continue_token(message: String) -> ApiError {
+    ApiError::invalid_argument("invalid continue token")
+        .with_detail(ApiErrorDetail::new(message).with_field("continue"))
+}
+
+/// replace the dataflow with the one defined in the body, which must be the only resource of it.
+/// The namespace and the name of a resource can't be changed, and the update must be based on its current resource version.
+/// 200 with the operators restarted by the coordinator, 409 if the resource version is stale
+pub(crate) async fn update_dataflow(
+    coordinator: &CoordinatorGateway,
+    caller: &Caller,
+    args: &ResourcePathArgs,
+    resources: &[ResourceDefinition],
+    strategy: UpdateStrategy,
+    format: BodyFormat,
+) -> Result<HttpResponse, ApiError> {
+    caller.authorize_namespace(&args.namespace)?;
+    let resource = match resources {
+        [resource] => resource,
+        _ => {
+            return Err(ApiError::invalid_argument(format!(
+                "exactly one resource is required, {} are given",
+                resources.len()
+            )))
+        }
+    };
+    [
+        ("namespace", &resource.namespace, &args.namespace),
+        ("name", &resource.name, &args.name),
+    ]
+    .into_iter()
+    .try_for_each(|(field, given, current)| {
+        if given == current {
+            Ok(())
+        } else {
+            Err(ApiError::new(
+                ApiErrorCode::FailedPrecondition,
+                format!(
+                    "{field} of resource {}/{} can't be changed",
+                    args.namespace, args.name
+                ),
+            )
+            .with_detail(
+                ApiErrorDetail::new(format!("{given:?} is given instead of {current:?}"))
+                    .with_field(field),
+            ))
+        }
+    })?;
+    update_resource_dataflow(coordinator, caller, resource, strategy)
+        .await
+        .and_then(|response| respond(HttpResponse::Ok(), format, &response))
+}
+
+/// the dataflow of the resource is replaced if its resource version is the current one
+async fn update_resource_dataflow(
+    coordinator: &CoordinatorGateway,
+    caller: &Caller,
+    resource: &ResourceDefinition,
+    strategy: UpdateStrategy,
+) -> Result<UpdateResourceResponse, ApiError> {
+    let resource_version = resource.resource_version.ok_or_else(|| {
+        ApiError::invalid_argument("no resource version").with_detail(
+            ApiErrorDetail::new("the resource version of the updated resource is required")
+                .with_field("resource_version"),
+        )
+    })?;
+    let request = resource.to_create_resource_request();
+    if request.is_dataflow_empty() {
+        return Err(ApiError::invalid_argument("empty dataflow")
+            .with_detail(ApiErrorDetail::new("dataflow is required").with_field("dataflow")));
+    }
+
+    // the existing dataflow is checked first, so that updating a deleted one responds 404 instead of a conflict
+    let job_id = resource.to_resource_id();
+    coordinator
+        .call(|mut client| {
+            let request = caller.new_request(GetDataflowRequest {
+                job_id: Some(job_id.clone()),
+                effective: false,
+                include_metrics: false,
+            });
+            async move { client.get_dataflow(request).await }
+        })
+        .await
+        .map_err(ApiError::from)?;
+    let dataflow = request.get_dataflow();
+    coordinator
+        .call(|mut client| {
+            let mut update = UpdateDataflowRequest {
+                dataflow: Some(dataflow.clone()),
+                resource_version,
+                ..Default::default()
+            };
+            update.set_strategy(strategy.to_dataflow_update_strategy());
+            let request = caller.new_request(update);
+            async move { client.update_dataflow(request).await }
+        })
+        .await
+        .map(UpdateResourceResponse::from)
+        .map_err(ApiError::from)
+}
+
+/// The resources are validated and authorized up front, then the valid ones are applied by the coordinators serving their namespaces:
+/// a resource with a resource version replaces the existing dataflow like `PUT /resources/{namespace}/{name}`, the others are created.
+/// A dataflow is applied after the ones it depends on in the same batch, and at most [`BatchConfig::concurrency`] of them are applied at the same time.
+///
+/// 200 with the result of each resource in the order of the resources, including the ones failing to be validated or applied.
+/// None of them is applied if the query `atomic` is true and any of them is invalid, which is responded as the error of the batch
+pub(crate) async fn apply_resources(
+    coordinators: &CoordinatorRouter,
+    config: &BatchConfig,
+    caller: &Caller,
+    resources: &[ResourceDefinition],
+    query: &BatchResourcesQuery,
+    format: BodyFormat,
+) -> Result<HttpResponse, ApiError> {
+    if resources.is_empty() {
+        return Err(ApiError::invalid_argument("no resource to apply"));
+    }
+    if resources.len() > config.max_resources {
+        return Err(ApiError::invalid_argument(format!(
+            "{} resources are given, at most {} of them are applied in a batch",
+            resources.len(),
+            config.max_resources
+        )));
+    }
+
+    let requests = resources
+        .iter()
+        .map(|resource| resource.to_create_resource_request())
+        .collect::<Vec<_>>();
+    let mut results = resources
+        .iter()
+        .enumerate()
+        .map(|(index, resource)| {
+            validate_batch_resource(caller, &resources[..index], resource, &requests[index])
+                .err()
+                .map(|err| BatchResourceResult::failed(resource, err))
+        })
+        .collect::<Vec<_>>();
+    if query.atomic && results.iter().any(Option::is_some) {
+        return Err(reject_batch(&results));
+    }
+
+    // the dataflows of a level depend on the ones of the former levels only, so each level is applied once the former ones are done
+    let dataflows = requests
+        .iter()
+        .map(|request| request.get_dataflow())
+        .collect::<Vec<_>>();
+    let mut levels = vec![0; resources.len()];
+    for index in order_by_dependencies(&dataflows) {
+        let level = (0..resources.len())
+            .filter(|dependency| {
+                dataflows[index].depends_on_job(&dataflows[*dependency].get_job_id())
+            })
+            .map(|dependency| levels[dependency] + 1)
+            .max()
+            .unwrap_or(0);
+        levels[index] = level;
+    }
+    let requests = &requests;
+    let max_level = levels.iter().copied().max().unwrap_or(0);
+    for level in 0..=max_level {
+        let pending = (0..resources.len())
+            .filter(|index| levels[*index] == level && results[*index].is_none())
+            .collect::<Vec<_>>();
+        let applied = futures_util::stream::iter(pending)
+            .map(|index| async move {
+                let resource = &resources[index];
+                let result = match resource.resource_version {
+                    Some(_) => update_resource_dataflow(
+                        coordinators.route(&resource.namespace),
+                        caller,
+                        resource,
+                        query.strategy,
+                    )
+                    .await
+                    .map(|response| BatchResourceResult::updated(resource, &response)),
+                    None => create_dataflow(coordinators, caller, requests[index].clone())
+                        .await
+                        .map(|response| BatchResourceResult::created(resource, &response)),
+                };
+                (
+                    index,
+                    result.unwrap_or_else(|err| BatchResourceResult::failed(resource, err)),
+                )
+            })
+            .buffer_unordered(config.concurrency)
+            .collect::<Vec<_>>()
+            .await;
+        applied
+            .into_iter()
+            .for_each(|(index, result)| results[index] = Some(result));
+    }
+
+    respond(
+        HttpResponse::Ok(),
+        format,
+        &BatchResourcesResponse::from(results.into_iter().flatten().collect::<Vec<_>>()),
+    )
+}
+
+/// a resource of a batch is invalid if the caller is not allowed to access its namespace, its dataflow or operator settings
+/// are invalid, or it's named the same as a former one
+fn validate_batch_resource(
+    caller: &Caller,
+    former: &[ResourceDefinition],
+    resource: &ResourceDefinition,
+    request: &CreateResourceRequest,
+) -> Result<(), ApiError> {
+    caller.authorize_namespace(&resource.namespace)?;
+    if former
+        .iter()
+        .any(|other| other.namespace == resource.namespace && other.name == resource.name)
     {
-        Ok(client) => {
-            let mut req = GetDataflowRequest::default();
-            req.job_id = Some(args.to_resource_id());
-            client
-                .get_dataflow(tonic::Request::new(req))
-                .await
-                .map_err(|err| ErrorInternalServerError(ApiError::from(err)))
-                .and_then(|resp| {
-                    let mut response = GetResourceResponse::default();
-                    let mut resource = Resource::default();
-                    match resp.into_inner().graph {
-                        Some(dataflow) => {
-                            resource.resource_id = dataflow.job_id.clone();
-                            resource.set_resource_type(ResourceTypeEnum::Dataflow);
-                            response.resource = Some(resource);
-                            Ok(response)
-                        }
-                        None => Err(ErrorBadRequest("empty graph response")),
-                    }
-                })
-                .map(|response| resp.body(pb_to_bytes_mut(response)))
+        return Err(ApiError::invalid_argument(format!(
+            "duplicate resource {}/{}",
+            resource.namespace, resource.name
+        ))
+        .with_detail(
+            ApiErrorDetail::new("the resource is already given in the batch").with_field("name"),
+        ));
+    }
+    if request.is_dataflow_empty() {
+        return Err(ApiError::invalid_argument("empty dataflow")
+            .with_detail(ApiErrorDetail::new("dataflow is required").with_field("dataflow")));
+    }
+    resource.validate()?;
+    request.get_dataflow().validate().map_err(|err| {
+        ApiError::invalid_argument(format!(
+            "invalid dataflow {}/{}",
+            resource.namespace, resource.name
+        ))
+        .with_detail(invalid_dataflow_detail(&err))
+    })
+}
+
+/// the detail of the error of [`Dataflow::validate`](proto::common::Dataflow::validate).
+/// An invalid config of an operator is detailed by the field of the operator
+fn invalid_dataflow_detail(err: &DataflowValidateError) -> ApiErrorDetail {
+    match err {
+        DataflowValidateError::InvalidOperatorConfig(err) => ApiErrorDetail::new(err).with_field(
+            format!("dataflow.nodes.{}.details.{}", err.operator_id, err.field),
+        ),
+        err => ApiErrorDetail::new(format!("{err:?}")).with_field("dataflow"),
+    }
+}
+
+/// the error of an atomic batch with invalid resources. It's forbidden if the caller is not allowed to access any of them,
+/// otherwise the details of the invalid ones are merged with the indexes of the resources as their documents
+fn reject_batch(results: &[Option<BatchResourceResult>]) -> ApiError {
+    let errors = results
+        .iter()
+        .enumerate()
+        .filter_map(|(index, result)| {
+            result
+                .as_ref()
+                .and_then(|result| result.error.as_ref())
+                .map(|err| (index, err))
+        })
+        .collect::<Vec<_>>();
+    if let Some((_, err)) = errors
+        .iter()
+        .find(|(_, err)| err.code == ApiErrorCode::PermissionDenied)
+    {
+        return (*err).clone();
+    }
+    errors.iter().fold(
+        ApiError::invalid_argument(format!(
+            "{} of the resources are invalid, none of them is applied",
+            errors.len()
+        )),
+        |batch, (index, err)| match err.details.as_slice() {
+            [] => batch.with_detail(ApiErrorDetail::new(&err.message).with_document(*index)),
+            details => details.iter().fold(batch, |batch, detail| {
+                batch.with_detail(detail.clone().with_document(*index))
+            }),
+        },
+    )
+}
+
+/// the result of each dataflow is responded, even if some of them fail to terminate.
+/// The dataflows are terminated by the coordinators serving their namespaces. If they're served by several coordinators,
+/// the dataflows of a coordinator which fails, e.g. it's unavailable, fail on their own
+pub(crate) async fn terminate_dataflows(
+    coordinators: &CoordinatorRouter,
+    caller: &Caller,
+    req: &TerminateResourcesRequest,
+) -> Result<HttpResponse, ApiError> {
+    if req.resources.is_empty() {
+        return Err(
+            ApiError::invalid_argument("no resource to terminate").with_detail(
+                ApiErrorDetail::new("at least one resource is required").with_field("resources"),
+            ),
+        );
+    }
+    req.resources
+        .iter()
+        .try_for_each(|resource| caller.authorize_namespace(&resource.namespace))?;
+
+    let mut groups: Vec<(&Arc<CoordinatorGateway>, Vec<&ResourceRef>)> = vec![];
+    for resource in &req.resources {
+        let coordinator = coordinators.route(&resource.namespace);
+        match groups
+            .iter_mut()
+            .find(|(group, _)| Arc::ptr_eq(group, coordinator))
+        {
+            Some((_, resources)) => resources.push(resource),
+            None => groups.push((coordinator, vec![resource])),
         }
-        Err(err) => Err(err),
+    }
+
+    let mut results = vec![];
+    for (coordinator, resources) in &groups {
+        let result = coordinator
+            .call(|mut client| {
+                let request = caller.new_request(TerminateDataflowsRequest {
+                    job_ids: resources
+                        .iter()
+                        .map(|resource| resource.to_resource_id())
+                        .collect(),
+                    force: req.mode.is_force(),
+                });
+                async move { client.terminate_dataflows(request).await }
+            })
+            .await
+            .map_err(ApiError::from);
+        match result {
+            Ok(response) => results.extend(TerminateResourcesResponse::from(response).results),
+            Err(err) if groups.len() == 1 => return Err(err),
+            Err(err) => results.extend(
+                resources
+                    .iter()
+                    .map(|resource| TerminateResourceResult::failed(resource, err.clone())),
+            ),
+        }
+    }
+    // in the order of the request
+    results.sort_by_key(|result| {
+        req.resources
+            .iter()
+            .position(|resource| resource.id == result.id && resource.namespace == result.namespace)
+    });
+    Ok(HttpResponse::Ok().json(TerminateResourcesResponse { results }))
+}
+
+/// a dataflow is deleted by the batch termination, which reports unknown dataflows as not found,
+/// so deleting a dataflow which has been deleted responds 404 instead of succeeding again
+pub(crate) async fn delete_dataflow(
+    coordinator: &CoordinatorGateway,
+    caller: &Caller,
+    args: &ResourcePathArgs,
+    mode: TerminateMode,
+) -> Result<HttpResponse, ApiError> {
+    caller.authorize_namespace(&args.namespace)?;
+    coordinator
+        .call(|mut client| {
+            let request = caller.new_request(TerminateDataflowsRequest {
+                job_ids: vec![args.to_resource_id()],
+                force: mode.is_force(),
+            });
+            async move { client.terminate_dataflows(request).await }
+        })
+        .await
+        .map_err(ApiError::from)
+        .and_then(|response| match response.results.first() {
+            Some(result) => to_delete_response(result),
+            None => Err(ApiError::internal("no termination result")),
+        })
+}
+
+/// 202 with the status if the dataflow is terminated, or 409 with the failures of the TaskManagers if it's partially terminated.
+/// Other failures are responded by the HTTP status of their codes, e.g. 404 if the dataflow doesn't exist
+pub(crate) fn to_delete_response(
+    result: &TerminateDataflowResult,
+) -> Result<HttpResponse, ApiError> {
+    let result = TerminateResourceResult::from(result);
+    match result.error.clone() {
+        None => Ok(HttpResponse::Accepted().json(result)),
+        Some(_) if !result.worker_failures.is_empty() => Ok(HttpResponse::Conflict().json(result)),
+        Some(err) => Err(err),
     }
 }
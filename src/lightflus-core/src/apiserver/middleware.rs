@@ -0,0 +1,1074 @@
+use std::{
+    collections::HashMap,
+    future::{ready, Ready},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::PayloadError,
+    http::{
+        header::{self, HeaderName, HeaderValue},
+        Method,
+    },
+    web::Bytes,
+    HttpMessage, HttpResponse, ResponseError,
+};
+use common::utils::times::now_timestamp;
+use futures_util::{future::LocalBoxFuture, Stream, StreamExt};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    errors::apiserver::{ApiError, ApiErrorCode},
+    metrics::{Counter, MetricsRegistry, LATENCY_BUCKETS, METRICS_PATH},
+};
+
+use super::{
+    audit::{AuditLog, AuditRecord},
+    auth::{Caller, Role, TokenStore},
+    config::{CorsConfig, LimitsConfig, RateLimitConfig},
+};
+
+/// header of the id of a request, it's set in both of the request and the response
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// request ids given by the clients are ignored if they're longer than it
+const MAX_REQUEST_ID_LEN: usize = 128;
+/// header of the responses which are served from the cache of the reads while the coordinators are unavailable, its value is `true`
+pub const STALE_HEADER: &str = "x-lightflus-stale";
+/// the headers of the responses which the scripts of the allowed origins can read besides the CORS-safelisted ones
+const EXPOSED_HEADERS: &str = "x-request-id, x-lightflus-stale, retry-after";
+
+/// the latency of the requests, labelled by their route templates, their methods and the classes of their statuses like `2xx`
+pub const REQUEST_DURATION_METRIC: &str = "lightflus_apiserver_request_duration_seconds";
+/// the requests whose responses are not started yet
+pub const REQUESTS_IN_FLIGHT_METRIC: &str = "lightflus_apiserver_requests_in_flight";
+/// the route label of the requests which match no route, so unknown paths don't create new series
+const UNMATCHED_ROUTE: &str = "unmatched";
+/// the requests which are responded with 429 because their clients exceed the rate limits
+pub const RATE_LIMITED_METRIC: &str = "lightflus_apiserver_rate_limited_requests_total";
+/// the requests which are responded with 413 because their bodies are too large
+pub const OVERSIZED_REQUESTS_METRIC: &str = "lightflus_apiserver_oversized_requests_total";
+/// the tokens which are taken from the buckets of the reads and not refilled yet, summed over all of the clients
+pub const READ_TOKENS_CONSUMED_METRIC: &str = "lightflus_apiserver_rate_limit_read_tokens_consumed";
+/// the tokens which are taken from the buckets of the writes and not refilled yet, summed over all of the clients
+pub const WRITE_TOKENS_CONSUMED_METRIC: &str =
+    "lightflus_apiserver_rate_limit_write_tokens_consumed";
+/// the full buckets, whose clients are idle, are dropped once there are more clients than it
+const MAX_RATE_LIMITED_CLIENTS: usize = 10_000;
+/// the methods of the API, which are all allowed if [`CorsConfig::allowed_methods`] is empty
+const API_METHODS: [&str; 5] = ["GET", "HEAD", "POST", "PUT", "DELETE"];
+
+tokio::task_local! {
+    static REQUEST_CONTEXT: RequestContext;
+}
+
+/// The context of the request in hand, which is set by [`RequestId`] while the request is served.
+/// The requests to the coordinator carry its id, and their latency is added up for [`AccessLog`].
+/// A successful response is marked by the [`STALE_HEADER`] if any of its reads is served from the cache
+#[derive(Clone, Debug)]
+pub(crate) struct RequestContext {
+    request_id: String,
+    /// the time spent on the coordinator in microseconds
+    upstream: Arc<AtomicU64>,
+    stale: Arc<AtomicBool>,
+}
+
+impl RequestContext {
+    pub(crate) fn new(request_id: &str) -> Self {
+        Self {
+            request_id: request_id.to_string(),
+            upstream: Default::default(),
+            stale: Default::default(),
+        }
+    }
+
+    /// the context of the request in hand. There is none out of the requests, e.g. in the background creation of resources
+    pub(crate) fn current() -> Option<Self> {
+        REQUEST_CONTEXT.try_with(Clone::clone).ok()
+    }
+
+    /// run the future in the context
+    pub(crate) async fn scope<F: std::future::Future>(self, future: F) -> F::Output {
+        REQUEST_CONTEXT.scope(self, future).await
+    }
+
+    pub(crate) fn get_request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    pub(crate) fn add_upstream_latency(&self, latency: Duration) {
+        self.upstream
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn get_upstream_latency(&self) -> Duration {
+        Duration::from_micros(self.upstream.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn mark_stale(&self) {
+        self.stale.store(true, Ordering::Relaxed);
+    }
+
+    fn is_stale(&self) -> bool {
+        self.stale.load(Ordering::Relaxed)
+    }
+}
+
+/// [`RequestId`] tags each request with the id in its `X-Request-Id` header, or a new one if it has none.
+/// The id is responded in the `X-Request-Id` header, and it's set as the `requestId` of the [`ApiError`] the request fails with.
+/// The request is served in its [`RequestContext`]
+pub(crate) struct RequestId;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RequestIdMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddleware { service }))
+    }
+}
+
+pub(crate) struct RequestIdMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = get_request_id(&req);
+        let context = RequestContext::new(&request_id);
+        req.extensions_mut().insert(context.clone());
+        let call = self.service.call(req);
+        let stale = context.clone();
+        Box::pin(context.scope(async move {
+            let resp = call.await?;
+            // the error responses are rendered again with the request id
+            let err = resp
+                .response()
+                .error()
+                .and_then(|err| err.as_error::<ApiError>())
+                .map(|err| err.clone().with_request_id(&request_id));
+            let mut resp = match err {
+                Some(err) => {
+                    // the headers added by the inner middlewares, e.g. the CORS headers, are kept
+                    let headers = resp.headers().clone();
+                    let mut resp = resp
+                        .into_response(err.error_response())
+                        .map_into_right_body();
+                    for (name, value) in headers.iter() {
+                        if !resp.headers().contains_key(name) {
+                            resp.headers_mut().append(name.clone(), value.clone());
+                        }
+                    }
+                    resp
+                }
+                None => resp.map_into_left_body(),
+            };
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                resp.headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            if stale.is_stale() && resp.status().is_success() {
+                resp.headers_mut().insert(
+                    HeaderName::from_static(STALE_HEADER),
+                    HeaderValue::from_static("true"),
+                );
+            }
+            Ok(resp)
+        }))
+    }
+}
+
+/// [`AccessLog`] records the latency of each request in the [`REQUEST_DURATION_METRIC`] histogram,
+/// and counts the requests in flight in the [`REQUESTS_IN_FLIGHT_METRIC`] gauge. A request is in flight until its response starts,
+/// so a stream of events is only counted until it's established.
+///
+/// Each request is logged in the `access` target with its id, its caller, its status, its duration and the time spent on the coordinator.
+/// It should be wrapped by [`RequestId`] and wrap [`Authentication`], so the requests failing the authentication are recorded as well
+#[derive(Clone)]
+pub(crate) struct AccessLog {
+    registry: Arc<MetricsRegistry>,
+    in_flight: Arc<AtomicU64>,
+}
+
+impl AccessLog {
+    pub(crate) fn new(registry: Arc<MetricsRegistry>) -> Self {
+        let in_flight = Arc::new(AtomicU64::new(0));
+        let gauge = in_flight.clone();
+        registry.register_gauge(REQUESTS_IN_FLIGHT_METRIC, move || {
+            gauge.load(Ordering::Relaxed)
+        });
+        Self {
+            registry,
+            in_flight,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AccessLog
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = AccessLogMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AccessLogMiddleware {
+            service,
+            log: self.clone(),
+        }))
+    }
+}
+
+pub(crate) struct AccessLogMiddleware<S> {
+    service: S,
+    log: AccessLog,
+}
+
+/// a request in flight, it's not in flight any more once it's dropped
+struct InFlight(Arc<AtomicU64>);
+
+impl InFlight {
+    fn enter(in_flight: &Arc<AtomicU64>) -> Self {
+        in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(in_flight.clone())
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for AccessLogMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started_at = Instant::now();
+        let in_flight = InFlight::enter(&self.log.in_flight);
+        let registry = self.log.registry.clone();
+        let context = req.extensions().get::<RequestContext>().cloned();
+        let method = req.method().clone();
+        let path = req.path().to_string();
+        let call = self.service.call(req);
+        Box::pin(async move {
+            let result = call.await;
+            drop(in_flight);
+            let duration = started_at.elapsed();
+
+            let (status, route, caller) = match &result {
+                Ok(resp) => (
+                    resp.status(),
+                    resp.request()
+                        .match_pattern()
+                        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string()),
+                    resp.request()
+                        .extensions()
+                        .get::<Caller>()
+                        .map(|caller| caller.get_identity().to_string()),
+                ),
+                Err(err) => (
+                    err.as_response_error().status_code(),
+                    UNMATCHED_ROUTE.to_string(),
+                    None,
+                ),
+            };
+            let status_class = format!("{}xx", status.as_u16() / 100);
+            registry
+                .histogram(
+                    REQUEST_DURATION_METRIC,
+                    &[
+                        ("route", &route),
+                        ("method", method.as_str()),
+                        ("status", &status_class),
+                    ],
+                    &LATENCY_BUCKETS,
+                )
+                .observe(duration);
+
+            let request_id = context
+                .as_ref()
+                .map(|context| context.get_request_id().to_string())
+                .unwrap_or_default();
+            let upstream = context
+                .as_ref()
+                .map(|context| context.get_upstream_latency())
+                .unwrap_or_default();
+            tracing::info!(
+                target: "access",
+                request_id = %request_id,
+                caller = caller.as_deref().unwrap_or("anonymous"),
+                method = %method,
+                route = %route,
+                status = status.as_u16(),
+                duration_ms = duration.as_millis() as u64,
+                upstream_ms = upstream.as_millis() as u64,
+                "{} {} {}",
+                method,
+                path,
+                status.as_u16()
+            );
+            result
+        })
+    }
+}
+
+/// [`Audit`] records each mutation, which is any request but `GET`, `HEAD` and `OPTIONS`, in the [`AuditLog`].
+/// The body is hashed while it's read by the handler, so a body which is rejected before it's read is hashed as an empty one.
+/// It should be wrapped by [`RequestId`] and wrap [`Authentication`], so the mutations failing the authentication are recorded as well
+#[derive(Clone)]
+pub(crate) struct Audit {
+    log: Arc<AuditLog>,
+}
+
+impl Audit {
+    pub(crate) fn new(log: Arc<AuditLog>) -> Self {
+        Self { log }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Audit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = AuditMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuditMiddleware {
+            service,
+            log: self.log.clone(),
+        }))
+    }
+}
+
+pub(crate) struct AuditMiddleware<S> {
+    service: S,
+    log: Arc<AuditLog>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuditMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+            return Box::pin(self.service.call(req));
+        }
+
+        let timestamp = now_timestamp();
+        let started_at = Instant::now();
+        let request_id = req
+            .extensions()
+            .get::<RequestContext>()
+            .map(|context| context.get_request_id().to_string())
+            .unwrap_or_default();
+        let method = req.method().clone();
+        let path = req.path().to_string();
+        let hasher = Arc::new(Mutex::new(Sha256::new()));
+        let digest = hasher.clone();
+        let payload: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> =
+            Box::pin(req.take_payload().map(move |chunk| {
+                if let Ok(chunk) = &chunk {
+                    digest
+                        .lock()
+                        .unwrap_or_else(|err| err.into_inner())
+                        .update(chunk);
+                }
+                chunk
+            }));
+        req.set_payload(Payload::from(payload));
+
+        let log = self.log.clone();
+        let call = self.service.call(req);
+        Box::pin(async move {
+            let result = call.await;
+            let (status, namespace, name, identity) = match &result {
+                Ok(resp) => {
+                    let req = resp.request();
+                    (
+                        resp.status(),
+                        req.match_info().get("namespace").map(str::to_string),
+                        req.match_info().get("name").map(str::to_string),
+                        req.extensions()
+                            .get::<Caller>()
+                            .map(|caller| caller.get_identity().to_string()),
+                    )
+                }
+                Err(err) => (err.as_response_error().status_code(), None, None, None),
+            };
+            let verb = match method {
+                Method::POST if path.ends_with("/terminate") => "terminate",
+                Method::POST if path.ends_with(":batch") => "apply",
+                Method::POST => "create",
+                Method::DELETE => "delete",
+                _ => "update",
+            };
+            let body_sha256 = hasher
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .clone()
+                .finalize()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect();
+            log.record(AuditRecord {
+                timestamp,
+                request_id,
+                identity: identity
+                    .unwrap_or_else(|| Caller::anonymous().get_identity().to_string()),
+                verb: verb.to_string(),
+                method: method.to_string(),
+                path,
+                namespace,
+                name,
+                status: status.as_u16(),
+                latency_ms: started_at.elapsed().as_millis() as u64,
+                body_sha256,
+            });
+            result
+        })
+    }
+}
+
+/// [`Cors`] serves the cross-origin requests of the browsers by the [`CorsConfig`]. It does nothing if CORS is disabled,
+/// and the requests without `Origin` or from the origin of the API server itself are served as they are.
+///
+/// A request from an origin which is not allowed fails with 403, instead of being served without the CORS headers.
+/// A preflight request is answered with 204 if its method and headers are allowed too, otherwise it fails with 403.
+/// The responses of the other requests carry the CORS headers, including the errors of the inner middlewares.
+/// It should wrap [`Authentication`] and [`RateLimit`], so the preflight requests are never authenticated nor rate limited
+#[derive(Clone)]
+pub(crate) struct Cors {
+    /// none if CORS is disabled
+    config: Option<Arc<CorsConfig>>,
+}
+
+impl Cors {
+    pub(crate) fn new(config: &CorsConfig) -> Self {
+        Self {
+            config: (!config.allowed_origins.is_empty()).then(|| Arc::new(config.clone())),
+        }
+    }
+}
+
+/// the origin of a cross-origin request, none if the request has no `Origin` or it's sent from the origin of the API server
+fn get_cross_origin(req: &ServiceRequest) -> Option<String> {
+    let origin = req.headers().get(header::ORIGIN)?.to_str().ok()?;
+    let host = origin
+        .split_once("://")
+        .map(|(_, host)| host)
+        .unwrap_or(origin);
+    (!host.eq_ignore_ascii_case(req.connection_info().host())).then(|| origin.to_string())
+}
+
+fn is_allowed_origin(config: &CorsConfig, origin: &str) -> bool {
+    config
+        .allowed_origins
+        .iter()
+        .any(|allowed| allowed == "*" || allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+}
+
+fn is_allowed_method(config: &CorsConfig, method: &str) -> bool {
+    if config.allowed_methods.is_empty() {
+        API_METHODS.contains(&method)
+    } else {
+        config
+            .allowed_methods
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(method))
+    }
+}
+
+/// the headers of the response of an allowed cross-origin request
+fn add_cors_headers(config: &CorsConfig, origin: &str, headers: &mut header::HeaderMap) {
+    let any_origin =
+        !config.allow_credentials && config.allowed_origins.iter().any(|allowed| allowed == "*");
+    let allowed_origin = if any_origin { "*" } else { origin };
+    if let Ok(value) = HeaderValue::from_str(allowed_origin) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+    }
+    if !any_origin {
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    }
+    if config.allow_credentials {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    }
+}
+
+/// the response of an allowed preflight request
+fn preflight(
+    config: &CorsConfig,
+    req: &ServiceRequest,
+    origin: &str,
+) -> Result<HttpResponse, ApiError> {
+    let method = req
+        .headers()
+        .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !is_allowed_method(config, method) {
+        return Err(ApiError::new(
+            ApiErrorCode::PermissionDenied,
+            format!("method {method} is not allowed for origin {origin}"),
+        ));
+    }
+    let requested_headers = req
+        .headers()
+        .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if let Some(header) = requested_headers
+        .split(',')
+        .map(|header| header.trim())
+        .filter(|header| !header.is_empty())
+        .find(|header| {
+            !config
+                .allowed_headers
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(header))
+        })
+    {
+        return Err(ApiError::new(
+            ApiErrorCode::PermissionDenied,
+            format!("header {header} is not allowed for origin {origin}"),
+        ));
+    }
+
+    let mut resp = HttpResponse::NoContent();
+    let methods = if config.allowed_methods.is_empty() {
+        API_METHODS.join(", ")
+    } else {
+        config.allowed_methods.join(", ")
+    };
+    resp.insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, methods));
+    if !requested_headers.is_empty() {
+        resp.insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, requested_headers));
+    }
+    if let Some(max_age) = config.max_age {
+        resp.insert_header((header::ACCESS_CONTROL_MAX_AGE, max_age));
+    }
+    let mut resp = resp.finish();
+    add_cors_headers(config, origin, resp.headers_mut());
+    Ok(resp)
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Cors
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = CorsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CorsMiddleware {
+            service,
+            cors: self.clone(),
+        }))
+    }
+}
+
+pub(crate) struct CorsMiddleware<S> {
+    service: S,
+    cors: Cors,
+}
+
+impl<S, B> Service<ServiceRequest> for CorsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let (config, origin) = match (self.cors.config.as_ref(), get_cross_origin(&req)) {
+            (Some(config), Some(origin)) => (config.clone(), origin),
+            _ => {
+                let call = self.service.call(req);
+                return Box::pin(async move { call.await.map(|resp| resp.map_into_left_body()) });
+            }
+        };
+        if !is_allowed_origin(&config, &origin) {
+            let resp = req
+                .error_response(ApiError::new(
+                    ApiErrorCode::PermissionDenied,
+                    format!("origin {origin} is not allowed"),
+                ))
+                .map_into_right_body();
+            return Box::pin(async move { Ok(resp) });
+        }
+        if req.method() == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            let resp = match preflight(&config, &req, &origin) {
+                Ok(resp) => req.into_response(resp),
+                Err(err) => req.error_response(err),
+            };
+            return Box::pin(async move { Ok(resp.map_into_right_body()) });
+        }
+
+        let call = self.service.call(req);
+        Box::pin(async move {
+            let mut resp = call.await?;
+            add_cors_headers(&config, &origin, resp.headers_mut());
+            resp.headers_mut().insert(
+                header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                HeaderValue::from_static(EXPOSED_HEADERS),
+            );
+            Ok(resp.map_into_left_body())
+        })
+    }
+}
+
+/// the health endpoints and the metrics, which are not authenticated unless [`Authentication`] locks them
+const HEALTH_PATHS: [&str; 6] = [
+    "/health",
+    "/ready",
+    "/healthz",
+    "/readyz",
+    "/overview",
+    METRICS_PATH,
+];
+
+/// [`Authentication`] requires `Authorization: Bearer <token>` of the requests if the [`TokenStore`] is given.
+/// `GET` and `HEAD` requests require the read role, and the other ones require the write role.
+/// The [`Caller`] of the token is attached to the request, so the handlers can check the namespaces of the resources with it.
+///
+/// Requests without a valid token fail with 401, and the ones without the role fail with 403
+#[derive(Clone)]
+pub(crate) struct Authentication {
+    tokens: Option<Arc<TokenStore>>,
+    /// whether the health endpoints are authenticated
+    lock_health: bool,
+}
+
+impl Authentication {
+    pub(crate) fn new(tokens: Option<Arc<TokenStore>>) -> Self {
+        Self {
+            tokens,
+            lock_health: false,
+        }
+    }
+
+    pub(crate) fn with_health_locked(mut self, lock_health: bool) -> Self {
+        self.lock_health = lock_health;
+        self
+    }
+
+    /// the caller is attached to the request once its token is valid, so the requests without the role are audited with their callers
+    fn authenticate(&self, req: &ServiceRequest) -> Result<(), ApiError> {
+        let tokens = match &self.tokens {
+            Some(tokens) if self.lock_health || !HEALTH_PATHS.contains(&req.path()) => tokens,
+            _ => return Ok(()),
+        };
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+            .map(|(_, token)| token.trim())
+            .ok_or_else(|| {
+                ApiError::new(ApiErrorCode::Unauthenticated, "bearer token is required")
+            })?;
+        let caller = tokens
+            .authenticate(token)
+            .ok_or_else(|| ApiError::new(ApiErrorCode::Unauthenticated, "invalid bearer token"))?;
+        let role = match *req.method() {
+            Method::GET | Method::HEAD => Role::Read,
+            _ => Role::Write,
+        };
+        let result = caller.authorize_role(role);
+        req.extensions_mut().insert(caller);
+        result
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Authentication
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = AuthenticationMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthenticationMiddleware {
+            service,
+            auth: self.clone(),
+        }))
+    }
+}
+
+pub(crate) struct AuthenticationMiddleware<S> {
+    service: S,
+    auth: Authentication,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthenticationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        match self.auth.authenticate(&req) {
+            Ok(_) => {
+                let call = self.service.call(req);
+                Box::pin(async move { call.await.map(|resp| resp.map_into_left_body()) })
+            }
+            Err(err) => {
+                let resp = req.error_response(err).map_into_right_body();
+                Box::pin(async move { Ok(resp) })
+            }
+        }
+    }
+}
+
+/// the bucket of the tokens of a client, see [`RateLimitConfig`]
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// the token buckets of the clients of a route group
+#[derive(Debug)]
+struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            rate: config.rate as f64,
+            burst: config.burst as f64,
+            buckets: Default::default(),
+        }
+    }
+
+    /// the tokens of the bucket at the moment
+    fn get_tokens(&self, bucket: &TokenBucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(self.burst)
+    }
+
+    /// take a token of the client, or tell how long it should wait for the next one
+    fn acquire(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+        if buckets.len() >= MAX_RATE_LIMITED_CLIENTS && !buckets.contains_key(client) {
+            buckets.retain(|_, bucket| self.get_tokens(bucket, now) < self.burst);
+        }
+        let bucket = buckets
+            .entry(client.to_string())
+            .or_insert_with(|| TokenBucket {
+                tokens: self.burst,
+                refilled_at: now,
+            });
+        bucket.tokens = self.get_tokens(bucket, now);
+        bucket.refilled_at = bucket.refilled_at.max(now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+
+    /// the tokens taken by all of the clients which are not refilled yet
+    fn get_consumed(&self, now: Instant) -> u64 {
+        self.buckets
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .values()
+            .map(|bucket| self.burst - self.get_tokens(bucket, now))
+            .sum::<f64>()
+            .round() as u64
+    }
+}
+
+/// [`RateLimit`] limits the size of the request bodies and the rate of the requests of each client.
+///
+/// A body which is larger than the limit fails with 413, either by its `Content-Length` before it's read,
+/// or once the limit is exceeded while it's streamed.
+/// The reads and the writes of a client are limited by two token buckets, see [`LimitsConfig`].
+/// A client is the caller attached by [`Authentication`], or the IP address of the peer if the request is not authenticated.
+/// A request which finds no token fails with 429, and the `Retry-After` header tells how long the client should wait.
+///
+/// The health endpoints and the polls of the operations are never rate limited, so a client which exhausts its writes
+/// by the asynchronous submissions can still follow them. It should be wrapped by [`Authentication`]
+#[derive(Clone)]
+pub(crate) struct RateLimit {
+    max_body_size: usize,
+    reads: Option<Arc<RateLimiter>>,
+    writes: Option<Arc<RateLimiter>>,
+    rate_limited: Counter,
+    oversized: Counter,
+}
+
+impl RateLimit {
+    pub(crate) fn new(config: &LimitsConfig, registry: &MetricsRegistry) -> Self {
+        let reads = config
+            .get_read_limit()
+            .map(|limit| Arc::new(RateLimiter::new(limit)));
+        let writes = config
+            .get_write_limit()
+            .map(|limit| Arc::new(RateLimiter::new(limit)));
+        for (name, limiter) in [
+            (READ_TOKENS_CONSUMED_METRIC, reads.clone()),
+            (WRITE_TOKENS_CONSUMED_METRIC, writes.clone()),
+        ] {
+            registry.register_gauge(name, move || {
+                limiter
+                    .as_ref()
+                    .map(|limiter| limiter.get_consumed(Instant::now()))
+                    .unwrap_or_default()
+            });
+        }
+        Self {
+            max_body_size: config.max_body_size,
+            reads,
+            writes,
+            rate_limited: registry.counter(RATE_LIMITED_METRIC),
+            oversized: registry.counter(OVERSIZED_REQUESTS_METRIC),
+        }
+    }
+
+    fn check(&self, req: &ServiceRequest) -> Result<(), ApiError> {
+        let content_length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+        if matches!(content_length, Some(length) if length > self.max_body_size) {
+            self.oversized.inc(1);
+            return Err(ApiError::from(PayloadError::Overflow));
+        }
+
+        if HEALTH_PATHS.contains(&req.path()) || req.path().starts_with("/operations/") {
+            return Ok(());
+        }
+        let (limiter, group) = match *req.method() {
+            Method::GET | Method::HEAD => (&self.reads, "reads"),
+            _ => (&self.writes, "writes"),
+        };
+        let limiter = match limiter {
+            Some(limiter) => limiter,
+            None => return Ok(()),
+        };
+        let client = match req.extensions().get::<Caller>() {
+            Some(caller) => caller.get_identity().to_string(),
+            None => req
+                .peer_addr()
+                .map(|addr| addr.ip().to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+        };
+        limiter.acquire(&client, Instant::now()).map_err(|wait| {
+            self.rate_limited.inc(1);
+            ApiError::new(
+                ApiErrorCode::ResourceExhausted,
+                format!("{client} exceeds the rate limit of the {group}"),
+            )
+            .with_retry_after(wait)
+        })
+    }
+
+    /// the body fails with [`PayloadError::Overflow`] once it exceeds the limit
+    fn limit_body(&self, req: &mut ServiceRequest) {
+        let max_body_size = self.max_body_size;
+        let oversized = self.oversized.clone();
+        let mut size = 0;
+        let payload: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> =
+            Box::pin(req.take_payload().map(move |chunk| {
+                let chunk = chunk?;
+                size += chunk.len();
+                if size > max_body_size {
+                    oversized.inc(1);
+                    return Err(PayloadError::Overflow);
+                }
+                Ok(chunk)
+            }));
+        req.set_payload(Payload::from(payload));
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service,
+            limit: self.clone(),
+        }))
+    }
+}
+
+pub(crate) struct RateLimitMiddleware<S> {
+    service: S,
+    limit: RateLimit,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        match self.limit.check(&req) {
+            Ok(_) => {
+                self.limit.limit_body(&mut req);
+                let call = self.service.call(req);
+                Box::pin(async move { call.await.map(|resp| resp.map_into_left_body()) })
+            }
+            Err(err) => {
+                let resp = req.error_response(err).map_into_right_body();
+                Box::pin(async move { Ok(resp) })
+            }
+        }
+    }
+}
+
+/// the id given by the client if it's valid, otherwise a new uuid
+fn get_request_id(req: &ServiceRequest) -> String {
+    req.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LEN)
+        .map(|value| value.to_string())
+        .unwrap_or_else(common::utils::uuid)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::apiserver::config::RateLimitConfig;
+
+    use super::RateLimiter;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(&RateLimitConfig { rate: 2, burst: 3 });
+        let now = Instant::now();
+
+        // a burst is allowed at once
+        for _ in 0..3 {
+            assert!(limiter.acquire("alice", now).is_ok());
+        }
+        assert_eq!(
+            limiter.acquire("alice", now),
+            Err(Duration::from_millis(500))
+        );
+        assert_eq!(limiter.get_consumed(now), 3);
+        // the other clients have their own buckets
+        assert!(limiter.acquire("bob", now).is_ok());
+        assert_eq!(limiter.get_consumed(now), 4);
+
+        // the bucket is refilled by the rate, but never over the burst
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.acquire("alice", later).is_ok());
+        assert!(limiter.acquire("alice", later).is_err());
+        let idle = now + Duration::from_secs(60);
+        assert_eq!(limiter.get_consumed(idle), 0);
+        for _ in 0..3 {
+            assert!(limiter.acquire("alice", idle).is_ok());
+        }
+        assert!(limiter.acquire("alice", idle).is_err());
+    }
+}
@@ -1,2 +1,186 @@
+use std::{sync::Arc, time::Duration};
+
+use actix_web::{web, App, HttpServer};
+
+use crate::{
+    errors::{apiserver::ApiError, server::ServerError},
+    metrics::{self, MetricsConfig},
+};
+
+use self::{
+    audit::AuditLog,
+    auth::TokenStore,
+    config::ApiServerConfig,
+    events::EventHub,
+    handler::{
+        coordinator::CoordinatorRouter,
+        resources::{
+            audit_tail, batch_resources, cluster, coordinator_health, create_resource,
+            delete_resource, get_resource, get_resource_detail, get_resource_events,
+            get_resource_graph, health, list_resources, liveness_probe, not_found,
+            openapi_document, operation, overview, prometheus_metrics, readiness, readiness_probe,
+            swagger_ui, terminate_resources, update_resource,
+        },
+        RESOURCES_HANDLER_ROOT,
+    },
+    middleware::{AccessLog, Audit, Authentication, Cors, RateLimit, RequestId},
+    operations::OperationStore,
+    probe::Prober,
+    shutdown::Draining,
+};
+
+pub use self::shutdown::ApiServer;
+
+mod audit;
+pub mod auth;
+pub mod config;
+mod events;
+mod graph;
 pub mod handler;
-mod types;
\ No newline at end of file
+mod middleware;
+mod openapi;
+mod operations;
+mod operators;
+mod probe;
+mod shutdown;
+mod types;
+
+/// default port of the HTTP API server
+pub const API_SERVER_PORT: u16 = 8080;
+
+/// create the HTTP API server by the config. It should be started along with the Coordinator.
+/// The config is validated first, the server is not created if any field of it is invalid.
+/// Requests are sent to the coordinators serving their namespaces, failing over between the endpoints of each coordinator.
+/// They're authenticated by the tokens in the token file if it's configured, see [`Authentication`],
+/// and the bodies and the rates of them are limited by the configured limits, see [`RateLimit`].
+/// The cross-origin requests are served by the CORS config if it's enabled, whose preflight requests are never authenticated, see [`Cors`].
+/// The mutations are recorded by the audit log, whose sink is served in the background, see [`Audit`].
+/// The dependencies are probed in the background while it's served, which `/healthz` and `/readyz` report, see [`Prober`].
+/// The server shuts down gracefully once it's signaled, see [`ApiServer::serve`].
+///
+/// The metrics of the requests are kept in the registry of the process, see [`AccessLog`]. They're served at `/metrics` of the API server,
+/// or by a metrics server in the background if a port of their own is configured, so it should be called in a Tokio runtime then
+pub fn new_api_server(config: &ApiServerConfig) -> Result<ApiServer, ServerError> {
+    config.validate()?;
+
+    let coordinators = web::Data::new(CoordinatorRouter::from_config(&config.coordinator));
+    let operations = web::Data::new(OperationStore::new(&config.operations));
+    let events = web::Data::new(EventHub::new(&config.events));
+    let draining = web::Data::new(Draining::default());
+    let prober = web::Data::new(Prober::new(config, coordinators.clone()));
+    let tokens = config
+        .auth
+        .token_file
+        .as_ref()
+        .map(|path| TokenStore::load(path).map(Arc::new))
+        .transpose()?;
+    let auth = Authentication::new(tokens).with_health_locked(config.auth.authenticate_health);
+    let registry = metrics::registry();
+    let access_log = AccessLog::new(registry.clone());
+    let audit_log = Arc::new(AuditLog::new(&config.audit, &registry)?);
+    let audit = Audit::new(audit_log.clone());
+    let audit_log = web::Data::from(audit_log);
+    let rate_limit = RateLimit::new(&config.limits, &registry);
+    let cors = Cors::new(&config.cors);
+    let metrics_port = config.metrics.port;
+    if let Some(port) = metrics_port {
+        metrics::serve(
+            &MetricsConfig {
+                host: config.host.clone(),
+                port: port as usize,
+            },
+            registry.clone(),
+        );
+    }
+    let registry = web::Data::from(registry);
+    let batch = web::Data::new(config.batch.clone());
+    let swagger_ui_enabled = config.swagger_ui;
+    let grace_period = config.shutdown.get_grace_period();
+    // the states are drained by the ApiServer once it's signaled, so the app takes clones of them
+    let app_operations = operations.clone();
+    let app_events = events.clone();
+    let app_draining = draining.clone();
+    let app_prober = prober.clone();
+    let app_audit_log = audit_log.clone();
+    let server = HttpServer::new(move || {
+        App::new()
+            .wrap(rate_limit.clone())
+            .wrap(auth.clone())
+            .wrap(cors.clone())
+            .wrap(audit.clone())
+            .wrap(access_log.clone())
+            .wrap(RequestId)
+            .app_data(coordinators.clone())
+            .app_data(batch.clone())
+            .app_data(app_operations.clone())
+            .app_data(app_events.clone())
+            .app_data(app_draining.clone())
+            .app_data(app_prober.clone())
+            .app_data(app_audit_log.clone())
+            .app_data(registry.clone())
+            .configure(|cfg| {
+                if swagger_ui_enabled {
+                    cfg.service(swagger_ui);
+                }
+                if metrics_port.is_none() {
+                    cfg.service(prometheus_metrics);
+                }
+                configure(cfg)
+            })
+    })
+    .client_disconnect_timeout(Duration::from_secs(3))
+    .client_request_timeout(Duration::from_secs(3))
+    .worker_max_blocking_threads(10)
+    .workers(3)
+    .shutdown_timeout(grace_period.as_secs())
+    // the signals are handled by ApiServer::serve, which drains the states before the server stops
+    .disable_signals();
+
+    let addr = (config.host.as_str(), config.port);
+    let server = match config.tls.as_ref() {
+        Some(tls) => server.bind_rustls(addr, ApiServerConfig::load_tls(tls)?)?,
+        None => server.bind(addr)?,
+    };
+    Ok(ApiServer {
+        server: server.run(),
+        draining,
+        prober,
+        events,
+        operations,
+        audit_log,
+        grace_period,
+    })
+}
+
+/// register the handlers. The failures of all of them, including the ones of extracting the path, the query and the JSON body
+/// and requesting an unknown endpoint, are responded as [`ApiError`]s
+pub(crate) fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::PathConfig::default().error_handler(|err, _| ApiError::from(err).into()))
+        .app_data(web::QueryConfig::default().error_handler(|err, _| ApiError::from(err).into()))
+        .app_data(web::JsonConfig::default().error_handler(|err, _| ApiError::from(err).into()))
+        // the batch is not in the scope of the resources, which only matches the paths under `/resources/`
+        .service(batch_resources)
+        .service(
+            web::scope(RESOURCES_HANDLER_ROOT)
+                .service(create_resource)
+                .service(get_resource)
+                .service(list_resources)
+                .service(terminate_resources)
+                .service(get_resource_detail)
+                .service(get_resource_events)
+                .service(get_resource_graph)
+                .service(update_resource)
+                .service(delete_resource),
+        )
+        .service(operation)
+        .service(overview)
+        .service(health)
+        .service(readiness)
+        .service(liveness_probe)
+        .service(readiness_probe)
+        .service(cluster)
+        .service(coordinator_health)
+        .service(audit_tail)
+        .service(openapi_document)
+        .default_service(web::to(not_found));
+}
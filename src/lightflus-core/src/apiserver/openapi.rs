@@ -0,0 +1,1589 @@
+use serde_json::{json, Map, Value};
+
+use crate::{
+    errors::apiserver::{ApiError, ApiErrorDetail},
+    metrics::METRICS_PATH,
+};
+
+use super::{
+    audit::{AuditRecord, AuditTail},
+    events::StatusEvent,
+    graph::{GraphLink, GraphNode, ResourceGraph},
+    handler::{coordinator::CoordinatorHealth, RESOURCES_HANDLER_ROOT},
+    operations::Operation,
+    operators::OperatorSettings,
+    probe::{ProbeCheck, ProbeReport},
+    types::{
+        BatchResourceResult, BatchResourcesResponse, CreateResourceResult, CreateResourcesResponse,
+        ListResourcesResponse, OperatorDetail, ResourceDefinition, ResourceDetail, ResourceRef,
+        ResourceSummary, TerminateResourceResult, TerminateResourcesRequest,
+        TerminateResourcesResponse, UpdateResourceResponse, WorkerFailureSummary,
+    },
+};
+
+/// the path of the OpenAPI document
+pub const OPENAPI_PATH: &str = "/openapi.json";
+/// the path of the Swagger UI, which is only served if it's enabled by the config
+pub const SWAGGER_UI_PATH: &str = "/docs";
+
+/// The JSON schema of a body in the OpenAPI document. It's implemented by the types of the bodies,
+/// so a body type can't be renamed or removed without the document. The fields are checked against the serialized bodies by the tests
+pub(crate) trait ApiSchema {
+    /// the name of the schema under `components/schemas`
+    const NAME: &'static str;
+
+    fn schema() -> Value;
+
+    fn reference() -> Value {
+        json!({ "$ref": format!("#/components/schemas/{}", Self::NAME) })
+    }
+}
+
+fn object(required: &[&str], properties: Value) -> Value {
+    json!({
+        "type": "object",
+        "required": required,
+        "properties": properties,
+    })
+}
+
+fn array_of(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+/// milliseconds since the unix epoch
+fn timestamp() -> Value {
+    json!({ "type": "integer", "format": "int64", "description": "milliseconds since the unix epoch" })
+}
+
+/// the labels of a dataflow
+fn labels() -> Value {
+    json!({
+        "type": "object",
+        "additionalProperties": { "type": "string" },
+        "description": "labels of the dataflow. Keys and values are at most 63 characters of alphanumerics, `-`, `_` and `.` beginning and ending with an alphanumeric, and at most 64 labels are allowed",
+    })
+}
+
+/// the version of a resource, see [`ResourceSummary::resource_version`]
+fn resource_version() -> Value {
+    json!({
+        "type": "integer",
+        "format": "int64",
+        "description": "it changes every time the resource is updated, and an update must be based on the current one",
+    })
+}
+
+/// an error policy of [`OperatorSettings`], keyed by the policy. A retry may fall back to any policy but another retry
+fn error_policy(fallback: bool) -> Value {
+    let mut policies = vec![
+        json!({ "type": "object", "required": ["fail"], "properties": { "fail": { "type": "object" } }, "additionalProperties": false }),
+        json!({ "type": "object", "required": ["skip"], "properties": { "skip": { "type": "object" } }, "additionalProperties": false }),
+        json!({
+            "type": "object",
+            "required": ["dead_letter"],
+            "properties": {
+                "dead_letter": object(&["sink"], json!({ "sink": { "type": "integer", "description": "id of a downstream of the operator" } })),
+            },
+            "additionalProperties": false,
+        }),
+    ];
+    if fallback {
+        policies.push(json!({
+            "type": "object",
+            "required": ["retry"],
+            "properties": {
+                "retry": object(
+                    &["attempts"],
+                    json!({
+                        "attempts": { "type": "integer", "minimum": 1 },
+                        "backoff": {
+                            "type": "object",
+                            "description": "exponential backoff in milliseconds, no backoff if it's absent",
+                            "properties": {
+                                "base": { "type": "integer", "minimum": 0, "default": 0 },
+                                "max": { "type": "integer", "minimum": 0, "default": 0 },
+                                "jitter": { "type": "boolean", "default": false },
+                            },
+                        },
+                        "fallback": error_policy(false),
+                    }),
+                ),
+            },
+            "additionalProperties": false,
+        }));
+    }
+    json!({ "oneOf": policies })
+}
+
+/// the input or output schema of [`OperatorSettings`]
+fn payload_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "fields": array_of(object(
+                &["name"],
+                json!({
+                    "name": { "type": "string", "minLength": 1, "description": "unique in the schema" },
+                    "data_type": {
+                        "type": "string",
+                        "enum": ["bigint", "number", "null", "string", "boolean", "object", "array"],
+                        "description": "any type is allowed if it's absent",
+                    },
+                    "required": { "type": "boolean", "default": false },
+                }),
+            )),
+            "validation": { "type": "string", "enum": ["disabled", "sampling", "strict"], "default": "disabled" },
+            "sample_fraction": {
+                "type": "number",
+                "exclusiveMinimum": 0,
+                "maximum": 1,
+                "description": "required by the `sampling` validation",
+            },
+        },
+    })
+}
+
+/// the codes of [`crate::errors::apiserver::ApiErrorCode`]
+const ERROR_CODES: [&str; 16] = [
+    "cancelled",
+    "unknown",
+    "invalid_argument",
+    "deadline_exceeded",
+    "not_found",
+    "already_exists",
+    "permission_denied",
+    "resource_exhausted",
+    "failed_precondition",
+    "aborted",
+    "out_of_range",
+    "unimplemented",
+    "internal",
+    "unavailable",
+    "data_loss",
+    "unauthenticated",
+];
+
+impl ApiSchema for ApiErrorDetail {
+    const NAME: &'static str = "ApiErrorDetail";
+
+    fn schema() -> Value {
+        object(
+            &["message"],
+            json!({
+                "field": { "type": "string", "description": "path of the field, e.g. `dataflow.nodes.0.upstreams[1]`" },
+                "message": { "type": "string" },
+                "document": { "type": "integer", "description": "index of the YAML document, starting from 0" },
+                "line": { "type": "integer", "minimum": 1 },
+                "column": { "type": "integer", "minimum": 1 },
+            }),
+        )
+    }
+}
+
+impl ApiSchema for ApiError {
+    const NAME: &'static str = "ApiError";
+
+    fn schema() -> Value {
+        object(
+            &["code", "message", "details"],
+            json!({
+                "code": { "type": "string", "enum": ERROR_CODES },
+                "message": { "type": "string" },
+                "details": array_of(ApiErrorDetail::reference()),
+                "requestId": { "type": "string", "description": "absent in the errors of the items of a batch" },
+            }),
+        )
+    }
+}
+
+impl ApiSchema for ResourceSummary {
+    const NAME: &'static str = "ResourceSummary";
+
+    fn schema() -> Value {
+        object(
+            &[
+                "id",
+                "name",
+                "namespace",
+                "status",
+                "operator_count",
+                "created_at",
+                "updated_at",
+                "labels",
+                "resource_version",
+            ],
+            json!({
+                "id": { "type": "string" },
+                "name": { "type": "string" },
+                "namespace": { "type": "string" },
+                "status": {
+                    "type": "string",
+                    "enum": [
+                        "pending",
+                        "deploying",
+                        "running",
+                        "degraded",
+                        "recovering",
+                        "sources_paused",
+                        "suspended",
+                        "failed",
+                        "closing",
+                        "closed",
+                    ],
+                },
+                "status_reason": { "type": "string", "description": "why the resource is in the status, absent if the status is expected" },
+                "operator_count": { "type": "integer" },
+                "created_at": timestamp(),
+                "updated_at": timestamp(),
+                "labels": labels(),
+                "resource_version": resource_version(),
+                "coordinator": { "type": "string", "description": "the coordinator serving the resource, present if the coordinators of the regions are configured" },
+            }),
+        )
+    }
+}
+
+impl ApiSchema for ListResourcesResponse {
+    const NAME: &'static str = "ListResourcesResponse";
+
+    fn schema() -> Value {
+        object(
+            &["resources"],
+            json!({
+                "resources": array_of(ResourceSummary::reference()),
+                "continue": { "type": "string", "description": "token of the next page, absent in the last page" },
+                "unavailable": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "the coordinators whose resources are missing in a fanned-out listing since they're unavailable",
+                },
+            }),
+        )
+    }
+}
+
+impl ApiSchema for CoordinatorHealth {
+    const NAME: &'static str = "CoordinatorHealth";
+
+    fn schema() -> Value {
+        object(
+            &["name", "endpoints"],
+            json!({
+                "name": { "type": "string", "description": "`default` or the region of the coordinator" },
+                "endpoints": array_of(json!({ "type": "string" })),
+                "available": { "type": "boolean", "description": "whether the latest request is served, absent before any request" },
+                "error": { "type": "string", "description": "why the latest request fails if the coordinator is unavailable" },
+                "checked_at": timestamp(),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for ProbeCheck {
+    const NAME: &'static str = "ProbeCheck";
+
+    fn schema() -> Value {
+        object(
+            &["name", "status"],
+            json!({
+                "name": { "type": "string", "description": "the dependency which is checked, e.g. `coordinators`" },
+                "status": { "type": "string", "enum": ["ok", "failed"] },
+                "message": { "type": "string", "description": "why the check fails, or what it's passed by" },
+                "checked_at": timestamp(),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for ProbeReport {
+    const NAME: &'static str = "ProbeReport";
+
+    fn schema() -> Value {
+        object(
+            &["status", "checks"],
+            json!({
+                "status": { "type": "string", "enum": ["ok", "failed"], "description": "`ok` only if all of the checks pass" },
+                "checks": array_of(ProbeCheck::reference()),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for AuditRecord {
+    const NAME: &'static str = "AuditRecord";
+
+    fn schema() -> Value {
+        object(
+            &[
+                "timestamp",
+                "requestId",
+                "identity",
+                "verb",
+                "method",
+                "path",
+                "status",
+                "latencyMs",
+                "bodySha256",
+            ],
+            json!({
+                "timestamp": timestamp(),
+                "requestId": { "type": "string" },
+                "identity": { "type": "string", "description": "`anonymous` if the request is not authenticated" },
+                "verb": { "type": "string", "enum": ["create", "update", "delete", "terminate"] },
+                "method": { "type": "string" },
+                "path": { "type": "string" },
+                "namespace": { "type": "string", "description": "present if the namespace is in the path" },
+                "name": { "type": "string", "description": "present if the name is in the path" },
+                "status": { "type": "integer", "description": "the HTTP status of the response" },
+                "latencyMs": { "type": "integer", "format": "int64" },
+                "bodySha256": { "type": "string", "description": "hex-encoded SHA-256 of the request body" },
+            }),
+        )
+    }
+}
+
+impl ApiSchema for AuditTail {
+    const NAME: &'static str = "AuditTail";
+
+    fn schema() -> Value {
+        object(
+            &["records"],
+            json!({ "records": array_of(AuditRecord::reference()) }),
+        )
+    }
+}
+
+impl ApiSchema for ResourceRef {
+    const NAME: &'static str = "ResourceRef";
+
+    fn schema() -> Value {
+        object(
+            &["id", "namespace"],
+            json!({
+                "id": { "type": "string" },
+                "namespace": { "type": "string" },
+            }),
+        )
+    }
+}
+
+fn terminate_mode() -> Value {
+    json!({ "type": "string", "enum": ["drain", "force"], "default": "drain" })
+}
+
+impl ApiSchema for TerminateResourcesRequest {
+    const NAME: &'static str = "TerminateResourcesRequest";
+
+    fn schema() -> Value {
+        object(
+            &["resources"],
+            json!({
+                "resources": array_of(ResourceRef::reference()),
+                "mode": terminate_mode(),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for WorkerFailureSummary {
+    const NAME: &'static str = "WorkerFailureSummary";
+
+    fn schema() -> Value {
+        object(
+            &["node", "error"],
+            json!({
+                "node": { "type": "string", "description": "`host:port` of the TaskManager" },
+                "error": ApiError::reference(),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for TerminateResourceResult {
+    const NAME: &'static str = "TerminateResourceResult";
+
+    fn schema() -> Value {
+        object(
+            &["id", "namespace"],
+            json!({
+                "id": { "type": "string" },
+                "namespace": { "type": "string" },
+                "status": { "type": "string" },
+                "error": ApiError::reference(),
+                "worker_failures": array_of(WorkerFailureSummary::reference()),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for TerminateResourcesResponse {
+    const NAME: &'static str = "TerminateResourcesResponse";
+
+    fn schema() -> Value {
+        object(
+            &["results"],
+            json!({ "results": array_of(TerminateResourceResult::reference()) }),
+        )
+    }
+}
+
+impl ApiSchema for OperatorDetail {
+    const NAME: &'static str = "OperatorDetail";
+
+    fn schema() -> Value {
+        object(
+            &["id"],
+            json!({
+                "id": { "type": "integer" },
+                "kind": { "type": "string", "description": "the type of the operator, e.g. `reducer`" },
+                "upstreams": array_of(json!({ "type": "integer" })),
+                "status": {
+                    "type": "string",
+                    "enum": ["pending", "initialized", "running", "terminating", "terminated", "drained", "failed"],
+                },
+                "settings": OperatorSettings::reference(),
+                "resolved": json!({
+                    "allOf": [OperatorSettings::reference()],
+                    "description": "the settings with the defaults filled by the server",
+                }),
+                "host": { "type": "string", "description": "`host:port` of the TaskManager" },
+                "last_heartbeat_at": timestamp(),
+                "restart_count": { "type": "integer" },
+            }),
+        )
+    }
+}
+
+impl ApiSchema for OperatorSettings {
+    const NAME: &'static str = "OperatorSettings";
+
+    fn schema() -> Value {
+        object(
+            &[],
+            json!({
+                "chaining": { "type": "string", "enum": ["enabled", "disabled"], "default": "enabled" },
+                "error_policy": error_policy(true),
+                "state_limit": object(
+                    &["max_bytes"],
+                    json!({
+                        "max_bytes": { "type": "integer", "minimum": 1 },
+                        "policy": {
+                            "type": "string",
+                            "enum": ["reject_new_keys", "evict_oldest", "fail"],
+                            "default": "reject_new_keys",
+                        },
+                    }),
+                ),
+                "processing_timeout": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "milliseconds an event may take in the operator before it's abandoned",
+                },
+                "headers": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "propertyNames": { "minLength": 1 },
+                    "description": "metadata headers added to the events emitted by the operator",
+                },
+                "input_schema": payload_schema(),
+                "output_schema": payload_schema(),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for ResourceDetail {
+    const NAME: &'static str = "ResourceDetail";
+
+    fn schema() -> Value {
+        json!({
+            "allOf": [
+                ResourceSummary::reference(),
+                object(&["operators"], json!({ "operators": array_of(OperatorDetail::reference()) })),
+            ]
+        })
+    }
+}
+
+impl ApiSchema for GraphNode {
+    const NAME: &'static str = "GraphNode";
+
+    fn schema() -> Value {
+        object(
+            &["id", "kind", "parallelism", "status", "color"],
+            json!({
+                "id": { "type": "integer" },
+                "kind": { "type": "string", "description": "the type of the operator, e.g. `reducer`" },
+                "parallelism": { "type": "integer" },
+                "host": { "type": "string", "description": "`host:port` of the TaskManager where the operator is placed" },
+                "status": {
+                    "type": "string",
+                    "enum": ["pending", "initialized", "running", "terminating", "terminated", "drained", "failed"],
+                },
+                "color": { "type": "string", "description": "the graphviz color of the status" },
+            }),
+        )
+    }
+}
+
+impl ApiSchema for GraphLink {
+    const NAME: &'static str = "GraphLink";
+
+    fn schema() -> Value {
+        object(
+            &["source", "target", "partitioning"],
+            json!({
+                "source": { "type": "integer", "description": "id of the upstream" },
+                "target": { "type": "integer", "description": "id of the downstream" },
+                "partitioning": { "type": "string", "enum": ["forward", "hash", "broadcast"] },
+            }),
+        )
+    }
+}
+
+impl ApiSchema for ResourceGraph {
+    const NAME: &'static str = "ResourceGraph";
+
+    fn schema() -> Value {
+        object(
+            &["name", "namespace", "status", "nodes", "links"],
+            json!({
+                "name": { "type": "string" },
+                "namespace": { "type": "string" },
+                "status": { "type": "string" },
+                "nodes": array_of(GraphNode::reference()),
+                "links": array_of(GraphLink::reference()),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for ResourceDefinition {
+    const NAME: &'static str = "ResourceDefinition";
+
+    fn schema() -> Value {
+        object(
+            &["namespace", "name"],
+            json!({
+                "kind": { "type": "string", "enum": ["dataflow"], "default": "dataflow" },
+                "namespace": { "type": "string" },
+                "name": { "type": "string", "description": "the job id of the dataflow" },
+                "labels": labels(),
+                "resource_version": resource_version(),
+                "dataflow": {
+                    "type": "object",
+                    "description": "the fields of the protobuf message `Dataflow`, the oneof fields are keyed by the snake case name of their cases",
+                },
+                "operators": {
+                    "type": "object",
+                    "additionalProperties": OperatorSettings::reference(),
+                    "propertyNames": { "pattern": "^[0-9]+$" },
+                    "description": "the operator-level settings keyed by the ids of the operators of the dataflow, they override the same fields of the operators",
+                },
+            }),
+        )
+    }
+}
+
+impl ApiSchema for UpdateResourceResponse {
+    const NAME: &'static str = "UpdateResourceResponse";
+
+    fn schema() -> Value {
+        json!({
+            "allOf": [
+                ResourceSummary::reference(),
+                object(
+                    &["restarted_operators"],
+                    json!({
+                        "restarted_operators": array_of(json!({ "type": "integer" })),
+                        "warnings": array_of(json!({ "type": "string" })),
+                    }),
+                ),
+            ]
+        })
+    }
+}
+
+impl ApiSchema for CreateResourceResult {
+    const NAME: &'static str = "CreateResourceResult";
+
+    fn schema() -> Value {
+        object(
+            &["name", "namespace"],
+            json!({
+                "name": { "type": "string" },
+                "namespace": { "type": "string" },
+                "status": { "type": "string" },
+                "error": ApiError::reference(),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for CreateResourcesResponse {
+    const NAME: &'static str = "CreateResourcesResponse";
+
+    fn schema() -> Value {
+        object(
+            &["results"],
+            json!({ "results": array_of(CreateResourceResult::reference()) }),
+        )
+    }
+}
+
+impl ApiSchema for BatchResourceResult {
+    const NAME: &'static str = "BatchResourceResult";
+
+    fn schema() -> Value {
+        object(
+            &["name", "namespace", "outcome"],
+            json!({
+                "name": { "type": "string" },
+                "namespace": { "type": "string" },
+                "outcome": { "type": "string", "enum": ["created", "updated", "failed"] },
+                "status": { "type": "string" },
+                "resource_version": { "type": "integer" },
+                "error": ApiError::reference(),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for BatchResourcesResponse {
+    const NAME: &'static str = "BatchResourcesResponse";
+
+    fn schema() -> Value {
+        object(
+            &["status", "results"],
+            json!({
+                "status": { "type": "string", "enum": ["succeeded", "partial", "failed"] },
+                "results": array_of(BatchResourceResult::reference()),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for Operation {
+    const NAME: &'static str = "Operation";
+
+    fn schema() -> Value {
+        object(
+            &["id", "status", "namespaces"],
+            json!({
+                "id": { "type": "string" },
+                "status": { "type": "string", "enum": ["pending", "succeeded", "failed"] },
+                "namespaces": array_of(json!({ "type": "string" })),
+                "result": CreateResourcesResponse::reference(),
+                "error": ApiError::reference(),
+            }),
+        )
+    }
+}
+
+impl ApiSchema for StatusEvent {
+    const NAME: &'static str = "StatusEvent";
+
+    fn schema() -> Value {
+        object(
+            &["type"],
+            json!({
+                "type": { "type": "string", "enum": ["status", "operator", "failover", "checkpoint", "deleted"] },
+                "operatorId": { "type": "integer" },
+                "from": { "type": "string" },
+                "to": { "type": "string" },
+                "restartCount": { "type": "integer" },
+                "completed": { "type": "integer" },
+                "total": { "type": "integer" },
+            }),
+        )
+    }
+}
+
+fn component<T: ApiSchema>(schemas: &mut Map<String, Value>) {
+    schemas.insert(T::NAME.to_string(), T::schema());
+}
+
+/// a JSON body which may be YAML as well, depending on `Content-Type` of the request or `Accept` of the response
+fn json_or_yaml(schema: Value) -> Value {
+    json!({
+        "application/json": { "schema": schema },
+        "application/yaml": { "schema": schema },
+    })
+}
+
+fn protobuf(message: &str) -> Value {
+    json!({
+        "application/octet-stream": {
+            "schema": { "type": "string", "format": "binary", "description": format!("protobuf message `{message}`") }
+        }
+    })
+}
+
+fn path_param(name: &str, description: &str) -> Value {
+    json!({ "name": name, "in": "path", "required": true, "description": description, "schema": { "type": "string" } })
+}
+
+fn query_param(name: &str, description: &str, schema: Value) -> Value {
+    json!({ "name": name, "in": "query", "required": false, "description": description, "schema": schema })
+}
+
+/// an endpoint of the API server in the OpenAPI document
+struct Endpoint {
+    method: &'static str,
+    path: String,
+    operation: Value,
+    /// the HTTP statuses of the errors besides the ones of the authentication and the internal failures
+    errors: Vec<u16>,
+    authenticated: bool,
+    /// whether the requests are rate limited, see [`RateLimit`](super::middleware::RateLimit)
+    rate_limited: bool,
+}
+
+impl Endpoint {
+    fn new(method: &'static str, path: &str, summary: &str) -> Self {
+        Self {
+            method,
+            path: path.to_string(),
+            operation: json!({ "summary": summary, "responses": {} }),
+            errors: vec![],
+            authenticated: true,
+            rate_limited: true,
+        }
+    }
+
+    fn resource(method: &'static str, path: &str, summary: &str) -> Self {
+        Self::new(method, &format!("{RESOURCES_HANDLER_ROOT}{path}"), summary)
+    }
+
+    fn parameters(mut self, parameters: Vec<Value>) -> Self {
+        self.operation["parameters"] = Value::Array(parameters);
+        self
+    }
+
+    fn request(mut self, content: Value) -> Self {
+        self.operation["requestBody"] = json!({ "required": true, "content": content });
+        self
+    }
+
+    fn response(mut self, status: u16, description: &str, content: Option<Value>) -> Self {
+        let mut response = json!({ "description": description });
+        if let Some(content) = content {
+            response["content"] = content;
+        }
+        self.operation["responses"][status.to_string()] = response;
+        self
+    }
+
+    fn errors(mut self, errors: &[u16]) -> Self {
+        self.errors = errors.to_vec();
+        self
+    }
+
+    fn public(mut self) -> Self {
+        self.authenticated = false;
+        self
+    }
+
+    fn unlimited(mut self) -> Self {
+        self.rate_limited = false;
+        self
+    }
+
+    fn build(mut self) -> Value {
+        let mut errors = self.errors.clone();
+        if self.authenticated {
+            errors.extend([401, 403]);
+        } else {
+            self.operation["security"] = json!([]);
+        }
+        if self.rate_limited {
+            errors.push(429);
+        }
+        // a body which is too large is rejected
+        if self.operation.get("requestBody").is_some() {
+            errors.push(413);
+        }
+        errors.push(500);
+        errors.sort();
+        errors.dedup();
+        for status in errors {
+            self.operation["responses"][status.to_string()] =
+                json!({ "$ref": "#/components/responses/Error" });
+        }
+        self.operation
+    }
+}
+
+fn endpoints() -> Vec<Endpoint> {
+    let namespace = || path_param("namespace", "namespace of the resource");
+    let name = || path_param("name", "the job id of the dataflow");
+    vec![
+        Endpoint::resource("post", "/create", "create resources")
+            .parameters(vec![query_param(
+                "async",
+                "the resources of a JSON or YAML body are created in the background if it's true",
+                json!({ "type": "boolean", "default": false }),
+            )])
+            .request({
+                let mut content = json_or_yaml(ResourceDefinition::reference());
+                content["application/yaml"]["schema"] = json!({
+                    "type": "string",
+                    "description": "one or more YAML documents, each of which is a ResourceDefinition",
+                });
+                content["application/octet-stream"] =
+                    protobuf("CreateResourceRequest")["application/octet-stream"].clone();
+                content
+            })
+            .response(
+                200,
+                "the result of each resource of a JSON or YAML body",
+                Some(json_or_yaml(CreateResourcesResponse::reference())),
+            )
+            .response(
+                201,
+                "the resource of a protobuf body is created",
+                Some(protobuf("CreateResourceResponse")),
+            )
+            .response(
+                202,
+                "the resources are being created in the background",
+                Some(json_or_yaml(Operation::reference())),
+            )
+            .errors(&[400, 429, 503]),
+        Endpoint::new(
+            "post",
+            &format!("{RESOURCES_HANDLER_ROOT}:batch"),
+            "create or update a batch of resources",
+        )
+        .parameters(vec![
+            query_param(
+                "atomic",
+                "none of the resources is applied if any of them is invalid",
+                json!({ "type": "boolean", "default": false }),
+            ),
+            query_param(
+                "strategy",
+                "how the resources with resource versions are updated",
+                json!({ "type": "string", "enum": ["rolling", "recreate"], "default": "rolling" }),
+            ),
+        ])
+        .request({
+            let mut content = json_or_yaml(array_of(ResourceDefinition::reference()));
+            content["application/yaml"]["schema"] = json!({
+                "type": "string",
+                "description": "one or more YAML documents, each of which is a ResourceDefinition",
+            });
+            content
+        })
+        .response(
+            200,
+            "the result of each resource in the order of the resources, the overall status is `failed` if none of them is applied",
+            Some(json_or_yaml(BatchResourcesResponse::reference())),
+        )
+        .errors(&[400, 503]),
+        Endpoint::resource(
+            "get",
+            "/get/{namespace}/{resource_type}/{resource_id}",
+            "get a resource in protobuf",
+        )
+        .parameters(vec![
+            namespace(),
+            json!({ "name": "resource_type", "in": "path", "required": true, "schema": { "type": "integer" } }),
+            path_param("resource_id", "id of the resource"),
+        ])
+        .response(200, "the resource", Some(protobuf("GetResourceResponse")))
+        .errors(&[400, 404, 503]),
+        Endpoint::resource("get", "", "list resources page by page")
+            .parameters(vec![
+                query_param(
+                    "limit",
+                    "the max number of resources in a page",
+                    json!({ "type": "integer" }),
+                ),
+                query_param(
+                    "continue",
+                    "the `continue` token of the previous page",
+                    json!({ "type": "string" }),
+                ),
+                query_param(
+                    "namespace",
+                    "only the resources of the namespace are listed",
+                    json!({ "type": "string" }),
+                ),
+                query_param(
+                    "labelSelector",
+                    "only the resources whose labels match all of the comma-separated requirements are listed, e.g. `team=payments,env!=dev`",
+                    json!({ "type": "string" }),
+                ),
+                query_param(
+                    "fanOut",
+                    "the resources of all namespaces are listed by all of the coordinators, each of which fills a page of its own",
+                    json!({ "type": "boolean", "default": false }),
+                ),
+            ])
+            .response(
+                200,
+                "a page of resources",
+                Some(json_or_yaml(ListResourcesResponse::reference())),
+            )
+            .errors(&[400, 503]),
+        Endpoint::resource("post", "/terminate", "terminate a batch of resources")
+            .request(json!({ "application/json": { "schema": TerminateResourcesRequest::reference() } }))
+            .response(
+                200,
+                "the result of each resource",
+                Some(json!({ "application/json": { "schema": TerminateResourcesResponse::reference() } })),
+            )
+            .errors(&[400, 503]),
+        Endpoint::resource("get", "/{namespace}/{name}", "get the detail of a resource")
+            .parameters(vec![
+                namespace(),
+                name(),
+                query_param(
+                    "view",
+                    "which parts of the operators are responded",
+                    json!({ "type": "string", "enum": ["spec", "status", "full"], "default": "full" }),
+                ),
+            ])
+            .response(
+                200,
+                "the detail of the resource",
+                Some(json_or_yaml(ResourceDetail::reference())),
+            )
+            .errors(&[400, 404, 503]),
+        Endpoint::resource("put", "/{namespace}/{name}", "update a resource")
+            .parameters(vec![
+                namespace(),
+                name(),
+                query_param(
+                    "strategy",
+                    "how the running resource is replaced. The rolling update is not supported yet, so `recreate` must be given",
+                    json!({ "type": "string", "enum": ["rolling", "recreate"], "default": "rolling" }),
+                ),
+            ])
+            .request(json_or_yaml(ResourceDefinition::reference()))
+            .response(
+                200,
+                "the resource is updated. `resource_version` of the body must be the current one, and its namespace and name can't be changed",
+                Some(json_or_yaml(UpdateResourceResponse::reference())),
+            )
+            .errors(&[400, 404, 409, 422, 501, 503]),
+        Endpoint::resource("delete", "/{namespace}/{name}", "terminate a resource")
+            .parameters(vec![
+                namespace(),
+                name(),
+                query_param("mode", "how the operators stop", terminate_mode()),
+            ])
+            .response(
+                202,
+                "the resource is terminated",
+                Some(json!({ "application/json": { "schema": TerminateResourceResult::reference() } })),
+            )
+            .response(
+                409,
+                "some TaskManagers fail to stop the resource",
+                Some(json!({ "application/json": { "schema": TerminateResourceResult::reference() } })),
+            )
+            .errors(&[400, 404, 503]),
+        Endpoint::resource(
+            "get",
+            "/{namespace}/{name}/events",
+            "stream the status changes of a resource",
+        )
+        .parameters(vec![namespace(), name()])
+        .response(
+            200,
+            "server-sent events named by their types, whose data are StatusEvents. The `deleted` event is the last one",
+            Some(json!({
+                "text/event-stream": { "schema": { "type": "string" } },
+                "application/json": { "schema": StatusEvent::reference() },
+            })),
+        )
+        .errors(&[404, 503]),
+        Endpoint::resource(
+            "get",
+            "/{namespace}/{name}/graph",
+            "get the graph of the operators of a resource",
+        )
+        .parameters(vec![
+            namespace(),
+            name(),
+            query_param(
+                "format",
+                "either the node-link structure in JSON or a graphviz document",
+                json!({ "type": "string", "enum": ["json", "dot"], "default": "json" }),
+            ),
+        ])
+        .response(
+            200,
+            "the operators annotated with their runtime status and the edges annotated with their partitioning",
+            Some(json!({
+                "application/json": { "schema": ResourceGraph::reference() },
+                "text/vnd.graphviz": { "schema": { "type": "string" } },
+            })),
+        )
+        .errors(&[400, 404, 503]),
+        Endpoint::new("get", "/operations/{id}", "get an asynchronous operation")
+            .parameters(vec![path_param("id", "id of the operation")])
+            .response(
+                200,
+                "the operation",
+                Some(json_or_yaml(Operation::reference())),
+            )
+            .errors(&[404])
+            .unlimited(),
+        Endpoint::new("get", "/cluster", "get the topology of the TaskManager cluster")
+            .parameters(vec![query_param(
+                "coordinator",
+                "`default` or the region of the coordinator managing the cluster, the default coordinator if it's not given",
+                json!({ "type": "string" }),
+            )])
+            .response(200, "the topology", Some(protobuf("ClusterTopology")))
+            .errors(&[404, 503]),
+        Endpoint::new("get", "/coordinators", "get the health of the coordinators")
+            .response(
+                200,
+                "the health of each coordinator by the outcome of its latest request",
+                Some(json!({ "application/json": { "schema": array_of(CoordinatorHealth::reference()) } })),
+            ),
+        Endpoint::new("get", "/audit", "get the latest mutations in the audit log")
+            .parameters(vec![query_param(
+                "limit",
+                "the number of the latest records, all of the ones kept in memory if it's not given",
+                json!({ "type": "integer", "minimum": 0 }),
+            )])
+            .response(
+                200,
+                "the records kept in memory, the oldest first. It requires the admin role",
+                Some(json!({ "application/json": { "schema": AuditTail::reference() } })),
+            )
+            .errors(&[403]),
+        Endpoint::new("get", "/overview", "the API server is alive")
+            .response(200, "alive", None)
+            .public()
+            .unlimited(),
+        Endpoint::new("get", "/health", "the API server is alive")
+            .response(200, "alive", None)
+            .public()
+            .unlimited(),
+        Endpoint::new(
+            "get",
+            "/ready",
+            "the API server is ready, it's unavailable once the server is shutting down",
+        )
+        .response(200, "ready", None)
+        .errors(&[503])
+        .public()
+        .unlimited(),
+        Endpoint::new(
+            "get",
+            "/healthz",
+            "the liveness probe, the API server is alive while its background probes keep running",
+        )
+        .response(200, "alive", Some(json!({ "application/json": { "schema": ProbeReport::reference() } })))
+        .response(503, "the event loop is stuck", Some(json!({ "application/json": { "schema": ProbeReport::reference() } })))
+        .public()
+        .unlimited(),
+        Endpoint::new(
+            "get",
+            "/readyz",
+            "the readiness probe, which checks the coordinators, the token file and the config by the background probes, and fails once the server is shutting down",
+        )
+        .response(200, "ready", Some(json!({ "application/json": { "schema": ProbeReport::reference() } })))
+        .response(503, "not ready, the failed checks name the dependencies", Some(json!({ "application/json": { "schema": ProbeReport::reference() } })))
+        .public()
+        .unlimited(),
+        Endpoint::new(
+            "get",
+            METRICS_PATH,
+            "the metrics of the requests in the Prometheus text format, they're served on a port of their own instead if it's configured",
+        )
+        .response(
+            200,
+            "the metrics",
+            Some(json!({ "text/plain": { "schema": { "type": "string" } } })),
+        )
+        .errors(&[404])
+        .public()
+        .unlimited(),
+        Endpoint::new("get", OPENAPI_PATH, "this document").response(
+            200,
+            "the OpenAPI document",
+            Some(json!({ "application/json": { "schema": { "type": "object" } } })),
+        ),
+        Endpoint::new(
+            "get",
+            SWAGGER_UI_PATH,
+            "the Swagger UI of this document, it's only served if it's enabled",
+        )
+        .response(
+            200,
+            "the page",
+            Some(json!({ "text/html": { "schema": { "type": "string" } } })),
+        )
+        .errors(&[404]),
+    ]
+}
+
+/// the OpenAPI 3 document of all of the endpoints of the API server
+pub(crate) fn openapi() -> Value {
+    let mut paths = Map::new();
+    for endpoint in endpoints() {
+        let method = endpoint.method;
+        let path = endpoint.path.clone();
+        paths
+            .entry(path)
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .unwrap()
+            .insert(method.to_string(), endpoint.build());
+    }
+
+    let mut schemas = Map::new();
+    component::<ApiErrorDetail>(&mut schemas);
+    component::<ApiError>(&mut schemas);
+    component::<ResourceSummary>(&mut schemas);
+    component::<ListResourcesResponse>(&mut schemas);
+    component::<CoordinatorHealth>(&mut schemas);
+    component::<ProbeCheck>(&mut schemas);
+    component::<ProbeReport>(&mut schemas);
+    component::<AuditRecord>(&mut schemas);
+    component::<AuditTail>(&mut schemas);
+    component::<ResourceRef>(&mut schemas);
+    component::<TerminateResourcesRequest>(&mut schemas);
+    component::<WorkerFailureSummary>(&mut schemas);
+    component::<TerminateResourceResult>(&mut schemas);
+    component::<TerminateResourcesResponse>(&mut schemas);
+    component::<OperatorSettings>(&mut schemas);
+    component::<OperatorDetail>(&mut schemas);
+    component::<ResourceDetail>(&mut schemas);
+    component::<ResourceDefinition>(&mut schemas);
+    component::<UpdateResourceResponse>(&mut schemas);
+    component::<CreateResourceResult>(&mut schemas);
+    component::<CreateResourcesResponse>(&mut schemas);
+    component::<BatchResourceResult>(&mut schemas);
+    component::<BatchResourcesResponse>(&mut schemas);
+    component::<Operation>(&mut schemas);
+    component::<StatusEvent>(&mut schemas);
+    component::<GraphNode>(&mut schemas);
+    component::<GraphLink>(&mut schemas);
+    component::<ResourceGraph>(&mut schemas);
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Lightflus API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "responses": {
+                "Error": {
+                    "description": "the HTTP status is decided by the code of the error. A rate-limited request is responded with 429 and `Retry-After`",
+                    "content": json_or_yaml(ApiError::reference()),
+                }
+            },
+            "securitySchemes": {
+                "bearer": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "required if the API server is configured with a token file. GET requests require the read role, the others require the write role, and the audit log requires the admin role",
+                }
+            }
+        },
+        "security": [{ "bearer": [] }],
+    })
+}
+
+/// a page loading the Swagger UI from the CDN, which renders the document
+pub(crate) fn swagger_ui_page() -> String {
+    format!(
+        r##"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8"/>
+  <title>Lightflus API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css"/>
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>window.ui = SwaggerUIBundle({{ url: "{OPENAPI_PATH}", dom_id: "#swagger-ui" }});</script>
+</body>
+</html>
+"##
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::{
+        apiserver::{
+            audit::{AuditRecord, AuditTail},
+            events::StatusEvent,
+            graph::{GraphLink, GraphNode, Partitioning, ResourceGraph},
+            handler::coordinator::CoordinatorHealth,
+            operations::{Operation, OperationStatus},
+            operators::{ErrorPolicySpec, OperatorSettings, StateLimitSpec},
+            probe::{CheckStatus, ProbeCheck, ProbeReport},
+            types::{
+                BatchOutcome, BatchResourceResult, BatchResourcesResponse, BatchStatus,
+                CreateResourceResult, CreateResourcesResponse, ListResourcesResponse,
+                OperatorDetail, ResourceDefinition, ResourceDetail, ResourceKind, ResourceRef,
+                ResourceSummary, TerminateMode, TerminateResourceResult, TerminateResourcesRequest,
+                TerminateResourcesResponse, UpdateResourceResponse, WorkerFailureSummary,
+            },
+        },
+        errors::apiserver::{ApiError, ApiErrorCode, ApiErrorDetail},
+    };
+
+    use super::{openapi, ApiSchema, ERROR_CODES};
+
+    /// the properties and the required fields of a schema, the ones of `allOf` are merged
+    fn fields(schema: &Value, schemas: &Value) -> (Vec<String>, Vec<String>) {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            return fields(&schemas[name], schemas);
+        }
+        let mut properties = schema["properties"]
+            .as_object()
+            .map(|properties| properties.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let mut required = schema["required"]
+            .as_array()
+            .map(|required| {
+                required
+                    .iter()
+                    .map(|field| field.as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        schema["allOf"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .for_each(|schema| {
+                let (more_properties, more_required) = fields(schema, schemas);
+                properties.extend(more_properties);
+                required.extend(more_required);
+            });
+        (properties, required)
+    }
+
+    /// every field of the serialized body is declared by its schema, and every required field of the schema is serialized
+    fn assert_schema<T: ApiSchema + serde::Serialize>(body: &T) {
+        let document = openapi();
+        let schemas = &document["components"]["schemas"];
+        let (properties, required) = fields(&T::reference(), schemas);
+        let body = serde_json::to_value(body).unwrap();
+        let keys = body
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.iter().for_each(|key| {
+            assert!(
+                properties.contains(key),
+                "{} is not declared by {}",
+                key,
+                T::NAME
+            )
+        });
+        required.iter().for_each(|field| {
+            assert!(
+                keys.contains(field),
+                "{} is required by {} but not serialized",
+                field,
+                T::NAME
+            )
+        });
+    }
+
+    /// all of the `$ref`s in the value
+    fn references(value: &Value, refs: &mut Vec<String>) {
+        match value {
+            Value::Object(object) => object.iter().for_each(|(key, value)| match value {
+                Value::String(reference) if key == "$ref" => refs.push(reference.clone()),
+                _ => references(value, refs),
+            }),
+            Value::Array(values) => values.iter().for_each(|value| references(value, refs)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_openapi_document() {
+        let text = serde_json::to_string(&openapi()).unwrap();
+        let document: Value = serde_json::from_str(&text).unwrap();
+
+        assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(!document["info"]["version"].as_str().unwrap().is_empty());
+        assert_eq!(
+            document["components"]["securitySchemes"]["bearer"]["scheme"],
+            "bearer"
+        );
+        // health checks are not authenticated, the others are
+        assert_eq!(
+            document["paths"]["/health"]["get"]["security"],
+            serde_json::json!([])
+        );
+        assert!(document["paths"]["/resources"]["get"]["responses"]["401"].is_object());
+        // the polls of the operations and the health checks are not rate limited, the others are
+        assert!(document["paths"]["/resources"]["get"]["responses"]["429"].is_object());
+        assert!(document["paths"]["/resources"]["get"]["responses"]["413"].is_null());
+        assert!(document["paths"]["/resources/create"]["post"]["responses"]["413"].is_object());
+        for path in [
+            "/operations/{id}",
+            "/health",
+            "/ready",
+            "/healthz",
+            "/readyz",
+        ] {
+            assert!(document["paths"][path]["get"]["responses"]["429"].is_null());
+        }
+
+        let mut refs = vec![];
+        references(&document, &mut refs);
+        assert!(!refs.is_empty());
+        refs.iter().for_each(|reference| {
+            let pointer = reference.trim_start_matches('#');
+            assert!(
+                document.pointer(pointer).is_some(),
+                "{} is not resolved",
+                reference
+            );
+        });
+
+        document["paths"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .flat_map(|(_, item)| item.as_object().unwrap().values())
+            .for_each(|operation| {
+                assert!(operation["responses"]["500"].is_object());
+                assert!(!operation["summary"].as_str().unwrap().is_empty());
+            });
+    }
+
+    #[test]
+    fn test_error_codes() {
+        ERROR_CODES.iter().for_each(|code| {
+            serde_json::from_value::<ApiErrorCode>(Value::String(code.to_string())).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_schemas_of_bodies() {
+        let error = ApiError {
+            code: ApiErrorCode::InvalidArgument,
+            message: "invalid".to_string(),
+            details: vec![ApiErrorDetail {
+                field: Some("dataflow".to_string()),
+                message: "invalid".to_string(),
+                document: Some(0),
+                line: Some(1),
+                column: Some(1),
+            }],
+            request_id: Some("request".to_string()),
+            status: None,
+            retry_after: None,
+        };
+        assert_schema(&error);
+        assert_schema(&error.details[0]);
+
+        let summary = ResourceSummary {
+            id: "job".to_string(),
+            name: "job".to_string(),
+            namespace: "default".to_string(),
+            status: "degraded".to_string(),
+            status_reason: Some("partition on tm-1:8792 fails to start".to_string()),
+            operator_count: 1,
+            created_at: 1,
+            updated_at: 1,
+            labels: [("team".to_string(), "payments".to_string())].into(),
+            resource_version: 1,
+            coordinator: Some("eu".to_string()),
+        };
+        assert_schema(&summary);
+        assert_schema(&ListResourcesResponse {
+            resources: vec![summary.clone()],
+            continue_token: Some("token".to_string()),
+            unavailable: vec!["us".to_string()],
+        });
+        assert_schema(&CoordinatorHealth {
+            name: "eu".to_string(),
+            endpoints: vec!["http://coordinator-eu:8791".to_string()],
+            available: Some(false),
+            error: Some("connection refused".to_string()),
+            checked_at: Some(1),
+        });
+        assert_schema(&ProbeReport::from(vec![ProbeCheck {
+            name: "coordinators".to_string(),
+            status: CheckStatus::Failed,
+            message: Some("no coordinator is reachable".to_string()),
+            checked_at: Some(1),
+        }]));
+        assert_schema(&ProbeCheck {
+            name: "coordinators".to_string(),
+            status: CheckStatus::Failed,
+            message: Some("no coordinator is reachable".to_string()),
+            checked_at: Some(1),
+        });
+        let record = AuditRecord {
+            timestamp: 1,
+            request_id: "request".to_string(),
+            identity: "alice".to_string(),
+            verb: "delete".to_string(),
+            method: "DELETE".to_string(),
+            path: "/resources/default/job".to_string(),
+            namespace: Some("default".to_string()),
+            name: Some("job".to_string()),
+            status: 202,
+            latency_ms: 1,
+            body_sha256: "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+                .to_string(),
+        };
+        assert_schema(&record);
+        assert_schema(&AuditTail {
+            records: vec![record],
+        });
+
+        let settings = OperatorSettings {
+            error_policy: Some(ErrorPolicySpec::Fail {}),
+            state_limit: Some(StateLimitSpec {
+                max_bytes: 1024,
+                policy: None,
+            }),
+            processing_timeout: Some(500),
+            headers: [("source".to_string(), "orders".to_string())].into(),
+            input_schema: Some(Default::default()),
+            output_schema: Some(Default::default()),
+            ..Default::default()
+        };
+        assert_schema(&settings.resolve());
+        let operator = OperatorDetail {
+            id: 0,
+            kind: Some("mapper".to_string()),
+            upstreams: Some(vec![]),
+            resolved: Some(settings.resolve()),
+            settings: Some(settings.clone()),
+            status: Some("running".to_string()),
+            host: Some("localhost:8792".to_string()),
+            last_heartbeat_at: Some(1),
+            restart_count: Some(0),
+        };
+        assert_schema(&operator);
+        assert_schema(&ResourceDetail {
+            summary: summary.clone(),
+            operators: vec![operator],
+        });
+        assert_schema(&UpdateResourceResponse {
+            summary,
+            restarted_operators: vec![0],
+            warnings: vec!["warning".to_string()],
+        });
+
+        let resource = ResourceRef {
+            id: "job".to_string(),
+            namespace: "default".to_string(),
+        };
+        assert_schema(&resource);
+        assert_schema(&TerminateResourcesRequest {
+            resources: vec![resource],
+            mode: TerminateMode::Force,
+        });
+        let terminated = TerminateResourceResult {
+            id: "job".to_string(),
+            namespace: "default".to_string(),
+            status: Some("closing".to_string()),
+            error: Some(error.clone()),
+            worker_failures: vec![WorkerFailureSummary {
+                node: "localhost:8792".to_string(),
+                error: error.clone(),
+            }],
+        };
+        assert_schema(&terminated.worker_failures[0]);
+        assert_schema(&terminated);
+        assert_schema(&TerminateResourcesResponse {
+            results: vec![terminated],
+        });
+
+        assert_schema(&ResourceDefinition {
+            kind: ResourceKind::Dataflow,
+            namespace: "default".to_string(),
+            name: "job".to_string(),
+            labels: [("team".to_string(), "payments".to_string())].into(),
+            resource_version: Some(1),
+            dataflow: Some(Default::default()),
+            operators: [(0, settings)].into(),
+        });
+        let created = CreateResourcesResponse {
+            results: vec![CreateResourceResult {
+                name: "job".to_string(),
+                namespace: "default".to_string(),
+                status: Some("starting".to_string()),
+                error: Some(error.clone()),
+            }],
+        };
+        assert_schema(&created.results[0]);
+        assert_schema(&created);
+        let applied = BatchResourcesResponse {
+            status: BatchStatus::Partial,
+            results: vec![BatchResourceResult {
+                name: "job".to_string(),
+                namespace: "default".to_string(),
+                outcome: BatchOutcome::Updated,
+                status: Some("starting".to_string()),
+                resource_version: Some(2),
+                error: Some(error.clone()),
+            }],
+        };
+        assert_schema(&applied.results[0]);
+        assert_schema(&applied);
+        assert_schema(&Operation {
+            id: "operation".to_string(),
+            status: OperationStatus::Failed,
+            namespaces: vec!["default".to_string()],
+            result: Some(created),
+            error: Some(error),
+        });
+
+        assert_schema(&StatusEvent::Failover {
+            operator_id: 0,
+            from: "a:1".to_string(),
+            to: "b:1".to_string(),
+            restart_count: 1,
+        });
+        assert_schema(&StatusEvent::Checkpoint {
+            completed: 1,
+            total: 1,
+        });
+
+        let graph = ResourceGraph {
+            name: "job".to_string(),
+            namespace: "default".to_string(),
+            status: "running".to_string(),
+            nodes: vec![GraphNode {
+                id: 0,
+                kind: "source".to_string(),
+                parallelism: 1,
+                host: Some("localhost:8792".to_string()),
+                status: "running".to_string(),
+                color: "palegreen".to_string(),
+            }],
+            links: vec![GraphLink {
+                source: 0,
+                target: 1,
+                partitioning: Partitioning::Hash,
+            }],
+        };
+        assert_schema(&graph.nodes[0]);
+        assert_schema(&graph.links[0]);
+        assert_schema(&graph);
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Notify;
+
+use crate::errors::apiserver::{ApiError, ApiErrorCode};
+
+use super::{auth::Caller, config::OperationsConfig, types::CreateResourcesResponse};
+
+/// the state of an [`Operation`]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum OperationStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// A request which is served in the background, e.g. `POST /resources/create?async=true`.
+/// It's polled by `GET /operations/{id}` until it's succeeded or failed
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Operation {
+    pub id: String,
+    pub status: OperationStatus,
+    /// the namespaces of the resources, the caller must be allowed to access all of them to get the operation
+    pub namespaces: Vec<String>,
+    /// the body which the synchronous request would respond. It's present once the operation is done
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub result: Option<CreateResourcesResponse>,
+    /// why the operation is failed
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<ApiError>,
+}
+
+impl Operation {
+    pub fn authorize(&self, caller: &Caller) -> Result<(), ApiError> {
+        self.namespaces
+            .iter()
+            .try_for_each(|namespace| caller.authorize_namespace(namespace))
+    }
+}
+
+struct OperationEntry {
+    operation: Operation,
+    /// the submission which the operation serves, a duplicate of it is served by the same operation while it's pending
+    key: String,
+    /// when the operation is done, it's expired after the ttl
+    done_at: Option<Instant>,
+}
+
+/// [`OperationStore`] keeps the operations in memory. The done operations are removed once they're expired,
+/// or the oldest of them is evicted if the store is full. New operations are rejected if all of the kept ones are pending
+pub(crate) struct OperationStore {
+    entries: Mutex<HashMap<String, OperationEntry>>,
+    capacity: usize,
+    ttl: Duration,
+    /// notified whenever an operation is done
+    done: Notify,
+}
+
+impl OperationStore {
+    pub(crate) fn new(config: &OperationsConfig) -> Self {
+        Self {
+            entries: Default::default(),
+            capacity: config.capacity,
+            ttl: config.get_ttl(),
+            done: Notify::new(),
+        }
+    }
+
+    /// create a pending operation of the submission identified by the key. The second value is false
+    /// if the submission is a duplicate of a pending one, whose operation is returned instead
+    pub(crate) fn submit(
+        &self,
+        key: String,
+        namespaces: Vec<String>,
+    ) -> Result<(Operation, bool), ApiError> {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        let now = Instant::now();
+        let ttl = self.ttl;
+        entries.retain(|_, entry| {
+            entry
+                .done_at
+                .map(|done_at| now.duration_since(done_at) < ttl)
+                .unwrap_or(true)
+        });
+
+        if let Some(entry) = entries
+            .values()
+            .find(|entry| entry.done_at.is_none() && entry.key == key)
+        {
+            return Ok((entry.operation.clone(), false));
+        }
+
+        if entries.len() >= self.capacity {
+            let oldest = entries
+                .iter()
+                .filter_map(|(id, entry)| entry.done_at.map(|done_at| (done_at, id.clone())))
+                .min();
+            match oldest {
+                Some((_, id)) => {
+                    entries.remove(&id);
+                }
+                None => {
+                    return Err(ApiError::new(
+                        ApiErrorCode::ResourceExhausted,
+                        format!("too many pending operations, the max is {}", self.capacity),
+                    ))
+                }
+            }
+        }
+
+        let operation = Operation {
+            id: common::utils::uuid(),
+            status: OperationStatus::Pending,
+            namespaces,
+            result: None,
+            error: None,
+        };
+        entries.insert(
+            operation.id.clone(),
+            OperationEntry {
+                operation: operation.clone(),
+                key,
+                done_at: None,
+            },
+        );
+        Ok((operation, true))
+    }
+
+    /// the operation is failed if any of the resources fails to be created. It's ignored if the operation is interrupted
+    pub(crate) fn complete(&self, id: &str, result: CreateResourcesResponse) {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(entry) = entries.get_mut(id).filter(|entry| entry.done_at.is_none()) {
+            let failures = result
+                .results
+                .iter()
+                .filter_map(|result| result.error.as_ref())
+                .collect::<Vec<_>>();
+            entry.operation.error = failures.first().map(|err| {
+                ApiError::new(
+                    err.code,
+                    format!(
+                        "{} of {} resources failed to be created",
+                        failures.len(),
+                        result.results.len()
+                    ),
+                )
+            });
+            entry.operation.status = if entry.operation.error.is_some() {
+                OperationStatus::Failed
+            } else {
+                OperationStatus::Succeeded
+            };
+            entry.operation.result = Some(result);
+            entry.done_at = Some(Instant::now());
+        }
+        drop(entries);
+        self.done.notify_waiters();
+    }
+
+    fn has_pending(&self) -> bool {
+        let entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        entries.values().any(|entry| entry.done_at.is_none())
+    }
+
+    /// wait until none of the operations is pending
+    pub(crate) async fn wait_pending(&self) {
+        loop {
+            let done = self.done.notified();
+            if !self.has_pending() {
+                return;
+            }
+            done.await;
+        }
+    }
+
+    /// fail the pending operations since they're interrupted by the shutdown of the API server.
+    /// Their resources may be partially created. The ids of them are returned
+    pub(crate) fn interrupt_pending(&self) -> Vec<String> {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        let now = Instant::now();
+        let ids = entries
+            .values_mut()
+            .filter(|entry| entry.done_at.is_none())
+            .map(|entry| {
+                entry.operation.status = OperationStatus::Failed;
+                entry.operation.error = Some(ApiError::new(
+                    ApiErrorCode::Unavailable,
+                    "the operation is interrupted by the shutdown of the API server",
+                ));
+                entry.done_at = Some(now);
+                entry.operation.id.clone()
+            })
+            .collect();
+        drop(entries);
+        self.done.notify_waiters();
+        ids
+    }
+
+    /// the operation is not found once it's expired or evicted
+    pub(crate) fn get(&self, id: &str) -> Option<Operation> {
+        let entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        entries
+            .get(id)
+            .filter(|entry| {
+                entry
+                    .done_at
+                    .map(|done_at| done_at.elapsed() < self.ttl)
+                    .unwrap_or(true)
+            })
+            .map(|entry| entry.operation.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        apiserver::{
+            config::OperationsConfig,
+            types::{CreateResourceResult, CreateResourcesResponse},
+        },
+        errors::apiserver::{ApiError, ApiErrorCode},
+    };
+
+    use super::{OperationStatus, OperationStore};
+
+    fn new_result(error: Option<ApiError>) -> CreateResourcesResponse {
+        CreateResourcesResponse {
+            results: vec![CreateResourceResult {
+                name: "job".to_string(),
+                namespace: "default".to_string(),
+                status: error.is_none().then(|| "starting".to_string()),
+                error,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_operation_lifecycle() {
+        let store = OperationStore::new(&OperationsConfig {
+            capacity: 10,
+            ttl: 60,
+        });
+        let (operation, created) = store
+            .submit("spec-a".to_string(), vec!["default".to_string()])
+            .unwrap();
+        assert!(created);
+        assert_eq!(operation.status, OperationStatus::Pending);
+
+        // the duplicate of the pending submission is served by the same operation
+        let (duplicate, created) = store
+            .submit("spec-a".to_string(), vec!["default".to_string()])
+            .unwrap();
+        assert!(!created);
+        assert_eq!(duplicate.id, operation.id);
+
+        store.complete(&operation.id, new_result(None));
+        let done = store.get(&operation.id).unwrap();
+        assert_eq!(done.status, OperationStatus::Succeeded);
+        assert!(done.result.is_some());
+        assert!(done.error.is_none());
+
+        // a new operation is created once the last one is done
+        let (resubmitted, created) = store
+            .submit("spec-a".to_string(), vec!["default".to_string()])
+            .unwrap();
+        assert!(created);
+        assert_ne!(resubmitted.id, operation.id);
+
+        store.complete(
+            &resubmitted.id,
+            new_result(Some(ApiError::new(ApiErrorCode::AlreadyExists, "exists"))),
+        );
+        let failed = store.get(&resubmitted.id).unwrap();
+        assert_eq!(failed.status, OperationStatus::Failed);
+        assert_eq!(failed.error.unwrap().code, ApiErrorCode::AlreadyExists);
+
+        assert!(store.get("unknown").is_none());
+    }
+
+    #[test]
+    fn test_operation_store_bounds() {
+        let store = OperationStore::new(&OperationsConfig {
+            capacity: 2,
+            ttl: 60,
+        });
+        let (first, _) = store.submit("a".to_string(), vec![]).unwrap();
+        let (second, _) = store.submit("b".to_string(), vec![]).unwrap();
+
+        // all of the kept operations are pending
+        let err = store.submit("c".to_string(), vec![]).unwrap_err();
+        assert_eq!(err.code, ApiErrorCode::ResourceExhausted);
+
+        // the oldest done operation is evicted
+        store.complete(&first.id, new_result(None));
+        store.complete(&second.id, new_result(None));
+        let (third, _) = store.submit("c".to_string(), vec![]).unwrap();
+        assert!(store.get(&first.id).is_none());
+        assert!(store.get(&second.id).is_some());
+        assert!(store.get(&third.id).is_some());
+
+        // the done operations are expired after the ttl
+        let mut store = OperationStore::new(&OperationsConfig {
+            capacity: 2,
+            ttl: 60,
+        });
+        store.ttl = Duration::from_millis(10);
+        let (operation, _) = store.submit("a".to_string(), vec![]).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        // a pending operation never expires
+        assert!(store.get(&operation.id).is_some());
+        store.complete(&operation.id, new_result(None));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(store.get(&operation.id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_operation_interrupted() {
+        let store = std::sync::Arc::new(OperationStore::new(&OperationsConfig::default()));
+        let (done, _) = store.submit("a".to_string(), vec![]).unwrap();
+        let (pending, _) = store.submit("b".to_string(), vec![]).unwrap();
+
+        // it waits until all of the operations are done
+        let waiter = tokio::spawn({
+            let store = store.clone();
+            async move { store.wait_pending().await }
+        });
+        store.complete(&done.id, new_result(None));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiter.is_finished());
+        assert_eq!(store.interrupt_pending(), vec![pending.id.clone()]);
+        waiter.await.unwrap();
+
+        let interrupted = store.get(&pending.id).unwrap();
+        assert_eq!(interrupted.status, OperationStatus::Failed);
+        assert_eq!(interrupted.error.unwrap().code, ApiErrorCode::Unavailable);
+        // the result of an interrupted operation is ignored
+        store.complete(&pending.id, new_result(None));
+        assert_eq!(
+            store.get(&pending.id).unwrap().status,
+            OperationStatus::Failed
+        );
+        assert_eq!(
+            store.get(&done.id).unwrap().status,
+            OperationStatus::Succeeded
+        );
+    }
+}
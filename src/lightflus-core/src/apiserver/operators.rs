@@ -0,0 +1,667 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use proto::common::{
+    error_policy, payload_schema, state_limit, Backoff, Chaining, DataTypeEnum, ErrorPolicy,
+    OperatorInfo, PayloadSchema, StateLimit, Time,
+};
+
+use crate::errors::apiserver::ApiErrorDetail;
+
+/// whether an operator may be chained with its neighbours into one task
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ChainingMode {
+    #[default]
+    Enabled,
+    /// the operator is never chained, e.g. for debugging
+    Disabled,
+}
+
+/// exponential backoff of the retries in milliseconds, with optional full jitter
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct BackoffSpec {
+    #[serde(default)]
+    pub base: u64,
+    #[serde(default)]
+    pub max: u64,
+    #[serde(default)]
+    pub jitter: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct RetrySpec {
+    /// max number of retries, it must be positive
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backoff: Option<BackoffSpec>,
+    /// what happens if the event still fails after the retries, it can't be another retry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<Box<ErrorPolicySpec>>,
+}
+
+/// what happens to an event which fails to be processed, keyed by the policy, e.g. `error_policy: { retry: { attempts: 3 } }`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ErrorPolicySpec {
+    /// the operator fails
+    Fail {},
+    /// the event is dropped
+    Skip {},
+    Retry(RetrySpec),
+    /// the event is sent to the operator `sink`, which must be a downstream of the operator
+    DeadLetter {
+        sink: u32,
+    },
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StateLimitPolicy {
+    #[default]
+    RejectNewKeys,
+    EvictOldest,
+    Fail,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct StateLimitSpec {
+    /// it must be positive
+    pub max_bytes: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<StateLimitPolicy>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DataType {
+    Bigint,
+    Number,
+    Null,
+    String,
+    Boolean,
+    Object,
+    Array,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct PayloadFieldSpec {
+    pub name: String,
+    /// any type is allowed if it's absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<DataType>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SchemaValidation {
+    /// the schema is only checked at submission
+    #[default]
+    Disabled,
+    Sampling,
+    Strict,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Default)]
+pub(crate) struct PayloadSchemaSpec {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<PayloadFieldSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation: Option<SchemaValidation>,
+    /// the fraction of the validated input events in `sampling` validation, in (0, 1]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_fraction: Option<f64>,
+}
+
+/// The operator-level settings of a resource, keyed by the operator ids under `operators`. They override the same fields
+/// of the operators in the dataflow, and all of them are optional. In `GET /resources/{namespace}/{name}`, the settings of an
+/// operator are the ones which differ from the defaults, and the defaults are filled by [`OperatorSettings::resolve`]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Default)]
+pub(crate) struct OperatorSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chaining: Option<ChainingMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_policy: Option<ErrorPolicySpec>,
+    /// the limit of the keyed state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_limit: Option<StateLimitSpec>,
+    /// the milliseconds an event may take in the operator before it's abandoned to the error policy without retries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub processing_timeout: Option<u64>,
+    /// metadata headers added to the events emitted by the operator
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_schema: Option<PayloadSchemaSpec>,
+    /// the schema of the emitted events, it's checked against the input schemas of the downstreams at submission
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<PayloadSchemaSpec>,
+}
+
+impl OperatorSettings {
+    /// override the fields of the operator which are set
+    pub fn apply(&self, operator: &mut OperatorInfo) {
+        if let Some(chaining) = self.chaining {
+            operator.set_chaining(match chaining {
+                ChainingMode::Enabled => Chaining::Enabled,
+                ChainingMode::Disabled => Chaining::Disabled,
+            });
+        }
+        if let Some(error_policy) = self.error_policy.as_ref() {
+            operator.error_policy = Some(error_policy.into());
+        }
+        if let Some(state_limit) = self.state_limit.as_ref() {
+            operator.state_limit = Some(state_limit.into());
+        }
+        if let Some(millis) = self.processing_timeout {
+            operator.processing_timeout = Some(Time {
+                millis,
+                ..Default::default()
+            });
+        }
+        operator.headers.extend(self.headers.clone());
+        if let Some(schema) = self.input_schema.as_ref() {
+            operator.input_schema = Some(schema.into());
+        }
+        if let Some(schema) = self.output_schema.as_ref() {
+            operator.output_schema = Some(schema.into());
+        }
+    }
+
+    /// the settings with the defaults of the runtime filled: chaining is enabled, failed events are skipped,
+    /// a retry has no backoff and skips the events it fails to recover, and a schema is not validated at runtime
+    pub fn resolve(&self) -> Self {
+        Self {
+            chaining: Some(self.chaining.unwrap_or_default()),
+            error_policy: Some(
+                self.error_policy
+                    .as_ref()
+                    .map(ErrorPolicySpec::resolve)
+                    .unwrap_or(ErrorPolicySpec::Skip {}),
+            ),
+            state_limit: self.state_limit.map(|state_limit| StateLimitSpec {
+                policy: Some(state_limit.policy.unwrap_or_default()),
+                ..state_limit
+            }),
+            processing_timeout: self.processing_timeout,
+            headers: self.headers.clone(),
+            input_schema: self.input_schema.as_ref().map(PayloadSchemaSpec::resolve),
+            output_schema: self.output_schema.as_ref().map(PayloadSchemaSpec::resolve),
+        }
+    }
+
+    /// the same checks as the ones of the dataflow, but the errors are detailed by the fields under `path`
+    pub fn validate(&self, path: &str) -> Result<(), ApiErrorDetail> {
+        if let Some(error_policy) = self.error_policy.as_ref() {
+            error_policy.validate(&format!("{path}.error_policy"))?;
+        }
+        if let Some(state_limit) = self.state_limit.as_ref() {
+            if state_limit.max_bytes == 0 {
+                return Err(ApiErrorDetail::new("max_bytes must be positive")
+                    .with_field(format!("{path}.state_limit.max_bytes")));
+            }
+        }
+        if self.processing_timeout == Some(0) {
+            return Err(ApiErrorDetail::new("processing_timeout must be positive")
+                .with_field(format!("{path}.processing_timeout")));
+        }
+        if self.headers.keys().any(|name| name.is_empty()) {
+            return Err(ApiErrorDetail::new("header name must not be empty")
+                .with_field(format!("{path}.headers")));
+        }
+        if let Some(schema) = self.input_schema.as_ref() {
+            schema.validate(&format!("{path}.input_schema"))?;
+        }
+        if let Some(schema) = self.output_schema.as_ref() {
+            schema.validate(&format!("{path}.output_schema"))?;
+        }
+        Ok(())
+    }
+}
+
+/// the settings which differ from the defaults
+impl From<&OperatorInfo> for OperatorSettings {
+    fn from(operator: &OperatorInfo) -> Self {
+        Self {
+            chaining: match operator.chaining() {
+                Chaining::Enabled => None,
+                Chaining::Disabled => Some(ChainingMode::Disabled),
+            },
+            error_policy: operator
+                .error_policy
+                .as_ref()
+                .and_then(|error_policy| error_policy.policy.as_ref())
+                .map(ErrorPolicySpec::from),
+            state_limit: operator.state_limit.as_ref().map(StateLimitSpec::from),
+            processing_timeout: operator
+                .processing_timeout
+                .as_ref()
+                .map(|timeout| timeout.to_duration().num_milliseconds() as u64)
+                .filter(|millis| *millis > 0),
+            headers: operator
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            input_schema: operator.input_schema.as_ref().map(PayloadSchemaSpec::from),
+            output_schema: operator.output_schema.as_ref().map(PayloadSchemaSpec::from),
+        }
+    }
+}
+
+impl ErrorPolicySpec {
+    fn resolve(&self) -> Self {
+        match self {
+            Self::Retry(retry) => Self::Retry(RetrySpec {
+                attempts: retry.attempts,
+                backoff: Some(retry.backoff.unwrap_or_default()),
+                fallback: Some(Box::new(
+                    retry
+                        .fallback
+                        .as_ref()
+                        .map(|fallback| fallback.resolve())
+                        .unwrap_or(Self::Skip {}),
+                )),
+            }),
+            policy => policy.clone(),
+        }
+    }
+
+    fn validate(&self, path: &str) -> Result<(), ApiErrorDetail> {
+        match self {
+            Self::Retry(retry) => {
+                if retry.attempts == 0 {
+                    return Err(ApiErrorDetail::new("attempts of retry must be positive")
+                        .with_field(format!("{path}.retry.attempts")));
+                }
+                match retry.fallback.as_deref() {
+                    Some(Self::Retry(_)) => Err(ApiErrorDetail::new(
+                        "fallback of retry can't be another retry",
+                    )
+                    .with_field(format!("{path}.retry.fallback"))),
+                    _ => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<&ErrorPolicySpec> for ErrorPolicy {
+    fn from(spec: &ErrorPolicySpec) -> Self {
+        let policy = match spec {
+            ErrorPolicySpec::Fail {} => error_policy::Policy::Fail(Default::default()),
+            ErrorPolicySpec::Skip {} => error_policy::Policy::Skip(Default::default()),
+            ErrorPolicySpec::Retry(retry) => {
+                error_policy::Policy::Retry(Box::new(error_policy::Retry {
+                    attempts: retry.attempts,
+                    backoff: retry.backoff.map(|backoff| Backoff {
+                        base: backoff.base,
+                        max: backoff.max,
+                        jitter: backoff.jitter,
+                    }),
+                    fallback: retry
+                        .fallback
+                        .as_ref()
+                        .map(|fallback| Box::new(ErrorPolicy::from(fallback.as_ref()))),
+                }))
+            }
+            ErrorPolicySpec::DeadLetter { sink } => {
+                error_policy::Policy::DeadLetter(error_policy::DeadLetter { sink: *sink })
+            }
+        };
+        Self {
+            policy: Some(policy),
+        }
+    }
+}
+
+impl From<&error_policy::Policy> for ErrorPolicySpec {
+    fn from(policy: &error_policy::Policy) -> Self {
+        match policy {
+            error_policy::Policy::Fail(_) => Self::Fail {},
+            error_policy::Policy::Skip(_) => Self::Skip {},
+            error_policy::Policy::Retry(retry) => Self::Retry(RetrySpec {
+                attempts: retry.attempts,
+                backoff: retry.backoff.as_ref().map(|backoff| BackoffSpec {
+                    base: backoff.base,
+                    max: backoff.max,
+                    jitter: backoff.jitter,
+                }),
+                fallback: retry
+                    .fallback
+                    .as_ref()
+                    .and_then(|fallback| fallback.policy.as_ref())
+                    .map(|fallback| Box::new(fallback.into())),
+            }),
+            error_policy::Policy::DeadLetter(dead_letter) => Self::DeadLetter {
+                sink: dead_letter.sink,
+            },
+        }
+    }
+}
+
+impl From<&StateLimitSpec> for StateLimit {
+    fn from(spec: &StateLimitSpec) -> Self {
+        let mut state_limit = StateLimit {
+            max_bytes: spec.max_bytes,
+            ..Default::default()
+        };
+        state_limit.set_policy(match spec.policy.unwrap_or_default() {
+            StateLimitPolicy::RejectNewKeys => state_limit::Policy::RejectNewKeys,
+            StateLimitPolicy::EvictOldest => state_limit::Policy::EvictOldest,
+            StateLimitPolicy::Fail => state_limit::Policy::Fail,
+        });
+        state_limit
+    }
+}
+
+impl From<&StateLimit> for StateLimitSpec {
+    fn from(state_limit: &StateLimit) -> Self {
+        Self {
+            max_bytes: state_limit.max_bytes,
+            policy: match state_limit.policy() {
+                state_limit::Policy::RejectNewKeys => None,
+                state_limit::Policy::EvictOldest => Some(StateLimitPolicy::EvictOldest),
+                state_limit::Policy::Fail => Some(StateLimitPolicy::Fail),
+            },
+        }
+    }
+}
+
+impl From<DataType> for DataTypeEnum {
+    fn from(data_type: DataType) -> Self {
+        match data_type {
+            DataType::Bigint => Self::Bigint,
+            DataType::Number => Self::Number,
+            DataType::Null => Self::Null,
+            DataType::String => Self::String,
+            DataType::Boolean => Self::Boolean,
+            DataType::Object => Self::Object,
+            DataType::Array => Self::Array,
+        }
+    }
+}
+
+impl DataType {
+    /// an unspecified type means any type
+    fn from_enum(data_type: DataTypeEnum) -> Option<Self> {
+        match data_type {
+            DataTypeEnum::Unspecified => None,
+            DataTypeEnum::Bigint => Some(Self::Bigint),
+            DataTypeEnum::Number => Some(Self::Number),
+            DataTypeEnum::Null => Some(Self::Null),
+            DataTypeEnum::String => Some(Self::String),
+            DataTypeEnum::Boolean => Some(Self::Boolean),
+            DataTypeEnum::Object => Some(Self::Object),
+            DataTypeEnum::Array => Some(Self::Array),
+        }
+    }
+}
+
+impl PayloadSchemaSpec {
+    fn resolve(&self) -> Self {
+        Self {
+            validation: Some(self.validation.unwrap_or_default()),
+            ..self.clone()
+        }
+    }
+
+    fn validate(&self, path: &str) -> Result<(), ApiErrorDetail> {
+        let mut names = BTreeSet::new();
+        for (index, field) in self.fields.iter().enumerate() {
+            if field.name.is_empty() {
+                return Err(ApiErrorDetail::new("field name must not be empty")
+                    .with_field(format!("{path}.fields.{index}.name")));
+            }
+            if !names.insert(field.name.as_str()) {
+                return Err(ApiErrorDetail::new(format!(
+                    "field [{}] is declared more than once",
+                    &field.name
+                ))
+                .with_field(format!("{path}.fields.{index}.name")));
+            }
+        }
+        let sample_fraction = self.sample_fraction.unwrap_or_default();
+        if self.validation == Some(SchemaValidation::Sampling)
+            && !(sample_fraction > 0.0 && sample_fraction <= 1.0)
+        {
+            return Err(ApiErrorDetail::new(format!(
+                "sample fraction [{sample_fraction}] is out of (0, 1]"
+            ))
+            .with_field(format!("{path}.sample_fraction")));
+        }
+        Ok(())
+    }
+}
+
+impl From<&PayloadSchemaSpec> for PayloadSchema {
+    fn from(spec: &PayloadSchemaSpec) -> Self {
+        let mut schema = PayloadSchema {
+            fields: spec
+                .fields
+                .iter()
+                .map(|field| {
+                    let mut proto_field = payload_schema::Field {
+                        name: field.name.clone(),
+                        required: field.required,
+                        ..Default::default()
+                    };
+                    if let Some(data_type) = field.data_type {
+                        proto_field.set_data_type(data_type.into());
+                    }
+                    proto_field
+                })
+                .collect(),
+            sample_fraction: spec.sample_fraction.unwrap_or_default(),
+            ..Default::default()
+        };
+        schema.set_validation(match spec.validation.unwrap_or_default() {
+            SchemaValidation::Disabled => payload_schema::Validation::Disabled,
+            SchemaValidation::Sampling => payload_schema::Validation::Sampling,
+            SchemaValidation::Strict => payload_schema::Validation::Strict,
+        });
+        schema
+    }
+}
+
+impl From<&PayloadSchema> for PayloadSchemaSpec {
+    fn from(schema: &PayloadSchema) -> Self {
+        Self {
+            fields: schema
+                .fields
+                .iter()
+                .map(|field| PayloadFieldSpec {
+                    name: field.name.clone(),
+                    data_type: DataType::from_enum(field.data_type()),
+                    required: field.required,
+                })
+                .collect(),
+            validation: match schema.validation() {
+                payload_schema::Validation::Disabled => None,
+                payload_schema::Validation::Sampling => Some(SchemaValidation::Sampling),
+                payload_schema::Validation::Strict => Some(SchemaValidation::Strict),
+            },
+            sample_fraction: Some(schema.sample_fraction).filter(|fraction| *fraction != 0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use proto::common::OperatorInfo;
+
+    use super::{
+        BackoffSpec, ChainingMode, DataType, ErrorPolicySpec, OperatorSettings, PayloadFieldSpec,
+        PayloadSchemaSpec, RetrySpec, SchemaValidation, StateLimitPolicy, StateLimitSpec,
+    };
+
+    /// every field of the settings is set to a value other than its default
+    fn every_field() -> OperatorSettings {
+        OperatorSettings {
+            chaining: Some(ChainingMode::Disabled),
+            error_policy: Some(ErrorPolicySpec::Retry(RetrySpec {
+                attempts: 3,
+                backoff: Some(BackoffSpec {
+                    base: 100,
+                    max: 2000,
+                    jitter: true,
+                }),
+                fallback: Some(Box::new(ErrorPolicySpec::DeadLetter { sink: 2 })),
+            })),
+            state_limit: Some(StateLimitSpec {
+                max_bytes: 1 << 20,
+                policy: Some(StateLimitPolicy::EvictOldest),
+            }),
+            processing_timeout: Some(500),
+            headers: BTreeMap::from([("source".to_string(), "orders".to_string())]),
+            input_schema: Some(PayloadSchemaSpec {
+                fields: vec![
+                    PayloadFieldSpec {
+                        name: "id".to_string(),
+                        data_type: Some(DataType::Bigint),
+                        required: true,
+                    },
+                    PayloadFieldSpec {
+                        name: "tags".to_string(),
+                        data_type: None,
+                        required: false,
+                    },
+                ],
+                validation: Some(SchemaValidation::Sampling),
+                sample_fraction: Some(0.25),
+            }),
+            output_schema: Some(PayloadSchemaSpec {
+                fields: vec![PayloadFieldSpec {
+                    name: "total".to_string(),
+                    data_type: Some(DataType::Number),
+                    required: false,
+                }],
+                validation: Some(SchemaValidation::Strict),
+                sample_fraction: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let settings = every_field();
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(
+            serde_json::from_str::<OperatorSettings>(&json).unwrap(),
+            settings
+        );
+        // oneofs in YAML are maps keyed by their cases as the ones in JSON
+        let yaml = serde_yaml::to_string(&serde_json::to_value(&settings).unwrap()).unwrap();
+        assert_eq!(
+            serde_yaml::with::singleton_map_recursive::deserialize::<OperatorSettings, _>(
+                serde_yaml::Deserializer::from_str(&yaml)
+            )
+            .unwrap(),
+            settings
+        );
+
+        let mut operator = OperatorInfo::default();
+        settings.apply(&mut operator);
+        assert_eq!(OperatorSettings::from(&operator), settings);
+
+        assert_eq!(
+            OperatorSettings::from(&OperatorInfo::default()),
+            Default::default()
+        );
+    }
+
+    #[test]
+    fn test_resolve_settings() {
+        assert_eq!(
+            serde_json::to_value(OperatorSettings::default().resolve()).unwrap(),
+            serde_json::json!({ "chaining": "enabled", "error_policy": { "skip": {} } })
+        );
+
+        let settings = OperatorSettings {
+            error_policy: Some(ErrorPolicySpec::Retry(RetrySpec {
+                attempts: 3,
+                backoff: None,
+                fallback: None,
+            })),
+            state_limit: Some(StateLimitSpec {
+                max_bytes: 1024,
+                policy: None,
+            }),
+            input_schema: Some(Default::default()),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(settings.resolve()).unwrap(),
+            serde_json::json!({
+                "chaining": "enabled",
+                "error_policy": {
+                    "retry": {
+                        "attempts": 3,
+                        "backoff": { "base": 0, "max": 0, "jitter": false },
+                        "fallback": { "skip": {} },
+                    }
+                },
+                "state_limit": { "max_bytes": 1024, "policy": "reject_new_keys" },
+                "input_schema": { "validation": "disabled" },
+            })
+        );
+        // the filled settings are the same as the ones of the runtime
+        assert_eq!(every_field().resolve().resolve(), every_field().resolve());
+    }
+
+    #[test]
+    fn test_validate_settings() {
+        let invalid = |settings: serde_json::Value| {
+            serde_json::from_value::<OperatorSettings>(settings)
+                .unwrap()
+                .validate("operators.1")
+                .unwrap_err()
+                .field
+                .unwrap()
+        };
+        assert!(every_field().validate("operators.1").is_ok());
+        assert_eq!(
+            invalid(serde_json::json!({ "error_policy": { "retry": { "attempts": 0 } } })),
+            "operators.1.error_policy.retry.attempts"
+        );
+        assert_eq!(
+            invalid(serde_json::json!({
+                "error_policy": { "retry": { "attempts": 1, "fallback": { "retry": { "attempts": 1 } } } }
+            })),
+            "operators.1.error_policy.retry.fallback"
+        );
+        assert_eq!(
+            invalid(serde_json::json!({ "state_limit": { "max_bytes": 0 } })),
+            "operators.1.state_limit.max_bytes"
+        );
+        assert_eq!(
+            invalid(serde_json::json!({ "processing_timeout": 0 })),
+            "operators.1.processing_timeout"
+        );
+        assert_eq!(
+            invalid(serde_json::json!({ "headers": { "": "orders" } })),
+            "operators.1.headers"
+        );
+        assert_eq!(
+            invalid(serde_json::json!({
+                "input_schema": { "fields": [{ "name": "id" }, { "name": "id" }] }
+            })),
+            "operators.1.input_schema.fields.1.name"
+        );
+        assert_eq!(
+            invalid(serde_json::json!({
+                "output_schema": { "validation": "sampling", "sample_fraction": 1.5 }
+            })),
+            "operators.1.output_schema.sample_fraction"
+        );
+
+        assert!(serde_json::from_value::<OperatorSettings>(
+            serde_json::json!({ "error_policy": { "ignore": {} } })
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::{web, HttpResponse};
+use common::utils::times;
+use futures_util::future::join_all;
+use tokio::time::MissedTickBehavior;
+
+use super::{config::ApiServerConfig, handler::coordinator::CoordinatorRouter, shutdown::Draining};
+
+/// whether a check of `GET /healthz` or `GET /readyz` passes
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CheckStatus {
+    Ok,
+    Failed,
+}
+
+/// a check of a probe, which names the dependency it's about
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ProbeCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    /// why the check fails, or what it's passed by
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub message: Option<String>,
+    /// milliseconds since the unix epoch, absent if the check is done by the request itself
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub checked_at: Option<i64>,
+}
+
+impl ProbeCheck {
+    fn new(name: &str, result: Result<Option<String>, String>, checked_at: Option<i64>) -> Self {
+        let (status, message) = match result {
+            Ok(message) => (CheckStatus::Ok, message),
+            Err(message) => (CheckStatus::Failed, Some(message)),
+        };
+        Self {
+            name: name.to_string(),
+            status,
+            message,
+            checked_at,
+        }
+    }
+}
+
+/// the body of `GET /healthz` and `GET /readyz`, the probe passes only if all of its checks pass
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ProbeReport {
+    pub status: CheckStatus,
+    pub checks: Vec<ProbeCheck>,
+}
+
+impl From<Vec<ProbeCheck>> for ProbeReport {
+    fn from(checks: Vec<ProbeCheck>) -> Self {
+        let status = if checks.iter().all(|check| check.status == CheckStatus::Ok) {
+            CheckStatus::Ok
+        } else {
+            CheckStatus::Failed
+        };
+        Self { status, checks }
+    }
+}
+
+impl ProbeReport {
+    /// 200 if the probe passes, otherwise 503. The checks are in the body either way
+    pub(crate) fn to_response(&self) -> HttpResponse {
+        match self.status {
+            CheckStatus::Ok => HttpResponse::Ok().json(self),
+            CheckStatus::Failed => HttpResponse::ServiceUnavailable().json(self),
+        }
+    }
+}
+
+/// the outcomes of the probes of a coordinator
+#[derive(Default)]
+struct CoordinatorProbe {
+    reachable_at: Option<Instant>,
+    /// why the latest probe fails
+    error: Option<String>,
+}
+
+#[derive(Default)]
+struct ProbeState {
+    /// when the latest probe is done
+    probed_at: Option<(Instant, i64)>,
+    /// the coordinators by their names
+    coordinators: BTreeMap<String, CoordinatorProbe>,
+    /// the checks of the local dependencies by the latest probe
+    checks: Vec<ProbeCheck>,
+}
+
+/// [`Prober`] probes the dependencies of the API server in the background every `interval` of [`ProbesConfig`](super::config::ProbesConfig),
+/// so `GET /healthz` and `GET /readyz` are served by the outcomes of the latest probes rather than calling the coordinators per request.
+///
+/// The API server is alive while the probes keep running, which shows its event loop is responsive.
+/// It's ready if all of these checks pass:
+/// - `shutdown`: it's not shutting down, see [`Draining`]
+/// - `coordinators`: at least one of the coordinators is reachable within the last interval, see [`CoordinatorGateway::probe`](super::handler::coordinator::CoordinatorGateway::probe)
+/// - `token_file`: the token file is readable, if it's configured
+/// - `config`: the config is still valid, e.g. the files it refers to still exist
+pub(crate) struct Prober {
+    config: ApiServerConfig,
+    coordinators: web::Data<CoordinatorRouter>,
+    interval: Duration,
+    timeout: Duration,
+    started_at: Instant,
+    state: Mutex<ProbeState>,
+}
+
+impl Prober {
+    pub(crate) fn new(
+        config: &ApiServerConfig,
+        coordinators: web::Data<CoordinatorRouter>,
+    ) -> Self {
+        Self {
+            config: config.clone(),
+            coordinators,
+            interval: config.probes.get_interval(),
+            timeout: config.probes.get_timeout(),
+            started_at: Instant::now(),
+            state: Default::default(),
+        }
+    }
+
+    /// probe the dependencies every interval until the task is aborted. The first probe is done at once
+    pub(crate) async fn run(self: Arc<Self>) {
+        let mut ticks = tokio::time::interval(self.interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            self.probe().await;
+        }
+    }
+
+    /// probe all of the coordinators at the same time and check the local dependencies
+    pub(crate) async fn probe(&self) {
+        let timeout = self.timeout;
+        let results = join_all(self.coordinators.all().map(|(name, gateway)| async move {
+            (name.to_string(), gateway.probe(timeout).await)
+        }))
+        .await;
+
+        let now = (Instant::now(), times::now_timestamp());
+        let mut checks = vec![];
+        if let Some(path) = self.config.auth.token_file.as_ref() {
+            let result = fs::File::open(path)
+                .map(|_| None)
+                .map_err(|err| format!("read token file {path} failed: {err}"));
+            checks.push(ProbeCheck::new("token_file", result, Some(now.1)));
+        }
+        let result = self
+            .config
+            .validate()
+            .map(|_| None)
+            .map_err(|err| err.to_string());
+        checks.push(ProbeCheck::new("config", result, Some(now.1)));
+
+        let mut state = self.lock_state();
+        results.into_iter().for_each(|(name, result)| {
+            let coordinator = state.coordinators.entry(name).or_default();
+            match result {
+                Ok(_) => {
+                    coordinator.reachable_at = Some(now.0);
+                    coordinator.error = None;
+                }
+                Err(err) => coordinator.error = Some(err.message().to_string()),
+            }
+        });
+        state.checks = checks;
+        state.probed_at = Some(now);
+    }
+
+    /// the API server is alive unless the probes stop running, since the event loop they run in is stuck
+    pub(crate) fn liveness(&self) -> ProbeReport {
+        let state = self.lock_state();
+        let last_run = state
+            .probed_at
+            .map(|(probed_at, _)| probed_at)
+            .unwrap_or(self.started_at);
+        // a round of the probes takes the interval and the timeout at most, so the event loop is stuck if two rounds are missed
+        let stuck_after = (self.interval + self.timeout) * 2;
+        let elapsed = last_run.elapsed();
+        let result = if elapsed <= stuck_after {
+            Ok(None)
+        } else {
+            Err(format!("no probe is done in {elapsed:?}"))
+        };
+        vec![ProbeCheck::new(
+            "event_loop",
+            result,
+            state.probed_at.map(|(_, timestamp)| timestamp),
+        )]
+        .into()
+    }
+
+    /// the API server is ready if all of the checks pass, see [`Prober`]
+    pub(crate) fn readiness(&self, draining: &Draining) -> ProbeReport {
+        let shutdown = if draining.is_draining() {
+            Err("the API server is shutting down".to_string())
+        } else {
+            Ok(None)
+        };
+        let mut checks = vec![ProbeCheck::new("shutdown", shutdown, None)];
+
+        let state = self.lock_state();
+        let (reachable, unreachable): (Vec<_>, Vec<_>) =
+            state.coordinators.iter().partition(|(_, probe)| {
+                probe
+                    .reachable_at
+                    .filter(|reachable_at| reachable_at.elapsed() <= self.interval + self.timeout)
+                    .is_some()
+            });
+        let unreachable = unreachable
+            .iter()
+            .map(|(name, probe)| match probe.error.as_ref() {
+                Some(error) => format!("{name}: {error}"),
+                None => format!("{name}: not reachable in the last interval"),
+            })
+            .collect::<Vec<_>>();
+        let coordinators = match (reachable.is_empty(), state.probed_at) {
+            (_, None) => Err("the coordinators are not probed yet".to_string()),
+            (true, _) => Err(format!(
+                "no coordinator is reachable: {}",
+                unreachable.join("; ")
+            )),
+            (false, _) if unreachable.is_empty() => Ok(None),
+            (false, _) => Ok(Some(format!("unreachable: {}", unreachable.join("; ")))),
+        };
+        checks.push(ProbeCheck::new(
+            "coordinators",
+            coordinators,
+            state.probed_at.map(|(_, timestamp)| timestamp),
+        ));
+        checks.extend(state.checks.iter().cloned());
+        checks.into()
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, ProbeState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    future::Future,
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use actix_web::{dev::Server, web};
+use tokio::time::Instant;
+
+use super::{audit::AuditLog, events::EventHub, operations::OperationStore, probe::Prober};
+
+/// how long the queued audit records are waited for once the requests are done
+const AUDIT_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// whether the API server is shutting down. `GET /ready` and `GET /readyz` are unavailable once it's draining
+#[derive(Default)]
+pub(crate) struct Draining(AtomicBool);
+
+impl Draining {
+    pub(crate) fn start(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    pub(crate) fn is_draining(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// The HTTP API server created by [`super::new_api_server`], along with the states which are drained when it shuts down
+pub struct ApiServer {
+    pub(crate) server: Server,
+    pub(crate) draining: web::Data<Draining>,
+    pub(crate) prober: web::Data<Prober>,
+    pub(crate) events: web::Data<EventHub>,
+    pub(crate) operations: web::Data<OperationStore>,
+    pub(crate) audit_log: web::Data<AuditLog>,
+    pub(crate) grace_period: Duration,
+}
+
+impl ApiServer {
+    /// serve until the server stops or the signal resolves, e.g. [`crate::server::terminated`]. The dependencies are probed in the background meanwhile, see [`Prober`].
+    /// Once it's signaled, the server shuts down gracefully:
+    /// 1. `GET /ready` and `GET /readyz` fail, and the streams of the status events are ended by a `shutdown` event
+    /// 2. new connections are not accepted anymore
+    /// 3. the pending asynchronous operations are waited for until the grace period elapses, the ones not done by then are failed as interrupted
+    /// 4. the in-flight requests are waited for until the grace period elapses
+    /// 5. the queued audit records are flushed to the sink
+    pub async fn serve(self, signal: impl Future<Output = ()>) -> io::Result<()> {
+        let handle = self.server.handle();
+        let mut server = tokio::spawn(self.server);
+        let prober = tokio::spawn(self.prober.into_inner().run());
+        tokio::select! {
+            result = &mut server => {
+                prober.abort();
+                return result.map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            }
+            _ = signal => {}
+        }
+
+        tracing::info!(
+            "API server is shutting down, the grace period is {:?}",
+            self.grace_period
+        );
+        let deadline = Instant::now() + self.grace_period;
+        self.draining.start();
+        self.events.close();
+        handle.pause().await;
+
+        if tokio::time::timeout_at(deadline, self.operations.wait_pending())
+            .await
+            .is_err()
+        {
+            self.operations
+                .interrupt_pending()
+                .iter()
+                .for_each(|id| tracing::warn!("operation {} is interrupted by the shutdown", id));
+        }
+
+        // the workers stop once their in-flight requests are done
+        let result = match tokio::time::timeout_at(deadline, handle.stop(true)).await {
+            Ok(_) => server
+                .await
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?,
+            Err(_) => {
+                tracing::warn!("in-flight requests are not done in the grace period");
+                Ok(())
+            }
+        };
+
+        if !self.audit_log.flush(AUDIT_FLUSH_TIMEOUT).await {
+            tracing::warn!(
+                target: "audit",
+                "queued audit records are not flushed in {:?}",
+                AUDIT_FLUSH_TIMEOUT
+            );
+        }
+        prober.abort();
+        tracing::info!("API server is shut down");
+        result
+    }
+}
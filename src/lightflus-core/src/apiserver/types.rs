@@ -1,4 +1,22 @@
-use proto::common::ResourceId;
+use std::collections::BTreeMap;
+
+use proto::{
+    apiserver::{
+        create_resource_request::Options, CreateDataflowOptions, CreateResourceRequest,
+        CreateResourceResponse, ResourceTypeEnum,
+    },
+    common::{Dataflow, ResourceId},
+    coordinator::{
+        DataflowRuntimeStatus, DataflowSummary, ListDataflowsRequest, ListDataflowsResponse,
+        OperatorRuntimeStatus, TerminateDataflowResult, TerminateDataflowsResponse,
+        UpdateDataflowResponse, UpdateStrategy as DataflowUpdateStrategy, WorkerFailure,
+    },
+    coordinator_impl::LabelSelector,
+};
+
+use crate::errors::apiserver::{ApiError, ApiErrorDetail};
+
+use super::operators::OperatorSettings;
 
 #[derive(serde::Deserialize)]
 pub(crate) struct GetResourceArgs {
@@ -9,15 +27,815 @@ pub(crate) struct GetResourceArgs {
 
 impl GetResourceArgs {
     pub fn to_resource_id(&self) -> ResourceId {
-        let mut resource_id = ResourceId::default();
-        resource_id.namespace_id = self.namespace.clone();
-        resource_id.resource_id = self.resource_id.clone();
-        resource_id
+        ResourceId::new(&self.namespace, &self.resource_id)
     }
 }
 
-#[derive(serde::Deserialize)]
+/// query of `POST /resources/create`
+#[derive(serde::Deserialize, Default)]
+pub(crate) struct CreateResourceQuery {
+    /// the resources are created in the background if it's true, see [`Operation`](crate::apiserver::operations::Operation)
+    #[serde(rename = "async", default)]
+    pub is_async: bool,
+}
+
+/// query of `POST /resources:batch`
+#[derive(serde::Deserialize, Default)]
+pub(crate) struct BatchResourcesQuery {
+    /// none of the resources is applied if any of them is invalid
+    #[serde(default)]
+    pub atomic: bool,
+    /// how the resources with resource versions are updated, the same as the one of `PUT /resources/{namespace}/{name}`
+    #[serde(default)]
+    pub strategy: UpdateStrategy,
+}
+
+/// query of `GET /resources`
+#[derive(serde::Deserialize, Default)]
 pub(crate) struct ListResourcesArgs {
-    pub resource_type: i32,
+    /// the max number of resources in a page
+    pub limit: Option<u32>,
+    /// the `continue` token of the previous page
+    #[serde(rename = "continue")]
+    pub continue_token: Option<String>,
+    /// only the resources of the namespace are listed if it's given
+    pub namespace: Option<String>,
+    /// only the resources whose labels match the selector are listed, e.g. `team=payments,env!=dev`, see [`LabelSelector`]
+    #[serde(rename = "labelSelector")]
+    pub label_selector: Option<String>,
+    /// the resources of all namespaces are listed by all of the coordinators if it's true, rather than the default one
+    #[serde(rename = "fanOut", default)]
+    pub fan_out: bool,
+}
+
+impl ListResourcesArgs {
+    pub fn get_label_selector(&self) -> Result<LabelSelector, ApiError> {
+        LabelSelector::parse(self.label_selector.as_deref().unwrap_or_default()).map_err(|err| {
+            ApiError::invalid_argument("invalid label selector")
+                .with_detail(ApiErrorDetail::new(err).with_field("labelSelector"))
+        })
+    }
+
+    pub fn to_list_dataflows_request(&self) -> ListDataflowsRequest {
+        ListDataflowsRequest {
+            namespace: self.namespace.clone().unwrap_or_default(),
+            page_size: self.limit.unwrap_or_default(),
+            page_token: self.continue_token.clone().unwrap_or_default(),
+            label_selector: self.label_selector.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// summary of a resource in the body of `GET /resources`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct ResourceSummary {
+    pub id: String,
+    /// a dataflow is named by its job id
+    pub name: String,
+    pub namespace: String,
+    /// one of `pending`, `deploying`, `running`, `degraded`, `recovering`, `sources_paused`, `suspended`, `failed`, `closing` and `closed`
+    pub status: String,
+    /// why the resource is in the status, e.g. the partitions which fail to start. It's absent if the status is expected
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub status_reason: Option<String>,
+    pub operator_count: u32,
+    /// milliseconds since the unix epoch
+    pub created_at: i64,
+    /// milliseconds since the unix epoch
+    pub updated_at: i64,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// it changes every time the resource is updated, and an update of the resource must be based on the current one
+    #[serde(default)]
+    pub resource_version: u64,
+    /// the name of the coordinator serving the resource. It's only present if the coordinators of the regions are configured
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub coordinator: Option<String>,
+}
+
+impl From<&DataflowSummary> for ResourceSummary {
+    fn from(summary: &DataflowSummary) -> Self {
+        let job_id = summary.job_id.clone().unwrap_or_default();
+        Self {
+            id: job_id.resource_id.clone(),
+            name: job_id.resource_id,
+            namespace: job_id.namespace_id,
+            status: summary.status().as_str_name().to_lowercase(),
+            status_reason: Some(summary.status_reason.clone()).filter(|reason| !reason.is_empty()),
+            operator_count: summary.operator_count,
+            created_at: summary.created_at,
+            updated_at: summary.updated_at,
+            labels: summary.labels.clone().into_iter().collect(),
+            resource_version: summary.resource_version,
+            coordinator: None,
+        }
+    }
+}
+
+/// body of `GET /resources`
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Default)]
+pub(crate) struct ListResourcesResponse {
+    pub resources: Vec<ResourceSummary>,
+    /// token of the next page. It's absent if it's the last page
+    #[serde(rename = "continue", skip_serializing_if = "Option::is_none", default)]
+    pub continue_token: Option<String>,
+    /// the coordinators whose resources are missing in a fanned-out listing since they're unavailable
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub unavailable: Vec<String>,
+}
+
+impl ListResourcesResponse {
+    /// mark the resources by the name of the coordinator serving them
+    pub fn with_coordinator(mut self, coordinator: Option<&str>) -> Self {
+        self.resources
+            .iter_mut()
+            .for_each(|resource| resource.coordinator = coordinator.map(|name| name.to_string()));
+        self
+    }
+}
+
+impl From<ListDataflowsResponse> for ListResourcesResponse {
+    fn from(response: ListDataflowsResponse) -> Self {
+        Self {
+            resources: response
+                .dataflows
+                .iter()
+                .map(ResourceSummary::from)
+                .collect(),
+            continue_token: Some(response.next_page_token).filter(|token| !token.is_empty()),
+            unavailable: vec![],
+        }
+    }
+}
+
+/// query of `GET /cluster`
+#[derive(serde::Deserialize, Default)]
+pub(crate) struct ClusterQuery {
+    /// `default` or the region of the coordinator managing the cluster
+    pub coordinator: Option<String>,
+}
+
+/// query of `GET /audit`
+#[derive(serde::Deserialize, Default)]
+pub(crate) struct AuditQuery {
+    /// the number of the latest records, all of the ones kept in memory if it's not given
+    pub limit: Option<usize>,
+}
+
+/// a resource referred by the body of a batch operation
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct ResourceRef {
+    pub id: String,
+    pub namespace: String,
+}
+
+impl ResourceRef {
+    pub fn to_resource_id(&self) -> ResourceId {
+        ResourceId::new(&self.namespace, &self.id)
+    }
+}
+
+/// how the operators of a terminated dataflow stop
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TerminateMode {
+    /// operators stop after the events buffered in them are processed and their sinks are flushed
+    #[default]
+    Drain,
+    /// operators stop at once, the events buffered in them are dropped
+    Force,
+}
+
+impl TerminateMode {
+    pub fn is_force(&self) -> bool {
+        *self == Self::Force
+    }
+}
+
+/// body of `POST /resources/terminate`
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub(crate) struct TerminateResourcesRequest {
+    pub resources: Vec<ResourceRef>,
+    #[serde(default)]
+    pub mode: TerminateMode,
+}
+
+/// path of `GET` and `DELETE /resources/{namespace}/{name}`
+#[derive(serde::Deserialize)]
+pub(crate) struct ResourcePathArgs {
+    pub namespace: String,
+    /// a dataflow is named by its job id
+    pub name: String,
+}
+
+impl ResourcePathArgs {
+    pub fn to_resource_id(&self) -> ResourceId {
+        ResourceId::new(&self.namespace, &self.name)
+    }
+}
+
+/// how the running resource is replaced by `PUT /resources/{namespace}/{name}`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum UpdateStrategy {
+    /// operators are replaced one by one without stopping the resource. It's not supported yet
+    #[default]
+    Rolling,
+    /// the resource is stopped after a savepoint is taken, then it's created again from the savepoint
+    Recreate,
+}
+
+impl UpdateStrategy {
+    pub fn to_dataflow_update_strategy(&self) -> DataflowUpdateStrategy {
+        match self {
+            Self::Rolling => DataflowUpdateStrategy::Rolling,
+            Self::Recreate => DataflowUpdateStrategy::Recreate,
+        }
+    }
+}
+
+/// query of `PUT /resources/{namespace}/{name}`
+#[derive(serde::Deserialize, Default)]
+pub(crate) struct UpdateResourceQuery {
+    #[serde(default)]
+    pub strategy: UpdateStrategy,
+}
+
+/// body of the response of `PUT /resources/{namespace}/{name}`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct UpdateResourceResponse {
+    /// the summary of the updated resource with its new resource version
+    #[serde(flatten)]
+    pub summary: ResourceSummary,
+    /// ids of the operators which are restarted by the update, in ascending order
+    pub restarted_operators: Vec<u32>,
+    /// e.g. the states of the removed operators are dropped
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub warnings: Vec<String>,
+}
+
+impl From<UpdateDataflowResponse> for UpdateResourceResponse {
+    fn from(response: UpdateDataflowResponse) -> Self {
+        Self {
+            summary: ResourceSummary::from(&response.summary.unwrap_or_default()),
+            restarted_operators: response.restarted_operator_ids,
+            warnings: response.warnings,
+        }
+    }
+}
+
+/// query of `DELETE /resources/{namespace}/{name}`
+#[derive(serde::Deserialize, Default)]
+pub(crate) struct DeleteResourceQuery {
+    #[serde(default)]
+    pub mode: TerminateMode,
+}
+
+/// a TaskManager which fails to stop its part of a terminated resource
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub(crate) struct WorkerFailureSummary {
+    /// `host:port` of the TaskManager
+    pub node: String,
+    pub error: ApiError,
+}
+
+impl From<&WorkerFailure> for WorkerFailureSummary {
+    fn from(failure: &WorkerFailure) -> Self {
+        let node = failure.node.clone().unwrap_or_default();
+        let error = failure.error.clone().unwrap_or_default();
+        Self {
+            node: format!("{}:{}", node.host, node.port),
+            error: ApiError::from(&error),
+        }
+    }
+}
+
+/// result of terminating a resource. Either `status` or `error` is present
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub(crate) struct TerminateResourceResult {
+    pub id: String,
+    pub namespace: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<ApiError>,
+    /// the TaskManagers which fail to stop if the resource is partially terminated
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub worker_failures: Vec<WorkerFailureSummary>,
+}
+
+impl From<&TerminateDataflowResult> for TerminateResourceResult {
+    fn from(result: &TerminateDataflowResult) -> Self {
+        let job_id = result.job_id.clone().unwrap_or_default();
+        let (status, error) = match result.error.as_ref() {
+            Some(detail) => (None, Some(ApiError::from(detail))),
+            None => (Some(result.status().as_str_name().to_lowercase()), None),
+        };
+        Self {
+            id: job_id.resource_id,
+            namespace: job_id.namespace_id,
+            status,
+            error,
+            worker_failures: result
+                .worker_failures
+                .iter()
+                .map(WorkerFailureSummary::from)
+                .collect(),
+        }
+    }
+}
+
+impl TerminateResourceResult {
+    /// the resource fails to terminate without the result of the coordinator, e.g. the coordinator is unavailable
+    pub fn failed(resource: &ResourceRef, error: ApiError) -> Self {
+        Self {
+            id: resource.id.clone(),
+            namespace: resource.namespace.clone(),
+            status: None,
+            error: Some(error),
+            worker_failures: vec![],
+        }
+    }
+}
+
+/// body of the response of `POST /resources/terminate`
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub(crate) struct TerminateResourcesResponse {
+    pub results: Vec<TerminateResourceResult>,
+}
+
+impl From<TerminateDataflowsResponse> for TerminateResourcesResponse {
+    fn from(response: TerminateDataflowsResponse) -> Self {
+        Self {
+            results: response
+                .results
+                .iter()
+                .map(TerminateResourceResult::from)
+                .collect(),
+        }
+    }
+}
+
+/// which parts of a resource are responded by `GET /resources/{namespace}/{name}`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ResourceView {
+    /// the operators as they're submitted
+    Spec,
+    /// the runtime status of the operators reported by the TaskManagers
+    Status,
+    /// both of the spec and the status of each operator
+    #[default]
+    Full,
+}
+
+impl ResourceView {
+    pub fn with_spec(&self) -> bool {
+        *self != Self::Status
+    }
+
+    pub fn with_status(&self) -> bool {
+        *self != Self::Spec
+    }
+}
+
+/// query of `GET /resources/{namespace}/{name}`
+#[derive(serde::Deserialize, Default)]
+pub(crate) struct GetResourceQuery {
+    #[serde(default)]
+    pub view: ResourceView,
+}
+
+/// an operator of a resource. The spec fields are absent in the `status` view, and the status fields are absent in the `spec` view
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct OperatorDetail {
+    pub id: u32,
+    /// the type of the operator, e.g. `reducer`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub upstreams: Option<Vec<u32>>,
+    /// the operator-level settings as they're submitted, the ones at their defaults are absent
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub settings: Option<OperatorSettings>,
+    /// the settings with the defaults filled by the server, which are the ones the operator runs with
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub resolved: Option<OperatorSettings>,
+    /// `pending` until the TaskManager reports the status of the operator, then one of `initialized`, `running`, `terminating`, `terminated`, `drained` and `failed`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub status: Option<String>,
+    /// `host:port` of the TaskManager where the operator is placed
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub host: Option<String>,
+    /// milliseconds since the unix epoch. It's zero if the operator has received no heartbeat
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub last_heartbeat_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub restart_count: Option<u32>,
+}
+
+impl OperatorDetail {
+    fn set_status(&mut self, status: &OperatorRuntimeStatus) {
+        self.status = Some(match status.status {
+            Some(_) => status
+                .status()
+                .as_str_name()
+                .trim_start_matches("EXECUTOR_STATUS_")
+                .to_lowercase(),
+            None => "pending".to_string(),
+        });
+        self.host = Some(
+            status
+                .host_addr
+                .as_ref()
+                .map(|addr| format!("{}:{}", addr.host, addr.port))
+                .unwrap_or_default(),
+        );
+        self.last_heartbeat_at = Some(status.last_heartbeat_at);
+        self.restart_count = Some(status.restart_count);
+    }
+}
+
+/// body of `GET /resources/{namespace}/{name}`. The fields of the summary are the same as the ones of `GET /resources`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct ResourceDetail {
+    #[serde(flatten)]
+    pub summary: ResourceSummary,
+    /// operators ordered by their ids
+    pub operators: Vec<OperatorDetail>,
+}
+
+impl ResourceDetail {
+    /// merge the spec of the dataflow and the runtime status of its operators. Either of them may be absent depending on the view
+    pub fn new(status: &DataflowRuntimeStatus, dataflow: Option<&Dataflow>) -> Self {
+        let mut operators = BTreeMap::<u32, OperatorDetail>::new();
+        if let Some(dataflow) = dataflow {
+            dataflow.nodes.iter().for_each(|(operator_id, info)| {
+                let operator = operators.entry(*operator_id).or_default();
+                operator.id = *operator_id;
+                operator.kind = Some(info.get_kind().to_string());
+                operator.upstreams = Some(info.upstreams.clone());
+                let settings = OperatorSettings::from(info);
+                operator.resolved = Some(settings.resolve());
+                operator.settings = Some(settings);
+            });
+        }
+        status.operators.iter().for_each(|runtime| {
+            let operator = operators.entry(runtime.operator_id).or_default();
+            operator.id = runtime.operator_id;
+            operator.set_status(runtime);
+        });
+        Self {
+            summary: ResourceSummary::from(&status.summary.clone().unwrap_or_default()),
+            operators: operators.into_values().collect(),
+        }
+    }
+}
+
+/// format of the bodies of the requests and the responses in JSON or YAML
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum BodyFormat {
+    #[default]
+    Json,
+    Yaml,
+}
+
+impl BodyFormat {
+    /// the format of a media type, e.g. `application/yaml; charset=utf-8`. It's none if the media type is neither JSON nor YAML
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type.split(';').next().unwrap_or_default().trim() {
+            "application/json" => Some(Self::Json),
+            "application/yaml" | "application/x-yaml" | "text/yaml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// the first format in the `Accept` header which is supported, JSON if none of them is. The quality values are not considered
+    pub fn from_accept(accept: &str) -> Self {
+        accept
+            .split(',')
+            .find_map(Self::from_media_type)
+            .unwrap_or_default()
+    }
+
+    /// the resources of `POST /resources:batch`. A JSON body is an array of resources, and a YAML body has a document for each of them
+    pub fn parse_resource_batch(&self, body: &[u8]) -> Result<Vec<ResourceDefinition>, ApiError> {
+        match self {
+            Self::Json => {
+                let mut deserializer = serde_json::Deserializer::from_slice(body);
+                serde_path_to_error::deserialize(&mut deserializer)
+                    .and_then(|resources| {
+                        deserializer.end().map(|_| resources).map_err(|err| {
+                            serde_path_to_error::Error::new(
+                                serde_path_to_error::Track::new().path(),
+                                err,
+                            )
+                        })
+                    })
+                    .map_err(ApiError::from)
+            }
+            Self::Yaml => self.parse_documents(body),
+        }
+    }
+
+    /// the resources defined in the body. A YAML body may have multiple documents, each of which defines a resource.
+    /// The error is detailed by the path of the field which fails to be parsed or validated
+    pub fn parse_resources(&self, body: &[u8]) -> Result<Vec<ResourceDefinition>, ApiError> {
+        let resources = self.parse_documents(body)?;
+        resources
+            .iter()
+            .enumerate()
+            .try_for_each(|(document, resource)| {
+                resource.validate().map_err(|mut err| {
+                    if *self == Self::Yaml {
+                        err.details = err
+                            .details
+                            .into_iter()
+                            .map(|detail| detail.with_document(document))
+                            .collect();
+                    }
+                    err
+                })
+            })?;
+        Ok(resources)
+    }
+
+    /// the resources of the documents, which are not validated. The resources of a batch are validated one by one
+    fn parse_documents(&self, body: &[u8]) -> Result<Vec<ResourceDefinition>, ApiError> {
+        match self {
+            Self::Json => {
+                let mut deserializer = serde_json::Deserializer::from_slice(body);
+                serde_path_to_error::deserialize(&mut deserializer)
+                    .and_then(|resource| {
+                        deserializer.end().map(|_| vec![resource]).map_err(|err| {
+                            serde_path_to_error::Error::new(
+                                serde_path_to_error::Track::new().path(),
+                                err,
+                            )
+                        })
+                    })
+                    .map_err(ApiError::from)
+            }
+            Self::Yaml => serde_yaml::Deserializer::from_slice(body)
+                .enumerate()
+                .map(|(document, deserializer)| {
+                    serde_path_to_error::deserialize(deserializer)
+                        .map(|SingletonMapRecursive(resource)| resource)
+                        .map_err(|err| {
+                            let mut err = ApiError::from(err);
+                            err.details = err
+                                .details
+                                .into_iter()
+                                .map(|detail| detail.with_document(document))
+                                .collect();
+                            err
+                        })
+                })
+                .collect(),
+        }
+    }
+}
+
+/// oneofs in YAML are maps keyed by their cases as the ones in JSON, instead of YAML tags
+struct SingletonMapRecursive<T>(T);
+
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for SingletonMapRecursive<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        serde_yaml::with::singleton_map_recursive::deserialize(deserializer).map(Self)
+    }
+}
+
+/// the kind of a resource definition
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ResourceKind {
+    #[default]
+    Dataflow,
+}
+
+/// a resource created by `POST /resources/create` in JSON or YAML. The fields of the dataflow are the same as its protobuf message,
+/// and the oneof fields are keyed by the snake case name of their cases, e.g. `details: { reducer: { func: { function: ... } } }`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct ResourceDefinition {
+    #[serde(default)]
+    pub kind: ResourceKind,
     pub namespace: String,
+    /// a dataflow is named by its job id, which overrides the `job_id` of the dataflow
+    pub name: String,
+    /// labels of the resource, they override the labels of the dataflow with the same keys
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// the version of the resource which is updated by `PUT /resources/{namespace}/{name}`, it's ignored by the creation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_version: Option<u64>,
+    #[serde(default)]
+    pub dataflow: Option<Dataflow>,
+    /// the operator-level settings keyed by the operator ids, they override the same fields of the operators of the dataflow
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub operators: BTreeMap<u32, OperatorSettings>,
+}
+
+impl ResourceDefinition {
+    pub fn to_resource_id(&self) -> ResourceId {
+        ResourceId::new(&self.namespace, &self.name)
+    }
+
+    /// the same request as the protobuf body of `POST /resources/create`
+    pub fn to_create_resource_request(&self) -> CreateResourceRequest {
+        let mut request = CreateResourceRequest {
+            namespace: self.namespace.clone(),
+            options: self.dataflow.as_ref().map(|dataflow| {
+                let builder = self.labels.iter().fold(
+                    dataflow.to_builder().job_id(&self.namespace, &self.name),
+                    |builder, (key, value)| builder.label(key, value),
+                );
+                let builder = self
+                    .operators
+                    .iter()
+                    .filter_map(|(operator_id, settings)| {
+                        dataflow.nodes.get(operator_id).map(|operator| {
+                            let mut operator = operator.clone();
+                            settings.apply(&mut operator);
+                            operator
+                        })
+                    })
+                    .fold(builder, |builder, operator| builder.operator(operator));
+                Options::Dataflow(CreateDataflowOptions {
+                    dataflow: Some(builder.build_unvalidated()),
+                })
+            }),
+            ..Default::default()
+        };
+        match self.kind {
+            ResourceKind::Dataflow => request.set_resource_type(ResourceTypeEnum::Dataflow),
+        }
+        request
+    }
+
+    /// the operator settings must be valid, and each of them must be of an operator of the dataflow.
+    /// The error is detailed by the path of the invalid field, e.g. `operators.1.error_policy.retry.attempts`
+    pub fn validate(&self) -> Result<(), ApiError> {
+        self.operators
+            .iter()
+            .try_for_each(|(operator_id, settings)| {
+                let path = format!("operators.{operator_id}");
+                if !self
+                    .dataflow
+                    .as_ref()
+                    .map(|dataflow| dataflow.nodes.contains_key(operator_id))
+                    .unwrap_or_default()
+                {
+                    return Err(ApiErrorDetail::new(format!(
+                        "operator {operator_id} is not in the dataflow"
+                    ))
+                    .with_field(path));
+                }
+                settings.validate(&path)
+            })
+            .map_err(|detail| {
+                ApiError::invalid_argument(format!(
+                    "invalid operator settings of {}/{}",
+                    self.namespace, self.name
+                ))
+                .with_detail(detail)
+            })
+    }
+}
+
+/// result of creating the resource of a document. Either `status` or `error` is present
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub(crate) struct CreateResourceResult {
+    pub name: String,
+    pub namespace: String,
+    /// `starting` if the resource is created
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<ApiError>,
+}
+
+impl CreateResourceResult {
+    pub fn new(
+        resource: &ResourceDefinition,
+        result: Result<CreateResourceResponse, ApiError>,
+    ) -> Self {
+        let (status, error) = match result {
+            Ok(response) => (Some(status_name(&response)), None),
+            Err(err) => (None, Some(err)),
+        };
+        Self {
+            name: resource.name.clone(),
+            namespace: resource.namespace.clone(),
+            status,
+            error,
+        }
+    }
+}
+
+/// the status of a created resource in lowercase, e.g. `starting`
+fn status_name(response: &CreateResourceResponse) -> String {
+    response
+        .status()
+        .as_str_name()
+        .trim_start_matches("RESOURCE_STATUS_ENUM_")
+        .to_lowercase()
+}
+
+/// body of the response of `POST /resources/create` in JSON or YAML, the results are in the order of the documents
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub(crate) struct CreateResourcesResponse {
+    pub results: Vec<CreateResourceResult>,
+}
+
+/// what's done to a resource of `POST /resources:batch`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BatchOutcome {
+    Created,
+    Updated,
+    Failed,
+}
+
+/// result of a resource of `POST /resources:batch`. `error` is present only if it's failed
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub(crate) struct BatchResourceResult {
+    pub name: String,
+    pub namespace: String,
+    pub outcome: BatchOutcome,
+    /// `starting` if the resource is created
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub status: Option<String>,
+    /// the new version of the resource if it's updated
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub resource_version: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<ApiError>,
+}
+
+impl BatchResourceResult {
+    pub fn created(resource: &ResourceDefinition, response: &CreateResourceResponse) -> Self {
+        Self {
+            status: Some(status_name(response)),
+            ..Self::new(resource, BatchOutcome::Created)
+        }
+    }
+
+    pub fn updated(resource: &ResourceDefinition, response: &UpdateResourceResponse) -> Self {
+        Self {
+            resource_version: Some(response.summary.resource_version),
+            ..Self::new(resource, BatchOutcome::Updated)
+        }
+    }
+
+    pub fn failed(resource: &ResourceDefinition, error: ApiError) -> Self {
+        Self {
+            error: Some(error),
+            ..Self::new(resource, BatchOutcome::Failed)
+        }
+    }
+
+    fn new(resource: &ResourceDefinition, outcome: BatchOutcome) -> Self {
+        Self {
+            name: resource.name.clone(),
+            namespace: resource.namespace.clone(),
+            outcome,
+            status: None,
+            resource_version: None,
+            error: None,
+        }
+    }
+}
+
+/// the overall status of `POST /resources:batch`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BatchStatus {
+    /// all of the resources are applied
+    Succeeded,
+    /// some of the resources are failed
+    Partial,
+    /// all of the resources are failed
+    Failed,
+}
+
+/// body of the response of `POST /resources:batch` in JSON or YAML, the results are in the order of the resources
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub(crate) struct BatchResourcesResponse {
+    pub status: BatchStatus,
+    pub results: Vec<BatchResourceResult>,
+}
+
+impl From<Vec<BatchResourceResult>> for BatchResourcesResponse {
+    fn from(results: Vec<BatchResourceResult>) -> Self {
+        let failed = results
+            .iter()
+            .filter(|result| result.outcome == BatchOutcome::Failed)
+            .count();
+        let status = match failed {
+            0 => BatchStatus::Succeeded,
+            failed if failed == results.len() => BatchStatus::Failed,
+            _ => BatchStatus::Partial,
+        };
+        Self { status, results }
+    }
 }
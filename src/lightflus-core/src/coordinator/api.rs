@@ -1,10 +1,19 @@
-use crate::new_rpc_response;
+use crate::{new_rpc_response, CALLER_METADATA_KEY, REQUEST_ID_METADATA_KEY};
 
 use super::coord;
-use proto::common::{Ack, Dataflow, DataflowStates, Heartbeat, ResourceId, Response};
+use prost::Message;
+use proto::common::{
+    Ack, Dataflow, DataflowStates, Heartbeat, OperatorError, ResourceId, Response,
+};
 
 use proto::coordinator::coordinator_api_server::CoordinatorApi;
-use proto::coordinator::GetDataflowRequest;
+use proto::coordinator::{
+    AddNodeRequest, AddNodeResponse, ClusterTopology, DataflowRuntimeStatus,
+    DeleteSavepointRequest, GetClusterTopologyRequest, GetDataflowRequest,
+    GetDataflowStatusRequest, ListDataflowsRequest, ListDataflowsResponse, ListSavepointsResponse,
+    Savepoint, TerminateDataflowsRequest, TerminateDataflowsResponse, UpdateDataflowRequest,
+    UpdateDataflowResponse,
+};
 
 use tonic::async_trait;
 
@@ -16,6 +25,50 @@ impl CoordinatorApiImpl {
     pub fn new(coordinator: coord::Coordinator) -> CoordinatorApiImpl {
         CoordinatorApiImpl { coordinator }
     }
+
+    pub(crate) async fn is_ready(&self) -> bool {
+        self.coordinator.is_ready().await
+    }
+
+    pub(crate) async fn watch_backpressure(&self) {
+        self.coordinator.watch_backpressure().await
+    }
+
+    pub(crate) async fn watch_liveness(&self) {
+        self.coordinator.watch_liveness().await
+    }
+}
+
+/// attach a failed [`Response`] to the status as details, so that clients can read the code, the message and whether it's retryable
+/// from the body. Statuses which already carry a response, e.g. failures of deploying a dataflow with its placement, are kept as they are
+fn with_error_detail(status: tonic::Status) -> tonic::Status {
+    let response = Response::from_status(&status);
+    tonic::Status::with_details_and_metadata(
+        status.code(),
+        status.message(),
+        response.encode_to_vec().into(),
+        status.metadata().clone(),
+    )
+}
+
+/// the mutations are logged with the caller and the request id attached by the API server in the `audit` target
+fn audit<T>(request: &tonic::Request<T>, action: &str, target: &dyn std::fmt::Debug) {
+    let metadata = |key: &str| {
+        request
+            .metadata()
+            .get(key)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("unknown")
+            .to_string()
+    };
+    tracing::info!(
+        target: "audit",
+        request_id = %metadata(REQUEST_ID_METADATA_KEY),
+        "{} {} {:?}",
+        metadata(CALLER_METADATA_KEY),
+        action,
+        target
+    );
 }
 
 unsafe impl Send for CoordinatorApiImpl {}
@@ -40,31 +93,160 @@ impl CoordinatorApi for CoordinatorApiImpl {
         Ok(tonic::Response::new(Response::ok()))
     }
 
+    async fn report_operator_error(
+        &self,
+        request: tonic::Request<OperatorError>,
+    ) -> Result<tonic::Response<Response>, tonic::Status> {
+        self.coordinator
+            .report_operator_error(request.into_inner())
+            .await
+            .map_err(with_error_detail)
+            .map(|_| tonic::Response::new(Response::ok()))
+    }
+
     async fn create_dataflow(
         &self,
         request: tonic::Request<Dataflow>,
     ) -> Result<tonic::Response<Response>, tonic::Status> {
+        audit(&request, "create dataflow", &request.get_ref().job_id);
         self.coordinator
             .create_dataflow(request.into_inner())
             .await
-            .map(|_| tonic::Response::new(Response::ok()))
+            .map_err(with_error_detail)
+            .map(|(placement, warnings)| {
+                tonic::Response::new(Response {
+                    warnings,
+                    ..Response::with_placement(placement)
+                })
+            })
+    }
+    async fn update_dataflow(
+        &self,
+        request: tonic::Request<UpdateDataflowRequest>,
+    ) -> Result<tonic::Response<UpdateDataflowResponse>, tonic::Status> {
+        audit(
+            &request,
+            "update dataflow",
+            &request
+                .get_ref()
+                .dataflow
+                .as_ref()
+                .and_then(|dataflow| dataflow.job_id.as_ref()),
+        );
+        self.coordinator
+            .update_dataflow(request.into_inner())
+            .await
+            .map_err(with_error_detail)
+            .map(new_rpc_response)
     }
     async fn terminate_dataflow(
         &self,
         request: tonic::Request<ResourceId>,
     ) -> Result<tonic::Response<Response>, tonic::Status> {
+        audit(&request, "terminate dataflow", request.get_ref());
         self.coordinator
             .terminate_dataflow(request.get_ref())
             .await
+            .map_err(with_error_detail)
             .map(|status| tonic::Response::new(Response::ok()))
     }
+    async fn terminate_dataflows(
+        &self,
+        request: tonic::Request<TerminateDataflowsRequest>,
+    ) -> Result<tonic::Response<TerminateDataflowsResponse>, tonic::Status> {
+        audit(&request, "terminate dataflows", &request.get_ref().job_ids);
+        Ok(new_rpc_response(
+            self.coordinator
+                .terminate_dataflows(request.get_ref())
+                .await,
+        ))
+    }
+
     async fn get_dataflow(
         &self,
         request: tonic::Request<GetDataflowRequest>,
     ) -> Result<tonic::Response<DataflowStates>, tonic::Status> {
         self.coordinator
-            .get_dataflow(request.get_ref().job_id.as_ref().unwrap())
+            .get_dataflow(request.get_ref())
             .await
+            .map_err(with_error_detail)
             .and_then(|dataflow| Ok(new_rpc_response(dataflow)))
     }
+
+    async fn list_dataflows(
+        &self,
+        request: tonic::Request<ListDataflowsRequest>,
+    ) -> Result<tonic::Response<ListDataflowsResponse>, tonic::Status> {
+        self.coordinator
+            .list_dataflows(request.get_ref())
+            .await
+            .map_err(with_error_detail)
+            .map(new_rpc_response)
+    }
+
+    async fn get_dataflow_status(
+        &self,
+        request: tonic::Request<GetDataflowStatusRequest>,
+    ) -> Result<tonic::Response<DataflowRuntimeStatus>, tonic::Status> {
+        self.coordinator
+            .get_dataflow_status(request.get_ref())
+            .await
+            .map_err(with_error_detail)
+            .map(new_rpc_response)
+    }
+
+    async fn get_cluster_topology(
+        &self,
+        _: tonic::Request<GetClusterTopologyRequest>,
+    ) -> Result<tonic::Response<ClusterTopology>, tonic::Status> {
+        Ok(new_rpc_response(self.coordinator.get_cluster_topology()))
+    }
+
+    async fn trigger_savepoint(
+        &self,
+        request: tonic::Request<ResourceId>,
+    ) -> Result<tonic::Response<Savepoint>, tonic::Status> {
+        audit(&request, "trigger savepoint", request.get_ref());
+        self.coordinator
+            .trigger_savepoint(request.get_ref())
+            .await
+            .map_err(with_error_detail)
+            .map(new_rpc_response)
+    }
+
+    async fn list_savepoints(
+        &self,
+        request: tonic::Request<ResourceId>,
+    ) -> Result<tonic::Response<ListSavepointsResponse>, tonic::Status> {
+        self.coordinator
+            .list_savepoints(request.get_ref())
+            .await
+            .map_err(with_error_detail)
+            .map(|savepoints| new_rpc_response(ListSavepointsResponse { savepoints }))
+    }
+
+    async fn delete_savepoint(
+        &self,
+        request: tonic::Request<DeleteSavepointRequest>,
+    ) -> Result<tonic::Response<Response>, tonic::Status> {
+        audit(&request, "delete savepoint", &request.get_ref().path);
+        let request = request.get_ref();
+        self.coordinator
+            .delete_savepoint(request.job_id.as_ref(), &request.path)
+            .await
+            .map_err(with_error_detail)
+            .map(|_| tonic::Response::new(Response::ok()))
+    }
+
+    async fn add_node(
+        &self,
+        request: tonic::Request<AddNodeRequest>,
+    ) -> Result<tonic::Response<AddNodeResponse>, tonic::Status> {
+        audit(&request, "add node", &request.get_ref().host_addr);
+        self.coordinator
+            .add_node(request.get_ref())
+            .await
+            .map_err(with_error_detail)
+            .map(new_rpc_response)
+    }
 }
@@ -1,19 +1,49 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::fs;
 
 use common::net::cluster;
 use common::net::AckResponderBuilder;
 use common::net::HeartbeatBuilder;
+use common::snapshot::SnapshotStoreBuilder;
 use common::utils;
 use proto::common::Ack;
 use proto::common::Dataflow;
+use proto::common::DataflowPlacement;
 use proto::common::DataflowStates;
 use proto::common::DataflowStatus;
+use proto::common::ErrorDetail;
 
 use proto::common::Heartbeat;
 use proto::common::NodeType;
+use proto::common::OperatorError;
 use proto::common::ResourceId;
+use proto::common_impl::LintSeverity;
+use proto::coordinator::AddNodeRequest;
+use proto::coordinator::AddNodeResponse;
+use proto::coordinator::ClusterTopology;
+use proto::coordinator::DataflowRuntimeStatus;
+use proto::coordinator::GetDataflowRequest;
+use proto::coordinator::GetDataflowStatusRequest;
+use proto::coordinator::ListDataflowsRequest;
+use proto::coordinator::ListDataflowsResponse;
+use proto::coordinator::Savepoint;
+use proto::coordinator::TerminateDataflowResult;
+use proto::coordinator::TerminateDataflowsRequest;
+use proto::coordinator::TerminateDataflowsResponse;
+use proto::coordinator::UpdateDataflowRequest;
+use proto::coordinator::UpdateDataflowResponse;
+use proto::coordinator::UpdateStrategy;
+use proto::taskmanager::StopMode;
+
+use crate::errors::coordinator::dependent_dataflows;
+use crate::errors::coordinator::job_id_unprovided;
+use crate::errors::coordinator::missing_dependencies;
+use crate::errors::coordinator::unsupported_update_strategy;
 
 use super::managers::Dispatcher;
+use super::managers::DispatcherException;
+use super::savepoints::get_savepoint_warnings;
 use super::storage::DataflowStorageBuilder;
 
 /// Builder for [Coordinator]
@@ -30,6 +60,99 @@ pub struct CoordinatorBuilder {
     pub heartbeat: HeartbeatBuilder,
     // ack responder builder
     pub ack: AckResponderBuilder,
+    /// the snapshot store which the subdataflows are restored from, it should be the same as TaskManager's
+    #[serde(default)]
+    pub snapshot_store: Option<SnapshotStoreBuilder>,
+    /// the jobs which are allowed to be submitted, all of them are allowed if it's not configured
+    #[serde(default)]
+    pub submission: Option<SubmissionPolicy>,
+    /// how the backpressure of the operators is sampled and avoided on placement
+    #[serde(default)]
+    pub backpressure: cluster::BackpressureConfig,
+    /// how the TaskManager nodes are probed and detected down
+    #[serde(default)]
+    pub liveness: cluster::LivenessConfig,
+    /// whether the operators are moved onto the TaskManager nodes added while the coordinator serves
+    #[serde(default)]
+    pub rebalance: cluster::RebalanceConfig,
+    /// what happens if the dependencies declared by the dataflows are not met
+    #[serde(default)]
+    pub dependencies: DependencyPolicy,
+}
+
+/// What happens if a dataflow is created before the jobs it depends on are running, or a job is terminated while running dataflows depend on it.
+/// See `depends_on` of [`Dataflow`]. Dataflows terminated in the same batch as the jobs they depend on are not counted
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum DependencyPolicy {
+    /// the dataflow is created or terminated anyway, and the unmet dependencies are logged. They're warnings of the created dataflow as well
+    #[default]
+    Warn,
+    /// the creation or the termination fails as a failed precondition
+    Block,
+}
+
+/// The jobs which are allowed to be submitted in a shared cluster.
+/// A pattern is a glob of `namespace` or `namespace/job`, where `*` matches any characters and `?` matches one character.
+/// A job is denied if it matches any pattern of `deny`. Otherwise it's allowed if `allow` is empty or it matches any pattern of `allow`
+#[derive(serde::Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct SubmissionPolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl SubmissionPolicy {
+    pub fn is_allowed(&self, job_id: &ResourceId) -> bool {
+        let matched = |pattern: &String| match pattern.split_once('/') {
+            Some((namespace, job)) => {
+                glob_match(namespace, &job_id.namespace_id) && glob_match(job, &job_id.resource_id)
+            }
+            None => glob_match(pattern, &job_id.namespace_id),
+        };
+        !self.deny.iter().any(matched) && (self.allow.is_empty() || self.allow.iter().any(matched))
+    }
+
+    fn check(&self, job_id: &ResourceId) -> Result<(), tonic::Status> {
+        if self.is_allowed(job_id) {
+            Ok(())
+        } else {
+            Err(tonic::Status::permission_denied(format!(
+                "job {}/{} is not allowed to be submitted",
+                job_id.namespace_id, job_id.resource_id
+            )))
+        }
+    }
+}
+
+/// whether the text matches the glob pattern, `*` matches any characters and `?` matches one character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    // where the last `*` is in the pattern, and where the text is when it starts to match
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // the last `*` matches one more character
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
 }
 
 impl CoordinatorBuilder {
@@ -41,7 +164,13 @@ impl CoordinatorBuilder {
                 &self.heartbeat,
                 &self.ack,
                 self.port,
-            ),
+            )
+            .with_snapshot_store(self.snapshot_store.as_ref())
+            .with_backpressure(&self.backpressure)
+            .with_liveness(&self.liveness)
+            .with_rebalance(&self.rebalance),
+            submission: self.submission.clone().unwrap_or_default(),
+            dependencies: self.dependencies,
         }
     }
 }
@@ -71,56 +200,421 @@ pub fn load_builder() -> CoordinatorBuilder {
 /// - Scale Up and Scale Down
 pub struct Coordinator {
     dispatcher: Dispatcher,
+    submission: SubmissionPolicy,
+    dependencies: DependencyPolicy,
 }
 
 impl Coordinator {
-    pub(crate) async fn create_dataflow(&self, dataflow: Dataflow) -> Result<(), tonic::Status> {
+    /// recover the dataflows deployed before restart. It should be called before the coordinator serves
+    pub fn init(&self) {
+        self.dispatcher.init()
+    }
+
+    /// whether the coordinator is ready to accept dataflows: the storage is reachable and any TaskManager answers
+    pub(crate) async fn is_ready(&self) -> bool {
+        self.dispatcher.is_ready().await
+    }
+
+    /// sample the backpressure of the operators periodically until the task is aborted. It returns at once if sampling is disabled
+    pub(crate) async fn watch_backpressure(&self) {
+        let period = self
+            .dispatcher
+            .get_backpressure_config()
+            .get_sample_interval();
+        if period.is_zero() {
+            return;
+        }
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            self.dispatcher.sample_backpressure().await;
+        }
+    }
+
+    /// probe the TaskManager nodes periodically until the task is aborted. Suspected nodes are probed more frequently.
+    /// It returns at once if probing is disabled
+    pub(crate) async fn watch_liveness(&self) {
+        let config = self.dispatcher.get_liveness_config();
+        let period = config.get_heartbeat_interval();
+        if period.is_zero() {
+            return;
+        }
+        let suspect = config.get_suspect_interval();
+        let period = if suspect.is_zero() {
+            period
+        } else {
+            period.min(suspect)
+        };
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            self.dispatcher.heartbeat().await;
+        }
+    }
+
+    /// create a dataflow and return where it's placed, with the warnings of restoring it from a savepoint if `restore_from` is set, or warm-starting it if `warm_start_from` is set.
+    /// The dataflow is denied if the [`SubmissionPolicy`] doesn't allow its job
+    pub(crate) async fn create_dataflow(
+        &self,
+        dataflow: Dataflow,
+    ) -> Result<(DataflowPlacement, Vec<String>), tonic::Status> {
         match dataflow
             .validate()
             .map_err(|err| tonic::Status::invalid_argument(format!("{:?}", err)))
         {
             Ok(_) => {
+                let job_id = dataflow.job_id.as_ref().unwrap();
+                self.submission.check(job_id)?;
+                // warnings never block the creation
+                for warning in dataflow.lint() {
+                    match warning.severity {
+                        LintSeverity::Warning => tracing::warn!("job {:?}: {}", job_id, warning),
+                        LintSeverity::Info => tracing::info!("job {:?}: {}", job_id, warning),
+                    }
+                }
+                // the savepoint is loaded before the running dataflow is terminated, so that it keeps running if the savepoint is invalid
+                let savepoint = if dataflow.restore_from.is_empty() {
+                    None
+                } else {
+                    Some(
+                        self.dispatcher
+                            .load_savepoint(&dataflow.restore_from)
+                            .await
+                            .map_err(|err| err.to_tonic_status())?,
+                    )
+                };
+                let mut warnings = self.check_dependencies(&dataflow)?;
+                warnings.extend(
+                    savepoint
+                        .as_ref()
+                        .map(|savepoint| get_savepoint_warnings(&dataflow, savepoint))
+                        .unwrap_or_default(),
+                );
+                if let Some(prior_job_id) = dataflow.warm_start_from.as_ref() {
+                    if savepoint.is_some() {
+                        warnings.push(format!(
+                            "job {:?} is not warm-started from because the dataflow is restored from a savepoint",
+                            prior_job_id
+                        ));
+                    } else if !self.dispatcher.has_snapshot_store() {
+                        warnings.push(format!(
+                            "job {:?} is not warm-started from because no snapshot store is configured",
+                            prior_job_id
+                        ));
+                    }
+                }
+                warnings
+                    .iter()
+                    .for_each(|warning| tracing::warn!("job {:?}: {}", job_id, warning));
+
+                // the running dataflow is replaced at once, so the ones depending on it are not checked
                 let terminate_result = self
-                    .terminate_dataflow(dataflow.job_id.as_ref().unwrap())
+                    .dispatcher
+                    .terminate_dataflow(dataflow.job_id.as_ref().unwrap(), StopMode::Drain)
                     .await;
-                if terminate_result.is_err() {
-                    return terminate_result.map(|_| ());
+                if let Err(err) = terminate_result {
+                    return Err(err.to_tonic_status());
                 }
                 self.dispatcher
-                    .create_dataflow(dataflow)
+                    .create_dataflow(dataflow, savepoint.as_ref())
                     .await
+                    .map(|placement| (placement, warnings))
                     .map_err(|err| err.to_tonic_status())
             }
             Err(err) => Err(err),
         }
     }
 
+    /// update a running dataflow by the strategy of the request, see [`Dispatcher::update_dataflow`]. Only the `Recreate` strategy is supported.
+    /// The new spec is validated and checked by the [`SubmissionPolicy`] like a created dataflow
+    pub(crate) async fn update_dataflow(
+        &self,
+        request: UpdateDataflowRequest,
+    ) -> Result<UpdateDataflowResponse, tonic::Status> {
+        let strategy = request.strategy();
+        let dataflow = request
+            .dataflow
+            .ok_or_else(|| tonic::Status::invalid_argument("no dataflow provided"))?;
+        dataflow
+            .validate()
+            .map_err(|err| tonic::Status::invalid_argument(format!("{:?}", err)))?;
+        let job_id = dataflow.get_job_id();
+        self.submission.check(&job_id)?;
+        if strategy != UpdateStrategy::Recreate {
+            return Err(unsupported_update_strategy(strategy.as_str_name()).into_tonic_status());
+        }
+
+        let (mut response, savepoint) = self
+            .dispatcher
+            .update_dataflow(dataflow.clone(), request.resource_version)
+            .await
+            .map_err(|err| err.to_tonic_status())?;
+        response.warnings = get_savepoint_warnings(&dataflow, &savepoint);
+        response
+            .warnings
+            .iter()
+            .for_each(|warning| tracing::warn!("job {:?}: {}", &job_id, warning));
+        Ok(response)
+    }
+
+    /// the warnings of the dependencies of the dataflow which are not running, or the error if they're blocked by the [`DependencyPolicy`]
+    fn check_dependencies(&self, dataflow: &Dataflow) -> Result<Vec<String>, tonic::Status> {
+        let missing = self.dispatcher.get_missing_dependencies(dataflow);
+        if missing.is_empty() {
+            return Ok(vec![]);
+        }
+        let err = missing_dependencies(&dataflow.get_job_id(), &missing);
+        match self.dependencies {
+            DependencyPolicy::Warn => Ok(vec![err.biz_err.message]),
+            DependencyPolicy::Block => Err(err.into_tonic_status()),
+        }
+    }
+
+    /// whether the job can be terminated while the running dataflows depending on it are not terminated together with it
+    fn check_dependents(
+        &self,
+        job_id: &ResourceId,
+        terminated: &BTreeSet<&ResourceId>,
+    ) -> Result<(), tonic::Status> {
+        let dependents = self
+            .dispatcher
+            .get_dependents(job_id)
+            .into_iter()
+            .filter(|dependent| !terminated.contains(dependent))
+            .collect::<Vec<_>>();
+        if dependents.is_empty() {
+            return Ok(());
+        }
+        let err = dependent_dataflows(job_id, &dependents);
+        match self.dependencies {
+            DependencyPolicy::Warn => {
+                tracing::warn!("{}", err.biz_err.message);
+                Ok(())
+            }
+            DependencyPolicy::Block => Err(err.into_tonic_status()),
+        }
+    }
+
+    /// the dataflow is drained before it's terminated, so the events buffered in its operators are not lost
     pub(crate) async fn terminate_dataflow(
         &self,
         job_id: &ResourceId,
     ) -> Result<DataflowStatus, tonic::Status> {
+        self.check_dependents(job_id, &BTreeSet::from([job_id]))?;
         self.dispatcher
-            .terminate_dataflow(job_id)
+            .terminate_dataflow(job_id, StopMode::Drain)
             .await
             .map_err(|err| err.to_tonic_status())
     }
 
+    /// the dataflows are drained before they're terminated like [`Coordinator::terminate_dataflow`], unless they're forced to stop
+    pub(crate) async fn terminate_dataflows(
+        &self,
+        request: &TerminateDataflowsRequest,
+    ) -> TerminateDataflowsResponse {
+        let mode = if request.force {
+            StopMode::Immediate
+        } else {
+            StopMode::Drain
+        };
+        // a job is blocked if the dataflows depending on it are blocked, so the blocked ones are excluded until none is blocked more
+        let mut terminated = request.job_ids.iter().collect::<BTreeSet<_>>();
+        let mut blocked = BTreeMap::new();
+        loop {
+            let newly_blocked = terminated
+                .iter()
+                .filter_map(|job_id| {
+                    self.check_dependents(job_id, &terminated)
+                        .err()
+                        .map(|status| (*job_id, status))
+                })
+                .collect::<Vec<_>>();
+            if newly_blocked.is_empty() {
+                break;
+            }
+            newly_blocked.into_iter().for_each(|(job_id, status)| {
+                terminated.remove(job_id);
+                blocked.insert(job_id.clone(), status);
+            });
+        }
+        let job_ids = request
+            .job_ids
+            .iter()
+            .filter(|job_id| terminated.contains(job_id))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut results = self
+            .dispatcher
+            .terminate_dataflows(&job_ids, mode)
+            .await
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+
+        // the results are in the order of the request like the ones of the dispatcher
+        let mut distinct = BTreeSet::new();
+        let results = request
+            .job_ids
+            .iter()
+            .filter(|job_id| distinct.insert(*job_id))
+            .filter_map(|job_id| {
+                let result = match blocked.remove(job_id) {
+                    Some(status) => Err(DispatcherException::Tonic(status)),
+                    None => results.remove(job_id)?,
+                };
+                Some((job_id.clone(), result))
+            })
+            .map(|(job_id, result)| match result {
+                Ok(status) => TerminateDataflowResult {
+                    job_id: Some(job_id),
+                    status: status as i32,
+                    error: None,
+                    worker_failures: vec![],
+                },
+                Err(err) => {
+                    let status = err.to_tonic_status();
+                    tracing::error!("terminate job {:?} failed: {}", &job_id, status);
+                    TerminateDataflowResult {
+                        job_id: Some(job_id),
+                        status: Default::default(),
+                        error: Some(ErrorDetail::from_status(&status)),
+                        worker_failures: match err {
+                            DispatcherException::TerminationFailed(failures) => failures,
+                            _ => vec![],
+                        },
+                    }
+                }
+            })
+            .collect();
+        TerminateDataflowsResponse { results }
+    }
+
     pub(crate) async fn get_dataflow(
         &self,
-        job_id: &ResourceId,
+        request: &GetDataflowRequest,
     ) -> Result<DataflowStates, tonic::Status> {
+        match request.job_id.as_ref() {
+            Some(job_id) => self
+                .dispatcher
+                .get_dataflow(job_id, request.effective, request.include_metrics)
+                .await
+                .map_err(|err| err.to_tonic_status()),
+            None => Err(job_id_unprovided().into_tonic_status()),
+        }
+    }
+
+    pub(crate) async fn get_dataflow_status(
+        &self,
+        request: &GetDataflowStatusRequest,
+    ) -> Result<DataflowRuntimeStatus, tonic::Status> {
+        match request.job_id.as_ref() {
+            Some(job_id) => self
+                .dispatcher
+                .get_dataflow_status(job_id, request.with_operators)
+                .await
+                .map_err(|err| err.to_tonic_status()),
+            None => Err(job_id_unprovided().into_tonic_status()),
+        }
+    }
+
+    pub(crate) async fn list_dataflows(
+        &self,
+        request: &ListDataflowsRequest,
+    ) -> Result<ListDataflowsResponse, tonic::Status> {
+        self.dispatcher
+            .list_dataflows(request)
+            .await
+            .map_err(|err| err.to_tonic_status())
+    }
+
+    pub(crate) async fn trigger_savepoint(
+        &self,
+        job_id: &ResourceId,
+    ) -> Result<Savepoint, tonic::Status> {
         self.dispatcher
-            .get_dataflow(job_id)
+            .trigger_savepoint(job_id)
             .await
             .map_err(|err| err.to_tonic_status())
     }
 
+    pub(crate) async fn list_savepoints(
+        &self,
+        job_id: &ResourceId,
+    ) -> Result<Vec<Savepoint>, tonic::Status> {
+        self.dispatcher
+            .list_savepoints(job_id)
+            .await
+            .map_err(|err| err.to_tonic_status())
+    }
+
+    pub(crate) async fn delete_savepoint(
+        &self,
+        job_id: Option<&ResourceId>,
+        path: &str,
+    ) -> Result<(), tonic::Status> {
+        match job_id {
+            Some(job_id) => self
+                .dispatcher
+                .delete_savepoint(job_id, path)
+                .await
+                .map_err(|err| err.to_tonic_status()),
+            None => Err(job_id_unprovided().into_tonic_status()),
+        }
+    }
+
+    pub(crate) fn get_cluster_topology(&self) -> ClusterTopology {
+        self.dispatcher.get_cluster_topology()
+    }
+
+    /// add a TaskManager node to the cluster, and move the operators it wins onto it if the rebalance is enabled, see [`Dispatcher::add_node`]
+    pub(crate) async fn add_node(
+        &self,
+        request: &AddNodeRequest,
+    ) -> Result<AddNodeResponse, tonic::Status> {
+        let host_addr = request
+            .host_addr
+            .as_ref()
+            .filter(|host_addr| host_addr.is_valid() && host_addr.port <= u16::MAX as u32)
+            .ok_or_else(|| tonic::Status::invalid_argument("no valid host address provided"))?;
+        let (node, migrations) = self
+            .dispatcher
+            .add_node(&host_addr.into())
+            .await
+            .map_err(|err| err.to_tonic_status())?;
+        migrations
+            .iter()
+            .filter(|migration| !migration.error.is_empty())
+            .for_each(|migration| {
+                tracing::warn!(
+                    "job {:?} is not migrated: {}",
+                    &migration.job_id,
+                    &migration.error
+                )
+            });
+        Ok(AddNodeResponse {
+            node: Some(node),
+            migrations,
+        })
+    }
+
     pub(crate) async fn receive_heartbeart(&self, heartbeat: &Heartbeat) {
         self.dispatcher
             .update_task_manager_heartbeat_status(heartbeat)
             .await
     }
 
+    pub(crate) async fn report_operator_error(
+        &self,
+        err: OperatorError,
+    ) -> Result<(), tonic::Status> {
+        if err.job_id.is_none() {
+            return Err(job_id_unprovided().into_tonic_status());
+        }
+        self.dispatcher
+            .report_operator_error(err)
+            .await
+            .map_err(|err| err.to_tonic_status())
+    }
+
     pub(crate) async fn receive_ack(&self, ack: Ack) {
         match ack.node_type() {
             NodeType::TaskWorker => self.dispatcher.ack_from_task_manager(ack).await,
@@ -128,3 +622,261 @@ impl Coordinator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proto::common::{
+        mapper, operator_info::Details, Dataflow, DataflowMeta, Func, Mapper, OperatorInfo,
+        ResourceId,
+    };
+
+    use proto::coordinator::{
+        GetDataflowRequest, TerminateDataflowResult, TerminateDataflowsRequest,
+    };
+
+    use super::{glob_match, CoordinatorBuilder, DependencyPolicy, SubmissionPolicy};
+
+    fn new_job_id(namespace: &str, job: &str) -> ResourceId {
+        ResourceId::new(namespace, job)
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("team-a", "team-a"));
+        assert!(!glob_match("team-a", "team-ab"));
+        assert!(glob_match("team-*", "team-a"));
+        assert!(glob_match("team-*", "team-"));
+        assert!(glob_match("*-prod", "payments-prod"));
+        assert!(glob_match("t?am-*-prod", "team-payments-prod"));
+        assert!(!glob_match("t?am", "tam"));
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(!glob_match("*a*b", "xaxxa"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_submission_policy() {
+        // everything is allowed by default
+        assert!(SubmissionPolicy::default().is_allowed(&new_job_id("default", "job")));
+
+        let policy = SubmissionPolicy {
+            allow: vec!["team-*".to_string(), "default/etl-?".to_string()],
+            deny: vec!["team-sandbox".to_string(), "*/debug-*".to_string()],
+        };
+        // allowed by the namespace pattern
+        assert!(policy.is_allowed(&new_job_id("team-a", "job")));
+        // allowed by the job pattern
+        assert!(policy.is_allowed(&new_job_id("default", "etl-1")));
+        assert!(!policy.is_allowed(&new_job_id("default", "etl-10")));
+        // not in the allowlist
+        assert!(!policy.is_allowed(&new_job_id("other", "job")));
+        // the denylist wins
+        assert!(!policy.is_allowed(&new_job_id("team-sandbox", "job")));
+        assert!(!policy.is_allowed(&new_job_id("team-a", "debug-1")));
+
+        let status = policy.check(&new_job_id("other", "job")).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    /// a coordinator whose TaskManager at 127.0.0.1:8824 is unreachable, the options override the default ones
+    fn new_builder(options: serde_json::Value) -> CoordinatorBuilder {
+        let mut config = serde_json::json!({
+            "port": 8823,
+            "cluster": {
+                "nodes": "127.0.0.1:8824",
+                "rpc_timeout": 3,
+                "connect_timeout": 3
+            },
+            "storage": {
+                "Memory": {
+                    "ttl": null,
+                    "max_entries": null
+                }
+            },
+            "heartbeat": {
+                "period": 3,
+                "connect_timeout": 3,
+                "rpc_timeout": 3
+            },
+            "ack": {
+                "delay": 1,
+                "buf_size": 500,
+                "connect_timeout": 3,
+                "rpc_timeout": 3
+            }
+        });
+        options
+            .as_object()
+            .unwrap()
+            .iter()
+            .for_each(|(key, value)| config[key] = value.clone());
+        serde_json::from_value(config).unwrap()
+    }
+
+    fn new_dataflow(job_id: ResourceId, depends_on: Vec<ResourceId>) -> Dataflow {
+        Dataflow {
+            job_id: Some(job_id),
+            meta: vec![DataflowMeta {
+                center: 0,
+                ..Default::default()
+            }],
+            nodes: [(
+                0,
+                OperatorInfo {
+                    operator_id: 0,
+                    details: Some(Details::Mapper(Mapper {
+                        value: Some(mapper::Value::Func(Func {
+                            function: "(a) => a".to_string(),
+                        })),
+                    })),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            depends_on,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_dataflow_denied() {
+        let builder = new_builder(serde_json::json!({
+            "submission": {
+                "deny": ["sandbox"]
+            }
+        }));
+        assert_eq!(
+            builder.submission,
+            Some(SubmissionPolicy {
+                allow: vec![],
+                deny: vec!["sandbox".to_string()]
+            })
+        );
+        let coordinator = builder.build();
+        let dataflow = new_dataflow(new_job_id("sandbox", "job"), vec![]);
+
+        let status = coordinator.create_dataflow(dataflow).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_create_dataflow_invalid_operator_config() {
+        let coordinator = new_builder(serde_json::json!({})).build();
+        let job_id = new_job_id("default", "job");
+        let mut dataflow = new_dataflow(job_id.clone(), vec![]);
+        dataflow.nodes.get_mut(&0).unwrap().details = Some(Details::Mapper(Mapper {
+            value: Some(mapper::Value::Func(Func {
+                function: " ".to_string(),
+            })),
+        }));
+
+        // the dataflow is rejected before it's dispatched
+        let status = coordinator.create_dataflow(dataflow).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(status.message().contains("mapper.value.func.function"));
+        assert!(coordinator
+            .get_dataflow(&GetDataflowRequest {
+                job_id: Some(job_id),
+                effective: false,
+                include_metrics: false,
+            })
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dataflow_dependencies() {
+        let (ingest, enrich) = (
+            new_job_id("default", "ingest"),
+            new_job_id("default", "enrich"),
+        );
+        let error_code = |result: &TerminateDataflowResult| {
+            result
+                .error
+                .as_ref()
+                .map(|error| error.get_code())
+                .unwrap_or(tonic::Code::Ok)
+        };
+
+        let coordinator = new_builder(serde_json::json!({ "dependencies": "Block" })).build();
+        assert_eq!(coordinator.dependencies, DependencyPolicy::Block);
+        // the dependency starts first
+        let status = coordinator
+            .create_dataflow(new_dataflow(enrich.clone(), vec![ingest.clone()]))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert!(coordinator.dispatcher.get_dependents(&ingest).is_empty());
+
+        // the dataflows are managed though their partitions fail to start on the unreachable TaskManager
+        let _ = coordinator
+            .create_dataflow(new_dataflow(ingest.clone(), vec![]))
+            .await;
+        let created = coordinator
+            .create_dataflow(new_dataflow(enrich.clone(), vec![ingest.clone()]))
+            .await;
+        assert_ne!(
+            created.err().map(|status| status.code()),
+            Some(tonic::Code::FailedPrecondition)
+        );
+        assert_eq!(
+            coordinator.dispatcher.get_dependents(&ingest),
+            vec![enrich.clone()]
+        );
+
+        // the dependency can't be terminated while the dataflow depending on it is running
+        let status = coordinator.terminate_dataflow(&ingest).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        let response = coordinator
+            .terminate_dataflows(&TerminateDataflowsRequest {
+                job_ids: vec![ingest.clone()],
+                force: true,
+            })
+            .await;
+        assert_eq!(
+            error_code(&response.results[0]),
+            tonic::Code::FailedPrecondition
+        );
+        assert_eq!(
+            coordinator.dispatcher.get_dependents(&ingest),
+            vec![enrich.clone()]
+        );
+
+        // unless they're terminated together
+        let response = coordinator
+            .terminate_dataflows(&TerminateDataflowsRequest {
+                job_ids: vec![ingest.clone(), enrich.clone()],
+                force: true,
+            })
+            .await;
+        assert_eq!(response.results.len(), 2);
+        response.results.iter().for_each(|result| {
+            assert_ne!(error_code(result), tonic::Code::FailedPrecondition);
+        });
+
+        // the unmet dependencies are only warned by default
+        let coordinator = new_builder(serde_json::json!({})).build();
+        assert_eq!(coordinator.dependencies, DependencyPolicy::Warn);
+        let warnings = coordinator
+            .check_dependencies(&new_dataflow(enrich.clone(), vec![ingest.clone()]))
+            .unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("default/ingest"));
+        let _ = coordinator
+            .create_dataflow(new_dataflow(ingest.clone(), vec![]))
+            .await;
+        let _ = coordinator
+            .create_dataflow(new_dataflow(enrich.clone(), vec![ingest.clone()]))
+            .await;
+        let response = coordinator
+            .terminate_dataflows(&TerminateDataflowsRequest {
+                job_ids: vec![ingest.clone()],
+                force: true,
+            })
+            .await;
+        assert_ne!(
+            error_code(&response.results[0]),
+            tonic::Code::FailedPrecondition
+        );
+    }
+}
@@ -11,13 +11,15 @@ use common::{
 use proto::{
     common::{
         ack::{AckType, RequestId},
-        Ack, Dataflow, Heartbeat, HostAddr, NodeType, OperatorInfo, ResourceId, SubDataflowId,
-        SubDataflowStates,
+        Ack, Dataflow, Heartbeat, HostAddr, NodeType, OperatorInfo, OperatorStates, ResourceId,
+        SubDataflowId, SubDataflowStates,
     },
-    taskmanager::CreateSubDataflowRequest,
+    taskmanager::{CreateSubDataflowRequest, StopDataflowRequest, StopMode},
 };
 use tokio::{sync::mpsc, task::JoinHandle};
 
+use crate::errors::coordinator::task_deployment_err;
+
 /// This module contains all logical execution contexts of a dataflow, an operator or an edge which are running on the remote TaskManager node.
 /// These contexts contains data which can reflect the inner state of the dataflows, operators and edges such as running or not, checkpoint status.
 ///
@@ -110,10 +112,18 @@ pub(crate) struct SubdataflowDeploymentPlan<'a> {
     job_id: &'a ResourceId,
     /// the node of TaskManager
     node: Option<&'a Node>,
+    /// the address of coordinator which operator errors will be reported to
+    coordinator: &'a HostAddr,
     /// ack responder
     ack: &'a AckResponderBuilder,
     // heartbeat sender
     heartbeat: &'a HeartbeatBuilder,
+    /// the key of the manifest of the remote checkpoint which the subdataflow is restored from
+    restore_from: Option<String>,
+    /// the operator states of the savepoint which the subdataflow is restored from. It has priority over `restore_from`
+    savepoint: Option<OperatorStates>,
+    /// the keys of the manifests of the prior job's latest checkpoints which the compatible operators are warm-started from
+    warm_start_from: Vec<String>,
 }
 
 impl<'a> SubdataflowDeploymentPlan<'a> {
@@ -121,6 +131,7 @@ impl<'a> SubdataflowDeploymentPlan<'a> {
         subdataflow: (&'a HostAddr, &'a mut Dataflow),
         job_id: &'a ResourceId,
         node: Option<&'a Node>,
+        coordinator: &'a HostAddr,
         ack_builder: &'a AckResponderBuilder,
         heartbeat_builder: &'a HeartbeatBuilder,
     ) -> Self {
@@ -129,11 +140,30 @@ impl<'a> SubdataflowDeploymentPlan<'a> {
             addr: subdataflow.0,
             job_id,
             node,
+            coordinator,
             ack: ack_builder,
             heartbeat: heartbeat_builder,
+            restore_from: None,
+            savepoint: None,
+            warm_start_from: vec![],
         }
     }
 
+    pub(crate) fn with_restore_from(mut self, restore_from: Option<String>) -> Self {
+        self.restore_from = restore_from;
+        self
+    }
+
+    pub(crate) fn with_savepoint(mut self, savepoint: Option<OperatorStates>) -> Self {
+        self.savepoint = savepoint;
+        self
+    }
+
+    pub(crate) fn with_warm_start_from(mut self, warm_start_from: &[String]) -> Self {
+        self.warm_start_from = warm_start_from.to_vec();
+        self
+    }
+
     #[inline]
     pub(crate) async fn deploy(mut self) -> Result<SubdataflowExecution, TaskDeploymentException> {
         match &self.node {
@@ -146,6 +176,10 @@ impl<'a> SubdataflowDeploymentPlan<'a> {
                 let req = CreateSubDataflowRequest {
                     job_id: Some(self.subdataflow.get_job_id()),
                     dataflow: Some(self.subdataflow.clone()),
+                    coordinator: Some(self.coordinator.clone()),
+                    restore_from: self.restore_from.take().unwrap_or_default(),
+                    savepoint: self.savepoint.take(),
+                    warm_start_from: std::mem::take(&mut self.warm_start_from),
                 };
 
                 match node.get_gateway().create_sub_dataflow(req).await {
@@ -172,6 +206,17 @@ pub(crate) enum TaskDeploymentException {
     RpcError(tonic::Status),
 }
 
+impl TaskDeploymentException {
+    pub(crate) fn to_tonic_status(&self) -> tonic::Status {
+        match self {
+            TaskDeploymentException::InvalidWorkerEndpoint => {
+                task_deployment_err("invalid worker endpoint").into_tonic_status()
+            }
+            TaskDeploymentException::RpcError(status) => status.clone(),
+        }
+    }
+}
+
 /// A [`SubdataflowExecution`] represents a execution context of a subdataflow. It's responsible for:
 /// - watch the status of subdataflow
 /// - send heartbeat ack to TaskWorker
@@ -210,14 +255,28 @@ impl SubdataflowExecution {
         }
     }
 
-    pub(crate) fn try_terminate(&self) {
-        todo!()
+    /// stop the subdataflow on the remote TaskManager. It returns once the subdataflow is stopped
+    pub(crate) async fn try_terminate(&self, mode: StopMode) -> Result<(), SubdataflowError> {
+        self.worker
+            .get_gateway()
+            .stop_dataflow(StopDataflowRequest {
+                job_id: Some(self.get_execution_id().get_job_id()),
+                mode: mode as i32,
+            })
+            .await
+            .map(|_| {})
+            .map_err(|err| SubdataflowError::RpcError(err))
     }
 
     pub(crate) fn get_execution_id(&self) -> &SubDataflowId {
         &self.execution_id
     }
 
+    /// the address of the TaskManager where the subdataflow is deployed
+    pub(crate) fn get_host_addr(&self) -> &HostAddr {
+        &self.worker.host_addr
+    }
+
     pub(crate) async fn update_heartbeat_status(&self, heartbeat: &Heartbeat) {
         match heartbeat.timestamp.as_ref() {
             Some(timestamp) => match heartbeat.node_type() {
@@ -246,6 +305,16 @@ impl SubdataflowExecution {
             .await
             .map_err(|err| SubdataflowError::RpcError(err))
     }
+
+    /// snapshot the states of all operators in the subdataflow as a savepoint
+    pub(crate) async fn trigger_savepoint(&self) -> Result<OperatorStates, SubdataflowError> {
+        let job_id = self.get_execution_id().get_job_id();
+        self.worker
+            .get_gateway()
+            .trigger_savepoint(job_id)
+            .await
+            .map_err(|err| SubdataflowError::RpcError(err))
+    }
 }
 
 #[derive(Debug)]
@@ -305,7 +374,7 @@ mod tests {
             })
             .await;
 
-        assert_eq!(execution.worker.get_status(), &NodeStatus::Running);
+        assert_eq!(execution.worker.get_status(), NodeStatus::Running);
         let option = ack_rx.recv().await;
         assert!(option.is_some());
 
@@ -1,20 +1,64 @@
-use common::net::{
-    cluster::{self, ClusterBuilder},
-    local, AckResponderBuilder, HeartbeatBuilder,
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    ops::Bound,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use common::{
+    backpressure::BACKPRESSURE_METRIC,
+    metrics::{
+        ERROR_POLICY_DEAD_LETTERED_METRIC, ERROR_POLICY_FAILED_METRIC, ERROR_POLICY_SKIPPED_METRIC,
+        OPERATOR_EVENTS_IN_METRIC,
+    },
+    net::{
+        cluster::{
+            self, BackpressureConfig, ClusterBuilder, ClusterError, LivenessConfig, NodeBuilder,
+            RebalanceConfig,
+        },
+        local, AckResponderBuilder, HeartbeatBuilder,
+    },
+    snapshot::{
+        get_partition_id, SnapshotStore, SnapshotStoreBuilder,
+        CHECKPOINT_DURABILITY_LAG_MILLIS_METRIC,
+    },
+    utils::times::now_timestamp,
 };
 use crossbeam_skiplist::SkipMap;
+use futures_util::StreamExt;
+use prost::Message;
 use proto::common::{
-    Ack, Dataflow, DataflowStates, DataflowStatus, Heartbeat, HostAddr, ResourceId,
+    Ack, Dataflow, DataflowMetricsSummary, DataflowPlacement, DataflowStates, DataflowStatus,
+    DeployedPartition, Heartbeat, HostAddr, OperatorError, OperatorStates, PartitionPlacement,
+    PartitionStatus, ResourceId, Response, SubDataflowId, SubdataflowInfo,
 };
+use proto::common_impl::order_by_dependencies;
+use proto::coordinator::{
+    ClusterTopology, DataflowMigration, DataflowRuntimeStatus, DataflowSummary,
+    ListDataflowsRequest, ListDataflowsResponse, NodeTopology, OperatorRuntimeStatus, Savepoint,
+    UpdateDataflowResponse, WorkerFailure,
+};
+use proto::taskmanager::StopMode;
+use tokio::sync::RwLock;
 
 use crate::errors::coordinator::{
-    not_found_dataflow, task_deployment_err, unexpected_dataflow_staus,
+    add_node_err, invalid_label_selector, invalid_page_token, not_found_dataflow,
+    resource_version_conflict, task_deployment_err, unexpected_dataflow_staus,
 };
 
+/// the max number of operator errors that a [`JobManager`] keeps. The oldest errors will be dropped if it's exceeded.
+const MAX_OPERATOR_ERRORS: usize = 100;
+/// the number of dataflows in a page if the page size of a listing is not given
+const DEFAULT_LIST_PAGE_SIZE: usize = 100;
+/// the max number of dataflows in a page, larger page sizes are truncated to it
+const MAX_LIST_PAGE_SIZE: usize = 1000;
+/// the max number of dataflows terminated at the same time by a batch termination
+const MAX_CONCURRENT_TERMINATIONS: usize = 8;
+
 use super::{
-    executions::{SubdataflowDeploymentPlan, TaskDeploymentException},
-    scheduler::Scheduler,
-    storage::{DataflowStorage, DataflowStorageBuilder},
+    executions::{SubdataflowDeploymentPlan, SubdataflowExecution},
+    savepoints::{SavepointError, SavepointStorage},
+    scheduler::{Scheduler, TaskExecutionException},
+    storage::{DataflowStorage, DataflowStorageBuilder, SharedDataflowStorage, StorageError},
 };
 
 /// [`JobManager`] is responsible for
@@ -27,60 +71,389 @@ pub(crate) struct JobManager {
     job_id: ResourceId,
     scheduler: Scheduler,
     location: HostAddr,
-    storage: Box<dyn DataflowStorage>,
+    storage: SharedDataflowStorage,
+    /// where the operators are placed. It's persisted so that it can be recovered after the coordinator restarts
+    placement: DataflowPlacement,
+    /// the latest errors reported by operators
+    operator_errors: RwLock<VecDeque<OperatorError>>,
+    /// the status and the timestamps of the dataflow which are listed without asking the TaskManagers
+    summary: RwLock<DataflowSummary>,
+    /// the dataflow is being replaced by an update, so other updates conflict until it finishes
+    updating: AtomicBool,
 }
 impl JobManager {
     pub(crate) fn new(
         location: &HostAddr,
         dataflow: Dataflow,
-        storage: &DataflowStorageBuilder,
+        storage: &SharedDataflowStorage,
     ) -> Self {
         let job_id = dataflow.get_job_id();
+        let now = now_timestamp();
+        let summary = DataflowSummary {
+            job_id: Some(job_id.clone()),
+            status: DataflowStatus::Pending as i32,
+            operator_count: dataflow.nodes.len() as u32,
+            created_at: now,
+            updated_at: now,
+            labels: dataflow.labels.clone(),
+            // a dataflow created again after it's terminated never reuses the versions of the terminated one
+            resource_version: now as u64,
+            status_reason: String::new(),
+        };
         Self {
             dataflow,
             job_id,
             scheduler: Scheduler::new(),
             location: location.clone(),
-            storage: storage.build(),
+            storage: storage.clone(),
+            placement: Default::default(),
+            operator_errors: Default::default(),
+            summary: RwLock::new(summary),
+            updating: AtomicBool::new(false),
+        }
+    }
+
+    /// the job manager of the updated dataflow, which keeps the creation time of the replaced one and takes the next resource version
+    fn replace(
+        location: &HostAddr,
+        dataflow: Dataflow,
+        storage: &SharedDataflowStorage,
+        replaced: &DataflowSummary,
+    ) -> Self {
+        let mut job_manager = Self::new(location, dataflow, storage);
+        let summary = job_manager.summary.get_mut();
+        summary.created_at = replaced.created_at;
+        summary.resource_version = replaced.resource_version + 1;
+        job_manager
+    }
+
+    /// recover a job manager from the persisted dataflow and placement without deploying the dataflow again.
+    /// Only the started partitions are managed again and the others are left as they are.
+    pub(crate) fn recover(
+        location: &HostAddr,
+        dataflow: Dataflow,
+        placement: DataflowPlacement,
+        storage: &SharedDataflowStorage,
+        cluster: &cluster::Cluster,
+        heartbeat_builder: &HeartbeatBuilder,
+        ack_builder: &AckResponderBuilder,
+    ) -> Self {
+        let mut job_manager = Self::new(location, dataflow, storage);
+        let subdataflows = cluster.split_into_subdataflow(&job_manager.dataflow);
+        let mut unrecovered = vec![];
+        placement
+            .partitions
+            .iter()
+            .filter(|partition| partition.status() == PartitionStatus::Started)
+            .for_each(|partition| {
+                let host_addr = partition.node.clone().unwrap_or_default();
+                match (
+                    cluster.get_node(&host_addr),
+                    subdataflows.get(&host_addr),
+                    partition.execution_id.as_ref(),
+                ) {
+                    (Some(node), Some(subdataflow), Some(execution_id)) => {
+                        job_manager.scheduler.resume(SubdataflowExecution::new(
+                            node.clone(),
+                            subdataflow,
+                            execution_id.clone(),
+                            ack_builder,
+                            heartbeat_builder,
+                        ))
+                    }
+                    _ => {
+                        tracing::warn!(
+                            "partition on {}:{} of job {:?} can not be recovered",
+                            &host_addr.host,
+                            host_addr.port,
+                            &job_manager.job_id
+                        );
+                        unrecovered.push(format!("{}:{}", &host_addr.host, host_addr.port));
+                    }
+                }
+            });
+
+        let summary = job_manager.summary.get_mut();
+        transition(
+            &job_manager.job_id,
+            summary,
+            DataflowStatus::Recovering,
+            String::new(),
+        );
+        let (status, reason) = match get_deployed_status(&placement) {
+            (DataflowStatus::Running, _) if !unrecovered.is_empty() => (
+                DataflowStatus::Degraded,
+                format!(
+                    "partitions on {} can not be recovered",
+                    unrecovered.join(", ")
+                ),
+            ),
+            deployed => deployed,
+        };
+        transition(&job_manager.job_id, summary, status, reason);
+        // placements persisted without timestamps keep the time of the recovery
+        if placement.created_at > 0 {
+            summary.created_at = placement.created_at;
+            // the dataflow which is recovered as it's persisted keeps the time its status is changed
+            if summary.status() == placement.status()
+                && summary.status_reason == placement.status_reason
+            {
+                summary.updated_at = placement.updated_at;
+            }
         }
+        if placement.resource_version > 0 {
+            summary.resource_version = placement.resource_version;
+        }
+        let changed = summary.status() != placement.status()
+            || summary.status_reason != placement.status_reason;
+        job_manager.placement = placement;
+        if changed {
+            let summary = job_manager.summary.get_mut().clone();
+            job_manager.persist_status(&summary);
+        }
+        job_manager
     }
 
     /// Once a dataflow is deployed, JobManager will receive the event of state transition of each subdataflow from TaskManager.
+    /// Every partition will be tried to deploy even if some of them fail. The returned [`DataflowPlacement`] records where each operator is placed and whether each partition is started.
+    /// If a savepoint is given, the operators are restored from it instead of the remote checkpoints.
+    /// Otherwise the subdataflows without remote checkpoints are warm-started from the latest checkpoints of the prior job if `warm_start_from` is set.
     async fn deploy_dataflow(
         &mut self,
         cluster: &cluster::Cluster,
         heartbeat_builder: &HeartbeatBuilder,
         ack_builder: &AckResponderBuilder,
-    ) -> Result<(), TaskDeploymentException> {
-        let _ = self.storage.save(&self.dataflow);
+        snapshot_store: Option<&SnapshotStore>,
+        savepoint: Option<&OperatorStates>,
+    ) -> DataflowPlacement {
         cluster.partition_dataflow(&mut self.dataflow);
+        self.deploy_placed_dataflow(
+            cluster,
+            heartbeat_builder,
+            ack_builder,
+            snapshot_store,
+            savepoint,
+        )
+        .await
+    }
+
+    /// deploy the dataflow where its operators are placed already, see [`JobManager::deploy_dataflow`]
+    async fn deploy_placed_dataflow(
+        &mut self,
+        cluster: &cluster::Cluster,
+        heartbeat_builder: &HeartbeatBuilder,
+        ack_builder: &AckResponderBuilder,
+        snapshot_store: Option<&SnapshotStore>,
+        savepoint: Option<&OperatorStates>,
+    ) -> DataflowPlacement {
+        // the dataflow is saved after it's partitioned so that the assignment of operators is persisted
+        self.save(|storage| storage.save(&self.dataflow));
+
+        let summary = self.summary.get_mut();
+        transition(
+            &self.job_id,
+            summary,
+            DataflowStatus::Deploying,
+            String::new(),
+        );
+        let mut placement = DataflowPlacement {
+            job_id: Some(self.job_id.clone()),
+            created_at: summary.created_at,
+            resource_version: summary.resource_version,
+            ..Default::default()
+        };
+        let mut subdataflow = cluster
+            .split_into_subdataflow(&self.dataflow)
+            .into_iter()
+            .collect::<Vec<_>>();
+        subdataflow.sort_by(|a, b| (&a.0.host, a.0.port).cmp(&(&b.0.host, b.0.port)));
+
+        // every subdataflow receives all the checkpoints of the prior job, since its operators may be placed anywhere before
+        let warm_start_from = match (
+            snapshot_store.filter(|_| savepoint.is_none()),
+            self.dataflow.warm_start_from.as_ref(),
+        ) {
+            (Some(store), Some(prior_job_id)) => match store.latest_manifests(prior_job_id).await {
+                Ok(manifests) => {
+                    if manifests.is_empty() {
+                        tracing::warn!(
+                            "job {:?} is not warm-started because no checkpoint of job {:?} is found",
+                            &self.job_id,
+                            prior_job_id
+                        );
+                    }
+                    manifests
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "lookup remote checkpoints of job {:?} failed: {}",
+                        prior_job_id,
+                        err
+                    );
+                    vec![]
+                }
+            },
+            _ => vec![],
+        };
+
+        for (host_addr, dataflow) in subdataflow.iter_mut() {
+            let host_addr: &HostAddr = host_addr;
+            let node = cluster.get_node(host_addr);
+            let mut partition = PartitionPlacement {
+                execution_id: node.map(|node| SubDataflowId {
+                    job_id: Some(self.job_id.clone()),
+                    sub_id: node.get_id(),
+                }),
+                node: Some(host_addr.clone()),
+                operator_ids: dataflow.meta.iter().map(|meta| meta.center).collect(),
+                ..Default::default()
+            };
+            partition.operator_ids.iter().for_each(|operator_id| {
+                placement.operators.insert(*operator_id, host_addr.clone());
+            });
+
+            // operators of a savepoint are matched by their ids
+            let subdataflow_savepoint = savepoint.map(|savepoint| OperatorStates {
+                states: savepoint
+                    .states
+                    .iter()
+                    .filter(|(operator_id, _)| dataflow.nodes.contains_key(operator_id))
+                    .map(|(operator_id, state)| (*operator_id, state.clone()))
+                    .collect(),
+            });
+            // otherwise the subdataflow is restored from its latest remote checkpoint, wherever it was running
+            let restore_from = match snapshot_store.filter(|_| savepoint.is_none()) {
+                Some(store) => match store
+                    .latest_manifest(&self.job_id, get_partition_id(dataflow))
+                    .await
+                {
+                    Ok(manifest) => manifest,
+                    Err(err) => {
+                        tracing::warn!(
+                            "lookup remote checkpoint of job {:?} failed: {}",
+                            &self.job_id,
+                            err
+                        );
+                        None
+                    }
+                },
+                None => None,
+            };
 
-        let mut subdataflow = cluster.split_into_subdataflow(&self.dataflow);
-        let executions = subdataflow.iter_mut().map(|pair| {
-            let host_addr = pair.0;
             let plan = SubdataflowDeploymentPlan::new(
-                pair,
+                (host_addr, dataflow),
                 &self.job_id,
-                cluster.get_node(host_addr),
+                node,
+                &self.location,
                 ack_builder,
                 heartbeat_builder,
-            );
-            plan
-        });
+            )
+            .with_restore_from(restore_from)
+            .with_savepoint(subdataflow_savepoint)
+            .with_warm_start_from(&warm_start_from);
+            match self.scheduler.execute(plan).await {
+                Ok(_) => partition.set_status(PartitionStatus::Started),
+                Err(err) => {
+                    partition.set_status(PartitionStatus::Failed);
+                    partition.err_msg = err.to_tonic_status().message().to_string();
+                }
+            }
+            placement.partitions.push(partition);
+        }
+
+        placement.updated_at = now_timestamp();
+        let summary = self.summary.get_mut();
+        let (status, reason) = get_deployed_status(&placement);
+        transition(&self.job_id, summary, status, reason);
+        summary.updated_at = placement.updated_at;
+        placement.set_status(summary.status());
+        placement.status_reason = summary.status_reason.clone();
+        self.save(|storage| storage.save_placement(&placement));
+        self.placement = placement.clone();
+        placement
+    }
+
+    fn save<F: FnOnce(&mut Box<dyn DataflowStorage>) -> Result<(), StorageError>>(&self, f: F) {
+        let mut storage = self.storage.lock().unwrap_or_else(|err| err.into_inner());
+        if let Err(err) = f(&mut *storage) {
+            tracing::error!("job {:?} is not persisted: {}", &self.job_id, err);
+        }
+    }
 
-        for execution in executions {
-            match self.scheduler.execute(execution).await {
-                Ok(_) => {}
-                Err(err) => return Err(err),
+    /// persist the status of the summary with the placement, so that it's recovered after the coordinator restarts.
+    /// Nothing is persisted before the dataflow is deployed
+    fn persist_status(&self, summary: &DataflowSummary) {
+        if self.placement.job_id.is_none() {
+            return;
+        }
+        let mut placement = self.placement.clone();
+        placement.set_status(summary.status());
+        placement.status_reason = summary.status_reason.clone();
+        placement.updated_at = summary.updated_at;
+        self.save(|storage| storage.save_placement(&placement));
+    }
+
+    /// the dataflow is closing while its subdataflows are stopped. If some of them fail to stop, it's degraded with the reason of the failures
+    async fn terminate_dataflow(
+        &self,
+        mode: StopMode,
+    ) -> Result<DataflowStatus, DispatcherException> {
+        {
+            let mut summary = self.summary.write().await;
+            if transition(
+                &self.job_id,
+                &mut summary,
+                DataflowStatus::Closing,
+                String::new(),
+            ) {
+                self.persist_status(&summary);
             }
         }
+        let result = self
+            .scheduler
+            .terminate_dataflow(mode)
+            .await
+            .map_err(|err| match err {
+                TaskExecutionException::WorkerFailures(failures) => {
+                    DispatcherException::TerminationFailed(failures)
+                }
+                err => DispatcherException::Tonic(err.to_tonic_status()),
+            });
+        let mut summary = self.summary.write().await;
+        let (status, reason) = match &result {
+            Ok(status) => (*status, String::new()),
+            Err(err) => (
+                DataflowStatus::Degraded,
+                err.to_tonic_status().message().to_string(),
+            ),
+        };
+        if transition(&self.job_id, &mut summary, status, reason) {
+            self.persist_status(&summary);
+        }
+        result
+    }
+
+    /// start to update the dataflow if it's still at the expected resource version and no other update is in progress
+    async fn start_update(&self, expected: u64) -> Result<DataflowSummary, DispatcherException> {
+        let summary = self.summary.read().await;
+        if summary.resource_version != expected || self.updating.swap(true, Ordering::SeqCst) {
+            return Err(DispatcherException::ResourceVersionConflict {
+                job_id: self.job_id.clone(),
+                expected,
+                actual: summary.resource_version,
+            });
+        }
+        Ok(summary.clone())
+    }
 
-        Ok(())
+    /// the update fails before the dataflow is terminated, so it can be updated again
+    fn abort_update(&self) {
+        self.updating.store(false, Ordering::SeqCst);
     }
 
-    async fn terminate_dataflow(&self) -> Result<DataflowStatus, tonic::Status> {
+    async fn trigger_savepoint(&self) -> Result<OperatorStates, tonic::Status> {
         self.scheduler
-            .terminate_dataflow()
+            .trigger_savepoint()
             .await
             .map_err(|err| err.to_tonic_status())
     }
@@ -98,8 +471,235 @@ impl JobManager {
     }
 
     async fn get_dataflow(&self) -> DataflowStates {
-        self.scheduler.get_dataflow(&self.dataflow).await
+        let mut states = self.scheduler.get_dataflow(&self.dataflow).await;
+        states.operator_errors = self.operator_errors.read().await.iter().cloned().collect();
+        let summary = self.summary.read().await;
+        states.status = summary.status;
+        states.status_reason = summary.status_reason.clone();
+        states
+    }
+
+    /// the sub-dataflows deployed on the TaskManagers, see [`get_deployed_partitions`]
+    fn get_deployed_partitions(&self, cluster: &cluster::Cluster) -> Vec<DeployedPartition> {
+        get_deployed_partitions(cluster, &self.dataflow, &self.placement)
+    }
+
+    async fn get_summary(&self) -> DataflowSummary {
+        self.summary.read().await.clone()
+    }
+
+    /// the backpressure of the most blocked operator of the dataflow on each node
+    async fn get_backpressure(&self) -> HashMap<HostAddr, u64> {
+        let states = self.scheduler.get_dataflow(&self.dataflow).await;
+        let mut backpressure = HashMap::new();
+        states
+            .subdataflow_infos
+            .iter()
+            .flat_map(|info| info.executors_info.iter())
+            .for_each(|(operator_id, executor)| {
+                let host_addr = self
+                    .dataflow
+                    .nodes
+                    .get(operator_id)
+                    .and_then(|operator| operator.host_addr.clone());
+                let ratio = executor.metrics.get(BACKPRESSURE_METRIC).cloned();
+                if let (Some(host_addr), Some(ratio)) = (host_addr, ratio) {
+                    let entry = backpressure.entry(host_addr).or_insert(0);
+                    *entry = ratio.max(*entry);
+                }
+            });
+        backpressure
     }
+
+    /// the TaskManagers are only asked for the status of the operators if `with_operators` is true
+    async fn get_status(&self, with_operators: bool) -> DataflowRuntimeStatus {
+        let operators = if with_operators {
+            let states = self.scheduler.get_dataflow(&self.dataflow).await;
+            get_operator_statuses(&self.dataflow, &states.subdataflow_infos)
+        } else {
+            vec![]
+        };
+        DataflowRuntimeStatus {
+            summary: Some(self.get_summary().await),
+            operators,
+        }
+    }
+
+    async fn report_operator_error(&self, err: OperatorError) {
+        tracing::error!(
+            "operator [{}] of job {:?} reports error [{:?}]: {}",
+            err.operator_id,
+            &self.job_id,
+            err.kind(),
+            &err.message
+        );
+        let mut guard = self.operator_errors.write().await;
+        if guard.len() >= MAX_OPERATOR_ERRORS {
+            guard.pop_front();
+        }
+        guard.push_back(err);
+    }
+}
+
+/// the sub-dataflow of each partition of the placement. The operators of the dataflow keep the TaskManagers they're assigned to,
+/// so it's split in the same way as it's deployed. A partition whose TaskManager has no operator of the dataflow has no sub-dataflow
+fn get_deployed_partitions(
+    cluster: &cluster::Cluster,
+    dataflow: &Dataflow,
+    placement: &DataflowPlacement,
+) -> Vec<DeployedPartition> {
+    let mut subdataflows = cluster.split_into_subdataflow(dataflow);
+    placement
+        .partitions
+        .iter()
+        .map(|partition| DeployedPartition {
+            placement: Some(partition.clone()),
+            dataflow: partition
+                .node
+                .as_ref()
+                .and_then(|node| subdataflows.remove(node)),
+        })
+        .collect()
+}
+
+/// a dataflow is running once all of its partitions are started, and it's failed if none of them is.
+/// The reason of a degraded or failed dataflow tells which partitions fail to start
+fn get_deployed_status(placement: &DataflowPlacement) -> (DataflowStatus, String) {
+    if placement.is_started() {
+        return (DataflowStatus::Running, String::new());
+    }
+    let reason = placement
+        .get_failed_partitions()
+        .iter()
+        .map(|partition| {
+            let node = partition.node.clone().unwrap_or_default();
+            format!(
+                "partition on {}:{} fails to start: {}",
+                &node.host, node.port, &partition.err_msg
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+    if placement
+        .partitions
+        .iter()
+        .any(|partition| partition.status() == PartitionStatus::Started)
+    {
+        (DataflowStatus::Degraded, reason)
+    } else {
+        (DataflowStatus::Failed, reason)
+    }
+}
+
+/// move the dataflow to the status with the reason if it's allowed by [`DataflowStatus::can_transition_to`].
+/// An illegal transition is rejected and logged, and the summary is left as it is.
+/// It returns whether the status or its reason is changed
+fn transition(
+    job_id: &ResourceId,
+    summary: &mut DataflowSummary,
+    status: DataflowStatus,
+    reason: String,
+) -> bool {
+    let current = summary.status();
+    if !current.can_transition_to(status) {
+        tracing::warn!(
+            "job {:?} can not move from {:?} to {:?}: {}",
+            job_id,
+            current,
+            status,
+            reason
+        );
+        return false;
+    }
+    if current == status && summary.status_reason == reason {
+        return false;
+    }
+    summary.set_status(status);
+    summary.status_reason = reason;
+    summary.updated_at = now_timestamp();
+    true
+}
+
+/// the runtime status of each operator, ordered by operator ids. Operators not reported by the TaskManagers have no status
+fn get_operator_statuses(
+    dataflow: &Dataflow,
+    subdataflow_infos: &[SubdataflowInfo],
+) -> Vec<OperatorRuntimeStatus> {
+    let executors = subdataflow_infos
+        .iter()
+        .flat_map(|info| info.executors_info.iter())
+        .collect::<HashMap<_, _>>();
+    let mut operators = dataflow
+        .nodes
+        .iter()
+        .map(|(operator_id, info)| {
+            let executor = executors.get(operator_id);
+            OperatorRuntimeStatus {
+                operator_id: *operator_id,
+                host_addr: info.host_addr.clone(),
+                status: executor.map(|executor| executor.status),
+                last_heartbeat_at: executor
+                    .map(|executor| executor.last_heartbeat_at)
+                    .unwrap_or_default(),
+                restart_count: executor
+                    .map(|executor| executor.restart_count)
+                    .unwrap_or_default(),
+            }
+        })
+        .collect::<Vec<_>>();
+    operators.sort_by_key(|operator| operator.operator_id);
+    operators
+}
+
+/// the metrics of the operators reported by the TaskManagers aggregated into the health of the dataflow.
+/// Counters are summed up, while the lag and the backpressure are the ones of the worst operator
+fn get_metrics_summary(subdataflow_infos: &[SubdataflowInfo]) -> DataflowMetricsSummary {
+    let mut summary = DataflowMetricsSummary::default();
+    subdataflow_infos
+        .iter()
+        .flat_map(|info| info.executors_info.values())
+        .for_each(|executor| {
+            let metric = |name: &str| executor.metrics.get(name).cloned().unwrap_or_default();
+            summary.events_processed += metric(OPERATOR_EVENTS_IN_METRIC);
+            summary.errors += metric(ERROR_POLICY_FAILED_METRIC)
+                + metric(ERROR_POLICY_SKIPPED_METRIC)
+                + metric(ERROR_POLICY_DEAD_LETTERED_METRIC);
+            summary.lag_millis = summary
+                .lag_millis
+                .max(metric(CHECKPOINT_DURABILITY_LAG_MILLIS_METRIC));
+            summary.max_backpressure = summary.max_backpressure.max(metric(BACKPRESSURE_METRIC));
+            summary.reported_operators += 1;
+        });
+    if summary.events_processed > 0 {
+        summary.error_rate = summary.errors as f64 / summary.events_processed as f64;
+    }
+    summary
+}
+
+/// a page token is the hex-encoded job id of the last dataflow of the previous page
+fn encode_page_token(job_id: &ResourceId) -> String {
+    job_id
+        .encode_to_vec()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn decode_page_token(token: &str) -> Option<ResourceId> {
+    let bytes = token
+        .as_bytes()
+        .chunks(2)
+        .map(|byte| match byte {
+            // `from_str_radix` accepts a leading sign, so the digits are checked first
+            [high, low] if high.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+                std::str::from_utf8(byte)
+                    .ok()
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            }
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    ResourceId::decode(bytes.as_slice()).ok()
 }
 
 /// [`Dispatcher`] is responsible for
@@ -110,11 +710,16 @@ impl JobManager {
 /// - heartbeat of remote cluster
 pub(crate) struct Dispatcher {
     managers: SkipMap<ResourceId, JobManager>,
-    cluster: cluster::Cluster,
+    /// the nodes are added to the cluster while the dispatcher serves, so each request works on a snapshot of it. See [`Dispatcher::read_cluster`]
+    cluster: std::sync::RwLock<cluster::Cluster>,
+    /// nodes are added one at a time, so that the cluster is not replaced by a stale snapshot
+    adding_node: tokio::sync::Mutex<()>,
     location: HostAddr,
     heartbeat: HeartbeatBuilder,
     ack: AckResponderBuilder,
-    storage: DataflowStorageBuilder,
+    storage: SharedDataflowStorage,
+    snapshot_store: Option<SnapshotStore>,
+    savepoints: SavepointStorage,
 }
 
 impl Dispatcher {
@@ -126,64 +731,635 @@ impl Dispatcher {
         port: usize,
     ) -> Self {
         let cluster = cluster_builder.build();
+        let storage = storage_builder.build_shared();
         Self {
             managers: Default::default(),
-            cluster,
+            cluster: std::sync::RwLock::new(cluster),
+            adding_node: Default::default(),
             location: local(port),
             heartbeat: heartbeat_builder.clone(),
             ack: ack_builder.clone(),
-            storage: storage_builder.clone(),
+            savepoints: SavepointStorage::Local(storage.clone()),
+            storage,
+            snapshot_store: None,
+        }
+    }
+
+    /// look up the remote checkpoints which the subdataflows are restored from when they're deployed.
+    /// Savepoints are uploaded to the snapshot store as well
+    pub fn with_snapshot_store(mut self, builder: Option<&SnapshotStoreBuilder>) -> Self {
+        self.snapshot_store = builder.and_then(|builder| match builder.build() {
+            Ok(store) => Some(store),
+            Err(err) => {
+                tracing::error!("create snapshot store failed: {}", err);
+                None
+            }
+        });
+        if let Some(store) = self.snapshot_store.as_ref() {
+            self.savepoints = SavepointStorage::Remote(store.clone());
+        }
+        self
+    }
+
+    /// how the backpressure sampled by [`Dispatcher::sample_backpressure`] affects the placement of new partitions
+    pub fn with_backpressure(mut self, config: &BackpressureConfig) -> Self {
+        let cluster = self.read_cluster().clone().with_backpressure(config);
+        self.cluster = std::sync::RwLock::new(cluster);
+        self
+    }
+
+    pub(crate) fn get_backpressure_config(&self) -> BackpressureConfig {
+        self.read_cluster().get_backpressure_config().clone()
+    }
+
+    /// how the nodes are probed by [`Dispatcher::heartbeat`] and detected down
+    pub fn with_liveness(mut self, config: &LivenessConfig) -> Self {
+        let cluster = self.read_cluster().clone().with_liveness(config);
+        self.cluster = std::sync::RwLock::new(cluster);
+        self
+    }
+
+    pub(crate) fn get_liveness_config(&self) -> LivenessConfig {
+        self.read_cluster().get_liveness_config().clone()
+    }
+
+    /// whether the operators are moved onto the nodes added by [`Dispatcher::add_node`]
+    pub fn with_rebalance(mut self, config: &RebalanceConfig) -> Self {
+        let cluster = self.read_cluster().clone().with_rebalance(config);
+        self.cluster = std::sync::RwLock::new(cluster);
+        self
+    }
+
+    /// the cluster shares the states of its nodes with its clones, so a clone of it is only stale in its members.
+    /// The guard should be dropped before awaiting
+    fn read_cluster(&self) -> std::sync::RwLockReadGuard<'_, cluster::Cluster> {
+        self.cluster.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// resume managing the dataflows which have been deployed before the coordinator restarts.
+    /// Dataflows without placements are not deployed successfully so they are ignored.
+    /// The dependencies of the dataflows are resumed before them, see [`order_by_dependencies`]
+    pub(crate) fn init(&self) {
+        let (dataflows, placements): (Vec<_>, Vec<_>) = {
+            let storage = self.storage.lock().unwrap_or_else(|err| err.into_inner());
+            storage
+                .list()
+                .into_iter()
+                .filter_map(|dataflow| {
+                    storage
+                        .get_placement(&dataflow.get_job_id())
+                        .map(|placement| (dataflow, placement))
+                })
+                .unzip()
+        };
+        let order = order_by_dependencies(&dataflows);
+        let mut recovered = dataflows
+            .into_iter()
+            .zip(placements)
+            .map(Some)
+            .collect::<Vec<_>>();
+
+        for (dataflow, placement) in order
+            .into_iter()
+            .filter_map(|index| recovered[index].take())
+        {
+            let job_id = dataflow.get_job_id();
+            tracing::info!("recover job {:?}", &job_id);
+            let job_manager = JobManager::recover(
+                &self.location,
+                dataflow,
+                placement,
+                &self.storage,
+                &self.read_cluster(),
+                &self.heartbeat,
+                &self.ack,
+            );
+            self.managers.insert(job_id, job_manager);
+        }
+    }
+
+    /// the dispatcher is ready once the storage is reachable and the cluster is probed
+    pub(crate) async fn is_ready(&self) -> bool {
+        let probe_result = self
+            .storage
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .probe();
+        if let Err(err) = probe_result {
+            tracing::warn!("dataflow storage is unreachable: {}", err);
+            return false;
         }
+        let cluster = self.read_cluster().clone();
+        cluster.probe().await
     }
 
+    /// deploy a dataflow. Its operators are restored from the savepoint if it's given
     pub(crate) async fn create_dataflow(
         &self,
         dataflow: Dataflow,
-    ) -> Result<(), DispatcherException> {
+        savepoint: Option<&OperatorStates>,
+    ) -> Result<DataflowPlacement, DispatcherException> {
         let job_id = dataflow.get_job_id();
+        let cluster = self.read_cluster().clone();
         let mut job_manager = JobManager::new(&self.location, dataflow, &self.storage);
-        let result = job_manager
-            .deploy_dataflow(&self.cluster, &self.heartbeat, &self.ack)
-            .await
-            .map_err(|err| DispatcherException::DeploymentError(err));
+        let placement = job_manager
+            .deploy_dataflow(
+                &cluster,
+                &self.heartbeat,
+                &self.ack,
+                self.snapshot_store.as_ref(),
+                savepoint,
+            )
+            .await;
         self.managers.insert(job_id, job_manager);
 
-        result
+        if placement.is_started() {
+            Ok(placement)
+        } else {
+            Err(DispatcherException::DeploymentError(placement))
+        }
     }
 
     pub(crate) async fn terminate_dataflow(
         &self,
         job_id: &ResourceId,
+        mode: StopMode,
     ) -> Result<DataflowStatus, DispatcherException> {
         match self.managers.get(job_id) {
-            Some(manager) => match manager.value().terminate_dataflow().await {
+            Some(manager) => match manager.value().terminate_dataflow(mode).await {
                 Ok(status) => match &status {
-                    DataflowStatus::Initialized => {
-                        Err(DispatcherException::UnexpectedDataflowStatus(status))
-                    }
-                    DataflowStatus::Running => {
-                        Err(DispatcherException::UnexpectedDataflowStatus(status))
-                    }
                     DataflowStatus::Closing => Ok(status),
                     DataflowStatus::Closed => {
                         let _ = self.managers.remove(job_id);
+                        manager.value().save(|storage| storage.delete(job_id));
                         Ok(status)
                     }
+                    _ => Err(DispatcherException::UnexpectedDataflowStatus(status)),
                 },
-                Err(err) => Err(DispatcherException::Tonic(err)),
+                Err(err) => Err(err),
             },
             None => Ok(DataflowStatus::Closed),
         }
     }
 
+    /// replace a running dataflow with the new spec if it's still at the expected resource version.
+    /// A savepoint of the running dataflow is taken before it's stopped at once, and the new dataflow is restored from it,
+    /// so the operators keep their states if their ids are not changed. The running dataflow is left as it is if the savepoint or the termination fails.
+    /// All operators are restarted, and the new dataflow takes the next resource version
+    pub(crate) async fn update_dataflow(
+        &self,
+        dataflow: Dataflow,
+        resource_version: u64,
+    ) -> Result<(UpdateDataflowResponse, OperatorStates), DispatcherException> {
+        let job_id = dataflow.get_job_id();
+        let (replaced, savepoint) = self.stop_with_savepoint(&job_id, resource_version).await?;
+
+        let mut restarted_operator_ids = dataflow.nodes.keys().cloned().collect::<Vec<_>>();
+        restarted_operator_ids.sort();
+        let cluster = self.read_cluster().clone();
+        let mut job_manager =
+            JobManager::replace(&self.location, dataflow, &self.storage, &replaced);
+        let placement = job_manager
+            .deploy_dataflow(
+                &cluster,
+                &self.heartbeat,
+                &self.ack,
+                self.snapshot_store.as_ref(),
+                Some(&savepoint),
+            )
+            .await;
+        let summary = job_manager.summary.get_mut().clone();
+        self.managers.insert(job_id, job_manager);
+
+        if placement.is_started() {
+            Ok((
+                UpdateDataflowResponse {
+                    summary: Some(summary),
+                    restarted_operator_ids,
+                    placement: Some(placement),
+                    warnings: vec![],
+                },
+                savepoint,
+            ))
+        } else {
+            Err(DispatcherException::DeploymentError(placement))
+        }
+    }
+
+    /// take a savepoint of the running dataflow and stop it at once if it's still at the expected resource version, so that it can be replaced by a dataflow restored from the savepoint.
+    /// The running dataflow is left as it is if the savepoint or the termination fails
+    async fn stop_with_savepoint(
+        &self,
+        job_id: &ResourceId,
+        resource_version: u64,
+    ) -> Result<(DataflowSummary, OperatorStates), DispatcherException> {
+        let (replaced, savepoint) = match self.managers.get(job_id) {
+            Some(entry) => {
+                let manager = entry.value();
+                let replaced = manager.start_update(resource_version).await?;
+                let savepoint = match manager.trigger_savepoint().await {
+                    Ok(savepoint) => savepoint,
+                    Err(status) => {
+                        manager.abort_update();
+                        return Err(DispatcherException::Tonic(status));
+                    }
+                };
+                (replaced, savepoint)
+            }
+            None => return Err(DispatcherException::NotFoundDataflow(job_id.clone())),
+        };
+        if let Err(err) = self.terminate_dataflow(job_id, StopMode::Immediate).await {
+            if let Some(entry) = self.managers.get(job_id) {
+                entry.value().abort_update();
+            }
+            return Err(err);
+        }
+        Ok((replaced, savepoint))
+    }
+
+    /// add a TaskManager node to the cluster once it answers, and return its topology after the rebalance. See [`cluster::Cluster::add_node`].
+    /// If the rebalance is enabled, the operators which the node wins are moved onto it, one dataflow after another in the order of their job ids.
+    /// Only the dataflows whose partitions are all started are migrated
+    pub(crate) async fn add_node(
+        &self,
+        builder: &NodeBuilder,
+    ) -> Result<(NodeTopology, Vec<DataflowMigration>), DispatcherException> {
+        let _adding = self.adding_node.lock().await;
+        let mut cluster = self.read_cluster().clone();
+        let node = cluster
+            .add_node(builder)
+            .await
+            .map_err(DispatcherException::Cluster)?;
+        *self.cluster.write().unwrap_or_else(|err| err.into_inner()) = cluster.clone();
+
+        let mut migrations = vec![];
+        if cluster.get_rebalance_config().enabled {
+            let job_ids = self
+                .managers
+                .iter()
+                .map(|entry| entry.key().clone())
+                .collect::<Vec<_>>();
+            for job_id in job_ids {
+                if let Some(migration) = self
+                    .migrate_dataflow(&cluster, &job_id, &node.host_addr)
+                    .await
+                {
+                    migrations.push(migration);
+                }
+            }
+        }
+
+        let topology = self
+            .get_cluster_topology()
+            .nodes
+            .into_iter()
+            .find(|topology| topology.host_addr.as_ref() == Some(&node.host_addr))
+            .unwrap_or_default();
+        Ok((topology, migrations))
+    }
+
+    /// move the operators of the dataflow which the node wins onto it, see [`cluster::Cluster::get_rebalanced_operators`].
+    /// Like [`Dispatcher::update_dataflow`], the dataflow is restored from a savepoint taken before it's stopped, so the moved operators keep their states.
+    /// It's left as it is if the savepoint or the termination fails. It returns none if no operator is moved
+    async fn migrate_dataflow(
+        &self,
+        cluster: &cluster::Cluster,
+        job_id: &ResourceId,
+        addr: &HostAddr,
+    ) -> Option<DataflowMigration> {
+        let (mut dataflow, resource_version, mut migration) = {
+            let entry = self.managers.get(job_id)?;
+            let manager = entry.value();
+            if !manager.placement.is_started() {
+                return None;
+            }
+            let operator_ids = cluster.get_rebalanced_operators(&manager.dataflow, addr);
+            if operator_ids.is_empty() {
+                return None;
+            }
+            let migration = DataflowMigration {
+                job_id: Some(job_id.clone()),
+                operator_ids,
+                placement: Some(manager.placement.clone()),
+                ..Default::default()
+            };
+            let resource_version = manager.summary.read().await.resource_version;
+            (manager.dataflow.clone(), resource_version, migration)
+        };
+
+        let (replaced, savepoint) = match self.stop_with_savepoint(job_id, resource_version).await {
+            Ok(stopped) => stopped,
+            Err(err) => {
+                migration.error = err.to_tonic_status().message().to_string();
+                return Some(migration);
+            }
+        };
+        migration.operator_ids.iter().for_each(|operator_id| {
+            if let Some(operator) = dataflow.nodes.get_mut(operator_id) {
+                operator.host_addr = Some(addr.clone());
+            }
+        });
+        let mut job_manager =
+            JobManager::replace(&self.location, dataflow, &self.storage, &replaced);
+        let placement = job_manager
+            .deploy_placed_dataflow(
+                cluster,
+                &self.heartbeat,
+                &self.ack,
+                self.snapshot_store.as_ref(),
+                Some(&savepoint),
+            )
+            .await;
+        self.managers.insert(job_id.clone(), job_manager);
+
+        if !placement.is_started() {
+            migration.error = DispatcherException::DeploymentError(placement.clone())
+                .to_tonic_status()
+                .message()
+                .to_string();
+        }
+        tracing::info!(
+            "operators {:?} of job {:?} are moved onto node {}:{}",
+            &migration.operator_ids,
+            job_id,
+            &addr.host,
+            addr.port
+        );
+        migration.placement = Some(placement);
+        Some(migration)
+    }
+
+    /// terminate a batch of dataflows, at most [`MAX_CONCURRENT_TERMINATIONS`] of them at the same time.
+    /// Duplicated job ids are terminated once. Unlike [`Dispatcher::terminate_dataflow`], unknown job ids are reported as not found
+    pub(crate) async fn terminate_dataflows(
+        &self,
+        job_ids: &[ResourceId],
+        mode: StopMode,
+    ) -> Vec<(ResourceId, Result<DataflowStatus, DispatcherException>)> {
+        let mut distinct = BTreeSet::new();
+        let job_ids = job_ids
+            .iter()
+            .filter(|job_id| distinct.insert(*job_id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        futures_util::stream::iter(job_ids)
+            .map(|job_id| async move {
+                let result = if self.managers.contains_key(&job_id) {
+                    self.terminate_dataflow(&job_id, mode).await
+                } else {
+                    Err(DispatcherException::NotFoundDataflow(job_id.clone()))
+                };
+                (job_id, result)
+            })
+            .buffered(MAX_CONCURRENT_TERMINATIONS)
+            .collect()
+            .await
+    }
+
+    /// the managed dataflows which depend on the job, ordered by their job ids
+    pub(crate) fn get_dependents(&self, job_id: &ResourceId) -> Vec<ResourceId> {
+        self.managers
+            .iter()
+            .filter(|entry| entry.key() != job_id && entry.value().dataflow.depends_on_job(job_id))
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// the dependencies of the dataflow which are not managed, i.e. they're never created or they're terminated
+    pub(crate) fn get_missing_dependencies(&self, dataflow: &Dataflow) -> Vec<ResourceId> {
+        dataflow
+            .depends_on
+            .iter()
+            .filter(|dependency| !self.managers.contains_key(*dependency))
+            .cloned()
+            .collect()
+    }
+
+    /// the dataflow and the runtime states of its operators. If `effective` is true, the sub-dataflows deployed on the TaskManagers are returned as well,
+    /// see [`DataflowStates::partitions`]. If `include_metrics` is true, the metrics of the operators are aggregated, see [`get_metrics_summary`]
     pub(crate) async fn get_dataflow(
         &self,
         job_id: &ResourceId,
+        effective: bool,
+        include_metrics: bool,
     ) -> Result<DataflowStates, DispatcherException> {
         match self.managers.get(job_id) {
-            Some(entry) => Ok(entry.value().get_dataflow().await),
-            None => Err(DispatcherException::NotFoundDataflow(job_id.clone())),
+            Some(entry) => {
+                let mut states = entry.value().get_dataflow().await;
+                if effective {
+                    states.partitions = entry.value().get_deployed_partitions(&self.read_cluster());
+                }
+                if include_metrics {
+                    states.metrics = Some(get_metrics_summary(&states.subdataflow_infos));
+                }
+                Ok(states)
+            }
+            // the dataflow is saved before it's deployed, so it has no runtime states yet
+            None => self
+                .get_stored_dataflow(job_id)
+                .map(|dataflow| {
+                    let partitions = match self.get_stored_placement(job_id) {
+                        Some(placement) if effective => {
+                            get_deployed_partitions(&self.read_cluster(), &dataflow, &placement)
+                        }
+                        _ => vec![],
+                    };
+                    DataflowStates {
+                        graph: Some(dataflow),
+                        partitions,
+                        metrics: include_metrics.then(Default::default),
+                        ..Default::default()
+                    }
+                })
+                .ok_or_else(|| DispatcherException::NotFoundDataflow(job_id.clone())),
+        }
+    }
+
+    /// the summary of a dataflow, with the runtime status of its operators if `with_operators` is true.
+    /// A dataflow which is saved but not managed yet is pending and none of its operators has status
+    pub(crate) async fn get_dataflow_status(
+        &self,
+        job_id: &ResourceId,
+        with_operators: bool,
+    ) -> Result<DataflowRuntimeStatus, DispatcherException> {
+        match self.managers.get(job_id) {
+            Some(entry) => Ok(entry.value().get_status(with_operators).await),
+            None => self
+                .get_stored_dataflow(job_id)
+                .map(|dataflow| DataflowRuntimeStatus {
+                    summary: Some(DataflowSummary {
+                        job_id: Some(job_id.clone()),
+                        status: DataflowStatus::Pending as i32,
+                        operator_count: dataflow.nodes.len() as u32,
+                        labels: dataflow.labels.clone(),
+                        ..Default::default()
+                    }),
+                    operators: if with_operators {
+                        get_operator_statuses(&dataflow, &[])
+                    } else {
+                        vec![]
+                    },
+                })
+                .ok_or_else(|| DispatcherException::NotFoundDataflow(job_id.clone())),
+        }
+    }
+
+    fn get_stored_dataflow(&self, job_id: &ResourceId) -> Option<Dataflow> {
+        self.storage
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(job_id)
+    }
+
+    fn get_stored_placement(&self, job_id: &ResourceId) -> Option<DataflowPlacement> {
+        self.storage
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get_placement(job_id)
+    }
+
+    /// list the summaries of the dataflows after the one of the page token, ordered by their job ids.
+    /// Dataflows created or terminated between two pages are listed or not depending on where their job ids are.
+    /// The label selector is applied before the page is cut, so a page is full unless it's the last one
+    pub(crate) async fn list_dataflows(
+        &self,
+        request: &ListDataflowsRequest,
+    ) -> Result<ListDataflowsResponse, DispatcherException> {
+        let selector = request
+            .get_label_selector()
+            .map_err(DispatcherException::InvalidLabelSelector)?;
+        let start = if request.page_token.is_empty() {
+            Bound::Unbounded
+        } else {
+            match decode_page_token(&request.page_token) {
+                Some(job_id) => Bound::Excluded(job_id),
+                None => {
+                    return Err(DispatcherException::InvalidPageToken(
+                        request.page_token.clone(),
+                    ))
+                }
+            }
+        };
+        let page_size = match request.page_size as usize {
+            0 => DEFAULT_LIST_PAGE_SIZE,
+            page_size => page_size.min(MAX_LIST_PAGE_SIZE),
+        };
+
+        let mut response = ListDataflowsResponse::default();
+        let mut entries = self
+            .managers
+            .range((start, Bound::Unbounded))
+            .filter(|entry| {
+                request.namespace.is_empty() || entry.key().namespace_id == request.namespace
+            })
+            .filter(|entry| selector.matches(&entry.value().dataflow.labels));
+        for entry in entries.by_ref().take(page_size) {
+            response.dataflows.push(entry.value().get_summary().await);
+        }
+        if entries.next().is_some() {
+            response.next_page_token = response
+                .dataflows
+                .last()
+                .and_then(|summary| summary.job_id.as_ref())
+                .map(encode_page_token)
+                .unwrap_or_default();
+        }
+        Ok(response)
+    }
+
+    /// snapshot the states of all operators of a running dataflow and save them as a savepoint
+    pub(crate) async fn trigger_savepoint(
+        &self,
+        job_id: &ResourceId,
+    ) -> Result<Savepoint, DispatcherException> {
+        let states = match self.managers.get(job_id) {
+            Some(entry) => entry
+                .value()
+                .trigger_savepoint()
+                .await
+                .map_err(DispatcherException::Tonic)?,
+            None => return Err(DispatcherException::NotFoundDataflow(job_id.clone())),
+        };
+        self.savepoints
+            .save(job_id, states)
+            .await
+            .map_err(DispatcherException::Savepoint)
+    }
+
+    /// savepoints are kept after their dataflows are terminated, so they can be listed and deleted anytime
+    pub(crate) async fn list_savepoints(
+        &self,
+        job_id: &ResourceId,
+    ) -> Result<Vec<Savepoint>, DispatcherException> {
+        self.savepoints
+            .list(job_id)
+            .await
+            .map_err(DispatcherException::Savepoint)
+    }
+
+    pub(crate) async fn delete_savepoint(
+        &self,
+        job_id: &ResourceId,
+        path: &str,
+    ) -> Result<(), DispatcherException> {
+        self.savepoints
+            .delete(job_id, path)
+            .await
+            .map_err(DispatcherException::Savepoint)
+    }
+
+    /// dataflows are only warm-started from the checkpoints in the snapshot store
+    pub(crate) fn has_snapshot_store(&self) -> bool {
+        self.snapshot_store.is_some()
+    }
+
+    pub(crate) async fn load_savepoint(
+        &self,
+        path: &str,
+    ) -> Result<OperatorStates, DispatcherException> {
+        self.savepoints
+            .load(path)
+            .await
+            .map_err(DispatcherException::Savepoint)
+    }
+
+    /// get the topology of the TaskManager cluster. Only the started partitions are counted in each node
+    pub(crate) fn get_cluster_topology(&self) -> ClusterTopology {
+        let mut partitions = HashMap::new();
+        self.managers.iter().for_each(|entry| {
+            entry
+                .value()
+                .placement
+                .partitions
+                .iter()
+                .filter(|partition| partition.status() == PartitionStatus::Started)
+                .for_each(|partition| {
+                    *partitions
+                        .entry(partition.node.clone().unwrap_or_default())
+                        .or_insert(0) += 1
+                })
+        });
+        self.read_cluster().get_topology(&partitions)
+    }
+
+    /// sample the backpressure of the operators of all dataflows. A node is as backpressured as the most blocked operator on it
+    pub(crate) async fn sample_backpressure(&self) {
+        let mut samples = HashMap::new();
+        for entry in self.managers.iter() {
+            for (host_addr, ratio) in entry.value().get_backpressure().await {
+                let sample = samples.entry(host_addr).or_insert(0);
+                *sample = ratio.max(*sample);
+            }
         }
+        self.read_cluster()
+            .observe_backpressure(&samples, std::time::Instant::now());
+    }
+
+    /// probe the nodes which are due and update their status
+    pub(crate) async fn heartbeat(&self) {
+        let cluster = self.read_cluster().clone();
+        cluster.heartbeat(std::time::Instant::now()).await
     }
 
     pub(crate) async fn update_task_manager_heartbeat_status(&self, heartbeat: &Heartbeat) {
@@ -198,6 +1374,20 @@ impl Dispatcher {
         }
     }
 
+    pub(crate) async fn report_operator_error(
+        &self,
+        err: OperatorError,
+    ) -> Result<(), DispatcherException> {
+        let job_id = err.job_id.clone().unwrap_or_default();
+        match self.managers.get(&job_id) {
+            Some(entry) => {
+                entry.value().report_operator_error(err).await;
+                Ok(())
+            }
+            None => Err(DispatcherException::NotFoundDataflow(job_id)),
+        }
+    }
+
     pub(crate) async fn ack_from_task_manager(&self, ack: Ack) {
         match ack
             .execution_id
@@ -213,9 +1403,23 @@ impl Dispatcher {
 
 pub(crate) enum DispatcherException {
     Tonic(tonic::Status),
-    DeploymentError(TaskDeploymentException),
+    /// some partitions of the dataflow fail to start
+    DeploymentError(DataflowPlacement),
     UnexpectedDataflowStatus(DataflowStatus),
     NotFoundDataflow(ResourceId),
+    Savepoint(SavepointError),
+    InvalidPageToken(String),
+    InvalidLabelSelector(String),
+    /// the dataflow is not at the resource version which an update is based on, or it's being updated
+    ResourceVersionConflict {
+        job_id: ResourceId,
+        expected: u64,
+        actual: u64,
+    },
+    /// some TaskManagers fail to stop the subdataflows of the dataflow
+    TerminationFailed(Vec<WorkerFailure>),
+    /// the node is not added to the cluster
+    Cluster(ClusterError),
 }
 
 impl DispatcherException {
@@ -225,15 +1429,1486 @@ impl DispatcherException {
             DispatcherException::UnexpectedDataflowStatus(status) => {
                 unexpected_dataflow_staus(status).into_tonic_status()
             }
-            DispatcherException::DeploymentError(err) => match err {
-                TaskDeploymentException::InvalidWorkerEndpoint => {
-                    task_deployment_err("invalid worker endpoint").into_tonic_status()
+            DispatcherException::DeploymentError(placement) => {
+                let message = placement
+                    .get_failed_partitions()
+                    .iter()
+                    .map(|partition| {
+                        let node = partition.node.clone().unwrap_or_default();
+                        format!("{}:{} [{}]", node.host, node.port, &partition.err_msg)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let status =
+                    task_deployment_err(format!("partitions fail to start: {message}").as_str())
+                        .into_tonic_status();
+                // the placement is attached with the error so that clients can still know which partitions are started
+                Response {
+                    placement: Some(placement.clone()),
+                    ..Response::from_status(&status)
                 }
-                TaskDeploymentException::RpcError(status) => status.clone(),
-            },
+                .into_status()
+            }
             DispatcherException::NotFoundDataflow(job_id) => {
                 not_found_dataflow(job_id).into_tonic_status()
             }
+            DispatcherException::Savepoint(err) => err.to_tonic_status(),
+            DispatcherException::InvalidPageToken(token) => {
+                invalid_page_token(token).into_tonic_status()
+            }
+            DispatcherException::InvalidLabelSelector(message) => {
+                invalid_label_selector(message).into_tonic_status()
+            }
+            DispatcherException::ResourceVersionConflict {
+                job_id,
+                expected,
+                actual,
+            } => resource_version_conflict(job_id, *expected, *actual).into_tonic_status(),
+            DispatcherException::TerminationFailed(failures) => {
+                TaskExecutionException::WorkerFailures(failures.clone()).to_tonic_status()
+            }
+            DispatcherException::Cluster(err) => add_node_err(err).into_tonic_status(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeSet, HashMap},
+        time::Duration,
+    };
+
+    use common::{
+        backpressure::BACKPRESSURE_METRIC,
+        metrics::{
+            ERROR_POLICY_DEAD_LETTERED_METRIC, ERROR_POLICY_FAILED_METRIC,
+            ERROR_POLICY_SKIPPED_METRIC, OPERATOR_EVENTS_IN_METRIC,
+        },
+        net::{
+            cluster::{BackpressureConfig, ClusterBuilder, NodeBuilder, RebalanceConfig},
+            AckResponderBuilder, HeartbeatBuilder,
+        },
+        snapshot::CHECKPOINT_DURABILITY_LAG_MILLIS_METRIC,
+    };
+    use proto::{
+        common::{
+            Ack, Dataflow, DataflowMeta, DataflowPlacement, DataflowStatus, ExecutorInfo,
+            ExecutorStatus, Heartbeat, HostAddr, KeyedDataEvent, KeyedEventSet, OperatorError,
+            OperatorErrorKind, OperatorInfo, OperatorStates, PartitionPlacement, PartitionStatus,
+            ResourceId, Response, SubDataflowId, SubDataflowStates, SubdataflowInfo,
+        },
+        coordinator::{DataflowSummary, ListDataflowsRequest, NodeHealth},
+        taskmanager::{
+            task_manager_api_server::{TaskManagerApi, TaskManagerApiServer},
+            BatchSendEventsToOperatorResponse, CreateSubDataflowRequest, CreateSubDataflowResponse,
+            OperatorRequest, SendEventToOperatorResponse, StopDataflowRequest,
+            StopDataflowResponse, StopMode, TapOperatorRequest, UpdateSinkConfigRequest,
+        },
+    };
+
+    use crate::coordinator::storage::{DataflowStorageBuilder, Durability};
+
+    use super::{
+        get_metrics_summary, transition, Dispatcher, DispatcherException, JobManager,
+        DEFAULT_LIST_PAGE_SIZE, MAX_OPERATOR_ERRORS,
+    };
+
+    fn new_dispatcher() -> Dispatcher {
+        new_dispatcher_with_nodes("localhost:8792")
+    }
+
+    fn new_dispatcher_with_nodes(nodes: &str) -> Dispatcher {
+        new_dispatcher_with_storage(
+            nodes,
+            &DataflowStorageBuilder::Memory {
+                ttl: None,
+                max_entries: None,
+            },
+        )
+    }
+
+    fn new_dispatcher_with_storage(nodes: &str, storage: &DataflowStorageBuilder) -> Dispatcher {
+        Dispatcher::new(
+            &ClusterBuilder {
+                nodes: nodes.to_string(),
+                rpc_timeout: 3,
+                connect_timeout: 3,
+            },
+            storage,
+            &HeartbeatBuilder {
+                period: 3,
+                connect_timeout: 3,
+                rpc_timeout: 3,
+            },
+            &AckResponderBuilder {
+                delay: 3,
+                buf_size: 10,
+                connect_timeout: 3,
+                rpc_timeout: 3,
+            },
+            8791,
+        )
+    }
+
+    fn new_operator_error(job_id: &ResourceId, operator_id: u32) -> OperatorError {
+        OperatorError {
+            job_id: Some(job_id.clone()),
+            operator_id,
+            kind: OperatorErrorKind::Execution as i32,
+            message: format!("operator {operator_id} process event failed"),
+            timestamp: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_dataflows() {
+        let dispatcher = new_dispatcher();
+        let storage = DataflowStorageBuilder::Memory {
+            ttl: None,
+            max_entries: None,
+        }
+        .build_shared();
+        for (namespace, count) in [("first", 3), ("second", DEFAULT_LIST_PAGE_SIZE + 1)] {
+            for index in 0..count {
+                let job_id = ResourceId::new(namespace.to_string(), format!("job-{index:03}"));
+                let mut labels = HashMap::from([("team".to_string(), namespace.to_string())]);
+                if index < 2 {
+                    labels.insert("env".to_string(), ["dev", "prod"][index].to_string());
+                }
+                let dataflow = Dataflow {
+                    job_id: Some(job_id.clone()),
+                    nodes: (0..index as u32)
+                        .map(|operator_id| (operator_id, OperatorInfo::default()))
+                        .collect(),
+                    labels,
+                    ..Default::default()
+                };
+                dispatcher.managers.insert(
+                    job_id,
+                    JobManager::new(&HostAddr::default(), dataflow, &storage),
+                );
+            }
+        }
+
+        // pages of the first namespace are listed in the order of job ids
+        let mut request = ListDataflowsRequest {
+            namespace: "first".to_string(),
+            page_size: 2,
+            ..Default::default()
+        };
+        let page = dispatcher.list_dataflows(&request).await.ok().unwrap();
+        assert_eq!(
+            page.dataflows
+                .iter()
+                .map(|summary| summary.job_id.clone().unwrap_or_default().resource_id)
+                .collect::<Vec<_>>(),
+            vec!["job-000", "job-001"]
+        );
+        let summary = &page.dataflows[1];
+        assert_eq!(summary.status(), DataflowStatus::Pending);
+        assert_eq!(summary.operator_count, 1);
+        assert!(summary.created_at > 0 && summary.updated_at >= summary.created_at);
+        assert!(!page.next_page_token.is_empty());
+
+        request.page_token = page.next_page_token;
+        let page = dispatcher.list_dataflows(&request).await.ok().unwrap();
+        assert_eq!(page.dataflows.len(), 1);
+        assert_eq!(page.dataflows[0].operator_count, 2);
+        assert!(page.next_page_token.is_empty());
+
+        // all namespaces are listed with the default page size
+        let page = dispatcher
+            .list_dataflows(&ListDataflowsRequest::default())
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(page.dataflows.len(), DEFAULT_LIST_PAGE_SIZE);
+        let page = dispatcher
+            .list_dataflows(&ListDataflowsRequest {
+                page_token: page.next_page_token,
+                ..Default::default()
+            })
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(page.dataflows.len(), 4);
+        assert!(page.next_page_token.is_empty());
+
+        for page_token in ["not a token", "0", "ff", "0a+161"] {
+            let result = dispatcher
+                .list_dataflows(&ListDataflowsRequest {
+                    page_token: page_token.to_string(),
+                    ..Default::default()
+                })
+                .await;
+            assert!(matches!(
+                result,
+                Err(DispatcherException::InvalidPageToken(token)) if token == page_token
+            ));
+        }
+
+        // the label selector is applied before the page is cut
+        let mut request = ListDataflowsRequest {
+            page_size: 1,
+            label_selector: "team=first,env!=dev".to_string(),
+            ..Default::default()
+        };
+        let page = dispatcher.list_dataflows(&request).await.ok().unwrap();
+        assert_eq!(page.dataflows.len(), 1);
+        let summary = &page.dataflows[0];
+        assert_eq!(
+            summary.job_id.clone().unwrap_or_default().resource_id,
+            "job-001"
+        );
+        assert_eq!(
+            summary.labels,
+            HashMap::from([
+                ("team".to_string(), "first".to_string()),
+                ("env".to_string(), "prod".to_string())
+            ])
+        );
+        request.page_token = page.next_page_token;
+        let page = dispatcher.list_dataflows(&request).await.ok().unwrap();
+        assert_eq!(page.dataflows.len(), 1);
+        assert_eq!(
+            page.dataflows[0]
+                .job_id
+                .clone()
+                .unwrap_or_default()
+                .resource_id,
+            "job-002"
+        );
+        assert!(page.next_page_token.is_empty());
+
+        let result = dispatcher
+            .list_dataflows(&ListDataflowsRequest {
+                label_selector: "team".to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(matches!(
+            result,
+            Err(DispatcherException::InvalidLabelSelector(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_report_operator_error() {
+        let dispatcher = new_dispatcher();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        dispatcher.managers.insert(
+            job_id.clone(),
+            JobManager::new(
+                &HostAddr::default(),
+                Dataflow {
+                    job_id: Some(job_id.clone()),
+                    ..Default::default()
+                },
+                &DataflowStorageBuilder::Memory {
+                    ttl: None,
+                    max_entries: None,
+                }
+                .build_shared(),
+            ),
+        );
+
+        let err = new_operator_error(&job_id, 1);
+        assert!(dispatcher.report_operator_error(err.clone()).await.is_ok());
+
+        let states = dispatcher.get_dataflow(&job_id, false, false).await;
+        assert!(states.is_ok());
+        assert_eq!(states.ok().unwrap().operator_errors, vec![err]);
+    }
+
+    #[tokio::test]
+    async fn test_report_operator_error_of_unknown_job() {
+        let dispatcher = new_dispatcher();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+
+        let result = dispatcher
+            .report_operator_error(new_operator_error(&job_id, 1))
+            .await;
+        match result {
+            Err(DispatcherException::NotFoundDataflow(id)) => assert_eq!(id, job_id),
+            _ => panic!("unexpected result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_manager_keeps_latest_operator_errors() {
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let manager = JobManager::new(
+            &HostAddr::default(),
+            Dataflow {
+                job_id: Some(job_id.clone()),
+                ..Default::default()
+            },
+            &DataflowStorageBuilder::Memory {
+                ttl: None,
+                max_entries: None,
+            }
+            .build_shared(),
+        );
+
+        for operator_id in 0..(MAX_OPERATOR_ERRORS as u32 + 1) {
+            manager
+                .report_operator_error(new_operator_error(&job_id, operator_id))
+                .await;
+        }
+
+        let states = manager.get_dataflow().await;
+        assert_eq!(states.operator_errors.len(), MAX_OPERATOR_ERRORS);
+        assert_eq!(states.operator_errors[0].operator_id, 1);
+        assert_eq!(
+            states.operator_errors[MAX_OPERATOR_ERRORS - 1].operator_id,
+            MAX_OPERATOR_ERRORS as u32
+        );
+    }
+
+    #[test]
+    fn test_status_transition() {
+        let job_id = ResourceId::default();
+        let mut summary = DataflowSummary::default();
+        assert_eq!(summary.status(), DataflowStatus::Pending);
+
+        assert!(transition(
+            &job_id,
+            &mut summary,
+            DataflowStatus::Deploying,
+            String::new()
+        ));
+        assert!(transition(
+            &job_id,
+            &mut summary,
+            DataflowStatus::Degraded,
+            "partition on tm-1:8792 fails to start".to_string()
+        ));
+        assert!(summary.updated_at > 0);
+        // the same status and reason change nothing
+        assert!(!transition(
+            &job_id,
+            &mut summary,
+            DataflowStatus::Degraded,
+            "partition on tm-1:8792 fails to start".to_string()
+        ));
+        assert!(transition(
+            &job_id,
+            &mut summary,
+            DataflowStatus::Closed,
+            String::new()
+        ));
+
+        // a closed dataflow is never running again without a creation
+        let closed = summary.clone();
+        assert!(!transition(
+            &job_id,
+            &mut summary,
+            DataflowStatus::Running,
+            String::new()
+        ));
+        assert_eq!(summary, closed);
+    }
+
+    /// a TaskManager which only accepts the creation, the termination of sub-dataflows and savepoints.
+    /// The state of each operator is its id, unless the sub-dataflow is restored from a savepoint.
+    /// Every operator reports the same backpressure
+    #[derive(Default)]
+    struct MockTaskManager {
+        states: std::sync::Mutex<OperatorStates>,
+        backpressure: u64,
+    }
+
+    #[tonic::async_trait]
+    impl TaskManagerApi for MockTaskManager {
+        async fn send_event_to_operator(
+            &self,
+            _: tonic::Request<KeyedDataEvent>,
+        ) -> Result<tonic::Response<SendEventToOperatorResponse>, tonic::Status> {
+            Err(tonic::Status::unimplemented("send_event_to_operator"))
+        }
+
+        async fn stop_dataflow(
+            &self,
+            _: tonic::Request<StopDataflowRequest>,
+        ) -> Result<tonic::Response<StopDataflowResponse>, tonic::Status> {
+            Ok(tonic::Response::new(StopDataflowResponse::default()))
+        }
+
+        async fn create_sub_dataflow(
+            &self,
+            request: tonic::Request<CreateSubDataflowRequest>,
+        ) -> Result<tonic::Response<CreateSubDataflowResponse>, tonic::Status> {
+            let request = request.into_inner();
+            // the sub-dataflow runs the centers of its metas, the other nodes are their remote neighbors
+            *self.states.lock().unwrap() = request.savepoint.unwrap_or_else(|| OperatorStates {
+                states: request
+                    .dataflow
+                    .iter()
+                    .flat_map(|dataflow| dataflow.meta.iter())
+                    .map(|meta| (meta.center, vec![meta.center as u8]))
+                    .collect(),
+            });
+            Ok(tonic::Response::new(CreateSubDataflowResponse::default()))
+        }
+
+        async fn receive_heartbeat(
+            &self,
+            _: tonic::Request<Heartbeat>,
+        ) -> Result<tonic::Response<Response>, tonic::Status> {
+            Ok(tonic::Response::new(Response::ok()))
+        }
+
+        async fn receive_ack(
+            &self,
+            _: tonic::Request<Ack>,
+        ) -> Result<tonic::Response<Response>, tonic::Status> {
+            Ok(tonic::Response::new(Response::ok()))
+        }
+
+        async fn batch_send_events_to_operator(
+            &self,
+            _: tonic::Request<KeyedEventSet>,
+        ) -> Result<tonic::Response<BatchSendEventsToOperatorResponse>, tonic::Status> {
+            Err(tonic::Status::unimplemented(
+                "batch_send_events_to_operator",
+            ))
+        }
+
+        async fn get_sub_dataflow(
+            &self,
+            _: tonic::Request<ResourceId>,
+        ) -> Result<tonic::Response<SubDataflowStates>, tonic::Status> {
+            // every operator is running, restarted once and receives a heartbeat at the epoch plus its id in seconds
+            let states = self.states.lock().unwrap();
+            Ok(tonic::Response::new(SubDataflowStates {
+                subdataflow_infos: Some(SubdataflowInfo {
+                    execution_id: None,
+                    executors_info: states
+                        .states
+                        .keys()
+                        .map(|operator_id| {
+                            (
+                                *operator_id,
+                                ExecutorInfo {
+                                    executor_id: *operator_id,
+                                    status: ExecutorStatus::Running as i32,
+                                    metrics: [(BACKPRESSURE_METRIC.to_string(), self.backpressure)]
+                                        .into(),
+                                    last_heartbeat_at: *operator_id as i64 * 1000,
+                                    restart_count: 1,
+                                },
+                            )
+                        })
+                        .collect(),
+                }),
+            }))
+        }
+
+        async fn drain_operator(
+            &self,
+            _: tonic::Request<OperatorRequest>,
+        ) -> Result<tonic::Response<Response>, tonic::Status> {
+            Err(tonic::Status::unimplemented("drain_operator"))
+        }
+
+        async fn resume_operator(
+            &self,
+            _: tonic::Request<OperatorRequest>,
+        ) -> Result<tonic::Response<Response>, tonic::Status> {
+            Err(tonic::Status::unimplemented("resume_operator"))
+        }
+
+        async fn update_sink_config(
+            &self,
+            _: tonic::Request<UpdateSinkConfigRequest>,
+        ) -> Result<tonic::Response<Response>, tonic::Status> {
+            Err(tonic::Status::unimplemented("update_sink_config"))
+        }
+
+        async fn trigger_savepoint(
+            &self,
+            _: tonic::Request<ResourceId>,
+        ) -> Result<tonic::Response<OperatorStates>, tonic::Status> {
+            Ok(tonic::Response::new(self.states.lock().unwrap().clone()))
+        }
+
+        type TapOperatorStream = tonic::codec::Streaming<KeyedDataEvent>;
+
+        async fn tap_operator(
+            &self,
+            _: tonic::Request<TapOperatorRequest>,
+        ) -> Result<tonic::Response<Self::TapOperatorStream>, tonic::Status> {
+            Err(tonic::Status::unimplemented("tap_operator"))
+        }
+    }
+
+    /// the mock TaskManager runs in its own runtime so that it won't be blocked by the heartbeat and ack tasks of the coordinator
+    fn start_mock_task_manager(port: u16) {
+        start_given_mock_task_manager(port, MockTaskManager::default())
+    }
+
+    fn start_given_mock_task_manager(port: u16, task_manager: MockTaskManager) {
+        std::thread::spawn(move || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(
+                    tonic::transport::Server::builder()
+                        .add_service(TaskManagerApiServer::new(task_manager))
+                        .serve(format!("127.0.0.1:{port}").parse().unwrap()),
+                )
+                .unwrap()
+        });
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    fn local_addr(port: u16) -> HostAddr {
+        HostAddr {
+            host: "127.0.0.1".to_string(),
+            port: port as u32,
+        }
+    }
+
+    /// operator 0 and 1 are placed on the first node, operator 2 is placed on the second one
+    fn new_partitioned_dataflow(
+        job_id: &ResourceId,
+        first: &HostAddr,
+        second: &HostAddr,
+    ) -> Dataflow {
+        let mut nodes = HashMap::new();
+        for (operator_id, host_addr) in [(0, first), (1, first), (2, second)] {
+            nodes.insert(
+                operator_id,
+                OperatorInfo {
+                    operator_id,
+                    host_addr: Some(host_addr.clone()),
+                    ..Default::default()
+                },
+            );
+        }
+
+        Dataflow {
+            job_id: Some(job_id.clone()),
+            meta: vec![
+                DataflowMeta {
+                    center: 0,
+                    neighbors: vec![1],
+                    edge_types: Default::default(),
+                },
+                DataflowMeta {
+                    center: 1,
+                    neighbors: vec![2],
+                    edge_types: Default::default(),
+                },
+                DataflowMeta {
+                    center: 2,
+                    neighbors: vec![],
+                    edge_types: Default::default(),
+                },
+            ],
+            nodes,
+            ..Default::default()
+        }
+    }
+
+    fn assert_operator_placement(
+        placement: &DataflowPlacement,
+        first: &HostAddr,
+        second: &HostAddr,
+    ) {
+        assert_eq!(
+            placement.operators,
+            HashMap::from([(0, first.clone()), (1, first.clone()), (2, second.clone())])
+        );
+        assert_eq!(placement.partitions.len(), 2);
+        assert_eq!(placement.partitions[0].node.as_ref(), Some(first));
+        assert_eq!(placement.partitions[0].operator_ids, vec![0, 1]);
+        assert_eq!(placement.partitions[1].node.as_ref(), Some(second));
+        assert_eq!(placement.partitions[1].operator_ids, vec![2]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_create_dataflow_returns_placement() {
+        start_mock_task_manager(8793);
+        start_mock_task_manager(8794);
+        let (first, second) = (local_addr(8793), local_addr(8794));
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8793,127.0.0.1:8794");
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+
+        let result = dispatcher
+            .create_dataflow(new_partitioned_dataflow(&job_id, &first, &second), None)
+            .await;
+        let placement = match result {
+            Ok(placement) => placement,
+            Err(err) => panic!("unexpected error {}", err.to_tonic_status()),
+        };
+
+        assert_eq!(placement.job_id.as_ref(), Some(&job_id));
+        assert!(placement.is_started());
+        assert_operator_placement(&placement, &first, &second);
+        for (sub_id, partition) in placement.partitions.iter().enumerate() {
+            assert_eq!(partition.status(), PartitionStatus::Started);
+            assert_eq!(
+                partition
+                    .execution_id
+                    .as_ref()
+                    .map(|execution_id| execution_id.sub_id),
+                Some(sub_id as u32)
+            );
+        }
+
+        let response = Response::with_placement(placement.clone());
+        assert_eq!(response.status, Response::ok().status);
+        assert_eq!(response.placement, Some(placement));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_get_effective_dataflow() {
+        start_mock_task_manager(8835);
+        start_mock_task_manager(8836);
+        let (first, second) = (local_addr(8835), local_addr(8836));
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8835,127.0.0.1:8836");
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let placement = match dispatcher
+            .create_dataflow(new_partitioned_dataflow(&job_id, &first, &second), None)
+            .await
+        {
+            Ok(placement) => placement,
+            Err(err) => panic!("unexpected error {}", err.to_tonic_status()),
+        };
+
+        // the partitions are only returned if the effective dataflow is requested
+        let states = dispatcher
+            .get_dataflow(&job_id, false, false)
+            .await
+            .ok()
+            .unwrap();
+        assert!(states.partitions.is_empty());
+
+        let states = dispatcher
+            .get_dataflow(&job_id, true, false)
+            .await
+            .ok()
+            .unwrap();
+        // every operator of the graph is assigned to the TaskManager it's placed on
+        states
+            .graph
+            .unwrap()
+            .nodes
+            .iter()
+            .for_each(|(operator_id, operator)| {
+                assert_eq!(
+                    operator.host_addr.as_ref(),
+                    placement.operators.get(operator_id)
+                )
+            });
+        assert_eq!(
+            states
+                .partitions
+                .iter()
+                .map(|partition| partition.placement.clone().unwrap())
+                .collect::<Vec<_>>(),
+            placement.partitions
+        );
+        let subdataflows = states
+            .partitions
+            .iter()
+            .map(|partition| partition.dataflow.clone().unwrap())
+            .collect::<Vec<_>>();
+        // operator 2 is the downstream of the first partition, so it's known by both of them
+        for (subdataflow, centers, operator_ids) in [
+            (&subdataflows[0], vec![0, 1], vec![0, 1, 2]),
+            (&subdataflows[1], vec![2], vec![2]),
+        ] {
+            assert_eq!(subdataflow.job_id.as_ref(), Some(&job_id));
+            assert_eq!(
+                subdataflow
+                    .meta
+                    .iter()
+                    .map(|meta| meta.center)
+                    .collect::<Vec<_>>(),
+                centers
+            );
+            assert_eq!(
+                subdataflow.nodes.keys().cloned().collect::<BTreeSet<_>>(),
+                BTreeSet::from_iter(operator_ids)
+            );
+        }
+        assert_eq!(subdataflows[0].nodes[&2].host_addr.as_ref(), Some(&second));
+    }
+
+    #[test]
+    fn test_get_metrics_summary() {
+        let executor = |metrics: &[(&str, u64)]| ExecutorInfo {
+            metrics: metrics
+                .iter()
+                .map(|(name, value)| (name.to_string(), *value))
+                .collect(),
+            ..Default::default()
+        };
+        let subdataflow_infos = vec![
+            SubdataflowInfo {
+                executors_info: [
+                    (
+                        0,
+                        executor(&[
+                            (OPERATOR_EVENTS_IN_METRIC, 100),
+                            (ERROR_POLICY_SKIPPED_METRIC, 2),
+                            (BACKPRESSURE_METRIC, 300),
+                        ]),
+                    ),
+                    (
+                        1,
+                        executor(&[
+                            (OPERATOR_EVENTS_IN_METRIC, 98),
+                            (ERROR_POLICY_DEAD_LETTERED_METRIC, 1),
+                            (CHECKPOINT_DURABILITY_LAG_MILLIS_METRIC, 1500),
+                        ]),
+                    ),
+                ]
+                .into(),
+                ..Default::default()
+            },
+            SubdataflowInfo {
+                executors_info: [(
+                    2,
+                    executor(&[
+                        (OPERATOR_EVENTS_IN_METRIC, 2),
+                        (ERROR_POLICY_FAILED_METRIC, 1),
+                        (BACKPRESSURE_METRIC, 100),
+                        (CHECKPOINT_DURABILITY_LAG_MILLIS_METRIC, 500),
+                    ]),
+                )]
+                .into(),
+                ..Default::default()
+            },
+        ];
+
+        let summary = get_metrics_summary(&subdataflow_infos);
+        assert_eq!(summary.events_processed, 200);
+        assert_eq!(summary.errors, 4);
+        assert_eq!(summary.error_rate, 0.02);
+        assert_eq!(summary.lag_millis, 1500);
+        assert_eq!(summary.max_backpressure, 300);
+        assert_eq!(summary.reported_operators, 3);
+
+        // no event is processed yet
+        let summary = get_metrics_summary(&[]);
+        assert_eq!(summary.error_rate, 0.0);
+        assert_eq!(summary.reported_operators, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_get_dataflow_with_metrics() {
+        start_given_mock_task_manager(
+            8846,
+            MockTaskManager {
+                backpressure: 300,
+                ..Default::default()
+            },
+        );
+        let addr = local_addr(8846);
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8846");
+        let job_id = ResourceId::new("default", "metrics");
+        assert!(dispatcher
+            .create_dataflow(new_partitioned_dataflow(&job_id, &addr, &addr), None)
+            .await
+            .is_ok());
+
+        // the metrics are only aggregated if they're requested
+        let states = dispatcher
+            .get_dataflow(&job_id, false, false)
+            .await
+            .ok()
+            .unwrap();
+        assert!(states.metrics.is_none());
+
+        let states = dispatcher
+            .get_dataflow(&job_id, false, true)
+            .await
+            .ok()
+            .unwrap();
+        let metrics = states.metrics.unwrap();
+        assert_eq!(metrics.reported_operators, 3);
+        assert_eq!(metrics.max_backpressure, 300);
+        assert_eq!(metrics.events_processed, 0);
+        assert_eq!(metrics.error_rate, 0.0);
+
+        // a dataflow which is saved but not deployed has no operator reporting the metrics
+        let pending = ResourceId::new("default", "pending");
+        assert!(dispatcher
+            .storage
+            .lock()
+            .unwrap()
+            .save(&new_partitioned_dataflow(&pending, &addr, &addr))
+            .is_ok());
+        let states = dispatcher
+            .get_dataflow(&pending, false, true)
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(
+            states.metrics.map(|metrics| metrics.reported_operators),
+            Some(0)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_create_dataflow_with_failed_partition() {
+        start_mock_task_manager(8795);
+        let (first, second) = (local_addr(8795), local_addr(8796));
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8795,127.0.0.1:8796");
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+
+        let result = dispatcher
+            .create_dataflow(new_partitioned_dataflow(&job_id, &first, &second), None)
+            .await;
+        let err = match result {
+            Err(err) => err,
+            Ok(_) => panic!("the partition on an unreachable node should fail to start"),
+        };
+
+        let placement = match &err {
+            DispatcherException::DeploymentError(placement) => placement.clone(),
+            _ => panic!("unexpected error {}", err.to_tonic_status()),
+        };
+        assert!(!placement.is_started());
+        assert_operator_placement(&placement, &first, &second);
+        assert_eq!(placement.partitions[0].status(), PartitionStatus::Started);
+        assert_eq!(placement.partitions[1].status(), PartitionStatus::Failed);
+        assert!(!placement.partitions[1].err_msg.is_empty());
+        // the dataflow keeps running on the started partition
+        assert_eq!(placement.status(), DataflowStatus::Degraded);
+        assert!(placement.status_reason.contains("127.0.0.1:8796"));
+        let summary = match dispatcher.get_dataflow_status(&job_id, false).await {
+            Ok(status) => status.summary.unwrap_or_default(),
+            Err(err) => panic!("unexpected error {}", err.to_tonic_status()),
+        };
+        assert_eq!(summary.status(), DataflowStatus::Degraded);
+        assert_eq!(summary.status_reason, placement.status_reason);
+
+        let status = err.to_tonic_status();
+        assert!(status.message().contains("127.0.0.1:8796"));
+        let response = Response::from_status(&status);
+        assert_eq!(response.placement, Some(placement));
+        assert_eq!(response.get_error().unwrap().get_code(), status.code());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_placement_survives_restart() {
+        start_mock_task_manager(8798);
+        start_mock_task_manager(8799);
+        let (first, second) = (local_addr(8798), local_addr(8799));
+        let nodes = "127.0.0.1:8798,127.0.0.1:8799";
+        let path = std::env::temp_dir().join(format!(
+            "lightflus-coord-{}",
+            common::utils::times::now_timestamp()
+        ));
+        let storage = DataflowStorageBuilder::Local {
+            dataflow_store_path: path.to_string_lossy().to_string(),
+            durability: Durability::Strict,
+        };
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+
+        let placement = {
+            let dispatcher = new_dispatcher_with_storage(nodes, &storage);
+            let result = dispatcher
+                .create_dataflow(new_partitioned_dataflow(&job_id, &first, &second), None)
+                .await;
+            match result {
+                Ok(placement) => placement,
+                Err(err) => panic!("unexpected error {}", err.to_tonic_status()),
+            }
+        };
+
+        // a restarted dispatcher resumes managing the job without deploying it again
+        let dispatcher = new_dispatcher_with_storage(nodes, &storage);
+        assert!(dispatcher.managers.get(&job_id).is_none());
+        dispatcher.init();
+
+        let entry = dispatcher.managers.get(&job_id);
+        assert!(entry.is_some());
+        let entry = entry.unwrap();
+        let job_manager = entry.value();
+        assert_eq!(job_manager.placement, placement);
+        assert_operator_placement(&job_manager.placement, &first, &second);
+        assert_eq!(
+            job_manager
+                .dataflow
+                .nodes
+                .iter()
+                .map(|(operator_id, info)| (*operator_id, info.get_host_addr()))
+                .collect::<HashMap<_, _>>(),
+            placement.operators
+        );
+        assert_eq!(job_manager.scheduler.executions.len(), 2);
+        // the timestamps are recovered with the placement
+        let summary = job_manager.get_summary().await;
+        assert_eq!(summary.status(), DataflowStatus::Running);
+        assert_eq!(
+            (summary.created_at, summary.updated_at),
+            (placement.created_at, placement.updated_at)
+        );
+        assert!(placement.created_at > 0 && placement.updated_at >= placement.created_at);
+        drop(entry);
+        drop(dispatcher);
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_cluster_topology_counts_started_partitions() {
+        start_mock_task_manager(8800);
+        let (first, second) = (local_addr(8800), local_addr(8801));
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8800,127.0.0.1:8801");
+
+        let topology = dispatcher.get_cluster_topology();
+        assert_eq!(
+            topology
+                .nodes
+                .iter()
+                .map(|node| (node.host_addr.clone(), node.partitions))
+                .collect::<Vec<_>>(),
+            vec![(Some(first.clone()), 0), (Some(second.clone()), 0)]
+        );
+
+        for resource_id in ["first_job", "second_job"] {
+            let job_id = ResourceId::new("namespace_id", resource_id.to_string());
+            // the partition on the second node fails to start because no TaskManager listens on it
+            let result = dispatcher
+                .create_dataflow(new_partitioned_dataflow(&job_id, &first, &second), None)
+                .await;
+            assert!(result.is_err());
+        }
+
+        let topology = dispatcher.get_cluster_topology();
+        assert_eq!(
+            topology
+                .nodes
+                .iter()
+                .map(|node| (node.node_id, node.partitions))
+                .collect::<Vec<_>>(),
+            vec![(0, 2), (1, 0)]
+        );
+        assert!(topology
+            .nodes
+            .iter()
+            .all(|node| node.health() == NodeHealth::Pending));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_sample_backpressure() {
+        start_given_mock_task_manager(
+            8825,
+            MockTaskManager {
+                backpressure: 900,
+                ..Default::default()
+            },
+        );
+        start_given_mock_task_manager(
+            8826,
+            MockTaskManager {
+                backpressure: 100,
+                ..Default::default()
+            },
+        );
+        let (first, second) = (local_addr(8825), local_addr(8826));
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8825,127.0.0.1:8826")
+            .with_backpressure(&BackpressureConfig {
+                threshold: 800,
+                sustain: 0,
+                sample_interval: 1,
+            });
+        let job_id = ResourceId::new("default", "backpressure");
+        assert!(dispatcher
+            .create_dataflow(new_partitioned_dataflow(&job_id, &first, &second), None)
+            .await
+            .is_ok());
+
+        dispatcher.sample_backpressure().await;
+        let topology = dispatcher.get_cluster_topology();
+        assert_eq!(
+            topology
+                .nodes
+                .iter()
+                .map(|node| (node.backpressure, node.backpressured))
+                .collect::<Vec<_>>(),
+            vec![(900, true), (100, false)]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_savepoints() {
+        start_mock_task_manager(8811);
+        start_mock_task_manager(8812);
+        let (first, second) = (local_addr(8811), local_addr(8812));
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8811,127.0.0.1:8812");
+        let job_id = ResourceId::new("default", "savepoint");
+
+        assert_eq!(
+            dispatcher
+                .trigger_savepoint(&job_id)
+                .await
+                .map_err(|err| err.to_tonic_status().code())
+                .err(),
+            Some(tonic::Code::NotFound)
+        );
+
+        assert!(dispatcher
+            .create_dataflow(new_partitioned_dataflow(&job_id, &first, &second), None)
+            .await
+            .is_ok());
+        let savepoint = match dispatcher.trigger_savepoint(&job_id).await {
+            Ok(savepoint) => savepoint,
+            Err(err) => panic!("unexpected error {}", err.to_tonic_status()),
+        };
+        assert_eq!(savepoint.operator_ids, vec![0, 1, 2]);
+        assert_eq!(
+            dispatcher.list_savepoints(&job_id).await.ok(),
+            Some(vec![savepoint.clone()])
+        );
+        assert_eq!(
+            dispatcher
+                .load_savepoint(&savepoint.path)
+                .await
+                .ok()
+                .map(|savepoint| savepoint.states),
+            Some([(0, vec![0]), (1, vec![1]), (2, vec![2])].into())
+        );
+
+        // states of the operators which are not in the dataflow are not sent to workers
+        let restored = OperatorStates {
+            states: [(1, vec![10]), (2, vec![20]), (3, vec![30])].into(),
+        };
+        assert!(dispatcher
+            .create_dataflow(
+                new_partitioned_dataflow(&job_id, &first, &second),
+                Some(&restored)
+            )
+            .await
+            .is_ok());
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let restored_savepoint = match dispatcher.trigger_savepoint(&job_id).await {
+            Ok(savepoint) => savepoint,
+            Err(err) => panic!("unexpected error {}", err.to_tonic_status()),
+        };
+        assert_eq!(restored_savepoint.operator_ids, vec![1, 2]);
+
+        assert!(dispatcher
+            .delete_savepoint(&job_id, &savepoint.path)
+            .await
+            .is_ok());
+        assert_eq!(
+            dispatcher.list_savepoints(&job_id).await.ok(),
+            Some(vec![restored_savepoint])
+        );
+        assert_eq!(
+            dispatcher
+                .delete_savepoint(&job_id, &savepoint.path)
+                .await
+                .map_err(|err| err.to_tonic_status().code())
+                .err(),
+            Some(tonic::Code::NotFound)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_terminate_dataflow() {
+        start_mock_task_manager(8813);
+        start_mock_task_manager(8814);
+        let (first, second) = (local_addr(8813), local_addr(8814));
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8813,127.0.0.1:8814");
+        let job_id = ResourceId::new("default", "terminate");
+
+        assert!(dispatcher
+            .create_dataflow(new_partitioned_dataflow(&job_id, &first, &second), None)
+            .await
+            .is_ok());
+        let status = dispatcher
+            .terminate_dataflow(&job_id, StopMode::Drain)
+            .await
+            .map_err(|err| err.to_tonic_status());
+        assert_eq!(status.ok(), Some(DataflowStatus::Closed));
+
+        // the dataflow is forgotten once all subdataflows are stopped
+        assert!(matches!(
+            dispatcher.get_dataflow(&job_id, false, false).await,
+            Err(DispatcherException::NotFoundDataflow(_))
+        ));
+        assert!(dispatcher.storage.lock().unwrap().get(&job_id).is_none());
+        assert_eq!(
+            dispatcher
+                .terminate_dataflow(&job_id, StopMode::Drain)
+                .await
+                .ok(),
+            Some(DataflowStatus::Closed)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_update_dataflow() {
+        start_mock_task_manager(8827);
+        start_mock_task_manager(8828);
+        let (first, second) = (local_addr(8827), local_addr(8828));
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8827,127.0.0.1:8828");
+        let job_id = ResourceId::new("default", "update");
+        let dataflow = new_partitioned_dataflow(&job_id, &first, &second);
+
+        assert!(matches!(
+            dispatcher.update_dataflow(dataflow.clone(), 1).await,
+            Err(DispatcherException::NotFoundDataflow(_))
+        ));
+
+        assert!(dispatcher
+            .create_dataflow(dataflow.clone(), None)
+            .await
+            .is_ok());
+        let created = match dispatcher.get_dataflow_status(&job_id, false).await {
+            Ok(status) => status.summary.unwrap_or_default(),
+            Err(err) => panic!("unexpected error {}", err.to_tonic_status()),
+        };
+        assert!(created.resource_version > 0);
+
+        // an update based on another version conflicts
+        let err = dispatcher
+            .update_dataflow(dataflow.clone(), created.resource_version + 1)
+            .await
+            .err()
+            .map(|err| err.to_tonic_status().code());
+        assert_eq!(err, Some(tonic::Code::Aborted));
+
+        let (response, savepoint) = match dispatcher
+            .update_dataflow(dataflow.clone(), created.resource_version)
+            .await
+        {
+            Ok(result) => result,
+            Err(err) => panic!("unexpected error {}", err.to_tonic_status()),
+        };
+        let summary = response.summary.unwrap_or_default();
+        assert_eq!(summary.resource_version, created.resource_version + 1);
+        assert_eq!(summary.created_at, created.created_at);
+        assert_eq!(response.restarted_operator_ids, vec![0, 1, 2]);
+        assert_eq!(
+            response
+                .placement
+                .map(|placement| placement.resource_version),
+            Some(summary.resource_version)
+        );
+        // the new dataflow is restored from the savepoint of the replaced one
+        assert_eq!(
+            savepoint.states,
+            [(0, vec![0]), (1, vec![1]), (2, vec![2])].into()
+        );
+        assert_eq!(
+            dispatcher
+                .get_dataflow_status(&job_id, false)
+                .await
+                .ok()
+                .and_then(|status| status.summary)
+                .map(|summary| summary.resource_version),
+            Some(summary.resource_version)
+        );
+
+        // the version which the update is based on is stale now
+        let err = dispatcher
+            .update_dataflow(dataflow.clone(), created.resource_version)
+            .await
+            .err()
+            .map(|err| err.to_tonic_status().code());
+        assert_eq!(err, Some(tonic::Code::Aborted));
+
+        // an update in progress makes the others conflict, and the version is kept if it fails before the termination
+        let entry = dispatcher.managers.get(&job_id).unwrap();
+        assert!(entry
+            .value()
+            .start_update(summary.resource_version)
+            .await
+            .is_ok());
+        assert!(matches!(
+            dispatcher
+                .update_dataflow(dataflow.clone(), summary.resource_version)
+                .await,
+            Err(DispatcherException::ResourceVersionConflict { .. })
+        ));
+        entry.value().abort_update();
+        assert!(dispatcher
+            .update_dataflow(dataflow, summary.resource_version)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_add_node_moves_operators() {
+        start_mock_task_manager(8843);
+        start_mock_task_manager(8844);
+        let (first, second, added) = (local_addr(8843), local_addr(8844), local_addr(8845));
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8843,127.0.0.1:8844")
+            .with_rebalance(&RebalanceConfig { enabled: true });
+        let job_id = ResourceId::new("default", "rebalance");
+        // a chain of operators placed on both nodes alternately
+        let dataflow = Dataflow {
+            job_id: Some(job_id.clone()),
+            meta: (0..32)
+                .map(|operator_id| DataflowMeta {
+                    center: operator_id,
+                    neighbors: if operator_id < 31 {
+                        vec![operator_id + 1]
+                    } else {
+                        vec![]
+                    },
+                    edge_types: Default::default(),
+                })
+                .collect(),
+            nodes: (0..32)
+                .map(|operator_id| {
+                    let host_addr = if operator_id % 2 == 0 {
+                        &first
+                    } else {
+                        &second
+                    };
+                    (
+                        operator_id,
+                        OperatorInfo {
+                            operator_id,
+                            host_addr: Some(host_addr.clone()),
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let created = match dispatcher.create_dataflow(dataflow, None).await {
+            Ok(placement) => placement,
+            Err(err) => panic!("unexpected error {}", err.to_tonic_status()),
+        };
+        dispatcher.heartbeat().await;
+
+        let err = dispatcher
+            .add_node(&NodeBuilder::from("127.0.0.1:8845"))
+            .await
+            .err()
+            .map(|err| err.to_tonic_status().code());
+        assert_eq!(err, Some(tonic::Code::Unavailable));
+        let err = dispatcher
+            .add_node(&NodeBuilder::from("127.0.0.1:8843"))
+            .await
+            .err()
+            .map(|err| err.to_tonic_status().code());
+        assert_eq!(err, Some(tonic::Code::AlreadyExists));
+
+        start_mock_task_manager(8845);
+        let (node, migrations) = match dispatcher
+            .add_node(&NodeBuilder::from("127.0.0.1:8845"))
+            .await
+        {
+            Ok(result) => result,
+            Err(err) => panic!("unexpected error {}", err.to_tonic_status()),
+        };
+        assert_eq!(node.host_addr.as_ref(), Some(&added));
+        assert_eq!(node.node_id, 2);
+        assert_eq!(node.health(), NodeHealth::Running);
+        assert_eq!(node.partitions, 1);
+
+        assert_eq!(migrations.len(), 1);
+        let migration = &migrations[0];
+        assert_eq!(migration.job_id.as_ref(), Some(&job_id));
+        assert_eq!(migration.error, "");
+        // some of the operators are moved onto the added node, the others stay where they are
+        assert!(!migration.operator_ids.is_empty());
+        assert!(migration.operator_ids.len() < 32);
+        let placement = migration.placement.clone().unwrap_or_default();
+        assert!(placement.is_started());
+        assert_eq!(placement.partitions.len(), 3);
+        for (operator_id, host_addr) in &placement.operators {
+            if migration.operator_ids.contains(operator_id) {
+                assert_eq!(host_addr, &added);
+            } else {
+                assert_eq!(Some(host_addr), created.operators.get(operator_id));
+            }
         }
+        // the dataflow is restored from a savepoint, so it takes the next resource version
+        assert_eq!(placement.resource_version, created.resource_version + 1);
+        assert_eq!(
+            dispatcher
+                .get_dataflow_status(&job_id, false)
+                .await
+                .ok()
+                .and_then(|status| status.summary)
+                .map(|summary| summary.resource_version),
+            Some(placement.resource_version)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_terminate_dataflows() {
+        start_mock_task_manager(8808);
+        // nothing listens on the second node
+        let (live, dead) = (local_addr(8808), local_addr(8809));
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8808,127.0.0.1:8809");
+        let new_job_id = |resource_id: &str| ResourceId::new("default", resource_id);
+        let (first, second, unreachable, unknown) = (
+            new_job_id("first"),
+            new_job_id("second"),
+            new_job_id("unreachable"),
+            new_job_id("unknown"),
+        );
+
+        for job_id in [&first, &second] {
+            assert!(dispatcher
+                .create_dataflow(new_partitioned_dataflow(job_id, &live, &live), None)
+                .await
+                .is_ok());
+        }
+        // a job whose partition is started on the dead node, so stopping it fails
+        let placement = DataflowPlacement {
+            job_id: Some(unreachable.clone()),
+            partitions: vec![PartitionPlacement {
+                execution_id: Some(SubDataflowId {
+                    job_id: Some(unreachable.clone()),
+                    sub_id: dispatcher.read_cluster().get_node(&dead).unwrap().get_id(),
+                }),
+                node: Some(dead.clone()),
+                operator_ids: vec![2],
+                status: PartitionStatus::Started as i32,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        dispatcher.managers.insert(
+            unreachable.clone(),
+            JobManager::recover(
+                &dispatcher.location,
+                new_partitioned_dataflow(&unreachable, &live, &dead),
+                placement,
+                &dispatcher.storage,
+                &dispatcher.read_cluster(),
+                &dispatcher.heartbeat,
+                &dispatcher.ack,
+            ),
+        );
+
+        let results = dispatcher
+            .terminate_dataflows(
+                &[
+                    first.clone(),
+                    unknown.clone(),
+                    unreachable.clone(),
+                    first.clone(),
+                    second.clone(),
+                ],
+                StopMode::Drain,
+            )
+            .await;
+        assert_eq!(
+            results
+                .iter()
+                .map(|(job_id, _)| job_id.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                first.clone(),
+                unknown.clone(),
+                unreachable.clone(),
+                second.clone()
+            ]
+        );
+        assert!(matches!(results[0].1, Ok(DataflowStatus::Closed)));
+        assert!(matches!(
+            &results[1].1,
+            Err(DispatcherException::NotFoundDataflow(job_id)) if job_id == &unknown
+        ));
+        match &results[2].1 {
+            Err(DispatcherException::TerminationFailed(failures)) => {
+                assert_eq!(failures.len(), 1);
+                assert_eq!(failures[0].node, Some(dead.clone()));
+                assert_eq!(
+                    failures[0].error.as_ref().map(|error| error.get_code()),
+                    Some(tonic::Code::Unavailable)
+                );
+            }
+            _ => panic!("unexpected result of job {:?}", &unreachable),
+        }
+        assert!(matches!(results[3].1, Ok(DataflowStatus::Closed)));
+
+        // the failed job is still managed, while the others are forgotten
+        assert!(dispatcher
+            .get_dataflow(&unreachable, false, false)
+            .await
+            .is_ok());
+        for job_id in [&first, &second] {
+            assert!(dispatcher.get_dataflow(job_id, false, false).await.is_err());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_get_dataflow_status() {
+        start_mock_task_manager(8815);
+        let live = local_addr(8815);
+        let dispatcher = new_dispatcher_with_nodes("127.0.0.1:8815");
+        let pending = ResourceId::new("default", "pending");
+        assert!(matches!(
+            dispatcher.get_dataflow_status(&pending, true).await,
+            Err(DispatcherException::NotFoundDataflow(_))
+        ));
+
+        // a dataflow saved before it's deployed has no runtime status yet
+        dispatcher
+            .storage
+            .lock()
+            .unwrap()
+            .save(&new_partitioned_dataflow(&pending, &live, &live))
+            .unwrap();
+        let status = dispatcher
+            .get_dataflow_status(&pending, true)
+            .await
+            .ok()
+            .unwrap();
+        let summary = status.summary.unwrap();
+        assert_eq!(summary.status(), DataflowStatus::Pending);
+        assert_eq!(summary.operator_count, 3);
+        assert_eq!(status.operators.len(), 3);
+        assert!(status
+            .operators
+            .iter()
+            .all(|operator| operator.status.is_none() && operator.host_addr == Some(live.clone())));
+        assert!(dispatcher
+            .get_dataflow(&pending, false, false)
+            .await
+            .ok()
+            .unwrap()
+            .graph
+            .is_some());
+
+        let job_id = ResourceId::new("default", "running");
+        assert!(dispatcher
+            .create_dataflow(new_partitioned_dataflow(&job_id, &live, &live), None)
+            .await
+            .is_ok());
+        let status = dispatcher
+            .get_dataflow_status(&job_id, false)
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(status.summary.unwrap().status(), DataflowStatus::Running);
+        assert!(status.operators.is_empty());
+
+        let status = dispatcher
+            .get_dataflow_status(&job_id, true)
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(
+            status
+                .operators
+                .iter()
+                .map(|operator| (operator.operator_id, operator.last_heartbeat_at))
+                .collect::<Vec<_>>(),
+            vec![(0, 0), (1, 1000), (2, 2000)]
+        );
+        assert!(status.operators.iter().all(|operator| {
+            operator.status == Some(ExecutorStatus::Running as i32)
+                && operator.restart_count == 1
+                && operator.host_addr == Some(live.clone())
+        }));
     }
 }
@@ -0,0 +1,286 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use common::{
+    snapshot::{SnapshotError, SnapshotStore},
+    utils,
+};
+use proto::{
+    common::{Dataflow, OperatorStates, ResourceId},
+    coordinator::Savepoint,
+};
+
+use crate::errors::coordinator::{not_found_savepoint, savepoint_err};
+
+use super::storage::{SharedDataflowStorage, StorageError};
+
+/// [`SavepointStorage`] keeps the savepoints of dataflows until they're deleted by users.
+/// Savepoints are uploaded to the snapshot store if it's configured, otherwise they're kept in the dataflow storage of the coordinator.
+pub(crate) enum SavepointStorage {
+    Remote(SnapshotStore),
+    Local(SharedDataflowStorage),
+}
+
+impl SavepointStorage {
+    /// save the operator states of a dataflow as a new savepoint
+    pub(crate) async fn save(
+        &self,
+        job_id: &ResourceId,
+        states: OperatorStates,
+    ) -> Result<Savepoint, SavepointError> {
+        let created_at = utils::times::now().timestamp_millis();
+        let savepoint_id = format!("savepoint_{}", created_at);
+        let mut operator_ids = states.states.keys().copied().collect::<Vec<_>>();
+        operator_ids.sort();
+
+        match self {
+            Self::Remote(store) => {
+                let states = states.states.into_iter().collect::<BTreeMap<_, _>>();
+                let path = store
+                    .upload_savepoint(job_id, &savepoint_id, &states)
+                    .await
+                    .map_err(SavepointError::Snapshot)?;
+                Ok(Savepoint {
+                    path,
+                    job_id: Some(job_id.clone()),
+                    created_at,
+                    operator_ids,
+                })
+            }
+            Self::Local(storage) => {
+                let savepoint = Savepoint {
+                    path: format!(
+                        "{}/{}/savepoints/{}",
+                        &job_id.namespace_id, &job_id.resource_id, savepoint_id
+                    ),
+                    job_id: Some(job_id.clone()),
+                    created_at,
+                    operator_ids,
+                };
+                storage
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .save_savepoint(&savepoint, &states)
+                    .map(|_| savepoint)
+                    .map_err(SavepointError::Storage)
+            }
+        }
+    }
+
+    /// the savepoints of a dataflow, the oldest first
+    pub(crate) async fn list(&self, job_id: &ResourceId) -> Result<Vec<Savepoint>, SavepointError> {
+        match self {
+            Self::Remote(store) => store
+                .list_savepoints(job_id)
+                .await
+                .map(|savepoints| {
+                    savepoints
+                        .into_iter()
+                        .map(|(path, manifest)| Savepoint {
+                            path,
+                            job_id: Some(job_id.clone()),
+                            created_at: manifest.created_at,
+                            operator_ids: manifest
+                                .artifacts
+                                .iter()
+                                .map(|artifact| artifact.operator_id)
+                                .collect(),
+                        })
+                        .collect()
+                })
+                .map_err(SavepointError::Snapshot),
+            Self::Local(storage) => Ok(storage
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .list_savepoints(job_id)),
+        }
+    }
+
+    /// the operator states of a savepoint. Savepoints of any dataflow can be loaded
+    pub(crate) async fn load(&self, path: &str) -> Result<OperatorStates, SavepointError> {
+        match self {
+            Self::Remote(store) => store
+                .download(path)
+                .await
+                .map(|checkpoint| OperatorStates {
+                    states: checkpoint.states.into_iter().collect(),
+                })
+                .map_err(|err| match err {
+                    SnapshotError::NotFound(_) => SavepointError::NotFound(path.to_string()),
+                    err => SavepointError::Snapshot(err),
+                }),
+            Self::Local(storage) => storage
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .get_savepoint_states(path)
+                .ok_or_else(|| SavepointError::NotFound(path.to_string())),
+        }
+    }
+
+    pub(crate) async fn delete(
+        &self,
+        job_id: &ResourceId,
+        path: &str,
+    ) -> Result<(), SavepointError> {
+        match self {
+            Self::Remote(store) => {
+                store
+                    .delete_savepoint(job_id, path)
+                    .await
+                    .map_err(|err| match err {
+                        SnapshotError::NotFound(_) => SavepointError::NotFound(path.to_string()),
+                        err => SavepointError::Snapshot(err),
+                    })
+            }
+            Self::Local(storage) => {
+                let mut storage = storage.lock().unwrap_or_else(|err| err.into_inner());
+                // savepoints of other dataflows can't be deleted
+                if !storage
+                    .list_savepoints(job_id)
+                    .iter()
+                    .any(|savepoint| savepoint.path == path)
+                {
+                    return Err(SavepointError::NotFound(path.to_string()));
+                }
+                match storage.delete_savepoint(path) {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err(SavepointError::NotFound(path.to_string())),
+                    Err(err) => Err(SavepointError::Storage(err)),
+                }
+            }
+        }
+    }
+}
+
+/// the warnings of restoring a dataflow from a savepoint. Operators are matched by their ids:
+/// - states of the operators which are removed from the dataflow are dropped
+/// - operators which are added to the dataflow start with empty states
+pub(crate) fn get_savepoint_warnings(
+    dataflow: &Dataflow,
+    savepoint: &OperatorStates,
+) -> Vec<String> {
+    let mut removed = savepoint
+        .states
+        .keys()
+        .filter(|operator_id| !dataflow.nodes.contains_key(operator_id))
+        .collect::<Vec<_>>();
+    removed.sort();
+    let mut added = dataflow
+        .nodes
+        .keys()
+        .filter(|operator_id| !savepoint.states.contains_key(operator_id))
+        .collect::<Vec<_>>();
+    added.sort();
+
+    removed
+        .into_iter()
+        .map(|operator_id| {
+            format!(
+                "operator {} of the savepoint is not in the dataflow, its state is dropped",
+                operator_id
+            )
+        })
+        .chain(added.into_iter().map(|operator_id| {
+            format!(
+                "operator {} is not in the savepoint, it starts with empty state",
+                operator_id
+            )
+        }))
+        .collect()
+}
+
+#[derive(Debug)]
+pub(crate) enum SavepointError {
+    NotFound(String),
+    Snapshot(SnapshotError),
+    Storage(StorageError),
+}
+
+impl SavepointError {
+    pub(crate) fn to_tonic_status(&self) -> tonic::Status {
+        match self {
+            SavepointError::NotFound(path) => not_found_savepoint(path).into_tonic_status(),
+            err => savepoint_err(err.to_string().as_str()).into_tonic_status(),
+        }
+    }
+}
+
+impl Display for SavepointError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SavepointError::NotFound(path) => write!(f, "savepoint {} is not found", path),
+            SavepointError::Snapshot(err) => write!(f, "savepoint fails: {}", err),
+            SavepointError::Storage(err) => write!(f, "savepoint fails: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proto::common::{Dataflow, OperatorInfo, OperatorStates, ResourceId};
+
+    use crate::coordinator::storage::DataflowStorageBuilder;
+
+    use super::{get_savepoint_warnings, SavepointStorage};
+
+    #[test]
+    fn test_get_savepoint_warnings() {
+        let dataflow = Dataflow {
+            nodes: [(1, OperatorInfo::default()), (2, OperatorInfo::default())].into(),
+            ..Default::default()
+        };
+        let savepoint = OperatorStates {
+            states: [(0, vec![0]), (1, vec![1])].into(),
+        };
+        assert_eq!(
+            get_savepoint_warnings(&dataflow, &savepoint),
+            vec![
+                "operator 0 of the savepoint is not in the dataflow, its state is dropped",
+                "operator 2 is not in the savepoint, it starts with empty state"
+            ]
+        );
+        assert!(get_savepoint_warnings(
+            &dataflow,
+            &OperatorStates {
+                states: [(1, vec![]), (2, vec![])].into()
+            }
+        )
+        .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_local_savepoint_storage() {
+        let storage = SavepointStorage::Local(
+            DataflowStorageBuilder::Memory {
+                ttl: None,
+                max_entries: None,
+            }
+            .build_shared(),
+        );
+        let job_id = ResourceId::new("default", "savepoint");
+        let other_job_id = ResourceId::new("default", "other");
+        let states = OperatorStates {
+            states: [(2, vec![2]), (1, vec![1])].into(),
+        };
+
+        let savepoint = storage.save(&job_id, states.clone()).await.unwrap();
+        assert!(savepoint
+            .path
+            .starts_with("default/savepoint/savepoints/savepoint_"));
+        assert_eq!(savepoint.operator_ids, vec![1, 2]);
+        assert_eq!(
+            storage.list(&job_id).await.unwrap(),
+            vec![savepoint.clone()]
+        );
+        assert_eq!(storage.load(&savepoint.path).await.unwrap(), states);
+
+        // a savepoint can only be deleted by its dataflow
+        assert!(storage
+            .delete(&other_job_id, &savepoint.path)
+            .await
+            .is_err());
+        assert!(storage.delete(&job_id, &savepoint.path).await.is_ok());
+        assert!(storage.list(&job_id).await.unwrap().is_empty());
+        assert!(storage.load(&savepoint.path).await.is_err());
+        assert!(storage.delete(&job_id, &savepoint.path).await.is_err());
+    }
+}
@@ -1,7 +1,10 @@
 use crossbeam_skiplist::SkipMap;
 use proto::common::{
-    Ack, Dataflow, DataflowStates, DataflowStatus, Heartbeat, SubDataflowId, SubdataflowInfo,
+    Ack, Dataflow, DataflowStates, DataflowStatus, ErrorDetail, Heartbeat, OperatorStates,
+    SubDataflowId, SubdataflowInfo,
 };
+use proto::coordinator::WorkerFailure;
+use proto::taskmanager::StopMode;
 
 use super::executions::{
     SubdataflowDeploymentPlan, SubdataflowError, SubdataflowExecution, TaskDeploymentException,
@@ -9,7 +12,7 @@ use super::executions::{
 
 /// The scheduler for a [`JobManager`].
 pub(crate) struct Scheduler {
-    executions: SkipMap<SubDataflowId, SubdataflowExecution>,
+    pub(super) executions: SkipMap<SubDataflowId, SubdataflowExecution>,
 }
 
 impl Scheduler {
@@ -29,14 +32,34 @@ impl Scheduler {
         })
     }
 
+    /// manage an execution which has been deployed, e.g. after the coordinator restarts
+    pub(crate) fn resume(&self, execution: SubdataflowExecution) {
+        self.executions
+            .insert(execution.get_execution_id().clone(), execution);
+    }
+
+    /// stop all executions. The dataflow is closed once all of them are stopped
     pub(crate) async fn terminate_dataflow(
         &self,
+        mode: StopMode,
     ) -> Result<DataflowStatus, TaskExecutionException> {
+        let mut failures = vec![];
         for entry in self.executions.iter() {
-            entry.value().try_terminate();
+            if let Err(err) = entry.value().try_terminate(mode).await {
+                tracing::error!("terminate subdataflow {:?} failed: {:?}", entry.key(), err);
+                let SubdataflowError::RpcError(status) = err;
+                failures.push(WorkerFailure {
+                    node: Some(entry.value().get_host_addr().clone()),
+                    error: Some(ErrorDetail::from_status(&status)),
+                })
+            }
         }
 
-        Ok(DataflowStatus::Closing)
+        if failures.is_empty() {
+            Ok(DataflowStatus::Closed)
+        } else {
+            Err(TaskExecutionException::WorkerFailures(failures))
+        }
     }
 
     pub(crate) async fn receive_heartbeat(&self, heartbeat: &Heartbeat) {
@@ -49,15 +72,40 @@ impl Scheduler {
         }
     }
 
+    /// trigger a savepoint on every execution and merge their operator states. It fails if any execution fails
+    pub(crate) async fn trigger_savepoint(&self) -> Result<OperatorStates, TaskExecutionException> {
+        let mut savepoint = OperatorStates::default();
+        let mut errors = vec![];
+        for entry in self.executions.iter() {
+            match entry.value().trigger_savepoint().await {
+                Ok(states) => savepoint.states.extend(states.states),
+                Err(err) => {
+                    tracing::error!(
+                        "trigger savepoint of subdataflow {:?} failed: {:?}",
+                        entry.key(),
+                        err
+                    );
+                    errors.push(err)
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(savepoint)
+        } else {
+            Err(TaskExecutionException::SubdataflowErrors(errors))
+        }
+    }
+
     pub(crate) fn ack(&self, ack: &Ack) {
         todo!()
     }
 
+    /// the runtime states of the executions. The status of the dataflow is left to its job manager
     pub async fn get_dataflow(&self, dataflow: &Dataflow) -> DataflowStates {
         let mut states = DataflowStates {
             graph: Some(dataflow.clone()),
-            subdataflow_infos: vec![],
-            status: DataflowStatus::Initialized as i32,
+            ..Default::default()
         };
 
         for entry in &self.executions {
@@ -73,7 +121,6 @@ impl Scheduler {
                         entry.key(),
                         err
                     );
-                    states.set_status(DataflowStatus::Running);
                     states.subdataflow_infos.push(SubdataflowInfo {
                         execution_id: Some(entry.key().clone()),
                         executors_info: Default::default(),
@@ -89,10 +136,35 @@ impl Scheduler {
 #[derive(Debug)]
 pub(crate) enum TaskExecutionException {
     SubdataflowErrors(Vec<SubdataflowError>),
+    /// some TaskManagers fail to stop the subdataflows
+    WorkerFailures(Vec<WorkerFailure>),
 }
 
 impl TaskExecutionException {
     pub(crate) fn to_tonic_status(&self) -> tonic::Status {
-        todo!()
+        match self {
+            TaskExecutionException::SubdataflowErrors(errors) => {
+                let message = errors
+                    .iter()
+                    .map(|err| match err {
+                        SubdataflowError::RpcError(status) => status.message().to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                tonic::Status::internal(format!("subdataflows fail: {message}"))
+            }
+            TaskExecutionException::WorkerFailures(failures) => {
+                let message = failures
+                    .iter()
+                    .map(|failure| {
+                        let node = failure.node.clone().unwrap_or_default();
+                        let error = failure.error.clone().unwrap_or_default();
+                        format!("{}:{} [{}]", node.host, node.port, error.message)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                tonic::Status::aborted(format!("subdataflows fail to stop: {message}"))
+            }
+        }
     }
 }
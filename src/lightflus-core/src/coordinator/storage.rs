@@ -1,13 +1,85 @@
-use std::{collections::BTreeMap, fmt::Display};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use common::utils;
 use prost::Message;
-use proto::common::{Dataflow, ResourceId};
+use proto::{
+    common::{Dataflow, DataflowPlacement, OperatorStates, ResourceId},
+    coordinator::Savepoint,
+};
+use serde::de::value::{MapAccessDeserializer, StrDeserializer};
 
+/// the sled tree which placements are stored in
+const PLACEMENT_TREE: &str = "placements";
+/// the sled tree which savepoints are stored in
+const SAVEPOINT_TREE: &str = "savepoints";
+/// the sled tree which the operator states of savepoints are stored in
+const SAVEPOINT_STATES_TREE: &str = "savepoint_states";
+
+/// a storage shared by all job managers of a coordinator
+pub(crate) type SharedDataflowStorage = Arc<Mutex<Box<dyn DataflowStorage>>>;
+
+/// The dataflow storage of the config. A bare `"Memory"` is an unbounded memory storage, so the configs written before the memory storage had bounds still work
 #[derive(serde::Deserialize, Clone, Debug)]
+#[serde(remote = "Self")]
 pub enum DataflowStorageBuilder {
-    Local { dataflow_store_path: String },
-    Memory,
+    /// Dataflows are stored in a sled database on the local disk.
+    /// - `durability`: whether the writes are flushed before they return, see [`Durability`]. It's `Strict` if it's absent.
+    Local {
+        dataflow_store_path: String,
+        #[serde(default)]
+        durability: Durability,
+    },
+    /// Dataflows are stored in memory. It's for test and development.
+    /// - `ttl`: seconds after which a saved dataflow expires. Dataflows never expire if it's absent.
+    /// - `max_entries`: the max number of dataflows. The least recently used one will be evicted if it's exceeded. Unbounded if it's absent.
+    Memory {
+        ttl: Option<u64>,
+        max_entries: Option<usize>,
+    },
+}
+
+impl<'de> serde::Deserialize<'de> for DataflowStorageBuilder {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct BuilderVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for BuilderVisitor {
+            type Value = DataflowStorageBuilder;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a dataflow storage")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                match v {
+                    "Memory" => Ok(DataflowStorageBuilder::Memory {
+                        ttl: None,
+                        max_entries: None,
+                    }),
+                    _ => DataflowStorageBuilder::deserialize(StrDeserializer::new(v)),
+                }
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                DataflowStorageBuilder::deserialize(MapAccessDeserializer::new(map))
+            }
+        }
+
+        deserializer.deserialize_any(BuilderVisitor)
+    }
 }
 
 impl DataflowStorageBuilder {
@@ -15,28 +87,106 @@ impl DataflowStorageBuilder {
         match self {
             Self::Local {
                 dataflow_store_path,
-            } => Box::new(LocalDataflowStorage::new(dataflow_store_path)),
-            Self::Memory => Box::new(MemDataflowStorage::default()),
+                durability,
+            } => Box::new(LocalDataflowStorage::new(dataflow_store_path, *durability)),
+            Self::Memory { ttl, max_entries } => Box::new(MemDataflowStorage::new(
+                ttl.map(Duration::from_secs),
+                *max_entries,
+            )),
         }
     }
+
+    pub(crate) fn build_shared(&self) -> SharedDataflowStorage {
+        Arc::new(Mutex::new(self.build()))
+    }
+}
+
+/// how the writes of the local storage are made durable
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// writes are left to the background flush of sled, so the latest ones may be lost if the process crashes. It's for test and development
+    Relaxed,
+    /// every write is flushed to the disk before it returns
+    #[default]
+    Strict,
 }
 
 pub trait DataflowStorage: Send + Sync {
     fn save(&mut self, dataflow: &Dataflow) -> Result<(), StorageError>;
     fn get(&self, job_id: &ResourceId) -> Option<Dataflow>;
     fn may_exists(&self, job_id: &ResourceId) -> bool;
+    /// delete the dataflow and its placement
     fn delete(&mut self, job_id: &ResourceId) -> Result<(), StorageError>;
+    /// save where the operators of a dataflow are placed. It's saved after the dataflow is deployed
+    fn save_placement(&mut self, placement: &DataflowPlacement) -> Result<(), StorageError>;
+    fn get_placement(&self, job_id: &ResourceId) -> Option<DataflowPlacement>;
+    /// all saved dataflows, ordered by their namespaces and then their resource ids whatever the backend is
+    fn list(&self) -> Vec<Dataflow>;
+    /// save a savepoint and its operator states. Savepoints are kept until they're deleted, even if their dataflows are deleted
+    fn save_savepoint(
+        &mut self,
+        savepoint: &Savepoint,
+        states: &OperatorStates,
+    ) -> Result<(), StorageError>;
+    fn get_savepoint_states(&self, path: &str) -> Option<OperatorStates>;
+    /// the savepoints of a dataflow, the oldest first
+    fn list_savepoints(&self, job_id: &ResourceId) -> Vec<Savepoint>;
+    /// delete a savepoint, returns whether it exists
+    fn delete_savepoint(&mut self, path: &str) -> Result<bool, StorageError>;
+    /// check whether the storage is reachable
+    fn probe(&self) -> Result<(), StorageError>;
+}
+
+/// sort the listed dataflows by their namespaces and then their resource ids. Neither the keys of sled, which are the job ids in protobuf,
+/// nor the derived order of [`ResourceId`], which compares the resource ids first, are in this order
+fn sort_dataflows(dataflows: &mut [Dataflow]) {
+    fn key(dataflow: &Dataflow) -> Option<(&str, &str)> {
+        dataflow
+            .job_id
+            .as_ref()
+            .map(|job_id| (job_id.namespace_id.as_str(), job_id.resource_id.as_str()))
+    }
+    dataflows.sort_by(|a, b| key(a).cmp(&key(b)))
 }
 
 #[derive(Clone, Debug)]
 pub(crate) struct LocalDataflowStorage {
     db: sled::Db,
+    placements: sled::Tree,
+    savepoints: sled::Tree,
+    savepoint_states: sled::Tree,
+    durability: Durability,
+    /// the number of writes which have been flushed
+    flushes: u64,
 }
 
 impl LocalDataflowStorage {
-    pub fn new<P: AsRef<std::path::Path>>(path: P) -> Self {
+    pub fn new<P: AsRef<std::path::Path>>(path: P, durability: Durability) -> Self {
+        let db = sled::open(path).expect("open sleddb failed");
+        let placements = db
+            .open_tree(PLACEMENT_TREE)
+            .expect("open placement tree failed");
+        let savepoints = db
+            .open_tree(SAVEPOINT_TREE)
+            .expect("open savepoint tree failed");
+        let savepoint_states = db
+            .open_tree(SAVEPOINT_STATES_TREE)
+            .expect("open savepoint states tree failed");
         Self {
-            db: sled::open(path).expect("open sleddb failed"),
+            db,
+            placements,
+            savepoints,
+            savepoint_states,
+            durability,
+            flushes: 0,
+        }
+    }
+
+    /// flush the written trees if the durability is strict. sled flushes all trees of a database at once
+    fn flush(&mut self) -> sled::Result<()> {
+        match self.durability {
+            Durability::Relaxed => Ok(()),
+            Durability::Strict => self.db.flush().map(|_| self.flushes += 1),
         }
     }
 }
@@ -52,7 +202,7 @@ impl DataflowStorage for LocalDataflowStorage {
                     .unwrap_or_default(),
                 dataflow.encode_to_vec(),
             )
-            .map(|_| {})
+            .and_then(|_| self.flush())
             .map_err(|err| StorageError::SaveDataflowFailed(err))
     }
 
@@ -78,43 +228,353 @@ impl DataflowStorage for LocalDataflowStorage {
     }
 
     fn delete(&mut self, job_id: &ResourceId) -> Result<(), StorageError> {
-        self.db
+        self.placements
             .remove(job_id.encode_to_vec())
+            .and_then(|_| self.db.remove(job_id.encode_to_vec()))
             .map(|_| {})
             .map_err(|err| StorageError::DeleteDataflowFailed(err))
     }
+
+    fn save_placement(&mut self, placement: &DataflowPlacement) -> Result<(), StorageError> {
+        self.placements
+            .insert(
+                placement
+                    .job_id
+                    .as_ref()
+                    .map(|key| key.encode_to_vec())
+                    .unwrap_or_default(),
+                placement.encode_to_vec(),
+            )
+            .and_then(|_| self.flush())
+            .map_err(|err| StorageError::SavePlacementFailed(err))
+    }
+
+    fn get_placement(&self, job_id: &ResourceId) -> Option<DataflowPlacement> {
+        match self
+            .placements
+            .get(&job_id.encode_to_vec())
+            .map(|data| data.and_then(|buf| utils::from_pb_slice(&buf).ok()))
+        {
+            Ok(result) => result,
+            Err(err) => {
+                tracing::error!("get placement of {:?} failed because: {}", job_id, err);
+                None
+            }
+        }
+    }
+
+    fn list(&self) -> Vec<Dataflow> {
+        let mut dataflows = self
+            .db
+            .iter()
+            .values()
+            .filter_map(|value| match value {
+                Ok(buf) => utils::from_pb_slice(&buf).ok(),
+                Err(err) => {
+                    tracing::error!("list dataflows failed because: {}", err);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        sort_dataflows(&mut dataflows);
+        dataflows
+    }
+
+    fn save_savepoint(
+        &mut self,
+        savepoint: &Savepoint,
+        states: &OperatorStates,
+    ) -> Result<(), StorageError> {
+        // states are saved first so that a listed savepoint always has its states
+        self.savepoint_states
+            .insert(savepoint.path.as_bytes(), states.encode_to_vec())
+            .and_then(|_| {
+                self.savepoints
+                    .insert(savepoint.path.as_bytes(), savepoint.encode_to_vec())
+            })
+            .and_then(|_| self.flush())
+            .map_err(|err| StorageError::SaveSavepointFailed(err))
+    }
+
+    fn get_savepoint_states(&self, path: &str) -> Option<OperatorStates> {
+        match self
+            .savepoint_states
+            .get(path.as_bytes())
+            .map(|data| data.and_then(|buf| utils::from_pb_slice(&buf).ok()))
+        {
+            Ok(result) => result,
+            Err(err) => {
+                tracing::error!("get savepoint {} failed because: {}", path, err);
+                None
+            }
+        }
+    }
+
+    fn list_savepoints(&self, job_id: &ResourceId) -> Vec<Savepoint> {
+        let mut savepoints = self
+            .savepoints
+            .iter()
+            .values()
+            .filter_map(|value| match value {
+                Ok(buf) => utils::from_pb_slice::<Savepoint>(&buf).ok(),
+                Err(err) => {
+                    tracing::error!("list savepoints failed because: {}", err);
+                    None
+                }
+            })
+            .filter(|savepoint| savepoint.job_id.as_ref() == Some(job_id))
+            .collect::<Vec<_>>();
+        savepoints.sort_by_key(|savepoint| savepoint.created_at);
+        savepoints
+    }
+
+    fn delete_savepoint(&mut self, path: &str) -> Result<bool, StorageError> {
+        self.savepoints
+            .remove(path.as_bytes())
+            .and_then(|savepoint| {
+                self.savepoint_states
+                    .remove(path.as_bytes())
+                    .map(|_| savepoint.is_some())
+            })
+            .map_err(|err| StorageError::DeleteSavepointFailed(err))
+    }
+
+    fn probe(&self) -> Result<(), StorageError> {
+        self.db
+            .first()
+            .map(|_| ())
+            .map_err(StorageError::ProbeFailed)
+    }
 }
 
-#[derive(Clone, Debug, Default)]
+/// In-memory dataflow storage with optional TTL expiry and LRU eviction.
+/// Expired dataflows are invisible to readers and are purged when a new dataflow is saved.
+#[derive(Debug, Default)]
 pub(crate) struct MemDataflowStorage {
-    cache: BTreeMap<ResourceId, Dataflow>,
+    ttl: Option<Duration>,
+    max_entries: Option<usize>,
+    cache: Mutex<LruCache>,
+    /// savepoints are not subject to ttl and eviction
+    savepoints: BTreeMap<String, (Savepoint, OperatorStates)>,
+}
+
+impl MemDataflowStorage {
+    pub(crate) fn new(ttl: Option<Duration>, max_entries: Option<usize>) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            cache: Default::default(),
+            savepoints: Default::default(),
+        }
+    }
+
+    fn is_expired(&self, entry: &CacheEntry) -> bool {
+        self.ttl
+            .map(|ttl| entry.saved_at.elapsed() >= ttl)
+            .unwrap_or(false)
+    }
 }
 
 impl DataflowStorage for MemDataflowStorage {
     fn save(&mut self, dataflow: &Dataflow) -> Result<(), StorageError> {
-        self.cache.insert(dataflow.get_job_id(), dataflow.clone());
+        let ttl = self.ttl;
+        let max_entries = self.max_entries;
+        let cache = self.cache.get_mut().unwrap_or_else(|err| err.into_inner());
+        ttl.iter().for_each(|ttl| cache.expire(*ttl));
+        cache.insert(dataflow.get_job_id(), dataflow.clone());
+        max_entries.iter().for_each(|max_entries| {
+            while cache.entries.len() > *max_entries {
+                cache.pop_lru();
+            }
+        });
         Ok(())
     }
 
     fn get(&self, job_id: &ResourceId) -> Option<Dataflow> {
-        self.cache.get(job_id).map(|dataflow| dataflow.clone())
+        let mut cache = self.cache.lock().unwrap_or_else(|err| err.into_inner());
+        match cache.entries.get(job_id) {
+            Some(entry) if self.is_expired(entry) => {
+                cache.remove(job_id);
+                None
+            }
+            Some(_) => cache.touch(job_id).map(|entry| entry.dataflow.clone()),
+            None => None,
+        }
     }
 
     fn may_exists(&self, job_id: &ResourceId) -> bool {
-        self.cache.contains_key(job_id)
+        self.cache
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .entries
+            .get(job_id)
+            .map(|entry| !self.is_expired(entry))
+            .unwrap_or(false)
     }
 
     fn delete(&mut self, job_id: &ResourceId) -> Result<(), StorageError> {
-        self.cache.remove(job_id);
+        self.cache
+            .get_mut()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(job_id);
+        Ok(())
+    }
+
+    fn save_placement(&mut self, placement: &DataflowPlacement) -> Result<(), StorageError> {
+        let job_id = placement.job_id.clone().unwrap_or_default();
+        let cache = self.cache.get_mut().unwrap_or_else(|err| err.into_inner());
+        // the placement is dropped if the dataflow has been evicted
+        if let Some(entry) = cache.entries.get_mut(&job_id) {
+            entry.placement = Some(placement.clone());
+        }
+        Ok(())
+    }
+
+    fn get_placement(&self, job_id: &ResourceId) -> Option<DataflowPlacement> {
+        self.cache
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .entries
+            .get(job_id)
+            .filter(|entry| !self.is_expired(entry))
+            .and_then(|entry| entry.placement.clone())
+    }
+
+    fn list(&self) -> Vec<Dataflow> {
+        let mut dataflows = self
+            .cache
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .entries
+            .values()
+            .filter(|entry| !self.is_expired(entry))
+            .map(|entry| entry.dataflow.clone())
+            .collect::<Vec<_>>();
+        sort_dataflows(&mut dataflows);
+        dataflows
+    }
+
+    fn save_savepoint(
+        &mut self,
+        savepoint: &Savepoint,
+        states: &OperatorStates,
+    ) -> Result<(), StorageError> {
+        self.savepoints
+            .insert(savepoint.path.clone(), (savepoint.clone(), states.clone()));
+        Ok(())
+    }
+
+    fn get_savepoint_states(&self, path: &str) -> Option<OperatorStates> {
+        self.savepoints.get(path).map(|(_, states)| states.clone())
+    }
+
+    fn list_savepoints(&self, job_id: &ResourceId) -> Vec<Savepoint> {
+        let mut savepoints = self
+            .savepoints
+            .values()
+            .map(|(savepoint, _)| savepoint)
+            .filter(|savepoint| savepoint.job_id.as_ref() == Some(job_id))
+            .cloned()
+            .collect::<Vec<_>>();
+        savepoints.sort_by_key(|savepoint| savepoint.created_at);
+        savepoints
+    }
+
+    fn delete_savepoint(&mut self, path: &str) -> Result<bool, StorageError> {
+        Ok(self.savepoints.remove(path).is_some())
+    }
+
+    fn probe(&self) -> Result<(), StorageError> {
         Ok(())
     }
 }
 
+#[derive(Debug)]
+struct CacheEntry {
+    dataflow: Dataflow,
+    placement: Option<DataflowPlacement>,
+    saved_at: Instant,
+    /// the sequence number of the latest access
+    seq: u64,
+}
+
+#[derive(Debug, Default)]
+struct LruCache {
+    entries: BTreeMap<ResourceId, CacheEntry>,
+    /// access sequence number -> job id. The first one is the least recently used.
+    recency: BTreeMap<u64, ResourceId>,
+    seq: u64,
+}
+
+impl LruCache {
+    fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    fn insert(&mut self, job_id: ResourceId, dataflow: Dataflow) {
+        self.remove(&job_id);
+        let seq = self.next_seq();
+        self.recency.insert(seq, job_id.clone());
+        self.entries.insert(
+            job_id,
+            CacheEntry {
+                dataflow,
+                placement: None,
+                saved_at: Instant::now(),
+                seq,
+            },
+        );
+    }
+
+    /// mark the entry as the most recently used
+    fn touch(&mut self, job_id: &ResourceId) -> Option<&CacheEntry> {
+        let seq = self.next_seq();
+        match self.entries.get_mut(job_id) {
+            Some(entry) => {
+                self.recency.remove(&entry.seq);
+                self.recency.insert(seq, job_id.clone());
+                entry.seq = seq;
+                Some(entry)
+            }
+            None => None,
+        }
+    }
+
+    fn remove(&mut self, job_id: &ResourceId) -> Option<CacheEntry> {
+        self.entries.remove(job_id).map(|entry| {
+            self.recency.remove(&entry.seq);
+            entry
+        })
+    }
+
+    fn pop_lru(&mut self) -> Option<CacheEntry> {
+        let job_id = self.recency.values().next().cloned();
+        job_id.and_then(|job_id| self.remove(&job_id))
+    }
+
+    fn expire(&mut self, ttl: Duration) {
+        self.entries
+            .retain(|_, entry| entry.saved_at.elapsed() < ttl);
+        let entries = &self.entries;
+        self.recency.retain(|seq, job_id| {
+            entries
+                .get(job_id)
+                .map(|entry| entry.seq == *seq)
+                .unwrap_or(false)
+        });
+    }
+}
+
 #[derive(Debug)]
 pub enum StorageError {
     SaveDataflowFailed(sled::Error),
     DeleteDataflowFailed(sled::Error),
     GetDataflowFailed(sled::Error),
+    SavePlacementFailed(sled::Error),
+    SaveSavepointFailed(sled::Error),
+    DeleteSavepointFailed(sled::Error),
+    ProbeFailed(sled::Error),
 }
 
 impl Display for StorageError {
@@ -129,6 +589,360 @@ impl Display for StorageError {
             StorageError::GetDataflowFailed(err) => {
                 f.write_fmt(format_args!("get dataflow failed: {}", err))
             }
+            StorageError::SavePlacementFailed(err) => {
+                f.write_fmt(format_args!("save placement failed: {}", err))
+            }
+            StorageError::SaveSavepointFailed(err) => {
+                f.write_fmt(format_args!("save savepoint failed: {}", err))
+            }
+            StorageError::DeleteSavepointFailed(err) => {
+                f.write_fmt(format_args!("delete savepoint failed: {}", err))
+            }
+            StorageError::ProbeFailed(err) => {
+                f.write_fmt(format_args!("probe storage failed: {}", err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use proto::{
+        common::{Dataflow, DataflowPlacement, HostAddr, OperatorStates, ResourceId},
+        coordinator::Savepoint,
+    };
+
+    use super::{
+        DataflowStorage, DataflowStorageBuilder, Durability, LocalDataflowStorage,
+        MemDataflowStorage,
+    };
+
+    fn new_dataflow(resource_id: &str) -> Dataflow {
+        Dataflow {
+            job_id: Some(ResourceId::new("default", resource_id.to_string())),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mem_storage_lru_eviction() {
+        let mut storage = MemDataflowStorage::new(None, Some(2));
+        let (job_1, job_2, job_3) = (new_dataflow("1"), new_dataflow("2"), new_dataflow("3"));
+
+        assert!(storage.save(&job_1).is_ok());
+        assert!(storage.save(&job_2).is_ok());
+        // job_1 becomes the most recently used, so job_2 will be evicted
+        assert_eq!(storage.get(&job_1.get_job_id()), Some(job_1.clone()));
+        assert!(storage.save(&job_3).is_ok());
+
+        assert!(storage.may_exists(&job_1.get_job_id()));
+        assert!(!storage.may_exists(&job_2.get_job_id()));
+        assert!(storage.may_exists(&job_3.get_job_id()));
+        assert_eq!(storage.get(&job_2.get_job_id()), None);
+
+        // saving an existing dataflow does not evict others
+        assert!(storage.save(&job_3).is_ok());
+        assert!(storage.may_exists(&job_1.get_job_id()));
+
+        assert!(storage.delete(&job_1.get_job_id()).is_ok());
+        assert!(storage.save(&job_2).is_ok());
+        assert!(storage.may_exists(&job_2.get_job_id()));
+        assert!(storage.may_exists(&job_3.get_job_id()));
+    }
+
+    #[test]
+    fn test_mem_storage_ttl_expiry() {
+        let mut storage = MemDataflowStorage::new(Some(Duration::from_millis(50)), None);
+        let (job_1, job_2) = (new_dataflow("1"), new_dataflow("2"));
+
+        assert!(storage.save(&job_1).is_ok());
+        assert_eq!(storage.get(&job_1.get_job_id()), Some(job_1.clone()));
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(!storage.may_exists(&job_1.get_job_id()));
+        assert_eq!(storage.get(&job_1.get_job_id()), None);
+
+        // saving again refreshes the ttl
+        assert!(storage.save(&job_1).is_ok());
+        assert!(storage.save(&job_2).is_ok());
+        assert!(storage.may_exists(&job_1.get_job_id()));
+        assert_eq!(storage.cache.lock().unwrap().entries.len(), 2);
+
+        // expired dataflows are purged when a new one is saved
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(storage.save(&new_dataflow("3")).is_ok());
+        let cache = storage.cache.lock().unwrap();
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(cache.recency.len(), 1);
+    }
+
+    #[test]
+    fn test_mem_storage_unbounded() {
+        let mut storage = MemDataflowStorage::default();
+        for id in 0..100 {
+            assert!(storage.save(&new_dataflow(&id.to_string())).is_ok());
+        }
+        assert!(storage.may_exists(&new_dataflow("0").get_job_id()));
+        assert_eq!(storage.cache.lock().unwrap().entries.len(), 100);
+    }
+
+    fn new_placement(dataflow: &Dataflow) -> DataflowPlacement {
+        DataflowPlacement {
+            job_id: dataflow.job_id.clone(),
+            operators: [(
+                0,
+                HostAddr {
+                    host: "localhost".to_string(),
+                    port: 8792,
+                },
+            )]
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_storage_builder_from_config() {
+        // a bare `"Memory"` is an unbounded memory storage
+        let builder: DataflowStorageBuilder = serde_json::from_str(r#""Memory""#).unwrap();
+        assert!(matches!(
+            builder,
+            DataflowStorageBuilder::Memory {
+                ttl: None,
+                max_entries: None
+            }
+        ));
+
+        let builder: DataflowStorageBuilder =
+            serde_json::from_value(serde_json::json!({ "Memory": { "ttl": 60 } })).unwrap();
+        assert!(matches!(
+            builder,
+            DataflowStorageBuilder::Memory {
+                ttl: Some(60),
+                max_entries: None
+            }
+        ));
+
+        let builder: DataflowStorageBuilder = serde_json::from_value(serde_json::json!({
+            "Local": { "dataflow_store_path": "/tmp/lightflus/dataflow" }
+        }))
+        .unwrap();
+        assert!(matches!(
+            builder,
+            DataflowStorageBuilder::Local {
+                durability: Durability::Strict,
+                ..
+            }
+        ));
+
+        // the local storage can't be bare since it has a mandatory path
+        assert!(serde_json::from_str::<DataflowStorageBuilder>(r#""Local""#).is_err());
+        assert!(serde_json::from_str::<DataflowStorageBuilder>(r#""Redis""#).is_err());
+    }
+
+    #[test]
+    fn test_local_storage_placement() {
+        let path = std::env::temp_dir().join(format!(
+            "lightflus-storage-{}",
+            common::utils::times::now_timestamp()
+        ));
+        let (job_1, job_2) = (new_dataflow("1"), new_dataflow("2"));
+        let placement = new_placement(&job_1);
+
+        {
+            let mut storage = LocalDataflowStorage::new(&path, Durability::Strict);
+            assert!(storage.save(&job_1).is_ok());
+            assert!(storage.save(&job_2).is_ok());
+            assert!(storage.save_placement(&placement).is_ok());
         }
+
+        let mut storage = LocalDataflowStorage::new(&path, Durability::Strict);
+        let mut dataflows = storage.list();
+        dataflows.sort_by_key(|dataflow| dataflow.get_job_id().resource_id);
+        assert_eq!(dataflows, vec![job_1.clone(), job_2.clone()]);
+        assert_eq!(
+            storage.get_placement(&job_1.get_job_id()),
+            Some(placement.clone())
+        );
+        assert_eq!(storage.get_placement(&job_2.get_job_id()), None);
+
+        assert!(storage.delete(&job_1.get_job_id()).is_ok());
+        assert_eq!(storage.get_placement(&job_1.get_job_id()), None);
+        assert_eq!(storage.list(), vec![job_2]);
+        drop(storage);
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn test_mem_storage_placement() {
+        let mut storage = MemDataflowStorage::new(None, Some(1));
+        let (job_1, job_2) = (new_dataflow("1"), new_dataflow("2"));
+        let placement = new_placement(&job_1);
+
+        assert!(storage.save(&job_1).is_ok());
+        assert!(storage.save_placement(&placement).is_ok());
+        assert_eq!(storage.get_placement(&job_1.get_job_id()), Some(placement));
+
+        // saving the dataflow again resets its placement
+        assert!(storage.save(&job_1).is_ok());
+        assert_eq!(storage.get_placement(&job_1.get_job_id()), None);
+
+        // the placement of an evicted dataflow is dropped
+        assert!(storage.save(&job_2).is_ok());
+        assert!(storage.save_placement(&new_placement(&job_1)).is_ok());
+        assert_eq!(storage.get_placement(&job_1.get_job_id()), None);
+        assert_eq!(storage.list(), vec![job_2]);
+    }
+
+    fn new_savepoint(dataflow: &Dataflow, created_at: i64) -> (Savepoint, OperatorStates) {
+        (
+            Savepoint {
+                path: format!(
+                    "default/{}/savepoints/savepoint_{created_at}",
+                    dataflow.get_job_id().resource_id
+                ),
+                job_id: dataflow.job_id.clone(),
+                created_at,
+                operator_ids: vec![0],
+            },
+            OperatorStates {
+                states: [(0, created_at.to_be_bytes().to_vec())].into(),
+            },
+        )
+    }
+
+    fn assert_savepoints(storage: &mut dyn DataflowStorage) {
+        let (job_1, job_2) = (new_dataflow("1"), new_dataflow("2"));
+        let (savepoint_1, states_1) = new_savepoint(&job_1, 2);
+        let (savepoint_2, states_2) = new_savepoint(&job_1, 1);
+        let (savepoint_3, states_3) = new_savepoint(&job_2, 3);
+
+        assert!(storage.save(&job_1).is_ok());
+        assert!(storage.save_savepoint(&savepoint_1, &states_1).is_ok());
+        assert!(storage.save_savepoint(&savepoint_2, &states_2).is_ok());
+        assert!(storage.save_savepoint(&savepoint_3, &states_3).is_ok());
+
+        assert_eq!(
+            storage.list_savepoints(&job_1.get_job_id()),
+            vec![savepoint_2.clone(), savepoint_1.clone()]
+        );
+        assert_eq!(
+            storage.get_savepoint_states(&savepoint_1.path),
+            Some(states_1)
+        );
+
+        // savepoints survive the deletion of their dataflows
+        assert!(storage.delete(&job_1.get_job_id()).is_ok());
+        assert_eq!(storage.list_savepoints(&job_1.get_job_id()).len(), 2);
+
+        assert_eq!(storage.delete_savepoint(&savepoint_2.path).ok(), Some(true));
+        assert_eq!(
+            storage.delete_savepoint(&savepoint_2.path).ok(),
+            Some(false)
+        );
+        assert_eq!(storage.get_savepoint_states(&savepoint_2.path), None);
+        assert_eq!(
+            storage.list_savepoints(&job_1.get_job_id()),
+            vec![savepoint_1]
+        );
+        assert_eq!(
+            storage.list_savepoints(&job_2.get_job_id()),
+            vec![savepoint_3]
+        );
+    }
+
+    #[test]
+    fn test_storage_savepoints() {
+        assert_savepoints(&mut MemDataflowStorage::new(
+            Some(Duration::from_millis(1)),
+            Some(1),
+        ));
+
+        let path = std::env::temp_dir().join(format!(
+            "lightflus-savepoints-{}",
+            common::utils::times::now_timestamp()
+        ));
+        assert_savepoints(&mut LocalDataflowStorage::new(&path, Durability::Relaxed));
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn test_local_storage_durability() {
+        let dataflow = new_dataflow("1");
+        let (savepoint, states) = new_savepoint(&dataflow, 1);
+        for (durability, flushes) in [(Durability::Strict, 3), (Durability::Relaxed, 0)] {
+            let path = std::env::temp_dir().join(format!(
+                "lightflus-durability-{:?}-{}",
+                durability,
+                common::utils::times::now_timestamp()
+            ));
+            let mut storage = LocalDataflowStorage::new(&path, durability);
+            assert!(storage.save(&dataflow).is_ok());
+            assert!(storage.save_placement(&new_placement(&dataflow)).is_ok());
+            assert!(storage.save_savepoint(&savepoint, &states).is_ok());
+            assert_eq!(storage.flushes, flushes);
+
+            // the writes are readable whether they are flushed or not
+            assert_eq!(storage.get(&dataflow.get_job_id()), Some(dataflow.clone()));
+            drop(storage);
+            let _ = std::fs::remove_dir_all(&path);
+        }
+    }
+
+    #[test]
+    fn test_storage_list_order() {
+        // the resource ids sort before the namespaces in the derived order, and a longer id is encoded before a shorter one by protobuf
+        let job_ids = [
+            ("team-b", "a"),
+            ("default", "job-10"),
+            ("team-a", "z"),
+            ("default", "job-2"),
+            ("team-a", "aa"),
+        ];
+        let dataflows = job_ids
+            .iter()
+            .map(|(namespace_id, resource_id)| Dataflow {
+                job_id: Some(ResourceId::new(
+                    namespace_id.to_string(),
+                    resource_id.to_string(),
+                )),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let list = |storage: &mut dyn DataflowStorage| {
+            dataflows
+                .iter()
+                .for_each(|dataflow| assert!(storage.save(dataflow).is_ok()));
+            storage
+                .list()
+                .iter()
+                .map(|dataflow| {
+                    let job_id = dataflow.get_job_id();
+                    format!("{}/{}", job_id.namespace_id, job_id.resource_id)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let path = std::env::temp_dir().join(format!(
+            "lightflus-list-{}",
+            common::utils::times::now_timestamp()
+        ));
+        let local = list(&mut LocalDataflowStorage::new(&path, Durability::Relaxed));
+        let _ = std::fs::remove_dir_all(&path);
+        let memory = list(&mut MemDataflowStorage::default());
+        assert_eq!(
+            local,
+            vec![
+                "default/job-10",
+                "default/job-2",
+                "team-a/aa",
+                "team-a/z",
+                "team-b/a"
+            ]
+        );
+        assert_eq!(local, memory);
     }
 }
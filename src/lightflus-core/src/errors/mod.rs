@@ -1,5 +1,8 @@
 pub mod taskmanager {
-    use common::err::{BizCode, BizError, RpcError};
+    use common::{
+        err::{BizCode, BizError, RpcError},
+        types::ExecutorId,
+    };
     use proto::common_impl::DataflowValidateError;
     use tokio::sync::mpsc::error::TryRecvError;
 
@@ -45,6 +48,13 @@ pub mod taskmanager {
         ChannelEmpty,
         ExecutionError(String),
         EventSendFailure(String),
+        InvalidThrottle(String),
+        OperatorNotFound(ExecutorId),
+        OperatorControlFailed(String),
+        InvalidTap(String),
+        RestoreFailed(String),
+        TooManyCreates(usize),
+        InvalidSink(String),
     }
 
     impl From<TryRecvError> for TaskWorkerError {
@@ -96,6 +106,53 @@ pub mod taskmanager {
                     rpc_err.biz_err.error_code = 8;
                     rpc_err.biz_err.message = format!("event sent error: {:?}", err);
                 }
+                TaskWorkerError::InvalidThrottle(err) => {
+                    rpc_err.status =
+                        tonic::Status::invalid_argument(format!("invalid throttle: {}", err));
+                    rpc_err.biz_err.error_code = 9;
+                    rpc_err.biz_err.message = format!("invalid throttle: {}", err);
+                }
+                TaskWorkerError::OperatorNotFound(operator_id) => {
+                    rpc_err.status =
+                        tonic::Status::not_found(format!("operator {} not found", operator_id));
+                    rpc_err.biz_err.error_code = 10;
+                    rpc_err.biz_err.message = format!("operator {} not found", operator_id);
+                }
+                TaskWorkerError::OperatorControlFailed(err) => {
+                    rpc_err.status = tonic::Status::failed_precondition(format!(
+                        "operator control failed: {}",
+                        err
+                    ));
+                    rpc_err.biz_err.error_code = 11;
+                    rpc_err.biz_err.message = format!("operator control failed: {}", err);
+                }
+                TaskWorkerError::InvalidTap(err) => {
+                    rpc_err.status =
+                        tonic::Status::invalid_argument(format!("invalid tap: {}", err));
+                    rpc_err.biz_err.error_code = 12;
+                    rpc_err.biz_err.message = format!("invalid tap: {}", err);
+                }
+                TaskWorkerError::RestoreFailed(err) => {
+                    rpc_err.status =
+                        tonic::Status::unavailable(format!("restore checkpoint failed: {}", err));
+                    rpc_err.biz_err.error_code = 13;
+                    rpc_err.biz_err.message = format!("restore checkpoint failed: {}", err);
+                }
+                TaskWorkerError::TooManyCreates(max_in_flight) => {
+                    rpc_err.status = tonic::Status::resource_exhausted(format!(
+                        "more than {} subdataflows are being created",
+                        max_in_flight
+                    ));
+                    rpc_err.biz_err.error_code = 14;
+                    rpc_err.biz_err.message =
+                        format!("more than {} subdataflows are being created", max_in_flight);
+                }
+                TaskWorkerError::InvalidSink(err) => {
+                    rpc_err.status =
+                        tonic::Status::invalid_argument(format!("invalid sink: {}", err));
+                    rpc_err.biz_err.error_code = 15;
+                    rpc_err.biz_err.message = format!("invalid sink: {}", err);
+                }
             }
             rpc_err.into_tonic_status()
         }
@@ -103,7 +160,10 @@ pub mod taskmanager {
 }
 
 pub mod coordinator {
-    use common::err::{BizCode, BizError, RpcError};
+    use common::{
+        err::{BizCode, BizError, RpcError},
+        net::cluster::ClusterError,
+    };
     use proto::common::{DataflowStatus, ResourceId};
 
     pub const COORDINATOR_BIZ_CODE: BizCode = 100;
@@ -142,35 +202,349 @@ pub mod coordinator {
             status: tonic::Status::not_found(message),
         }
     }
+
+    pub fn job_id_unprovided() -> RpcError {
+        RpcError {
+            biz_err: BizError {
+                biz_code: COORDINATOR_BIZ_CODE,
+                error_code: 4,
+                message: "no job id provided".to_string(),
+            },
+            status: tonic::Status::invalid_argument("no job id provided"),
+        }
+    }
+
+    pub fn not_found_savepoint(path: &str) -> RpcError {
+        let message = format!("not found savepoint {}", path);
+        RpcError {
+            biz_err: BizError {
+                biz_code: COORDINATOR_BIZ_CODE,
+                error_code: 5,
+                message: message.clone(),
+            },
+            status: tonic::Status::not_found(message),
+        }
+    }
+
+    pub fn savepoint_err(message: &str) -> RpcError {
+        RpcError {
+            biz_err: BizError {
+                biz_code: COORDINATOR_BIZ_CODE,
+                error_code: 6,
+                message: message.to_string(),
+            },
+            status: tonic::Status::internal(message),
+        }
+    }
+
+    pub fn invalid_page_token(token: &str) -> RpcError {
+        let message = format!("invalid page token {}", token);
+        RpcError {
+            biz_err: BizError {
+                biz_code: COORDINATOR_BIZ_CODE,
+                error_code: 7,
+                message: message.clone(),
+            },
+            status: tonic::Status::invalid_argument(message),
+        }
+    }
+
+    pub fn invalid_label_selector(message: &str) -> RpcError {
+        let message = format!("invalid label selector: {}", message);
+        RpcError {
+            biz_err: BizError {
+                biz_code: COORDINATOR_BIZ_CODE,
+                error_code: 8,
+                message: message.clone(),
+            },
+            status: tonic::Status::invalid_argument(message),
+        }
+    }
+
+    /// the dataflow is updated by someone else or it's being updated, so the update based on the given version is aborted
+    pub fn resource_version_conflict(job_id: &ResourceId, expected: u64, actual: u64) -> RpcError {
+        let message = if actual == expected {
+            format!("dataflow {:?} is being updated by another request", job_id)
+        } else {
+            format!(
+                "dataflow {:?} is at resource version {} instead of {}",
+                job_id, actual, expected
+            )
+        };
+        RpcError {
+            biz_err: BizError {
+                biz_code: COORDINATOR_BIZ_CODE,
+                error_code: 9,
+                message: message.clone(),
+            },
+            status: tonic::Status::aborted(message),
+        }
+    }
+
+    pub fn unsupported_update_strategy(strategy: &str) -> RpcError {
+        let message = format!("update strategy {} is not supported", strategy);
+        RpcError {
+            biz_err: BizError {
+                biz_code: COORDINATOR_BIZ_CODE,
+                error_code: 10,
+                message: message.clone(),
+            },
+            status: tonic::Status::unimplemented(message),
+        }
+    }
+
+    fn format_job_ids(job_ids: &[ResourceId]) -> String {
+        job_ids
+            .iter()
+            .map(|job_id| format!("{}/{}", job_id.namespace_id, job_id.resource_id))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// the job is not terminated because running dataflows depend on it
+    pub fn dependent_dataflows(job_id: &ResourceId, dependents: &[ResourceId]) -> RpcError {
+        let message = format!(
+            "job {}/{} is depended on by running jobs {}",
+            job_id.namespace_id,
+            job_id.resource_id,
+            format_job_ids(dependents)
+        );
+        RpcError {
+            biz_err: BizError {
+                biz_code: COORDINATOR_BIZ_CODE,
+                error_code: 11,
+                message: message.clone(),
+            },
+            status: tonic::Status::failed_precondition(message),
+        }
+    }
+
+    /// the job is not created because some jobs which it depends on are not running
+    pub fn missing_dependencies(job_id: &ResourceId, dependencies: &[ResourceId]) -> RpcError {
+        let message = format!(
+            "job {}/{} depends on jobs {} which are not running",
+            job_id.namespace_id,
+            job_id.resource_id,
+            format_job_ids(dependencies)
+        );
+        RpcError {
+            biz_err: BizError {
+                biz_code: COORDINATOR_BIZ_CODE,
+                error_code: 12,
+                message: message.clone(),
+            },
+            status: tonic::Status::failed_precondition(message),
+        }
+    }
+
+    /// the node is not added to the cluster because it's a member already or it doesn't answer
+    pub fn add_node_err(err: &ClusterError) -> RpcError {
+        let message = err.to_string();
+        RpcError {
+            biz_err: BizError {
+                biz_code: COORDINATOR_BIZ_CODE,
+                error_code: 13,
+                message: message.clone(),
+            },
+            status: match err {
+                ClusterError::NodeExists(_) => tonic::Status::already_exists(message),
+                ClusterError::NodeUnreachable(_) => tonic::Status::unavailable(message),
+            },
+        }
+    }
 }
 
 pub mod apiserver {
     use std::fmt;
 
     use common::err::Error;
-    use proto::common::{ErrorCode, Response};
+    use proto::common::{ErrorCode, ErrorDetail, Response};
+
+    /// machine-readable code of an [`ApiError`]. They're the gRPC status codes in snake case, so the statuses of the coordinator keep their codes
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ApiErrorCode {
+        Cancelled,
+        Unknown,
+        InvalidArgument,
+        DeadlineExceeded,
+        NotFound,
+        AlreadyExists,
+        PermissionDenied,
+        ResourceExhausted,
+        FailedPrecondition,
+        Aborted,
+        OutOfRange,
+        Unimplemented,
+        Internal,
+        Unavailable,
+        DataLoss,
+        Unauthenticated,
+    }
 
-    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    /// a failure can't be [`tonic::Code::Ok`], it's internal if it is
+    impl From<tonic::Code> for ApiErrorCode {
+        fn from(code: tonic::Code) -> Self {
+            match code {
+                tonic::Code::Cancelled => Self::Cancelled,
+                tonic::Code::Unknown => Self::Unknown,
+                tonic::Code::InvalidArgument => Self::InvalidArgument,
+                tonic::Code::DeadlineExceeded => Self::DeadlineExceeded,
+                tonic::Code::NotFound => Self::NotFound,
+                tonic::Code::AlreadyExists => Self::AlreadyExists,
+                tonic::Code::PermissionDenied => Self::PermissionDenied,
+                tonic::Code::ResourceExhausted => Self::ResourceExhausted,
+                tonic::Code::FailedPrecondition => Self::FailedPrecondition,
+                tonic::Code::Aborted => Self::Aborted,
+                tonic::Code::OutOfRange => Self::OutOfRange,
+                tonic::Code::Unimplemented => Self::Unimplemented,
+                tonic::Code::Unavailable => Self::Unavailable,
+                tonic::Code::DataLoss => Self::DataLoss,
+                tonic::Code::Unauthenticated => Self::Unauthenticated,
+                tonic::Code::Ok | tonic::Code::Internal => Self::Internal,
+            }
+        }
+    }
+
+    impl From<ApiErrorCode> for tonic::Code {
+        fn from(code: ApiErrorCode) -> Self {
+            match code {
+                ApiErrorCode::Cancelled => Self::Cancelled,
+                ApiErrorCode::Unknown => Self::Unknown,
+                ApiErrorCode::InvalidArgument => Self::InvalidArgument,
+                ApiErrorCode::DeadlineExceeded => Self::DeadlineExceeded,
+                ApiErrorCode::NotFound => Self::NotFound,
+                ApiErrorCode::AlreadyExists => Self::AlreadyExists,
+                ApiErrorCode::PermissionDenied => Self::PermissionDenied,
+                ApiErrorCode::ResourceExhausted => Self::ResourceExhausted,
+                ApiErrorCode::FailedPrecondition => Self::FailedPrecondition,
+                ApiErrorCode::Aborted => Self::Aborted,
+                ApiErrorCode::OutOfRange => Self::OutOfRange,
+                ApiErrorCode::Unimplemented => Self::Unimplemented,
+                ApiErrorCode::Internal => Self::Internal,
+                ApiErrorCode::Unavailable => Self::Unavailable,
+                ApiErrorCode::DataLoss => Self::DataLoss,
+                ApiErrorCode::Unauthenticated => Self::Unauthenticated,
+            }
+        }
+    }
+
+    impl From<ErrorCode> for ApiErrorCode {
+        fn from(code: ErrorCode) -> Self {
+            match code {
+                ErrorCode::ResourceNotFound => Self::NotFound,
+                ErrorCode::RpcUnauthorized => Self::Unauthenticated,
+                ErrorCode::RpcPermissionDenied => Self::PermissionDenied,
+                ErrorCode::RpcInvalidArgument
+                | ErrorCode::DataflowOperatorInfoMissing
+                | ErrorCode::CyclicDataflow
+                | ErrorCode::DataflowConfigurationMissing => Self::InvalidArgument,
+                ErrorCode::ServiceUnavailable => Self::Unavailable,
+                ErrorCode::Unspecified | ErrorCode::InternalError => Self::Internal,
+            }
+        }
+    }
+
+    #[cfg(feature = "apiserver")]
+    impl ApiErrorCode {
+        pub fn get_http_status(&self) -> actix_web::http::StatusCode {
+            use actix_web::http::StatusCode;
+
+            match self {
+                Self::InvalidArgument | Self::OutOfRange => StatusCode::BAD_REQUEST,
+                // the request is well-formed but it can't be applied to the resource, e.g. an immutable field is changed
+                Self::FailedPrecondition => StatusCode::UNPROCESSABLE_ENTITY,
+                Self::Unauthenticated => StatusCode::UNAUTHORIZED,
+                Self::PermissionDenied => StatusCode::FORBIDDEN,
+                Self::NotFound => StatusCode::NOT_FOUND,
+                Self::AlreadyExists | Self::Aborted => StatusCode::CONFLICT,
+                Self::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+                // the client closes the request
+                Self::Cancelled => StatusCode::from_u16(499).unwrap(),
+                Self::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+                Self::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+                Self::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+                Self::Unknown | Self::Internal | Self::DataLoss => {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            }
+        }
+    }
+
+    /// what is wrong with the request. It's located by the path of the field, e.g. `dataflow.nodes.0.upstreams[1]`,
+    /// or by the document, the line and the column of the body if the body can't be parsed to the field.
+    /// The line and the column start from 1
+    #[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+    pub struct ApiErrorDetail {
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        pub field: Option<String>,
+        pub message: String,
+        /// index of the YAML document, or of the resource in a batch, starting from 0
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        pub document: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        pub line: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        pub column: Option<usize>,
+    }
+
+    impl ApiErrorDetail {
+        pub fn new(message: impl ToString) -> Self {
+            Self {
+                message: message.to_string(),
+                ..Default::default()
+            }
+        }
+
+        /// the root of the body is not a field
+        pub fn with_field(mut self, field: impl ToString) -> Self {
+            self.field = Some(field.to_string()).filter(|field| field != ".");
+            self
+        }
+
+        pub fn with_document(mut self, document: usize) -> Self {
+            self.document = Some(document);
+            self
+        }
+
+        pub fn with_location(mut self, line: Option<usize>, column: Option<usize>) -> Self {
+            self.line = line;
+            self.column = column;
+            self
+        }
+    }
+
+    /// The body of all error responses of the API server, e.g.
+    /// `{"code": "invalid_argument", "message": "...", "details": [{"field": "resources", "message": "..."}], "requestId": "..."}`.
+    /// The HTTP status is decided by the code, see [`ApiErrorCode::get_http_status`], unless it's overridden by the error
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
     pub struct ApiError {
-        pub code: i32,
-        pub msg: String,
+        pub code: ApiErrorCode,
+        pub message: String,
+        #[serde(default)]
+        pub details: Vec<ApiErrorDetail>,
+        /// it's only absent in the errors of the items of a batch, which are responded in a successful response
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        pub request_id: Option<String>,
+        /// the HTTP status which is more specific than the one of the code, e.g. 413 of a body which is too large
+        #[serde(skip)]
+        pub status: Option<u16>,
+        /// how long the client should wait before it retries in seconds, it's responded in the `Retry-After` header
+        #[serde(skip)]
+        pub retry_after: Option<u64>,
     }
 
+    /// a coordinator which can't be connected is unavailable
     impl From<&mut tonic::transport::Error> for ApiError {
         fn from(err: &mut tonic::transport::Error) -> Self {
-            Self {
-                code: ErrorCode::InternalError as i32,
-                msg: err.to_string(),
-            }
+            Self::new(ApiErrorCode::Unavailable, err)
         }
     }
 
     impl From<tonic::transport::Error> for ApiError {
         fn from(err: tonic::transport::Error) -> Self {
-            Self {
-                code: ErrorCode::InternalError as i32,
-                msg: err.to_string(),
-            }
+            Self::new(ApiErrorCode::Unavailable, err)
         }
     }
 
@@ -181,49 +555,224 @@ pub mod apiserver {
     }
 
     impl ApiError {
-        pub fn from_error<T: Error>(err: T) -> Self {
+        pub fn new(code: ApiErrorCode, message: impl ToString) -> Self {
             Self {
-                code: err.code() as i32,
-                msg: err.msg(),
+                code,
+                message: message.to_string(),
+                details: vec![],
+                request_id: None,
+                status: None,
+                retry_after: None,
             }
         }
 
+        pub fn invalid_argument(message: impl ToString) -> Self {
+            Self::new(ApiErrorCode::InvalidArgument, message)
+        }
+
+        pub fn internal(message: impl ToString) -> Self {
+            Self::new(ApiErrorCode::Internal, message)
+        }
+
+        pub fn with_detail(mut self, detail: ApiErrorDetail) -> Self {
+            self.details.push(detail);
+            self
+        }
+
+        pub fn with_request_id(mut self, request_id: &str) -> Self {
+            self.request_id = Some(request_id.to_string());
+            self
+        }
+
+        pub fn with_status(mut self, status: u16) -> Self {
+            self.status = Some(status);
+            self
+        }
+
+        /// the wait is rounded up to whole seconds, and it's at least one second
+        pub fn with_retry_after(mut self, wait: std::time::Duration) -> Self {
+            self.retry_after = Some(wait.as_secs_f64().ceil().max(1.0) as u64);
+            self
+        }
+
+        pub fn from_error<T: Error>(err: T) -> Self {
+            Self::new(ApiErrorCode::from(err.code()), err.msg())
+        }
+
         pub fn into_tonic_status(&self) -> tonic::Status {
-            todo!()
+            tonic::Status::new(self.code.into(), self.message.clone())
         }
     }
 
+    /// the message of the status is kept only, so the body never contains the details or the metadata of the status
     impl From<tonic::Status> for ApiError {
         fn from(err: tonic::Status) -> Self {
-            let msg = format!("{}", err);
-            match err.code() {
-                tonic::Code::InvalidArgument => Self {
-                    code: ErrorCode::RpcInvalidArgument as i32,
-                    msg,
-                },
-                tonic::Code::NotFound => Self {
-                    code: ErrorCode::ResourceNotFound as i32,
-                    msg,
-                },
-                tonic::Code::PermissionDenied => Self {
-                    code: ErrorCode::RpcPermissionDenied as i32,
-                    msg,
-                },
-                tonic::Code::Unauthenticated => Self {
-                    code: ErrorCode::RpcUnauthorized as i32,
-                    msg,
-                },
-                _ => Self {
-                    code: ErrorCode::InternalError as i32,
-                    msg,
-                },
-            }
+            Self::from(&Response::from_status(&err))
+        }
+    }
+
+    impl From<&ErrorDetail> for ApiError {
+        fn from(detail: &ErrorDetail) -> Self {
+            Self::new(ApiErrorCode::from(detail.get_code()), &detail.message)
         }
     }
 
+    /// a failed response of the coordinator without details is internal
     impl From<&Response> for ApiError {
-        fn from(_resp: &Response) -> Self {
-            todo!()
+        fn from(resp: &Response) -> Self {
+            match resp.get_error() {
+                Some(detail) => Self::from(detail),
+                None => Self::internal(&resp.err_msg),
+            }
+        }
+    }
+
+    /// the line of a JSON error is zero if it's not caused by the input, e.g. an IO error
+    #[cfg(feature = "apiserver")]
+    impl From<serde_path_to_error::Error<serde_json::Error>> for ApiError {
+        fn from(err: serde_path_to_error::Error<serde_json::Error>) -> Self {
+            let line = Some(err.inner().line()).filter(|line| *line > 0);
+            let column = Some(err.inner().column()).filter(|_| line.is_some());
+            Self::invalid_argument(&err).with_detail(
+                ApiErrorDetail::new(err.inner())
+                    .with_field(err.path())
+                    .with_location(line, column),
+            )
+        }
+    }
+
+    #[cfg(feature = "apiserver")]
+    impl From<serde_path_to_error::Error<serde_yaml::Error>> for ApiError {
+        fn from(err: serde_path_to_error::Error<serde_yaml::Error>) -> Self {
+            let location = err.inner().location();
+            // the errors of serde_yaml are prefixed with the path already
+            Self::invalid_argument(err.inner()).with_detail(
+                ApiErrorDetail::new(err.inner())
+                    .with_field(err.path())
+                    .with_location(
+                        location.as_ref().map(|location| location.line()),
+                        location.as_ref().map(|location| location.column()),
+                    ),
+            )
+        }
+    }
+
+    #[cfg(feature = "apiserver")]
+    impl From<actix_web::error::PathError> for ApiError {
+        fn from(err: actix_web::error::PathError) -> Self {
+            Self::invalid_argument(format!("invalid path: {err}"))
+        }
+    }
+
+    #[cfg(feature = "apiserver")]
+    impl From<actix_web::error::QueryPayloadError> for ApiError {
+        fn from(err: actix_web::error::QueryPayloadError) -> Self {
+            Self::invalid_argument(format!("invalid query: {err}"))
+        }
+    }
+
+    /// the JSON body which can't be deserialized is located by the line and the column, and a body which is too large is responded with 413
+    #[cfg(feature = "apiserver")]
+    impl From<actix_web::error::JsonPayloadError> for ApiError {
+        fn from(err: actix_web::error::JsonPayloadError) -> Self {
+            use actix_web::error::JsonPayloadError;
+
+            match err {
+                JsonPayloadError::Deserialize(err) if err.line() > 0 => {
+                    Self::invalid_argument(format!("invalid body: {err}")).with_detail(
+                        ApiErrorDetail::new(&err)
+                            .with_location(Some(err.line()), Some(err.column())),
+                    )
+                }
+                JsonPayloadError::OverflowKnownLength { .. }
+                | JsonPayloadError::Overflow { .. }
+                | JsonPayloadError::Payload(actix_web::error::PayloadError::Overflow) => {
+                    Self::from(actix_web::error::PayloadError::Overflow)
+                }
+                err => Self::invalid_argument(format!("invalid body: {err}")),
+            }
+        }
+    }
+
+    /// a body which exceeds the limit of its size is responded with 413
+    #[cfg(feature = "apiserver")]
+    impl From<actix_web::error::PayloadError> for ApiError {
+        fn from(err: actix_web::error::PayloadError) -> Self {
+            use actix_web::error::PayloadError;
+
+            match err {
+                PayloadError::Overflow => {
+                    Self::new(ApiErrorCode::ResourceExhausted, "the body is too large")
+                        .with_status(413)
+                }
+                err => Self::invalid_argument(format!("invalid body: {err}")),
+            }
+        }
+    }
+
+    /// an [`ApiError`] is responded as a JSON body with the HTTP status of its code
+    #[cfg(feature = "apiserver")]
+    impl actix_web::ResponseError for ApiError {
+        fn status_code(&self) -> actix_web::http::StatusCode {
+            self.status
+                .and_then(|status| actix_web::http::StatusCode::from_u16(status).ok())
+                .unwrap_or_else(|| self.code.get_http_status())
+        }
+
+        /// the bearer token is challenged if the request is unauthenticated
+        fn error_response(&self) -> actix_web::HttpResponse {
+            let mut builder = actix_web::HttpResponse::build(self.status_code());
+            if self.code == ApiErrorCode::Unauthenticated {
+                builder.insert_header((actix_web::http::header::WWW_AUTHENTICATE, "Bearer"));
+            }
+            if let Some(retry_after) = self.retry_after {
+                builder.insert_header((actix_web::http::header::RETRY_AFTER, retry_after));
+            }
+            builder.json(self)
+        }
+    }
+}
+
+pub mod server {
+    use std::fmt;
+
+    #[derive(Debug)]
+    pub enum ServerError {
+        /// the role is not compiled into this binary
+        RoleUnsupported(String),
+        /// the configuration of the services started by the role is missing
+        ConfigMissing(String),
+        InvalidAddress(String),
+        /// every invalid field of the configuration with what is wrong with it
+        InvalidConfig(Vec<String>),
+        TransportError(tonic::transport::Error),
+        IoError(std::io::Error),
+    }
+
+    impl fmt::Display for ServerError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::RoleUnsupported(role) => write!(f, "role {role} is unsupported"),
+                Self::ConfigMissing(name) => write!(f, "config {name} is missing"),
+                Self::InvalidAddress(addr) => write!(f, "invalid address {addr}"),
+                Self::InvalidConfig(fields) => write!(f, "invalid config: {}", fields.join("; ")),
+                Self::TransportError(err) => write!(f, "transport error: {err}"),
+                Self::IoError(err) => write!(f, "io error: {err}"),
+            }
+        }
+    }
+
+    impl std::error::Error for ServerError {}
+
+    impl From<tonic::transport::Error> for ServerError {
+        fn from(err: tonic::transport::Error) -> Self {
+            Self::TransportError(err)
+        }
+    }
+
+    impl From<std::io::Error> for ServerError {
+        fn from(err: std::io::Error) -> Self {
+            Self::IoError(err)
         }
     }
 }
@@ -6,6 +6,15 @@ pub mod errors;
 pub mod taskmanager;
 #[cfg(feature = "apiserver")]
 pub mod apiserver;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(any(feature = "coordinator", feature = "taskmanager"))]
+pub mod server;
+
+/// metadata of the requests sent by the API server to the coordinator, which is the identity of the caller of the API server
+pub const CALLER_METADATA_KEY: &str = "x-lightflus-caller";
+/// metadata of the requests sent by the API server to the coordinator, which is the id of the request to the API server
+pub const REQUEST_ID_METADATA_KEY: &str = "x-request-id";
 
 pub(crate) type RpcResponse<T> = Result<tonic::Response<T>, tonic::Status>;
 pub(crate) type RpcRequest<T> = tonic::Request<T>;
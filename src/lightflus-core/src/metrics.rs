@@ -0,0 +1,458 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock, RwLock,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+/// the path which the metrics are exported at, the other paths are not found
+pub const METRICS_PATH: &str = "/metrics";
+/// the events received by the TaskManager, both of the single ones and the batched ones
+pub const EVENTS_RECEIVED_METRIC: &str = "lightflus_taskmanager_events_received_total";
+/// the jobs running on the TaskManager
+pub const JOBS_METRIC: &str = "lightflus_taskmanager_jobs";
+
+/// a scrape which doesn't send its request in time is closed, so slow clients never pile up connections
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// max size of a scrape request, only its request line is read
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// The HTTP server which exports the metrics in the Prometheus text format.
+/// It's not started if it's not configured
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub host: String,
+    pub port: usize,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 9100,
+        }
+    }
+}
+
+/// A monotonic counter. Incrementing it is a single atomic add, so it never blocks the event processing
+#[derive(Clone, Default, Debug)]
+pub struct Counter(Arc<AtomicU64>);
+
+impl Counter {
+    pub fn inc(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+type Gauge = Arc<dyn Fn() -> u64 + Send + Sync>;
+
+/// the upper bounds of the buckets of a latency histogram in seconds
+pub const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// A histogram of durations. Observing a duration is a few atomic adds, so it never blocks the requests either.
+/// The sum is kept in microseconds and rendered in seconds
+#[derive(Clone, Debug)]
+pub struct Histogram(Arc<HistogramState>);
+
+#[derive(Debug)]
+struct HistogramState {
+    bounds: Vec<f64>,
+    /// the count of each bucket, not cumulative
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn new(bounds: &[f64]) -> Self {
+        Self(Arc::new(HistogramState {
+            bounds: bounds.to_vec(),
+            buckets: bounds.iter().map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }))
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(index) = self.0.bounds.iter().position(|bound| seconds <= *bound) {
+            self.0.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.0.count.fetch_add(1, Ordering::Relaxed);
+        self.0
+            .sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn get_count(&self) -> u64 {
+        self.0.count.load(Ordering::Relaxed)
+    }
+
+    fn render(&self, text: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (bound, bucket) in self.0.bounds.iter().zip(self.0.buckets.iter()) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(
+                text,
+                "{name}_bucket{{{labels}{separator}le=\"{bound}\"}} {cumulative}"
+            );
+        }
+        let count = self.get_count();
+        let _ = writeln!(
+            text,
+            "{name}_bucket{{{labels}{separator}le=\"+Inf\"}} {count}"
+        );
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        let sum = self.0.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = write!(
+            text,
+            "{name}_sum{labels} {sum}\n{name}_count{labels} {count}\n"
+        );
+    }
+}
+
+/// the labels of a series like `route="/health",method="GET"`, the values are escaped
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{name}=\"{}\"",
+                value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// [`MetricsRegistry`] keeps the metrics exported by the metrics server.
+/// The lock of the registry is only taken to register a metric or to scrape, the data plane holds its [`Counter`]s and [`Histogram`]s directly.
+/// A gauge is read by its callback when it's scraped, and the callback is called without the lock
+#[derive(Default)]
+pub struct MetricsRegistry {
+    counters: RwLock<BTreeMap<String, Counter>>,
+    gauges: RwLock<BTreeMap<String, Gauge>>,
+    /// the series of each histogram by their labels
+    histograms: RwLock<BTreeMap<String, BTreeMap<String, Histogram>>>,
+}
+
+impl MetricsRegistry {
+    /// the counter of the name, it's shared by everyone registering the same name
+    pub fn counter(&self, name: &str) -> Counter {
+        if let Some(counter) = self
+            .counters
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(name)
+        {
+            return counter.clone();
+        }
+        self.counters
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .entry(name.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// register the gauge of the name, the last registered one wins
+    pub fn register_gauge<F: Fn() -> u64 + Send + Sync + 'static>(&self, name: &str, gauge: F) {
+        self.gauges
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(name.to_string(), Arc::new(gauge));
+    }
+
+    /// the histogram of the name and the labels with the given bucket bounds, it's shared by everyone registering the same series.
+    /// The bounds of a series are the ones it's registered with first
+    pub fn histogram(&self, name: &str, labels: &[(&str, &str)], bounds: &[f64]) -> Histogram {
+        let labels = format_labels(labels);
+        if let Some(histogram) = self
+            .histograms
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(name)
+            .and_then(|series| series.get(&labels))
+        {
+            return histogram.clone();
+        }
+        self.histograms
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .entry(name.to_string())
+            .or_default()
+            .entry(labels)
+            .or_insert_with(|| Histogram::new(bounds))
+            .clone()
+    }
+
+    /// the metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        let counters = self
+            .counters
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+            .map(|(name, counter)| (name.clone(), counter.get()))
+            .collect::<Vec<_>>();
+        for (name, value) in counters {
+            let _ = write!(text, "# TYPE {name} counter\n{name} {value}\n");
+        }
+        let gauges = self
+            .gauges
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+            .map(|(name, gauge)| (name.clone(), gauge.clone()))
+            .collect::<Vec<_>>();
+        for (name, gauge) in gauges {
+            let _ = write!(text, "# TYPE {name} gauge\n{name} {}\n", gauge());
+        }
+        let histograms = self
+            .histograms
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone();
+        for (name, series) in histograms {
+            let _ = writeln!(text, "# TYPE {name} histogram");
+            for (labels, histogram) in series {
+                histogram.render(&mut text, &name, &labels);
+            }
+        }
+        text
+    }
+}
+
+/// the registry of the process
+pub fn registry() -> Arc<MetricsRegistry> {
+    static REGISTRY: OnceLock<Arc<MetricsRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default).clone()
+}
+
+/// Start the metrics server in the background. It's isolated from the gRPC server:
+/// - if it fails to bind, the failure is logged and the task finishes
+/// - each scrape is served by its own task, so a panic of a scrape is logged and the server keeps accepting
+pub fn serve(config: &MetricsConfig, registry: Arc<MetricsRegistry>) -> JoinHandle<()> {
+    let addr = format!("{}:{}", config.host, config.port);
+    tokio::spawn(async move {
+        let listener = match TcpListener::bind(&addr).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                tracing::error!("metrics server fails to bind {}: {}", addr, err);
+                return;
+            }
+        };
+        tracing::info!("metrics server will start at {}", addr);
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::warn!("metrics server fails to accept: {}", err);
+                    continue;
+                }
+            };
+            let scrape = tokio::spawn(serve_scrape(stream, registry.clone()));
+            tokio::spawn(async move {
+                if let Err(err) = scrape.await {
+                    if err.is_panic() {
+                        tracing::error!("metrics scrape panicked: {}", err);
+                    }
+                }
+            });
+        }
+    })
+}
+
+async fn serve_scrape(mut stream: TcpStream, registry: Arc<MetricsRegistry>) {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    // the request line is enough to route the scrape
+    while !request.windows(2).any(|window| window == b"\r\n") {
+        match tokio::time::timeout(READ_TIMEOUT, stream.read(&mut buf)).await {
+            Ok(Ok(n)) if n > 0 && request.len() + n <= MAX_REQUEST_SIZE => {
+                request.extend_from_slice(&buf[..n])
+            }
+            _ => return,
+        }
+    }
+
+    let request_line = String::from_utf8_lossy(&request);
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(METRICS_PATH)) => {
+            let body = registry.render();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+    if let Err(err) = stream.write_all(response.as_bytes()).await {
+        tracing::warn!("metrics server fails to respond: {}", err);
+    }
+    let _ = stream.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::{serve, MetricsConfig, MetricsRegistry};
+
+    /// scrape the path, the response is empty if the connection is closed without a response
+    async fn scrape(port: usize, path: &str) -> String {
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+            .await
+            .unwrap();
+        stream
+            .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response).await;
+        response
+    }
+
+    #[test]
+    fn test_registry_render() {
+        let registry = MetricsRegistry::default();
+        let counter = registry.counter("events_total");
+        counter.inc(2);
+        registry.counter("events_total").inc(1);
+        registry.register_gauge("jobs", || 5);
+
+        assert_eq!(counter.get(), 3);
+        assert_eq!(
+            registry.render(),
+            "# TYPE events_total counter\nevents_total 3\n# TYPE jobs gauge\njobs 5\n"
+        );
+    }
+
+    #[test]
+    fn test_histogram_render() {
+        let registry = MetricsRegistry::default();
+        let histogram = registry.histogram(
+            "latency_seconds",
+            &[("route", "/a\"b"), ("status", "2xx")],
+            &[0.1, 1.0],
+        );
+        histogram.observe(Duration::from_millis(50));
+        histogram.observe(Duration::from_millis(500));
+        registry
+            .histogram(
+                "latency_seconds",
+                &[("route", "/a\"b"), ("status", "2xx")],
+                &[0.1, 1.0],
+            )
+            .observe(Duration::from_secs(2));
+        registry
+            .histogram("latency_seconds", &[], &[0.1, 1.0])
+            .observe(Duration::from_millis(10));
+
+        assert_eq!(histogram.get_count(), 3);
+        assert_eq!(
+            registry.render(),
+            "# TYPE latency_seconds histogram\n\
+            latency_seconds_bucket{le=\"0.1\"} 1\n\
+            latency_seconds_bucket{le=\"1\"} 1\n\
+            latency_seconds_bucket{le=\"+Inf\"} 1\n\
+            latency_seconds_sum 0.01\n\
+            latency_seconds_count 1\n\
+            latency_seconds_bucket{route=\"/a\\\"b\",status=\"2xx\",le=\"0.1\"} 1\n\
+            latency_seconds_bucket{route=\"/a\\\"b\",status=\"2xx\",le=\"1\"} 2\n\
+            latency_seconds_bucket{route=\"/a\\\"b\",status=\"2xx\",le=\"+Inf\"} 3\n\
+            latency_seconds_sum{route=\"/a\\\"b\",status=\"2xx\"} 2.55\n\
+            latency_seconds_count{route=\"/a\\\"b\",status=\"2xx\"} 3\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics_server() {
+        let registry = Arc::new(MetricsRegistry::default());
+        registry.counter("events_total").inc(1);
+        // the first scrape panics
+        let panicked = Arc::new(AtomicBool::new(false));
+        let gauge = panicked.clone();
+        registry.register_gauge("jobs", move || {
+            if !gauge.swap(true, Ordering::SeqCst) {
+                panic!("gauge failed");
+            }
+            1
+        });
+
+        let port = 8820;
+        serve(
+            &MetricsConfig {
+                host: "127.0.0.1".to_string(),
+                port,
+            },
+            registry,
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(scrape(port, "/metrics").await, "");
+        assert!(panicked.load(Ordering::SeqCst));
+        let response = scrape(port, "/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("events_total 1\n# TYPE jobs gauge\njobs 1\n"));
+        assert!(scrape(port, "/unknown")
+            .await
+            .starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_server_bind_failure() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port() as usize;
+        let handle = serve(
+            &MetricsConfig {
+                host: "127.0.0.1".to_string(),
+                port,
+            },
+            Default::default(),
+        );
+        // the failure finishes the server instead of panicking
+        assert!(tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .unwrap()
+            .is_ok());
+    }
+}
@@ -0,0 +1,649 @@
+use std::{fmt, fs, sync::Arc, time::Duration};
+
+use common::utils;
+use tonic::transport::{server::Router, Server};
+use tonic_health::{server::HealthReporter, ServingStatus};
+
+#[cfg(feature = "coordinator")]
+use crate::coordinator::{api::CoordinatorApiImpl, coord::CoordinatorBuilder};
+use crate::errors::server::ServerError;
+#[cfg(feature = "taskmanager")]
+use crate::taskmanager::rpc::{TaskManager, TaskManagerBuilder};
+#[cfg(feature = "coordinator")]
+use proto::coordinator::coordinator_api_server::CoordinatorApiServer;
+#[cfg(feature = "taskmanager")]
+use proto::taskmanager::task_manager_api_server::TaskManagerApiServer;
+
+/// the name of the health service of liveness. It's serving as long as the process runs
+pub const LIVENESS_SERVICE: &str = "lightflus.liveness";
+/// the name of the health service of readiness. It's serving only if the services of the role are ready to accept requests
+pub const READINESS_SERVICE: &str = "lightflus.readiness";
+/// the period of probing whether the services of the role are ready
+const READINESS_PROBE_PERIOD: Duration = Duration::from_secs(1);
+
+/// The role of a Lightflus process. It decides which gRPC services are started
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// starts the Coordinator and the HTTP API server
+    Coordinator,
+    /// starts the TaskManager
+    TaskManager,
+    /// starts the Coordinator, the HTTP API server and the TaskManager in one process.
+    /// The Coordinator and the TaskManager share the port of the Coordinator
+    Standalone,
+}
+
+impl Role {
+    pub fn runs_coordinator(&self) -> bool {
+        matches!(self, Self::Coordinator | Self::Standalone)
+    }
+
+    pub fn runs_taskmanager(&self) -> bool {
+        matches!(self, Self::TaskManager | Self::Standalone)
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Coordinator => f.write_str("coordinator"),
+            Self::TaskManager => f.write_str("taskmanager"),
+            Self::Standalone => f.write_str("standalone"),
+        }
+    }
+}
+
+/// Builder for the services of a Lightflus process.
+/// It's also the configuration of the unified binary. You can see in the file `etc/lightflus.json`
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ServerBuilder {
+    /// role of the process
+    pub role: Role,
+    /// Coordinator builder, required by the roles which run the Coordinator
+    #[cfg(feature = "coordinator")]
+    pub coordinator: Option<CoordinatorBuilder>,
+    /// TaskManager builder, required by the roles which run the TaskManager
+    #[cfg(feature = "taskmanager")]
+    pub taskmanager: Option<TaskManagerBuilder>,
+    /// HTTP API server config of the roles which run the Coordinator. The defaults are used if it's not configured,
+    /// and the API server sends the requests to the local Coordinator unless the coordinator endpoints are configured
+    #[cfg(feature = "apiserver")]
+    #[serde(default)]
+    pub apiserver: Option<crate::apiserver::config::ApiServerConfig>,
+    /// the HTTP server exporting the metrics, it's not started if it's not configured.
+    /// It fails on its own, the gRPC services keep serving if it can't bind or its scrape panics
+    #[cfg(feature = "metrics")]
+    #[serde(default)]
+    pub metrics: Option<crate::metrics::MetricsConfig>,
+}
+
+pub fn load_builder() -> ServerBuilder {
+    serde_json::from_str::<ServerBuilder>(
+        utils::from_reader(
+            fs::File::open(
+                utils::Args::default()
+                    .arg("c")
+                    .map(|arg| arg.value.clone())
+                    .unwrap_or("src/lightflus/etc/lightflus.json".to_string()),
+            )
+            .expect("fail to read config file: "),
+        )
+        .expect("fail to read config file: ")
+        .as_str(),
+    )
+    .expect("fail to parser config file: ")
+}
+
+impl ServerBuilder {
+    pub fn new(role: Role) -> Self {
+        Self {
+            role,
+            #[cfg(feature = "coordinator")]
+            coordinator: None,
+            #[cfg(feature = "taskmanager")]
+            taskmanager: None,
+            #[cfg(feature = "apiserver")]
+            apiserver: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    #[cfg(feature = "coordinator")]
+    pub fn with_coordinator(mut self, builder: CoordinatorBuilder) -> Self {
+        self.coordinator = Some(builder);
+        self
+    }
+
+    #[cfg(feature = "taskmanager")]
+    pub fn with_taskmanager(mut self, builder: TaskManagerBuilder) -> Self {
+        self.taskmanager = Some(builder);
+        self
+    }
+
+    #[cfg(feature = "apiserver")]
+    pub fn with_apiserver(mut self, config: crate::apiserver::config::ApiServerConfig) -> Self {
+        self.apiserver = Some(config);
+        self
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, config: crate::metrics::MetricsConfig) -> Self {
+        self.metrics = Some(config);
+        self
+    }
+
+    /// the gRPC port of the process. The services of the standalone role share the port of the Coordinator
+    pub fn get_port(&self) -> Result<usize, ServerError> {
+        match self.role {
+            #[cfg(feature = "coordinator")]
+            Role::Coordinator | Role::Standalone => self
+                .coordinator
+                .as_ref()
+                .map(|builder| builder.port)
+                .ok_or_else(|| ServerError::ConfigMissing("coordinator".to_string())),
+            #[cfg(feature = "taskmanager")]
+            Role::TaskManager => self
+                .taskmanager
+                .as_ref()
+                .map(|builder| builder.port)
+                .ok_or_else(|| ServerError::ConfigMissing("taskmanager".to_string())),
+            #[allow(unreachable_patterns)]
+            role => Err(ServerError::RoleUnsupported(role.to_string())),
+        }
+    }
+
+    /// register the gRPC services of the role, and the health service which reports their liveness and readiness.
+    /// Readiness is not serving until the services are probed ready
+    pub async fn build_router(&self) -> Result<Router, ServerError> {
+        let mut server = Server::builder();
+        if self.role.runs_coordinator() {
+            server = server.timeout(Duration::from_secs(3));
+        }
+
+        let mut probe = ReadinessProbe::default();
+        let router = match self.role {
+            #[cfg(feature = "coordinator")]
+            Role::Coordinator => server.add_service(self.build_coordinator(&mut probe)?),
+            #[cfg(feature = "taskmanager")]
+            Role::TaskManager => server.add_service(self.build_taskmanager(&mut probe)?),
+            #[cfg(all(feature = "coordinator", feature = "taskmanager"))]
+            Role::Standalone => server
+                .add_service(self.build_coordinator(&mut probe)?)
+                .add_service(self.build_taskmanager(&mut probe)?),
+            #[allow(unreachable_patterns)]
+            role => return Err(ServerError::RoleUnsupported(role.to_string())),
+        };
+
+        let (mut reporter, health) = tonic_health::server::health_reporter();
+        reporter
+            .set_service_status(LIVENESS_SERVICE, ServingStatus::Serving)
+            .await;
+        reporter
+            .set_service_status(READINESS_SERVICE, ServingStatus::NotServing)
+            .await;
+        tokio::spawn(probe.report(self.role, reporter));
+
+        Ok(router.add_service(health))
+    }
+
+    /// start the services of the role and serve until the gRPC server stops or the process is terminated, see [`terminated`].
+    /// The HTTP API server is started along with the Coordinator, and the metrics server is started if it's configured.
+    ///
+    /// Once the process is terminated, the HTTP API server shuts down gracefully before the gRPC server,
+    /// since its in-flight requests are served by the Coordinator of the process
+    pub async fn serve(&self) -> Result<(), ServerError> {
+        let router = self.build_router().await?;
+        let port = self.get_port()?;
+        let addr = format!("0.0.0.0:{port}")
+            .parse()
+            .map_err(|_| ServerError::InvalidAddress(format!("0.0.0.0:{port}")))?;
+
+        #[cfg(feature = "apiserver")]
+        let mut handler = if self.role.runs_coordinator() {
+            let mut config = self
+                .apiserver
+                .clone()
+                .unwrap_or_default()
+                .with_env_overrides();
+            if config.coordinator.endpoints.is_empty() {
+                config.coordinator.endpoints = vec![format!("localhost:{port}")];
+            }
+            Some(tokio::spawn(
+                crate::apiserver::new_api_server(&config)?.serve(terminated()),
+            ))
+        } else {
+            None
+        };
+
+        #[cfg(feature = "metrics")]
+        let metrics = self
+            .metrics
+            .as_ref()
+            .map(|config| crate::metrics::serve(config, crate::metrics::registry()));
+
+        tracing::info!("{} service will start at {}", self.role, port);
+        let shutdown = async {
+            terminated().await;
+            #[cfg(feature = "apiserver")]
+            if let Some(handler) = handler.as_mut() {
+                match handler.await {
+                    Ok(Err(err)) => tracing::error!("API server stopped: {}", err),
+                    Err(err) => tracing::error!("API server stopped: {}", err),
+                    Ok(Ok(_)) => {}
+                }
+            }
+        };
+        let result = router.serve_with_shutdown(addr, shutdown).await;
+
+        #[cfg(feature = "apiserver")]
+        handler.iter().for_each(|handler| handler.abort());
+        #[cfg(feature = "metrics")]
+        metrics.iter().for_each(|metrics| metrics.abort());
+
+        result.map_err(ServerError::from)
+    }
+
+    #[cfg(feature = "coordinator")]
+    fn build_coordinator(
+        &self,
+        probe: &mut ReadinessProbe,
+    ) -> Result<CoordinatorApiServer<CoordinatorApiImpl>, ServerError> {
+        let builder = self
+            .coordinator
+            .as_ref()
+            .ok_or_else(|| ServerError::ConfigMissing("coordinator".to_string()))?;
+        let coordinator = builder.build();
+        coordinator.init();
+        let api = Arc::new(CoordinatorApiImpl::new(coordinator));
+        let watcher = api.clone();
+        tokio::spawn(async move { watcher.watch_backpressure().await });
+        let watcher = api.clone();
+        tokio::spawn(async move { watcher.watch_liveness().await });
+        probe.coordinator = Some(api.clone());
+        Ok(CoordinatorApiServer::from_arc(api))
+    }
+
+    #[cfg(feature = "taskmanager")]
+    fn build_taskmanager(
+        &self,
+        probe: &mut ReadinessProbe,
+    ) -> Result<TaskManagerApiServer<TaskManager>, ServerError> {
+        let taskmanager = self
+            .taskmanager
+            .as_ref()
+            .map(|builder| builder.build_shared())
+            .ok_or_else(|| ServerError::ConfigMissing("taskmanager".to_string()))?;
+        probe.taskmanager = Some(taskmanager.clone());
+        Ok(TaskManagerApiServer::from_arc(taskmanager))
+    }
+}
+
+/// resolves once the process receives SIGTERM or SIGINT
+pub async fn terminated() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = sigterm.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+            Err(err) => {
+                tracing::warn!("listen to SIGTERM failed: {}", err);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// The services which the readiness of a process depends on:
+/// - the Coordinator is ready once its storage is reachable and its TaskManager cluster is probed
+/// - the TaskManager is ready if it has capacity for more jobs
+#[derive(Default)]
+struct ReadinessProbe {
+    #[cfg(feature = "coordinator")]
+    coordinator: Option<Arc<CoordinatorApiImpl>>,
+    #[cfg(feature = "taskmanager")]
+    taskmanager: Option<Arc<TaskManager>>,
+}
+
+impl ReadinessProbe {
+    async fn is_ready(&self) -> bool {
+        #[cfg(feature = "coordinator")]
+        if let Some(coordinator) = self.coordinator.as_ref() {
+            if !coordinator.is_ready().await {
+                return false;
+            }
+        }
+        #[cfg(feature = "taskmanager")]
+        if let Some(taskmanager) = self.taskmanager.as_ref() {
+            if !taskmanager.has_capacity() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// probe the services periodically and report the readiness whenever it changes
+    async fn report(self, role: Role, mut reporter: HealthReporter) {
+        let mut ready = false;
+        loop {
+            let is_ready = self.is_ready().await;
+            if is_ready != ready {
+                ready = is_ready;
+                let status = if ready {
+                    ServingStatus::Serving
+                } else {
+                    ServingStatus::NotServing
+                };
+                tracing::info!("readiness of {} is {}", role, status);
+                reporter.set_service_status(READINESS_SERVICE, status).await;
+            }
+            tokio::time::sleep(READINESS_PROBE_PERIOD).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use proto::{
+        common::ResourceId,
+        coordinator::{coordinator_api_client::CoordinatorApiClient, GetClusterTopologyRequest},
+        taskmanager::task_manager_api_client::TaskManagerApiClient,
+    };
+
+    use tonic_health::proto::{
+        health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+    };
+
+    use crate::errors::server::ServerError;
+
+    use super::{Role, ServerBuilder, LIVENESS_SERVICE, READINESS_PROBE_PERIOD, READINESS_SERVICE};
+
+    /// a service is registered unless its rpc is unimplemented
+    fn is_registered<T>(result: Result<T, tonic::Status>) -> bool {
+        !matches!(result, Err(status) if status.code() == tonic::Code::Unimplemented)
+    }
+
+    /// serve the router of the builder in the background
+    async fn serve(builder: &ServerBuilder) -> usize {
+        let port = builder.get_port().expect("no port");
+        let router = builder.build_router().await.expect("build router failed");
+        let addr = format!("127.0.0.1:{port}").parse().unwrap();
+        tokio::spawn(router.serve(addr));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        port
+    }
+
+    /// serve the router of the builder and check whether the Coordinator and the TaskManager are registered
+    async fn get_registered_services(builder: &ServerBuilder) -> (bool, bool) {
+        let port = serve(builder).await;
+
+        let dst = format!("http://127.0.0.1:{port}");
+        let mut coordinator = CoordinatorApiClient::connect(dst.clone()).await.unwrap();
+        let mut taskmanager = TaskManagerApiClient::connect(dst).await.unwrap();
+        (
+            is_registered(
+                coordinator
+                    .get_cluster_topology(GetClusterTopologyRequest::default())
+                    .await,
+            ),
+            is_registered(taskmanager.get_sub_dataflow(ResourceId::default()).await),
+        )
+    }
+
+    async fn get_serving_status(port: usize, service: &str) -> ServingStatus {
+        let channel = tonic::transport::Endpoint::from_shared(format!("http://127.0.0.1:{port}"))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let mut client = HealthClient::new(channel);
+        client
+            .check(HealthCheckRequest {
+                service: service.to_string(),
+            })
+            .await
+            .map(|resp| resp.into_inner().status())
+            .expect("check health failed")
+    }
+
+    /// wait until the readiness is serving, returns false if it's still not serving after several probes
+    async fn wait_for_readiness(port: usize) -> bool {
+        for _ in 0..5 {
+            if get_serving_status(port, READINESS_SERVICE).await == ServingStatus::Serving {
+                return true;
+            }
+            tokio::time::sleep(READINESS_PROBE_PERIOD).await;
+        }
+        false
+    }
+
+    #[cfg(feature = "taskmanager")]
+    fn new_taskmanager_builder(
+        port: usize,
+        max_job_nums: usize,
+    ) -> crate::taskmanager::rpc::TaskManagerBuilder {
+        crate::taskmanager::rpc::TaskManagerBuilder {
+            port,
+            max_job_nums,
+            snapshot_store: None,
+            scratch: None,
+            create_limit: None,
+            offset_store: None,
+        }
+    }
+
+    #[cfg(feature = "coordinator")]
+    fn new_coordinator_builder(port: usize) -> crate::coordinator::coord::CoordinatorBuilder {
+        serde_json::from_value(serde_json::json!({
+            "port": port,
+            "cluster": {
+                "nodes": format!("127.0.0.1:{port}"),
+                "rpc_timeout": 3,
+                "connect_timeout": 3
+            },
+            "storage": {
+                "Memory": {
+                    "ttl": null,
+                    "max_entries": null
+                }
+            },
+            "heartbeat": {
+                "period": 3,
+                "connect_timeout": 3,
+                "rpc_timeout": 3
+            },
+            "ack": {
+                "delay": 1,
+                "buf_size": 500,
+                "connect_timeout": 3,
+                "rpc_timeout": 3
+            }
+        }))
+        .expect("invalid coordinator config")
+    }
+
+    #[test]
+    fn test_role_from_config() {
+        for (role, expected) in [
+            ("coordinator", Role::Coordinator),
+            ("taskmanager", Role::TaskManager),
+            ("standalone", Role::Standalone),
+        ] {
+            let builder: ServerBuilder =
+                serde_json::from_value(serde_json::json!({ "role": role })).unwrap();
+            assert_eq!(builder.role, expected);
+            assert_eq!(builder.role.to_string(), role);
+        }
+        assert!(
+            serde_json::from_value::<ServerBuilder>(serde_json::json!({ "role": "unknown" }))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_role_config_missing() {
+        assert!(matches!(
+            ServerBuilder::new(Role::Coordinator).build_router().await,
+            Err(ServerError::ConfigMissing(_)) | Err(ServerError::RoleUnsupported(_))
+        ));
+        assert!(matches!(
+            ServerBuilder::new(Role::TaskManager).build_router().await,
+            Err(ServerError::ConfigMissing(_)) | Err(ServerError::RoleUnsupported(_))
+        ));
+    }
+
+    #[cfg(feature = "coordinator")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_coordinator_role_services() {
+        let builder =
+            ServerBuilder::new(Role::Coordinator).with_coordinator(new_coordinator_builder(8802));
+        assert_eq!(get_registered_services(&builder).await, (true, false));
+    }
+
+    #[cfg(feature = "taskmanager")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_taskmanager_role_services() {
+        let builder = ServerBuilder::new(Role::TaskManager).with_taskmanager(
+            crate::taskmanager::rpc::TaskManagerBuilder {
+                port: 8803,
+                max_job_nums: 10,
+                snapshot_store: None,
+                scratch: None,
+                create_limit: None,
+                offset_store: None,
+            },
+        );
+        assert_eq!(get_registered_services(&builder).await, (false, true));
+    }
+
+    #[cfg(all(feature = "coordinator", feature = "taskmanager"))]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_standalone_role_services() {
+        let builder = ServerBuilder::new(Role::Standalone)
+            .with_coordinator(new_coordinator_builder(8804))
+            .with_taskmanager(crate::taskmanager::rpc::TaskManagerBuilder {
+                port: 8805,
+                max_job_nums: 10,
+                snapshot_store: None,
+                scratch: None,
+                create_limit: None,
+                offset_store: None,
+            });
+        assert_eq!(builder.get_port().unwrap(), 8804);
+        assert_eq!(get_registered_services(&builder).await, (true, true));
+    }
+
+    #[cfg(feature = "coordinator")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_coordinator_readiness() {
+        let mut coordinator = new_coordinator_builder(8816);
+        coordinator.cluster.nodes = "127.0.0.1:8817".to_string();
+        let port =
+            serve(&ServerBuilder::new(Role::Coordinator).with_coordinator(coordinator)).await;
+
+        // no TaskManager of the cluster answers during startup
+        tokio::time::sleep(READINESS_PROBE_PERIOD).await;
+        assert_eq!(
+            get_serving_status(port, LIVENESS_SERVICE).await,
+            ServingStatus::Serving
+        );
+        assert_eq!(
+            get_serving_status(port, READINESS_SERVICE).await,
+            ServingStatus::NotServing
+        );
+
+        // it's ready once the TaskManager starts
+        #[cfg(feature = "taskmanager")]
+        {
+            serve(
+                &ServerBuilder::new(Role::TaskManager)
+                    .with_taskmanager(new_taskmanager_builder(8817, 10)),
+            )
+            .await;
+            assert!(wait_for_readiness(port).await);
+            assert_eq!(
+                get_serving_status(port, LIVENESS_SERVICE).await,
+                ServingStatus::Serving
+            );
+        }
+    }
+
+    #[cfg(all(feature = "taskmanager", feature = "metrics"))]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_metrics_server_failure() {
+        use proto::common::KeyedDataEvent;
+
+        use crate::metrics::{registry, MetricsConfig, EVENTS_RECEIVED_METRIC};
+
+        // the port of the metrics server is taken, so it fails to bind
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = 8821;
+        let builder = ServerBuilder::new(Role::TaskManager)
+            .with_taskmanager(new_taskmanager_builder(port, 10))
+            .with_metrics(MetricsConfig {
+                host: "127.0.0.1".to_string(),
+                port: taken.local_addr().unwrap().port() as usize,
+            });
+        let server = tokio::spawn(async move { builder.serve().await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!server.is_finished());
+
+        assert_eq!(
+            get_serving_status(port, LIVENESS_SERVICE).await,
+            ServingStatus::Serving
+        );
+        assert!(wait_for_readiness(port).await);
+        let mut client = TaskManagerApiClient::connect(format!("http://127.0.0.1:{port}"))
+            .await
+            .unwrap();
+        let received = registry().counter(EVENTS_RECEIVED_METRIC).get();
+        // the event is sent to the TaskManager, although no job runs
+        let status = client
+            .send_event_to_operator(KeyedDataEvent::default())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        assert!(registry().counter(EVENTS_RECEIVED_METRIC).get() > received);
+        server.abort();
+    }
+
+    #[cfg(feature = "taskmanager")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_taskmanager_readiness() {
+        // a TaskManager without capacity is alive but never ready
+        let port = serve(
+            &ServerBuilder::new(Role::TaskManager)
+                .with_taskmanager(new_taskmanager_builder(8818, 0)),
+        )
+        .await;
+        tokio::time::sleep(READINESS_PROBE_PERIOD).await;
+        assert_eq!(
+            get_serving_status(port, LIVENESS_SERVICE).await,
+            ServingStatus::Serving
+        );
+        assert_eq!(
+            get_serving_status(port, READINESS_SERVICE).await,
+            ServingStatus::NotServing
+        );
+
+        let port = serve(
+            &ServerBuilder::new(Role::TaskManager)
+                .with_taskmanager(new_taskmanager_builder(8819, 10)),
+        )
+        .await;
+        assert!(wait_for_readiness(port).await);
+    }
+}
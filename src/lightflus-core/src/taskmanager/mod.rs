@@ -1,2 +1,3 @@
 pub mod rpc;
+pub mod scratch;
 pub mod taskworker;
@@ -1,25 +1,37 @@
-use std::fs;
+use std::{collections::BTreeMap, fs, pin::Pin, sync::Arc};
 
-use common::utils;
+use common::{
+    snapshot::{SnapshotStore, SnapshotStoreBuilder},
+    utils,
+};
 use crossbeam_skiplist::SkipMap;
+use futures_util::Stream;
 use proto::{
     common::{
-        Ack, DataflowStatus, Heartbeat, KeyedDataEvent, KeyedEventSet, ResourceId, Response,
-        SubDataflowStates,
+        Ack, DataflowStatus, Heartbeat, KeyedDataEvent, KeyedEventSet, OperatorStates, ResourceId,
+        Response, SubDataflowStates,
     },
     taskmanager::{
         task_manager_api_server::{TaskManagerApi, TaskManagerApiServer},
         BatchSendEventsToOperatorResponse, CreateSubDataflowRequest, CreateSubDataflowResponse,
-        SendEventToOperatorResponse, StopDataflowResponse,
+        OperatorRequest, SendEventToOperatorResponse, StopDataflowRequest, StopDataflowResponse,
+        TapOperatorRequest, UpdateSinkConfigRequest,
     },
 };
 
+use stream::offsets::{OffsetStoreBuilder, SharedOffsetStore};
+use tokio::sync::{Semaphore, SemaphorePermit};
 use tonic::async_trait;
 
 use crate::{
-    errors::taskmanager::{execution_id_unprovided, no_found_worker, resource_id_unprovided},
+    errors::taskmanager::{
+        execution_id_unprovided, no_found_worker, resource_id_unprovided, TaskWorkerError,
+    },
     new_rpc_response,
-    taskmanager::taskworker::{TaskWorker, TaskWorkerBuilder},
+    taskmanager::{
+        scratch::{ScratchConfig, ScratchManager},
+        taskworker::{TaskWorker, TaskWorkerBuilder},
+    },
     RpcRequest, RpcResponse,
 };
 
@@ -29,6 +41,70 @@ pub struct TaskManagerBuilder {
     pub port: usize,
     // max available number of jobs
     pub max_job_nums: usize,
+    // S3-compatible storage which the checkpoints are uploaded to, they're only kept locally if it's not configured
+    #[serde(default)]
+    pub snapshot_store: Option<SnapshotStoreBuilder>,
+    // scratch disk of the jobs, they have no scratch directory if it's not configured
+    #[serde(default)]
+    pub scratch: Option<ScratchConfig>,
+    // max number of the subdataflows created at the same time, they're not limited if it's not configured
+    #[serde(default)]
+    pub create_limit: Option<CreateLimitConfig>,
+    // store of the offsets of the sources, the Kafka sources commit their offsets to the consumer groups automatically if it's not configured
+    #[serde(default)]
+    pub offset_store: Option<OffsetStoreBuilder>,
+}
+
+/// the limit of the subdataflows created at the same time, which paces the setup work when many of them are dispatched at once
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct CreateLimitConfig {
+    /// max number of the in-flight creates
+    pub max_in_flight: usize,
+    /// what the creates exceeding the max do
+    #[serde(default)]
+    pub overflow: CreateOverflow,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CreateOverflow {
+    /// wait until one of the in-flight creates finishes
+    #[default]
+    Queue,
+    /// fail with `ResourceExhausted`
+    Reject,
+}
+
+/// [`CreateLimiter`] gates the creates of the subdataflows by a semaphore
+struct CreateLimiter {
+    semaphore: Semaphore,
+    max_in_flight: usize,
+    overflow: CreateOverflow,
+}
+
+impl CreateLimiter {
+    fn new(config: &CreateLimitConfig) -> Self {
+        Self {
+            semaphore: Semaphore::new(config.max_in_flight),
+            max_in_flight: config.max_in_flight,
+            overflow: config.overflow,
+        }
+    }
+
+    /// the create holds the permit until it finishes
+    async fn acquire(&self) -> Result<SemaphorePermit<'_>, TaskWorkerError> {
+        match self.overflow {
+            CreateOverflow::Queue => self
+                .semaphore
+                .acquire()
+                .await
+                .map_err(|_| TaskWorkerError::TooManyCreates(self.max_in_flight)),
+            CreateOverflow::Reject => self
+                .semaphore
+                .try_acquire()
+                .map_err(|_| TaskWorkerError::TooManyCreates(self.max_in_flight)),
+        }
+    }
 }
 
 pub fn load_builder() -> TaskManagerBuilder {
@@ -50,13 +126,113 @@ pub fn load_builder() -> TaskManagerBuilder {
 
 impl TaskManagerBuilder {
     pub fn build(&self) -> TaskManagerApiServer<TaskManager> {
+        TaskManagerApiServer::from_arc(self.build_shared())
+    }
+
+    /// the TaskManager shared by its gRPC service and the readiness probe
+    pub(crate) fn build_shared(&self) -> Arc<TaskManager> {
         let workers = SkipMap::new();
-        TaskManagerApiServer::new(TaskManager { workers })
+        let snapshot_store =
+            self.snapshot_store
+                .as_ref()
+                .and_then(|builder| match builder.build() {
+                    Ok(store) => Some(store),
+                    Err(err) => {
+                        tracing::error!("create snapshot store failed: {}", err);
+                        None
+                    }
+                });
+        // no job is running on startup, so the directories under the root are left by crashed jobs
+        let scratch = self
+            .scratch
+            .as_ref()
+            .map(|config| {
+                ScratchManager::new(config).and_then(|scratch| {
+                    scratch
+                        .sweep_orphans(std::time::SystemTime::now())
+                        .map(|swept| {
+                            tracing::info!("{} orphaned scratch directories are swept", swept);
+                            scratch
+                        })
+                })
+            })
+            .and_then(|result| match result {
+                Ok(scratch) => Some(scratch),
+                Err(err) => {
+                    tracing::error!("create scratch manager failed: {}", err);
+                    None
+                }
+            });
+        let taskmanager = Arc::new(TaskManager {
+            workers,
+            max_job_nums: self.max_job_nums,
+            snapshot_store,
+            scratch,
+            create_limiter: self.create_limit.as_ref().map(CreateLimiter::new),
+            offset_store: self
+                .offset_store
+                .as_ref()
+                .map(OffsetStoreBuilder::build_shared),
+            #[cfg(feature = "metrics")]
+            events_received: crate::metrics::registry()
+                .counter(crate::metrics::EVENTS_RECEIVED_METRIC),
+        });
+        #[cfg(feature = "metrics")]
+        {
+            let weak = Arc::downgrade(&taskmanager);
+            crate::metrics::registry().register_gauge(crate::metrics::JOBS_METRIC, move || {
+                weak.upgrade()
+                    .map(|taskmanager| taskmanager.workers.len() as u64)
+                    .unwrap_or_default()
+            });
+        }
+        taskmanager
     }
 }
 
 pub struct TaskManager {
     workers: SkipMap<ResourceId, TaskWorker>,
+    max_job_nums: usize,
+    snapshot_store: Option<SnapshotStore>,
+    scratch: Option<ScratchManager>,
+    create_limiter: Option<CreateLimiter>,
+    offset_store: Option<SharedOffsetStore>,
+    #[cfg(feature = "metrics")]
+    events_received: crate::metrics::Counter,
+}
+
+impl TaskManager {
+    /// the TaskManager has capacity if the number of its jobs is less than the max
+    pub(crate) fn has_capacity(&self) -> bool {
+        self.workers.len() < self.max_job_nums
+    }
+
+    /// allocate the scratch directory of the job, the job has no scratch directory if it fails
+    fn allocate_scratch_dir(&self, job_id: &ResourceId) -> Option<std::path::PathBuf> {
+        self.scratch
+            .as_ref()
+            .and_then(|scratch| match scratch.allocate(job_id) {
+                Ok(dir) => Some(dir),
+                Err(err) => {
+                    tracing::error!(
+                        "allocate scratch directory of job {:?} failed: {}",
+                        job_id,
+                        err
+                    );
+                    None
+                }
+            })
+    }
+
+    fn release_scratch_dir(&self, job_id: &ResourceId) {
+        if let Some(Err(err)) = self.scratch.as_ref().map(|scratch| scratch.release(job_id)) {
+            tracing::error!(
+                "release scratch directory of job {:?} failed: {}",
+                job_id,
+                err
+            );
+        }
+    }
 }
 
 #[async_trait]
@@ -66,6 +242,8 @@ impl TaskManagerApi for TaskManager {
         request: RpcRequest<KeyedDataEvent>,
     ) -> RpcResponse<SendEventToOperatorResponse> {
         let event = request.into_inner();
+        #[cfg(feature = "metrics")]
+        self.events_received.inc(1);
         match event
             .get_job_id_opt_ref()
             .and_then(|job_id| self.workers.get(job_id))
@@ -77,6 +255,7 @@ impl TaskManagerApi for TaskManager {
                 .map(|status| {
                     new_rpc_response(SendEventToOperatorResponse {
                         status: status as i32,
+                        retry_after_ms: 0,
                     })
                 })
                 .map_err(|err| err.into_grpc_status()),
@@ -86,11 +265,19 @@ impl TaskManagerApi for TaskManager {
 
     async fn stop_dataflow(
         &self,
-        request: RpcRequest<ResourceId>,
+        request: RpcRequest<StopDataflowRequest>,
     ) -> RpcResponse<StopDataflowResponse> {
-        match self.workers.remove(request.get_ref()) {
+        let request = request.into_inner();
+        // the worker keeps receiving events while it's drained
+        match request
+            .job_id
+            .as_ref()
+            .and_then(|job_id| self.workers.get(job_id))
+        {
             Some(entry) => {
+                entry.value().stop(request.mode()).await;
                 entry.remove();
+                self.release_scratch_dir(entry.key());
             }
             None => {}
         };
@@ -101,6 +288,16 @@ impl TaskManagerApi for TaskManager {
         &self,
         request: RpcRequest<CreateSubDataflowRequest>,
     ) -> RpcResponse<CreateSubDataflowResponse> {
+        // the permit is released once the subdataflow is created or fails to be created
+        let _permit = match &self.create_limiter {
+            Some(limiter) => Some(
+                limiter
+                    .acquire()
+                    .await
+                    .map_err(|err| err.into_grpc_status())?,
+            ),
+            None => None,
+        };
         let request = request.into_inner();
         let opt = request.dataflow.as_ref();
         opt.and_then(|dataflow| dataflow.job_id.as_ref())
@@ -111,7 +308,83 @@ impl TaskManagerApi for TaskManager {
             });
         match opt {
             Some(dataflow) => {
-                let worker_builder = TaskWorkerBuilder::new(dataflow);
+                let restored = match (&self.snapshot_store, request.restore_from.is_empty()) {
+                    // the savepoint takes precedence over the remote checkpoint
+                    _ if request.savepoint.is_some() => None,
+                    (_, true) => None,
+                    (Some(store), false) => match store.download(&request.restore_from).await {
+                        Ok(checkpoint) => Some(checkpoint),
+                        Err(err) => {
+                            return Err(
+                                TaskWorkerError::RestoreFailed(err.to_string()).into_grpc_status()
+                            )
+                        }
+                    },
+                    (None, false) => {
+                        tracing::warn!(
+                            "checkpoint {} is not restored because no snapshot store is configured",
+                            &request.restore_from
+                        );
+                        None
+                    }
+                };
+                // the compatible operators are warm-started from the prior job if the subdataflow has no state of its own
+                let warm_start = match &self.snapshot_store {
+                    _ if request.savepoint.is_some()
+                        || !request.restore_from.is_empty()
+                        || request.warm_start_from.is_empty() =>
+                    {
+                        None
+                    }
+                    Some(store) => {
+                        let state_schemas = dataflow
+                            .nodes
+                            .iter()
+                            .map(|(operator_id, info)| (*operator_id, info.get_state_schema()))
+                            .collect();
+                        match store
+                            .warm_start(&request.warm_start_from, &state_schemas)
+                            .await
+                        {
+                            Ok(warm_start) => {
+                                warm_start.warnings.iter().for_each(|warning| {
+                                    tracing::warn!("job {:?}: {}", &dataflow.job_id, warning)
+                                });
+                                Some(warm_start.states)
+                            }
+                            Err(err) => {
+                                return Err(TaskWorkerError::RestoreFailed(err.to_string())
+                                    .into_grpc_status())
+                            }
+                        }
+                    }
+                    None => {
+                        tracing::warn!(
+                            "job {:?} is not warm-started because no snapshot store is configured",
+                            &dataflow.job_id
+                        );
+                        None
+                    }
+                };
+                let savepoint = request.savepoint.as_ref().map(|savepoint| {
+                    savepoint
+                        .states
+                        .iter()
+                        .map(|(operator_id, state)| (*operator_id, state.clone()))
+                        .collect::<BTreeMap<_, _>>()
+                });
+                // the files left by the former run of the job are removed
+                let scratch_dir = dataflow
+                    .job_id
+                    .as_ref()
+                    .and_then(|job_id| self.allocate_scratch_dir(job_id));
+                let worker_builder = TaskWorkerBuilder::new(dataflow)
+                    .with_coordinator(request.coordinator.as_ref())
+                    .with_snapshot_store(self.snapshot_store.as_ref(), restored.as_ref())
+                    .with_savepoint(savepoint.as_ref())
+                    .with_warm_start(warm_start.as_ref())
+                    .with_scratch_dir(scratch_dir.as_deref())
+                    .with_offset_store(self.offset_store.as_ref());
                 match worker_builder.build().await {
                     Ok(worker) => {
                         match dataflow.job_id.as_ref() {
@@ -122,7 +395,7 @@ impl TaskManagerApi for TaskManager {
                         };
 
                         Ok(new_rpc_response(CreateSubDataflowResponse {
-                            status: DataflowStatus::Initialized as i32,
+                            status: DataflowStatus::Running as i32,
                         }))
                     }
                     Err(err) => Err(err.into_grpc_status()),
@@ -174,6 +447,8 @@ impl TaskManagerApi for TaskManager {
         request: RpcRequest<KeyedEventSet>,
     ) -> RpcResponse<BatchSendEventsToOperatorResponse> {
         let event_set = request.into_inner();
+        #[cfg(feature = "metrics")]
+        self.events_received.inc(event_set.events.len() as u64);
         match event_set
             .job_id
             .as_ref()
@@ -183,9 +458,13 @@ impl TaskManagerApi for TaskManager {
                 .value()
                 .batch_send_event_to_operator(event_set)
                 .await
-                .map(|_status| new_rpc_response(BatchSendEventsToOperatorResponse {}))
+                .map(|_status| {
+                    new_rpc_response(BatchSendEventsToOperatorResponse { retry_after_ms: 0 })
+                })
                 .map_err(|err| err.into_grpc_status()),
-            None => Ok(new_rpc_response(BatchSendEventsToOperatorResponse {})),
+            None => Ok(new_rpc_response(BatchSendEventsToOperatorResponse {
+                retry_after_ms: 0,
+            })),
         }
     }
     async fn get_sub_dataflow(
@@ -199,4 +478,179 @@ impl TaskManagerApi for TaskManager {
             None => Err(no_found_worker().into_tonic_status()),
         }
     }
+
+    async fn drain_operator(&self, request: RpcRequest<OperatorRequest>) -> RpcResponse<Response> {
+        let request = request.into_inner();
+        match request
+            .job_id
+            .as_ref()
+            .and_then(|job_id| self.workers.get(job_id))
+        {
+            Some(worker) => worker
+                .value()
+                .drain_operator(request.operator_id)
+                .await
+                .map(|_| new_rpc_response(Response::ok()))
+                .map_err(|err| err.into_grpc_status()),
+            None => Err(no_found_worker().into_tonic_status()),
+        }
+    }
+
+    async fn resume_operator(&self, request: RpcRequest<OperatorRequest>) -> RpcResponse<Response> {
+        let request = request.into_inner();
+        match request
+            .job_id
+            .as_ref()
+            .and_then(|job_id| self.workers.get(job_id))
+        {
+            Some(worker) => worker
+                .value()
+                .resume_operator(request.operator_id)
+                .map(|_| new_rpc_response(Response::ok()))
+                .map_err(|err| err.into_grpc_status()),
+            None => Err(no_found_worker().into_tonic_status()),
+        }
+    }
+
+    async fn update_sink_config(
+        &self,
+        request: RpcRequest<UpdateSinkConfigRequest>,
+    ) -> RpcResponse<Response> {
+        let request = request.into_inner();
+        match request
+            .job_id
+            .as_ref()
+            .and_then(|job_id| self.workers.get(job_id))
+        {
+            Some(worker) => worker
+                .value()
+                .update_sink_config(request.operator_id, &request.sink.unwrap_or_default())
+                .await
+                .map(|_| new_rpc_response(Response::ok()))
+                .map_err(|err| err.into_grpc_status()),
+            None => Err(no_found_worker().into_tonic_status()),
+        }
+    }
+
+    type TapOperatorStream =
+        Pin<Box<dyn Stream<Item = Result<KeyedDataEvent, tonic::Status>> + Send>>;
+
+    async fn tap_operator(
+        &self,
+        request: RpcRequest<TapOperatorRequest>,
+    ) -> RpcResponse<Self::TapOperatorStream> {
+        let request = request.into_inner();
+        match request
+            .job_id
+            .as_ref()
+            .and_then(|job_id| self.workers.get(job_id))
+        {
+            Some(worker) => worker
+                .value()
+                .tap_operator(request.operator_id, request.sample_rate)
+                .map(|mut rx| {
+                    // the tap is closed once the caller drops the stream
+                    let stream = futures_util::stream::poll_fn(move |cx| {
+                        rx.poll_recv(cx).map(|event| event.map(Ok))
+                    });
+                    new_rpc_response(Box::pin(stream) as Self::TapOperatorStream)
+                })
+                .map_err(|err| err.into_grpc_status()),
+            None => Err(no_found_worker().into_tonic_status()),
+        }
+    }
+
+    async fn trigger_savepoint(
+        &self,
+        request: RpcRequest<ResourceId>,
+    ) -> RpcResponse<OperatorStates> {
+        match self.workers.get(request.get_ref()) {
+            Some(worker) => worker
+                .value()
+                .savepoint()
+                .await
+                .map(new_rpc_response)
+                .map_err(|err| err.into_grpc_status()),
+            None => Err(no_found_worker().into_tonic_status()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use proto::taskmanager::{task_manager_api_server::TaskManagerApi, CreateSubDataflowRequest};
+
+    use super::{CreateLimitConfig, CreateLimiter, CreateOverflow, TaskManagerBuilder};
+
+    #[tokio::test]
+    async fn test_create_limit_queue() {
+        let limiter = Arc::new(CreateLimiter::new(&CreateLimitConfig {
+            max_in_flight: 2,
+            overflow: CreateOverflow::Queue,
+        }));
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let handles = (0..8)
+            .map(|_| {
+                let limiter = limiter.clone();
+                let in_flight = in_flight.clone();
+                let max_in_flight = max_in_flight.clone();
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire().await.unwrap();
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(current, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        // all the creates are done, but never more than the max at the same time
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+        assert_eq!(in_flight.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_create_limit_reject() {
+        let manager = TaskManagerBuilder {
+            port: 0,
+            max_job_nums: 10,
+            snapshot_store: None,
+            scratch: None,
+            create_limit: Some(
+                serde_json::from_str(r#"{"max_in_flight": 1, "overflow": "reject"}"#).unwrap(),
+            ),
+            offset_store: None,
+        }
+        .build_shared();
+        let limiter = manager.create_limiter.as_ref().unwrap();
+
+        // the create exceeding the max is rejected
+        let permit = limiter.acquire().await.unwrap();
+        let err = manager
+            .create_sub_dataflow(tonic::Request::new(CreateSubDataflowRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::ResourceExhausted);
+
+        // the permit is released once the create fails
+        drop(permit);
+        let err = manager
+            .create_sub_dataflow(tonic::Request::new(CreateSubDataflowRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        assert!(limiter.acquire().await.is_ok());
+    }
 }
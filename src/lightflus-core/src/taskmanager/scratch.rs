@@ -0,0 +1,202 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use proto::common::ResourceId;
+
+/// scratch disk of the jobs running on the TaskManager
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ScratchConfig {
+    /// the directory under which the scratch directories of the jobs are allocated. It should be used by the TaskManager only
+    pub root: String,
+    /// how long the directories left by crashed jobs are kept before they're swept, in seconds
+    #[serde(default)]
+    pub retention: u64,
+}
+
+/// [`ScratchManager`] manages the lifecycle of the scratch directories of the jobs, e.g. for disk-spill buffers and local states.
+///
+/// Each job has its own directory under the root, it's allocated when the job is created and removed when the job stops.
+/// A directory left by a job which crashed with the TaskManager is an orphan. Orphans are swept on startup once they're older than the retention.
+#[derive(Debug, Clone)]
+pub struct ScratchManager {
+    root: PathBuf,
+    retention: Duration,
+}
+
+impl ScratchManager {
+    /// create the root if it doesn't exist
+    pub fn new(config: &ScratchConfig) -> io::Result<Self> {
+        let root = PathBuf::from(&config.root);
+        fs::create_dir_all(&root)?;
+        Ok(Self {
+            root,
+            retention: Duration::from_secs(config.retention),
+        })
+    }
+
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+
+    /// the scratch directory of the job. The ids are sanitized so that the directory is always a direct child of the root
+    pub fn get_job_dir(&self, job_id: &ResourceId) -> PathBuf {
+        let sanitize = |id: &str| {
+            id.chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>()
+        };
+        self.root.join(format!(
+            "{}.{}",
+            sanitize(&job_id.namespace_id),
+            sanitize(&job_id.resource_id)
+        ))
+    }
+
+    /// allocate an empty scratch directory for the job. The files left by its former run are removed
+    pub fn allocate(&self, job_id: &ResourceId) -> io::Result<PathBuf> {
+        let dir = self.get_job_dir(job_id);
+        remove_entry(&dir)?;
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// remove the scratch directory of the stopped job
+    pub fn release(&self, job_id: &ResourceId) -> io::Result<()> {
+        remove_entry(&self.get_job_dir(job_id))
+    }
+
+    /// remove the orphans which haven't been modified within the retention until `now`. It's called on startup when no job is running.
+    /// It returns the number of the removed orphans
+    pub fn sweep_orphans(&self, now: SystemTime) -> io::Result<usize> {
+        let mut swept = 0;
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let modified = entry.metadata()?.modified()?;
+            let expired = now
+                .duration_since(modified)
+                .map(|age| age >= self.retention)
+                .unwrap_or_default();
+            if expired {
+                remove_entry(&entry.path())?;
+                swept += 1;
+            }
+        }
+        Ok(swept)
+    }
+}
+
+fn remove_entry(path: &Path) -> io::Result<()> {
+    let result = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    match result {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{Duration, SystemTime},
+    };
+
+    use proto::common::ResourceId;
+
+    use super::{ScratchConfig, ScratchManager};
+
+    fn new_manager(name: &str, retention: u64) -> ScratchManager {
+        let root = std::env::temp_dir().join(format!(
+            "lightflus-scratch-{}-{}",
+            name,
+            common::utils::times::now_timestamp()
+        ));
+        ScratchManager::new(&ScratchConfig {
+            root: root.to_string_lossy().to_string(),
+            retention,
+        })
+        .unwrap()
+    }
+
+    fn job_id(resource_id: &str) -> ResourceId {
+        ResourceId::new("default", resource_id)
+    }
+
+    #[test]
+    fn test_scratch_dir_per_job() {
+        let manager = new_manager("jobs", 0);
+        let first = manager.allocate(&job_id("first")).unwrap();
+        let second = manager.allocate(&job_id("../second")).unwrap();
+        assert_ne!(first, second);
+        assert!(first.is_dir() && second.is_dir());
+        // the directories never escape the root
+        assert_eq!(first.parent(), Some(manager.get_root()));
+        assert_eq!(second.parent(), Some(manager.get_root()));
+
+        // the directory is cleaned when the job is created again
+        fs::write(first.join("spill"), b"events").unwrap();
+        assert_eq!(manager.allocate(&job_id("first")).unwrap(), first);
+        assert_eq!(fs::read_dir(&first).unwrap().count(), 0);
+
+        // the directory is removed when the job stops
+        fs::write(first.join("spill"), b"events").unwrap();
+        manager.release(&job_id("first")).unwrap();
+        assert!(!first.exists());
+        assert!(second.is_dir());
+        assert!(manager.release(&job_id("first")).is_ok());
+        fs::remove_dir_all(manager.get_root()).unwrap();
+    }
+
+    #[test]
+    fn test_sweep_orphans() {
+        let manager = new_manager("orphans", 60);
+        let orphan = manager.allocate(&job_id("crashed")).unwrap();
+        fs::write(orphan.join("spill"), b"events").unwrap();
+        fs::write(manager.get_root().join("stray"), b"file").unwrap();
+
+        // the orphans are kept within the retention
+        assert_eq!(manager.sweep_orphans(SystemTime::now()).unwrap(), 0);
+        assert!(orphan.is_dir());
+
+        let now = SystemTime::now() + Duration::from_secs(60);
+        assert_eq!(manager.sweep_orphans(now).unwrap(), 2);
+        assert!(!orphan.exists());
+        assert_eq!(fs::read_dir(manager.get_root()).unwrap().count(), 0);
+        fs::remove_dir_all(manager.get_root()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sweep_orphans_on_startup() {
+        use crate::taskmanager::rpc::TaskManagerBuilder;
+
+        let manager = new_manager("startup", 0);
+        let orphan = manager.allocate(&job_id("crashed")).unwrap();
+        let builder = TaskManagerBuilder {
+            port: 0,
+            max_job_nums: 1,
+            snapshot_store: None,
+            scratch: Some(ScratchConfig {
+                root: manager.get_root().to_string_lossy().to_string(),
+                retention: 0,
+            }),
+            create_limit: None,
+            offset_store: None,
+        };
+        let _server = builder.build();
+        assert!(!orphan.exists());
+        assert!(manager.get_root().is_dir());
+        fs::remove_dir_all(manager.get_root()).unwrap();
+    }
+}
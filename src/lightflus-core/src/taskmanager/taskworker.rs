@@ -1,25 +1,58 @@
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
-
+use std::collections::VecDeque;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use common::consts::default_configs::DEFAULT_CHANNEL_SIZE;
+use common::consts::default_configs::DEFAULT_REPORT_OPERATOR_ERROR_CONNECT_TIMEOUT_SECS;
+use common::consts::default_configs::DEFAULT_REPORT_OPERATOR_ERROR_RPC_TIMEOUT_SECS;
+use common::consts::default_configs::DEFAULT_STOP_DRAIN_TIMEOUT_MILLIS;
+use common::consts::env_keys::REPORT_OPERATOR_ERROR_CONNECT_TIMEOUT;
+use common::consts::env_keys::REPORT_OPERATOR_ERROR_RPC_TIMEOUT;
+use common::consts::env_keys::STOP_DRAIN_TIMEOUT;
 use common::event::LocalEvent;
+use common::net::gateway::coordinator::SafeCoordinatorRpcGateway;
+use common::net::OperatorErrorReporter;
+use common::snapshot::get_partition_id;
+use common::snapshot::CheckpointUploader;
+use common::snapshot::RemoteCheckpoint;
+use common::snapshot::SharedCheckpointMetrics;
+use common::snapshot::SnapshotStore;
 use common::types::ExecutorId;
+use common::utils::get_env;
 use common::utils::is_remote_operator;
 use proto::common::Ack;
 use proto::common::Dataflow;
+use proto::common::DataflowMeta;
 use proto::common::Heartbeat;
+use proto::common::HostAddr;
 use proto::common::KeyedDataEvent;
 
 use proto::common::KeyedEventSet;
 use proto::common::NodeType;
+use proto::common::OperatorStates;
+use proto::common::Sink;
 
 use proto::common::SubDataflowId;
 use proto::common::SubdataflowInfo;
+use proto::common::Throttle;
 use proto::taskmanager::SendEventToOperatorStatusEnum;
+use proto::taskmanager::StopMode;
 
 use stream::connector::SinkImpl;
+use stream::err::TaskError;
+use stream::offsets::SharedOffsetStore;
 use stream::task::EdgeBuilder;
+use stream::task::ErrorReporter;
 
 use stream::task::Task;
+use stream::watchdog::Watchdog;
+
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
 use crate::errors::taskmanager::TaskWorkerError;
 
@@ -27,15 +60,93 @@ use crate::errors::taskmanager::TaskWorkerError;
 pub struct TaskWorker {
     tasks: HashMap<ExecutorId, Task>,
     subdataflow_id: SubDataflowId,
+    /// the asynchronous task of the operator error reporter
+    _error_reporter_handler: Option<JoinHandle<()>>,
+    /// the asynchronous task which uploads the checkpoints to the remote snapshot store
+    _checkpoint_uploader_handler: Option<JoinHandle<()>>,
+    /// the asynchronous task of the watchdog of the operators, it's aborted once the subdataflow stops
+    watchdog_handler: Option<JoinHandle<()>>,
+    /// the operator which identifies the subdataflow in the remote snapshot store
+    partition: ExecutorId,
+    /// metrics of the checkpoint uploads, they're reported with the states of the partition operator
+    checkpoint_metrics: Option<SharedCheckpointMetrics>,
+    /// the operators of the tasks from upstreams to downstreams, in which order they're drained when the subdataflow stops
+    stop_order: Vec<ExecutorId>,
+    /// the scratch directory of the job, it's removed by the TaskManager when the job stops
+    scratch_dir: Option<PathBuf>,
 }
 
 pub(crate) struct TaskWorkerBuilder<'a> {
     dataflow: &'a Dataflow,
+    /// the address of coordinator which operator errors will be reported to
+    coordinator: Option<&'a HostAddr>,
+    /// the store which checkpoints are uploaded to
+    snapshot_store: Option<&'a SnapshotStore>,
+    /// the remote checkpoint which the states of the operators are restored from
+    restored: Option<&'a RemoteCheckpoint>,
+    /// the states of the operators in the savepoint which the dataflow is restored from. They take precedence over the remote checkpoint
+    savepoint: Option<&'a BTreeMap<ExecutorId, Vec<u8>>>,
+    /// the compatible states of the operators in the prior job's checkpoints. They're only restored if there's no savepoint or remote checkpoint
+    warm_start: Option<&'a BTreeMap<ExecutorId, Vec<u8>>>,
+    /// the scratch directory allocated for the job
+    scratch_dir: Option<&'a Path>,
+    /// the store which the sources commit their offsets to
+    offset_store: Option<&'a SharedOffsetStore>,
 }
 
 impl<'a> TaskWorkerBuilder<'a> {
     pub(crate) fn new(dataflow: &'a Dataflow) -> Self {
-        Self { dataflow }
+        Self {
+            dataflow,
+            coordinator: None,
+            snapshot_store: None,
+            restored: None,
+            savepoint: None,
+            warm_start: None,
+            scratch_dir: None,
+            offset_store: None,
+        }
+    }
+
+    pub(crate) fn with_coordinator(mut self, coordinator: Option<&'a HostAddr>) -> Self {
+        self.coordinator = coordinator.filter(|addr| addr.is_valid());
+        self
+    }
+
+    pub(crate) fn with_snapshot_store(
+        mut self,
+        snapshot_store: Option<&'a SnapshotStore>,
+        restored: Option<&'a RemoteCheckpoint>,
+    ) -> Self {
+        self.snapshot_store = snapshot_store;
+        self.restored = restored;
+        self
+    }
+
+    pub(crate) fn with_savepoint(
+        mut self,
+        savepoint: Option<&'a BTreeMap<ExecutorId, Vec<u8>>>,
+    ) -> Self {
+        self.savepoint = savepoint;
+        self
+    }
+
+    pub(crate) fn with_warm_start(
+        mut self,
+        warm_start: Option<&'a BTreeMap<ExecutorId, Vec<u8>>>,
+    ) -> Self {
+        self.warm_start = warm_start;
+        self
+    }
+
+    pub(crate) fn with_scratch_dir(mut self, scratch_dir: Option<&'a Path>) -> Self {
+        self.scratch_dir = scratch_dir;
+        self
+    }
+
+    pub(crate) fn with_offset_store(mut self, offset_store: Option<&'a SharedOffsetStore>) -> Self {
+        self.offset_store = offset_store;
+        self
     }
 
     pub(crate) async fn build(&self) -> Result<TaskWorker, TaskWorkerError> {
@@ -53,10 +164,84 @@ impl<'a> TaskWorkerBuilder<'a> {
                     .unwrap_or_default();
 
                 let job_id = self.dataflow.job_id.as_ref().unwrap();
+                let error_reporter_tx = self.coordinator.map(|addr| {
+                    let connect_timeout = get_env(REPORT_OPERATOR_ERROR_CONNECT_TIMEOUT)
+                        .and_then(|timeout| timeout.parse::<u64>().ok())
+                        .unwrap_or(DEFAULT_REPORT_OPERATOR_ERROR_CONNECT_TIMEOUT_SECS);
+                    let rpc_timeout = get_env(REPORT_OPERATOR_ERROR_RPC_TIMEOUT)
+                        .and_then(|timeout| timeout.parse::<u64>().ok())
+                        .unwrap_or(DEFAULT_REPORT_OPERATOR_ERROR_RPC_TIMEOUT_SECS);
+                    let (reporter, tx) = OperatorErrorReporter::new(
+                        SafeCoordinatorRpcGateway::with_timeout(addr, connect_timeout, rpc_timeout),
+                        DEFAULT_CHANNEL_SIZE,
+                    );
+                    worker._error_reporter_handler = Some(tokio::spawn(reporter));
+                    tx
+                });
+                worker.partition = get_partition_id(self.dataflow);
+                worker.scratch_dir = self.scratch_dir.map(Path::to_path_buf);
+                let checkpoint_tx = self.snapshot_store.map(|store| {
+                    let (uploader, tx) =
+                        CheckpointUploader::new(store, job_id, worker.partition, self.restored);
+                    let uploader = uploader.with_state_schemas(
+                        self.dataflow
+                            .nodes
+                            .iter()
+                            .map(|(operator_id, info)| (*operator_id, info.get_state_schema()))
+                            .collect(),
+                    );
+                    worker.checkpoint_metrics = Some(uploader.get_metrics());
+                    worker._checkpoint_uploader_handler = Some(tokio::spawn(uploader.run()));
+                    tx
+                });
                 let info_set = &self.dataflow.nodes;
-                self.dataflow.meta.iter().for_each(|meta| {
+                let restored_states = self
+                    .savepoint
+                    .or(self.restored.map(|checkpoint| &checkpoint.states))
+                    .or(self.warm_start);
+                // the dataflow has been validated, so its log level is valid
+                let log_level = self.dataflow.get_log_level().unwrap_or_default();
+                // chained operators are fused into the executor of the chain head, so no task is created for them
+                let chains = self.dataflow.get_operator_chains();
+                let chained = chains.values().flatten().collect::<BTreeSet<_>>();
+                let metas = self
+                    .dataflow
+                    .meta
+                    .iter()
+                    .filter(|meta| !chained.contains(&meta.center))
+                    .map(|meta| self.fuse_chain(meta, chains.get(&meta.center)))
+                    .collect::<Vec<_>>();
+                worker.stop_order = get_stop_order(&metas);
+                // the dataflow has been validated, so the timeout is positive
+                let mut watchdog = self
+                    .dataflow
+                    .watchdog
+                    .as_ref()
+                    .and_then(|watchdog| watchdog.get_stuck_timeout().ok())
+                    .and_then(|timeout| timeout.to_std().ok())
+                    .map(Watchdog::new);
+                metas.iter().for_each(|meta| {
                     let info = info_set.get(&meta.center).unwrap();
-                    let task = Task::new(job_id, &meta);
+                    let mut task = Task::new(job_id, &meta);
+                    task.set_log_level(log_level);
+                    task.set_event_dedup(self.dataflow.event_dedup.as_ref());
+                    task.set_offset_store(self.offset_store.cloned());
+                    task.set_watchdog(self.dataflow.watchdog.as_ref());
+                    if let Some(restored_states) = restored_states {
+                        let operators = chains.get(&meta.center);
+                        task.set_restored_states(
+                            restored_states
+                                .iter()
+                                .filter(|(operator_id, _)| {
+                                    **operator_id == meta.center
+                                        || operators
+                                            .map(|chain| chain.contains(operator_id))
+                                            .unwrap_or_default()
+                                })
+                                .map(|(operator_id, state)| (*operator_id, state.clone()))
+                                .collect(),
+                        );
+                    }
                     edge_builders.insert(meta.center, EdgeBuilder::local(info));
 
                     meta.neighbors.iter().for_each(|neighbor_id| {
@@ -97,16 +282,60 @@ impl<'a> TaskWorkerBuilder<'a> {
                             executor.add_external_sink(SinkImpl::from((job_id, operator_info)))
                         }
 
+                        chains
+                            .get(&executor_id)
+                            .iter()
+                            .flat_map(|chain| chain.iter())
+                            .for_each(|operator_id| {
+                                task.chain_operator(
+                                    &mut executor,
+                                    info_set.get(operator_id).unwrap(),
+                                )
+                            });
+
+                        let error_reporter = error_reporter_tx
+                            .as_ref()
+                            .map(|tx| ErrorReporter::new(job_id, executor_id, tx.clone()));
+                        error_reporter
+                            .iter()
+                            .for_each(|reporter| executor.set_error_reporter(reporter.clone()));
+
+                        if let (Some(watchdog), Some(progress)) =
+                            (watchdog.as_mut(), task.get_progress())
+                        {
+                            watchdog.watch(executor_id, progress, error_reporter);
+                        }
+
+                        checkpoint_tx
+                            .iter()
+                            .for_each(|tx| executor.set_checkpoint_sender(tx.clone()));
+
                         task.start(executor);
 
                         (executor_id, task)
                     })
                     .collect();
+                worker.watchdog_handler = watchdog.map(|watchdog| tokio::spawn(watchdog.run()));
 
                 worker
             })
             .map_err(|err| TaskWorkerError::DataflowValidateError(err))
     }
+
+    /// the head of a chain takes over the downstreams of the last chained operator
+    fn fuse_chain(&self, meta: &DataflowMeta, chain: Option<&Vec<u32>>) -> DataflowMeta {
+        match chain
+            .and_then(|chain| chain.last())
+            .and_then(|tail| self.dataflow.meta.iter().find(|meta| meta.center == *tail))
+        {
+            Some(tail) => DataflowMeta {
+                center: meta.center,
+                neighbors: tail.neighbors.clone(),
+                edge_types: tail.edge_types.clone(),
+            },
+            None => meta.clone(),
+        }
+    }
 }
 
 impl TaskWorker {
@@ -126,6 +355,11 @@ impl TaskWorker {
         }
     }
 
+    /// the scratch directory of the job, e.g. for disk-spill buffers
+    pub fn get_scratch_dir(&self) -> Option<&Path> {
+        self.scratch_dir.as_deref()
+    }
+
     #[inline]
     pub fn receive_heartbeat(&self, heartbeat: &Heartbeat) {
         match heartbeat.node_type() {
@@ -163,6 +397,125 @@ impl TaskWorker {
         }
     }
 
+    /// apply a new configuration to the Throttle operator at runtime
+    pub fn update_throttle(
+        &self,
+        executor_id: ExecutorId,
+        throttle: &Throttle,
+    ) -> Result<(), TaskWorkerError> {
+        match self.tasks.get(&executor_id) {
+            Some(task) => task
+                .update_throttle(throttle)
+                .map_err(|err| TaskWorkerError::InvalidThrottle(err.to_string())),
+            None => Err(TaskWorkerError::OperatorNotFound(executor_id)),
+        }
+    }
+
+    /// drain a single operator. It returns once the operator is drained while the other operators keep running
+    pub async fn drain_operator(&self, executor_id: ExecutorId) -> Result<(), TaskWorkerError> {
+        match self.tasks.get(&executor_id) {
+            Some(task) => task
+                .drain()
+                .await
+                .map_err(|err| TaskWorkerError::OperatorControlFailed(err.to_string())),
+            None => Err(TaskWorkerError::OperatorNotFound(executor_id)),
+        }
+    }
+
+    /// resume a drained operator
+    pub fn resume_operator(&self, executor_id: ExecutorId) -> Result<(), TaskWorkerError> {
+        match self.tasks.get(&executor_id) {
+            Some(task) => task
+                .resume()
+                .map_err(|err| TaskWorkerError::OperatorControlFailed(err.to_string())),
+            None => Err(TaskWorkerError::OperatorNotFound(executor_id)),
+        }
+    }
+
+    /// apply a new configuration to a sink operator while the other operators keep running
+    pub async fn update_sink_config(
+        &self,
+        executor_id: ExecutorId,
+        sink: &Sink,
+    ) -> Result<(), TaskWorkerError> {
+        match self.tasks.get(&executor_id) {
+            Some(task) => task.update_sink(sink).await.map_err(|err| match err {
+                TaskError::InvalidSink(err) => TaskWorkerError::InvalidSink(err),
+                err => TaskWorkerError::OperatorControlFailed(err.to_string()),
+            }),
+            None => Err(TaskWorkerError::OperatorNotFound(executor_id)),
+        }
+    }
+
+    /// tap the events passing through an operator. At most `sample_rate` events per second are sampled
+    pub fn tap_operator(
+        &self,
+        executor_id: ExecutorId,
+        sample_rate: f64,
+    ) -> Result<mpsc::Receiver<KeyedDataEvent>, TaskWorkerError> {
+        match self.tasks.get(&executor_id) {
+            Some(task) => task.tap(sample_rate).map_err(|err| match err {
+                TaskError::InvalidTap(err) => TaskWorkerError::InvalidTap(err.to_string()),
+                err => TaskWorkerError::OperatorControlFailed(err.to_string()),
+            }),
+            None => Err(TaskWorkerError::OperatorNotFound(executor_id)),
+        }
+    }
+
+    /// snapshot the states of all operators for a savepoint
+    pub async fn savepoint(&self) -> Result<OperatorStates, TaskWorkerError> {
+        let mut states = OperatorStates::default();
+        for task in self.tasks.values() {
+            states.states.extend(
+                task.savepoint()
+                    .await
+                    .map_err(|err| TaskWorkerError::OperatorControlFailed(err.to_string()))?,
+            );
+        }
+        Ok(states)
+    }
+
+    /// stop the tasks of the subdataflow. In drain mode, the sources stop fetching at once, then the tasks are stopped from upstreams to downstreams
+    /// and each of them stops after the events buffered in it are processed. The tasks which are not drained in time are stopped immediately.
+    /// The cancellation tokens of all tasks fire in the end, so the waits for the external systems are abandoned
+    pub async fn stop(&self, mode: StopMode) {
+        if mode == StopMode::Drain {
+            self.tasks.values().for_each(|task| task.cancel_source());
+            let timeout = get_env(STOP_DRAIN_TIMEOUT)
+                .and_then(|timeout| timeout.parse::<u64>().ok())
+                .unwrap_or(DEFAULT_STOP_DRAIN_TIMEOUT_MILLIS);
+            let deadline = tokio::time::Instant::now() + Duration::from_millis(timeout);
+            for executor_id in &self.stop_order {
+                let task = match self.tasks.get(executor_id) {
+                    Some(task) => task,
+                    None => continue,
+                };
+                match tokio::time::timeout_at(deadline, task.drain_and_stop()).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(err)) => tracing::warn!(
+                        "operator {} of subdataflow {:?} is not drained: {}",
+                        executor_id,
+                        &self.subdataflow_id,
+                        err
+                    ),
+                    Err(_) => {
+                        tracing::warn!(
+                            "drain of subdataflow {:?} times out at operator {}",
+                            &self.subdataflow_id,
+                            executor_id
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.tasks.values().for_each(|task| task.abort());
+        self.watchdog_handler
+            .iter()
+            .for_each(|handler| handler.abort());
+    }
+
     pub async fn get_state(&self) -> SubdataflowInfo {
         let mut info = SubdataflowInfo {
             execution_id: Some(self.subdataflow_id.clone()),
@@ -172,36 +525,90 @@ impl TaskWorker {
         for (executor_id, task) in &self.tasks {
             info.executors_info
                 .insert(*executor_id, task.get_state().await);
+            for chained_info in task.get_chained_states().await {
+                info.executors_info
+                    .insert(chained_info.executor_id, chained_info);
+            }
+        }
+        // the executors overwrite their own metrics, so the checkpoint metrics are merged when the states are read
+        if let (Some(metrics), Some(executor_info)) = (
+            &self.checkpoint_metrics,
+            info.executors_info.get_mut(&self.partition),
+        ) {
+            let metrics = metrics.lock().unwrap_or_else(|err| err.into_inner());
+            executor_info
+                .metrics
+                .extend(metrics.iter().map(|(name, value)| (name.clone(), *value)));
         }
 
         info
     }
 }
 
+/// sort the operators of the tasks from upstreams to downstreams. Downstreams on other workers are ignored
+fn get_stop_order(metas: &[DataflowMeta]) -> Vec<ExecutorId> {
+    let mut in_degrees = metas
+        .iter()
+        .map(|meta| (meta.center, 0))
+        .collect::<BTreeMap<_, _>>();
+    metas
+        .iter()
+        .flat_map(|meta| meta.neighbors.iter())
+        .for_each(|neighbor| {
+            if let Some(in_degree) = in_degrees.get_mut(neighbor) {
+                *in_degree += 1;
+            }
+        });
+
+    let mut ready = in_degrees
+        .iter()
+        .filter(|(_, in_degree)| **in_degree == 0)
+        .map(|(executor_id, _)| *executor_id)
+        .collect::<VecDeque<_>>();
+    let mut order = vec![];
+    while let Some(executor_id) = ready.pop_front() {
+        order.push(executor_id);
+        metas
+            .iter()
+            .filter(|meta| meta.center == executor_id)
+            .flat_map(|meta| meta.neighbors.iter())
+            .for_each(|neighbor| {
+                if let Some(in_degree) = in_degrees.get_mut(neighbor) {
+                    *in_degree -= 1;
+                    if *in_degree == 0 {
+                        ready.push_back(*neighbor);
+                    }
+                }
+            });
+    }
+    order
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
-    use proto::common::{Dataflow, OperatorInfo, ResourceId, SubDataflowId};
+    use proto::common::{Dataflow, DataflowMeta, OperatorInfo, ResourceId, SubDataflowId};
 
-    use super::TaskWorkerBuilder;
+    use super::{get_stop_order, TaskWorkerBuilder};
 
     #[tokio::test]
     async fn test_task_worker_build() {
         let dataflow = Dataflow {
-            job_id: Some(ResourceId {
-                resource_id: "resource_id".to_string(),
-                namespace_id: "namespace_id".to_string(),
-            }),
+            job_id: Some(ResourceId::new("namespace_id", "resource_id")),
             meta: vec![],
             nodes: HashMap::from_iter(vec![(0, OperatorInfo::default())].into_iter()),
             execution_id: Some(SubDataflowId {
-                job_id: Some(ResourceId {
-                    resource_id: "resource_id".to_string(),
-                    namespace_id: "namespace_id".to_string(),
-                }),
+                job_id: Some(ResourceId::new("namespace_id", "resource_id")),
                 sub_id: 0,
             }),
+            log_level: Default::default(),
+            restore_from: Default::default(),
+            warm_start_from: None,
+            event_dedup: None,
+            watchdog: None,
+            labels: Default::default(),
+            depends_on: vec![],
         };
         let builder = TaskWorkerBuilder::new(&dataflow);
         let result = builder.build().await;
@@ -210,4 +617,21 @@ mod tests {
 
     #[tokio::test]
     async fn test_edge_builder_build_out_edge() {}
+
+    #[test]
+    fn test_get_stop_order() {
+        let new_meta = |center: u32, neighbors: Vec<u32>| DataflowMeta {
+            center,
+            neighbors,
+            edge_types: Default::default(),
+        };
+        // operator 5 is on another worker
+        let metas = vec![
+            new_meta(3, vec![4]),
+            new_meta(2, vec![3, 5]),
+            new_meta(4, vec![]),
+            new_meta(1, vec![2, 4]),
+        ];
+        assert_eq!(get_stop_order(&metas), vec![1, 2, 3, 4]);
+    }
 }
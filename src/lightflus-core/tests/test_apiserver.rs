@@ -0,0 +1,203 @@
+#![cfg(unix)]
+
+use std::time::Duration;
+
+use lightflus_core::{
+    apiserver::{config::ApiServerConfig, new_api_server},
+    server::terminated,
+};
+use proto::{
+    common::{Ack, Dataflow, DataflowStates, Heartbeat, OperatorError, ResourceId, Response},
+    coordinator::{
+        coordinator_api_server::{CoordinatorApi, CoordinatorApiServer},
+        AddNodeRequest, AddNodeResponse, ClusterTopology, DataflowRuntimeStatus,
+        DeleteSavepointRequest, GetClusterTopologyRequest, GetDataflowRequest,
+        GetDataflowStatusRequest, ListDataflowsRequest, ListDataflowsResponse,
+        ListSavepointsResponse, Savepoint, TerminateDataflowsRequest, TerminateDataflowsResponse,
+        UpdateDataflowRequest, UpdateDataflowResponse,
+    },
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// how long the coordinator takes to respond the topology of the cluster
+const SLOW_RPC_DELAY: Duration = Duration::from_secs(1);
+
+/// a coordinator which responds the topology of the cluster slowly, and whose dataflows are always in their default status
+struct SlowCoordinator;
+
+#[tonic::async_trait]
+impl CoordinatorApi for SlowCoordinator {
+    async fn create_dataflow(
+        &self,
+        _: tonic::Request<Dataflow>,
+    ) -> Result<tonic::Response<Response>, tonic::Status> {
+        Err(tonic::Status::unimplemented("create_dataflow"))
+    }
+
+    async fn terminate_dataflow(
+        &self,
+        _: tonic::Request<ResourceId>,
+    ) -> Result<tonic::Response<Response>, tonic::Status> {
+        Err(tonic::Status::unimplemented("terminate_dataflow"))
+    }
+
+    async fn get_dataflow(
+        &self,
+        _: tonic::Request<GetDataflowRequest>,
+    ) -> Result<tonic::Response<DataflowStates>, tonic::Status> {
+        Ok(tonic::Response::new(DataflowStates::default()))
+    }
+
+    async fn receive_ack(
+        &self,
+        _: tonic::Request<Ack>,
+    ) -> Result<tonic::Response<Response>, tonic::Status> {
+        Err(tonic::Status::unimplemented("receive_ack"))
+    }
+
+    async fn receive_heartbeat(
+        &self,
+        _: tonic::Request<Heartbeat>,
+    ) -> Result<tonic::Response<Response>, tonic::Status> {
+        Err(tonic::Status::unimplemented("receive_heartbeat"))
+    }
+
+    async fn report_operator_error(
+        &self,
+        _: tonic::Request<OperatorError>,
+    ) -> Result<tonic::Response<Response>, tonic::Status> {
+        Err(tonic::Status::unimplemented("report_operator_error"))
+    }
+
+    async fn get_cluster_topology(
+        &self,
+        _: tonic::Request<GetClusterTopologyRequest>,
+    ) -> Result<tonic::Response<ClusterTopology>, tonic::Status> {
+        tokio::time::sleep(SLOW_RPC_DELAY).await;
+        Ok(tonic::Response::new(ClusterTopology::default()))
+    }
+
+    async fn trigger_savepoint(
+        &self,
+        _: tonic::Request<ResourceId>,
+    ) -> Result<tonic::Response<Savepoint>, tonic::Status> {
+        Err(tonic::Status::unimplemented("trigger_savepoint"))
+    }
+
+    async fn list_savepoints(
+        &self,
+        _: tonic::Request<ResourceId>,
+    ) -> Result<tonic::Response<ListSavepointsResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented("list_savepoints"))
+    }
+
+    async fn delete_savepoint(
+        &self,
+        _: tonic::Request<DeleteSavepointRequest>,
+    ) -> Result<tonic::Response<Response>, tonic::Status> {
+        Err(tonic::Status::unimplemented("delete_savepoint"))
+    }
+
+    async fn list_dataflows(
+        &self,
+        _: tonic::Request<ListDataflowsRequest>,
+    ) -> Result<tonic::Response<ListDataflowsResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented("list_dataflows"))
+    }
+
+    async fn terminate_dataflows(
+        &self,
+        _: tonic::Request<TerminateDataflowsRequest>,
+    ) -> Result<tonic::Response<TerminateDataflowsResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented("terminate_dataflows"))
+    }
+
+    async fn get_dataflow_status(
+        &self,
+        _: tonic::Request<GetDataflowStatusRequest>,
+    ) -> Result<tonic::Response<DataflowRuntimeStatus>, tonic::Status> {
+        Ok(tonic::Response::new(DataflowRuntimeStatus::default()))
+    }
+
+    async fn update_dataflow(
+        &self,
+        _: tonic::Request<UpdateDataflowRequest>,
+    ) -> Result<tonic::Response<UpdateDataflowResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented("update_dataflow"))
+    }
+
+    async fn add_node(
+        &self,
+        _: tonic::Request<AddNodeRequest>,
+    ) -> Result<tonic::Response<AddNodeResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented("add_node"))
+    }
+}
+
+/// send a GET request and read the whole response, the connection is closed once the response is done
+async fn get(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    stream
+        .write_all(
+            format!("GET {path} HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: close\r\n\r\n")
+                .as_bytes(),
+        )
+        .await
+        .unwrap();
+    let mut response = vec![];
+    stream.read_to_end(&mut response).await.unwrap();
+    String::from_utf8_lossy(&response).to_string()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_apiserver_graceful_shutdown() {
+    let coordinator_port = 8838;
+    let port = 8839;
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(CoordinatorApiServer::new(SlowCoordinator))
+            .serve(format!("127.0.0.1:{coordinator_port}").parse().unwrap()),
+    );
+
+    let mut config = ApiServerConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        ..Default::default()
+    };
+    config.coordinator.endpoints = vec![format!("127.0.0.1:{coordinator_port}")];
+    config.shutdown.grace_period = 10;
+    let server = tokio::spawn(new_api_server(&config).unwrap().serve(terminated()));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(get(port, "/ready").await.starts_with("HTTP/1.1 200"));
+
+    let events = tokio::spawn(get(port, "/resources/default/job/events"));
+    let slow = tokio::spawn(get(port, "/cluster"));
+    tokio::time::sleep(SLOW_RPC_DELAY / 4).await;
+    let status = std::process::Command::new("kill")
+        .args(["-TERM", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // the slow request is served although it's in flight when the server is terminated
+    let response = tokio::time::timeout(Duration::from_secs(5), slow)
+        .await
+        .expect("the slow request is not done")
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    // the stream of the status events is ended by the shutdown event
+    let events = tokio::time::timeout(Duration::from_secs(5), events)
+        .await
+        .expect("the stream of the events is not ended")
+        .unwrap();
+    assert!(events.contains("event: shutdown"), "{events}");
+
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("the server is not shut down")
+        .unwrap()
+        .unwrap();
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::Once, time::Duration};
+use std::{sync::Once, time::Duration};
 
 use common::{
     kafka::run_producer,
@@ -17,9 +17,8 @@ use lightflus_core::{
 };
 use proto::{
     common::{
-        flat_map, kafka_desc, key_by, operator_info, redis_desc::ConnectionOpts, reducer, sink,
-        source, DataTypeEnum, Dataflow, DataflowMeta, DeliveryGuarentee, FlatMap, Func, HostAddr,
-        KafkaDesc, KeyBy, OperatorInfo, RedisDesc, Reducer, ResourceId, Sink, Source,
+        kafka_desc, redis_desc::ConnectionOpts, sink, source, DataTypeEnum, Dataflow,
+        DeliveryGuarentee, Func, HostAddr, KafkaDesc, OperatorInfo, RedisDesc, Sink, Source,
     },
     coordinator::coordinator_api_server::CoordinatorApiServer,
 };
@@ -37,158 +36,96 @@ pub fn setup() {
 }
 
 fn setup_wordcount_dataflow(worker_port: u32) -> Dataflow {
-    Dataflow {
-        job_id: Some(ResourceId {
-            resource_id: "rsId".to_string(),
-            namespace_id: "nsId".to_string(),
-        }),
-        meta: vec![
-            DataflowMeta {
-                center: 0,
-                neighbors: vec![1],
-            },
-            DataflowMeta {
-                center: 1,
-                neighbors: vec![2],
-            },
-            DataflowMeta {
-                center: 2,
-                neighbors: vec![3],
-            },
-            DataflowMeta {
-                center: 3,
-                neighbors: vec![4],
-            },
-            DataflowMeta {
-                center: 4,
-                neighbors: vec![],
-            },
-        ],
-        nodes: HashMap::from_iter([
-            (
-                0,
-                OperatorInfo {
-                    operator_id: 0,
-                    host_addr: Some(HostAddr {
-                        host: "localhost".to_string(),
-                        port: worker_port,
-                    }),
-                    upstreams: vec![],
-                    details: Some(operator_info::Details::Source(Source {
-                        desc: Some(source::Desc::Kafka(KafkaDesc {
-                            brokers: vec!["localhost:9092".to_string()],
-                            topic: "topic-1".to_string(),
-                            opts: Some(kafka_desc::KafkaOptions {
-                                group: Some("word_count".to_string()),
-                                partition: None,
-                            }),
-                            data_type: DataTypeEnum::String as i32,
-                        })),
-                    })),
-                },
-            ),
-            (
-                1,
-                OperatorInfo {
-                    operator_id: 1,
-                    host_addr: Some(HostAddr {
-                        host: "localhost".to_string(),
-                        port: worker_port,
-                    }),
-                    upstreams: vec![0],
-                    details: Some(operator_info::Details::FlatMap(FlatMap {
-                        value: Some(flat_map::Value::Func(Func {
-                            function: [
-                                format!("function _operator_{}_process(v) ", "flatMap"),
-                                "{ return v.split(\" \").map(v => {
+    let source = OperatorInfo::source(
+        0,
+        Source {
+            desc: Some(source::Desc::Kafka(KafkaDesc {
+                brokers: vec!["localhost:9092".to_string()],
+                topic: "topic-1".to_string(),
+                opts: Some(kafka_desc::KafkaOptions {
+                    group: Some("word_count".to_string()),
+                    partition: None,
+                }),
+                data_type: DataTypeEnum::String as i32,
+                format: None,
+                sink_opts: None,
+            })),
+            sampling: None,
+            redactions: vec![],
+        },
+    );
+    let flat_map = OperatorInfo::flat_map(
+        1,
+        [
+            format!("function _operator_{}_process(v) ", "flatMap"),
+            "{ return v.split(\" \").map(v => {
                             return { t0: 1, t1: v };
                           })}"
-                                .to_string(),
-                            ]
-                            .concat(),
-                        })),
-                    })),
-                },
-            ),
-            (
-                2,
-                OperatorInfo {
-                    operator_id: 2,
-                    host_addr: Some(HostAddr {
-                        host: "localhost".to_string(),
-                        port: worker_port,
-                    }),
-                    upstreams: vec![1],
-                    details: Some(operator_info::Details::KeyBy(KeyBy {
-                        value: Some(key_by::Value::Func(Func {
-                            function: [
-                                format!("function _operator_{}_process(v) ", "keyBy"),
-                                "{ return v.t1 }".to_string(),
-                            ]
-                            .concat(),
-                        })),
-                    })),
-                },
-            ),
-            (
-                3,
-                OperatorInfo {
-                    operator_id: 3,
-                    host_addr: Some(HostAddr {
-                        host: "localhost".to_string(),
-                        port: worker_port,
-                    }),
-                    upstreams: vec![2],
-                    details: Some(operator_info::Details::Reducer(Reducer {
-                        value: Some(reducer::Value::Func(Func {
-                            function: [
-                                format!("function _operator_{}_process(v1, v2) ", "reduce"),
-                                "{ return { t1: v1.t1, t0: v1.t0 + v2.t0 }; }".to_string(),
-                            ]
-                            .concat(),
-                        })),
-                    })),
-                },
-            ),
-            (
-                4,
-                OperatorInfo {
-                    operator_id: 4,
-                    host_addr: Some(HostAddr {
-                        host: "localhost".to_string(),
-                        port: worker_port,
-                    }),
-                    upstreams: vec![3],
-                    details: Some(operator_info::Details::Sink(Sink {
-                        delivery_guarentee: DeliveryGuarentee::None as i32,
-                        desc: Some(sink::Desc::Redis(RedisDesc {
-                            connection_opts: Some(ConnectionOpts {
-                                host: "localhost:6379".to_string(),
-                                username: "".to_string(),
-                                password: "".to_string(),
-                                database: 0,
-                                tls: false,
-                            }),
-                            key_extractor: Some(Func {
-                                function: "function redis_extractor(a) { return a.t1 }".to_string(),
-                            }),
-                            value_extractor: Some(Func {
-                                function: "function redis_extractor(a) { return a.t0.toString() }"
-                                    .to_string(),
-                            }),
-                        })),
-                    })),
-                },
-            ),
-        ]),
-        execution_id: None,
-    }
+            .to_string(),
+        ]
+        .concat(),
+    );
+    let key_by = OperatorInfo::key_by(
+        2,
+        [
+            format!("function _operator_{}_process(v) ", "keyBy"),
+            "{ return v.t1 }".to_string(),
+        ]
+        .concat(),
+    );
+    let reduce = OperatorInfo::reduce(
+        3,
+        [
+            format!("function _operator_{}_process(v1, v2) ", "reduce"),
+            "{ return { t1: v1.t1, t0: v1.t0 + v2.t0 }; }".to_string(),
+        ]
+        .concat(),
+    );
+    let sink = OperatorInfo::sink(
+        4,
+        Sink {
+            delivery_guarentee: DeliveryGuarentee::None as i32,
+            desc: Some(sink::Desc::Redis(RedisDesc {
+                connection_opts: Some(ConnectionOpts {
+                    host: "localhost:6379".to_string(),
+                    username: "".to_string(),
+                    password: "".to_string(),
+                    database: 0,
+                    tls: false,
+                }),
+                key_extractor: Some(Func {
+                    function: "function redis_extractor(a) { return a.t1 }".to_string(),
+                }),
+                value_extractor: Some(Func {
+                    function: "function redis_extractor(a) { return a.t0.toString() }".to_string(),
+                }),
+            })),
+            batching: None,
+        },
+    );
+
+    [source, flat_map, key_by, reduce, sink]
+        .into_iter()
+        .fold(
+            Dataflow::builder().job_id("nsId", "rsId"),
+            |builder, operator| builder.operator(operator.with_host_addr("localhost", worker_port)),
+        )
+        .edge(0, 1)
+        .edge(1, 2)
+        .edge(2, 3)
+        .edge(3, 4)
+        .build()
+        .expect("wordcount dataflow should be valid")
 }
 
 fn setup_builder(port: usize) -> TaskManagerBuilder {
     TaskManagerBuilder {
         port,
         max_job_nums: 10,
+        snapshot_store: None,
+        scratch: None,
+        create_limit: None,
+        offset_store: None,
     }
 }
 
@@ -210,7 +147,10 @@ async fn test_e2e() {
             rpc_timeout: 5,
             connect_timeout: 5,
         },
-        storage: DataflowStorageBuilder::Memory,
+        storage: DataflowStorageBuilder::Memory {
+            ttl: None,
+            max_entries: None,
+        },
         heartbeat: HeartbeatBuilder {
             period: 3,
             connect_timeout: 3,
@@ -222,6 +162,12 @@ async fn test_e2e() {
             connect_timeout: 5,
             rpc_timeout: 5,
         },
+        snapshot_store: None,
+        submission: None,
+        backpressure: Default::default(),
+        liveness: Default::default(),
+        rebalance: Default::default(),
+        dependencies: Default::default(),
     };
 
     let addr = format!("0.0.0.0:{}", builder.port).parse().expect("msg");
@@ -1,13 +1,20 @@
-use std::{collections::HashMap, sync::Once};
+use std::{collections::HashMap, sync::Once, time::Duration};
 
-use common::net::gateway::taskmanager::SafeTaskManagerRpcGateway;
+use common::{
+    net::gateway::taskmanager::SafeTaskManagerRpcGateway, redis::RedisClient, types::TypedValue,
+    utils::get_env,
+};
 use lightflus_core::taskmanager::rpc::TaskManagerBuilder;
 use proto::{
     common::{
-        mapper, operator_info, Dataflow, DataflowMeta, ExecutorStatus, Func, HostAddr, Mapper,
-        OperatorInfo, ResourceId,
+        mapper, operator_info, redis_desc, sink, wasm_udf, Dataflow, DataflowMeta, ExecutorStatus,
+        Func, HostAddr, KeyedDataEvent, Mapper, OperatorInfo, RedisDesc, ResourceId, Sink,
+        SubDataflowStates, WasmUdf,
+    },
+    taskmanager::{
+        CreateSubDataflowRequest, OperatorRequest, StopDataflowRequest, StopMode,
+        TapOperatorRequest, UpdateSinkConfigRequest,
     },
-    taskmanager::CreateSubDataflowRequest,
 };
 use stream::initialize_v8;
 use tokio::task::JoinHandle;
@@ -17,6 +24,10 @@ fn setup_builder(port: usize) -> TaskManagerBuilder {
     TaskManagerBuilder {
         port,
         max_job_nums: 10,
+        snapshot_store: None,
+        scratch: None,
+        create_limit: None,
+        offset_store: None,
     }
 }
 
@@ -29,7 +40,6 @@ pub fn setup() {
     })
 }
 
-
 fn setup_server(port: usize) -> JoinHandle<Result<(), Error>> {
     let builder = setup_builder(port);
     let server = builder.build();
@@ -44,6 +54,7 @@ fn setup_dataflow(resource_id: ResourceId, server_port: usize) -> Dataflow {
     dataflow.meta = vec![DataflowMeta {
         center: 0,
         neighbors: vec![1],
+        edge_types: Default::default(),
     }];
 
     dataflow.nodes = HashMap::from_iter([
@@ -56,6 +67,13 @@ fn setup_dataflow(resource_id: ResourceId, server_port: usize) -> Dataflow {
                     port: server_port as u32,
                 }),
                 upstreams: vec![],
+                error_policy: None,
+                chaining: Default::default(),
+                input_schema: None,
+                output_schema: None,
+                state_limit: None,
+                headers: Default::default(),
+                processing_timeout: None,
                 details: Some(operator_info::Details::Mapper(Mapper {
                     value: Some(mapper::Value::Func(Func {
                         function: format!("_operator_{}_process", "map"),
@@ -72,6 +90,13 @@ fn setup_dataflow(resource_id: ResourceId, server_port: usize) -> Dataflow {
                     port: server_port as u32,
                 }),
                 upstreams: vec![0],
+                error_policy: None,
+                chaining: Default::default(),
+                input_schema: None,
+                output_schema: None,
+                state_limit: None,
+                headers: Default::default(),
+                processing_timeout: None,
                 details: Some(operator_info::Details::Mapper(Mapper {
                     value: Some(mapper::Value::Func(Func {
                         function: format!("_operator_{}_process", "map"),
@@ -94,30 +119,22 @@ async fn test_taskmanager_deploy_and_terminate() {
         port: 8793,
     });
 
-    let dataflow = setup_dataflow(
-        ResourceId {
-            resource_id: "rs_id".to_string(),
-            namespace_id: "ns_id".to_string(),
-        },
-        server_port,
-    );
+    let dataflow = setup_dataflow(ResourceId::new("ns_id", "rs_id"), server_port);
 
     let r = gateway
         .create_sub_dataflow(CreateSubDataflowRequest {
-            job_id: Some(ResourceId {
-                resource_id: "rs_id".to_string(),
-                namespace_id: "ns_id".to_string(),
-            }),
+            job_id: Some(ResourceId::new("ns_id", "rs_id")),
             dataflow: Some(dataflow),
+            coordinator: None,
+            restore_from: Default::default(),
+            savepoint: None,
+            warm_start_from: vec![],
         })
         .await;
     assert!(r.is_ok());
 
     let r = gateway
-        .get_sub_dataflow(ResourceId {
-            resource_id: "rs_id".to_string(),
-            namespace_id: "ns_id".to_string(),
-        })
+        .get_sub_dataflow(ResourceId::new("ns_id", "rs_id"))
         .await;
     assert!(r.is_ok());
 
@@ -133,12 +150,304 @@ async fn test_taskmanager_deploy_and_terminate() {
         .for_each(|(_, info)| assert_eq!(info.status(), ExecutorStatus::Running));
 
     let r = gateway
-        .stop_dataflow(ResourceId {
-            resource_id: "rs_id".to_string(),
-            namespace_id: "ns_id".to_string(),
+        .stop_dataflow(StopDataflowRequest {
+            job_id: Some(ResourceId::new("ns_id", "rs_id")),
+            mode: StopMode::Drain as i32,
         })
         .await;
     assert!(r.is_ok());
 
     server_1.abort();
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_taskmanager_drain_and_resume_operator() {
+    setup();
+    let server_port = 8797;
+    let server = setup_server(server_port);
+
+    let gateway = SafeTaskManagerRpcGateway::new(&HostAddr {
+        host: "localhost".to_string(),
+        port: server_port as u32,
+    });
+    let job_id = ResourceId::new("ns_id", "drain_rs_id");
+
+    let r = gateway
+        .create_sub_dataflow(CreateSubDataflowRequest {
+            job_id: Some(job_id.clone()),
+            dataflow: Some(setup_dataflow(job_id.clone(), server_port)),
+            coordinator: None,
+            restore_from: Default::default(),
+            savepoint: None,
+            warm_start_from: vec![],
+        })
+        .await;
+    assert!(r.is_ok());
+
+    let get_status = |states: SubDataflowStates, operator_id: u32| {
+        states
+            .subdataflow_infos
+            .unwrap_or_default()
+            .executors_info
+            .get(&operator_id)
+            .map(|info| info.status())
+    };
+
+    let r = gateway
+        .drain_operator(OperatorRequest {
+            job_id: Some(job_id.clone()),
+            operator_id: 1,
+        })
+        .await;
+    assert!(r.is_ok());
+
+    // only the drained operator is affected
+    let states = gateway.get_sub_dataflow(job_id.clone()).await.expect("msg");
+    assert_eq!(get_status(states.clone(), 1), Some(ExecutorStatus::Drained));
+    assert_eq!(get_status(states, 0), Some(ExecutorStatus::Running));
+
+    let r = gateway
+        .resume_operator(OperatorRequest {
+            job_id: Some(job_id.clone()),
+            operator_id: 1,
+        })
+        .await;
+    assert!(r.is_ok());
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let states = gateway.get_sub_dataflow(job_id.clone()).await.expect("msg");
+    assert_eq!(get_status(states.clone(), 1), Some(ExecutorStatus::Running));
+    assert_eq!(get_status(states, 0), Some(ExecutorStatus::Running));
+
+    // unknown operator
+    let r = gateway
+        .drain_operator(OperatorRequest {
+            job_id: Some(job_id.clone()),
+            operator_id: 10,
+        })
+        .await;
+    assert_eq!(r.unwrap_err().code(), tonic::Code::NotFound);
+
+    let r = gateway
+        .stop_dataflow(StopDataflowRequest {
+            job_id: Some(job_id),
+            mode: StopMode::Immediate as i32,
+        })
+        .await;
+    assert!(r.is_ok());
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_taskmanager_wasm_udf() {
+    setup();
+    let server_port = 8806;
+    let server = setup_server(server_port);
+
+    let gateway = SafeTaskManagerRpcGateway::new(&HostAddr {
+        host: "localhost".to_string(),
+        port: server_port as u32,
+    });
+    let job_id = ResourceId::new("ns_id", "wasm_rs_id");
+
+    let mut dataflow = setup_dataflow(job_id.clone(), server_port);
+    dataflow.nodes.get_mut(&0).unwrap().details = Some(operator_info::Details::WasmUdf(WasmUdf {
+        module: Some(wasm_udf::Module::Inline(
+            include_bytes!("../../stream/wasm/wrap.wat").to_vec(),
+        )),
+        ..Default::default()
+    }));
+
+    let r = gateway
+        .create_sub_dataflow(CreateSubDataflowRequest {
+            job_id: Some(job_id.clone()),
+            dataflow: Some(dataflow),
+            coordinator: None,
+            restore_from: Default::default(),
+            savepoint: None,
+            warm_start_from: vec![],
+        })
+        .await;
+    assert!(r.is_ok());
+
+    // the outputs of the function are observed by the input of the downstream operator
+    let mut tap = gateway
+        .tap_operator(TapOperatorRequest {
+            job_id: Some(job_id.clone()),
+            operator_id: 1,
+            sample_rate: 1000.0,
+        })
+        .await
+        .expect("msg");
+
+    let r = gateway
+        .send_event_to_operator(
+            KeyedDataEvent::builder()
+                .job_id(&job_id)
+                .to_operator_id(0)
+                .payload(TypedValue::from_json_value(serde_json::json!({"a": 1})))
+                .build(),
+        )
+        .await;
+    assert!(r.is_ok());
+
+    let event = tokio::time::timeout(Duration::from_secs(5), tap.message())
+        .await
+        .expect("msg")
+        .expect("msg")
+        .expect("msg");
+    assert_eq!(event.from_operator_id, 0);
+    assert_eq!(
+        event
+            .data
+            .iter()
+            .map(|entry| TypedValue::from(entry).to_json_value())
+            .collect::<Vec<_>>(),
+        vec![serde_json::json!([{"a": 1}])]
+    );
+
+    let r = gateway
+        .stop_dataflow(StopDataflowRequest {
+            job_id: Some(job_id),
+            mode: StopMode::Immediate as i32,
+        })
+        .await;
+    assert!(r.is_ok());
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_taskmanager_update_sink_config() {
+    setup();
+    let server_port = 8837;
+    let server = setup_server(server_port);
+
+    let gateway = SafeTaskManagerRpcGateway::new(&HostAddr {
+        host: "localhost".to_string(),
+        port: server_port as u32,
+    });
+    let job_id = ResourceId::new("ns_id", "sink_rs_id");
+    let redis_host = get_env("REDIS_HOST").unwrap_or("localhost".to_string());
+    let redis_desc = |host: &str, database: i64| RedisDesc {
+        connection_opts: Some(redis_desc::ConnectionOpts {
+            host: host.to_string(),
+            database,
+            ..Default::default()
+        }),
+        key_extractor: Some(Func {
+            function: "function redis_extractor(a) { return a.key }".to_string(),
+        }),
+        value_extractor: Some(Func {
+            function: "function redis_extractor(a) { return a.value }".to_string(),
+        }),
+    };
+    let redis_sink = |host: &str, database: i64| Sink {
+        desc: Some(sink::Desc::Redis(redis_desc(host, database))),
+        ..Default::default()
+    };
+
+    let mut dataflow = setup_dataflow(job_id.clone(), server_port);
+    dataflow.nodes.get_mut(&0).unwrap().details = Some(operator_info::Details::Mapper(Mapper {
+        value: Some(mapper::Value::Func(Func {
+            function: "function _operator_map_process(a) { return a }".to_string(),
+        })),
+    }));
+    dataflow.nodes.get_mut(&1).unwrap().details =
+        Some(operator_info::Details::Sink(redis_sink(&redis_host, 0)));
+    let r = gateway
+        .create_sub_dataflow(CreateSubDataflowRequest {
+            job_id: Some(job_id.clone()),
+            dataflow: Some(dataflow),
+            coordinator: None,
+            restore_from: Default::default(),
+            savepoint: None,
+            warm_start_from: vec![],
+        })
+        .await;
+    assert!(r.is_ok());
+
+    let keys = (0..3)
+        .map(|index| format!("sink-update-{}-{}", index, common::utils::uuid()))
+        .collect::<Vec<_>>();
+    let send = |key: String| {
+        gateway.send_event_to_operator(
+            KeyedDataEvent::builder()
+                .job_id(&job_id)
+                .to_operator_id(0)
+                .payload(TypedValue::from_json_value(
+                    serde_json::json!({"key": key, "value": 10}),
+                ))
+                .build(),
+        )
+    };
+    // wait until the key is written to the database, or the timeout elapses
+    let written = |key: String, database: i64| {
+        let mut client = RedisClient::new(&redis_desc(&redis_host, database));
+        async move {
+            for _ in 0..50 {
+                if !client
+                    .get(&TypedValue::String(key.clone()))
+                    .unwrap_or_default()
+                    .is_empty()
+                {
+                    return true;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            false
+        }
+    };
+
+    assert!(send(keys[0].clone()).await.is_ok());
+    assert!(written(keys[0].clone(), 0).await);
+
+    // the sink writes to the new database while the pipeline keeps flowing
+    let r = gateway
+        .update_sink_config(UpdateSinkConfigRequest {
+            job_id: Some(job_id.clone()),
+            operator_id: 1,
+            sink: Some(redis_sink(&redis_host, 1)),
+        })
+        .await;
+    assert!(r.is_ok());
+    assert!(send(keys[1].clone()).await.is_ok());
+    assert!(written(keys[1].clone(), 1).await);
+    let mut client = RedisClient::new(&redis_desc(&redis_host, 0));
+    assert!(client
+        .get(&TypedValue::String(keys[1].clone()))
+        .unwrap_or_default()
+        .is_empty());
+
+    // an invalid configuration is rejected, and the sink keeps writing to the new database
+    let r = gateway
+        .update_sink_config(UpdateSinkConfigRequest {
+            job_id: Some(job_id.clone()),
+            operator_id: 1,
+            sink: Some(redis_sink("", 2)),
+        })
+        .await;
+    assert_eq!(r.unwrap_err().code(), tonic::Code::InvalidArgument);
+    assert!(send(keys[2].clone()).await.is_ok());
+    assert!(written(keys[2].clone(), 1).await);
+
+    // only the sink operators can be updated
+    let r = gateway
+        .update_sink_config(UpdateSinkConfigRequest {
+            job_id: Some(job_id.clone()),
+            operator_id: 0,
+            sink: Some(redis_sink(&redis_host, 1)),
+        })
+        .await;
+    assert_eq!(r.unwrap_err().code(), tonic::Code::FailedPrecondition);
+
+    let r = gateway
+        .stop_dataflow(StopDataflowRequest {
+            job_id: Some(job_id),
+            mode: StopMode::Immediate as i32,
+        })
+        .await;
+    assert!(r.is_ok());
+
+    server.abort();
+}
@@ -0,0 +1,32 @@
+use common::utils::get_env;
+use lightflus_core::server::load_builder;
+
+use stream::initialize_v8;
+
+const DEFAULT_WORKER_THREADS_NUM: usize = 100;
+
+/// The unified binary of Lightflus. The services it starts are decided by the `role` in the config file:
+/// - `coordinator`: the Coordinator and the HTTP API server
+/// - `taskmanager`: the TaskManager
+/// - `standalone`: all of them in one process, which is convenient for small deployments
+fn main() {
+    common::logging::init();
+    let worker_threads = get_env("WORKER_THREADS")
+        .and_then(|num| num.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_WORKER_THREADS_NUM);
+
+    let builder = load_builder();
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(worker_threads)
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(async {
+            if builder.role.runs_taskmanager() {
+                initialize_v8();
+            }
+            if let Err(err) = builder.serve().await {
+                tracing::error!("{} stopped: {}", builder.role, err);
+            }
+        });
+}
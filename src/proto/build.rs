@@ -1,5 +1,21 @@
+// /// messages of a dataflow, which can be defined in JSON or YAML
+// const DATAFLOW_TYPES: &[&str] = &[
+//     ".common.Dataflow", ".common.DataflowMeta", ".common.OperatorInfo", ".common.StateLimit",
+//     ".common.PayloadSchema", ".common.ErrorPolicy", ".common.Backoff", ".common.Reducer",
+//     ".common.FlatMap", ".common.Join", ".common.Mapper", ".common.Func", ".common.Project",
+//     ".common.Throttle", ".common.Deduplicate", ".common.WasmUdf", ".common.FilterExpr",
+//     ".common.MapExpr", ".common.AsyncLookup", ".common.Route", ".common.SortBuffer",
+//     ".common.Filter", ".common.KeyBy", ".common.Sink", ".common.SinkBatching", ".common.ConstOp",
+//     ".common.Source", ".common.SourceSampling", ".common.Redaction", ".common.KafkaDesc",
+//     ".common.CsvFormat", ".common.AvroFormat", ".common.ProtobufFormat", ".common.MysqlDesc",
+//     ".common.RedisDesc", ".common.Window", ".common.Trigger", ".common.Time", ".common.EventDedup",
+//     ".common.OperatorWatchdog",
+// ];
+
 /**
- * If proto has been changed. You must remove all comments and rerun build.rs to generate new rust files
+ * If proto has been changed. You must remove all comments and rerun build.rs to generate new rust files.
+ * The generated files are shared by all features, so nothing is generated per feature: the client and server modules
+ * of the services are gated by the `cfg` attributes set on them here, and the modules of the crate are gated in `lib.rs`
  */
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     // let mut config = prost_build::Config::new();
@@ -34,18 +50,34 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     //         "#[derive(serde::Serialize,serde::Deserialize,Eq,Hash,PartialOrd)]",
     //     )
     //     .out_dir("src");
+    // for path in DATAFLOW_TYPES {
+    //     config
+    //         .type_attribute(path, "#[derive(serde::Serialize,serde::Deserialize)]")
+    //         .enum_attribute(path, "#[serde(rename_all = \"snake_case\")]");
+    // }
+    // for path in DATAFLOW_TYPES
+    //     .iter()
+    //     .chain(&[".common.ResourceId", ".common.HostAddr", ".common.SubDataflowId"])
+    // {
+    //     config.message_attribute(path, "#[serde(default)]");
+    // }
 
-    // let generator = tonic_build::configure().service_generator();
-    // config.service_generator(generator).compile_protos(
-    //     &[
-    //         "../../proto/common/common.proto",
-    //         "../../proto/common/event.proto",
-    //         "../../proto/common/stream.proto",
-    //         "../../proto/coordinator/coordinator.proto",
-    //         "../../proto/taskmanager/taskmanager.proto",
-    //         "../../proto/apiserver/apiserver.proto",
-    //     ],
-    //     &["../../proto"],
-    // )?;
+    // tonic_build::configure()
+    //     .client_mod_attribute(".coordinator", "#[cfg(feature = \"coordinator-client\")]")
+    //     .server_mod_attribute(".coordinator", "#[cfg(feature = \"coordinator-server\")]")
+    //     .client_mod_attribute(".taskmanager", "#[cfg(feature = \"worker-client\")]")
+    //     .server_mod_attribute(".taskmanager", "#[cfg(feature = \"worker-server\")]")
+    //     .compile_with_config(
+    //         config,
+    //         &[
+    //             "../../proto/common/common.proto",
+    //             "../../proto/common/event.proto",
+    //             "../../proto/common/stream.proto",
+    //             "../../proto/coordinator/coordinator.proto",
+    //             "../../proto/taskmanager/taskmanager.proto",
+    //             "../../proto/apiserver/apiserver.proto",
+    //         ],
+    //         &["../../proto"],
+    //     )?;
     Ok(())
 }
@@ -1,6 +1,7 @@
 /// *
 /// JobId, represents a stream job.
 #[derive(serde::Serialize, serde::Deserialize, Eq, PartialOrd, Ord, Hash)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ResourceId {
@@ -17,9 +18,81 @@ pub struct Response {
     pub status: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub err_msg: ::prost::alloc::string::String,
+    /// the placement of a dataflow. It's only set in the response of creating a dataflow
+    #[prost(message, optional, tag = "3")]
+    pub placement: ::core::option::Option<DataflowPlacement>,
+    /// warnings which don't fail the request, e.g. operators which don't match the savepoint a dataflow is restored from
+    #[prost(string, repeated, tag = "4")]
+    pub warnings: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// details of the failure. It's only set when the request fails and the response is attached to the status as details
+    #[prost(message, optional, tag = "5")]
+    pub error: ::core::option::Option<ErrorDetail>,
+}
+/// structured error of a failed Rpc request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ErrorDetail {
+    /// gRPC status code of the failure
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
+    /// whether the request may succeed if it's sent again
+    #[prost(bool, tag = "3")]
+    pub retryable: bool,
+}
+/// the placement of a submitted dataflow
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DataflowPlacement {
+    /// job id of the dataflow
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<ResourceId>,
+    /// operator id -> the address of TaskManager where the operator is deployed
+    #[prost(map = "uint32, message", tag = "2")]
+    pub operators: ::std::collections::HashMap<u32, HostAddr>,
+    /// all partitions of the dataflow. Each partition is a sub-dataflow deployed on one TaskManager
+    #[prost(message, repeated, tag = "3")]
+    pub partitions: ::prost::alloc::vec::Vec<PartitionPlacement>,
+    /// milliseconds since the unix epoch when the dataflow is created
+    #[prost(int64, tag = "4")]
+    pub created_at: i64,
+    /// milliseconds since the unix epoch when the dataflow is deployed or its status is changed the last time
+    #[prost(int64, tag = "5")]
+    pub updated_at: i64,
+    /// the resource version of the dataflow when it's deployed, see `coordinator.DataflowSummary`
+    #[prost(uint64, tag = "6")]
+    pub resource_version: u64,
+    /// the latest status of the dataflow, which is persisted every time it changes once the dataflow is deployed
+    #[prost(enumeration = "DataflowStatus", tag = "7")]
+    pub status: i32,
+    /// why the dataflow is in the status, e.g. the partitions which fail to start. It's empty if the status is expected
+    #[prost(string, tag = "8")]
+    pub status_reason: ::prost::alloc::string::String,
+}
+/// the placement and start status of a partition of a dataflow
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PartitionPlacement {
+    /// execution id of the sub-dataflow. It's empty if the TaskManager is unknown
+    #[prost(message, optional, tag = "1")]
+    pub execution_id: ::core::option::Option<SubDataflowId>,
+    /// the address of TaskManager
+    #[prost(message, optional, tag = "2")]
+    pub node: ::core::option::Option<HostAddr>,
+    /// ids of operators in this partition
+    #[prost(uint32, repeated, tag = "3")]
+    pub operator_ids: ::prost::alloc::vec::Vec<u32>,
+    /// start status of the partition
+    #[prost(enumeration = "PartitionStatus", tag = "4")]
+    pub status: i32,
+    /// the reason why the partition fails to start
+    #[prost(string, tag = "5")]
+    pub err_msg: ::prost::alloc::string::String,
 }
 /// The common structure of remote host address in Lightflus
 #[derive(serde::Serialize, serde::Deserialize, Eq, Hash)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct HostAddr {
@@ -29,6 +102,8 @@ pub struct HostAddr {
     pub port: u32,
 }
 /// The common structure of Timestamp in Lightflus
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Time {
@@ -43,6 +118,7 @@ pub struct Time {
 }
 /// Id of sub-dataflow execution
 #[derive(serde::Serialize, serde::Deserialize, Eq, Hash, PartialOrd)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SubDataflowId {
@@ -165,6 +241,155 @@ pub struct ExecutorInfo {
     pub executor_id: u32,
     #[prost(enumeration = "ExecutorStatus", tag = "2")]
     pub status: i32,
+    /// counters of the executor, e.g. the number of payloads dropped by the Throttle operator
+    #[prost(map = "string, uint64", tag = "3")]
+    pub metrics: ::std::collections::HashMap<::prost::alloc::string::String, u64>,
+    /// milliseconds since the unix epoch when the executor receives the latest heartbeat. It's zero if no heartbeat is received
+    #[prost(int64, tag = "4")]
+    pub last_heartbeat_at: i64,
+    /// the number of times the executor of the operator is created again
+    #[prost(uint32, tag = "5")]
+    pub restart_count: u32,
+}
+/// structured error report of an operator, sent from TaskWorker to Coordinator
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OperatorError {
+    /// job id of the operator
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<ResourceId>,
+    /// id of the operator
+    #[prost(uint32, tag = "2")]
+    pub operator_id: u32,
+    /// error kind
+    #[prost(enumeration = "OperatorErrorKind", tag = "3")]
+    pub kind: i32,
+    /// error message
+    #[prost(string, tag = "4")]
+    pub message: ::prost::alloc::string::String,
+    /// the timestamp when the error occurs
+    #[prost(message, optional, tag = "5")]
+    pub timestamp: ::core::option::Option<::prost_types::Timestamp>,
+}
+/// all states of an operator. It's the artifact of the operator in a remote checkpoint
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StateSnapshot {
+    /// keyed states
+    #[prost(message, repeated, tag = "1")]
+    pub keyed: ::prost::alloc::vec::Vec<StateEntry>,
+    /// the non-keyed broadcast state
+    #[prost(message, repeated, tag = "2")]
+    pub broadcast: ::prost::alloc::vec::Vec<StateEntry>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StateEntry {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub value: ::prost::alloc::vec::Vec<u8>,
+}
+/// encoded states of operators, keyed by operator id
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OperatorStates {
+    #[prost(map = "uint32, bytes", tag = "1")]
+    pub states: ::std::collections::HashMap<u32, ::prost::alloc::vec::Vec<u8>>,
+}
+/// start status of a partition
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum PartitionStatus {
+    Unspecified = 0,
+    /// the sub-dataflow is created on TaskManager successfully
+    Started = 1,
+    /// the sub-dataflow fails to be created
+    Failed = 2,
+}
+impl PartitionStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            PartitionStatus::Unspecified => "PARTITION_STATUS_UNSPECIFIED",
+            PartitionStatus::Started => "PARTITION_STATUS_STARTED",
+            PartitionStatus::Failed => "PARTITION_STATUS_FAILED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "PARTITION_STATUS_UNSPECIFIED" => Some(Self::Unspecified),
+            "PARTITION_STATUS_STARTED" => Some(Self::Started),
+            "PARTITION_STATUS_FAILED" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+/// The lifecycle of a dataflow. The coordinator only moves a dataflow between the statuses which are allowed by
+/// `DataflowStatus::can_transition_to`, e.g. a closed dataflow is never running again unless it's created again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum DataflowStatus {
+    /// the dataflow is saved but not deployed yet
+    Pending = 0,
+    /// all partitions of the dataflow are started
+    Running = 1,
+    /// the dataflow is being terminated
+    Closing = 2,
+    /// all partitions of the dataflow are stopped
+    Closed = 3,
+    /// the partitions of the dataflow are being started on the TaskManagers
+    Deploying = 4,
+    /// some partitions of the dataflow are not running, the reason tells which of them
+    Degraded = 5,
+    /// the coordinator resumes managing the deployed dataflow, e.g. after it restarts
+    Recovering = 6,
+    /// the sources of the dataflow stop reading while the other operators keep running
+    SourcesPaused = 7,
+    /// all operators of the dataflow are stopped with their states kept, so it can be deployed again
+    Suspended = 8,
+    /// none of the partitions of the dataflow is running
+    Failed = 9,
+}
+impl DataflowStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            DataflowStatus::Pending => "PENDING",
+            DataflowStatus::Running => "RUNNING",
+            DataflowStatus::Closing => "CLOSING",
+            DataflowStatus::Closed => "CLOSED",
+            DataflowStatus::Deploying => "DEPLOYING",
+            DataflowStatus::Degraded => "DEGRADED",
+            DataflowStatus::Recovering => "RECOVERING",
+            DataflowStatus::SourcesPaused => "SOURCES_PAUSED",
+            DataflowStatus::Suspended => "SUSPENDED",
+            DataflowStatus::Failed => "FAILED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "PENDING" => Some(Self::Pending),
+            "RUNNING" => Some(Self::Running),
+            "CLOSING" => Some(Self::Closing),
+            "CLOSED" => Some(Self::Closed),
+            "DEPLOYING" => Some(Self::Deploying),
+            "DEGRADED" => Some(Self::Degraded),
+            "RECOVERING" => Some(Self::Recovering),
+            "SOURCES_PAUSED" => Some(Self::SourcesPaused),
+            "SUSPENDED" => Some(Self::Suspended),
+            "FAILED" => Some(Self::Failed),
+            _ => None,
+        }
+    }
 }
 /// Enum of Data Type. each one corresponds to a primitive type in JavaScript
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
@@ -232,6 +457,7 @@ pub enum ErrorCode {
     DataflowOperatorInfoMissing = 6,
     CyclicDataflow = 7,
     DataflowConfigurationMissing = 8,
+    ServiceUnavailable = 9,
 }
 impl ErrorCode {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -253,6 +479,7 @@ impl ErrorCode {
             ErrorCode::DataflowConfigurationMissing => {
                 "ERROR_CODE_DATAFLOW_CONFIGURATION_MISSING"
             }
+            ErrorCode::ServiceUnavailable => "ERROR_CODE_SERVICE_UNAVAILABLE",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -271,6 +498,7 @@ impl ErrorCode {
             "ERROR_CODE_DATAFLOW_CONFIGURATION_MISSING" => {
                 Some(Self::DataflowConfigurationMissing)
             }
+            "ERROR_CODE_SERVICE_UNAVAILABLE" => Some(Self::ServiceUnavailable),
             _ => None,
         }
     }
@@ -304,6 +532,46 @@ impl NodeType {
         }
     }
 }
+/// kind of operator error
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum OperatorErrorKind {
+    Unspecified = 0,
+    /// operator fails to process an event
+    Execution = 1,
+    /// operator fails to send events to external sink
+    Sink = 2,
+    /// operator fails to send events to downstream operators
+    OutEdge = 3,
+    /// operator makes no progress within the timeout of the watchdog
+    Stuck = 4,
+}
+impl OperatorErrorKind {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            OperatorErrorKind::Unspecified => "OPERATOR_ERROR_KIND_UNSPECIFIED",
+            OperatorErrorKind::Execution => "OPERATOR_ERROR_KIND_EXECUTION",
+            OperatorErrorKind::Sink => "OPERATOR_ERROR_KIND_SINK",
+            OperatorErrorKind::OutEdge => "OPERATOR_ERROR_KIND_OUT_EDGE",
+            OperatorErrorKind::Stuck => "OPERATOR_ERROR_KIND_STUCK",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "OPERATOR_ERROR_KIND_UNSPECIFIED" => Some(Self::Unspecified),
+            "OPERATOR_ERROR_KIND_EXECUTION" => Some(Self::Execution),
+            "OPERATOR_ERROR_KIND_SINK" => Some(Self::Sink),
+            "OPERATOR_ERROR_KIND_OUT_EDGE" => Some(Self::OutEdge),
+            "OPERATOR_ERROR_KIND_STUCK" => Some(Self::Stuck),
+            _ => None,
+        }
+    }
+}
 /// status of executor
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
@@ -312,6 +580,10 @@ pub enum ExecutorStatus {
     Running = 1,
     Terminating = 2,
     Terminated = 3,
+    /// input is paused and all buffers are flushed
+    Drained = 4,
+    /// executor stops because of an error which its error policy fails on
+    Failed = 5,
 }
 impl ExecutorStatus {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -324,6 +596,8 @@ impl ExecutorStatus {
             ExecutorStatus::Running => "EXECUTOR_STATUS_RUNNING",
             ExecutorStatus::Terminating => "EXECUTOR_STATUS_TERMINATING",
             ExecutorStatus::Terminated => "EXECUTOR_STATUS_TERMINATED",
+            ExecutorStatus::Drained => "EXECUTOR_STATUS_DRAINED",
+            ExecutorStatus::Failed => "EXECUTOR_STATUS_FAILED",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -333,6 +607,8 @@ impl ExecutorStatus {
             "EXECUTOR_STATUS_RUNNING" => Some(Self::Running),
             "EXECUTOR_STATUS_TERMINATING" => Some(Self::Terminating),
             "EXECUTOR_STATUS_TERMINATED" => Some(Self::Terminated),
+            "EXECUTOR_STATUS_DRAINED" => Some(Self::Drained),
+            "EXECUTOR_STATUS_FAILED" => Some(Self::Failed),
             _ => None,
         }
     }
@@ -366,6 +642,28 @@ pub struct KeyedDataEvent {
     /// event id, generated by source
     #[prost(int64, tag = "9")]
     pub event_id: i64,
+    /// whether the event is delivered by a broadcast edge. It updates the broadcast state of the receiver instead of being processed
+    #[prost(bool, tag = "10")]
+    pub broadcast: bool,
+    /// sequence of the event among the events with the same key sent by the operator from_operator_id, starting from 1.
+    /// The receiver drops the events whose sequence is not increasing. 0 means the event is not sequenced
+    #[prost(uint64, tag = "11")]
+    pub sequence: u64,
+    /// epoch of the sequence. The sequences of an operator start over in a new epoch, e.g. after its TaskWorker restarts
+    #[prost(uint64, tag = "12")]
+    pub sequence_epoch: u64,
+    /// run of the operator from_operator_id in the epoch of the sequence, starting from 1. It increases every time the operator restarts,
+    /// and sinks discard the output of the superseded runs which isn't flushed yet. 0 means the event is not fenced
+    #[prost(uint64, tag = "13")]
+    pub restart_epoch: u64,
+    /// metadata of the event, e.g. the partition of the source, the ingest time and the trace id. They flow through the operators
+    /// without being part of the payload, and are emitted by the Kafka sinks as record headers
+    #[prost(map = "string, string", tag = "14")]
+    #[serde(default)]
+    pub headers: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
 }
 /// Nested message and enum types in `KeyedDataEvent`.
 pub mod keyed_data_event {
@@ -409,6 +707,8 @@ pub struct KeyedEventSet {
 }
 /// *
 /// StreamGraph metadata, it stores the structural information of a stream graph
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DataflowMeta {
@@ -418,9 +718,14 @@ pub struct DataflowMeta {
     /// center's neighbors
     #[prost(uint32, repeated, tag = "2")]
     pub neighbors: ::prost::alloc::vec::Vec<u32>,
+    /// types of the edges from the center to its neighbors. The edge is forward if its type is missing
+    #[prost(map = "uint32, enumeration(EdgeType)", tag = "3")]
+    pub edge_types: ::std::collections::HashMap<u32, i32>,
 }
 /// *
 /// OperatorInfo, stores detail information of an operator
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct OperatorInfo {
@@ -432,13 +737,45 @@ pub struct OperatorInfo {
     /// upstreams operator_id
     #[prost(uint32, repeated, tag = "3")]
     pub upstreams: ::prost::alloc::vec::Vec<u32>,
+    /// how the errors of processing or sinking events are handled. Failed events are skipped if it's not set
+    #[prost(message, optional, tag = "17")]
+    pub error_policy: ::core::option::Option<ErrorPolicy>,
+    /// whether the operator can be chained with its adjacent operators. Chaining is enabled by default
+    #[prost(enumeration = "Chaining", tag = "18")]
+    pub chaining: i32,
+    /// optional schema of the payloads the operator receives
+    #[prost(message, optional, tag = "20")]
+    pub input_schema: ::core::option::Option<PayloadSchema>,
+    /// optional schema of the payloads the operator emits
+    #[prost(message, optional, tag = "21")]
+    pub output_schema: ::core::option::Option<PayloadSchema>,
+    /// optional limit of the keyed state of the operator. The state size is unlimited if it's not set
+    #[prost(message, optional, tag = "25")]
+    pub state_limit: ::core::option::Option<StateLimit>,
+    /// metadata headers added to the events emitted by the operator, replacing the headers of the events with the same names
+    #[prost(map = "string, string", tag = "27")]
+    pub headers: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// how long the operator and the operators chained to it may take to process an event. Once it's exceeded, the JavaScript function processing
+    /// the event is interrupted and the event is abandoned. It's handled by the error policy without retries, e.g. it's sent to the dead-letter operator
+    /// with the header `lightflus.error_reason`, and the next event is processed. The states updated by the abandoned event are kept.
+    /// Events are never timed out if it's not set or zero. WasmUdf is limited by its own timeout, and the other native operators are not interrupted
+    #[prost(message, optional, tag = "28")]
+    pub processing_timeout: ::core::option::Option<Time>,
     /// optional for different operator type
-    #[prost(oneof = "operator_info::Details", tags = "5, 6, 7, 8, 9, 10, 11, 12")]
+    #[prost(
+        oneof = "operator_info::Details",
+        tags = "5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 19, 22, 23, 24, 26"
+    )]
     pub details: ::core::option::Option<operator_info::Details>,
 }
 /// Nested message and enum types in `OperatorInfo`.
 pub mod operator_info {
     /// optional for different operator type
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Details {
@@ -458,11 +795,254 @@ pub mod operator_info {
         Reducer(super::Reducer),
         #[prost(message, tag = "11")]
         FlatMap(super::FlatMap),
-        ///     Join join = 11;
         #[prost(message, tag = "12")]
         Window(super::Window),
+        #[prost(message, tag = "13")]
+        Project(super::Project),
+        #[prost(message, tag = "14")]
+        Throttle(super::Throttle),
+        #[prost(message, tag = "15")]
+        Deduplicate(super::Deduplicate),
+        #[prost(message, tag = "16")]
+        SortBuffer(super::SortBuffer),
+        #[prost(message, tag = "19")]
+        WasmUdf(super::WasmUdf),
+        #[prost(message, tag = "22")]
+        FilterExpr(super::FilterExpr),
+        #[prost(message, tag = "23")]
+        MapExpr(super::MapExpr),
+        ///     Join join = 11;
+        #[prost(message, tag = "24")]
+        AsyncLookup(super::AsyncLookup),
+        #[prost(message, tag = "26")]
+        Route(super::Route),
+    }
+}
+/// *
+/// Limit of the keyed state of an operator. The size of the state is the total bytes of the keys and values of the keyed state,
+/// the broadcast state is not counted
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StateLimit {
+    /// the max size of the keyed state in bytes, it must be positive
+    #[prost(uint64, tag = "1")]
+    pub max_bytes: u64,
+    /// what happens when a write makes the state exceed the limit
+    #[prost(enumeration = "state_limit::Policy", tag = "2")]
+    pub policy: i32,
+}
+/// Nested message and enum types in `StateLimit`.
+pub mod state_limit {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Policy {
+        /// writes of new keys are rejected. The existing keys can still be updated
+        RejectNewKeys = 0,
+        /// the least recently written keys are evicted until the state fits the limit
+        EvictOldest = 1,
+        /// the write is rejected and the operator fails
+        Fail = 2,
+    }
+    impl Policy {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Policy::RejectNewKeys => "POLICY_REJECT_NEW_KEYS",
+                Policy::EvictOldest => "POLICY_EVICT_OLDEST",
+                Policy::Fail => "POLICY_FAIL",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "POLICY_REJECT_NEW_KEYS" => Some(Self::RejectNewKeys),
+                "POLICY_EVICT_OLDEST" => Some(Self::EvictOldest),
+                "POLICY_FAIL" => Some(Self::Fail),
+                _ => None,
+            }
+        }
+    }
+}
+/// *
+/// Schema of the payloads of an operator. A payload is an object and only the declared fields are checked.
+/// At submission, the output schema of an upstream must satisfy the input schema of its downstream if both of them are declared.
+/// The input schema also fills the format mappings of a sink which are not configured, and the output schema fills the ones of a source
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PayloadSchema {
+    #[prost(message, repeated, tag = "1")]
+    pub fields: ::prost::alloc::vec::Vec<payload_schema::Field>,
+    /// how the input events are validated at runtime. It's ignored by the output schema
+    #[prost(enumeration = "payload_schema::Validation", tag = "2")]
+    pub validation: i32,
+    /// the fraction of input events which are validated in sampling mode, in (0, 1]
+    #[prost(double, tag = "3")]
+    pub sample_fraction: f64,
+}
+/// Nested message and enum types in `PayloadSchema`.
+pub mod payload_schema {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Field {
+        #[prost(string, tag = "1")]
+        pub name: ::prost::alloc::string::String,
+        /// unspecified means any type. A bigint value is also a number
+        #[prost(enumeration = "super::DataTypeEnum", tag = "2")]
+        pub data_type: i32,
+        /// whether the field must be present and not null
+        #[prost(bool, tag = "3")]
+        pub required: bool,
+    }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Validation {
+        /// the schema is only checked at submission
+        Disabled = 0,
+        /// a fraction of input events are validated
+        Sampling = 1,
+        /// all input events are validated
+        Strict = 2,
+    }
+    impl Validation {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Validation::Disabled => "VALIDATION_DISABLED",
+                Validation::Sampling => "VALIDATION_SAMPLING",
+                Validation::Strict => "VALIDATION_STRICT",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "VALIDATION_DISABLED" => Some(Self::Disabled),
+                "VALIDATION_SAMPLING" => Some(Self::Sampling),
+                "VALIDATION_STRICT" => Some(Self::Strict),
+                _ => None,
+            }
+        }
+    }
+}
+/// *
+/// Error policy of an operator. It's applied to the errors of processing events and sinking events to external sinks
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ErrorPolicy {
+    #[prost(oneof = "error_policy::Policy", tags = "1, 2, 3, 4")]
+    pub policy: ::core::option::Option<error_policy::Policy>,
+}
+/// Nested message and enum types in `ErrorPolicy`.
+pub mod error_policy {
+    /// the operator stops and its status becomes FAILED
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Fail {}
+    /// the failed event is dropped
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Skip {}
+    /// the failed event is retried with backoff, then the fallback policy is applied if it still fails.
+    /// Errors which can't be recovered by retrying, e.g. events rejected by the external sink, are handled by the fallback policy directly
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Retry {
+        /// max number of retries, must be positive
+        #[prost(uint32, tag = "1")]
+        pub attempts: u32,
+        #[prost(message, optional, tag = "2")]
+        pub backoff: ::core::option::Option<super::Backoff>,
+        /// it can't be another Retry. Failed events are skipped if it's not set
+        #[prost(message, optional, boxed, tag = "3")]
+        pub fallback: ::core::option::Option<
+            ::prost::alloc::boxed::Box<super::ErrorPolicy>,
+        >,
+    }
+    /// the failed event is sent to the dead-letter operator. It must be one of the downstreams of the operator
+    /// and it doesn't receive the other events
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DeadLetter {
+        #[prost(uint32, tag = "1")]
+        pub sink: u32,
+    }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Policy {
+        #[prost(message, tag = "1")]
+        Fail(Fail),
+        #[prost(message, tag = "2")]
+        Skip(Skip),
+        #[prost(message, tag = "3")]
+        Retry(::prost::alloc::boxed::Box<Retry>),
+        #[prost(message, tag = "4")]
+        DeadLetter(DeadLetter),
     }
 }
+/// exponential backoff with optional full jitter
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Backoff {
+    /// the ceiling of the first delay in milliseconds
+    #[prost(uint64, tag = "1")]
+    pub base: u64,
+    /// the max delay in milliseconds
+    #[prost(uint64, tag = "2")]
+    pub max: u64,
+    #[prost(bool, tag = "3")]
+    pub jitter: bool,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Reducer {
@@ -471,6 +1051,8 @@ pub struct Reducer {
 }
 /// Nested message and enum types in `Reducer`.
 pub mod reducer {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Value {
@@ -478,6 +1060,8 @@ pub mod reducer {
         Func(super::Func),
     }
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct FlatMap {
@@ -486,6 +1070,8 @@ pub struct FlatMap {
 }
 /// Nested message and enum types in `FlatMap`.
 pub mod flat_map {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Value {
@@ -493,6 +1079,8 @@ pub mod flat_map {
         Func(super::Func),
     }
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Join {
@@ -501,6 +1089,8 @@ pub struct Join {
 }
 /// Nested message and enum types in `Join`.
 pub mod join {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct StreamJoin {
@@ -509,6 +1099,8 @@ pub mod join {
         #[prost(message, optional, tag = "2")]
         pub func: ::core::option::Option<super::Func>,
     }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Value {
@@ -516,6 +1108,8 @@ pub mod join {
         StreamJoin(StreamJoin),
     }
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Mapper {
@@ -524,6 +1118,8 @@ pub struct Mapper {
 }
 /// Nested message and enum types in `Mapper`.
 pub mod mapper {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Value {
@@ -531,52 +1127,505 @@ pub mod mapper {
         Func(super::Func),
     }
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Func {
     #[prost(string, tag = "1")]
     pub function: ::prost::alloc::string::String,
 }
+/// *
+/// Project operator, it builds a new payload which only contains the projected fields without any UDF.
+/// Path expressions are JSON-path-like, for example:
+/// - `$.user.name`: nested field
+/// - `$.tags\[0\]`, `$['first name']`: array index and quoted field name
+/// - `$.user?(@.email).name`: existence filter. The payload is dropped if `user.email` is missing or null
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct Filter {
-    #[prost(oneof = "filter::Value", tags = "1")]
-    pub value: ::core::option::Option<filter::Value>,
+pub struct Project {
+    /// fields of the new payload
+    #[prost(message, repeated, tag = "1")]
+    pub fields: ::prost::alloc::vec::Vec<project::Field>,
+    /// name of the projected field which the event key will be re-derived from. the key is kept if it's empty
+    #[prost(string, tag = "2")]
+    pub key_field: ::prost::alloc::string::String,
 }
-/// Nested message and enum types in `Filter`.
-pub mod filter {
+/// Nested message and enum types in `Project`.
+pub mod project {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
     #[allow(clippy::derive_partial_eq_without_eq)]
-    #[derive(Clone, PartialEq, ::prost::Oneof)]
-    pub enum Value {
-        #[prost(message, tag = "1")]
-        Func(super::Func),
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Field {
+        /// name of the field in the new payload
+        #[prost(string, tag = "1")]
+        pub name: ::prost::alloc::string::String,
+        /// path expression over the payload
+        #[prost(string, tag = "2")]
+        pub path: ::prost::alloc::string::String,
+        /// the type which the value will be cast into. unspecified means no cast.
+        /// only string, bigint, number and boolean are supported
+        #[prost(enumeration = "super::DataTypeEnum", tag = "3")]
+        pub cast: i32,
+        /// JSON literal which is used if the path is missing or null
+        #[prost(string, optional, tag = "4")]
+        pub default_value: ::core::option::Option<::prost::alloc::string::String>,
     }
 }
+/// *
+/// Throttle operator, it caps the rate of a stream or only lets a sample of the stream pass through.
+/// Each payload of an event is throttled individually.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct KeyBy {
-    #[prost(oneof = "key_by::Value", tags = "1")]
-    pub value: ::core::option::Option<key_by::Value>,
+pub struct Throttle {
+    #[prost(oneof = "throttle::Mode", tags = "1, 2, 3")]
+    pub mode: ::core::option::Option<throttle::Mode>,
 }
-/// Nested message and enum types in `KeyBy`.
-pub mod key_by {
+/// Nested message and enum types in `Throttle`.
+pub mod throttle {
+    /// token-bucket rate limit
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct RateLimit {
+        /// rate of the tokens, must be positive
+        #[prost(double, tag = "1")]
+        pub events_per_sec: f64,
+        /// capacity of the bucket. it's max(1, events_per_sec) if it's zero
+        #[prost(uint32, tag = "2")]
+        pub burst: u32,
+        /// payloads without tokens are dropped if it's true.
+        /// Otherwise the operator stops receiving events until tokens are available, so the upstreams are blocked by backpressure
+        #[prost(bool, tag = "3")]
+        pub drop: bool,
+    }
+    /// probabilistic sampling
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Sampling {
+        /// fraction of payloads which are kept, in [0, 1]
+        #[prost(double, tag = "1")]
+        pub fraction: f64,
+    }
+    /// deterministic per-key sampling. Events whose hash of the key modulo `modulus` is zero are kept,
+    /// so the events of a key are either all kept or all dropped. The payload is hashed if the event has no key
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct KeySampling {
+        /// must be positive
+        #[prost(uint32, tag = "1")]
+        pub modulus: u32,
+    }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
-    pub enum Value {
+    pub enum Mode {
         #[prost(message, tag = "1")]
-        Func(super::Func),
+        RateLimit(RateLimit),
+        #[prost(message, tag = "2")]
+        Sampling(Sampling),
+        #[prost(message, tag = "3")]
+        KeySampling(KeySampling),
     }
 }
+/// *
+/// Deduplicate operator, it drops the payloads whose dedup key has been observed within the time horizon.
+/// Seen keys are kept in the state backend and expire after the horizon since they are first observed
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct Sink {
-    #[prost(enumeration = "DeliveryGuarentee", tag = "4")]
-    pub delivery_guarentee: i32,
-    #[prost(oneof = "sink::Desc", tags = "1, 2, 3")]
-    pub desc: ::core::option::Option<sink::Desc>,
-}
+pub struct Deduplicate {
+    /// path expression of the dedup key, see Project for the syntax. The key of the event is used if it's empty.
+    /// Payloads without the dedup key are never deduplicated
+    #[prost(string, tag = "1")]
+    pub key_path: ::prost::alloc::string::String,
+    /// how long a seen key is remembered, must be positive
+    #[prost(message, optional, tag = "2")]
+    pub horizon: ::core::option::Option<Time>,
+    /// operator id of the side output which duplicates are emitted to. It must be one of the downstreams of the operator
+    /// and it doesn't receive the unique payloads. Duplicates are dropped if it's not set
+    #[prost(uint32, optional, tag = "3")]
+    pub side_output: ::core::option::Option<u32>,
+}
+/// *
+/// WasmUdf operator, it processes each payload of an event by a user-defined function compiled to a WASM module.
+/// The module is compiled once per checksum and instantiated once per task. It must export
+/// - `memory`: the linear memory
+/// - `alloc(len: i32) -> i32`: allocate a buffer of `len` bytes for the input payload
+/// - `process(ptr: i32, len: i32) -> i64`: process the JSON-encoded payload in the buffer. It returns the output buffer whose pointer is
+/// the high 32 bits and length is the low 32 bits. The output buffer contains zero or more JSON-encoded payloads, each of which is
+/// prefixed by its length as a little-endian u32
+/// Traps, including running out of fuel or time, are handled by the error policy of the operator
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WasmUdf {
+    /// hex-encoded sha256 checksum of the module. It's required if the module is fetched from the url
+    #[prost(string, tag = "3")]
+    pub sha256: ::prost::alloc::string::String,
+    /// max fuel consumed by one invocation. The default limit is used if it's zero
+    #[prost(uint64, tag = "4")]
+    pub fuel: u64,
+    /// max time of one invocation. The default limit is used if it's not set
+    #[prost(message, optional, tag = "5")]
+    pub timeout: ::core::option::Option<Time>,
+    /// max linear memory of the module in bytes. The default limit is used if it's zero
+    #[prost(uint64, tag = "6")]
+    pub max_memory_bytes: u64,
+    #[prost(oneof = "wasm_udf::Module", tags = "1, 2")]
+    pub module: ::core::option::Option<wasm_udf::Module>,
+}
+/// Nested message and enum types in `WasmUdf`.
+pub mod wasm_udf {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Module {
+        /// binary or text format of the module
+        #[prost(bytes, tag = "1")]
+        Inline(::prost::alloc::vec::Vec<u8>),
+        /// url to fetch the module from
+        #[prost(string, tag = "2")]
+        Url(::prost::alloc::string::String),
+    }
+}
+/// *
+/// FilterExpr operator, it keeps the payloads for which a SQL-like expression over their fields is true, for example
+/// `amount > 100 AND country = 'SE'`. Payloads for which the expression is false or null are dropped.
+/// The expression supports
+/// - field references, including nested fields `user.name` and quoted names `"first name"`
+/// - literals: strings in single quotes, integers, decimals, TRUE, FALSE and NULL
+/// - comparison `=, <>, !=, <, <=, >, >=`, boolean `AND, OR, NOT`, arithmetic `+, -, *, /, %` and string concatenation `||`
+/// - `IS \[NOT\] NULL` and `CASE \[operand\] WHEN ... THEN ... [ELSE ...] END`
+/// - functions `lower, upper, substr, length, trim, coalesce`
+/// Absent fields are null, and nulls follow the SQL semantics. The expression is parsed at submission
+/// and compiled once per task
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FilterExpr {
+    #[prost(string, tag = "1")]
+    pub expression: ::prost::alloc::string::String,
+}
+/// *
+/// MapExpr operator, it builds a new payload from a SQL-like select list, for example `SELECT amount * rate AS amount_eur, user_id`.
+/// Each item is an expression of FilterExpr with an optional alias. The alias of a field reference defaults to its last field name,
+/// and other expressions must have an alias
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MapExpr {
+    #[prost(string, tag = "1")]
+    pub expression: ::prost::alloc::string::String,
+}
+/// *
+/// Route operator, it sends each payload to the output of the first rule whose predicate is true, for content-based routing
+/// to different downstreams. Predicates are expressions of FilterExpr and they're evaluated in order.
+/// Payloads matching no rule are sent to the default output, or dropped if there is no default output.
+/// The outputs receive the input payloads unchanged. The rules are parsed at submission and compiled once per task
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Route {
+    /// names of the outputs and the downstreams they're sent to. Each downstream must be a neighbor of the operator
+    #[prost(map = "string, uint32", tag = "1")]
+    pub outputs: ::std::collections::HashMap<::prost::alloc::string::String, u32>,
+    #[prost(message, repeated, tag = "2")]
+    pub rules: ::prost::alloc::vec::Vec<route::Rule>,
+    /// name of the output of the payloads matching no rule
+    #[prost(string, tag = "3")]
+    pub default_output: ::prost::alloc::string::String,
+}
+/// Nested message and enum types in `Route`.
+pub mod route {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Rule {
+        #[prost(string, tag = "1")]
+        pub predicate: ::prost::alloc::string::String,
+        /// name of the output
+        #[prost(string, tag = "2")]
+        pub output: ::prost::alloc::string::String,
+    }
+}
+/// *
+/// AsyncLookup operator, it enriches each payload by looking up its key in an external system, e.g. an HTTP service or Redis.
+/// Lookups are done concurrently, so the throughput isn't capped by the round-trip latency of one lookup.
+/// The result is set to the target field of the payload. It's null if the payload has no lookup key or nothing is found.
+/// A failed lookup is handled by the error policy of the operator, and the whole event is resolved by the outcome.
+/// In-flight lookups are completed before the operator is checkpointed, drained or stopped, and the events whose lookups are
+/// not completed are processed again by the next executor if the task is restarted
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AsyncLookup {
+    /// path expression of the lookup key, see Project for the syntax
+    #[prost(string, tag = "3")]
+    pub key_path: ::prost::alloc::string::String,
+    /// name of the field which the result is set to. The payload must be an object
+    #[prost(string, tag = "4")]
+    pub target_field: ::prost::alloc::string::String,
+    /// max number of in-flight lookups of a task. The default limit is used if it's zero.
+    /// The operator stops receiving events while the limit is reached, so the upstreams are blocked by backpressure
+    #[prost(uint32, tag = "5")]
+    pub concurrency: u32,
+    /// max time of one lookup. The default limit is used if it's not set
+    #[prost(message, optional, tag = "6")]
+    pub timeout: ::core::option::Option<Time>,
+    /// in-task LRU cache of the results keyed by the lookup key. Results are not cached if it's not set
+    #[prost(message, optional, tag = "7")]
+    pub cache: ::core::option::Option<async_lookup::Cache>,
+    #[prost(enumeration = "async_lookup::Emission", tag = "8")]
+    pub emission: i32,
+    #[prost(oneof = "async_lookup::Backend", tags = "1, 2")]
+    pub backend: ::core::option::Option<async_lookup::Backend>,
+}
+/// Nested message and enum types in `AsyncLookup`.
+pub mod async_lookup {
+    /// HTTP GET. The response body is decoded as JSON, or kept as a string if it's not JSON. The result of 404 is null
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct HttpLookup {
+        /// `{key}` in the template is replaced by the url-encoded lookup key, for example `<http://users/{key}`>
+        #[prost(string, tag = "1")]
+        pub url_template: ::prost::alloc::string::String,
+        #[prost(map = "string, string", tag = "2")]
+        pub headers: ::std::collections::HashMap<
+            ::prost::alloc::string::String,
+            ::prost::alloc::string::String,
+        >,
+    }
+    /// GET, or HGET if the hash field is set. The value is decoded as JSON, or kept as a string if it's not JSON
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct RedisLookup {
+        #[prost(message, optional, tag = "1")]
+        pub connection_opts: ::core::option::Option<super::redis_desc::ConnectionOpts>,
+        /// `{key}` in the template is replaced by the lookup key, for example `user:{key}`. The lookup key is used if it's empty
+        #[prost(string, tag = "2")]
+        pub key_template: ::prost::alloc::string::String,
+        #[prost(string, tag = "3")]
+        pub hash_field: ::prost::alloc::string::String,
+    }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Cache {
+        /// max number of cached results, must be positive
+        #[prost(uint32, tag = "1")]
+        pub capacity: u32,
+        /// how long a result is cached. Results never expire if it's not set
+        #[prost(message, optional, tag = "2")]
+        pub ttl: ::core::option::Option<super::Time>,
+    }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Emission {
+        /// events are emitted in the input order per event key, so a slow lookup holds back the later events of the same key
+        Ordered = 0,
+        /// events are emitted as soon as their lookups complete
+        Unordered = 1,
+    }
+    impl Emission {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Emission::Ordered => "EMISSION_ORDERED",
+                Emission::Unordered => "EMISSION_UNORDERED",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "EMISSION_ORDERED" => Some(Self::Ordered),
+                "EMISSION_UNORDERED" => Some(Self::Unordered),
+                _ => None,
+            }
+        }
+    }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Backend {
+        #[prost(message, tag = "1")]
+        Http(HttpLookup),
+        #[prost(message, tag = "2")]
+        Redis(RedisLookup),
+    }
+}
+/// *
+/// SortBuffer operator, it buffers events in the state backend and releases them in the order of event time,
+/// so the event time of each key is monotone in the downstreams.
+/// The watermark is the max observed event time minus the max out-of-orderness. A buffered event is released once the watermark
+/// passes its event time plus the safety margin. Events behind the watermark are late
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SortBuffer {
+    /// how far an event can fall behind the latest one without being late
+    #[prost(message, optional, tag = "1")]
+    pub max_out_of_orderness: ::core::option::Option<Time>,
+    /// extra delay before a buffered event is released after the watermark passes it
+    #[prost(message, optional, tag = "2")]
+    pub safety_margin: ::core::option::Option<Time>,
+    /// max number of buffered events of a key. It's unlimited if it's zero
+    #[prost(uint32, tag = "3")]
+    pub max_buffered_per_key: u32,
+    /// max number of buffered events of the operator. It's unlimited if it's zero
+    #[prost(uint32, tag = "4")]
+    pub max_buffered: u32,
+    /// what happens if an event arrives while a limit is reached
+    #[prost(enumeration = "sort_buffer::OverflowPolicy", tag = "5")]
+    pub overflow_policy: i32,
+    /// operator id of the side output which late events are emitted to. It must be one of the downstreams of the operator.
+    /// Late events are dropped if it's not set
+    #[prost(uint32, optional, tag = "6")]
+    pub side_output: ::core::option::Option<u32>,
+}
+/// Nested message and enum types in `SortBuffer`.
+pub mod sort_buffer {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum OverflowPolicy {
+        /// release the earliest buffered event before the watermark passes it. Later events of the same key behind it become late
+        ReleaseEarliest = 0,
+        /// drop the earliest buffered event
+        DropEarliest = 1,
+        /// drop the incoming event
+        DropNewest = 2,
+    }
+    impl OverflowPolicy {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                OverflowPolicy::ReleaseEarliest => "OVERFLOW_POLICY_RELEASE_EARLIEST",
+                OverflowPolicy::DropEarliest => "OVERFLOW_POLICY_DROP_EARLIEST",
+                OverflowPolicy::DropNewest => "OVERFLOW_POLICY_DROP_NEWEST",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "OVERFLOW_POLICY_RELEASE_EARLIEST" => Some(Self::ReleaseEarliest),
+                "OVERFLOW_POLICY_DROP_EARLIEST" => Some(Self::DropEarliest),
+                "OVERFLOW_POLICY_DROP_NEWEST" => Some(Self::DropNewest),
+                _ => None,
+            }
+        }
+    }
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Filter {
+    #[prost(oneof = "filter::Value", tags = "1")]
+    pub value: ::core::option::Option<filter::Value>,
+}
+/// Nested message and enum types in `Filter`.
+pub mod filter {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Value {
+        #[prost(message, tag = "1")]
+        Func(super::Func),
+    }
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeyBy {
+    #[prost(oneof = "key_by::Value", tags = "1")]
+    pub value: ::core::option::Option<key_by::Value>,
+}
+/// Nested message and enum types in `KeyBy`.
+pub mod key_by {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Value {
+        #[prost(message, tag = "1")]
+        Func(super::Func),
+    }
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Sink {
+    #[prost(enumeration = "DeliveryGuarentee", tag = "4")]
+    pub delivery_guarentee: i32,
+    /// batching of the writes of MySQL and Redis sinks. Events are written one by one if it's not set
+    #[prost(message, optional, tag = "5")]
+    pub batching: ::core::option::Option<SinkBatching>,
+    #[prost(oneof = "sink::Desc", tags = "1, 2, 3")]
+    pub desc: ::core::option::Option<sink::Desc>,
+}
 /// Nested message and enum types in `Sink`.
 pub mod sink {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Desc {
@@ -589,7 +1638,79 @@ pub mod sink {
     }
 }
 /// *
+/// Batching of the writes to an external sink. The buffered events are written as one batch once any threshold is reached,
+/// on a checkpoint barrier and before the operator stops
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SinkBatching {
+    /// the max number of events in a batch, 0 means unlimited
+    #[prost(uint32, tag = "1")]
+    pub max_events: u32,
+    /// the max encoded size of the events in a batch, 0 means unlimited
+    #[prost(uint64, tag = "2")]
+    pub max_bytes: u64,
+    /// how long the oldest event can be buffered before the batch is written, 0 means unlimited
+    #[prost(uint64, tag = "3")]
+    pub linger_millis: u64,
+    /// the max number of buffered events, including the ones of failed batches. 0 means unlimited
+    #[prost(uint32, tag = "4")]
+    pub capacity: u32,
+    /// what happens to a new event when the buffer is full
+    #[prost(enumeration = "sink_batching::Overflow", tag = "5")]
+    pub overflow: i32,
+    /// how many times a failed batch is written again before the failure is handled by the error policy of the operator
+    #[prost(uint32, tag = "6")]
+    pub max_retries: u32,
+}
+/// Nested message and enum types in `SinkBatching`.
+pub mod sink_batching {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Overflow {
+        /// the new event is rejected with a retryable failure, so it's sent again by the error policy
+        Reject = 0,
+        /// the oldest buffered events are dropped
+        DropOldest = 1,
+    }
+    impl Overflow {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Overflow::Reject => "OVERFLOW_REJECT",
+                Overflow::DropOldest => "OVERFLOW_DROP_OLDEST",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "OVERFLOW_REJECT" => Some(Self::Reject),
+                "OVERFLOW_DROP_OLDEST" => Some(Self::DropOldest),
+                _ => None,
+            }
+        }
+    }
+}
+/// *
 /// Constant operator
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ConstOp {
@@ -600,14 +1721,28 @@ pub struct ConstOp {
     #[prost(uint32, tag = "2")]
     pub operator_id: u32,
 }
+/// *
+/// Sampling and redaction are applied to the events fetched from the external system before they enter the pipeline.
+/// They are applied before the events are recorded by the replay buffer or reach any checkpoint, dead letter, tap or log,
+/// so the raw values of the redacted fields are never persisted
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Source {
+    /// optional sampling of the fetched events. All events are kept if it's not set
+    #[prost(message, optional, tag = "4")]
+    pub sampling: ::core::option::Option<SourceSampling>,
+    /// payload fields whose values are redacted
+    #[prost(message, repeated, tag = "5")]
+    pub redactions: ::prost::alloc::vec::Vec<Redaction>,
     #[prost(oneof = "source::Desc", tags = "3")]
     pub desc: ::core::option::Option<source::Desc>,
 }
 /// Nested message and enum types in `Source`.
 pub mod source {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Desc {
@@ -615,6 +1750,124 @@ pub mod source {
         Kafka(super::KafkaDesc),
     }
 }
+/// *
+/// Sampling keeps 1 of every N events fetched by the source, e.g. to feed a staging environment with a part of the production traffic
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SourceSampling {
+    /// N, it must be positive. 1 keeps all events
+    #[prost(uint32, tag = "1")]
+    pub rate: u32,
+    #[prost(enumeration = "source_sampling::Mode", tag = "2")]
+    pub mode: i32,
+}
+/// Nested message and enum types in `SourceSampling`.
+pub mod source_sampling {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Mode {
+        /// each event is kept with the probability of 1/N
+        Random = 0,
+        /// the events are kept if their keys are hashed into 1 of N buckets, so all or none of the events of a key are kept.
+        /// The events without key are sampled randomly
+        Key = 1,
+    }
+    impl Mode {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Mode::Random => "MODE_RANDOM",
+                Mode::Key => "MODE_KEY",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "MODE_RANDOM" => Some(Self::Random),
+                "MODE_KEY" => Some(Self::Key),
+                _ => None,
+            }
+        }
+    }
+}
+/// *
+/// Redaction replaces the value of a payload field. Missing and null fields are left as is
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Redaction {
+    /// path of the field, e.g. `$.user.email`. Existence filters are not supported
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+    #[prost(enumeration = "redaction::Strategy", tag = "2")]
+    pub strategy: i32,
+    /// the value which replaces the redacted values by STRATEGY_MASK. It's "***" if it's empty
+    #[prost(string, tag = "3")]
+    pub mask: ::prost::alloc::string::String,
+}
+/// Nested message and enum types in `Redaction`.
+pub mod redaction {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Strategy {
+        /// the value is replaced by the mask
+        Mask = 0,
+        /// the value is replaced by the hex-encoded SHA-256 of its JSON encoding, so the redacted values can still be grouped or joined
+        Hash = 1,
+    }
+    impl Strategy {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Strategy::Mask => "STRATEGY_MASK",
+                Strategy::Hash => "STRATEGY_HASH",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "STRATEGY_MASK" => Some(Self::Mask),
+                "STRATEGY_HASH" => Some(Self::Hash),
+                _ => None,
+            }
+        }
+    }
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct KafkaDesc {
@@ -626,9 +1879,17 @@ pub struct KafkaDesc {
     pub opts: ::core::option::Option<kafka_desc::KafkaOptions>,
     #[prost(enumeration = "DataTypeEnum", tag = "4")]
     pub data_type: i32,
+    /// options of the sink, they are ignored by the source
+    #[prost(message, optional, tag = "8")]
+    pub sink_opts: ::core::option::Option<kafka_desc::KafkaSinkOptions>,
+    /// format of the message payload. if it's absent, payload will be decoded by data_type
+    #[prost(oneof = "kafka_desc::Format", tags = "5, 6, 7")]
+    pub format: ::core::option::Option<kafka_desc::Format>,
 }
 /// Nested message and enum types in `KafkaDesc`.
 pub mod kafka_desc {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct KafkaOptions {
@@ -637,7 +1898,241 @@ pub mod kafka_desc {
         #[prost(uint32, optional, tag = "2")]
         pub partition: ::core::option::Option<u32>,
     }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct KafkaSinkOptions {
+        /// the payload field whose JSON-encoded value is the record key. if it's empty, the record key is the event key
+        #[prost(string, tag = "1")]
+        pub key_field: ::prost::alloc::string::String,
+        #[prost(enumeration = "kafka_sink_options::Partitioner", tag = "2")]
+        pub partitioner: i32,
+        /// the payload field whose value is the partition, it's required by PARTITIONER_FIELD
+        #[prost(string, tag = "3")]
+        pub partition_field: ::prost::alloc::string::String,
+        /// whether the provenance of the event (job id, operator id and event id) is attached as record headers
+        #[prost(bool, tag = "4")]
+        pub provenance_headers: bool,
+        /// whether the record timestamp is the event time. Otherwise, it's the time when the record is sent
+        #[prost(bool, tag = "5")]
+        pub event_time_timestamp: bool,
+        /// the field of the JSON payload which the headers of the event are added to as an object. They're not added if it's empty,
+        /// or the payload is not a JSON object
+        #[prost(string, tag = "6")]
+        pub headers_field: ::prost::alloc::string::String,
+    }
+    /// Nested message and enum types in `KafkaSinkOptions`.
+    pub mod kafka_sink_options {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        #[serde(rename_all = "snake_case")]
+        #[derive(
+            Clone,
+            Copy,
+            Debug,
+            PartialEq,
+            Eq,
+            Hash,
+            PartialOrd,
+            Ord,
+            ::prost::Enumeration
+        )]
+        #[repr(i32)]
+        pub enum Partitioner {
+            /// the partition in KafkaOptions
+            Default = 0,
+            /// murmur2 hash of the record key, the same as the default partitioner of the Java client
+            KeyHash = 1,
+            /// records are spread across partitions in turn
+            RoundRobin = 2,
+            /// the partition is taken from the payload field
+            Field = 3,
+        }
+        impl Partitioner {
+            /// String value of the enum field names used in the ProtoBuf definition.
+            ///
+            /// The values are not transformed in any way and thus are considered stable
+            /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+            pub fn as_str_name(&self) -> &'static str {
+                match self {
+                    Partitioner::Default => "PARTITIONER_DEFAULT",
+                    Partitioner::KeyHash => "PARTITIONER_KEY_HASH",
+                    Partitioner::RoundRobin => "PARTITIONER_ROUND_ROBIN",
+                    Partitioner::Field => "PARTITIONER_FIELD",
+                }
+            }
+            /// Creates an enum from field names used in the ProtoBuf definition.
+            pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+                match value {
+                    "PARTITIONER_DEFAULT" => Some(Self::Default),
+                    "PARTITIONER_KEY_HASH" => Some(Self::KeyHash),
+                    "PARTITIONER_ROUND_ROBIN" => Some(Self::RoundRobin),
+                    "PARTITIONER_FIELD" => Some(Self::Field),
+                    _ => None,
+                }
+            }
+        }
+    }
+    /// format of the message payload. if it's absent, payload will be decoded by data_type
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Format {
+        #[prost(message, tag = "5")]
+        Csv(super::CsvFormat),
+        #[prost(message, tag = "6")]
+        Avro(super::AvroFormat),
+        #[prost(message, tag = "7")]
+        Protobuf(super::ProtobufFormat),
+    }
 }
+/// *
+/// CSV format of records, shared by sources and sinks
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CsvFormat {
+    /// field delimiter, ',' by default
+    #[prost(string, tag = "1")]
+    pub delimiter: ::prost::alloc::string::String,
+    /// quote character, '"' by default
+    #[prost(string, tag = "2")]
+    pub quote: ::prost::alloc::string::String,
+    /// for source, whether the first row is a header row; for sink, whether a header row should be emitted
+    #[prost(bool, tag = "3")]
+    pub has_header: bool,
+    /// column-to-field mapping. for sink, it's also the order of columns
+    #[prost(message, repeated, tag = "4")]
+    pub columns: ::prost::alloc::vec::Vec<csv_format::Column>,
+    /// quoting policy, only for sink
+    #[prost(enumeration = "csv_format::QuotePolicy", tag = "5")]
+    pub quote_policy: i32,
+}
+/// Nested message and enum types in `CsvFormat`.
+pub mod csv_format {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Column {
+        /// field name of the column
+        #[prost(string, tag = "1")]
+        pub name: ::prost::alloc::string::String,
+        /// index of the column in a row. It's used when there's no header row or the name is not found in header
+        #[prost(uint32, tag = "2")]
+        pub index: u32,
+        /// the type which the column will be coerced into. unspecified means string
+        #[prost(enumeration = "super::DataTypeEnum", tag = "3")]
+        pub data_type: i32,
+    }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum QuotePolicy {
+        /// quote fields only when necessary
+        Necessary = 0,
+        /// quote all fields
+        Always = 1,
+        /// quote all non-numeric fields
+        NonNumeric = 2,
+        /// never quote fields
+        Never = 3,
+    }
+    impl QuotePolicy {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                QuotePolicy::Necessary => "QUOTE_POLICY_NECESSARY",
+                QuotePolicy::Always => "QUOTE_POLICY_ALWAYS",
+                QuotePolicy::NonNumeric => "QUOTE_POLICY_NON_NUMERIC",
+                QuotePolicy::Never => "QUOTE_POLICY_NEVER",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "QUOTE_POLICY_NECESSARY" => Some(Self::Necessary),
+                "QUOTE_POLICY_ALWAYS" => Some(Self::Always),
+                "QUOTE_POLICY_NON_NUMERIC" => Some(Self::NonNumeric),
+                "QUOTE_POLICY_NEVER" => Some(Self::Never),
+                _ => None,
+            }
+        }
+    }
+}
+/// *
+/// Avro format of records with the Confluent wire format, shared by sources and sinks.
+/// Each message is framed as a magic byte 0, a 4-byte big-endian schema id and the Avro binary datum.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AvroFormat {
+    /// the schema registry where schemas are fetched and registered
+    #[prost(message, optional, tag = "1")]
+    pub registry: ::core::option::Option<avro_format::SchemaRegistry>,
+    /// subject of the schema. required by sink
+    #[prost(string, tag = "2")]
+    pub subject: ::prost::alloc::string::String,
+    /// for sink, whether the schema should be registered under the subject automatically
+    #[prost(bool, tag = "3")]
+    pub auto_register: bool,
+    /// for sink, the schema which records are encoded against. if it's empty, the latest schema of the subject will be used.
+    /// for source, an optional reader schema which records written by older or newer schemas will be resolved into
+    #[prost(string, tag = "4")]
+    pub schema: ::prost::alloc::string::String,
+}
+/// Nested message and enum types in `AvroFormat`.
+pub mod avro_format {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct SchemaRegistry {
+        #[prost(string, tag = "1")]
+        pub url: ::prost::alloc::string::String,
+        /// username of basic auth. auth is disabled if it's empty
+        #[prost(string, tag = "2")]
+        pub username: ::prost::alloc::string::String,
+        #[prost(string, tag = "3")]
+        pub password: ::prost::alloc::string::String,
+    }
+}
+/// *
+/// Protobuf format of records, only for sources. Records are decoded dynamically by the message descriptor.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProtobufFormat {
+    /// serialized google.protobuf.FileDescriptorSet which contains the message and all its dependencies
+    #[prost(bytes = "vec", tag = "1")]
+    pub file_descriptor_set: ::prost::alloc::vec::Vec<u8>,
+    /// fully-qualified name of the message, e.g. 'package.Message'
+    #[prost(string, tag = "2")]
+    pub message_name: ::prost::alloc::string::String,
+    /// whether fields which are not in the descriptor should be kept under the reserved key '__unknown_fields'
+    #[prost(bool, tag = "3")]
+    pub preserve_unknown_fields: bool,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct MysqlDesc {
@@ -648,6 +2143,8 @@ pub struct MysqlDesc {
 }
 /// Nested message and enum types in `MysqlDesc`.
 pub mod mysql_desc {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct ConnectionOpts {
@@ -660,6 +2157,8 @@ pub mod mysql_desc {
         #[prost(string, tag = "4")]
         pub database: ::prost::alloc::string::String,
     }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct Statement {
@@ -670,6 +2169,8 @@ pub mod mysql_desc {
     }
     /// Nested message and enum types in `Statement`.
     pub mod statement {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        #[serde(default)]
         #[allow(clippy::derive_partial_eq_without_eq)]
         #[derive(Clone, PartialEq, ::prost::Message)]
         pub struct Extractor {
@@ -680,6 +2181,8 @@ pub mod mysql_desc {
         }
     }
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RedisDesc {
@@ -692,6 +2195,8 @@ pub struct RedisDesc {
 }
 /// Nested message and enum types in `RedisDesc`.
 pub mod redis_desc {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct ConnectionOpts {
@@ -712,6 +2217,8 @@ pub mod redis_desc {
 /// However, they may check the Dataflow by distinct validators.
 /// Each part's concern is different and they must be sure it's a legal Dataflow
 /// to them.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Dataflow {
@@ -727,17 +2234,94 @@ pub struct Dataflow {
     /// execution id, optional for API, mandatory for TaskManager
     #[prost(message, optional, tag = "4")]
     pub execution_id: ::core::option::Option<SubDataflowId>,
+    /// log level of the operators of this dataflow on the workers: trace, debug, info, warn, error or off.
+    /// It overrides the log level of the workers for this dataflow only. The level of the workers is used if it's empty
+    #[prost(string, tag = "5")]
+    pub log_level: ::prost::alloc::string::String,
+    /// path of the savepoint which the states of the operators are restored from when the dataflow is submitted.
+    /// States are matched by operator id. The states are not restored if it's empty
+    #[prost(string, tag = "6")]
+    pub restore_from: ::prost::alloc::string::String,
+    /// the prior job whose latest checkpoints the operators are warm-started from when the dataflow is submitted. It's ignored if
+    /// `restore_from` is set or the job has checkpoints of its own. Operators whose type, upstreams or input schema changed start with empty state
+    #[prost(message, optional, tag = "7")]
+    pub warm_start_from: ::core::option::Option<ResourceId>,
+    /// deduplication of the events fetched by the sources. Events are not deduplicated if it's not set
+    #[prost(message, optional, tag = "8")]
+    pub event_dedup: ::core::option::Option<EventDedup>,
+    /// liveness watchdog of the operators on the workers. Operators are not watched if it's not set
+    #[prost(message, optional, tag = "9")]
+    pub watchdog: ::core::option::Option<OperatorWatchdog>,
+    /// labels of the dataflow, e.g. `team: payments`. Keys are 1 to 63 characters and values are at most 63 characters of letters, digits,
+    /// `-`, `_` and `.`, beginning and ending with a letter or a digit. Dataflows are listed by label selectors, see `ListDataflowsRequest`
+    #[prost(map = "string, string", tag = "10")]
+    pub labels: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// jobs which this dataflow depends on, e.g. the jobs whose sinks feed the sources of this one. The coordinator starts the dependencies
+    /// before the dataflows depending on them, and warns or blocks the termination of a job which running dataflows depend on, depending on its config.
+    /// A dataflow can't depend on itself
+    #[prost(message, repeated, tag = "11")]
+    pub depends_on: ::prost::alloc::vec::Vec<ResourceId>,
+}
+/// *
+/// Deduplication of the events fetched by the sources of a dataflow, so that an event redelivered by the external system, e.g. after a retry,
+/// is processed once. The dedup key of each payload combines the configured fields of the event, and the payloads whose dedup key has been seen
+/// within the retention are dropped before they enter the pipeline. Seen keys are kept in the state backend of the sources
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EventDedup {
+    /// fields which the dedup key combines in order, it must not be empty. Each one is `key` for the key of the event, `event_time` for the event time,
+    /// or a path expression of the payload, see Project for the syntax. Payloads missing any of the fields are never deduplicated
+    #[prost(string, repeated, tag = "1")]
+    pub key_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// how long a seen key is remembered, must be positive
+    #[prost(message, optional, tag = "2")]
+    pub retention: ::core::option::Option<Time>,
 }
+/// *
+/// Liveness watchdog of the operators of a dataflow on the workers. An operator is stuck if it has work in hand but makes no progress,
+/// i.e. processes no event, within the timeout, e.g. its sink is blocked forever on a dead connection. A stuck operator is reported to
+/// the coordinator and restarted: the waits of the work in hand are abandoned, which are resolved as cancelled by the error policy,
+/// and the operator goes on with its states and its input kept. An operator waiting for input is never stuck. The timeout should be
+/// longer than the retries of the error policies, because an operator makes no progress while it retries a sink
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OperatorWatchdog {
+    /// how long an operator with work in hand may make no progress, must be positive
+    #[prost(message, optional, tag = "1")]
+    pub stuck_timeout: ::core::option::Option<Time>,
+    /// how many times a stuck operator is restarted. It fails once it's stuck again after that
+    #[prost(uint32, tag = "2")]
+    pub max_restarts: u32,
+}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Window {
     #[prost(message, optional, tag = "4")]
     pub trigger: ::core::option::Option<Trigger>,
+    /// how long a window is kept after the max observed event time passes its end. The watermark is the max observed event time
+    /// minus the allowed lateness. Late events of a window which the watermark hasn't passed update the window and it's emitted again.
+    /// Events of windows which the watermark has passed are dropped
+    #[prost(message, optional, tag = "5")]
+    pub allowed_lateness: ::core::option::Option<Time>,
+    /// operator id of the side output which the dropped late events are emitted to. It must be one of the downstreams of the operator
+    #[prost(uint32, optional, tag = "6")]
+    pub side_output: ::core::option::Option<u32>,
     #[prost(oneof = "window::Value", tags = "1, 2, 3")]
     pub value: ::core::option::Option<window::Value>,
 }
 /// Nested message and enum types in `Window`.
 pub mod window {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct FixedWindow {
@@ -745,6 +2329,8 @@ pub mod window {
         #[prost(message, optional, tag = "1")]
         pub size: ::core::option::Option<super::Time>,
     }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct SlidingWindow {
@@ -753,6 +2339,8 @@ pub mod window {
         #[prost(message, optional, tag = "2")]
         pub period: ::core::option::Option<super::Time>,
     }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct SessionWindow {
@@ -760,6 +2348,8 @@ pub mod window {
         #[prost(message, optional, tag = "1")]
         pub timeout: ::core::option::Option<super::Time>,
     }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Value {
@@ -771,6 +2361,8 @@ pub mod window {
         Session(SessionWindow),
     }
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Trigger {
@@ -779,12 +2371,16 @@ pub struct Trigger {
 }
 /// Nested message and enum types in `Trigger`.
 pub mod trigger {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(default)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct Watermark {
         #[prost(message, optional, tag = "1")]
         pub trigger_time: ::core::option::Option<super::Time>,
     }
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Value {
@@ -804,6 +2400,54 @@ pub struct DataflowStates {
     /// dataflow status
     #[prost(enumeration = "DataflowStatus", tag = "3")]
     pub status: i32,
+    /// the latest errors reported by operators
+    #[prost(message, repeated, tag = "4")]
+    pub operator_errors: ::prost::alloc::vec::Vec<OperatorError>,
+    /// the partitions of the dataflow as it's deployed, in the order of its placement. They're only returned if the effective dataflow is requested,
+    /// and there is none if the dataflow is not deployed yet
+    #[prost(message, repeated, tag = "5")]
+    pub partitions: ::prost::alloc::vec::Vec<DeployedPartition>,
+    /// why the dataflow is in the status, see `DataflowPlacement.status_reason`
+    #[prost(string, tag = "6")]
+    pub status_reason: ::prost::alloc::string::String,
+    /// the health of the dataflow at a glance. It's only returned if it's requested by `include_metrics`
+    #[prost(message, optional, tag = "7")]
+    pub metrics: ::core::option::Option<DataflowMetricsSummary>,
+}
+/// the metrics of the operators of a dataflow aggregated from the states reported by the TaskManagers.
+/// Operators on the TaskManagers which fail to report are not counted
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DataflowMetricsSummary {
+    /// the events received by the operators. An event is counted once by each operator it passes through
+    #[prost(uint64, tag = "1")]
+    pub events_processed: u64,
+    /// the failed events which are failed, skipped or dead-lettered by the error policies of the operators
+    #[prost(uint64, tag = "2")]
+    pub errors: u64,
+    /// errors per processed event. It's zero if no event is processed
+    #[prost(double, tag = "3")]
+    pub error_rate: f64,
+    /// the max milliseconds an operator has its local checkpoints not uploaded to the remote snapshot store
+    #[prost(uint64, tag = "4")]
+    pub lag_millis: u64,
+    /// the backpressure of the most blocked operator in per mille, i.e. 1000 if it's blocked all the time
+    #[prost(uint64, tag = "5")]
+    pub max_backpressure: u64,
+    /// the number of operators whose metrics are aggregated
+    #[prost(uint32, tag = "6")]
+    pub reported_operators: u32,
+}
+/// a partition of a deployed dataflow, which is the sub-dataflow running on a TaskManager
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeployedPartition {
+    /// the TaskManager of the partition and whether the partition is started
+    #[prost(message, optional, tag = "1")]
+    pub placement: ::core::option::Option<PartitionPlacement>,
+    /// the sub-dataflow deployed on the TaskManager. It has the operators of the partition and their downstreams
+    #[prost(message, optional, tag = "2")]
+    pub dataflow: ::core::option::Option<Dataflow>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -812,35 +2456,70 @@ pub struct SubDataflowStates {
     pub subdataflow_infos: ::core::option::Option<SubdataflowInfo>,
 }
 /// *
-/// Stream Graph Status. It shows which status a stream job is now.
+/// EdgeType, the way events are delivered from an operator to its downstream
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
-pub enum DataflowStatus {
-    Initialized = 0,
-    Running = 1,
-    Closing = 2,
-    Closed = 3,
+pub enum EdgeType {
+    /// events are delivered to the downstream and processed by it
+    Forward = 0,
+    /// every event is delivered to all instances of the downstream and it updates their broadcast state instead of being processed.
+    /// The broadcast state is a non-keyed table which the downstream can read while processing the events from other edges,
+    /// for example the rules of a filter: UDFs of map, filter, flatMap and keyBy receive it as the second argument if it's not empty.
+    /// The key of the event is the key of the table and the last payload of the event is the value.
+    /// An event without payloads deletes its key.
+    /// There is no ordering guarantee between the events from broadcast edges and other edges, so an event may be processed
+    /// with the broadcast state before or after an update which is sent earlier. Broadcast edges into sinks or sources are invalid.
+    Broadcast = 1,
 }
-impl DataflowStatus {
+impl EdgeType {
     /// String value of the enum field names used in the ProtoBuf definition.
     ///
     /// The values are not transformed in any way and thus are considered stable
     /// (if the ProtoBuf definition does not change) and safe for programmatic use.
     pub fn as_str_name(&self) -> &'static str {
         match self {
-            DataflowStatus::Initialized => "INITIALIZED",
-            DataflowStatus::Running => "RUNNING",
-            DataflowStatus::Closing => "CLOSING",
-            DataflowStatus::Closed => "CLOSED",
+            EdgeType::Forward => "EDGE_TYPE_FORWARD",
+            EdgeType::Broadcast => "EDGE_TYPE_BROADCAST",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
     pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
         match value {
-            "INITIALIZED" => Some(Self::Initialized),
-            "RUNNING" => Some(Self::Running),
-            "CLOSING" => Some(Self::Closing),
-            "CLOSED" => Some(Self::Closed),
+            "EDGE_TYPE_FORWARD" => Some(Self::Forward),
+            "EDGE_TYPE_BROADCAST" => Some(Self::Broadcast),
+            _ => None,
+        }
+    }
+}
+/// *
+/// Operator chaining fuses adjacent operators into one executor, so they process events in sequence on the same thread
+/// without channel hops between them. An operator is chained with its downstream if
+/// - both of them are map, filter, flatMap or project operators without error policy, and chaining is not disabled
+/// - the operator has the only downstream and the downstream has the only upstream
+/// - they are connected by a forward edge and placed on the same host
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Chaining {
+    Enabled = 0,
+    /// the operator is never chained, e.g. for debugging
+    Disabled = 1,
+}
+impl Chaining {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Chaining::Enabled => "CHAINING_ENABLED",
+            Chaining::Disabled => "CHAINING_DISABLED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "CHAINING_ENABLED" => Some(Self::Enabled),
+            "CHAINING_DISABLED" => Some(Self::Disabled),
             _ => None,
         }
     }
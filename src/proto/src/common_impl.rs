@@ -1,17 +1,69 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use chrono::Duration;
+use tracing::level_filters::LevelFilter;
 
 use crate::common::{
+    async_lookup, csv_format, error_policy, kafka_desc,
     mysql_desc::{self, Statement},
     operator_info::Details,
-    sink, source,
+    payload_schema, project, redaction, sink, sink_batching, sort_buffer, source, source_sampling,
+    state_limit, throttle,
     trigger::Watermark,
+    wasm_udf,
     window::{self, FixedWindow, SessionWindow, SlidingWindow},
-    Ack, DataTypeEnum, Dataflow, Entry, SubDataflowId, Func, Heartbeat, HostAddr, KafkaDesc,
-    KeyedDataEvent, MysqlDesc, OperatorInfo, RedisDesc, ResourceId, Response, Sink, Source, Time,
-    Trigger, Window,
+    Ack, AsyncLookup, AvroFormat, Chaining, CsvFormat, DataTypeEnum, Dataflow, DataflowMeta,
+    DataflowPlacement, DataflowStatus, Deduplicate, DeliveryGuarentee, EdgeType, Entry,
+    ErrorDetail, ErrorPolicy, EventDedup, FilterExpr, Func, Heartbeat, HostAddr, KafkaDesc,
+    KeyedDataEvent, MapExpr, MysqlDesc, OperatorInfo, OperatorWatchdog, PartitionPlacement,
+    PartitionStatus, PayloadSchema, Project, ProtobufFormat, Redaction, RedisDesc, ResourceId,
+    Response, Route, Sink, SinkBatching, SortBuffer, Source, SourceSampling, StateLimit,
+    SubDataflowId, Throttle, Time, Trigger, WasmUdf, Window,
 };
+use crate::json_path::JsonPath;
+use crate::sql_expr::{Expr, Select};
 
 pub const SUCCESS_RPC_RESPONSE: &str = "success";
+pub const FAILURE_RPC_RESPONSE: &str = "failure";
+/// the value which replaces the redacted values if the mask of the redaction is not set
+pub const DEFAULT_REDACTION_MASK: &str = "***";
+/// the max number of labels of a dataflow
+pub const MAX_LABELS: usize = 64;
+/// the max length of the key or the value of a label
+pub const MAX_LABEL_LENGTH: usize = 63;
+
+/// a label key is 1 to [`MAX_LABEL_LENGTH`] characters, and a label value is at most [`MAX_LABEL_LENGTH`] characters.
+/// Both of them consist of ASCII letters, digits, `-`, `_` and `.`, and begin and end with a letter or a digit
+pub fn check_label(key: &str, value: &str) -> Result<(), String> {
+    if key.is_empty() {
+        return Err("label key is empty".to_string());
+    }
+    check_label_part("key", key)?;
+    check_label_part("value", value)
+}
+
+fn check_label_part(part: &str, text: &str) -> Result<(), String> {
+    if text.len() > MAX_LABEL_LENGTH {
+        return Err(format!(
+            "label {part} [{text}] is longer than {MAX_LABEL_LENGTH} characters"
+        ));
+    }
+    if !text
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "label {part} [{text}] has characters other than letters, digits, '-', '_' and '.'"
+        ));
+    }
+    let is_alphanumeric = |c: Option<char>| c.map(|c| c.is_ascii_alphanumeric()).unwrap_or(true);
+    if !is_alphanumeric(text.chars().next()) || !is_alphanumeric(text.chars().last()) {
+        return Err(format!(
+            "label {part} [{text}] doesn't begin and end with a letter or a digit"
+        ));
+    }
+    Ok(())
+}
 
 const RESOURCE_ID_SCHEMA: &str = r#"{
     "name": "ResourceId", 
@@ -97,6 +149,96 @@ const KEYED_DATA_EVENT_SCHEMA: &str = r#"{
 }"#;
 
 impl OperatorInfo {
+    pub fn new(operator_id: u32, details: Details) -> Self {
+        Self {
+            operator_id,
+            details: Some(details),
+            ..Default::default()
+        }
+    }
+
+    pub fn source(operator_id: u32, source: Source) -> Self {
+        Self::new(operator_id, Details::Source(source))
+    }
+
+    pub fn sink(operator_id: u32, sink: Sink) -> Self {
+        Self::new(operator_id, Details::Sink(sink))
+    }
+
+    /// a Mapper operator of the JavaScript function
+    pub fn map<F: Into<String>>(operator_id: u32, function: F) -> Self {
+        let func = Func {
+            function: function.into(),
+        };
+        Self::new(
+            operator_id,
+            Details::Mapper(Mapper {
+                value: Some(mapper::Value::Func(func)),
+            }),
+        )
+    }
+
+    /// a Filter operator of the JavaScript function
+    pub fn filter<F: Into<String>>(operator_id: u32, function: F) -> Self {
+        let func = Func {
+            function: function.into(),
+        };
+        Self::new(
+            operator_id,
+            Details::Filter(Filter {
+                value: Some(filter::Value::Func(func)),
+            }),
+        )
+    }
+
+    /// a FlatMap operator of the JavaScript function
+    pub fn flat_map<F: Into<String>>(operator_id: u32, function: F) -> Self {
+        let func = Func {
+            function: function.into(),
+        };
+        Self::new(
+            operator_id,
+            Details::FlatMap(FlatMap {
+                value: Some(flat_map::Value::Func(func)),
+            }),
+        )
+    }
+
+    /// a KeyBy operator of the JavaScript function
+    pub fn key_by<F: Into<String>>(operator_id: u32, function: F) -> Self {
+        let func = Func {
+            function: function.into(),
+        };
+        Self::new(
+            operator_id,
+            Details::KeyBy(KeyBy {
+                value: Some(key_by::Value::Func(func)),
+            }),
+        )
+    }
+
+    /// a Reducer operator of the JavaScript function
+    pub fn reduce<F: Into<String>>(operator_id: u32, function: F) -> Self {
+        let func = Func {
+            function: function.into(),
+        };
+        Self::new(
+            operator_id,
+            Details::Reducer(Reducer {
+                value: Some(reducer::Value::Func(func)),
+            }),
+        )
+    }
+
+    /// place the operator on the worker
+    pub fn with_host_addr<H: Into<String>>(mut self, host: H, port: u32) -> Self {
+        self.host_addr = Some(HostAddr {
+            host: host.into(),
+            port,
+        });
+        self
+    }
+
     pub fn has_source(&self) -> bool {
         self.details
             .as_ref()
@@ -117,6 +259,48 @@ impl OperatorInfo {
             .is_some()
     }
 
+    /// whether the operator can be fused with its adjacent operators by operator chaining
+    pub fn is_chainable(&self) -> bool {
+        self.chaining() == Chaining::Enabled
+            && self.error_policy.is_none()
+            && !self.has_runtime_validation()
+            && matches!(
+                self.details,
+                Some(Details::Mapper(_))
+                    | Some(Details::Filter(_))
+                    | Some(Details::FlatMap(_))
+                    | Some(Details::Project(_))
+            )
+    }
+
+    /// whether the input events are validated against the input schema at runtime
+    pub fn has_runtime_validation(&self) -> bool {
+        self.input_schema
+            .as_ref()
+            .map(|schema| schema.validation() != payload_schema::Validation::Disabled)
+            .unwrap_or_default()
+    }
+
+    /// the downstreams which only receive the side output or the failed events of the operator, or the routed events of the Route operator.
+    /// They receive the input events of the operator rather than its outputs
+    pub fn get_side_outputs(&self) -> BTreeSet<u32> {
+        let mut side_outputs: BTreeSet<_> = match self.details.as_ref() {
+            Some(Details::Deduplicate(deduplicate)) => {
+                deduplicate.side_output.into_iter().collect()
+            }
+            Some(Details::SortBuffer(sort_buffer)) => sort_buffer.side_output.into_iter().collect(),
+            Some(Details::Window(window)) => window.side_output.into_iter().collect(),
+            Some(Details::Route(route)) => route.outputs.values().copied().collect(),
+            _ => Default::default(),
+        };
+        side_outputs.extend(
+            self.error_policy
+                .as_ref()
+                .and_then(|error_policy| error_policy.get_dead_letter()),
+        );
+        side_outputs
+    }
+
     pub fn get_host_addr(&self) -> HostAddr {
         self.host_addr
             .as_ref()
@@ -167,6 +351,141 @@ impl OperatorInfo {
             })
             .unwrap_or_default()
     }
+
+    /// the type of the operator, e.g. `reducer` or `fixed_window`
+    pub fn get_kind(&self) -> &'static str {
+        match self.details.as_ref() {
+            Some(Details::Source(_)) => "source",
+            Some(Details::Sink(_)) => "sink",
+            Some(Details::Mapper(_)) => "mapper",
+            Some(Details::Filter(_)) => "filter",
+            Some(Details::KeyBy(_)) => "key_by",
+            Some(Details::Reducer(_)) => "reducer",
+            Some(Details::FlatMap(_)) => "flat_map",
+            Some(Details::Window(window)) => match window.get_value() {
+                Some(window::Value::Fixed(_)) => "fixed_window",
+                Some(window::Value::Slide(_)) => "sliding_window",
+                Some(window::Value::Session(_)) => "session_window",
+                None => "window",
+            },
+            Some(Details::Project(_)) => "project",
+            Some(Details::Throttle(_)) => "throttle",
+            Some(Details::Deduplicate(_)) => "deduplicate",
+            Some(Details::SortBuffer(_)) => "sort_buffer",
+            Some(Details::WasmUdf(_)) => "wasm_udf",
+            Some(Details::FilterExpr(_)) => "filter_expr",
+            Some(Details::MapExpr(_)) => "map_expr",
+            Some(Details::AsyncLookup(_)) => "async_lookup",
+            Some(Details::Route(_)) => "route",
+            None => "none",
+        }
+    }
+
+    /// the config of the operator checked by its type, after the checks of its details by [`Dataflow::validate`].
+    /// The error names the invalid field by its path in the details, e.g. `sink.desc.kafka.brokers[0]`
+    pub fn check_config(&self) -> Result<(), OperatorConfigError> {
+        let into_err = |field: String, message: String| OperatorConfigError {
+            operator_id: self.operator_id,
+            kind: self.get_kind(),
+            field,
+            message,
+        };
+        let details = match self.details.as_ref() {
+            Some(details) => details,
+            None => return Ok(()),
+        };
+        let func = match details {
+            Details::Mapper(mapper) => mapper.value.as_ref().map(|_| mapper.get_func()),
+            Details::Filter(filter) => filter.value.as_ref().map(|_| filter.get_func()),
+            Details::KeyBy(key_by) => key_by.value.as_ref().map(|_| key_by.get_func()),
+            Details::Reducer(reducer) => reducer.value.as_ref().map(|_| reducer.get_func()),
+            Details::FlatMap(flat_map) => flat_map.value.as_ref().map(|_| flat_map.get_func()),
+            _ => None,
+        };
+        if func
+            .filter(|func| func.function.trim().is_empty())
+            .is_some()
+        {
+            return Err(into_err(
+                format!("{}.value.func.function", self.get_kind()),
+                "function must not be empty".to_string(),
+            ));
+        }
+
+        match details {
+            Details::Source(Source {
+                desc: Some(source::Desc::Kafka(kafka)),
+                ..
+            }) => kafka.check_config().map_err(|(field, message)| {
+                into_err(format!("source.desc.kafka.{}", field), message)
+            }),
+            Details::Sink(Sink {
+                desc: Some(desc), ..
+            }) => match desc {
+                sink::Desc::Kafka(kafka) => kafka
+                    .check_config()
+                    .map_err(|(field, message)| (format!("kafka.{}", field), message)),
+                sink::Desc::Mysql(mysql) => mysql
+                    .connection_opts
+                    .as_ref()
+                    .map_or(Ok(()), |opts| check_address(&opts.host, false))
+                    .map_err(|message| ("mysql.connection_opts.host".to_string(), message)),
+                sink::Desc::Redis(redis) => redis
+                    .connection_opts
+                    .as_ref()
+                    .map_or(Ok(()), |opts| check_address(&opts.host, false))
+                    .map_err(|message| ("redis.connection_opts.host".to_string(), message)),
+            }
+            .map_err(|(field, message)| into_err(format!("sink.desc.{}", field), message)),
+            Details::AsyncLookup(AsyncLookup {
+                backend: Some(async_lookup::Backend::Http(http)),
+                ..
+            }) => check_http_url(&http.url_template).map_err(|message| {
+                into_err(
+                    "async_lookup.backend.http.url_template".to_string(),
+                    message,
+                )
+            }),
+            Details::WasmUdf(WasmUdf {
+                module: Some(wasm_udf::Module::Url(url)),
+                ..
+            }) => check_http_url(url)
+                .map_err(|message| into_err("wasm_udf.module.url".to_string(), message)),
+            _ => Ok(()),
+        }
+    }
+
+    /// the fingerprint of the layout of the operator's state: its type, its upstreams and its input schema.
+    /// A checkpointed state is only restored into an operator with the same fingerprint
+    pub fn get_state_schema(&self) -> String {
+        let upstreams = self.upstreams.iter().collect::<BTreeSet<_>>();
+        let input = self
+            .input_schema
+            .as_ref()
+            .map(|schema| {
+                let mut fields = schema
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        format!(
+                            "{}:{}:{}",
+                            field.name,
+                            field.data_type().as_str_name(),
+                            field.required
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                fields.sort();
+                fields.join(",")
+            })
+            .unwrap_or_default();
+        format!(
+            "{};upstreams={:?};input=[{}]",
+            self.get_kind(),
+            upstreams,
+            input
+        )
+    }
 }
 
 impl Window {
@@ -177,6 +496,37 @@ impl Window {
     pub fn get_trigger(&self) -> Option<&Trigger> {
         self.trigger.as_ref()
     }
+
+    pub fn get_allowed_lateness(&self) -> Duration {
+        self.allowed_lateness
+            .as_ref()
+            .map(|time| time.to_duration())
+            .unwrap_or_else(Duration::zero)
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        let positive = |time: Time, name: &str| {
+            if time.to_duration() > Duration::zero() {
+                Ok(())
+            } else {
+                Err(DataflowValidateError::InvalidWindow(format!(
+                    "{} must be positive",
+                    name
+                )))
+            }
+        };
+        match self.get_value() {
+            Some(window::Value::Fixed(fixed)) => positive(fixed.get_size(), "size"),
+            Some(window::Value::Slide(slide)) => {
+                positive(slide.get_size(), "size")?;
+                positive(slide.get_period(), "period")
+            }
+            Some(window::Value::Session(session)) => positive(session.get_timeout(), "timeout"),
+            None => Err(DataflowValidateError::InvalidWindow(
+                "window type is missing".to_string(),
+            )),
+        }
+    }
 }
 
 impl KafkaDesc {
@@ -188,8 +538,100 @@ impl KafkaDesc {
         } else if self.topic.is_empty() {
             Err(DataflowValidateError::MissingKafkaTopic)
         } else {
-            Ok(())
+            match self.format.as_ref() {
+                Some(kafka_desc::Format::Csv(csv)) => csv.check(),
+                Some(kafka_desc::Format::Avro(avro)) => avro.check(),
+                Some(kafka_desc::Format::Protobuf(protobuf)) => protobuf.check(),
+                None => Ok(()),
+            }
+        }
+    }
+
+    pub(crate) fn check_sink(&self) -> Result<(), DataflowValidateError> {
+        self.check().and_then(|_| match self.format.as_ref() {
+            Some(kafka_desc::Format::Avro(avro)) if avro.subject.is_empty() => Err(
+                DataflowValidateError::InvalidAvroFormat("subject is required by sink".to_string()),
+            ),
+            Some(kafka_desc::Format::Protobuf(_)) => {
+                Err(DataflowValidateError::InvalidProtobufFormat(
+                    "protobuf format is not supported by sink".to_string(),
+                ))
+            }
+            _ => Ok(()),
+        })?;
+        match self.sink_opts.as_ref() {
+            Some(opts)
+                if opts.partitioner() == kafka_desc::kafka_sink_options::Partitioner::Field
+                    && opts.partition_field.is_empty() =>
+            {
+                Err(DataflowValidateError::InvalidKafkaSinkOptions(
+                    "partition_field is required by field partitioner".to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// each broker is `host:port`, and the schema registry of Avro format is an HTTP url.
+    /// The error is the path of the invalid field in the description and the reason
+    fn check_config(&self) -> Result<(), (String, String)> {
+        self.brokers
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, broker)| {
+                check_address(broker, true)
+                    .map_err(|message| (format!("brokers[{}]", index), message))
+            })?;
+        match self.get_avro_format() {
+            Some(avro) if !avro.get_registry_url().is_empty() => {
+                check_http_url(avro.get_registry_url())
+                    .map_err(|message| ("format.avro.registry.url".to_string(), message))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn get_csv_format(&self) -> Option<&CsvFormat> {
+        self.format.as_ref().and_then(|format| match format {
+            kafka_desc::Format::Csv(csv) => Some(csv),
+            _ => None,
+        })
+    }
+
+    pub fn get_avro_format(&self) -> Option<&AvroFormat> {
+        self.format.as_ref().and_then(|format| match format {
+            kafka_desc::Format::Avro(avro) => Some(avro),
+            _ => None,
+        })
+    }
+
+    pub fn get_protobuf_format(&self) -> Option<&ProtobufFormat> {
+        self.format.as_ref().and_then(|format| match format {
+            kafka_desc::Format::Protobuf(protobuf) => Some(protobuf),
+            _ => None,
+        })
+    }
+
+    /// fill the format mappings which are not configured by the payload schema:
+    /// the columns of CSV format, and the schema of Avro format if every field can be mapped into Avro
+    pub fn with_payload_schema(&self, schema: Option<&PayloadSchema>) -> Self {
+        let mut desc = self.clone();
+        let schema = match schema.filter(|schema| !schema.fields.is_empty()) {
+            Some(schema) => schema,
+            None => return desc,
+        };
+        match desc.format.as_mut() {
+            Some(kafka_desc::Format::Csv(csv)) if csv.columns.is_empty() => {
+                csv.columns = schema.get_csv_columns()
+            }
+            Some(kafka_desc::Format::Avro(avro)) if avro.schema.is_empty() => {
+                if let Some(avro_schema) = schema.get_avro_schema() {
+                    avro.schema = avro_schema
+                }
+            }
+            _ => {}
         }
+        desc
     }
 
     pub fn get_kafka_group(&self) -> String {
@@ -207,6 +649,793 @@ impl KafkaDesc {
     }
 }
 
+impl CsvFormat {
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        if !self.delimiter.is_ascii() || self.delimiter.len() > 1 {
+            Err(DataflowValidateError::InvalidCsvFormat(format!(
+                "delimiter [{}] must be a single ascii character",
+                &self.delimiter
+            )))
+        } else if !self.quote.is_ascii() || self.quote.len() > 1 {
+            Err(DataflowValidateError::InvalidCsvFormat(format!(
+                "quote [{}] must be a single ascii character",
+                &self.quote
+            )))
+        } else if self.columns.iter().any(|column| column.name.is_empty()) {
+            Err(DataflowValidateError::InvalidCsvFormat(
+                "column name must not be empty".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// the field delimiter, ',' by default
+    pub fn get_delimiter(&self) -> u8 {
+        self.delimiter.as_bytes().first().copied().unwrap_or(b',')
+    }
+
+    /// the quote character, '"' by default
+    pub fn get_quote(&self) -> u8 {
+        self.quote.as_bytes().first().copied().unwrap_or(b'"')
+    }
+}
+
+impl AvroFormat {
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        if self.get_registry_url().is_empty() {
+            Err(DataflowValidateError::InvalidAvroFormat(
+                "schema registry url is required".to_string(),
+            ))
+        } else if !self.schema.is_empty() {
+            apache_avro::Schema::parse_str(&self.schema)
+                .map(|_| ())
+                .map_err(|err| {
+                    DataflowValidateError::InvalidAvroFormat(format!("invalid schema: {}", err))
+                })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// the url of schema registry without the trailing slash
+    pub fn get_registry_url(&self) -> &str {
+        self.registry
+            .as_ref()
+            .map(|registry| registry.url.trim_end_matches('/'))
+            .unwrap_or_default()
+    }
+}
+
+impl ProtobufFormat {
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        self.get_message_descriptor().map(|_| ())
+    }
+
+    /// resolve the descriptor of the message from the file descriptor set
+    pub fn get_message_descriptor(
+        &self,
+    ) -> Result<prost_reflect::MessageDescriptor, DataflowValidateError> {
+        let pool = prost_reflect::DescriptorPool::decode(self.file_descriptor_set.as_slice())
+            .map_err(|err| {
+                DataflowValidateError::InvalidProtobufFormat(format!(
+                    "invalid file descriptor set: {}",
+                    err
+                ))
+            })?;
+        pool.get_message_by_name(&self.message_name).ok_or_else(|| {
+            DataflowValidateError::InvalidProtobufFormat(format!(
+                "message [{}] is not found in the file descriptor set",
+                &self.message_name
+            ))
+        })
+    }
+}
+
+impl Project {
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        if self.fields.is_empty() {
+            return Err(DataflowValidateError::InvalidProject(
+                "no field is projected".to_string(),
+            ));
+        }
+
+        let mut names = BTreeSet::new();
+        for field in &self.fields {
+            if field.name.is_empty() {
+                return Err(DataflowValidateError::InvalidProject(
+                    "name of projected field is empty".to_string(),
+                ));
+            }
+            if !names.insert(field.name.as_str()) {
+                return Err(DataflowValidateError::InvalidProject(format!(
+                    "field [{}] is projected more than once",
+                    &field.name
+                )));
+            }
+            field.get_path()?;
+            field.get_default_value()?;
+            match field.cast() {
+                DataTypeEnum::Unspecified
+                | DataTypeEnum::String
+                | DataTypeEnum::Bigint
+                | DataTypeEnum::Number
+                | DataTypeEnum::Boolean => {}
+                cast => {
+                    return Err(DataflowValidateError::InvalidProject(format!(
+                        "field [{}] can not be cast into {:?}",
+                        &field.name, cast
+                    )))
+                }
+            }
+        }
+
+        if !self.key_field.is_empty() && !names.contains(self.key_field.as_str()) {
+            return Err(DataflowValidateError::InvalidProject(format!(
+                "key field [{}] is not projected",
+                &self.key_field
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Throttle {
+    /// check the throttle configuration. It's also used to validate the configuration changed at runtime
+    pub fn check(&self) -> Result<(), DataflowValidateError> {
+        match self.mode.as_ref() {
+            Some(throttle::Mode::RateLimit(rate_limit)) => {
+                if rate_limit.events_per_sec.is_finite() && rate_limit.events_per_sec > 0.0 {
+                    Ok(())
+                } else {
+                    Err(DataflowValidateError::InvalidThrottle(format!(
+                        "rate [{}] must be positive",
+                        rate_limit.events_per_sec
+                    )))
+                }
+            }
+            Some(throttle::Mode::Sampling(sampling)) => {
+                if (0.0..=1.0).contains(&sampling.fraction) {
+                    Ok(())
+                } else {
+                    Err(DataflowValidateError::InvalidThrottle(format!(
+                        "sampling fraction [{}] is out of [0, 1]",
+                        sampling.fraction
+                    )))
+                }
+            }
+            Some(throttle::Mode::KeySampling(key_sampling)) => {
+                if key_sampling.modulus > 0 {
+                    Ok(())
+                } else {
+                    Err(DataflowValidateError::InvalidThrottle(
+                        "modulus of key sampling must be positive".to_string(),
+                    ))
+                }
+            }
+            None => Err(DataflowValidateError::InvalidThrottle(
+                "throttle mode is missing".to_string(),
+            )),
+        }
+    }
+}
+
+/// a field of an event which the dedup key of [`EventDedup`] combines
+#[derive(Clone, Debug, PartialEq)]
+pub enum DedupKeyField {
+    /// the key of the event
+    Key,
+    /// the event time
+    EventTime,
+    /// a field of the payload
+    Path(JsonPath),
+}
+
+impl EventDedup {
+    pub fn get_key_fields(&self) -> Result<Vec<DedupKeyField>, DataflowValidateError> {
+        if self.key_fields.is_empty() {
+            return Err(DataflowValidateError::InvalidEventDedup(
+                "key fields must not be empty".to_string(),
+            ));
+        }
+        self.key_fields
+            .iter()
+            .map(|field| match field.as_str() {
+                "key" => Ok(DedupKeyField::Key),
+                "event_time" => Ok(DedupKeyField::EventTime),
+                path => JsonPath::parse(path)
+                    .map(DedupKeyField::Path)
+                    .map_err(|err| {
+                        DataflowValidateError::InvalidEventDedup(format!(
+                            "invalid key field [{}]: {}",
+                            path, err
+                        ))
+                    }),
+            })
+            .collect()
+    }
+
+    pub fn get_retention(&self) -> Result<Duration, DataflowValidateError> {
+        self.retention
+            .as_ref()
+            .map(|retention| retention.to_duration())
+            .filter(|retention| *retention > Duration::zero())
+            .ok_or_else(|| {
+                DataflowValidateError::InvalidEventDedup("retention must be positive".to_string())
+            })
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        self.get_key_fields()?;
+        self.get_retention()?;
+        Ok(())
+    }
+}
+
+impl OperatorWatchdog {
+    pub fn get_stuck_timeout(&self) -> Result<Duration, DataflowValidateError> {
+        self.stuck_timeout
+            .as_ref()
+            .map(|timeout| timeout.to_duration())
+            .filter(|timeout| *timeout > Duration::zero())
+            .ok_or_else(|| {
+                DataflowValidateError::InvalidWatchdog("stuck timeout must be positive".to_string())
+            })
+    }
+}
+
+impl Deduplicate {
+    /// path of the dedup key. It's `None` if the key of the event is used
+    pub fn get_key_path(&self) -> Result<Option<JsonPath>, DataflowValidateError> {
+        if self.key_path.is_empty() {
+            return Ok(None);
+        }
+        JsonPath::parse(&self.key_path).map(Some).map_err(|err| {
+            DataflowValidateError::InvalidDeduplicate(format!(
+                "invalid key path [{}]: {}",
+                &self.key_path, err
+            ))
+        })
+    }
+
+    pub fn get_horizon(&self) -> Result<Duration, DataflowValidateError> {
+        self.horizon
+            .as_ref()
+            .map(|horizon| horizon.to_duration())
+            .filter(|horizon| *horizon > Duration::zero())
+            .ok_or_else(|| {
+                DataflowValidateError::InvalidDeduplicate("horizon must be positive".to_string())
+            })
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        self.get_key_path()?;
+        self.get_horizon()?;
+        Ok(())
+    }
+}
+
+impl SortBuffer {
+    pub fn get_max_out_of_orderness(&self) -> Duration {
+        self.max_out_of_orderness
+            .as_ref()
+            .map(|time| time.to_duration())
+            .unwrap_or_else(Duration::zero)
+    }
+
+    pub fn get_safety_margin(&self) -> Duration {
+        self.safety_margin
+            .as_ref()
+            .map(|time| time.to_duration())
+            .unwrap_or_else(Duration::zero)
+    }
+
+    pub fn get_overflow_policy(
+        &self,
+    ) -> Result<sort_buffer::OverflowPolicy, DataflowValidateError> {
+        sort_buffer::OverflowPolicy::from_i32(self.overflow_policy).ok_or_else(|| {
+            DataflowValidateError::InvalidSortBuffer(format!(
+                "unknown overflow policy {}",
+                self.overflow_policy
+            ))
+        })
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        self.get_overflow_policy().map(|_| {})
+    }
+}
+
+impl WasmUdf {
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        let into_err = |msg: &str| Err(DataflowValidateError::InvalidWasmUdf(msg.to_string()));
+        match self.module.as_ref() {
+            Some(wasm_udf::Module::Inline(module)) if module.is_empty() => {
+                return into_err("inline module is empty")
+            }
+            Some(wasm_udf::Module::Url(url)) if url.is_empty() => return into_err("url is empty"),
+            Some(wasm_udf::Module::Url(_)) if self.sha256.is_empty() => {
+                return into_err("sha256 checksum is required for the module fetched from url")
+            }
+            Some(_) => {}
+            None => return into_err("module is missing"),
+        }
+        if !self.sha256.is_empty()
+            && (self.sha256.len() != 64 || !self.sha256.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            return into_err("sha256 checksum must be 64 hex digits");
+        }
+        Ok(())
+    }
+}
+
+impl FilterExpr {
+    pub fn get_expr(&self) -> Result<Expr, DataflowValidateError> {
+        Expr::parse(&self.expression).map_err(|err| {
+            DataflowValidateError::InvalidSqlExpr(format!(
+                "invalid filter expression [{}]: {}",
+                &self.expression, err
+            ))
+        })
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        self.get_expr().map(|_| {})
+    }
+}
+
+impl MapExpr {
+    pub fn get_select(&self) -> Result<Select, DataflowValidateError> {
+        Select::parse(&self.expression).map_err(|err| {
+            DataflowValidateError::InvalidSqlExpr(format!(
+                "invalid map expression [{}]: {}",
+                &self.expression, err
+            ))
+        })
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        self.get_select().map(|_| {})
+    }
+}
+
+impl Route {
+    /// the downstream of the output, which must be one of the outputs
+    fn get_output(&self, name: &str) -> Result<u32, DataflowValidateError> {
+        self.outputs.get(name).copied().ok_or_else(|| {
+            DataflowValidateError::InvalidRoute(format!("output [{}] is not declared", name))
+        })
+    }
+
+    /// the predicates of the rules and the downstreams of their outputs, in the order of the rules
+    pub fn get_rules(&self) -> Result<Vec<(Expr, u32)>, DataflowValidateError> {
+        if self.rules.is_empty() {
+            return Err(DataflowValidateError::InvalidRoute(
+                "at least one rule is required".to_string(),
+            ));
+        }
+        self.rules
+            .iter()
+            .map(|rule| {
+                let predicate = Expr::parse(&rule.predicate).map_err(|err| {
+                    DataflowValidateError::InvalidRoute(format!(
+                        "invalid predicate [{}]: {}",
+                        &rule.predicate, err
+                    ))
+                })?;
+                Ok((predicate, self.get_output(&rule.output)?))
+            })
+            .collect()
+    }
+
+    /// the downstream of the payloads matching no rule, they're dropped if it's none
+    pub fn get_default_output(&self) -> Result<Option<u32>, DataflowValidateError> {
+        if self.default_output.is_empty() {
+            Ok(None)
+        } else {
+            self.get_output(&self.default_output).map(Some)
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        self.get_rules()?;
+        self.get_default_output().map(|_| {})
+    }
+}
+
+impl AsyncLookup {
+    pub fn get_key_path(&self) -> Result<JsonPath, DataflowValidateError> {
+        JsonPath::parse(&self.key_path).map_err(|err| {
+            DataflowValidateError::InvalidAsyncLookup(format!(
+                "invalid key path [{}]: {}",
+                &self.key_path, err
+            ))
+        })
+    }
+
+    pub fn get_emission(&self) -> Result<async_lookup::Emission, DataflowValidateError> {
+        async_lookup::Emission::from_i32(self.emission).ok_or_else(|| {
+            DataflowValidateError::InvalidAsyncLookup(format!("unknown emission {}", self.emission))
+        })
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        let into_err = |msg: &str| Err(DataflowValidateError::InvalidAsyncLookup(msg.to_string()));
+        match self.backend.as_ref() {
+            Some(async_lookup::Backend::Http(http)) if http.url_template.is_empty() => {
+                return into_err("url template is empty")
+            }
+            Some(async_lookup::Backend::Redis(redis))
+                if redis
+                    .connection_opts
+                    .as_ref()
+                    .map_or(true, |opts| opts.host.is_empty()) =>
+            {
+                return into_err("redis host is missing")
+            }
+            Some(_) => {}
+            None => return into_err("lookup backend is missing"),
+        }
+        if self.key_path.is_empty() {
+            return into_err("key path is empty");
+        }
+        if self.target_field.is_empty() {
+            return into_err("target field is empty");
+        }
+        if matches!(&self.cache, Some(cache) if cache.capacity == 0) {
+            return into_err("cache capacity must be positive");
+        }
+        self.get_key_path()?;
+        self.get_emission().map(|_| {})
+    }
+}
+
+impl StateLimit {
+    pub fn get_policy(&self) -> Result<state_limit::Policy, DataflowValidateError> {
+        state_limit::Policy::from_i32(self.policy).ok_or_else(|| {
+            DataflowValidateError::InvalidStateLimit(format!("unknown policy {}", self.policy))
+        })
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        if self.max_bytes == 0 {
+            return Err(DataflowValidateError::InvalidStateLimit(
+                "max bytes must be positive".to_string(),
+            ));
+        }
+        self.get_policy().map(|_| {})
+    }
+}
+
+impl SinkBatching {
+    pub fn get_overflow(&self) -> Result<sink_batching::Overflow, DataflowValidateError> {
+        sink_batching::Overflow::from_i32(self.overflow).ok_or_else(|| {
+            DataflowValidateError::InvalidSinkBatching(format!(
+                "unknown overflow {}",
+                self.overflow
+            ))
+        })
+    }
+
+    pub fn get_linger(&self) -> Option<std::time::Duration> {
+        Some(self.linger_millis)
+            .filter(|millis| *millis > 0)
+            .map(std::time::Duration::from_millis)
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        if self.capacity > 0 && self.max_events > self.capacity {
+            return Err(DataflowValidateError::InvalidSinkBatching(format!(
+                "max events {} exceeds the capacity {}",
+                self.max_events, self.capacity
+            )));
+        }
+        self.get_overflow().map(|_| {})
+    }
+}
+
+impl SourceSampling {
+    pub fn get_mode(&self) -> Result<source_sampling::Mode, DataflowValidateError> {
+        source_sampling::Mode::from_i32(self.mode).ok_or_else(|| {
+            DataflowValidateError::InvalidSourceSampling(format!("unknown mode {}", self.mode))
+        })
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        if self.rate == 0 {
+            return Err(DataflowValidateError::InvalidSourceSampling(
+                "rate must be positive".to_string(),
+            ));
+        }
+        self.get_mode().map(|_| {})
+    }
+}
+
+impl Redaction {
+    pub fn get_path(&self) -> Result<JsonPath, DataflowValidateError> {
+        let path = JsonPath::parse(&self.path).map_err(|err| {
+            DataflowValidateError::InvalidRedaction(format!(
+                "invalid path [{}]: {}",
+                &self.path, err
+            ))
+        })?;
+        if path.segments.is_empty() {
+            return Err(DataflowValidateError::InvalidRedaction(format!(
+                "path [{}] must select a field of the payload",
+                &self.path
+            )));
+        }
+        if path.has_filter() {
+            return Err(DataflowValidateError::InvalidRedaction(format!(
+                "existence filters of path [{}] are not supported",
+                &self.path
+            )));
+        }
+        Ok(path)
+    }
+
+    pub fn get_strategy(&self) -> Result<redaction::Strategy, DataflowValidateError> {
+        redaction::Strategy::from_i32(self.strategy).ok_or_else(|| {
+            DataflowValidateError::InvalidRedaction(format!("unknown strategy {}", self.strategy))
+        })
+    }
+
+    pub fn get_mask(&self) -> &str {
+        if self.mask.is_empty() {
+            DEFAULT_REDACTION_MASK
+        } else {
+            &self.mask
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        self.get_path()?;
+        self.get_strategy().map(|_| {})
+    }
+}
+
+impl PayloadSchema {
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        let mut names = BTreeSet::new();
+        for field in &self.fields {
+            if field.name.is_empty() {
+                return Err(DataflowValidateError::InvalidPayloadSchema(
+                    "field name must not be empty".to_string(),
+                ));
+            }
+            if !names.insert(field.name.as_str()) {
+                return Err(DataflowValidateError::InvalidPayloadSchema(format!(
+                    "field [{}] is declared more than once",
+                    &field.name
+                )));
+            }
+        }
+        if self.validation() == payload_schema::Validation::Sampling
+            && !(self.sample_fraction > 0.0 && self.sample_fraction <= 1.0)
+        {
+            return Err(DataflowValidateError::InvalidPayloadSchema(format!(
+                "sample fraction [{}] is out of (0, 1]",
+                self.sample_fraction
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn get_field(&self, name: &str) -> Option<&payload_schema::Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// check whether the payloads of this output schema of `upstream` are accepted by the input schema of `downstream`:
+    /// each required input field must be a required output field, and the types of a field must be compatible if both are declared
+    pub fn check_satisfies(
+        &self,
+        upstream: u32,
+        input: &PayloadSchema,
+        downstream: u32,
+    ) -> Result<(), DataflowValidateError> {
+        let into_err = |field: &str, msg: String| {
+            Err(DataflowValidateError::PayloadSchemaMismatch(format!(
+                "output of operator {} does not satisfy input of operator {}: field [{}] {}",
+                upstream, downstream, field, msg
+            )))
+        };
+        for field in &input.fields {
+            match self.get_field(&field.name) {
+                None if field.required => {
+                    return into_err(&field.name, "is required but not emitted".to_string())
+                }
+                None => {}
+                Some(output) if field.required && !output.required => {
+                    return into_err(
+                        &field.name,
+                        "is required but emitted as optional".to_string(),
+                    )
+                }
+                Some(output) if !output.data_type().is_assignable_to(field.data_type()) => {
+                    return into_err(
+                        &field.name,
+                        format!(
+                            "is emitted as {:?} but received as {:?}",
+                            output.data_type(),
+                            field.data_type()
+                        ),
+                    )
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// the CSV columns in the order of the fields
+    pub fn get_csv_columns(&self) -> Vec<csv_format::Column> {
+        self.fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| csv_format::Column {
+                name: field.name.clone(),
+                index: index as u32,
+                data_type: field.data_type,
+            })
+            .collect()
+    }
+
+    /// the Avro record schema of the fields. Optional fields are nullable with the default value null.
+    /// It's `None` if any field name or type can't be mapped into Avro
+    pub fn get_avro_schema(&self) -> Option<String> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| {
+                let avro_type = match field.data_type() {
+                    DataTypeEnum::Bigint => "long",
+                    DataTypeEnum::Number => "double",
+                    DataTypeEnum::String => "string",
+                    DataTypeEnum::Boolean => "boolean",
+                    _ => return None,
+                };
+                if !is_avro_name(&field.name) {
+                    None
+                } else if field.required {
+                    Some(serde_json::json!({ "name": &field.name, "type": avro_type }))
+                } else {
+                    Some(serde_json::json!({
+                        "name": &field.name,
+                        "type": ["null", avro_type],
+                        "default": null,
+                    }))
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(
+            serde_json::json!({
+                "type": "record",
+                "name": "Payload",
+                "namespace": "lightflus",
+                "fields": fields,
+            })
+            .to_string(),
+        )
+    }
+}
+
+/// an address is `host[:port]`, the port is required if `port_required` is true
+fn check_address(address: &str, port_required: bool) -> Result<(), String> {
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (address, None),
+    };
+    if host.is_empty()
+        || host
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '/' | '@' | '?' | '#'))
+    {
+        return Err(format!("[{}] is not a valid host", address));
+    }
+    match port {
+        Some(port) if port.parse::<u16>().map_or(true, |port| port == 0) => {
+            Err(format!("port of [{}] is invalid", address))
+        }
+        None if port_required => Err(format!("port of [{}] is missing", address)),
+        _ => Ok(()),
+    }
+}
+
+/// an HTTP or HTTPS url with a valid host
+fn check_http_url(url: &str) -> Result<(), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .or_else(|| url.strip_prefix("https://"))
+        .ok_or_else(|| format!("[{}] is not an http or https url", url))?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    check_address(authority, false).map_err(|_| format!("host of [{}] is invalid", url))
+}
+
+/// names of Avro must start with [A-Za-z_] and subsequently contain only [A-Za-z0-9_]
+fn is_avro_name(name: &str) -> bool {
+    name.chars()
+        .enumerate()
+        .all(|(index, c)| c == '_' || c.is_ascii_alphabetic() || (index > 0 && c.is_ascii_digit()))
+}
+
+impl DataTypeEnum {
+    /// whether a value of this type is accepted by the other type.
+    /// Unspecified accepts or is accepted by any type, and a bigint is also a number
+    pub fn is_assignable_to(&self, other: DataTypeEnum) -> bool {
+        match (*self, other) {
+            (Self::Unspecified, _) | (_, Self::Unspecified) => true,
+            (Self::Bigint, Self::Number) => true,
+            (this, other) => this == other,
+        }
+    }
+}
+
+impl ErrorPolicy {
+    /// operator id of the dead-letter operator, including the one of the fallback policy
+    pub fn get_dead_letter(&self) -> Option<u32> {
+        match self.policy.as_ref() {
+            Some(error_policy::Policy::DeadLetter(dead_letter)) => Some(dead_letter.sink),
+            Some(error_policy::Policy::Retry(retry)) => retry
+                .fallback
+                .as_ref()
+                .and_then(|fallback| fallback.get_dead_letter()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        match self.policy.as_ref() {
+            Some(error_policy::Policy::Retry(retry)) => {
+                if retry.attempts == 0 {
+                    return Err(DataflowValidateError::InvalidErrorPolicy(
+                        "attempts of retry must be positive".to_string(),
+                    ));
+                }
+                match retry
+                    .fallback
+                    .as_ref()
+                    .and_then(|fallback| fallback.policy.as_ref())
+                {
+                    Some(error_policy::Policy::Retry(_)) => {
+                        Err(DataflowValidateError::InvalidErrorPolicy(
+                            "fallback of retry can't be another retry".to_string(),
+                        ))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl project::Field {
+    pub fn get_path(&self) -> Result<JsonPath, DataflowValidateError> {
+        JsonPath::parse(&self.path).map_err(|err| {
+            DataflowValidateError::InvalidProject(format!(
+                "invalid path [{}] of field [{}]: {}",
+                &self.path, &self.name, err
+            ))
+        })
+    }
+
+    pub fn get_default_value(&self) -> Result<Option<serde_json::Value>, DataflowValidateError> {
+        self.default_value
+            .as_ref()
+            .map(|value| {
+                serde_json::from_str(value).map_err(|err| {
+                    DataflowValidateError::InvalidProject(format!(
+                        "invalid default value [{}] of field [{}]: {}",
+                        value, &self.name, err
+                    ))
+                })
+            })
+            .transpose()
+    }
+}
+
 impl MysqlDesc {
     pub fn get_mysql_statement(&self) -> Statement {
         self.statement
@@ -260,13 +1489,113 @@ impl RedisDesc {
             }
         }
     }
-}
+}
+
+impl DataflowMeta {
+    /// neighbors which are connected by broadcast edges
+    pub fn get_broadcast_neighbors(&self) -> BTreeSet<u32> {
+        self.edge_types
+            .iter()
+            .filter(|(_, edge_type)| **edge_type == EdgeType::Broadcast as i32)
+            .map(|(neighbor, _)| *neighbor)
+            .collect()
+    }
+}
+
+impl Dataflow {
+    pub fn builder() -> DataflowBuilder {
+        Default::default()
+    }
+
+    /// a builder of a copy of this dataflow, whose edges are the ones of its metas
+    pub fn to_builder(&self) -> DataflowBuilder {
+        let edges = self
+            .meta
+            .iter()
+            .map(|meta| {
+                let broadcast = meta.get_broadcast_neighbors();
+                (
+                    meta.center,
+                    meta.neighbors
+                        .iter()
+                        .map(|neighbor| (*neighbor, broadcast.contains(neighbor)))
+                        .collect(),
+                )
+            })
+            .collect();
+        DataflowBuilder {
+            dataflow: Dataflow {
+                meta: vec![],
+                ..self.clone()
+            },
+            edges,
+        }
+    }
+
+    /// the log level which overrides the level of the workers for the operators of this dataflow
+    pub fn get_log_level(&self) -> Result<Option<LevelFilter>, DataflowValidateError> {
+        if self.log_level.is_empty() {
+            return Ok(None);
+        }
+        self.log_level
+            .parse::<LevelFilter>()
+            .map(Some)
+            .map_err(|_| DataflowValidateError::InvalidLogLevel(self.log_level.clone()))
+    }
+
+    /// there are at most [`MAX_LABELS`] labels, and each of them is valid, see [`check_label`]
+    pub fn check_labels(&self) -> Result<(), DataflowValidateError> {
+        if self.labels.len() > MAX_LABELS {
+            return Err(DataflowValidateError::InvalidLabels(format!(
+                "{} labels are more than {}",
+                self.labels.len(),
+                MAX_LABELS
+            )));
+        }
+        self.labels
+            .iter()
+            .try_for_each(|(key, value)| check_label(key, value))
+            .map_err(DataflowValidateError::InvalidLabels)
+    }
+
+    /// a dataflow can't depend on itself, and each of its dependencies is declared once
+    pub fn check_dependencies(&self) -> Result<(), DataflowValidateError> {
+        let job_id = self.get_job_id();
+        let mut dependencies = BTreeSet::new();
+        self.depends_on.iter().try_for_each(|dependency| {
+            if *dependency == job_id {
+                Err(DataflowValidateError::InvalidDependencies(format!(
+                    "job {}/{} depends on itself",
+                    job_id.namespace_id, job_id.resource_id
+                )))
+            } else if !dependencies.insert(dependency) {
+                Err(DataflowValidateError::InvalidDependencies(format!(
+                    "dependency {}/{} is declared more than once",
+                    dependency.namespace_id, dependency.resource_id
+                )))
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    pub fn depends_on_job(&self, job_id: &ResourceId) -> bool {
+        self.depends_on.contains(job_id)
+    }
 
-impl Dataflow {
     pub fn validate(&self) -> Result<(), DataflowValidateError> {
         if self.job_id.is_none() {
             return Err(DataflowValidateError::MissingResourceId);
         }
+        self.get_log_level()?;
+        self.check_labels()?;
+        self.check_dependencies()?;
+        if let Some(event_dedup) = self.event_dedup.as_ref() {
+            event_dedup.check()?;
+        }
+        if let Some(watchdog) = self.watchdog.as_ref() {
+            watchdog.get_stuck_timeout()?;
+        }
         let mut metas = self.meta.to_vec();
         metas.sort_by(|prev, next| prev.center.cmp(&next.center));
 
@@ -286,11 +1615,92 @@ impl Dataflow {
                     return result;
                 }
             }
+
+            for neighbor in meta.get_broadcast_neighbors() {
+                self.check_broadcast_edge(meta, neighbor)?;
+            }
+
+            self.check_payload_schemas(meta)?;
         }
 
         return Ok(());
     }
 
+    /// find the suboptimal parts of the dataflow which don't make it invalid. Warnings are sorted by operator id
+    pub fn lint(&self) -> Vec<DataflowWarning> {
+        let mut operators = self.nodes.iter().collect::<Vec<_>>();
+        operators.sort_by_key(|(operator_id, _)| **operator_id);
+
+        let mut warnings = vec![];
+        for (operator_id, operator) in operators {
+            let mut warn = |kind: LintKind, message: String| {
+                warnings.push(DataflowWarning {
+                    severity: kind.get_severity(),
+                    kind,
+                    operator_id: *operator_id,
+                    message,
+                })
+            };
+
+            // the outputs of the Route operator are its downstreams though they're side outputs
+            let side_outputs = match operator.details.as_ref() {
+                Some(Details::Route(_)) => Default::default(),
+                _ => operator.get_side_outputs(),
+            };
+            let has_downstream = self
+                .meta
+                .iter()
+                .filter(|meta| meta.center == *operator_id)
+                .flat_map(|meta| meta.neighbors.iter())
+                .any(|neighbor| !side_outputs.contains(neighbor));
+            if !operator.has_sink() && !has_downstream {
+                warn(
+                    LintKind::DanglingOperator,
+                    "the outputs are dropped because it's neither a sink nor connected to any downstream".to_string(),
+                );
+            }
+
+            if let Some(Details::Sink(sink)) = operator.details.as_ref() {
+                if sink.may_write_duplicates() {
+                    warn(
+                        LintKind::DuplicateSinkWrites,
+                        "the sink is not exactly-once and its writes are not idempotent, so retried or replayed events may be written more than once".to_string(),
+                    );
+                }
+            }
+
+            if operator.has_runtime_validation() && operator.error_policy.is_none() {
+                warn(
+                    LintKind::SilentSchemaViolations,
+                    "events violating the input schema are skipped silently because no error policy is set".to_string(),
+                );
+            }
+
+            if let Some(Details::Window(window)) = operator.details.as_ref() {
+                if window.side_output.is_none() {
+                    warn(
+                        LintKind::DroppedLateEvents,
+                        "late events are dropped because no side output is set".to_string(),
+                    );
+                }
+            }
+
+            if let Some(error_policy::Policy::Retry(retry)) = operator
+                .error_policy
+                .as_ref()
+                .and_then(|error_policy| error_policy.policy.as_ref())
+            {
+                if retry.fallback.is_none() {
+                    warn(
+                        LintKind::RetryWithoutFallback,
+                        "failed events are skipped after the retries because no fallback policy is set".to_string(),
+                    );
+                }
+            }
+        }
+        warnings
+    }
+
     pub fn check_operator(&self, node_id: u32) -> Result<(), DataflowValidateError> {
         if !self.nodes.contains_key(&node_id) {
             Err(DataflowValidateError::OperatorInfoMissing(format!(
@@ -299,15 +1709,214 @@ impl Dataflow {
             )))
         } else {
             let operator = self.nodes.get(&node_id).unwrap();
+            for schema in operator
+                .input_schema
+                .iter()
+                .chain(operator.output_schema.iter())
+            {
+                schema.check()?;
+            }
+            if let Some(state_limit) = operator.state_limit.as_ref() {
+                state_limit.check()?;
+            }
+            if operator.headers.keys().any(|name| name.is_empty()) {
+                return Err(DataflowValidateError::InvalidHeaders(format!(
+                    "header name of operator {} is empty",
+                    node_id
+                )));
+            }
+            if let Some(error_policy) = operator.error_policy.as_ref() {
+                error_policy.check()?;
+                self.check_side_output(
+                    node_id,
+                    error_policy.get_dead_letter(),
+                    DataflowValidateError::InvalidErrorPolicy,
+                )?;
+            }
 
-            match operator.details.as_ref() {
+            let result = match operator.details.as_ref() {
                 Some(detail) => match detail {
                     Details::Source(source) => source.check(),
                     Details::Sink(sink) => sink.check(),
+                    Details::Project(project) => project.check(),
+                    Details::Throttle(throttle) => throttle.check(),
+                    Details::WasmUdf(wasm_udf) => wasm_udf.check(),
+                    Details::FilterExpr(filter_expr) => filter_expr.check(),
+                    Details::MapExpr(map_expr) => map_expr.check(),
+                    Details::AsyncLookup(async_lookup) => async_lookup.check(),
+                    Details::Route(route) => {
+                        route.check()?;
+                        let mut outputs = route.outputs.iter().collect::<Vec<_>>();
+                        outputs.sort();
+                        outputs.into_iter().try_for_each(|(_, downstream)| {
+                            self.check_side_output(
+                                node_id,
+                                Some(*downstream),
+                                DataflowValidateError::InvalidRoute,
+                            )
+                        })
+                    }
+                    Details::Deduplicate(deduplicate) => {
+                        deduplicate.check()?;
+                        self.check_side_output(
+                            node_id,
+                            deduplicate.side_output,
+                            DataflowValidateError::InvalidDeduplicate,
+                        )
+                    }
+                    Details::Window(window) => {
+                        window.check()?;
+                        self.check_side_output(
+                            node_id,
+                            window.side_output,
+                            DataflowValidateError::InvalidWindow,
+                        )
+                    }
+                    Details::SortBuffer(sort_buffer) => {
+                        sort_buffer.check()?;
+                        self.check_side_output(
+                            node_id,
+                            sort_buffer.side_output,
+                            DataflowValidateError::InvalidSortBuffer,
+                        )
+                    }
                     _ => Ok(()),
                 },
                 None => return Err(DataflowValidateError::OperatorDetailMissing(node_id)),
+            };
+            result?;
+            operator
+                .check_config()
+                .map_err(DataflowValidateError::InvalidOperatorConfig)
+        }
+    }
+
+    fn check_broadcast_edge(
+        &self,
+        meta: &DataflowMeta,
+        neighbor: u32,
+    ) -> Result<(), DataflowValidateError> {
+        let into_err = |msg: &str| {
+            Err(DataflowValidateError::InvalidBroadcastEdge(format!(
+                "broadcast edge from node {} to node {} {}",
+                meta.center, neighbor, msg
+            )))
+        };
+        match self.nodes.get(&neighbor) {
+            _ if !meta.neighbors.contains(&neighbor) => into_err("is not in neighbors"),
+            Some(operator) if operator.has_sink() => into_err("points to a sink"),
+            Some(operator) if operator.has_source() => into_err("points to a source"),
+            _ => Ok(()),
+        }
+    }
+
+    /// the output schema of the operator must satisfy the input schemas of its downstreams where both are declared.
+    /// Side outputs are not checked because they receive the input events of the operator
+    fn check_payload_schemas(&self, meta: &DataflowMeta) -> Result<(), DataflowValidateError> {
+        let operator = match self.nodes.get(&meta.center) {
+            Some(operator) => operator,
+            None => return Ok(()),
+        };
+        let output = match operator.output_schema.as_ref() {
+            Some(output) => output,
+            None => return Ok(()),
+        };
+        let side_outputs = operator.get_side_outputs();
+        for neighbor in meta
+            .neighbors
+            .iter()
+            .filter(|neighbor| !side_outputs.contains(neighbor))
+        {
+            if let Some(input) = self
+                .nodes
+                .get(neighbor)
+                .and_then(|downstream| downstream.input_schema.as_ref())
+            {
+                output.check_satisfies(meta.center, input, *neighbor)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_side_output<F: FnOnce(String) -> DataflowValidateError>(
+        &self,
+        node_id: u32,
+        side_output: Option<u32>,
+        into_err: F,
+    ) -> Result<(), DataflowValidateError> {
+        match side_output {
+            Some(side_output) if !self.is_downstream(node_id, side_output) => {
+                Err(into_err(format!(
+                    "side output {} is not a downstream of node {}",
+                    side_output, node_id
+                )))
             }
+            _ => Ok(()),
+        }
+    }
+
+    fn is_downstream(&self, node_id: u32, downstream: u32) -> bool {
+        self.meta
+            .iter()
+            .any(|meta| meta.center == node_id && meta.neighbors.contains(&downstream))
+    }
+
+    /// the operators chained after each head operator in order. A head operator is not chained with its upstream.
+    /// The rules of chaining are described in [`Chaining`]
+    pub fn get_operator_chains(&self) -> BTreeMap<u32, Vec<u32>> {
+        let members = self
+            .meta
+            .iter()
+            .filter_map(|meta| self.get_chained_downstream(meta.center))
+            .collect::<BTreeSet<_>>();
+
+        self.meta
+            .iter()
+            .map(|meta| meta.center)
+            .filter(|operator_id| !members.contains(operator_id))
+            .filter_map(|head| {
+                let mut chain = vec![];
+                let mut current = head;
+                while let Some(downstream) = self.get_chained_downstream(current) {
+                    chain.push(downstream);
+                    current = downstream;
+                }
+                if chain.is_empty() {
+                    None
+                } else {
+                    Some((head, chain))
+                }
+            })
+            .collect()
+    }
+
+    /// the downstream which the operator is chained with
+    fn get_chained_downstream(&self, operator_id: u32) -> Option<u32> {
+        let meta = self.meta.iter().find(|meta| meta.center == operator_id)?;
+        let downstream = match meta.neighbors.as_slice() {
+            [downstream] => *downstream,
+            _ => return None,
+        };
+        let operator = self.nodes.get(&operator_id)?;
+        let downstream_operator = self.nodes.get(&downstream)?;
+        // upstreams on other hosts are not in the metas of a sub-dataflow, so the declared upstreams are checked too
+        let upstreams = self
+            .meta
+            .iter()
+            .filter(|meta| meta.neighbors.contains(&downstream))
+            .count();
+
+        if operator.is_chainable()
+            && downstream_operator.is_chainable()
+            && meta.get_broadcast_neighbors().is_empty()
+            && upstreams == 1
+            && downstream_operator.upstreams.len() <= 1
+            && operator.host_addr == downstream_operator.host_addr
+            && self.meta.iter().any(|meta| meta.center == downstream)
+        {
+            Some(downstream)
+        } else {
+            None
         }
     }
 
@@ -323,6 +1932,141 @@ impl Dataflow {
     }
 }
 
+/// Builder of [`Dataflow`]. The graph is described by the operators and the edges between them: the metas of the dataflow and
+/// the upstreams of the operators are derived from the edges, so they can't disagree. The dataflow is validated by [`DataflowBuilder::build`]
+#[derive(Debug, Default, Clone)]
+pub struct DataflowBuilder {
+    dataflow: Dataflow,
+    // downstreams of each operator, and whether the edge to it is a broadcast edge
+    edges: BTreeMap<u32, BTreeMap<u32, bool>>,
+}
+
+impl DataflowBuilder {
+    pub fn job_id<N: Into<String>, R: Into<String>>(mut self, namespace: N, name: R) -> Self {
+        self.dataflow.job_id = Some(ResourceId::new(namespace, name));
+        self
+    }
+
+    /// add the operator, it replaces the operator with the same id
+    pub fn operator(mut self, operator: OperatorInfo) -> Self {
+        self.dataflow.nodes.insert(operator.operator_id, operator);
+        self
+    }
+
+    /// add a forward edge from the upstream operator to the downstream one
+    pub fn edge(mut self, upstream: u32, downstream: u32) -> Self {
+        self.edges
+            .entry(upstream)
+            .or_default()
+            .insert(downstream, false);
+        self
+    }
+
+    /// add a broadcast edge from the upstream operator to the downstream one, see [`EdgeType::Broadcast`]
+    pub fn broadcast_edge(mut self, upstream: u32, downstream: u32) -> Self {
+        self.edges
+            .entry(upstream)
+            .or_default()
+            .insert(downstream, true);
+        self
+    }
+
+    pub fn label<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.dataflow.labels.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn depends_on(mut self, job_id: ResourceId) -> Self {
+        self.dataflow.depends_on.push(job_id);
+        self
+    }
+
+    /// the dataflow with a meta of every operator, or the error of [`Dataflow::validate`]
+    pub fn build(self) -> Result<Dataflow, DataflowValidateError> {
+        let dataflow = self.build_unvalidated();
+        dataflow.validate()?;
+        Ok(dataflow)
+    }
+
+    /// the same as [`DataflowBuilder::build`] without validating the dataflow.
+    /// It's for the conversion of the requests, whose dataflows are validated by their handlers so the errors can be detailed
+    pub fn build_unvalidated(self) -> Dataflow {
+        let Self {
+            mut dataflow,
+            edges,
+        } = self;
+        let centers = dataflow
+            .nodes
+            .keys()
+            .chain(edges.keys())
+            .copied()
+            .collect::<BTreeSet<_>>();
+        dataflow.meta = centers
+            .into_iter()
+            .map(|center| {
+                let downstreams = edges.get(&center);
+                DataflowMeta {
+                    center,
+                    neighbors: downstreams
+                        .map(|downstreams| downstreams.keys().copied().collect())
+                        .unwrap_or_default(),
+                    edge_types: downstreams
+                        .into_iter()
+                        .flatten()
+                        .filter(|(_, broadcast)| **broadcast)
+                        .map(|(downstream, _)| (*downstream, EdgeType::Broadcast as i32))
+                        .collect(),
+                }
+            })
+            .collect();
+        for (upstream, downstreams) in &edges {
+            for downstream in downstreams.keys() {
+                if let Some(operator) = dataflow.nodes.get_mut(downstream) {
+                    if !operator.upstreams.contains(upstream) {
+                        operator.upstreams.push(*upstream);
+                    }
+                }
+            }
+        }
+        dataflow
+    }
+}
+
+/// the indexes of the dataflows in the order where each one comes after the ones it depends on, so that the dependencies start first.
+/// Dependencies which are not in the slice are ignored, and the dataflows are kept in their relative order otherwise.
+/// A dependency cycle is broken by ignoring the dependency which closes it
+pub fn order_by_dependencies(dataflows: &[Dataflow]) -> Vec<usize> {
+    fn visit(
+        index: usize,
+        dataflows: &[Dataflow],
+        indexes: &BTreeMap<ResourceId, usize>,
+        visited: &mut [bool],
+        ordered: &mut Vec<usize>,
+    ) {
+        if visited[index] {
+            return;
+        }
+        visited[index] = true;
+        dataflows[index]
+            .depends_on
+            .iter()
+            .filter_map(|dependency| indexes.get(dependency))
+            .for_each(|dependency| visit(*dependency, dataflows, indexes, visited, ordered));
+        ordered.push(index);
+    }
+
+    let indexes = dataflows
+        .iter()
+        .enumerate()
+        .map(|(index, dataflow)| (dataflow.get_job_id(), index))
+        .collect::<BTreeMap<_, _>>();
+    let mut visited = vec![false; dataflows.len()];
+    let mut ordered = Vec::with_capacity(dataflows.len());
+    (0..dataflows.len())
+        .for_each(|index| visit(index, dataflows, &indexes, &mut visited, &mut ordered));
+    ordered
+}
+
 #[derive(Debug, serde::Serialize)]
 pub enum DataflowValidateError {
     MissingRedisConnectionOpts,
@@ -342,10 +2086,115 @@ pub enum DataflowValidateError {
     MissingKafkaBrokers,
     MissingKafkaDataType,
     MissingKafkaTopic,
+    InvalidCsvFormat(String),
+    InvalidAvroFormat(String),
+    InvalidProtobufFormat(String),
+    InvalidProject(String),
+    InvalidThrottle(String),
+    InvalidDeduplicate(String),
+    InvalidEventDedup(String),
+    InvalidWatchdog(String),
+    InvalidSortBuffer(String),
+    InvalidWindow(String),
+    InvalidBroadcastEdge(String),
+    InvalidErrorPolicy(String),
+    InvalidWasmUdf(String),
+    InvalidSqlExpr(String),
+    InvalidRoute(String),
+    InvalidAsyncLookup(String),
+    InvalidStateLimit(String),
+    InvalidSinkBatching(String),
+    InvalidSourceSampling(String),
+    InvalidRedaction(String),
+    InvalidLogLevel(String),
+    InvalidLabels(String),
+    InvalidDependencies(String),
+    InvalidHeaders(String),
+    InvalidKafkaSinkOptions(String),
+    InvalidPayloadSchema(String),
+    PayloadSchemaMismatch(String),
+    InvalidOperatorConfig(OperatorConfigError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+pub enum LintSeverity {
+    Info,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum LintKind {
+    /// an operator which is not a sink has no downstream
+    DanglingOperator,
+    /// a sink which is not exactly-once writes to a destination where writes are not idempotent
+    DuplicateSinkWrites,
+    /// the input events are validated at runtime but the operator has no error policy
+    SilentSchemaViolations,
+    /// a window has no side output for late events
+    DroppedLateEvents,
+    /// a retry policy has no fallback policy
+    RetryWithoutFallback,
+}
+
+impl LintKind {
+    pub fn get_severity(&self) -> LintSeverity {
+        match self {
+            Self::DanglingOperator | Self::DuplicateSinkWrites | Self::SilentSchemaViolations => {
+                LintSeverity::Warning
+            }
+            Self::DroppedLateEvents | Self::RetryWithoutFallback => LintSeverity::Info,
+        }
+    }
+}
+
+/// an invalid field of the config of an operator, found by [`OperatorInfo::check_config`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct OperatorConfigError {
+    pub operator_id: u32,
+    /// the type of the operator, see [`OperatorInfo::get_kind`]
+    pub kind: &'static str,
+    /// the path of the field in the details of the operator
+    pub field: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for OperatorConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} operator {}: field [{}] {}",
+            self.kind, self.operator_id, self.field, self.message
+        )
+    }
+}
+
+/// a non-fatal finding of [`Dataflow::lint`]. It doesn't block the creation of the dataflow
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DataflowWarning {
+    pub severity: LintSeverity,
+    pub kind: LintKind,
+    pub operator_id: u32,
+    pub message: String,
+}
+
+impl std::fmt::Display for DataflowWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{:?}] {:?} of operator {}: {}",
+            self.severity, self.kind, self.operator_id, self.message
+        )
+    }
 }
 
 impl Source {
     pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+        if let Some(sampling) = self.sampling.as_ref() {
+            sampling.check()?;
+        }
+        for redaction in &self.redactions {
+            redaction.check()?;
+        }
         match self.desc.as_ref() {
             Some(desc) => match desc {
                 source::Desc::Kafka(kafka) => kafka.check(),
@@ -356,19 +2205,48 @@ impl Source {
 }
 
 impl Sink {
-    pub(crate) fn check(&self) -> Result<(), DataflowValidateError> {
+    /// check the sink configuration. It's also used to validate the configuration changed at runtime
+    pub fn check(&self) -> Result<(), DataflowValidateError> {
+        if let Some(batching) = self.batching.as_ref() {
+            batching.check()?;
+        }
         match self.desc.as_ref() {
             Some(desc) => match desc {
                 sink::Desc::Redis(redis) => redis.check(),
-                sink::Desc::Kafka(kafka) => kafka.check(),
+                sink::Desc::Kafka(kafka) => kafka.check_sink(),
                 sink::Desc::Mysql(mysql) => mysql.check(),
             },
             None => Err(DataflowValidateError::MissingSinkDesc),
         }
     }
+
+    /// whether retried or replayed events may be written more than once.
+    /// Redis writes and MySQL upserts are idempotent, the other sinks have to be exactly-once
+    pub(crate) fn may_write_duplicates(&self) -> bool {
+        if self.delivery_guarentee() == DeliveryGuarentee::DeliveryExactlyOnce {
+            return false;
+        }
+        match self.desc.as_ref() {
+            Some(sink::Desc::Redis(_)) | None => false,
+            Some(sink::Desc::Kafka(_)) => true,
+            Some(sink::Desc::Mysql(mysql)) => {
+                let statement = mysql.get_mysql_statement().statement.to_ascii_uppercase();
+                let statement = statement.trim_start();
+                !(statement.starts_with("REPLACE")
+                    || statement.starts_with("UPDATE")
+                    || statement.starts_with("DELETE")
+                    || statement.starts_with("INSERT IGNORE")
+                    || statement.contains("ON DUPLICATE KEY UPDATE"))
+            }
+        }
+    }
 }
 
 impl KeyedDataEvent {
+    pub fn builder() -> KeyedDataEventBuilder {
+        Default::default()
+    }
+
     #[inline]
     pub fn get_job_id(&self) -> ResourceId {
         if self.job_id.is_none() {
@@ -392,6 +2270,81 @@ impl KeyedDataEvent {
     pub fn get_event_time(&self) -> i64 {
         self.event_time
     }
+
+    /// the value of a metadata header of the event
+    #[inline]
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|value| value.as_str())
+    }
+
+    /// add a metadata header to the event, it replaces the header with the same name
+    pub fn set_header<K: Into<String>, V: Into<String>>(&mut self, name: K, value: V) {
+        self.headers.insert(name.into(), value.into());
+    }
+
+    /// the metadata headers ordered by their names, so that they're emitted in the same order
+    pub fn get_sorted_headers(&self) -> Vec<(&str, &str)> {
+        let mut headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        headers.sort();
+        headers
+    }
+}
+
+/// Builder of [`KeyedDataEvent`]. The key and the payloads are anything which can be converted to an [`Entry`]
+#[derive(Debug, Default, Clone)]
+pub struct KeyedDataEventBuilder {
+    event: KeyedDataEvent,
+}
+
+impl KeyedDataEventBuilder {
+    pub fn job_id(mut self, job_id: &ResourceId) -> Self {
+        self.event.job_id = Some(job_id.clone());
+        self
+    }
+
+    pub fn key<E: Into<Entry>>(mut self, key: E) -> Self {
+        self.event.key = Some(key.into());
+        self
+    }
+
+    /// append a payload to the event
+    pub fn payload<E: Into<Entry>>(mut self, payload: E) -> Self {
+        self.event.data.push(payload.into());
+        self
+    }
+
+    pub fn event_time(mut self, event_time: i64) -> Self {
+        self.event.event_time = event_time;
+        self
+    }
+
+    pub fn event_id(mut self, event_id: i64) -> Self {
+        self.event.event_id = event_id;
+        self
+    }
+
+    pub fn from_operator_id(mut self, from_operator_id: u32) -> Self {
+        self.event.from_operator_id = from_operator_id;
+        self
+    }
+
+    pub fn to_operator_id(mut self, to_operator_id: u32) -> Self {
+        self.event.to_operator_id = to_operator_id;
+        self
+    }
+
+    pub fn header<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.event.set_header(name, value);
+        self
+    }
+
+    pub fn build(self) -> KeyedDataEvent {
+        self.event
+    }
 }
 
 impl FixedWindow {
@@ -463,6 +2416,139 @@ impl Response {
         Self {
             status: SUCCESS_RPC_RESPONSE.to_string(),
             err_msg: String::default(),
+            placement: None,
+            warnings: vec![],
+            error: None,
+        }
+    }
+
+    /// failed response carrying the details of the error
+    pub fn error(detail: ErrorDetail) -> Self {
+        Self {
+            status: FAILURE_RPC_RESPONSE.to_string(),
+            err_msg: detail.message.clone(),
+            error: Some(detail),
+            ..Self::ok()
+        }
+    }
+
+    /// whether the request succeeds. A successful response may still carry warnings
+    pub fn is_ok(&self) -> bool {
+        self.status == SUCCESS_RPC_RESPONSE && self.error.is_none()
+    }
+
+    pub fn get_error(&self) -> Option<&ErrorDetail> {
+        self.error.as_ref()
+    }
+
+    /// read the response attached to a failed status. If the status carries no response, e.g. it's created by tonic itself,
+    /// the details of the error are built from the code and the message of the status
+    pub fn from_status(status: &tonic::Status) -> Self {
+        use prost::Message;
+        match Self::decode(status.details()) {
+            Ok(response) if response.error.is_some() => response,
+            _ => Self::error(ErrorDetail::from_status(status)),
+        }
+    }
+
+    /// convert a failed response into a status with the same code, attaching the response as its details
+    pub fn into_status(self) -> tonic::Status {
+        use prost::Message;
+        let detail = self.error.clone().unwrap_or_default();
+        tonic::Status::with_details(
+            tonic::Code::from_i32(detail.code),
+            detail.message,
+            self.encode_to_vec().into(),
+        )
+    }
+
+    /// successful response of creating a dataflow, carrying where the dataflow is deployed
+    pub fn with_placement(placement: DataflowPlacement) -> Self {
+        Self {
+            placement: Some(placement),
+            ..Self::ok()
+        }
+    }
+}
+
+impl ErrorDetail {
+    pub fn from_status(status: &tonic::Status) -> Self {
+        Self {
+            code: status.code() as i32,
+            message: status.message().to_string(),
+            retryable: is_retryable(status.code()),
+        }
+    }
+
+    pub fn get_code(&self) -> tonic::Code {
+        tonic::Code::from_i32(self.code)
+    }
+}
+
+/// failures caused by transient conditions of the cluster, which may disappear if the request is sent again
+fn is_retryable(code: tonic::Code) -> bool {
+    matches!(
+        code,
+        tonic::Code::Unavailable
+            | tonic::Code::DeadlineExceeded
+            | tonic::Code::ResourceExhausted
+            | tonic::Code::Aborted
+    )
+}
+
+impl DataflowPlacement {
+    /// whether all partitions of the dataflow are started
+    pub fn is_started(&self) -> bool {
+        self.partitions
+            .iter()
+            .all(|partition| partition.status() == PartitionStatus::Started)
+    }
+
+    pub fn get_failed_partitions(&self) -> Vec<&PartitionPlacement> {
+        self.partitions
+            .iter()
+            .filter(|partition| partition.status() == PartitionStatus::Failed)
+            .collect()
+    }
+}
+
+impl DataflowStatus {
+    /// whether a dataflow in the status may move to the next one. A dataflow may stay in its status with another reason,
+    /// but a closed dataflow never changes, because a dataflow created again starts from [`DataflowStatus::Pending`]
+    pub fn can_transition_to(&self, next: DataflowStatus) -> bool {
+        use DataflowStatus::*;
+        if *self == next {
+            return true;
+        }
+        match self {
+            Pending => matches!(next, Deploying | Recovering | Closing | Closed),
+            Deploying => matches!(next, Running | Degraded | Failed | Closing | Closed),
+            Running | Degraded | SourcesPaused => matches!(
+                next,
+                Running
+                    | Degraded
+                    | Recovering
+                    | SourcesPaused
+                    | Suspended
+                    | Failed
+                    | Closing
+                    | Closed
+            ),
+            Recovering => matches!(next, Running | Degraded | Failed | Closing | Closed),
+            Suspended => matches!(next, Deploying | Closing | Closed),
+            Failed => matches!(next, Deploying | Recovering | Closing | Closed),
+            // a termination which fails on some of the TaskManagers leaves the others running
+            Closing => matches!(next, Degraded | Failed | Closed),
+            Closed => false,
+        }
+    }
+}
+
+impl ResourceId {
+    pub fn new<N: Into<String>, R: Into<String>>(namespace: N, name: R) -> Self {
+        Self {
+            resource_id: name.into(),
+            namespace_id: namespace.into(),
         }
     }
 }
@@ -574,3 +2660,452 @@ impl KeyedDataEvent {
 pub enum KeyedDataEventError {
     AvroError(apache_avro::Error),
 }
+
+/// Error of connecting to a remote gRPC server eagerly
+#[derive(Debug)]
+pub enum ConnectionError {
+    /// the connection can't be established before the connect timeout
+    Timeout,
+    Transport(tonic::transport::Error),
+}
+
+impl std::fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Timeout => f.write_str("connect timeout"),
+            Self::Transport(err) => f.write_fmt(format_args!("transport error: {}", err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use proptest::prelude::*;
+
+    use super::{
+        kafka_desc, payload_schema, AvroFormat, CsvFormat, DataTypeEnum, Dataflow,
+        DataflowPlacement, DataflowStatus, DataflowValidateError, Details, EdgeType, Entry,
+        ErrorDetail, HostAddr, KafkaDesc, KeyedDataEvent, OperatorInfo, PayloadSchema, ResourceId,
+        Response,
+    };
+
+    fn hash(addr: &HostAddr) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        addr.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// gateway caches and node de-duplication are keyed by the host and the port only
+    #[test]
+    fn test_host_addr_eq_and_hash() {
+        let addr = HostAddr {
+            host: "localhost".to_string(),
+            port: 8080,
+        };
+        let same = addr.clone();
+        assert_eq!(addr, same);
+        assert_eq!(hash(&addr), hash(&same));
+
+        let other_port = HostAddr {
+            host: "localhost".to_string(),
+            port: 8081,
+        };
+        assert_ne!(addr, other_port);
+
+        let other_host = HostAddr {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+        };
+        assert_ne!(addr, other_host);
+    }
+
+    #[test]
+    fn test_payload_schema_format_mappings() {
+        let field = |name: &str, data_type: DataTypeEnum, required| payload_schema::Field {
+            name: name.to_string(),
+            data_type: data_type as i32,
+            required,
+        };
+        let schema = PayloadSchema {
+            fields: vec![
+                field("id", DataTypeEnum::Bigint, true),
+                field("name", DataTypeEnum::String, false),
+            ],
+            ..Default::default()
+        };
+        let with_format = |format| KafkaDesc {
+            format: Some(format),
+            ..Default::default()
+        };
+
+        // csv columns are taken from the fields in order
+        let desc = with_format(kafka_desc::Format::Csv(CsvFormat::default()))
+            .with_payload_schema(Some(&schema));
+        let columns = desc.get_csv_format().unwrap().columns.clone();
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[1].name, "name");
+        assert_eq!(columns[1].index, 1);
+        assert_eq!(columns[1].data_type(), DataTypeEnum::String);
+
+        // configured columns are kept
+        let mut csv = CsvFormat::default();
+        csv.columns = vec![Default::default()];
+        let desc = with_format(kafka_desc::Format::Csv(csv)).with_payload_schema(Some(&schema));
+        assert_eq!(desc.get_csv_format().unwrap().columns.len(), 1);
+
+        let desc = with_format(kafka_desc::Format::Avro(AvroFormat::default()))
+            .with_payload_schema(Some(&schema));
+        let avro_schema =
+            apache_avro::Schema::parse_str(&desc.get_avro_format().unwrap().schema).unwrap();
+        assert_eq!(
+            avro_schema.canonical_form(),
+            r#"{"name":"lightflus.Payload","type":"record","fields":[{"name":"id","type":"long"},{"name":"name","type":["null","string"]}]}"#
+        );
+
+        // fields which can't be mapped into avro
+        let mut object_schema = schema.clone();
+        object_schema
+            .fields
+            .push(field("tags", DataTypeEnum::Object, false));
+        assert!(object_schema.get_avro_schema().is_none());
+        let mut invalid_name = schema.clone();
+        invalid_name
+            .fields
+            .push(field("1st", DataTypeEnum::String, false));
+        assert!(invalid_name.get_avro_schema().is_none());
+    }
+
+    #[test]
+    fn test_error_response() {
+        let ok = Response {
+            warnings: vec!["operator 1 is not in the savepoint".to_string()],
+            ..Response::ok()
+        };
+        assert!(ok.is_ok());
+        assert!(ok.get_error().is_none());
+
+        let response = Response::error(ErrorDetail {
+            code: tonic::Code::Unavailable as i32,
+            message: "no available worker".to_string(),
+            retryable: true,
+        });
+        assert!(!response.is_ok());
+        assert_eq!(response.err_msg, "no available worker");
+
+        // clients read the same response from the status
+        let status = Response {
+            placement: Some(DataflowPlacement::default()),
+            ..response.clone()
+        }
+        .into_status();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert_eq!(status.message(), "no available worker");
+        let received = Response::from_status(&status);
+        assert!(!received.is_ok());
+        assert_eq!(received.get_error(), response.get_error());
+        assert_eq!(received.placement, Some(DataflowPlacement::default()));
+
+        // statuses without a response attached
+        let received = Response::from_status(&tonic::Status::invalid_argument("no job id"));
+        let detail = received.get_error().unwrap();
+        assert_eq!(detail.get_code(), tonic::Code::InvalidArgument);
+        assert_eq!(detail.message, "no job id");
+        assert!(!detail.retryable);
+        assert!(
+            Response::from_status(&tonic::Status::deadline_exceeded("timeout"))
+                .get_error()
+                .unwrap()
+                .retryable
+        );
+    }
+
+    #[test]
+    fn test_operator_state_schema() {
+        let reducer = OperatorInfo {
+            operator_id: 2,
+            upstreams: vec![1, 0],
+            details: Some(Details::Reducer(Default::default())),
+            input_schema: Some(PayloadSchema {
+                fields: vec![payload_schema::Field {
+                    name: "count".to_string(),
+                    data_type: DataTypeEnum::Number as i32,
+                    required: true,
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            reducer.get_state_schema(),
+            "reducer;upstreams={0, 1};input=[count:DATA_TYPE_ENUM_NUMBER:true]"
+        );
+
+        // placement and the order of upstreams don't change the layout of the state
+        let moved = OperatorInfo {
+            host_addr: Some(HostAddr {
+                host: "localhost".to_string(),
+                port: 8080,
+            }),
+            upstreams: vec![0, 1],
+            ..reducer.clone()
+        };
+        assert_eq!(moved.get_state_schema(), reducer.get_state_schema());
+
+        let incompatible = vec![
+            OperatorInfo {
+                details: Some(Details::Filter(Default::default())),
+                ..reducer.clone()
+            },
+            OperatorInfo {
+                upstreams: vec![1],
+                ..reducer.clone()
+            },
+            OperatorInfo {
+                input_schema: None,
+                ..reducer.clone()
+            },
+        ];
+        incompatible.iter().for_each(|operator| {
+            assert_ne!(operator.get_state_schema(), reducer.get_state_schema())
+        });
+    }
+
+    /// every pair of the statuses, with the rows as the current statuses and the columns as the next ones
+    #[test]
+    fn test_dataflow_status_transitions() {
+        use DataflowStatus::*;
+        let statuses = [
+            Pending,
+            Deploying,
+            Running,
+            Degraded,
+            Recovering,
+            SourcesPaused,
+            Suspended,
+            Failed,
+            Closing,
+            Closed,
+        ];
+        // P  D  R  Dg Rc SP S  F  C  Cd
+        let table = [
+            [1, 1, 0, 0, 1, 0, 0, 0, 1, 1], // Pending
+            [0, 1, 1, 1, 0, 0, 0, 1, 1, 1], // Deploying
+            [0, 0, 1, 1, 1, 1, 1, 1, 1, 1], // Running
+            [0, 0, 1, 1, 1, 1, 1, 1, 1, 1], // Degraded
+            [0, 0, 1, 1, 1, 0, 0, 1, 1, 1], // Recovering
+            [0, 0, 1, 1, 1, 1, 1, 1, 1, 1], // SourcesPaused
+            [0, 1, 0, 0, 0, 0, 1, 0, 1, 1], // Suspended
+            [0, 1, 0, 0, 1, 0, 0, 1, 1, 1], // Failed
+            [0, 0, 0, 1, 0, 0, 0, 1, 1, 1], // Closing
+            [0, 0, 0, 0, 0, 0, 0, 0, 0, 1], // Closed
+        ];
+        for (from, row) in statuses.iter().zip(table) {
+            for (to, allowed) in statuses.iter().zip(row) {
+                assert_eq!(
+                    from.can_transition_to(*to),
+                    allowed == 1,
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+        // a terminated dataflow runs again only if it's created again
+        assert!(!Closed.can_transition_to(Running));
+        assert!(Pending.can_transition_to(Deploying));
+    }
+
+    #[test]
+    fn test_dataflow_builder() {
+        let dataflow = Dataflow::builder()
+            .job_id("default", "word_count")
+            .operator(
+                OperatorInfo::flat_map(1, "(v) => v.split(' ')").with_host_addr("localhost", 8792),
+            )
+            .operator(OperatorInfo::key_by(2, "(v) => v"))
+            .operator(OperatorInfo::reduce(3, "(a, b) => a + b"))
+            .operator(OperatorInfo::map(4, "(v) => v"))
+            .edge(1, 2)
+            .edge(2, 3)
+            .broadcast_edge(1, 4)
+            .edge(1, 2)
+            .label("team", "search")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            dataflow.get_job_id(),
+            ResourceId::new("default", "word_count")
+        );
+        assert_eq!(
+            dataflow
+                .meta
+                .iter()
+                .map(|meta| (meta.center, meta.neighbors.clone()))
+                .collect::<Vec<_>>(),
+            vec![(1, vec![2, 4]), (2, vec![3]), (3, vec![]), (4, vec![])]
+        );
+        assert_eq!(
+            dataflow.meta[0]
+                .get_broadcast_neighbors()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![4]
+        );
+        assert_eq!(
+            dataflow.meta[0].edge_types.get(&4),
+            Some(&(EdgeType::Broadcast as i32))
+        );
+        assert!(dataflow.nodes[&1].upstreams.is_empty());
+        assert_eq!(dataflow.nodes[&2].upstreams, vec![1]);
+        assert_eq!(dataflow.nodes[&3].upstreams, vec![2]);
+        assert_eq!(dataflow.nodes[&4].upstreams, vec![1]);
+        assert_eq!(
+            dataflow.nodes[&1].host_addr,
+            Some(HostAddr {
+                host: "localhost".to_string(),
+                port: 8792,
+            })
+        );
+        assert_eq!(
+            dataflow.labels.get("team").map(String::as_str),
+            Some("search")
+        );
+
+        // a dataflow is built again from its metas
+        assert_eq!(dataflow.to_builder().build().unwrap(), dataflow);
+        let relabeled = dataflow
+            .to_builder()
+            .job_id("default", "word_count_v2")
+            .label("team", "ads")
+            .build()
+            .unwrap();
+        assert_eq!(relabeled.meta, dataflow.meta);
+        assert_eq!(relabeled.nodes, dataflow.nodes);
+        assert_eq!(
+            relabeled.labels.get("team").map(String::as_str),
+            Some("ads")
+        );
+
+        // the dataflow is validated when it's built
+        assert!(matches!(
+            Dataflow::builder()
+                .operator(OperatorInfo::map(1, "(v) => v"))
+                .build(),
+            Err(DataflowValidateError::MissingResourceId)
+        ));
+        assert!(Dataflow::builder()
+            .operator(OperatorInfo::map(1, "(v) => v"))
+            .build_unvalidated()
+            .job_id
+            .is_none());
+        assert!(matches!(
+            Dataflow::builder()
+                .job_id("default", "job")
+                .operator(OperatorInfo::map(1, "(v) => v"))
+                .edge(1, 2)
+                .build(),
+            Err(DataflowValidateError::OperatorInfoMissing(_))
+        ));
+        assert!(matches!(
+            Dataflow::builder()
+                .job_id("default", "job")
+                .operator(OperatorInfo::map(1, "(v) => v"))
+                .operator(OperatorInfo::map(2, "(v) => v"))
+                .edge(2, 1)
+                .build(),
+            Err(DataflowValidateError::CyclicDataflow)
+        ));
+        assert!(matches!(
+            Dataflow::builder()
+                .job_id("default", "job")
+                .operator(OperatorInfo::map(1, " "))
+                .build(),
+            Err(DataflowValidateError::InvalidOperatorConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_keyed_data_event_builder() {
+        let job_id = ResourceId::new("default", "job");
+        let key = Entry {
+            data_type: DataTypeEnum::String as i32,
+            value: bytes::Bytes::from_static(b"key"),
+        };
+        let payload = Entry {
+            data_type: DataTypeEnum::Bigint as i32,
+            value: bytes::Bytes::from_static(&[1]),
+        };
+        let event = KeyedDataEvent::builder()
+            .job_id(&job_id)
+            .key(key.clone())
+            .payload(payload.clone())
+            .payload(payload.clone())
+            .event_time(100)
+            .event_id(1)
+            .from_operator_id(2)
+            .to_operator_id(3)
+            .header("trace_id", "abc")
+            .build();
+        assert_eq!(
+            event,
+            KeyedDataEvent {
+                job_id: Some(job_id),
+                key: Some(key),
+                data: vec![payload.clone(), payload],
+                event_time: 100,
+                event_id: 1,
+                from_operator_id: 2,
+                to_operator_id: 3,
+                headers: [("trace_id".to_string(), "abc".to_string())].into(),
+                ..Default::default()
+            }
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_built_dataflow_is_valid(
+            kinds in prop::collection::vec(0u8..5, 1..8),
+            edges in prop::collection::vec((0u32..8, 0u32..8), 0..16),
+            broadcast in any::<bool>(),
+        ) {
+            let mut builder = Dataflow::builder().job_id("default", "job");
+            for (operator_id, kind) in kinds.iter().enumerate() {
+                let operator_id = operator_id as u32;
+                builder = builder.operator(match kind {
+                    0 => OperatorInfo::map(operator_id, "(v) => v"),
+                    1 => OperatorInfo::filter(operator_id, "(v) => true"),
+                    2 => OperatorInfo::flat_map(operator_id, "(v) => [v]"),
+                    3 => OperatorInfo::key_by(operator_id, "(v) => v"),
+                    _ => OperatorInfo::reduce(operator_id, "(a, b) => a + b"),
+                });
+            }
+            // edges between the operators always go forward
+            let edges = edges
+                .into_iter()
+                .map(|(from, to)| (from.min(to), from.max(to)))
+                .filter(|(from, to)| from < to && (*to as usize) < kinds.len())
+                .collect::<Vec<_>>();
+            for (index, (from, to)) in edges.iter().enumerate() {
+                builder = if broadcast && index == 0 {
+                    builder.broadcast_edge(*from, *to)
+                } else {
+                    builder.edge(*from, *to)
+                };
+            }
+
+            let dataflow = builder.build();
+            prop_assert!(dataflow.is_ok(), "{:?}", dataflow.err());
+            let dataflow = dataflow.unwrap();
+            prop_assert!(dataflow.validate().is_ok());
+            prop_assert_eq!(dataflow.meta.len(), kinds.len());
+            for (from, to) in edges {
+                prop_assert!(dataflow.nodes[&to].upstreams.contains(&from));
+            }
+        }
+    }
+}
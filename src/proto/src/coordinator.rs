@@ -3,8 +3,337 @@
 pub struct GetDataflowRequest {
     #[prost(message, optional, tag = "1")]
     pub job_id: ::core::option::Option<super::common::ResourceId>,
+    /// the partitions of the dataflow are returned as they're deployed if it's true, see `common.DataflowStates.partitions`
+    #[prost(bool, tag = "2")]
+    pub effective: bool,
+    /// the metrics of the operators are aggregated into `common.DataflowStates.metrics` if it's true
+    #[prost(bool, tag = "3")]
+    pub include_metrics: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetClusterTopologyRequest {}
+/// topology of a TaskManager node
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NodeTopology {
+    #[prost(uint32, tag = "1")]
+    pub node_id: u32,
+    #[prost(message, optional, tag = "2")]
+    pub host_addr: ::core::option::Option<super::common::HostAddr>,
+    #[prost(enumeration = "NodeHealth", tag = "3")]
+    pub health: i32,
+    /// the share of operators the node takes when a dataflow is partitioned
+    #[prost(uint32, tag = "4")]
+    pub weight: u32,
+    /// the number of started partitions hosted by the node
+    #[prost(uint32, tag = "5")]
+    pub partitions: u32,
+    /// per mille of the time the most blocked operator on the node is blocked by its downstreams in the latest sample
+    #[prost(uint32, tag = "6")]
+    pub backpressure: u32,
+    /// the node is under sustained backpressure, so new partitions are not placed on it unless all of the nodes are
+    #[prost(bool, tag = "7")]
+    pub backpressured: bool,
+}
+/// topology of the TaskManager cluster
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ClusterTopology {
+    #[prost(message, repeated, tag = "1")]
+    pub nodes: ::prost::alloc::vec::Vec<NodeTopology>,
+}
+/// a savepoint of a dataflow. A dataflow is restored from it by setting `restore_from` to its path on submission
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Savepoint {
+    /// path of the savepoint. It's the key of its manifest if it's uploaded to the snapshot store
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub job_id: ::core::option::Option<super::common::ResourceId>,
+    /// milliseconds since the unix epoch
+    #[prost(int64, tag = "3")]
+    pub created_at: i64,
+    /// operators whose states are kept in the savepoint
+    #[prost(uint32, repeated, tag = "4")]
+    pub operator_ids: ::prost::alloc::vec::Vec<u32>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListSavepointsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub savepoints: ::prost::alloc::vec::Vec<Savepoint>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteSavepointRequest {
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<super::common::ResourceId>,
+    #[prost(string, tag = "2")]
+    pub path: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListDataflowsRequest {
+    /// only the dataflows of the namespace are listed. Dataflows of all namespaces are listed if it's empty
+    #[prost(string, tag = "1")]
+    pub namespace: ::prost::alloc::string::String,
+    /// the max number of dataflows in a page. The default page size is used if it's zero
+    #[prost(uint32, tag = "2")]
+    pub page_size: u32,
+    /// the `next_page_token` of the previous page. The first page is listed if it's empty
+    #[prost(string, tag = "3")]
+    pub page_token: ::prost::alloc::string::String,
+    /// only the dataflows whose labels match the selector are listed, e.g. `team=payments,env!=dev`. The requirements are separated by commas
+    /// and all of them must be met: `key=value` requires the label, and `key!=value` requires the label to be absent or different. All are listed if it's empty
+    #[prost(string, tag = "4")]
+    pub label_selector: ::prost::alloc::string::String,
+}
+/// summary of a dataflow which is cheap to list, unlike `common.DataflowStates` which asks every TaskManager for the states
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DataflowSummary {
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<super::common::ResourceId>,
+    #[prost(enumeration = "super::common::DataflowStatus", tag = "2")]
+    pub status: i32,
+    /// the number of operators of the dataflow
+    #[prost(uint32, tag = "3")]
+    pub operator_count: u32,
+    /// milliseconds since the unix epoch
+    #[prost(int64, tag = "4")]
+    pub created_at: i64,
+    /// milliseconds since the unix epoch when the dataflow is deployed or its status is changed the last time
+    #[prost(int64, tag = "5")]
+    pub updated_at: i64,
+    /// labels of the dataflow
+    #[prost(map = "string, string", tag = "6")]
+    pub labels: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// opaque version of the spec of the dataflow, which changes every time the dataflow is created or updated
+    #[prost(uint64, tag = "7")]
+    pub resource_version: u64,
+    /// why the dataflow is in the status, e.g. the partitions which fail to start. It's empty if the status is expected
+    #[prost(string, tag = "8")]
+    pub status_reason: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListDataflowsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub dataflows: ::prost::alloc::vec::Vec<DataflowSummary>,
+    /// token of the next page. It's empty if it's the last page
+    #[prost(string, tag = "2")]
+    pub next_page_token: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TerminateDataflowsRequest {
+    /// duplicated job ids are terminated once
+    #[prost(message, repeated, tag = "1")]
+    pub job_ids: ::prost::alloc::vec::Vec<super::common::ResourceId>,
+    /// operators stop at once and drop the events buffered in them if it's true. Otherwise the dataflows are drained before they stop
+    #[prost(bool, tag = "2")]
+    pub force: bool,
+}
+/// failure of stopping the subdataflow on a TaskManager
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WorkerFailure {
+    #[prost(message, optional, tag = "1")]
+    pub node: ::core::option::Option<super::common::HostAddr>,
+    #[prost(message, optional, tag = "2")]
+    pub error: ::core::option::Option<super::common::ErrorDetail>,
+}
+/// result of terminating a dataflow in a batch
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TerminateDataflowResult {
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<super::common::ResourceId>,
+    /// status of the dataflow after it's terminated. It's only set if the termination succeeds
+    #[prost(enumeration = "super::common::DataflowStatus", tag = "2")]
+    pub status: i32,
+    /// details of the failure, e.g. the dataflow is not found or some of its subdataflows fail to stop
+    #[prost(message, optional, tag = "3")]
+    pub error: ::core::option::Option<super::common::ErrorDetail>,
+    /// the TaskManagers which fail to stop the subdataflows if the termination partially fails
+    #[prost(message, repeated, tag = "4")]
+    pub worker_failures: ::prost::alloc::vec::Vec<WorkerFailure>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TerminateDataflowsResponse {
+    /// results in the order of the distinct job ids of the request
+    #[prost(message, repeated, tag = "1")]
+    pub results: ::prost::alloc::vec::Vec<TerminateDataflowResult>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetDataflowStatusRequest {
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<super::common::ResourceId>,
+    /// the TaskManagers are only asked for the runtime status of the operators if it's true
+    #[prost(bool, tag = "2")]
+    pub with_operators: bool,
+}
+/// runtime status of an operator
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OperatorRuntimeStatus {
+    #[prost(uint32, tag = "1")]
+    pub operator_id: u32,
+    /// the TaskManager where the operator is placed
+    #[prost(message, optional, tag = "2")]
+    pub host_addr: ::core::option::Option<super::common::HostAddr>,
+    /// status of the executor of the operator. It's absent if the TaskManager hasn't reported it yet
+    #[prost(enumeration = "super::common::ExecutorStatus", optional, tag = "3")]
+    pub status: ::core::option::Option<i32>,
+    /// milliseconds since the unix epoch when the operator receives the latest heartbeat. It's zero if no heartbeat is received
+    #[prost(int64, tag = "4")]
+    pub last_heartbeat_at: i64,
+    /// the number of times the executor of the operator is created again
+    #[prost(uint32, tag = "5")]
+    pub restart_count: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DataflowRuntimeStatus {
+    #[prost(message, optional, tag = "1")]
+    pub summary: ::core::option::Option<DataflowSummary>,
+    /// operators ordered by their ids. It's empty unless `with_operators` is set
+    #[prost(message, repeated, tag = "2")]
+    pub operators: ::prost::alloc::vec::Vec<OperatorRuntimeStatus>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateDataflowRequest {
+    /// the new spec, whose job id is the one of the updated dataflow
+    #[prost(message, optional, tag = "1")]
+    pub dataflow: ::core::option::Option<super::common::Dataflow>,
+    /// the resource version of the running dataflow which the new spec is based on. The update is aborted if the version is changed
+    #[prost(uint64, tag = "2")]
+    pub resource_version: u64,
+    #[prost(enumeration = "UpdateStrategy", tag = "3")]
+    pub strategy: i32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateDataflowResponse {
+    /// summary of the updated dataflow with its new resource version
+    #[prost(message, optional, tag = "1")]
+    pub summary: ::core::option::Option<DataflowSummary>,
+    /// operators which are restarted by the update, ordered by their ids
+    #[prost(uint32, repeated, tag = "2")]
+    pub restarted_operator_ids: ::prost::alloc::vec::Vec<u32>,
+    /// where the operators of the updated dataflow are placed
+    #[prost(message, optional, tag = "3")]
+    pub placement: ::core::option::Option<super::common::DataflowPlacement>,
+    /// e.g. operators of the new spec which are not restored from the savepoint
+    #[prost(string, repeated, tag = "4")]
+    pub warnings: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AddNodeRequest {
+    /// the address of the TaskManager
+    #[prost(message, optional, tag = "1")]
+    pub host_addr: ::core::option::Option<super::common::HostAddr>,
+}
+/// the operators of a dataflow which are moved onto the added node
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DataflowMigration {
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<super::common::ResourceId>,
+    /// operators moved onto the added node, ordered by their ids
+    #[prost(uint32, repeated, tag = "2")]
+    pub operator_ids: ::prost::alloc::vec::Vec<u32>,
+    /// where the operators are placed after the migration
+    #[prost(message, optional, tag = "3")]
+    pub placement: ::core::option::Option<super::common::DataflowPlacement>,
+    /// why the migration fails, it's empty if the dataflow is migrated
+    #[prost(string, tag = "4")]
+    pub error: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AddNodeResponse {
+    /// topology of the added node
+    #[prost(message, optional, tag = "1")]
+    pub node: ::core::option::Option<NodeTopology>,
+    /// the dataflows which are migrated by the rebalance, ordered by their job ids. It's empty if the rebalance is disabled
+    #[prost(message, repeated, tag = "2")]
+    pub migrations: ::prost::alloc::vec::Vec<DataflowMigration>,
+}
+/// health of a TaskManager node
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum NodeHealth {
+    Pending = 0,
+    Running = 1,
+    Unreachable = 2,
+    /// the node misses heartbeats but it's not unreachable yet, so it's probed more frequently
+    Suspect = 3,
+}
+impl NodeHealth {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            NodeHealth::Pending => "NODE_HEALTH_PENDING",
+            NodeHealth::Running => "NODE_HEALTH_RUNNING",
+            NodeHealth::Unreachable => "NODE_HEALTH_UNREACHABLE",
+            NodeHealth::Suspect => "NODE_HEALTH_SUSPECT",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "NODE_HEALTH_PENDING" => Some(Self::Pending),
+            "NODE_HEALTH_RUNNING" => Some(Self::Running),
+            "NODE_HEALTH_UNREACHABLE" => Some(Self::Unreachable),
+            "NODE_HEALTH_SUSPECT" => Some(Self::Suspect),
+            _ => None,
+        }
+    }
+}
+/// how the operators of an updated dataflow are restarted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum UpdateStrategy {
+    /// only the partitions whose operators are changed are restarted, one by one. It's not supported yet
+    Rolling = 0,
+    /// the running dataflow is terminated after a savepoint is taken, then the new one is created from the savepoint
+    Recreate = 1,
+}
+impl UpdateStrategy {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            UpdateStrategy::Rolling => "UPDATE_STRATEGY_ROLLING",
+            UpdateStrategy::Recreate => "UPDATE_STRATEGY_RECREATE",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "UPDATE_STRATEGY_ROLLING" => Some(Self::Rolling),
+            "UPDATE_STRATEGY_RECREATE" => Some(Self::Recreate),
+            _ => None,
+        }
+    }
 }
 /// Generated client implementations.
+#[cfg(feature = "coordinator-client")]
 pub mod coordinator_api_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
     use tonic::codegen::*;
@@ -181,9 +510,210 @@ pub mod coordinator_api_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// / Receive error reports of operators from TaskWorker
+        pub async fn report_operator_error(
+            &mut self,
+            request: impl tonic::IntoRequest<super::super::common::OperatorError>,
+        ) -> Result<tonic::Response<super::super::common::Response>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/coordinator.CoordinatorApi/ReportOperatorError",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / Get the current topology of the TaskManager cluster
+        pub async fn get_cluster_topology(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetClusterTopologyRequest>,
+        ) -> Result<tonic::Response<super::ClusterTopology>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/coordinator.CoordinatorApi/GetClusterTopology",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / Take a savepoint of a running dataflow. Unlike checkpoints, savepoints are never deleted automatically
+        pub async fn trigger_savepoint(
+            &mut self,
+            request: impl tonic::IntoRequest<super::super::common::ResourceId>,
+        ) -> Result<tonic::Response<super::Savepoint>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/coordinator.CoordinatorApi/TriggerSavepoint",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / List the savepoints of a dataflow
+        pub async fn list_savepoints(
+            &mut self,
+            request: impl tonic::IntoRequest<super::super::common::ResourceId>,
+        ) -> Result<tonic::Response<super::ListSavepointsResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/coordinator.CoordinatorApi/ListSavepoints",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / Delete a savepoint of a dataflow
+        pub async fn delete_savepoint(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteSavepointRequest>,
+        ) -> Result<tonic::Response<super::super::common::Response>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/coordinator.CoordinatorApi/DeleteSavepoint",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / List the summaries of the dataflows managed by the coordinator page by page, ordered by their job ids
+        pub async fn list_dataflows(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListDataflowsRequest>,
+        ) -> Result<tonic::Response<super::ListDataflowsResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/coordinator.CoordinatorApi/ListDataflows",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / Terminate a batch of dataflows concurrently. The result of each dataflow is returned, and failures of some dataflows never fail the others
+        pub async fn terminate_dataflows(
+            &mut self,
+            request: impl tonic::IntoRequest<super::TerminateDataflowsRequest>,
+        ) -> Result<tonic::Response<super::TerminateDataflowsResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/coordinator.CoordinatorApi/TerminateDataflows",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / Get the summary of a dataflow, optionally with the runtime status of each operator reported by the TaskManagers
+        pub async fn get_dataflow_status(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetDataflowStatusRequest>,
+        ) -> Result<tonic::Response<super::DataflowRuntimeStatus>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/coordinator.CoordinatorApi/GetDataflowStatus",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / Replace the spec of a running dataflow if its resource version is still the given one. The operators are restored from a savepoint of the running dataflow
+        pub async fn update_dataflow(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateDataflowRequest>,
+        ) -> Result<tonic::Response<super::UpdateDataflowResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/coordinator.CoordinatorApi/UpdateDataflow",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / Add a TaskManager node to the cluster once it answers. The operators which it wins by consistent hashing are moved onto it if the rebalance is enabled
+        pub async fn add_node(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AddNodeRequest>,
+        ) -> Result<tonic::Response<super::AddNodeResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/coordinator.CoordinatorApi/AddNode",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
     }
 }
 /// Generated server implementations.
+#[cfg(feature = "coordinator-server")]
 pub mod coordinator_api_server {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
     use tonic::codegen::*;
@@ -222,17 +752,67 @@ pub mod coordinator_api_server {
             &self,
             request: tonic::Request<super::super::common::Heartbeat>,
         ) -> Result<tonic::Response<super::super::common::Response>, tonic::Status>;
-    }
-    /// / RPC Api for Coordinator
-    #[derive(Debug)]
-    pub struct CoordinatorApiServer<T: CoordinatorApi> {
-        inner: _Inner<T>,
-        accept_compression_encodings: EnabledCompressionEncodings,
-        send_compression_encodings: EnabledCompressionEncodings,
-    }
-    struct _Inner<T>(Arc<T>);
-    impl<T: CoordinatorApi> CoordinatorApiServer<T> {
-        pub fn new(inner: T) -> Self {
+        /// / Receive error reports of operators from TaskWorker
+        async fn report_operator_error(
+            &self,
+            request: tonic::Request<super::super::common::OperatorError>,
+        ) -> Result<tonic::Response<super::super::common::Response>, tonic::Status>;
+        /// / Get the current topology of the TaskManager cluster
+        async fn get_cluster_topology(
+            &self,
+            request: tonic::Request<super::GetClusterTopologyRequest>,
+        ) -> Result<tonic::Response<super::ClusterTopology>, tonic::Status>;
+        /// / Take a savepoint of a running dataflow. Unlike checkpoints, savepoints are never deleted automatically
+        async fn trigger_savepoint(
+            &self,
+            request: tonic::Request<super::super::common::ResourceId>,
+        ) -> Result<tonic::Response<super::Savepoint>, tonic::Status>;
+        /// / List the savepoints of a dataflow
+        async fn list_savepoints(
+            &self,
+            request: tonic::Request<super::super::common::ResourceId>,
+        ) -> Result<tonic::Response<super::ListSavepointsResponse>, tonic::Status>;
+        /// / Delete a savepoint of a dataflow
+        async fn delete_savepoint(
+            &self,
+            request: tonic::Request<super::DeleteSavepointRequest>,
+        ) -> Result<tonic::Response<super::super::common::Response>, tonic::Status>;
+        /// / List the summaries of the dataflows managed by the coordinator page by page, ordered by their job ids
+        async fn list_dataflows(
+            &self,
+            request: tonic::Request<super::ListDataflowsRequest>,
+        ) -> Result<tonic::Response<super::ListDataflowsResponse>, tonic::Status>;
+        /// / Terminate a batch of dataflows concurrently. The result of each dataflow is returned, and failures of some dataflows never fail the others
+        async fn terminate_dataflows(
+            &self,
+            request: tonic::Request<super::TerminateDataflowsRequest>,
+        ) -> Result<tonic::Response<super::TerminateDataflowsResponse>, tonic::Status>;
+        /// / Get the summary of a dataflow, optionally with the runtime status of each operator reported by the TaskManagers
+        async fn get_dataflow_status(
+            &self,
+            request: tonic::Request<super::GetDataflowStatusRequest>,
+        ) -> Result<tonic::Response<super::DataflowRuntimeStatus>, tonic::Status>;
+        /// / Replace the spec of a running dataflow if its resource version is still the given one. The operators are restored from a savepoint of the running dataflow
+        async fn update_dataflow(
+            &self,
+            request: tonic::Request<super::UpdateDataflowRequest>,
+        ) -> Result<tonic::Response<super::UpdateDataflowResponse>, tonic::Status>;
+        /// / Add a TaskManager node to the cluster once it answers. The operators which it wins by consistent hashing are moved onto it if the rebalance is enabled
+        async fn add_node(
+            &self,
+            request: tonic::Request<super::AddNodeRequest>,
+        ) -> Result<tonic::Response<super::AddNodeResponse>, tonic::Status>;
+    }
+    /// / RPC Api for Coordinator
+    #[derive(Debug)]
+    pub struct CoordinatorApiServer<T: CoordinatorApi> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: CoordinatorApi> CoordinatorApiServer<T> {
+        pub fn new(inner: T) -> Self {
             Self::from_arc(Arc::new(inner))
         }
         pub fn from_arc(inner: Arc<T>) -> Self {
@@ -481,6 +1061,404 @@ pub mod coordinator_api_server {
                     };
                     Box::pin(fut)
                 }
+                "/coordinator.CoordinatorApi/ReportOperatorError" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReportOperatorErrorSvc<T: CoordinatorApi>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorApi,
+                    > tonic::server::UnaryService<super::super::common::OperatorError>
+                    for ReportOperatorErrorSvc<T> {
+                        type Response = super::super::common::Response;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::super::common::OperatorError>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).report_operator_error(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ReportOperatorErrorSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/coordinator.CoordinatorApi/GetClusterTopology" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetClusterTopologySvc<T: CoordinatorApi>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorApi,
+                    > tonic::server::UnaryService<super::GetClusterTopologyRequest>
+                    for GetClusterTopologySvc<T> {
+                        type Response = super::ClusterTopology;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetClusterTopologyRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_cluster_topology(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetClusterTopologySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/coordinator.CoordinatorApi/TriggerSavepoint" => {
+                    #[allow(non_camel_case_types)]
+                    struct TriggerSavepointSvc<T: CoordinatorApi>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorApi,
+                    > tonic::server::UnaryService<super::super::common::ResourceId>
+                    for TriggerSavepointSvc<T> {
+                        type Response = super::Savepoint;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::super::common::ResourceId>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).trigger_savepoint(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = TriggerSavepointSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/coordinator.CoordinatorApi/ListSavepoints" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListSavepointsSvc<T: CoordinatorApi>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorApi,
+                    > tonic::server::UnaryService<super::super::common::ResourceId>
+                    for ListSavepointsSvc<T> {
+                        type Response = super::ListSavepointsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::super::common::ResourceId>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).list_savepoints(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListSavepointsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/coordinator.CoordinatorApi/DeleteSavepoint" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeleteSavepointSvc<T: CoordinatorApi>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorApi,
+                    > tonic::server::UnaryService<super::DeleteSavepointRequest>
+                    for DeleteSavepointSvc<T> {
+                        type Response = super::super::common::Response;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteSavepointRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).delete_savepoint(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = DeleteSavepointSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/coordinator.CoordinatorApi/ListDataflows" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListDataflowsSvc<T: CoordinatorApi>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorApi,
+                    > tonic::server::UnaryService<super::ListDataflowsRequest>
+                    for ListDataflowsSvc<T> {
+                        type Response = super::ListDataflowsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListDataflowsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).list_dataflows(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListDataflowsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/coordinator.CoordinatorApi/TerminateDataflows" => {
+                    #[allow(non_camel_case_types)]
+                    struct TerminateDataflowsSvc<T: CoordinatorApi>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorApi,
+                    > tonic::server::UnaryService<super::TerminateDataflowsRequest>
+                    for TerminateDataflowsSvc<T> {
+                        type Response = super::TerminateDataflowsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TerminateDataflowsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).terminate_dataflows(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = TerminateDataflowsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/coordinator.CoordinatorApi/GetDataflowStatus" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetDataflowStatusSvc<T: CoordinatorApi>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorApi,
+                    > tonic::server::UnaryService<super::GetDataflowStatusRequest>
+                    for GetDataflowStatusSvc<T> {
+                        type Response = super::DataflowRuntimeStatus;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetDataflowStatusRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_dataflow_status(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetDataflowStatusSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/coordinator.CoordinatorApi/UpdateDataflow" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpdateDataflowSvc<T: CoordinatorApi>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorApi,
+                    > tonic::server::UnaryService<super::UpdateDataflowRequest>
+                    for UpdateDataflowSvc<T> {
+                        type Response = super::UpdateDataflowResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateDataflowRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).update_dataflow(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = UpdateDataflowSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/coordinator.CoordinatorApi/AddNode" => {
+                    #[allow(non_camel_case_types)]
+                    struct AddNodeSvc<T: CoordinatorApi>(pub Arc<T>);
+                    impl<
+                        T: CoordinatorApi,
+                    > tonic::server::UnaryService<super::AddNodeRequest>
+                    for AddNodeSvc<T> {
+                        type Response = super::AddNodeResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AddNodeRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).add_node(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = AddNodeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
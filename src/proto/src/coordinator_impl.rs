@@ -1,37 +1,180 @@
+use std::collections::HashMap;
+#[cfg(feature = "coordinator-client")]
 use std::time::Duration;
 
+#[cfg(feature = "coordinator-client")]
 use tonic::codegen::StdError;
 
+#[cfg(feature = "coordinator-client")]
+pub use crate::common_impl::ConnectionError;
+#[cfg(feature = "coordinator-client")]
 use crate::coordinator::coordinator_api_client::CoordinatorApiClient;
+use crate::{common_impl::check_label, coordinator::ListDataflowsRequest};
 
 /// Extra implementation of [`CoordinatorApiClient`]
+#[cfg(feature = "coordinator-client")]
 impl CoordinatorApiClient<tonic::transport::Channel> {
-    /// Connect to remote coordinator lazily with connection timeout
-    pub fn with_connection_timeout<D>(dst: D, timeout: Duration) -> Self
+    /// Connect to remote coordinator lazily. Every (re)connect is bounded by `connect_timeout` and every request, including the time it waits for the connection, is bounded by `rpc_timeout`
+    pub fn with_connection_timeout<D>(
+        dst: D,
+        connect_timeout: Duration,
+        rpc_timeout: Duration,
+    ) -> Self
     where
         D: TryInto<tonic::transport::Endpoint>,
         D::Error: Into<StdError>,
     {
         let conn = tonic::transport::Endpoint::new(dst)
             .expect("parse endpoint failed")
-            .connect_timeout(timeout)
+            .connect_timeout(connect_timeout)
+            .timeout(rpc_timeout)
             .connect_lazy();
         Self::new(conn)
     }
 
-    /// Try to connect to remote coordinator with connection timeout
+    /// Try to connect to remote coordinator eagerly. The connect is bounded by `connect_timeout` and every request of the client is bounded by `rpc_timeout`
     pub async fn connect_with_timeout<D>(
         dst: D,
-        timeout: Duration,
-    ) -> Result<Self, tonic::transport::Error>
+        connect_timeout: Duration,
+        rpc_timeout: Duration,
+    ) -> Result<Self, ConnectionError>
     where
         D: TryInto<tonic::transport::Endpoint>,
         D::Error: Into<StdError>,
     {
-        let conn = tonic::transport::Endpoint::new(dst)?
-            .connect_timeout(timeout)
-            .connect()
-            .await;
-        conn.map(|channel| Self::new(channel))
+        let endpoint = tonic::transport::Endpoint::new(dst)
+            .map_err(ConnectionError::Transport)?
+            .connect_timeout(connect_timeout)
+            .timeout(rpc_timeout);
+        match tokio::time::timeout(connect_timeout, endpoint.connect()).await {
+            Ok(conn) => conn.map(Self::new).map_err(ConnectionError::Transport),
+            Err(_) => Err(ConnectionError::Timeout),
+        }
+    }
+}
+
+/// a requirement of a [`LabelSelector`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelRequirement {
+    /// the label is present with the value
+    Equals(String, String),
+    /// the label is absent or has another value
+    NotEquals(String, String),
+}
+
+impl LabelRequirement {
+    fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        match self {
+            Self::Equals(key, value) => labels.get(key) == Some(value),
+            Self::NotEquals(key, value) => labels.get(key) != Some(value),
+        }
+    }
+}
+
+/// [`LabelSelector`] selects dataflows by their labels. It's parsed from [`ListDataflowsRequest::label_selector`], e.g. `team=payments,env!=dev`,
+/// and a dataflow is selected if it meets all of the requirements. An empty selector selects all dataflows
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelSelector {
+    requirements: Vec<LabelRequirement>,
+}
+
+impl LabelSelector {
+    /// each requirement is `key=value`, `key==value` or `key!=value`, and the key and the value must be a valid label, see [`check_label`]
+    pub fn parse(selector: &str) -> Result<Self, String> {
+        if selector.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        selector
+            .split(',')
+            .map(|requirement| {
+                let requirement = requirement.trim();
+                let (key, value, equals) = match requirement.split_once("!=") {
+                    Some((key, value)) => (key, value, false),
+                    None => match requirement.split_once('=') {
+                        Some((key, value)) => (key, value.strip_prefix('=').unwrap_or(value), true),
+                        None => {
+                            return Err(format!(
+                                "requirement [{requirement}] is neither `key=value` nor `key!=value`"
+                            ))
+                        }
+                    },
+                };
+                let (key, value) = (key.trim().to_string(), value.trim().to_string());
+                check_label(&key, &value)?;
+                Ok(if equals {
+                    LabelRequirement::Equals(key, value)
+                } else {
+                    LabelRequirement::NotEquals(key, value)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|requirements| Self { requirements })
+    }
+
+    pub fn get_requirements(&self) -> &[LabelRequirement] {
+        &self.requirements
+    }
+
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.requirements
+            .iter()
+            .all(|requirement| requirement.matches(labels))
+    }
+}
+
+impl ListDataflowsRequest {
+    pub fn get_label_selector(&self) -> Result<LabelSelector, String> {
+        LabelSelector::parse(&self.label_selector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{LabelRequirement, LabelSelector};
+
+    #[test]
+    fn test_label_selector() {
+        let selector = LabelSelector::parse(" team=payments, env!=dev,tier==1 ").unwrap();
+        assert_eq!(
+            selector.get_requirements(),
+            &[
+                LabelRequirement::Equals("team".to_string(), "payments".to_string()),
+                LabelRequirement::NotEquals("env".to_string(), "dev".to_string()),
+                LabelRequirement::Equals("tier".to_string(), "1".to_string()),
+            ]
+        );
+
+        let labels = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        assert!(selector.matches(&labels(&[("team", "payments"), ("tier", "1")])));
+        assert!(selector.matches(&labels(&[
+            ("team", "payments"),
+            ("env", "prod"),
+            ("tier", "1")
+        ])));
+        assert!(!selector.matches(&labels(&[
+            ("team", "payments"),
+            ("env", "dev"),
+            ("tier", "1")
+        ])));
+        assert!(!selector.matches(&labels(&[("team", "search"), ("tier", "1")])));
+        assert!(!selector.matches(&labels(&[])));
+
+        // an empty selector selects all
+        assert!(LabelSelector::parse("").unwrap().matches(&labels(&[])));
+
+        for invalid in ["team", "team=payments,", "=payments", "team=pay ments", "-team=a"] {
+            assert!(
+                LabelSelector::parse(invalid).is_err(),
+                "{} is invalid",
+                invalid
+            );
+        }
     }
 }
@@ -0,0 +1,287 @@
+use std::fmt::{self, Display};
+
+/// The root symbol of a path expression
+const ROOT: char = '$';
+/// The symbol of the current value in an existence filter
+const CURRENT: char = '@';
+
+/// A segment of [`JsonPath`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    /// `.name` or `['name']`
+    Field(String),
+    /// `[0]`
+    Index(usize),
+    /// `?(@.name)`: the current value is kept only if the relative path exists and is not null
+    Exists(JsonPath),
+}
+
+/// [`JsonPath`] is a JSON-path-like expression over an event payload. It supports:
+/// - nested fields: `$.user.name`, `$['first name']`
+/// - array indices: `$.tags[0]`
+/// - existence filters: `$.user?(@.email).name`
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct JsonPath {
+    pub segments: Vec<PathSegment>,
+}
+
+impl JsonPath {
+    pub fn parse(expr: &str) -> Result<Self, JsonPathError> {
+        let mut parser = Parser {
+            chars: expr.chars().collect(),
+            pos: 0,
+        };
+        let path = parser.parse_path(ROOT)?;
+        match parser.peek() {
+            Some(c) => Err(parser.error(format!("unexpected character '{c}'"))),
+            None => Ok(path),
+        }
+    }
+
+    /// whether the path contains any existence filter
+    pub fn has_filter(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, PathSegment::Exists(_)))
+    }
+
+    fn fmt_with_root(&self, root: char, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{root}")?;
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Field(name) => {
+                    if is_identifier(name) {
+                        write!(f, ".{name}")?
+                    } else {
+                        write!(f, "['{}']", name.replace('\'', "\\'"))?
+                    }
+                }
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+                PathSegment::Exists(path) => {
+                    write!(f, "?(")?;
+                    path.fmt_with_root(CURRENT, f)?;
+                    write!(f, ")")?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_with_root(ROOT, f)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct JsonPathError {
+    /// char offset in the expression where the error occurs
+    pub position: usize,
+    pub message: String,
+}
+
+impl Display for JsonPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_identifier_char)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    fn error(&self, message: String) -> JsonPathError {
+        JsonPathError {
+            position: self.pos,
+            message,
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonPathError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!("expect '{expected}' but found '{c}'"))),
+            None => Err(self.error(format!("expect '{expected}' but found the end"))),
+        }
+    }
+
+    fn parse_path(&mut self, root: char) -> Result<JsonPath, JsonPathError> {
+        self.expect(root)?;
+        let mut segments = vec![];
+        while let Some(c) = self.peek() {
+            match c {
+                '.' => {
+                    self.bump();
+                    segments.push(PathSegment::Field(self.parse_identifier()?));
+                }
+                '[' => {
+                    self.bump();
+                    segments.push(self.parse_bracket()?);
+                }
+                '?' => {
+                    self.bump();
+                    self.expect('(')?;
+                    let path = self.parse_path(CURRENT)?;
+                    self.expect(')')?;
+                    segments.push(PathSegment::Exists(path));
+                }
+                ')' if root == CURRENT => break,
+                _ => return Err(self.error(format!("unexpected character '{c}'"))),
+            }
+        }
+
+        Ok(JsonPath { segments })
+    }
+
+    fn parse_identifier(&mut self) -> Result<String, JsonPathError> {
+        let mut name = String::new();
+        while let Some(c) = self.peek().filter(|c| is_identifier_char(*c)) {
+            name.push(c);
+            self.bump();
+        }
+        if name.is_empty() {
+            Err(self.error("empty field name".to_string()))
+        } else {
+            Ok(name)
+        }
+    }
+
+    fn parse_bracket(&mut self) -> Result<PathSegment, JsonPathError> {
+        let segment = match self.peek() {
+            Some(quote) if quote == '\'' || quote == '"' => {
+                self.bump();
+                PathSegment::Field(self.parse_quoted(quote)?)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut index = String::new();
+                while let Some(c) = self.peek().filter(|c| c.is_ascii_digit()) {
+                    index.push(c);
+                    self.bump();
+                }
+                index
+                    .parse::<usize>()
+                    .map(PathSegment::Index)
+                    .map_err(|err| self.error(format!("invalid array index {index}: {err}")))?
+            }
+            Some(c) => return Err(self.error(format!("unexpected character '{c}' in brackets"))),
+            None => return Err(self.error("unclosed brackets".to_string())),
+        };
+        self.expect(']')?;
+        Ok(segment)
+    }
+
+    fn parse_quoted(&mut self, quote: char) -> Result<String, JsonPathError> {
+        let mut name = String::new();
+        loop {
+            match self.bump() {
+                Some('\\') => match self.bump() {
+                    Some(c) => name.push(c),
+                    None => return Err(self.error("unclosed quoted field name".to_string())),
+                },
+                Some(c) if c == quote => return Ok(name),
+                Some(c) => name.push(c),
+                None => return Err(self.error("unclosed quoted field name".to_string())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonPath, PathSegment};
+
+    fn field(name: &str) -> PathSegment {
+        PathSegment::Field(name.to_string())
+    }
+
+    #[test]
+    fn test_parse_json_path() {
+        assert_eq!(JsonPath::parse("$").unwrap(), JsonPath::default());
+        assert_eq!(
+            JsonPath::parse("$.user.tags[10]").unwrap().segments,
+            vec![field("user"), field("tags"), PathSegment::Index(10)]
+        );
+        assert_eq!(
+            JsonPath::parse(r#"$['first name']["it's"]"#)
+                .unwrap()
+                .segments,
+            vec![field("first name"), field("it's")]
+        );
+
+        let path = JsonPath::parse("$.users[0]?(@.contact?(@.email)).name").unwrap();
+        assert!(path.has_filter());
+        assert_eq!(
+            path.segments,
+            vec![
+                field("users"),
+                PathSegment::Index(0),
+                PathSegment::Exists(JsonPath {
+                    segments: vec![
+                        field("contact"),
+                        PathSegment::Exists(JsonPath {
+                            segments: vec![field("email")]
+                        })
+                    ]
+                }),
+                field("name"),
+            ]
+        );
+        assert_eq!(
+            path.to_string(),
+            "$.users[0]?(@.contact?(@.email)).name".to_string()
+        );
+        assert_eq!(
+            JsonPath::parse(r#"$["it's"]"#).unwrap().to_string(),
+            r#"$['it\'s']"#.to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_json_path() {
+        for expr in [
+            "",
+            "user.name",
+            "$.",
+            "$..name",
+            "$.name)",
+            "$[",
+            "$[-1]",
+            "$['name]",
+            "$.user?(@.email",
+            "$.user?($.email)",
+            "$.user?@.email",
+            "$ .name",
+        ] {
+            assert!(JsonPath::parse(expr).is_err(), "{expr} should be invalid");
+        }
+
+        let err = JsonPath::parse("$.user.").unwrap_err();
+        assert_eq!(err.position, 7);
+    }
+}
@@ -2,19 +2,23 @@
 pub mod common;
 #[cfg(feature = "proto-common")]
 pub mod common_impl;
+#[cfg(feature = "proto-common")]
+pub mod json_path;
+#[cfg(feature = "proto-common")]
+pub mod sql_expr;
 
-#[cfg(feature = "coordinator")]
+#[cfg(any(feature = "coordinator-client", feature = "coordinator-server"))]
 pub mod coordinator;
 
-#[cfg(feature = "coordinator")]
+#[cfg(any(feature = "coordinator-client", feature = "coordinator-server"))]
 pub mod coordinator_impl;
 
-#[cfg(feature = "taskmanager")]
+#[cfg(any(feature = "worker-client", feature = "worker-server"))]
 pub mod taskmanager;
-#[cfg(feature = "taskmanager")]
+#[cfg(feature = "worker-client")]
 pub mod taskmanager_impl;
 
-#[cfg(feature = "apiserver")]
+#[cfg(feature = "apiserver-types")]
 pub mod apiserver;
-#[cfg(feature = "apiserver")]
+#[cfg(feature = "apiserver-types")]
 pub mod apiserver_impl;
@@ -0,0 +1,967 @@
+use std::fmt::{self, Display};
+
+/// A literal value of [`Expr`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `-`
+    Neg,
+    /// `NOT`
+    Not,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    /// `||`
+    Concat,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    fn get_symbol(&self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Mod => "%",
+            Self::Concat => "||",
+            Self::Eq => "=",
+            Self::NotEq => "<>",
+            Self::Lt => "<",
+            Self::LtEq => "<=",
+            Self::Gt => ">",
+            Self::GtEq => ">=",
+            Self::And => "AND",
+            Self::Or => "OR",
+        }
+    }
+}
+
+/// the builtin functions of [`Expr`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    /// `lower(s)`
+    Lower,
+    /// `upper(s)`
+    Upper,
+    /// `substr(s, start [, length])`, `start` is 1-based
+    Substr,
+    /// `length(s)`: number of characters
+    Length,
+    /// `trim(s)`
+    Trim,
+    /// `coalesce(a, b, ...)`: the first argument which is not null
+    Coalesce,
+}
+
+impl Function {
+    pub const ALL: [Function; 6] = [
+        Self::Lower,
+        Self::Upper,
+        Self::Substr,
+        Self::Length,
+        Self::Trim,
+        Self::Coalesce,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::Lower => "lower",
+            Self::Upper => "upper",
+            Self::Substr => "substr",
+            Self::Length => "length",
+            Self::Trim => "trim",
+            Self::Coalesce => "coalesce",
+        }
+    }
+
+    /// the min and max number of arguments
+    pub fn get_arity(&self) -> (usize, usize) {
+        match self {
+            Self::Lower | Self::Upper | Self::Length | Self::Trim => (1, 1),
+            Self::Substr => (2, 3),
+            Self::Coalesce => (1, usize::MAX),
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "substring" => Some(Self::Substr),
+            _ => Self::ALL.into_iter().find(|func| func.get_name() == name),
+        }
+    }
+}
+
+/// [`Expr`] is a SQL-like expression over the fields of an event payload, for example `amount > 100 AND country = 'SE'`.
+/// It's parsed once when the dataflow is submitted and compiled by the task which runs it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Literal(Literal),
+    /// `user.name` or `"first name"`. An absent field is null
+    Field(Vec<String>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `expr IS [NOT] NULL`
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+    Function(Function, Vec<Expr>),
+    /// `CASE [operand] WHEN condition THEN result ... [ELSE otherwise] END`
+    Case {
+        operand: Option<Box<Expr>>,
+        branches: Vec<(Expr, Expr)>,
+        otherwise: Option<Box<Expr>>,
+    },
+}
+
+impl Expr {
+    pub fn parse(sql: &str) -> Result<Self, SqlExprError> {
+        let mut parser = Parser::new(sql)?;
+        let expr = parser.parse_expr()?;
+        parser.expect_end()?;
+        Ok(expr)
+    }
+}
+
+/// Expressions are printed fully parenthesized, so the printed expression is parsed into the same one
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Literal(literal) => match literal {
+                Literal::Null => write!(f, "NULL"),
+                Literal::Boolean(true) => write!(f, "TRUE"),
+                Literal::Boolean(false) => write!(f, "FALSE"),
+                Literal::Integer(value) => write!(f, "{value}"),
+                Literal::Float(value) => write!(f, "{value:?}"),
+                Literal::String(value) => write!(f, "'{}'", value.replace('\'', "''")),
+            },
+            Expr::Field(path) => {
+                for (index, name) in path.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ".")?;
+                    }
+                    if is_identifier(name) {
+                        write!(f, "{name}")?;
+                    } else {
+                        write!(f, "\"{}\"", name.replace('"', "\"\""))?;
+                    }
+                }
+                Ok(())
+            }
+            Expr::Unary(UnaryOp::Neg, expr) => write!(f, "(-{expr})"),
+            Expr::Unary(UnaryOp::Not, expr) => write!(f, "(NOT {expr})"),
+            Expr::Binary(op, left, right) => write!(f, "({left} {} {right})", op.get_symbol()),
+            Expr::IsNull { expr, negated } => {
+                write!(f, "({expr} IS {}NULL)", if *negated { "NOT " } else { "" })
+            }
+            Expr::Function(func, args) => {
+                write!(f, "{}(", func.get_name())?;
+                for (index, arg) in args.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                write!(f, ")")
+            }
+            Expr::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                write!(f, "CASE")?;
+                if let Some(operand) = operand {
+                    write!(f, " {operand}")?;
+                }
+                for (condition, result) in branches {
+                    write!(f, " WHEN {condition} THEN {result}")?;
+                }
+                if let Some(otherwise) = otherwise {
+                    write!(f, " ELSE {otherwise}")?;
+                }
+                write!(f, " END")
+            }
+        }
+    }
+}
+
+/// an item of [`Select`], the value of the field `alias` in the new payload is the value of `expr`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectItem {
+    pub alias: String,
+    pub expr: Expr,
+}
+
+/// [`Select`] is a select list over the fields of an event payload, for example `SELECT amount * rate AS amount_eur, user_id`.
+/// The alias of a field reference defaults to its last field name, and other expressions must have an alias.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Select {
+    pub items: Vec<SelectItem>,
+}
+
+impl Select {
+    pub fn parse(sql: &str) -> Result<Self, SqlExprError> {
+        let mut parser = Parser::new(sql)?;
+        parser.expect_keyword(Keyword::Select)?;
+        let mut items: Vec<SelectItem> = vec![];
+        loop {
+            let position = parser.peek_position();
+            let expr = parser.parse_expr()?;
+            let alias = if parser.eat_keyword(Keyword::As) {
+                parser.parse_name()?
+            } else {
+                match &expr {
+                    Expr::Field(path) => path.last().cloned().unwrap_or_default(),
+                    _ => {
+                        return Err(SqlExprError {
+                            position,
+                            message: "expression must have an alias".to_string(),
+                        })
+                    }
+                }
+            };
+            if items.iter().any(|item| item.alias == alias) {
+                return Err(SqlExprError {
+                    position,
+                    message: format!("duplicated alias [{alias}]"),
+                });
+            }
+            items.push(SelectItem { alias, expr });
+            if !parser.eat(&Token::Comma) {
+                break;
+            }
+        }
+        parser.expect_end()?;
+        Ok(Self { items })
+    }
+}
+
+impl Display for Select {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SELECT")?;
+        for (index, item) in self.items.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, " {} AS ", item.expr)?;
+            Expr::Field(vec![item.alias.clone()]).fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct SqlExprError {
+    /// char offset in the expression where the error occurs
+    pub position: usize,
+    pub message: String,
+}
+
+impl Display for SqlExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Keyword {
+    And,
+    Or,
+    Not,
+    Is,
+    Null,
+    True,
+    False,
+    Case,
+    When,
+    Then,
+    Else,
+    End,
+    Select,
+    As,
+}
+
+impl Keyword {
+    const ALL: [Keyword; 14] = [
+        Self::And,
+        Self::Or,
+        Self::Not,
+        Self::Is,
+        Self::Null,
+        Self::True,
+        Self::False,
+        Self::Case,
+        Self::When,
+        Self::Then,
+        Self::Else,
+        Self::End,
+        Self::Select,
+        Self::As,
+    ];
+
+    fn get_name(&self) -> &'static str {
+        match self {
+            Self::And => "AND",
+            Self::Or => "OR",
+            Self::Not => "NOT",
+            Self::Is => "IS",
+            Self::Null => "NULL",
+            Self::True => "TRUE",
+            Self::False => "FALSE",
+            Self::Case => "CASE",
+            Self::When => "WHEN",
+            Self::Then => "THEN",
+            Self::Else => "ELSE",
+            Self::End => "END",
+            Self::Select => "SELECT",
+            Self::As => "AS",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|keyword| keyword.get_name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// whether the name can be written without quotes
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && Keyword::from_name(name).is_none()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Keyword(Keyword),
+    /// unquoted or double-quoted name
+    Name(String),
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Op(BinaryOp),
+    Comma,
+    Dot,
+    LeftParen,
+    RightParen,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Keyword(keyword) => write!(f, "{}", keyword.get_name()),
+            Token::Name(name) => write!(f, "{name}"),
+            Token::String(value) => write!(f, "'{value}'"),
+            Token::Integer(value) => write!(f, "{value}"),
+            Token::Float(value) => write!(f, "{value:?}"),
+            Token::Op(op) => write!(f, "{}", op.get_symbol()),
+            Token::Comma => write!(f, ","),
+            Token::Dot => write!(f, "."),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(sql: &str) -> Result<Vec<(usize, Token)>, SqlExprError> {
+    let chars = sql.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut pos = 0;
+    let error = |position: usize, message: String| SqlExprError { position, message };
+
+    while pos < chars.len() {
+        let start = pos;
+        let c = chars[pos];
+        if c.is_whitespace() {
+            pos += 1;
+            continue;
+        }
+        let token = match c {
+            ',' => Token::Comma,
+            '.' if !chars.get(pos + 1).iter().any(|c| c.is_ascii_digit()) => Token::Dot,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '+' => Token::Op(BinaryOp::Add),
+            '-' => Token::Op(BinaryOp::Sub),
+            '*' => Token::Op(BinaryOp::Mul),
+            '/' => Token::Op(BinaryOp::Div),
+            '%' => Token::Op(BinaryOp::Mod),
+            '=' => Token::Op(BinaryOp::Eq),
+            '|' if chars.get(pos + 1) == Some(&'|') => {
+                pos += 1;
+                Token::Op(BinaryOp::Concat)
+            }
+            '!' if chars.get(pos + 1) == Some(&'=') => {
+                pos += 1;
+                Token::Op(BinaryOp::NotEq)
+            }
+            '<' => match chars.get(pos + 1) {
+                Some('=') => {
+                    pos += 1;
+                    Token::Op(BinaryOp::LtEq)
+                }
+                Some('>') => {
+                    pos += 1;
+                    Token::Op(BinaryOp::NotEq)
+                }
+                _ => Token::Op(BinaryOp::Lt),
+            },
+            '>' => match chars.get(pos + 1) {
+                Some('=') => {
+                    pos += 1;
+                    Token::Op(BinaryOp::GtEq)
+                }
+                _ => Token::Op(BinaryOp::Gt),
+            },
+            '\'' | '"' => {
+                // quotes are escaped by doubling them
+                let mut value = String::new();
+                loop {
+                    pos += 1;
+                    match chars.get(pos) {
+                        Some(q) if *q == c && chars.get(pos + 1) == Some(&c) => {
+                            value.push(c);
+                            pos += 1;
+                        }
+                        Some(q) if *q == c => break,
+                        Some(other) => value.push(*other),
+                        None => {
+                            return Err(error(start, "unclosed quotes".to_string()));
+                        }
+                    }
+                }
+                if c == '\'' {
+                    Token::String(value)
+                } else if value.is_empty() {
+                    return Err(error(start, "empty field name".to_string()));
+                } else {
+                    Token::Name(value)
+                }
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                while chars.get(pos + 1).iter().any(|c| c.is_ascii_digit()) {
+                    pos += 1;
+                }
+                let mut is_float = false;
+                if chars.get(pos + 1) == Some(&'.') {
+                    is_float = true;
+                    pos += 1;
+                    while chars.get(pos + 1).iter().any(|c| c.is_ascii_digit()) {
+                        pos += 1;
+                    }
+                }
+                if chars.get(pos + 1).iter().any(|c| **c == 'e' || **c == 'E') {
+                    is_float = true;
+                    pos += 1;
+                    if chars.get(pos + 1).iter().any(|c| **c == '+' || **c == '-') {
+                        pos += 1;
+                    }
+                    if !chars.get(pos + 1).iter().any(|c| c.is_ascii_digit()) {
+                        return Err(error(start, "invalid exponent".to_string()));
+                    }
+                    while chars.get(pos + 1).iter().any(|c| c.is_ascii_digit()) {
+                        pos += 1;
+                    }
+                }
+                let text = chars[start..=pos].iter().collect::<String>();
+                if is_float || text.starts_with('.') {
+                    match text.parse::<f64>() {
+                        Ok(value) if value.is_finite() => Token::Float(value),
+                        Ok(_) => {
+                            return Err(error(start, format!("number {text} is out of range")))
+                        }
+                        Err(err) => {
+                            return Err(error(start, format!("invalid number {text}: {err}")))
+                        }
+                    }
+                } else {
+                    text.parse::<i64>()
+                        .map(Token::Integer)
+                        .map_err(|err| error(start, format!("invalid integer {text}: {err}")))?
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while chars
+                    .get(pos + 1)
+                    .iter()
+                    .any(|c| c.is_alphanumeric() || **c == '_')
+                {
+                    pos += 1;
+                }
+                let name = chars[start..=pos].iter().collect::<String>();
+                match Keyword::from_name(&name) {
+                    Some(keyword) => Token::Keyword(keyword),
+                    None => Token::Name(name),
+                }
+            }
+            _ => return Err(error(start, format!("unexpected character '{c}'"))),
+        };
+        tokens.push((start, token));
+        pos += 1;
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser. The precedence from low to high is
+/// `OR`, `AND`, `NOT`, comparison and `IS NULL`, `+ - ||`, `* / %`, unary `-`
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// char length of the expression
+    len: usize,
+}
+
+impl Parser {
+    fn new(sql: &str) -> Result<Self, SqlExprError> {
+        Ok(Self {
+            tokens: tokenize(sql)?,
+            pos: 0,
+            len: sql.chars().count(),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn peek_position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|(position, _)| *position)
+            .unwrap_or(self.len)
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, token)| token.clone());
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error(&self, message: String) -> SqlExprError {
+        SqlExprError {
+            position: self.peek_position(),
+            message,
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> SqlExprError {
+        match self.peek() {
+            Some(token) => self.error(format!("expect {expected} but found '{token}'")),
+            None => self.error(format!("expect {expected} but found the end")),
+        }
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: Keyword) -> bool {
+        self.eat(&Token::Keyword(keyword))
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), SqlExprError> {
+        if self.eat(&token) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{token}'")))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: Keyword) -> Result<(), SqlExprError> {
+        self.expect(Token::Keyword(keyword))
+    }
+
+    fn expect_end(&self) -> Result<(), SqlExprError> {
+        match self.peek() {
+            Some(token) => Err(self.error(format!("unexpected '{token}'"))),
+            None => Ok(()),
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, SqlExprError> {
+        match self.peek() {
+            Some(Token::Name(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, SqlExprError> {
+        let mut left = self.parse_and()?;
+        while self.eat_keyword(Keyword::Or) {
+            let right = self.parse_and()?;
+            left = Expr::Binary(BinaryOp::Or, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, SqlExprError> {
+        let mut left = self.parse_not()?;
+        while self.eat_keyword(Keyword::And) {
+            let right = self.parse_not()?;
+            left = Expr::Binary(BinaryOp::And, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, SqlExprError> {
+        if self.eat_keyword(Keyword::Not) {
+            Ok(Expr::Unary(UnaryOp::Not, Box::new(self.parse_not()?)))
+        } else {
+            self.parse_comparison()
+        }
+    }
+
+    /// comparisons are not associative, `a < b < c` is a syntax error
+    fn parse_comparison(&mut self) -> Result<Expr, SqlExprError> {
+        let left = self.parse_additive()?;
+        match self.peek() {
+            Some(Token::Op(
+                op @ (BinaryOp::Eq
+                | BinaryOp::NotEq
+                | BinaryOp::Lt
+                | BinaryOp::LtEq
+                | BinaryOp::Gt
+                | BinaryOp::GtEq),
+            )) => {
+                let op = *op;
+                self.pos += 1;
+                let right = self.parse_additive()?;
+                Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+            }
+            Some(Token::Keyword(Keyword::Is)) => {
+                self.pos += 1;
+                let negated = self.eat_keyword(Keyword::Not);
+                self.expect_keyword(Keyword::Null)?;
+                Ok(Expr::IsNull {
+                    expr: Box::new(left),
+                    negated,
+                })
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, SqlExprError> {
+        let mut left = self.parse_multiplicative()?;
+        while let Some(Token::Op(op @ (BinaryOp::Add | BinaryOp::Sub | BinaryOp::Concat))) =
+            self.peek()
+        {
+            let op = *op;
+            self.pos += 1;
+            let right = self.parse_multiplicative()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, SqlExprError> {
+        let mut left = self.parse_unary()?;
+        while let Some(Token::Op(op @ (BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod))) =
+            self.peek()
+        {
+            let op = *op;
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, SqlExprError> {
+        if self.eat(&Token::Op(BinaryOp::Sub)) {
+            Ok(Expr::Unary(UnaryOp::Neg, Box::new(self.parse_unary()?)))
+        } else {
+            self.parse_primary()
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, SqlExprError> {
+        let position = self.peek_position();
+        let token = match self.bump() {
+            Some(token) => token,
+            None => return Err(self.unexpected("an expression")),
+        };
+        match token {
+            Token::Keyword(Keyword::Null) => Ok(Expr::Literal(Literal::Null)),
+            Token::Keyword(Keyword::True) => Ok(Expr::Literal(Literal::Boolean(true))),
+            Token::Keyword(Keyword::False) => Ok(Expr::Literal(Literal::Boolean(false))),
+            Token::Keyword(Keyword::Case) => self.parse_case(),
+            Token::Integer(value) => Ok(Expr::Literal(Literal::Integer(value))),
+            Token::Float(value) => Ok(Expr::Literal(Literal::Float(value))),
+            Token::String(value) => Ok(Expr::Literal(Literal::String(value))),
+            Token::LeftParen => {
+                let expr = self.parse_expr()?;
+                self.expect(Token::RightParen)?;
+                Ok(expr)
+            }
+            Token::Name(name) if self.peek() == Some(&Token::LeftParen) => {
+                self.pos += 1;
+                let func = Function::from_name(&name).ok_or_else(|| SqlExprError {
+                    position,
+                    message: format!("unknown function {name}"),
+                })?;
+                let mut args = vec![];
+                if !self.eat(&Token::RightParen) {
+                    loop {
+                        args.push(self.parse_expr()?);
+                        if !self.eat(&Token::Comma) {
+                            break;
+                        }
+                    }
+                    self.expect(Token::RightParen)?;
+                }
+                let (min, max) = func.get_arity();
+                if args.len() < min || args.len() > max {
+                    return Err(SqlExprError {
+                        position,
+                        message: format!(
+                            "wrong number of arguments of {}: {}",
+                            func.get_name(),
+                            args.len()
+                        ),
+                    });
+                }
+                Ok(Expr::Function(func, args))
+            }
+            Token::Name(name) => {
+                let mut path = vec![name];
+                while self.eat(&Token::Dot) {
+                    path.push(self.parse_name()?);
+                }
+                Ok(Expr::Field(path))
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("an expression"))
+            }
+        }
+    }
+
+    fn parse_case(&mut self) -> Result<Expr, SqlExprError> {
+        let operand = if self.peek() == Some(&Token::Keyword(Keyword::When)) {
+            None
+        } else {
+            Some(Box::new(self.parse_expr()?))
+        };
+        let mut branches = vec![];
+        while self.eat_keyword(Keyword::When) {
+            let condition = self.parse_expr()?;
+            self.expect_keyword(Keyword::Then)?;
+            branches.push((condition, self.parse_expr()?));
+        }
+        if branches.is_empty() {
+            return Err(self.unexpected("'WHEN'"));
+        }
+        let otherwise = if self.eat_keyword(Keyword::Else) {
+            Some(Box::new(self.parse_expr()?))
+        } else {
+            None
+        };
+        self.expect_keyword(Keyword::End)?;
+        Ok(Expr::Case {
+            operand,
+            branches,
+            otherwise,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BinaryOp, Expr, Function, Literal, Select, SelectItem, UnaryOp};
+
+    fn field(name: &str) -> Expr {
+        Expr::Field(vec![name.to_string()])
+    }
+
+    fn binary(op: BinaryOp, left: Expr, right: Expr) -> Expr {
+        Expr::Binary(op, Box::new(left), Box::new(right))
+    }
+
+    fn string(value: &str) -> Expr {
+        Expr::Literal(Literal::String(value.to_string()))
+    }
+
+    #[test]
+    fn test_parse_sql_expr() {
+        assert_eq!(
+            Expr::parse("amount > 100 AND country = 'SE'").unwrap(),
+            binary(
+                BinaryOp::And,
+                binary(
+                    BinaryOp::Gt,
+                    field("amount"),
+                    Expr::Literal(Literal::Integer(100))
+                ),
+                binary(BinaryOp::Eq, field("country"), string("SE"))
+            )
+        );
+        // precedence and associativity
+        assert_eq!(
+            Expr::parse("NOT a OR b AND -c * 2 + d - 1.5 >= 0")
+                .unwrap()
+                .to_string(),
+            "((NOT a) OR (b AND (((((-c) * 2) + d) - 1.5) >= 0)))"
+        );
+        assert_eq!(
+            Expr::parse(r#"lower(user.name) || 'x' <> "first name" and "it""s" is not null"#)
+                .unwrap()
+                .to_string(),
+            r#"(((lower(user.name) || 'x') <> "first name") AND ("it""s" IS NOT NULL))"#
+        );
+        assert_eq!(
+            Expr::parse("CASE WHEN a IS NULL THEN 'it''s' ELSE substring(b, 1, 2) END").unwrap(),
+            Expr::Case {
+                operand: None,
+                branches: vec![(
+                    Expr::IsNull {
+                        expr: Box::new(field("a")),
+                        negated: false
+                    },
+                    string("it's")
+                )],
+                otherwise: Some(Box::new(Expr::Function(
+                    Function::Substr,
+                    vec![
+                        field("b"),
+                        Expr::Literal(Literal::Integer(1)),
+                        Expr::Literal(Literal::Integer(2))
+                    ]
+                )))
+            }
+        );
+        assert_eq!(
+            Expr::parse("case -x when 1 then true end").unwrap(),
+            Expr::Case {
+                operand: Some(Box::new(Expr::Unary(UnaryOp::Neg, Box::new(field("x"))))),
+                branches: vec![(
+                    Expr::Literal(Literal::Integer(1)),
+                    Expr::Literal(Literal::Boolean(true))
+                )],
+                otherwise: None
+            }
+        );
+        assert_eq!(
+            Expr::parse(".5 + 1e3 - 2.5E-1").unwrap().to_string(),
+            "((0.5 + 1000.0) - 0.25)"
+        );
+
+        for sql in [
+            "(a + 1) * 2 = b OR c != 'x'",
+            "coalesce(a, b, NULL) || upper(trim(c))",
+            "CASE a WHEN 1 THEN 'one' WHEN 2 THEN 'two' ELSE length(b) END",
+        ] {
+            let expr = Expr::parse(sql).unwrap();
+            assert_eq!(Expr::parse(&expr.to_string()).unwrap(), expr);
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_sql_expr() {
+        for sql in [
+            "",
+            "a >",
+            "a > 1 b",
+            "(a",
+            "a < b < c",
+            "a IS 1",
+            "'abc",
+            "\"\" = 1",
+            "a.",
+            "a.1",
+            "unknown(a)",
+            "lower(a, b)",
+            "substr(a)",
+            "coalesce()",
+            "CASE END",
+            "CASE WHEN a THEN b",
+            "a = 99999999999999999999",
+            "1e",
+            "a # b",
+            "select",
+        ] {
+            assert!(Expr::parse(sql).is_err(), "{sql} should be invalid");
+        }
+
+        let err = Expr::parse("a > 1 AND").unwrap_err();
+        assert_eq!(err.position, 9);
+        let err = Expr::parse("a = 'x' $").unwrap_err();
+        assert_eq!(err.position, 8);
+    }
+
+    #[test]
+    fn test_parse_select() {
+        let select =
+            Select::parse("SELECT amount * rate AS amount_eur, user.id, 'x' AS \"a b\"").unwrap();
+        assert_eq!(
+            select.items,
+            vec![
+                SelectItem {
+                    alias: "amount_eur".to_string(),
+                    expr: binary(BinaryOp::Mul, field("amount"), field("rate"))
+                },
+                SelectItem {
+                    alias: "id".to_string(),
+                    expr: Expr::Field(vec!["user".to_string(), "id".to_string()])
+                },
+                SelectItem {
+                    alias: "a b".to_string(),
+                    expr: string("x")
+                },
+            ]
+        );
+        assert_eq!(
+            select.to_string(),
+            r#"SELECT (amount * rate) AS amount_eur, user.id AS id, 'x' AS "a b""#
+        );
+        assert_eq!(Select::parse(&select.to_string()).unwrap(), select);
+
+        for sql in [
+            "amount",
+            "SELECT",
+            "SELECT amount + 1",
+            "SELECT a, b AS a",
+            "SELECT a AS",
+            "SELECT a,",
+            "SELECT a FROM b",
+        ] {
+            assert!(Select::parse(sql).is_err(), "{sql} should be invalid");
+        }
+    }
+}
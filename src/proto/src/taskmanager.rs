@@ -3,10 +3,27 @@
 pub struct SendEventToOperatorResponse {
     #[prost(enumeration = "SendEventToOperatorStatusEnum", tag = "1")]
     pub status: i32,
+    /// backpressure hint of the receiver. If it's not zero, the event is not accepted
+    /// and the sender should retry after the delay in milliseconds
+    #[prost(uint64, tag = "2")]
+    pub retry_after_ms: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct BatchSendEventsToOperatorResponse {}
+pub struct BatchSendEventsToOperatorResponse {
+    /// backpressure hint of the receiver. If it's not zero, the events are not accepted
+    /// and the sender should retry after the delay in milliseconds
+    #[prost(uint64, tag = "1")]
+    pub retry_after_ms: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StopDataflowRequest {
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<super::common::ResourceId>,
+    #[prost(enumeration = "StopMode", tag = "2")]
+    pub mode: i32,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct StopDataflowResponse {
@@ -20,6 +37,50 @@ pub struct CreateSubDataflowRequest {
     pub job_id: ::core::option::Option<super::common::ResourceId>,
     #[prost(message, optional, tag = "2")]
     pub dataflow: ::core::option::Option<super::common::Dataflow>,
+    /// address of the coordinator that operator errors will be reported to
+    #[prost(message, optional, tag = "3")]
+    pub coordinator: ::core::option::Option<super::common::HostAddr>,
+    /// key of the manifest of the remote checkpoint which the states of the subdataflow are restored from.
+    /// The states are not restored if it's empty
+    #[prost(string, tag = "4")]
+    pub restore_from: ::prost::alloc::string::String,
+    /// states of the operators restored from a savepoint. `restore_from` is ignored if they're set
+    #[prost(message, optional, tag = "5")]
+    pub savepoint: ::core::option::Option<super::common::OperatorStates>,
+    /// keys of the manifests of the latest checkpoints of the prior job which the compatible operators are warm-started from.
+    /// They're ignored if `restore_from` or `savepoint` is set
+    #[prost(string, repeated, tag = "6")]
+    pub warm_start_from: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OperatorRequest {
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<super::common::ResourceId>,
+    #[prost(uint32, tag = "2")]
+    pub operator_id: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TapOperatorRequest {
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<super::common::ResourceId>,
+    #[prost(uint32, tag = "2")]
+    pub operator_id: u32,
+    /// the max number of sampled events per second
+    #[prost(double, tag = "3")]
+    pub sample_rate: f64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateSinkConfigRequest {
+    #[prost(message, optional, tag = "1")]
+    pub job_id: ::core::option::Option<super::common::ResourceId>,
+    #[prost(uint32, tag = "2")]
+    pub operator_id: u32,
+    /// the new configuration of the sink, its delivery guarantee and batching are applied as well
+    #[prost(message, optional, tag = "3")]
+    pub sink: ::core::option::Option<super::common::Sink>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -56,7 +117,36 @@ impl SendEventToOperatorStatusEnum {
         }
     }
 }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum StopMode {
+    /// operators stop at once, the events buffered in them are dropped
+    Immediate = 0,
+    /// operators stop after the events buffered in them are processed and their sinks are flushed
+    Drain = 1,
+}
+impl StopMode {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            StopMode::Immediate => "STOP_MODE_IMMEDIATE",
+            StopMode::Drain => "STOP_MODE_DRAIN",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "STOP_MODE_IMMEDIATE" => Some(Self::Immediate),
+            "STOP_MODE_DRAIN" => Some(Self::Drain),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
+#[cfg(feature = "worker-client")]
 pub mod task_manager_api_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
     use tonic::codegen::*;
@@ -149,7 +239,7 @@ pub mod task_manager_api_client {
         /// / Attempt to terminate a sub-dataflow
         pub async fn stop_dataflow(
             &mut self,
-            request: impl tonic::IntoRequest<super::super::common::ResourceId>,
+            request: impl tonic::IntoRequest<super::StopDataflowRequest>,
         ) -> Result<tonic::Response<super::StopDataflowResponse>, tonic::Status> {
             self.inner
                 .ready()
@@ -272,9 +362,119 @@ pub mod task_manager_api_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// / Drain an operator: pause its input, flush its buffers and sinks. It returns once the operator is drained while the rest of the sub-dataflow keeps running
+        pub async fn drain_operator(
+            &mut self,
+            request: impl tonic::IntoRequest<super::OperatorRequest>,
+        ) -> Result<tonic::Response<super::super::common::Response>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/taskmanager.TaskManagerApi/DrainOperator",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / Resume a drained operator
+        pub async fn resume_operator(
+            &mut self,
+            request: impl tonic::IntoRequest<super::OperatorRequest>,
+        ) -> Result<tonic::Response<super::super::common::Response>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/taskmanager.TaskManagerApi/ResumeOperator",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / Stream a sampled copy of the events passing through an operator. Sampled events are dropped if the caller can't keep up
+        pub async fn tap_operator(
+            &mut self,
+            request: impl tonic::IntoRequest<super::TapOperatorRequest>,
+        ) -> Result<
+            tonic::Response<
+                tonic::codec::Streaming<super::super::common::KeyedDataEvent>,
+            >,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/taskmanager.TaskManagerApi/TapOperator",
+            );
+            self.inner.server_streaming(request.into_request(), path, codec).await
+        }
+        /// / Snapshot the states of all operators of a sub-dataflow for a savepoint. Each operator is snapshotted between two events while the sub-dataflow keeps running
+        pub async fn trigger_savepoint(
+            &mut self,
+            request: impl tonic::IntoRequest<super::super::common::ResourceId>,
+        ) -> Result<
+            tonic::Response<super::super::common::OperatorStates>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/taskmanager.TaskManagerApi/TriggerSavepoint",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// / Apply a new configuration to a sink operator: the sink is drained and replaced by a new one while the rest of the sub-dataflow keeps running.
+        /// / The old sink keeps running if the new configuration is invalid
+        pub async fn update_sink_config(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateSinkConfigRequest>,
+        ) -> Result<tonic::Response<super::super::common::Response>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/taskmanager.TaskManagerApi/UpdateSinkConfig",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
     }
 }
 /// Generated server implementations.
+#[cfg(feature = "worker-server")]
 pub mod task_manager_api_server {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
     use tonic::codegen::*;
@@ -289,7 +489,7 @@ pub mod task_manager_api_server {
         /// / Attempt to terminate a sub-dataflow
         async fn stop_dataflow(
             &self,
-            request: tonic::Request<super::super::common::ResourceId>,
+            request: tonic::Request<super::StopDataflowRequest>,
         ) -> Result<tonic::Response<super::StopDataflowResponse>, tonic::Status>;
         /// / Attempt to create a sub-dataflow
         async fn create_sub_dataflow(
@@ -322,6 +522,41 @@ pub mod task_manager_api_server {
             tonic::Response<super::super::common::SubDataflowStates>,
             tonic::Status,
         >;
+        /// / Drain an operator: pause its input, flush its buffers and sinks. It returns once the operator is drained while the rest of the sub-dataflow keeps running
+        async fn drain_operator(
+            &self,
+            request: tonic::Request<super::OperatorRequest>,
+        ) -> Result<tonic::Response<super::super::common::Response>, tonic::Status>;
+        /// / Resume a drained operator
+        async fn resume_operator(
+            &self,
+            request: tonic::Request<super::OperatorRequest>,
+        ) -> Result<tonic::Response<super::super::common::Response>, tonic::Status>;
+        /// Server streaming response type for the TapOperator method.
+        type TapOperatorStream: futures_core::Stream<
+                Item = Result<super::super::common::KeyedDataEvent, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// / Stream a sampled copy of the events passing through an operator. Sampled events are dropped if the caller can't keep up
+        async fn tap_operator(
+            &self,
+            request: tonic::Request<super::TapOperatorRequest>,
+        ) -> Result<tonic::Response<Self::TapOperatorStream>, tonic::Status>;
+        /// / Snapshot the states of all operators of a sub-dataflow for a savepoint. Each operator is snapshotted between two events while the sub-dataflow keeps running
+        async fn trigger_savepoint(
+            &self,
+            request: tonic::Request<super::super::common::ResourceId>,
+        ) -> Result<
+            tonic::Response<super::super::common::OperatorStates>,
+            tonic::Status,
+        >;
+        /// / Apply a new configuration to a sink operator: the sink is drained and replaced by a new one while the rest of the sub-dataflow keeps running.
+        /// / The old sink keeps running if the new configuration is invalid
+        async fn update_sink_config(
+            &self,
+            request: tonic::Request<super::UpdateSinkConfigRequest>,
+        ) -> Result<tonic::Response<super::super::common::Response>, tonic::Status>;
     }
     /// / RPC Api for Task Manager
     #[derive(Debug)]
@@ -428,7 +663,7 @@ pub mod task_manager_api_server {
                     struct StopDataflowSvc<T: TaskManagerApi>(pub Arc<T>);
                     impl<
                         T: TaskManagerApi,
-                    > tonic::server::UnaryService<super::super::common::ResourceId>
+                    > tonic::server::UnaryService<super::StopDataflowRequest>
                     for StopDataflowSvc<T> {
                         type Response = super::StopDataflowResponse;
                         type Future = BoxFuture<
@@ -437,7 +672,7 @@ pub mod task_manager_api_server {
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::super::common::ResourceId>,
+                            request: tonic::Request<super::StopDataflowRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
@@ -661,6 +896,207 @@ pub mod task_manager_api_server {
                     };
                     Box::pin(fut)
                 }
+                "/taskmanager.TaskManagerApi/DrainOperator" => {
+                    #[allow(non_camel_case_types)]
+                    struct DrainOperatorSvc<T: TaskManagerApi>(pub Arc<T>);
+                    impl<
+                        T: TaskManagerApi,
+                    > tonic::server::UnaryService<super::OperatorRequest>
+                    for DrainOperatorSvc<T> {
+                        type Response = super::super::common::Response;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::OperatorRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).drain_operator(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = DrainOperatorSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/taskmanager.TaskManagerApi/ResumeOperator" => {
+                    #[allow(non_camel_case_types)]
+                    struct ResumeOperatorSvc<T: TaskManagerApi>(pub Arc<T>);
+                    impl<
+                        T: TaskManagerApi,
+                    > tonic::server::UnaryService<super::OperatorRequest>
+                    for ResumeOperatorSvc<T> {
+                        type Response = super::super::common::Response;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::OperatorRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).resume_operator(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ResumeOperatorSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/taskmanager.TaskManagerApi/TapOperator" => {
+                    #[allow(non_camel_case_types)]
+                    struct TapOperatorSvc<T: TaskManagerApi>(pub Arc<T>);
+                    impl<
+                        T: TaskManagerApi,
+                    > tonic::server::ServerStreamingService<super::TapOperatorRequest>
+                    for TapOperatorSvc<T> {
+                        type Response = super::super::common::KeyedDataEvent;
+                        type ResponseStream = T::TapOperatorStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TapOperatorRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).tap_operator(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = TapOperatorSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/taskmanager.TaskManagerApi/TriggerSavepoint" => {
+                    #[allow(non_camel_case_types)]
+                    struct TriggerSavepointSvc<T: TaskManagerApi>(pub Arc<T>);
+                    impl<
+                        T: TaskManagerApi,
+                    > tonic::server::UnaryService<super::super::common::ResourceId>
+                    for TriggerSavepointSvc<T> {
+                        type Response = super::super::common::OperatorStates;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::super::common::ResourceId>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).trigger_savepoint(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = TriggerSavepointSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/taskmanager.TaskManagerApi/UpdateSinkConfig" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpdateSinkConfigSvc<T: TaskManagerApi>(pub Arc<T>);
+                    impl<
+                        T: TaskManagerApi,
+                    > tonic::server::UnaryService<super::UpdateSinkConfigRequest>
+                    for UpdateSinkConfigSvc<T> {
+                        type Response = super::super::common::Response;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateSinkConfigRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).update_sink_config(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = UpdateSinkConfigSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -2,44 +2,46 @@ use std::time::Duration;
 
 use tonic::codegen::StdError;
 
+pub use crate::common_impl::ConnectionError;
 use crate::taskmanager::task_manager_api_client::TaskManagerApiClient;
 
 /// Extra implementation of [`TaskManagerApiClient`]
 impl TaskManagerApiClient<tonic::transport::Channel> {
-    /// Connect to remote task worker lazily with connection timeout
-    pub fn with_connection_timeout<D>(dst: D, timeout: Duration) -> Self
+    /// Connect to remote task worker lazily. Every (re)connect is bounded by `connect_timeout` and every request, including the time it waits for the connection, is bounded by `rpc_timeout`
+    pub fn with_connection_timeout<D>(
+        dst: D,
+        connect_timeout: Duration,
+        rpc_timeout: Duration,
+    ) -> Self
     where
         D: TryInto<tonic::transport::Endpoint>,
         D::Error: Into<StdError>,
     {
         let conn = tonic::transport::Endpoint::new(dst)
             .expect("parse endpoint failed")
-            .connect_timeout(timeout)
+            .connect_timeout(connect_timeout)
+            .timeout(rpc_timeout)
             .connect_lazy();
         Self::new(conn)
     }
 
-    /// Try to connect to remote task worker with connection timeout
+    /// Try to connect to remote task worker eagerly. The connect is bounded by `connect_timeout` and every request of the client is bounded by `rpc_timeout`
     pub async fn connect_with_timeout<D>(
         dst: D,
-        timeout: Duration,
-    ) -> Result<Self, tonic::transport::Error>
+        connect_timeout: Duration,
+        rpc_timeout: Duration,
+    ) -> Result<Self, ConnectionError>
     where
         D: TryInto<tonic::transport::Endpoint>,
         D::Error: Into<StdError>,
     {
-        match tonic::transport::Endpoint::new(dst) {
-            Ok(endpoint) => endpoint
-                .connect_timeout(timeout)
-                .connect()
-                .await
-                .map(|channel| Self::new(channel)),
-            Err(err) => Err(err),
+        let endpoint = tonic::transport::Endpoint::new(dst)
+            .map_err(ConnectionError::Transport)?
+            .connect_timeout(connect_timeout)
+            .timeout(rpc_timeout);
+        match tokio::time::timeout(connect_timeout, endpoint.connect()).await {
+            Ok(conn) => conn.map(Self::new).map_err(ConnectionError::Transport),
+            Err(_) => Err(ConnectionError::Timeout),
         }
     }
 }
-
-pub enum ConnectionError {
-    Timeout,
-    Transport(tonic::transport::Error),
-}
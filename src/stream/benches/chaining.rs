@@ -0,0 +1,130 @@
+use common::{event::LocalEvent, types::TypedValue};
+use criterion::{criterion_group, criterion_main, Criterion};
+use proto::common::{
+    operator_info::Details, project::Field, DataTypeEnum, DataflowMeta, KeyedDataEvent,
+    OperatorInfo, Project, ResourceId,
+};
+use stream::{
+    edge::{InEdge, LocalInEdge, LocalOutEdge, OutEdge},
+    new_event_channel,
+    task::Task,
+};
+
+/// the number of stateless operators in the pipeline
+const STAGES: u32 = 5;
+
+/// the number of events sent through the pipeline in one iteration
+const EVENTS: usize = 100;
+
+/// the operator id of the downstream which receives the output of the pipeline
+const OUTPUT_ID: u32 = STAGES + 1;
+
+fn new_operator_info(operator_id: u32) -> OperatorInfo {
+    let mut info = OperatorInfo::default();
+    info.operator_id = operator_id;
+    info.details = Some(Details::Project(Project {
+        fields: vec![Field {
+            name: "id".to_string(),
+            path: "$.id".to_string(),
+            cast: DataTypeEnum::Bigint as i32,
+            default_value: None,
+        }],
+        key_field: Default::default(),
+    }));
+    info
+}
+
+fn new_event(job_id: &ResourceId, id: usize) -> LocalEvent {
+    LocalEvent::KeyedDataStreamEvent(
+        KeyedDataEvent::builder()
+            .job_id(job_id)
+            .payload(TypedValue::from_json_value(serde_json::json!({ "id": id })))
+            .build(),
+    )
+}
+
+/// start the pipeline and return its input and output edges.
+/// If `chained` is true, all operators are fused into the task of the first operator.
+/// Otherwise every operator runs in its own task and forwards events through a local channel.
+fn start_pipeline(
+    job_id: &ResourceId,
+    chained: bool,
+) -> (Vec<Task>, LocalOutEdge<LocalEvent>, LocalInEdge<LocalEvent>) {
+    let (in_tx, mut rx) = new_event_channel(EVENTS);
+    let mut tasks = vec![];
+    if chained {
+        let mut task = Task::new(
+            job_id,
+            &DataflowMeta {
+                center: 1,
+                neighbors: vec![OUTPUT_ID],
+                edge_types: Default::default(),
+            },
+        );
+        let mut executor = task.create_stream_executor(&new_operator_info(1));
+        (2..=STAGES).for_each(|operator_id| {
+            task.chain_operator(&mut executor, &new_operator_info(operator_id))
+        });
+        let (tx, next_rx) = new_event_channel(EVENTS);
+        executor.set_in_edge(Some(Box::pin(LocalInEdge::new(rx))));
+        executor.add_out_edge(OUTPUT_ID, Box::new(LocalOutEdge::new(tx)));
+        rx = next_rx;
+        task.start(executor);
+        tasks.push(task);
+    } else {
+        for operator_id in 1..=STAGES {
+            let mut task = Task::new(
+                job_id,
+                &DataflowMeta {
+                    center: operator_id,
+                    neighbors: vec![operator_id + 1],
+                    edge_types: Default::default(),
+                },
+            );
+            let mut executor = task.create_stream_executor(&new_operator_info(operator_id));
+            let (tx, next_rx) = new_event_channel(EVENTS);
+            executor.set_in_edge(Some(Box::pin(LocalInEdge::new(rx))));
+            executor.add_out_edge(operator_id + 1, Box::new(LocalOutEdge::new(tx)));
+            rx = next_rx;
+            task.start(executor);
+            tasks.push(task);
+        }
+    }
+
+    (tasks, LocalOutEdge::new(in_tx), LocalInEdge::new(rx))
+}
+
+/// compares a 5-stage stateless pipeline with and without operator chaining
+fn bench_chaining(c: &mut Criterion) {
+    stream::initialize_v8();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("build runtime failed");
+    let job_id = ResourceId::default();
+
+    let mut group = c.benchmark_group("chaining");
+    for (name, chained) in [("unchained", false), ("chained", true)] {
+        let (_tasks, in_edge, mut out_edge) =
+            runtime.block_on(async { start_pipeline(&job_id, chained) });
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    for id in 0..EVENTS {
+                        in_edge
+                            .write(new_event(&job_id, id))
+                            .await
+                            .expect("write event failed");
+                    }
+                    for _ in 0..EVENTS {
+                        out_edge.next().await.expect("pipeline closed");
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_chaining);
+criterion_main!(benches);
@@ -0,0 +1,81 @@
+use common::types::TypedValue;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use proto::common::{
+    mapper, operator_info::Details, project::Field, DataTypeEnum, Entry, Func, KeyedDataEvent,
+    Mapper, Project,
+};
+use stream::{dataflow::Execution, state::MemoryStateManager};
+
+/// the number of payloads in one event
+const EVENT_SIZE: usize = 100;
+
+const PROJECT_UDF: &str = "function _operator_map_process(a) { return { id: Number(a.user.id), name: a.user.name, city: a.user.address.city } }";
+
+fn new_event() -> KeyedDataEvent {
+    let mut event = KeyedDataEvent::default();
+    event.data = (0..EVENT_SIZE)
+        .map(|index| {
+            let payload = TypedValue::from_json_value(serde_json::json!({
+                "user": {
+                    "id": index.to_string(),
+                    "name": format!("user-{index}"),
+                    "address": {"city": "Shenzhen", "street": "Nanshan"},
+                    "tags": ["a", "b", "c"],
+                },
+                "timestamp": 1672531200000i64,
+            }));
+            let mut entry = Entry::default();
+            entry.set_data_type(payload.get_type());
+            entry.value = payload.get_data_bytes();
+            entry
+        })
+        .collect();
+    event
+}
+
+fn new_field(name: &str, path: &str, cast: DataTypeEnum) -> Field {
+    Field {
+        name: name.to_string(),
+        path: path.to_string(),
+        cast: cast as i32,
+        default_value: None,
+    }
+}
+
+/// compares the native project operator with the equivalent map UDF
+fn bench_project(c: &mut Criterion) {
+    stream::initialize_v8();
+    let event = new_event();
+
+    let udf = Details::Mapper(Mapper {
+        value: Some(mapper::Value::Func(Func {
+            function: PROJECT_UDF.to_string(),
+        })),
+    });
+    let project = Details::Project(Project {
+        fields: vec![
+            new_field("id", "$.user.id", DataTypeEnum::Number),
+            new_field("name", "$.user.name", DataTypeEnum::Unspecified),
+            new_field("city", "$.user.address.city", DataTypeEnum::Unspecified),
+        ],
+        key_field: Default::default(),
+    });
+
+    let mut group = c.benchmark_group("project");
+    for (name, details) in [("udf", udf), ("native", project)] {
+        let isolate = &mut v8::Isolate::new(Default::default());
+        let scope = &mut v8::HandleScope::new(isolate);
+        let execution = Execution::new(0, &details, MemoryStateManager::new(), scope);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                execution
+                    .process(black_box(&event))
+                    .expect("process failed")
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_project);
+criterion_main!(benches);
@@ -0,0 +1,700 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use common::{
+    event::LocalEvent,
+    ordering::{Fencing, RestartFence},
+    types::{ExecutorId, SinkId},
+};
+use futures_util::future::Either;
+use prost::Message;
+use proto::common::{sink_batching::Overflow, KeyedDataEvent, KeyedEventSet, SinkBatching};
+use tokio_util::sync::CancellationToken;
+use tonic::async_trait;
+
+use crate::{
+    connector::Sink,
+    err::{BatchSinkException, ErrorKind, SinkException},
+};
+
+/// [`BatchFlush`] writes batches of events to an external system. It's driven by [`BatchingSink`], which decides when a batch is written.
+#[async_trait]
+pub trait BatchFlush {
+    fn sink_id(&self) -> SinkId;
+
+    /// write the events of the batch in order. If some of them are written before the failure,
+    /// the error carries the id of the first event which isn't written. Otherwise, none of them is considered written
+    async fn flush(&mut self, batch: &[KeyedDataEvent]) -> Result<(), BatchSinkException>;
+
+    fn close(&mut self);
+}
+
+/// thresholds of a batch resolved from [`SinkBatching`]. 0 means unlimited
+struct Thresholds {
+    max_events: usize,
+    max_bytes: usize,
+    linger: Option<Duration>,
+}
+
+/// [`BatchingSink`] buffers the events sent to a [`BatchFlush`] and writes them in batches.
+///
+/// The buffer is flushed when:
+/// 1. the number or the encoded size of the buffered events reaches the thresholds
+/// 2. the oldest buffered event has lingered for the configured time. The executor polls [`Sink::get_flush_deadline`] for it
+/// 3. the executor flushes its sinks by [`Sink::flush_sink`], i.e. on a checkpoint barrier and before it stops
+///
+/// Without [`SinkBatching`], the events of each `sink` or `batch_sink` call are written as one batch at once.
+///
+/// A failed batch is retried at most `max_retries` times. If the failure carries the first event which isn't written,
+/// the events before it are removed from the buffer and the rest are retried. Otherwise, the batch is split in halves,
+/// so that a single bad event doesn't fail the other events of the batch. If the batch still fails, the events of the call are
+/// removed from the buffer and the failure is returned to the error policy of the operator, which sends them again.
+/// The events of the former calls are kept and written by the next flush.
+///
+/// Once its cancellation token fires, a flush stops at once and the buffered events are kept.
+///
+/// The executor fences the sink by the event which the following output derives from. Once a new run of an upstream operator is observed,
+/// the buffered output of its former runs is discarded, since it may be partial or be sent again by the new run.
+pub struct BatchingSink<F> {
+    inner: F,
+    thresholds: Option<Thresholds>,
+    capacity: usize,
+    overflow: Overflow,
+    max_retries: u32,
+    buffer: VecDeque<KeyedDataEvent>,
+    // the upstream operator which each buffered event derives from
+    origins: VecDeque<Option<ExecutorId>>,
+    buffered_bytes: usize,
+    // when the oldest buffered event is buffered or the last flush fails, the lingering time is counted from it
+    since: Option<Instant>,
+    dropped_events: u64,
+    cancellation: CancellationToken,
+    fence: RestartFence,
+    // the upstream operator which the events written next derive from
+    origin: Option<ExecutorId>,
+    discarded_events: u64,
+}
+
+impl<F: BatchFlush + Send> BatchingSink<F> {
+    pub fn new(inner: F, batching: Option<&SinkBatching>) -> Self {
+        Self {
+            inner,
+            thresholds: batching.map(|batching| Thresholds {
+                max_events: batching.max_events as usize,
+                max_bytes: batching.max_bytes as usize,
+                linger: batching.get_linger(),
+            }),
+            capacity: batching
+                .map(|batching| batching.capacity as usize)
+                .unwrap_or_default(),
+            overflow: batching
+                .and_then(|batching| batching.get_overflow().ok())
+                .unwrap_or(Overflow::Reject),
+            max_retries: batching
+                .map(|batching| batching.max_retries)
+                .unwrap_or_default(),
+            buffer: Default::default(),
+            origins: Default::default(),
+            buffered_bytes: 0,
+            since: None,
+            dropped_events: 0,
+            cancellation: Default::default(),
+            fence: Default::default(),
+            origin: None,
+            discarded_events: 0,
+        }
+    }
+
+    pub fn get_inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn get_buffered_events(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// the number of events dropped by the [`Overflow::DropOldest`] policy
+    pub fn get_dropped_events(&self) -> u64 {
+        self.dropped_events
+    }
+
+    /// the number of buffered events discarded because their upstream operator restarted
+    pub fn get_discarded_events(&self) -> u64 {
+        self.discarded_events
+    }
+
+    pub fn set_cancellation_token(&mut self, cancellation: CancellationToken) {
+        self.cancellation = cancellation
+    }
+
+    /// the events written next derive from `event`. If it begins a new run of its operator, the buffered events derived from the former runs are discarded
+    pub fn fence(&mut self, event: &KeyedDataEvent) {
+        let origin = Some(event.from_operator_id);
+        self.origin = origin;
+        if self.fence.observe(event) != Fencing::NewRun {
+            return;
+        }
+        let len = self.buffer.len();
+        let (buffer, origins) = self
+            .buffer
+            .drain(..)
+            .zip(self.origins.drain(..))
+            .filter(|(_, buffered)| *buffered != origin)
+            .unzip();
+        self.buffer = buffer;
+        self.origins = origins;
+        let discarded = len - self.buffer.len();
+        if discarded > 0 {
+            self.buffered_bytes = self.buffer.iter().map(|event| event.encoded_len()).sum();
+            self.discarded_events += discarded as u64;
+            if self.buffer.is_empty() {
+                self.since = None;
+            }
+            tracing::warn!(
+                "operator {} restarted, {} events buffered by sink {} are discarded",
+                event.from_operator_id,
+                discarded,
+                self.inner.sink_id()
+            );
+        }
+    }
+
+    /// the time when the buffered events have to be written even if no more events arrive
+    pub fn get_deadline(&self) -> Option<Instant> {
+        let linger = self
+            .thresholds
+            .as_ref()
+            .and_then(|thresholds| thresholds.linger);
+        self.since.zip(linger).map(|(since, linger)| since + linger)
+    }
+
+    /// whether the buffered events should be written now
+    pub fn is_due(&self, now: Instant) -> bool {
+        if self.buffer.is_empty() {
+            return false;
+        }
+        match &self.thresholds {
+            Some(thresholds) => {
+                (thresholds.max_events > 0 && self.buffer.len() >= thresholds.max_events)
+                    || (thresholds.max_bytes > 0 && self.buffered_bytes >= thresholds.max_bytes)
+                    || self
+                        .get_deadline()
+                        .map(|deadline| deadline <= now)
+                        .unwrap_or_default()
+            }
+            None => true,
+        }
+    }
+
+    /// buffer the events and flush the buffer if it's due
+    pub async fn write(
+        &mut self,
+        events: Vec<KeyedDataEvent>,
+        now: Instant,
+    ) -> Result<(), BatchSinkException> {
+        let mut buffered = 0;
+        for event in events {
+            let event_id = event.event_id as u64;
+            if let Err(err) = self.push(event, now) {
+                self.pop_back(buffered);
+                return Err(BatchSinkException { err, event_id });
+            }
+            buffered += 1;
+        }
+
+        if self.is_due(now) {
+            if let Err(err) = self.flush(now).await {
+                // the events of this call are always behind the events which are not written
+                self.pop_back(buffered.min(self.buffer.len()));
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// write all the buffered events in batches. The events which are not written are kept in the buffer
+    pub async fn flush(&mut self, now: Instant) -> Result<(), BatchSinkException> {
+        let mut retries = 0;
+        // the max length of a batch, it's halved when a batch fails as a whole
+        let mut limit = usize::MAX;
+        while !self.buffer.is_empty() {
+            let len = self.next_batch_len().min(limit);
+            let buffered = self.buffer.len();
+            let batch = &self.buffer.make_contiguous()[..len];
+            let first_event_id = batch[0].event_id as u64;
+            let result = if self.cancellation.is_cancelled() {
+                None
+            } else {
+                let cancelled = self.cancellation.cancelled();
+                let flush = self.inner.flush(batch);
+                futures_util::pin_mut!(flush, cancelled);
+                match futures_util::future::select(flush, cancelled).await {
+                    Either::Left((result, _)) => Some(result),
+                    Either::Right(_) => None,
+                }
+            };
+            let result = match result {
+                Some(result) => result,
+                None => {
+                    self.since = Some(now);
+                    return Err(BatchSinkException {
+                        err: SinkException {
+                            kind: ErrorKind::MessageSendFailed,
+                            msg: format!(
+                                "flush is cancelled, {} events are left in the buffer",
+                                buffered
+                            ),
+                        },
+                        event_id: first_event_id,
+                    });
+                }
+            };
+
+            match result {
+                Ok(_) => self.pop_front(len),
+                Err(err) => {
+                    let written = batch
+                        .iter()
+                        .position(|event| event.event_id as u64 == err.event_id)
+                        .unwrap_or_default();
+                    self.pop_front(written);
+                    if retries >= self.max_retries {
+                        self.since = Some(now);
+                        return Err(err);
+                    }
+                    retries += 1;
+                    if written == 0 && len > 1 {
+                        limit = len / 2;
+                    }
+                    tracing::warn!(
+                        "retry the batch of sink {} [{}/{}] after {} events are written: {}",
+                        self.inner.sink_id(),
+                        retries,
+                        self.max_retries,
+                        written,
+                        err
+                    );
+                }
+            }
+        }
+        self.since = None;
+        Ok(())
+    }
+
+    fn push(&mut self, event: KeyedDataEvent, now: Instant) -> Result<(), SinkException> {
+        if self.capacity > 0 && self.buffer.len() >= self.capacity {
+            match self.overflow {
+                Overflow::Reject => {
+                    return Err(SinkException {
+                        kind: ErrorKind::BufferFull,
+                        msg: format!("{} events are buffered", self.buffer.len()),
+                    })
+                }
+                Overflow::DropOldest => {
+                    self.pop_front(1);
+                    self.dropped_events += 1;
+                    tracing::warn!(
+                        "the buffer of sink {} is full, the oldest event is dropped",
+                        self.inner.sink_id()
+                    );
+                }
+            }
+        }
+        self.buffered_bytes += event.encoded_len();
+        self.buffer.push_back(event);
+        self.origins.push_back(self.origin);
+        self.since.get_or_insert(now);
+        Ok(())
+    }
+
+    /// the number of events from the front of the buffer which fit the thresholds. It's at least 1
+    fn next_batch_len(&self) -> usize {
+        let (max_events, max_bytes) = match &self.thresholds {
+            Some(thresholds) => (thresholds.max_events, thresholds.max_bytes),
+            None => (0, 0),
+        };
+        let mut bytes = 0;
+        let len = self
+            .buffer
+            .iter()
+            .enumerate()
+            .take_while(|(index, event)| {
+                bytes += event.encoded_len();
+                (max_events == 0 || *index < max_events) && (max_bytes == 0 || bytes <= max_bytes)
+            })
+            .count();
+        len.max(1)
+    }
+
+    fn pop_front(&mut self, len: usize) {
+        self.buffer.drain(..len).for_each(|event| {
+            self.buffered_bytes -= event.encoded_len();
+        });
+        self.origins.drain(..len);
+        if self.buffer.is_empty() {
+            self.since = None;
+        }
+    }
+
+    fn pop_back(&mut self, len: usize) {
+        let start = self.buffer.len() - len;
+        self.buffer.drain(start..).for_each(|event| {
+            self.buffered_bytes -= event.encoded_len();
+        });
+        self.origins.drain(start..);
+        if self.buffer.is_empty() {
+            self.since = None;
+        }
+    }
+}
+
+#[async_trait]
+impl<F: BatchFlush + Send> Sink for BatchingSink<F> {
+    fn sink_id(&self) -> SinkId {
+        self.inner.sink_id()
+    }
+
+    async fn sink(&mut self, msg: LocalEvent) -> Result<(), SinkException> {
+        match msg {
+            LocalEvent::Terminate { .. } => Ok(()),
+            LocalEvent::KeyedDataStreamEvent(event) => self
+                .write(vec![event], Instant::now())
+                .await
+                .map_err(|err| err.err),
+        }
+    }
+
+    async fn batch_sink(&mut self, event_set: KeyedEventSet) -> Result<(), BatchSinkException> {
+        self.write(event_set.events, Instant::now()).await
+    }
+
+    fn close_sink(&mut self) {
+        if !self.buffer.is_empty() {
+            tracing::warn!(
+                "sink {} is closed, {} buffered events are dropped",
+                self.inner.sink_id(),
+                self.buffer.len()
+            );
+        }
+        self.pop_front(self.buffer.len());
+        self.inner.close()
+    }
+
+    fn flush_sink(&mut self) -> Result<(), SinkException> {
+        futures_executor::block_on(self.flush(Instant::now())).map_err(|err| err.err)
+    }
+
+    fn get_flush_deadline(&self) -> Option<Instant> {
+        self.get_deadline()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use common::types::SinkId;
+    use prost::Message;
+    use proto::common::{sink_batching::Overflow, KeyedDataEvent, SinkBatching};
+    use tonic::async_trait;
+
+    use crate::{
+        connector::Sink,
+        err::{BatchSinkException, ErrorKind, SinkException},
+    };
+
+    use super::{BatchFlush, BatchingSink};
+
+    /// it records the ids of the written batches. The event of `reject` fails each batch containing it for `failures` times
+    #[derive(Default)]
+    struct RecordingFlush {
+        batches: Vec<Vec<i64>>,
+        reject: Option<i64>,
+        failures: u32,
+        // whether the events before the rejected one are written
+        partial: bool,
+    }
+
+    #[async_trait]
+    impl BatchFlush for RecordingFlush {
+        fn sink_id(&self) -> SinkId {
+            1
+        }
+
+        async fn flush(&mut self, batch: &[KeyedDataEvent]) -> Result<(), BatchSinkException> {
+            let ids = batch.iter().map(|event| event.event_id).collect::<Vec<_>>();
+            match self.reject.filter(|reject| ids.contains(reject)) {
+                Some(reject) if self.failures > 0 => {
+                    self.failures -= 1;
+                    if self.partial {
+                        let written = ids.iter().take_while(|id| **id != reject).cloned();
+                        self.batches.push(written.collect());
+                    }
+                    Err(BatchSinkException {
+                        err: SinkException {
+                            kind: ErrorKind::RemoteSinkRejected,
+                            msg: format!("event {} is rejected", reject),
+                        },
+                        event_id: if self.partial { reject as u64 } else { 0 },
+                    })
+                }
+                _ => {
+                    self.batches.push(ids);
+                    Ok(())
+                }
+            }
+        }
+
+        fn close(&mut self) {}
+    }
+
+    fn new_events(ids: std::ops::Range<i64>) -> Vec<KeyedDataEvent> {
+        ids.map(|event_id| KeyedDataEvent {
+            event_id,
+            ..Default::default()
+        })
+        .collect()
+    }
+
+    fn new_sink(flush: RecordingFlush, batching: SinkBatching) -> BatchingSink<RecordingFlush> {
+        BatchingSink::new(flush, Some(&batching))
+    }
+
+    #[tokio::test]
+    async fn test_flush_triggers() {
+        let start = Instant::now();
+
+        // the number of events
+        let mut sink = new_sink(
+            Default::default(),
+            SinkBatching {
+                max_events: 3,
+                ..Default::default()
+            },
+        );
+        sink.write(new_events(1..3), start).await.unwrap();
+        assert!(sink.get_inner().batches.is_empty());
+        sink.write(new_events(3..5), start).await.unwrap();
+        assert_eq!(sink.get_inner().batches, vec![vec![1, 2, 3], vec![4]]);
+        assert_eq!(sink.get_buffered_events(), 0);
+
+        // the encoded size of the events
+        let size = new_events(1..2)[0].encoded_len() as u64;
+        let mut sink = new_sink(
+            Default::default(),
+            SinkBatching {
+                max_bytes: size * 2,
+                ..Default::default()
+            },
+        );
+        sink.write(new_events(1..2), start).await.unwrap();
+        assert!(sink.get_inner().batches.is_empty());
+        sink.write(new_events(2..3), start).await.unwrap();
+        assert_eq!(sink.get_inner().batches, vec![vec![1, 2]]);
+
+        // the lingering time counted from the oldest buffered event
+        let mut sink = new_sink(
+            Default::default(),
+            SinkBatching {
+                max_events: 10,
+                linger_millis: 100,
+                ..Default::default()
+            },
+        );
+        assert_eq!(sink.get_deadline(), None);
+        sink.write(new_events(1..2), start).await.unwrap();
+        let deadline = start + Duration::from_millis(100);
+        assert_eq!(sink.get_deadline(), Some(deadline));
+        sink.write(new_events(2..3), start + Duration::from_millis(50))
+            .await
+            .unwrap();
+        assert_eq!(sink.get_deadline(), Some(deadline));
+        assert!(!sink.is_due(start + Duration::from_millis(99)));
+        assert!(sink.is_due(deadline));
+        sink.write(new_events(3..4), deadline).await.unwrap();
+        assert_eq!(sink.get_inner().batches, vec![vec![1, 2, 3]]);
+        assert_eq!(sink.get_deadline(), None);
+
+        // the barrier and the stop of the executor flush the buffer by flush_sink
+        sink.write(new_events(4..6), deadline).await.unwrap();
+        assert!(sink.flush_sink().is_ok());
+        assert_eq!(sink.get_inner().batches, vec![vec![1, 2, 3], vec![4, 5]]);
+
+        // the events of each call are written at once without batching
+        let mut sink = BatchingSink::new(RecordingFlush::default(), None);
+        sink.write(new_events(1..4), start).await.unwrap();
+        sink.write(new_events(4..5), start).await.unwrap();
+        assert_eq!(sink.get_inner().batches, vec![vec![1, 2, 3], vec![4]]);
+        assert_eq!(sink.get_deadline(), None);
+    }
+
+    #[tokio::test]
+    async fn test_flush_on_cancel() {
+        let start = Instant::now();
+        let mut sink = new_sink(
+            Default::default(),
+            SinkBatching {
+                max_events: 10,
+                ..Default::default()
+            },
+        );
+        let cancellation = tokio_util::sync::CancellationToken::new();
+        sink.set_cancellation_token(cancellation.clone());
+        sink.write(new_events(1..3), start).await.unwrap();
+
+        cancellation.cancel();
+        assert!(sink.flush(start).await.is_err());
+        assert!(sink.get_inner().batches.is_empty());
+        assert_eq!(sink.get_buffered_events(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_overflow() {
+        let start = Instant::now();
+        let failing = || RecordingFlush {
+            reject: Some(1),
+            failures: u32::MAX,
+            ..Default::default()
+        };
+
+        let mut sink = new_sink(
+            failing(),
+            SinkBatching {
+                max_events: 2,
+                capacity: 2,
+                ..Default::default()
+            },
+        );
+        sink.write(new_events(1..2), start).await.unwrap();
+        // the failed batch keeps the former event buffered, and the event of the call is sent again by the error policy
+        assert!(sink.write(new_events(2..3), start).await.is_err());
+        assert_eq!(sink.get_buffered_events(), 1);
+        sink.push(new_events(2..3).remove(0), start).unwrap();
+        let err = sink.write(new_events(3..4), start).await.unwrap_err();
+        assert_eq!(err.err.kind, ErrorKind::BufferFull);
+        assert!(err.err.is_retryable());
+        assert_eq!(err.event_id, 3);
+        assert_eq!(sink.get_buffered_events(), 2);
+
+        let mut sink = new_sink(
+            failing(),
+            SinkBatching {
+                max_events: 2,
+                capacity: 2,
+                overflow: Overflow::DropOldest as i32,
+                ..Default::default()
+            },
+        );
+        sink.write(new_events(1..2), start).await.unwrap();
+        sink.push(new_events(2..3).remove(0), start).unwrap();
+        // the oldest event is dropped, so the batch without the rejected event is written
+        sink.write(new_events(3..4), start).await.unwrap();
+        assert_eq!(sink.get_dropped_events(), 1);
+        assert_eq!(sink.get_inner().batches, vec![vec![2, 3]]);
+    }
+
+    #[tokio::test]
+    async fn test_retry_partial_failure() {
+        let start = Instant::now();
+        let mut sink = new_sink(
+            RecordingFlush {
+                reject: Some(3),
+                failures: 1,
+                partial: true,
+                ..Default::default()
+            },
+            SinkBatching {
+                max_events: 4,
+                max_retries: 1,
+                ..Default::default()
+            },
+        );
+        sink.write(new_events(1..5), start).await.unwrap();
+        // the written events are not written again
+        assert_eq!(sink.get_inner().batches, vec![vec![1, 2], vec![3, 4]]);
+
+        // the batch is split in halves if it fails as a whole
+        let mut sink = new_sink(
+            RecordingFlush {
+                reject: Some(4),
+                failures: 2,
+                ..Default::default()
+            },
+            SinkBatching {
+                max_events: 4,
+                max_retries: 2,
+                ..Default::default()
+            },
+        );
+        sink.write(new_events(1..5), start).await.unwrap();
+        assert_eq!(sink.get_inner().batches, vec![vec![1, 2], vec![3], vec![4]]);
+
+        // the events which are not written after the retries
+        let mut sink = new_sink(
+            RecordingFlush {
+                reject: Some(2),
+                failures: u32::MAX,
+                ..Default::default()
+            },
+            SinkBatching {
+                max_events: 4,
+                linger_millis: 100,
+                max_retries: 2,
+                ..Default::default()
+            },
+        );
+        sink.write(new_events(1..2), start).await.unwrap();
+        let now = start + Duration::from_millis(100);
+        let err = sink.write(new_events(2..3), now).await.unwrap_err();
+        assert_eq!(err.err.kind, ErrorKind::RemoteSinkRejected);
+        assert_eq!(sink.get_inner().batches, vec![vec![1]]);
+        assert_eq!(sink.get_buffered_events(), 0);
+        assert_eq!(sink.get_deadline(), None);
+    }
+
+    #[tokio::test]
+    async fn test_discard_output_of_superseded_runs() {
+        let start = Instant::now();
+        let mut sink = new_sink(
+            Default::default(),
+            SinkBatching {
+                max_events: 10,
+                ..Default::default()
+            },
+        );
+        let input = |from_operator_id, restart_epoch| KeyedDataEvent {
+            from_operator_id,
+            sequence_epoch: 1,
+            restart_epoch,
+            ..Default::default()
+        };
+
+        // the output of operator 1 in its first run and the output of operator 2
+        sink.fence(&input(1, 1));
+        sink.write(new_events(1..3), start).await.unwrap();
+        sink.fence(&input(2, 1));
+        sink.write(new_events(3..4), start).await.unwrap();
+        sink.fence(&input(1, 1));
+        sink.write(new_events(4..5), start).await.unwrap();
+        assert_eq!(sink.get_buffered_events(), 4);
+
+        // operator 1 restarts, its partial output since the last flush is discarded
+        sink.fence(&input(1, 2));
+        assert_eq!(sink.get_discarded_events(), 3);
+        assert_eq!(sink.get_buffered_events(), 1);
+        sink.write(new_events(5..7), start).await.unwrap();
+
+        assert!(sink.flush_sink().is_ok());
+        assert_eq!(sink.get_inner().batches, vec![vec![3, 5, 6]]);
+        assert_eq!(sink.get_deadline(), None);
+
+        // the flushed output is not discarded by a later restart
+        sink.write(new_events(7..8), start).await.unwrap();
+        sink.fence(&input(1, 3));
+        assert_eq!(sink.get_buffered_events(), 0);
+        assert_eq!(sink.get_discarded_events(), 4);
+        assert_eq!(sink.get_inner().batches, vec![vec![3, 5, 6]]);
+    }
+}
@@ -1,13 +1,23 @@
 use std::{
-    collections::{hash_map::DefaultHasher, BTreeMap},
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    fmt::Display,
     hash::{Hash, Hasher},
     task::Poll,
+    time::Duration,
 };
 
 use common::{
     db::MysqlConn,
     event::{LocalEvent, StreamEvent},
-    kafka::{run_consumer, run_producer, KafkaConsumer, KafkaMessage, KafkaProducer},
+    formats::{
+        avro::{AvroDecoder, AvroEncoder},
+        csv::{CsvDecoder, CsvEncoder},
+        protobuf::ProtobufDecoder,
+    },
+    kafka::{
+        key_hash_partition, run_consumer, run_consumer_from, run_producer, KafkaConsumer,
+        KafkaDelivery, KafkaMessage, KafkaProducer, KafkaRecord,
+    },
     redis::RedisClient,
     types::{ExecutorId, SinkId, SourceId, TypedValue},
     utils::times::{now, now_timestamp},
@@ -15,17 +25,22 @@ use common::{
 use prost::Message;
 
 use proto::common::{
+    kafka_desc::kafka_sink_options::Partitioner,
     operator_info::{self, Details},
     sink, source, Entry, KafkaDesc, KeyedDataEvent, KeyedEventSet, MysqlDesc, OperatorInfo,
     RedisDesc, ResourceId,
 };
 
+use futures_util::future::Either;
 use tokio::sync::mpsc::error::TryRecvError;
+use tokio_util::sync::CancellationToken;
 use tonic::async_trait;
 
 use crate::{
-    err::{BatchSinkException, SinkException},
+    batching::{BatchFlush, BatchingSink},
+    err::{BatchSinkException, DecodeFailure, ErrorKind, SinkException},
     new_event_channel,
+    offsets::{SharedOffsetStore, SourceOffsets},
     v8_runtime::RuntimeEngine,
     Receiver, Sender,
 };
@@ -63,6 +78,15 @@ pub trait Source {
 
     fn poll_next(&mut self, cx: &mut std::task::Context<'_>)
         -> std::task::Poll<Option<LocalEvent>>;
+
+    /// the event at the offset of the partition has been sent to all the downstreams, so the source resumes after it once it restarts.
+    /// Sources which can't resume from an offset ignore it
+    fn commit(&mut self, _partition: i32, _offset: i64) {}
+
+    /// the rows of the last fetched message which fail to be decoded, they're handled by the error policy of the source operator
+    fn take_decode_failures(&mut self) -> Vec<DecodeFailure> {
+        vec![]
+    }
 }
 
 #[async_trait]
@@ -85,6 +109,19 @@ pub trait Sink {
      * Gracefully close sink
      */
     fn close_sink(&mut self);
+
+    /**
+     * Block until all the messages buffered by the sink are written to the external system
+     */
+    fn flush_sink(&mut self) -> Result<(), SinkException>;
+
+    /**
+     * The time when the messages buffered by the sink have to be flushed even if no more messages arrive.
+     * Sinks which don't buffer messages by time return none
+     */
+    fn get_flush_deadline(&self) -> Option<std::time::Instant> {
+        None
+    }
 }
 
 pub enum SourceImpl {
@@ -131,6 +168,20 @@ impl Source for SourceImpl {
         }
     }
 
+    fn commit(&mut self, partition: i32, offset: i64) {
+        match self {
+            Self::Kafka(source, _, _) => source.commit(partition, offset),
+            Self::Empty(..) => {}
+        }
+    }
+
+    fn take_decode_failures(&mut self) -> Vec<DecodeFailure> {
+        match self {
+            Self::Kafka(source, _, _) => source.take_decode_failures(),
+            Self::Empty(..) => vec![],
+        }
+    }
+
     async fn close_source(&mut self) {
         match self {
             Self::Kafka(kafka, tx, rx) => {
@@ -146,27 +197,56 @@ impl Source for SourceImpl {
     }
 }
 
-impl From<(&ResourceId, SourceId, &operator_info::Details)> for SourceImpl {
-    fn from(args: (&ResourceId, SourceId, &operator_info::Details)) -> Self {
+/// the format mappings of the source which are not configured are filled by the output schema of the operator
+impl SourceImpl {
+    /// the source stops fetching from the external system once the token fires, and a blocking fetch returns at once
+    pub fn set_cancellation_token(&mut self, cancellation: CancellationToken) {
+        match self {
+            Self::Kafka(kafka, _, _) => kafka.cancellation = cancellation,
+            // the terminator channel never blocks the executor
+            Self::Empty(..) => {}
+        }
+    }
+
+    /// the source resumes from the offsets committed to the offset store if it's set, and it commits the offsets to the store.
+    /// Otherwise the offsets are committed to the external system automatically
+    pub fn new(
+        resource_id: &ResourceId,
+        info: &OperatorInfo,
+        offset_store: Option<SharedOffsetStore>,
+    ) -> Self {
         let (tx, rx) = new_event_channel(1);
-        match args.2 {
-            operator_info::Details::Source(source) => match source.desc.as_ref() {
+        match &info.details {
+            Some(operator_info::Details::Source(source)) => match source.desc.as_ref() {
                 Some(desc) => match desc {
-                    source::Desc::Kafka(conf) => {
-                        SourceImpl::Kafka(Kafka::with_source_config(args.0, args.1, conf), tx, rx)
-                    }
+                    source::Desc::Kafka(conf) => SourceImpl::Kafka(
+                        Kafka::with_offset_store(
+                            resource_id,
+                            info.operator_id,
+                            &conf.with_payload_schema(info.output_schema.as_ref()),
+                            offset_store,
+                        ),
+                        tx,
+                        rx,
+                    ),
                 },
-                None => SourceImpl::Empty(args.1, tx, rx),
+                None => SourceImpl::Empty(info.operator_id, tx, rx),
             },
-            _ => SourceImpl::Empty(args.1, tx, rx),
+            _ => SourceImpl::Empty(info.operator_id, tx, rx),
         }
     }
 }
 
+impl From<(&ResourceId, &OperatorInfo)> for SourceImpl {
+    fn from((resource_id, info): (&ResourceId, &OperatorInfo)) -> Self {
+        Self::new(resource_id, info, None)
+    }
+}
+
 pub enum SinkImpl {
     Kafka(Kafka),
-    Mysql(Mysql),
-    Redis(Redis),
+    Mysql(BatchingSink<Mysql>),
+    Redis(BatchingSink<Redis>),
     Empty(SinkId),
 }
 
@@ -199,6 +279,15 @@ impl Sink for SinkImpl {
         }
     }
 
+    fn flush_sink(&mut self) -> Result<(), SinkException> {
+        match self {
+            Self::Kafka(sink) => sink.flush_sink(),
+            Self::Mysql(sink) => sink.flush_sink(),
+            Self::Redis(sink) => sink.flush_sink(),
+            Self::Empty(_) => Ok(()),
+        }
+    }
+
     async fn batch_sink(&mut self, event_set: KeyedEventSet) -> Result<(), BatchSinkException> {
         match self {
             Self::Kafka(sink) => sink.batch_sink(event_set).await,
@@ -207,8 +296,46 @@ impl Sink for SinkImpl {
             Self::Redis(redis) => redis.batch_sink(event_set).await,
         }
     }
+
+    fn get_flush_deadline(&self) -> Option<std::time::Instant> {
+        match self {
+            Self::Mysql(sink) => sink.get_flush_deadline(),
+            Self::Redis(sink) => sink.get_flush_deadline(),
+            Self::Kafka(_) | Self::Empty(_) => None,
+        }
+    }
+}
+
+impl SinkImpl {
+    /// a running flush of the batching sinks stops at once once the token fires
+    pub fn set_cancellation_token(&mut self, cancellation: CancellationToken) {
+        match self {
+            Self::Mysql(sink) => sink.set_cancellation_token(cancellation),
+            Self::Redis(sink) => sink.set_cancellation_token(cancellation),
+            // the deliveries of Kafka are bounded by the producer
+            Self::Kafka(_) | Self::Empty(_) => {}
+        }
+    }
+
+    /// whether the sink is connected to the external system. A Kafka sink whose producer fails to be created can't send any message
+    pub fn is_connected(&self) -> bool {
+        match self {
+            Self::Kafka(kafka) => kafka.producer.is_some(),
+            Self::Mysql(_) | Self::Redis(_) | Self::Empty(_) => true,
+        }
+    }
+
+    /// the messages sent next derive from `event`. Kafka delivers the messages at once, so there is no output to discard
+    pub fn fence(&mut self, event: &KeyedDataEvent) {
+        match self {
+            Self::Mysql(sink) => sink.fence(event),
+            Self::Redis(sink) => sink.fence(event),
+            Self::Kafka(_) | Self::Empty(_) => {}
+        }
+    }
 }
 
+/// the format mappings of the sink which are not configured are filled by the input schema of the operator
 impl From<(&ResourceId, &OperatorInfo)> for SinkImpl {
     fn from((resource_id, info): (&ResourceId, &OperatorInfo)) -> Self {
         match &info.details {
@@ -218,14 +345,16 @@ impl From<(&ResourceId, &OperatorInfo)> for SinkImpl {
                         sink::Desc::Kafka(desc) => SinkImpl::Kafka(Kafka::with_sink_config(
                             resource_id,
                             info.operator_id,
-                            desc,
+                            &desc.with_payload_schema(info.input_schema.as_ref()),
+                        )),
+                        sink::Desc::Mysql(desc) => SinkImpl::Mysql(BatchingSink::new(
+                            Mysql::with_config(info.operator_id, desc),
+                            sink.batching.as_ref(),
+                        )),
+                        sink::Desc::Redis(desc) => SinkImpl::Redis(BatchingSink::new(
+                            Redis::with_config(info.operator_id, desc),
+                            sink.batching.as_ref(),
                         )),
-                        sink::Desc::Mysql(desc) => {
-                            SinkImpl::Mysql(Mysql::with_config(info.operator_id, desc))
-                        }
-                        sink::Desc::Redis(desc) => {
-                            SinkImpl::Redis(Redis::with_config(info.operator_id, desc))
-                        }
                     },
                     None => Self::Empty(info.operator_id),
                 },
@@ -236,6 +365,19 @@ impl From<(&ResourceId, &OperatorInfo)> for SinkImpl {
     }
 }
 
+/// record header of the job id of the event, whose value is `{namespace_id}/{resource_id}`
+pub const KAFKA_JOB_ID_HEADER: &str = "lightflus.job_id";
+/// record header of the operator which emits the event
+pub const KAFKA_OPERATOR_ID_HEADER: &str = "lightflus.operator_id";
+/// record header of the event id
+pub const KAFKA_EVENT_ID_HEADER: &str = "lightflus.event_id";
+/// event header of the partition which the source fetches the event from
+pub const SOURCE_PARTITION_HEADER: &str = "lightflus.source_partition";
+/// event header of the offset of the event in the partition which the source fetches it from
+pub const SOURCE_OFFSET_HEADER: &str = "lightflus.source_offset";
+/// event header of the time when the source fetches the event, in milliseconds since the unix epoch
+pub const INGEST_TIME_HEADER: &str = "lightflus.ingest_time";
+
 /// An unified implementation for Kafka Source and Sink.
 pub struct Kafka {
     connector_id: SourceId,
@@ -244,6 +386,25 @@ pub struct Kafka {
     consumer: Option<KafkaConsumer>,
     producer: Option<KafkaProducer>,
     job_id_hash: u64,
+    csv_decoder: Option<CsvDecoder>,
+    csv_encoder: Option<CsvEncoder>,
+    avro_decoder: Option<AvroDecoder>,
+    avro_encoder: Option<AvroEncoder>,
+    protobuf_decoder: Option<ProtobufDecoder>,
+    // the number of partitions of the topic, it's fetched once by the partitioners which need it
+    partitions: Option<i32>,
+    // the number of records which have been sent by the round-robin partitioner
+    round_robin: u32,
+    // the source stops fetching messages once it fires
+    cancellation: CancellationToken,
+    // the store which the offsets of the source are committed to. The offsets are committed to the group automatically if it's not set
+    offset_store: Option<SharedOffsetStore>,
+    // the next offsets to consume of the partitions whose events have been committed
+    offsets: SourceOffsets,
+    // the offsets of the events which have been fetched but not committed yet, keyed by partition, with whether they've been acked
+    fetched: BTreeMap<i32, BTreeMap<i64, bool>>,
+    // the rows of the last fetched message which fail to be decoded
+    decode_failures: Vec<DecodeFailure>,
 }
 
 impl Kafka {
@@ -251,6 +412,16 @@ impl Kafka {
         job_id: &ResourceId,
         executor_id: ExecutorId,
         config: &KafkaDesc,
+    ) -> Kafka {
+        Self::with_offset_store(job_id, executor_id, config, None)
+    }
+
+    /// the source resumes from the offsets committed to the offset store if it's set, see [`Source::commit`]
+    pub fn with_offset_store(
+        job_id: &ResourceId,
+        executor_id: ExecutorId,
+        config: &KafkaDesc,
+        offset_store: Option<SharedOffsetStore>,
     ) -> Kafka {
         let ref mut hasher = DefaultHasher::new();
         Hash::hash(job_id, hasher);
@@ -263,18 +434,48 @@ impl Kafka {
             consumer: None,
             producer: None,
             job_id_hash,
+            csv_decoder: config.get_csv_format().map(CsvDecoder::new),
+            csv_encoder: None,
+            avro_decoder: config.get_avro_format().map(AvroDecoder::new),
+            avro_encoder: None,
+            protobuf_decoder: config.get_protobuf_format().and_then(|format| {
+                ProtobufDecoder::new(format)
+                    .map_err(|err| tracing::error!("kafka source create decoder failed: {}", err))
+                    .ok()
+            }),
+            partitions: None,
+            round_robin: 0,
+            cancellation: Default::default(),
+            offsets: offset_store
+                .as_ref()
+                .and_then(|store| {
+                    store
+                        .lock()
+                        .unwrap_or_else(|err| err.into_inner())
+                        .load(job_id, executor_id)
+                })
+                .unwrap_or_default(),
+            offset_store,
+            fetched: Default::default(),
+            decode_failures: vec![],
         };
-        match run_consumer(
-            config
-                .brokers
-                .iter()
-                .map(|v| v.clone())
-                .collect::<Vec<String>>()
-                .join(",")
-                .as_str(),
-            &config.get_kafka_group(),
-            &config.topic,
-        ) {
+        let brokers = config
+            .brokers
+            .iter()
+            .map(|v| v.clone())
+            .collect::<Vec<String>>()
+            .join(",");
+        let consumer = match &this.offset_store {
+            Some(_) => run_consumer_from(
+                &brokers,
+                &config.get_kafka_group(),
+                &config.topic,
+                &this.offsets,
+                Duration::from_secs(3),
+            ),
+            None => run_consumer(&brokers, &config.get_kafka_group(), &config.topic),
+        };
+        match consumer {
             Ok(consumer) => this.consumer = Some(consumer),
             Err(err) => tracing::error!("kafka source connect failed: {}", err),
         };
@@ -298,6 +499,18 @@ impl Kafka {
             consumer: None,
             producer: None,
             job_id_hash,
+            csv_decoder: None,
+            csv_encoder: config.get_csv_format().map(CsvEncoder::new),
+            avro_decoder: None,
+            avro_encoder: config.get_avro_format().map(AvroEncoder::new),
+            protobuf_decoder: None,
+            partitions: None,
+            round_robin: 0,
+            cancellation: Default::default(),
+            offset_store: None,
+            offsets: Default::default(),
+            fetched: Default::default(),
+            decode_failures: vec![],
         };
         match run_producer(
             config
@@ -318,11 +531,63 @@ impl Kafka {
         this
     }
 
-    fn process(&self, message: KafkaMessage) -> LocalEvent {
+    /// the rows which fail to be decoded are returned with the event, see [`Source::take_decode_failures`]
+    fn process(&self, message: KafkaMessage) -> (LocalEvent, Vec<DecodeFailure>) {
         let data_type = self.conf.data_type();
+        let mut failures = vec![];
+        let data = match (
+            &self.csv_decoder,
+            &self.avro_decoder,
+            &self.protobuf_decoder,
+        ) {
+            (Some(decoder), _, _) => self.decode_csv(decoder, &message, &mut failures),
+            (_, Some(decoder), _) => self.to_entries(
+                "avro",
+                decoder.decode_cached(&message.payload),
+                &mut failures,
+            ),
+            (_, _, Some(decoder)) => {
+                self.to_entries("protobuf", decoder.decode(&message.payload), &mut failures)
+            }
+            _ => {
+                let val = TypedValue::from_slice_with_type(&message.payload, data_type);
+                vec![Entry {
+                    data_type: data_type as i32,
+                    value: val.get_data_bytes(),
+                }]
+            }
+        };
+
+        (self.new_event(message, data), failures)
+    }
+
+    /// The writer schema of an Avro record will be fetched from schema registry if it's not cached.
+    async fn process_avro(
+        &self,
+        decoder: &AvroDecoder,
+        message: KafkaMessage,
+    ) -> (LocalEvent, Vec<DecodeFailure>) {
+        let mut failures = vec![];
+        let data = self.to_entries(
+            "avro",
+            decoder.decode(&message.payload).await,
+            &mut failures,
+        );
+        (self.new_event(message, data), failures)
+    }
+
+    /// the headers of the message are kept by the event, e.g. the trace id set by the producer
+    fn new_event(&self, message: KafkaMessage, data: Vec<Entry>) -> LocalEvent {
         let key = TypedValue::from_slice(&message.key);
-        let val = TypedValue::from_slice_with_type(&message.payload, data_type);
         let event_id = self.generate_new_event_id();
+        let mut headers = HashMap::from_iter(message.headers);
+        if let Some(partition) = message.partition {
+            headers.insert(SOURCE_PARTITION_HEADER.to_string(), partition.to_string());
+        }
+        if let Some(offset) = message.offset {
+            headers.insert(SOURCE_OFFSET_HEADER.to_string(), offset.to_string());
+        }
+        headers.insert(INGEST_TIME_HEADER.to_string(), now_timestamp().to_string());
 
         let result = LocalEvent::KeyedDataStreamEvent(KeyedDataEvent {
             job_id: Some(self.job_id.clone()),
@@ -331,20 +596,287 @@ impl Kafka {
                 value: key.get_data_bytes(),
             }),
             to_operator_id: 0,
-            data: vec![Entry {
-                data_type: self.conf.data_type() as i32,
-                value: val.get_data_bytes(),
-            }],
-
+            data,
             event_time: message.timestamp.unwrap_or_else(|| now_timestamp()),
             from_operator_id: self.connector_id,
             window: None,
             event_id,
+            broadcast: false,
+            sequence: 0,
+            sequence_epoch: 0,
+            restart_epoch: 0,
+            headers,
         });
 
         result
     }
 
+    /// the fetched event is tracked until it's acked, see [`Source::commit`]
+    pub(crate) fn track(&mut self, event: LocalEvent) -> LocalEvent {
+        let position = match &event {
+            LocalEvent::KeyedDataStreamEvent(event) if self.offset_store.is_some() => {
+                get_source_position(event)
+            }
+            _ => None,
+        };
+        if let Some((partition, offset)) = position {
+            if offset >= self.offsets.get(&partition).copied().unwrap_or_default() {
+                self.fetched
+                    .entry(partition)
+                    .or_default()
+                    .insert(offset, false);
+            }
+        }
+        event
+    }
+
+    /// each row of a CSV payload will be an entry of the event. Rows which fail to be decoded are left out of the event,
+    /// and they're handled by the error policy of the source operator
+    fn decode_csv(
+        &self,
+        decoder: &CsvDecoder,
+        message: &KafkaMessage,
+        failures: &mut Vec<DecodeFailure>,
+    ) -> Vec<Entry> {
+        decoder
+            .decode(message.payload.as_ref())
+            .filter_map(|row| match row {
+                Ok(val) => Some(Entry {
+                    data_type: val.get_type() as i32,
+                    value: val.get_data_bytes(),
+                }),
+                Err(err) => {
+                    failures.push(DecodeFailure {
+                        format: "csv",
+                        topic: self.conf.topic.clone(),
+                        row: err.row(),
+                        message: err.to_string(),
+                    });
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// a record which fails to be decoded is handled by the error policy of the source operator,
+    /// so schema evolution or registry outage fails the task only if the policy says so
+    fn to_entries<E: Display>(
+        &self,
+        format: &'static str,
+        result: Result<TypedValue, E>,
+        failures: &mut Vec<DecodeFailure>,
+    ) -> Vec<Entry> {
+        match result {
+            Ok(val) => vec![Entry {
+                data_type: val.get_type() as i32,
+                value: val.get_data_bytes(),
+            }],
+            Err(err) => {
+                failures.push(DecodeFailure {
+                    format,
+                    topic: self.conf.topic.clone(),
+                    row: 1,
+                    message: err.to_string(),
+                });
+                vec![]
+            }
+        }
+    }
+
+    /// If csv format is configured, all entries of an event will be encoded as rows of one message.
+    /// If avro format is configured, each entry of an event will be encoded as one message.
+    async fn to_kafka_message(
+        &self,
+        event: &LocalEvent,
+    ) -> Result<Vec<KafkaMessage>, SinkException> {
+        match (&self.csv_encoder, &self.avro_encoder, event) {
+            (Some(encoder), _, LocalEvent::KeyedDataStreamEvent(e)) => {
+                let key = TypedValue::from_slice(&e.get_key().value).to_json_value();
+                let rows = e
+                    .data
+                    .iter()
+                    .map(|entry| TypedValue::from_slice(&entry.value))
+                    .collect::<Vec<_>>();
+                let payload = encoder.encode(&rows)?;
+                let key = serde_json::to_vec(&key).map_err(|err| SinkException {
+                    kind: ErrorKind::CsvEncodeFailed,
+                    msg: err.to_string(),
+                })?;
+
+                Ok(vec![KafkaMessage {
+                    key: bytes::Bytes::from(key),
+                    payload: bytes::Bytes::from(payload),
+                    timestamp: Some(now_timestamp()),
+                    partition: None,
+                    offset: None,
+                    headers: vec![],
+                }])
+            }
+            (_, Some(encoder), LocalEvent::KeyedDataStreamEvent(e)) => {
+                let key = TypedValue::from_slice(&e.get_key().value).to_json_value();
+                let key = serde_json::to_vec(&key).map_err(|err| SinkException {
+                    kind: ErrorKind::AvroEncodeFailed,
+                    msg: err.to_string(),
+                })?;
+                let timestamp = now_timestamp();
+                let mut messages = vec![];
+                for entry in &e.data {
+                    let payload = encoder
+                        .encode(&TypedValue::from_slice(&entry.value))
+                        .await?;
+                    messages.push(KafkaMessage {
+                        key: bytes::Bytes::copy_from_slice(&key),
+                        payload: bytes::Bytes::from(payload),
+                        timestamp: Some(timestamp),
+                        partition: None,
+                        offset: None,
+                        headers: vec![],
+                    })
+                }
+
+                Ok(messages)
+            }
+            _ => event.to_kafka_message().map_err(|err| err.into()),
+        }
+    }
+
+    /// build the records of an event by the sink options.
+    /// The payload fields of the options are looked up in the entry of each record. For CSV format, they are looked up in the first row
+    async fn build_kafka_records(
+        &mut self,
+        event: &LocalEvent,
+    ) -> Result<Vec<KafkaRecord>, SinkException> {
+        let messages = self.to_kafka_message(event).await?;
+        let e = match event {
+            LocalEvent::KeyedDataStreamEvent(e) => e,
+            LocalEvent::Terminate { .. } => return Ok(vec![]),
+        };
+        let opts = self.conf.sink_opts.clone().unwrap_or_default();
+        let invalid_record = |msg: String| SinkException {
+            kind: ErrorKind::InvalidRecord,
+            msg,
+        };
+
+        let mut records = vec![];
+        for (index, message) in messages.into_iter().enumerate() {
+            let value = || {
+                e.data
+                    .get(index)
+                    .or_else(|| e.data.first())
+                    .map(|entry| TypedValue::from_slice(&entry.value).to_json_value())
+                    .unwrap_or_default()
+            };
+            let key = if opts.key_field.is_empty() {
+                message.key
+            } else {
+                match value().get(&opts.key_field) {
+                    Some(key) => {
+                        serde_json::to_vec(key)
+                            .map(bytes::Bytes::from)
+                            .map_err(|err| SinkException {
+                                kind: ErrorKind::JsonEncodeFailed,
+                                msg: err.to_string(),
+                            })?
+                    }
+                    None => {
+                        return Err(invalid_record(format!(
+                            "key field [{}] is missing",
+                            &opts.key_field
+                        )))
+                    }
+                }
+            };
+            let partition = match opts.partitioner() {
+                Partitioner::Default => None,
+                Partitioner::KeyHash => Some(key_hash_partition(&key, self.get_partitions()?)),
+                Partitioner::RoundRobin => {
+                    let partitions = self.get_partitions()?;
+                    let partition = (self.round_robin % partitions as u32) as i32;
+                    self.round_robin = self.round_robin.wrapping_add(1);
+                    Some(partition)
+                }
+                Partitioner::Field => match value()
+                    .get(&opts.partition_field)
+                    .and_then(|partition| partition.as_u64())
+                    .and_then(|partition| i32::try_from(partition).ok())
+                {
+                    Some(partition) => Some(partition),
+                    None => {
+                        return Err(invalid_record(format!(
+                            "partition field [{}] is missing or not a partition",
+                            &opts.partition_field
+                        )))
+                    }
+                },
+            };
+            let mut headers = if opts.provenance_headers {
+                let job_id = e.job_id.clone().unwrap_or_default();
+                vec![
+                    (
+                        KAFKA_JOB_ID_HEADER.to_string(),
+                        format!("{}/{}", job_id.namespace_id, job_id.resource_id).into_bytes(),
+                    ),
+                    (
+                        KAFKA_OPERATOR_ID_HEADER.to_string(),
+                        e.from_operator_id.to_string().into_bytes(),
+                    ),
+                    (
+                        KAFKA_EVENT_ID_HEADER.to_string(),
+                        e.event_id.to_string().into_bytes(),
+                    ),
+                ]
+            } else {
+                vec![]
+            };
+            headers.extend(
+                e.get_sorted_headers()
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec())),
+            );
+            let payload = if opts.headers_field.is_empty() || e.headers.is_empty() {
+                message.payload
+            } else {
+                add_headers_field(message.payload, &opts.headers_field, e)?
+            };
+
+            records.push(KafkaRecord {
+                key,
+                payload,
+                partition,
+                timestamp: if opts.event_time_timestamp {
+                    Some(e.event_time)
+                } else {
+                    message.timestamp
+                },
+                headers,
+            })
+        }
+
+        Ok(records)
+    }
+
+    /// enqueue a record to the producer. Records with empty payload are skipped
+    fn enqueue(&self, record: &KafkaRecord) -> Result<Option<KafkaDelivery>, SinkException> {
+        match &self.producer {
+            Some(producer) if !record.payload.is_empty() => {
+                producer.enqueue(record).map(Some).map_err(|err| err.into())
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn get_partitions(&mut self) -> Result<i32, SinkException> {
+        match (self.partitions, &self.producer) {
+            (Some(partitions), _) => Ok(partitions),
+            (None, Some(producer)) => {
+                let partitions = producer.get_partition_count(Duration::from_secs(3))?;
+                self.partitions = Some(partitions);
+                Ok(partitions)
+            }
+            (None, None) => Ok(1),
+        }
+    }
+
     fn generate_new_event_id(&self) -> i64 {
         const EPOCH: i64 = 1640966400;
 
@@ -354,6 +886,41 @@ impl Kafka {
     }
 }
 
+/// add the headers of the event to the JSON payload as an object. The payload is kept if it's not a JSON object, e.g. a CSV payload
+fn add_headers_field(
+    payload: bytes::Bytes,
+    field: &str,
+    event: &KeyedDataEvent,
+) -> Result<bytes::Bytes, SinkException> {
+    let mut value = match serde_json::from_slice::<serde_json::Value>(&payload) {
+        Ok(serde_json::Value::Object(value)) => value,
+        _ => return Ok(payload),
+    };
+    value.insert(
+        field.to_string(),
+        serde_json::Value::Object(
+            event
+                .get_sorted_headers()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), serde_json::Value::from(value)))
+                .collect(),
+        ),
+    );
+    serde_json::to_vec(&value)
+        .map(bytes::Bytes::from)
+        .map_err(|err| SinkException {
+            kind: ErrorKind::JsonEncodeFailed,
+            msg: err.to_string(),
+        })
+}
+
+/// the partition and the offset of an event fetched by a source, which are kept in its headers. See [`Source::commit`]
+pub fn get_source_position(event: &KeyedDataEvent) -> Option<(i32, i64)> {
+    let partition = event.get_header(SOURCE_PARTITION_HEADER)?.parse().ok()?;
+    let offset = event.get_header(SOURCE_OFFSET_HEADER)?.parse().ok()?;
+    Some((partition, offset))
+}
+
 #[async_trait]
 impl Source for Kafka {
     fn source_id(&self) -> SourceId {
@@ -371,18 +938,74 @@ impl Source for Kafka {
     }
 
     async fn next(&mut self) -> Option<LocalEvent> {
-        match &self.consumer {
-            Some(consumer) => consumer.fetch(|message| self.process(message)).await,
-            None => None,
-        }
+        let message = {
+            let consumer = self.consumer.as_ref()?;
+            let fetch = consumer.fetch(|message| message);
+            let cancelled = self.cancellation.cancelled();
+            futures_util::pin_mut!(fetch, cancelled);
+            match futures_util::future::select(fetch, cancelled).await {
+                Either::Left((message, _)) => message?,
+                Either::Right(_) => return None,
+            }
+        };
+        let (event, failures) = match &self.avro_decoder {
+            Some(decoder) => self.process_avro(decoder, message).await,
+            None => self.process(message),
+        };
+        self.decode_failures = failures;
+        Some(self.track(event))
     }
 
     fn poll_next(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Option<LocalEvent>> {
-        Poll::Ready(
-            self.consumer
-                .as_ref()
-                .and_then(|consumer| consumer.blocking_fetch(|message| self.process(message))),
-        )
+        let fetched = self.consumer.as_ref().and_then(|consumer| {
+            consumer.blocking_fetch(&self.cancellation, |message| self.process(message))
+        });
+        Poll::Ready(fetched.map(|(event, failures)| {
+            self.decode_failures = failures;
+            self.track(event)
+        }))
+    }
+
+    fn take_decode_failures(&mut self) -> Vec<DecodeFailure> {
+        std::mem::take(&mut self.decode_failures)
+    }
+
+    /// all offsets of the source are committed at once. The offset of a partition only advances over the events which have been acked
+    /// since the first one which isn't, so an event acked before the ones fetched earlier doesn't skip them.
+    /// The events which are not fetched by this source, e.g. the replayed ones, don't move the offsets
+    fn commit(&mut self, partition: i32, offset: i64) {
+        let store = match &self.offset_store {
+            Some(store) => store,
+            None => return,
+        };
+        let fetched = match self.fetched.get_mut(&partition) {
+            Some(fetched) => fetched,
+            None => return,
+        };
+        match fetched.get_mut(&offset) {
+            Some(acked) => *acked = true,
+            None => return,
+        }
+        let mut next = None;
+        while let Some(entry) = fetched.first_entry() {
+            if !*entry.get() {
+                break;
+            }
+            next = Some(*entry.key() + 1);
+            entry.remove();
+        }
+        let next = match next {
+            Some(next) => next,
+            None => return,
+        };
+        self.offsets.insert(partition, next);
+        if let Err(err) = store.lock().unwrap_or_else(|err| err.into_inner()).commit(
+            &self.job_id,
+            self.connector_id,
+            &self.offsets,
+        ) {
+            tracing::error!("kafka source commit offsets failed: {}", err)
+        }
     }
 }
 
@@ -393,25 +1016,18 @@ impl Sink for Kafka {
     }
 
     async fn sink(&mut self, msg: LocalEvent) -> Result<(), SinkException> {
-        match &self.producer {
-            Some(producer) => {
-                let result = msg.to_kafka_message();
-                match result.map_err(|err| err.into()) {
-                    Ok(messages) => {
-                        for msg in messages {
-                            let send_result = producer.send(&msg.key, &msg.payload).await;
-                            if send_result.is_err() {
-                                return send_result.map_err(|err| err.into());
-                            }
-                        }
-
-                        Ok(())
-                    }
-                    Err(err) => Err(err),
-                }
-            }
-            None => Ok(()),
+        if self.producer.is_none() {
+            return Ok(());
+        }
+        let mut deliveries = vec![];
+        for record in self.build_kafka_records(&msg).await? {
+            deliveries.extend(self.enqueue(&record)?);
         }
+        for delivery in deliveries {
+            delivery.wait().await?;
+        }
+
+        Ok(())
     }
 
     fn close_sink(&mut self) {
@@ -423,45 +1039,61 @@ impl Sink for Kafka {
             .for_each(|producer| producer.close())
     }
 
-    async fn batch_sink(&mut self, event_set: KeyedEventSet) -> Result<(), BatchSinkException> {
+    fn flush_sink(&mut self) -> Result<(), SinkException> {
         match &self.producer {
-            Some(producer) => {
-                for event in event_set
-                    .events
-                    .into_iter()
-                    .map(|event| LocalEvent::KeyedDataStreamEvent(event))
-                {
-                    let kafka_msg = event.to_kafka_message();
-                    match kafka_msg {
-                        Ok(messages) => {
-                            for msg in messages {
-                                match producer.send(&msg.key, &msg.payload).await {
-                                    Err(err) => {
-                                        tracing::error!(
-                                            "sink [{:?}] to kafka failed: {}",
-                                            &msg,
-                                            err
-                                        )
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
-                        Err(err) => {
-                            tracing::error!(
-                                "LocalEvent {:?} to KafkaMessage failed: {:?}",
-                                &event,
-                                err
-                            )
-                        }
-                    }
-                }
+            Some(producer) => producer
+                .flush(Duration::from_secs(3))
+                .map_err(|err| err.into()),
+            None => Ok(()),
+        }
+    }
 
-                Ok(())
+    async fn batch_sink(&mut self, event_set: KeyedEventSet) -> Result<(), BatchSinkException> {
+        if self.producer.is_none() {
+            return Ok(());
+        }
+        // records of all events are enqueued before their delivery reports are waited.
+        // The failure is handled by the error policy of the operator
+        let mut deliveries = vec![];
+        for event in event_set.events {
+            let event_id = event.event_id as u64;
+            let result = self
+                .build_kafka_records(&LocalEvent::KeyedDataStreamEvent(event))
+                .await
+                .and_then(|records| {
+                    records
+                        .iter()
+                        .map(|record| self.enqueue(record))
+                        .collect::<Result<Vec<_>, _>>()
+                });
+            match result {
+                Ok(enqueued) => deliveries.extend(
+                    enqueued
+                        .into_iter()
+                        .flatten()
+                        .map(|delivery| (event_id, delivery)),
+                ),
+                Err(err) => {
+                    // the failure of a former event is reported first
+                    wait_deliveries(deliveries).await?;
+                    return Err(BatchSinkException { err, event_id });
+                }
             }
-            None => Ok(()),
         }
+
+        wait_deliveries(deliveries).await
+    }
+}
+
+/// wait for the delivery reports of the records in order. The event of the first failed record is reported
+async fn wait_deliveries(deliveries: Vec<(u64, KafkaDelivery)>) -> Result<(), BatchSinkException> {
+    for (event_id, delivery) in deliveries {
+        delivery.wait().await.map_err(|err| BatchSinkException {
+            err: err.into(),
+            event_id,
+        })?;
     }
+    Ok(())
 }
 
 /// An unified implementation for Mysql Source and Sink
@@ -506,56 +1138,36 @@ impl Mysql {
     }
 }
 
+/// the statement is executed once per row in order, so the rows of the events before the failed one are written
 #[async_trait]
-impl Sink for Mysql {
+impl BatchFlush for Mysql {
     fn sink_id(&self) -> SinkId {
         self.connector_id
     }
 
-    async fn sink(&mut self, msg: LocalEvent) -> Result<(), SinkException> {
-        let row_arguments = self.get_arguments(&msg);
-        for arguments in row_arguments {
-            let result = self
-                .conn
-                .execute(&self.statement, arguments)
-                .await
-                .map_err(|err| err.into());
-            if result.is_err() {
-                return result.map(|_| {});
+    async fn flush(&mut self, batch: &[KeyedDataEvent]) -> Result<(), BatchSinkException> {
+        for event in batch {
+            let event_id = event.event_id as u64;
+            for arguments in self.get_arguments(&LocalEvent::KeyedDataStreamEvent(event.clone())) {
+                self.conn
+                    .execute(&self.statement, arguments)
+                    .await
+                    .map_err(|err| BatchSinkException {
+                        err: err.into(),
+                        event_id,
+                    })?;
             }
         }
 
         Ok(())
     }
 
-    fn close_sink(&mut self) {
+    fn close(&mut self) {
         self.conn.close();
         self.extractors.clear();
         drop(self.connector_id);
         self.statement.clear();
     }
-
-    async fn batch_sink(&mut self, event_set: KeyedEventSet) -> Result<(), BatchSinkException> {
-        let row_arguments = event_set
-            .events
-            .into_iter()
-            .map(|event| LocalEvent::KeyedDataStreamEvent(event))
-            .map(|event| self.get_arguments(&event))
-            .flatten()
-            .flatten()
-            .collect::<Vec<_>>();
-
-        self.conn
-            .execute(&self.statement, row_arguments)
-            .await
-            .map(|_| {})
-            .map_err(|err| {
-                tracing::error!("execute mysql statement failed: {}", err);
-                BatchSinkException::from(err)
-            })?;
-
-        Ok(())
-    }
 }
 
 /// An unified implement for Redis Source and Sink
@@ -588,40 +1200,24 @@ impl Redis {
     }
 }
 const REDIS_EXTRACTOR_FUN_NAME: &str = "redis_extractor";
+/// the values of a batch are set by one command, only the last value of each key in event time is set
 #[async_trait]
-impl Sink for Redis {
+impl BatchFlush for Redis {
     fn sink_id(&self) -> SinkId {
         self.connector_id
     }
 
-    async fn sink(&mut self, msg: LocalEvent) -> Result<(), SinkException> {
-        let key_values = extract_arguments(
-            &[self.key_extractor.clone(), self.value_extractor.clone()],
-            &msg,
-            REDIS_EXTRACTOR_FUN_NAME,
-        );
-        let kvs = Vec::from_iter(key_values.iter().map(|kv| (&kv[0], &kv[1])));
-        self.client
-            .set_multiple(kvs.as_slice())
-            .map_err(|err| err.into())
-    }
-
-    fn close_sink(&mut self) {
-        drop(self.connector_id);
-        self.key_extractor.clear();
-        self.value_extractor.clear();
-    }
-
-    async fn batch_sink(&mut self, mut event_set: KeyedEventSet) -> Result<(), BatchSinkException> {
+    async fn flush(&mut self, batch: &[KeyedDataEvent]) -> Result<(), BatchSinkException> {
         let mut kv_set = BTreeMap::new();
-        event_set.events.sort_by_key(|event| event.event_time);
+        let mut events = batch.iter().collect::<Vec<_>>();
+        events.sort_by_key(|event| event.event_time);
         let isolate = &mut v8::Isolate::new(Default::default());
         let scope = &mut v8::HandleScope::new(isolate);
 
-        event_set.events.into_iter().for_each(|event| {
+        events.into_iter().for_each(|event| {
             extract_arguments_scope(
                 &[self.key_extractor.clone(), self.value_extractor.clone()],
-                &LocalEvent::KeyedDataStreamEvent(event),
+                &LocalEvent::KeyedDataStreamEvent(event.clone()),
                 REDIS_EXTRACTOR_FUN_NAME,
                 scope,
             )
@@ -636,6 +1232,12 @@ impl Sink for Redis {
             .set_multiple(kv_set.iter().collect::<Vec<_>>().as_slice())
             .map_err(|err| err.into())
     }
+
+    fn close(&mut self) {
+        drop(self.connector_id);
+        self.key_extractor.clear();
+        self.value_extractor.clear();
+    }
 }
 
 fn extract_arguments_scope(
@@ -687,7 +1289,7 @@ mod tests {
         mysql_desc, redis_desc, Entry, Func, KafkaDesc, MysqlDesc, RedisDesc, ResourceId,
     };
 
-    use crate::{new_event_channel, MOD_TEST_START};
+    use crate::{batching::BatchingSink, new_event_channel, MOD_TEST_START};
 
     use super::{Sink, SinkImpl, Source, SourceImpl};
 
@@ -774,15 +1376,14 @@ mod tests {
 
     #[tokio::test]
     async fn test_kafka_source_sink_close() {
-        let job_id = ResourceId {
-            resource_id: "resource_id".to_string(),
-            namespace_id: "ns_id".to_string(),
-        };
+        let job_id = ResourceId::new("ns_id", "resource_id");
         let desc = KafkaDesc {
             brokers: vec!["localhost:9092".to_string()],
             topic: "topic".to_string(),
             opts: None,
             data_type: 6,
+            format: None,
+            sink_opts: None,
         };
         let (tx, rx) = new_event_channel(1);
         let mut kafka_source = SourceImpl::Kafka(
@@ -805,6 +1406,8 @@ mod tests {
                         topic: Default::default(),
                         opts: None,
                         data_type: 0,
+                        format: None,
+                        sink_opts: None,
                     }
                 );
                 assert!(tx.is_closed());
@@ -826,6 +1429,8 @@ mod tests {
                         topic: Default::default(),
                         opts: None,
                         data_type: 0,
+                        format: None,
+                        sink_opts: None,
                     }
                 );
             }
@@ -833,6 +1438,257 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_kafka_event_headers() {
+        use common::{event::LocalEvent, kafka::KafkaMessage, types::TypedValue};
+        use proto::common::kafka_desc;
+
+        use super::{INGEST_TIME_HEADER, SOURCE_OFFSET_HEADER, SOURCE_PARTITION_HEADER};
+
+        let job_id = ResourceId::default();
+        let mut desc = KafkaDesc {
+            brokers: vec!["localhost:9092".to_string()],
+            topic: "topic".to_string(),
+            opts: None,
+            data_type: 6,
+            format: None,
+            sink_opts: None,
+        };
+        let source = super::Kafka::with_source_config(&job_id, 0, &desc);
+        let value = TypedValue::from_json_value(serde_json::json!({"id": 1}));
+        let mut event = match source.new_event(
+            KafkaMessage {
+                key: Default::default(),
+                payload: Default::default(),
+                timestamp: Some(1000),
+                partition: Some(3),
+                offset: Some(42),
+                headers: vec![("trace_id".to_string(), "abc".to_string())],
+            },
+            vec![Entry {
+                data_type: value.get_type() as i32,
+                value: value.get_data_bytes(),
+            }],
+        ) {
+            LocalEvent::KeyedDataStreamEvent(event) => event,
+            other => panic!("unexpected event {:?}", other),
+        };
+        // the headers of the message are kept, and the source adds its partition, its offset and the ingest time
+        assert_eq!(event.get_header("trace_id"), Some("abc"));
+        assert_eq!(event.get_header(SOURCE_PARTITION_HEADER), Some("3"));
+        assert_eq!(event.get_header(SOURCE_OFFSET_HEADER), Some("42"));
+        assert!(event.get_header(INGEST_TIME_HEADER).is_some());
+
+        event.headers.remove(INGEST_TIME_HEADER);
+        desc.sink_opts = Some(kafka_desc::KafkaSinkOptions {
+            headers_field: "_headers".to_string(),
+            ..Default::default()
+        });
+        let mut sink = super::Kafka::with_sink_config(&job_id, 1, &desc);
+        let records = sink
+            .build_kafka_records(&LocalEvent::KeyedDataStreamEvent(event))
+            .await
+            .expect("msg");
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].headers,
+            vec![
+                (SOURCE_OFFSET_HEADER.to_string(), b"42".to_vec()),
+                (SOURCE_PARTITION_HEADER.to_string(), b"3".to_vec()),
+                ("trace_id".to_string(), b"abc".to_vec()),
+            ]
+        );
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&records[0].payload).expect("msg"),
+            serde_json::json!({
+                "id": 1,
+                "_headers": {
+                    "lightflus.source_offset": "42",
+                    "lightflus.source_partition": "3",
+                    "trace_id": "abc",
+                },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kafka_source_commit_offsets() {
+        use common::{event::LocalEvent, kafka::KafkaMessage};
+
+        use super::get_source_position;
+        use crate::offsets::{OffsetStoreBuilder, SourceOffsets};
+
+        let job_id = ResourceId::new("ns_id", "resource_id");
+        let desc = KafkaDesc {
+            brokers: vec!["localhost:9092".to_string()],
+            topic: "topic".to_string(),
+            opts: None,
+            data_type: 6,
+            format: None,
+            sink_opts: None,
+        };
+        let store = OffsetStoreBuilder::Memory.build_shared();
+        let mut source = super::Kafka::with_offset_store(&job_id, 0, &desc, Some(store.clone()));
+        assert!(source.offsets.is_empty());
+        let fetch = |source: &mut super::Kafka, partition: i32, offset: i64| {
+            let event = source.new_event(
+                KafkaMessage {
+                    key: Default::default(),
+                    payload: Default::default(),
+                    timestamp: None,
+                    partition: Some(partition),
+                    offset: Some(offset),
+                    headers: vec![],
+                },
+                vec![],
+            );
+            match source.track(event) {
+                LocalEvent::KeyedDataStreamEvent(event) => {
+                    get_source_position(&event).expect("msg")
+                }
+                other => panic!("unexpected event {:?}", other),
+            }
+        };
+        let positions = [(0, 5), (1, 2), (0, 6), (0, 7)]
+            .map(|(partition, offset)| fetch(&mut source, partition, offset));
+        let load = || store.lock().unwrap().load(&job_id, 0);
+
+        // the event acked before the one fetched earlier doesn't move the offset of the partition
+        let (partition, offset) = positions[2];
+        source.commit(partition, offset);
+        assert_eq!(load(), None);
+        let (partition, offset) = positions[1];
+        source.commit(partition, offset);
+        assert_eq!(load(), Some(SourceOffsets::from_iter([(1, 3)])));
+        // the offset advances over all the acked events once the earlier one is acked
+        let (partition, offset) = positions[0];
+        source.commit(partition, offset);
+        assert_eq!(load(), Some(SourceOffsets::from_iter([(0, 7), (1, 3)])));
+        let (partition, offset) = positions[3];
+        source.commit(partition, offset);
+        // the replayed event at offset 3 and the ack of an event which isn't fetched don't move the offsets
+        source.commit(0, 3);
+        source.commit(0, 9);
+        let committed = SourceOffsets::from_iter([(0, 8), (1, 3)]);
+        assert_eq!(load(), Some(committed.clone()));
+
+        // the source resumes from the committed offsets after it restarts
+        source.close_source().await;
+        let restarted = super::Kafka::with_offset_store(&job_id, 0, &desc, Some(store.clone()));
+        assert_eq!(restarted.offsets, committed);
+
+        // the offsets of a source without offset store are committed to the group
+        let mut source = super::Kafka::with_source_config(&job_id, 1, &desc);
+        source.commit(0, 1);
+        assert!(source.offsets.is_empty());
+        assert_eq!(store.lock().unwrap().load(&job_id, 1), None);
+    }
+
+    #[tokio::test]
+    async fn test_kafka_source_decode_failures() {
+        use common::{event::LocalEvent, kafka::KafkaMessage};
+        use prost::Message;
+        use prost_types::{
+            field_descriptor_proto::{Label, Type},
+            DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+        };
+        use proto::common::{
+            csv_format, kafka_desc, AvroFormat, CsvFormat, DataTypeEnum, ProtobufFormat,
+        };
+
+        let job_id = ResourceId::new("ns_id", "resource_id");
+        let new_source = |format: kafka_desc::Format| {
+            super::Kafka::with_source_config(
+                &job_id,
+                0,
+                &KafkaDesc {
+                    brokers: vec!["localhost:9092".to_string()],
+                    topic: "topic".to_string(),
+                    opts: None,
+                    data_type: 6,
+                    format: Some(format),
+                    sink_opts: None,
+                },
+            )
+        };
+        let new_message = |payload: &[u8]| KafkaMessage {
+            key: Default::default(),
+            payload: payload.to_vec().into(),
+            timestamp: None,
+            partition: Some(0),
+            offset: Some(1),
+            headers: vec![],
+        };
+        let data_len = |event: &LocalEvent| match event {
+            LocalEvent::KeyedDataStreamEvent(event) => event.data.len(),
+            other => panic!("unexpected event {:?}", other),
+        };
+
+        // the rows which fail to be decoded are left out of the event, and they're reported with their row numbers
+        let source = new_source(kafka_desc::Format::Csv(CsvFormat {
+            columns: vec![csv_format::Column {
+                name: "id".to_string(),
+                index: 0,
+                data_type: DataTypeEnum::Bigint as i32,
+            }],
+            ..Default::default()
+        }));
+        let (event, failures) = source.process(new_message(b"1\nx\n3\ny\n"));
+        assert_eq!(data_len(&event), 2);
+        assert_eq!(
+            failures
+                .iter()
+                .map(|failure| (failure.format, failure.row))
+                .collect::<Vec<_>>(),
+            vec![("csv", 2), ("csv", 4)]
+        );
+        assert!(failures[0]
+            .to_string()
+            .starts_with("decode csv of topic [topic] failed at row [2]"));
+
+        let source = new_source(kafka_desc::Format::Avro(AvroFormat::default()));
+        let (event, failures) = source.process(new_message(b"not avro"));
+        assert_eq!(data_len(&event), 0);
+        assert_eq!(failures.len(), 1);
+        assert_eq!((failures[0].format, failures[0].row), ("avro", 1));
+
+        let file_descriptor_set = FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("row.proto".to_string()),
+                package: Some("test".to_string()),
+                syntax: Some("proto3".to_string()),
+                message_type: vec![DescriptorProto {
+                    name: Some("Row".to_string()),
+                    field: vec![FieldDescriptorProto {
+                        name: Some("id".to_string()),
+                        number: Some(1),
+                        label: Some(Label::Optional as i32),
+                        r#type: Some(Type::String as i32),
+                        json_name: Some("id".to_string()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+        let source = new_source(kafka_desc::Format::Protobuf(ProtobufFormat {
+            file_descriptor_set: file_descriptor_set.encode_to_vec(),
+            message_name: "test.Row".to_string(),
+            preserve_unknown_fields: false,
+        }));
+        let (event, failures) = source.process(new_message(&[0xff, 0xff]));
+        assert_eq!(data_len(&event), 0);
+        assert_eq!(failures.len(), 1);
+        assert_eq!((failures[0].format, failures[0].row), ("protobuf", 1));
+
+        // the failures of the last fetched message are taken once
+        let mut source = source;
+        source.decode_failures = failures;
+        assert_eq!(source.take_decode_failures().len(), 1);
+        assert!(source.take_decode_failures().is_empty());
+    }
+
     #[test]
     fn test_redis_source_sink_close() {
         let desc = RedisDesc {
@@ -850,13 +1706,14 @@ mod tests {
                 function: "value_extractor".to_string(),
             }),
         };
-        let mut redis_sink = SinkImpl::Redis(super::Redis::with_config(0, &desc));
+        let mut redis_sink =
+            SinkImpl::Redis(BatchingSink::new(super::Redis::with_config(0, &desc), None));
 
         redis_sink.close_sink();
         match redis_sink {
             SinkImpl::Redis(redis) => {
-                assert_eq!(&redis.key_extractor, "");
-                assert_eq!(&redis.value_extractor, "");
+                assert_eq!(&redis.get_inner().key_extractor, "");
+                assert_eq!(&redis.get_inner().value_extractor, "");
             }
             _ => {}
         }
@@ -879,12 +1736,13 @@ mod tests {
                 }],
             }),
         };
-        let mut mysql_sink = SinkImpl::Mysql(super::Mysql::with_config(0, conf));
+        let mut mysql_sink =
+            SinkImpl::Mysql(BatchingSink::new(super::Mysql::with_config(0, conf), None));
         mysql_sink.close_sink();
         match mysql_sink {
             SinkImpl::Mysql(mysql) => {
-                assert!(mysql.extractors.is_empty());
-                assert!(mysql.statement.is_empty());
+                assert!(mysql.get_inner().extractors.is_empty());
+                assert!(mysql.get_inner().statement.is_empty());
             }
             _ => {}
         }
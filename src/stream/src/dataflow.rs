@@ -1,11 +1,27 @@
 use std::{cell::RefCell, collections::BTreeMap};
 
-use common::types::{ExecutorId, NodeIdx, TypedValue};
-
-use proto::common::{operator_info::Details, Entry, KeyedDataEvent};
+use common::{
+    project::{select_path, ProjectError, Projector},
+    types::{ExecutorId, NodeIdx, TypedValue},
+    utils::times::now_timestamp,
+};
+
+use prost::Message;
+use proto::{
+    common::{
+        keyed_data_event, operator_info::Details, sort_buffer::OverflowPolicy, window, Deduplicate,
+        Entry, EventDedup, KeyedDataEvent, Project, SortBuffer, Window,
+    },
+    common_impl::{DataflowValidateError, DedupKeyField},
+    json_path::JsonPath,
+};
 use v8::HandleScope;
 
-use crate::{err::ExecutionError, state, v8_runtime::RuntimeEngine};
+use crate::{
+    err::ExecutionError,
+    state::{self, BroadcastState},
+    v8_runtime::RuntimeEngine,
+};
 
 /// This is the execution context of an operator. Execution's lifecycle must be explict because one execution corresponds to one v8 instance.
 /// After execution is dropped, the v8 instance will be destroied at the same time.
@@ -68,6 +84,30 @@ where
                 )),
                 OperatorImpl::FlatMap(FlatMapOperator::new(executor_id, state_manager)),
             ),
+            Details::Project(project) => (
+                RefCell::new(RuntimeEngine::new("", "", scope)),
+                OperatorImpl::Project(ProjectOperator::new(executor_id, project)),
+            ),
+            Details::Deduplicate(deduplicate) => (
+                RefCell::new(RuntimeEngine::new("", "", scope)),
+                OperatorImpl::Deduplicate(DeduplicateOperator::new(
+                    executor_id,
+                    deduplicate,
+                    state_manager,
+                )),
+            ),
+            Details::Window(window) => (
+                RefCell::new(RuntimeEngine::new("", "", scope)),
+                OperatorImpl::Window(WindowOperator::new(executor_id, window, state_manager)),
+            ),
+            Details::SortBuffer(sort_buffer) => (
+                RefCell::new(RuntimeEngine::new("", "", scope)),
+                OperatorImpl::SortBuffer(SortBufferOperator::new(
+                    executor_id,
+                    sort_buffer,
+                    state_manager,
+                )),
+            ),
             _ => (
                 RefCell::new(RuntimeEngine::new("", "", scope)),
                 OperatorImpl::Empty(executor_id),
@@ -79,10 +119,7 @@ where
         }
     }
 
-    pub(crate) fn process(
-        &self,
-        event: &KeyedDataEvent,
-    ) -> Result<Vec<KeyedDataEvent>, ExecutionError> {
+    pub fn process(&self, event: &KeyedDataEvent) -> Result<Vec<KeyedDataEvent>, ExecutionError> {
         self.operator.process_event(event, &self.rt_engine)
     }
 }
@@ -114,6 +151,10 @@ pub(crate) enum OperatorImpl<S: state::StateManager> {
     KeyBy(KeyByOperator<S>),
     FlatMap(FlatMapOperator<S>),
     Reduce(ReduceOperator<S>),
+    Project(ProjectOperator),
+    Deduplicate(DeduplicateOperator<S>),
+    SortBuffer(SortBufferOperator<S>),
+    Window(WindowOperator<S>),
     Empty(NodeIdx),
 }
 
@@ -132,6 +173,10 @@ impl<S: state::StateManager> OperatorImpl<S> {
             Self::KeyBy(op) => op.call_fn(event, rt_engine),
             Self::FlatMap(op) => op.call_fn(event, rt_engine),
             Self::Reduce(op) => op.call_fn(event, rt_engine),
+            Self::Project(op) => op.call_fn(event, rt_engine),
+            Self::Deduplicate(op) => op.call_fn(event, rt_engine),
+            Self::SortBuffer(op) => op.call_fn(event, rt_engine),
+            Self::Window(op) => op.call_fn(event, rt_engine),
             Self::Empty(operator_id) => Err(ExecutionError::OperatorUnimplemented(*operator_id)),
         }
     }
@@ -147,6 +192,9 @@ impl<S: state::StateManager> IOperator for KeyByOperator<S> {
         'p: 'i,
     {
         let mut new_events = BTreeMap::<TypedValue, KeyedDataEvent>::new();
+        let broadcast = get_broadcast_value(&self.state_manager);
+        // the events of the new keys keep the metadata of the input
+        let headers = &event.headers;
 
         event
             .data
@@ -154,9 +202,7 @@ impl<S: state::StateManager> IOperator for KeyByOperator<S> {
             .map(|entry| TypedValue::from(entry))
             .map(|typed_val| {
                 (
-                    rt_engine
-                        .borrow_mut()
-                        .call_one_arg(&typed_val)
+                    call_udf(rt_engine, &typed_val, broadcast.as_ref())
                         .unwrap_or(TypedValue::Invalid),
                     typed_val,
                 )
@@ -177,6 +223,7 @@ impl<S: state::StateManager> IOperator for KeyByOperator<S> {
                     let mut event = KeyedDataEvent::default();
                     event.from_operator_id = self.operator_id;
                     event.key = Some(key_entry);
+                    event.headers = headers.clone();
                     new_events.insert(key.clone(), event);
                 }
 
@@ -252,14 +299,13 @@ impl<S: state::StateManager> IOperator for FilterOperator<S> {
     where
         'p: 'i,
     {
+        let broadcast = get_broadcast_value(&self.state_manager);
         let filtered = event
             .data
             .iter()
             .filter(|entry| {
                 let val = TypedValue::from_slice(&entry.value);
-                let result = rt_engine
-                    .borrow_mut()
-                    .call_one_arg(&val)
+                let result = call_udf(rt_engine, &val, broadcast.as_ref())
                     .unwrap_or(TypedValue::Boolean(false));
                 match result {
                     TypedValue::Boolean(flag) => flag,
@@ -283,12 +329,11 @@ impl<S: state::StateManager> IOperator for FlatMapOperator<S> {
     where
         'p: 'i,
     {
+        let broadcast = get_broadcast_value(&self.state_manager);
         let flat_map_value = event.data.iter().map(|entry| {
             let val = TypedValue::from_slice(&entry.value);
-            let result = rt_engine
-                .borrow_mut()
-                .call_one_arg(&val)
-                .unwrap_or(TypedValue::Array(vec![]));
+            let result =
+                call_udf(rt_engine, &val, broadcast.as_ref()).unwrap_or(TypedValue::Array(vec![]));
 
             match result {
                 TypedValue::Array(v) => v,
@@ -316,204 +361,1105 @@ impl<S: state::StateManager> IOperator for FlatMapOperator<S> {
     }
 }
 
-macro_rules! define_operator {
-    ($name: ident) => {
-        pub(crate) struct $name<S>
-        where
-            S: state::StateManager,
-        {
-            state_manager: S,
-            operator_id: NodeIdx,
-        }
-    };
+/// call the UDF with the payload. The broadcast state is passed as the second argument if it's not empty
+fn call_udf<'p, 'i>(
+    rt_engine: &RefCell<RuntimeEngine<'p, 'i>>,
+    val: &TypedValue,
+    broadcast: Option<&TypedValue>,
+) -> Option<TypedValue>
+where
+    'p: 'i,
+{
+    match broadcast {
+        Some(broadcast) => rt_engine.borrow_mut().call_two_args((val, broadcast)),
+        None => rt_engine.borrow_mut().call_one_arg(val),
+    }
 }
 
-macro_rules! new_operator {
-    ($name:ident) => {
-        impl<S> $name<S>
-        where
-            S: state::StateManager,
-        {
-            pub(crate) fn new(operator_id: ExecutorId, state_manager: S) -> Self {
-                $name {
-                    state_manager,
-                    operator_id,
-                }
-            }
+/// the broadcast state as an object whose fields are the keys of the broadcast state. It's `None` if the broadcast state is empty
+fn get_broadcast_value<S: state::StateManager>(state_manager: &S) -> Option<TypedValue> {
+    let entries = BroadcastState::new(state_manager).list();
+    if entries.is_empty() {
+        return None;
+    }
+
+    Some(TypedValue::Object(
+        entries
+            .iter()
+            .filter_map(|(key, value)| {
+                let key = Entry::decode(key.as_slice()).ok()?;
+                let value = Entry::decode(value.as_slice()).ok()?;
+                let key = match TypedValue::from(&key) {
+                    TypedValue::String(key) => key,
+                    key => key.to_json_value().to_string(),
+                };
+                Some((key, TypedValue::from(&value)))
+            })
+            .collect(),
+    ))
+}
+
+/// [`ProjectOperator`] projects the payloads natively by [`Projector`] without calling any UDF.
+/// If the key field is configured, events will be grouped by the re-derived keys.
+pub(crate) struct ProjectOperator {
+    operator_id: NodeIdx,
+    projector: Result<Projector, ProjectError>,
+}
+
+impl ProjectOperator {
+    pub(crate) fn new(operator_id: ExecutorId, project: &Project) -> Self {
+        Self {
+            operator_id,
+            projector: Projector::new(project),
         }
-    };
+    }
 }
 
-macro_rules! stateless_operator_impl {
-    ($name: ident) => {
-        impl<S: state::StateManager> IOperator for $name<S> {
-            fn call_fn<'p, 'i>(
-                &self,
-                event: &KeyedDataEvent,
-                rt_engine: &RefCell<RuntimeEngine<'p, 'i>>,
-            ) -> Result<Vec<KeyedDataEvent>, ExecutionError>
-            where
-                'p: 'i,
+impl IOperator for ProjectOperator {
+    fn call_fn<'p, 'i>(
+        &self,
+        event: &KeyedDataEvent,
+        _rt_engine: &RefCell<RuntimeEngine<'p, 'i>>,
+    ) -> Result<Vec<KeyedDataEvent>, ExecutionError>
+    where
+        'p: 'i,
+    {
+        let projector = self
+            .projector
+            .as_ref()
+            .map_err(|err| ExecutionError::ProjectFailed(err.clone()))?;
+        let mut new_events = BTreeMap::<Option<TypedValue>, KeyedDataEvent>::new();
+
+        for entry in &event.data {
+            let (value, key) = match projector
+                .project(&TypedValue::from(entry))
+                .map_err(|err| ExecutionError::ProjectFailed(err))?
             {
-                let mut new_event = event.clone();
+                Some(projected) => projected,
+                None => continue,
+            };
 
-                let value_entry_results = event
-                    .data
-                    .iter()
-                    .map(|entry| TypedValue::from(entry))
-                    .map(|typed_val| {
-                        rt_engine
-                            .borrow_mut()
-                            .call_one_arg(&typed_val)
-                            .unwrap_or(TypedValue::Invalid)
-                    })
-                    .map(|val| {
-                        let mut entry = Entry::default();
-                        entry.set_data_type(val.get_type());
-                        entry.value = val.get_data_bytes();
-                        entry
-                    });
-                new_event.data = Vec::from_iter(value_entry_results);
+            let new_event = new_events.entry(key.clone()).or_insert_with(|| {
+                let mut new_event = event.clone();
+                new_event.data = vec![];
                 new_event.from_operator_id = self.operator_id;
-                Ok(vec![new_event])
-            }
+                key.iter().for_each(|key| {
+                    let mut key_entry = Entry::default();
+                    key_entry.set_data_type(key.get_type());
+                    key_entry.value = key.get_data_bytes();
+                    new_event.key = Some(key_entry);
+                });
+                new_event
+            });
+
+            let mut value_entry = Entry::default();
+            value_entry.set_data_type(value.get_type());
+            value_entry.value = value.get_data_bytes();
+            new_event.data.push(value_entry);
         }
-    };
-}
 
-define_operator!(MapOperator);
-new_operator!(MapOperator);
-stateless_operator_impl!(MapOperator);
+        Ok(new_events.into_values().collect())
+    }
+}
 
-define_operator!(FlatMapOperator);
-new_operator!(FlatMapOperator);
+/// metric of the payloads which are observed for the first time within the horizon
+pub const DEDUPLICATE_UNIQUE_METRIC: &str = "deduplicate.unique";
+/// metric of the duplicated payloads, no matter they are dropped or emitted to the side output
+pub const DEDUPLICATE_DUPLICATE_METRIC: &str = "deduplicate.duplicate";
+
+/// [`DeduplicateOperator`] drops the payloads whose dedup key has been observed within the horizon,
+/// or emits them to the side output if it's configured.
+///
+/// The first-seen time of each key is kept in the state backend. Keys expire after the horizon and the expired keys are cleaned up at most once per horizon.
+pub(crate) struct DeduplicateOperator<S: state::StateManager> {
+    operator_id: NodeIdx,
+    state_manager: S,
+    key_path: Result<Option<JsonPath>, DataflowValidateError>,
+    horizon: Result<i64, DataflowValidateError>,
+    side_output: Option<ExecutorId>,
+}
 
-define_operator!(FilterOperator);
-new_operator!(FilterOperator);
+impl<S: state::StateManager> DeduplicateOperator<S> {
+    pub(crate) fn new(
+        operator_id: ExecutorId,
+        deduplicate: &Deduplicate,
+        state_manager: S,
+    ) -> Self {
+        Self {
+            operator_id,
+            state_manager,
+            key_path: deduplicate.get_key_path(),
+            horizon: deduplicate
+                .get_horizon()
+                .map(|horizon| horizon.num_milliseconds()),
+            side_output: deduplicate.side_output,
+        }
+    }
 
-define_operator!(KeyByOperator);
-new_operator!(KeyByOperator);
+    /// deduplicate the payloads at the time `now` in milliseconds.
+    /// The unique payloads are in the first event and the duplicates are in the second one if the side output is configured
+    pub(crate) fn deduplicate(
+        &self,
+        event: &KeyedDataEvent,
+        now: i64,
+    ) -> Result<Vec<KeyedDataEvent>, ExecutionError> {
+        let key_path = self.key_path.as_ref().map_err(into_deduplicate_error)?;
+        let horizon = *self.horizon.as_ref().map_err(into_deduplicate_error)?;
+        self.clean_expired_keys(now, horizon);
+
+        let mut unique = event.clone();
+        unique.data = vec![];
+        unique.from_operator_id = self.operator_id;
+        let mut duplicates = unique.clone();
+
+        for entry in &event.data {
+            let dedup_key = match key_path {
+                Some(path) => {
+                    select_path(&TypedValue::from(entry), path).map(|value| value.get_data_bytes())
+                }
+                None => event.key.as_ref().map(|key| key.value.clone()),
+            };
+            let is_unique = dedup_key
+                .map(|dedup_key| {
+                    observe_dedup_key(
+                        &self.state_manager,
+                        &get_dedup_state_key(DEDUP_STATE_PREFIX, self.operator_id, &dedup_key),
+                        now,
+                        horizon,
+                    )
+                })
+                .unwrap_or(true);
+            if is_unique {
+                unique.data.push(entry.clone());
+            } else {
+                duplicates.data.push(entry.clone());
+            }
+        }
 
-define_operator!(ReduceOperator);
-new_operator!(ReduceOperator);
+        let mut new_events = vec![];
+        if !unique.data.is_empty() {
+            new_events.push(unique);
+        }
+        if let Some(side_output) = self.side_output {
+            if !duplicates.data.is_empty() {
+                duplicates.to_operator_id = side_output;
+                new_events.push(duplicates);
+            }
+        }
+        Ok(new_events)
+    }
 
-fn get_operator_state_key(operator_id: NodeIdx, operator: &str, reference: &[u8]) -> Vec<u8> {
-    let mut prefix = format!("{}-{}", operator, operator_id).as_bytes().to_vec();
-    prefix.append(&mut reference.to_vec());
-    prefix
+    fn clean_expired_keys(&self, now: i64, horizon: i64) {
+        clean_expired_dedup_keys(
+            &self.state_manager,
+            DEDUP_STATE_PREFIX,
+            self.operator_id,
+            now,
+            horizon,
+        )
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::collections::BTreeMap;
+impl<S: state::StateManager> IOperator for DeduplicateOperator<S> {
+    fn call_fn<'p, 'i>(
+        &self,
+        event: &KeyedDataEvent,
+        _rt_engine: &RefCell<RuntimeEngine<'p, 'i>>,
+    ) -> Result<Vec<KeyedDataEvent>, ExecutionError>
+    where
+        'p: 'i,
+    {
+        self.deduplicate(event, now_timestamp())
+    }
+}
 
-    use proto::common::{
-        filter, flat_map, key_by, mapper, operator_info::Details, reducer, OperatorInfo,
-    };
+fn into_deduplicate_error(err: &DataflowValidateError) -> ExecutionError {
+    match err {
+        DataflowValidateError::InvalidDeduplicate(msg)
+        | DataflowValidateError::InvalidEventDedup(msg) => {
+            ExecutionError::DeduplicateFailed(msg.clone())
+        }
+        _ => ExecutionError::DeduplicateFailed(format!("{:?}", err)),
+    }
+}
 
-    use crate::MOD_TEST_START;
+/// metric of the payloads fetched by a source which are dropped by the event deduplication of the dataflow
+pub const SOURCE_DEDUPLICATED_METRIC: &str = "source.deduplicated";
+
+/// [`EventDeduplicator`] drops the payloads of the events fetched by a source whose dedup key has been seen within the retention,
+/// so that the events redelivered by the external system are processed once.
+///
+/// The dedup key combines the configured fields of the event. Like [`DeduplicateOperator`], the first-seen time of each key is kept in the state backend of the source.
+pub(crate) struct EventDeduplicator {
+    operator_id: NodeIdx,
+    key_fields: Result<Vec<DedupKeyField>, DataflowValidateError>,
+    retention: Result<i64, DataflowValidateError>,
+}
 
-    fn get_opeartor_udf(op_info: &OperatorInfo) -> String {
-        match op_info.details.as_ref() {
-            Some(info) => match info {
-                Details::Mapper(func) => match &func.value {
-                    Some(value) => match value {
-                        mapper::Value::Func(func) => func.function.clone(),
-                    },
-                    None => "".to_string(),
-                },
-                Details::Filter(func) => match &func.value {
-                    Some(value) => match value {
-                        filter::Value::Func(func) => func.function.clone(),
-                    },
-                    None => "".to_string(),
-                },
-                Details::KeyBy(func) => match &func.value {
-                    Some(value) => match value {
-                        key_by::Value::Func(func) => func.function.clone(),
-                    },
-                    None => "".to_string(),
-                },
-                Details::Reducer(func) => match &func.value {
-                    Some(value) => match value {
-                        reducer::Value::Func(func) => func.function.clone(),
-                    },
-                    None => "".to_string(),
-                },
-                Details::FlatMap(func) => match &func.value {
-                    Some(value) => match value {
-                        flat_map::Value::Func(func) => func.function.clone(),
-                    },
-                    None => "".to_string(),
-                },
-                _ => "".to_string(),
-            },
-            None => "".to_string(),
+impl EventDeduplicator {
+    pub(crate) fn new(operator_id: ExecutorId, event_dedup: &EventDedup) -> Self {
+        Self {
+            operator_id,
+            key_fields: event_dedup.get_key_fields(),
+            retention: event_dedup
+                .get_retention()
+                .map(|retention| retention.num_milliseconds()),
         }
     }
 
-    struct SetupGuard {}
+    /// drop the duplicated payloads of the event at the time `now` in milliseconds. It returns the number of the dropped payloads
+    pub(crate) fn deduplicate<S: state::StateManager>(
+        &self,
+        event: &mut KeyedDataEvent,
+        state_manager: &S,
+        now: i64,
+    ) -> Result<usize, ExecutionError> {
+        let key_fields = self.key_fields.as_ref().map_err(into_deduplicate_error)?;
+        let retention = *self.retention.as_ref().map_err(into_deduplicate_error)?;
+        clean_expired_dedup_keys(
+            state_manager,
+            EVENT_DEDUP_STATE_PREFIX,
+            self.operator_id,
+            now,
+            retention,
+        );
 
-    impl Drop for SetupGuard {
-        fn drop(&mut self) {}
+        let received = event.data.len();
+        let data = std::mem::take(&mut event.data);
+        event.data = data
+            .into_iter()
+            .filter(|entry| {
+                get_event_dedup_key(key_fields, event, entry)
+                    .map(|dedup_key| {
+                        observe_dedup_key(
+                            state_manager,
+                            &get_dedup_state_key(
+                                EVENT_DEDUP_STATE_PREFIX,
+                                self.operator_id,
+                                &dedup_key,
+                            ),
+                            now,
+                            retention,
+                        )
+                    })
+                    .unwrap_or(true)
+            })
+            .collect();
+        Ok(received - event.data.len())
     }
+}
 
-    fn setup() -> SetupGuard {
-        MOD_TEST_START.call_once(|| {
-            v8::V8::set_flags_from_string(
-                "--no_freeze_flags_after_init --expose_gc --harmony-import-assertions --harmony-shadow-realm --allow_natives_syntax --turbo_fast_api_calls",
-              );
-                  v8::V8::initialize_platform(v8::new_default_platform(0, false).make_shared());
-                  v8::V8::initialize();
-        });
-        std::env::set_var("STATE_MANAGER", "MEM");
-
-        SetupGuard {}
+/// the dedup key of a payload of the event. Each field is prefixed by its length so that different combinations never collide.
+/// It's none if any of the fields is missing
+fn get_event_dedup_key(
+    key_fields: &[DedupKeyField],
+    event: &KeyedDataEvent,
+    entry: &Entry,
+) -> Option<Vec<u8>> {
+    let payload = TypedValue::from(entry);
+    let mut dedup_key = vec![];
+    for field in key_fields {
+        let value = match field {
+            DedupKeyField::Key => event.key.as_ref().map(|key| key.value.to_vec())?,
+            DedupKeyField::EventTime => event.event_time.to_be_bytes().to_vec(),
+            DedupKeyField::Path(path) => select_path(&payload, path)?.get_data_bytes().to_vec(),
+        };
+        dedup_key.extend_from_slice(&(value.len() as u32).to_be_bytes());
+        dedup_key.extend_from_slice(&value);
     }
+    Some(dedup_key)
+}
 
-    #[test]
-    fn test_map_operator() {
-        use super::MapOperator;
-        use crate::dataflow::get_function_name;
-        use crate::dataflow::IOperator;
-        use crate::state::MemoryStateManager;
-        use crate::v8_runtime::RuntimeEngine;
-        use common::types::TypedValue;
-        use proto::common::Func;
-        use proto::common::{Entry, KeyedDataEvent};
-        use proto::common::{Mapper, OperatorInfo};
-        use std::cell::RefCell;
+/// prefix of the state keys of [`DeduplicateOperator`]
+const DEDUP_STATE_PREFIX: &str = "dedup";
+/// prefix of the state keys of [`EventDeduplicator`], so that they never collide with the ones of a chained [`DeduplicateOperator`]
+const EVENT_DEDUP_STATE_PREFIX: &str = "event-dedup";
 
-        let _setup_guard = setup();
+/// unlike [`get_operator_state_key`], the operator id is terminated so that the keys of an operator can be scanned by prefix
+fn get_dedup_state_key(prefix: &str, operator_id: NodeIdx, dedup_key: &[u8]) -> Vec<u8> {
+    let mut state_key = format!("{}-{}:", prefix, operator_id).into_bytes();
+    state_key.extend_from_slice(dedup_key);
+    state_key
+}
 
-        let op_info = OperatorInfo {
-            operator_id: 0,
-            host_addr: None,
-            upstreams: Default::default(),
-            details: Some(Details::Mapper(Mapper {
-                value: Some(mapper::Value::Func(Func {
-                    function: "function _operator_map_process(a) { return a+1 }".to_string(),
-                })),
-            })),
-        };
+/// whether the dedup key hasn't been seen within the horizon. The time `now` is recorded as the first-seen time of a unique key
+fn observe_dedup_key<S: state::StateManager>(
+    state_manager: &S,
+    state_key: &[u8],
+    now: i64,
+    horizon: i64,
+) -> bool {
+    match get_state_timestamp(&state_manager.get_keyed_state(state_key)) {
+        Some(first_seen) if now - first_seen < horizon => false,
+        _ => {
+            state_manager.set_key_state(state_key, &now.to_be_bytes());
+            true
+        }
+    }
+}
 
-        let state_manager = MemoryStateManager::new();
+/// the expired dedup keys of an operator are cleaned up at most once per horizon
+fn clean_expired_dedup_keys<S: state::StateManager>(
+    state_manager: &S,
+    prefix: &str,
+    operator_id: NodeIdx,
+    now: i64,
+    horizon: i64,
+) {
+    let meta_key = format!("{}-meta-{}", prefix, operator_id).into_bytes();
+    if matches!(
+        get_state_timestamp(&state_manager.get_keyed_state(&meta_key)),
+        Some(last_cleaned) if now - last_cleaned < horizon
+    ) {
+        return;
+    }
 
-        let isolate = &mut v8::Isolate::new(Default::default());
-        let isolated_scope = &mut v8::HandleScope::new(isolate);
-        let rt_engine = RefCell::new(RuntimeEngine::new(
-            "function _operator_map_process(a) { return a+1 }",
-            &get_function_name(&op_info.details.unwrap()),
-            isolated_scope,
-        ));
+    state_manager
+        .scan_keyed_state(&get_dedup_state_key(prefix, operator_id, &[]))
+        .into_iter()
+        .filter(|(_, value)| {
+            get_state_timestamp(value)
+                .map(|first_seen| now - first_seen >= horizon)
+                .unwrap_or(true)
+        })
+        .for_each(|(key, _)| state_manager.delete_keyed_state(&key));
+    state_manager.set_key_state(&meta_key, &now.to_be_bytes());
+}
 
-        let operator = MapOperator::new(op_info.operator_id, state_manager);
-        let mut event = KeyedDataEvent::default();
-        event.from_operator_id = 0;
+fn get_state_timestamp(state: &[u8]) -> Option<i64> {
+    state.try_into().ok().map(i64::from_be_bytes)
+}
 
-        let mut entry = Entry::default();
-        let val = TypedValue::Number(1.0);
-        entry.set_data_type(val.get_type());
+/// [`SortBufferOperator`] buffers events in the state backend and releases them in the order of event time,
+/// so the downstreams observe monotone event time per key.
+///
+/// The watermark is the max observed event time minus the max out-of-orderness. A buffered event is released once the watermark passes its event time plus the safety margin.
+/// Events behind the watermark, or behind an event of the same key which has been released early by the overflow policy, are late and they are emitted to the side output if it's configured.
+///
+/// Since the buffer only lives in the state backend, it's checkpointed with the other states of the executor.
+pub(crate) struct SortBufferOperator<S: state::StateManager> {
+    operator_id: NodeIdx,
+    state_manager: S,
+    max_out_of_orderness: i64,
+    safety_margin: i64,
+    max_buffered_per_key: usize,
+    max_buffered: usize,
+    overflow_policy: Result<OverflowPolicy, DataflowValidateError>,
+    side_output: Option<ExecutorId>,
+}
+
+/// the metadata of a [`SortBufferOperator`] which is kept in the state backend
+#[derive(Default)]
+struct SortBufferMeta {
+    /// the max observed event time
+    max_event_time: Option<i64>,
+    /// sequence of the next buffered event, it keeps the events with the same event time in the arrival order
+    next_seq: u64,
+    /// the number of buffered events
+    buffered: u64,
+}
+
+impl SortBufferMeta {
+    fn from_state(state: &[u8]) -> Self {
+        if state.len() != 25 {
+            return Default::default();
+        }
+        let get_u64 = |offset: usize| {
+            u64::from_be_bytes(state[offset..offset + 8].try_into().unwrap_or_default())
+        };
+        Self {
+            max_event_time: (state[0] == 1).then(|| get_u64(1) as i64),
+            next_seq: get_u64(9),
+            buffered: get_u64(17),
+        }
+    }
+
+    fn to_state(&self) -> Vec<u8> {
+        let mut state = vec![self.max_event_time.is_some() as u8];
+        state.extend_from_slice(&self.max_event_time.unwrap_or_default().to_be_bytes());
+        state.extend_from_slice(&self.next_seq.to_be_bytes());
+        state.extend_from_slice(&self.buffered.to_be_bytes());
+        state
+    }
+}
+
+impl<S: state::StateManager> SortBufferOperator<S> {
+    pub(crate) fn new(operator_id: ExecutorId, sort_buffer: &SortBuffer, state_manager: S) -> Self {
+        Self {
+            operator_id,
+            state_manager,
+            max_out_of_orderness: sort_buffer.get_max_out_of_orderness().num_milliseconds(),
+            safety_margin: sort_buffer.get_safety_margin().num_milliseconds(),
+            max_buffered_per_key: sort_buffer.max_buffered_per_key as usize,
+            max_buffered: sort_buffer.max_buffered as usize,
+            overflow_policy: sort_buffer.get_overflow_policy(),
+            side_output: sort_buffer.side_output,
+        }
+    }
+
+    /// buffer the event, then release the buffered events which the watermark has passed in the order of event time.
+    /// The late event is emitted to the side output if it's configured
+    pub(crate) fn sort(
+        &self,
+        event: &KeyedDataEvent,
+    ) -> Result<Vec<KeyedDataEvent>, ExecutionError> {
+        let overflow_policy = *self
+            .overflow_policy
+            .as_ref()
+            .map_err(into_sort_buffer_error)?;
+        let mut meta = SortBufferMeta::from_state(
+            &self
+                .state_manager
+                .get_keyed_state(&get_sort_buffer_meta_key(self.operator_id)),
+        );
+        let mut new_events = vec![];
+
+        let key = get_event_key(event);
+        let watermark = meta
+            .max_event_time
+            .map(|max_event_time| max_event_time.saturating_sub(self.max_out_of_orderness));
+        let released = get_state_timestamp(
+            &self
+                .state_manager
+                .get_keyed_state(&get_sort_buffer_released_key(self.operator_id, &key)),
+        );
+        if watermark
+            .into_iter()
+            .chain(released)
+            .any(|bound| event.event_time < bound)
+        {
+            if let Some(side_output) = self.side_output {
+                let mut late = event.clone();
+                late.from_operator_id = self.operator_id;
+                late.to_operator_id = side_output;
+                new_events.push(late);
+            }
+            return Ok(new_events);
+        }
+
+        let key_positions = if self.max_buffered_per_key > 0 {
+            self.state_manager
+                .scan_keyed_state(&get_sort_buffer_index_prefix(self.operator_id, &key))
+        } else {
+            vec![]
+        };
+        let key_full =
+            self.max_buffered_per_key > 0 && key_positions.len() >= self.max_buffered_per_key;
+        let full = self.max_buffered > 0 && meta.buffered as usize >= self.max_buffered;
+        if key_full || full {
+            // the earliest buffered event of the key, or of the operator if only the overall limit is reached
+            let earliest = if key_full {
+                key_positions.first().map(|(_, position)| {
+                    let state_key = get_sort_buffer_state_key(self.operator_id, position);
+                    let state = self.state_manager.get_keyed_state(&state_key);
+                    (state_key, state)
+                })
+            } else {
+                self.state_manager
+                    .scan_keyed_state(&get_sort_buffer_state_key(self.operator_id, &[]))
+                    .into_iter()
+                    .next()
+            };
+            let earliest = earliest.and_then(|(state_key, state)| {
+                KeyedDataEvent::decode(state.as_slice())
+                    .map(|buffered| (state_key, buffered))
+                    .map_err(|err| tracing::error!("decode buffered event failed: {}", err))
+                    .ok()
+            });
+
+            match (overflow_policy, earliest) {
+                (OverflowPolicy::DropNewest, _) => return Ok(new_events),
+                // the incoming event is the earliest one
+                (policy, Some((_, buffered))) if event.event_time < buffered.event_time => {
+                    if policy == OverflowPolicy::ReleaseEarliest {
+                        new_events.push(self.release_early(event.clone(), &key));
+                    }
+                    return Ok(new_events);
+                }
+                (policy, Some((state_key, buffered))) => {
+                    self.remove_buffered(&state_key, &buffered, &mut meta);
+                    if policy == OverflowPolicy::ReleaseEarliest {
+                        let buffered_key = get_event_key(&buffered);
+                        new_events.push(self.release_early(buffered, &buffered_key));
+                    }
+                }
+                (_, None) => {}
+            }
+        }
+
+        let position = get_sort_buffer_position(event.event_time, meta.next_seq);
+        self.state_manager.set_key_state(
+            &get_sort_buffer_state_key(self.operator_id, &position),
+            &event.encode_to_vec(),
+        );
+        let mut index_key = get_sort_buffer_index_prefix(self.operator_id, &key);
+        index_key.extend_from_slice(&position);
+        self.state_manager.set_key_state(&index_key, &position);
+        meta.next_seq = meta.next_seq.wrapping_add(1);
+        meta.buffered += 1;
+        meta.max_event_time = Some(
+            meta.max_event_time
+                .map(|max_event_time| max_event_time.max(event.event_time))
+                .unwrap_or(event.event_time),
+        );
+
+        let watermark = meta
+            .max_event_time
+            .unwrap_or(event.event_time)
+            .saturating_sub(self.max_out_of_orderness);
+        for (state_key, state) in self
+            .state_manager
+            .scan_keyed_state(&get_sort_buffer_state_key(self.operator_id, &[]))
+        {
+            let buffered = match KeyedDataEvent::decode(state.as_slice()) {
+                Ok(buffered) => buffered,
+                Err(err) => {
+                    tracing::error!("decode buffered event failed: {}", err);
+                    self.state_manager.delete_keyed_state(&state_key);
+                    meta.buffered = meta.buffered.saturating_sub(1);
+                    continue;
+                }
+            };
+            if buffered.event_time.saturating_add(self.safety_margin) > watermark {
+                break;
+            }
+            self.remove_buffered(&state_key, &buffered, &mut meta);
+            let mut released = buffered;
+            released.from_operator_id = self.operator_id;
+            new_events.push(released);
+        }
+
+        // the released bounds of keys are useless once the watermark passes them
+        self.state_manager
+            .scan_keyed_state(&get_sort_buffer_released_key(self.operator_id, &[]))
+            .into_iter()
+            .filter(|(_, value)| {
+                get_state_timestamp(value)
+                    .map(|released| released <= watermark)
+                    .unwrap_or(true)
+            })
+            .for_each(|(key, _)| self.state_manager.delete_keyed_state(&key));
+        self.state_manager.set_key_state(
+            &get_sort_buffer_meta_key(self.operator_id),
+            &meta.to_state(),
+        );
+        Ok(new_events)
+    }
+
+    /// release an event before the watermark passes it. Later events of the key behind it become late
+    fn release_early(&self, mut event: KeyedDataEvent, key: &[u8]) -> KeyedDataEvent {
+        let released_key = get_sort_buffer_released_key(self.operator_id, key);
+        let released = get_state_timestamp(&self.state_manager.get_keyed_state(&released_key))
+            .map(|released| released.max(event.event_time))
+            .unwrap_or(event.event_time);
+        self.state_manager
+            .set_key_state(&released_key, &released.to_be_bytes());
+        event.from_operator_id = self.operator_id;
+        event
+    }
+
+    fn remove_buffered(
+        &self,
+        state_key: &[u8],
+        buffered: &KeyedDataEvent,
+        meta: &mut SortBufferMeta,
+    ) {
+        let position = &state_key[state_key.len().saturating_sub(16)..];
+        let mut index_key =
+            get_sort_buffer_index_prefix(self.operator_id, &get_event_key(buffered));
+        index_key.extend_from_slice(position);
+        self.state_manager.delete_keyed_state(&index_key);
+        self.state_manager.delete_keyed_state(state_key);
+        meta.buffered = meta.buffered.saturating_sub(1);
+    }
+}
+
+impl<S: state::StateManager> IOperator for SortBufferOperator<S> {
+    fn call_fn<'p, 'i>(
+        &self,
+        event: &KeyedDataEvent,
+        _rt_engine: &RefCell<RuntimeEngine<'p, 'i>>,
+    ) -> Result<Vec<KeyedDataEvent>, ExecutionError>
+    where
+        'p: 'i,
+    {
+        self.sort(event)
+    }
+}
+
+fn into_sort_buffer_error(err: &DataflowValidateError) -> ExecutionError {
+    match err {
+        DataflowValidateError::InvalidSortBuffer(msg) => {
+            ExecutionError::SortBufferFailed(msg.clone())
+        }
+        _ => ExecutionError::SortBufferFailed(format!("{:?}", err)),
+    }
+}
+
+fn get_event_key(event: &KeyedDataEvent) -> Vec<u8> {
+    event
+        .key
+        .as_ref()
+        .map(|key| key.value.to_vec())
+        .unwrap_or_default()
+}
+
+/// the position of a buffered event. Its bytes are ordered by the event time first, then the sequence
+fn get_sort_buffer_position(event_time: i64, seq: u64) -> Vec<u8> {
+    let mut position = get_ordered_timestamp_bytes(event_time).to_vec();
+    position.extend_from_slice(&seq.to_be_bytes());
+    position
+}
+
+/// the bytes of the timestamp which are ordered as same as the timestamp
+fn get_ordered_timestamp_bytes(timestamp: i64) -> [u8; 8] {
+    ((timestamp as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+fn get_ordered_timestamp(bytes: &[u8]) -> i64 {
+    (u64::from_be_bytes(bytes.try_into().unwrap_or_default()) ^ (1 << 63)) as i64
+}
+
+fn get_sort_buffer_meta_key(operator_id: NodeIdx) -> Vec<u8> {
+    format!("sort-meta-{}", operator_id).into_bytes()
+}
+
+/// the buffered events of all keys, ordered by their positions
+fn get_sort_buffer_state_key(operator_id: NodeIdx, position: &[u8]) -> Vec<u8> {
+    let mut state_key = format!("sort-{}:", operator_id).into_bytes();
+    state_key.extend_from_slice(position);
+    state_key
+}
+
+/// the index of the buffered events of a key. The key is length-prefixed so that the prefix of a key never covers another key
+fn get_sort_buffer_index_prefix(operator_id: NodeIdx, key: &[u8]) -> Vec<u8> {
+    let mut prefix = format!("sort-index-{}:", operator_id).into_bytes();
+    prefix.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    prefix.extend_from_slice(key);
+    prefix
+}
+
+/// the latest event time of a key which has been released early
+fn get_sort_buffer_released_key(operator_id: NodeIdx, key: &[u8]) -> Vec<u8> {
+    let mut state_key = format!("sort-released-{}:", operator_id).into_bytes();
+    state_key.extend_from_slice(key);
+    state_key
+}
+
+/// [`WindowAssigner`] assigns an event time to the windows which contain it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum WindowAssigner {
+    Fixed { size: i64 },
+    Sliding { size: i64, period: i64 },
+}
+
+impl WindowAssigner {
+    fn new(window: &Window) -> Result<Self, String> {
+        let positive = |duration: chrono::Duration| {
+            Some(duration.num_milliseconds())
+                .filter(|millis| *millis > 0)
+                .ok_or_else(|| "window size must be positive".to_string())
+        };
+        match window.get_value() {
+            Some(window::Value::Fixed(fixed)) => Ok(Self::Fixed {
+                size: positive(fixed.get_size().to_duration())?,
+            }),
+            Some(window::Value::Slide(slide)) => Ok(Self::Sliding {
+                size: positive(slide.get_size().to_duration())?,
+                period: positive(slide.get_period().to_duration())?,
+            }),
+            Some(window::Value::Session(_)) => Err("session window is unsupported".to_string()),
+            None => Err("window type is missing".to_string()),
+        }
+    }
+
+    /// windows which contain the event time in the order of their start, each window is `[start, end)`
+    pub(crate) fn assign(&self, event_time: i64) -> Vec<(i64, i64)> {
+        match *self {
+            Self::Fixed { size } => {
+                let start = event_time - event_time.rem_euclid(size);
+                vec![(start, start.saturating_add(size))]
+            }
+            Self::Sliding { size, period } => {
+                let mut windows = vec![];
+                let mut start = event_time - event_time.rem_euclid(period);
+                while start > event_time.saturating_sub(size) {
+                    windows.push((start, start.saturating_add(size)));
+                    start -= period;
+                }
+                windows.reverse();
+                windows
+            }
+        }
+    }
+}
+
+/// [`WindowOperator`] collects the payloads of each key into time windows by event time.
+///
+/// A window is emitted once the max observed event time passes its end. The watermark trails the max observed event time by the allowed lateness:
+/// late events of a window which the watermark hasn't passed update the window and it's emitted again.
+/// The window is purged after the watermark passes its end, and its later events are emitted to the side output if it's configured.
+pub(crate) struct WindowOperator<S: state::StateManager> {
+    operator_id: NodeIdx,
+    state_manager: S,
+    assigner: Result<WindowAssigner, String>,
+    allowed_lateness: i64,
+    side_output: Option<ExecutorId>,
+}
+
+impl<S: state::StateManager> WindowOperator<S> {
+    pub(crate) fn new(operator_id: ExecutorId, window: &Window, state_manager: S) -> Self {
+        Self {
+            operator_id,
+            state_manager,
+            assigner: WindowAssigner::new(window),
+            allowed_lateness: window.get_allowed_lateness().num_milliseconds(),
+            side_output: window.side_output,
+        }
+    }
+
+    /// add the payloads of the event to its windows, then emit the windows which are updated by late events or the max observed event time passes
+    pub(crate) fn window(
+        &self,
+        event: &KeyedDataEvent,
+    ) -> Result<Vec<KeyedDataEvent>, ExecutionError> {
+        let assigner = self
+            .assigner
+            .as_ref()
+            .map_err(|msg| ExecutionError::WindowFailed(msg.clone()))?;
+        let meta_key = get_window_meta_key(self.operator_id);
+        let max_event_time = get_state_timestamp(&self.state_manager.get_keyed_state(&meta_key));
+        let watermark = max_event_time
+            .map(|max_event_time| max_event_time.saturating_sub(self.allowed_lateness));
+        let key = get_event_key(event);
+        let mut new_events = vec![];
+
+        let mut late = true;
+        for (start, end) in assigner.assign(event.event_time) {
+            // the window has been purged
+            if watermark.map(|watermark| end <= watermark).unwrap_or(false) {
+                continue;
+            }
+            late = false;
+
+            let state_key = get_window_state_key(self.operator_id, start, end, &key);
+            let (fired, mut pane) =
+                match decode_window_state(&self.state_manager.get_keyed_state(&state_key))? {
+                    Some(window_state) => window_state,
+                    None => {
+                        let mut pane = event.clone();
+                        pane.data = vec![];
+                        pane.from_operator_id = self.operator_id;
+                        // the max event time of the window
+                        pane.event_time = end - 1;
+                        pane.window = Some(keyed_data_event::Window {
+                            start_time: start,
+                            end_time: end,
+                        });
+                        (false, pane)
+                    }
+                };
+            pane.data.extend(event.data.iter().cloned());
+            self.state_manager
+                .set_key_state(&state_key, &encode_window_state(fired, &pane));
+            if fired {
+                new_events.push(pane);
+            }
+        }
+
+        if late {
+            if let Some(side_output) = self.side_output {
+                let mut late_event = event.clone();
+                late_event.from_operator_id = self.operator_id;
+                late_event.to_operator_id = side_output;
+                new_events.push(late_event);
+            }
+            return Ok(new_events);
+        }
+
+        let max_event_time = max_event_time
+            .map(|max_event_time| max_event_time.max(event.event_time))
+            .unwrap_or(event.event_time);
+        self.state_manager
+            .set_key_state(&meta_key, &max_event_time.to_be_bytes());
+        let watermark = max_event_time.saturating_sub(self.allowed_lateness);
+
+        let prefix = get_window_state_prefix(self.operator_id);
+        for (state_key, state) in self.state_manager.scan_keyed_state(&prefix) {
+            let end = get_ordered_timestamp(&state_key[prefix.len()..prefix.len() + 8]);
+            if end > max_event_time {
+                break;
+            }
+            let (fired, pane) = match decode_window_state(&state)? {
+                Some(window_state) => window_state,
+                None => continue,
+            };
+            if end <= watermark {
+                self.state_manager.delete_keyed_state(&state_key);
+            } else if !fired {
+                self.state_manager
+                    .set_key_state(&state_key, &encode_window_state(true, &pane));
+            }
+            if !fired {
+                new_events.push(pane);
+            }
+        }
+
+        Ok(new_events)
+    }
+}
+
+impl<S: state::StateManager> IOperator for WindowOperator<S> {
+    fn call_fn<'p, 'i>(
+        &self,
+        event: &KeyedDataEvent,
+        _rt_engine: &RefCell<RuntimeEngine<'p, 'i>>,
+    ) -> Result<Vec<KeyedDataEvent>, ExecutionError>
+    where
+        'p: 'i,
+    {
+        self.window(event)
+    }
+}
+
+fn get_window_meta_key(operator_id: NodeIdx) -> Vec<u8> {
+    format!("window-meta-{}", operator_id).into_bytes()
+}
+
+fn get_window_state_prefix(operator_id: NodeIdx) -> Vec<u8> {
+    format!("window-{}:", operator_id).into_bytes()
+}
+
+/// the windows of all keys, ordered by their ends
+fn get_window_state_key(operator_id: NodeIdx, start: i64, end: i64, key: &[u8]) -> Vec<u8> {
+    let mut state_key = get_window_state_prefix(operator_id);
+    state_key.extend_from_slice(&get_ordered_timestamp_bytes(end));
+    state_key.extend_from_slice(&get_ordered_timestamp_bytes(start));
+    state_key.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    state_key.extend_from_slice(key);
+    state_key
+}
+
+/// the state of a window is whether it has been emitted, followed by the encoded pane
+fn encode_window_state(fired: bool, pane: &KeyedDataEvent) -> Vec<u8> {
+    let mut state = vec![fired as u8];
+    state.extend(pane.encode_to_vec());
+    state
+}
+
+fn decode_window_state(state: &[u8]) -> Result<Option<(bool, KeyedDataEvent)>, ExecutionError> {
+    match state.split_first() {
+        Some((fired, pane)) => KeyedDataEvent::decode(pane)
+            .map(|pane| Some((*fired == 1, pane)))
+            .map_err(|err| ExecutionError::WindowFailed(format!("decode window failed: {}", err))),
+        None => Ok(None),
+    }
+}
+
+macro_rules! define_operator {
+    ($name: ident) => {
+        pub(crate) struct $name<S>
+        where
+            S: state::StateManager,
+        {
+            state_manager: S,
+            operator_id: NodeIdx,
+        }
+    };
+}
+
+macro_rules! new_operator {
+    ($name:ident) => {
+        impl<S> $name<S>
+        where
+            S: state::StateManager,
+        {
+            pub(crate) fn new(operator_id: ExecutorId, state_manager: S) -> Self {
+                $name {
+                    state_manager,
+                    operator_id,
+                }
+            }
+        }
+    };
+}
+
+macro_rules! stateless_operator_impl {
+    ($name: ident) => {
+        impl<S: state::StateManager> IOperator for $name<S> {
+            fn call_fn<'p, 'i>(
+                &self,
+                event: &KeyedDataEvent,
+                rt_engine: &RefCell<RuntimeEngine<'p, 'i>>,
+            ) -> Result<Vec<KeyedDataEvent>, ExecutionError>
+            where
+                'p: 'i,
+            {
+                let mut new_event = event.clone();
+                let broadcast = get_broadcast_value(&self.state_manager);
+
+                let value_entry_results = event
+                    .data
+                    .iter()
+                    .map(|entry| TypedValue::from(entry))
+                    .map(|typed_val| {
+                        call_udf(rt_engine, &typed_val, broadcast.as_ref())
+                            .unwrap_or(TypedValue::Invalid)
+                    })
+                    .map(|val| {
+                        let mut entry = Entry::default();
+                        entry.set_data_type(val.get_type());
+                        entry.value = val.get_data_bytes();
+                        entry
+                    });
+                new_event.data = Vec::from_iter(value_entry_results);
+                new_event.from_operator_id = self.operator_id;
+                Ok(vec![new_event])
+            }
+        }
+    };
+}
+
+define_operator!(MapOperator);
+new_operator!(MapOperator);
+stateless_operator_impl!(MapOperator);
+
+define_operator!(FlatMapOperator);
+new_operator!(FlatMapOperator);
+
+define_operator!(FilterOperator);
+new_operator!(FilterOperator);
+
+define_operator!(KeyByOperator);
+new_operator!(KeyByOperator);
+
+define_operator!(ReduceOperator);
+new_operator!(ReduceOperator);
+
+fn get_operator_state_key(operator_id: NodeIdx, operator: &str, reference: &[u8]) -> Vec<u8> {
+    let mut prefix = format!("{}-{}", operator, operator_id).as_bytes().to_vec();
+    prefix.append(&mut reference.to_vec());
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use proto::common::{
+        filter, flat_map, key_by, mapper, operator_info::Details, reducer, OperatorInfo,
+    };
+
+    use crate::MOD_TEST_START;
+
+    fn get_opeartor_udf(op_info: &OperatorInfo) -> String {
+        match op_info.details.as_ref() {
+            Some(info) => match info {
+                Details::Mapper(func) => match &func.value {
+                    Some(value) => match value {
+                        mapper::Value::Func(func) => func.function.clone(),
+                    },
+                    None => "".to_string(),
+                },
+                Details::Filter(func) => match &func.value {
+                    Some(value) => match value {
+                        filter::Value::Func(func) => func.function.clone(),
+                    },
+                    None => "".to_string(),
+                },
+                Details::KeyBy(func) => match &func.value {
+                    Some(value) => match value {
+                        key_by::Value::Func(func) => func.function.clone(),
+                    },
+                    None => "".to_string(),
+                },
+                Details::Reducer(func) => match &func.value {
+                    Some(value) => match value {
+                        reducer::Value::Func(func) => func.function.clone(),
+                    },
+                    None => "".to_string(),
+                },
+                Details::FlatMap(func) => match &func.value {
+                    Some(value) => match value {
+                        flat_map::Value::Func(func) => func.function.clone(),
+                    },
+                    None => "".to_string(),
+                },
+                _ => "".to_string(),
+            },
+            None => "".to_string(),
+        }
+    }
+
+    struct SetupGuard {}
+
+    impl Drop for SetupGuard {
+        fn drop(&mut self) {}
+    }
+
+    fn setup() -> SetupGuard {
+        MOD_TEST_START.call_once(|| {
+            v8::V8::set_flags_from_string(
+                "--no_freeze_flags_after_init --expose_gc --harmony-import-assertions --harmony-shadow-realm --allow_natives_syntax --turbo_fast_api_calls",
+              );
+                  v8::V8::initialize_platform(v8::new_default_platform(0, false).make_shared());
+                  v8::V8::initialize();
+        });
+        std::env::set_var("STATE_MANAGER", "MEM");
+
+        SetupGuard {}
+    }
+
+    #[test]
+    fn test_map_operator() {
+        use super::MapOperator;
+        use crate::dataflow::get_function_name;
+        use crate::dataflow::IOperator;
+        use crate::state::MemoryStateManager;
+        use crate::v8_runtime::RuntimeEngine;
+        use common::types::TypedValue;
+        use proto::common::Func;
+        use proto::common::{Entry, KeyedDataEvent};
+        use proto::common::{Mapper, OperatorInfo};
+        use std::cell::RefCell;
+
+        let _setup_guard = setup();
+
+        let op_info = OperatorInfo {
+            operator_id: 0,
+            host_addr: None,
+            upstreams: Default::default(),
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
+            details: Some(Details::Mapper(Mapper {
+                value: Some(mapper::Value::Func(Func {
+                    function: "function _operator_map_process(a) { return a+1 }".to_string(),
+                })),
+            })),
+        };
+
+        let state_manager = MemoryStateManager::new();
+
+        let isolate = &mut v8::Isolate::new(Default::default());
+        let isolated_scope = &mut v8::HandleScope::new(isolate);
+        let rt_engine = RefCell::new(RuntimeEngine::new(
+            "function _operator_map_process(a) { return a+1 }",
+            &get_function_name(&op_info.details.unwrap()),
+            isolated_scope,
+        ));
+
+        let operator = MapOperator::new(op_info.operator_id, state_manager);
+        let mut event = KeyedDataEvent::default();
+        event.from_operator_id = 0;
+
+        let mut entry = Entry::default();
+        let val = TypedValue::Number(1.0);
+        entry.set_data_type(val.get_type());
         entry.value = val.get_data_bytes();
 
         event.data = vec![entry.clone(), entry.clone()];
@@ -552,6 +1498,13 @@ mod tests {
             operator_id: 0,
             host_addr: None,
             upstreams: Default::default(),
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
             details: Some(Details::Filter(Filter {
                 value: Some(filter::Value::Func(Func {
                     function: "function _operator_filter_process(a) { return a === 1 }".to_string(),
@@ -618,6 +1571,13 @@ mod tests {
             operator_id: 0,
             host_addr: None,
             upstreams: Default::default(),
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
             details: Some(Details::KeyBy(KeyBy {
                 value: Some(key_by::Value::Func(Func {
                     function: "function _operator_keyBy_process(a) { return a.foo }".to_string(),
@@ -638,6 +1598,7 @@ mod tests {
 
         let mut event = KeyedDataEvent::default();
         event.from_operator_id = 0;
+        event.set_header("trace_id", "abc");
 
         let mut entry = Entry::default();
         let mut val = BTreeMap::default();
@@ -674,6 +1635,10 @@ mod tests {
                     && TypedValue::from(event.key.as_ref().unwrap())
                         == TypedValue::String("bar1".to_string())
             }));
+            // the headers are kept by the events of all keys
+            assert!(events
+                .iter()
+                .all(|event| event.get_header("trace_id") == Some("abc")));
         }
     }
 
@@ -697,6 +1662,13 @@ mod tests {
             operator_id: 0,
             host_addr: None,
             upstreams: Default::default(),
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
             details: Some(Details::Reducer(Reducer {
                 value: Some(reducer::Value::Func(Func {
                     function:
@@ -742,16 +1714,63 @@ mod tests {
             entry.set_data_type(val.get_type());
             entry.value = val.get_data_bytes();
 
-            assert_eq!(new_events[0].data, vec![entry]);
-            let state = operator
-                .state_manager
-                .get_keyed_state(&get_operator_state_key(
-                    operator.operator_id,
-                    "reduce",
-                    &new_events[0].get_key().value,
-                ));
-            assert_eq!(TypedValue::from_vec(&state), val);
-        }
+            assert_eq!(new_events[0].data, vec![entry]);
+            let state = operator
+                .state_manager
+                .get_keyed_state(&get_operator_state_key(
+                    operator.operator_id,
+                    "reduce",
+                    &new_events[0].get_key().value,
+                ));
+            assert_eq!(TypedValue::from_vec(&state), val);
+        }
+    }
+
+    #[test]
+    fn test_filter_operator_with_broadcast_state() {
+        use super::FilterOperator;
+        use crate::dataflow::IOperator;
+        use crate::state::{MemoryStateManager, StateManager};
+        use crate::v8_runtime::RuntimeEngine;
+        use common::types::TypedValue;
+        use prost::Message;
+        use proto::common::{Entry, KeyedDataEvent};
+        use std::cell::RefCell;
+
+        let _setup_guard = setup();
+
+        let new_entry = |val: TypedValue| {
+            let mut entry = Entry::default();
+            entry.set_data_type(val.get_type());
+            entry.value = val.get_data_bytes();
+            entry
+        };
+        let state_manager = MemoryStateManager::new();
+
+        let isolate = &mut v8::Isolate::new(Default::default());
+        let isolated_scope = &mut v8::HandleScope::new(isolate);
+        let rt_engine = RefCell::new(RuntimeEngine::new(
+            "function _operator_filter_process(a, rules) { return rules !== undefined && a >= rules.threshold }",
+            "_operator_filter_process",
+            isolated_scope,
+        ));
+
+        let operator = FilterOperator::new(0, &state_manager);
+        let mut event = KeyedDataEvent::default();
+        event.data = (1..4)
+            .map(|val| new_entry(TypedValue::Number(val as f64)))
+            .collect();
+
+        // the UDF is called without the broadcast state if it's empty
+        let new_events = operator.call_fn(&event, &rt_engine).expect("");
+        assert!(new_events[0].data.is_empty());
+
+        state_manager.set_broadcast_state(
+            &new_entry(TypedValue::String("threshold".to_string())).encode_to_vec(),
+            &new_entry(TypedValue::Number(2.0)).encode_to_vec(),
+        );
+        let new_events = operator.call_fn(&event, &rt_engine).expect("");
+        assert_eq!(new_events[0].data, event.data[1..].to_vec());
     }
 
     #[test]
@@ -773,6 +1792,13 @@ mod tests {
             operator_id: 0,
             host_addr: None,
             upstreams: Default::default(),
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
             details: Some(Details::FlatMap(FlatMap {
                 value: Some(flat_map::Value::Func(Func {
                     function: "function _operator_flatMap_process(v) { return [v, v, 2] }"
@@ -845,6 +1871,13 @@ mod tests {
             operator_id: 0,
             host_addr: None,
             upstreams: Default::default(),
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
             details: Some(Details::FlatMap(FlatMap {
                 value: Some(flat_map::Value::Func(Func {
                     function: "function _operator_flatMap_process(value) { return value.split(\" \").map(v => { return { t0: 1, t1: v }; }) }".to_string(),
@@ -896,4 +1929,781 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_project_operator() {
+        use super::ProjectOperator;
+        use crate::dataflow::IOperator;
+        use crate::v8_runtime::RuntimeEngine;
+        use common::types::TypedValue;
+        use proto::common::{project::Field, DataTypeEnum, Project};
+        use proto::common::{Entry, KeyedDataEvent};
+        use std::cell::RefCell;
+
+        let _setup_guard = setup();
+
+        let isolate = &mut v8::Isolate::new(Default::default());
+        let isolated_scope = &mut v8::HandleScope::new(isolate);
+        let rt_engine = RefCell::new(RuntimeEngine::new("", "", isolated_scope));
+        let operator = ProjectOperator::new(
+            1,
+            &Project {
+                fields: vec![
+                    Field {
+                        name: "id".to_string(),
+                        path: "$.user?(@.id).id".to_string(),
+                        cast: DataTypeEnum::Bigint as i32,
+                        default_value: None,
+                    },
+                    Field {
+                        name: "name".to_string(),
+                        path: "$.user.name".to_string(),
+                        cast: DataTypeEnum::Unspecified as i32,
+                        default_value: Some("\"unknown\"".to_string()),
+                    },
+                ],
+                key_field: "id".to_string(),
+            },
+        );
+
+        let new_entry = |val: TypedValue| {
+            let mut entry = Entry::default();
+            entry.set_data_type(val.get_type());
+            entry.value = val.get_data_bytes();
+            entry
+        };
+        let new_payload = |val: serde_json::Value| new_entry(TypedValue::from_json_value(val));
+
+        let mut event = KeyedDataEvent::default();
+        event.from_operator_id = 0;
+        event.data = vec![
+            new_payload(serde_json::json!({"user": {"id": "1", "name": "a", "age": 10}})),
+            new_payload(serde_json::json!({"user": {"id": 2}})),
+            new_payload(serde_json::json!({"user": {"name": "c"}})),
+            new_payload(serde_json::json!({"user": {"id": 1, "name": "d"}})),
+        ];
+
+        let result = operator.call_fn(&event, &rt_engine);
+        assert!(result.is_ok());
+        let new_events = result.expect("");
+        assert_eq!(new_events.len(), 2);
+
+        let new_projected = |id: i64, name: &str| {
+            new_entry(TypedValue::Object(BTreeMap::from_iter([
+                ("id".to_string(), TypedValue::BigInt(id)),
+                ("name".to_string(), TypedValue::String(name.to_string())),
+            ])))
+        };
+        assert_eq!(new_events[0].key, Some(new_entry(TypedValue::BigInt(1))));
+        assert_eq!(new_events[0].from_operator_id, 1);
+        assert_eq!(
+            new_events[0].data,
+            vec![new_projected(1, "a"), new_projected(1, "d")]
+        );
+        assert_eq!(new_events[1].key, Some(new_entry(TypedValue::BigInt(2))));
+        assert_eq!(new_events[1].data, vec![new_projected(2, "unknown")]);
+
+        event.data = vec![new_payload(serde_json::json!({"user": {"id": "one"}}))];
+        assert!(operator.call_fn(&event, &rt_engine).is_err());
+    }
+
+    fn new_dedup_event(
+        key: Option<i64>,
+        payloads: Vec<serde_json::Value>,
+    ) -> proto::common::KeyedDataEvent {
+        use common::types::TypedValue;
+        use proto::common::{Entry, KeyedDataEvent};
+
+        let new_entry = |val: TypedValue| {
+            let mut entry = Entry::default();
+            entry.set_data_type(val.get_type());
+            entry.value = val.get_data_bytes();
+            entry
+        };
+        let mut event = KeyedDataEvent::default();
+        event.to_operator_id = 1;
+        event.key = key.map(|key| new_entry(TypedValue::BigInt(key)));
+        event.data = payloads
+            .into_iter()
+            .map(|payload| new_entry(TypedValue::from_json_value(payload)))
+            .collect();
+        event
+    }
+
+    fn get_dedup_ids(event: &proto::common::KeyedDataEvent) -> Vec<i64> {
+        use common::types::TypedValue;
+
+        event
+            .data
+            .iter()
+            .map(|entry| {
+                TypedValue::from(entry).to_json_value()["id"]
+                    .as_f64()
+                    .map(|id| id as i64)
+                    .unwrap_or(-1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_deduplicate_operator() {
+        use super::DeduplicateOperator;
+        use crate::state::{MemoryStateManager, StateManager};
+        use proto::common::{Deduplicate, Time};
+
+        let state_manager = MemoryStateManager::new();
+        let operator = DeduplicateOperator::new(
+            1,
+            &Deduplicate {
+                key_path: "$.id".to_string(),
+                horizon: Some(Time {
+                    millis: 0,
+                    seconds: 10,
+                    minutes: 0,
+                    hours: 0,
+                }),
+                side_output: Some(3),
+            },
+            &state_manager,
+        );
+
+        let event = new_dedup_event(
+            None,
+            vec![
+                serde_json::json!({"id": 1}),
+                serde_json::json!({"id": 2}),
+                serde_json::json!({"id": 1}),
+                serde_json::json!({"name": "no key"}),
+            ],
+        );
+        let new_events = operator.deduplicate(&event, 1000).expect("");
+        assert_eq!(new_events.len(), 2);
+        assert_eq!(new_events[0].from_operator_id, 1);
+        assert_eq!(new_events[0].to_operator_id, 1);
+        assert_eq!(get_dedup_ids(&new_events[0]), vec![1, 2, -1]);
+        assert_eq!(new_events[1].to_operator_id, 3);
+        assert_eq!(get_dedup_ids(&new_events[1]), vec![1]);
+
+        // keys are still remembered within the horizon
+        let event = new_dedup_event(
+            None,
+            vec![serde_json::json!({"id": 2}), serde_json::json!({"id": 3})],
+        );
+        let new_events = operator.deduplicate(&event, 10999).expect("");
+        assert_eq!(get_dedup_ids(&new_events[0]), vec![3]);
+        assert_eq!(get_dedup_ids(&new_events[1]), vec![2]);
+
+        // expired keys are cleaned up and they are unique again
+        let new_events = operator
+            .deduplicate(
+                &new_dedup_event(None, vec![serde_json::json!({"id": 1})]),
+                11000,
+            )
+            .expect("");
+        assert_eq!(new_events.len(), 1);
+        assert_eq!(get_dedup_ids(&new_events[0]), vec![1]);
+        let dedup_states = state_manager.scan_keyed_state("dedup-1:".as_bytes());
+        assert_eq!(dedup_states.len(), 2);
+        assert!(dedup_states
+            .iter()
+            .all(|(_, value)| value.as_slice() != 1000i64.to_be_bytes().as_slice()));
+    }
+
+    #[test]
+    fn test_deduplicate_operator_by_event_key() {
+        use super::DeduplicateOperator;
+        use crate::state::MemoryStateManager;
+        use proto::common::{Deduplicate, Time};
+
+        let operator = DeduplicateOperator::new(
+            1,
+            &Deduplicate {
+                key_path: Default::default(),
+                horizon: Some(Time {
+                    millis: 100,
+                    seconds: 0,
+                    minutes: 0,
+                    hours: 0,
+                }),
+                side_output: None,
+            },
+            MemoryStateManager::new(),
+        );
+
+        let event = new_dedup_event(Some(1), vec![serde_json::json!({"id": 1})]);
+        let new_events = operator.deduplicate(&event, 0).expect("");
+        assert_eq!(get_dedup_ids(&new_events[0]), vec![1]);
+
+        // duplicates are dropped without side output
+        let event = new_dedup_event(
+            Some(1),
+            vec![serde_json::json!({"id": 2}), serde_json::json!({"id": 3})],
+        );
+        assert!(operator.deduplicate(&event, 50).expect("").is_empty());
+
+        let event = new_dedup_event(Some(2), vec![serde_json::json!({"id": 4})]);
+        let new_events = operator.deduplicate(&event, 50).expect("");
+        assert_eq!(get_dedup_ids(&new_events[0]), vec![4]);
+
+        let invalid = DeduplicateOperator::new(
+            1,
+            &Deduplicate {
+                key_path: "$.id".to_string(),
+                horizon: None,
+                side_output: None,
+            },
+            MemoryStateManager::new(),
+        );
+        assert!(invalid.deduplicate(&event, 0).is_err());
+    }
+
+    #[test]
+    fn test_deduplicate_operator_across_restart() {
+        use super::DeduplicateOperator;
+        use crate::state::{KeyValueStateManager, StateManager};
+        use common::utils::times::now_timestamp;
+        use proto::common::{Deduplicate, Time};
+
+        let path = std::env::temp_dir().join(format!("lightflus-dedup-{}", now_timestamp()));
+        let deduplicate = Deduplicate {
+            key_path: "$.id".to_string(),
+            horizon: Some(Time {
+                millis: 0,
+                seconds: 0,
+                minutes: 1,
+                hours: 0,
+            }),
+            side_output: None,
+        };
+
+        {
+            let state_manager = KeyValueStateManager::new(&path);
+            let operator = DeduplicateOperator::new(1, &deduplicate, &state_manager);
+            let event = new_dedup_event(
+                None,
+                vec![serde_json::json!({"id": 1}), serde_json::json!({"id": 2})],
+            );
+            let new_events = operator.deduplicate(&event, 0).expect("");
+            assert_eq!(get_dedup_ids(&new_events[0]), vec![1, 2]);
+            state_manager.checkpoint();
+        }
+
+        // the seen keys are recovered from the checkpoint after restart
+        {
+            let state_manager = KeyValueStateManager::new(&path);
+            let operator = DeduplicateOperator::new(1, &deduplicate, &state_manager);
+            let event = new_dedup_event(
+                None,
+                vec![serde_json::json!({"id": 2}), serde_json::json!({"id": 3})],
+            );
+            let new_events = operator.deduplicate(&event, 30000).expect("");
+            assert_eq!(get_dedup_ids(&new_events[0]), vec![3]);
+
+            // the key seen before restart still expires by its first-seen time
+            let event = new_dedup_event(None, vec![serde_json::json!({"id": 1})]);
+            let new_events = operator.deduplicate(&event, 60000).expect("");
+            assert_eq!(get_dedup_ids(&new_events[0]), vec![1]);
+        }
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    fn new_sort_event(key: i64, event_time: i64) -> proto::common::KeyedDataEvent {
+        let mut event = new_dedup_event(Some(key), vec![serde_json::json!({ "id": event_time })]);
+        event.event_time = event_time;
+        event
+    }
+
+    fn new_sort_buffer(
+        max_buffered_per_key: u32,
+        overflow_policy: proto::common::sort_buffer::OverflowPolicy,
+    ) -> proto::common::SortBuffer {
+        use proto::common::{SortBuffer, Time};
+
+        SortBuffer {
+            max_out_of_orderness: Some(Time {
+                millis: 10,
+                seconds: 0,
+                minutes: 0,
+                hours: 0,
+            }),
+            safety_margin: Some(Time {
+                millis: 5,
+                seconds: 0,
+                minutes: 0,
+                hours: 0,
+            }),
+            max_buffered_per_key,
+            max_buffered: 0,
+            overflow_policy: overflow_policy as i32,
+            side_output: Some(3),
+        }
+    }
+
+    #[test]
+    fn test_event_deduplicator() {
+        use super::EventDeduplicator;
+        use crate::state::{MemoryStateManager, StateManager};
+        use proto::common::{EventDedup, Time};
+
+        let state_manager = MemoryStateManager::new();
+        let deduplicator = EventDeduplicator::new(
+            1,
+            &EventDedup {
+                key_fields: vec!["key".to_string(), "$.id".to_string()],
+                retention: Some(Time {
+                    millis: 0,
+                    seconds: 10,
+                    minutes: 0,
+                    hours: 0,
+                }),
+            },
+        );
+
+        let mut event = new_dedup_event(
+            Some(1),
+            vec![
+                serde_json::json!({"id": 1}),
+                serde_json::json!({"id": 2}),
+                serde_json::json!({"id": 1}),
+                serde_json::json!({"name": "no id"}),
+                serde_json::json!({"name": "no id"}),
+            ],
+        );
+        assert_eq!(
+            deduplicator
+                .deduplicate(&mut event, &state_manager, 1000)
+                .expect(""),
+            1
+        );
+        assert_eq!(get_dedup_ids(&event), vec![1, 2, -1, -1]);
+
+        // the event redelivered within the retention is dropped, and the ones with distinct keys pass
+        let mut redelivered = new_dedup_event(Some(1), vec![serde_json::json!({"id": 2})]);
+        assert_eq!(
+            deduplicator
+                .deduplicate(&mut redelivered, &state_manager, 10999)
+                .expect(""),
+            1
+        );
+        assert!(redelivered.data.is_empty());
+        let mut distinct = new_dedup_event(
+            Some(2),
+            vec![serde_json::json!({"id": 2}), serde_json::json!({"id": 3})],
+        );
+        assert_eq!(
+            deduplicator
+                .deduplicate(&mut distinct, &state_manager, 10999)
+                .expect(""),
+            0
+        );
+        assert_eq!(get_dedup_ids(&distinct), vec![2, 3]);
+        // events without a key are never deduplicated
+        let mut unkeyed = new_dedup_event(None, vec![serde_json::json!({"id": 1})]);
+        assert_eq!(
+            deduplicator
+                .deduplicate(&mut unkeyed, &state_manager, 10999)
+                .expect(""),
+            0
+        );
+
+        // expired keys are cleaned up and they are unique again
+        let mut event = new_dedup_event(Some(1), vec![serde_json::json!({"id": 1})]);
+        assert_eq!(
+            deduplicator
+                .deduplicate(&mut event, &state_manager, 11000)
+                .expect(""),
+            0
+        );
+        assert_eq!(get_dedup_ids(&event), vec![1]);
+        assert_eq!(
+            state_manager
+                .scan_keyed_state("event-dedup-1:".as_bytes())
+                .len(),
+            3
+        );
+        // the keys never collide with the ones of the Deduplicate operator
+        assert!(state_manager
+            .scan_keyed_state("dedup-1:".as_bytes())
+            .is_empty());
+
+        let invalid = EventDeduplicator::new(
+            1,
+            &EventDedup {
+                key_fields: vec![],
+                retention: None,
+            },
+        );
+        assert!(invalid.deduplicate(&mut event, &state_manager, 0).is_err());
+    }
+
+    #[test]
+    fn test_sort_buffer_operator_shuffled_input() {
+        use super::SortBufferOperator;
+        use crate::state::{MemoryStateManager, StateManager};
+        use proto::common::sort_buffer::OverflowPolicy;
+
+        let state_manager = MemoryStateManager::new();
+        let operator = SortBufferOperator::new(
+            1,
+            &new_sort_buffer(0, OverflowPolicy::ReleaseEarliest),
+            &state_manager,
+        );
+
+        // events are shuffled within blocks of 10ms, so none of them is behind the watermark
+        const SHUFFLE: [i64; 10] = [3, 7, 1, 9, 0, 5, 2, 8, 4, 6];
+        let mut released = vec![];
+        for block in 0..30 {
+            for offset in SHUFFLE {
+                let event_time = block * 10 + offset;
+                let new_events = operator
+                    .sort(&new_sort_event(event_time % 3, event_time))
+                    .expect("");
+                assert!(new_events.iter().all(|event| event.to_operator_id == 1));
+                released.extend(new_events);
+            }
+        }
+        assert!(!state_manager
+            .scan_keyed_state("sort-1:".as_bytes())
+            .is_empty());
+
+        // the watermark passes all the shuffled events
+        released.extend(operator.sort(&new_sort_event(0, 1000)).expect(""));
+        let event_times = released
+            .iter()
+            .map(|event| event.event_time)
+            .collect::<Vec<_>>();
+        assert_eq!(event_times, (0..300).collect::<Vec<_>>());
+        assert!(released.iter().all(|event| event.from_operator_id == 1
+            && event.key.as_ref().map(|key| key.value.clone())
+                == new_sort_event(event.event_time % 3, 0)
+                    .key
+                    .map(|key| key.value)
+            && get_dedup_ids(event) == vec![event.event_time]));
+        assert_eq!(
+            state_manager.scan_keyed_state("sort-1:".as_bytes()).len(),
+            1
+        );
+        assert_eq!(
+            state_manager
+                .scan_keyed_state("sort-index-1:".as_bytes())
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_sort_buffer_operator_late_event() {
+        use super::SortBufferOperator;
+        use crate::state::MemoryStateManager;
+        use proto::common::sort_buffer::OverflowPolicy;
+
+        let operator = SortBufferOperator::new(
+            1,
+            &new_sort_buffer(0, OverflowPolicy::ReleaseEarliest),
+            MemoryStateManager::new(),
+        );
+        assert!(operator.sort(&new_sort_event(1, 100)).expect("").is_empty());
+        assert!(operator.sort(&new_sort_event(1, 90)).expect("").is_empty());
+
+        // the watermark is 90 now
+        let new_events = operator.sort(&new_sort_event(2, 89)).expect("");
+        assert_eq!(new_events.len(), 1);
+        assert_eq!(new_events[0].to_operator_id, 3);
+        assert_eq!(new_events[0].event_time, 89);
+
+        let new_events = operator.sort(&new_sort_event(2, 115)).expect("");
+        assert_eq!(
+            new_events
+                .iter()
+                .map(|event| (event.event_time, event.to_operator_id))
+                .collect::<Vec<_>>(),
+            vec![(90, 1), (100, 1)]
+        );
+    }
+
+    #[test]
+    fn test_sort_buffer_operator_overflow() {
+        use super::SortBufferOperator;
+        use crate::state::MemoryStateManager;
+        use proto::common::sort_buffer::OverflowPolicy;
+
+        let get_event_times = |events: Vec<proto::common::KeyedDataEvent>| {
+            events
+                .iter()
+                .map(|event| (event.event_time, event.to_operator_id))
+                .collect::<Vec<_>>()
+        };
+
+        // drop the incoming event
+        let operator = SortBufferOperator::new(
+            1,
+            &new_sort_buffer(2, OverflowPolicy::DropNewest),
+            MemoryStateManager::new(),
+        );
+        for event_time in [5, 3, 4] {
+            assert!(operator
+                .sort(&new_sort_event(1, event_time))
+                .expect("")
+                .is_empty());
+        }
+        // other keys are not limited
+        assert!(operator.sort(&new_sort_event(2, 6)).expect("").is_empty());
+        assert_eq!(
+            get_event_times(operator.sort(&new_sort_event(2, 100)).expect("")),
+            vec![(3, 1), (5, 1), (6, 1)]
+        );
+
+        // drop the earliest buffered event
+        let operator = SortBufferOperator::new(
+            1,
+            &new_sort_buffer(2, OverflowPolicy::DropEarliest),
+            MemoryStateManager::new(),
+        );
+        for event_time in [5, 3, 4, 1] {
+            assert!(operator
+                .sort(&new_sort_event(1, event_time))
+                .expect("")
+                .is_empty());
+        }
+        assert_eq!(
+            get_event_times(operator.sort(&new_sort_event(2, 100)).expect("")),
+            vec![(4, 1), (5, 1)]
+        );
+
+        // release the earliest buffered event, the events behind it become late
+        let operator = SortBufferOperator::new(
+            1,
+            &new_sort_buffer(2, OverflowPolicy::ReleaseEarliest),
+            MemoryStateManager::new(),
+        );
+        assert!(operator.sort(&new_sort_event(1, 5)).expect("").is_empty());
+        assert!(operator.sort(&new_sort_event(1, 3)).expect("").is_empty());
+        assert_eq!(
+            get_event_times(operator.sort(&new_sort_event(1, 4)).expect("")),
+            vec![(3, 1)]
+        );
+        assert_eq!(
+            get_event_times(operator.sort(&new_sort_event(1, 2)).expect("")),
+            vec![(2, 3)]
+        );
+        // the incoming event is released directly if it's the earliest one
+        assert_eq!(
+            get_event_times(operator.sort(&new_sort_event(1, 3)).expect("")),
+            vec![(3, 1)]
+        );
+        assert_eq!(
+            get_event_times(operator.sort(&new_sort_event(2, 100)).expect("")),
+            vec![(4, 1), (5, 1)]
+        );
+    }
+
+    #[test]
+    fn test_sort_buffer_operator_across_restart() {
+        use super::SortBufferOperator;
+        use crate::state::{KeyValueStateManager, StateManager};
+        use common::utils::times::now_timestamp;
+        use proto::common::sort_buffer::OverflowPolicy;
+
+        let path = std::env::temp_dir().join(format!("lightflus-sort-{}", now_timestamp()));
+        let sort_buffer = new_sort_buffer(0, OverflowPolicy::ReleaseEarliest);
+
+        {
+            let state_manager = KeyValueStateManager::new(&path);
+            let operator = SortBufferOperator::new(1, &sort_buffer, &state_manager);
+            for event_time in [30, 25, 21] {
+                assert!(operator
+                    .sort(&new_sort_event(1, event_time))
+                    .expect("")
+                    .is_empty());
+            }
+            state_manager.checkpoint();
+        }
+
+        // the buffer and the watermark are recovered from the checkpoint after restart
+        {
+            let state_manager = KeyValueStateManager::new(&path);
+            let operator = SortBufferOperator::new(1, &sort_buffer, &state_manager);
+            let new_events = operator.sort(&new_sort_event(1, 19)).expect("");
+            assert_eq!(new_events.len(), 1);
+            assert_eq!(new_events[0].to_operator_id, 3);
+
+            let new_events = operator.sort(&new_sort_event(1, 50)).expect("");
+            assert_eq!(
+                new_events
+                    .iter()
+                    .map(|event| event.event_time)
+                    .collect::<Vec<_>>(),
+                vec![21, 25, 30]
+            );
+        }
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    fn new_window(
+        value: proto::common::window::Value,
+        allowed_lateness: u64,
+    ) -> proto::common::Window {
+        use proto::common::{Time, Window};
+
+        Window {
+            value: Some(value),
+            trigger: None,
+            allowed_lateness: Some(Time {
+                millis: allowed_lateness,
+                seconds: 0,
+                minutes: 0,
+                hours: 0,
+            }),
+            side_output: Some(3),
+        }
+    }
+
+    fn get_window_panes(events: Vec<proto::common::KeyedDataEvent>) -> Vec<(i64, i64, Vec<i64>)> {
+        events
+            .iter()
+            .map(|event| {
+                let window = event.window.clone().unwrap_or_default();
+                (window.start_time, window.end_time, get_dedup_ids(event))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_window_assigner() {
+        use super::WindowAssigner;
+
+        let fixed = WindowAssigner::Fixed { size: 10 };
+        assert_eq!(fixed.assign(0), vec![(0, 10)]);
+        assert_eq!(fixed.assign(19), vec![(10, 20)]);
+        assert_eq!(fixed.assign(-1), vec![(-10, 0)]);
+
+        let sliding = WindowAssigner::Sliding {
+            size: 10,
+            period: 5,
+        };
+        assert_eq!(sliding.assign(7), vec![(0, 10), (5, 15)]);
+        assert_eq!(sliding.assign(10), vec![(5, 15), (10, 20)]);
+    }
+
+    #[test]
+    fn test_window_operator_allowed_lateness() {
+        use super::WindowOperator;
+        use crate::state::{MemoryStateManager, StateManager};
+        use proto::common::{window, Time};
+
+        let state_manager = MemoryStateManager::new();
+        let operator = WindowOperator::new(
+            1,
+            &new_window(
+                window::Value::Fixed(window::FixedWindow {
+                    size: Some(Time {
+                        millis: 10,
+                        seconds: 0,
+                        minutes: 0,
+                        hours: 0,
+                    }),
+                }),
+                5,
+            ),
+            &state_manager,
+        );
+
+        for event_time in [1, 5] {
+            assert!(operator
+                .window(&new_sort_event(1, event_time))
+                .expect("")
+                .is_empty());
+        }
+        assert!(operator.window(&new_sort_event(2, 3)).expect("").is_empty());
+
+        // the windows are emitted once the max event time passes their end
+        let new_events = operator.window(&new_sort_event(1, 12)).expect("");
+        assert_eq!(
+            get_window_panes(new_events.clone()),
+            vec![(0, 10, vec![1, 5]), (0, 10, vec![3])]
+        );
+        assert!(new_events
+            .iter()
+            .all(|event| event.from_operator_id == 1 && event.to_operator_id == 1));
+
+        // the late event within the lateness updates the window and it's emitted again
+        let new_events = operator.window(&new_sort_event(1, 8)).expect("");
+        assert_eq!(get_window_panes(new_events), vec![(0, 10, vec![1, 5, 8])]);
+
+        // the windows are purged after the watermark passes their end
+        assert!(operator
+            .window(&new_sort_event(1, 16))
+            .expect("")
+            .is_empty());
+        assert_eq!(
+            state_manager.scan_keyed_state("window-1:".as_bytes()).len(),
+            1
+        );
+
+        // the late event beyond the lateness is emitted to the side output
+        let new_events = operator.window(&new_sort_event(2, 9)).expect("");
+        assert_eq!(new_events.len(), 1);
+        assert_eq!(new_events[0].to_operator_id, 3);
+        assert_eq!(new_events[0].event_time, 9);
+        assert!(new_events[0].window.is_none());
+
+        let new_events = operator.window(&new_sort_event(1, 25)).expect("");
+        assert_eq!(get_window_panes(new_events), vec![(10, 20, vec![12, 16])]);
+    }
+
+    #[test]
+    fn test_sliding_window_operator_allowed_lateness() {
+        use super::WindowOperator;
+        use crate::state::MemoryStateManager;
+        use proto::common::{window, Time};
+
+        let new_time = |millis| Time {
+            millis,
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+        };
+        let operator = WindowOperator::new(
+            1,
+            &new_window(
+                window::Value::Slide(window::SlidingWindow {
+                    size: Some(new_time(10)),
+                    period: Some(new_time(5)),
+                }),
+                3,
+            ),
+            MemoryStateManager::new(),
+        );
+
+        assert!(operator.window(&new_sort_event(1, 7)).expect("").is_empty());
+        assert_eq!(
+            get_window_panes(operator.window(&new_sort_event(1, 11)).expect("")),
+            vec![(0, 10, vec![7])]
+        );
+
+        // [0, 10) is purged since the watermark is 10, but the event is still within the lateness of [5, 15)
+        assert!(operator
+            .window(&new_sort_event(1, 13))
+            .expect("")
+            .is_empty());
+        assert!(operator.window(&new_sort_event(1, 9)).expect("").is_empty());
+        assert_eq!(
+            get_window_panes(operator.window(&new_sort_event(1, 15)).expect("")),
+            vec![(5, 15, vec![7, 11, 13, 9])]
+        );
+
+        let invalid = WindowOperator::new(
+            1,
+            &new_window(
+                window::Value::Session(window::SessionWindow {
+                    timeout: Some(new_time(10)),
+                }),
+                0,
+            ),
+            MemoryStateManager::new(),
+        );
+        assert!(invalid.window(&new_sort_event(1, 7)).is_err());
+    }
 }
@@ -2,17 +2,16 @@ use std::{
     fmt::Display,
     marker::PhantomData,
     task::{Context, Poll},
+    time::Duration,
     vec,
 };
 
 use common::{
-    collections::lang,
     event::{LocalEvent, StreamEvent},
     net::gateway::taskmanager::SafeTaskManagerRpcGateway,
     types::ExecutorId,
 };
 use proto::common::{KeyedDataEvent, KeyedEventSet, ResourceId};
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use tokio::sync::mpsc::error::TrySendError;
 use tonic::async_trait;
 
@@ -89,29 +88,22 @@ impl<T: StreamEvent> OutEdge for LocalOutEdge<T> {
         _from_operator_id: ExecutorId,
         iter: Vec<Self::Output>,
     ) -> Result<(), OutEdgeError> {
-        let failed_events = iter
-            .into_par_iter()
-            .map(|mut event| {
-                let event_id = event.event_id();
-                event.set_to_operator_id(to_operator_id);
-                match self.try_write(event) {
-                    Ok(_) => (event_id, None),
-                    Err(err) => (event_id, Some(err)),
-                }
-            })
-            .collect::<Vec<_>>();
-        if lang::all_match(&failed_events, |(_, err)| err.is_none()) {
+        // the events are sent in order, and the ones after a failed event are not sent so that they can't overtake it
+        let mut errors = vec![];
+        for mut event in iter {
+            let event_id = event.event_id();
+            if !errors.is_empty() {
+                errors.push((event_id, OutEdgeError::Unsent));
+                continue;
+            }
+            event.set_to_operator_id(to_operator_id);
+            if let Err(err) = self.try_write(event) {
+                errors.push((event_id, err));
+            }
+        }
+        if errors.is_empty() {
             Ok(())
         } else {
-            let mut errors = vec![];
-            failed_events
-                .into_iter()
-                .filter(|(_, err)| err.is_some())
-                .for_each(|(event_id, opt)| {
-                    let err = opt.unwrap();
-                    errors.push((event_id, err));
-                });
-
             Err(OutEdgeError::BatchSendFailed(errors))
         }
     }
@@ -125,6 +117,34 @@ pub enum OutEdgeError {
     QueueFull,
     QueueClosed,
     BatchSendFailed(Vec<(i64, OutEdgeError)>),
+    /// the event is not sent because an earlier event of the same batch failed
+    Unsent,
+    /// the remote operator doesn't accept the events and asks to retry after the delay
+    RemoteBackpressure(Duration),
+}
+
+impl OutEdgeError {
+    /// Only transient failures of remote out edges are retryable: the remote TaskManager is unavailable,
+    /// the request times out or the remote operator asks to retry by a backpressure hint.
+    /// Failures of local batches are not retryable because some events of the batch may have been sent.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::SendToRemoteFailed(status) => matches!(
+                status.code(),
+                tonic::Code::Unavailable | tonic::Code::DeadlineExceeded
+            ),
+            Self::RemoteBackpressure(_) => true,
+            _ => false,
+        }
+    }
+
+    /// the delay which the remote operator asks the sender to wait before retrying
+    pub fn get_retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RemoteBackpressure(delay) => Some(*delay),
+            _ => None,
+        }
+    }
 }
 
 impl From<rmp_serde::encode::Error> for OutEdgeError {
@@ -145,9 +165,16 @@ impl Display for OutEdgeError {
             OutEdgeError::EncodeError(err) => f.write_fmt(format_args!("RmpEncodeError: {}", err)),
             OutEdgeError::QueueFull => f.write_str("Local queue is full"),
             OutEdgeError::QueueClosed => f.write_str("local queue is closed"),
+            OutEdgeError::Unsent => {
+                f.write_str("event is not sent after an earlier failure of the batch")
+            }
             OutEdgeError::BatchSendFailed(errors) => {
                 f.write_fmt(format_args!("Batchly send event failed: [{:?}]", errors))
             }
+            OutEdgeError::RemoteBackpressure(delay) => f.write_fmt(format_args!(
+                "remote operator asks to retry after {:?}",
+                delay
+            )),
         }
     }
 }
@@ -176,8 +203,8 @@ impl OutEdge for RemoteOutEdge {
                 .gateway
                 .send_event_to_operator(event)
                 .await
-                .map(|_| ())
-                .map_err(|err| OutEdgeError::SendToRemoteFailed(err)),
+                .map_err(|err| OutEdgeError::SendToRemoteFailed(err))
+                .and_then(|resp| check_retry_after(resp.retry_after_ms)),
         }
     }
 
@@ -204,8 +231,19 @@ impl OutEdge for RemoteOutEdge {
                 from_operator_id,
             })
             .await
-            .map(|_| {})
             .map_err(|err| OutEdgeError::SendToRemoteFailed(err))
+            .and_then(|resp| check_retry_after(resp.retry_after_ms))
+    }
+}
+
+/// the events are not accepted by the remote operator if there is a backpressure hint
+fn check_retry_after(retry_after_ms: u64) -> Result<(), OutEdgeError> {
+    if retry_after_ms > 0 {
+        Err(OutEdgeError::RemoteBackpressure(Duration::from_millis(
+            retry_after_ms,
+        )))
+    } else {
+        Ok(())
     }
 }
 
@@ -268,12 +306,14 @@ impl<T> LocalInEdge<T> {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use common::event::LocalEvent;
     use proto::common::KeyedDataEvent;
 
     use crate::{edge::InEdge, new_event_channel};
 
-    use super::{LocalInEdge, LocalOutEdge, OutEdge};
+    use super::{check_retry_after, LocalInEdge, LocalOutEdge, OutEdge, OutEdgeError};
 
     #[tokio::test]
     async fn test_local_edge_success() {
@@ -290,4 +330,64 @@ mod tests {
         let opt = in_edge.next().await;
         assert!(opt.is_some());
     }
+
+    #[tokio::test]
+    async fn test_local_batch_write_keeps_order() {
+        let (tx, rx) = new_event_channel(2);
+        let mut in_edge = LocalInEdge::<LocalEvent>::new(rx);
+        let out_edge = LocalOutEdge::<LocalEvent>::new(tx);
+
+        let events = (0..4)
+            .map(|event_id| {
+                LocalEvent::KeyedDataStreamEvent(KeyedDataEvent {
+                    event_id,
+                    to_operator_id: 1,
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+        match out_edge.batch_write(&None, 1, 0, events.clone()).await {
+            Err(OutEdgeError::BatchSendFailed(errors)) => {
+                assert_eq!(
+                    errors
+                        .iter()
+                        .map(|(event_id, _)| *event_id)
+                        .collect::<Vec<_>>(),
+                    vec![2, 3]
+                );
+                assert!(matches!(errors[0].1, OutEdgeError::QueueFull));
+                assert!(matches!(errors[1].1, OutEdgeError::Unsent));
+            }
+            _ => panic!("unexpected result"),
+        }
+
+        // the events in the queue are the first ones of the batch
+        assert_eq!(in_edge.next().await, Some(events[0].clone()));
+        assert_eq!(in_edge.next().await, Some(events[1].clone()));
+    }
+
+    #[test]
+    fn test_out_edge_error_retryable() {
+        assert!(OutEdgeError::SendToRemoteFailed(tonic::Status::unavailable("")).is_retryable());
+        assert!(
+            OutEdgeError::SendToRemoteFailed(tonic::Status::deadline_exceeded("")).is_retryable()
+        );
+        assert!(
+            !OutEdgeError::SendToRemoteFailed(tonic::Status::invalid_argument("")).is_retryable()
+        );
+        // resource exhausted is only retried with a backpressure hint
+        assert!(
+            !OutEdgeError::SendToRemoteFailed(tonic::Status::resource_exhausted("")).is_retryable()
+        );
+        assert!(OutEdgeError::RemoteBackpressure(Duration::from_millis(5)).is_retryable());
+        assert!(!OutEdgeError::QueueFull.is_retryable());
+
+        assert!(check_retry_after(0).is_ok());
+        assert_eq!(
+            check_retry_after(5)
+                .err()
+                .and_then(|err| err.get_retry_after()),
+            Some(Duration::from_millis(5))
+        );
+    }
 }
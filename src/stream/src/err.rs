@@ -1,21 +1,45 @@
-use std::fmt::{self, Display};
+use std::{
+    fmt::{self, Display},
+    time::Duration,
+};
 
 use common::{
     err::{KafkaException, RedisException},
     event::KafkaEventError,
-    types::NodeIdx,
+    formats::{avro::AvroError, csv::CsvError},
+    lookup::LookupError,
+    project::ProjectError,
+    schema::SchemaError,
+    sql_expr::EvalError,
+    tap::TapError,
+    throttle::ThrottleError,
+    types::{ExecutorId, NodeIdx},
 };
 
-use crate::edge::OutEdgeError;
+use crate::{edge::OutEdgeError, wasm::WasmUdfError};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ErrorKind {
     InvalidMessageType,
     MessageSendFailed,
-    KafkaMessageSendFailed,
-    SqlExecutionFailed,
-    EventSentToRemoteFailed,
-    RedisSinkFailed,
+    /// the remote sink can't be reached, e.g. the connection is refused or the request times out
+    RemoteTransportFailed,
+    /// the remote sink receives the events but rejects them, e.g. a constraint violation
+    RemoteSinkRejected,
+    JsonEncodeFailed,
+    CsvEncodeFailed,
+    AvroEncodeFailed,
+    /// the record key or partition can't be derived from the event, e.g. the configured payload field is missing
+    InvalidRecord,
+    /// the buffer of a batching sink is full because its batches keep failing
+    BufferFull,
+}
+
+impl ErrorKind {
+    /// Only transport failures and full buffers are retryable by default. Rejected events will be rejected again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::RemoteTransportFailed | Self::BufferFull)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -24,10 +48,39 @@ pub struct SinkException {
     pub msg: String,
 }
 
+impl SinkException {
+    pub fn is_retryable(&self) -> bool {
+        self.kind.is_retryable()
+    }
+}
+
+fn get_sql_error_kind(err: &sqlx::Error) -> ErrorKind {
+    match err {
+        sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_)
+        | sqlx::Error::PoolTimedOut
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::WorkerCrashed => ErrorKind::RemoteTransportFailed,
+        _ => ErrorKind::RemoteSinkRejected,
+    }
+}
+
+fn get_redis_error_kind(err: &RedisException) -> ErrorKind {
+    if err.is_transport_failure() {
+        ErrorKind::RemoteTransportFailed
+    } else {
+        ErrorKind::RemoteSinkRejected
+    }
+}
+
 impl From<KafkaException> for SinkException {
     fn from(err: KafkaException) -> Self {
         Self {
-            kind: ErrorKind::KafkaMessageSendFailed,
+            kind: if err.is_transport_failure() {
+                ErrorKind::RemoteTransportFailed
+            } else {
+                ErrorKind::RemoteSinkRejected
+            },
             msg: format!("message detail: {}", err),
         }
     }
@@ -36,7 +89,7 @@ impl From<KafkaException> for SinkException {
 impl From<sqlx::Error> for SinkException {
     fn from(err: sqlx::Error) -> Self {
         Self {
-            kind: ErrorKind::SqlExecutionFailed,
+            kind: get_sql_error_kind(&err),
             msg: format!("{}", err),
         }
     }
@@ -45,7 +98,7 @@ impl From<sqlx::Error> for SinkException {
 impl From<&mut sqlx::Error> for SinkException {
     fn from(err: &mut sqlx::Error) -> Self {
         Self {
-            kind: ErrorKind::SqlExecutionFailed,
+            kind: get_sql_error_kind(err),
             msg: format!("{}", err),
         }
     }
@@ -54,7 +107,7 @@ impl From<&mut sqlx::Error> for SinkException {
 impl From<&mut RedisException> for SinkException {
     fn from(err: &mut RedisException) -> Self {
         Self {
-            kind: ErrorKind::RedisSinkFailed,
+            kind: get_redis_error_kind(err),
             msg: format!("{:?}", err),
         }
     }
@@ -63,7 +116,7 @@ impl From<&mut RedisException> for SinkException {
 impl From<RedisException> for SinkException {
     fn from(err: RedisException) -> Self {
         Self {
-            kind: ErrorKind::RedisSinkFailed,
+            kind: get_redis_error_kind(&err),
             msg: format!("{:?}", err),
         }
     }
@@ -72,16 +125,34 @@ impl From<RedisException> for SinkException {
 impl From<KafkaEventError> for SinkException {
     fn from(err: KafkaEventError) -> Self {
         Self {
-            kind: ErrorKind::KafkaMessageSendFailed,
+            kind: ErrorKind::JsonEncodeFailed,
             msg: format!("{:?}", err),
         }
     }
 }
 
+impl From<CsvError> for SinkException {
+    fn from(err: CsvError) -> Self {
+        Self {
+            kind: ErrorKind::CsvEncodeFailed,
+            msg: format!("{}", err),
+        }
+    }
+}
+
+impl From<AvroError> for SinkException {
+    fn from(err: AvroError) -> Self {
+        Self {
+            kind: ErrorKind::AvroEncodeFailed,
+            msg: format!("{}", err),
+        }
+    }
+}
+
 impl From<&mut tonic::transport::Error> for SinkException {
     fn from(err: &mut tonic::transport::Error) -> Self {
         Self {
-            kind: ErrorKind::EventSentToRemoteFailed,
+            kind: ErrorKind::RemoteTransportFailed,
             msg: err.to_string(),
         }
     }
@@ -138,9 +209,43 @@ impl From<sqlx::Error> for BatchSinkException {
     }
 }
 
+/// a row of a message fetched by the source which fails to be decoded
+#[derive(Debug, Clone)]
+pub struct DecodeFailure {
+    pub format: &'static str,
+    pub topic: String,
+    /// the number of the row in the payload. A message of Avro or Protobuf has one row
+    pub row: u64,
+    pub message: String,
+}
+
+impl Display for DecodeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "decode {} of topic [{}] failed at row [{}]: {}",
+            self.format, self.topic, self.row, self.message
+        ))
+    }
+}
+
 #[derive(Debug)]
 pub enum ExecutionError {
     OperatorUnimplemented(NodeIdx),
+    ProjectFailed(ProjectError),
+    ThrottleFailed(ThrottleError),
+    DeduplicateFailed(String),
+    SortBufferFailed(String),
+    WindowFailed(String),
+    WasmUdfFailed(WasmUdfError),
+    SchemaViolation(SchemaError),
+    SqlExprFailed(EvalError),
+    AsyncLookupFailed(LookupError),
+    /// the keyed state of the operator exceeds its max bytes with the `Fail` policy
+    StateLimitExceeded(ExecutorId, u64),
+    /// the event is not processed in the processing timeout of the operator, so it's abandoned
+    ProcessingTimeout(Duration),
+    /// a row of the message fetched by the source fails to be decoded
+    DecodeFailed(DecodeFailure),
 }
 
 impl fmt::Display for ExecutionError {
@@ -149,6 +254,30 @@ impl fmt::Display for ExecutionError {
             Self::OperatorUnimplemented(operator_id) => {
                 f.write_str(format!("operator {} does not implement", operator_id).as_str())
             }
+            Self::ProjectFailed(err) => f.write_fmt(format_args!("project failed: {}", err)),
+            Self::ThrottleFailed(err) => f.write_fmt(format_args!("throttle failed: {}", err)),
+            Self::DeduplicateFailed(msg) => {
+                f.write_fmt(format_args!("deduplicate failed: {}", msg))
+            }
+            Self::SortBufferFailed(msg) => f.write_fmt(format_args!("sort buffer failed: {}", msg)),
+            Self::WindowFailed(msg) => f.write_fmt(format_args!("window failed: {}", msg)),
+            Self::WasmUdfFailed(err) => f.write_fmt(format_args!("wasm udf failed: {}", err)),
+            Self::SchemaViolation(err) => {
+                f.write_fmt(format_args!("input schema is violated: {}", err))
+            }
+            Self::SqlExprFailed(err) => f.write_fmt(format_args!("sql expression failed: {}", err)),
+            Self::AsyncLookupFailed(err) => {
+                f.write_fmt(format_args!("async lookup failed: {}", err))
+            }
+            Self::StateLimitExceeded(operator_id, max_bytes) => f.write_fmt(format_args!(
+                "state of operator {} exceeds the limit of {} bytes",
+                operator_id, max_bytes
+            )),
+            Self::ProcessingTimeout(timeout) => f.write_fmt(format_args!(
+                "processing timeout: the event is not processed in {:?}",
+                timeout
+            )),
+            Self::DecodeFailed(failure) => failure.fmt(f),
         }
     }
 }
@@ -156,12 +285,118 @@ impl fmt::Display for ExecutionError {
 #[derive(Debug)]
 pub enum TaskError {
     OutEdgeError(OutEdgeError),
+    InvalidThrottle(ThrottleError),
+    ThrottleUnsupported(ExecutorId),
+    ExecutorUnavailable(ExecutorId),
+    DrainInterrupted(ExecutorId),
+    InvalidTap(TapError),
+    SavepointInterrupted(ExecutorId),
+    InvalidSink(String),
+    SinkUnsupported(ExecutorId),
+    SinkUpdateFailed(ExecutorId, String),
 }
 
 impl fmt::Display for TaskError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             TaskError::OutEdgeError(err) => f.write_fmt(format_args!("out edge error [{}]", err)),
+            TaskError::InvalidThrottle(err) => f.write_fmt(format_args!("{}", err)),
+            TaskError::ThrottleUnsupported(executor_id) => f.write_fmt(format_args!(
+                "operator {} is not a throttle operator",
+                executor_id
+            )),
+            TaskError::ExecutorUnavailable(executor_id) => f.write_fmt(format_args!(
+                "executor of operator {} is not running",
+                executor_id
+            )),
+            TaskError::DrainInterrupted(executor_id) => f.write_fmt(format_args!(
+                "drain of operator {} is interrupted",
+                executor_id
+            )),
+            TaskError::InvalidTap(err) => f.write_fmt(format_args!("{}", err)),
+            TaskError::SavepointInterrupted(executor_id) => f.write_fmt(format_args!(
+                "savepoint of operator {} is interrupted",
+                executor_id
+            )),
+            TaskError::InvalidSink(err) => f.write_fmt(format_args!("{}", err)),
+            TaskError::SinkUnsupported(executor_id) => f.write_fmt(format_args!(
+                "operator {} is not a sink operator",
+                executor_id
+            )),
+            TaskError::SinkUpdateFailed(executor_id, err) => f.write_fmt(format_args!(
+                "update sink of operator {} failed: {}",
+                executor_id, err
+            )),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use common::err::{KafkaException, RedisException};
+    use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+
+    use super::{ErrorKind, SinkException};
+
+    #[test]
+    fn test_sql_error_classification() {
+        let refused = sqlx::Error::Io(std::io::Error::from(std::io::ErrorKind::ConnectionRefused));
+        let exception = SinkException::from(refused);
+        assert_eq!(exception.kind, ErrorKind::RemoteTransportFailed);
+        assert!(exception.is_retryable());
+
+        let exception = SinkException::from(sqlx::Error::PoolTimedOut);
+        assert_eq!(exception.kind, ErrorKind::RemoteTransportFailed);
+        assert!(exception.is_retryable());
+
+        let exception = SinkException::from(sqlx::Error::RowNotFound);
+        assert_eq!(exception.kind, ErrorKind::RemoteSinkRejected);
+        assert!(!exception.is_retryable());
+
+        let exception = SinkException::from(sqlx::Error::ColumnNotFound("id".to_string()));
+        assert_eq!(exception.kind, ErrorKind::RemoteSinkRejected);
+        assert!(!exception.is_retryable());
+    }
+
+    #[test]
+    fn test_redis_error_classification() {
+        let exception = SinkException::from(RedisException::ConnectFailed(
+            "Connection refused (os error 111)".to_string(),
+        ));
+        assert_eq!(exception.kind, ErrorKind::RemoteTransportFailed);
+        assert!(exception.is_retryable());
+
+        let exception = SinkException::from(RedisException::SetValueFailed(
+            "WRONGTYPE: Operation against a key holding the wrong kind of value".to_string(),
+        ));
+        assert_eq!(exception.kind, ErrorKind::RemoteSinkRejected);
+        assert!(!exception.is_retryable());
+    }
+
+    #[test]
+    fn test_kafka_error_classification() {
+        let exception = SinkException::from(KafkaException {
+            err: KafkaError::MessageProduction(RDKafkaErrorCode::MessageTimedOut),
+        });
+        assert_eq!(exception.kind, ErrorKind::RemoteTransportFailed);
+        assert!(exception.is_retryable());
+
+        let exception = SinkException::from(KafkaException {
+            err: KafkaError::MessageProduction(RDKafkaErrorCode::AllBrokersDown),
+        });
+        assert_eq!(exception.kind, ErrorKind::RemoteTransportFailed);
+
+        let exception = SinkException::from(KafkaException {
+            err: KafkaError::MessageProduction(RDKafkaErrorCode::MessageSizeTooLarge),
+        });
+        assert_eq!(exception.kind, ErrorKind::RemoteSinkRejected);
+        assert!(!exception.is_retryable());
+    }
+
+    #[test]
+    fn test_encode_error_is_not_retryable() {
+        let exception = SinkException::from(common::event::KafkaEventError::UnsupportedEvent);
+        assert_eq!(exception.kind, ErrorKind::JsonEncodeFailed);
+        assert!(!exception.is_retryable());
+    }
+}
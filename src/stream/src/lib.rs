@@ -1,10 +1,15 @@
+pub mod batching;
 pub mod connector;
-mod dataflow;
+pub mod dataflow;
 pub mod edge;
 pub mod err;
-mod state;
+pub mod offsets;
+pub mod policy;
+pub mod state;
 pub mod task;
 mod v8_runtime;
+pub mod wasm;
+pub mod watchdog;
 
 pub type Receiver<Output> = tokio::sync::mpsc::Receiver<Output>;
 pub type Sender<Output> = tokio::sync::mpsc::Sender<Output>;
@@ -0,0 +1,215 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
+
+use common::types::SourceId;
+use prost::Message;
+use proto::common::ResourceId;
+
+/// the next offsets to consume of the partitions of a source, keyed by partition
+pub type SourceOffsets = BTreeMap<i32, i64>;
+
+/// a store shared by the sources of all jobs of a TaskManager
+pub type SharedOffsetStore = Arc<Mutex<Box<dyn OffsetStore>>>;
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub enum OffsetStoreBuilder {
+    /// Offsets are stored in a sled database on the local disk.
+    /// The writes are left to the background flush of sled, so the latest commits may be lost if the process crashes,
+    /// and then the sources resume from earlier offsets, so some events are delivered again.
+    Local { offset_store_path: String },
+    /// Offsets are stored in memory. They're lost once the TaskManager exits, so it's for test and development.
+    Memory,
+}
+
+impl OffsetStoreBuilder {
+    pub fn build(&self) -> Box<dyn OffsetStore> {
+        match self {
+            Self::Local { offset_store_path } => Box::new(LocalOffsetStore::new(offset_store_path)),
+            Self::Memory => Box::new(MemOffsetStore::default()),
+        }
+    }
+
+    pub fn build_shared(&self) -> SharedOffsetStore {
+        Arc::new(Mutex::new(self.build()))
+    }
+}
+
+/// [`OffsetStore`] keeps the positions of the sources of the jobs, so that a source resumes from its committed offsets after it restarts.
+/// The sources commit the offsets of the events which have been sent to all their downstreams
+pub trait OffsetStore: Send + Sync {
+    /// replace the committed offsets of the source
+    fn commit(
+        &mut self,
+        job_id: &ResourceId,
+        source_id: SourceId,
+        offsets: &SourceOffsets,
+    ) -> Result<(), OffsetStoreError>;
+    /// the committed offsets of the source, it's none if the source has never committed
+    fn load(&self, job_id: &ResourceId, source_id: SourceId) -> Option<SourceOffsets>;
+}
+
+/// the key of the offsets of a source. The encoded job id is followed by the source id, which has a fixed length
+fn offset_key(job_id: &ResourceId, source_id: SourceId) -> Vec<u8> {
+    let mut key = job_id.encode_to_vec();
+    key.extend_from_slice(&source_id.to_be_bytes());
+    key
+}
+
+/// each offset is encoded as the partition followed by the offset, both in big-endian
+fn encode_offsets(offsets: &SourceOffsets) -> Vec<u8> {
+    offsets
+        .iter()
+        .flat_map(|(partition, offset)| {
+            partition
+                .to_be_bytes()
+                .into_iter()
+                .chain(offset.to_be_bytes())
+        })
+        .collect()
+}
+
+fn decode_offsets(bytes: &[u8]) -> SourceOffsets {
+    bytes
+        .chunks_exact(12)
+        .map(|chunk| {
+            let (partition, offset) = chunk.split_at(4);
+            (
+                i32::from_be_bytes(partition.try_into().unwrap_or_default()),
+                i64::from_be_bytes(offset.try_into().unwrap_or_default()),
+            )
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct LocalOffsetStore {
+    db: sled::Db,
+}
+
+impl LocalOffsetStore {
+    pub fn new<P: AsRef<std::path::Path>>(path: P) -> Self {
+        let db = sled::open(path).expect("open sleddb failed");
+        Self { db }
+    }
+}
+
+impl OffsetStore for LocalOffsetStore {
+    fn commit(
+        &mut self,
+        job_id: &ResourceId,
+        source_id: SourceId,
+        offsets: &SourceOffsets,
+    ) -> Result<(), OffsetStoreError> {
+        self.db
+            .insert(offset_key(job_id, source_id), encode_offsets(offsets))
+            .map(|_| {})
+            .map_err(OffsetStoreError::CommitFailed)
+    }
+
+    fn load(&self, job_id: &ResourceId, source_id: SourceId) -> Option<SourceOffsets> {
+        self.db
+            .get(offset_key(job_id, source_id))
+            .map_err(|err| {
+                tracing::error!("load offsets failed: {}", OffsetStoreError::LoadFailed(err))
+            })
+            .ok()
+            .flatten()
+            .map(|value| decode_offsets(&value))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemOffsetStore {
+    offsets: BTreeMap<(ResourceId, SourceId), SourceOffsets>,
+}
+
+impl OffsetStore for MemOffsetStore {
+    fn commit(
+        &mut self,
+        job_id: &ResourceId,
+        source_id: SourceId,
+        offsets: &SourceOffsets,
+    ) -> Result<(), OffsetStoreError> {
+        self.offsets
+            .insert((job_id.clone(), source_id), offsets.clone());
+        Ok(())
+    }
+
+    fn load(&self, job_id: &ResourceId, source_id: SourceId) -> Option<SourceOffsets> {
+        self.offsets.get(&(job_id.clone(), source_id)).cloned()
+    }
+}
+
+#[derive(Debug)]
+pub enum OffsetStoreError {
+    CommitFailed(sled::Error),
+    LoadFailed(sled::Error),
+}
+
+impl Display for OffsetStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OffsetStoreError::CommitFailed(err) => {
+                f.write_fmt(format_args!("commit offsets failed: {}", err))
+            }
+            OffsetStoreError::LoadFailed(err) => {
+                f.write_fmt(format_args!("load offsets failed: {}", err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proto::common::ResourceId;
+
+    use super::{LocalOffsetStore, MemOffsetStore, OffsetStore, SourceOffsets};
+
+    fn assert_commit_and_load(store: &mut dyn OffsetStore) {
+        let job_id = ResourceId::new("default", "offsets");
+        assert_eq!(store.load(&job_id, 0), None);
+
+        let offsets = SourceOffsets::from_iter([(0, 10), (1, i64::MAX), (-1, 0)]);
+        assert!(store.commit(&job_id, 0, &offsets).is_ok());
+        assert_eq!(store.load(&job_id, 0), Some(offsets));
+
+        // a commit replaces the offsets of the source only
+        let offsets = SourceOffsets::from_iter([(0, 12)]);
+        assert!(store.commit(&job_id, 0, &offsets).is_ok());
+        assert!(store
+            .commit(&job_id, 1, &SourceOffsets::from_iter([(0, 1)]))
+            .is_ok());
+        assert_eq!(store.load(&job_id, 0), Some(offsets));
+        assert_eq!(
+            store.load(&job_id, 1),
+            Some(SourceOffsets::from_iter([(0, 1)]))
+        );
+        assert_eq!(store.load(&ResourceId::new("default", "other"), 0), None);
+    }
+
+    #[test]
+    fn test_mem_offset_store() {
+        assert_commit_and_load(&mut MemOffsetStore::default());
+    }
+
+    #[test]
+    fn test_local_offset_store() {
+        let path = std::env::temp_dir().join(format!(
+            "lightflus-offsets-{}",
+            common::utils::times::now_timestamp()
+        ));
+        assert_commit_and_load(&mut LocalOffsetStore::new(&path));
+
+        // the committed offsets survive a restart
+        let store = LocalOffsetStore::new(&path);
+        assert_eq!(
+            store.load(&ResourceId::new("default", "offsets"), 0),
+            Some(SourceOffsets::from_iter([(0, 12)]))
+        );
+        drop(store);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+}
@@ -0,0 +1,913 @@
+use std::{fmt, future::Future, time::Duration};
+
+use common::{
+    backoff::{Backoff, BackoffBuilder},
+    event::LocalEvent,
+    types::{ExecutorId, SinkId},
+};
+use futures_util::future::Either;
+use proto::common::{
+    error_policy, ErrorPolicy, KeyedDataEvent, KeyedEventSet, OperatorErrorKind, ResourceId,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    connector::Sink,
+    edge::{OutEdge, OutEdgeError},
+    err::{ExecutionError, SinkException},
+    task::ErrorReporter,
+    wasm::WasmUdfError,
+};
+
+pub use common::metrics::{
+    ERROR_POLICY_DEAD_LETTERED_METRIC, ERROR_POLICY_FAILED_METRIC, ERROR_POLICY_SKIPPED_METRIC,
+};
+
+pub const ERROR_POLICY_RETRIED_METRIC: &str = "error_policy_retried";
+/// sends to external sinks and out edges which are abandoned because the operator is cancelled
+pub const ERROR_POLICY_CANCELLED_METRIC: &str = "error_policy_cancelled";
+/// sends to external sinks and out edges which succeed at the first attempt
+pub const SEND_FIRST_ATTEMPT_METRIC: &str = "send_succeeded_first_attempt";
+/// sends to external sinks and out edges which succeed after retries
+pub const SEND_AFTER_RETRY_METRIC: &str = "send_succeeded_after_retry";
+/// the header of a dead-lettered event which tells why it failed, e.g. `processing timeout: ...`.
+/// It's set for timed out events and the rows of source messages which fail to be decoded
+pub const ERROR_REASON_HEADER: &str = "lightflus.error_reason";
+
+/// [`Policy`] is the error policy of an operator resolved from [`ErrorPolicy`]. Failed events are skipped if the policy is not set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Policy {
+    Fail,
+    Skip,
+    Retry {
+        attempts: u32,
+        backoff: BackoffBuilder,
+        fallback: Box<Policy>,
+    },
+    DeadLetter(ExecutorId),
+}
+
+impl From<Option<&ErrorPolicy>> for Policy {
+    fn from(error_policy: Option<&ErrorPolicy>) -> Self {
+        match error_policy.and_then(|error_policy| error_policy.policy.as_ref()) {
+            Some(error_policy::Policy::Fail(_)) => Self::Fail,
+            Some(error_policy::Policy::Skip(_)) | None => Self::Skip,
+            Some(error_policy::Policy::Retry(retry)) => Self::Retry {
+                attempts: retry.attempts,
+                backoff: retry
+                    .backoff
+                    .as_ref()
+                    .map(|backoff| BackoffBuilder {
+                        base: backoff.base,
+                        max: backoff.max,
+                        jitter: backoff.jitter,
+                    })
+                    .unwrap_or(BackoffBuilder {
+                        base: 0,
+                        max: 0,
+                        jitter: false,
+                    }),
+                fallback: Box::new(Self::from(retry.fallback.as_deref())),
+            },
+            Some(error_policy::Policy::DeadLetter(dead_letter)) => {
+                Self::DeadLetter(dead_letter.sink)
+            }
+        }
+    }
+}
+
+/// the errors which the error policy is applied to
+pub enum Failure<'a> {
+    Execution(&'a ExecutionError),
+    Sink(&'a SinkException),
+    OutEdge(&'a OutEdgeError),
+}
+
+impl Failure<'_> {
+    /// Sink and out edge failures are retryable only if they are transport failures.
+    /// Failures of the throttle configuration, of loading the WASM module, of violating the input schema, of evaluating a SQL expression,
+    /// of the processing timeout or of decoding a source message will happen again, so they are not retryable either. Lookups are retryable unless the operator or the payload is invalid.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Execution(ExecutionError::ThrottleFailed(_)) => false,
+            Self::Execution(ExecutionError::ProcessingTimeout(_)) => false,
+            Self::Execution(ExecutionError::SchemaViolation(_)) => false,
+            Self::Execution(ExecutionError::SqlExprFailed(_)) => false,
+            Self::Execution(ExecutionError::DecodeFailed(_)) => false,
+            Self::Execution(ExecutionError::AsyncLookupFailed(err)) => err.is_retryable(),
+            Self::Execution(ExecutionError::WasmUdfFailed(
+                WasmUdfError::LoadFailed(_) | WasmUdfError::InstantiateFailed(_),
+            )) => false,
+            Self::Execution(_) => true,
+            Self::Sink(err) => err.is_retryable(),
+            Self::OutEdge(err) => err.is_retryable(),
+        }
+    }
+
+    pub fn get_kind(&self) -> OperatorErrorKind {
+        match self {
+            Self::Execution(_) => OperatorErrorKind::Execution,
+            Self::Sink(_) => OperatorErrorKind::Sink,
+            Self::OutEdge(_) => OperatorErrorKind::OutEdge,
+        }
+    }
+
+    /// the backpressure hint of the remote operator
+    pub fn get_retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::OutEdge(err) => err.get_retry_after(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Failure<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Execution(err) => f.write_fmt(format_args!("process event failed: {}", err)),
+            Self::Sink(err) => err.fmt(f),
+            Self::OutEdge(err) => f.write_fmt(format_args!("send to out edge failed: {}", err)),
+        }
+    }
+}
+
+/// where the failed events come from
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Provenance {
+    pub job_id: ResourceId,
+    pub operator_id: ExecutorId,
+    pub from_operator_id: ExecutorId,
+    pub event_ids: Vec<u64>,
+    // the external sink which the events are sent to
+    pub sink_id: Option<SinkId>,
+    // the downstream operator which the events are sent to by the out edge
+    pub to_operator_id: Option<ExecutorId>,
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "[job_id: {:?}], [operator_id: {}], [from_operator_id: {}], [event_ids: {:?}], [sink_id: {:?}], [to_operator_id: {:?}]",
+            self.job_id,
+            self.operator_id,
+            self.from_operator_id,
+            self.event_ids,
+            self.sink_id,
+            self.to_operator_id
+        ))
+    }
+}
+
+/// what happens to the failed events once they are not retried anymore
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Failed,
+    Skipped,
+    DeadLettered(ExecutorId),
+    /// the operator is cancelled before the events are sent
+    Cancelled,
+}
+
+impl Outcome {
+    pub fn get_metric(&self) -> &'static str {
+        match self {
+            Self::Failed => ERROR_POLICY_FAILED_METRIC,
+            Self::Skipped => ERROR_POLICY_SKIPPED_METRIC,
+            Self::DeadLettered(_) => ERROR_POLICY_DEAD_LETTERED_METRIC,
+            Self::Cancelled => ERROR_POLICY_CANCELLED_METRIC,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// retry the failed events after the delay
+    Retry(Duration),
+    Resolve(Outcome),
+}
+
+/// retries of the same failed events
+#[derive(Debug, Default)]
+pub struct Retries {
+    count: u32,
+    backoff: Option<Backoff>,
+}
+
+impl Retries {
+    pub fn get_count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// result of sending events to an external sink or an out edge through [`ErrorHandler`]
+#[derive(Debug)]
+pub struct SinkOutcome {
+    pub retries: u32,
+    /// it's [`None`] if the events are sent successfully
+    pub outcome: Option<Outcome>,
+    /// the events which fail to be sent
+    pub events: Vec<KeyedDataEvent>,
+}
+
+/// [`ErrorHandler`] applies the error policy of an operator. All the errors of processing events, sinking events to external sinks
+/// and sending events to out edges go through it, so every operator handles errors in the same way.
+///
+/// Resolved failures are logged and reported with their provenance. Retries are only logged.
+/// Sends and the delays between their retries are abandoned once the cancellation token fires.
+#[derive(Clone)]
+pub struct ErrorHandler {
+    job_id: ResourceId,
+    executor_id: ExecutorId,
+    policy: Policy,
+    reporter: Option<ErrorReporter>,
+    cancellation: CancellationToken,
+}
+
+impl ErrorHandler {
+    pub fn new(
+        job_id: &ResourceId,
+        executor_id: ExecutorId,
+        error_policy: Option<&ErrorPolicy>,
+    ) -> Self {
+        Self {
+            job_id: job_id.clone(),
+            executor_id,
+            policy: Policy::from(error_policy),
+            reporter: None,
+            cancellation: Default::default(),
+        }
+    }
+
+    pub fn set_error_reporter(&mut self, reporter: ErrorReporter) {
+        self.reporter = Some(reporter);
+    }
+
+    pub fn set_cancellation_token(&mut self, cancellation: CancellationToken) {
+        self.cancellation = cancellation;
+    }
+
+    /// the output of the future, or `None` if the cancellation token fires first
+    async fn unless_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        if self.cancellation.is_cancelled() {
+            return None;
+        }
+        let cancelled = self.cancellation.cancelled();
+        futures_util::pin_mut!(future, cancelled);
+        match futures_util::future::select(future, cancelled).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+
+    fn cancelled(&self, retries: Retries, events: Vec<KeyedDataEvent>) -> SinkOutcome {
+        tracing::warn!(
+            "operator {} is cancelled, {} events are not sent after {} retries",
+            self.executor_id,
+            events.len(),
+            retries.count
+        );
+        SinkOutcome {
+            retries: retries.count,
+            outcome: Some(Outcome::Cancelled),
+            events,
+        }
+    }
+
+    pub fn get_provenance<'a, I: IntoIterator<Item = &'a KeyedDataEvent>>(
+        &self,
+        events: I,
+    ) -> Provenance {
+        let mut provenance = Provenance {
+            job_id: self.job_id.clone(),
+            operator_id: self.executor_id,
+            ..Default::default()
+        };
+        events.into_iter().for_each(|event| {
+            provenance.from_operator_id = event.from_operator_id;
+            provenance.event_ids.push(event.event_id as u64);
+        });
+        provenance
+    }
+
+    /// decide what to do with the failed events. `retries` should be kept until the events are resolved
+    pub fn handle(
+        &self,
+        failure: &Failure,
+        provenance: &Provenance,
+        retries: &mut Retries,
+    ) -> Decision {
+        let policy = match &self.policy {
+            Policy::Retry {
+                attempts,
+                backoff,
+                fallback,
+            } => {
+                if failure.is_retryable() && retries.count < *attempts {
+                    let delay = retries
+                        .backoff
+                        .get_or_insert_with(|| backoff.build())
+                        .next_delay();
+                    // the backpressure hint of the remote operator is respected
+                    let delay = failure
+                        .get_retry_after()
+                        .map_or(delay, |retry_after| retry_after.max(delay));
+                    retries.count += 1;
+                    tracing::warn!(
+                        "retry failed events [{}/{}] after {:?}: {}. error details: {}",
+                        retries.count,
+                        attempts,
+                        delay,
+                        provenance,
+                        failure
+                    );
+                    return Decision::Retry(delay);
+                }
+                fallback.as_ref()
+            }
+            policy => policy,
+        };
+
+        let outcome = match policy {
+            Policy::Fail => Outcome::Failed,
+            Policy::DeadLetter(sink) => Outcome::DeadLettered(*sink),
+            // the fallback can't be another Retry
+            Policy::Skip | Policy::Retry { .. } => Outcome::Skipped,
+        };
+        tracing::error!(
+            "events failed after {} retries, outcome: {:?}: {}. error details: {}",
+            retries.count,
+            outcome,
+            provenance,
+            failure
+        );
+        self.reporter.iter().for_each(|reporter| {
+            reporter.report(failure.get_kind(), format!("{}. {}", failure, provenance))
+        });
+        Decision::Resolve(outcome)
+    }
+
+    /// send the events to the external sink and retry the failures as the policy.
+    /// Events which have been sent before the failure will be sent again
+    pub async fn batch_sink<S: Sink + Send>(
+        &self,
+        sink: &mut S,
+        event_set: KeyedEventSet,
+    ) -> SinkOutcome {
+        let mut provenance = self.get_provenance(&event_set.events);
+        provenance.sink_id = Some(sink.sink_id());
+        let mut retries = Retries::default();
+        loop {
+            let result = match self
+                .unless_cancelled(sink.batch_sink(event_set.clone()))
+                .await
+            {
+                Some(result) => result,
+                None => return self.cancelled(retries, event_set.events),
+            };
+            let err = match result {
+                Ok(_) => {
+                    return SinkOutcome {
+                        retries: retries.count,
+                        outcome: None,
+                        events: vec![],
+                    }
+                }
+                Err(err) => err.err,
+            };
+            let decision = self.handle(&Failure::Sink(&err), &provenance, &mut retries);
+            match decision {
+                Decision::Retry(delay) => {
+                    if self
+                        .unless_cancelled(tokio::time::sleep(delay))
+                        .await
+                        .is_none()
+                    {
+                        return self.cancelled(retries, event_set.events);
+                    }
+                }
+                Decision::Resolve(outcome) => {
+                    return SinkOutcome {
+                        retries: retries.count,
+                        outcome: Some(outcome),
+                        events: event_set.events,
+                    }
+                }
+            }
+        }
+    }
+
+    /// send the event to the external sink and retry the failures as the policy
+    pub async fn sink<S: Sink + Send>(&self, sink: &mut S, event: KeyedDataEvent) -> SinkOutcome {
+        let mut provenance = self.get_provenance([&event]);
+        provenance.sink_id = Some(sink.sink_id());
+        let mut retries = Retries::default();
+        loop {
+            let result = match self
+                .unless_cancelled(sink.sink(LocalEvent::KeyedDataStreamEvent(event.clone())))
+                .await
+            {
+                Some(result) => result,
+                None => return self.cancelled(retries, vec![event]),
+            };
+            let err = match result {
+                Ok(_) => {
+                    return SinkOutcome {
+                        retries: retries.count,
+                        outcome: None,
+                        events: vec![],
+                    }
+                }
+                Err(err) => err,
+            };
+            let decision = self.handle(&Failure::Sink(&err), &provenance, &mut retries);
+            match decision {
+                Decision::Retry(delay) => {
+                    if self
+                        .unless_cancelled(tokio::time::sleep(delay))
+                        .await
+                        .is_none()
+                    {
+                        return self.cancelled(retries, vec![event]);
+                    }
+                }
+                Decision::Resolve(outcome) => {
+                    return SinkOutcome {
+                        retries: retries.count,
+                        outcome: Some(outcome),
+                        events: vec![event],
+                    }
+                }
+            }
+        }
+    }
+
+    /// send the event to the out edge and retry the failures as the policy.
+    /// The executor waits until the event is resolved, so the retried event is never reordered with the following ones
+    pub async fn write(
+        &self,
+        out_edge: &dyn OutEdge<Output = LocalEvent>,
+        event: KeyedDataEvent,
+    ) -> SinkOutcome {
+        let mut provenance = self.get_provenance([&event]);
+        provenance.to_operator_id = Some(event.to_operator_id);
+        let mut retries = Retries::default();
+        loop {
+            let result = match self
+                .unless_cancelled(out_edge.write(LocalEvent::KeyedDataStreamEvent(event.clone())))
+                .await
+            {
+                Some(result) => result,
+                None => return self.cancelled(retries, vec![event]),
+            };
+            let err = match result {
+                Ok(_) => {
+                    return SinkOutcome {
+                        retries: retries.count,
+                        outcome: None,
+                        events: vec![],
+                    }
+                }
+                Err(err) => err,
+            };
+            let decision = self.handle(&Failure::OutEdge(&err), &provenance, &mut retries);
+            match decision {
+                Decision::Retry(delay) => {
+                    if self
+                        .unless_cancelled(tokio::time::sleep(delay))
+                        .await
+                        .is_none()
+                    {
+                        return self.cancelled(retries, vec![event]);
+                    }
+                }
+                Decision::Resolve(outcome) => {
+                    return SinkOutcome {
+                        retries: retries.count,
+                        outcome: Some(outcome),
+                        events: vec![event],
+                    }
+                }
+            }
+        }
+    }
+
+    /// send the events to the out edge and retry the failures as the policy.
+    /// The whole batch is sent again by retries, so the order of the events is kept
+    pub async fn batch_write(
+        &self,
+        out_edge: &dyn OutEdge<Output = LocalEvent>,
+        event_set: KeyedEventSet,
+    ) -> SinkOutcome {
+        let mut provenance = self.get_provenance(&event_set.events);
+        provenance.to_operator_id = Some(event_set.to_operator_id);
+        let mut retries = Retries::default();
+        loop {
+            let write = out_edge.batch_write(
+                &event_set.job_id,
+                event_set.to_operator_id,
+                event_set.from_operator_id,
+                event_set
+                    .events
+                    .iter()
+                    .map(|event| LocalEvent::KeyedDataStreamEvent(event.clone()))
+                    .collect(),
+            );
+            let result = match self.unless_cancelled(write).await {
+                Some(result) => result,
+                None => return self.cancelled(retries, event_set.events),
+            };
+            let err = match result {
+                Ok(_) => {
+                    return SinkOutcome {
+                        retries: retries.count,
+                        outcome: None,
+                        events: vec![],
+                    }
+                }
+                Err(err) => err,
+            };
+            let decision = self.handle(&Failure::OutEdge(&err), &provenance, &mut retries);
+            match decision {
+                Decision::Retry(delay) => {
+                    if self
+                        .unless_cancelled(tokio::time::sleep(delay))
+                        .await
+                        .is_none()
+                    {
+                        return self.cancelled(retries, event_set.events);
+                    }
+                }
+                Decision::Resolve(outcome) => {
+                    return SinkOutcome {
+                        retries: retries.count,
+                        outcome: Some(outcome),
+                        events: event_set.events,
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use common::{
+        backoff::BackoffBuilder, event::LocalEvent, lookup::LookupError, sql_expr::EvalError,
+        types::SinkId,
+    };
+    use proto::common::{
+        error_policy, Backoff, ErrorPolicy, KeyedDataEvent, KeyedEventSet, ResourceId,
+    };
+    use tonic::async_trait;
+
+    use crate::{
+        connector::Sink,
+        edge::{OutEdge, OutEdgeError},
+        err::{BatchSinkException, ErrorKind, ExecutionError, SinkException},
+    };
+
+    use super::{Decision, ErrorHandler, Failure, Outcome, Policy, Provenance, Retries};
+
+    fn retry(attempts: u32, fallback: Option<ErrorPolicy>) -> ErrorPolicy {
+        ErrorPolicy {
+            policy: Some(error_policy::Policy::Retry(Box::new(error_policy::Retry {
+                attempts,
+                backoff: Some(Backoff {
+                    base: 1,
+                    max: 4,
+                    jitter: false,
+                }),
+                fallback: fallback.map(Box::new),
+            }))),
+        }
+    }
+
+    fn dead_letter(sink: u32) -> ErrorPolicy {
+        ErrorPolicy {
+            policy: Some(error_policy::Policy::DeadLetter(error_policy::DeadLetter {
+                sink,
+            })),
+        }
+    }
+
+    fn fail() -> ErrorPolicy {
+        ErrorPolicy {
+            policy: Some(error_policy::Policy::Fail(Default::default())),
+        }
+    }
+
+    fn new_sink_exception(kind: ErrorKind) -> SinkException {
+        SinkException {
+            kind,
+            msg: "sink failed".to_string(),
+        }
+    }
+
+    /// a sink which fails for the first `failures` times
+    struct FlakySink {
+        failures: u32,
+        kind: ErrorKind,
+        calls: u32,
+    }
+
+    impl FlakySink {
+        fn new(failures: u32, kind: ErrorKind) -> Self {
+            Self {
+                failures,
+                kind,
+                calls: 0,
+            }
+        }
+
+        fn call(&mut self) -> Result<(), SinkException> {
+            self.calls += 1;
+            if self.calls <= self.failures {
+                Err(new_sink_exception(self.kind.clone()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Sink for FlakySink {
+        fn sink_id(&self) -> SinkId {
+            2
+        }
+
+        async fn sink(&mut self, _msg: LocalEvent) -> Result<(), SinkException> {
+            self.call()
+        }
+
+        async fn batch_sink(
+            &mut self,
+            _event_set: KeyedEventSet,
+        ) -> Result<(), BatchSinkException> {
+            self.call()
+                .map_err(|err| BatchSinkException { err, event_id: 0 })
+        }
+
+        fn close_sink(&mut self) {}
+
+        fn flush_sink(&mut self) -> Result<(), SinkException> {
+            Ok(())
+        }
+    }
+
+    /// an out edge which fails for the first `failures` times
+    struct FlakyOutEdge {
+        failures: u32,
+        status: tonic::Status,
+        calls: std::sync::Mutex<u32>,
+    }
+
+    impl FlakyOutEdge {
+        fn new(failures: u32, status: tonic::Status) -> Self {
+            Self {
+                failures,
+                status,
+                calls: Default::default(),
+            }
+        }
+
+        fn call(&self) -> Result<(), OutEdgeError> {
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+            if *calls <= self.failures {
+                Err(OutEdgeError::SendToRemoteFailed(self.status.clone()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[async_trait]
+    impl OutEdge for FlakyOutEdge {
+        type Output = LocalEvent;
+
+        async fn write(&self, _val: LocalEvent) -> Result<(), OutEdgeError> {
+            self.call()
+        }
+
+        async fn batch_write(
+            &self,
+            _job_id: &Option<ResourceId>,
+            _to_operator_id: u32,
+            _from_operator_id: u32,
+            _iter: Vec<LocalEvent>,
+        ) -> Result<(), OutEdgeError> {
+            self.call()
+        }
+    }
+
+    #[test]
+    fn test_policy_from_error_policy() {
+        assert_eq!(Policy::from(None), Policy::Skip);
+        assert_eq!(Policy::from(Some(&ErrorPolicy::default())), Policy::Skip);
+        assert_eq!(Policy::from(Some(&fail())), Policy::Fail);
+        assert_eq!(
+            Policy::from(Some(&retry(3, Some(dead_letter(1))))),
+            Policy::Retry {
+                attempts: 3,
+                backoff: BackoffBuilder {
+                    base: 1,
+                    max: 4,
+                    jitter: false
+                },
+                fallback: Box::new(Policy::DeadLetter(1)),
+            }
+        );
+        // failed events are skipped if the fallback is not set
+        match Policy::from(Some(&retry(3, None))) {
+            Policy::Retry { fallback, .. } => assert_eq!(*fallback, Policy::Skip),
+            policy => panic!("unexpected policy {:?}", policy),
+        }
+    }
+
+    #[test]
+    fn test_fail_and_skip_policy() {
+        let err = ExecutionError::DeduplicateFailed("corrupted state".to_string());
+        let provenance = Provenance::default();
+
+        let handler = ErrorHandler::new(&ResourceId::default(), 1, Some(&fail()));
+        assert_eq!(
+            handler.handle(
+                &Failure::Execution(&err),
+                &provenance,
+                &mut Retries::default()
+            ),
+            Decision::Resolve(Outcome::Failed)
+        );
+
+        let handler = ErrorHandler::new(&ResourceId::default(), 1, None);
+        assert_eq!(
+            handler.handle(
+                &Failure::Execution(&err),
+                &provenance,
+                &mut Retries::default()
+            ),
+            Decision::Resolve(Outcome::Skipped)
+        );
+    }
+
+    #[test]
+    fn test_dead_letter_policy() {
+        let handler = ErrorHandler::new(&ResourceId::default(), 1, Some(&dead_letter(3)));
+        let err = new_sink_exception(ErrorKind::RemoteTransportFailed);
+        assert_eq!(
+            handler.handle(
+                &Failure::Sink(&err),
+                &Provenance::default(),
+                &mut Retries::default()
+            ),
+            Decision::Resolve(Outcome::DeadLettered(3))
+        );
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let handler = ErrorHandler::new(
+            &ResourceId::default(),
+            1,
+            Some(&retry(3, Some(dead_letter(3)))),
+        );
+        let err = ExecutionError::WindowFailed("state unavailable".to_string());
+        let provenance = Provenance::default();
+        let mut retries = Retries::default();
+        let decisions = (0..4)
+            .map(|_| handler.handle(&Failure::Execution(&err), &provenance, &mut retries))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            decisions,
+            vec![
+                Decision::Retry(Duration::from_millis(1)),
+                Decision::Retry(Duration::from_millis(2)),
+                Decision::Retry(Duration::from_millis(4)),
+                Decision::Resolve(Outcome::DeadLettered(3)),
+            ]
+        );
+        assert_eq!(retries.get_count(), 3);
+
+        // rejected events are handled by the fallback policy without retries
+        let err = new_sink_exception(ErrorKind::RemoteSinkRejected);
+        let mut retries = Retries::default();
+        assert_eq!(
+            handler.handle(&Failure::Sink(&err), &provenance, &mut retries),
+            Decision::Resolve(Outcome::DeadLettered(3))
+        );
+        assert_eq!(retries.get_count(), 0);
+
+        // evaluating a SQL expression and looking up an invalid payload fail deterministically
+        for err in [
+            ExecutionError::SqlExprFailed(EvalError::DivisionByZero),
+            ExecutionError::AsyncLookupFailed(LookupError::InvalidPayload("1".to_string())),
+        ] {
+            let mut retries = Retries::default();
+            assert_eq!(
+                handler.handle(&Failure::Execution(&err), &provenance, &mut retries),
+                Decision::Resolve(Outcome::DeadLettered(3))
+            );
+            assert_eq!(retries.get_count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_provenance() {
+        let handler = ErrorHandler::new(&ResourceId::default(), 1, None);
+        let events = (0..2)
+            .map(|event_id| KeyedDataEvent {
+                event_id,
+                from_operator_id: 0,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            handler.get_provenance(&events),
+            Provenance {
+                job_id: ResourceId::default(),
+                operator_id: 1,
+                from_operator_id: 0,
+                event_ids: vec![0, 1],
+                sink_id: None,
+                to_operator_id: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_sink_with_retry_policy() {
+        let handler = ErrorHandler::new(&ResourceId::default(), 1, Some(&retry(3, Some(fail()))));
+        let event_set = KeyedEventSet {
+            events: vec![KeyedDataEvent::default()],
+            ..Default::default()
+        };
+
+        // transport failures are recovered by retrying
+        let mut sink = FlakySink::new(2, ErrorKind::RemoteTransportFailed);
+        let outcome = handler.batch_sink(&mut sink, event_set.clone()).await;
+        assert_eq!(outcome.retries, 2);
+        assert_eq!(outcome.outcome, None);
+        assert!(outcome.events.is_empty());
+        assert_eq!(sink.calls, 3);
+
+        // the fallback policy is applied once the attempts are exhausted
+        let mut sink = FlakySink::new(5, ErrorKind::RemoteTransportFailed);
+        let outcome = handler.batch_sink(&mut sink, event_set.clone()).await;
+        assert_eq!(outcome.retries, 3);
+        assert_eq!(outcome.outcome, Some(Outcome::Failed));
+        assert_eq!(outcome.events, event_set.events);
+        assert_eq!(sink.calls, 4);
+
+        // rejections are not retried
+        let mut sink = FlakySink::new(1, ErrorKind::RemoteSinkRejected);
+        let outcome = handler.sink(&mut sink, KeyedDataEvent::default()).await;
+        assert_eq!(outcome.retries, 0);
+        assert_eq!(outcome.outcome, Some(Outcome::Failed));
+        assert_eq!(sink.calls, 1);
+    }
+
+    #[test]
+    fn test_retry_respects_backpressure_hint() {
+        let handler = ErrorHandler::new(&ResourceId::default(), 1, Some(&retry(3, None)));
+        let provenance = Provenance::default();
+        let mut retries = Retries::default();
+
+        let err = OutEdgeError::RemoteBackpressure(Duration::from_millis(10));
+        assert_eq!(
+            handler.handle(&Failure::OutEdge(&err), &provenance, &mut retries),
+            Decision::Retry(Duration::from_millis(10))
+        );
+        // the backoff delay is used if it's longer than the hint
+        let err = OutEdgeError::RemoteBackpressure(Duration::from_millis(1));
+        assert_eq!(
+            handler.handle(&Failure::OutEdge(&err), &provenance, &mut retries),
+            Decision::Retry(Duration::from_millis(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_out_edge_with_retry_policy() {
+        let handler = ErrorHandler::new(&ResourceId::default(), 1, Some(&retry(3, Some(fail()))));
+        let event_set = KeyedEventSet {
+            events: vec![KeyedDataEvent::default()],
+            ..Default::default()
+        };
+
+        // the remote TaskManager recovers from a brief network blip
+        let out_edge = FlakyOutEdge::new(2, tonic::Status::unavailable("connection refused"));
+        let outcome = handler.batch_write(&out_edge, event_set.clone()).await;
+        assert_eq!(outcome.retries, 2);
+        assert_eq!(outcome.outcome, None);
+        assert_eq!(*out_edge.calls.lock().unwrap(), 3);
+
+        // non-retriable codes go to the fallback policy directly
+        let out_edge = FlakyOutEdge::new(1, tonic::Status::invalid_argument("bad event"));
+        let outcome = handler.write(&out_edge, KeyedDataEvent::default()).await;
+        assert_eq!(outcome.retries, 0);
+        assert_eq!(outcome.outcome, Some(Outcome::Failed));
+        assert_eq!(outcome.events, vec![KeyedDataEvent::default()]);
+        assert_eq!(*out_edge.calls.lock().unwrap(), 1);
+    }
+}
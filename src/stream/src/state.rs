@@ -1,44 +1,287 @@
-use std::{cell::RefCell, collections::BTreeMap, path::Path};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display},
+    path::Path,
+};
 
-use sled::Db;
-use proto::common::ResourceId;
+use common::types::ExecutorId;
+use prost::Message;
+use proto::common::{state_limit, ResourceId, StateEntry, StateLimit, StateSnapshot};
+use sled::{Db, Tree};
 
 const KEY_VALUE: &str = "key_value";
 const STATE_MANAGER: &str = "STATE_MANAGER";
+const BROADCAST_TREE: &str = "broadcast";
 pub(crate) const KEY_VALUE_STATE_PATH: &str = "KEY_VALUE_STATE_PATH";
 const DEFAULT_STATE_PATH: &str = "/tmp/state";
+/// the codec of the snapshots of the states, one of `none`, `lz4` and `zstd`. See [`SnapshotCodec`]
+pub const STATE_SNAPSHOT_CODEC: &str = "STATE_SNAPSHOT_CODEC";
+/// the size of the keyed state of an operator in bytes
+pub const STATE_SIZE_METRIC: &str = "state.size_bytes";
+/// the number of writes of new keys rejected by the state limit
+pub const STATE_REJECTED_KEYS_METRIC: &str = "state.limit.rejected_keys";
+/// the number of keys evicted by the state limit
+pub const STATE_EVICTED_KEYS_METRIC: &str = "state.limit.evicted_keys";
 pub trait StateManager {
     fn get_keyed_state(&self, key: &[u8]) -> Vec<u8>;
     fn set_key_state(&self, key: &[u8], value: &[u8]);
+    fn delete_keyed_state(&self, key: &[u8]);
+    /// all states whose keys start with the prefix, in the order of keys
+    fn scan_keyed_state(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)>;
+    /// the non-keyed broadcast state, which is updated by the events from broadcast edges only
+    fn get_broadcast_state(&self, key: &[u8]) -> Vec<u8>;
+    fn set_broadcast_state(&self, key: &[u8], value: &[u8]);
+    fn delete_broadcast_state(&self, key: &[u8]);
+    /// all entries of the broadcast state, in the order of keys
+    fn list_broadcast_state(&self) -> Vec<(Vec<u8>, Vec<u8>)>;
+    /// persist the states, including the broadcast state, so that they can be recovered after restart
+    fn checkpoint(&self);
+
+    /// all keyed states and the broadcast state encoded as a [`StateSnapshot`] and compressed by the codec,
+    /// which is uploaded to the remote snapshot store
+    fn snapshot(&self, codec: SnapshotCodec) -> Vec<u8> {
+        let into_entries = |states: Vec<(Vec<u8>, Vec<u8>)>| {
+            states
+                .into_iter()
+                .map(|(key, value)| StateEntry { key, value })
+                .collect()
+        };
+        codec.compress(
+            &StateSnapshot {
+                keyed: into_entries(self.scan_keyed_state(&[])),
+                broadcast: into_entries(self.list_broadcast_state()),
+            }
+            .encode_to_vec(),
+        )
+    }
+
+    /// replace all states with a snapshot taken by [`StateManager::snapshot`], which is decompressed by the codec recorded in it
+    fn restore(&self, snapshot: &[u8]) -> Result<(), RestoreError> {
+        let snapshot = StateSnapshot::decode(SnapshotCodec::decompress(snapshot)?.as_ref())
+            .map_err(RestoreError::Decode)?;
+        self.scan_keyed_state(&[])
+            .iter()
+            .for_each(|(key, _)| self.delete_keyed_state(key));
+        self.list_broadcast_state()
+            .iter()
+            .for_each(|(key, _)| self.delete_broadcast_state(key));
+        snapshot
+            .keyed
+            .iter()
+            .for_each(|entry| self.set_key_state(&entry.key, &entry.value));
+        snapshot
+            .broadcast
+            .iter()
+            .for_each(|entry| self.set_broadcast_state(&entry.key, &entry.value));
+        Ok(())
+    }
+}
+
+/// [`SnapshotCodec`] compresses the snapshots of the states before they're persisted, trading CPU for storage and bandwidth.
+/// A snapshot starts with a header byte recording its codec, so it's always restored by the codec it's taken with.
+///
+/// The header bytes are never the first byte of an encoded [`StateSnapshot`],
+/// so the snapshots taken by former versions, which have no header, are restored as they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnapshotCodec {
+    #[default]
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl SnapshotCodec {
+    /// the codec configured by [`STATE_SNAPSHOT_CODEC`], the snapshots are not compressed by default
+    pub fn from_env() -> Self {
+        match common::utils::get_env(STATE_SNAPSHOT_CODEC).as_deref() {
+            Some("lz4") => Self::Lz4,
+            Some("zstd") => Self::Zstd,
+            Some("none") | None => Self::None,
+            Some(codec) => {
+                tracing::warn!(
+                    "unknown state snapshot codec {}, snapshots are not compressed",
+                    codec
+                );
+                Self::None
+            }
+        }
+    }
+
+    fn header(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Lz4 => 1,
+            Self::Zstd => 2,
+        }
+    }
+
+    /// none if the byte is not a header, e.g. the snapshot is taken by a former version
+    fn from_header(header: u8) -> Option<Self> {
+        match header {
+            0 => Some(Self::None),
+            1 => Some(Self::Lz4),
+            2 => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// the snapshot prefixed by the header. It's not compressed if the compression fails
+    fn compress(&self, snapshot: &[u8]) -> Vec<u8> {
+        let compressed = match self {
+            Self::None => Ok(snapshot.to_vec()),
+            Self::Lz4 => Ok(lz4_flex::compress_prepend_size(snapshot)),
+            Self::Zstd => zstd::encode_all(snapshot, zstd::DEFAULT_COMPRESSION_LEVEL),
+        };
+        let (codec, body) = match compressed {
+            Ok(body) => (*self, body),
+            Err(err) => {
+                tracing::error!("compress state snapshot by {:?} failed: {}", self, err);
+                (Self::None, snapshot.to_vec())
+            }
+        };
+        let mut bytes = Vec::with_capacity(body.len() + 1);
+        bytes.push(codec.header());
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    /// the encoded [`StateSnapshot`] of a snapshot taken by [`StateManager::snapshot`]
+    fn decompress(snapshot: &[u8]) -> Result<Cow<[u8]>, RestoreError> {
+        let codec = match snapshot
+            .first()
+            .and_then(|header| Self::from_header(*header))
+        {
+            Some(codec) => codec,
+            None => return Ok(Cow::Borrowed(snapshot)),
+        };
+        let body = &snapshot[1..];
+        match codec {
+            Self::None => Ok(Cow::Borrowed(body)),
+            Self::Lz4 => lz4_flex::decompress_size_prepended(body)
+                .map(Cow::Owned)
+                .map_err(|err| RestoreError::Decompress(codec, err.to_string())),
+            Self::Zstd => zstd::decode_all(body)
+                .map(Cow::Owned)
+                .map_err(|err| RestoreError::Decompress(codec, err.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestoreError {
+    Decompress(SnapshotCodec, String),
+    Decode(prost::DecodeError),
+}
+
+impl Display for RestoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decompress(codec, err) => {
+                write!(f, "decompress snapshot by {:?} failed: {}", codec, err)
+            }
+            Self::Decode(err) => write!(f, "decode snapshot failed: {}", err),
+        }
+    }
+}
+
+/// [`BroadcastState`] is the read-only view of the broadcast state for processing the events from the other edges.
+/// Since there is no ordering guarantee between the broadcast edges and the other edges, it may not contain the latest updates.
+pub struct BroadcastState<'a, S: StateManager> {
+    state_manager: &'a S,
+}
+
+impl<'a, S: StateManager> BroadcastState<'a, S> {
+    pub fn new(state_manager: &'a S) -> Self {
+        Self { state_manager }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        Some(self.state_manager.get_broadcast_state(key)).filter(|value| !value.is_empty())
+    }
+
+    pub fn list(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.state_manager.list_broadcast_state()
+    }
+}
+
+impl<T: StateManager> StateManager for &T {
+    fn get_keyed_state(&self, key: &[u8]) -> Vec<u8> {
+        T::get_keyed_state(self, key)
+    }
+
+    fn set_key_state(&self, key: &[u8], value: &[u8]) {
+        T::set_key_state(self, key, value)
+    }
+
+    fn delete_keyed_state(&self, key: &[u8]) {
+        T::delete_keyed_state(self, key)
+    }
+
+    fn scan_keyed_state(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        T::scan_keyed_state(self, prefix)
+    }
+
+    fn get_broadcast_state(&self, key: &[u8]) -> Vec<u8> {
+        T::get_broadcast_state(self, key)
+    }
+
+    fn set_broadcast_state(&self, key: &[u8], value: &[u8]) {
+        T::set_broadcast_state(self, key, value)
+    }
+
+    fn delete_broadcast_state(&self, key: &[u8]) {
+        T::delete_broadcast_state(self, key)
+    }
+
+    fn list_broadcast_state(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        T::list_broadcast_state(self)
+    }
+
+    fn checkpoint(&self) {
+        T::checkpoint(self)
+    }
 }
 
-fn new_key_value_state_mgt(resource_id: &ResourceId) -> KeyValueStateManager {
+fn new_key_value_state_mgt(
+    resource_id: &ResourceId,
+    executor_id: ExecutorId,
+) -> KeyValueStateManager {
     let mut path =
         common::utils::get_env(KEY_VALUE_STATE_PATH).unwrap_or(DEFAULT_STATE_PATH.to_string());
     path.push_str("/");
     path.push_str(&resource_id.namespace_id);
     path.push_str(&resource_id.resource_id);
+    // each executor has its own db because a db can't be opened by more than one executor at the same time
+    path.push_str(&format!("/{}", executor_id));
     KeyValueStateManager::new(path)
 }
 
-pub fn new_state_mgt(resource_id: &ResourceId) -> impl StateManager {
+pub fn new_state_mgt(resource_id: &ResourceId, executor_id: ExecutorId) -> StateManagerEnum {
     match state_mgt_type() {
-        StateMangerType::KeyValue => StateManagerEnum::KeyValue(new_key_value_state_mgt(resource_id)),
+        StateMangerType::KeyValue => {
+            StateManagerEnum::KeyValue(new_key_value_state_mgt(resource_id, executor_id))
+        }
         StateMangerType::Memory => StateManagerEnum::Memory(MemoryStateManager::new()),
     }
 }
 
 pub struct KeyValueStateManager {
     db: Db,
+    broadcast: Tree,
 }
 
 impl KeyValueStateManager {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
-        Self {
-            db: sled::open(path)
-                .map_err(|err| tracing::error!("db open failed: {}", err))
-                .unwrap(),
-        }
+        let db = sled::open(path)
+            .map_err(|err| tracing::error!("db open failed: {}", err))
+            .unwrap();
+        let broadcast = db
+            .open_tree(BROADCAST_TREE)
+            .map_err(|err| tracing::error!("broadcast state open failed: {}", err))
+            .unwrap();
+        Self { db, broadcast }
     }
 }
 
@@ -58,6 +301,71 @@ impl StateManager for KeyValueStateManager {
             .map_err(|err| tracing::error!("set key state failed: {}", err))
             .unwrap_or_default()
     }
+
+    fn delete_keyed_state(&self, key: &[u8]) {
+        self.db
+            .remove(key)
+            .map(|_| {})
+            .map_err(|err| tracing::error!("delete key state failed: {}", err))
+            .unwrap_or_default()
+    }
+
+    fn scan_keyed_state(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.db
+            .scan_prefix(prefix)
+            .filter_map(|result| {
+                result
+                    .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                    .map_err(|err| tracing::error!("scan state failed: {}", err))
+                    .ok()
+            })
+            .collect()
+    }
+
+    fn get_broadcast_state(&self, key: &[u8]) -> Vec<u8> {
+        self.broadcast
+            .get(key)
+            .map(|value| value.map(|v| v.to_vec()).unwrap_or_default())
+            .map_err(|err| tracing::error!("get broadcast state failed: {}", err))
+            .unwrap_or_default()
+    }
+
+    fn set_broadcast_state(&self, key: &[u8], value: &[u8]) {
+        self.broadcast
+            .insert(key, value)
+            .map(|_| {})
+            .map_err(|err| tracing::error!("set broadcast state failed: {}", err))
+            .unwrap_or_default()
+    }
+
+    fn delete_broadcast_state(&self, key: &[u8]) {
+        self.broadcast
+            .remove(key)
+            .map(|_| {})
+            .map_err(|err| tracing::error!("delete broadcast state failed: {}", err))
+            .unwrap_or_default()
+    }
+
+    fn list_broadcast_state(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.broadcast
+            .iter()
+            .filter_map(|result| {
+                result
+                    .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                    .map_err(|err| tracing::error!("list broadcast state failed: {}", err))
+                    .ok()
+            })
+            .collect()
+    }
+
+    // flushing the db flushes all its trees
+    fn checkpoint(&self) {
+        self.db
+            .flush()
+            .map(|_| {})
+            .map_err(|err| tracing::error!("checkpoint state failed: {}", err))
+            .unwrap_or_default()
+    }
 }
 
 pub enum StateMangerType {
@@ -96,10 +404,60 @@ impl StateManager for StateManagerEnum {
             StateManagerEnum::Memory(manager) => manager.set_key_state(key, value),
         }
     }
+
+    fn delete_keyed_state(&self, key: &[u8]) {
+        match self {
+            StateManagerEnum::KeyValue(manager) => manager.delete_keyed_state(key),
+            StateManagerEnum::Memory(manager) => manager.delete_keyed_state(key),
+        }
+    }
+
+    fn scan_keyed_state(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        match self {
+            StateManagerEnum::KeyValue(manager) => manager.scan_keyed_state(prefix),
+            StateManagerEnum::Memory(manager) => manager.scan_keyed_state(prefix),
+        }
+    }
+
+    fn get_broadcast_state(&self, key: &[u8]) -> Vec<u8> {
+        match self {
+            StateManagerEnum::KeyValue(manager) => manager.get_broadcast_state(key),
+            StateManagerEnum::Memory(manager) => manager.get_broadcast_state(key),
+        }
+    }
+
+    fn set_broadcast_state(&self, key: &[u8], value: &[u8]) {
+        match self {
+            StateManagerEnum::KeyValue(manager) => manager.set_broadcast_state(key, value),
+            StateManagerEnum::Memory(manager) => manager.set_broadcast_state(key, value),
+        }
+    }
+
+    fn delete_broadcast_state(&self, key: &[u8]) {
+        match self {
+            StateManagerEnum::KeyValue(manager) => manager.delete_broadcast_state(key),
+            StateManagerEnum::Memory(manager) => manager.delete_broadcast_state(key),
+        }
+    }
+
+    fn list_broadcast_state(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        match self {
+            StateManagerEnum::KeyValue(manager) => manager.list_broadcast_state(),
+            StateManagerEnum::Memory(manager) => manager.list_broadcast_state(),
+        }
+    }
+
+    fn checkpoint(&self) {
+        match self {
+            StateManagerEnum::KeyValue(manager) => manager.checkpoint(),
+            StateManagerEnum::Memory(manager) => manager.checkpoint(),
+        }
+    }
 }
 
 pub struct MemoryStateManager {
     cache: RefCell<BTreeMap<Vec<u8>, Vec<u8>>>,
+    broadcast: RefCell<BTreeMap<Vec<u8>, Vec<u8>>>,
 }
 
 impl StateManager for MemoryStateManager {
@@ -114,12 +472,509 @@ impl StateManager for MemoryStateManager {
     fn set_key_state(&self, key: &[u8], value: &[u8]) {
         self.cache.borrow_mut().insert(key.to_vec(), value.to_vec());
     }
+
+    fn delete_keyed_state(&self, key: &[u8]) {
+        self.cache.borrow_mut().remove(key);
+    }
+
+    fn scan_keyed_state(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.cache
+            .borrow()
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    fn get_broadcast_state(&self, key: &[u8]) -> Vec<u8> {
+        self.broadcast
+            .borrow()
+            .get(key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn set_broadcast_state(&self, key: &[u8], value: &[u8]) {
+        self.broadcast
+            .borrow_mut()
+            .insert(key.to_vec(), value.to_vec());
+    }
+
+    fn delete_broadcast_state(&self, key: &[u8]) {
+        self.broadcast.borrow_mut().remove(key);
+    }
+
+    fn list_broadcast_state(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.broadcast
+            .borrow()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    // memory states are lost after restart
+    fn checkpoint(&self) {}
 }
 
 impl MemoryStateManager {
     pub fn new() -> Self {
         Self {
             cache: Default::default(),
+            broadcast: Default::default(),
         }
     }
 }
+
+/// [`LimitedStateManager`] tracks the size of the keyed state of an operator and applies its [`StateLimit`].
+/// A write which grows the state beyond the limit is handled by the policy of the limit:
+/// - `RejectNewKeys`: the write is skipped if the key is new, the existing keys can still be updated;
+/// - `EvictOldest`: the least recently written keys are evicted until the state fits the limit. The written key is never evicted;
+/// - `Fail`: the write is skipped and the state is marked as exceeded, then the executor fails the operator.
+pub struct LimitedStateManager<S: StateManager> {
+    inner: S,
+    limit: Option<(u64, state_limit::Policy)>,
+    // total bytes of the keys and values of the keyed state
+    size: Cell<u64>,
+    // keys in the order they are written, they are only tracked by the EvictOldest policy
+    write_order: RefCell<BTreeMap<u64, Vec<u8>>>,
+    write_sequences: RefCell<HashMap<Vec<u8>, u64>>,
+    next_sequence: Cell<u64>,
+    // rejected and evicted keys since they are taken last time
+    rejected_keys: Cell<u64>,
+    evicted_keys: Cell<u64>,
+    exceeded: Cell<bool>,
+}
+
+impl<S: StateManager> LimitedStateManager<S> {
+    /// the limit is ignored if it's invalid, which is prevented by the validation of the dataflow
+    pub fn new(inner: S, limit: Option<&StateLimit>) -> Self {
+        let limit = limit.and_then(|limit| {
+            limit
+                .get_policy()
+                .ok()
+                .filter(|_| limit.max_bytes > 0)
+                .map(|policy| (limit.max_bytes, policy))
+        });
+        let state_manager = Self {
+            inner,
+            limit,
+            size: Cell::new(0),
+            write_order: Default::default(),
+            write_sequences: Default::default(),
+            next_sequence: Cell::new(0),
+            rejected_keys: Cell::new(0),
+            evicted_keys: Cell::new(0),
+            exceeded: Cell::new(false),
+        };
+        state_manager.reload();
+        state_manager
+    }
+
+    pub fn get_size(&self) -> u64 {
+        self.size.get()
+    }
+
+    pub fn get_max_bytes(&self) -> Option<u64> {
+        self.limit.map(|(max_bytes, _)| max_bytes)
+    }
+
+    /// whether a write has exceeded the limit with the `Fail` policy
+    pub fn is_exceeded(&self) -> bool {
+        self.exceeded.get()
+    }
+
+    /// the number of keys rejected since the last call
+    pub fn take_rejected_keys(&self) -> u64 {
+        self.rejected_keys.take()
+    }
+
+    /// the number of keys evicted since the last call
+    pub fn take_evicted_keys(&self) -> u64 {
+        self.evicted_keys.take()
+    }
+
+    /// recompute the size from the inner state manager, e.g. after the states are restored.
+    /// The existing keys are considered written in the order of keys
+    fn reload(&self) {
+        let states = self.inner.scan_keyed_state(&[]);
+        self.size.set(
+            states
+                .iter()
+                .map(|(key, value)| entry_size(key, value))
+                .sum(),
+        );
+        self.write_order.borrow_mut().clear();
+        self.write_sequences.borrow_mut().clear();
+        if self.is_evicting() {
+            states.into_iter().for_each(|(key, _)| self.touch(&key));
+        }
+    }
+
+    fn is_evicting(&self) -> bool {
+        matches!(self.limit, Some((_, state_limit::Policy::EvictOldest)))
+    }
+
+    /// move the key to the end of the write order
+    fn touch(&self, key: &[u8]) {
+        let sequence = self.next_sequence.get();
+        self.next_sequence.set(sequence + 1);
+        if let Some(old) = self
+            .write_sequences
+            .borrow_mut()
+            .insert(key.to_vec(), sequence)
+        {
+            self.write_order.borrow_mut().remove(&old);
+        }
+        self.write_order.borrow_mut().insert(sequence, key.to_vec());
+    }
+
+    fn forget(&self, key: &[u8]) {
+        if let Some(sequence) = self.write_sequences.borrow_mut().remove(key) {
+            self.write_order.borrow_mut().remove(&sequence);
+        }
+    }
+
+    /// evict the least recently written keys except the given one until the state fits the limit
+    fn evict(&self, max_bytes: u64, written: &[u8]) {
+        while self.size.get() > max_bytes {
+            let oldest = self
+                .write_order
+                .borrow()
+                .values()
+                .find(|key| key.as_slice() != written)
+                .cloned();
+            match oldest {
+                Some(key) => {
+                    self.delete_keyed_state(&key);
+                    self.evicted_keys.set(self.evicted_keys.get() + 1);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+fn entry_size(key: &[u8], value: &[u8]) -> u64 {
+    (key.len() + value.len()) as u64
+}
+
+impl<S: StateManager> StateManager for LimitedStateManager<S> {
+    fn get_keyed_state(&self, key: &[u8]) -> Vec<u8> {
+        self.inner.get_keyed_state(key)
+    }
+
+    fn set_key_state(&self, key: &[u8], value: &[u8]) {
+        let old = self.inner.get_keyed_state(key);
+        let old_size = if old.is_empty() {
+            0
+        } else {
+            entry_size(key, &old)
+        };
+        let size = self.size.get() - old_size + entry_size(key, value);
+        if let Some((max_bytes, policy)) = self.limit {
+            // a write which doesn't grow the state is always applied
+            if size > max_bytes && size > self.size.get() {
+                match policy {
+                    state_limit::Policy::RejectNewKeys if old.is_empty() => {
+                        self.rejected_keys.set(self.rejected_keys.get() + 1);
+                        return;
+                    }
+                    state_limit::Policy::Fail => {
+                        self.exceeded.set(true);
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.inner.set_key_state(key, value);
+        self.size.set(size);
+        if let Some((max_bytes, state_limit::Policy::EvictOldest)) = self.limit {
+            self.touch(key);
+            self.evict(max_bytes, key);
+        }
+    }
+
+    fn delete_keyed_state(&self, key: &[u8]) {
+        let old = self.inner.get_keyed_state(key);
+        if !old.is_empty() {
+            self.size.set(self.size.get() - entry_size(key, &old));
+        }
+        self.inner.delete_keyed_state(key);
+        self.forget(key);
+    }
+
+    fn scan_keyed_state(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.inner.scan_keyed_state(prefix)
+    }
+
+    fn get_broadcast_state(&self, key: &[u8]) -> Vec<u8> {
+        self.inner.get_broadcast_state(key)
+    }
+
+    fn set_broadcast_state(&self, key: &[u8], value: &[u8]) {
+        self.inner.set_broadcast_state(key, value)
+    }
+
+    fn delete_broadcast_state(&self, key: &[u8]) {
+        self.inner.delete_broadcast_state(key)
+    }
+
+    fn list_broadcast_state(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.inner.list_broadcast_state()
+    }
+
+    fn checkpoint(&self) {
+        self.inner.checkpoint()
+    }
+
+    // the restored states are not limited, the limit is applied by the following writes
+    fn restore(&self, snapshot: &[u8]) -> Result<(), RestoreError> {
+        let result = self.inner.restore(snapshot);
+        self.reload();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proto::common::{state_limit, StateLimit};
+
+    use super::{
+        BroadcastState, KeyValueStateManager, LimitedStateManager, MemoryStateManager,
+        RestoreError, SnapshotCodec, StateManager,
+    };
+
+    #[test]
+    fn test_broadcast_state_is_not_keyed() {
+        let state_manager = MemoryStateManager::new();
+        state_manager.set_key_state("rule".as_bytes(), "keyed".as_bytes());
+        state_manager.set_broadcast_state("rule".as_bytes(), "broadcast".as_bytes());
+
+        let broadcast = BroadcastState::new(&state_manager);
+        assert_eq!(
+            broadcast.get("rule".as_bytes()),
+            Some("broadcast".as_bytes().to_vec())
+        );
+        assert_eq!(
+            state_manager.get_keyed_state("rule".as_bytes()),
+            "keyed".as_bytes()
+        );
+        assert_eq!(state_manager.scan_keyed_state(&[]).len(), 1);
+
+        state_manager.delete_broadcast_state("rule".as_bytes());
+        assert_eq!(broadcast.get("rule".as_bytes()), None);
+        assert!(broadcast.list().is_empty());
+    }
+
+    #[test]
+    fn test_broadcast_state_checkpoint() {
+        let path = std::env::temp_dir().join(format!(
+            "lightflus-broadcast-{}",
+            common::utils::times::now_timestamp()
+        ));
+
+        {
+            let state_manager = KeyValueStateManager::new(&path);
+            state_manager.set_broadcast_state("rule-1".as_bytes(), "1".as_bytes());
+            state_manager.set_broadcast_state("rule-2".as_bytes(), "2".as_bytes());
+            state_manager.checkpoint();
+        }
+
+        {
+            let state_manager = KeyValueStateManager::new(&path);
+            assert_eq!(
+                BroadcastState::new(&state_manager).list(),
+                vec![
+                    ("rule-1".as_bytes().to_vec(), "1".as_bytes().to_vec()),
+                    ("rule-2".as_bytes().to_vec(), "2".as_bytes().to_vec())
+                ]
+            );
+            assert!(state_manager.scan_keyed_state(&[]).is_empty());
+        }
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let state_manager = MemoryStateManager::new();
+        state_manager.set_key_state("key-1".as_bytes(), "1".as_bytes());
+        state_manager.set_key_state("key-2".as_bytes(), "2".as_bytes());
+        state_manager.set_broadcast_state("rule".as_bytes(), "broadcast".as_bytes());
+        let snapshot = state_manager.snapshot(SnapshotCodec::None);
+
+        let restored = MemoryStateManager::new();
+        restored.set_key_state("stale".as_bytes(), "0".as_bytes());
+        restored.set_broadcast_state("stale".as_bytes(), "0".as_bytes());
+        assert!(restored.restore(&snapshot).is_ok());
+        assert_eq!(
+            restored.scan_keyed_state(&[]),
+            state_manager.scan_keyed_state(&[])
+        );
+        assert_eq!(
+            restored.list_broadcast_state(),
+            state_manager.list_broadcast_state()
+        );
+
+        assert!(restored.restore(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_codecs() {
+        let state_manager = MemoryStateManager::new();
+        // repeated values are compressible
+        for i in 0..100 {
+            state_manager.set_key_state(format!("key-{i}").as_bytes(), &[b'v'; 64]);
+        }
+        state_manager.set_broadcast_state("rule".as_bytes(), "broadcast".as_bytes());
+        let uncompressed = state_manager.snapshot(SnapshotCodec::None);
+
+        for codec in [SnapshotCodec::None, SnapshotCodec::Lz4, SnapshotCodec::Zstd] {
+            let snapshot = state_manager.snapshot(codec);
+            assert_eq!(snapshot[0], codec.header());
+            if codec != SnapshotCodec::None {
+                assert!(snapshot.len() < uncompressed.len() / 2);
+            }
+
+            let restored = MemoryStateManager::new();
+            restored.set_key_state("stale".as_bytes(), "0".as_bytes());
+            assert!(restored.restore(&snapshot).is_ok());
+            assert_eq!(
+                restored.scan_keyed_state(&[]),
+                state_manager.scan_keyed_state(&[])
+            );
+            assert_eq!(
+                restored.list_broadcast_state(),
+                state_manager.list_broadcast_state()
+            );
+        }
+
+        // an empty state is restored by every codec as well
+        for codec in [SnapshotCodec::None, SnapshotCodec::Lz4, SnapshotCodec::Zstd] {
+            let restored = MemoryStateManager::new();
+            restored.set_key_state("stale".as_bytes(), "0".as_bytes());
+            assert!(restored
+                .restore(&MemoryStateManager::new().snapshot(codec))
+                .is_ok());
+            assert!(restored.scan_keyed_state(&[]).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_restore_by_recorded_codec() {
+        let state_manager = MemoryStateManager::new();
+        state_manager.set_key_state("key-1".as_bytes(), "1".as_bytes());
+        let encoded = state_manager.snapshot(SnapshotCodec::None)[1..].to_vec();
+
+        // a snapshot without header is taken by a former version
+        let restored = MemoryStateManager::new();
+        assert!(restored.restore(&encoded).is_ok());
+        assert_eq!(restored.get_keyed_state("key-1".as_bytes()), b"1".to_vec());
+
+        // the snapshot is decompressed by the codec in its header
+        let mut compressed = vec![SnapshotCodec::Zstd.header()];
+        compressed.extend(zstd::encode_all(encoded.as_slice(), 0).unwrap());
+        let restored = MemoryStateManager::new();
+        assert!(restored.restore(&compressed).is_ok());
+        assert_eq!(restored.get_keyed_state("key-1".as_bytes()), b"1".to_vec());
+
+        // a corrupted snapshot fails to be restored by the recorded codec, and the states are kept
+        for codec in [SnapshotCodec::Lz4, SnapshotCodec::Zstd] {
+            let mut truncated = state_manager.snapshot(codec);
+            truncated.truncate(truncated.len() - 2);
+            assert_eq!(
+                restored.restore(&truncated).map_err(
+                    |err| matches!(err, RestoreError::Decompress(recorded, _) if recorded == codec)
+                ),
+                Err(true)
+            );
+            assert_eq!(restored.get_keyed_state("key-1".as_bytes()), b"1".to_vec());
+        }
+        assert!(matches!(
+            restored.restore(&[SnapshotCodec::None.header(), 0xff]),
+            Err(RestoreError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn test_state_size_tracking() {
+        let inner = MemoryStateManager::new();
+        inner.set_key_state("restored".as_bytes(), "0".as_bytes());
+        let state_manager = LimitedStateManager::new(inner, None);
+        assert_eq!(state_manager.get_size(), 9);
+
+        state_manager.set_key_state("key-1".as_bytes(), "100".as_bytes());
+        assert_eq!(state_manager.get_size(), 17);
+        state_manager.set_key_state("key-1".as_bytes(), "1".as_bytes());
+        assert_eq!(state_manager.get_size(), 15);
+        state_manager.set_broadcast_state("rule".as_bytes(), "broadcast".as_bytes());
+        assert_eq!(state_manager.get_size(), 15);
+        state_manager.delete_keyed_state("key-1".as_bytes());
+        state_manager.delete_keyed_state("missing".as_bytes());
+        assert_eq!(state_manager.get_size(), 9);
+
+        let snapshot = MemoryStateManager::new();
+        snapshot.set_key_state("key-2".as_bytes(), "2222".as_bytes());
+        assert!(state_manager
+            .restore(&snapshot.snapshot(SnapshotCodec::Lz4))
+            .is_ok());
+        assert_eq!(state_manager.get_size(), 9);
+    }
+
+    #[test]
+    fn test_state_limit_policies() {
+        let new_state_manager = |policy: state_limit::Policy| {
+            LimitedStateManager::new(
+                MemoryStateManager::new(),
+                Some(&StateLimit {
+                    max_bytes: 20,
+                    policy: policy as i32,
+                }),
+            )
+        };
+        // each key takes 6 bytes, so the state fits 3 keys
+        let grow = |state_manager: &LimitedStateManager<MemoryStateManager>| {
+            (0..5).for_each(|index| {
+                state_manager.set_key_state(format!("key-{}", index).as_bytes(), "0".as_bytes())
+            })
+        };
+        let get_keys = |state_manager: &LimitedStateManager<MemoryStateManager>| {
+            state_manager
+                .scan_keyed_state(&[])
+                .into_iter()
+                .map(|(key, _)| String::from_utf8(key).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let state_manager = new_state_manager(state_limit::Policy::RejectNewKeys);
+        grow(&state_manager);
+        assert_eq!(get_keys(&state_manager), vec!["key-0", "key-1", "key-2"]);
+        assert_eq!(state_manager.get_size(), 18);
+        assert_eq!(state_manager.take_rejected_keys(), 2);
+        assert_eq!(state_manager.take_rejected_keys(), 0);
+        // the existing keys can still be updated beyond the limit
+        state_manager.set_key_state("key-0".as_bytes(), "0000".as_bytes());
+        assert_eq!(state_manager.get_keyed_state("key-0".as_bytes()).len(), 4);
+        assert_eq!(state_manager.get_size(), 21);
+        assert!(!state_manager.is_exceeded());
+
+        let state_manager = new_state_manager(state_limit::Policy::EvictOldest);
+        grow(&state_manager);
+        assert_eq!(get_keys(&state_manager), vec!["key-2", "key-3", "key-4"]);
+        assert_eq!(state_manager.take_evicted_keys(), 2);
+        // the updated key becomes the most recently written one
+        state_manager.set_key_state("key-2".as_bytes(), "2".as_bytes());
+        state_manager.set_key_state("key-5".as_bytes(), "5".as_bytes());
+        assert_eq!(get_keys(&state_manager), vec!["key-2", "key-4", "key-5"]);
+        assert_eq!(state_manager.take_evicted_keys(), 1);
+        assert!(state_manager.get_size() <= 20);
+
+        let state_manager = new_state_manager(state_limit::Policy::Fail);
+        grow(&state_manager);
+        assert_eq!(get_keys(&state_manager), vec!["key-0", "key-1", "key-2"]);
+        assert!(state_manager.is_exceeded());
+        assert_eq!(state_manager.take_rejected_keys(), 0);
+    }
+}
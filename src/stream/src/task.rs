@@ -1,58 +1,155 @@
 use std::{
-    collections::{btree_set::Iter, BTreeMap, BTreeSet},
+    cell::RefCell,
+    collections::{btree_set::Iter, BTreeMap, BTreeSet, HashMap, VecDeque},
     ops::ControlFlow,
     pin::Pin,
     sync::{
-        atomic::{AtomicU64, Ordering},
-        Arc,
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex,
     },
     task::{Context, Poll},
     time::Duration,
 };
 
 use common::{
+    backpressure::{Backpressure, SharedBackpressure, BACKPRESSURE_METRIC, BACKPRESSURE_WINDOW},
     consts::{
         default_configs::{
             DEFAULT_CHANNEL_SIZE, DEFAULT_SEND_OPERATOR_EVENT_CONNECT_TIMEOUT_MILLIS,
-            DEFAULT_SEND_OPERATOR_EVENT_RPC_TIMEOUT_MILLIS,
+            DEFAULT_SEND_OPERATOR_EVENT_FAN_OUT_CONCURRENCY,
+            DEFAULT_SEND_OPERATOR_EVENT_RPC_TIMEOUT_MILLIS, DEFAULT_SOURCE_REPLAY_BUFFER_CAPACITY,
+            DEFAULT_SOURCE_REPLAY_BUFFER_MAX_DOWNTIME_MILLIS,
         },
         env_keys::{
-            CHANNEL_SIZE, SEND_OPERATOR_EVENT_CONNECT_TIMEOUT, SEND_OPERATOR_EVENT_RPC_TIMEOUT,
+            CHANNEL_SIZE, SEND_OPERATOR_EVENT_CONNECT_TIMEOUT,
+            SEND_OPERATOR_EVENT_FAN_OUT_CONCURRENCY, SEND_OPERATOR_EVENT_RPC_TIMEOUT,
+            SOURCE_REPLAY_BUFFER_CAPACITY, SOURCE_REPLAY_BUFFER_MAX_DOWNTIME,
         },
     },
     event::LocalEvent,
-    futures::join_all,
+    futures::{join_all, JoinBounded},
+    ingest::{IngestError, Ingestion, SOURCE_REDACTED_FIELDS_METRIC, SOURCE_SAMPLED_OUT_METRIC},
+    logging::dataflow_span,
+    lookup::{
+        AsyncLookupRuntime, EmissionBuffer, LookupError, LOOKUP_CACHE_HITS_METRIC,
+        LOOKUP_CACHE_MISSES_METRIC, LOOKUP_IN_FLIGHT_METRIC,
+    },
     map_iter_mut,
     net::gateway::taskmanager::SafeTaskManagerRpcGateway,
-    types::{ExecutorId, SinkId},
-    utils::get_env,
+    ordering::{Fencing, IngressOrdering, Sequencer, SharedRestartFence, SharedSequencer},
+    replay::{Replay, ReplayBuffer, SharedReplayBuffer, REPLAYED_EVENTS_METRIC},
+    schema::{SchemaValidator, SCHEMA_VALIDATED_METRIC, SCHEMA_VIOLATIONS_METRIC},
+    snapshot::LocalCheckpoint,
+    sql_expr::{EvalError, RouteOperator, SqlExprOperator},
+    tap::Tap,
+    throttle::{Admission, ThrottleError, Throttler},
+    types::{ExecutorId, SinkId, TypedValue},
+    utils::{
+        get_env,
+        times::{now_timestamp, prost_now, prost_to_millis},
+    },
 };
 
-use futures_util::{ready, Future};
+use futures_util::{ready, stream::FuturesUnordered, Future, FutureExt, StreamExt};
+use prost::Message;
 use proto::common::{
-    operator_info::Details, Ack, DataflowMeta, ExecutorInfo, ExecutorStatus, Heartbeat,
-    KeyedDataEvent, KeyedEventSet, OperatorInfo, ResourceId,
+    operator_info::Details, Ack, AsyncLookup, DataflowMeta, EventDedup, ExecutorInfo,
+    ExecutorStatus, Heartbeat, KeyedDataEvent, KeyedEventSet, OperatorError, OperatorErrorKind,
+    OperatorInfo, OperatorWatchdog, ResourceId, StateLimit, Throttle,
 };
-use tokio::{sync::RwLock, task::JoinHandle};
+use tokio::{
+    sync::{mpsc, oneshot, watch, RwLock},
+    task::JoinHandle,
+    time::{Instant, Sleep},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{level_filters::LevelFilter, Instrument, Span};
 
 use crate::{
-    connector::{Sink, SinkImpl, Source, SourceImpl},
-    dataflow::Execution,
-    edge::{InEdge, LocalInEdge, LocalOutEdge, OutEdge, RemoteOutEdge},
-    err::{ExecutionError, TaskError},
+    connector::{get_source_position, Sink, SinkImpl, Source, SourceImpl},
+    dataflow::{
+        EventDeduplicator, Execution, DEDUPLICATE_DUPLICATE_METRIC, DEDUPLICATE_UNIQUE_METRIC,
+        SOURCE_DEDUPLICATED_METRIC,
+    },
+    edge::{InEdge, LocalInEdge, LocalOutEdge, OutEdge, OutEdgeError, RemoteOutEdge},
+    err::{DecodeFailure, ExecutionError, SinkException, TaskError},
     new_event_channel,
-    state::new_state_mgt,
+    offsets::SharedOffsetStore,
+    policy::{
+        Decision, ErrorHandler, Failure, Outcome, Retries, SinkOutcome,
+        ERROR_POLICY_RETRIED_METRIC, ERROR_REASON_HEADER, SEND_AFTER_RETRY_METRIC,
+        SEND_FIRST_ATTEMPT_METRIC,
+    },
+    state::{
+        new_state_mgt, LimitedStateManager, SnapshotCodec, StateManager, StateManagerEnum,
+        STATE_EVICTED_KEYS_METRIC, STATE_REJECTED_KEYS_METRIC, STATE_SIZE_METRIC,
+    },
+    wasm::{
+        WasmUdfError, WasmUdfRuntime, WASM_UDF_CALLS_METRIC, WASM_UDF_CALL_MICROS_METRIC,
+        WASM_UDF_COLD_START_METRIC,
+    },
+    watchdog::{
+        ProcessingTimer, Progress, SharedProgress, OPERATOR_STUCK_METRIC, PROCESSING_TIMEOUT_METRIC,
+    },
     Receiver, Sender,
 };
 
+pub use common::metrics::{OPERATOR_EVENTS_IN_METRIC, OPERATOR_EVENTS_OUT_METRIC};
+
+/// metric of the events dropped by a sink operator because the upstream operator sending them has restarted since
+pub const SINK_SUPERSEDED_EVENTS_METRIC: &str = "sink.superseded_events";
+
 pub struct Task {
     executor_id: ExecutorId,
     job_id: ResourceId,
     main_executor_handle: Option<JoinHandle<()>>,
     downstream: BTreeSet<ExecutorId>,
+    // downstreams which are connected by broadcast edges
+    broadcast_downstream: BTreeSet<ExecutorId>,
     last_receive_heartbeat_id: AtomicU64,
+    // milliseconds since the unix epoch of the latest heartbeat received
+    last_receive_heartbeat_at: AtomicI64,
+    // the number of stream executors created for the task, the first one is not a restart
+    created_executors: u32,
     in_edge: Option<Box<dyn OutEdge<Output = LocalEvent>>>,
     states: Arc<RwLock<ExecutorInfo>>,
+    // configuration updates of the Throttle operator
+    throttle_tx: Option<watch::Sender<Throttle>>,
+    // the operator info of the sink operator, the new configurations of the sink are applied to it
+    sink_info: Option<OperatorInfo>,
+    control_tx: mpsc::UnboundedSender<ExecutorControl>,
+    // it's taken by the stream executor once it's created
+    control_rx: Option<mpsc::UnboundedReceiver<ExecutorControl>>,
+    // events recently emitted by the source. They are replayed if the source executor is created again shortly
+    replay_buffer: Option<SharedReplayBuffer>,
+    // states of the operators chained into the executor of this task
+    chained_states: BTreeMap<ExecutorId, Arc<RwLock<ExecutorInfo>>>,
+    // sequences of the events sent by the operator, they are kept across the restarts of the executor
+    sequencer: SharedSequencer,
+    // the last accepted sequences of the events received from remote upstreams
+    ingress: tokio::sync::Mutex<IngressOrdering>,
+    // the latest runs of the upstream operators seen by the external sinks, they are kept across the restarts of the executor
+    restart_fence: SharedRestartFence,
+    // the input left by the last stopped executor
+    handoff: SharedHandoff,
+    // the span which the executor runs in
+    span: Span,
+    // states restored from a remote checkpoint, each of them is restored once the state manager of its operator is created
+    restored_states: BTreeMap<ExecutorId, Vec<u8>>,
+    // it fires once the task is stopped for good, and it cancels the executors created later too
+    cancellation: CancellationToken,
+    // the token of the running executor, it fires when the task or the executor is stopped
+    executor_cancellation: Option<CancellationToken>,
+    // the token of the source of the running executor, it fires before the executor's token when the dataflow is drained
+    source_cancellation: Option<CancellationToken>,
+    // deduplication of the events fetched by the source of the dataflow
+    event_dedup: Option<EventDedup>,
+    // the store which the offsets of the source are committed to, the source resumes from them once it's created again
+    offset_store: Option<SharedOffsetStore>,
+    // liveness of the executors checked by the watchdog, it's kept across the executors of the task
+    progress: Option<SharedProgress>,
+    // time the executors are blocked by their downstreams and sinks, it's kept across the executors of the task
+    backpressure: SharedBackpressure,
 }
 
 impl Task {
@@ -60,87 +157,478 @@ impl Task {
     pub fn receive_ack(&self, ack: &Ack) {}
 
     pub fn new(job_id: &ResourceId, adjacent_node: &DataflowMeta) -> Self {
+        let (control_tx, control_rx) = mpsc::unbounded_channel();
         Self {
             executor_id: adjacent_node.center,
             job_id: job_id.clone(),
             main_executor_handle: None,
             downstream: adjacent_node.neighbors.iter().map(|id| *id).collect(),
+            broadcast_downstream: adjacent_node.get_broadcast_neighbors(),
             last_receive_heartbeat_id: Default::default(),
+            last_receive_heartbeat_at: Default::default(),
+            created_executors: 0,
             in_edge: None,
             states: Arc::new(RwLock::new(ExecutorInfo {
                 executor_id: adjacent_node.center,
                 status: ExecutorStatus::Initialized as i32,
+                metrics: Default::default(),
+                last_heartbeat_at: 0,
+                restart_count: 0,
             })),
+            throttle_tx: None,
+            sink_info: None,
+            control_tx,
+            control_rx: Some(control_rx),
+            replay_buffer: None,
+            chained_states: Default::default(),
+            sequencer: Sequencer::new_shared(),
+            ingress: Default::default(),
+            restart_fence: Default::default(),
+            handoff: Default::default(),
+            span: dataflow_span(job_id, adjacent_node.center, None),
+            restored_states: Default::default(),
+            cancellation: Default::default(),
+            executor_cancellation: None,
+            source_cancellation: None,
+            event_dedup: None,
+            offset_store: None,
+            progress: None,
+            backpressure: Backpressure::new_shared(BACKPRESSURE_WINDOW),
         }
     }
 
+    /// override the log level of the task's executor, the level of the worker is used if it's `None`
+    pub fn set_log_level(&mut self, log_level: Option<LevelFilter>) {
+        self.span = dataflow_span(&self.job_id, self.executor_id, log_level);
+    }
+
+    /// the events fetched by the source are deduplicated if it's set. It does nothing if the task has no source
+    pub fn set_event_dedup(&mut self, event_dedup: Option<&EventDedup>) {
+        self.event_dedup = event_dedup.cloned();
+    }
+
+    /// the source commits its offsets to the store if it's set, see [`Source::commit`]. It does nothing if the task has no source
+    pub fn set_offset_store(&mut self, offset_store: Option<SharedOffsetStore>) {
+        self.offset_store = offset_store;
+    }
+
+    /// the executors of the task are watched by the watchdog if it's set, see [`crate::watchdog::Watchdog`]
+    pub fn set_watchdog(&mut self, watchdog: Option<&OperatorWatchdog>) {
+        self.progress = watchdog.map(Progress::new_shared);
+    }
+
+    /// the liveness of the executors of the task, it's none if they're not watched
+    pub fn get_progress(&self) -> Option<SharedProgress> {
+        self.progress.clone()
+    }
+
+    /// restore the states of the operators of the task from a remote checkpoint, keyed by operator id
+    pub fn set_restored_states(&mut self, restored_states: BTreeMap<ExecutorId, Vec<u8>>) {
+        self.restored_states = restored_states;
+    }
+
     pub fn get_downstream_id_iter(&self) -> Iter<ExecutorId> {
         self.downstream.iter()
     }
 
-    pub fn create_stream_executor(&self, operator_info: &OperatorInfo) -> StreamExecutor {
+    pub fn create_stream_executor(&mut self, operator_info: &OperatorInfo) -> StreamExecutor {
+        self.created_executors += 1;
+        self.sequencer
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .begin_run();
         let details = operator_info.details.clone().unwrap();
+        let throttle = match &details {
+            Details::Throttle(throttle) => {
+                let (tx, rx) = watch::channel(throttle.clone());
+                self.throttle_tx = Some(tx);
+                Some(ThrottleState::new(throttle, rx))
+            }
+            _ => None,
+        };
+        // the module is loaded once per task, and the error is reported by the first event
+        let wasm_udf = match &details {
+            Details::WasmUdf(wasm_udf) => {
+                Some(WasmUdfRuntime::new(operator_info.operator_id, wasm_udf))
+            }
+            _ => None,
+        };
+        // the expression is compiled once per task, and the error is reported by the first event
+        let sql_expr = SqlExprOperator::new(&details);
+        let route = match &details {
+            Details::Route(route) => Some(RouteOperator::new(route)),
+            _ => None,
+        };
+        // the lookup backend and the cache are kept across events, and the error is reported by the first event
+        let async_lookup = match &details {
+            Details::AsyncLookup(async_lookup) => Some(AsyncLookupState::new(async_lookup)),
+            _ => None,
+        };
+        if operator_info.has_sink() {
+            self.sink_info = Some(operator_info.clone());
+        }
+        // the dead-letter operator only receives the failed events
+        let side_outputs = operator_info.get_side_outputs();
+        let cancellation = self.cancellation.child_token();
+        // the events fetched by the source are sampled and redacted before they enter the pipeline, and the error is reported by the first event
+        let ingestion = match &details {
+            Details::Source(source) => Ingestion::new(source).transpose(),
+            _ => None,
+        };
         let source = if operator_info.has_source() {
-            Some(SourceImpl::from((
-                &self.job_id,
-                operator_info.operator_id,
-                &details,
-            )))
+            let mut source =
+                SourceImpl::new(&self.job_id, operator_info, self.offset_store.clone());
+            let source_cancellation = cancellation.child_token();
+            source.set_cancellation_token(source_cancellation.clone());
+            self.source_cancellation = Some(source_cancellation);
+            Some(source)
+        } else {
+            None
+        };
+        // the seen dedup keys are kept in the state manager of the source, so they survive the restarts of the executor
+        let event_dedup = self
+            .event_dedup
+            .as_ref()
+            .filter(|_| source.is_some())
+            .map(|event_dedup| EventDeduplicator::new(self.executor_id, event_dedup));
+        let mut error_handler = ErrorHandler::new(
+            &self.job_id,
+            self.executor_id,
+            operator_info.error_policy.as_ref(),
+        );
+        // the watchdog abandons the work of a stuck executor without stopping it
+        let work = match &self.progress {
+            Some(progress) => progress.renew_work(&cancellation),
+            None => cancellation.clone(),
+        };
+        error_handler.set_cancellation_token(work.clone());
+        // the events left by the last executor are processed before the new input
+        let handoff = self
+            .handoff
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take()
+            .unwrap_or_default();
+        let mut replaying = handoff.pending;
+        let replay_buffer = if source.is_some() {
+            replaying.extend(self.replay());
+            self.replay_buffer.clone()
         } else {
             None
         };
 
+        let mut metrics = HashMap::new();
+        if let Some(Ok(runtime)) = &wasm_udf {
+            metrics.insert(
+                WASM_UDF_COLD_START_METRIC.to_string(),
+                runtime.get_cold_start().as_micros() as u64,
+            );
+        }
+
         StreamExecutor {
             external_sinks: Default::default(),
             executor_id: self.executor_id,
             out_edges: Default::default(),
-            in_edge: None,
+            in_edge: handoff.in_edge,
             source,
+            ingestion,
+            event_dedup,
             operator_details: details,
             job_id: self.job_id.clone(),
             states: self.states.clone(),
+            error_reporter: None,
+            error_handler,
+            retrying: None,
+            failed: false,
+            throttle,
+            wasm_udf,
+            sql_expr,
+            route,
+            async_lookup,
+            schema_validator: operator_info
+                .input_schema
+                .as_ref()
+                .and_then(SchemaValidator::new),
+            processing_timer: operator_info
+                .processing_timeout
+                .as_ref()
+                .and_then(|timeout| timeout.to_duration().to_std().ok())
+                .filter(|timeout| !timeout.is_zero())
+                .map(|timeout| ProcessingTimer::new(self.executor_id, timeout)),
+            control: self.control_rx.take().or(handoff.control),
+            paused: false,
+            drain_acks: vec![],
+            stop_acks: vec![],
+            taps: vec![],
+            state_manager: self
+                .create_state_manager(self.executor_id, operator_info.state_limit.as_ref()),
+            side_outputs,
+            broadcast_downstream: self.broadcast_downstream.clone(),
+            metrics,
+            replay_buffer,
+            replaying,
+            chained: vec![],
+            sequencer: self.sequencer.clone(),
+            headers: operator_info.headers.clone().into_iter().collect(),
+            restart_fence: self.restart_fence.clone(),
+            handoff: self.handoff.clone(),
+            checkpoint_tx: None,
+            snapshot_codec: SnapshotCodec::from_env(),
+            cancellation,
+            work,
+            progress: self.progress.clone(),
+            backpressure: self.backpressure.clone(),
+            flush_timer: None,
+            fan_out_concurrency: get_fan_out_concurrency(),
+            sending: None,
+            unsent: Default::default(),
+        }
+    }
+
+    /// fuse a downstream operator into the executor of this task by operator chaining.
+    /// The operators should be chained in the order they process events
+    pub fn chain_operator(&mut self, executor: &mut StreamExecutor, operator_info: &OperatorInfo) {
+        let executor_id = operator_info.operator_id;
+        let states = Arc::new(RwLock::new(ExecutorInfo {
+            executor_id,
+            status: ExecutorStatus::Initialized as i32,
+            metrics: Default::default(),
+            last_heartbeat_at: 0,
+            restart_count: 0,
+        }));
+        self.chained_states.insert(executor_id, states.clone());
+        // the headers of a chained operator replace the ones of the operators before it
+        executor.headers.extend(operator_info.headers.clone());
+        executor.chained.push(ChainedOperator {
+            executor_id,
+            details: operator_info.details.clone().unwrap(),
+            state_manager: self
+                .create_state_manager(executor_id, operator_info.state_limit.as_ref()),
+            states,
+            metrics: Default::default(),
+        });
+    }
+
+    fn create_state_manager(
+        &mut self,
+        executor_id: ExecutorId,
+        state_limit: Option<&StateLimit>,
+    ) -> LimitedStateManager<StateManagerEnum> {
+        let state_manager = new_state_mgt(&self.job_id, executor_id);
+        if let Some(state) = self.restored_states.remove(&executor_id) {
+            match state_manager.restore(&state) {
+                Ok(_) => tracing::info!(
+                    "states of operator {} of job {:?} are restored",
+                    executor_id,
+                    &self.job_id
+                ),
+                Err(err) => tracing::error!(
+                    "restore states of operator {} of job {:?} failed: {}",
+                    executor_id,
+                    &self.job_id,
+                    err
+                ),
+            }
+        }
+        LimitedStateManager::new(state_manager, state_limit)
+    }
+
+    /// get the events which should be replayed by a new source executor.
+    /// Nothing will be replayed if the source executor is created for the first time or the restart is not short enough,
+    /// and then the source resumes from its committed offset.
+    fn replay(&mut self) -> VecDeque<KeyedDataEvent> {
+        let replay = match &self.replay_buffer {
+            Some(buffer) => buffer
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .replay(Instant::now().into_std()),
+            None => {
+                self.replay_buffer = Some(new_replay_buffer());
+                Replay::Source
+            }
+        };
+        match replay {
+            Replay::Buffer(events) => {
+                tracing::info!(
+                    "source {} of job {:?} replays {} buffered events",
+                    self.executor_id,
+                    &self.job_id,
+                    events.len()
+                );
+                events.into()
+            }
+            Replay::Source => Default::default(),
         }
     }
 
+    /// run the executor until it stops or its cancellation token fires
     pub fn start(&mut self, executor: StreamExecutor) {
-        self.main_executor_handle = Some(tokio::spawn(executor));
+        let cancellation = executor.cancellation.clone();
+        self.executor_cancellation = Some(cancellation.clone());
+        let progress = self.progress.clone();
+        let main_loop = async move {
+            let cancelled = cancellation.cancelled();
+            futures_util::pin_mut!(executor, cancelled);
+            // the executor makes progress once it's polled, and it's idle while it waits to be woken up.
+            // The writes in flight are the work in hand, so an executor waiting for a hung downstream is stuck
+            let executor = futures_util::future::poll_fn(|cx| {
+                progress
+                    .iter()
+                    .for_each(|progress| progress.advance(Instant::now().into_std()));
+                let poll = executor.as_mut().poll(cx);
+                if poll.is_pending() && !executor.is_sending() {
+                    progress.iter().for_each(|progress| progress.idle());
+                }
+                poll
+            });
+            futures_util::pin_mut!(executor);
+            futures_util::future::select(executor, cancelled).await;
+        };
+        self.main_executor_handle = Some(tokio::spawn(main_loop.instrument(self.span.clone())));
+    }
+
+    /// stop the executor of the task. The events it has received but not processed are handed over to the next executor of the task,
+    /// which processes them in the same order before its new input
+    pub async fn stop(&mut self) {
+        self.executor_cancellation
+            .take()
+            .iter()
+            .for_each(|cancellation| cancellation.cancel());
+        if let Some(handle) = self.main_executor_handle.take() {
+            handle.abort();
+            let _ = handle.await;
+        }
+    }
+
+    /// stop the executor of the task at once without waiting for it. The events buffered in it are dropped
+    pub fn abort(&self) {
+        self.cancel();
+        if let Some(handle) = &self.main_executor_handle {
+            handle.abort();
+        }
+    }
+
+    /// fire the cancellation token of the task. The executor stops after the event it's processing,
+    /// and the waits for the external systems and the delays between retries are abandoned
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    /// fire the cancellation token of the source, so that it stops fetching from the external system at once.
+    /// It does nothing if the task has no source
+    pub fn cancel_source(&self) {
+        self.source_cancellation
+            .iter()
+            .for_each(|cancellation| cancellation.cancel());
     }
 
+    /// stop the executor of the task once the events buffered in its input are processed and its sinks are flushed.
+    /// A source stops consuming the external system at once, and a drained operator is resumed to process its queued events
+    pub async fn drain_and_stop(&self) -> Result<(), TaskError> {
+        if self.control_rx.is_some() {
+            return Err(TaskError::ExecutorUnavailable(self.executor_id));
+        }
+        match &self.main_executor_handle {
+            Some(handle) if !handle.is_finished() => {
+                let (tx, rx) = oneshot::channel();
+                self.control_tx
+                    .send(ExecutorControl::Stop(tx))
+                    .map_err(|_| TaskError::ExecutorUnavailable(self.executor_id))?;
+                rx.await
+                    .map_err(|_| TaskError::DrainInterrupted(self.executor_id))
+            }
+            // the executor has stopped, e.g. it fails or receives a terminate event
+            _ => Ok(()),
+        }
+    }
+
+    /// The ingress of the events sent by remote upstreams. The events whose sequences are not increasing are dropped,
+    /// e.g. a late copy of a request which has been retried by the upstream.
+    /// The ingress is locked until the events are delivered, so the requests of an upstream can't be interleaved
     pub async fn send_event_to_operator(&self, event: LocalEvent) -> Result<(), TaskError> {
-        match &self.in_edge {
-            Some(in_edge) => in_edge
-                .write(event)
-                .await
-                .map_err(|err| TaskError::OutEdgeError(err)),
-            None => Ok(()),
+        let in_edge = match &self.in_edge {
+            Some(in_edge) => in_edge,
+            None => return Ok(()),
+        };
+        let event = match event {
+            LocalEvent::KeyedDataStreamEvent(event) => event,
+            LocalEvent::Terminate { .. } => {
+                return in_edge
+                    .write(event)
+                    .await
+                    .map_err(|err| TaskError::OutEdgeError(err))
+            }
+        };
+
+        let mut ingress = self.ingress.lock().await;
+        let from_operator_id = event.from_operator_id;
+        let mut events = vec![event];
+        if ingress.retain_in_order(from_operator_id, &mut events) > 0 {
+            self.log_stale_events(from_operator_id, 1);
+            return Ok(());
         }
+        in_edge
+            .write(LocalEvent::KeyedDataStreamEvent(events[0].clone()))
+            .await
+            .map_err(|err| TaskError::OutEdgeError(err))?;
+        ingress.commit(from_operator_id, &events);
+        Ok(())
     }
 
     pub fn set_in_edge(&mut self, in_edge: Box<dyn OutEdge<Output = LocalEvent>>) {
         self.in_edge = Some(in_edge)
     }
 
-    #[inline]
+    /// the ingress of the event sets sent by remote upstreams, like [`Task::send_event_to_operator`].
+    /// The events delivered before a failure of the batch are accepted, and the others can be sent again by the upstream
     pub async fn batch_send_event_to_operator(
         &self,
         event_set: KeyedEventSet,
     ) -> Result<(), TaskError> {
-        match &self.in_edge {
-            Some(in_edge) => in_edge
-                .batch_write(
-                    &event_set.job_id,
-                    event_set.to_operator_id,
-                    event_set.from_operator_id,
-                    event_set
-                        .events
-                        .into_iter()
-                        .map(|event| LocalEvent::KeyedDataStreamEvent(event))
-                        .collect(),
-                )
-                .await
-                .map_err(|err| TaskError::OutEdgeError(err)),
-            None => Ok(()),
+        let in_edge = match &self.in_edge {
+            Some(in_edge) => in_edge,
+            None => return Ok(()),
+        };
+
+        let mut ingress = self.ingress.lock().await;
+        let from_operator_id = event_set.from_operator_id;
+        let mut events = event_set.events;
+        let stale = ingress.retain_in_order(from_operator_id, &mut events);
+        if stale > 0 {
+            self.log_stale_events(from_operator_id, stale);
+        }
+        if events.is_empty() {
+            return Ok(());
         }
+
+        let result = in_edge
+            .batch_write(
+                &event_set.job_id,
+                event_set.to_operator_id,
+                from_operator_id,
+                events
+                    .iter()
+                    .map(|event| LocalEvent::KeyedDataStreamEvent(event.clone()))
+                    .collect(),
+            )
+            .await;
+        let delivered = match &result {
+            Ok(_) => events.len(),
+            Err(OutEdgeError::BatchSendFailed(errors)) => events.len().saturating_sub(errors.len()),
+            Err(_) => 0,
+        };
+        ingress.commit(from_operator_id, &events[..delivered]);
+        result.map_err(|err| TaskError::OutEdgeError(err))
+    }
+
+    fn log_stale_events(&self, from_operator_id: ExecutorId, stale: usize) {
+        tracing::debug!(
+            "operator {} of job {:?} drops {} stale events from operator {}",
+            self.executor_id,
+            &self.job_id,
+            stale,
+            from_operator_id
+        )
     }
 
     pub fn receive_heartbeat(&self, heartbeat: &Heartbeat) {
@@ -148,14 +636,169 @@ impl Task {
             self.last_receive_heartbeat_id
                 .fetch_max(heartbeat.heartbeat_id, Ordering::Relaxed),
             Ordering::SeqCst,
-        )
+        );
+        if let Some(timestamp) = heartbeat.timestamp.as_ref() {
+            self.last_receive_heartbeat_at
+                .fetch_max(prost_to_millis(timestamp), Ordering::SeqCst);
+        }
     }
 
     pub async fn get_state(&self) -> ExecutorInfo {
-        self.states.read().await.clone()
+        let mut info = self.states.read().await.clone();
+        self.set_liveness(&mut info);
+        info
+    }
+
+    /// states of the operators chained into the executor of this task. Their status is the same as the status of the executor
+    pub async fn get_chained_states(&self) -> Vec<ExecutorInfo> {
+        let status = self.states.read().await.status;
+        let mut chained_states = vec![];
+        for states in self.chained_states.values() {
+            let mut info = states.read().await.clone();
+            info.status = status;
+            self.set_liveness(&mut info);
+            chained_states.push(info);
+        }
+        chained_states
+    }
+
+    /// chained operators share the heartbeats, the restarts and the backpressure of the executor
+    fn set_liveness(&self, info: &mut ExecutorInfo) {
+        info.metrics.insert(
+            BACKPRESSURE_METRIC.to_string(),
+            self.backpressure.get_ratio(Instant::now().into_std()),
+        );
+        info.last_heartbeat_at = self.last_receive_heartbeat_at.load(Ordering::SeqCst);
+        info.restart_count = self.created_executors.saturating_sub(1)
+            + self
+                .progress
+                .as_ref()
+                .map(|progress| progress.get_restarts())
+                .unwrap_or_default();
+    }
+
+    /// pause the input of the operator, then flush its buffers and sinks. It returns once the operator is drained.
+    /// Events sent to a drained operator are queued in its in-edge, so its upstreams will be blocked by backpressure when the queue is full.
+    pub async fn drain(&self) -> Result<(), TaskError> {
+        if self.control_rx.is_some() {
+            return Err(TaskError::ExecutorUnavailable(self.executor_id));
+        }
+        let (tx, rx) = oneshot::channel();
+        self.control_tx
+            .send(ExecutorControl::Drain(tx))
+            .map_err(|_| TaskError::ExecutorUnavailable(self.executor_id))?;
+        rx.await
+            .map_err(|_| TaskError::DrainInterrupted(self.executor_id))
+    }
+
+    /// resume a drained operator
+    pub fn resume(&self) -> Result<(), TaskError> {
+        if self.control_rx.is_some() {
+            return Err(TaskError::ExecutorUnavailable(self.executor_id));
+        }
+        self.control_tx
+            .send(ExecutorControl::Resume)
+            .map_err(|_| TaskError::ExecutorUnavailable(self.executor_id))
+    }
+
+    /// tap the events passing through the operator. At most `sample_rate` events per second are sampled and sent to the receiver.
+    /// The tap is removed once the receiver is dropped.
+    pub fn tap(&self, sample_rate: f64) -> Result<mpsc::Receiver<KeyedDataEvent>, TaskError> {
+        if self.control_rx.is_some() {
+            return Err(TaskError::ExecutorUnavailable(self.executor_id));
+        }
+        let (tap, rx) =
+            Tap::new(sample_rate, Instant::now().into_std()).map_err(TaskError::InvalidTap)?;
+        self.control_tx
+            .send(ExecutorControl::Tap(tap))
+            .map_err(|_| TaskError::ExecutorUnavailable(self.executor_id))?;
+        Ok(rx)
+    }
+
+    /// snapshot the states of the operators of the task for a savepoint. The executor snapshots them between two events without pausing its input
+    pub async fn savepoint(&self) -> Result<BTreeMap<ExecutorId, Vec<u8>>, TaskError> {
+        if self.control_rx.is_some() {
+            return Err(TaskError::ExecutorUnavailable(self.executor_id));
+        }
+        let (tx, rx) = oneshot::channel();
+        self.control_tx
+            .send(ExecutorControl::Savepoint(tx))
+            .map_err(|_| TaskError::ExecutorUnavailable(self.executor_id))?;
+        rx.await
+            .map_err(|_| TaskError::SavepointInterrupted(self.executor_id))
+    }
+
+    /// update the configuration of the Throttle operator at runtime. The executor applies it before processing the next event.
+    pub fn update_throttle(&self, throttle: &Throttle) -> Result<(), TaskError> {
+        throttle
+            .check()
+            .map_err(|err| TaskError::InvalidThrottle(ThrottleError::from(err)))?;
+        match &self.throttle_tx {
+            Some(tx) => {
+                tx.send_replace(throttle.clone());
+                Ok(())
+            }
+            None => Err(TaskError::ThrottleUnsupported(self.executor_id)),
+        }
+    }
+
+    /// apply a new configuration to the sink operator at runtime. The configuration is validated and the new sink is created first,
+    /// then the executor flushes the old sink and replaces it before processing the next event.
+    /// The old sink keeps running if the configuration is invalid or the old sink fails to be flushed
+    pub async fn update_sink(&self, sink: &proto::common::Sink) -> Result<(), TaskError> {
+        if self.control_rx.is_some() {
+            return Err(TaskError::ExecutorUnavailable(self.executor_id));
+        }
+        let info = self
+            .sink_info
+            .as_ref()
+            .ok_or(TaskError::SinkUnsupported(self.executor_id))?;
+        sink.check()
+            .map_err(|err| TaskError::InvalidSink(format!("{:?}", err)))?;
+        let new_sink = SinkImpl::from((
+            &self.job_id,
+            &OperatorInfo {
+                details: Some(Details::Sink(sink.clone())),
+                ..info.clone()
+            },
+        ));
+        if !new_sink.is_connected() {
+            return Err(TaskError::InvalidSink(
+                "the sink can't connect to the external system".to_string(),
+            ));
+        }
+        let (tx, rx) = oneshot::channel();
+        self.control_tx
+            .send(ExecutorControl::UpdateSink(Box::new(new_sink), tx))
+            .map_err(|_| TaskError::ExecutorUnavailable(self.executor_id))?;
+        match rx.await {
+            Ok(result) => {
+                result.map_err(|err| TaskError::SinkUpdateFailed(self.executor_id, err.to_string()))
+            }
+            Err(_) => Err(TaskError::SinkUpdateFailed(
+                self.executor_id,
+                "the executor has stopped".to_string(),
+            )),
+        }
     }
 }
 
+fn get_fan_out_concurrency() -> usize {
+    get_env(SEND_OPERATOR_EVENT_FAN_OUT_CONCURRENCY)
+        .and_then(|concurrency| concurrency.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_SEND_OPERATOR_EVENT_FAN_OUT_CONCURRENCY)
+}
+
+fn new_replay_buffer() -> SharedReplayBuffer {
+    let capacity = get_env(SOURCE_REPLAY_BUFFER_CAPACITY)
+        .and_then(|capacity| capacity.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_SOURCE_REPLAY_BUFFER_CAPACITY);
+    let max_downtime = get_env(SOURCE_REPLAY_BUFFER_MAX_DOWNTIME)
+        .and_then(|max_downtime| max_downtime.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_SOURCE_REPLAY_BUFFER_MAX_DOWNTIME_MILLIS);
+    ReplayBuffer::new_shared(capacity, Duration::from_millis(max_downtime))
+}
+
 pub enum EdgeBuilder<'a> {
     Local {
         tx: Sender<bytes::Bytes>,
@@ -217,31 +860,460 @@ impl<'a> EdgeBuilder<'a> {
     }
 }
 
+/// [`ErrorReporter`] reports the errors of an operator to the coordinator.
+/// Errors will be dropped if the report queue is full so that the operator will never be blocked.
+#[derive(Clone)]
+pub struct ErrorReporter {
+    job_id: ResourceId,
+    executor_id: ExecutorId,
+    tx: mpsc::Sender<OperatorError>,
+}
+
+impl ErrorReporter {
+    pub fn new(
+        job_id: &ResourceId,
+        executor_id: ExecutorId,
+        tx: mpsc::Sender<OperatorError>,
+    ) -> Self {
+        Self {
+            job_id: job_id.clone(),
+            executor_id,
+            tx,
+        }
+    }
+
+    pub fn report<T: std::fmt::Display>(&self, kind: OperatorErrorKind, err: T) {
+        let result = self.tx.try_send(OperatorError {
+            job_id: Some(self.job_id.clone()),
+            operator_id: self.executor_id,
+            kind: kind as i32,
+            message: err.to_string(),
+            timestamp: Some(prost_now()),
+        });
+        if let Err(err) = result {
+            tracing::warn!(
+                "report error of operator {} failed: {}",
+                self.executor_id,
+                err
+            )
+        }
+    }
+}
+
+/// control commands sent from [`Task`] to its [`StreamExecutor`]
+enum ExecutorControl {
+    /// the sender is notified once the executor is drained
+    Drain(oneshot::Sender<()>),
+    Resume,
+    Tap(Tap),
+    /// the sender receives the states of the operators for a savepoint
+    Savepoint(oneshot::Sender<BTreeMap<ExecutorId, Vec<u8>>>),
+    /// the executor stops once its buffered input is processed, and the sender is notified before it stops
+    Stop(oneshot::Sender<()>),
+    /// the old sink is flushed and replaced by the new one, and the sender receives the result of the flush
+    UpdateSink(Box<SinkImpl>, oneshot::Sender<Result<(), SinkException>>),
+}
+
+/// [`ThrottleState`] is the runtime state of the Throttle operator. Unlike other operators, it's kept across events.
+///
+/// In blocking mode, payloads without tokens are held as the blocked event and the executor stops receiving events until the delay is elapsed.
+/// Then the bounded in-edge channel will be full and the upstreams will be blocked.
+struct ThrottleState {
+    throttler: Result<Throttler, ThrottleError>,
+    updates: watch::Receiver<Throttle>,
+    blocked: Option<KeyedDataEvent>,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl ThrottleState {
+    fn new(throttle: &Throttle, updates: watch::Receiver<Throttle>) -> Self {
+        Self {
+            throttler: Throttler::new(throttle, Instant::now().into_std()),
+            updates,
+            blocked: None,
+            delay: None,
+        }
+    }
+
+    fn apply_updates(&mut self) {
+        if !self.updates.has_changed().unwrap_or(false) {
+            return;
+        }
+        let throttle = self.updates.borrow_and_update().clone();
+        let now = Instant::now().into_std();
+        let result = match &mut self.throttler {
+            Ok(throttler) => throttler.update(&throttle, now),
+            Err(_) => Throttler::new(&throttle, now).map(|throttler| {
+                self.throttler = Ok(throttler);
+            }),
+        };
+        if let Err(err) = result {
+            tracing::error!("update throttle failed: {}", err)
+        }
+    }
+}
+
+/// an operator fused into the executor of its upstream by operator chaining.
+/// It processes the outputs of its upstream on the same thread, but its metrics are still reported as a separate operator
+struct ChainedOperator {
+    executor_id: ExecutorId,
+    details: Details,
+    state_manager: LimitedStateManager<StateManagerEnum>,
+    states: Arc<RwLock<ExecutorInfo>>,
+    metrics: HashMap<String, u64>,
+}
+
+impl ChainedOperator {
+    fn add_metric(&mut self, name: &str, value: u64) {
+        publish_metric(&mut self.metrics, &self.states, name, value)
+    }
+}
+
+/// add the value to the metric and publish the metrics to the states.
+/// The metrics will be published by the next event if the states are locked
+fn publish_metric(
+    metrics: &mut HashMap<String, u64>,
+    states: &Arc<RwLock<ExecutorInfo>>,
+    name: &str,
+    value: u64,
+) {
+    *metrics.entry(name.to_string()).or_default() += value;
+    if let Ok(mut guard) = states.try_write() {
+        guard.metrics = metrics.clone();
+    }
+}
+
+/// publish the size of the keyed state and the keys rejected or evicted by its limit.
+/// The size of a stateless operator is not published
+fn publish_state_metrics(
+    metrics: &mut HashMap<String, u64>,
+    states: &Arc<RwLock<ExecutorInfo>>,
+    state_manager: &LimitedStateManager<StateManagerEnum>,
+) {
+    let size = state_manager.get_size();
+    let mut changed = metrics.get(STATE_SIZE_METRIC).unwrap_or(&0) != &size;
+    if changed {
+        metrics.insert(STATE_SIZE_METRIC.to_string(), size);
+    }
+    for (name, value) in [
+        (
+            STATE_REJECTED_KEYS_METRIC,
+            state_manager.take_rejected_keys(),
+        ),
+        (STATE_EVICTED_KEYS_METRIC, state_manager.take_evicted_keys()),
+    ] {
+        if value > 0 {
+            *metrics.entry(name.to_string()).or_default() += value;
+            changed = true;
+        }
+    }
+    if changed {
+        if let Ok(mut guard) = states.try_write() {
+            guard.metrics = metrics.clone();
+        }
+    }
+}
+
+/// the input left by a stopped executor: the events it has received but not processed, its in-edge and its control commands.
+/// The next executor of the task takes it over, so the events are processed in the receiving order across restarts
+#[derive(Default)]
+struct Handoff {
+    pending: VecDeque<KeyedDataEvent>,
+    in_edge: Option<Pin<Box<dyn InEdge<Output = LocalEvent>>>>,
+    control: Option<mpsc::UnboundedReceiver<ExecutorControl>>,
+}
+
+type SharedHandoff = Arc<Mutex<Option<Handoff>>>;
+
+/// an event whose processing fails and is waiting for the next retry.
+/// Like the event blocked by the Throttle operator, no more events will be received until it's processed
+struct RetryingEvent {
+    event: KeyedDataEvent,
+    retries: Retries,
+    delay: Pin<Box<Sleep>>,
+}
+
+/// an output of the operator which is sent to the external sinks and the out edges
+enum Output {
+    Event {
+        event: KeyedDataEvent,
+        // the position of the source event, it's committed once the event is delivered
+        position: Option<(i32, i64)>,
+    },
+    EventSet(KeyedEventSet),
+}
+
+/// an output whose writes to the out edges are in flight. The writes are kept across the polls of the executor like the retrying event,
+/// and no more events will be received until they're done
+struct SendingOutput {
+    writes: JoinBounded<'static, SinkOutcome>,
+    // outcomes of the external sinks, they're resolved with the outcomes of the writes
+    sink_outcomes: Vec<SinkOutcome>,
+    position: Option<(i32, i64)>,
+}
+
+/// a lookup of the AsyncLookup operator. It's queued until the number of in-flight lookups is below the concurrency limit
+struct LookupCall {
+    // sequence of the event in the emission buffer
+    seq: u64,
+    // index of the payload in the event
+    index: usize,
+    key: String,
+    retries: Retries,
+}
+
+type InFlightLookup =
+    Pin<Box<dyn Future<Output = (LookupCall, Result<TypedValue, LookupError>)> + Send>>;
+
+/// an event of the AsyncLookup operator which is waiting for its lookups
+struct PendingLookup {
+    event: KeyedDataEvent,
+    // results of the payloads, they are filled as the lookups complete
+    results: Vec<Option<TypedValue>>,
+    // outcome of the error policy once a lookup of the event fails
+    outcome: Option<Outcome>,
+}
+
+/// the payloads of an event which are prepared for lookups
+#[derive(Default)]
+struct PreparedLookups {
+    // results of the payloads without lookup key or with cached results
+    results: Vec<Option<TypedValue>>,
+    // indexes and lookup keys of the payloads to look up
+    keys: Vec<(usize, String)>,
+    hits: u64,
+    misses: u64,
+}
+
+/// [`AsyncLookupState`] is the runtime state of the AsyncLookup operator. Like the state of the Throttle operator, it's kept across events.
+///
+/// Lookups are polled by the executor, so they are done concurrently without blocking it.
+/// The executor stops receiving events while lookups are queued, and it waits for the in-flight lookups before it's drained or stopped
+struct AsyncLookupState {
+    runtime: Result<AsyncLookupRuntime, LookupError>,
+    queued: VecDeque<LookupCall>,
+    in_flight: FuturesUnordered<InFlightLookup>,
+    pending: EmissionBuffer<PendingLookup>,
+}
+
+impl AsyncLookupState {
+    fn new(async_lookup: &AsyncLookup) -> Self {
+        let runtime = AsyncLookupRuntime::new(async_lookup);
+        let ordered = runtime
+            .as_ref()
+            .map(|runtime| runtime.is_ordered())
+            .unwrap_or(true);
+        Self {
+            runtime,
+            queued: Default::default(),
+            in_flight: Default::default(),
+            pending: EmissionBuffer::new(ordered),
+        }
+    }
+
+    /// get the lookup keys of the payloads of the event. Cached results are used without lookups
+    fn prepare(
+        &mut self,
+        event: &KeyedDataEvent,
+        now: std::time::Instant,
+    ) -> Result<PreparedLookups, LookupError> {
+        let runtime = self.runtime.as_mut().map_err(|err| err.clone())?;
+        let mut prepared = PreparedLookups {
+            results: Vec::with_capacity(event.data.len()),
+            ..Default::default()
+        };
+        for (index, entry) in event.data.iter().enumerate() {
+            let key = match runtime.get_lookup_key(entry)? {
+                Some(key) => key,
+                None => {
+                    prepared.results.push(Some(TypedValue::Null));
+                    continue;
+                }
+            };
+            match runtime.get_cached(&key, now) {
+                Some(value) => {
+                    prepared.hits += 1;
+                    prepared.results.push(Some(value));
+                }
+                None => {
+                    if runtime.has_cache() {
+                        prepared.misses += 1;
+                    }
+                    prepared.results.push(None);
+                    prepared.keys.push((index, key));
+                }
+            }
+        }
+        Ok(prepared)
+    }
+
+    /// buffer the event and queue its lookups. It returns the events which can be emitted now
+    fn submit(&mut self, event: KeyedDataEvent, prepared: PreparedLookups) -> Vec<PendingLookup> {
+        let PreparedLookups { results, keys, .. } = prepared;
+        let event_key = event
+            .key
+            .as_ref()
+            .map(|key| key.value.to_vec())
+            .unwrap_or_default();
+        let lookups = keys.len();
+        let (seq, released) = self.pending.push(
+            event_key,
+            PendingLookup {
+                event,
+                results,
+                outcome: None,
+            },
+            lookups,
+        );
+        self.queued
+            .extend(keys.into_iter().map(|(index, key)| LookupCall {
+                seq,
+                index,
+                key,
+                retries: Retries::default(),
+            }));
+        self.start_lookups();
+        released
+    }
+
+    /// start the queued lookups while the concurrency limit is not reached
+    fn start_lookups(&mut self) {
+        let runtime = match self.runtime.as_ref() {
+            Ok(runtime) => runtime,
+            Err(_) => return,
+        };
+        while self.in_flight.len() < runtime.get_concurrency() {
+            let call = match self.queued.pop_front() {
+                Some(call) => call,
+                None => break,
+            };
+            let lookup = runtime.lookup(&call.key);
+            self.in_flight
+                .push(Box::pin(async move { (call, lookup.await) }));
+        }
+    }
+
+    /// retry the failed lookup after the delay. It keeps its slot of concurrency while it's waiting
+    fn retry(&mut self, call: LookupCall, delay: Duration) {
+        if let Ok(runtime) = self.runtime.as_ref() {
+            let lookup = runtime.lookup(&call.key);
+            self.in_flight.push(Box::pin(async move {
+                tokio::time::sleep(delay).await;
+                (call, lookup.await)
+            }));
+        }
+    }
+
+    fn is_saturated(&self) -> bool {
+        !self.queued.is_empty()
+            || matches!(&self.runtime, Ok(runtime) if self.in_flight.len() >= runtime.get_concurrency())
+    }
+}
+
 /// The stream executor
 pub struct StreamExecutor {
     // external sink connectors
     external_sinks: BTreeMap<SinkId, SinkImpl>,
     // executor id
     executor_id: ExecutorId,
-    // out edges, remote or local
-    out_edges: BTreeMap<ExecutorId, Box<dyn OutEdge<Output = LocalEvent>>>,
+    // out edges, remote or local. They're shared with the writes in flight
+    out_edges: BTreeMap<ExecutorId, Arc<dyn OutEdge<Output = LocalEvent>>>,
     // in edge
     in_edge: Option<Pin<Box<dyn InEdge<Output = LocalEvent>>>>,
     // external source
     source: Option<SourceImpl>,
+    // sampling and redaction of the events fetched by the source
+    ingestion: Option<Result<Ingestion, IngestError>>,
+    // deduplication of the events fetched by the source, after they are sampled and redacted
+    event_dedup: Option<EventDeduplicator>,
     // operator details
     operator_details: Details,
     // job id
     job_id: ResourceId,
     // inner states
     states: Arc<RwLock<ExecutorInfo>>,
+    // reporter of operator errors
+    error_reporter: Option<ErrorReporter>,
+    // handler of processing and sinking errors, it applies the error policy of the operator
+    error_handler: ErrorHandler,
+    // the event waiting for the next retry of processing
+    retrying: Option<RetryingEvent>,
+    // whether the executor stops because of an error
+    failed: bool,
+    // state of the Throttle operator
+    throttle: Option<ThrottleState>,
+    // runtime of the WasmUdf operator, it's kept across events like the state of the Throttle operator
+    wasm_udf: Option<Result<WasmUdfRuntime, WasmUdfError>>,
+    // compiled expression of the FilterExpr or MapExpr operator
+    sql_expr: Option<Result<SqlExprOperator, EvalError>>,
+    // compiled rules of the Route operator
+    route: Option<Result<RouteOperator, EvalError>>,
+    // state of the AsyncLookup operator
+    async_lookup: Option<AsyncLookupState>,
+    // validator of the input payloads if the runtime validation of the input schema is enabled
+    schema_validator: Option<SchemaValidator>,
+    // timer of the processing timeout of the operator, it interrupts the JavaScript functions of the timed out events
+    processing_timer: Option<ProcessingTimer>,
+    // control commands from the task
+    control: Option<mpsc::UnboundedReceiver<ExecutorControl>>,
+    // whether the input is paused by draining
+    paused: bool,
+    // drain requests waiting for the executor to be drained
+    drain_acks: Vec<oneshot::Sender<()>>,
+    // stop requests waiting for the buffered input to be processed
+    stop_acks: Vec<oneshot::Sender<()>>,
+    // debug taps which receive sampled copies of the input events
+    taps: Vec<Tap>,
+    // operator states, they are checkpointed when the executor is drained
+    state_manager: LimitedStateManager<StateManagerEnum>,
+    // out edges which only receive the side output of the operator
+    side_outputs: BTreeSet<ExecutorId>,
+    // out edges which are broadcast edges
+    broadcast_downstream: BTreeSet<ExecutorId>,
+    // metrics of the operator
+    metrics: HashMap<String, u64>,
+    // events emitted by the source are recorded so that they can be replayed after a short restart
+    replay_buffer: Option<SharedReplayBuffer>,
+    // events processed before the new input: the events left by the last executor of the task and the replayed events of the source
+    replaying: VecDeque<KeyedDataEvent>,
+    // operators fused into this executor by operator chaining, they process the outputs of this operator in sequence
+    chained: Vec<ChainedOperator>,
+    // sequences of the events sent by the operator
+    sequencer: SharedSequencer,
+    // metadata headers added to the events sent by the operator, including the headers of the chained operators
+    headers: BTreeMap<String, String>,
+    // the latest runs of the upstream operators seen by the external sinks
+    restart_fence: SharedRestartFence,
+    // the input is handed over to the next executor of the task once this executor is dropped
+    handoff: SharedHandoff,
+    // checkpoints are sent to the uploader of the remote snapshot store if it's configured
+    checkpoint_tx: Option<mpsc::UnboundedSender<LocalCheckpoint>>,
+    // the codec compressing the snapshots of the states which are checkpointed remotely
+    snapshot_codec: SnapshotCodec,
+    // the executor stops once it fires
+    cancellation: CancellationToken,
+    // the waits of the work in hand are abandoned once it fires. It's a child of the executor's token which the watchdog fires if the executor is stuck
+    work: CancellationToken,
+    // liveness of the executor checked by the watchdog
+    progress: Option<SharedProgress>,
+    // time the executor is blocked by its downstreams and sinks
+    backpressure: SharedBackpressure,
+    // timer of the earliest time when the events buffered by the external sinks have to be flushed
+    flush_timer: Option<(std::time::Instant, Pin<Box<Sleep>>)>,
+    // how many out edges an event is sent to at the same time. A failure or a slow downstream doesn't hold up the others
+    fan_out_concurrency: usize,
+    // the output whose writes to the out edges are in flight
+    sending: Option<SendingOutput>,
+    // outputs waiting for the output in hand to be sent, so that they're sent in order
+    unsent: VecDeque<Output>,
 }
 
 unsafe impl Send for StreamExecutor {}
 unsafe impl Sync for StreamExecutor {}
 
 impl StreamExecutor {
-    pub fn add_external_sink(&mut self, sink: SinkImpl) {
+    pub fn add_external_sink(&mut self, mut sink: SinkImpl) {
+        sink.set_cancellation_token(self.work.clone());
         self.external_sinks.insert(sink.sink_id(), sink);
     }
 
@@ -250,52 +1322,317 @@ impl StreamExecutor {
         executor_id: ExecutorId,
         out_edge: Box<dyn OutEdge<Output = LocalEvent>>,
     ) {
-        self.out_edges.insert(executor_id, out_edge);
+        self.out_edges.insert(executor_id, Arc::from(out_edge));
+    }
+
+    pub fn set_fan_out_concurrency(&mut self, concurrency: usize) {
+        self.fan_out_concurrency = concurrency;
     }
 
     pub fn set_in_edge(&mut self, in_edge: Option<Pin<Box<dyn InEdge<Output = LocalEvent>>>>) {
         self.in_edge = in_edge;
     }
 
+    pub fn set_error_reporter(&mut self, error_reporter: ErrorReporter) {
+        self.error_handler
+            .set_error_reporter(error_reporter.clone());
+        self.error_reporter = Some(error_reporter);
+    }
+
+    pub fn set_checkpoint_sender(&mut self, checkpoint_tx: mpsc::UnboundedSender<LocalCheckpoint>) {
+        self.checkpoint_tx = Some(checkpoint_tx);
+    }
+
     pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<LocalEvent>> {
+        if let Some(event) = self.replaying.pop_front() {
+            if self.source.is_some() {
+                self.add_metric(REPLAYED_EVENTS_METRIC, 1);
+            }
+            return Poll::Ready(Some(LocalEvent::KeyedDataStreamEvent(event)));
+        }
         if self.in_edge.is_some() {
             match &mut self.in_edge {
                 Some(in_edge) => in_edge.poll_next(cx),
                 None => Poll::Pending,
             }
-        } else if self.source.is_some() {
-            match &mut self.source {
-                Some(source) => source.poll_next(cx),
-                None => Poll::Ready(None),
-            }
+        } else if self.source.is_some() && self.stop_acks.is_empty() {
+            self.poll_source(cx)
         } else {
             Poll::Pending
         }
     }
 
-    #[inline]
-    fn process(&mut self, event: KeyedDataEvent, cx: &mut Context<'_>) {
-        if self.source.is_some() {
-            self.sink_event_to_external_and_local(event, cx);
-            return;
+    /// fetch the next event from the source. It's sampled and redacted before it enters the pipeline,
+    /// so the raw values of the redacted fields are never recorded by the replay buffer, the taps or the downstream operators
+    fn poll_source(&mut self, cx: &mut Context<'_>) -> Poll<Option<LocalEvent>> {
+        let mut event = match &mut self.source {
+            Some(source) => match ready!(source.poll_next(cx)) {
+                Some(LocalEvent::KeyedDataStreamEvent(event)) => event,
+                event => return Poll::Ready(event),
+            },
+            None => return Poll::Ready(None),
+        };
+        let failures = self
+            .source
+            .as_mut()
+            .map(|source| source.take_decode_failures())
+            .unwrap_or_default();
+        for failure in failures {
+            self.handle_decode_failure(&event, failure, cx);
+        }
+        if self.failed {
+            // the event is not sent, so its offset is not committed and it's fetched again after a restart
+            return Poll::Ready(None);
+        }
+        let ingested = match &self.ingestion {
+            Some(Ok(ingestion)) => ingestion.ingest(&mut event),
+            Some(Err(err)) => {
+                // the raw event is dropped instead of being handled by the error policy
+                tracing::error!("operator {} failed: {}", self.executor_id, err);
+                if let Some(reporter) = self.error_reporter.as_ref() {
+                    reporter.report(OperatorErrorKind::Execution, err)
+                }
+                self.failed = true;
+                return Poll::Ready(None);
+            }
+            None => Some(0),
+        };
+        match ingested {
+            Some(redacted) => {
+                if redacted > 0 {
+                    self.add_metric(SOURCE_REDACTED_FIELDS_METRIC, redacted as u64);
+                }
+            }
+            None => {
+                self.add_metric(SOURCE_SAMPLED_OUT_METRIC, 1);
+                self.commit_source_position(&event);
+                // the source is polled again for the next event
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
         }
 
-        let isolate = &mut v8::Isolate::new(Default::default());
-        let scope = &mut v8::HandleScope::new(isolate);
-        let execution = Execution::new(
-            self.executor_id,
-            &self.operator_details,
-            new_state_mgt(&self.job_id),
-            scope,
-        );
-
-        match execution.process(&event) {
-            Ok(events) => self.sink_event_set_to_external_and_local(KeyedEventSet {
-                events,
-                job_id: event.job_id.clone(),
-                to_operator_id: event.to_operator_id,
-                from_operator_id: self.executor_id,
-            },cx),
+        let deduplicated = match &self.event_dedup {
+            Some(event_dedup) => {
+                event_dedup.deduplicate(&mut event, &self.state_manager, now_timestamp())
+            }
+            None => Ok(0),
+        };
+        match deduplicated {
+            Ok(deduplicated) if deduplicated > 0 => {
+                self.add_metric(SOURCE_DEDUPLICATED_METRIC, deduplicated as u64);
+                if event.data.is_empty() {
+                    self.commit_source_position(&event);
+                    // the source is polled again for the next event
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                // the raw event is dropped instead of being handled by the error policy
+                tracing::error!("operator {} failed: {}", self.executor_id, err);
+                if let Some(reporter) = self.error_reporter.as_ref() {
+                    reporter.report(OperatorErrorKind::Execution, err)
+                }
+                self.failed = true;
+                return Poll::Ready(None);
+            }
+        }
+        Poll::Ready(Some(LocalEvent::KeyedDataStreamEvent(event)))
+    }
+
+    /// the event dropped by the source on purpose is acked like a delivered one, so the events after it can be committed
+    fn commit_source_position(&mut self, event: &KeyedDataEvent) {
+        if let (Some(source), Some((partition, offset))) =
+            (self.source.as_mut(), get_source_position(event))
+        {
+            source.commit(partition, offset)
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, event: KeyedDataEvent, cx: &mut Context<'_>) {
+        if !self.taps.is_empty() {
+            let now = Instant::now().into_std();
+            // closed taps are removed
+            self.taps.retain_mut(|tap| tap.offer(&event, now));
+        }
+
+        if self.source.is_some() {
+            if let Some(buffer) = &self.replay_buffer {
+                buffer
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .push(&event, Instant::now().into_std());
+            }
+            let position = get_source_position(&event);
+            self.sink_event_to_external_and_local(event, position, cx);
+            return;
+        }
+
+        if event.broadcast {
+            self.update_broadcast_state(&event);
+            return;
+        }
+
+        if !self.fence_external_sinks(&event) {
+            return;
+        }
+
+        let event = match self.validate_schema(event, cx) {
+            Some(event) => event,
+            None => return,
+        };
+
+        if self.throttle.is_some() {
+            self.throttle_event(event, cx);
+            return;
+        }
+
+        self.execute(event, Retries::default(), cx)
+    }
+
+    /// validate the payloads of the event against the input schema. The payloads which violate the schema are handled by the error policy,
+    /// and the event of the others is returned. Nothing is returned if no payload is left
+    fn validate_schema(
+        &mut self,
+        mut event: KeyedDataEvent,
+        cx: &mut Context<'_>,
+    ) -> Option<KeyedDataEvent> {
+        let validator = match &self.schema_validator {
+            Some(validator) => validator,
+            None => return Some(event),
+        };
+
+        let mut validated = 0;
+        let mut violated = vec![];
+        let mut violation = None;
+        event.data.retain(|entry| {
+            if !validator.should_validate() {
+                return true;
+            }
+            validated += 1;
+            match validator.validate(&TypedValue::from(entry)) {
+                Ok(_) => true,
+                Err(err) => {
+                    violated.push(entry.clone());
+                    violation.get_or_insert(err);
+                    false
+                }
+            }
+        });
+
+        if validated > 0 {
+            self.add_metric(SCHEMA_VALIDATED_METRIC, validated);
+        }
+        if let Some(err) = violation {
+            self.add_metric(SCHEMA_VIOLATIONS_METRIC, violated.len() as u64);
+            let mut violated_event = event.clone();
+            violated_event.data = violated;
+            // violations will happen again, so they are never retried
+            self.handle_execution_error(
+                violated_event,
+                &ExecutionError::SchemaViolation(err),
+                Retries::default(),
+                cx,
+            );
+        }
+
+        if event.data.is_empty() || self.failed {
+            None
+        } else {
+            Some(event)
+        }
+    }
+
+    /// process the event by the operator. `retries` are the retries of the event which have been done
+    fn execute(&mut self, event: KeyedDataEvent, retries: Retries, cx: &mut Context<'_>) {
+        self.add_metric(OPERATOR_EVENTS_IN_METRIC, 1);
+        if self.wasm_udf.is_some() {
+            self.execute_wasm_udf(event, retries, cx);
+            return;
+        }
+        if self.sql_expr.is_some() {
+            self.execute_sql_expr(event, retries, cx);
+            return;
+        }
+        if self.route.is_some() {
+            self.execute_route(event, retries, cx);
+            return;
+        }
+        if self.async_lookup.is_some() {
+            self.execute_async_lookup(event, cx);
+            return;
+        }
+        let result = {
+            let isolate = &mut v8::Isolate::new(Default::default());
+            if let Some(timer) = self.processing_timer.as_ref() {
+                timer.arm(isolate.thread_safe_handle());
+            }
+            let result = {
+                let scope = &mut v8::HandleScope::new(&mut *isolate);
+                let execution = Execution::new(
+                    self.executor_id,
+                    &self.operator_details,
+                    &self.state_manager,
+                    scope,
+                );
+                execution.process(&event)
+            };
+            // the chained operators are not called once the event is timed out
+            let expired = self
+                .processing_timer
+                .as_ref()
+                .filter(|timer| timer.is_expired())
+                .is_some();
+            let result = result.and_then(|events| {
+                self.add_metric(OPERATOR_EVENTS_OUT_METRIC, events.len() as u64);
+                if expired {
+                    return Ok(vec![]);
+                }
+                self.process_chain(events, isolate)
+            });
+            match self.processing_timer.as_ref() {
+                Some(timer) if timer.disarm() => {
+                    Err(ExecutionError::ProcessingTimeout(timer.get_timeout()))
+                }
+                _ => result,
+            }
+        };
+
+        match result {
+            Ok(events) => {
+                let (side_events, events): (Vec<_>, Vec<_>) = events
+                    .into_iter()
+                    .partition(|new_event| self.side_outputs.contains(&new_event.to_operator_id));
+                if matches!(self.operator_details, Details::Deduplicate(_)) {
+                    let unique = events
+                        .iter()
+                        .map(|new_event| new_event.data.len())
+                        .sum::<usize>();
+                    self.add_metric(DEDUPLICATE_UNIQUE_METRIC, unique as u64);
+                    self.add_metric(
+                        DEDUPLICATE_DUPLICATE_METRIC,
+                        event.data.len().saturating_sub(unique) as u64,
+                    );
+                }
+
+                for side_event in side_events {
+                    self.sink_event_to_side_output(side_event, cx);
+                }
+                if !events.is_empty() {
+                    self.sink_event_set_to_external_and_local(
+                        KeyedEventSet {
+                            events,
+                            job_id: event.job_id.clone(),
+                            to_operator_id: event.to_operator_id,
+                            from_operator_id: self.executor_id,
+                        },
+                        cx,
+                    )
+                }
+            }
             Err(err) => match err {
                 ExecutionError::OperatorUnimplemented(_) => {
                     let event_set = KeyedEventSet {
@@ -305,317 +1642,3560 @@ impl StreamExecutor {
                         from_operator_id: self.executor_id,
                     };
                     self.sink_event_set_to_external_and_local(event_set, cx)
-                },
-                _ => tracing::error!("process event failed: job_id: {:?}, operator_id: {}, event: {:?}. error details: {}", &self.job_id,self.executor_id, event, err)
+                }
+                ExecutionError::ProcessingTimeout(_) => {
+                    tracing::warn!("operator {} abandons the event: {}", self.executor_id, err);
+                    self.add_metric(PROCESSING_TIMEOUT_METRIC, 1);
+                    let mut event = event;
+                    event
+                        .headers
+                        .insert(ERROR_REASON_HEADER.to_string(), err.to_string());
+                    self.handle_execution_error(event, &err, retries, cx)
+                }
+                _ => self.handle_execution_error(event, &err, retries, cx),
             },
         }
+        self.apply_state_limits();
     }
 
-    #[inline]
-    fn sink_event_to_external_and_local(&mut self, event: KeyedDataEvent, cx: &mut Context<'_>) {
-        let ref mut external_sink_futures =
-            map_iter_mut!(self.external_sinks, |(executor_id, sink)| {
-                let mut new_event = event.clone();
-                new_event.to_operator_id = *executor_id;
-                sink.sink(LocalEvent::KeyedDataStreamEvent(new_event))
-            })
-            .collect::<Vec<_>>();
-
-        let ref mut out_edge_futures = map_iter_mut!(self.out_edges, |(executor_id, out_edge)| {
-            let mut new_event = event.clone();
-            new_event.to_operator_id = *executor_id;
-            out_edge.write(LocalEvent::KeyedDataStreamEvent(new_event))
-        })
-        .collect::<Vec<_>>();
-
-        join_all(cx, out_edge_futures, |r| match r {
-            Ok(_) => {}
-            Err(err) => tracing::error!("sink to out edge failed: {}", err),
-        });
+    /// publish the state metrics of this operator and the chained operators.
+    /// The executor fails if the state of any of them exceeds the limit with the `Fail` policy
+    fn apply_state_limits(&mut self) {
+        publish_state_metrics(&mut self.metrics, &self.states, &self.state_manager);
+        for operator in self.chained.iter_mut() {
+            publish_state_metrics(
+                &mut operator.metrics,
+                &operator.states,
+                &operator.state_manager,
+            );
+        }
 
-        join_all(cx, external_sink_futures, |r| match r {
-            Ok(_) => {}
-            Err(err) => tracing::error!("send to external sink failed: {}", err),
-        })
+        let exceeded = self
+            .get_state_managers()
+            .find(|(_, state_manager)| state_manager.is_exceeded())
+            .map(|(operator_id, state_manager)| {
+                ExecutionError::StateLimitExceeded(
+                    operator_id,
+                    state_manager.get_max_bytes().unwrap_or_default(),
+                )
+            });
+        if let Some(err) = exceeded {
+            tracing::error!("operator {} failed: {}", self.executor_id, err);
+            if let Some(reporter) = self.error_reporter.as_ref() {
+                reporter.report(OperatorErrorKind::Execution, &err)
+            }
+            self.failed = true;
+        }
     }
 
-    #[inline]
-    fn sink_event_set_to_external_and_local(
-        &mut self,
-        event_set: KeyedEventSet,
-        cx: &mut Context<'_>,
-    ) {
-        let ref mut external_sink_futures =
-            map_iter_mut!(self.external_sinks, |(executor_id, sink)| {
-                let mut new_event_set = event_set.clone();
-                new_event_set.to_operator_id = *executor_id;
-                sink.batch_sink(new_event_set)
-            })
-            .collect::<Vec<_>>();
+    /// process the event by the WasmUdf operator. It's never chained, so its outputs are sunk directly
+    fn execute_wasm_udf(&mut self, event: KeyedDataEvent, retries: Retries, cx: &mut Context<'_>) {
+        let result = match self.wasm_udf.as_mut() {
+            Some(Ok(runtime)) => {
+                let result = runtime.process(&event);
+                let (calls, call_time) = runtime.take_call_stats();
+                self.add_metric(WASM_UDF_CALLS_METRIC, calls);
+                self.add_metric(WASM_UDF_CALL_MICROS_METRIC, call_time.as_micros() as u64);
+                result
+            }
+            Some(Err(err)) => Err(err.clone()),
+            None => return,
+        };
 
-        let ref mut out_edge_futures = map_iter_mut!(self.out_edges, |(executor_id, out_edge)| {
-            let mut new_event_set = event_set.clone();
-            new_event_set.to_operator_id = *executor_id;
-            out_edge.batch_write(
-                &event_set.job_id,
-                *executor_id,
-                self.executor_id,
-                new_event_set
-                    .events
-                    .into_iter()
-                    .map(|mut event| {
-                        event.to_operator_id = *executor_id;
-                        LocalEvent::KeyedDataStreamEvent(event)
-                    })
-                    .collect(),
-            )
-        })
-        .collect::<Vec<_>>();
+        match result {
+            Ok(events) => {
+                self.add_metric(OPERATOR_EVENTS_OUT_METRIC, events.len() as u64);
+                if !events.is_empty() {
+                    self.sink_event_set_to_external_and_local(
+                        KeyedEventSet {
+                            events,
+                            job_id: event.job_id.clone(),
+                            to_operator_id: event.to_operator_id,
+                            from_operator_id: self.executor_id,
+                        },
+                        cx,
+                    )
+                }
+            }
+            Err(err) => {
+                self.handle_execution_error(event, &ExecutionError::WasmUdfFailed(err), retries, cx)
+            }
+        }
+    }
 
-        join_all(cx, out_edge_futures, |r| match r {
-            Ok(_) => {}
-            Err(err) => tracing::error!("sink to out edge failed: {}", err),
-        });
+    /// process the event by the FilterExpr or MapExpr operator natively. They are never chained, so the outputs are sunk directly
+    fn execute_sql_expr(&mut self, event: KeyedDataEvent, retries: Retries, cx: &mut Context<'_>) {
+        let result = match self.sql_expr.as_ref() {
+            Some(Ok(operator)) => operator.process(&event.data),
+            Some(Err(err)) => Err(err.clone()),
+            None => return,
+        };
 
-        join_all(cx, external_sink_futures, |r| match r {
-            Ok(_) => {}
-            Err(err) => tracing::error!("send to external sink failed: {}", err),
-        })
+        match result {
+            Ok(data) => {
+                if data.is_empty() {
+                    return;
+                }
+                self.add_metric(OPERATOR_EVENTS_OUT_METRIC, 1);
+                let mut new_event = event.clone();
+                new_event.data = data;
+                new_event.from_operator_id = self.executor_id;
+                self.sink_event_set_to_external_and_local(
+                    KeyedEventSet {
+                        events: vec![new_event],
+                        job_id: event.job_id.clone(),
+                        to_operator_id: event.to_operator_id,
+                        from_operator_id: self.executor_id,
+                    },
+                    cx,
+                )
+            }
+            Err(err) => {
+                self.handle_execution_error(event, &ExecutionError::SqlExprFailed(err), retries, cx)
+            }
+        }
     }
-}
 
-impl Future for StreamExecutor {
-    type Output = ();
+    /// route the payloads of the event by the Route operator. The outputs are side outputs, so each downstream only receives
+    /// the payloads routed to it
+    fn execute_route(&mut self, event: KeyedDataEvent, retries: Retries, cx: &mut Context<'_>) {
+        let result = match self.route.as_ref() {
+            Some(Ok(operator)) => operator.process(&event.data),
+            Some(Err(err)) => Err(err.clone()),
+            None => return,
+        };
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.get_mut();
+        match result {
+            Ok(outputs) => outputs.into_iter().for_each(|(downstream, data)| {
+                self.add_metric(OPERATOR_EVENTS_OUT_METRIC, 1);
+                let mut new_event = event.clone();
+                new_event.data = data;
+                new_event.to_operator_id = downstream;
+                new_event.from_operator_id = self.executor_id;
+                self.sink_event_to_side_output(new_event, cx);
+            }),
+            Err(err) => {
+                self.handle_execution_error(event, &ExecutionError::SqlExprFailed(err), retries, cx)
+            }
+        }
+    }
 
-        match this.states.try_write() {
-            Ok(mut guard) => {
-                guard.set_status(ExecutorStatus::Running);
-                drop(guard)
+    /// look up the payloads of the event by the AsyncLookup operator. It's never chained, and the event is sunk directly
+    /// once its lookups are completed
+    fn execute_async_lookup(&mut self, event: KeyedDataEvent, cx: &mut Context<'_>) {
+        let state = match self.async_lookup.as_mut() {
+            Some(state) => state,
+            None => return,
+        };
+        match state.prepare(&event, Instant::now().into_std()) {
+            Ok(prepared) => {
+                let (hits, misses) = (prepared.hits, prepared.misses);
+                let released = state.submit(event, prepared);
+                if hits > 0 {
+                    self.add_metric(LOOKUP_CACHE_HITS_METRIC, hits);
+                }
+                if misses > 0 {
+                    self.add_metric(LOOKUP_CACHE_MISSES_METRIC, misses);
+                }
+                self.emit_lookups(released, cx);
+                self.publish_lookups_in_flight();
             }
-            Err(_) => return Poll::Pending,
+            // invalid operators and payloads are never retried, so the retries are not needed
+            Err(err) => self.handle_execution_error(
+                event,
+                &ExecutionError::AsyncLookupFailed(err),
+                Retries::default(),
+                cx,
+            ),
         }
+    }
+
+    /// poll the in-flight lookups of the AsyncLookup operator and emit the events whose lookups are completed.
+    /// A failed lookup is retried or resolved by the error policy, and its event is resolved by the first outcome
+    fn poll_lookups(&mut self, cx: &mut Context<'_>) {
         loop {
-            let event = ready!(this.poll_next(cx));
-            match event.into_iter().try_for_each(|event| match event {
-                LocalEvent::Terminate { .. } => return ControlFlow::Break(()),
-                LocalEvent::KeyedDataStreamEvent(event) => {
-                    this.process(event, cx);
-                    ControlFlow::Continue(())
-                }
-            }) {
-                ControlFlow::Continue(_) => {
-                    if this.source.is_some() {
-                        return Poll::Pending;
-                    } else {
-                        continue;
+            let state = match self.async_lookup.as_mut() {
+                Some(state) => state,
+                None => return,
+            };
+            state.start_lookups();
+            let (mut call, result) = match state.in_flight.poll_next_unpin(cx) {
+                Poll::Ready(Some(completed)) => completed,
+                _ => break,
+            };
+
+            let released = match result {
+                Ok(value) => {
+                    if let Ok(runtime) = state.runtime.as_mut() {
+                        runtime.cache(call.key.clone(), &value, Instant::now().into_std());
+                    }
+                    if let Some(pending) = state.pending.get_mut(call.seq) {
+                        pending.results[call.index] = Some(value);
                     }
+                    state.pending.complete(call.seq)
                 }
-                ControlFlow::Break(_) => return Poll::Ready(()),
+                Err(err) => match state.pending.get_mut(call.seq) {
+                    Some(pending) if pending.outcome.is_none() => {
+                        let err = ExecutionError::AsyncLookupFailed(err);
+                        let provenance = self.error_handler.get_provenance([&pending.event]);
+                        match self.error_handler.handle(
+                            &Failure::Execution(&err),
+                            &provenance,
+                            &mut call.retries,
+                        ) {
+                            Decision::Retry(delay) => {
+                                state.retry(call, delay);
+                                self.add_metric(ERROR_POLICY_RETRIED_METRIC, 1);
+                                continue;
+                            }
+                            Decision::Resolve(outcome) => {
+                                pending.outcome = Some(outcome);
+                                state.pending.complete(call.seq)
+                            }
+                        }
+                    }
+                    // the event has been resolved by another failed lookup
+                    _ => state.pending.complete(call.seq),
+                },
+            };
+            self.emit_lookups(released, cx);
+        }
+        self.publish_lookups_in_flight();
+    }
+
+    /// sink the events whose lookups are completed, or resolve them by the outcome of the error policy
+    fn emit_lookups(&mut self, released: Vec<PendingLookup>, cx: &mut Context<'_>) {
+        for PendingLookup {
+            event,
+            results,
+            outcome,
+        } in released
+        {
+            if let Some(outcome) = outcome {
+                self.resolve(outcome, vec![event], cx);
+                continue;
             }
+            let runtime = match self.async_lookup.as_ref() {
+                Some(AsyncLookupState {
+                    runtime: Ok(runtime),
+                    ..
+                }) => runtime,
+                _ => return,
+            };
+            let mut new_event = event.clone();
+            new_event.data = event
+                .data
+                .iter()
+                .zip(results)
+                .map(|(entry, result)| runtime.enrich(entry, result.unwrap_or(TypedValue::Null)))
+                .collect();
+            new_event.from_operator_id = self.executor_id;
+            self.add_metric(OPERATOR_EVENTS_OUT_METRIC, 1);
+            self.sink_event_set_to_external_and_local(
+                KeyedEventSet {
+                    events: vec![new_event],
+                    job_id: event.job_id.clone(),
+                    to_operator_id: event.to_operator_id,
+                    from_operator_id: self.executor_id,
+                },
+                cx,
+            )
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
+    /// the number of in-flight lookups is a gauge, so it's set instead of added
+    fn publish_lookups_in_flight(&mut self) {
+        let in_flight = match self.async_lookup.as_ref() {
+            Some(state) => state.in_flight.len() as u64,
+            None => return,
+        };
+        if self.metrics.get(LOOKUP_IN_FLIGHT_METRIC) != Some(&in_flight) {
+            self.metrics
+                .insert(LOOKUP_IN_FLIGHT_METRIC.to_string(), in_flight);
+            if let Ok(mut guard) = self.states.try_write() {
+                guard.metrics = self.metrics.clone();
+            }
+        }
+    }
 
-    use common::{event::LocalEvent, types::TypedValue, utils::times::now_timestamp};
-    use proto::common::{
-        mapper, operator_info, source, DataTypeEnum, DataflowMeta, Entry, ExecutorStatus, Func,
-        KafkaDesc, KeyedDataEvent, Mapper, OperatorInfo, ResourceId, Source,
-    };
+    fn is_lookup_saturated(&self) -> bool {
+        matches!(&self.async_lookup, Some(state) if state.is_saturated())
+    }
 
-    use crate::{
-        edge::{InEdge, LocalInEdge, LocalOutEdge, OutEdge},
-        new_event_channel, MOD_TEST_START,
-    };
+    fn is_lookup_idle(&self) -> bool {
+        !matches!(&self.async_lookup, Some(state) if !state.pending.is_empty())
+    }
 
-    use super::Task;
+    /// process the outputs of this operator by the chained operators in sequence.
+    /// The chained operators have no error policy, so their errors are handled by the policy of this operator
+    fn process_chain(
+        &mut self,
+        mut events: Vec<KeyedDataEvent>,
+        isolate: &mut v8::OwnedIsolate,
+    ) -> Result<Vec<KeyedDataEvent>, ExecutionError> {
+        for operator in self.chained.iter_mut() {
+            operator.add_metric(OPERATOR_EVENTS_IN_METRIC, events.len() as u64);
+            let scope = &mut v8::HandleScope::new(&mut *isolate);
+            let execution = Execution::new(
+                operator.executor_id,
+                &operator.details,
+                &operator.state_manager,
+                scope,
+            );
+            let mut new_events = vec![];
+            for event in &events {
+                new_events.extend(execution.process(event)?);
+            }
+            drop(execution);
+            operator.add_metric(OPERATOR_EVENTS_OUT_METRIC, new_events.len() as u64);
+            events = new_events;
+        }
+        Ok(events)
+    }
 
-    struct TestStreamExecutorSuite {
-        pub in_edge_tx_endpoint: LocalOutEdge<LocalEvent>,
-        pub out_edge_rx_endpoint: LocalInEdge<LocalEvent>,
+    fn handle_execution_error(
+        &mut self,
+        event: KeyedDataEvent,
+        err: &ExecutionError,
+        mut retries: Retries,
+        cx: &mut Context<'_>,
+    ) {
+        let provenance = self.error_handler.get_provenance([&event]);
+        match self
+            .error_handler
+            .handle(&Failure::Execution(err), &provenance, &mut retries)
+        {
+            Decision::Retry(delay) => {
+                self.add_metric(ERROR_POLICY_RETRIED_METRIC, 1);
+                self.retrying = Some(RetryingEvent {
+                    event,
+                    retries,
+                    delay: Box::pin(tokio::time::sleep(delay)),
+                });
+            }
+            Decision::Resolve(outcome) => self.resolve(outcome, vec![event], cx),
+        }
     }
 
-    struct SetupGuard {}
+    /// a row of the fetched message which fails to be decoded is handled by the error policy like a failed event.
+    /// The dead-lettered event has no payloads, its source headers locate the message and the reason header tells the row and the error
+    fn handle_decode_failure(
+        &mut self,
+        event: &KeyedDataEvent,
+        failure: DecodeFailure,
+        cx: &mut Context<'_>,
+    ) {
+        let err = ExecutionError::DecodeFailed(failure);
+        let mut failed = event.clone();
+        failed.data.clear();
+        failed
+            .headers
+            .insert(ERROR_REASON_HEADER.to_string(), err.to_string());
+        self.handle_execution_error(failed, &err, Retries::default(), cx)
+    }
 
-    impl Drop for SetupGuard {
-        fn drop(&mut self) {}
+    /// apply the outcome of the error policy to the failed events
+    fn resolve(&mut self, outcome: Outcome, events: Vec<KeyedDataEvent>, cx: &mut Context<'_>) {
+        self.add_metric(outcome.get_metric(), 1);
+        match outcome {
+            Outcome::Failed => self.failed = true,
+            Outcome::Skipped | Outcome::Cancelled => {}
+            Outcome::DeadLettered(dead_letter) => {
+                for mut event in events {
+                    event.to_operator_id = dead_letter;
+                    self.sink_event_to_side_output(event, cx);
+                }
+            }
+        }
     }
 
-    fn setup() -> SetupGuard {
-        MOD_TEST_START.call_once(|| {
-            v8::V8::set_flags_from_string(
-                "--no_freeze_flags_after_init --expose_gc --harmony-import-assertions --harmony-shadow-realm --allow_natives_syntax --turbo_fast_api_calls",
-              );
-                  v8::V8::initialize_platform(v8::new_default_platform(0, false).make_shared());
-                  v8::V8::initialize();
-        });
-        std::env::set_var("STATE_MANAGER", "MEM");
-        tracing_subscriber::fmt().init();
-        SetupGuard {}
+    /// it returns whether all the events are sent, or resolved by the error policy without failing or being cancelled
+    fn resolve_sink_outcomes(
+        &mut self,
+        sink_outcomes: Vec<SinkOutcome>,
+        cx: &mut Context<'_>,
+    ) -> bool {
+        let mut delivered = true;
+        for sink_outcome in sink_outcomes {
+            if sink_outcome.retries > 0 {
+                self.add_metric(ERROR_POLICY_RETRIED_METRIC, sink_outcome.retries as u64);
+            }
+            match sink_outcome.outcome {
+                Some(outcome) => {
+                    delivered &= !matches!(outcome, Outcome::Failed | Outcome::Cancelled);
+                    self.resolve(outcome, sink_outcome.events, cx)
+                }
+                None if sink_outcome.retries > 0 => self.add_metric(SEND_AFTER_RETRY_METRIC, 1),
+                None => self.add_metric(SEND_FIRST_ATTEMPT_METRIC, 1),
+            }
+        }
+        delivered
     }
 
-    #[test]
-    fn test_task_get_downstream_id_iter() {
-        let job_id = ResourceId {
-            resource_id: "resource_id".to_string(),
-            namespace_id: "namespace_id".to_string(),
+    fn throttle_event(&mut self, mut event: KeyedDataEvent, cx: &mut Context<'_>) {
+        let throttle = match self.throttle.as_mut() {
+            Some(throttle) => throttle,
+            None => return,
         };
-
-        let meta = DataflowMeta {
-            center: 0,
-            neighbors: vec![1, 2, 3, 4],
+        throttle.apply_updates();
+        let throttler = match throttle.throttler.as_mut() {
+            Ok(throttler) => throttler,
+            Err(err) => {
+                let err = ExecutionError::ThrottleFailed(err.clone());
+                // it's never retried so that the retries are not needed
+                self.handle_execution_error(event, &err, Retries::default(), cx);
+                return;
+            }
         };
-        let task = Task::new(&job_id, &meta);
 
-        let mut index = 1;
-        task.get_downstream_id_iter().for_each(|x| {
-            assert_eq!(*x, index);
-            index += 1;
-        });
+        let now = Instant::now().into_std();
+        let key = event.key.as_ref().map(|key| key.value.clone());
+        let mut entries = std::mem::take(&mut event.data).into_iter();
+        let mut passed = vec![];
+        while let Some(entry) = entries.next() {
+            match throttler.admit(key.as_deref(), &entry.value, now) {
+                Admission::Pass => passed.push(entry),
+                Admission::Drop => {}
+                Admission::Wait(delay) => {
+                    let mut blocked = event.clone();
+                    blocked.data = std::iter::once(entry).chain(entries).collect();
+                    throttle.blocked = Some(blocked);
+                    throttle.delay = Some(Box::pin(tokio::time::sleep(delay)));
+                    break;
+                }
+            }
+        }
+
+        // the metrics of the throttler are merged so that the other metrics of the executor are kept
+        self.metrics.extend(throttler.get_metrics());
+        if let Ok(mut guard) = self.states.try_write() {
+            guard.metrics = self.metrics.clone();
+        }
+
+        if !passed.is_empty() {
+            event.data = passed;
+            event.from_operator_id = self.executor_id;
+            self.sink_event_set_to_external_and_local(
+                KeyedEventSet {
+                    job_id: event.job_id.clone(),
+                    to_operator_id: event.to_operator_id,
+                    from_operator_id: self.executor_id,
+                    events: vec![event],
+                },
+                cx,
+            )
+        }
     }
 
-    #[tokio::test]
-    async fn test_task_create_stream_executor() {
-        let job_id = ResourceId {
-            resource_id: "resource_id".to_string(),
-            namespace_id: "namespace_id".to_string(),
-        };
+    fn add_metric(&mut self, name: &str, value: u64) {
+        publish_metric(&mut self.metrics, &self.states, name, value)
+    }
 
-        let meta = DataflowMeta {
-            center: 0,
-            neighbors: vec![1, 2, 3, 4],
-        };
-        let task = Task::new(&job_id, &meta);
-        let executor = task.create_stream_executor(&OperatorInfo {
-            operator_id: 0,
-            host_addr: None,
+    fn poll_control(&mut self, cx: &mut Context<'_>) {
+        // the commands are received once the outputs in hand are sent, so a savepoint or a replaced sink never misses them.
+        // The executor is woken up by the writes in flight
+        if self.is_sending() {
+            return;
+        }
+        while let Some(control) = self.control.as_mut() {
+            match control.poll_recv(cx) {
+                Poll::Ready(Some(ExecutorControl::Drain(ack))) => {
+                    self.paused = true;
+                    self.drain_acks.push(ack);
+                }
+                Poll::Ready(Some(ExecutorControl::Resume)) => {
+                    // drain requests which are not finished yet are interrupted
+                    self.drain_acks.clear();
+                    if self.paused {
+                        self.paused = false;
+                        // the status will be set back to running by the next poll
+                        cx.waker().wake_by_ref();
+                    }
+                }
+                Poll::Ready(Some(ExecutorControl::Tap(tap))) => self.taps.push(tap),
+                Poll::Ready(Some(ExecutorControl::Savepoint(tx))) => {
+                    let _ = tx.send(self.savepoint());
+                }
+                Poll::Ready(Some(ExecutorControl::Stop(ack))) => {
+                    // the queued events of a drained operator are processed before it stops
+                    self.drain_acks.clear();
+                    self.paused = false;
+                    self.stop_acks.push(ack);
+                }
+                Poll::Ready(Some(ExecutorControl::UpdateSink(sink, ack))) => {
+                    let _ = ack.send(self.replace_sink(*sink));
+                }
+                // the task has been dropped
+                Poll::Ready(None) => self.control = None,
+                Poll::Pending => break,
+            }
+        }
+    }
+
+    /// the events from broadcast edges update the broadcast state instead of being processed.
+    /// The encoded key of the event is the key of the state, and the last payload is the value. An event without payloads deletes the key
+    fn update_broadcast_state(&self, event: &KeyedDataEvent) {
+        let key = event.get_key().encode_to_vec();
+        match event.data.last() {
+            Some(entry) => self
+                .state_manager
+                .set_broadcast_state(&key, &entry.encode_to_vec()),
+            None => self.state_manager.delete_broadcast_state(&key),
+        }
+    }
+
+    /// checkpoint the states of the operator and the operators chained into it.
+    /// The checkpoints are uploaded to the remote snapshot store if it's configured
+    fn checkpoint(&self) {
+        let completed_at = std::time::Instant::now();
+        for (operator_id, state_manager) in self.get_state_managers() {
+            state_manager.checkpoint();
+            if let Some(checkpoint_tx) = &self.checkpoint_tx {
+                let _ = checkpoint_tx.send(LocalCheckpoint {
+                    operator_id,
+                    state: state_manager.snapshot(self.snapshot_codec),
+                    completed_at,
+                });
+            }
+        }
+    }
+
+    /// checkpoint the states of the operator and the operators chained into it for a savepoint, and return them keyed by operator id
+    fn savepoint(&self) -> BTreeMap<ExecutorId, Vec<u8>> {
+        self.get_state_managers()
+            .map(|(operator_id, state_manager)| {
+                state_manager.checkpoint();
+                (operator_id, state_manager.snapshot(self.snapshot_codec))
+            })
+            .collect()
+    }
+
+    fn get_state_managers(
+        &self,
+    ) -> impl Iterator<Item = (ExecutorId, &LimitedStateManager<StateManagerEnum>)> {
+        std::iter::once((self.executor_id, &self.state_manager)).chain(
+            self.chained
+                .iter()
+                .map(|operator| (operator.executor_id, &operator.state_manager)),
+        )
+    }
+
+    /// flush the sinks and acknowledge the drain requests. It's called after the input is paused and no event is blocked.
+    fn drain(&mut self, cx: &mut Context<'_>) {
+        // in-flight lookups are completed before the checkpoint, and the executor is woken up by them
+        if self.drain_acks.is_empty() || !self.is_lookup_idle() {
+            return;
+        }
+
+        self.checkpoint();
+        self.flush_sinks();
+
+        match self.states.try_write() {
+            Ok(mut guard) => guard.set_status(ExecutorStatus::Drained),
+            Err(_) => {
+                cx.waker().wake_by_ref();
+                return;
+            }
+        }
+        self.drain_acks.drain(..).for_each(|ack| {
+            let _ = ack.send(());
+        });
+    }
+
+    /// checkpoint the states and flush the sinks before the executor stops. It's called once the buffered input is processed
+    fn poll_stopped(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match self.states.try_write() {
+            Ok(mut guard) => guard.set_status(ExecutorStatus::Terminated),
+            Err(_) => {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        }
+        self.checkpoint();
+        self.flush_sinks();
+        self.stop_acks.drain(..).for_each(|ack| {
+            let _ = ack.send(());
+        });
+        Poll::Ready(())
+    }
+
+    /// flush the external sinks once the events buffered by them have lingered until the deadline, even if no more events arrive.
+    /// The deadline of a sink is postponed by a failed flush, so the failures are not retried in a busy loop
+    fn poll_flush_deadline(&mut self, cx: &mut Context<'_>) {
+        let deadline = match self
+            .external_sinks
+            .values()
+            .filter_map(|sink| sink.get_flush_deadline())
+            .min()
+        {
+            Some(deadline) => deadline,
+            None => {
+                self.flush_timer = None;
+                return;
+            }
+        };
+        if !matches!(&self.flush_timer, Some((at, _)) if *at == deadline) {
+            let timer = tokio::time::sleep_until(Instant::from_std(deadline));
+            self.flush_timer = Some((deadline, Box::pin(timer)));
+        }
+        if let Some((_, timer)) = self.flush_timer.as_mut() {
+            if timer.as_mut().poll(cx).is_ready() {
+                self.flush_timer = None;
+                self.flush_sinks();
+            }
+        }
+    }
+
+    /// flush the external sink with the same id and replace it by the new sink. The old sink is kept if it fails to be flushed,
+    /// so the events buffered in it are not lost
+    fn replace_sink(&mut self, mut sink: SinkImpl) -> Result<(), SinkException> {
+        let sink_id = sink.sink_id();
+        if let Some(old) = self.external_sinks.get_mut(&sink_id) {
+            old.flush_sink()?;
+            old.close_sink();
+        }
+        sink.set_cancellation_token(self.work.clone());
+        self.external_sinks.insert(sink_id, sink);
+        // the timer of the old sink is reset by the next poll
+        self.flush_timer = None;
+        tracing::info!("sink {} of job {:?} is updated", sink_id, &self.job_id);
+        Ok(())
+    }
+
+    fn flush_sinks(&mut self) {
+        for sink in self.external_sinks.values_mut() {
+            if let Err(err) = sink.flush_sink() {
+                tracing::error!("flush external sink failed: {}", err);
+                self.error_reporter
+                    .iter()
+                    .for_each(|reporter| reporter.report(OperatorErrorKind::Sink, &err))
+            }
+        }
+    }
+
+    /// process the event blocked by the Throttle operator or waiting for retry after the delay is elapsed.
+    /// It returns [`Poll::Pending`] if the delay is not elapsed so that no more events will be received.
+    fn poll_blocked(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        while let Some(retrying) = self.retrying.as_mut() {
+            ready!(retrying.delay.as_mut().poll(cx));
+            let RetryingEvent { event, retries, .. } = self.retrying.take().unwrap();
+            self.execute(event, retries, cx);
+        }
+
+        loop {
+            let blocked = match self.throttle.as_mut() {
+                Some(throttle) => match throttle.delay.as_mut() {
+                    Some(delay) => {
+                        ready!(delay.as_mut().poll(cx));
+                        throttle.delay = None;
+                        throttle.blocked.take()
+                    }
+                    None => None,
+                },
+                None => None,
+            };
+
+            match blocked {
+                Some(event) => self.throttle_event(event, cx),
+                None => return Poll::Ready(()),
+            }
+        }
+    }
+
+    /// restart the executor in place once the watchdog finds it stuck. The waits of the work in hand have been abandoned,
+    /// and the following work goes on with the states and the input of the executor. It fails once the restarts are exhausted
+    fn restart_if_stuck(&mut self) {
+        let progress = match &self.progress {
+            Some(progress) if progress.is_stuck() => progress.clone(),
+            _ => return,
+        };
+        self.add_metric(OPERATOR_STUCK_METRIC, 1);
+        match progress.restart(&self.cancellation, Instant::now().into_std()) {
+            Some(work) => {
+                tracing::warn!("stuck operator {} restarts", self.executor_id);
+                // the output of the abandoned work is superseded by the new run
+                self.sequencer
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .begin_run();
+                self.error_handler.set_cancellation_token(work.clone());
+                self.external_sinks
+                    .values_mut()
+                    .for_each(|sink| sink.set_cancellation_token(work.clone()));
+                self.work = work;
+            }
+            None => {
+                tracing::error!(
+                    "operator {} fails because it's stuck after {} restarts",
+                    self.executor_id,
+                    progress.get_restarts()
+                );
+                self.failed = true;
+            }
+        }
+    }
+
+    /// the executor stops once an error is resolved as failed by the error policy
+    fn poll_failed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match self.states.try_write() {
+            Ok(mut guard) => {
+                guard.set_status(ExecutorStatus::Failed);
+                Poll::Ready(())
+            }
+            Err(_) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    /// the output sent to the external sinks next derives from `event`. It's dropped if the upstream operator sending `event` has restarted since,
+    /// and the buffered output of the former runs of the upstream is discarded by the sinks once its new run is seen
+    fn fence_external_sinks(&mut self, event: &KeyedDataEvent) -> bool {
+        if self.external_sinks.is_empty() {
+            return true;
+        }
+        let fencing = self
+            .restart_fence
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .observe(event);
+        if fencing == Fencing::Superseded {
+            tracing::debug!(
+                "event {} of a superseded run of operator {} is dropped",
+                event.event_id,
+                event.from_operator_id
+            );
+            self.add_metric(SINK_SUPERSEDED_EVENTS_METRIC, 1);
+            return false;
+        }
+        self.external_sinks
+            .values_mut()
+            .for_each(|sink| sink.fence(event));
+        true
+    }
+
+    /// number the events sent by this operator, so that its downstreams can keep them in order.
+    /// The headers of the operator are added to the events as well
+    fn stamp(&self, events: &mut [KeyedDataEvent]) {
+        let mut sequencer = self.sequencer.lock().unwrap_or_else(|err| err.into_inner());
+        events.iter_mut().for_each(|event| {
+            self.headers
+                .iter()
+                .for_each(|(name, value)| event.set_header(name.as_str(), value.as_str()));
+            sequencer.stamp(self.executor_id, event)
+        })
+    }
+
+    /// the position of a source event is committed once the event is delivered to all the external sinks and out edges,
+    /// so an undelivered event is fetched again after a restart, see [`Self::poll_sending`]
+    #[inline]
+    fn sink_event_to_external_and_local(
+        &mut self,
+        mut event: KeyedDataEvent,
+        position: Option<(i32, i64)>,
+        cx: &mut Context<'_>,
+    ) {
+        self.stamp(std::slice::from_mut(&mut event));
+        self.send_output(Output::Event { event, position }, cx)
+    }
+
+    /// the side output is an out edge which only receives the events addressed to it
+    fn sink_event_to_side_output(&mut self, mut event: KeyedDataEvent, cx: &mut Context<'_>) {
+        self.stamp(std::slice::from_mut(&mut event));
+        let reporter = self.error_reporter.clone();
+        let side_output = event.to_operator_id;
+        let job_id = event.job_id.clone();
+        let ref mut out_edge_futures = match self.out_edges.get_mut(&side_output) {
+            Some(out_edge) => vec![out_edge.batch_write(
+                &job_id,
+                side_output,
+                self.executor_id,
+                vec![LocalEvent::KeyedDataStreamEvent(event)],
+            )],
+            None => vec![],
+        };
+
+        self.backpressure.block(Instant::now().into_std());
+        join_all(cx, out_edge_futures, |r| match r {
+            Ok(_) => {}
+            Err(err) => {
+                tracing::error!("sink to side output failed: {}", err);
+                reporter
+                    .iter()
+                    .for_each(|reporter| reporter.report(OperatorErrorKind::OutEdge, &err))
+            }
+        });
+        self.backpressure.unblock(Instant::now().into_std());
+    }
+
+    #[inline]
+    fn sink_event_set_to_external_and_local(
+        &mut self,
+        mut event_set: KeyedEventSet,
+        cx: &mut Context<'_>,
+    ) {
+        self.stamp(&mut event_set.events);
+        self.send_output(Output::EventSet(event_set), cx)
+    }
+
+    fn is_sending(&self) -> bool {
+        self.sending.is_some() || !self.unsent.is_empty()
+    }
+
+    /// the output is sent once the outputs before it are sent
+    fn send_output(&mut self, output: Output, cx: &mut Context<'_>) {
+        self.unsent.push_back(output);
+        if self.sending.is_none() {
+            let _ = self.poll_sending(cx);
+        }
+    }
+
+    /// send the outputs in order. It's pending while the writes of an output to the out edges are in flight,
+    /// so a slow downstream yields the thread, and the executor is woken up by the writes
+    fn poll_sending(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if let Some(sending) = self.sending.as_mut() {
+                let writes = ready!(sending.writes.poll_unpin(cx));
+                let SendingOutput {
+                    mut sink_outcomes,
+                    position,
+                    ..
+                } = self.sending.take().unwrap();
+                self.backpressure.unblock(Instant::now().into_std());
+                sink_outcomes.extend(writes.into_iter().map(|(_, sink_outcome)| sink_outcome));
+                if self.resolve_sink_outcomes(sink_outcomes, cx) {
+                    if let (Some(source), Some((partition, offset))) =
+                        (self.source.as_mut(), position)
+                    {
+                        source.commit(partition, offset)
+                    }
+                }
+            }
+            match self.unsent.pop_front() {
+                Some(output) => self.start_sending(output, cx),
+                None => return Poll::Ready(()),
+            }
+        }
+    }
+
+    /// sink the output to the external sinks and start to fan it out to the out edges concurrently.
+    /// The failures are resolved for each of them once all the writes are done
+    fn start_sending(&mut self, output: Output, cx: &mut Context<'_>) {
+        let error_handler = &self.error_handler;
+        let side_outputs = &self.side_outputs;
+        let broadcast_downstream = &self.broadcast_downstream;
+        let from_operator_id = self.executor_id;
+        let out_edges = self
+            .out_edges
+            .iter()
+            .filter(|(executor_id, _)| !side_outputs.contains(*executor_id));
+        let sink_outcomes = RefCell::new(vec![]);
+        self.backpressure.block(Instant::now().into_std());
+
+        let (writes, position) = match output {
+            Output::Event { event, position } => {
+                let writes = out_edges
+                    .map(|(executor_id, out_edge)| {
+                        let mut new_event = event.clone();
+                        new_event.to_operator_id = *executor_id;
+                        new_event.broadcast = broadcast_downstream.contains(executor_id);
+                        let (error_handler, out_edge) = (error_handler.clone(), out_edge.clone());
+                        Box::pin(
+                            async move { error_handler.write(out_edge.as_ref(), new_event).await },
+                        )
+                            as Pin<Box<dyn Future<Output = SinkOutcome> + Send>>
+                    })
+                    .collect::<Vec<_>>();
+                let mut external_sink_futures =
+                    map_iter_mut!(self.external_sinks, |(executor_id, sink)| {
+                        let mut new_event = event.clone();
+                        new_event.to_operator_id = *executor_id;
+                        Box::pin(error_handler.sink(sink, new_event))
+                            as Pin<Box<dyn Future<Output = SinkOutcome> + Send + '_>>
+                    })
+                    .collect::<Vec<_>>();
+                join_all(cx, &mut external_sink_futures, |sink_outcome| {
+                    sink_outcomes.borrow_mut().push(sink_outcome)
+                });
+                (writes, position)
+            }
+            Output::EventSet(event_set) => {
+                let writes = out_edges
+                    .map(|(executor_id, out_edge)| {
+                        let mut new_event_set = event_set.clone();
+                        new_event_set.to_operator_id = *executor_id;
+                        new_event_set.from_operator_id = from_operator_id;
+                        new_event_set.events.iter_mut().for_each(|event| {
+                            event.to_operator_id = *executor_id;
+                            event.broadcast = broadcast_downstream.contains(executor_id);
+                        });
+                        let (error_handler, out_edge) = (error_handler.clone(), out_edge.clone());
+                        Box::pin(async move {
+                            error_handler
+                                .batch_write(out_edge.as_ref(), new_event_set)
+                                .await
+                        })
+                            as Pin<Box<dyn Future<Output = SinkOutcome> + Send>>
+                    })
+                    .collect::<Vec<_>>();
+                let mut external_sink_futures =
+                    map_iter_mut!(self.external_sinks, |(executor_id, sink)| {
+                        let mut new_event_set = event_set.clone();
+                        new_event_set.to_operator_id = *executor_id;
+                        Box::pin(error_handler.batch_sink(sink, new_event_set))
+                            as Pin<Box<dyn Future<Output = SinkOutcome> + Send + '_>>
+                    })
+                    .collect::<Vec<_>>();
+                join_all(cx, &mut external_sink_futures, |sink_outcome| {
+                    sink_outcomes.borrow_mut().push(sink_outcome)
+                });
+                (writes, None)
+            }
+        };
+        self.sending = Some(SendingOutput {
+            writes: JoinBounded::new(writes, self.fan_out_concurrency),
+            sink_outcomes: sink_outcomes.into_inner(),
+            position,
+        });
+    }
+}
+
+impl Drop for StreamExecutor {
+    fn drop(&mut self) {
+        // the event waiting for retry and the event blocked by the Throttle operator are received before the events in the in-edge
+        let mut pending = VecDeque::new();
+        // the events whose lookups are not completed are looked up again by the next executor
+        pending.extend(
+            self.async_lookup
+                .iter_mut()
+                .flat_map(|state| state.pending.take_all())
+                .map(|pending| pending.event),
+        );
+        pending.extend(self.retrying.take().map(|retrying| retrying.event));
+        pending.extend(
+            self.throttle
+                .as_mut()
+                .and_then(|throttle| throttle.blocked.take()),
+        );
+        pending.extend(self.replaying.drain(..));
+        *self.handoff.lock().unwrap_or_else(|err| err.into_inner()) = Some(Handoff {
+            pending,
+            in_edge: self.in_edge.take(),
+            control: self.control.take(),
+        });
+    }
+}
+
+impl Future for StreamExecutor {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        this.poll_control(cx);
+        if !this.paused {
+            match this.states.try_write() {
+                Ok(mut guard) => {
+                    guard.set_status(ExecutorStatus::Running);
+                    drop(guard)
+                }
+                Err(_) => return Poll::Pending,
+            }
+        }
+        loop {
+            // the writes in flight are abandoned once the token fires, and they're resolved before the executor stops
+            let sent = this.poll_sending(cx);
+            // the task is woken up by the token
+            if this.cancellation.is_cancelled() {
+                return Poll::Ready(());
+            }
+            ready!(sent);
+            this.restart_if_stuck();
+            this.poll_control(cx);
+            ready!(this.poll_blocked(cx));
+            this.poll_lookups(cx);
+            this.poll_flush_deadline(cx);
+            if this.is_sending() {
+                // the outputs of the retried, throttled or looked up events are sent before the next input
+                continue;
+            }
+            if this.failed {
+                return this.poll_failed(cx);
+            }
+            if this.paused {
+                // it will be woken up by the control commands
+                this.drain(cx);
+                return Poll::Pending;
+            }
+            if this.is_lookup_saturated() {
+                // it will be woken up by the lookups
+                return Poll::Pending;
+            }
+            let event = match this.poll_next(cx) {
+                // nothing is buffered in the input any more, and the executor waits for the in-flight lookups
+                Poll::Pending | Poll::Ready(None) if !this.stop_acks.is_empty() => {
+                    if !this.is_lookup_idle() {
+                        return Poll::Pending;
+                    }
+                    return this.poll_stopped(cx);
+                }
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(event) => event,
+            };
+            this.progress
+                .iter()
+                .for_each(|progress| progress.advance(Instant::now().into_std()));
+            match event.into_iter().try_for_each(|event| match event {
+                LocalEvent::Terminate { .. } => return ControlFlow::Break(()),
+                LocalEvent::KeyedDataStreamEvent(event) => {
+                    this.process(event, cx);
+                    ControlFlow::Continue(())
+                }
+            }) {
+                ControlFlow::Continue(_) => {
+                    if this.source.is_some() && !this.failed && this.stop_acks.is_empty() {
+                        return Poll::Pending;
+                    } else {
+                        continue;
+                    }
+                }
+                ControlFlow::Break(_) => return Poll::Ready(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{
+        collections::HashMap,
+        future::Future,
+        sync::Arc,
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    use common::{
+        backpressure::BACKPRESSURE_METRIC,
+        event::LocalEvent,
+        ingest::{SOURCE_REDACTED_FIELDS_METRIC, SOURCE_SAMPLED_OUT_METRIC},
+        lookup::{LOOKUP_CACHE_HITS_METRIC, LOOKUP_IN_FLIGHT_METRIC},
+        ordering::Sequencer,
+        replay::{ReplayBuffer, REPLAYED_EVENTS_METRIC},
+        schema::{SCHEMA_VALIDATED_METRIC, SCHEMA_VIOLATIONS_METRIC},
+        types::TypedValue,
+        utils::times::now_timestamp,
+    };
+    use futures_util::task::noop_waker_ref;
+    use prost::Message;
+    use proto::common::{
+        async_lookup, error_policy, mapper, operator_info, payload_schema, project, redaction,
+        redis_desc, route, sink, source, source_sampling, state_limit, throttle, AsyncLookup,
+        Backoff, DataTypeEnum, DataflowMeta, Deduplicate, Entry, ErrorPolicy, ExecutorStatus,
+        FilterExpr, Func, KafkaDesc, KeyedDataEvent, KeyedEventSet, MapExpr, Mapper,
+        OperatorErrorKind, OperatorInfo, OperatorWatchdog, PayloadSchema, Project, Redaction,
+        RedisDesc, ResourceId, Route, Sink, Source, SourceSampling, StateLimit, Throttle, Time,
+    };
+
+    use tonic::async_trait;
+
+    use crate::{
+        connector::{SinkImpl, SourceImpl, SOURCE_OFFSET_HEADER, SOURCE_PARTITION_HEADER},
+        edge::{InEdge, LocalInEdge, LocalOutEdge, OutEdge, OutEdgeError},
+        err::{DecodeFailure, TaskError},
+        new_event_channel,
+        offsets::{OffsetStoreBuilder, SourceOffsets},
+        policy::{
+            ERROR_POLICY_CANCELLED_METRIC, ERROR_POLICY_DEAD_LETTERED_METRIC,
+            ERROR_POLICY_FAILED_METRIC, ERROR_POLICY_RETRIED_METRIC, ERROR_POLICY_SKIPPED_METRIC,
+            ERROR_REASON_HEADER, SEND_AFTER_RETRY_METRIC, SEND_FIRST_ATTEMPT_METRIC,
+        },
+        state::{STATE_EVICTED_KEYS_METRIC, STATE_SIZE_METRIC},
+        watchdog::{Watchdog, OPERATOR_STUCK_METRIC, PROCESSING_TIMEOUT_METRIC},
+        MOD_TEST_START,
+    };
+
+    use super::{
+        ErrorReporter, RetryingEvent, StreamExecutor, Task, OPERATOR_EVENTS_IN_METRIC,
+        OPERATOR_EVENTS_OUT_METRIC, SINK_SUPERSEDED_EVENTS_METRIC,
+    };
+
+    struct TestStreamExecutorSuite {
+        pub in_edge_tx_endpoint: LocalOutEdge<LocalEvent>,
+        pub out_edge_rx_endpoint: LocalInEdge<LocalEvent>,
+    }
+
+    struct SetupGuard {}
+
+    impl Drop for SetupGuard {
+        fn drop(&mut self) {}
+    }
+
+    fn setup() -> SetupGuard {
+        MOD_TEST_START.call_once(|| {
+            v8::V8::set_flags_from_string(
+                "--no_freeze_flags_after_init --expose_gc --harmony-import-assertions --harmony-shadow-realm --allow_natives_syntax --turbo_fast_api_calls",
+              );
+                  v8::V8::initialize_platform(v8::new_default_platform(0, false).make_shared());
+                  v8::V8::initialize();
+        });
+        std::env::set_var("STATE_MANAGER", "MEM");
+        common::logging::init();
+        SetupGuard {}
+    }
+
+    #[test]
+    fn test_task_get_downstream_id_iter() {
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+
+        let meta = DataflowMeta {
+            center: 0,
+            neighbors: vec![1, 2, 3, 4],
+            edge_types: Default::default(),
+        };
+        let task = Task::new(&job_id, &meta);
+
+        let mut index = 1;
+        task.get_downstream_id_iter().for_each(|x| {
+            assert_eq!(*x, index);
+            index += 1;
+        });
+    }
+
+    #[tokio::test]
+    async fn test_task_create_stream_executor() {
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+
+        let meta = DataflowMeta {
+            center: 0,
+            neighbors: vec![1, 2, 3, 4],
+            edge_types: Default::default(),
+        };
+        let mut task = Task::new(&job_id, &meta);
+        let executor = task.create_stream_executor(&OperatorInfo {
+            operator_id: 0,
+            host_addr: None,
+            upstreams: Default::default(),
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
+            details: Some(operator_info::Details::Source(Source {
+                desc: Some(source::Desc::Kafka(KafkaDesc::default())),
+                sampling: None,
+                redactions: vec![],
+            })),
+        });
+
+        assert_eq!(&executor.job_id, &job_id);
+        assert!(executor.source.is_some());
+        assert!(executor.in_edge.is_none());
+        assert!(executor.external_sinks.is_empty());
+        assert!(executor.out_edges.is_empty());
+    }
+
+    fn new_source_info() -> OperatorInfo {
+        OperatorInfo {
+            operator_id: 0,
+            host_addr: None,
+            upstreams: Default::default(),
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
+            details: Some(operator_info::Details::Source(Source {
+                desc: Some(source::Desc::Kafka(KafkaDesc::default())),
+                sampling: None,
+                redactions: vec![],
+            })),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_source_replays_events_after_restart() {
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let meta = DataflowMeta {
+            center: 0,
+            neighbors: vec![1],
+            edge_types: Default::default(),
+        };
+        let events = (0..3)
+            .map(|event_id| KeyedDataEvent {
+                job_id: Some(job_id.clone()),
+                event_id,
+                from_operator_id: 0,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        // a short restart replays the buffered events
+        let mut task = Task::new(&job_id, &meta);
+        task.replay_buffer = Some(ReplayBuffer::new_shared(10, Duration::from_secs(60)));
+        let executor = task.create_stream_executor(&new_source_info());
+        assert!(executor.replaying.is_empty());
+        events.iter().for_each(|event| {
+            executor
+                .replay_buffer
+                .as_ref()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .push(event, std::time::Instant::now())
+        });
+        drop(executor);
+
+        let mut executor = task.create_stream_executor(&new_source_info());
+        assert_eq!(executor.replaying, events);
+        let ref mut cx = Context::from_waker(noop_waker_ref());
+        for event in &events {
+            assert_eq!(
+                executor.poll_next(cx),
+                Poll::Ready(Some(LocalEvent::KeyedDataStreamEvent(event.clone())))
+            );
+        }
+        assert_eq!(
+            executor.metrics.get(REPLAYED_EVENTS_METRIC),
+            Some(&(events.len() as u64))
+        );
+
+        // a long restart resumes from the committed offset of the source
+        let mut task = Task::new(&job_id, &meta);
+        task.replay_buffer = Some(ReplayBuffer::new_shared(10, Duration::from_secs(60)));
+        let executor = task.create_stream_executor(&new_source_info());
+        let emitted_at = std::time::Instant::now()
+            .checked_sub(Duration::from_secs(61))
+            .unwrap();
+        events.iter().for_each(|event| {
+            executor
+                .replay_buffer
+                .as_ref()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .push(event, emitted_at)
+        });
+        drop(executor);
+
+        let executor = task.create_stream_executor(&new_source_info());
+        assert!(executor.replaying.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_source_commits_offsets_after_downstream_ack() {
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let new_event = |offset: i64| {
+            KeyedDataEvent::builder()
+                .job_id(&job_id)
+                .header(SOURCE_PARTITION_HEADER, "0")
+                .header(SOURCE_OFFSET_HEADER, offset.to_string())
+                .build()
+        };
+        let store = OffsetStoreBuilder::Memory.build_shared();
+        let mut task = Task::new(
+            &job_id,
+            &DataflowMeta {
+                center: 0,
+                neighbors: vec![1],
+                edge_types: Default::default(),
+            },
+        );
+        task.set_offset_store(Some(store.clone()));
+        let mut info = new_source_info();
+        info.error_policy = Some(ErrorPolicy {
+            policy: Some(error_policy::Policy::Fail(Default::default())),
+        });
+        let ref mut cx = Context::from_waker(noop_waker_ref());
+        // the event is fetched by the Kafka source of the executor, so its offset is committed once it's acked
+        let fetch = |executor: &mut StreamExecutor, offset: i64| match executor.source.as_mut() {
+            Some(SourceImpl::Kafka(source, ..)) => {
+                match source.track(LocalEvent::KeyedDataStreamEvent(new_event(offset))) {
+                    LocalEvent::KeyedDataStreamEvent(event) => event,
+                    other => panic!("unexpected event {:?}", other),
+                }
+            }
+            _ => panic!("unexpected source"),
+        };
+
+        let mut executor = task.create_stream_executor(&info);
+        let (out_tx, _out_rx) = new_event_channel(10);
+        executor.add_out_edge(1, Box::new(LocalOutEdge::new(out_tx.clone())));
+        let event = fetch(&mut executor, 5);
+        executor.process(event, cx);
+        assert_eq!(
+            store.lock().unwrap().load(&job_id, 0),
+            Some(SourceOffsets::from_iter([(0, 6)]))
+        );
+
+        // the event which fails to be sent to the downstream is not committed
+        executor.add_out_edge(1, Box::new(ClosedOutEdge));
+        let event = fetch(&mut executor, 6);
+        executor.process(event, cx);
+        assert!(executor.failed);
+        assert_eq!(
+            store.lock().unwrap().load(&job_id, 0),
+            Some(SourceOffsets::from_iter([(0, 6)]))
+        );
+        drop(executor);
+
+        // the restarted source resumes from the committed offset, so the failed event is delivered again
+        let mut executor = task.create_stream_executor(&info);
+        executor.add_out_edge(1, Box::new(LocalOutEdge::new(out_tx)));
+        let event = fetch(&mut executor, 6);
+        executor.process(event, cx);
+        assert!(!executor.failed);
+        assert_eq!(
+            store.lock().unwrap().load(&job_id, 0),
+            Some(SourceOffsets::from_iter([(0, 7)]))
+        );
+    }
+
+    /// the writer of the logs captured by the test
+    #[derive(Clone, Default)]
+    struct LogOutput(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for LogOutput {
+        type Writer = LogOutput;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    fn new_redacted_source_info(sampling: Option<SourceSampling>) -> OperatorInfo {
+        OperatorInfo {
+            operator_id: 0,
+            details: Some(operator_info::Details::Source(Source {
+                desc: None,
+                sampling,
+                redactions: vec![
+                    Redaction {
+                        path: "$.user.email".to_string(),
+                        strategy: redaction::Strategy::Mask as i32,
+                        mask: Default::default(),
+                    },
+                    Redaction {
+                        path: "$.ssn".to_string(),
+                        strategy: redaction::Strategy::Hash as i32,
+                        mask: Default::default(),
+                    },
+                ],
+            })),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_source_redacts_events_before_pipeline() {
+        let _ = setup();
+        let logs = LogOutput::default();
+        let _log_guard = tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_writer(logs.clone())
+                .with_ansi(false)
+                .finish(),
+        );
+        let raw_values = ["alice@example.com", "123-45-6789"];
+        let leaks = |bytes: &[u8]| {
+            raw_values.iter().any(|raw| {
+                bytes
+                    .windows(raw.len())
+                    .any(|window| window == raw.as_bytes())
+            })
+        };
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+
+        let mut source_task = Task::new(
+            &job_id,
+            &DataflowMeta {
+                center: 0,
+                neighbors: vec![1, 2],
+                edge_types: Default::default(),
+            },
+        );
+        source_task.replay_buffer = Some(ReplayBuffer::new_shared(10, Duration::from_secs(60)));
+        let mut source = source_task.create_stream_executor(&new_redacted_source_info(None));
+        let source_tx = match source.source.as_ref() {
+            Some(SourceImpl::Empty(_, tx, _)) => tx.clone(),
+            _ => panic!("unexpected source"),
+        };
+        let (out_tx, out_rx) = new_event_channel(10);
+        source.add_out_edge(1, Box::new(LocalOutEdge::new(out_tx)));
+        let mut out = LocalInEdge::new(out_rx);
+
+        // the payload which can not be projected is dead-lettered, and the ssn is the dedup key kept by the state
+        let (_project_task, project, mut dead_letter) = start_task_with_dead_letter(
+            &job_id,
+            1,
+            ErrorPolicy {
+                policy: Some(error_policy::Policy::DeadLetter(error_policy::DeadLetter {
+                    sink: 21,
+                })),
+            },
+            None,
+            operator_info::Details::Project(Project {
+                fields: vec![project::Field {
+                    name: "email".to_string(),
+                    path: "$.user.email".to_string(),
+                    cast: DataTypeEnum::Bigint as i32,
+                    default_value: None,
+                }],
+                key_field: Default::default(),
+            }),
+        );
+        let (deduplicate_task, mut deduplicate, _) = start_task_with_dead_letter(
+            &job_id,
+            2,
+            ErrorPolicy::default(),
+            None,
+            operator_info::Details::Deduplicate(Deduplicate {
+                key_path: "$.ssn".to_string(),
+                horizon: Some(Time {
+                    seconds: 10,
+                    ..Default::default()
+                }),
+                side_output: None,
+            }),
+        );
+
+        assert!(source_tx
+            .send(new_object_event(
+                &job_id,
+                serde_json::json!({
+                    "user": {"name": "alice", "email": "alice@example.com"},
+                    "ssn": "123-45-6789",
+                }),
+            ))
+            .await
+            .is_ok());
+        let ref mut cx = Context::from_waker(noop_waker_ref());
+        let event = match source.poll_next(cx) {
+            Poll::Ready(Some(LocalEvent::KeyedDataStreamEvent(event))) => event,
+            _ => panic!("unexpected event"),
+        };
+        source.process(event, cx);
+        assert_eq!(source.metrics.get(SOURCE_REDACTED_FIELDS_METRIC), Some(&2));
+
+        let event = out.next().await;
+        let payload = get_json(event.clone());
+        assert_eq!(
+            payload["user"],
+            serde_json::json!({"name": "alice", "email": "***"})
+        );
+        assert_eq!(payload["ssn"].as_str().map(str::len), Some(64));
+
+        // the replay buffer only keeps the redacted events
+        let replayed = match source
+            .replay_buffer
+            .as_ref()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .replay(std::time::Instant::now())
+        {
+            common::replay::Replay::Buffer(events) => events,
+            _ => panic!("unexpected replay"),
+        };
+        assert_eq!(replayed.len(), 1);
+        assert!(replayed.iter().all(|event| !leaks(&event.encode_to_vec())));
+
+        // neither the dead letters nor the checkpoints contain the raw values
+        assert!(project
+            .in_edge_tx_endpoint
+            .write(event.clone().unwrap())
+            .await
+            .is_ok());
+        assert_eq!(get_json(dead_letter.next().await), payload);
+        assert!(deduplicate
+            .in_edge_tx_endpoint
+            .write(event.unwrap())
+            .await
+            .is_ok());
+        assert_eq!(
+            get_json(deduplicate.out_edge_rx_endpoint.next().await),
+            payload
+        );
+        let savepoint = deduplicate_task.savepoint().await.unwrap();
+        assert!(!savepoint.is_empty());
+        assert!(savepoint
+            .values()
+            .all(|state| !state.is_empty() && !leaks(state)));
+
+        // the failure of the redacted value is logged
+        let logs = logs.0.lock().unwrap().clone();
+        assert!(String::from_utf8_lossy(&logs).contains("value [\"***\"]"));
+        assert!(!leaks(&logs));
+    }
+
+    #[tokio::test]
+    async fn test_source_samples_events() {
+        let job_id = ResourceId::default();
+        let mut task = Task::new(
+            &job_id,
+            &DataflowMeta {
+                center: 0,
+                neighbors: vec![1],
+                edge_types: Default::default(),
+            },
+        );
+        // the key is hashed out of the only kept bucket
+        let mut source =
+            task.create_stream_executor(&new_redacted_source_info(Some(SourceSampling {
+                rate: u32::MAX,
+                mode: source_sampling::Mode::Key as i32,
+            })));
+        let source_tx = match source.source.as_ref() {
+            Some(SourceImpl::Empty(_, tx, _)) => tx.clone(),
+            _ => panic!("unexpected source"),
+        };
+        let event = KeyedDataEvent::builder()
+            .job_id(&job_id)
+            .key(TypedValue::String("alice".to_string()))
+            .build();
+        assert!(source_tx
+            .send(LocalEvent::KeyedDataStreamEvent(event))
+            .await
+            .is_ok());
+
+        let ref mut cx = Context::from_waker(noop_waker_ref());
+        assert_eq!(source.poll_next(cx), Poll::Pending);
+        assert_eq!(source.metrics.get(SOURCE_SAMPLED_OUT_METRIC), Some(&1));
+        assert_eq!(source.poll_next(cx), Poll::Pending);
+        assert_eq!(source.metrics.get(SOURCE_SAMPLED_OUT_METRIC), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_executor_restart_keeps_events_in_order() {
+        let job_id = ResourceId::default();
+        let meta = DataflowMeta {
+            center: 1,
+            neighbors: vec![2],
+            edge_types: Default::default(),
+        };
+        let operator_info = OperatorInfo {
+            operator_id: 1,
+            details: Some(operator_info::Details::Mapper(Mapper::default())),
+            ..Default::default()
+        };
+        let events = (0..4)
+            .map(|event_id| KeyedDataEvent {
+                job_id: Some(job_id.clone()),
+                event_id,
+                to_operator_id: 1,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let mut task = Task::new(&job_id, &meta);
+        let mut executor = task.create_stream_executor(&operator_info);
+        let (tx, rx) = new_event_channel(10);
+        executor.set_in_edge(Some(Box::pin(LocalInEdge::new(rx))));
+        let in_edge_tx_endpoint = LocalOutEdge::<LocalEvent>::new(tx);
+        // the first event is waiting for retry when the executor stops, and the others are still in the in-edge
+        executor.retrying = Some(RetryingEvent {
+            event: events[0].clone(),
+            retries: Default::default(),
+            delay: Box::pin(tokio::time::sleep(Duration::from_secs(60))),
+        });
+        for event in &events[1..] {
+            in_edge_tx_endpoint
+                .write(LocalEvent::KeyedDataStreamEvent(event.clone()))
+                .await
+                .unwrap();
+        }
+        drop(executor);
+
+        let mut executor = task.create_stream_executor(&operator_info);
+        assert!(executor.control.is_some());
+        let ref mut cx = Context::from_waker(noop_waker_ref());
+        for event in &events {
+            assert_eq!(
+                executor.poll_next(cx),
+                Poll::Ready(Some(LocalEvent::KeyedDataStreamEvent(event.clone())))
+            );
+        }
+        assert_eq!(executor.poll_next(cx), Poll::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_task_ingress_drops_stale_events() {
+        let job_id = ResourceId::default();
+        let meta = DataflowMeta {
+            center: 1,
+            neighbors: vec![],
+            edge_types: Default::default(),
+        };
+        let mut task = Task::new(&job_id, &meta);
+        let (tx, rx) = new_event_channel(10);
+        task.set_in_edge(Box::new(LocalOutEdge::<LocalEvent>::new(tx)));
+        let mut in_edge = LocalInEdge::<LocalEvent>::new(rx);
+
+        let mut sequencer = Sequencer::new(1);
+        let events = (0..3)
+            .map(|event_id| {
+                let mut event = KeyedDataEvent {
+                    job_id: Some(job_id.clone()),
+                    event_id,
+                    to_operator_id: 1,
+                    ..Default::default()
+                };
+                sequencer.stamp(0, &mut event);
+                event
+            })
+            .collect::<Vec<_>>();
+        let new_event_set = |events: &[KeyedDataEvent]| KeyedEventSet {
+            events: events.to_vec(),
+            job_id: Some(job_id.clone()),
+            to_operator_id: 1,
+            from_operator_id: 0,
+        };
+
+        task.batch_send_event_to_operator(new_event_set(&events[..2]))
+            .await
+            .unwrap();
+        // late copies of the requests which have been retried are dropped
+        task.batch_send_event_to_operator(new_event_set(&events[..2]))
+            .await
+            .unwrap();
+        task.send_event_to_operator(LocalEvent::KeyedDataStreamEvent(events[0].clone()))
+            .await
+            .unwrap();
+        task.batch_send_event_to_operator(new_event_set(&events[1..]))
+            .await
+            .unwrap();
+
+        for event in &events {
+            assert_eq!(
+                in_edge.next().await,
+                Some(LocalEvent::KeyedDataStreamEvent(event.clone()))
+            );
+        }
+        let ref mut cx = Context::from_waker(noop_waker_ref());
+        assert!(in_edge.poll_next(cx).is_pending());
+    }
+
+    #[tokio::test]
+    async fn test_sink_drops_output_of_superseded_runs() {
+        let _ = setup();
+        let job_id = ResourceId::default();
+        let meta = DataflowMeta {
+            center: 2,
+            neighbors: vec![3],
+            edge_types: Default::default(),
+        };
+        let mut task = Task::new(&job_id, &meta);
+        let mut executor = task.create_stream_executor(&OperatorInfo {
+            operator_id: 2,
+            details: Some(operator_info::Details::Sink(Default::default())),
+            ..Default::default()
+        });
+        executor.add_external_sink(SinkImpl::Empty(2));
+        let (tx, rx) = new_event_channel(10);
+        executor.add_out_edge(3, Box::new(LocalOutEdge::new(tx)));
+        let mut out_edge = LocalInEdge::new(rx);
+
+        let new_event = |event_id, restart_epoch| KeyedDataEvent {
+            job_id: Some(job_id.clone()),
+            data: vec![Entry {
+                data_type: DataTypeEnum::Number as i32,
+                value: TypedValue::Number(1.0).get_data_bytes(),
+            }],
+            event_id,
+            to_operator_id: 2,
+            from_operator_id: 1,
+            sequence_epoch: 1,
+            restart_epoch,
+            ..Default::default()
+        };
+        let ref mut cx = Context::from_waker(noop_waker_ref());
+        executor.process(new_event(1, 1), cx);
+        // the upstream operator restarts, and the late output of its first run is dropped
+        executor.process(new_event(2, 2), cx);
+        executor.process(new_event(3, 1), cx);
+        executor.process(new_event(4, 2), cx);
+
+        for event_id in [1, 2, 4] {
+            match out_edge.next().await {
+                Some(LocalEvent::KeyedDataStreamEvent(event)) => {
+                    assert_eq!(event.event_id, event_id);
+                    // the output is stamped with the run of the sink operator
+                    assert_eq!((event.from_operator_id, event.restart_epoch), (2, 1));
+                }
+                other => panic!("unexpected output {:?}", other),
+            }
+        }
+        assert!(out_edge.poll_next(cx).is_pending());
+        assert_eq!(
+            executor.metrics.get(SINK_SUPERSEDED_EVENTS_METRIC),
+            Some(&1)
+        );
+
+        // the fence is kept across the restarts of the sink operator
+        drop(executor);
+        let mut executor = task.create_stream_executor(&OperatorInfo {
+            operator_id: 2,
+            details: Some(operator_info::Details::Sink(Default::default())),
+            ..Default::default()
+        });
+        executor.add_external_sink(SinkImpl::Empty(2));
+        executor.process(new_event(5, 1), cx);
+        assert_eq!(
+            executor.metrics.get(SINK_SUPERSEDED_EVENTS_METRIC),
+            Some(&1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_executor_process() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+
+        let meta = DataflowMeta {
+            center: 0,
+            neighbors: vec![1, 2, 3, 4],
+            edge_types: Default::default(),
+        };
+        let mut task = Task::new(&job_id, &meta);
+        assert_eq!(
+            task.states.read().await.status(),
+            ExecutorStatus::Initialized
+        );
+        let mut executor = task.create_stream_executor(&OperatorInfo {
+            operator_id: 1,
+            host_addr: None,
+            upstreams: Default::default(),
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
+            details: Some(operator_info::Details::Mapper(Mapper {
+                value: Some(mapper::Value::Func(Func {
+                    function: "function _operator_map_process(a) { return a+1 }".to_string(),
+                })),
+            })),
+        });
+
+        let (tx, rx) = new_event_channel(10);
+        {
+            executor.set_in_edge(Some(Box::pin(LocalInEdge::new(rx))));
+            assert!(executor.in_edge.is_some());
+        }
+
+        let in_edge_tx_endpoint = LocalOutEdge::new(tx);
+        let (tx, rx) = new_event_channel(10);
+
+        {
+            executor.add_out_edge(2, Box::new(LocalOutEdge::new(tx)));
+            assert_eq!(executor.out_edges.len(), 1);
+        }
+
+        let out_edge_rx_endpoint = LocalInEdge::new(rx);
+
+        let ref mut suite = TestStreamExecutorSuite {
+            in_edge_tx_endpoint,
+            out_edge_rx_endpoint,
+        };
+
+        let handler = tokio::spawn(executor);
+        let timestamp = now_timestamp();
+        for _ in 0..10 {
+            {
+                let result = suite
+                    .in_edge_tx_endpoint
+                    .write(LocalEvent::KeyedDataStreamEvent(KeyedDataEvent {
+                        job_id: Some(ResourceId::new("ns_id", "resource_id")),
+                        key: None,
+                        to_operator_id: 2,
+                        data: vec![Entry {
+                            data_type: DataTypeEnum::Number as i32,
+                            value: TypedValue::Number(1.0).get_data_bytes(),
+                        }],
+                        event_time: timestamp,
+                        from_operator_id: 0,
+                        window: None,
+                        event_id: 0,
+                        broadcast: false,
+                        sequence: 0,
+                        sequence_epoch: 0,
+                        restart_epoch: 0,
+                        headers: Default::default(),
+                    }))
+                    .await;
+                assert!(result.is_ok());
+                let opt = suite.out_edge_rx_endpoint.next().await;
+                assert_eq!(
+                    opt,
+                    Some(LocalEvent::KeyedDataStreamEvent(KeyedDataEvent {
+                        job_id: Some(ResourceId::new("ns_id", "resource_id")),
+                        key: None,
+                        to_operator_id: 2,
+                        data: vec![Entry {
+                            data_type: DataTypeEnum::Number as i32,
+                            value: TypedValue::Number(2.0).get_data_bytes(),
+                        }],
+                        event_time: timestamp,
+                        from_operator_id: 0,
+                        window: None,
+                        event_id: 0,
+                        broadcast: false,
+                        sequence: 0,
+                        sequence_epoch: 0,
+                        restart_epoch: 0,
+                        headers: Default::default(),
+                    }))
+                );
+            }
+        }
+
+        assert_eq!(task.states.read().await.status(), ExecutorStatus::Running);
+
+        let result = suite
+            .in_edge_tx_endpoint
+            .write(LocalEvent::Terminate {
+                job_id: Default::default(),
+                to: 1,
+                event_time: now_timestamp(),
+            })
+            .await;
+        assert!(result.is_ok());
+
+        let _ = handler.await;
+    }
+
+    #[tokio::test]
+    async fn test_task_update_throttle() {
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let meta = DataflowMeta {
+            center: 1,
+            neighbors: vec![2],
+            edge_types: Default::default(),
+        };
+        let rate_limit = |events_per_sec| Throttle {
+            mode: Some(throttle::Mode::RateLimit(throttle::RateLimit {
+                events_per_sec,
+                burst: 10,
+                drop: false,
+            })),
+        };
+
+        let mut task = Task::new(&job_id, &meta);
+        assert!(matches!(
+            task.update_throttle(&rate_limit(10.0)),
+            Err(TaskError::ThrottleUnsupported(1))
+        ));
+
+        let mut executor = task.create_stream_executor(&OperatorInfo {
+            operator_id: 1,
+            host_addr: None,
+            upstreams: vec![0],
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
+            details: Some(operator_info::Details::Throttle(rate_limit(10.0))),
+        });
+        let mut throttle = executor.throttle.take().unwrap();
+        assert!(throttle.throttler.is_ok());
+
+        assert!(task.update_throttle(&rate_limit(100.0)).is_ok());
+        assert!(throttle.updates.has_changed().unwrap());
+        throttle.apply_updates();
+        assert!(!throttle.updates.has_changed().unwrap());
+
+        assert!(matches!(
+            task.update_throttle(&rate_limit(-1.0)),
+            Err(TaskError::InvalidThrottle(_))
+        ));
+        assert!(!throttle.updates.has_changed().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_task_update_sink() {
+        let _ = setup();
+        let job_id = ResourceId::default();
+        let redis_sink = |host: &str| Sink {
+            desc: Some(sink::Desc::Redis(RedisDesc {
+                connection_opts: Some(redis_desc::ConnectionOpts {
+                    host: host.to_string(),
+                    ..Default::default()
+                }),
+                key_extractor: Some(Func {
+                    function: "function redis_extractor(a) { return a.key }".to_string(),
+                }),
+                value_extractor: Some(Func {
+                    function: "function redis_extractor(a) { return a.value }".to_string(),
+                }),
+            })),
+            ..Default::default()
+        };
+
+        let mut task = Task::new(
+            &job_id,
+            &DataflowMeta {
+                center: 2,
+                neighbors: vec![],
+                edge_types: Default::default(),
+            },
+        );
+        assert!(matches!(
+            task.update_sink(&redis_sink("localhost")).await,
+            Err(TaskError::ExecutorUnavailable(2))
+        ));
+        let mut executor = task.create_stream_executor(&OperatorInfo {
+            operator_id: 2,
+            details: Some(operator_info::Details::Sink(Default::default())),
+            ..Default::default()
+        });
+        executor.add_external_sink(SinkImpl::Empty(2));
+        let ref mut cx = Context::from_waker(noop_waker_ref());
+
+        // an invalid configuration never reaches the executor, so the old sink keeps running
+        assert!(matches!(
+            task.update_sink(&redis_sink("")).await,
+            Err(TaskError::InvalidSink(_))
+        ));
+        executor.poll_control(cx);
+        assert!(matches!(
+            executor.external_sinks.get(&2),
+            Some(SinkImpl::Empty(2))
+        ));
+
+        // the executor replaces the sink once it polls the control commands
+        let sink = redis_sink("localhost");
+        let update = task.update_sink(&sink);
+        futures_util::pin_mut!(update);
+        assert!(update.as_mut().poll(cx).is_pending());
+        executor.poll_control(cx);
+        assert!(update.await.is_ok());
+        assert!(matches!(
+            executor.external_sinks.get(&2),
+            Some(SinkImpl::Redis(_))
+        ));
+        assert_eq!(executor.external_sinks.len(), 1);
+
+        // the operators other than sinks are rejected
+        let (map_task, _suite) = start_map_task(&job_id, 3);
+        assert!(matches!(
+            map_task.update_sink(&redis_sink("localhost")).await,
+            Err(TaskError::SinkUnsupported(3))
+        ));
+    }
+
+    /// start a map operator whose in-edge and out-edge are returned
+    fn start_map_task(job_id: &ResourceId, operator_id: u32) -> (Task, TestStreamExecutorSuite) {
+        let mut task = Task::new(
+            job_id,
+            &DataflowMeta {
+                center: operator_id,
+                neighbors: vec![operator_id + 10],
+                edge_types: Default::default(),
+            },
+        );
+        let mut executor = task.create_stream_executor(&OperatorInfo {
+            operator_id,
+            host_addr: None,
+            upstreams: Default::default(),
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
+            details: Some(operator_info::Details::Mapper(Mapper {
+                value: Some(mapper::Value::Func(Func {
+                    function: "function _operator_map_process(a) { return a+1 }".to_string(),
+                })),
+            })),
+        });
+
+        let (in_tx, in_rx) = new_event_channel(10);
+        executor.set_in_edge(Some(Box::pin(LocalInEdge::new(in_rx))));
+        let (out_tx, out_rx) = new_event_channel(10);
+        executor.add_out_edge(operator_id + 10, Box::new(LocalOutEdge::new(out_tx)));
+        task.start(executor);
+
+        (
+            task,
+            TestStreamExecutorSuite {
+                in_edge_tx_endpoint: LocalOutEdge::new(in_tx),
+                out_edge_rx_endpoint: LocalInEdge::new(out_rx),
+            },
+        )
+    }
+
+    fn new_number_event(job_id: &ResourceId, value: f64) -> LocalEvent {
+        LocalEvent::KeyedDataStreamEvent(KeyedDataEvent {
+            job_id: Some(job_id.clone()),
+            data: vec![Entry {
+                data_type: DataTypeEnum::Number as i32,
+                value: TypedValue::Number(value).get_data_bytes(),
+            }],
+            event_time: now_timestamp(),
+            ..Default::default()
+        })
+    }
+
+    fn get_number(event: Option<LocalEvent>) -> TypedValue {
+        match event {
+            Some(LocalEvent::KeyedDataStreamEvent(event)) => TypedValue::from(&event.data[0]),
+            _ => TypedValue::Invalid,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drain_and_resume_operator() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let (drained_task, mut drained) = start_map_task(&job_id, 1);
+        let (running_task, mut running) = start_map_task(&job_id, 2);
+
+        assert!(drained_task.drain().await.is_ok());
+        assert_eq!(
+            drained_task.get_state().await.status(),
+            ExecutorStatus::Drained
+        );
+
+        for value in [1.0, 2.0] {
+            assert!(drained
+                .in_edge_tx_endpoint
+                .write(new_number_event(&job_id, value))
+                .await
+                .is_ok());
+            assert!(running
+                .in_edge_tx_endpoint
+                .write(new_number_event(&job_id, value))
+                .await
+                .is_ok());
+        }
+
+        // the other operator is not affected
+        assert_eq!(
+            get_number(running.out_edge_rx_endpoint.next().await),
+            TypedValue::Number(2.0)
+        );
+        assert_eq!(
+            get_number(running.out_edge_rx_endpoint.next().await),
+            TypedValue::Number(3.0)
+        );
+        assert_eq!(
+            running_task.get_state().await.status(),
+            ExecutorStatus::Running
+        );
+
+        // events are queued until the drained operator is resumed
+        assert!(tokio::time::timeout(
+            std::time::Duration::from_millis(100),
+            drained.out_edge_rx_endpoint.next()
+        )
+        .await
+        .is_err());
+
+        assert!(drained_task.resume().is_ok());
+        assert_eq!(
+            get_number(drained.out_edge_rx_endpoint.next().await),
+            TypedValue::Number(2.0)
+        );
+        assert_eq!(
+            get_number(drained.out_edge_rx_endpoint.next().await),
+            TypedValue::Number(3.0)
+        );
+        assert_eq!(
+            drained_task.get_state().await.status(),
+            ExecutorStatus::Running
+        );
+
+        // a task without executor can't be drained
+        let task = Task::new(
+            &job_id,
+            &DataflowMeta {
+                center: 3,
+                neighbors: vec![],
+                edge_types: Default::default(),
+            },
+        );
+        assert!(matches!(
+            task.drain().await,
+            Err(TaskError::ExecutorUnavailable(3))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tap_operator() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let (task, mut suite) = start_map_task(&job_id, 1);
+
+        assert!(matches!(task.tap(0.0), Err(TaskError::InvalidTap(_))));
+        let mut tap = task.tap(20.0).expect("tap operator failed");
+
+        // 100 events per second are sent to the operator
+        let start = std::time::Instant::now();
+        for value in 0..100 {
+            assert!(suite
+                .in_edge_tx_endpoint
+                .write(new_number_event(&job_id, value as f64))
+                .await
+                .is_ok());
+            // the main path is not affected by the tap
+            assert_eq!(
+                get_number(suite.out_edge_rx_endpoint.next().await),
+                TypedValue::Number(value as f64 + 1.0)
+            );
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let expected = start.elapsed().as_secs_f64() * 20.0 + 1.0;
+
+        let mut sampled = 0;
+        while tap.try_recv().is_ok() {
+            sampled += 1;
+        }
+        assert!(
+            sampled as f64 <= expected && sampled as f64 >= expected * 0.7,
+            "sampled {} events, expected about {}",
+            sampled,
+            expected
+        );
+
+        // the operator keeps running after the tap is closed
+        drop(tap);
+        assert!(suite
+            .in_edge_tx_endpoint
+            .write(new_number_event(&job_id, 1.0))
+            .await
+            .is_ok());
+        assert_eq!(
+            get_number(suite.out_edge_rx_endpoint.next().await),
+            TypedValue::Number(2.0)
+        );
+    }
+
+    /// start a project operator whose in-edge, out-edge and dead-letter out-edge are returned
+    fn start_project_task(
+        job_id: &ResourceId,
+        operator_id: u32,
+        error_policy: ErrorPolicy,
+        input_schema: Option<PayloadSchema>,
+    ) -> (Task, TestStreamExecutorSuite, LocalInEdge<LocalEvent>) {
+        start_task_with_dead_letter(
+            job_id,
+            operator_id,
+            error_policy,
+            input_schema,
+            operator_info::Details::Project(Project {
+                fields: vec![project::Field {
+                    name: "id".to_string(),
+                    path: "$.id".to_string(),
+                    cast: DataTypeEnum::Bigint as i32,
+                    default_value: None,
+                }],
+                key_field: Default::default(),
+            }),
+        )
+    }
+
+    /// the operator sends its outputs to `operator_id + 10` and the dead letters to `operator_id + 20`
+    fn start_task_with_dead_letter(
+        job_id: &ResourceId,
+        operator_id: u32,
+        error_policy: ErrorPolicy,
+        input_schema: Option<PayloadSchema>,
+        details: operator_info::Details,
+    ) -> (Task, TestStreamExecutorSuite, LocalInEdge<LocalEvent>) {
+        start_operator_with_dead_letter(
+            job_id,
+            &OperatorInfo {
+                operator_id,
+                host_addr: None,
+                upstreams: Default::default(),
+                error_policy: Some(error_policy),
+                chaining: Default::default(),
+                input_schema,
+                output_schema: None,
+                state_limit: None,
+                headers: Default::default(),
+                processing_timeout: None,
+                details: Some(details),
+            },
+        )
+    }
+
+    /// the same as [`start_task_with_dead_letter`] for an operator with any settings
+    fn start_operator_with_dead_letter(
+        job_id: &ResourceId,
+        operator_info: &OperatorInfo,
+    ) -> (Task, TestStreamExecutorSuite, LocalInEdge<LocalEvent>) {
+        let operator_id = operator_info.operator_id;
+        let mut task = Task::new(
+            job_id,
+            &DataflowMeta {
+                center: operator_id,
+                neighbors: vec![operator_id + 10, operator_id + 20],
+                edge_types: Default::default(),
+            },
+        );
+        let mut executor = task.create_stream_executor(operator_info);
+
+        let (in_tx, in_rx) = new_event_channel(10);
+        executor.set_in_edge(Some(Box::pin(LocalInEdge::new(in_rx))));
+        let (out_tx, out_rx) = new_event_channel(10);
+        executor.add_out_edge(operator_id + 10, Box::new(LocalOutEdge::new(out_tx)));
+        let (dead_letter_tx, dead_letter_rx) = new_event_channel(10);
+        executor.add_out_edge(
+            operator_id + 20,
+            Box::new(LocalOutEdge::new(dead_letter_tx)),
+        );
+        task.start(executor);
+
+        (
+            task,
+            TestStreamExecutorSuite {
+                in_edge_tx_endpoint: LocalOutEdge::new(in_tx),
+                out_edge_rx_endpoint: LocalInEdge::new(out_rx),
+            },
+            LocalInEdge::new(dead_letter_rx),
+        )
+    }
+
+    fn new_object_event(job_id: &ResourceId, value: serde_json::Value) -> LocalEvent {
+        LocalEvent::KeyedDataStreamEvent(
+            KeyedDataEvent::builder()
+                .job_id(job_id)
+                .payload(TypedValue::from_json_value(value))
+                .event_time(now_timestamp())
+                .build(),
+        )
+    }
+
+    fn get_json(event: Option<LocalEvent>) -> serde_json::Value {
+        match event {
+            Some(LocalEvent::KeyedDataStreamEvent(event)) => {
+                TypedValue::from(&event.data[0]).to_json_value()
+            }
+            _ => serde_json::Value::Null,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_error_policy_of_operator() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+
+        // the failed event is sent to the dead-letter operator after retries
+        let retry = ErrorPolicy {
+            policy: Some(error_policy::Policy::Retry(Box::new(error_policy::Retry {
+                attempts: 2,
+                backoff: Some(Backoff {
+                    base: 1,
+                    max: 1,
+                    jitter: false,
+                }),
+                fallback: Some(Box::new(ErrorPolicy {
+                    policy: Some(error_policy::Policy::DeadLetter(error_policy::DeadLetter {
+                        sink: 21,
+                    })),
+                })),
+            }))),
+        };
+        let (task, mut suite, mut dead_letter) = start_project_task(&job_id, 1, retry, None);
+        for value in [
+            serde_json::json!({"id": "abc"}),
+            serde_json::json!({"id": "1"}),
+        ] {
+            assert!(suite
+                .in_edge_tx_endpoint
+                .write(new_object_event(&job_id, value))
+                .await
+                .is_ok());
+        }
+        assert_eq!(
+            get_json(dead_letter.next().await),
+            serde_json::json!({"id": "abc"})
+        );
+        assert_eq!(
+            get_json(suite.out_edge_rx_endpoint.next().await),
+            serde_json::json!({"id": 1})
+        );
+        let metrics = task.get_state().await.metrics;
+        assert_eq!(metrics.get(ERROR_POLICY_RETRIED_METRIC), Some(&2));
+        assert_eq!(metrics.get(ERROR_POLICY_DEAD_LETTERED_METRIC), Some(&1));
+
+        // the operator stops on the failed event
+        let fail = ErrorPolicy {
+            policy: Some(error_policy::Policy::Fail(Default::default())),
+        };
+        let (task, suite, _) = start_project_task(&job_id, 2, fail, None);
+        assert!(suite
+            .in_edge_tx_endpoint
+            .write(new_object_event(&job_id, serde_json::json!({"id": "abc"})))
+            .await
+            .is_ok());
+        let mut status = task.get_state().await.status();
+        for _ in 0..100 {
+            if status == ExecutorStatus::Failed {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            status = task.get_state().await.status();
+        }
+        assert_eq!(status, ExecutorStatus::Failed);
+        assert_eq!(
+            task.get_state()
+                .await
+                .metrics
+                .get(ERROR_POLICY_FAILED_METRIC),
+            Some(&1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_processing_timeout_of_operator() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        // the timed out event is never retried, so it falls back to the dead-letter operator at once
+        let error_policy = ErrorPolicy {
+            policy: Some(error_policy::Policy::Retry(Box::new(error_policy::Retry {
+                attempts: 3,
+                backoff: None,
+                fallback: Some(Box::new(ErrorPolicy {
+                    policy: Some(error_policy::Policy::DeadLetter(error_policy::DeadLetter {
+                        sink: 21,
+                    })),
+                })),
+            }))),
+        };
+        let (task, mut suite, mut dead_letter) = start_operator_with_dead_letter(
+            &job_id,
+            &OperatorInfo {
+                operator_id: 1,
+                error_policy: Some(error_policy),
+                processing_timeout: Some(Time {
+                    millis: 100,
+                    ..Default::default()
+                }),
+                details: Some(operator_info::Details::Mapper(Mapper {
+                    value: Some(mapper::Value::Func(Func {
+                        function:
+                            "function _operator_map_process(a) { while (a.id === 2) {} return a }"
+                                .to_string(),
+                    })),
+                })),
+                ..Default::default()
+            },
+        );
+        for id in 1..=3 {
+            assert!(suite
+                .in_edge_tx_endpoint
+                .write(new_object_event(&job_id, serde_json::json!({ "id": id })))
+                .await
+                .is_ok());
+        }
+
+        assert_eq!(
+            get_json(suite.out_edge_rx_endpoint.next().await),
+            serde_json::json!({"id": 1})
+        );
+        // the event hanging the operator is abandoned, and the next one is processed
+        let timed_out = match dead_letter.next().await {
+            Some(LocalEvent::KeyedDataStreamEvent(event)) => event,
+            _ => panic!("the timed out event is not dead-lettered"),
+        };
+        assert_eq!(
+            TypedValue::from(&timed_out.data[0]).to_json_value(),
+            serde_json::json!({"id": 2})
+        );
+        assert!(timed_out.headers[ERROR_REASON_HEADER].starts_with("processing timeout"));
+        assert_eq!(
+            get_json(suite.out_edge_rx_endpoint.next().await),
+            serde_json::json!({"id": 3})
+        );
+
+        let metrics = task.get_state().await.metrics;
+        assert_eq!(metrics.get(PROCESSING_TIMEOUT_METRIC), Some(&1));
+        assert_eq!(metrics.get(ERROR_POLICY_DEAD_LETTERED_METRIC), Some(&1));
+        assert_eq!(metrics.get(ERROR_POLICY_RETRIED_METRIC), None);
+    }
+
+    #[tokio::test]
+    async fn test_state_limit_of_operator() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let start_deduplicate_task = |operator_id: u32, policy: state_limit::Policy| {
+            let mut task = Task::new(
+                &job_id,
+                &DataflowMeta {
+                    center: operator_id,
+                    neighbors: vec![operator_id + 10],
+                    edge_types: Default::default(),
+                },
+            );
+            let mut executor = task.create_stream_executor(&OperatorInfo {
+                operator_id,
+                state_limit: Some(StateLimit {
+                    max_bytes: 64,
+                    policy: policy as i32,
+                }),
+                details: Some(operator_info::Details::Deduplicate(Deduplicate {
+                    key_path: "$.id".to_string(),
+                    horizon: Some(Time {
+                        seconds: 10,
+                        ..Default::default()
+                    }),
+                    side_output: None,
+                })),
+                ..Default::default()
+            });
+            let (in_tx, in_rx) = new_event_channel(10);
+            executor.set_in_edge(Some(Box::pin(LocalInEdge::new(in_rx))));
+            let (out_tx, out_rx) = new_event_channel(10);
+            executor.add_out_edge(operator_id + 10, Box::new(LocalOutEdge::new(out_tx)));
+            task.start(executor);
+            (
+                task,
+                TestStreamExecutorSuite {
+                    in_edge_tx_endpoint: LocalOutEdge::new(in_tx),
+                    out_edge_rx_endpoint: LocalInEdge::new(out_rx),
+                },
+            )
+        };
+
+        // the oldest dedup keys are evicted, so the state never exceeds the limit
+        let (task, mut suite) = start_deduplicate_task(1, state_limit::Policy::EvictOldest);
+        for id in 0..5 {
+            assert!(suite
+                .in_edge_tx_endpoint
+                .write(new_object_event(&job_id, serde_json::json!({ "id": id })))
+                .await
+                .is_ok());
+            assert_eq!(
+                get_json(suite.out_edge_rx_endpoint.next().await),
+                serde_json::json!({ "id": id })
+            );
+        }
+        let metrics = task.get_state().await.metrics;
+        assert!(metrics.get(STATE_SIZE_METRIC).unwrap_or(&0) <= &64);
+        assert!(metrics.get(STATE_SIZE_METRIC).unwrap_or(&0) > &0);
+        assert!(metrics.get(STATE_EVICTED_KEYS_METRIC).unwrap_or(&0) > &0);
+
+        // the operator fails once its state exceeds the limit
+        let (task, suite) = start_deduplicate_task(2, state_limit::Policy::Fail);
+        for id in 0..5 {
+            assert!(suite
+                .in_edge_tx_endpoint
+                .write(new_object_event(&job_id, serde_json::json!({ "id": id })))
+                .await
+                .is_ok());
+        }
+        let mut status = task.get_state().await.status();
+        for _ in 0..100 {
+            if status == ExecutorStatus::Failed {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            status = task.get_state().await.status();
+        }
+        assert_eq!(status, ExecutorStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_input_schema_validation() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+
+        // violations are sent to the dead-letter operator without retries
+        let retry = ErrorPolicy {
+            policy: Some(error_policy::Policy::Retry(Box::new(error_policy::Retry {
+                attempts: 2,
+                backoff: None,
+                fallback: Some(Box::new(ErrorPolicy {
+                    policy: Some(error_policy::Policy::DeadLetter(error_policy::DeadLetter {
+                        sink: 21,
+                    })),
+                })),
+            }))),
+        };
+        let mut schema = PayloadSchema {
+            fields: vec![payload_schema::Field {
+                name: "id".to_string(),
+                data_type: DataTypeEnum::String as i32,
+                required: true,
+            }],
+            ..Default::default()
+        };
+        schema.set_validation(payload_schema::Validation::Strict);
+        let (task, mut suite, mut dead_letter) =
+            start_project_task(&job_id, 1, retry, Some(schema));
+        for value in [
+            serde_json::json!({"ID": "1"}),
+            serde_json::json!({"id": "2"}),
+        ] {
+            assert!(suite
+                .in_edge_tx_endpoint
+                .write(new_object_event(&job_id, value))
+                .await
+                .is_ok());
+        }
+        assert_eq!(
+            get_json(dead_letter.next().await),
+            serde_json::json!({"ID": "1"})
+        );
+        assert_eq!(
+            get_json(suite.out_edge_rx_endpoint.next().await),
+            serde_json::json!({"id": 2})
+        );
+        let metrics = task.get_state().await.metrics;
+        assert_eq!(metrics.get(SCHEMA_VALIDATED_METRIC), Some(&2));
+        assert_eq!(metrics.get(SCHEMA_VIOLATIONS_METRIC), Some(&1));
+        assert_eq!(metrics.get(ERROR_POLICY_DEAD_LETTERED_METRIC), Some(&1));
+        assert_eq!(metrics.get(ERROR_POLICY_RETRIED_METRIC), None);
+    }
+
+    #[tokio::test]
+    async fn test_sql_expr_operators() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let dead_letter = ErrorPolicy {
+            policy: Some(error_policy::Policy::DeadLetter(error_policy::DeadLetter {
+                sink: 21,
+            })),
+        };
+
+        let (task, mut suite, _) = start_task_with_dead_letter(
+            &job_id,
+            1,
+            dead_letter.clone(),
+            None,
+            operator_info::Details::FilterExpr(FilterExpr {
+                expression: "amount > 100 AND country = 'SE'".to_string(),
+            }),
+        );
+        for value in [
+            serde_json::json!({"amount": 50, "country": "SE"}),
+            serde_json::json!({"amount": 150}),
+            serde_json::json!({"amount": 200, "country": "SE"}),
+        ] {
+            assert!(suite
+                .in_edge_tx_endpoint
+                .write(new_object_event(&job_id, value))
+                .await
+                .is_ok());
+        }
+        assert_eq!(
+            get_json(suite.out_edge_rx_endpoint.next().await),
+            serde_json::json!({"amount": 200, "country": "SE"})
+        );
+        let metrics = task.get_state().await.metrics;
+        assert_eq!(metrics.get(OPERATOR_EVENTS_IN_METRIC), Some(&3));
+        assert_eq!(metrics.get(OPERATOR_EVENTS_OUT_METRIC), Some(&1));
+        // the downstream keeps up, so the operator is not backpressured
+        assert_eq!(metrics.get(BACKPRESSURE_METRIC), Some(&0));
+
+        // evaluation errors are handled by the error policy
+        let (task, mut suite, mut dead_letter) = start_task_with_dead_letter(
+            &job_id,
+            1,
+            dead_letter,
+            None,
+            operator_info::Details::MapExpr(MapExpr {
+                expression: "SELECT amount * rate AS amount_eur, user_id".to_string(),
+            }),
+        );
+        for value in [
+            serde_json::json!({"amount": 1, "rate": "x"}),
+            serde_json::json!({"amount": 200, "rate": 0.5, "user_id": "u1"}),
+        ] {
+            assert!(suite
+                .in_edge_tx_endpoint
+                .write(new_object_event(&job_id, value))
+                .await
+                .is_ok());
+        }
+        assert_eq!(
+            get_json(dead_letter.next().await),
+            serde_json::json!({"amount": 1, "rate": "x"})
+        );
+        assert_eq!(
+            get_json(suite.out_edge_rx_endpoint.next().await),
+            serde_json::json!({"amount_eur": 100.0, "user_id": "u1"})
+        );
+        let metrics = task.get_state().await.metrics;
+        assert_eq!(metrics.get(ERROR_POLICY_DEAD_LETTERED_METRIC), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_route_operator() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        // large payloads are routed to 11 and the others to 21, which is the dead letter operator as well
+        let (task, mut suite, mut others) = start_task_with_dead_letter(
+            &job_id,
+            1,
+            ErrorPolicy {
+                policy: Some(error_policy::Policy::DeadLetter(error_policy::DeadLetter {
+                    sink: 21,
+                })),
+            },
+            None,
+            operator_info::Details::Route(Route {
+                outputs: [("large".to_string(), 11), ("others".to_string(), 21)]
+                    .into_iter()
+                    .collect(),
+                rules: vec![
+                    route::Rule {
+                        predicate: "amount > 100".to_string(),
+                        output: "large".to_string(),
+                    },
+                    route::Rule {
+                        predicate: "amount > 10".to_string(),
+                        output: "others".to_string(),
+                    },
+                ],
+                default_output: "others".to_string(),
+            }),
+        );
+        for value in [
+            serde_json::json!({"amount": 150}),
+            serde_json::json!({"amount": 5}),
+            serde_json::json!({"amount": "x"}),
+            serde_json::json!({"amount": 200}),
+        ] {
+            assert!(suite
+                .in_edge_tx_endpoint
+                .write(new_object_event(&job_id, value))
+                .await
+                .is_ok());
+        }
+        assert_eq!(
+            get_json(suite.out_edge_rx_endpoint.next().await),
+            serde_json::json!({"amount": 150})
+        );
+        assert_eq!(
+            get_json(suite.out_edge_rx_endpoint.next().await),
+            serde_json::json!({"amount": 200})
+        );
+        assert_eq!(
+            get_json(others.next().await),
+            serde_json::json!({"amount": 5})
+        );
+        // the payload which fails the predicates is dead-lettered
+        assert_eq!(
+            get_json(others.next().await),
+            serde_json::json!({"amount": "x"})
+        );
+        let metrics = task.get_state().await.metrics;
+        assert_eq!(metrics.get(OPERATOR_EVENTS_IN_METRIC), Some(&4));
+        assert_eq!(metrics.get(OPERATOR_EVENTS_OUT_METRIC), Some(&3));
+        assert_eq!(metrics.get(ERROR_POLICY_DEAD_LETTERED_METRIC), Some(&1));
+    }
+
+    /// a mock HTTP service of the AsyncLookup operator. `GET /users/{id}` returns `{"id": id}`,
+    /// ids starting with `slow` are answered after 300ms and `broken` always fails
+    fn start_lookup_service() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => return,
+                };
+                std::thread::spawn(move || {
+                    use std::io::{Read, Write};
+                    let mut buf = [0u8; 1024];
+                    let n = stream.read(&mut buf).unwrap_or_default();
+                    let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                    let id = request
+                        .split_whitespace()
+                        .nth(1)
+                        .and_then(|path| path.strip_prefix("/users/"))
+                        .unwrap_or_default()
+                        .to_string();
+                    if id.starts_with("slow") {
+                        std::thread::sleep(Duration::from_millis(300));
+                    }
+                    let (status, body) = if id == "broken" {
+                        ("500 Internal Server Error", String::new())
+                    } else {
+                        ("200 OK", serde_json::json!({ "id": id }).to_string())
+                    };
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                });
+            }
+        });
+        format!("http://{}/users/{{key}}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_async_lookup_operator() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let url_template = start_lookup_service();
+        let new_lookup = |emission: async_lookup::Emission| {
+            operator_info::Details::AsyncLookup(AsyncLookup {
+                backend: Some(async_lookup::Backend::Http(async_lookup::HttpLookup {
+                    url_template: url_template.clone(),
+                    headers: Default::default(),
+                })),
+                key_path: "$.user_id".to_string(),
+                target_field: "user".to_string(),
+                cache: Some(async_lookup::Cache {
+                    capacity: 10,
+                    ttl: None,
+                }),
+                emission: emission as i32,
+                ..Default::default()
+            })
+        };
+        let retry = |dead_letter: u32| ErrorPolicy {
+            policy: Some(error_policy::Policy::Retry(Box::new(error_policy::Retry {
+                attempts: 1,
+                backoff: Some(Backoff {
+                    base: 1,
+                    max: 1,
+                    jitter: false,
+                }),
+                fallback: Some(Box::new(ErrorPolicy {
+                    policy: Some(error_policy::Policy::DeadLetter(error_policy::DeadLetter {
+                        sink: dead_letter,
+                    })),
+                })),
+            }))),
+        };
+        let enriched =
+            |user_id: &str| serde_json::json!({"user_id": user_id, "user": {"id": user_id}});
+
+        // in ordered mode, the fast lookups are held back by the slow one
+        let (task, mut suite, _dead_letter) = start_task_with_dead_letter(
+            &job_id,
+            1,
+            retry(21),
+            None,
+            new_lookup(async_lookup::Emission::Ordered),
+        );
+        for user_id in ["slow", "fast", "fast", "fast"] {
+            assert!(suite
+                .in_edge_tx_endpoint
+                .write(new_object_event(
+                    &job_id,
+                    serde_json::json!({ "user_id": user_id })
+                ))
+                .await
+                .is_ok());
+            if user_id == "fast" {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+        for user_id in ["slow", "fast", "fast", "fast"] {
+            assert_eq!(
+                get_json(suite.out_edge_rx_endpoint.next().await),
+                enriched(user_id)
+            );
+        }
+        // the last lookup is answered by the cache
+        let metrics = task.get_state().await.metrics;
+        assert!(metrics.get(LOOKUP_CACHE_HITS_METRIC).unwrap_or(&0) >= &1);
+        assert_eq!(metrics.get(LOOKUP_IN_FLIGHT_METRIC), Some(&0));
+
+        // in unordered mode, the events are emitted as their lookups complete
+        let (task, mut suite, mut dead_letter) = start_task_with_dead_letter(
+            &job_id,
+            2,
+            retry(22),
+            None,
+            new_lookup(async_lookup::Emission::Unordered),
+        );
+        for user_id in ["slow", "fast"] {
+            assert!(suite
+                .in_edge_tx_endpoint
+                .write(new_object_event(
+                    &job_id,
+                    serde_json::json!({ "user_id": user_id })
+                ))
+                .await
+                .is_ok());
+        }
+        for user_id in ["fast", "slow"] {
+            assert_eq!(
+                get_json(suite.out_edge_rx_endpoint.next().await),
+                enriched(user_id)
+            );
+        }
+
+        // failed lookups are retried and then handled by the fallback policy
+        assert!(suite
+            .in_edge_tx_endpoint
+            .write(new_object_event(
+                &job_id,
+                serde_json::json!({ "user_id": "broken" })
+            ))
+            .await
+            .is_ok());
+        assert_eq!(
+            get_json(dead_letter.next().await),
+            serde_json::json!({ "user_id": "broken" })
+        );
+        let metrics = task.get_state().await.metrics;
+        assert_eq!(metrics.get(ERROR_POLICY_RETRIED_METRIC), Some(&1));
+        assert_eq!(metrics.get(ERROR_POLICY_DEAD_LETTERED_METRIC), Some(&1));
+
+        // the in-flight lookups are completed before the operator is drained
+        assert!(suite
+            .in_edge_tx_endpoint
+            .write(new_object_event(
+                &job_id,
+                serde_json::json!({ "user_id": "slow-2" })
+            ))
+            .await
+            .is_ok());
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(task.drain().await.is_ok());
+        assert_eq!(
+            get_json(
+                tokio::time::timeout(Duration::from_millis(10), suite.out_edge_rx_endpoint.next())
+                    .await
+                    .unwrap_or_default()
+            ),
+            enriched("slow-2")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stop_modes() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let filter = operator_info::Details::FilterExpr(FilterExpr {
+            expression: "amount > 0".to_string(),
+        });
+
+        // events are buffered in the input of a drained operator
+        let (drained_task, mut drained, _drained_dead_letter) =
+            start_task_with_dead_letter(&job_id, 1, ErrorPolicy::default(), None, filter.clone());
+        let (aborted_task, mut aborted, _aborted_dead_letter) =
+            start_task_with_dead_letter(&job_id, 2, ErrorPolicy::default(), None, filter);
+        for (task, suite) in [(&drained_task, &drained), (&aborted_task, &aborted)] {
+            assert!(task.drain().await.is_ok());
+            for amount in [1, 2] {
+                assert!(suite
+                    .in_edge_tx_endpoint
+                    .write(new_object_event(
+                        &job_id,
+                        serde_json::json!({ "amount": amount })
+                    ))
+                    .await
+                    .is_ok());
+            }
+        }
+
+        // buffered events are processed before the executor stops
+        assert!(drained_task.drain_and_stop().await.is_ok());
+        for amount in [1, 2] {
+            assert_eq!(
+                get_json(drained.out_edge_rx_endpoint.next().await),
+                serde_json::json!({ "amount": amount })
+            );
+        }
+        assert_eq!(
+            drained_task.get_state().await.status(),
+            ExecutorStatus::Terminated
+        );
+        assert!(drained_task.drain_and_stop().await.is_ok());
+
+        // buffered events are dropped if the executor stops immediately
+        aborted_task.abort();
+        assert!(!matches!(
+            tokio::time::timeout(
+                std::time::Duration::from_millis(100),
+                aborted.out_edge_rx_endpoint.next()
+            )
+            .await,
+            Ok(Some(_))
+        ));
+        assert_eq!(
+            aborted_task.get_state().await.status(),
+            ExecutorStatus::Drained
+        );
+    }
+
+    fn new_project_info(
+        operator_id: u32,
+        name: &str,
+        path: &str,
+        cast: DataTypeEnum,
+    ) -> OperatorInfo {
+        OperatorInfo {
+            operator_id,
+            host_addr: None,
             upstreams: Default::default(),
-            details: Some(operator_info::Details::Source(Source {
-                desc: Some(source::Desc::Kafka(KafkaDesc::default())),
+            error_policy: None,
+            chaining: Default::default(),
+            input_schema: None,
+            output_schema: None,
+            state_limit: None,
+            headers: Default::default(),
+            processing_timeout: None,
+            details: Some(operator_info::Details::Project(Project {
+                fields: vec![project::Field {
+                    name: name.to_string(),
+                    path: path.to_string(),
+                    cast: cast as i32,
+                    default_value: None,
+                }],
+                key_field: Default::default(),
             })),
-        });
+        }
+    }
 
-        assert_eq!(&executor.job_id, &job_id);
-        assert!(executor.source.is_some());
-        assert!(executor.in_edge.is_none());
-        assert!(executor.external_sinks.is_empty());
-        assert!(executor.out_edges.is_empty());
+    #[tokio::test]
+    async fn test_chained_operators() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        // operator 2 is chained with operator 1, so the task of operator 1 takes over the downstream of operator 2
+        let mut task = Task::new(
+            &job_id,
+            &DataflowMeta {
+                center: 1,
+                neighbors: vec![3],
+                edge_types: Default::default(),
+            },
+        );
+        let mut executor =
+            task.create_stream_executor(&new_project_info(1, "id", "$.id", DataTypeEnum::Bigint));
+        task.chain_operator(
+            &mut executor,
+            &new_project_info(2, "value", "$.id", DataTypeEnum::Unspecified),
+        );
+        let (in_tx, in_rx) = new_event_channel(10);
+        executor.set_in_edge(Some(Box::pin(LocalInEdge::new(in_rx))));
+        let (out_tx, out_rx) = new_event_channel(10);
+        executor.add_out_edge(3, Box::new(LocalOutEdge::new(out_tx)));
+        task.start(executor);
+
+        let in_edge = LocalOutEdge::new(in_tx);
+        let mut out_edge = LocalInEdge::new(out_rx);
+        assert!(in_edge
+            .write(new_object_event(&job_id, serde_json::json!({"id": "1"})))
+            .await
+            .is_ok());
+        assert_eq!(
+            get_json(out_edge.next().await),
+            serde_json::json!({"value": 1})
+        );
+
+        // metrics are still reported by each operator
+        let states = task.get_state().await;
+        assert_eq!(states.metrics.get(OPERATOR_EVENTS_IN_METRIC), Some(&1));
+        assert_eq!(states.metrics.get(OPERATOR_EVENTS_OUT_METRIC), Some(&1));
+        let chained_states = task.get_chained_states().await;
+        assert_eq!(chained_states.len(), 1);
+        assert_eq!(chained_states[0].executor_id, 2);
+        assert_eq!(chained_states[0].status(), ExecutorStatus::Running);
+        assert_eq!(
+            chained_states[0].metrics.get(OPERATOR_EVENTS_IN_METRIC),
+            Some(&1)
+        );
+        assert_eq!(
+            chained_states[0].metrics.get(OPERATOR_EVENTS_OUT_METRIC),
+            Some(&1)
+        );
     }
 
     #[tokio::test]
-    async fn test_stream_executor_process() {
+    async fn test_operator_headers() {
         let _ = setup();
-        let job_id = ResourceId {
-            resource_id: "resource_id".to_string(),
-            namespace_id: "namespace_id".to_string(),
-        };
+        let job_id = ResourceId::default();
+        let mut task = Task::new(
+            &job_id,
+            &DataflowMeta {
+                center: 1,
+                neighbors: vec![3],
+                edge_types: Default::default(),
+            },
+        );
+        let mut project = new_project_info(1, "id", "$.id", DataTypeEnum::Bigint);
+        project.headers = HashMap::from_iter([
+            ("stage".to_string(), "project".to_string()),
+            ("team".to_string(), "payments".to_string()),
+        ]);
+        let mut executor = task.create_stream_executor(&project);
+        let mut chained = new_project_info(2, "value", "$.id", DataTypeEnum::Unspecified);
+        chained.headers = HashMap::from_iter([("stage".to_string(), "enrich".to_string())]);
+        task.chain_operator(&mut executor, &chained);
+        let (out_tx, out_rx) = new_event_channel(10);
+        executor.add_out_edge(3, Box::new(LocalOutEdge::new(out_tx)));
+        let mut out_edge = LocalInEdge::new(out_rx);
 
-        let meta = DataflowMeta {
-            center: 0,
-            neighbors: vec![1, 2, 3, 4],
+        let mut event = match new_object_event(&job_id, serde_json::json!({"id": "1"})) {
+            LocalEvent::KeyedDataStreamEvent(event) => event,
+            other => panic!("unexpected event {:?}", other),
         };
-        let task = Task::new(&job_id, &meta);
+        event.set_header("trace_id", "abc");
+        event.set_header("team", "search");
+        let ref mut cx = Context::from_waker(noop_waker_ref());
+        executor.process(event, cx);
+
+        // the headers of the input are kept unless the operators replace them
+        match out_edge.next().await {
+            Some(LocalEvent::KeyedDataStreamEvent(event)) => assert_eq!(
+                event.get_sorted_headers(),
+                vec![
+                    ("stage", "enrich"),
+                    ("team", "payments"),
+                    ("trace_id", "abc")
+                ]
+            ),
+            other => panic!("unexpected output {:?}", other),
+        }
+    }
+
+    /// an out edge to a remote TaskManager which is unavailable for the first `failures` writes
+    struct FlakyOutEdge {
+        inner: LocalOutEdge<LocalEvent>,
+        failures: std::sync::Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl OutEdge for FlakyOutEdge {
+        type Output = LocalEvent;
+
+        async fn write(&self, val: LocalEvent) -> Result<(), OutEdgeError> {
+            self.inner.write(val).await
+        }
+
+        async fn batch_write(
+            &self,
+            job_id: &Option<ResourceId>,
+            to_operator_id: u32,
+            from_operator_id: u32,
+            iter: Vec<LocalEvent>,
+        ) -> Result<(), OutEdgeError> {
+            {
+                let mut failures = self.failures.lock().unwrap();
+                if *failures > 0 {
+                    *failures -= 1;
+                    return Err(OutEdgeError::SendToRemoteFailed(
+                        tonic::Status::unavailable("connection refused"),
+                    ));
+                }
+            }
+            self.inner
+                .batch_write(job_id, to_operator_id, from_operator_id, iter)
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_out_edge_retries_keep_order() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let mut task = Task::new(
+            &job_id,
+            &DataflowMeta {
+                center: 1,
+                neighbors: vec![2],
+                edge_types: Default::default(),
+            },
+        );
+        let mut operator_info = new_project_info(1, "id", "$.id", DataTypeEnum::Bigint);
+        operator_info.error_policy = Some(ErrorPolicy {
+            policy: Some(error_policy::Policy::Retry(Box::new(error_policy::Retry {
+                attempts: 3,
+                backoff: Some(Backoff {
+                    base: 1,
+                    max: 4,
+                    jitter: true,
+                }),
+                fallback: None,
+            }))),
+        });
+        let mut executor = task.create_stream_executor(&operator_info);
+        let (in_tx, in_rx) = new_event_channel(10);
+        executor.set_in_edge(Some(Box::pin(LocalInEdge::new(in_rx))));
+        let (out_tx, out_rx) = new_event_channel(10);
+        executor.add_out_edge(
+            2,
+            Box::new(FlakyOutEdge {
+                inner: LocalOutEdge::new(out_tx),
+                failures: std::sync::Mutex::new(2),
+            }),
+        );
+        task.start(executor);
+
+        let in_edge = LocalOutEdge::new(in_tx);
+        let mut out_edge = LocalInEdge::new(out_rx);
+        for id in ["1", "2"] {
+            assert!(in_edge
+                .write(new_object_event(&job_id, serde_json::json!({ "id": id })))
+                .await
+                .is_ok());
+        }
+        // the first event is sent after retries and it's not overtaken by the second one
         assert_eq!(
-            task.states.read().await.status(),
-            ExecutorStatus::Initialized
+            get_json(out_edge.next().await),
+            serde_json::json!({"id": 1})
+        );
+        assert_eq!(
+            get_json(out_edge.next().await),
+            serde_json::json!({"id": 2})
+        );
+
+        let mut metrics = task.get_state().await.metrics;
+        for _ in 0..100 {
+            if metrics.contains_key(SEND_FIRST_ATTEMPT_METRIC) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            metrics = task.get_state().await.metrics;
+        }
+        assert_eq!(metrics.get(ERROR_POLICY_RETRIED_METRIC), Some(&2));
+        assert_eq!(metrics.get(SEND_AFTER_RETRY_METRIC), Some(&1));
+        assert_eq!(metrics.get(SEND_FIRST_ATTEMPT_METRIC), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_task_in_retry_backoff() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let mut task = Task::new(
+            &job_id,
+            &DataflowMeta {
+                center: 1,
+                neighbors: vec![2],
+                edge_types: Default::default(),
+            },
         );
         let mut executor = task.create_stream_executor(&OperatorInfo {
             operator_id: 1,
-            host_addr: None,
-            upstreams: Default::default(),
-            details: Some(operator_info::Details::Mapper(Mapper {
-                value: Some(mapper::Value::Func(Func {
-                    function: "function _operator_map_process(a) { return a+1 }".to_string(),
-                })),
+            error_policy: Some(ErrorPolicy {
+                policy: Some(error_policy::Policy::Retry(Box::new(error_policy::Retry {
+                    attempts: 3,
+                    backoff: Some(Backoff {
+                        base: 30000,
+                        max: 30000,
+                        jitter: false,
+                    }),
+                    fallback: None,
+                }))),
+            }),
+            details: Some(operator_info::Details::FilterExpr(FilterExpr {
+                expression: "amount > 100".to_string(),
             })),
+            ..Default::default()
         });
+        let (in_tx, in_rx) = new_event_channel(10);
+        executor.set_in_edge(Some(Box::pin(LocalInEdge::new(in_rx))));
+        let (out_tx, _out_rx) = new_event_channel(10);
+        executor.add_out_edge(
+            2,
+            Box::new(FlakyOutEdge {
+                inner: LocalOutEdge::new(out_tx),
+                failures: std::sync::Mutex::new(u32::MAX),
+            }),
+        );
+        task.start(executor);
 
-        let (tx, rx) = new_event_channel(10);
-        {
-            executor.set_in_edge(Some(Box::pin(LocalInEdge::new(rx))));
-            assert!(executor.in_edge.is_some());
+        assert!(LocalOutEdge::new(in_tx)
+            .write(new_object_event(
+                &job_id,
+                serde_json::json!({ "amount": 200 })
+            ))
+            .await
+            .is_ok());
+        // the event waits for the retry after the first attempt fails
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let handle = task.main_executor_handle.take().unwrap();
+        assert!(!handle.is_finished());
+
+        task.cancel();
+        assert!(tokio::time::timeout(Duration::from_millis(100), handle)
+            .await
+            .is_ok());
+        assert_eq!(
+            task.get_state()
+                .await
+                .metrics
+                .get(ERROR_POLICY_CANCELLED_METRIC),
+            Some(&1)
+        );
+    }
+
+    /// an out edge to a downstream which hangs forever on the next `hangs` writes
+    struct HungOutEdge {
+        inner: LocalOutEdge<LocalEvent>,
+        hangs: Arc<std::sync::Mutex<u32>>,
+    }
+
+    #[async_trait]
+    impl OutEdge for HungOutEdge {
+        type Output = LocalEvent;
+
+        async fn write(&self, val: LocalEvent) -> Result<(), OutEdgeError> {
+            self.inner.write(val).await
         }
 
-        let in_edge_tx_endpoint = LocalOutEdge::new(tx);
-        let (tx, rx) = new_event_channel(10);
+        async fn batch_write(
+            &self,
+            job_id: &Option<ResourceId>,
+            to_operator_id: u32,
+            from_operator_id: u32,
+            iter: Vec<LocalEvent>,
+        ) -> Result<(), OutEdgeError> {
+            let hang = {
+                let mut hangs = self.hangs.lock().unwrap();
+                let hang = *hangs > 0;
+                *hangs = hangs.saturating_sub(1);
+                hang
+            };
+            if hang {
+                futures_util::future::pending::<()>().await;
+            }
+            self.inner
+                .batch_write(job_id, to_operator_id, from_operator_id, iter)
+                .await
+        }
+    }
 
-        {
-            executor.add_out_edge(2, Box::new(LocalOutEdge::new(tx)));
-            assert_eq!(executor.out_edges.len(), 1);
+    #[tokio::test]
+    async fn test_watchdog_restarts_stuck_operator() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let mut task = Task::new(
+            &job_id,
+            &DataflowMeta {
+                center: 1,
+                neighbors: vec![2],
+                edge_types: Default::default(),
+            },
+        );
+        task.set_watchdog(Some(&OperatorWatchdog {
+            stuck_timeout: Some(Time {
+                millis: 200,
+                seconds: 0,
+                minutes: 0,
+                hours: 0,
+            }),
+            max_restarts: 1,
+        }));
+        let mut executor =
+            task.create_stream_executor(&new_project_info(1, "id", "$.id", DataTypeEnum::Bigint));
+        let (in_tx, in_rx) = new_event_channel(10);
+        executor.set_in_edge(Some(Box::pin(LocalInEdge::new(in_rx))));
+        let (out_tx, out_rx) = new_event_channel(10);
+        let hangs = Arc::new(std::sync::Mutex::new(1));
+        executor.add_out_edge(
+            2,
+            Box::new(HungOutEdge {
+                inner: LocalOutEdge::new(out_tx),
+                hangs: hangs.clone(),
+            }),
+        );
+        let (errors_tx, mut errors) = tokio::sync::mpsc::channel(10);
+        let reporter = ErrorReporter::new(&job_id, 1, errors_tx);
+        executor.set_error_reporter(reporter.clone());
+        let mut watchdog = Watchdog::new(Duration::from_millis(200));
+        watchdog.watch(1, task.get_progress().unwrap(), Some(reporter));
+        let watchdog = tokio::spawn(watchdog.run());
+        task.start(executor);
+
+        // an operator waiting for input is not stuck
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(errors.try_recv().is_err());
+
+        let in_edge = LocalOutEdge::new(in_tx);
+        let mut out_edge = LocalInEdge::new(out_rx);
+        for id in ["1", "2"] {
+            assert!(in_edge
+                .write(new_object_event(&job_id, serde_json::json!({ "id": id })))
+                .await
+                .is_ok());
         }
+        // the hung event is abandoned, and the restarted operator goes on with the next one
+        let event = tokio::time::timeout(Duration::from_secs(2), out_edge.next())
+            .await
+            .unwrap();
+        // the output after the restart belongs to the new run of the operator
+        assert!(
+            matches!(&event, Some(LocalEvent::KeyedDataStreamEvent(event)) if event.restart_epoch == 2)
+        );
+        assert_eq!(get_json(event), serde_json::json!({"id": 2}));
+        let err = errors.recv().await.unwrap();
+        assert_eq!(err.operator_id, 1);
+        assert_eq!(err.kind(), OperatorErrorKind::Stuck);
+        let mut state = task.get_state().await;
+        for _ in 0..100 {
+            if state.metrics.contains_key(ERROR_POLICY_CANCELLED_METRIC) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            state = task.get_state().await;
+        }
+        assert_eq!(state.restart_count, 1);
+        assert_eq!(state.metrics.get(OPERATOR_STUCK_METRIC), Some(&1));
+        assert_eq!(state.metrics.get(ERROR_POLICY_CANCELLED_METRIC), Some(&1));
+        assert_eq!(state.status(), ExecutorStatus::Running);
 
-        let out_edge_rx_endpoint = LocalInEdge::new(rx);
+        // the operator fails once it's stuck again after the restarts
+        *hangs.lock().unwrap() = 1;
+        assert!(in_edge
+            .write(new_object_event(&job_id, serde_json::json!({ "id": "3" })))
+            .await
+            .is_ok());
+        assert_eq!(
+            errors.recv().await.unwrap().kind(),
+            OperatorErrorKind::Stuck
+        );
+        for _ in 0..100 {
+            if state.status() == ExecutorStatus::Failed {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            state = task.get_state().await;
+        }
+        assert_eq!(state.status(), ExecutorStatus::Failed);
+        assert_eq!(state.restart_count, 1);
+        watchdog.abort();
+    }
 
-        let ref mut suite = TestStreamExecutorSuite {
-            in_edge_tx_endpoint,
-            out_edge_rx_endpoint,
-        };
+    /// an out edge to a slow downstream. The out edges sharing `in_flight` record the current and the most writes in flight
+    struct SlowOutEdge {
+        inner: LocalOutEdge<LocalEvent>,
+        delay: Duration,
+        in_flight: Arc<std::sync::Mutex<(u32, u32)>>,
+    }
 
-        let handler = tokio::spawn(executor);
-        let timestamp = now_timestamp();
-        for _ in 0..10 {
+    impl SlowOutEdge {
+        async fn slowly<F: Future>(&self, write: F) -> F::Output {
             {
-                let result = suite
-                    .in_edge_tx_endpoint
-                    .write(LocalEvent::KeyedDataStreamEvent(KeyedDataEvent {
-                        job_id: Some(ResourceId {
-                            resource_id: "resource_id".to_string(),
-                            namespace_id: "ns_id".to_string(),
-                        }),
-                        key: None,
-                        to_operator_id: 2,
-                        data: vec![Entry {
-                            data_type: DataTypeEnum::Number as i32,
-                            value: TypedValue::Number(1.0).get_data_bytes(),
-                        }],
-                        event_time: timestamp,
-                        from_operator_id: 0,
-                        window: None,
-                        event_id: 0,
-                    }))
-                    .await;
-                assert!(result.is_ok());
-                let opt = suite.out_edge_rx_endpoint.next().await;
+                let mut in_flight = self.in_flight.lock().unwrap();
+                in_flight.0 += 1;
+                in_flight.1 = in_flight.1.max(in_flight.0);
+            }
+            tokio::time::sleep(self.delay).await;
+            let result = write.await;
+            self.in_flight.lock().unwrap().0 -= 1;
+            result
+        }
+    }
+
+    #[async_trait]
+    impl OutEdge for SlowOutEdge {
+        type Output = LocalEvent;
+
+        async fn write(&self, val: LocalEvent) -> Result<(), OutEdgeError> {
+            self.slowly(self.inner.write(val)).await
+        }
+
+        async fn batch_write(
+            &self,
+            job_id: &Option<ResourceId>,
+            to_operator_id: u32,
+            from_operator_id: u32,
+            iter: Vec<LocalEvent>,
+        ) -> Result<(), OutEdgeError> {
+            self.slowly(
+                self.inner
+                    .batch_write(job_id, to_operator_id, from_operator_id, iter),
+            )
+            .await
+        }
+    }
+
+    /// an out edge to a downstream whose queue is closed, the writes fail without retries
+    struct ClosedOutEdge;
+
+    #[async_trait]
+    impl OutEdge for ClosedOutEdge {
+        type Output = LocalEvent;
+
+        async fn write(&self, _val: LocalEvent) -> Result<(), OutEdgeError> {
+            Err(OutEdgeError::QueueClosed)
+        }
+
+        async fn batch_write(
+            &self,
+            _job_id: &Option<ResourceId>,
+            _to_operator_id: u32,
+            _from_operator_id: u32,
+            _iter: Vec<LocalEvent>,
+        ) -> Result<(), OutEdgeError> {
+            Err(OutEdgeError::QueueClosed)
+        }
+    }
+
+    // the executor yields to the test while the slow writes are in flight, so they share one thread
+    #[tokio::test]
+    async fn test_fan_out_to_out_edges_concurrently() {
+        let _ = setup();
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        // (fan-out concurrency, most writes in flight)
+        for (concurrency, max_in_flight) in [(8, 3), (2, 2), (1, 1)] {
+            let mut task = Task::new(
+                &job_id,
+                &DataflowMeta {
+                    center: 1,
+                    neighbors: vec![2, 3, 4, 5],
+                    edge_types: Default::default(),
+                },
+            );
+            let mut executor = task.create_stream_executor(&new_project_info(
+                1,
+                "id",
+                "$.id",
+                DataTypeEnum::Bigint,
+            ));
+            executor.set_fan_out_concurrency(concurrency);
+            let (in_tx, in_rx) = new_event_channel(10);
+            executor.set_in_edge(Some(Box::pin(LocalInEdge::new(in_rx))));
+            let in_flight = Arc::new(std::sync::Mutex::new((0, 0)));
+            let mut out_edges = vec![];
+            for executor_id in 2..5 {
+                let (out_tx, out_rx) = new_event_channel(10);
+                executor.add_out_edge(
+                    executor_id,
+                    Box::new(SlowOutEdge {
+                        inner: LocalOutEdge::new(out_tx),
+                        delay: Duration::from_millis(50),
+                        in_flight: in_flight.clone(),
+                    }),
+                );
+                out_edges.push(LocalInEdge::new(out_rx));
+            }
+            executor.add_out_edge(5, Box::new(ClosedOutEdge));
+            let (errors_tx, mut errors) = tokio::sync::mpsc::channel(10);
+            executor.set_error_reporter(ErrorReporter::new(&job_id, 1, errors_tx));
+            task.start(executor);
+
+            let in_edge = LocalOutEdge::new(in_tx);
+            assert!(in_edge
+                .write(new_object_event(&job_id, serde_json::json!({ "id": "1" })))
+                .await
+                .is_ok());
+            // the failed downstream doesn't hold up the others
+            for out_edge in out_edges.iter_mut() {
                 assert_eq!(
-                    opt,
-                    Some(LocalEvent::KeyedDataStreamEvent(KeyedDataEvent {
-                        job_id: Some(ResourceId {
-                            resource_id: "resource_id".to_string(),
-                            namespace_id: "ns_id".to_string(),
-                        }),
-                        key: None,
-                        to_operator_id: 2,
-                        data: vec![Entry {
-                            data_type: DataTypeEnum::Number as i32,
-                            value: TypedValue::Number(2.0).get_data_bytes(),
-                        }],
-                        event_time: timestamp,
-                        from_operator_id: 0,
-                        window: None,
-                        event_id: 0,
-                    }))
+                    get_json(out_edge.next().await),
+                    serde_json::json!({"id": 1})
                 );
             }
+            assert_eq!(in_flight.lock().unwrap().1, max_in_flight);
+
+            // the failure is reported with the downstream which the event is sent to
+            let err = errors.recv().await.unwrap();
+            assert_eq!(err.kind(), OperatorErrorKind::OutEdge);
+            assert!(
+                err.message.contains("[to_operator_id: Some(5)]"),
+                "{}",
+                err.message
+            );
+            assert!(errors.try_recv().is_err());
+
+            let mut metrics = task.get_state().await.metrics;
+            for _ in 0..100 {
+                if metrics.contains_key(ERROR_POLICY_SKIPPED_METRIC) {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                metrics = task.get_state().await.metrics;
+            }
+            assert_eq!(metrics.get(SEND_FIRST_ATTEMPT_METRIC), Some(&3));
+            assert_eq!(metrics.get(ERROR_POLICY_SKIPPED_METRIC), Some(&1));
         }
+    }
 
-        assert_eq!(task.states.read().await.status(), ExecutorStatus::Running);
+    #[tokio::test]
+    async fn test_source_decode_failures_follow_error_policy() {
+        let job_id = ResourceId::new("namespace_id", "resource_id");
+        let mut task = Task::new(
+            &job_id,
+            &DataflowMeta {
+                center: 0,
+                neighbors: vec![1, 21],
+                edge_types: Default::default(),
+            },
+        );
+        let event = KeyedDataEvent::builder()
+            .job_id(&job_id)
+            .header(SOURCE_PARTITION_HEADER, "0")
+            .header(SOURCE_OFFSET_HEADER, "5")
+            .build();
+        let failure = || DecodeFailure {
+            format: "csv",
+            topic: "topic".to_string(),
+            row: 2,
+            message: "found record with 1 fields, but the previous record has 2 fields".to_string(),
+        };
+        let ref mut cx = Context::from_waker(noop_waker_ref());
+        let new_executor = |task: &mut Task, policy: error_policy::Policy| {
+            let mut info = new_source_info();
+            info.error_policy = Some(ErrorPolicy {
+                policy: Some(policy),
+            });
+            task.create_stream_executor(&info)
+        };
 
-        let result = suite
-            .in_edge_tx_endpoint
-            .write(LocalEvent::Terminate {
-                job_id: Default::default(),
-                to: 1,
-                event_time: now_timestamp(),
-            })
-            .await;
-        assert!(result.is_ok());
+        let mut executor = new_executor(&mut task, error_policy::Policy::Skip(Default::default()));
+        executor.handle_decode_failure(&event, failure(), cx);
+        assert!(!executor.failed);
+        assert_eq!(executor.metrics.get(ERROR_POLICY_SKIPPED_METRIC), Some(&1));
 
-        let _ = handler.await;
+        // the dead-lettered event carries the source position of the message and the failed row
+        let mut executor = new_executor(
+            &mut task,
+            error_policy::Policy::DeadLetter(error_policy::DeadLetter { sink: 21 }),
+        );
+        let (dead_letter_tx, dead_letter_rx) = new_event_channel(10);
+        executor.add_out_edge(21, Box::new(LocalOutEdge::new(dead_letter_tx)));
+        executor.handle_decode_failure(&event, failure(), cx);
+        assert!(!executor.failed);
+        assert_eq!(
+            executor.metrics.get(ERROR_POLICY_DEAD_LETTERED_METRIC),
+            Some(&1)
+        );
+        let dead_letter = match LocalInEdge::new(dead_letter_rx).next().await {
+            Some(LocalEvent::KeyedDataStreamEvent(event)) => event,
+            _ => panic!("the failed row is not dead-lettered"),
+        };
+        assert!(dead_letter.data.is_empty());
+        assert_eq!(dead_letter.headers[SOURCE_OFFSET_HEADER], "5");
+        assert_eq!(
+            dead_letter.headers[ERROR_REASON_HEADER],
+            format!("{}", failure())
+        );
+        assert!(dead_letter.headers[ERROR_REASON_HEADER].contains("at row [2]"));
+
+        let mut executor = new_executor(&mut task, error_policy::Policy::Fail(Default::default()));
+        executor.handle_decode_failure(&event, failure(), cx);
+        assert!(executor.failed);
+        assert_eq!(executor.metrics.get(ERROR_POLICY_FAILED_METRIC), Some(&1));
     }
 
     #[tokio::test]
@@ -0,0 +1,459 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use common::types::{ExecutorId, TypedValue};
+use proto::common::{wasm_udf, Entry, KeyedDataEvent, WasmUdf};
+use sha2::{Digest, Sha256};
+use wasmtime::{
+    Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+/// metric of the time to load, compile and instantiate the module of the WasmUdf operator in microseconds
+pub const WASM_UDF_COLD_START_METRIC: &str = "wasm_udf.cold_start_micros";
+/// metric of the invocations of the WasmUdf operator
+pub const WASM_UDF_CALLS_METRIC: &str = "wasm_udf.calls";
+/// metric of the total latency of the invocations in microseconds. The average latency is it divided by the invocations
+pub const WASM_UDF_CALL_MICROS_METRIC: &str = "wasm_udf.call_micros";
+
+const DEFAULT_FUEL: u64 = 10_000_000;
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_MEMORY_BYTES: u64 = 64 << 20;
+/// the engine epoch is incremented by this interval, it's the granularity of the invocation timeout
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WasmUdfError {
+    /// the module can't be fetched or its checksum mismatches
+    LoadFailed(String),
+    /// the module can't be compiled or instantiated, or it doesn't export the required items
+    InstantiateFailed(String),
+    /// the invocation traps, runs out of fuel or time, or returns a malformed output buffer
+    Trapped(String),
+    /// the payload or an output payload is not valid JSON
+    InvalidPayload(String),
+}
+
+impl Display for WasmUdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WasmUdfError::LoadFailed(msg) => write!(f, "load wasm module failed: {}", msg),
+            WasmUdfError::InstantiateFailed(msg) => {
+                write!(f, "instantiate wasm module failed: {}", msg)
+            }
+            WasmUdfError::Trapped(msg) => write!(f, "wasm function trapped: {}", msg),
+            WasmUdfError::InvalidPayload(msg) => write!(f, "invalid wasm payload: {}", msg),
+        }
+    }
+}
+
+/// all modules are compiled by the same engine, whose epoch is incremented by a background thread for the invocation timeout
+fn get_engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut config = Config::new();
+        config.consume_fuel(true).epoch_interruption(true);
+        let engine = Engine::new(&config).expect("create wasm engine failed");
+        let ticker = engine.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(EPOCH_TICK);
+            ticker.increment_epoch();
+        });
+        engine
+    })
+}
+
+/// compiled modules are cached by their checksums, so a module is compiled once no matter how many tasks run it
+fn get_module(bytes: &[u8], checksum: &str) -> Result<Module, WasmUdfError> {
+    static MODULES: OnceLock<Mutex<HashMap<String, Module>>> = OnceLock::new();
+    let modules = MODULES.get_or_init(Default::default);
+    if let Some(module) = modules
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(checksum)
+    {
+        return Ok(module.clone());
+    }
+
+    let module = Module::new(get_engine(), bytes).map_err(into_instantiate_failed)?;
+    modules
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(checksum.to_string(), module.clone());
+    Ok(module)
+}
+
+/// load the module and verify its checksum. It returns the module and its checksum
+fn load_module(wasm_udf: &WasmUdf) -> Result<(Vec<u8>, String), WasmUdfError> {
+    let bytes = match wasm_udf.module.as_ref() {
+        Some(wasm_udf::Module::Inline(bytes)) => bytes.clone(),
+        Some(wasm_udf::Module::Url(url)) => fetch_module(url)?,
+        None => return Err(WasmUdfError::LoadFailed("module is missing".to_string())),
+    };
+    let checksum = format!("{:x}", Sha256::digest(&bytes));
+    if !wasm_udf.sha256.is_empty() && !wasm_udf.sha256.eq_ignore_ascii_case(&checksum) {
+        return Err(WasmUdfError::LoadFailed(format!(
+            "checksum mismatches, expected: {}, actual: {}",
+            wasm_udf.sha256, checksum
+        )));
+    }
+    Ok((bytes, checksum))
+}
+
+/// the module is fetched in a dedicated thread because the blocking client can't run inside the async runtime
+fn fetch_module(url: &str) -> Result<Vec<u8>, WasmUdfError> {
+    let url = url.to_string();
+    std::thread::spawn(move || {
+        reqwest::blocking::get(&url)
+            .and_then(|resp| resp.error_for_status())
+            .and_then(|resp| resp.bytes())
+            .map(|bytes| bytes.to_vec())
+            .map_err(|err| err.to_string())
+    })
+    .join()
+    .unwrap_or_else(|_| Err("fetch module panicked".to_string()))
+    .map_err(WasmUdfError::LoadFailed)
+}
+
+fn into_trapped<E: Display>(err: E) -> WasmUdfError {
+    WasmUdfError::Trapped(err.to_string())
+}
+
+fn into_instantiate_failed<E: Display>(err: E) -> WasmUdfError {
+    WasmUdfError::InstantiateFailed(err.to_string())
+}
+
+/// decode the output buffer, which contains the payloads prefixed by their lengths as little-endian u32
+fn decode_outputs(mut buf: &[u8]) -> Result<Vec<Vec<u8>>, WasmUdfError> {
+    let mut outputs = vec![];
+    while !buf.is_empty() {
+        if buf.len() < 4 {
+            return Err(WasmUdfError::Trapped("malformed output buffer".to_string()));
+        }
+        let len = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+        buf = &buf[4..];
+        if buf.len() < len {
+            return Err(WasmUdfError::Trapped("malformed output buffer".to_string()));
+        }
+        outputs.push(buf[..len].to_vec());
+        buf = &buf[len..];
+    }
+    Ok(outputs)
+}
+
+struct WasmInstance {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    process: TypedFunc<(i32, i32), i64>,
+}
+
+impl WasmInstance {
+    fn call(
+        &mut self,
+        payload: &[u8],
+        fuel: u64,
+        timeout_ticks: u64,
+    ) -> Result<Vec<Vec<u8>>, WasmUdfError> {
+        // each invocation has its own fuel and deadline
+        let remaining = self.store.consume_fuel(0).map_err(into_trapped)?;
+        if remaining < fuel {
+            self.store
+                .add_fuel(fuel - remaining)
+                .map_err(into_trapped)?;
+        }
+        self.store.set_epoch_deadline(timeout_ticks);
+
+        let len = i32::try_from(payload.len())
+            .map_err(|_| WasmUdfError::InvalidPayload("payload is too large".to_string()))?;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(into_trapped)?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, payload)
+            .map_err(into_trapped)?;
+        let output = self
+            .process
+            .call(&mut self.store, (ptr, len))
+            .map_err(into_trapped)? as u64;
+
+        let (output_ptr, output_len) = ((output >> 32) as usize, (output & 0xffff_ffff) as usize);
+        self.memory
+            .data(&self.store)
+            .get(output_ptr..output_ptr + output_len)
+            .ok_or_else(|| WasmUdfError::Trapped("output buffer is out of bounds".to_string()))
+            .and_then(decode_outputs)
+    }
+}
+
+/// [`WasmUdfRuntime`] is the runtime of the WasmUdf operator. It's created once per task.
+///
+/// Each invocation is limited by fuel and time, and the linear memory is capped. Modules with imports are not supported.
+/// An instance which traps is dropped, and the module is instantiated again by the next invocation.
+pub struct WasmUdfRuntime {
+    operator_id: ExecutorId,
+    module: Module,
+    fuel: u64,
+    timeout_ticks: u64,
+    max_memory_bytes: usize,
+    instance: Option<WasmInstance>,
+    cold_start: Duration,
+    // invocations and their total latency since they are taken last time
+    calls: u64,
+    call_time: Duration,
+}
+
+impl WasmUdfRuntime {
+    pub fn new(operator_id: ExecutorId, wasm_udf: &WasmUdf) -> Result<Self, WasmUdfError> {
+        let start = Instant::now();
+        let (bytes, checksum) = load_module(wasm_udf)?;
+        let module = get_module(&bytes, &checksum)?;
+        let timeout = wasm_udf
+            .timeout
+            .as_ref()
+            .and_then(|timeout| timeout.to_duration().to_std().ok())
+            .filter(|timeout| !timeout.is_zero())
+            .unwrap_or(DEFAULT_TIMEOUT);
+
+        let mut runtime = Self {
+            operator_id,
+            module,
+            fuel: Some(wasm_udf.fuel)
+                .filter(|fuel| *fuel > 0)
+                .unwrap_or(DEFAULT_FUEL),
+            timeout_ticks: timeout.as_millis().div_ceil(EPOCH_TICK.as_millis()) as u64,
+            max_memory_bytes: Some(wasm_udf.max_memory_bytes)
+                .filter(|max_memory_bytes| *max_memory_bytes > 0)
+                .unwrap_or(DEFAULT_MAX_MEMORY_BYTES) as usize,
+            instance: None,
+            cold_start: Duration::ZERO,
+            calls: 0,
+            call_time: Duration::ZERO,
+        };
+        runtime.instance = Some(runtime.instantiate()?);
+        runtime.cold_start = start.elapsed();
+        Ok(runtime)
+    }
+
+    /// the time to load, compile and instantiate the module
+    #[inline]
+    pub fn get_cold_start(&self) -> Duration {
+        self.cold_start
+    }
+
+    /// take the number of invocations and their total latency since they were taken last time
+    pub fn take_call_stats(&mut self) -> (u64, Duration) {
+        (
+            std::mem::take(&mut self.calls),
+            std::mem::take(&mut self.call_time),
+        )
+    }
+
+    /// process each payload of the event by the function.
+    /// The outputs of all payloads are emitted in one event, and no event is emitted if there is no output
+    pub fn process(&mut self, event: &KeyedDataEvent) -> Result<Vec<KeyedDataEvent>, WasmUdfError> {
+        let mut new_event = event.clone();
+        new_event.data = vec![];
+        new_event.from_operator_id = self.operator_id;
+
+        for entry in &event.data {
+            let payload = TypedValue::from(entry).to_json_value().to_string();
+            for output in self.call(payload.as_bytes())? {
+                let value = serde_json::from_slice::<serde_json::Value>(&output)
+                    .map(TypedValue::from_json_value)
+                    .map_err(|err| WasmUdfError::InvalidPayload(err.to_string()))?;
+                let mut new_entry = Entry::default();
+                new_entry.set_data_type(value.get_type());
+                new_entry.value = value.get_data_bytes();
+                new_event.data.push(new_entry);
+            }
+        }
+
+        if new_event.data.is_empty() {
+            Ok(vec![])
+        } else {
+            Ok(vec![new_event])
+        }
+    }
+
+    /// call the function with a payload and return its output payloads
+    pub fn call(&mut self, payload: &[u8]) -> Result<Vec<Vec<u8>>, WasmUdfError> {
+        let mut instance = match self.instance.take() {
+            Some(instance) => instance,
+            None => self.instantiate()?,
+        };
+        let start = Instant::now();
+        let result = instance.call(payload, self.fuel, self.timeout_ticks);
+        self.calls += 1;
+        self.call_time += start.elapsed();
+        // the instance may be corrupted by the trap
+        if result.is_ok() {
+            self.instance = Some(instance);
+        }
+        result
+    }
+
+    fn instantiate(&self) -> Result<WasmInstance, WasmUdfError> {
+        let mut store = Store::new(
+            get_engine(),
+            StoreLimitsBuilder::new()
+                .memory_size(self.max_memory_bytes)
+                .instances(1)
+                .build(),
+        );
+        store.limiter(|limits| limits);
+        // the start function of the module is limited as well
+        store.add_fuel(self.fuel).map_err(into_instantiate_failed)?;
+        store.set_epoch_deadline(self.timeout_ticks);
+
+        let instance =
+            Instance::new(&mut store, &self.module, &[]).map_err(into_instantiate_failed)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| WasmUdfError::InstantiateFailed("memory is not exported".to_string()))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .map_err(into_instantiate_failed)?;
+        let process = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, "process")
+            .map_err(into_instantiate_failed)?;
+        Ok(WasmInstance {
+            store,
+            memory,
+            alloc,
+            process,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::types::TypedValue;
+    use proto::common::{wasm_udf::Module, DataTypeEnum, Entry, KeyedDataEvent, Time, WasmUdf};
+
+    use super::{WasmUdfError, WasmUdfRuntime};
+
+    const WRAP_MODULE: &str = include_str!("../wasm/wrap.wat");
+
+    const LOOP_MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "process") (param i32 i32) (result i64)
+            (loop $forever (br $forever))
+            (i64.const 0)))
+    "#;
+
+    fn new_wasm_udf(module: &str) -> WasmUdf {
+        WasmUdf {
+            module: Some(Module::Inline(module.as_bytes().to_vec())),
+            ..Default::default()
+        }
+    }
+
+    fn new_entry(value: serde_json::Value) -> Entry {
+        let value = TypedValue::from_json_value(value);
+        Entry {
+            data_type: value.get_type() as i32,
+            value: value.get_data_bytes(),
+        }
+    }
+
+    #[test]
+    fn test_wasm_udf_process() {
+        let mut runtime = WasmUdfRuntime::new(1, &new_wasm_udf(WRAP_MODULE)).unwrap();
+
+        let event = KeyedDataEvent {
+            to_operator_id: 1,
+            data: vec![
+                new_entry(serde_json::json!(1)),
+                new_entry(serde_json::Value::Null),
+                new_entry(serde_json::json!("a")),
+            ],
+            ..Default::default()
+        };
+        let events = runtime.process(&event).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].from_operator_id, 1);
+        assert_eq!(
+            events[0]
+                .data
+                .iter()
+                .map(|entry| TypedValue::from(entry).to_json_value())
+                .collect::<Vec<_>>(),
+            vec![serde_json::json!([1]), serde_json::json!(["a"])]
+        );
+        assert_eq!(events[0].data[0].data_type(), DataTypeEnum::Array);
+
+        // no event is emitted without outputs
+        let event = KeyedDataEvent {
+            data: vec![new_entry(serde_json::Value::Null)],
+            ..Default::default()
+        };
+        assert!(runtime.process(&event).unwrap().is_empty());
+
+        let (calls, _) = runtime.take_call_stats();
+        assert_eq!(calls, 4);
+        assert_eq!(runtime.take_call_stats().0, 0);
+    }
+
+    #[test]
+    fn test_wasm_udf_checksum() {
+        let mut wasm_udf = new_wasm_udf(WRAP_MODULE);
+        wasm_udf.sha256 = "0".repeat(64);
+        assert!(matches!(
+            WasmUdfRuntime::new(1, &wasm_udf),
+            Err(WasmUdfError::LoadFailed(_))
+        ));
+
+        wasm_udf.sha256 = format!(
+            "{:X}",
+            <sha2::Sha256 as sha2::Digest>::digest(WRAP_MODULE.as_bytes())
+        );
+        assert!(WasmUdfRuntime::new(1, &wasm_udf).is_ok());
+
+        assert!(matches!(
+            WasmUdfRuntime::new(1, &new_wasm_udf("(module)")),
+            Err(WasmUdfError::InstantiateFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_wasm_udf_limits() {
+        // fuel
+        let mut wasm_udf = new_wasm_udf(LOOP_MODULE);
+        wasm_udf.fuel = 10_000;
+        let mut runtime = WasmUdfRuntime::new(1, &wasm_udf).unwrap();
+        assert!(matches!(runtime.call(b"1"), Err(WasmUdfError::Trapped(_))));
+
+        // time
+        wasm_udf.fuel = u64::MAX / 2;
+        wasm_udf.timeout = Some(Time {
+            millis: 50,
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+        });
+        let mut runtime = WasmUdfRuntime::new(1, &wasm_udf).unwrap();
+        let start = std::time::Instant::now();
+        assert!(matches!(runtime.call(b"1"), Err(WasmUdfError::Trapped(_))));
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+
+        // memory
+        let mut wasm_udf = new_wasm_udf(WRAP_MODULE);
+        wasm_udf.max_memory_bytes = 65536;
+        let mut runtime = WasmUdfRuntime::new(1, &wasm_udf).unwrap();
+        let payload = vec![b'1'; 40000];
+        assert!(matches!(
+            runtime.call(&payload),
+            Err(WasmUdfError::Trapped(_))
+        ));
+
+        // the module is instantiated again after the trap
+        assert_eq!(runtime.call(b"1").unwrap(), vec![b"[1]".to_vec()]);
+    }
+}
@@ -0,0 +1,341 @@
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use common::types::ExecutorId;
+use proto::common::{OperatorErrorKind, OperatorWatchdog};
+use tokio_util::sync::CancellationToken;
+
+use crate::task::ErrorReporter;
+
+/// metric of the times an operator is found stuck by the watchdog
+pub const OPERATOR_STUCK_METRIC: &str = "operator.stuck";
+
+/// metric of the events abandoned because they're not processed in the processing timeout of the operator
+pub const PROCESSING_TIMEOUT_METRIC: &str = "operator.processing_timeouts";
+
+/// the operators are checked this many times in a timeout
+const CHECKS_PER_TIMEOUT: u32 = 4;
+
+/// once an event is timed out, the JavaScript functions called for the rest of it are interrupted at this interval,
+/// since the isolate accepts calls again once the interrupted one returns
+const INTERRUPT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// [`Progress`] is the liveness of an executor shared with the [`Watchdog`]. The executor makes progress once it's polled or it takes an event,
+/// and it's idle while it waits for the input, a timer or the lookups. It's stuck if it's not idle for the timeout since its last progress
+pub struct Progress {
+    max_restarts: u32,
+    state: Mutex<ProgressState>,
+}
+
+struct ProgressState {
+    /// when the executor makes the last progress, none if it's idle
+    busy_since: Option<Instant>,
+    /// the token of the work in hand, it fires once the executor is found stuck
+    work: CancellationToken,
+    /// whether the executor is found stuck and it has not restarted yet
+    stuck: bool,
+    restarts: u32,
+}
+
+pub type SharedProgress = Arc<Progress>;
+
+impl Progress {
+    pub fn new_shared(watchdog: &OperatorWatchdog) -> SharedProgress {
+        Arc::new(Self {
+            max_restarts: watchdog.max_restarts,
+            state: Mutex::new(ProgressState {
+                busy_since: None,
+                work: Default::default(),
+                stuck: false,
+                restarts: 0,
+            }),
+        })
+    }
+
+    /// the times the executor restarts because it's stuck
+    pub fn get_restarts(&self) -> u32 {
+        self.lock().restarts
+    }
+
+    /// a new token for the work of a new executor, it's a child of the token of the executor
+    pub(crate) fn renew_work(&self, cancellation: &CancellationToken) -> CancellationToken {
+        let mut state = self.lock();
+        state.work = cancellation.child_token();
+        state.stuck = false;
+        state.work.clone()
+    }
+
+    pub(crate) fn advance(&self, now: Instant) {
+        self.lock().busy_since = Some(now);
+    }
+
+    pub(crate) fn idle(&self) {
+        self.lock().busy_since = None;
+    }
+
+    pub(crate) fn is_stuck(&self) -> bool {
+        self.lock().stuck
+    }
+
+    /// restart the stuck executor at `now`, the work in hand has been abandoned. It returns the token of the following work,
+    /// or none if the restarts are exhausted
+    pub(crate) fn restart(
+        &self,
+        cancellation: &CancellationToken,
+        now: Instant,
+    ) -> Option<CancellationToken> {
+        let mut state = self.lock();
+        if state.restarts >= self.max_restarts {
+            return None;
+        }
+        state.restarts += 1;
+        state.stuck = false;
+        state.busy_since = Some(now);
+        state.work = cancellation.child_token();
+        Some(state.work.clone())
+    }
+
+    /// flag the executor as stuck and abandon its work if it makes no progress for the timeout. It returns whether it's flagged just now
+    fn check(&self, now: Instant, timeout: Duration) -> bool {
+        let mut state = self.lock();
+        match state.busy_since {
+            Some(since) if !state.stuck && now.saturating_duration_since(since) >= timeout => {
+                state.stuck = true;
+                state.work.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ProgressState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+struct WatchedOperator {
+    executor_id: ExecutorId,
+    progress: SharedProgress,
+    reporter: Option<ErrorReporter>,
+}
+
+/// [`Watchdog`] watches the liveness of the operators of a dataflow on a worker. A stuck operator is reported to the coordinator,
+/// and its executor restarts in place once the waits of its work are abandoned, see [`OperatorWatchdog`]
+pub struct Watchdog {
+    timeout: Duration,
+    operators: Vec<WatchedOperator>,
+}
+
+impl Watchdog {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            operators: vec![],
+        }
+    }
+
+    pub fn watch(
+        &mut self,
+        executor_id: ExecutorId,
+        progress: SharedProgress,
+        reporter: Option<ErrorReporter>,
+    ) {
+        self.operators.push(WatchedOperator {
+            executor_id,
+            progress,
+            reporter,
+        })
+    }
+
+    /// check the operators until the task is aborted
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.timeout / CHECKS_PER_TIMEOUT);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            self.check(tokio::time::Instant::now().into_std());
+        }
+    }
+
+    fn check(&self, now: Instant) {
+        for operator in &self.operators {
+            if !operator.progress.check(now, self.timeout) {
+                continue;
+            }
+            tracing::warn!(
+                "operator {} makes no progress for {:?}, it's stuck",
+                operator.executor_id,
+                self.timeout
+            );
+            operator.reporter.iter().for_each(|reporter| {
+                reporter.report(
+                    OperatorErrorKind::Stuck,
+                    format!("operator makes no progress for {:?}", self.timeout),
+                )
+            });
+        }
+    }
+}
+
+#[derive(Default)]
+struct TimerState {
+    /// the deadline of the event in hand and the isolate processing it
+    armed: Option<(Instant, v8::IsolateHandle)>,
+    /// whether the event in hand is timed out
+    expired: bool,
+    stopped: bool,
+}
+
+/// [`ProcessingTimer`] interrupts the JavaScript functions processing an event once the processing timeout of the operator is exceeded,
+/// see `OperatorInfo.processing_timeout`. The functions run on the thread of the executor without yielding, so the deadline is watched
+/// by a thread of the timer, which stops once the timer is dropped
+pub struct ProcessingTimer {
+    timeout: Duration,
+    shared: Arc<(Mutex<TimerState>, Condvar)>,
+}
+
+impl ProcessingTimer {
+    pub fn new(executor_id: ExecutorId, timeout: Duration) -> Self {
+        let shared = Arc::new((Mutex::new(TimerState::default()), Condvar::new()));
+        let watched = shared.clone();
+        if let Err(err) = std::thread::Builder::new()
+            .name(format!("processing-timer-{executor_id}"))
+            .spawn(move || Self::run(&watched))
+        {
+            tracing::error!(
+                "processing timer of operator {} failed to start, its events are never timed out: {}",
+                executor_id,
+                err
+            );
+        }
+        Self { timeout, shared }
+    }
+
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// start the deadline of the event which the isolate is going to process
+    pub(crate) fn arm(&self, isolate: v8::IsolateHandle) {
+        let mut state = self.lock();
+        state.armed = Some((Instant::now() + self.timeout, isolate));
+        state.expired = false;
+        self.shared.1.notify_one();
+    }
+
+    pub(crate) fn is_expired(&self) -> bool {
+        self.lock().expired
+    }
+
+    /// stop the deadline of the event in hand. It returns whether the event is timed out
+    pub(crate) fn disarm(&self) -> bool {
+        let mut state = self.lock();
+        state.armed = None;
+        std::mem::take(&mut state.expired)
+    }
+
+    fn run(shared: &(Mutex<TimerState>, Condvar)) {
+        let (state, condvar) = shared;
+        let mut state = state.lock().unwrap_or_else(|err| err.into_inner());
+        while !state.stopped {
+            let deadline = state.armed.as_ref().map(|(deadline, _)| *deadline);
+            state = match deadline {
+                None => condvar.wait(state).unwrap_or_else(|err| err.into_inner()),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now < deadline {
+                        condvar
+                            .wait_timeout(state, deadline - now)
+                            .unwrap_or_else(|err| err.into_inner())
+                            .0
+                    } else {
+                        if let Some((deadline, isolate)) = state.armed.as_mut() {
+                            isolate.terminate_execution();
+                            *deadline = now + INTERRUPT_INTERVAL;
+                        }
+                        state.expired = true;
+                        state
+                    }
+                }
+            };
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TimerState> {
+        self.shared.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Drop for ProcessingTimer {
+    fn drop(&mut self) {
+        let mut state = self.lock();
+        state.armed = None;
+        state.stopped = true;
+        self.shared.1.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use proto::common::{OperatorErrorKind, OperatorWatchdog, ResourceId};
+    use tokio_util::sync::CancellationToken;
+
+    use crate::task::ErrorReporter;
+
+    use super::{Progress, Watchdog};
+
+    #[test]
+    fn test_watchdog_flags_stuck_operator() {
+        let progress = Progress::new_shared(&OperatorWatchdog {
+            stuck_timeout: None,
+            max_restarts: 1,
+        });
+        let cancellation = CancellationToken::new();
+        let work = progress.renew_work(&cancellation);
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        let mut watchdog = Watchdog::new(Duration::from_secs(1));
+        watchdog.watch(
+            1,
+            progress.clone(),
+            Some(ErrorReporter::new(&ResourceId::default(), 1, tx)),
+        );
+
+        // an idle operator is never stuck
+        let start = Instant::now();
+        watchdog.check(start + Duration::from_secs(10));
+        assert!(!progress.is_stuck());
+
+        progress.advance(start);
+        watchdog.check(start + Duration::from_millis(500));
+        assert!(!progress.is_stuck());
+        watchdog.check(start + Duration::from_secs(1));
+        assert!(progress.is_stuck());
+        assert!(work.is_cancelled());
+        assert!(!cancellation.is_cancelled());
+        let err = rx.try_recv().unwrap();
+        assert_eq!(err.operator_id, 1);
+        assert_eq!(err.kind(), OperatorErrorKind::Stuck);
+        // it's reported once until it restarts
+        watchdog.check(start + Duration::from_secs(2));
+        assert!(rx.try_recv().is_err());
+
+        let work = progress
+            .restart(&cancellation, start + Duration::from_secs(2))
+            .unwrap();
+        assert!(!progress.is_stuck());
+        assert!(!work.is_cancelled());
+        assert_eq!(progress.get_restarts(), 1);
+
+        // the restarts are exhausted
+        watchdog.check(start + Duration::from_secs(3));
+        assert!(progress.is_stuck());
+        assert!(progress
+            .restart(&cancellation, start + Duration::from_secs(3))
+            .is_none());
+        assert_eq!(progress.get_restarts(), 1);
+    }
+}
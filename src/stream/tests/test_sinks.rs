@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use bytes::Buf;
 use common::{
@@ -17,7 +17,10 @@ use proto::common::{
     ResourceId,
 };
 use sqlx::Row;
-use stream::connector::{Kafka, Mysql, Redis, Sink, SinkImpl};
+use stream::{
+    batching::BatchingSink,
+    connector::{Kafka, Mysql, Redis, Sink, SinkImpl},
+};
 
 static MOD_TEST_START: std::sync::Once = std::sync::Once::new();
 
@@ -53,6 +56,8 @@ async fn test_kafka_sink() {
                 partition: Some(0),
             }),
             data_type: DataTypeEnum::String as i32,
+            format: None,
+            sink_opts: None,
         },
     ));
 
@@ -63,10 +68,7 @@ async fn test_kafka_sink() {
 
     let consumer = consumer.unwrap();
     let event = KeyedDataEvent {
-        job_id: Some(ResourceId {
-            resource_id: "resource_id".to_string(),
-            namespace_id: "namespaceId".to_string(),
-        }),
+        job_id: Some(ResourceId::new("namespaceId", "resource_id")),
         key: None,
         to_operator_id: 1,
         data: vec![
@@ -99,6 +101,11 @@ async fn test_kafka_sink() {
         from_operator_id: 0,
         window: None,
         event_id: 1,
+        broadcast: false,
+        sequence: 0,
+        sequence_epoch: 0,
+        restart_epoch: 0,
+        headers: Default::default(),
     };
 
     let result = kafka_sink
@@ -130,6 +137,135 @@ async fn test_kafka_sink() {
     assert!(opt.is_some());
 }
 
+#[tokio::test]
+async fn test_kafka_sink_key_hash_partition() {
+    use rdkafka::{
+        admin::{AdminClient, AdminOptions, NewTopic, TopicReplication},
+        client::DefaultClientContext,
+        consumer::{Consumer, StreamConsumer},
+        message::Headers,
+        ClientConfig, Message,
+    };
+
+    let kafka_host = get_env("KAFKA_HOST").unwrap_or("localhost".to_string());
+    let brokers = format!("{kafka_host}:9092");
+    let topic = "ci_partitioned";
+    let partitions = 4;
+
+    // the topic may exist if the test has been run before
+    let admin: AdminClient<DefaultClientContext> = ClientConfig::new()
+        .set("bootstrap.servers", &brokers)
+        .create()
+        .expect("msg");
+    let result = admin
+        .create_topics(
+            &[NewTopic::new(topic, partitions, TopicReplication::Fixed(1))],
+            &AdminOptions::new(),
+        )
+        .await;
+    assert!(result.is_ok());
+
+    let mut kafka_sink = SinkImpl::Kafka(Kafka::with_sink_config(
+        &ResourceId::default(),
+        1,
+        &KafkaDesc {
+            brokers: vec![brokers.clone()],
+            topic: topic.to_string(),
+            opts: None,
+            data_type: DataTypeEnum::Object as i32,
+            format: None,
+            sink_opts: Some(kafka_desc::KafkaSinkOptions {
+                key_field: "id".to_string(),
+                partitioner: kafka_desc::kafka_sink_options::Partitioner::KeyHash as i32,
+                partition_field: Default::default(),
+                provenance_headers: true,
+                event_time_timestamp: true,
+                headers_field: "_headers".to_string(),
+            }),
+        },
+    ));
+
+    let consumer: StreamConsumer = ClientConfig::new()
+        .set("group.id", "ci_partitioned_group")
+        .set("bootstrap.servers", &brokers)
+        .set("auto.offset.reset", "latest")
+        .create()
+        .expect("msg");
+    consumer.subscribe(&[topic]).expect("msg");
+
+    let keys = (0..10).map(|i| format!("key-{i}")).collect::<Vec<_>>();
+    let event = KeyedDataEvent {
+        job_id: Some(ResourceId::new("namespaceId", "resource_id")),
+        key: None,
+        to_operator_id: 1,
+        data: keys
+            .iter()
+            .map(|key| Entry {
+                data_type: DataTypeEnum::Object as i32,
+                value: TypedValue::Object(BTreeMap::from_iter([(
+                    "id".to_string(),
+                    TypedValue::String(key.clone()),
+                )]))
+                .get_data_bytes(),
+            })
+            .collect(),
+        event_time: 1000,
+        from_operator_id: 0,
+        window: None,
+        event_id: 1,
+        broadcast: false,
+        sequence: 0,
+        sequence_epoch: 0,
+        restart_epoch: 0,
+        headers: HashMap::from_iter([("trace_id".to_string(), "abc".to_string())]),
+    };
+
+    let result = kafka_sink
+        .sink(LocalEvent::KeyedDataStreamEvent(event))
+        .await;
+    assert!(result.is_ok());
+
+    for _ in 0..keys.len() {
+        let message = consumer.recv().await.expect("msg");
+        let key = message.key().unwrap_or_default();
+        assert!(keys.contains(&serde_json::from_slice::<String>(key).expect("msg")));
+        // the same partition as the default partitioner of the Java client
+        assert_eq!(
+            message.partition(),
+            common::kafka::key_hash_partition(key, partitions)
+        );
+        assert_eq!(message.timestamp().to_millis(), Some(1000));
+
+        let headers = message.headers().expect("msg");
+        let headers = (0..headers.count())
+            .map(|idx| headers.get(idx))
+            .map(|header| {
+                (
+                    header.key.to_string(),
+                    header.value.unwrap_or_default().to_vec(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            headers,
+            vec![
+                (
+                    "lightflus.job_id".to_string(),
+                    b"namespaceId/resource_id".to_vec()
+                ),
+                ("lightflus.operator_id".to_string(), b"0".to_vec()),
+                ("lightflus.event_id".to_string(), b"1".to_vec()),
+                ("trace_id".to_string(), b"abc".to_vec()),
+            ]
+        );
+        // the headers of the event are added to the JSON payload as well
+        let payload =
+            serde_json::from_slice::<serde_json::Value>(message.payload().unwrap_or_default())
+                .expect("msg");
+        assert_eq!(payload["_headers"], serde_json::json!({"trace_id": "abc"}));
+    }
+}
+
 #[tokio::test]
 async fn test_redis_sink_success() {
     let _setup_guard = setup();
@@ -149,15 +285,12 @@ async fn test_redis_sink_success() {
         }),
     };
 
-    let mut redis_sink = SinkImpl::Redis(Redis::with_config(1, desc));
+    let mut redis_sink = SinkImpl::Redis(BatchingSink::new(Redis::with_config(1, desc), None));
 
     assert_eq!(redis_sink.sink_id(), 1);
 
     let event = KeyedDataEvent {
-        job_id: Some(ResourceId {
-            resource_id: "resource_id".to_string(),
-            namespace_id: "namespaceId".to_string(),
-        }),
+        job_id: Some(ResourceId::new("namespaceId", "resource_id")),
         key: None,
         to_operator_id: 1,
         data: vec![
@@ -190,6 +323,11 @@ async fn test_redis_sink_success() {
         from_operator_id: 0,
         window: None,
         event_id: 1,
+        broadcast: false,
+        sequence: 0,
+        sequence_epoch: 0,
+        restart_epoch: 0,
+        headers: Default::default(),
     };
 
     let result = redis_sink
@@ -228,40 +366,40 @@ async fn test_mysql_sink() {
     let result = conn.execute("create table if not exists person (id int NOT NULL AUTO_INCREMENT, name varchar(36), age int, country varchar(36), address varchar(255), PRIMARY KEY (id))", vec![]).await;
 
     assert!(result.is_ok());
-    let mut mysql = SinkImpl::Mysql(Mysql::with_config(
-        0,
-        &MysqlDesc {
-            connection_opts: Some(conn_opts),
-            statement: Some(mysql_desc::Statement {
-                statement: "insert into person (name,age,country,address) values (?,?,?,?)"
-                    .to_string(),
-                extractors: vec![
-                    statement::Extractor {
-                        index: 1,
-                        extractor: "function mysql_extractor(a) {return a.v1}".to_string(),
-                    },
-                    statement::Extractor {
-                        index: 2,
-                        extractor: "function mysql_extractor(a) {return a.v2}".to_string(),
-                    },
-                    statement::Extractor {
-                        index: 3,
-                        extractor: "function mysql_extractor(a) {return a.v3}".to_string(),
-                    },
-                    statement::Extractor {
-                        index: 4,
-                        extractor: "function mysql_extractor(a) {return a.v4}".to_string(),
-                    },
-                ],
-            }),
-        },
+    let mut mysql = SinkImpl::Mysql(BatchingSink::new(
+        Mysql::with_config(
+            0,
+            &MysqlDesc {
+                connection_opts: Some(conn_opts),
+                statement: Some(mysql_desc::Statement {
+                    statement: "insert into person (name,age,country,address) values (?,?,?,?)"
+                        .to_string(),
+                    extractors: vec![
+                        statement::Extractor {
+                            index: 1,
+                            extractor: "function mysql_extractor(a) {return a.v1}".to_string(),
+                        },
+                        statement::Extractor {
+                            index: 2,
+                            extractor: "function mysql_extractor(a) {return a.v2}".to_string(),
+                        },
+                        statement::Extractor {
+                            index: 3,
+                            extractor: "function mysql_extractor(a) {return a.v3}".to_string(),
+                        },
+                        statement::Extractor {
+                            index: 4,
+                            extractor: "function mysql_extractor(a) {return a.v4}".to_string(),
+                        },
+                    ],
+                }),
+            },
+        ),
+        None,
     ));
 
     let event = KeyedDataEvent {
-        job_id: Some(ResourceId {
-            resource_id: "resource_id".to_string(),
-            namespace_id: "namespaceId".to_string(),
-        }),
+        job_id: Some(ResourceId::new("namespaceId", "resource_id")),
         key: None,
         to_operator_id: 1,
         data: vec![Entry {
@@ -288,6 +426,11 @@ async fn test_mysql_sink() {
         from_operator_id: 0,
         window: None,
         event_id: 1,
+        broadcast: false,
+        sequence: 0,
+        sequence_epoch: 0,
+        restart_epoch: 0,
+        headers: Default::default(),
     };
 
     let result = mysql.sink(LocalEvent::KeyedDataStreamEvent(event)).await;
@@ -7,7 +7,10 @@ use common::{event::LocalEvent, kafka::run_producer, types::TypedValue, utils::g
 
 use futures_util::{ready, Future};
 use proto::common::{kafka_desc::KafkaOptions, DataTypeEnum, KafkaDesc, ResourceId};
-use stream::connector::{Kafka, Source};
+use stream::{
+    connector::{get_source_position, Kafka, Source},
+    offsets::OffsetStoreBuilder,
+};
 
 #[tokio::test]
 async fn test_kafka_source_next() {
@@ -20,16 +23,12 @@ async fn test_kafka_source_next() {
             partition: None,
         }),
         data_type: DataTypeEnum::String as i32,
+        format: None,
+        sink_opts: None,
     };
 
-    let mut kafka_source = Kafka::with_source_config(
-        &ResourceId {
-            resource_id: "resource_id".to_string(),
-            namespace_id: "default".to_string(),
-        },
-        0,
-        &kafka_desc,
-    );
+    let mut kafka_source =
+        Kafka::with_source_config(&ResourceId::new("default", "resource_id"), 0, &kafka_desc);
 
     let producer = run_producer(format!("{kafka_host}:9092").as_str(), "ci", "ci_group", 0);
     assert!(producer.is_ok());
@@ -67,16 +66,12 @@ async fn test_kafka_source_poll_next() {
             partition: None,
         }),
         data_type: DataTypeEnum::String as i32,
+        format: None,
+        sink_opts: None,
     };
 
-    let kafka_source = Kafka::with_source_config(
-        &ResourceId {
-            resource_id: "resource_id".to_string(),
-            namespace_id: "default".to_string(),
-        },
-        0,
-        &kafka_desc,
-    );
+    let kafka_source =
+        Kafka::with_source_config(&ResourceId::new("default", "resource_id"), 0, &kafka_desc);
 
     let producer = run_producer(format!("{kafka_host}:9092").as_str(), "ci", "ci_group", 0);
     assert!(producer.is_ok());
@@ -126,3 +121,53 @@ async fn test_kafka_source_poll_next() {
     let result = tokio::spawn(test_kafka_poll).await;
     assert!(result.is_ok())
 }
+
+#[tokio::test]
+async fn test_kafka_source_resumes_from_committed_offsets() {
+    let kafka_host = get_env("KAFKA_HOST").unwrap_or("localhost".to_string());
+    let kafka_desc = KafkaDesc {
+        brokers: vec![format!("{kafka_host}:9092")],
+        topic: "ci".to_string(),
+        opts: Some(KafkaOptions {
+            group: Some("ci_offset_group".to_string()),
+            partition: None,
+        }),
+        data_type: DataTypeEnum::String as i32,
+        format: None,
+        sink_opts: None,
+    };
+    let job_id = ResourceId::new("default", "resource_id");
+    let store = OffsetStoreBuilder::Memory.build_shared();
+
+    let producer = run_producer(
+        format!("{kafka_host}:9092").as_str(),
+        "ci",
+        "ci_offset_group",
+        0,
+    );
+    assert!(producer.is_ok());
+    let producer = producer.unwrap();
+    let result = producer.send("key".as_bytes(), "value".as_bytes()).await;
+    assert!(result.is_ok());
+
+    let mut kafka_source = Kafka::with_offset_store(&job_id, 0, &kafka_desc, Some(store.clone()));
+    let position = match kafka_source.next().await {
+        Some(LocalEvent::KeyedDataStreamEvent(e)) => get_source_position(&e),
+        _ => panic!("unexpected event"),
+    };
+    assert!(position.is_some());
+    let (partition, offset) = position.unwrap();
+    kafka_source.commit(partition, offset);
+    kafka_source.close_source().await;
+
+    // the restarted source fetches the event after the committed one
+    let result = producer.send("key".as_bytes(), "value".as_bytes()).await;
+    assert!(result.is_ok());
+    let mut kafka_source = Kafka::with_offset_store(&job_id, 0, &kafka_desc, Some(store));
+    match kafka_source.next().await {
+        Some(LocalEvent::KeyedDataStreamEvent(e)) => {
+            assert_eq!(get_source_position(&e), Some((partition, offset + 1)))
+        }
+        _ => panic!("unexpected event"),
+    }
+}
@@ -0,0 +1,47 @@
+;; A sample module of the WasmUdf operator. It wraps each payload into a JSON array, e.g. `1` -> `[1]`,
+;; and drops null payloads.
+;;
+;; The input buffer always starts at 1024 and the output buffer follows it, so no allocator is needed.
+(module
+  (memory (export "memory") 1)
+
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $required i32)
+    ;; pages required by the input buffer and the output buffer, which is at most len + 6 bytes
+    (local.set $required
+      (i32.add
+        (i32.div_u
+          (i32.add (i32.const 1030) (i32.mul (local.get $len) (i32.const 2)))
+          (i32.const 65536))
+        (i32.const 1)))
+    (if (i32.gt_u (local.get $required) (memory.size))
+      (then
+        (if (i32.eq
+              (memory.grow (i32.sub (local.get $required) (memory.size)))
+              (i32.const -1))
+          (then unreachable))))
+    (i32.const 1024))
+
+  (func (export "process") (param $ptr i32) (param $len i32) (result i64)
+    (local $out i32)
+    ;; `null` has no outputs
+    (if (i32.and
+          (i32.eq (local.get $len) (i32.const 4))
+          (i32.eq (i32.load8_u (local.get $ptr)) (i32.const 110)))
+      (then (return (i64.const 0))))
+    (local.set $out (i32.add (local.get $ptr) (local.get $len)))
+    ;; length prefix of the output payload
+    (i32.store (local.get $out) (i32.add (local.get $len) (i32.const 2)))
+    ;; [
+    (i32.store8 (i32.add (local.get $out) (i32.const 4)) (i32.const 91))
+    (memory.copy
+      (i32.add (local.get $out) (i32.const 5))
+      (local.get $ptr)
+      (local.get $len))
+    ;; ]
+    (i32.store8
+      (i32.add (i32.add (local.get $out) (i32.const 5)) (local.get $len))
+      (i32.const 93))
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $out)) (i64.const 32))
+      (i64.extend_i32_u (i32.add (local.get $len) (i32.const 6))))))